@@ -0,0 +1,145 @@
+//! What a device's clock looked like during an ingest session, so timelines
+//! assembled from several devices can account for time zones and clocks which
+//! disagree.
+
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A device's time zone and clock, see [`DeviceClock::observe`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceClock {
+    /// the IANA time zone (e.g. `America/New_York`) when it can be determined
+    pub timezone: Option<String>,
+    /// the local UTC offset when observed, e.g. `-04:00`
+    pub utc_offset: String,
+    /// where the kernel reads the time from (e.g. `tsc`, `kvm-clock`)
+    pub clock_source: Option<String>,
+    /// whether the kernel considers the clock synchronized by NTP
+    pub ntp_synchronized: Option<bool>,
+    /// the kernel's estimate of the clock's offset from NTP time
+    pub ntp_offset_ms: Option<f64>,
+    /// the kernel's estimate of the clock's error
+    pub ntp_estimated_error_ms: Option<f64>,
+    /// the device's time when observed, in UTC
+    pub observed_at: DateTime<Utc>,
+}
+
+impl DeviceClock {
+    pub fn observe() -> DeviceClock {
+        let now = Local::now();
+        let ntp = ntp_status();
+        DeviceClock {
+            timezone: local_timezone(),
+            utc_offset: now.offset().to_string(),
+            clock_source: clock_source(),
+            ntp_synchronized: ntp.map(|ntp| ntp.synchronized),
+            ntp_offset_ms: ntp.map(|ntp| ntp.offset_ms),
+            ntp_estimated_error_ms: ntp.map(|ntp| ntp.estimated_error_ms),
+            observed_at: now.with_timezone(&Utc),
+        }
+    }
+}
+
+/// A timestamp normalized to UTC, with the UTC offset it was recorded with.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NormalizedTimestamp {
+    /// formatted like the other `TIMESTAMPTZ` values surveilr stores, e.g.
+    /// `2024-03-04 04:00:00 UTC`
+    pub utc: String,
+    /// e.g. `+05:00`
+    pub original_offset: String,
+}
+
+/// Normalize an RFC 3339 (or RFC 2822) timestamp to UTC, `None` if it can't be
+/// parsed.
+pub fn normalize_to_utc(timestamp: &str) -> Option<NormalizedTimestamp> {
+    let parsed = DateTime::parse_from_rfc3339(timestamp)
+        .or_else(|_| DateTime::parse_from_rfc2822(timestamp))
+        .ok()?;
+    Some(NormalizedTimestamp {
+        utc: parsed.with_timezone(&Utc).to_string(),
+        original_offset: parsed.offset().to_string(),
+    })
+}
+
+/// The `TZ` environment variable, else the zone `/etc/timezone` or the
+/// `/etc/localtime` link names.
+fn local_timezone() -> Option<String> {
+    let tz = std::env::var("TZ").unwrap_or_default();
+    let tz = tz.trim_start_matches(':');
+    if !tz.is_empty() && !tz.starts_with('/') {
+        return Some(tz.to_string());
+    }
+    if let Ok(timezone) = std::fs::read_to_string("/etc/timezone") {
+        if !timezone.trim().is_empty() {
+            return Some(timezone.trim().to_string());
+        }
+    }
+    let localtime = std::fs::read_link("/etc/localtime").ok()?;
+    let localtime = localtime.to_string_lossy();
+    localtime
+        .split_once("zoneinfo/")
+        .map(|(_, zone)| zone.to_string())
+}
+
+fn clock_source() -> Option<String> {
+    std::fs::read_to_string("/sys/devices/system/clocksource/clocksource0/current_clocksource")
+        .ok()
+        .map(|source| source.trim().to_string())
+        .filter(|source| !source.is_empty())
+}
+
+#[derive(Debug, Clone, Copy)]
+struct NtpStatus {
+    synchronized: bool,
+    offset_ms: f64,
+    estimated_error_ms: f64,
+}
+
+/// The kernel's NTP discipline state, read (not changed) with `adjtimex`.
+#[cfg(target_os = "linux")]
+fn ntp_status() -> Option<NtpStatus> {
+    // SAFETY: with `modes` zeroed adjtimex only reads the kernel's clock state
+    let (state, timex) = unsafe {
+        let mut timex: libc::timex = std::mem::zeroed();
+        (libc::adjtimex(&mut timex), timex)
+    };
+    if state == -1 {
+        return None;
+    }
+    let offset_ms = match timex.status & libc::STA_NANO {
+        0 => timex.offset as f64 / 1_000.0,
+        _ => timex.offset as f64 / 1_000_000.0,
+    };
+    Some(NtpStatus {
+        synchronized: state != libc::TIME_ERROR,
+        offset_ms,
+        estimated_error_ms: timex.esterror as f64 / 1_000.0,
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn ntp_status() -> Option<NtpStatus> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_to_utc() {
+        assert_eq!(
+            normalize_to_utc("2024-03-04T09:00:00+05:00"),
+            Some(NormalizedTimestamp {
+                utc: "2024-03-04 04:00:00 UTC".to_string(),
+                original_offset: "+05:00".to_string(),
+            })
+        );
+        assert_eq!(
+            normalize_to_utc("Mon, 4 Mar 2024 09:00:00 -0330").map(|ts| ts.utc),
+            Some("2024-03-04 12:30:00 UTC".to_string())
+        );
+        assert_eq!(normalize_to_utc(""), None);
+    }
+}
@@ -7,6 +7,9 @@ lazy_static! {
     pub static ref DEVICE: Device = Device::new(None);
 }
 
+pub mod clock;
 pub mod device;
 pub mod format;
+pub mod oauth2;
+pub mod secret;
 pub mod sqlite_helpers;
@@ -0,0 +1,592 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use reqwest::{header::ACCEPT, Url};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+use tracing::{debug, warn};
+
+use crate::secret::Secret;
+
+/// Overrides the directory the tokens are cached in
+pub const TOKEN_CACHE_ENV: &str = "SURVEILR_OAUTH_TOKEN_CACHE";
+/// Tokens this close to expiring are refreshed before they're used
+const EXPIRY_MARGIN_SECS: i64 = 60;
+/// Scopes the Microsoft 365 (Graph API) ingest source needs, `offline_access`
+/// for a refresh token
+pub const MICROSOFT_GRAPH_SCOPES: [&str; 4] =
+    ["files.read", "Mail.Read", "User.Read", "offline_access"];
+/// Scopes the Gmail API ingest source needs, Google hands out refresh tokens
+/// without an extra scope
+pub const GMAIL_SCOPES: [&str; 1] = ["https://www.googleapis.com/auth/gmail.readonly"];
+/// How long the user has to sign in through the browser
+const AUTHORIZATION_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// The endpoints of an OAuth 2.0 authorization server.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OAuth2Provider {
+    /// Name of the provider, part of the token cache's file names
+    pub name: String,
+    pub authorize_url: String,
+    pub token_url: String,
+    /// RFC 8628 device authorization endpoint, for providers supporting the device code flow
+    pub device_authorization_url: Option<String>,
+}
+
+impl OAuth2Provider {
+    /// Microsoft identity platform (Microsoft 365, Graph API), `tenant` is
+    /// usually `common`, `organizations` or a tenant ID.
+    pub fn microsoft(tenant: &str) -> Self {
+        let base = format!("https://login.microsoftonline.com/{tenant}/oauth2/v2.0");
+        OAuth2Provider {
+            name: "microsoft".to_string(),
+            authorize_url: format!("{base}/authorize"),
+            token_url: format!("{base}/token"),
+            device_authorization_url: Some(format!("{base}/devicecode")),
+        }
+    }
+
+    pub fn google() -> Self {
+        OAuth2Provider {
+            name: "google".to_string(),
+            authorize_url: "https://accounts.google.com/o/oauth2/v2/auth".to_string(),
+            token_url: "https://oauth2.googleapis.com/token".to_string(),
+            device_authorization_url: Some("https://oauth2.googleapis.com/device/code".to_string()),
+        }
+    }
+
+    pub fn github() -> Self {
+        OAuth2Provider {
+            name: "github".to_string(),
+            authorize_url: "https://github.com/login/oauth/authorize".to_string(),
+            token_url: "https://github.com/login/oauth/access_token".to_string(),
+            device_authorization_url: Some("https://github.com/login/device/code".to_string()),
+        }
+    }
+
+    /// Okta's default custom authorization server of the `domain` organization,
+    /// e.g. `example.okta.com`.
+    pub fn okta(domain: &str) -> Self {
+        let base = format!("https://{domain}/oauth2/default/v1");
+        OAuth2Provider {
+            name: "okta".to_string(),
+            authorize_url: format!("{base}/authorize"),
+            token_url: format!("{base}/token"),
+            device_authorization_url: Some(format!("{base}/device/authorize")),
+        }
+    }
+}
+
+/// How the user is asked to grant access when no usable token is cached.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OAuth2Flow {
+    /// Sign in on any device with the code shown (RFC 8628), nothing has to
+    /// reach this machine
+    DeviceCode,
+    /// Sign in with the browser, which is redirected with the authorization code
+    /// to `redirect_uri`, served on `127.0.0.1:port` (RFC 6749 with PKCE)
+    AuthCode { redirect_uri: String, port: u16 },
+}
+
+/// The tokens are `Secret`s, they never show in logs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OAuth2Token {
+    pub access_token: Secret,
+    pub refresh_token: Option<Secret>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub scope: Option<String>,
+}
+
+impl OAuth2Token {
+    /// Whether the access token expired, or is about to.
+    pub fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|expires_at| {
+            expires_at <= Utc::now() + chrono::Duration::seconds(EXPIRY_MARGIN_SECS)
+        })
+    }
+}
+
+/// What the token cache stores, `Secret` refuses to serialize its value.
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedToken {
+    provider: String,
+    client_id: String,
+    scopes: Vec<String>,
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_at: Option<DateTime<Utc>>,
+    scope: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: Option<String>,
+    refresh_token: Option<String>,
+    expires_in: Option<i64>,
+    scope: Option<String>,
+    error: Option<String>,
+    error_description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeviceAuthorization {
+    device_code: String,
+    user_code: String,
+    // Google calls it `verification_url`
+    #[serde(alias = "verification_url")]
+    verification_uri: String,
+    expires_in: u64,
+    interval: Option<u64>,
+    message: Option<String>,
+}
+
+/// Obtains access tokens for API-based ingest sources from any OAuth 2.0
+/// provider. Tokens are cached on disk per provider, client and scopes and
+/// refreshed when they expire, so the user only signs in again once the
+/// refresh token is no longer accepted.
+#[derive(Debug, Clone)]
+pub struct OAuth2Client {
+    pub provider: OAuth2Provider,
+    pub client_id: String,
+    /// Sent by confidential clients, public clients (device code) have none
+    pub client_secret: Option<Secret>,
+    pub scopes: Vec<String>,
+    pub cache_dir: PathBuf,
+    http: reqwest::Client,
+}
+
+impl OAuth2Client {
+    pub fn new(
+        provider: OAuth2Provider,
+        client_id: &str,
+        client_secret: Option<Secret>,
+        scopes: &[&str],
+    ) -> Self {
+        OAuth2Client {
+            provider,
+            client_id: client_id.to_string(),
+            client_secret,
+            scopes: scopes.iter().map(|s| s.to_string()).collect(),
+            cache_dir: token_cache_dir(),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// A valid access token: the cached one, refreshed if it expired, or a new
+    /// one granted by the user through `flow`.
+    pub async fn token(&self, flow: &OAuth2Flow) -> Result<OAuth2Token> {
+        if let Some(cached) = self.cached()? {
+            if !cached.is_expired() {
+                debug!("Using the cached {} token", self.provider.name);
+                return Ok(cached);
+            }
+            if let Some(refresh_token) = &cached.refresh_token {
+                match self.refresh(refresh_token).await {
+                    Ok(token) => {
+                        self.save(&token)?;
+                        return Ok(token);
+                    }
+                    Err(err) => warn!(
+                        "Unable to refresh the {} token, signing in again: {err:#}",
+                        self.provider.name
+                    ),
+                }
+            }
+        }
+        self.authorize(flow).await
+    }
+
+    /// Ask the user to grant access through `flow`, even when a token is
+    /// cached, and cache the new token.
+    pub async fn authorize(&self, flow: &OAuth2Flow) -> Result<OAuth2Token> {
+        let token = match flow {
+            OAuth2Flow::DeviceCode => self.device_code().await?,
+            OAuth2Flow::AuthCode { redirect_uri, port } => {
+                self.auth_code(redirect_uri, *port).await?
+            }
+        };
+        self.save(&token)?;
+        Ok(token)
+    }
+
+    /// The file the token of this provider, client and scopes is cached in.
+    pub fn cache_path(&self) -> PathBuf {
+        let mut hasher = Sha256::new();
+        hasher.update(self.client_id.as_bytes());
+        for scope in &self.scopes {
+            hasher.update(b" ");
+            hasher.update(scope.as_bytes());
+        }
+        let key = format!("{:x}", hasher.finalize());
+        self.cache_dir
+            .join(format!("{}-{}.json", self.provider.name, &key[..16]))
+    }
+
+    pub fn cached(&self) -> Result<Option<OAuth2Token>> {
+        let path = self.cache_path();
+        if !path.exists() {
+            return Ok(None);
+        }
+        let cached: CachedToken = serde_json::from_str(
+            &std::fs::read_to_string(&path)
+                .with_context(|| format!("[oauth2::cached] reading {}", path.display()))?,
+        )
+        .with_context(|| format!("[oauth2::cached] parsing {}", path.display()))?;
+        Ok(Some(OAuth2Token {
+            access_token: Secret::new(cached.access_token),
+            refresh_token: cached.refresh_token.map(Secret::new),
+            expires_at: cached.expires_at,
+            scope: cached.scope,
+        }))
+    }
+
+    fn save(&self, token: &OAuth2Token) -> Result<()> {
+        let path = self.cache_path();
+        std::fs::create_dir_all(&self.cache_dir)
+            .with_context(|| format!("[oauth2::save] creating {}", self.cache_dir.display()))?;
+        let cached = CachedToken {
+            provider: self.provider.name.clone(),
+            client_id: self.client_id.clone(),
+            scopes: self.scopes.clone(),
+            access_token: token.access_token.expose().to_string(),
+            refresh_token: token.refresh_token.as_ref().map(|t| t.expose().to_string()),
+            expires_at: token.expires_at,
+            scope: token.scope.clone(),
+        };
+        write_private(&path, &serde_json::to_string_pretty(&cached)?)
+            .with_context(|| format!("[oauth2::save] writing {}", path.display()))
+    }
+
+    async fn refresh(&self, refresh_token: &Secret) -> Result<OAuth2Token> {
+        let scope = self.scopes.join(" ");
+        let mut token = self
+            .request_token(vec![
+                ("grant_type", "refresh_token"),
+                ("refresh_token", refresh_token.expose()),
+                ("scope", &scope),
+            ])
+            .await?;
+        // providers may keep the refresh token valid without issuing a new one
+        if token.refresh_token.is_none() {
+            token.refresh_token = Some(refresh_token.clone());
+        }
+        Ok(token)
+    }
+
+    async fn device_code(&self) -> Result<OAuth2Token> {
+        let url = self
+            .provider
+            .device_authorization_url
+            .as_ref()
+            .ok_or_else(|| {
+                anyhow!(
+                    "[oauth2::device_code] {} doesn't support the device code flow",
+                    self.provider.name
+                )
+            })?;
+        let scope = self.scopes.join(" ");
+        let form = self.client_form(vec![("scope", &scope)]);
+        let response = self
+            .http
+            .post(url)
+            .header(ACCEPT, "application/json")
+            .form(&form)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "[oauth2::device_code] {} device authorization failed: {}",
+                self.provider.name,
+                response.text().await?
+            ));
+        }
+        let authorization: DeviceAuthorization = response.json().await?;
+        match &authorization.message {
+            Some(message) => eprintln!("{message}"),
+            None => eprintln!(
+                "To sign in, open {} and enter the code {}",
+                authorization.verification_uri, authorization.user_code
+            ),
+        }
+
+        let mut interval = Duration::from_secs(authorization.interval.unwrap_or(5));
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(authorization.expires_in);
+        loop {
+            tokio::time::sleep(interval).await;
+            if tokio::time::Instant::now() > deadline {
+                return Err(anyhow!(
+                    "[oauth2::device_code] the code expired before signing in"
+                ));
+            }
+            let form = self.client_form(vec![
+                ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
+                ("device_code", authorization.device_code.as_str()),
+            ]);
+            match self.token_response(&form).await? {
+                TokenResponse {
+                    error: Some(error), ..
+                } if error == "authorization_pending" => {
+                    debug!("Still waiting on the user to sign in")
+                }
+                TokenResponse {
+                    error: Some(error), ..
+                } if error == "slow_down" => interval += Duration::from_secs(5),
+                response => {
+                    eprintln!("Signed in successfully");
+                    return self.granted_token(response);
+                }
+            }
+        }
+    }
+
+    async fn auth_code(&self, redirect_uri: &str, port: u16) -> Result<OAuth2Token> {
+        let state = ulid::Ulid::new().to_string();
+        let verifier = format!("{}{}", ulid::Ulid::new(), ulid::Ulid::new());
+        let url = Url::parse_with_params(
+            &self.provider.authorize_url,
+            &[
+                ("response_type", "code"),
+                ("client_id", &self.client_id),
+                ("redirect_uri", redirect_uri),
+                ("scope", &self.scopes.join(" ")),
+                ("state", &state),
+                ("code_challenge", &pkce_challenge(&verifier)),
+                ("code_challenge_method", "S256"),
+            ],
+        )?;
+        let listener = TcpListener::bind(("127.0.0.1", port))
+            .await
+            .with_context(|| format!("[oauth2::auth_code] unable to listen on port {port}"))?;
+        eprintln!("To sign in, open this URL in your browser:\n{url}");
+
+        let code = tokio::time::timeout(
+            AUTHORIZATION_TIMEOUT,
+            receive_code(&listener, redirect_uri, &state),
+        )
+        .await
+        .map_err(|_| anyhow!("[oauth2::auth_code] timed out waiting for the browser sign in"))??;
+
+        self.request_token(vec![
+            ("grant_type", "authorization_code"),
+            ("code", code.as_str()),
+            ("redirect_uri", redirect_uri),
+            ("code_verifier", verifier.as_str()),
+        ])
+        .await
+    }
+
+    /// `form` with the client's credentials.
+    fn client_form<'a>(&'a self, mut form: Vec<(&'a str, &'a str)>) -> Vec<(&'a str, &'a str)> {
+        form.push(("client_id", &self.client_id));
+        if let Some(secret) = &self.client_secret {
+            form.push(("client_secret", secret.expose()));
+        }
+        form
+    }
+
+    async fn request_token(&self, form: Vec<(&str, &str)>) -> Result<OAuth2Token> {
+        let response = self.token_response(&self.client_form(form)).await?;
+        self.granted_token(response)
+    }
+
+    async fn token_response(&self, form: &[(&str, &str)]) -> Result<TokenResponse> {
+        // GitHub answers form-encoded unless JSON is asked for
+        self.http
+            .post(&self.provider.token_url)
+            .header(ACCEPT, "application/json")
+            .form(form)
+            .send()
+            .await
+            .with_context(|| format!("[oauth2] POST {}", self.provider.token_url))?
+            .json()
+            .await
+            .with_context(|| format!("[oauth2] parsing the {} token", self.provider.name))
+    }
+
+    fn granted_token(&self, response: TokenResponse) -> Result<OAuth2Token> {
+        match response {
+            TokenResponse {
+                access_token: Some(access_token),
+                error: None,
+                refresh_token,
+                expires_in,
+                scope,
+                ..
+            } => Ok(OAuth2Token {
+                access_token: Secret::new(access_token),
+                refresh_token: refresh_token.map(Secret::new),
+                expires_at: expires_in.map(|secs| Utc::now() + chrono::Duration::seconds(secs)),
+                scope,
+            }),
+            TokenResponse {
+                error,
+                error_description,
+                ..
+            } => Err(anyhow!(
+                "[oauth2] {} refused the token request: {} {}",
+                self.provider.name,
+                error.unwrap_or_default(),
+                error_description.unwrap_or_default()
+            )),
+        }
+    }
+}
+
+/// `$SURVEILR_OAUTH_TOKEN_CACHE`, else `surveilr/oauth` in the user's cache
+/// directory.
+pub fn token_cache_dir() -> PathBuf {
+    if let Ok(dir) = std::env::var(TOKEN_CACHE_ENV) {
+        return PathBuf::from(dir);
+    }
+    let cache = std::env::var("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|_| std::env::var("LOCALAPPDATA").map(PathBuf::from))
+        .or_else(|_| std::env::var("HOME").map(|home| Path::new(&home).join(".cache")))
+        .unwrap_or_else(|_| std::env::temp_dir());
+    cache.join("surveilr").join("oauth")
+}
+
+/// The S256 PKCE code challenge of `verifier` (RFC 7636).
+fn pkce_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+/// Serve the redirect URI until the browser arrives with the authorization
+/// code for `state`.
+async fn receive_code(listener: &TcpListener, redirect_uri: &str, state: &str) -> Result<String> {
+    let redirect_path = Url::parse(redirect_uri)?.path().to_string();
+    loop {
+        let (mut stream, _) = listener.accept().await?;
+        let mut request = vec![0; 8192];
+        let read = stream.read(&mut request).await?;
+        let (status, body, result) = match redirect_code(&request[..read], &redirect_path, state) {
+            None => ("404 Not Found", "Not found", None),
+            Some(Ok(code)) => (
+                "200 OK",
+                "Signed in to surveilr, you can close this window.",
+                Some(Ok(code)),
+            ),
+            Some(Err(err)) => ("400 Bad Request", "Sign in failed.", Some(Err(err))),
+        };
+        let response = format!(
+            "HTTP/1.1 {status}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        stream.write_all(response.as_bytes()).await?;
+        if let Some(result) = result {
+            return result;
+        }
+    }
+}
+
+/// The authorization code of a redirect request, `None` when the request is
+/// for another path (e.g. the browser asking for `/favicon.ico`).
+fn redirect_code(request: &[u8], redirect_path: &str, state: &str) -> Option<Result<String>> {
+    let request = String::from_utf8_lossy(request);
+    let target = request.lines().next()?.split_whitespace().nth(1)?;
+    let url = Url::parse(&format!("http://127.0.0.1{target}")).ok()?;
+    if url.path() != redirect_path {
+        return None;
+    }
+    let param = |name: &str| {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.to_string())
+    };
+    Some(if let Some(error) = param("error") {
+        Err(anyhow!(
+            "[oauth2::auth_code] sign in failed: {} {}",
+            error,
+            param("error_description").unwrap_or_default()
+        ))
+    } else if param("state").as_deref() != Some(state) {
+        Err(anyhow!(
+            "[oauth2::auth_code] the redirect's state doesn't match the request"
+        ))
+    } else {
+        param("code").ok_or_else(|| anyhow!("[oauth2::auth_code] the redirect has no code"))
+    })
+}
+
+/// Write a file only its owner can read, it holds credentials.
+fn write_private(path: &Path, content: &str) -> std::io::Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    std::io::Write::write_all(&mut options.open(path)?, content.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pkce_and_redirect() {
+        // RFC 7636 appendix B
+        assert_eq!(
+            pkce_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+
+        let request = b"GET /redirect?code=abc%2F1&state=s1 HTTP/1.1\r\nHost: 127.0.0.1\r\n\r\n";
+        assert_eq!(
+            redirect_code(request, "/redirect", "s1").unwrap().unwrap(),
+            "abc/1"
+        );
+        assert!(redirect_code(request, "/redirect", "s2").unwrap().is_err());
+        assert!(redirect_code(b"GET /favicon.ico HTTP/1.1\r\n", "/redirect", "s1").is_none());
+        let denied = b"GET /redirect?error=access_denied&state=s1 HTTP/1.1\r\n";
+        assert!(redirect_code(denied, "/redirect", "s1").unwrap().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_token_cache() -> Result<()> {
+        let mut client = OAuth2Client::new(
+            OAuth2Provider::github(),
+            "client-1",
+            None,
+            &["repo", "read:org"],
+        );
+        client.cache_dir =
+            std::env::temp_dir().join(format!("surveilr-oauth-{}", ulid::Ulid::new()));
+        assert!(client.cached()?.is_none());
+
+        let token = OAuth2Token {
+            access_token: Secret::new("at-1"),
+            refresh_token: Some(Secret::new("rt-1")),
+            expires_at: Some(Utc::now() + chrono::Duration::hours(1)),
+            scope: Some("repo read:org".to_string()),
+        };
+        client.save(&token)?;
+        assert_eq!(client.cached()?, Some(token.clone()));
+        // a valid cached token is used without asking the user
+        assert_eq!(client.token(&OAuth2Flow::DeviceCode).await?, token);
+
+        // tokens are cached per client and scopes
+        let mut other = client.clone();
+        other.scopes = vec!["repo".to_string()];
+        assert_ne!(other.cache_path(), client.cache_path());
+        assert!(other.cached()?.is_none());
+
+        let expiring = OAuth2Token {
+            expires_at: Some(Utc::now() + chrono::Duration::seconds(10)),
+            ..token
+        };
+        assert!(expiring.is_expired());
+        std::fs::remove_dir_all(&client.cache_dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,301 @@
+use std::{fmt, path::PathBuf};
+
+use anyhow::{anyhow, Context};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+use sha2::Sha256;
+
+/// What a [`Secret`] looks like in logs and serialized output.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Prefix of credentials which have been replaced by their digest.
+pub const CREDENTIAL_DIGEST_PREFIX: &str = "hmac-sha256:";
+
+/// Length in bytes of the key credential digests are computed with.
+pub const CREDENTIAL_DIGEST_KEY_LEN: usize = 32;
+
+/// Where a secret given as a reference (rather than as its plain value) is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SecretReference {
+    /// `env://NAME`, an environment variable
+    Env(String),
+    /// `keyring://service/account`, the OS keyring (macOS Keychain, the
+    /// Windows Credential Manager or the Secret Service of Linux desktops)
+    Keyring { service: String, account: String },
+    /// `vault://path#key`, a key of a HashiCorp Vault secret, e.g.
+    /// `vault://secret/data/surveilr/imap#password` (KV v1 or v2)
+    Vault { path: String, key: String },
+}
+
+impl SecretReference {
+    /// The reference in `value`, `None` for plain values.
+    pub fn parse(value: &str) -> anyhow::Result<Option<SecretReference>> {
+        let Some((scheme, rest)) = value.split_once("://") else {
+            return Ok(None);
+        };
+        let reference = match scheme {
+            "env" => SecretReference::Env(rest.to_string()),
+            "keyring" => {
+                let (service, account) = rest
+                    .split_once('/')
+                    .ok_or_else(|| anyhow!("expected keyring://service/account"))?;
+                SecretReference::Keyring {
+                    service: service.to_string(),
+                    account: account.to_string(),
+                }
+            }
+            "vault" => {
+                let (path, key) = rest
+                    .split_once('#')
+                    .ok_or_else(|| anyhow!("expected vault://path#key"))?;
+                SecretReference::Vault {
+                    path: path.trim_matches('/').to_string(),
+                    key: key.to_string(),
+                }
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(reference))
+    }
+
+    pub fn resolve(&self) -> anyhow::Result<String> {
+        match self {
+            SecretReference::Env(name) => std::env::var(name)
+                .with_context(|| format!("environment variable {name} is not set")),
+            SecretReference::Keyring { service, account } => keyring_password(service, account),
+            SecretReference::Vault { path, key } => {
+                // in a thread since the blocking client can't run inside an async runtime
+                let (path, key) = (path.clone(), key.clone());
+                std::thread::spawn(move || vault_secret(&path, &key))
+                    .join()
+                    .map_err(|_| anyhow!("Vault lookup panicked"))?
+            }
+        }
+    }
+}
+
+fn keyring_password(service: &str, account: &str) -> anyhow::Result<String> {
+    keyring::Entry::new(service, account)?
+        .get_password()
+        .with_context(|| {
+            format!("no password for account {account} of service {service} in the keyring")
+        })
+}
+
+/// `VAULT_ADDR` (`http://127.0.0.1:8200` by default), `VAULT_TOKEN` or the
+/// token `vault login` saved in `~/.vault-token`, and `VAULT_NAMESPACE` are
+/// used like the Vault CLI does.
+fn vault_secret(path: &str, key: &str) -> anyhow::Result<String> {
+    let addr = std::env::var("VAULT_ADDR").unwrap_or_else(|_| "http://127.0.0.1:8200".into());
+    let token = match std::env::var("VAULT_TOKEN") {
+        Ok(token) => token,
+        Err(_) => std::env::var_os("HOME")
+            .map(|home| PathBuf::from(home).join(".vault-token"))
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .map(|token| token.trim().to_string())
+            .ok_or_else(|| anyhow!("VAULT_TOKEN is not set and there's no ~/.vault-token"))?,
+    };
+    let url = format!("{}/v1/{path}", addr.trim_end_matches('/'));
+    let mut request = reqwest::blocking::Client::new()
+        .get(&url)
+        .header("X-Vault-Token", token);
+    if let Ok(namespace) = std::env::var("VAULT_NAMESPACE") {
+        request = request.header("X-Vault-Namespace", namespace);
+    }
+    let response = request
+        .send()
+        .with_context(|| format!("unable to reach Vault at {addr}"))?;
+    if !response.status().is_success() {
+        return Err(anyhow!("Vault refused {url}: {}", response.status()));
+    }
+    let body: Value = response.json()?;
+    // KV v2 nests the secret's keys in `data.data`
+    let data = match body.pointer("/data/data") {
+        Some(Value::Object(data)) => data,
+        _ => body
+            .get("data")
+            .and_then(Value::as_object)
+            .ok_or_else(|| anyhow!("{url} is not a Vault secret"))?,
+    };
+    match data.get(key) {
+        Some(Value::String(value)) => Ok(value.clone()),
+        Some(other) => Ok(other.to_string()),
+        None => Err(anyhow!("Vault secret {path} has no key {key}")),
+    }
+}
+
+/// A password, token or other credential. Its `Debug` and `Serialize` output is
+/// always [`REDACTED`] so it can't leak through `debug!("{config:#?}")` or
+/// serialized CLI arguments; the actual value is only available via `expose()`.
+/// Deserializing (e.g. from a Nickel/JSON configuration) reads the plain value.
+#[derive(Clone, PartialEq, Eq, Default)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Secret(value.into())
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    /// The value of a secret given as a reference (`env://NAME`,
+    /// `keyring://service/account` or `vault://path#key`) so it doesn't have to
+    /// be passed in plain text, e.g. as a CLI argument anyone can see in the
+    /// process list. Other values are plain secrets, returned as is.
+    pub fn resolve(&self) -> anyhow::Result<Secret> {
+        match SecretReference::parse(&self.0) {
+            Ok(Some(reference)) => reference
+                .resolve()
+                .map(Secret)
+                .with_context(|| format!("unable to resolve the secret {}", self.0)),
+            Ok(None) => Ok(self.clone()),
+            Err(err) => Err(err.context(format!("invalid secret reference {}", self.0))),
+        }
+    }
+
+    /// The form of the credential which may be stored, see `credential_digest`.
+    pub fn digest(&self, key: &[u8]) -> String {
+        credential_digest(key, &self.0)
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl Serialize for Secret {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(REDACTED)
+    }
+}
+
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Secret)
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Secret(value)
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(value: &str) -> Self {
+        Secret(value.to_string())
+    }
+}
+
+/// A new random key for `credential_digest`, generated once per RSSD.
+pub fn new_credential_digest_key() -> Vec<u8> {
+    let mut key = vec![0u8; CREDENTIAL_DIGEST_KEY_LEN];
+    rand::thread_rng().fill_bytes(&mut key);
+    key
+}
+
+/// An HMAC-SHA256 of a credential keyed with the RSSD's own key, prefixed with
+/// `hmac-sha256:`. It lets the RSSD tell whether two sessions used the same
+/// credential without storing it; since every RSSD has its own key, precomputed
+/// tables don't apply and the same credential has different digests in different
+/// RSSDs. The value is always hashed, even when it looks like a digest, so a
+/// caller can't get an arbitrary value stored.
+pub fn credential_digest(key: &[u8], value: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(value.as_bytes());
+    let digest: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    format!("{CREDENTIAL_DIGEST_PREFIX}{digest}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secrets_are_redacted() {
+        let secret = Secret::from("hunter2");
+        assert_eq!(secret.expose(), "hunter2");
+        assert_eq!(format!("{secret:?}"), REDACTED);
+        assert_eq!(
+            serde_json::to_string(&secret).unwrap(),
+            format!("\"{REDACTED}\"")
+        );
+
+        let secret: Secret = serde_json::from_str("\"hunter2\"").unwrap();
+        assert_eq!(secret.expose(), "hunter2");
+    }
+
+    #[test]
+    fn secret_references() {
+        assert_eq!(SecretReference::parse("hunter2").unwrap(), None);
+        assert_eq!(SecretReference::parse("https://example.com").unwrap(), None);
+        assert_eq!(
+            SecretReference::parse("keyring://surveilr/user@gmail.com").unwrap(),
+            Some(SecretReference::Keyring {
+                service: "surveilr".to_string(),
+                account: "user@gmail.com".to_string()
+            })
+        );
+        assert_eq!(
+            SecretReference::parse("vault://secret/data/surveilr/imap#password").unwrap(),
+            Some(SecretReference::Vault {
+                path: "secret/data/surveilr/imap".to_string(),
+                key: "password".to_string()
+            })
+        );
+        assert!(SecretReference::parse("vault://secret/data/surveilr/imap").is_err());
+
+        std::env::set_var("SURVEILR_TEST_SECRET", "hunter2");
+        let secret = Secret::from("env://SURVEILR_TEST_SECRET")
+            .resolve()
+            .unwrap();
+        assert_eq!(secret.expose(), "hunter2");
+        assert_eq!(
+            Secret::from("hunter2").resolve().unwrap().expose(),
+            "hunter2"
+        );
+        let err = Secret::from("env://SURVEILR_TEST_MISSING_SECRET")
+            .resolve()
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("SURVEILR_TEST_MISSING_SECRET is not set"));
+    }
+
+    #[test]
+    fn keyring_secret_references() {
+        keyring::set_default_credential_builder(keyring::mock::default_credential_builder());
+        let err = Secret::from("keyring://surveilr/user@gmail.com")
+            .resolve()
+            .unwrap_err();
+        assert!(format!("{err:#}")
+            .contains("no password for account user@gmail.com of service surveilr in the keyring"));
+    }
+
+    #[test]
+    fn credential_digest_is_keyed() {
+        let key = new_credential_digest_key();
+        assert_eq!(key.len(), CREDENTIAL_DIGEST_KEY_LEN);
+        let digest = credential_digest(&key, "hunter2");
+        assert!(digest.starts_with(CREDENTIAL_DIGEST_PREFIX));
+        assert!(!digest.contains("hunter2"));
+        assert_eq!(credential_digest(&key, "hunter2"), digest);
+        assert_eq!(Secret::from("hunter2").digest(&key), digest);
+
+        // another RSSD's key gives another digest
+        let other_key = new_credential_digest_key();
+        assert_ne!(credential_digest(&other_key, "hunter2"), digest);
+
+        // values which look like digests are hashed too
+        assert_ne!(credential_digest(&key, &digest), digest);
+    }
+}
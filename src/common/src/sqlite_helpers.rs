@@ -1,4 +1,4 @@
-/**
+/*
  * These are helper macros for creating type-safe functions that wrap SQLite SQL
  * statements in Rusqlite accessors. When you need convenience, use these helpers
  * but if you need high performance (like in a loop or batch), it's probably best
@@ -225,3 +225,17 @@ macro_rules! query_sql_rows_json {
         }
     };
 }
+
+/// How long a connection waits for another connection's lock (e.g. a long ingest
+/// while SQLPage is reading) before failing with `database is locked`.
+pub const DEFAULT_BUSY_TIMEOUT_MS: u64 = 30_000;
+
+/// Wait up to `SURVEILR_DB_BUSY_TIMEOUT_MS` (or `DEFAULT_BUSY_TIMEOUT_MS`) for
+/// locks held by other connections instead of failing immediately.
+pub fn set_busy_timeout(conn: &rusqlite::Connection) -> rusqlite::Result<()> {
+    let timeout_ms = std::env::var("SURVEILR_DB_BUSY_TIMEOUT_MS")
+        .ok()
+        .and_then(|ms| ms.parse().ok())
+        .unwrap_or(DEFAULT_BUSY_TIMEOUT_MS);
+    conn.busy_timeout(std::time::Duration::from_millis(timeout_ms))
+}
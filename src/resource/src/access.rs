@@ -0,0 +1,183 @@
+use std::error::Error;
+use std::io::ErrorKind;
+use std::path::Path;
+
+use anyhow::Context;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::Serialize;
+
+use crate::{
+    digest::DigestAlgorithm, BinaryContent, EncounteredResourceContentSuppliers,
+    ResourceBinaryContent, ResourceTextContent, TextContent,
+};
+
+/// When, during a session, a path couldn't be read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AccessStage {
+    /// the walk couldn't list a directory (or stat an entry)
+    Walk,
+    /// the content of a file couldn't be read
+    Read,
+}
+
+/// A path surveilr wasn't allowed to (or couldn't) read, reported once per
+/// session instead of as generic per-file errors.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AccessIssue {
+    pub path: String,
+    pub stage: AccessStage,
+    pub error: String,
+}
+
+/// Whether `err`, or one of its sources, is an I/O permission error.
+pub fn is_access_denied(err: &(dyn Error + 'static)) -> bool {
+    let mut source = Some(err);
+    while let Some(err) = source {
+        if let Some(io_err) = err.downcast_ref::<std::io::Error>() {
+            if io_err.kind() == ErrorKind::PermissionDenied {
+                return true;
+            }
+        }
+        source = err.source();
+    }
+    false
+}
+
+/// The access issue of an error yielded by an `ignore` walk, these don't
+/// prevent the rest of the tree from being walked.
+pub fn walk_access_issue(err: &ignore::Error) -> Option<AccessIssue> {
+    fn path(err: &ignore::Error) -> Option<&Path> {
+        match err {
+            ignore::Error::WithPath { path, .. } => Some(path),
+            ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => {
+                path(err)
+            }
+            ignore::Error::Loop { child, .. } => Some(child),
+            _ => None,
+        }
+    }
+
+    Some(AccessIssue {
+        path: path(err)
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        stage: AccessStage::Walk,
+        error: err.io_error()?.to_string(),
+    })
+}
+
+/// Paths designated (by glob) to be read as another user with
+/// `sudo -n -u <user> cat` when surveilr's own user isn't allowed to read them.
+/// `-n` makes sudo fail instead of prompting, so the sudoers policy must allow
+/// the command without a password.
+#[derive(Debug, Clone)]
+pub struct SudoRead {
+    globs: GlobSet,
+    pub user: String,
+}
+
+impl SudoRead {
+    pub fn new(globs: &[String], user: &str) -> anyhow::Result<SudoRead> {
+        let mut builder = GlobSetBuilder::new();
+        for glob in globs {
+            builder.add(Glob::new(glob)?);
+        }
+        Ok(SudoRead {
+            globs: builder.build()?,
+            user: user.to_string(),
+        })
+    }
+
+    /// Whether `path` is designated and surveilr's own user can't open it.
+    pub fn applies(&self, path: &str) -> bool {
+        self.globs.is_match(path)
+            && matches!(std::fs::File::open(path), Err(err) if err.kind() == ErrorKind::PermissionDenied)
+    }
+
+    pub fn read(&self, path: &str) -> anyhow::Result<Vec<u8>> {
+        let captured = subprocess::Exec::cmd("sudo")
+            .args(&["-n", "-u", &self.user, "--", "cat", "--", path])
+            .stdout(subprocess::Redirection::Pipe)
+            .stderr(subprocess::Redirection::Pipe)
+            .capture()
+            .with_context(|| format!("[SudoRead::read] running sudo to read {}", path))?;
+        if !captured.success() {
+            return Err(anyhow::anyhow!(
+                "[SudoRead::read] sudo -u {} cat {} failed ({:?}): {}",
+                self.user,
+                path,
+                captured.exit_status,
+                captured.stderr_str().trim()
+            ));
+        }
+        Ok(captured.stdout)
+    }
+
+    /// Content suppliers which read `path` through sudo.
+    pub fn content_suppliers(
+        &self,
+        path: &str,
+        digest: DigestAlgorithm,
+    ) -> EncounteredResourceContentSuppliers {
+        let (sudo, path_cbs) = (self.clone(), path.to_string());
+        let binary = Box::new(move || -> Result<Box<dyn BinaryContent>, Box<dyn Error>> {
+            let binary = sudo.read(&path_cbs).map_err(|err| format!("{:#}", err))?;
+            let hash = digest.hex_digest(&binary);
+            Ok(Box::new(ResourceBinaryContent { hash, binary }) as Box<dyn BinaryContent>)
+        });
+
+        let (sudo, path_cts) = (self.clone(), path.to_string());
+        let text = Box::new(move || -> Result<Box<dyn TextContent>, Box<dyn Error>> {
+            let text =
+                String::from_utf8(sudo.read(&path_cts).map_err(|err| format!("{:#}", err))?)?;
+            let hash = digest.hex_digest(&text);
+            Ok(Box::new(ResourceTextContent { hash, text }) as Box<dyn TextContent>)
+        });
+
+        EncounteredResourceContentSuppliers {
+            binary: Some(binary),
+            text: Some(text),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_access_denied() {
+        let denied = std::io::Error::new(ErrorKind::PermissionDenied, "denied");
+        assert!(is_access_denied(&denied));
+        let wrapped = anyhow::Error::new(denied).context("reading /etc/shadow");
+        assert!(wrapped.chain().any(is_access_denied));
+        let missing = std::io::Error::new(ErrorKind::NotFound, "missing");
+        assert!(!is_access_denied(&missing));
+
+        let walk_err = ignore::Error::WithPath {
+            path: "/root/private".into(),
+            err: Box::new(ignore::Error::Io(std::io::Error::new(
+                ErrorKind::PermissionDenied,
+                "denied",
+            ))),
+        };
+        let issue = walk_access_issue(&walk_err).unwrap();
+        assert_eq!(issue.path, "/root/private");
+        assert_eq!(issue.stage, AccessStage::Walk);
+    }
+
+    #[test]
+    fn test_sudo_read_applies_to_designated_unreadable_paths() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let readable = dir.path().join("readable.log");
+        std::fs::write(&readable, "content")?;
+        let readable = readable.to_string_lossy().to_string();
+
+        let sudo = SudoRead::new(&["**/*.log".to_string()], "root")?;
+        // readable paths are read by surveilr itself
+        assert!(!sudo.applies(&readable));
+        assert!(!sudo.applies("/nonexistent/file.log"));
+        Ok(())
+    }
+}
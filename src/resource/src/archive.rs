@@ -0,0 +1,522 @@
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use anyhow::Context;
+use chrono::DateTime;
+use common::secret::Secret;
+use flate2::read::MultiGzDecoder;
+use serde::Serialize;
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+use crate::tar::{normalized_path, read_tar, TarEntryKind};
+
+/// End of central directory record
+const EOCD_SIGNATURE: &[u8] = b"PK\x05\x06";
+/// Locator of the zip64 end of central directory record, which immediately
+/// precedes the (classic) end of central directory record in zip64 archives
+const ZIP64_EOCD_LOCATOR_SIGNATURE: &[u8] = b"PK\x06\x07";
+const ZIP64_EOCD_LOCATOR_LEN: usize = 20;
+/// The end of central directory record is 22 bytes plus a comment of up to 64K
+const EOCD_MAX_LEN: u64 = 22 + u16::MAX as u64;
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+/// ustar (POSIX, GNU and PAX) headers carry `ustar` at this offset
+const USTAR_MAGIC_OFFSET: usize = 257;
+
+/// The kinds of archives whose members can be expanded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub enum ArchiveFormat {
+    #[default]
+    #[serde(rename = "zip")]
+    Zip,
+    #[serde(rename = "tar")]
+    Tar,
+    #[serde(rename = "tar.gz")]
+    TarGz,
+}
+
+/// How the members of an archive are expanded.
+#[derive(Debug, Clone, Default)]
+pub struct ArchivePolicy {
+    /// tried in order on encrypted members (ZipCrypto and AES)
+    pub passwords: Vec<Secret>,
+    /// members larger than this (uncompressed) are skipped
+    pub max_member_size: u64,
+}
+
+/// Why a member of an archive wasn't expanded.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum MemberSkip {
+    /// none of the supplied passwords (if any) decrypts the member
+    Encrypted {
+        passwords_tried: usize,
+    },
+    TooLarge {
+        size_bytes: u64,
+        max_size_bytes: u64,
+    },
+    Unreadable {
+        error: String,
+    },
+}
+
+/// A file inside an archive, with its content or the reason it was skipped.
+#[derive(Debug)]
+pub struct ArchiveMember {
+    pub name: String,
+    pub size_bytes: u64,
+    /// `YYYY-MM-DD HH:MM:SS` (zip timestamps have no time zone, tar ones are UTC)
+    pub last_modified_at: String,
+    pub encrypted: bool,
+    pub content: Result<Vec<u8>, MemberSkip>,
+}
+
+/// What was found while expanding an archive.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ArchiveSummary {
+    pub format: ArchiveFormat,
+    pub zip64: bool,
+    pub members: usize,
+    pub expanded: usize,
+    pub skipped_encrypted: usize,
+    pub skipped_other: usize,
+}
+
+/// Whether the file starts like a zip archive (regardless of its extension).
+pub fn is_zip(path: &Path) -> bool {
+    let mut magic = [0u8; 4];
+    File::open(path)
+        .and_then(|mut file| file.read_exact(&mut magic))
+        .map(|_| matches!(&magic, b"PK\x03\x04" | b"PK\x05\x06"))
+        .unwrap_or(false)
+}
+
+/// Which kind of archive the file is, judging by its content (regardless of
+/// its extension); gzipped files are only archives if they hold a tar.
+pub fn archive_format(path: &Path) -> Option<ArchiveFormat> {
+    if is_zip(path) {
+        return Some(ArchiveFormat::Zip);
+    }
+    let mut magic = [0u8; 2];
+    File::open(path).ok()?.read_exact(&mut magic).ok()?;
+    let mut header = [0u8; USTAR_MAGIC_OFFSET + 5];
+    if magic == GZIP_MAGIC {
+        MultiGzDecoder::new(File::open(path).ok()?)
+            .read_exact(&mut header)
+            .ok()?;
+        (&header[USTAR_MAGIC_OFFSET..] == b"ustar").then_some(ArchiveFormat::TarGz)
+    } else {
+        File::open(path).ok()?.read_exact(&mut header).ok()?;
+        (&header[USTAR_MAGIC_OFFSET..] == b"ustar").then_some(ArchiveFormat::Tar)
+    }
+}
+
+/// Expand the file members of the zip, tar or tar.gz archive at `path` into
+/// `visit`, see [`expand_zip`] and [`expand_tar`].
+pub fn expand_archive(
+    path: &Path,
+    policy: &ArchivePolicy,
+    visit: impl FnMut(ArchiveMember) -> anyhow::Result<()>,
+) -> anyhow::Result<ArchiveSummary> {
+    match archive_format(path) {
+        Some(ArchiveFormat::Zip) => expand_zip(path, policy, visit),
+        Some(format) => expand_tar(path, format, policy, visit),
+        None => Err(anyhow::anyhow!(
+            "[expand_archive] {} is not a zip, tar or tar.gz archive",
+            path.display()
+        )),
+    }
+}
+
+/// Expand the regular files of the (possibly gzipped) tar archive at `path`,
+/// streamed rather than loaded in memory. Directories, links and devices
+/// aren't members; tar has no encryption so nothing is skipped as encrypted.
+pub fn expand_tar(
+    path: &Path,
+    format: ArchiveFormat,
+    policy: &ArchivePolicy,
+    mut visit: impl FnMut(ArchiveMember) -> anyhow::Result<()>,
+) -> anyhow::Result<ArchiveSummary> {
+    let file = BufReader::new(
+        File::open(path).with_context(|| format!("[expand_tar] open {}", path.display()))?,
+    );
+    let reader: Box<dyn Read> = match format {
+        ArchiveFormat::TarGz => Box::new(MultiGzDecoder::new(file)),
+        _ => Box::new(file),
+    };
+
+    let mut summary = ArchiveSummary {
+        format,
+        ..Default::default()
+    };
+    read_tar(reader, |entry, mut content| {
+        let Some(name) = normalized_path(&entry.path) else {
+            return Ok(());
+        };
+        if entry.kind != TarEntryKind::File {
+            return Ok(());
+        }
+        let content = if entry.size > policy.max_member_size {
+            Err(MemberSkip::TooLarge {
+                size_bytes: entry.size,
+                max_size_bytes: policy.max_member_size,
+            })
+        } else {
+            read_member(&mut content, policy.max_member_size)
+        };
+
+        summary.members += 1;
+        match &content {
+            Ok(_) => summary.expanded += 1,
+            Err(_) => summary.skipped_other += 1,
+        }
+        visit(ArchiveMember {
+            name,
+            size_bytes: entry.size,
+            last_modified_at: DateTime::from_timestamp(entry.mtime, 0)
+                .map(|mtime| mtime.format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_default(),
+            encrypted: false,
+            content,
+        })
+    })
+    .with_context(|| format!("[expand_tar] invalid archive {}", path.display()))?;
+    Ok(summary)
+}
+
+/// Expand the file members of the zip archive at `path`, one at a time, into
+/// `visit`. The archive is read through seeks rather than loaded in memory so
+/// zip64 archives larger than 4GB work as well; only members up to
+/// `policy.max_member_size` are held in memory.
+pub fn expand_zip(
+    path: &Path,
+    policy: &ArchivePolicy,
+    mut visit: impl FnMut(ArchiveMember) -> anyhow::Result<()>,
+) -> anyhow::Result<ArchiveSummary> {
+    let mut file =
+        File::open(path).with_context(|| format!("[expand_zip] open {}", path.display()))?;
+    let zip64 = is_zip64(&mut file).with_context(|| {
+        format!(
+            "[expand_zip] end of central directory of {}",
+            path.display()
+        )
+    })?;
+    let mut archive = ZipArchive::new(file)
+        .with_context(|| format!("[expand_zip] invalid archive {}", path.display()))?;
+
+    let mut summary = ArchiveSummary {
+        zip64,
+        ..Default::default()
+    };
+    for index in 0..archive.len() {
+        let (name, size_bytes, last_modified_at) = {
+            let raw = archive
+                .by_index_raw(index)
+                .with_context(|| format!("[expand_zip] member {index} of {}", path.display()))?;
+            if raw.is_dir() {
+                continue;
+            }
+            // named like tar members, those escaping the archive are ignored
+            let Some(name) = normalized_path(raw.name()) else {
+                continue;
+            };
+            let modified = raw.last_modified();
+            (
+                name,
+                raw.size(),
+                format!(
+                    "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+                    modified.year(),
+                    modified.month(),
+                    modified.day(),
+                    modified.hour(),
+                    modified.minute(),
+                    modified.second()
+                ),
+            )
+        };
+
+        let (encrypted, content) = if size_bytes > policy.max_member_size {
+            (
+                false,
+                Err(MemberSkip::TooLarge {
+                    size_bytes,
+                    max_size_bytes: policy.max_member_size,
+                }),
+            )
+        } else {
+            let plain = match archive.by_index(index) {
+                Ok(mut member) => Some(read_member(&mut member, policy.max_member_size)),
+                Err(ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED)) => None,
+                Err(err) => Some(Err(MemberSkip::Unreadable {
+                    error: err.to_string(),
+                })),
+            };
+            match plain {
+                Some(content) => (false, content),
+                None => (true, decrypt_member(&mut archive, index, policy)),
+            }
+        };
+
+        summary.members += 1;
+        match &content {
+            Ok(_) => summary.expanded += 1,
+            Err(MemberSkip::Encrypted { .. }) => summary.skipped_encrypted += 1,
+            Err(_) => summary.skipped_other += 1,
+        }
+        visit(ArchiveMember {
+            name,
+            size_bytes,
+            last_modified_at,
+            encrypted,
+            content,
+        })?;
+    }
+    Ok(summary)
+}
+
+/// Read a member's content, at most `max_size` bytes of it whatever size the
+/// archive declares for it (a zip bomb declares a small one).
+fn read_member(member: &mut impl Read, max_size: u64) -> Result<Vec<u8>, MemberSkip> {
+    let mut content = Vec::new();
+    member
+        .take(max_size.saturating_add(1))
+        .read_to_end(&mut content)
+        .map_err(|err| MemberSkip::Unreadable {
+            error: err.to_string(),
+        })?;
+    if content.len() as u64 > max_size {
+        return Err(MemberSkip::TooLarge {
+            size_bytes: content.len() as u64,
+            max_size_bytes: max_size,
+        });
+    }
+    Ok(content)
+}
+
+/// Try every password on an encrypted member. ZipCrypto only checks one byte
+/// of the password up front, so a wrong password may only fail once the
+/// content doesn't match its CRC; that's treated as a wrong password too.
+fn decrypt_member(
+    archive: &mut ZipArchive<File>,
+    index: usize,
+    policy: &ArchivePolicy,
+) -> Result<Vec<u8>, MemberSkip> {
+    let passwords = &policy.passwords;
+    for password in passwords {
+        match archive.by_index_decrypt(index, password.expose().as_bytes()) {
+            Ok(Ok(mut member)) => match read_member(&mut member, policy.max_member_size) {
+                Ok(content) => return Ok(content),
+                Err(too_large @ MemberSkip::TooLarge { .. }) => return Err(too_large),
+                Err(_) => {}
+            },
+            Ok(Err(_invalid_password)) => {}
+            Err(err) => {
+                return Err(MemberSkip::Unreadable {
+                    error: err.to_string(),
+                })
+            }
+        }
+    }
+    Err(MemberSkip::Encrypted {
+        passwords_tried: passwords.len(),
+    })
+}
+
+/// Whether the archive has a zip64 end of central directory, which is how
+/// archives over 4GB (or with more than 65535 members) are laid out.
+fn is_zip64(file: &mut File) -> std::io::Result<bool> {
+    let len = file.seek(SeekFrom::End(0))?;
+    let tail_len = len.min(EOCD_MAX_LEN + ZIP64_EOCD_LOCATOR_LEN as u64);
+    file.seek(SeekFrom::Start(len - tail_len))?;
+    let mut tail = vec![0u8; tail_len as usize];
+    file.read_exact(&mut tail)?;
+    file.rewind()?;
+    Ok(has_zip64_locator(&tail))
+}
+
+fn has_zip64_locator(tail: &[u8]) -> bool {
+    let Some(eocd) = tail
+        .windows(EOCD_SIGNATURE.len())
+        .rposition(|window| window == EOCD_SIGNATURE)
+    else {
+        return false;
+    };
+    eocd >= ZIP64_EOCD_LOCATOR_LEN
+        && tail[eocd - ZIP64_EOCD_LOCATOR_LEN..].starts_with(ZIP64_EOCD_LOCATOR_SIGNATURE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::FileOptions;
+
+    fn write_zip(path: &Path, members: &[(&str, &[u8])]) {
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        for (name, content) in members {
+            zip.start_file(*name, FileOptions::default()).unwrap();
+            zip.write_all(content).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    fn expanded(path: &Path, policy: &ArchivePolicy) -> (ArchiveSummary, Vec<ArchiveMember>) {
+        let mut members = Vec::new();
+        let summary = expand_zip(path, policy, |member| {
+            members.push(member);
+            Ok(())
+        })
+        .unwrap();
+        (summary, members)
+    }
+
+    #[test]
+    fn test_expand_zip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("evidence.zip");
+        write_zip(&path, &[("notes.txt", b"hello"), ("big.bin", &[0u8; 64])]);
+        assert!(is_zip(&path));
+        assert!(!is_zip(Path::new("Cargo.toml")));
+
+        let policy = ArchivePolicy {
+            passwords: vec![],
+            max_member_size: 32,
+        };
+        let (summary, members) = expanded(&path, &policy);
+        assert!(!summary.zip64);
+        assert_eq!((summary.expanded, summary.skipped_other), (1, 1));
+        assert_eq!(members[0].name, "notes.txt");
+        assert_eq!(members[0].content, Ok(b"hello".to_vec()));
+        assert_eq!(
+            members[1].content,
+            Err(MemberSkip::TooLarge {
+                size_bytes: 64,
+                max_size_bytes: 32
+            })
+        );
+    }
+
+    #[test]
+    fn test_understated_member_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bomb.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&path).unwrap());
+        zip.start_file(
+            "./evidence/../bomb.bin",
+            FileOptions::default().compression_method(zip::CompressionMethod::Stored),
+        )
+        .unwrap();
+        zip.write_all(&[b'x'; 64]).unwrap();
+        zip.finish().unwrap();
+        // declare 4 bytes (uncompressed) in the local and central headers
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[22..26].copy_from_slice(&4u32.to_le_bytes());
+        let central = bytes
+            .windows(4)
+            .position(|window| window == b"PK\x01\x02")
+            .unwrap();
+        bytes[central + 24..central + 28].copy_from_slice(&4u32.to_le_bytes());
+        std::fs::write(&path, bytes).unwrap();
+
+        let policy = ArchivePolicy {
+            passwords: vec![],
+            max_member_size: 32,
+        };
+        let (summary, members) = expanded(&path, &policy);
+        assert_eq!((summary.expanded, summary.skipped_other), (0, 1));
+        assert_eq!(members[0].name, "bomb.bin");
+        assert_eq!(members[0].size_bytes, 4);
+        assert_eq!(
+            members[0].content,
+            Err(MemberSkip::TooLarge {
+                size_bytes: 33,
+                max_size_bytes: 32
+            })
+        );
+    }
+
+    #[test]
+    fn test_encrypted_member_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sealed.zip");
+        write_zip(&path, &[("secret.txt", b"classified evidence")]);
+        // flag the (stored) member as encrypted in its local and central headers
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[6] |= 1;
+        let central = bytes
+            .windows(4)
+            .position(|window| window == b"PK\x01\x02")
+            .unwrap();
+        bytes[central + 8] |= 1;
+        std::fs::write(&path, bytes).unwrap();
+
+        let policy = ArchivePolicy {
+            passwords: vec![Secret::new("wrong"), Secret::new("also wrong")],
+            max_member_size: 1024,
+        };
+        let (summary, members) = expanded(&path, &policy);
+        assert_eq!((summary.expanded, summary.skipped_encrypted), (0, 1));
+        assert!(members[0].encrypted);
+        assert_eq!(
+            members[0].content,
+            Err(MemberSkip::Encrypted { passwords_tried: 2 })
+        );
+    }
+
+    #[test]
+    fn test_expand_tar() {
+        let dir = tempfile::tempdir().unwrap();
+        let tar = crate::tar::tar_bytes(&[
+            ("./etc/", b'5', b""),
+            ("./etc/hosts", b'0', b"127.0.0.1 localhost"),
+            ("./etc/localhost", b'2', b"hosts"),
+            ("var/log/big.log", b'0', &[b'x'; 64]),
+        ]);
+        let tar_path = dir.path().join("rootfs.tar");
+        std::fs::write(&tar_path, &tar).unwrap();
+        let tgz_path = dir.path().join("rootfs.bin");
+        let mut gz =
+            flate2::write::GzEncoder::new(File::create(&tgz_path).unwrap(), Default::default());
+        gz.write_all(&tar).unwrap();
+        gz.finish().unwrap();
+        assert_eq!(archive_format(&tar_path), Some(ArchiveFormat::Tar));
+        assert_eq!(archive_format(&tgz_path), Some(ArchiveFormat::TarGz));
+        assert_eq!(archive_format(Path::new("Cargo.toml")), None);
+
+        let policy = ArchivePolicy {
+            passwords: vec![],
+            max_member_size: 32,
+        };
+        for path in [&tar_path, &tgz_path] {
+            let mut members = Vec::new();
+            let summary = expand_archive(path, &policy, |member| {
+                members.push(member);
+                Ok(())
+            })
+            .unwrap();
+            assert_eq!((summary.members, summary.expanded), (2, 1));
+            assert_eq!(members[0].name, "etc/hosts");
+            assert_eq!(members[0].last_modified_at, "2023-11-14 22:13:20");
+            assert_eq!(members[0].content, Ok(b"127.0.0.1 localhost".to_vec()));
+            assert_eq!(members[1].name, "var/log/big.log");
+            assert!(matches!(
+                members[1].content,
+                Err(MemberSkip::TooLarge { size_bytes: 64, .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_zip64_locator() {
+        let eocd = [&b"PK\x05\x06"[..], &[0u8; 18]].concat();
+        assert!(!has_zip64_locator(&eocd));
+        assert!(!has_zip64_locator(b"not an archive"));
+
+        let locator = [&b"PK\x06\x07"[..], &[0u8; 16]].concat();
+        let zip64_tail = [&b"central directory"[..], &locator, &eocd].concat();
+        assert!(has_zip64_locator(&zip64_tail));
+    }
+}
@@ -0,0 +1,137 @@
+use std::io;
+
+use serde::Serialize;
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+
+/// The algorithm of the `content_digest` of resources an ingest session
+/// stores, recorded as the session's and each resource's
+/// `content_digest_algorithm`. SHA-1 unless `--digest` asks for another one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DigestAlgorithm {
+    #[default]
+    Sha1,
+    Sha256,
+    Blake3,
+}
+
+impl std::str::FromStr for DigestAlgorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sha1" => Ok(DigestAlgorithm::Sha1),
+            "sha256" => Ok(DigestAlgorithm::Sha256),
+            "blake3" => Ok(DigestAlgorithm::Blake3),
+            _ => Err(anyhow::anyhow!(
+                "unknown digest `{}`, expected `sha1`, `sha256` or `blake3`",
+                s
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for DigestAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            DigestAlgorithm::Sha1 => "sha1",
+            DigestAlgorithm::Sha256 => "sha256",
+            DigestAlgorithm::Blake3 => "blake3",
+        })
+    }
+}
+
+impl DigestAlgorithm {
+    pub fn hasher(&self) -> ContentHasher {
+        match self {
+            DigestAlgorithm::Sha1 => ContentHasher::Sha1(Sha1::new()),
+            DigestAlgorithm::Sha256 => ContentHasher::Sha256(Sha256::new()),
+            DigestAlgorithm::Blake3 => ContentHasher::Blake3(Box::default()),
+        }
+    }
+
+    /// The lowercase hex digest of `content`.
+    pub fn hex_digest(&self, content: impl AsRef<[u8]>) -> String {
+        let mut hasher = self.hasher();
+        hasher.update(content.as_ref());
+        hasher.finalize_hex()
+    }
+}
+
+/// Hashes content with a `DigestAlgorithm`, written to it in pieces when it's
+/// streamed (e.g. with `std::io::copy`).
+#[derive(Clone)]
+pub enum ContentHasher {
+    Sha1(Sha1),
+    Sha256(Sha256),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl ContentHasher {
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            ContentHasher::Sha1(hasher) => hasher.update(data),
+            ContentHasher::Sha256(hasher) => hasher.update(data),
+            ContentHasher::Blake3(hasher) => {
+                hasher.update(data);
+            }
+        }
+    }
+
+    pub fn finalize_hex(self) -> String {
+        match self {
+            ContentHasher::Sha1(hasher) => format!("{:x}", hasher.finalize()),
+            ContentHasher::Sha256(hasher) => format!("{:x}", hasher.finalize()),
+            ContentHasher::Blake3(hasher) => hasher.finalize().to_hex().to_string(),
+        }
+    }
+}
+
+impl io::Write for ContentHasher {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn digests() {
+        assert_eq!(
+            DigestAlgorithm::Sha1.hex_digest("abc"),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+        assert_eq!(
+            DigestAlgorithm::Sha256.hex_digest("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            DigestAlgorithm::Blake3.hex_digest("abc"),
+            "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"
+        );
+        assert_eq!(
+            DigestAlgorithm::Blake3.hex_digest(""),
+            "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+        );
+
+        let mut hasher = DigestAlgorithm::Sha256.hasher();
+        std::io::copy(&mut &b"abc"[..], &mut hasher).unwrap();
+        assert_eq!(
+            hasher.finalize_hex(),
+            DigestAlgorithm::Sha256.hex_digest("abc")
+        );
+        for algorithm in ["sha1", "sha256", "blake3"] {
+            let parsed: DigestAlgorithm = algorithm.parse().unwrap();
+            assert_eq!(parsed.to_string(), algorithm);
+        }
+        assert!("md5".parse::<DigestAlgorithm>().is_err());
+    }
+}
@@ -69,6 +69,21 @@ pub fn frontmatter(text: &str) -> FrontmatterComponents {
     (nature, frontmatter_raw, frontmatter_json, content)
 }
 
+/// Parse a whole YAML or TOML document, rather than a frontmatter block at the
+/// top of Markdown, so config files get the same queryable structure.
+#[autometrics]
+pub fn document_frontmatter(
+    nature: &FrontmatterNature,
+    text: &str,
+) -> Result<JsonValue, Box<dyn Error>> {
+    match nature {
+        FrontmatterNature::YamlFM => serde_yaml::from_str(text).map_err(Into::into),
+        FrontmatterNature::TomlFM => toml::from_str(text).map_err(Into::into),
+        FrontmatterNature::JsonFM => serde_json::from_str(text).map_err(Into::into),
+        FrontmatterNature::None => Err("No frontmatter nature".into()),
+    }
+}
+
 // The rest of the code, including tests, remains the same.
 
 #[cfg(test)]
@@ -135,6 +150,21 @@ mod tests {
         assert!(fm_json.is_err());
     }
 
+    #[test]
+    fn test_document_frontmatter() {
+        let yaml = "server:\n  port: 8080\n  tls: true\n";
+        assert_eq!(
+            document_frontmatter(&FrontmatterNature::YamlFM, yaml).unwrap(),
+            json!({"server": {"port": 8080, "tls": true}})
+        );
+        let toml = "[server]\nport = 8080\n";
+        assert_eq!(
+            document_frontmatter(&FrontmatterNature::TomlFM, toml).unwrap(),
+            json!({"server": {"port": 8080}})
+        );
+        assert!(document_frontmatter(&FrontmatterNature::TomlFM, "port = ").is_err());
+    }
+
     #[test]
     fn test_invalid_json_frontmatter() {
         let text = "{\n\"title\": \"Example\"\n\nContent goes here."; // Missing closing }
@@ -0,0 +1,454 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use anyhow::{anyhow, bail, Context};
+use serde::Serialize;
+
+/// The git repository (work tree) a walked path belongs to, read straight from
+/// the `.git` directory so no `git` executable is required.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GitRepo {
+    /// the work tree root, the directory which holds `.git`
+    pub repo_root: PathBuf,
+    /// the walked path relative to `repo_root` (empty when they're the same)
+    pub root_path_rel: PathBuf,
+    /// the checked out branch, `None` when HEAD is detached
+    pub branch: Option<String>,
+    /// the commit HEAD points to, `None` in a repository without commits
+    pub commit: Option<String>,
+}
+
+impl GitRepo {
+    /// Find the repository containing `path` (which should be canonical) by
+    /// looking for `.git` in it and its ancestors.
+    pub fn discover(path: &Path) -> Option<GitRepo> {
+        let repo_root = path.ancestors().find(|p| p.join(".git").exists())?;
+        let git_dir = resolve_git_dir(&repo_root.join(".git"))?;
+        // linked work trees keep their refs in the main repository's git dir
+        let common_dir = fs::read_to_string(git_dir.join("commondir"))
+            .map(|common| git_dir.join(common.trim()))
+            .unwrap_or_else(|_| git_dir.clone());
+
+        let head = fs::read_to_string(git_dir.join("HEAD")).ok()?;
+        let head = head.trim();
+        let (branch, commit) = match head.strip_prefix("ref:") {
+            Some(reference) => {
+                let reference = reference.trim();
+                let branch = reference
+                    .strip_prefix("refs/heads/")
+                    .unwrap_or(reference)
+                    .to_string();
+                (
+                    Some(branch),
+                    resolve_ref(&git_dir, reference)
+                        .or_else(|| resolve_ref(&common_dir, reference)),
+                )
+            }
+            None => (None, Some(head.to_string())),
+        };
+
+        Some(GitRepo {
+            repo_root: repo_root.to_path_buf(),
+            root_path_rel: path.strip_prefix(repo_root).ok()?.to_path_buf(),
+            branch,
+            commit,
+        })
+    }
+}
+
+/// The repositories of walked files, which may be in different (e.g. sibling)
+/// work trees under the walked root. Each directory is only looked up once.
+#[derive(Debug, Default)]
+pub struct GitRepoCache(HashMap<PathBuf, Option<GitRepo>>);
+
+impl GitRepoCache {
+    /// The repository containing the file at `path` (which should be
+    /// canonical), `root_path_rel` is the file relative to the repository.
+    pub fn discover(&mut self, path: &Path) -> Option<GitRepo> {
+        let dir = path.parent()?;
+        let repo = self
+            .0
+            .entry(dir.to_path_buf())
+            .or_insert_with(|| GitRepo::discover(dir))
+            .as_ref()?;
+        Some(GitRepo {
+            root_path_rel: path.strip_prefix(&repo.repo_root).ok()?.to_path_buf(),
+            ..repo.clone()
+        })
+    }
+}
+
+/// `.git` is a directory, or a file with `gitdir: <path>` for linked work trees
+/// and submodules.
+fn resolve_git_dir(dot_git: &Path) -> Option<PathBuf> {
+    if dot_git.is_dir() {
+        return Some(dot_git.to_path_buf());
+    }
+    let content = fs::read_to_string(dot_git).ok()?;
+    let git_dir = Path::new(content.trim().strip_prefix("gitdir:")?.trim());
+    Some(dot_git.parent()?.join(git_dir))
+}
+
+/// Look up a reference as a loose ref file, then in `packed-refs`.
+fn resolve_ref(git_dir: &Path, reference: &str) -> Option<String> {
+    if let Ok(commit) = fs::read_to_string(git_dir.join(reference)) {
+        return Some(commit.trim().to_string());
+    }
+    fs::read_to_string(git_dir.join("packed-refs"))
+        .ok()?
+        .lines()
+        .filter(|line| !line.starts_with('#') && !line.starts_with('^'))
+        .find_map(|line| match line.split_once(' ') {
+            Some((commit, name)) if name.trim() == reference => Some(commit.to_string()),
+            _ => None,
+        })
+}
+
+/// A commit of a local or remote repository read with the `git` executable, so
+/// unlike [`GitRepo`] its files can be read without a work tree. Remote
+/// repositories are cloned (bare) into a temporary directory which is removed
+/// when the `GitTree` is dropped.
+#[derive(Debug)]
+pub struct GitTree {
+    /// the repository as given, a path or a URL
+    pub repo: String,
+    /// the reference which was resolved, `HEAD` by default
+    pub reference: String,
+    pub commit: GitCommit,
+    /// whether the repository was cloned
+    pub cloned: bool,
+    git_dir: PathBuf,
+    _clone: Option<tempfile::TempDir>,
+}
+
+/// The commit a [`GitTree`] was resolved to. The dates are RFC 3339 with the
+/// author's and committer's UTC offsets.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GitCommit {
+    pub sha: String,
+    pub author_name: String,
+    pub author_email: String,
+    pub authored_at: String,
+    pub committer_name: String,
+    pub committer_email: String,
+    pub committed_at: String,
+    pub subject: String,
+}
+
+/// A file (blob) of a [`GitTree`], submodules are left out.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GitBlob {
+    /// relative to the repository's root, with `/` separators
+    pub path: String,
+    /// e.g. `100644`, `100755` or `120000` (a symlink)
+    pub mode: String,
+    pub oid: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GitWalkSummary {
+    pub blobs: usize,
+    pub oversized: usize,
+}
+
+impl GitTree {
+    /// Resolve `reference` (a branch, tag or commit, `HEAD` when `None`) in the
+    /// repository at `repo`, which is cloned first unless it's a local
+    /// directory.
+    pub fn open(repo: &str, reference: Option<&str>) -> anyhow::Result<GitTree> {
+        let (git_dir, clone) = if Path::new(repo).is_dir() {
+            (PathBuf::from(repo), None)
+        } else {
+            let clone = tempfile::Builder::new().prefix("surveilr-git-").tempdir()?;
+            let mut command = Command::new("git");
+            command
+                .args(["clone", "--quiet", "--bare", "--", repo])
+                .arg(clone.path());
+            run(&mut command).with_context(|| format!("[GitTree::open] cloning {repo}"))?;
+            (clone.path().to_path_buf(), Some(clone))
+        };
+        let reference = reference.unwrap_or("HEAD").to_string();
+        let sha = git(
+            &git_dir,
+            &["rev-parse", "--verify", "--quiet", "--end-of-options"],
+        )
+        .arg(format!("{reference}^{{commit}}"))
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .ok_or_else(|| anyhow!("[GitTree::open] {reference} is not a commit in {repo}"))?;
+        let commit = commit(&git_dir, &sha).with_context(|| format!("[GitTree::open] {repo}"))?;
+        Ok(GitTree {
+            repo: repo.to_string(),
+            reference,
+            commit,
+            cloned: clone.is_some(),
+            git_dir,
+            _clone: clone,
+        })
+    }
+
+    /// The files of the commit, in path order.
+    pub fn blobs(&self) -> anyhow::Result<Vec<GitBlob>> {
+        let stdout = run(&mut git(
+            &self.git_dir,
+            &[
+                "ls-tree",
+                "-r",
+                "-z",
+                "--long",
+                "--full-tree",
+                &self.commit.sha,
+            ],
+        ))
+        .context("[GitTree::blobs] ls-tree")?;
+        let mut blobs = Vec::new();
+        for entry in stdout.split(|b| *b == 0).filter(|entry| !entry.is_empty()) {
+            let entry = String::from_utf8_lossy(entry);
+            // <mode> SP <type> SP <object> SP+ <size> TAB <path>
+            let (meta, path) = entry
+                .split_once('\t')
+                .ok_or_else(|| anyhow!("[GitTree::blobs] unexpected entry {entry}"))?;
+            let meta: Vec<&str> = meta.split_whitespace().collect();
+            let [mode, kind, oid, size] = meta[..] else {
+                bail!("[GitTree::blobs] unexpected entry {entry}");
+            };
+            if kind != "blob" {
+                continue;
+            }
+            blobs.push(GitBlob {
+                path: path.to_string(),
+                mode: mode.to_string(),
+                oid: oid.to_string(),
+                size: size.parse()?,
+            });
+        }
+        Ok(blobs)
+    }
+
+    /// Visit each file with its content, `None` when it's larger than
+    /// `max_file_size` bytes. The contents are read by a single
+    /// `git cat-file --batch`.
+    pub fn walk(
+        &self,
+        max_file_size: u64,
+        mut visit: impl FnMut(&GitBlob, Option<Vec<u8>>) -> anyhow::Result<()>,
+    ) -> anyhow::Result<GitWalkSummary> {
+        let mut summary = GitWalkSummary::default();
+        let mut cat_file = git(&self.git_dir, &["cat-file", "--batch"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .context("[GitTree::walk] spawning git cat-file")?;
+        let mut stdin = cat_file.stdin.take().expect("stdin is piped");
+        let mut stdout = BufReader::new(cat_file.stdout.take().expect("stdout is piped"));
+        for blob in self.blobs()? {
+            summary.blobs += 1;
+            if blob.size > max_file_size {
+                summary.oversized += 1;
+                visit(&blob, None)?;
+                continue;
+            }
+            writeln!(stdin, "{}", blob.oid)?;
+            stdin.flush()?;
+            // <oid> SP <type> SP <size> LF <content> LF
+            let mut header = String::new();
+            stdout.read_line(&mut header)?;
+            let size: usize = match header.split_whitespace().collect::<Vec<_>>()[..] {
+                [_, "blob", size] => size.parse()?,
+                _ => bail!(
+                    "[GitTree::walk] {} in {}: {}",
+                    blob.oid,
+                    self.repo,
+                    header.trim()
+                ),
+            };
+            let mut content = vec![0; size + 1];
+            stdout.read_exact(&mut content)?;
+            content.truncate(size);
+            visit(&blob, Some(content))?;
+        }
+        drop(stdin);
+        cat_file.wait()?;
+        Ok(summary)
+    }
+}
+
+fn git(git_dir: &Path, args: &[&str]) -> Command {
+    let mut command = Command::new("git");
+    command.arg("-C").arg(git_dir).args(args);
+    command
+}
+
+/// Run `command`, returning its standard output or failing with its standard
+/// error.
+fn run(command: &mut Command) -> anyhow::Result<Vec<u8>> {
+    let output = command.output().context("unable to run git")?;
+    if !output.status.success() {
+        bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(output.stdout)
+}
+
+fn commit(git_dir: &Path, sha: &str) -> anyhow::Result<GitCommit> {
+    let stdout = run(&mut git(
+        git_dir,
+        &[
+            "show",
+            "--no-patch",
+            "--format=%H%x00%an%x00%ae%x00%aI%x00%cn%x00%ce%x00%cI%x00%s",
+            sha,
+        ],
+    ))?;
+    let stdout = String::from_utf8_lossy(&stdout);
+    let fields: Vec<&str> = stdout.trim_end_matches('\n').split('\0').collect();
+    let [sha, author_name, author_email, authored_at, committer_name, committer_email, committed_at, subject] =
+        fields[..]
+    else {
+        bail!("unexpected commit {}", stdout);
+    };
+    Ok(GitCommit {
+        sha: sha.to_string(),
+        author_name: author_name.to_string(),
+        author_email: author_email.to_string(),
+        authored_at: authored_at.to_string(),
+        committer_name: committer_name.to_string(),
+        committer_email: committer_email.to_string(),
+        committed_at: committed_at.to_string(),
+        subject: subject.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMMIT: &str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+
+    #[test]
+    fn discover_repo() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let git_dir = root.join(".git");
+        fs::create_dir_all(git_dir.join("refs/heads")).unwrap();
+        fs::create_dir_all(root.join("docs/api")).unwrap();
+
+        fs::write(git_dir.join("HEAD"), "ref: refs/heads/main\n").unwrap();
+        // a new repository has no commits yet
+        assert!(GitRepo::discover(&root.join("docs")).is_some_and(|r| r.commit.is_none()));
+
+        fs::write(
+            git_dir.join("packed-refs"),
+            format!("# pack-refs with: peeled fully-peeled sorted\n{COMMIT} refs/heads/main\n"),
+        )
+        .unwrap();
+        assert_eq!(
+            GitRepo::discover(&root.join("docs/api")),
+            Some(GitRepo {
+                repo_root: root.clone(),
+                root_path_rel: PathBuf::from("docs/api"),
+                branch: Some("main".to_string()),
+                commit: Some(COMMIT.to_string()),
+            })
+        );
+
+        fs::write(git_dir.join("HEAD"), format!("{COMMIT}\n")).unwrap();
+        let detached = GitRepo::discover(&root).unwrap();
+        assert_eq!(detached.branch, None);
+        assert_eq!(detached.commit.as_deref(), Some(COMMIT));
+    }
+
+    #[test]
+    fn discover_sibling_repos() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        for (repo, branch) in [("a", "main"), ("b", "dev")] {
+            fs::create_dir_all(root.join(repo).join(".git")).unwrap();
+            fs::create_dir_all(root.join(repo).join("docs")).unwrap();
+            fs::write(
+                root.join(repo).join(".git/HEAD"),
+                format!("ref: refs/heads/{branch}\n"),
+            )
+            .unwrap();
+        }
+
+        let mut repos = GitRepoCache::default();
+        assert_eq!(repos.discover(&root.join("README.md")), None);
+        let a = repos.discover(&root.join("a/docs/index.md")).unwrap();
+        assert_eq!(a.repo_root, root.join("a"));
+        assert_eq!(a.root_path_rel, PathBuf::from("docs/index.md"));
+        assert_eq!(a.branch.as_deref(), Some("main"));
+        let b = repos.discover(&root.join("b/README.md")).unwrap();
+        assert_eq!(b.repo_root, root.join("b"));
+        assert_eq!(b.root_path_rel, PathBuf::from("README.md"));
+        assert_eq!(b.branch.as_deref(), Some("dev"));
+    }
+
+    #[test]
+    fn walk_git_tree() {
+        let dir = tempfile::tempdir().unwrap();
+        let git = |args: &[&str]| {
+            let mut command = Command::new("git");
+            command
+                .arg("-C")
+                .arg(dir.path())
+                .args([
+                    "-c",
+                    "user.name=Jane Doe",
+                    "-c",
+                    "user.email=jane@example.com",
+                ])
+                .args(args)
+                .env("GIT_AUTHOR_DATE", "2024-03-04T09:00:00+05:00")
+                .env("GIT_COMMITTER_DATE", "2024-03-04T09:00:00+05:00");
+            run(&mut command).unwrap();
+        };
+        git(&["init", "--quiet"]);
+        fs::create_dir_all(dir.path().join("docs")).unwrap();
+        fs::write(dir.path().join("README.md"), "# repo\n").unwrap();
+        fs::write(dir.path().join("docs/big.txt"), "0123456789").unwrap();
+        git(&["add", "."]);
+        git(&["commit", "--quiet", "-m", "initial"]);
+        git(&["tag", "v1"]);
+        fs::write(dir.path().join("README.md"), "# repo, changed\n").unwrap();
+        git(&["commit", "--quiet", "-am", "second"]);
+
+        let repo = dir.path().to_string_lossy().to_string();
+        let tree = GitTree::open(&repo, Some("v1")).unwrap();
+        assert!(!tree.cloned);
+        assert_eq!(tree.commit.subject, "initial");
+        assert_eq!(tree.commit.author_email, "jane@example.com");
+        assert_eq!(tree.commit.authored_at, "2024-03-04T09:00:00+05:00");
+
+        let mut files = Vec::new();
+        let summary = tree
+            .walk(8, |blob, content| {
+                files.push((blob.path.clone(), content));
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!((summary.blobs, summary.oversized), (2, 1));
+        assert_eq!(
+            files,
+            vec![
+                ("README.md".to_string(), Some(b"# repo\n".to_vec())),
+                ("docs/big.txt".to_string(), None),
+            ]
+        );
+
+        let head = GitTree::open(&repo, None).unwrap();
+        assert_eq!(head.commit.subject, "second");
+        assert!(GitTree::open(&repo, Some("v2")).is_err());
+
+        // anything but a local directory is cloned
+        let cloned = GitTree::open(&format!("file://{repo}"), Some("v1")).unwrap();
+        assert!(cloned.cloned);
+        assert_eq!(cloned.commit, tree.commit);
+        assert_eq!(cloned.blobs().unwrap(), tree.blobs().unwrap());
+    }
+}
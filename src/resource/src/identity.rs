@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use serde_json::Value as JsonValue;
+
+/// Extracts the semantic identity of a resource from its text, `None` when the
+/// text doesn't carry one (in which case the content digest identifies it).
+pub type SemanticIdentityFn = fn(&str) -> Option<String>;
+
+lazy_static::lazy_static! {
+    static ref SEMANTIC_IDENTITIES: RwLock<HashMap<String, SemanticIdentityFn>> = {
+        let mut identities: HashMap<String, SemanticIdentityFn> = HashMap::new();
+        identities.insert("json".to_string(), sbom_identity);
+        identities.insert("eml".to_string(), message_id_identity);
+        RwLock::new(identities)
+    };
+}
+
+/// Register (or replace) the identity function of a nature. Resources of that
+/// nature with the same identity are the same resource even when their content
+/// digest differs (e.g. an email re-exported with different headers).
+pub fn register_semantic_identity(nature: &str, identity: SemanticIdentityFn) {
+    SEMANTIC_IDENTITIES
+        .write()
+        .unwrap()
+        .insert(nature.to_string(), identity);
+}
+
+/// The semantic identity of text of the given nature, if its nature has an
+/// identity function and the text carries one.
+pub fn semantic_identity(nature: &str, text: &str) -> Option<String> {
+    let identity = *SEMANTIC_IDENTITIES.read().unwrap().get(nature)?;
+    identity(text)
+}
+
+/// CycloneDX BOMs are identified by their serial number and version (as a
+/// BOM-Link), SPDX documents by their document namespace.
+pub fn sbom_identity(text: &str) -> Option<String> {
+    let sbom: JsonValue = serde_json::from_str(text).ok()?;
+    if sbom.get("bomFormat").and_then(JsonValue::as_str) == Some("CycloneDX") {
+        let serial = sbom.get("serialNumber")?.as_str()?;
+        let version = sbom.get("version").and_then(JsonValue::as_u64).unwrap_or(1);
+        return Some(format!(
+            "urn:cdx:{}/{}",
+            serial.trim_start_matches("urn:uuid:"),
+            version
+        ));
+    }
+    sbom.get("spdxVersion")?;
+    sbom.get("documentNamespace")?.as_str().map(str::to_string)
+}
+
+/// RFC 5322 messages are identified by their `Message-ID` header (without the
+/// angle brackets, like the Message-ID of messages ingested from IMAP).
+pub fn message_id_identity(text: &str) -> Option<String> {
+    text.lines()
+        .take_while(|line| !line.trim().is_empty())
+        .find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.eq_ignore_ascii_case("message-id")
+                .then(|| {
+                    value
+                        .trim()
+                        .trim_start_matches('<')
+                        .trim_end_matches('>')
+                        .to_string()
+                })
+                .filter(|value| !value.is_empty())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_semantic_identity() {
+        let bom = r#"{"bomFormat": "CycloneDX", "serialNumber": "urn:uuid:3e671687-395b-41f5-a30f-a58921a69b79", "version": 2}"#;
+        assert_eq!(
+            semantic_identity("json", bom),
+            Some("urn:cdx:3e671687-395b-41f5-a30f-a58921a69b79/2".to_string())
+        );
+        let spdx = r#"{"spdxVersion": "SPDX-2.3", "documentNamespace": "https://example.com/spdx/app-1.0"}"#;
+        assert_eq!(
+            semantic_identity("json", spdx),
+            Some("https://example.com/spdx/app-1.0".to_string())
+        );
+        assert_eq!(semantic_identity("json", r#"{"bomFormat": "other"}"#), None);
+        assert_eq!(semantic_identity("txt", bom), None);
+
+        let message =
+            "From: a@example.com\r\nMessage-Id: <1234@example.com>\r\n\r\nMessage-ID: <body>";
+        assert_eq!(
+            semantic_identity("eml", message),
+            Some("1234@example.com".to_string())
+        );
+
+        register_semantic_identity("csv", |text| text.lines().next().map(str::to_string));
+        assert_eq!(
+            semantic_identity("csv", "id,name\n1,a"),
+            Some("id,name".to_string())
+        );
+    }
+}
@@ -0,0 +1,76 @@
+use anyhow::anyhow;
+use jaq_interpret::{Ctx, FilterT, ParseCtx, RcIter, Val};
+use serde_json::Value as JsonValue;
+
+/// Run a jq-style `filter` (using the embedded jaq interpreter along with its
+/// standard library) against `input`. A filter which yields exactly one value
+/// returns that value, otherwise all yielded values are collected into an array.
+pub fn jq_filter(filter: &str, input: JsonValue) -> anyhow::Result<JsonValue> {
+    let mut defs = ParseCtx::new(Vec::new());
+    defs.insert_natives(jaq_core::core());
+    defs.insert_defs(jaq_std::std());
+
+    let (parsed, errs) = jaq_parse::parse(filter, jaq_parse::main());
+    if !errs.is_empty() {
+        let errs: Vec<String> = errs.iter().map(|e| e.to_string()).collect();
+        return Err(anyhow!(
+            "[jq_filter] unable to parse filter '{}': {}",
+            filter,
+            errs.join("; ")
+        ));
+    }
+    let parsed = parsed.ok_or_else(|| anyhow!("[jq_filter] empty filter '{}'", filter))?;
+
+    let compiled = defs.compile(parsed);
+    if !defs.errs.is_empty() {
+        let errs: Vec<String> = defs.errs.iter().map(|(e, _)| e.to_string()).collect();
+        return Err(anyhow!(
+            "[jq_filter] unable to compile filter '{}': {}",
+            filter,
+            errs.join("; ")
+        ));
+    }
+
+    let inputs = RcIter::new(core::iter::empty());
+    let mut outputs = compiled
+        .run((Ctx::new([], &inputs), Val::from(input)))
+        .map(|result| {
+            result
+                .map(JsonValue::from)
+                .map_err(|err| anyhow!("[jq_filter] error running filter '{}': {}", filter, err))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    if outputs.len() == 1 {
+        Ok(outputs.remove(0))
+    } else {
+        Ok(JsonValue::Array(outputs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_single_output() {
+        let input = json!({ "name": "surveilr", "noise": [1, 2, 3] });
+        let result = jq_filter("{ name }", input).unwrap();
+        assert_eq!(result, json!({ "name": "surveilr" }));
+    }
+
+    #[test]
+    fn test_multiple_outputs_become_array() {
+        let input = json!([{ "id": 1, "ok": true }, { "id": 2, "ok": false }]);
+        let result = jq_filter(".[] | select(.ok) | .id, .id", input).unwrap();
+        assert_eq!(result, json!([1, 1]));
+    }
+
+    #[test]
+    fn test_invalid_filter() {
+        assert!(jq_filter(".[", json!({})).is_err());
+    }
+}
@@ -12,25 +12,51 @@ use chrono::{DateTime, Utc};
 use is_executable::IsExecutable;
 use regex::Captures;
 use regex::Regex;
+#[cfg(feature = "imap")]
+use resource_imap::EmailResource;
 use rusqlite::{Connection, Result as RusqliteResult};
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 use sha1::{Digest, Sha1};
 use tracing::error;
-use resource_imap::EmailResource;
 
+use crate::digest::DigestAlgorithm;
 use crate::frontmatter::frontmatter;
 use crate::shell::*;
 use common::query_sql_rows_no_args;
 
+pub mod access;
+pub mod archive;
+pub mod digest;
 pub mod frontmatter;
+pub mod git;
+pub mod identity;
+pub mod jq;
+pub mod oci;
+pub mod ooxml;
+pub mod payload;
+pub mod pdf;
+pub mod priority;
+pub mod remote;
 pub mod shell;
+pub mod tar;
+pub mod walk;
 
 // See src/resources.states.puml for PlantUML specification of the state machine
 
+/// Files larger than this aren't read into memory, their content is streamed
+/// through `BinaryContent::content_reader` instead.
+pub const LARGE_CONTENT_SIZE: u64 = 1024 * 1024 * 1024;
+
 pub trait BinaryContent {
     fn content_digest_hash(&self) -> &str;
     fn content_binary(&self) -> &Vec<u8>;
+
+    /// The content of resources too large to hold in memory, `content_binary`
+    /// is empty when it's supplied this way.
+    fn content_reader(&self) -> Option<std::io::Result<Box<dyn Read>>> {
+        None
+    }
 }
 
 pub type FrontmatterComponents = (
@@ -120,12 +146,25 @@ impl ResourcePathRewriteRule {
 }
 
 // "?P<nature>" in the `nature` field means read it from the Regex via group capture
-const PFRE_READ_NATURE_FROM_REGEX: &str = "?P<nature>";
+pub const PFRE_READ_NATURE_FROM_REGEX: &str = "?P<nature>";
 const PFRE_READ_NATURE_FROM_REGEX_CAPTURE: &str = "nature";
 
 const DEFAULT_IGNORE_PATHS_REGEX_PATTERNS: [&str; 1] = [r"/(\.git|node_modules)/"];
-const DEFAULT_ACQUIRE_CONTENT_EXTNS_REGEX_PATTERNS: [&str; 1] =
-    [r"\.(?P<nature>md|mdx|html|json|jsonc|puml|txt|toml|yml|xml|tap)$"];
+const DEFAULT_ACQUIRE_CONTENT_EXTNS_REGEX_PATTERNS: [&str; 2] = [
+    r"\.(?P<nature>md|mdx|html|json|jsonc|puml|txt|toml|yml|xml|tap)$",
+    r"\.(?P<nature>docx|xlsx|pptx)$",
+];
+// (regex, nature) of files whose content is acquired as a fixed nature, e.g. those
+// without a telling extension
+const DEFAULT_ACQUIRE_CONTENT_NATURE_REGEX_PATTERNS: [(&str, &str); 4] = [
+    (
+        r"/systemd/(.+/)?[^/]+\.(service|socket|timer|path|mount|automount)$",
+        "systemd-unit",
+    ),
+    (r"(/crontab|/cron\.d/[^/]+|/var/spool/cron/.+)$", "crontab"),
+    (r"(/init\.d/[^/]+|/etc/rc\.local)$", "init-script"),
+    (r"\.pdf$", "pdf"),
+];
 const DEFAULT_CAPTURE_EXEC_REGEX_PATTERNS: [&str; 1] = [r"surveilr\[(?P<nature>[^\]]*)\]"];
 const DEFAULT_CAPTURE_SQL_EXEC_REGEX_PATTERNS: [&str; 1] = [r"surveilr-SQL"];
 
@@ -194,6 +233,12 @@ impl Default for EncounterableResourcePathRules {
                 flags: "CONTENT_ACQUIRABLE".to_string(),
                 nature: Some(PFRE_READ_NATURE_FROM_REGEX.to_string()),
             });
+        let content_acquirable_natures =
+            DEFAULT_ACQUIRE_CONTENT_NATURE_REGEX_PATTERNS.map(|p| PersistableFlaggableRegEx {
+                regex: p.0.to_string(),
+                flags: "CONTENT_ACQUIRABLE".to_string(),
+                nature: Some(p.1.to_string()),
+            });
         let capturable_executables =
             DEFAULT_CAPTURE_EXEC_REGEX_PATTERNS.map(|p| PersistableFlaggableRegEx {
                 regex: p.to_string(),
@@ -211,6 +256,7 @@ impl Default for EncounterableResourcePathRules {
         let flaggables_iter = ignore
             .into_iter()
             .chain(content_acquirable)
+            .chain(content_acquirable_natures)
             .chain(capturable_executables)
             .chain(capturable_executables_sql);
 
@@ -377,7 +423,7 @@ impl EncounterableResourceUriClassifier for EncounterableResourcePathClassifier
                             return true;
                         }
                     }
-                } else {
+                } else if f.regex.is_match(text) {
                     // Since nature is NOT "?P<nature>", we take the nature value literally
                     class.flags.insert(f.flags);
                     class.nature = Some(potential_nature.clone());
@@ -421,6 +467,45 @@ pub struct ImageResource<Resource> {
     pub resource: Resource,
 }
 
+pub struct OfficeResource<Resource> {
+    pub resource: Resource,
+    pub format: ooxml::OfficeFormat,
+}
+
+impl OfficeResource<ContentResource> {
+    /// The document's text (paragraphs, sheet cells or slides) and properties.
+    pub fn extract(&self) -> Result<ooxml::OfficeDocument, anyhow::Error> {
+        if let Some(binary_supplier) = &self.resource.content_binary_supplier {
+            let binary = binary_supplier().map_err(|err| anyhow!("{}", err.to_string()))?;
+            ooxml::office_document(self.format, binary.content_binary())
+        } else {
+            Err(anyhow!(
+                "Content supplier absent for: {}",
+                self.resource.uri
+            ))
+        }
+    }
+}
+
+pub struct PdfResource<Resource> {
+    pub resource: Resource,
+}
+
+impl PdfResource<ContentResource> {
+    /// The PDF's page count, document information and text.
+    pub fn extract(&self) -> Result<pdf::PdfDocument, anyhow::Error> {
+        if let Some(binary_supplier) = &self.resource.content_binary_supplier {
+            let binary = binary_supplier().map_err(|err| anyhow!("{}", err.to_string()))?;
+            pdf::pdf_document(binary.content_binary())
+        } else {
+            Err(anyhow!(
+                "Content supplier absent for: {}",
+                self.resource.uri
+            ))
+        }
+    }
+}
+
 pub enum JsonFormat {
     Json,
     JsonWithComments,
@@ -471,22 +556,26 @@ pub struct XmlResource<Resource> {
     pub schema: XmlSchema,
 }
 
+/// Convert XML source text to pretty-printed JSON, returning the JSON and its hash.
+pub fn xml_to_json(src: &str) -> Result<(String, String), anyhow::Error> {
+    let value: serde_json::Value =
+        xmltojson::to_json(src).map_err(|_| anyhow!("Failed to convert JSON to XML"))?;
+    let json = serde_json::to_string_pretty(&value)?;
+
+    let hash = {
+        let mut hasher = Sha1::new();
+        hasher.update(&json);
+        format!("{:x}", hasher.finalize())
+    };
+
+    Ok((json, hash))
+}
+
 impl XmlResource<ContentResource> {
     pub fn transform_to_json(&self) -> Result<(String, String), anyhow::Error> {
         if let Some(text_supplier) = &self.resource.content_text_supplier {
             let text = text_supplier().map_err(|err| anyhow!("{}", err.to_string()))?;
-            let src = text.content_text();
-            let value: serde_json::Value =
-                xmltojson::to_json(src).map_err(|_| anyhow!("Failed to convert JSON to XML"))?;
-            let json = serde_json::to_string_pretty(&value)?;
-
-            let hash = {
-                let mut hasher = Sha1::new();
-                hasher.update(&json);
-                format!("{:x}", hasher.finalize())
-            };
-
-            Ok((json, hash))
+            xml_to_json(text.content_text())
         } else {
             Err(anyhow!(
                 "Content supplier absent for: {}",
@@ -500,6 +589,7 @@ pub struct ImapResource<Resource> {
     pub resource: Resource,
 }
 
+#[cfg(feature = "imap")]
 impl ImapResource<EmailResource> {
     pub fn uri() -> String {
         "".to_string()
@@ -513,6 +603,8 @@ pub enum UniformResource<Resource> {
     Json(JsonResource<Resource>),
     JsonableText(JsonableTextResource<Resource>),
     Markdown(MarkdownResource<Resource>),
+    Office(OfficeResource<Resource>),
+    Pdf(PdfResource<Resource>),
     PlainText(PlainTextResource<Resource>),
     SourceCode(SourceCodeResource<Resource>),
     Xml(XmlResource<Resource>),
@@ -541,6 +633,8 @@ impl UriNatureSupplier<ContentResource> for UniformResource<ContentResource> {
             UniformResource::Json(json) => &json.resource.uri,
             UniformResource::JsonableText(json) => &json.resource.uri,
             UniformResource::Markdown(md) => &md.resource.uri,
+            UniformResource::Office(office) => &office.resource.uri,
+            UniformResource::Pdf(pdf) => &pdf.resource.uri,
             UniformResource::PlainText(txt) => &txt.resource.uri,
             UniformResource::SourceCode(sc) => &sc.resource.uri,
             UniformResource::Xml(xml) => &xml.resource.uri,
@@ -557,6 +651,8 @@ impl UriNatureSupplier<ContentResource> for UniformResource<ContentResource> {
             UniformResource::Json(json) => &json.resource.nature,
             UniformResource::JsonableText(jsonable) => &jsonable.resource.nature,
             UniformResource::Markdown(md) => &md.resource.nature,
+            UniformResource::Office(office) => &office.resource.nature,
+            UniformResource::Pdf(pdf) => &pdf.resource.nature,
             UniformResource::PlainText(txt) => &txt.resource.nature,
             UniformResource::SourceCode(sc) => &sc.resource.nature,
             UniformResource::Xml(xml) => &xml.resource.nature,
@@ -566,6 +662,47 @@ impl UriNatureSupplier<ContentResource> for UniformResource<ContentResource> {
     }
 }
 
+impl<Resource> UniformResource<Resource> {
+    /// The resource itself, e.g. to check its size and modification time before
+    /// its content is acquired.
+    pub fn resource(&self) -> &Resource {
+        match self {
+            UniformResource::CapturableExec(cer) => &cer.resource,
+            UniformResource::Html(html) => &html.resource,
+            UniformResource::Image(img) => &img.resource,
+            UniformResource::Json(json) => &json.resource,
+            UniformResource::JsonableText(jsonable) => &jsonable.resource,
+            UniformResource::Markdown(md) => &md.resource,
+            UniformResource::Office(office) => &office.resource,
+            UniformResource::Pdf(pdf) => &pdf.resource,
+            UniformResource::PlainText(txt) => &txt.resource,
+            UniformResource::SourceCode(sc) => &sc.resource,
+            UniformResource::Xml(xml) => &xml.resource,
+            UniformResource::ImapResource(email) => &email.resource,
+            UniformResource::Unknown(cr, _alternate) => cr,
+        }
+    }
+
+    /// The resource itself, e.g. to supply content acquired ahead of time.
+    pub fn resource_mut(&mut self) -> &mut Resource {
+        match self {
+            UniformResource::CapturableExec(cer) => &mut cer.resource,
+            UniformResource::Html(html) => &mut html.resource,
+            UniformResource::Image(img) => &mut img.resource,
+            UniformResource::Json(json) => &mut json.resource,
+            UniformResource::JsonableText(jsonable) => &mut jsonable.resource,
+            UniformResource::Markdown(md) => &mut md.resource,
+            UniformResource::Office(office) => &mut office.resource,
+            UniformResource::Pdf(pdf) => &mut pdf.resource,
+            UniformResource::PlainText(txt) => &mut txt.resource,
+            UniformResource::SourceCode(sc) => &mut sc.resource,
+            UniformResource::Xml(xml) => &mut xml.resource,
+            UniformResource::ImapResource(email) => &mut email.resource,
+            UniformResource::Unknown(cr, _alternate) => cr,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ResourceBinaryContent {
     pub hash: String,
@@ -582,6 +719,56 @@ impl BinaryContent for ResourceBinaryContent {
     }
 }
 
+/// A file larger than `LARGE_CONTENT_SIZE`, hashed as it's streamed and read
+/// again from `path` when it's stored.
+#[derive(Debug, Clone)]
+pub struct LargeFileBinaryContent {
+    pub hash: String,
+    pub path: PathBuf,
+    empty: Vec<u8>,
+}
+
+impl LargeFileBinaryContent {
+    pub fn from_fs_path(
+        path: &Path,
+        digest: DigestAlgorithm,
+    ) -> std::io::Result<LargeFileBinaryContent> {
+        let mut hasher = digest.hasher();
+        std::io::copy(&mut fs::File::open(path)?, &mut hasher)?;
+        Ok(LargeFileBinaryContent {
+            hash: hasher.finalize_hex(),
+            path: path.to_path_buf(),
+            empty: Vec::new(),
+        })
+    }
+
+    /// A file whose digest was already computed while it was streamed.
+    pub fn prehashed(path: &Path, hash: String) -> LargeFileBinaryContent {
+        LargeFileBinaryContent {
+            hash,
+            path: path.to_path_buf(),
+            empty: Vec::new(),
+        }
+    }
+}
+
+impl BinaryContent for LargeFileBinaryContent {
+    fn content_digest_hash(&self) -> &str {
+        &self.hash
+    }
+
+    fn content_binary(&self) -> &Vec<u8> {
+        &self.empty
+    }
+
+    fn content_reader(&self) -> Option<std::io::Result<Box<dyn Read>>> {
+        Some(
+            fs::File::open(&self.path)
+                .map(|file| Box::new(std::io::BufReader::new(file)) as Box<dyn Read>),
+        )
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ResourceTextContent {
     pub hash: String,
@@ -697,6 +884,7 @@ impl EncounteredResourceContentSuppliers {
     pub fn from_fs_path(
         fs_path: &Path,
         erc: &EncounterableResourceClass,
+        digest: DigestAlgorithm,
     ) -> EncounteredResourceContentSuppliers {
         let binary: Option<BinaryContentSupplier>;
         let text: Option<TextContentSupplier>;
@@ -707,15 +895,16 @@ impl EncounteredResourceContentSuppliers {
             let path_cbs = fs_path.to_string_lossy().to_string(); // Clone for the first closure
             binary = Some(Box::new(
                 move || -> Result<Box<dyn BinaryContent>, Box<dyn Error>> {
-                    let mut binary = Vec::new();
                     let mut file = fs::File::open(&path_cbs)?;
+                    if file.metadata()?.len() > LARGE_CONTENT_SIZE {
+                        let large =
+                            LargeFileBinaryContent::from_fs_path(Path::new(&path_cbs), digest)?;
+                        return Ok(Box::new(large) as Box<dyn BinaryContent>);
+                    }
+                    let mut binary = Vec::new();
                     file.read_to_end(&mut binary)?;
 
-                    let hash = {
-                        let mut hasher = Sha1::new();
-                        hasher.update(&binary);
-                        format!("{:x}", hasher.finalize())
-                    };
+                    let hash = digest.hex_digest(&binary);
 
                     Ok(Box::new(ResourceBinaryContent { hash, binary }) as Box<dyn BinaryContent>)
                 },
@@ -728,11 +917,7 @@ impl EncounteredResourceContentSuppliers {
                     let mut file = fs::File::open(&path_cts)?;
                     file.read_to_string(&mut text)?;
 
-                    let hash = {
-                        let mut hasher = Sha1::new();
-                        hasher.update(&text);
-                        format!("{:x}", hasher.finalize())
-                    };
+                    let hash = digest.hex_digest(&text);
 
                     Ok(Box::new(ResourceTextContent { hash, text }) as Box<dyn TextContent>)
                 },
@@ -748,6 +933,7 @@ impl EncounteredResourceContentSuppliers {
     pub fn from_vfs_path(
         vfs_path: &vfs::VfsPath,
         erc: &EncounterableResourceClass,
+        digest: DigestAlgorithm,
     ) -> EncounteredResourceContentSuppliers {
         let binary: Option<BinaryContentSupplier>;
         let text: Option<TextContentSupplier>;
@@ -763,11 +949,7 @@ impl EncounteredResourceContentSuppliers {
                     let mut file = path_clone_cbs.open_file()?;
                     file.read_to_end(&mut binary)?;
 
-                    let hash = {
-                        let mut hasher = Sha1::new();
-                        hasher.update(&binary);
-                        format!("{:x}", hasher.finalize())
-                    };
+                    let hash = digest.hex_digest(&binary);
 
                     Ok(Box::new(ResourceBinaryContent { hash, binary }) as Box<dyn BinaryContent>)
                 },
@@ -780,11 +962,7 @@ impl EncounteredResourceContentSuppliers {
                     let mut file = path_clone_cts.open_file()?;
                     file.read_to_string(&mut text)?;
 
-                    let hash = {
-                        let mut hasher = Sha1::new();
-                        hasher.update(&text);
-                        format!("{:x}", hasher.finalize())
-                    };
+                    let hash = digest.hex_digest(&text);
 
                     Ok(Box::new(ResourceTextContent { hash, text }) as Box<dyn TextContent>)
                 },
@@ -902,16 +1080,17 @@ impl EncounterableResource {
     pub fn content_suppliers(
         &self,
         options: &EncounterableResourceClass,
+        digest: DigestAlgorithm,
     ) -> EncounteredResourceContentSuppliers {
         match self {
             EncounterableResource::WalkDir(de) => {
-                EncounteredResourceContentSuppliers::from_fs_path(de.path(), options)
+                EncounteredResourceContentSuppliers::from_fs_path(de.path(), options, digest)
             }
             EncounterableResource::SmartIgnore(de) => {
-                EncounteredResourceContentSuppliers::from_fs_path(de.path(), options)
+                EncounteredResourceContentSuppliers::from_fs_path(de.path(), options, digest)
             }
             EncounterableResource::Vfs(path) => {
-                EncounteredResourceContentSuppliers::from_vfs_path(path, options)
+                EncounteredResourceContentSuppliers::from_vfs_path(path, options, digest)
             }
             EncounterableResource::DenoTaskShellLine(_, _, _) => {
                 EncounteredResourceContentSuppliers {
@@ -925,6 +1104,7 @@ impl EncounterableResource {
     pub fn encountered(
         &self,
         erc: &EncounterableResourceClass,
+        digest: DigestAlgorithm,
     ) -> EncounteredResource<ContentResource> {
         let uri = self.uri();
 
@@ -950,7 +1130,7 @@ impl EncounterableResource {
             Err(_) => return EncounteredResource::NotFound(uri, erc.to_owned()),
         };
 
-        let content_suppliers = self.content_suppliers(erc);
+        let content_suppliers = self.content_suppliers(erc, digest);
         let nature: String;
         match &erc.nature {
             Some(classification_nature) => nature = classification_nature.to_owned(),
@@ -1228,6 +1408,8 @@ pub struct ResourcesCollection {
     pub encounterable: Vec<EncounterableResource>,
     pub classifier: EncounterableResourcePathClassifier,
     pub nature_aliases: Option<HashMap<String, String>>,
+    pub sudo_read: Option<access::SudoRead>,
+    pub digest: DigestAlgorithm,
 }
 
 impl ResourcesCollection {
@@ -1240,9 +1422,33 @@ impl ResourcesCollection {
             encounterable,
             classifier: classifier.clone(),
             nature_aliases: nature_aliases.clone(),
+            sudo_read: None,
+            digest: DigestAlgorithm::default(),
         }
     }
 
+    // read the designated files surveilr's own user isn't allowed to read through sudo
+    pub fn with_sudo_read(mut self, sudo_read: Option<access::SudoRead>) -> ResourcesCollection {
+        self.sudo_read = sudo_read;
+        self
+    }
+
+    // classify the walked resources with `classifier`, e.g. once the walk found the
+    // rules of the directories it walked
+    pub fn with_classifier(
+        mut self,
+        classifier: EncounterableResourcePathClassifier,
+    ) -> ResourcesCollection {
+        self.classifier = classifier;
+        self
+    }
+
+    // hash the content of the resources with `digest` instead of SHA-1
+    pub fn with_digest(mut self, digest: DigestAlgorithm) -> ResourcesCollection {
+        self.digest = digest;
+        self
+    }
+
     // create a physical file system mapped via VFS, mainly for testing and experimental use
     pub fn from_vfs_physical_fs(
         fs_root_paths: &[String],
@@ -1285,19 +1491,47 @@ impl ResourcesCollection {
         nature_aliases: Option<HashMap<String, String>>,
         ignore_hidden: bool,
     ) -> ResourcesCollection {
-        let vfs_iter = fs_root_paths.iter().flat_map(move |root_path| {
-            let mut walk_builder = ignore::WalkBuilder::new(root_path);
-            walk_builder.hidden(ignore_hidden);
-            for cf in &classifier.smart_ignore_conf_files {
-                walk_builder.add_custom_ignore_filename(cf);
-            }
-            walk_builder.build().flatten()
-        });
-
-        ResourcesCollection::new(
-            vfs_iter.map(EncounterableResource::SmartIgnore).collect(),
+        Self::from_smart_ignore_walk(
+            fs_root_paths,
             classifier,
-            nature_aliases.clone(),
+            nature_aliases,
+            ignore_hidden,
+            &walk::WalkOptions::default(),
+        )
+        .0
+    }
+
+    // same as `from_smart_ignore` but walks in `walk_options.order` and bounds the
+    // entries of each directory; also reports what the walk skipped or couldn't read
+    pub fn from_smart_ignore_walk(
+        fs_root_paths: &[String],
+        classifier: &EncounterableResourcePathClassifier,
+        nature_aliases: Option<HashMap<String, String>>,
+        ignore_hidden: bool,
+        walk_options: &walk::WalkOptions,
+    ) -> (ResourcesCollection, walk::WalkReport) {
+        let mut encounterable = Vec::new();
+        let mut report = walk::WalkReport::default();
+        for root_path in fs_root_paths {
+            let walked = walk::smart_ignore_walk(
+                root_path,
+                ignore_hidden,
+                &classifier.smart_ignore_conf_files,
+                walk_options,
+            );
+            encounterable.extend(
+                walked
+                    .entries
+                    .into_iter()
+                    .map(EncounterableResource::SmartIgnore),
+            );
+            report.truncated_dirs.extend(walked.report.truncated_dirs);
+            report.unreadable.extend(walked.report.unreadable);
+        }
+
+        (
+            ResourcesCollection::new(encounterable, classifier, nature_aliases),
+            report,
         )
     }
 
@@ -1361,15 +1595,35 @@ impl ResourcesCollection {
     }
 
     pub fn encountered(&self) -> impl Iterator<Item = EncounteredResource<ContentResource>> + '_ {
-        self.encounterable.iter().map(move |er| {
-            let uri = er.uri();
-            let mut ero = EncounterableResourceClass {
-                nature: None,
-                flags: EncounterableResourceFlags::empty(),
-            };
-            self.classifier.classify(&uri, &mut ero);
-            er.encountered(&ero)
-        })
+        self.encounterable
+            .iter()
+            .map(move |er| self.encountered_with(er, &self.classifier))
+    }
+
+    fn encountered_with(
+        &self,
+        er: &EncounterableResource,
+        classifier: &EncounterableResourcePathClassifier,
+    ) -> EncounteredResource<ContentResource> {
+        let uri = er.uri();
+        let mut ero = EncounterableResourceClass {
+            nature: None,
+            flags: EncounterableResourceFlags::empty(),
+        };
+        classifier.classify(&uri, &mut ero);
+        let mut encountered = er.encountered(&ero, self.digest);
+        if let (Some(sudo_read), EncounteredResource::Resource(cr, _)) =
+            (&self.sudo_read, &mut encountered)
+        {
+            if cr.flags.contains(ContentResourceFlags::CONTENT_ACQUIRABLE)
+                && sudo_read.applies(&cr.uri)
+            {
+                let suppliers = sudo_read.content_suppliers(&cr.uri, self.digest);
+                cr.content_binary_supplier = suppliers.binary;
+                cr.content_text_supplier = suppliers.text;
+            }
+        }
+        encountered
     }
 
     pub fn uniform_resources(
@@ -1377,23 +1631,39 @@ impl ResourcesCollection {
     ) -> impl Iterator<Item = anyhow::Result<UniformResource<ContentResource>, Box<dyn Error>>> + '_
     {
         self.encountered()
-            .filter_map(move |er: EncounteredResource<ContentResource>| match er {
-                EncounteredResource::Resource(resource, _) => {
-                    match self.uniform_resource(resource) {
-                        Ok(uniform_resource) => Some(Ok(*uniform_resource)),
-                        Err(e) => Some(Err(e)), // error will be returned
-                    }
-                }
-                EncounteredResource::CapturableExec(resource, executable, _) => Some(Ok(
-                    UniformResource::CapturableExec(CapturableExecResource {
-                        resource,
-                        executable,
-                    }),
-                )),
-                EncounteredResource::Ignored(_, _)
-                | EncounteredResource::NotFile(_, _)
-                | EncounteredResource::NotFound(_, _) => None, // these will be filtered via `filter_map`
-            })
+            .filter_map(move |er| self.encountered_uniform_resource(er))
+    }
+
+    // the uniform resource of `er` classified with `classifier` rather than the
+    // collection's own, e.g. as a walk streams its entries and finds the rules of
+    // the directories it enters; `None` when it's ignored or not a file
+    pub fn classified_uniform_resource(
+        &self,
+        er: &EncounterableResource,
+        classifier: &EncounterableResourcePathClassifier,
+    ) -> Option<anyhow::Result<UniformResource<ContentResource>, Box<dyn Error>>> {
+        self.encountered_uniform_resource(self.encountered_with(er, classifier))
+    }
+
+    fn encountered_uniform_resource(
+        &self,
+        er: EncounteredResource<ContentResource>,
+    ) -> Option<anyhow::Result<UniformResource<ContentResource>, Box<dyn Error>>> {
+        match er {
+            EncounteredResource::Resource(resource, _) => match self.uniform_resource(resource) {
+                Ok(uniform_resource) => Some(Ok(*uniform_resource)),
+                Err(e) => Some(Err(e)), // error will be returned
+            },
+            EncounteredResource::CapturableExec(resource, executable, _) => Some(Ok(
+                UniformResource::CapturableExec(CapturableExecResource {
+                    resource,
+                    executable,
+                }),
+            )),
+            EncounteredResource::Ignored(_, _)
+            | EncounteredResource::NotFile(_, _)
+            | EncounteredResource::NotFound(_, _) => None, // these will be filtered via `filter_map`
+        }
     }
 
     pub fn uniform_resource(
@@ -1468,7 +1738,8 @@ impl ResourcesCollection {
                     let markdown = MarkdownResource { resource: cr };
                     Ok(Box::new(UniformResource::Markdown(markdown)))
                 }
-                "txt" | "text/plain" => {
+                // the persistence natures are plain text which ingestion extracts further
+                "txt" | "text/plain" | "systemd-unit" | "crontab" | "init-script" => {
                     let plain_text = PlainTextResource { resource: cr };
                     Ok(Box::new(UniformResource::PlainText(plain_text)))
                 }
@@ -1477,6 +1748,22 @@ impl ResourcesCollection {
                     let image = ImageResource { resource: cr };
                     Ok(Box::new(UniformResource::Image(image)))
                 }
+                "docx"
+                | "xlsx"
+                | "pptx"
+                | "application/vnd.openxmlformats-officedocument.wordprocessingml.document"
+                | "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
+                | "application/vnd.openxmlformats-officedocument.presentationml.presentation" => {
+                    let office = OfficeResource {
+                        format: ooxml::OfficeFormat::from_nature(candidate_nature).unwrap(),
+                        resource: cr,
+                    };
+                    Ok(Box::new(UniformResource::Office(office)))
+                }
+                "pdf" | "application/pdf" => {
+                    let pdf = PdfResource { resource: cr };
+                    Ok(Box::new(UniformResource::Pdf(pdf)))
+                }
                 "svg" | "image/svg+xml" | "xml" | "text/xml" | "application/xml" => {
                     let schema = match candidate_nature {
                         "svg" | "image/svg+xml" => XmlSchema::Svg,
@@ -0,0 +1,1040 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context};
+use common::secret::Secret;
+use reqwest::header::{ACCEPT, AUTHORIZATION, WWW_AUTHENTICATE};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tempfile::TempDir;
+
+use crate::tar::{normalized_path, read_tar, TarEntryKind};
+
+pub const OCI_INDEX_MEDIA_TYPE: &str = "application/vnd.oci.image.index.v1+json";
+pub const OCI_MANIFEST_MEDIA_TYPE: &str = "application/vnd.oci.image.manifest.v1+json";
+pub const DOCKER_MANIFEST_LIST_MEDIA_TYPE: &str =
+    "application/vnd.docker.distribution.manifest.list.v2+json";
+pub const DOCKER_MANIFEST_MEDIA_TYPE: &str = "application/vnd.docker.distribution.manifest.v2+json";
+/// layers of `docker save` archives are uncompressed tarballs
+const DOCKER_LAYER_MEDIA_TYPE: &str = "application/vnd.docker.image.rootfs.diff.tar";
+const DOCKER_HUB_REGISTRY: &str = "registry-1.docker.io";
+
+/// Whiteout files delete the file of the same name (without the prefix) from
+/// lower layers, the opaque whiteout all the files of its directory.
+const WHITEOUT_PREFIX: &str = ".wh.";
+const OPAQUE_WHITEOUT: &str = ".wh..wh..opq";
+
+/// The operating system, architecture and variant of an image, e.g. `linux/arm64/v8`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OciPlatform {
+    pub os: String,
+    pub architecture: String,
+    pub variant: Option<String>,
+}
+
+impl FromStr for OciPlatform {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<_> = s.split('/').collect();
+        match parts.as_slice() {
+            [os, architecture] | [os, architecture, _]
+                if !os.is_empty() && !architecture.is_empty() =>
+            {
+                Ok(OciPlatform {
+                    os: os.to_string(),
+                    architecture: architecture.to_string(),
+                    variant: parts.get(2).map(|variant| variant.to_string()),
+                })
+            }
+            _ => Err(anyhow!(
+                "'{s}' should be `<os>/<architecture>[/<variant>]`, e.g. linux/amd64"
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for OciPlatform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.os, self.architecture)?;
+        match &self.variant {
+            Some(variant) => write!(f, "/{variant}"),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Descriptor {
+    #[serde(default)]
+    media_type: Option<String>,
+    digest: String,
+    #[serde(default)]
+    size: u64,
+    #[serde(default)]
+    platform: Option<DescriptorPlatform>,
+    #[serde(default)]
+    annotations: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct DescriptorPlatform {
+    os: String,
+    architecture: String,
+    #[serde(default)]
+    variant: Option<String>,
+}
+
+/// An image index (or manifest list) or an image manifest, told apart by
+/// their content since registries and `docker save` don't always say.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+    #[serde(default)]
+    manifests: Vec<Descriptor>,
+    #[serde(default)]
+    config: Option<Descriptor>,
+    #[serde(default)]
+    layers: Vec<Descriptor>,
+}
+
+impl Manifest {
+    fn is_index(&self) -> bool {
+        self.config.is_none() && !self.manifests.is_empty()
+    }
+
+    /// The manifest of `platform`, or the only one.
+    fn select(&self, platform: &OciPlatform) -> anyhow::Result<&Descriptor> {
+        let matching = self.manifests.iter().find(|m| {
+            m.platform.as_ref().is_some_and(|p| {
+                p.os == platform.os
+                    && p.architecture == platform.architecture
+                    && (platform.variant.is_none() || p.variant == platform.variant)
+            })
+        });
+        match (matching, self.manifests.as_slice()) {
+            (Some(manifest), _) => Ok(manifest),
+            (None, [only]) if only.platform.is_none() => Ok(only),
+            _ => Err(anyhow!(
+                "[oci::select] no manifest for {platform}, the image has {}",
+                self.manifests
+                    .iter()
+                    .filter_map(|m| m.platform.as_ref())
+                    .map(|p| match &p.variant {
+                        Some(variant) => format!("{}/{}/{}", p.os, p.architecture, variant),
+                        None => format!("{}/{}", p.os, p.architecture),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        }
+    }
+}
+
+/// `docker save` archives have a `manifest.json` with one entry per image.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DockerSaveManifest {
+    config: String,
+    #[serde(default)]
+    repo_tags: Option<Vec<String>>,
+    layers: Vec<String>,
+}
+
+/// Where an image came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OciSource {
+    Registry,
+    Tarball,
+}
+
+/// A layer of an image, bottom first.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OciLayer {
+    pub digest: String,
+    pub media_type: String,
+    pub size: u64,
+}
+
+/// A regular file of an image's flattened file system.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OciFile {
+    /// absolute, e.g. `/etc/os-release`
+    pub path: String,
+    /// the index of the (topmost) layer the file comes from
+    pub layer: usize,
+    pub size: u64,
+    pub mode: u32,
+    pub mtime: i64,
+}
+
+/// What was found while walking an image.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct OciWalkSummary {
+    pub files: usize,
+    /// files larger than the maximum, visited without content
+    pub oversized: usize,
+    /// symlinks, devices and other entries without content of their own
+    pub skipped_entries: usize,
+    pub whiteouts: usize,
+}
+
+/// A container image whose config and layers have been downloaded (or
+/// extracted from a tarball) into a temporary directory.
+#[derive(Debug)]
+pub struct OciImage {
+    /// as given, the image reference or the tarball path
+    pub reference: String,
+    pub source: OciSource,
+    /// `RepoTags` or `org.opencontainers.image.ref.name` of tarballs
+    pub name: Option<String>,
+    pub manifest_digest: String,
+    pub config_digest: String,
+    pub config: serde_json::Value,
+    pub layers: Vec<OciLayer>,
+    layer_paths: Vec<PathBuf>,
+    _blobs: TempDir,
+}
+
+/// The flattened file system, see [`OciImage::flatten`].
+type Flattened = BTreeMap<String, (OciFile, String)>;
+
+fn sha256_digest(content: &[u8]) -> String {
+    format!("sha256:{:x}", Sha256::digest(content))
+}
+
+fn sha256_file_digest(path: &Path) -> anyhow::Result<String> {
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(format!("sha256:{:x}", hasher.finalize()))
+}
+
+/// The hex part of a `sha256:<64 hex digits>` digest; blobs are addressed
+/// (and verified) by these only, anything else could point outside of them.
+fn sha256_digest_hex(digest: &str) -> anyhow::Result<&str> {
+    match digest.strip_prefix("sha256:") {
+        Some(hex)
+            if hex.len() == 64
+                && hex
+                    .bytes()
+                    .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b)) =>
+        {
+            Ok(hex)
+        }
+        _ => Err(anyhow!(
+            "[oci::sha256_digest_hex] unsupported digest '{digest}', expected sha256:<64 hex digits>"
+        )),
+    }
+}
+
+/// Layers are tarballs, gzip or zstd compressed (whatever their media type says).
+fn layer_reader(path: &Path) -> anyhow::Result<Box<dyn Read>> {
+    let mut magic = [0u8; 4];
+    let read = File::open(path)?.read(&mut magic)?;
+    let file = BufReader::new(File::open(path)?);
+    Ok(match &magic[..read] {
+        [0x1f, 0x8b, ..] => Box::new(flate2::read::MultiGzDecoder::new(file)),
+        [0x28, 0xb5, 0x2f, 0xfd] => Box::new(zstd::stream::read::Decoder::new(file)?),
+        _ => Box::new(file),
+    })
+}
+
+impl OciImage {
+    /// Read an image from a `docker save` archive or an OCI image layout
+    /// tarball (e.g. `skopeo copy ... oci-archive:image.tar`).
+    pub fn from_tarball(path: &Path, platform: &OciPlatform) -> anyhow::Result<OciImage> {
+        let reference = path.to_string_lossy().to_string();
+        let blobs = TempDir::new()?;
+        // `docker save -o image.tar` or `docker save | gzip > image.tar.gz`
+        let reader =
+            layer_reader(path).with_context(|| format!("[OciImage::from_tarball] {reference}"))?;
+        read_tar(reader, |entry, content| {
+            let Some(rel) = normalized_path(&entry.path) else {
+                return Ok(());
+            };
+            if entry.kind == TarEntryKind::File {
+                let dest = blobs.path().join(rel);
+                std::fs::create_dir_all(dest.parent().unwrap())?;
+                std::io::copy(content, &mut File::create(dest)?)?;
+            }
+            Ok(())
+        })
+        .with_context(|| format!("[OciImage::from_tarball] {reference}"))?;
+
+        let root = blobs.path().canonicalize()?;
+        let blob_path = |digest: &str| -> anyhow::Result<PathBuf> {
+            let hex = sha256_digest_hex(digest)
+                .with_context(|| format!("[OciImage::from_tarball] {}", path.display()))?;
+            let blob = root
+                .join("blobs")
+                .join("sha256")
+                .join(hex)
+                .canonicalize()
+                .with_context(|| format!("[OciImage::from_tarball] blob {digest}"))?;
+            if !blob.starts_with(&root) {
+                return Err(anyhow!(
+                    "[OciImage::from_tarball] blob {digest} is outside of {}",
+                    path.display()
+                ));
+            }
+            let actual = sha256_file_digest(&blob)?;
+            if actual != digest {
+                return Err(anyhow!(
+                    "[OciImage::from_tarball] blob {digest} has digest {actual}"
+                ));
+            }
+            Ok(blob)
+        };
+
+        if root.join("index.json").is_file() {
+            let mut manifest: Manifest = serde_json::from_slice(&std::fs::read(
+                root.join("index.json"),
+            )?)
+            .with_context(|| format!("[OciImage::from_tarball] index.json of {reference}"))?;
+            let mut name = None;
+            let mut manifest_digest = String::new();
+            while manifest.is_index() {
+                let descriptor = manifest.select(platform)?.clone();
+                name = name.or_else(|| {
+                    descriptor
+                        .annotations
+                        .get("org.opencontainers.image.ref.name")
+                        .or_else(|| descriptor.annotations.get("io.containerd.image.name"))
+                        .cloned()
+                });
+                manifest = serde_json::from_slice(&std::fs::read(blob_path(&descriptor.digest)?)?)
+                    .with_context(|| {
+                        format!("[OciImage::from_tarball] manifest {}", descriptor.digest)
+                    })?;
+                manifest_digest = descriptor.digest;
+            }
+            let config = manifest.config.as_ref().ok_or_else(|| {
+                anyhow!("[OciImage::from_tarball] {reference} has no image manifest")
+            })?;
+            let layer_paths = manifest
+                .layers
+                .iter()
+                .map(|layer| blob_path(&layer.digest))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            return Ok(OciImage {
+                reference,
+                source: OciSource::Tarball,
+                name,
+                manifest_digest,
+                config_digest: config.digest.clone(),
+                config: serde_json::from_slice(&std::fs::read(blob_path(&config.digest)?)?)?,
+                layers: manifest
+                    .layers
+                    .iter()
+                    .map(|layer| OciLayer {
+                        digest: layer.digest.clone(),
+                        media_type: layer.media_type.clone().unwrap_or_default(),
+                        size: layer.size,
+                    })
+                    .collect(),
+                layer_paths,
+                _blobs: blobs,
+            });
+        }
+
+        if root.join("manifest.json").is_file() {
+            let manifest_json = std::fs::read(root.join("manifest.json"))?;
+            let mut images: Vec<DockerSaveManifest> = serde_json::from_slice(&manifest_json)
+                .with_context(|| {
+                    format!("[OciImage::from_tarball] manifest.json of {reference}")
+                })?;
+            if images.is_empty() {
+                return Err(anyhow!(
+                    "[OciImage::from_tarball] {reference} has no images"
+                ));
+            }
+            let image = images.remove(0);
+            let config_path = root.join(normalized_path(&image.config).unwrap_or_default());
+            let mut layers = vec![];
+            let mut layer_paths = vec![];
+            for layer in &image.layers {
+                let layer_path = root.join(normalized_path(layer).unwrap_or_default());
+                layers.push(OciLayer {
+                    digest: sha256_file_digest(&layer_path)
+                        .with_context(|| format!("[OciImage::from_tarball] layer {layer}"))?,
+                    media_type: DOCKER_LAYER_MEDIA_TYPE.to_string(),
+                    size: std::fs::metadata(&layer_path)?.len(),
+                });
+                layer_paths.push(layer_path);
+            }
+            return Ok(OciImage {
+                reference,
+                source: OciSource::Tarball,
+                name: image.repo_tags.and_then(|tags| tags.into_iter().next()),
+                manifest_digest: sha256_digest(&manifest_json),
+                config_digest: sha256_file_digest(&config_path)?,
+                config: serde_json::from_slice(&std::fs::read(&config_path)?)?,
+                layers,
+                layer_paths,
+                _blobs: blobs,
+            });
+        }
+
+        Err(anyhow!(
+            "[OciImage::from_tarball] {reference} is neither an OCI image layout nor a `docker save` archive"
+        ))
+    }
+
+    /// Pull an image (`alpine:3.19`, `ghcr.io/org/app@sha256:...`) from its
+    /// registry, as `platform` when it's a multi-platform image.
+    pub async fn pull(
+        reference: &str,
+        platform: &OciPlatform,
+        credentials: Option<&RegistryCredentials>,
+        plain_http: bool,
+    ) -> anyhow::Result<OciImage> {
+        let image_ref = ImageReference::parse(reference)?;
+        let mut registry = Registry::new(&image_ref, credentials, plain_http);
+        let blobs = TempDir::new()?;
+
+        let (mut manifest_json, mut manifest_digest) = registry
+            .manifest(image_ref.digest.as_deref().unwrap_or(&image_ref.tag))
+            .await?;
+        let mut manifest: Manifest = serde_json::from_slice(&manifest_json)
+            .with_context(|| format!("[OciImage::pull] manifest of {reference}"))?;
+        while manifest.is_index() {
+            let descriptor = manifest.select(platform)?.clone();
+            (manifest_json, manifest_digest) = registry.manifest(&descriptor.digest).await?;
+            manifest = serde_json::from_slice(&manifest_json)
+                .with_context(|| format!("[OciImage::pull] manifest {}", descriptor.digest))?;
+        }
+        let config = manifest
+            .config
+            .clone()
+            .ok_or_else(|| anyhow!("[OciImage::pull] {reference} has no image manifest"))?;
+        let config_path = blobs.path().join("config.json");
+        registry.blob(&config.digest, &config_path).await?;
+
+        let mut layer_paths = vec![];
+        for (index, layer) in manifest.layers.iter().enumerate() {
+            let layer_path = blobs.path().join(format!("layer-{index}"));
+            registry.blob(&layer.digest, &layer_path).await?;
+            layer_paths.push(layer_path);
+        }
+        Ok(OciImage {
+            reference: reference.to_string(),
+            source: OciSource::Registry,
+            name: None,
+            manifest_digest,
+            config_digest: config.digest.clone(),
+            config: serde_json::from_slice(&std::fs::read(&config_path)?)?,
+            layers: manifest
+                .layers
+                .iter()
+                .map(|layer| OciLayer {
+                    digest: layer.digest.clone(),
+                    media_type: layer.media_type.clone().unwrap_or_default(),
+                    size: layer.size,
+                })
+                .collect(),
+            layer_paths,
+            _blobs: blobs,
+        })
+    }
+
+    /// Flatten the layers (applying whiteouts) into the image's file system.
+    pub fn filesystem(&self) -> anyhow::Result<(BTreeMap<String, OciFile>, OciWalkSummary)> {
+        let (files, summary) = self.flatten()?;
+        Ok((
+            files
+                .into_iter()
+                .map(|(path, (file, _))| (path, file))
+                .collect(),
+            summary,
+        ))
+    }
+
+    /// The regular files of the image's file system with the path of the
+    /// entry of their layer whose content they have (hard links can only point
+    /// to an earlier entry of the same layer).
+    fn flatten(&self) -> anyhow::Result<(Flattened, OciWalkSummary)> {
+        let mut files = Flattened::new();
+        let mut summary = OciWalkSummary::default();
+        let remove_tree = |files: &mut Flattened, dir: &str, layer| {
+            let prefix = format!("{dir}/");
+            files.retain(|path, (file, _)| {
+                file.layer >= layer || (path != dir && !path.starts_with(&prefix))
+            });
+        };
+
+        for (index, layer_path) in self.layer_paths.iter().enumerate() {
+            read_tar(layer_reader(layer_path)?, |entry, _| {
+                let Some(path) = normalized_path(&entry.path) else {
+                    return Ok(());
+                };
+                let (dir, name) = match path.rsplit_once('/') {
+                    Some((dir, name)) => (dir, name),
+                    None => ("", path.as_str()),
+                };
+                if name == OPAQUE_WHITEOUT {
+                    summary.whiteouts += 1;
+                    let prefix = format!("{dir}/");
+                    files.retain(|path, (file, _)| {
+                        file.layer >= index || (!dir.is_empty() && !path.starts_with(&prefix))
+                    });
+                    return Ok(());
+                }
+                if let Some(deleted) = name.strip_prefix(WHITEOUT_PREFIX) {
+                    summary.whiteouts += 1;
+                    let deleted = match dir {
+                        "" => deleted.to_string(),
+                        dir => format!("{dir}/{deleted}"),
+                    };
+                    remove_tree(&mut files, &deleted, index);
+                    return Ok(());
+                }
+
+                let (source, size) = match &entry.kind {
+                    TarEntryKind::File => (path.clone(), entry.size),
+                    TarEntryKind::Hardlink(target) => match normalized_path(target)
+                        .and_then(|target| files.get(&target))
+                        .filter(|(file, _)| file.layer == index)
+                    {
+                        Some((file, source)) => (source.clone(), file.size),
+                        None => {
+                            summary.skipped_entries += 1;
+                            return Ok(());
+                        }
+                    },
+                    TarEntryKind::Dir => {
+                        // a directory replaces a file of the same name
+                        files.remove(&path);
+                        return Ok(());
+                    }
+                    TarEntryKind::Symlink(_) | TarEntryKind::Other(_) => {
+                        summary.skipped_entries += 1;
+                        remove_tree(&mut files, &path, index + 1);
+                        return Ok(());
+                    }
+                };
+                // a file replaces a directory of the same name
+                remove_tree(&mut files, &path, index + 1);
+                files.insert(
+                    path.clone(),
+                    (
+                        OciFile {
+                            path: format!("/{path}"),
+                            layer: index,
+                            size,
+                            mode: entry.mode,
+                            mtime: entry.mtime,
+                        },
+                        source,
+                    ),
+                );
+                Ok(())
+            })
+            .with_context(|| format!("[OciImage::flatten] layer {}", self.layers[index].digest))?;
+        }
+        summary.files = files.len();
+        Ok((files, summary))
+    }
+
+    /// Visit the regular files of the flattened file system with their content,
+    /// layer by layer; files larger than `max_file_size` are visited without it.
+    pub fn walk(
+        &self,
+        max_file_size: u64,
+        mut visit: impl FnMut(&OciFile, Option<Vec<u8>>) -> anyhow::Result<()>,
+    ) -> anyhow::Result<OciWalkSummary> {
+        let (files, mut summary) = self.flatten()?;
+        // per layer, the files it provides and the entries hard links read
+        let mut visiting: Vec<HashMap<&str, &OciFile>> = vec![HashMap::new(); self.layers.len()];
+        let mut linked: Vec<HashSet<&str>> = vec![HashSet::new(); self.layers.len()];
+        for (path, (file, source)) in &files {
+            visiting[file.layer].insert(path, file);
+            if source != path {
+                linked[file.layer].insert(source);
+            }
+        }
+
+        for (index, layer_path) in self.layer_paths.iter().enumerate() {
+            if visiting[index].is_empty() {
+                continue;
+            }
+            let mut link_sources: HashMap<String, Vec<u8>> = HashMap::new();
+            read_tar(layer_reader(layer_path)?, |entry, content| {
+                let Some(path) = normalized_path(&entry.path) else {
+                    return Ok(());
+                };
+                let file = visiting[index].get(path.as_str());
+                let data = match &entry.kind {
+                    TarEntryKind::File
+                        if file.is_none() && !linked[index].contains(path.as_str()) =>
+                    {
+                        return Ok(());
+                    }
+                    TarEntryKind::File if entry.size > max_file_size => None,
+                    TarEntryKind::File => {
+                        let mut data = Vec::with_capacity(entry.size as usize);
+                        content.read_to_end(&mut data)?;
+                        if linked[index].contains(path.as_str()) {
+                            link_sources.insert(path.clone(), data.clone());
+                        }
+                        Some(data)
+                    }
+                    TarEntryKind::Hardlink(_) if file.is_some() => {
+                        let (_, source) = &files[&path];
+                        link_sources.get(source).cloned()
+                    }
+                    _ => return Ok(()),
+                };
+                let Some(file) = file else {
+                    return Ok(());
+                };
+                if data.is_none() {
+                    summary.oversized += 1;
+                }
+                visit(file, data)
+            })
+            .with_context(|| format!("[OciImage::walk] layer {}", self.layers[index].digest))?;
+        }
+        Ok(summary)
+    }
+}
+
+/// `[registry/]repository[:tag][@digest]`, Docker Hub by default.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageReference {
+    pub registry: String,
+    pub repository: String,
+    pub tag: String,
+    pub digest: Option<String>,
+}
+
+impl ImageReference {
+    pub fn parse(reference: &str) -> anyhow::Result<ImageReference> {
+        let (name, digest) = match reference.split_once('@') {
+            Some((name, digest)) => (name, Some(digest.to_string())),
+            None => (reference, None),
+        };
+        let (registry, path) = match name.split_once('/') {
+            Some((host, path))
+                if host.contains('.') || host.contains(':') || host == "localhost" =>
+            {
+                (host.to_string(), path.to_string())
+            }
+            _ => (DOCKER_HUB_REGISTRY.to_string(), name.to_string()),
+        };
+        let (repository, tag) = match path.rsplit_once(':') {
+            Some((repository, tag)) if !tag.contains('/') => {
+                (repository.to_string(), tag.to_string())
+            }
+            _ => (path, "latest".to_string()),
+        };
+        if repository.is_empty() {
+            return Err(anyhow!(
+                "[ImageReference::parse] '{reference}' has no repository"
+            ));
+        }
+        let repository = match (registry.as_str(), repository.contains('/')) {
+            (DOCKER_HUB_REGISTRY, false) => format!("library/{repository}"),
+            _ => repository,
+        };
+        Ok(ImageReference {
+            registry: match registry.as_str() {
+                "docker.io" | "index.docker.io" => DOCKER_HUB_REGISTRY.to_string(),
+                _ => registry,
+            },
+            repository,
+            tag,
+            digest,
+        })
+    }
+}
+
+/// Basic credentials, exchanged for a token when the registry asks for one.
+#[derive(Debug, Clone)]
+pub struct RegistryCredentials {
+    pub username: String,
+    pub password: Secret,
+}
+
+/// The distribution API of one repository of a registry.
+struct Registry<'a> {
+    http: reqwest::Client,
+    base_url: String,
+    repository: String,
+    credentials: Option<&'a RegistryCredentials>,
+    authorization: Option<String>,
+}
+
+impl<'a> Registry<'a> {
+    fn new(
+        image_ref: &ImageReference,
+        credentials: Option<&'a RegistryCredentials>,
+        plain_http: bool,
+    ) -> Registry<'a> {
+        Registry {
+            http: reqwest::Client::new(),
+            base_url: format!(
+                "{}://{}/v2/{}",
+                if plain_http { "http" } else { "https" },
+                image_ref.registry,
+                image_ref.repository
+            ),
+            repository: image_ref.repository.clone(),
+            credentials,
+            authorization: None,
+        }
+    }
+
+    /// GET `url`, answering the registry's `WWW-Authenticate` challenge once.
+    async fn get(&mut self, url: &str, accept: &str) -> anyhow::Result<reqwest::Response> {
+        for _ in 0..2 {
+            let mut request = self.http.get(url).header(ACCEPT, accept);
+            if let Some(authorization) = &self.authorization {
+                request = request.header(AUTHORIZATION, authorization);
+            }
+            let response = request
+                .send()
+                .await
+                .with_context(|| format!("[oci::Registry::get] GET {url}"))?;
+            if response.status() != StatusCode::UNAUTHORIZED || self.authorization.is_some() {
+                return response
+                    .error_for_status()
+                    .with_context(|| format!("[oci::Registry::get] GET {url}"));
+            }
+            let challenge = response
+                .headers()
+                .get(WWW_AUTHENTICATE)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default()
+                .to_string();
+            self.authorization = Some(self.authorize(&challenge).await?);
+        }
+        Err(anyhow!("[oci::Registry::get] GET {url} is not authorized"))
+    }
+
+    /// The `Authorization` header answering a `Basic` or `Bearer` challenge.
+    async fn authorize(&self, challenge: &str) -> anyhow::Result<String> {
+        let basic = self.credentials.map(|c| {
+            use base64::Engine;
+            base64::engine::general_purpose::STANDARD.encode(format!(
+                "{}:{}",
+                c.username,
+                c.password.expose()
+            ))
+        });
+        let Some(params) = challenge.strip_prefix("Bearer ") else {
+            return basic.map(|basic| format!("Basic {basic}")).ok_or_else(|| {
+                anyhow!("[oci::Registry::authorize] the registry requires credentials")
+            });
+        };
+        let params: HashMap<_, _> = params
+            .split(',')
+            .filter_map(|param| param.trim().split_once('='))
+            .map(|(key, value)| (key, value.trim_matches('"')))
+            .collect();
+        let realm = params
+            .get("realm")
+            .ok_or_else(|| anyhow!("[oci::Registry::authorize] no realm in '{challenge}'"))?;
+        let scope = format!("repository:{}:pull", self.repository);
+        let mut request = self.http.get(*realm).query(&[
+            (
+                "service",
+                params.get("service").copied().unwrap_or_default(),
+            ),
+            (
+                "scope",
+                params.get("scope").copied().unwrap_or(scope.as_str()),
+            ),
+        ]);
+        if let Some(basic) = basic {
+            request = request.header(AUTHORIZATION, format!("Basic {basic}"));
+        }
+        let token: serde_json::Value = serde_json::from_slice(
+            &request
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .with_context(|| format!("[oci::Registry::authorize] token from {realm}"))?
+                .bytes()
+                .await?,
+        )?;
+        token
+            .get("token")
+            .or_else(|| token.get("access_token"))
+            .and_then(|token| token.as_str())
+            .map(|token| format!("Bearer {token}"))
+            .ok_or_else(|| anyhow!("[oci::Registry::authorize] no token from {realm}"))
+    }
+
+    /// The manifest (or index) of a tag or digest, with its digest.
+    async fn manifest(&mut self, reference: &str) -> anyhow::Result<(Vec<u8>, String)> {
+        let accept = [
+            OCI_INDEX_MEDIA_TYPE,
+            OCI_MANIFEST_MEDIA_TYPE,
+            DOCKER_MANIFEST_LIST_MEDIA_TYPE,
+            DOCKER_MANIFEST_MEDIA_TYPE,
+        ]
+        .join(", ");
+        // tags can't have a colon, anything else is a digest
+        let by_digest = reference.contains(':');
+        if by_digest {
+            sha256_digest_hex(reference)?;
+        }
+        let url = format!("{}/manifests/{}", self.base_url, reference);
+        let content = self.get(&url, &accept).await?.bytes().await?.to_vec();
+        let digest = sha256_digest(&content);
+        if by_digest && reference != digest {
+            return Err(anyhow!(
+                "[oci::Registry::manifest] {url} has digest {digest}"
+            ));
+        }
+        Ok((content, digest))
+    }
+
+    /// Download a blob into `dest`, verifying its digest.
+    async fn blob(&mut self, digest: &str, dest: &Path) -> anyhow::Result<()> {
+        sha256_digest_hex(digest)?;
+        let url = format!("{}/blobs/{}", self.base_url, digest);
+        let mut response = self.get(&url, "*/*").await?;
+        let mut file = File::create(dest)?;
+        let mut hasher = Sha256::new();
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .with_context(|| format!("[oci::Registry::blob] reading {url}"))?
+        {
+            hasher.update(&chunk);
+            file.write_all(&chunk)?;
+        }
+        let actual = format!("sha256:{:x}", hasher.finalize());
+        if actual != digest {
+            return Err(anyhow!("[oci::Registry::blob] {url} has digest {actual}"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tar::tar_bytes;
+    use serde_json::json;
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn layers() -> Vec<Vec<u8>> {
+        vec![
+            tar_bytes(&[
+                ("etc/", b'5', b""),
+                ("etc/os-release", b'0', b"ID=alpine\n"),
+                ("etc/secret", b'0', b"s3cr3t"),
+                ("opt/app/", b'5', b""),
+                ("opt/app/old.conf", b'0', b"old"),
+                ("bin/busybox", b'0', b"ELF"),
+                ("bin/sh", b'2', b"/bin/busybox"),
+            ]),
+            tar_bytes(&[
+                ("./etc/.wh.secret", b'0', b""),
+                ("./opt/app/.wh..wh..opq", b'0', b""),
+                ("./opt/app/new.conf", b'0', b"new"),
+                ("./etc/motd", b'0', b"welcome"),
+                ("./etc/issue", b'1', b"etc/motd"),
+            ]),
+            // overrides lower layers' files, the hard link keeps the old motd
+            tar_bytes(&[
+                ("etc/os-release", b'0', b"ID=alpine\nVERSION_ID=3.19\n"),
+                ("etc/motd", b'0', b"hi"),
+            ]),
+        ]
+    }
+
+    fn assert_walked(image: &OciImage) -> anyhow::Result<()> {
+        let mut walked = vec![];
+        let summary = image.walk(10, |file, content| {
+            walked.push((
+                file.path.clone(),
+                file.layer,
+                content.map(|c| String::from_utf8(c).unwrap()),
+            ));
+            Ok(())
+        })?;
+        walked.sort();
+        assert_eq!(
+            walked,
+            vec![
+                ("/bin/busybox".to_string(), 0, Some("ELF".to_string())),
+                ("/etc/issue".to_string(), 1, Some("welcome".to_string())),
+                ("/etc/motd".to_string(), 2, Some("hi".to_string())),
+                ("/etc/os-release".to_string(), 2, None),
+                ("/opt/app/new.conf".to_string(), 1, Some("new".to_string())),
+            ]
+        );
+        assert_eq!(summary.oversized, 1);
+        assert_eq!((summary.files, summary.whiteouts), (5, 2));
+        assert_eq!(summary.skipped_entries, 1);
+
+        let (files, _) = image.filesystem()?;
+        assert_eq!(files["etc/issue"].size, 7);
+        Ok(())
+    }
+
+    #[test]
+    fn test_docker_save_tarball() -> anyhow::Result<()> {
+        let layers = layers();
+        let manifest = json!([{
+            "Config": "config.json",
+            "RepoTags": ["example/app:1.0"],
+            "Layers": ["l0/layer.tar", "l1/layer.tar", "l2/layer.tar"],
+        }])
+        .to_string();
+        let config = json!({ "architecture": "amd64", "os": "linux" }).to_string();
+        let tarball = tar_bytes(&[
+            ("manifest.json", b'0', manifest.as_bytes()),
+            ("config.json", b'0', config.as_bytes()),
+            ("l0/layer.tar", b'0', &layers[0]),
+            ("l1/layer.tar", b'0', &layers[1]),
+            ("l2/layer.tar", b'0', &gzip(&layers[2])),
+        ]);
+        let dir = TempDir::new()?;
+        let path = dir.path().join("image.tar");
+        std::fs::write(&path, tarball)?;
+
+        let image = OciImage::from_tarball(&path, &"linux/amd64".parse()?)?;
+        assert_eq!(image.name.as_deref(), Some("example/app:1.0"));
+        assert_eq!(image.layers.len(), 3);
+        assert_eq!(image.layers[0].digest, sha256_digest(&layers[0]));
+        assert_eq!(image.config["os"], "linux");
+        assert_walked(&image)
+    }
+
+    #[test]
+    fn test_oci_layout_tarball() -> anyhow::Result<()> {
+        let layers: Vec<_> = layers().iter().map(|layer| gzip(layer)).collect();
+        let config = json!({ "architecture": "arm64", "os": "linux" }).to_string();
+        let descriptor = |media_type: &str, content: &[u8]| json!({ "mediaType": media_type, "digest": sha256_digest(content), "size": content.len() });
+        let manifest = json!({
+            "schemaVersion": 2,
+            "mediaType": OCI_MANIFEST_MEDIA_TYPE,
+            "config": descriptor("application/vnd.oci.image.config.v1+json", config.as_bytes()),
+            "layers": layers
+                .iter()
+                .map(|layer| descriptor("application/vnd.oci.image.layer.v1.tar+gzip", layer))
+                .collect::<Vec<_>>(),
+        })
+        .to_string();
+        let mut manifest_descriptor = descriptor(OCI_MANIFEST_MEDIA_TYPE, manifest.as_bytes());
+        manifest_descriptor["platform"] =
+            json!({ "os": "linux", "architecture": "arm64", "variant": "v8" });
+        manifest_descriptor["annotations"] = json!({ "org.opencontainers.image.ref.name": "3.19" });
+        let index = json!({ "schemaVersion": 2, "manifests": [manifest_descriptor] }).to_string();
+
+        let blob = |content: &[u8]| format!("blobs/sha256/{}", &sha256_digest(content)[7..]);
+        let mut entries: Vec<(String, Vec<u8>)> = vec![
+            (
+                "oci-layout".to_string(),
+                br#"{"imageLayoutVersion":"1.0.0"}"#.to_vec(),
+            ),
+            ("index.json".to_string(), index.into_bytes()),
+            (blob(manifest.as_bytes()), manifest.clone().into_bytes()),
+            (blob(config.as_bytes()), config.into_bytes()),
+        ];
+        entries.extend(layers.iter().map(|layer| (blob(layer), layer.clone())));
+        let tarball = tar_bytes(
+            &entries
+                .iter()
+                .map(|(path, content)| (path.as_str(), b'0', content.as_slice()))
+                .collect::<Vec<_>>(),
+        );
+        let dir = TempDir::new()?;
+        let path = dir.path().join("image.tar");
+        std::fs::write(&path, tarball)?;
+
+        assert!(OciImage::from_tarball(&path, &"linux/amd64".parse()?)
+            .unwrap_err()
+            .to_string()
+            .contains("linux/arm64/v8"));
+        let image = OciImage::from_tarball(&path, &"linux/arm64".parse()?)?;
+        assert_eq!(image.name.as_deref(), Some("3.19"));
+        assert_eq!(image.manifest_digest, sha256_digest(manifest.as_bytes()));
+        assert_eq!(
+            image.layers[2].media_type,
+            "application/vnd.oci.image.layer.v1.tar+gzip"
+        );
+        assert_walked(&image)
+    }
+
+    #[test]
+    fn test_tarball_blob_digests() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        let from_index = |manifest_digest: &str, blobs: &[(&str, &[u8])]| {
+            let index = json!({
+                "schemaVersion": 2,
+                "manifests": [{ "mediaType": OCI_MANIFEST_MEDIA_TYPE, "digest": manifest_digest, "size": 2 }],
+            })
+            .to_string();
+            let mut entries = vec![("index.json", b'0', index.as_bytes())];
+            entries.extend(blobs.iter().map(|(path, content)| (*path, b'0', *content)));
+            let path = dir.path().join("image.tar");
+            std::fs::write(&path, tar_bytes(&entries))?;
+            OciImage::from_tarball(&path, &"linux/amd64".parse()?)
+        };
+
+        for digest in ["sha256:../../../../etc/passwd", "/etc/passwd", "md5:abc"] {
+            let err = from_index(digest, &[]).unwrap_err();
+            assert!(format!("{err:#}").contains("unsupported digest"), "{err:#}");
+        }
+
+        // the content of a blob must match its digest
+        let digest = sha256_digest(b"{}");
+        let tampered = format!("blobs/sha256/{}", &digest[7..]);
+        let err = from_index(&digest, &[(tampered.as_str(), b"[]")]).unwrap_err();
+        assert!(format!("{err:#}").contains("has digest"), "{err:#}");
+        Ok(())
+    }
+
+    #[test]
+    fn test_image_reference() -> anyhow::Result<()> {
+        let parsed = |reference| {
+            ImageReference::parse(reference).map(|r| (r.registry, r.repository, r.tag, r.digest))
+        };
+        assert_eq!(
+            parsed("alpine")?,
+            (
+                DOCKER_HUB_REGISTRY.to_string(),
+                "library/alpine".to_string(),
+                "latest".to_string(),
+                None
+            )
+        );
+        assert_eq!(
+            parsed("docker.io/grafana/grafana:10.2")?,
+            (
+                DOCKER_HUB_REGISTRY.to_string(),
+                "grafana/grafana".to_string(),
+                "10.2".to_string(),
+                None
+            )
+        );
+        assert_eq!(
+            parsed("localhost:5000/team/app@sha256:abc")?,
+            (
+                "localhost:5000".to_string(),
+                "team/app".to_string(),
+                "latest".to_string(),
+                Some("sha256:abc".to_string())
+            )
+        );
+        assert!(parsed("ghcr.io/").is_err());
+        assert!("linux".parse::<OciPlatform>().is_err());
+        Ok(())
+    }
+}
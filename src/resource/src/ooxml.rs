@@ -0,0 +1,432 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Cursor, Read};
+
+use anyhow::{anyhow, Context};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde::Serialize;
+use zip::ZipArchive;
+
+/// The Office Open XML formats whose content is extracted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OfficeFormat {
+    Docx,
+    Xlsx,
+    Pptx,
+}
+
+impl OfficeFormat {
+    pub fn from_nature(nature: &str) -> Option<OfficeFormat> {
+        match nature {
+            "docx" | "application/vnd.openxmlformats-officedocument.wordprocessingml.document" => {
+                Some(OfficeFormat::Docx)
+            }
+            "xlsx" | "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet" => {
+                Some(OfficeFormat::Xlsx)
+            }
+            "pptx"
+            | "application/vnd.openxmlformats-officedocument.presentationml.presentation" => {
+                Some(OfficeFormat::Pptx)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A worksheet's cells by row, shorter rows have no trailing empty cells.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct OfficeSheet {
+    pub name: String,
+    pub rows: Vec<Vec<String>>,
+}
+
+/// The text of a Word document, workbook or presentation and its document
+/// properties (`title`, `creator`, `lastModifiedBy`, `created`, etc.).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OfficeDocument {
+    pub format: OfficeFormat,
+    pub properties: BTreeMap<String, String>,
+    /// the non-empty paragraphs of a Word document's body
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub paragraphs: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sheets: Vec<OfficeSheet>,
+    /// the text of each slide, its paragraphs on separate lines
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub slides: Vec<String>,
+}
+
+pub fn office_document(format: OfficeFormat, binary: &[u8]) -> anyhow::Result<OfficeDocument> {
+    let mut zip = ZipArchive::new(Cursor::new(binary))
+        .context("[ooxml::office_document] not a zip package")?;
+    let properties = match member_text(&mut zip, "docProps/core.xml")? {
+        Some(xml) => core_properties(&xml)?,
+        None => BTreeMap::new(),
+    };
+    let mut document = OfficeDocument {
+        format,
+        properties,
+        paragraphs: vec![],
+        sheets: vec![],
+        slides: vec![],
+    };
+    match format {
+        OfficeFormat::Docx => {
+            let xml = member_text(&mut zip, "word/document.xml")?
+                .ok_or_else(|| anyhow!("[ooxml::office_document] word/document.xml missing"))?;
+            document.paragraphs = paragraphs(&xml)?;
+        }
+        OfficeFormat::Xlsx => document.sheets = sheets(&mut zip)?,
+        OfficeFormat::Pptx => {
+            let slide = regex::Regex::new(r"^ppt/slides/slide(\d+)\.xml$").unwrap();
+            let mut slides = zip
+                .file_names()
+                .filter_map(|name| {
+                    let number = slide.captures(name)?[1].parse::<usize>().ok()?;
+                    Some((number, name.to_string()))
+                })
+                .collect::<Vec<_>>();
+            slides.sort();
+            for (_, name) in slides {
+                if let Some(xml) = member_text(&mut zip, &name)? {
+                    document.slides.push(paragraphs(&xml)?.join("\n"));
+                }
+            }
+        }
+    }
+    Ok(document)
+}
+
+type Package<'a> = ZipArchive<Cursor<&'a [u8]>>;
+
+fn member_text(zip: &mut Package<'_>, name: &str) -> anyhow::Result<Option<String>> {
+    let mut member = match zip.by_name(name) {
+        Ok(member) => member,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(err) => return Err(err).with_context(|| format!("[ooxml::member_text] {name}")),
+    };
+    let mut text = String::new();
+    member
+        .read_to_string(&mut text)
+        .with_context(|| format!("[ooxml::member_text] {name}"))?;
+    Ok(Some(text))
+}
+
+enum XmlEvent<'a> {
+    /// start and self-closing tags, the latter are closed right away
+    Open(&'a BytesStart<'a>),
+    Close(&'a [u8]),
+    Text(&'a str),
+}
+
+fn walk_xml(xml: &str, mut on_event: impl FnMut(XmlEvent<'_>)) -> anyhow::Result<()> {
+    let mut reader = Reader::from_str(xml);
+    loop {
+        match reader.read_event()? {
+            Event::Start(start) => on_event(XmlEvent::Open(&start)),
+            Event::Empty(start) => {
+                on_event(XmlEvent::Open(&start));
+                on_event(XmlEvent::Close(start.local_name().as_ref()));
+            }
+            Event::End(end) => on_event(XmlEvent::Close(end.local_name().as_ref())),
+            Event::Text(text) => on_event(XmlEvent::Text(&text.unescape()?)),
+            Event::CData(cdata) => on_event(XmlEvent::Text(&String::from_utf8_lossy(
+                &cdata.into_inner(),
+            ))),
+            Event::Eof => return Ok(()),
+            _ => {}
+        }
+    }
+}
+
+fn attribute(start: &BytesStart<'_>, name: &[u8]) -> Option<String> {
+    start
+        .attributes()
+        .flatten()
+        .find(|attr| attr.key.local_name().as_ref() == name)
+        .and_then(|attr| attr.unescape_value().ok().map(|value| value.to_string()))
+}
+
+fn core_properties(xml: &str) -> anyhow::Result<BTreeMap<String, String>> {
+    let mut properties = BTreeMap::new();
+    let mut property: Option<String> = None;
+    walk_xml(xml, |event| match event {
+        XmlEvent::Open(start) if start.local_name().as_ref() != b"coreProperties" => {
+            property = Some(String::from_utf8_lossy(start.local_name().as_ref()).to_string())
+        }
+        XmlEvent::Text(text) if !text.trim().is_empty() => {
+            if let Some(name) = &property {
+                properties.insert(name.clone(), text.trim().to_string());
+            }
+        }
+        XmlEvent::Close(_) => property = None,
+        _ => {}
+    })?;
+    Ok(properties)
+}
+
+/// The non-empty paragraphs of WordprocessingML (`w:p` of `w:t` texts) and
+/// DrawingML (`a:p` of `a:t` texts) parts.
+fn paragraphs(xml: &str) -> anyhow::Result<Vec<String>> {
+    let mut paragraphs = vec![];
+    let mut paragraph = String::new();
+    let mut in_text = false;
+    walk_xml(xml, |event| match event {
+        XmlEvent::Open(start) => match start.local_name().as_ref() {
+            b"t" => in_text = true,
+            b"tab" => paragraph.push('\t'),
+            b"br" | b"cr" => paragraph.push('\n'),
+            _ => {}
+        },
+        XmlEvent::Text(text) if in_text => paragraph.push_str(text),
+        XmlEvent::Close(b"p") => {
+            let text = paragraph.trim();
+            if !text.is_empty() {
+                paragraphs.push(text.to_string());
+            }
+            paragraph.clear();
+        }
+        XmlEvent::Close(b"t") => in_text = false,
+        _ => {}
+    })?;
+    Ok(paragraphs)
+}
+
+/// The worksheets in workbook order with their shared strings resolved.
+fn sheets(zip: &mut Package<'_>) -> anyhow::Result<Vec<OfficeSheet>> {
+    let workbook = member_text(zip, "xl/workbook.xml")?
+        .ok_or_else(|| anyhow!("[ooxml::sheets] xl/workbook.xml missing"))?;
+    let mut workbook_sheets = vec![];
+    walk_xml(&workbook, |event| {
+        if let XmlEvent::Open(start) = event {
+            if start.local_name().as_ref() == b"sheet" {
+                workbook_sheets.push((
+                    attribute(start, b"name").unwrap_or_default(),
+                    attribute(start, b"id"),
+                ));
+            }
+        }
+    })?;
+
+    let mut targets = HashMap::new();
+    if let Some(rels) = member_text(zip, "xl/_rels/workbook.xml.rels")? {
+        walk_xml(&rels, |event| {
+            if let XmlEvent::Open(start) = event {
+                if let (Some(id), Some(target)) =
+                    (attribute(start, b"Id"), attribute(start, b"Target"))
+                {
+                    let path = match target.strip_prefix('/') {
+                        Some(absolute) => absolute.to_string(),
+                        None => format!("xl/{target}"),
+                    };
+                    targets.insert(id, path);
+                }
+            }
+        })?;
+    }
+
+    let mut shared = vec![];
+    if let Some(xml) = member_text(zip, "xl/sharedStrings.xml")? {
+        let mut string = String::new();
+        let mut in_text = false;
+        walk_xml(&xml, |event| match event {
+            XmlEvent::Open(start) if start.local_name().as_ref() == b"t" => in_text = true,
+            XmlEvent::Text(text) if in_text => string.push_str(text),
+            XmlEvent::Close(b"t") => in_text = false,
+            XmlEvent::Close(b"si") => shared.push(std::mem::take(&mut string)),
+            _ => {}
+        })?;
+    }
+
+    let mut sheets = vec![];
+    for (index, (name, id)) in workbook_sheets.into_iter().enumerate() {
+        let path = id
+            .and_then(|id| targets.get(&id).cloned())
+            .unwrap_or_else(|| format!("xl/worksheets/sheet{}.xml", index + 1));
+        if let Some(xml) = member_text(zip, &path)? {
+            sheets.push(OfficeSheet {
+                name,
+                rows: sheet_rows(&xml, &shared)?,
+            });
+        }
+    }
+    Ok(sheets)
+}
+
+/// The zero-based column of a cell reference such as `AB12`.
+fn column_index(reference: &str) -> Option<usize> {
+    let letters = reference
+        .chars()
+        .take_while(char::is_ascii_alphabetic)
+        .collect::<String>();
+    if letters.is_empty() {
+        return None;
+    }
+    Some(
+        letters
+            .to_ascii_uppercase()
+            .bytes()
+            .fold(0, |n, b| n * 26 + (b - b'A' + 1) as usize)
+            - 1,
+    )
+}
+
+fn sheet_rows(xml: &str, shared: &[String]) -> anyhow::Result<Vec<Vec<String>>> {
+    let mut rows = vec![];
+    let mut row: Vec<String> = vec![];
+    // the cell's column and type, and whether its value is being read
+    let mut cell: Option<(Option<usize>, Option<String>)> = None;
+    let mut value = String::new();
+    let mut in_value = false;
+    walk_xml(xml, |event| match event {
+        XmlEvent::Open(start) => match start.local_name().as_ref() {
+            b"c" => {
+                cell = Some((
+                    attribute(start, b"r").and_then(|r| column_index(&r)),
+                    attribute(start, b"t"),
+                ));
+                value.clear();
+            }
+            b"v" | b"t" if cell.is_some() => in_value = true,
+            _ => {}
+        },
+        XmlEvent::Text(text) if in_value => value.push_str(text),
+        XmlEvent::Close(b"v" | b"t") => in_value = false,
+        XmlEvent::Close(b"c") => {
+            if let Some((column, cell_type)) = cell.take() {
+                let text = match cell_type.as_deref() {
+                    Some("s") => value
+                        .trim()
+                        .parse::<usize>()
+                        .ok()
+                        .and_then(|i| shared.get(i).cloned())
+                        .unwrap_or_default(),
+                    _ => value.clone(),
+                };
+                let column = column.unwrap_or(row.len());
+                if !text.is_empty() {
+                    if row.len() <= column {
+                        row.resize(column + 1, String::new());
+                    }
+                    row[column] = text;
+                }
+            }
+        }
+        XmlEvent::Close(b"row") => rows.push(std::mem::take(&mut row)),
+        _ => {}
+    })?;
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use zip::write::FileOptions;
+
+    use super::*;
+
+    fn package(members: &[(&str, &str)]) -> Vec<u8> {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in members {
+            zip.start_file(*name, FileOptions::default()).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    const CORE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<cp:coreProperties xmlns:cp="http://schemas.openxmlformats.org/package/2006/metadata/core-properties" xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:dcterms="http://purl.org/dc/terms/">
+  <dc:title>Access Review Q1</dc:title><dc:creator>Joe &amp; Ann</dc:creator>
+  <dcterms:created>2024-01-02T03:04:05Z</dcterms:created>
+</cp:coreProperties>"#;
+
+    #[test]
+    fn test_office_documents() -> anyhow::Result<()> {
+        let docx = package(&[
+            ("docProps/core.xml", CORE),
+            (
+                "word/document.xml",
+                r#"<w:document xmlns:w="w"><w:body>
+                <w:p><w:r><w:t>Access</w:t></w:r><w:r><w:t xml:space="preserve"> reviews</w:t><w:tab/><w:t>are quarterly</w:t></w:r></w:p>
+                <w:p/>
+                <w:p><w:r><w:t>Owner: IT</w:t></w:r></w:p>
+                </w:body></w:document>"#,
+            ),
+        ]);
+        let doc = office_document(OfficeFormat::Docx, &docx)?;
+        assert_eq!(
+            doc.paragraphs,
+            vec!["Access reviews\tare quarterly", "Owner: IT"]
+        );
+        assert_eq!(
+            doc.properties,
+            BTreeMap::from([
+                ("created".to_string(), "2024-01-02T03:04:05Z".to_string()),
+                ("creator".to_string(), "Joe & Ann".to_string()),
+                ("title".to_string(), "Access Review Q1".to_string()),
+            ])
+        );
+
+        let xlsx = package(&[
+            (
+                "xl/workbook.xml",
+                r#"<workbook xmlns:r="r"><sheets><sheet name="Users" sheetId="1" r:id="rId2"/></sheets></workbook>"#,
+            ),
+            (
+                "xl/_rels/workbook.xml.rels",
+                r#"<Relationships><Relationship Id="rId2" Target="worksheets/users.xml"/></Relationships>"#,
+            ),
+            (
+                "xl/sharedStrings.xml",
+                r#"<sst><si><t>user</t></si><si><r><t>ad</t></r><r><t>min</t></r></si><si><t>alice</t></si></sst>"#,
+            ),
+            (
+                "xl/worksheets/users.xml",
+                r#"<worksheet><sheetData>
+                <row r="1"><c r="A1" t="s"><v>0</v></c><c r="C1" t="s"><v>1</v></c></row>
+                <row r="2"><c r="A2" t="s"><v>2</v></c><c r="B2"><v>42</v></c><c r="C2" t="b"><v>1</v></c><c r="D2" t="inlineStr"><is><t>note</t></is></c></row>
+                </sheetData></worksheet>"#,
+            ),
+        ]);
+        let doc = office_document(OfficeFormat::Xlsx, &xlsx)?;
+        assert_eq!(
+            doc.sheets,
+            vec![OfficeSheet {
+                name: "Users".to_string(),
+                rows: vec![
+                    vec!["user".to_string(), "".to_string(), "admin".to_string()],
+                    vec![
+                        "alice".to_string(),
+                        "42".to_string(),
+                        "1".to_string(),
+                        "note".to_string()
+                    ],
+                ],
+            }]
+        );
+        assert!(doc.properties.is_empty());
+
+        let slide = |text: &str| {
+            format!(
+                r#"<p:sld xmlns:a="a" xmlns:p="p"><p:cSld><p:spTree><p:sp><p:txBody><a:p><a:r><a:t>{text}</a:t></a:r></a:p><a:p><a:r><a:t>more</a:t></a:r></a:p></p:txBody></p:sp></p:spTree></p:cSld></p:sld>"#
+            )
+        };
+        let (second, tenth) = (slide("Second"), slide("Tenth"));
+        let pptx = package(&[
+            ("ppt/slides/slide10.xml", &tenth),
+            ("ppt/slides/slide2.xml", &second),
+            ("ppt/slides/_rels/slide2.xml.rels", "<Relationships/>"),
+        ]);
+        let doc = office_document(OfficeFormat::Pptx, &pptx)?;
+        assert_eq!(doc.slides, vec!["Second\nmore", "Tenth\nmore"]);
+        assert_eq!(
+            serde_json::to_value(&doc)?,
+            serde_json::json!({ "format": "pptx", "properties": {}, "slides": ["Second\nmore", "Tenth\nmore"] })
+        );
+
+        assert!(office_document(OfficeFormat::Docx, b"not a zip").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,222 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use mail_parser::{MessageParser, MimeHeaders};
+use serde::Serialize;
+use sha1::{Digest, Sha1};
+
+/// Bare base64 shorter than this is too likely to be an ordinary word or token.
+const MIN_BARE_BASE64_LEN: usize = 24;
+
+/// How an ingested payload was encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PayloadEncoding {
+    Base64,
+    Multipart,
+}
+
+/// A payload decoded from base64 (bare or `data:` URI) or from a MIME
+/// multipart body, with its declared (or sniffed) inner content type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedPayload {
+    pub encoding: PayloadEncoding,
+    pub content_type: String,
+    pub content: Vec<u8>,
+    /// 1-based position of the MIME part, `None` for base64 payloads
+    pub part: Option<usize>,
+    pub filename: Option<String>,
+}
+
+impl DecodedPayload {
+    pub fn content_digest_hash(&self) -> String {
+        let mut hasher = Sha1::new();
+        hasher.update(&self.content);
+        format!("{:x}", hasher.finalize())
+    }
+}
+
+/// Decode `src` when it is a `data:<type>;base64,` URI, bare base64 or a MIME
+/// multipart body (with or without its `Content-Type` header). Returns an empty
+/// list when the content is not encoded.
+pub fn decode_payload(src: &str) -> Vec<DecodedPayload> {
+    let trimmed = src.trim();
+    if let Some(decoded) = decode_data_uri(trimmed) {
+        return vec![decoded];
+    }
+    if let Some(parts) = decode_multipart(trimmed) {
+        return parts;
+    }
+    decode_bare_base64(trimmed).into_iter().collect()
+}
+
+fn decode_data_uri(src: &str) -> Option<DecodedPayload> {
+    let (meta, data) = src.strip_prefix("data:")?.split_once(',')?;
+    let content_type = meta.strip_suffix(";base64")?;
+    let content = decode_base64(data)?;
+    Some(DecodedPayload {
+        encoding: PayloadEncoding::Base64,
+        content_type: if content_type.is_empty() {
+            sniff_content_type(&content).to_string()
+        } else {
+            content_type.to_string()
+        },
+        content,
+        part: None,
+        filename: None,
+    })
+}
+
+fn decode_bare_base64(src: &str) -> Option<DecodedPayload> {
+    if src.len() < MIN_BARE_BASE64_LEN {
+        return None;
+    }
+    let content = decode_base64(src)?;
+    // without a declared type only accept payloads which are recognizably text or a known format
+    let content_type = sniff_content_type(&content);
+    if content_type == "application/octet-stream" {
+        return None;
+    }
+    Some(DecodedPayload {
+        encoding: PayloadEncoding::Base64,
+        content_type: content_type.to_string(),
+        content,
+        part: None,
+        filename: None,
+    })
+}
+
+fn decode_base64(src: &str) -> Option<Vec<u8>> {
+    let compact: String = src.chars().filter(|c| !c.is_ascii_whitespace()).collect();
+    if compact.is_empty()
+        || !compact.len().is_multiple_of(4)
+        || !compact
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '/' || c == '=')
+    {
+        return None;
+    }
+    STANDARD.decode(compact).ok()
+}
+
+/// A multipart body either starts with its own `Content-Type: multipart/...`
+/// header or (common in exported HTTP payloads) directly with `--<boundary>`.
+fn decode_multipart(src: &str) -> Option<Vec<DecodedPayload>> {
+    let first_line = src.lines().next()?.trim_end();
+    let raw = if let Some(boundary) = first_line.strip_prefix("--") {
+        if boundary.is_empty() || !src.contains(&format!("--{boundary}--")) {
+            return None;
+        }
+        format!("Content-Type: multipart/mixed; boundary=\"{boundary}\"\r\n\r\n{src}")
+    } else if first_line
+        .to_ascii_lowercase()
+        .starts_with("content-type: multipart/")
+    {
+        src.to_string()
+    } else {
+        return None;
+    };
+
+    let message = MessageParser::default().parse(raw.as_bytes())?;
+    let parts: Vec<DecodedPayload> = message
+        .parts
+        .iter()
+        .filter(|part| !part.is_multipart() && !part.is_empty())
+        .enumerate()
+        .map(|(index, part)| DecodedPayload {
+            encoding: PayloadEncoding::Multipart,
+            content_type: part
+                .content_type()
+                .map(|ct| match ct.subtype() {
+                    Some(subtype) => format!("{}/{}", ct.ctype(), subtype),
+                    None => ct.ctype().to_string(),
+                })
+                .unwrap_or_else(|| "text/plain".to_string()),
+            content: part.contents().to_vec(),
+            part: Some(index + 1),
+            filename: part.attachment_name().map(str::to_string),
+        })
+        .collect();
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts)
+    }
+}
+
+fn sniff_content_type(content: &[u8]) -> &'static str {
+    const MAGIC: [(&[u8], &str); 5] = [
+        (b"\x89PNG", "image/png"),
+        (b"%PDF", "application/pdf"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"\x1f\x8b", "application/gzip"),
+        (b"PK\x03\x04", "application/zip"),
+    ];
+    if let Some((_, content_type)) = MAGIC.iter().find(|(magic, _)| content.starts_with(magic)) {
+        return content_type;
+    }
+    match std::str::from_utf8(content) {
+        Ok(text) => {
+            let text = text.trim_start();
+            if serde_json::from_str::<serde_json::Value>(text).is_ok() {
+                "application/json"
+            } else if text
+                .get(..5)
+                .is_some_and(|s| s.eq_ignore_ascii_case("<html"))
+                || text
+                    .get(..9)
+                    .is_some_and(|s| s.eq_ignore_ascii_case("<!doctype"))
+            {
+                "text/html"
+            } else if text.starts_with('<') {
+                "application/xml"
+            } else if text.chars().all(|c| !c.is_control() || c.is_whitespace()) {
+                "text/plain"
+            } else {
+                "application/octet-stream"
+            }
+        }
+        Err(_) => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_base64_payloads() {
+        let json = STANDARD.encode(r#"{"hello": "world", "n": 1}"#);
+        let decoded = decode_payload(&json);
+        assert_eq!(decoded.len(), 1);
+        assert_eq!(decoded[0].content_type, "application/json");
+        assert_eq!(decoded[0].content, br#"{"hello": "world", "n": 1}"#);
+
+        let data_uri = format!("data:text/csv;base64,{}", STANDARD.encode("a,b\n1,2\n"));
+        let decoded = decode_payload(&data_uri);
+        assert_eq!(decoded[0].content_type, "text/csv");
+        assert_eq!(decoded[0].content, b"a,b\n1,2\n");
+
+        // ordinary text is not mistaken for base64
+        assert!(decode_payload("just some plain text which is not encoded").is_empty());
+        assert!(decode_payload("abcdefghabcdefghabcdefgh").is_empty());
+    }
+
+    #[test]
+    fn test_decode_multipart_payloads() {
+        let body = "--XYZ\r\n\
+            Content-Type: application/json\r\n\r\n\
+            {\"id\": 1}\r\n\
+            --XYZ\r\n\
+            Content-Type: text/plain\r\n\
+            Content-Disposition: attachment; filename=\"note.txt\"\r\n\
+            Content-Transfer-Encoding: base64\r\n\r\n\
+            aGVsbG8gd29ybGQ=\r\n\
+            --XYZ--\r\n";
+        let decoded = decode_payload(body);
+        assert_eq!(decoded.len(), 2);
+        assert_eq!(decoded[0].encoding, PayloadEncoding::Multipart);
+        assert_eq!(decoded[0].content_type, "application/json");
+        assert_eq!(decoded[1].content, b"hello world");
+        assert_eq!(decoded[1].filename.as_deref(), Some("note.txt"));
+        assert_eq!(decoded[1].part, Some(2));
+    }
+}
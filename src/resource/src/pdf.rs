@@ -0,0 +1,269 @@
+use std::collections::BTreeMap;
+use std::panic::{self, AssertUnwindSafe};
+
+use anyhow::Context;
+use lopdf::{decode_text_string, Document, Object};
+use pdf_extract::{output_doc, PlainTextOutput};
+use serde::Serialize;
+
+const INFO_KEYS: [&str; 8] = [
+    "Title",
+    "Author",
+    "Subject",
+    "Keywords",
+    "Creator",
+    "Producer",
+    "CreationDate",
+    "ModDate",
+];
+
+/// What a PDF tells about itself and the text of its pages, read with `lopdf`
+/// and `pdf-extract` (which decodes fonts' encodings and `ToUnicode` maps).
+/// There's no text for scanned pages (images, there's no OCR), and nothing but
+/// the version for encrypted documents which don't open without a password.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PdfDocument {
+    /// e.g. `1.7`
+    pub version: String,
+    pub pages: usize,
+    /// encrypted documents are only read when they open with an empty user
+    /// password (e.g. they only restrict printing or copying), the others'
+    /// pages aren't even counted
+    pub encrypted: bool,
+    /// `Title`, `Author`, `CreationDate`, etc. of the document information
+    pub info: BTreeMap<String, String>,
+    #[serde(skip)]
+    pub text: String,
+    /// why the text couldn't be extracted, e.g. a font `pdf-extract` doesn't
+    /// support; the version, page count and info are still known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text_error: Option<String>,
+}
+
+pub fn pdf_document(pdf: &[u8]) -> anyhow::Result<PdfDocument> {
+    let document =
+        Document::load_mem(pdf).context("[pdf::pdf_document] unable to parse the PDF")?;
+    let encrypted = document.is_encrypted();
+    let mut pdf_document = PdfDocument {
+        version: document.version.clone(),
+        pages: document.get_pages().len(),
+        encrypted,
+        ..Default::default()
+    };
+    // lopdf decrypts the documents which open with an empty user password
+    if encrypted && document.encryption_state.is_none() {
+        return Ok(pdf_document);
+    }
+
+    pdf_document.info = document_info(&document);
+    match page_text(&document) {
+        Ok(text) => pdf_document.text = collapse_blank_lines(&text),
+        Err(err) => pdf_document.text_error = Some(err),
+    }
+    Ok(pdf_document)
+}
+
+/// The values of the document information dictionary which are text strings.
+fn document_info(document: &Document) -> BTreeMap<String, String> {
+    let Ok(info) = document
+        .trailer
+        .get(b"Info")
+        .and_then(|info| document.dereference(info))
+        .and_then(|(_, info)| info.as_dict())
+    else {
+        return BTreeMap::new();
+    };
+    INFO_KEYS
+        .iter()
+        .filter_map(|key| {
+            let value = match info.get(key.as_bytes()).ok()? {
+                Object::Reference(id) => document.get_object(*id).ok()?,
+                value => value,
+            };
+            let value = decode_text_string(value).ok()?;
+            let value: String = value.chars().filter(|c| !c.is_control()).collect();
+            (!value.is_empty()).then(|| (key.to_string(), value))
+        })
+        .collect()
+}
+
+/// The text of every page, `pdf-extract` panics on some malformed fonts which
+/// is reported like its errors.
+fn page_text(document: &Document) -> Result<String, String> {
+    panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut text = String::new();
+        output_doc(document, &mut PlainTextOutput::new(&mut text))
+            .map(|_| text)
+            .map_err(|err| err.to_string())
+    }))
+    .unwrap_or_else(|_| Err("pdf-extract panicked".to_string()))
+}
+
+fn collapse_blank_lines(text: &str) -> String {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use lopdf::{
+        content::{Content, Operation},
+        dictionary, text_string, EncryptionState, EncryptionVersion, Permissions, Stream,
+        StringFormat,
+    };
+
+    use super::*;
+
+    /// A PDF of one page per item of `pages`, each line shown with Helvetica,
+    /// an empty page for `None` (like a scanned page, which only has an image).
+    fn document(pages: &[Option<&[&str]>], info: lopdf::Dictionary) -> Document {
+        let mut doc = Document::with_version("1.7");
+        let pages_id = doc.new_object_id();
+        let font_id = doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Helvetica",
+        });
+        let resources_id = doc.add_object(dictionary! {
+            "Font" => dictionary! { "F1" => font_id },
+        });
+        let mut kids = vec![];
+        for lines in pages {
+            let mut operations = vec![];
+            if let Some(lines) = lines {
+                operations.push(Operation::new("BT", vec![]));
+                operations.push(Operation::new("Tf", vec!["F1".into(), 12.into()]));
+                operations.push(Operation::new("Td", vec![72.into(), 720.into()]));
+                for line in *lines {
+                    operations.push(Operation::new("Tj", vec![Object::string_literal(*line)]));
+                    operations.push(Operation::new("Td", vec![0.into(), (-20).into()]));
+                }
+                operations.push(Operation::new("ET", vec![]));
+            }
+            let content = Content { operations };
+            let content_id = doc.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
+            let page_id = doc.add_object(dictionary! {
+                "Type" => "Page",
+                "Parent" => pages_id,
+                "Contents" => content_id,
+                "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+            });
+            kids.push(page_id.into());
+        }
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Count" => kids.len() as i64,
+                "Kids" => kids,
+                "Resources" => resources_id,
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        let info_id = doc.add_object(info);
+        doc.trailer.set("Root", catalog_id);
+        doc.trailer.set("Info", info_id);
+        doc.trailer.set(
+            "ID",
+            vec![
+                Object::String(b"surveilr".to_vec(), StringFormat::Hexadecimal),
+                Object::String(b"surveilr".to_vec(), StringFormat::Hexadecimal),
+            ],
+        );
+        doc
+    }
+
+    fn saved(doc: &mut Document) -> Vec<u8> {
+        let mut pdf = vec![];
+        doc.save_to(&mut pdf).unwrap();
+        pdf
+    }
+
+    fn encrypted(doc: &mut Document, user_password: &str) -> Vec<u8> {
+        let version = EncryptionVersion::V2 {
+            document: doc,
+            owner_password: "owner",
+            user_password,
+            key_length: 128,
+            permissions: Permissions::PRINTABLE,
+        };
+        let state = EncryptionState::try_from(version).unwrap();
+        doc.encrypt(&state).unwrap();
+        saved(doc)
+    }
+
+    #[test]
+    fn test_pdf_document() -> anyhow::Result<()> {
+        let info = dictionary! {
+            "Title" => Object::string_literal("Password Policy"),
+            "Author" => text_string("Joë"),
+            "Producer" => 9,
+            "CreationDate" => Object::string_literal("D:20240102030405Z"),
+        };
+        let mut doc = document(
+            &[
+                Some(&["Password Policy (v2)", "Rotate every 90 days"]),
+                Some(&["Access reviews are quarterly"]),
+            ],
+            info.clone(),
+        );
+
+        let pdf = pdf_document(&saved(&mut doc))?;
+        assert_eq!(pdf.version, "1.7");
+        assert_eq!(pdf.pages, 2);
+        assert!(!pdf.encrypted);
+        assert_eq!(
+            pdf.info
+                .iter()
+                .map(|(k, v)| (k.as_str(), v.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("Author", "Joë"),
+                ("CreationDate", "D:20240102030405Z"),
+                ("Title", "Password Policy"),
+            ]
+        );
+        assert_eq!(
+            pdf.text,
+            "Password Policy (v2)\nRotate every 90 days\nAccess reviews are quarterly"
+        );
+        assert_eq!(pdf.text_error, None);
+
+        // restricting permissions only (an empty user password) doesn't stop reading
+        let mut doc = document(&[Some(&["Access reviews are quarterly"])], info.clone());
+        let pdf = pdf_document(&encrypted(&mut doc, ""))?;
+        assert!(pdf.encrypted);
+        assert_eq!(pdf.text, "Access reviews are quarterly");
+        assert_eq!(pdf.info["Title"], "Password Policy");
+
+        assert!(pdf_document(b"not a pdf").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_pdf_document_unsupported() -> anyhow::Result<()> {
+        // a password is needed to read the text and info of the document
+        let info = dictionary! { "Title" => Object::string_literal("Secret") };
+        let mut doc = document(&[Some(&["secret"])], info);
+        let pdf = pdf_document(&encrypted(&mut doc, "user"))?;
+        assert!(pdf.encrypted);
+        assert_eq!(pdf.version, "1.7");
+        assert_eq!(pdf.pages, 0);
+        assert!(pdf.info.is_empty());
+        assert!(pdf.text.is_empty());
+
+        // scanned pages are images, there's no OCR
+        let mut doc = document(&[None], dictionary! {});
+        let pdf = pdf_document(&saved(&mut doc))?;
+        assert_eq!(pdf.pages, 1);
+        assert!(pdf.text.is_empty());
+        assert_eq!(pdf.text_error, None);
+        Ok(())
+    }
+}
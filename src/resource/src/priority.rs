@@ -0,0 +1,170 @@
+//! Lower the CPU and IO priority of `surveilr` so scheduled collection on
+//! shared hosts yields to production workloads. Subprocesses (capturable
+//! executables) inherit the lowered priorities.
+
+use serde::Serialize;
+
+/// The lowest niceness, used for background collection.
+pub const BACKGROUND_NICENESS: i32 = 19;
+
+/// What was lowered by [`enter_background_mode`]; a priority which couldn't be
+/// lowered (e.g. not supported by the OS) is `None`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct BackgroundPriority {
+    /// the niceness (Unix) or priority class (Windows) now in effect
+    pub cpu: Option<String>,
+    /// the IO scheduling class now in effect
+    pub io: Option<String>,
+}
+
+/// Run this process, its threads and the processes it spawns at the lowest CPU
+/// and IO priorities: `nice 19` and the idle IO class (`ionice -c3`) on Linux,
+/// `nice 19` and throttled IO on macOS, the idle priority class and background
+/// processing mode on Windows.
+pub fn enter_background_mode() -> BackgroundPriority {
+    imp::enter_background_mode()
+}
+
+#[cfg(target_os = "linux")]
+mod imp {
+    use super::{BackgroundPriority, BACKGROUND_NICENESS};
+
+    const IOPRIO_WHO_PROCESS: libc::c_long = 1;
+    const IOPRIO_CLASS_IDLE: libc::c_long = 3;
+    const IOPRIO_CLASS_SHIFT: libc::c_long = 13;
+
+    /// Linux priorities are per thread, so lower the ones of the threads which
+    /// already exist (e.g. the async runtime's); new threads inherit them.
+    pub fn enter_background_mode() -> BackgroundPriority {
+        let tids: Vec<libc::id_t> = std::fs::read_dir("/proc/self/task")
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .filter_map(|entry| entry.file_name().to_str()?.parse().ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let tids = if tids.is_empty() { vec![0] } else { tids };
+
+        let mut niced = true;
+        let mut io_idle = true;
+        for tid in tids {
+            // SAFETY: setpriority and ioprio_set only change scheduling attributes
+            unsafe {
+                niced &= libc::setpriority(libc::PRIO_PROCESS, tid, BACKGROUND_NICENESS) == 0;
+                io_idle &= libc::syscall(
+                    libc::SYS_ioprio_set,
+                    IOPRIO_WHO_PROCESS,
+                    tid as libc::c_long,
+                    IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT,
+                ) == 0;
+            }
+        }
+        BackgroundPriority {
+            cpu: niced.then(|| format!("nice {BACKGROUND_NICENESS}")),
+            io: io_idle.then(|| "idle".to_string()),
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod imp {
+    use super::{BackgroundPriority, BACKGROUND_NICENESS};
+
+    const IOPOL_TYPE_DISK: libc::c_int = 0;
+    const IOPOL_SCOPE_PROCESS: libc::c_int = 0;
+    const IOPOL_THROTTLE: libc::c_int = 3;
+
+    extern "C" {
+        fn setiopolicy_np(
+            iotype: libc::c_int,
+            scope: libc::c_int,
+            policy: libc::c_int,
+        ) -> libc::c_int;
+    }
+
+    pub fn enter_background_mode() -> BackgroundPriority {
+        // SAFETY: setpriority and setiopolicy_np only change scheduling attributes
+        let (niced, throttled) = unsafe {
+            (
+                libc::setpriority(libc::PRIO_PROCESS, 0, BACKGROUND_NICENESS) == 0,
+                setiopolicy_np(IOPOL_TYPE_DISK, IOPOL_SCOPE_PROCESS, IOPOL_THROTTLE) == 0,
+            )
+        };
+        BackgroundPriority {
+            cpu: niced.then(|| format!("nice {BACKGROUND_NICENESS}")),
+            io: throttled.then(|| "throttle".to_string()),
+        }
+    }
+}
+
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "macos"))))]
+mod imp {
+    use super::{BackgroundPriority, BACKGROUND_NICENESS};
+
+    pub fn enter_background_mode() -> BackgroundPriority {
+        // SAFETY: setpriority only changes scheduling attributes
+        let niced = unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, BACKGROUND_NICENESS) == 0 };
+        BackgroundPriority {
+            cpu: niced.then(|| format!("nice {BACKGROUND_NICENESS}")),
+            io: None,
+        }
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use super::BackgroundPriority;
+    use windows_sys::Win32::System::Threading::{
+        GetCurrentProcess, SetPriorityClass, IDLE_PRIORITY_CLASS, PROCESS_MODE_BACKGROUND_BEGIN,
+    };
+
+    pub fn enter_background_mode() -> BackgroundPriority {
+        // SAFETY: the pseudo handle of the current process is always valid.
+        // Child processes inherit the idle priority class but not background
+        // mode, which lowers this process' IO and memory priorities.
+        let (idle, background) = unsafe {
+            (
+                SetPriorityClass(GetCurrentProcess(), IDLE_PRIORITY_CLASS) != 0,
+                SetPriorityClass(GetCurrentProcess(), PROCESS_MODE_BACKGROUND_BEGIN) != 0,
+            )
+        };
+        BackgroundPriority {
+            cpu: idle.then(|| "idle".to_string()),
+            io: background.then(|| "background".to_string()),
+        }
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod imp {
+    use super::BackgroundPriority;
+
+    pub fn enter_background_mode() -> BackgroundPriority {
+        BackgroundPriority::default()
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn background_mode_is_inherited() {
+        let priority = enter_background_mode();
+        let child = std::process::Command::new("cat")
+            .arg("/proc/self/stat")
+            .output()
+            .unwrap();
+        let stat = String::from_utf8(child.stdout).unwrap();
+        assert_eq!(priority.cpu.as_deref(), Some("nice 19"));
+        // niceness is the 19th field of /proc/<pid>/stat, after the parenthesized command
+        let fields: Vec<&str> = stat
+            .rsplit(')')
+            .next()
+            .unwrap()
+            .split_whitespace()
+            .collect();
+        assert_eq!(fields[16], "19");
+    }
+}
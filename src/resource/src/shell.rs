@@ -1,7 +1,9 @@
 use std::collections::HashMap;
 use std::io::{Read, Write};
 use std::path::PathBuf;
+use std::sync::Mutex;
 use std::thread;
+use std::time::Duration;
 
 use deno_task_shell::execute_with_pipes;
 use deno_task_shell::parser::parse;
@@ -53,6 +55,9 @@ pub struct ShellResult {
     pub status: ExitStatus,
     pub stderr: String,
     pub stdout: String,
+    /// Peak resident set size (in KB) of the process itself, `None` when it
+    /// couldn't be measured (e.g. on Windows or for Deno Task Shell commands).
+    pub max_rss_kb: Option<i64>,
 }
 
 #[allow(dead_code)]
@@ -137,26 +142,71 @@ pub fn execute_subprocess(
         } // else: no one is listening to the stdin of the subprocess, so we can't pipe anything to it
     }
 
-    let status = popen.wait()?;
+    let (status, max_rss_kb) = wait_with_max_rss(&mut popen)?;
 
     let mut output = String::new();
     popen.stdout.take().unwrap().read_to_string(&mut output)?;
 
     let mut error_output = String::new();
-    match &mut popen.stderr.take() {
-        Some(stderr) => {
-            stderr.read_to_string(&mut error_output)?;
-        }
-        None => {}
+    if let Some(stderr) = &mut popen.stderr.take() {
+        stderr.read_to_string(&mut error_output)?;
     }
 
     Ok(ShellResult {
         status,
         stdout: output,
         stderr: error_output,
+        max_rss_kb,
     })
 }
 
+/// Wait for `popen` with `wait4` so that the resource usage of that child alone
+/// (rather than of every child reaped so far) is available.
+#[cfg(unix)]
+fn wait_with_max_rss(popen: &mut subprocess::Popen) -> anyhow::Result<(ExitStatus, Option<i64>)> {
+    let Some(pid) = popen.pid() else {
+        return Ok((popen.wait()?, None));
+    };
+    let mut status: libc::c_int = 0;
+    let mut usage = std::mem::MaybeUninit::<libc::rusage>::zeroed();
+    loop {
+        // SAFETY: wait4 only writes into the provided status and rusage
+        let reaped = unsafe { libc::wait4(pid as libc::pid_t, &mut status, 0, usage.as_mut_ptr()) };
+        if reaped == pid as libc::pid_t {
+            break;
+        }
+        let err = std::io::Error::last_os_error();
+        if err.kind() != std::io::ErrorKind::Interrupted {
+            return Err(err.into());
+        }
+    }
+    // the child has been reaped, `popen` must not wait for it again
+    popen.detach();
+    // SAFETY: wait4 succeeded so the struct has been written
+    let usage = unsafe { usage.assume_init() };
+    let status = if libc::WIFEXITED(status) {
+        ExitStatus::Exited(libc::WEXITSTATUS(status) as u32)
+    } else if libc::WIFSIGNALED(status) {
+        ExitStatus::Signaled(libc::WTERMSIG(status) as u8)
+    } else {
+        ExitStatus::Other(status)
+    };
+    // ru_maxrss is in bytes on macOS and KB elsewhere, and a c_long which is
+    // narrower than i64 on 32-bit targets
+    #[allow(clippy::unnecessary_cast)]
+    let max_rss_kb = if cfg!(target_os = "macos") {
+        usage.ru_maxrss as i64 / 1024
+    } else {
+        usage.ru_maxrss as i64
+    };
+    Ok((status, Some(max_rss_kb)))
+}
+
+#[cfg(not(unix))]
+fn wait_with_max_rss(popen: &mut subprocess::Popen) -> anyhow::Result<(ExitStatus, Option<i64>)> {
+    Ok((popen.wait()?, None))
+}
+
 pub trait ShellExecutive {
     fn execute(&self, stdin: ShellStdIn) -> anyhow::Result<ShellResult>;
 }
@@ -167,6 +217,154 @@ impl ShellExecutive for String {
     }
 }
 
+lazy_static::lazy_static! {
+    static ref INTERPRETER_VERSIONS: Mutex<HashMap<String, Option<String>>> = Mutex::new(HashMap::new());
+}
+
+/// Interpreters whose `--version` is safe to probe; anything else (e.g. the
+/// capturable executable itself or an arbitrary task command) is never run
+/// just to record metadata.
+const KNOWN_INTERPRETERS: &[&str] = &[
+    "sh",
+    "bash",
+    "dash",
+    "zsh",
+    "ksh",
+    "fish",
+    "python",
+    "python2",
+    "python3",
+    "pypy",
+    "pypy3",
+    "deno",
+    "node",
+    "bun",
+    "ruby",
+    "perl",
+    "php",
+    "lua",
+    "pwsh",
+    "powershell",
+    "osqueryi",
+    "sqlite3",
+    "duckdb",
+];
+
+fn is_known_interpreter(program: &str) -> bool {
+    let name = std::path::Path::new(program)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let name = name.strip_suffix(".exe").unwrap_or(&name);
+    // allow versioned names such as `python3.11`
+    let name = name.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    KNOWN_INTERPRETERS
+        .iter()
+        .any(|known| name == known.trim_end_matches(|c: char| c.is_ascii_digit()))
+}
+
+/// Find the interpreter which will run `interpretable_code`: the shebang
+/// interpreter of a script (following `/usr/bin/env`) or the first word of a
+/// command line when it is a known interpreter. Executables without a shebang
+/// and other commands have no interpreter.
+pub fn resolve_interpreter(interpretable_code: &str) -> Option<String> {
+    let code_path = std::path::Path::new(interpretable_code);
+    let program = if code_path.is_file() {
+        let mut first_line = String::new();
+        if let Ok(file) = std::fs::File::open(code_path) {
+            let _ =
+                std::io::BufRead::read_line(&mut std::io::BufReader::new(file), &mut first_line);
+        }
+        let shebang = first_line.strip_prefix("#!")?;
+        let mut words = shebang.split_whitespace();
+        let interpreter = words.next()?;
+        if interpreter.ends_with("/env") {
+            words.find(|w| !w.starts_with('-'))?.to_string()
+        } else {
+            interpreter.to_string()
+        }
+    } else {
+        let program = interpretable_code.split_whitespace().next()?;
+        if !is_known_interpreter(program) {
+            return None;
+        }
+        program.to_string()
+    };
+
+    if program.contains(std::path::MAIN_SEPARATOR) {
+        return Some(program);
+    }
+    std::env::var_os("PATH").and_then(|paths| {
+        std::env::split_paths(&paths)
+            .map(|dir| dir.join(&program))
+            .find(|candidate| candidate.is_file())
+            .map(|candidate| candidate.to_string_lossy().to_string())
+    })
+}
+
+/// First line emitted by `<interpreter> --version`, cached since the same
+/// interpreters are used by many capturable executables. Only called for
+/// interpreters found by `resolve_interpreter`.
+fn interpreter_version(interpreter: &str) -> Option<String> {
+    let mut versions = INTERPRETER_VERSIONS.lock().unwrap();
+    versions
+        .entry(interpreter.to_string())
+        .or_insert_with(|| {
+            let mut popen = subprocess::Exec::cmd(interpreter)
+                .arg("--version")
+                .stdin(subprocess::NullFile)
+                .stdout(subprocess::Redirection::Pipe)
+                .stderr(subprocess::Redirection::Merge)
+                .popen()
+                .ok()?;
+            let status = match popen.wait_timeout(Duration::from_secs(5)) {
+                Ok(Some(status)) => status,
+                _ => {
+                    let _ = popen.kill();
+                    return None;
+                }
+            };
+            if !status.success() {
+                return None;
+            }
+            let mut output = String::new();
+            popen.stdout.take()?.read_to_string(&mut output).ok()?;
+            output
+                .lines()
+                .map(str::trim)
+                .find(|line| !line.is_empty())
+                .map(str::to_string)
+        })
+        .clone()
+}
+
+/// Fingerprint of the environment a capturable executable ran in so that its
+/// results can be reproduced later with the same toolchain. `max_rss_kb` is the
+/// capturable executable's own peak memory, see `ShellResult::max_rss_kb`.
+pub fn execution_environment(
+    interpretable_code: &str,
+    duration: Duration,
+    max_rss_kb: Option<i64>,
+) -> Value {
+    let interpreter = resolve_interpreter(interpretable_code);
+    let interpreter_version = interpreter.as_deref().and_then(interpreter_version);
+    let path: Vec<String> = std::env::var_os("PATH")
+        .map(|paths| {
+            std::env::split_paths(&paths)
+                .map(|p| p.to_string_lossy().to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+    json!({
+        "cwd": std::env::current_dir().ok().map(|cwd| cwd.to_string_lossy().to_string()),
+        "path": path,
+        "interpreter": interpreter,
+        "interpreter-version": interpreter_version,
+        "duration-ms": duration.as_millis() as u64,
+        "max-rss-kb": max_rss_kb,
+    })
+}
+
 /// `ShellResultSupplier` provides a mechanism to execute shell commands and
 /// capture their results using the `deno_task_shell` crate (cross-OS portable
 /// shell).
@@ -231,7 +429,7 @@ impl ShellExecutive for DenoTaskShellExecutive {
     ///
     /// * `command` - A string slice that holds the command to be executed.
     /// * `stdin_bytes` - A vector of bytes that will be written to the command's
-    ///    standard input.
+    ///   standard input.
     ///
     /// # Returns
     ///
@@ -240,7 +438,7 @@ impl ShellExecutive for DenoTaskShellExecutive {
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```ignore
     /// let mut supplier = ShellResultSupplier::new();
     /// let (exit_code, stdout, stderr) = supplier.result("echo Hello", Default::default());
     /// assert_eq!(stdout, "Hello\n");
@@ -289,12 +487,14 @@ impl ShellExecutive for DenoTaskShellExecutive {
                             status: ExitStatus::Exited(status as u32),
                             stderr,
                             stdout,
+                            max_rss_kb: None,
                         })
                     }
                     Err(err) => Ok(ShellResult {
                         status: ExitStatus::Undetermined,
                         stderr: format!("{err:?}"),
                         stdout: String::new(),
+                        max_rss_kb: None,
                     }),
                 }
             })
@@ -309,9 +509,29 @@ mod tests {
 
     use crate::shell::ShellExecutive;
 
+    use super::resolve_interpreter;
     use super::DenoTaskShellExecutive;
     use super::ShellStdIn;
 
+    #[test]
+    fn test_resolve_interpreter() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("env-shebang.surveilr[json].sh");
+        std::fs::write(&script, "#!/usr/bin/env sh\necho '{}'\n").unwrap();
+        let interpreter = resolve_interpreter(script.to_str().unwrap()).unwrap();
+        assert!(interpreter.ends_with("/sh"));
+
+        let no_shebang = dir.path().join("no-shebang.surveilr[json].sh");
+        std::fs::write(&no_shebang, "echo '{}'\n").unwrap();
+        assert_eq!(resolve_interpreter(no_shebang.to_str().unwrap()), None);
+
+        assert!(resolve_interpreter("sh -c 'echo task'")
+            .unwrap()
+            .ends_with("/sh"));
+        assert_eq!(resolve_interpreter("rm -rf ./scratch"), None);
+        assert_eq!(resolve_interpreter("./collect-inventory --json"), None);
+    }
+
     #[test]
     fn test_command_execution() {
         let shell_result_supplier =
@@ -336,6 +556,31 @@ mod tests {
         assert_eq!(result.stdout.trim(), "123");
     }
 
+    #[test]
+    fn test_subprocess_max_rss() {
+        let result =
+            super::execute_subprocess("sh", ShellStdIn::Text("echo measured\n".to_string()))
+                .unwrap();
+        assert!(result.success());
+        assert_eq!(result.stdout.trim(), "measured");
+        if cfg!(unix) {
+            assert!(result.max_rss_kb.is_some_and(|kb| kb > 0));
+        }
+
+        let environment = super::execution_environment(
+            "sh",
+            std::time::Duration::from_millis(1),
+            result.max_rss_kb,
+        );
+        assert_eq!(
+            environment["max-rss-kb"],
+            serde_json::json!(result.max_rss_kb)
+        );
+        let unmeasured =
+            super::execution_environment("sh", std::time::Duration::from_millis(1), None);
+        assert!(unmeasured["max-rss-kb"].is_null());
+    }
+
     #[test]
     fn test_custom_command_handling() {
         // Implement this test based on how you're using custom commands
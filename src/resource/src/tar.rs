@@ -0,0 +1,356 @@
+use std::io::{self, Read};
+use std::path::{Component, Path};
+
+use anyhow::{anyhow, Context};
+
+const BLOCK_LEN: usize = 512;
+/// GNU long names and PAX headers are read into memory, their size comes
+/// from the (untrusted) header.
+const MAX_METADATA_LEN: u64 = 1024 * 1024;
+
+/// What a tar entry is, links carry their target.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TarEntryKind {
+    File,
+    Dir,
+    Symlink(String),
+    /// a link to an earlier entry of the same archive
+    Hardlink(String),
+    /// devices, FIFOs and other entries without content
+    Other(u8),
+}
+
+/// The header of a tar entry, after GNU long names and PAX extended headers
+/// have been applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TarEntry {
+    /// as stored, e.g. `./etc/hosts` or `etc/`
+    pub path: String,
+    pub kind: TarEntryKind,
+    pub size: u64,
+    pub mode: u32,
+    /// seconds since the epoch
+    pub mtime: i64,
+}
+
+fn field_str(field: &[u8]) -> String {
+    let end = field.iter().position(|b| *b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).to_string()
+}
+
+/// Octal numbers, or base-256 (GNU) when the high bit of the first byte is set.
+fn field_num(field: &[u8]) -> anyhow::Result<u64> {
+    if field.first().is_some_and(|b| b & 0x80 != 0) {
+        return Ok(field[1..]
+            .iter()
+            .fold((field[0] & 0x7f) as u64, |n, b| (n << 8) | *b as u64));
+    }
+    let text = field_str(field);
+    let text = text.trim_matches(|c: char| c == ' ' || c == '\0');
+    if text.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(text, 8).with_context(|| format!("[tar::field_num] '{text}' isn't octal"))
+}
+
+fn checksum_matches(header: &[u8; BLOCK_LEN]) -> anyhow::Result<bool> {
+    let expected = field_num(&header[148..156])?;
+    let actual: u64 = header
+        .iter()
+        .enumerate()
+        .map(|(i, b)| {
+            if (148..156).contains(&i) {
+                b' ' as u64
+            } else {
+                *b as u64
+            }
+        })
+        .sum();
+    Ok(expected == actual)
+}
+
+/// Read `size` bytes of metadata plus the padding to the next block.
+fn read_data(reader: &mut impl Read, size: u64) -> anyhow::Result<Vec<u8>> {
+    if size > MAX_METADATA_LEN {
+        return Err(anyhow!(
+            "[tar::read_data] {size} bytes of long name or PAX header, at most {MAX_METADATA_LEN} are allowed"
+        ));
+    }
+    let mut data = vec![];
+    reader.take(size).read_to_end(&mut data)?;
+    if (data.len() as u64) < size {
+        return Err(anyhow!("[tar::read_data] archive ends inside an entry"));
+    }
+    skip_padding(reader, size)?;
+    Ok(data)
+}
+
+fn skip_padding(reader: &mut impl Read, size: u64) -> io::Result<()> {
+    let padding = (BLOCK_LEN as u64 - size % BLOCK_LEN as u64) % BLOCK_LEN as u64;
+    io::copy(&mut reader.take(padding), &mut io::sink())?;
+    Ok(())
+}
+
+/// PAX records are `<length> <key>=<value>\n`.
+fn pax_records(data: &[u8]) -> Vec<(String, String)> {
+    let mut records = vec![];
+    let mut rest = data;
+    while let Some(space) = rest.iter().position(|b| *b == b' ') {
+        let Some(len) = std::str::from_utf8(&rest[..space])
+            .ok()
+            .and_then(|len| len.parse::<usize>().ok())
+            .filter(|len| *len > space && *len <= rest.len())
+        else {
+            break;
+        };
+        let record = String::from_utf8_lossy(&rest[space + 1..len]).to_string();
+        if let Some((key, value)) = record.trim_end_matches('\n').split_once('=') {
+            records.push((key.to_string(), value.to_string()));
+        }
+        rest = &rest[len..];
+    }
+    records
+}
+
+/// Paths inside archives and images are often stored as `./etc/hosts`, they're
+/// normalized to `etc/hosts` and those escaping the archive (`../../etc/hosts`)
+/// are `None`.
+pub(crate) fn normalized_path(path: &str) -> Option<String> {
+    let mut parts = vec![];
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().to_string()),
+            Component::ParentDir => {
+                parts.pop()?;
+            }
+            _ => {}
+        }
+    }
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+/// Read the entries of the (uncompressed) tar archive in `reader` one at a
+/// time into `visit` along with a reader of their content, which doesn't have
+/// to be consumed. Supports ustar, GNU long names and PAX extended headers.
+pub fn read_tar<R: Read>(
+    mut reader: R,
+    mut visit: impl FnMut(&TarEntry, &mut dyn Read) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let mut long_path: Option<String> = None;
+    let mut long_link: Option<String> = None;
+    let mut pax: Vec<(String, String)> = vec![];
+    loop {
+        let mut header = [0u8; BLOCK_LEN];
+        match reader.read_exact(&mut header) {
+            Ok(()) => {}
+            // archives without the two zero blocks at the end are common enough
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(err) => return Err(err.into()),
+        }
+        if header.iter().all(|b| *b == 0) {
+            return Ok(());
+        }
+        if !checksum_matches(&header)? {
+            return Err(anyhow!("[tar::read_tar] invalid header checksum"));
+        }
+
+        let mut size = field_num(&header[124..136])?;
+        let typeflag = header[156];
+        match typeflag {
+            b'L' => {
+                long_path = Some(field_str(&read_data(&mut reader, size)?));
+                continue;
+            }
+            b'K' => {
+                long_link = Some(field_str(&read_data(&mut reader, size)?));
+                continue;
+            }
+            b'x' => {
+                pax = pax_records(&read_data(&mut reader, size)?);
+                continue;
+            }
+            b'g' => {
+                read_data(&mut reader, size)?;
+                continue;
+            }
+            _ => {}
+        }
+
+        let mut path = field_str(&header[0..100]);
+        if &header[257..262] == b"ustar" {
+            let prefix = field_str(&header[345..500]);
+            if !prefix.is_empty() {
+                path = format!("{prefix}/{path}");
+            }
+        }
+        let mut link = field_str(&header[157..257]);
+        let mut mtime = field_num(&header[136..148])? as i64;
+        if let Some(long_path) = long_path.take() {
+            path = long_path;
+        }
+        if let Some(long_link) = long_link.take() {
+            link = long_link;
+        }
+        for (key, value) in pax.drain(..) {
+            match key.as_str() {
+                "path" => path = value,
+                "linkpath" => link = value,
+                "size" => size = value.parse().unwrap_or(size),
+                "mtime" => {
+                    mtime = value
+                        .split('.')
+                        .next()
+                        .and_then(|secs| secs.parse().ok())
+                        .unwrap_or(mtime)
+                }
+                _ => {}
+            }
+        }
+
+        let kind = match typeflag {
+            0 | b'0' | b'7' => TarEntryKind::File,
+            b'5' => TarEntryKind::Dir,
+            b'2' => TarEntryKind::Symlink(link),
+            b'1' => TarEntryKind::Hardlink(link),
+            other => TarEntryKind::Other(other),
+        };
+        // directories and links have no content, whatever their size field says
+        if !matches!(kind, TarEntryKind::File | TarEntryKind::Other(_)) {
+            size = 0;
+        }
+        let entry = TarEntry {
+            path,
+            kind,
+            size,
+            mode: field_num(&header[100..108])? as u32,
+            mtime,
+        };
+
+        let mut content = (&mut reader).take(size);
+        visit(&entry, &mut content).with_context(|| format!("[tar::read_tar] {}", entry.path))?;
+        // whatever the visitor didn't read
+        io::copy(&mut content, &mut io::sink())?;
+        if content.limit() > 0 {
+            return Err(anyhow!(
+                "[tar::read_tar] archive ends inside {}",
+                entry.path
+            ));
+        }
+        skip_padding(&mut reader, size)?;
+    }
+}
+
+/// Build tar archives in tests, `(path, typeflag, content or link target)`.
+#[cfg(test)]
+pub(crate) fn tar_bytes(entries: &[(&str, u8, &[u8])]) -> Vec<u8> {
+    let mut tar = vec![];
+    for (path, typeflag, data) in entries {
+        let mut header = [0u8; BLOCK_LEN];
+        let content: &[u8] = if matches!(typeflag, b'1' | b'2') {
+            header[157..157 + data.len()].copy_from_slice(data);
+            &[]
+        } else {
+            data
+        };
+        header[..path.len()].copy_from_slice(path.as_bytes());
+        header[100..108].copy_from_slice(b"0000644\0");
+        header[124..136].copy_from_slice(format!("{:011o}\0", content.len()).as_bytes());
+        header[136..148].copy_from_slice(format!("{:011o}\0", 1_700_000_000).as_bytes());
+        header[156] = *typeflag;
+        header[257..263].copy_from_slice(b"ustar\0");
+        header[263..265].copy_from_slice(b"00");
+        header[148..156].copy_from_slice(b"        ");
+        let checksum: u64 = header.iter().map(|b| *b as u64).sum();
+        header[148..156].copy_from_slice(format!("{:06o}\0 ", checksum).as_bytes());
+        tar.extend_from_slice(&header);
+        tar.extend_from_slice(content);
+        tar.resize(tar.len().div_ceil(BLOCK_LEN) * BLOCK_LEN, 0);
+    }
+    tar.resize(tar.len() + 2 * BLOCK_LEN, 0);
+    tar
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalized_path() {
+        assert_eq!(normalized_path("./etc/hosts").as_deref(), Some("etc/hosts"));
+        assert_eq!(
+            normalized_path("/etc/../var/log/").as_deref(),
+            Some("var/log")
+        );
+        assert_eq!(normalized_path("../../etc/hosts"), None);
+        assert_eq!(normalized_path("./"), None);
+    }
+
+    #[test]
+    fn test_read_tar() -> anyhow::Result<()> {
+        let long_name = format!("usr/share/{}/README", "very-long-directory-name".repeat(5));
+        // the length of a PAX record includes its own 3 digits
+        let record = format!(" path={long_name}\n");
+        let pax = format!("{}{}", record.len() + 3, record);
+        let tar = tar_bytes(&[
+            ("./etc/", b'5', b""),
+            ("./etc/os-release", b'0', b"ID=alpine\n"),
+            ("././@LongLink", b'L', format!("{long_name}\0").as_bytes()),
+            ("usr/share/truncated", b'0', b"gnu"),
+            ("PaxHeaders/README", b'x', pax.as_bytes()),
+            ("usr/share/README", b'0', b"pax"),
+            ("etc/issue", b'1', b"etc/os-release"),
+            ("bin/sh", b'2', b"/bin/busybox"),
+        ]);
+
+        let mut entries = vec![];
+        read_tar(tar.as_slice(), |entry, content| {
+            let mut text = String::new();
+            // leave the content of links and directories unread
+            if entry.kind == TarEntryKind::File {
+                content.read_to_string(&mut text)?;
+            }
+            entries.push((entry.path.clone(), entry.kind.clone(), text));
+            Ok(())
+        })?;
+        assert_eq!(
+            entries,
+            vec![
+                ("./etc/".to_string(), TarEntryKind::Dir, String::new()),
+                (
+                    "./etc/os-release".to_string(),
+                    TarEntryKind::File,
+                    "ID=alpine\n".to_string()
+                ),
+                (long_name.clone(), TarEntryKind::File, "gnu".to_string()),
+                (long_name, TarEntryKind::File, "pax".to_string()),
+                (
+                    "etc/issue".to_string(),
+                    TarEntryKind::Hardlink("etc/os-release".to_string()),
+                    String::new()
+                ),
+                (
+                    "bin/sh".to_string(),
+                    TarEntryKind::Symlink("/bin/busybox".to_string()),
+                    String::new()
+                ),
+            ]
+        );
+
+        let mut corrupt = tar.clone();
+        corrupt[0] = b'x';
+        assert!(read_tar(corrupt.as_slice(), |_, _| Ok(())).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_oversized_metadata() {
+        // a PAX header claiming 1 GiB must not be allocated or read
+        let mut tar = tar_bytes(&[("PaxHeaders/README", b'x', b"")]);
+        tar[124..136].copy_from_slice(format!("{:011o}\0", 1u64 << 30).as_bytes());
+        tar[148..156].copy_from_slice(b"        ");
+        let checksum: u64 = tar[..BLOCK_LEN].iter().map(|b| *b as u64).sum();
+        tar[148..156].copy_from_slice(format!("{:06o}\0 ", checksum).as_bytes());
+        let err = read_tar(tar.as_slice(), |_, _| Ok(())).unwrap_err();
+        assert!(err.to_string().contains("PAX header"));
+    }
+}
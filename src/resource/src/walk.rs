@@ -0,0 +1,432 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use serde::Serialize;
+
+use crate::access::{walk_access_issue, AccessIssue};
+
+/// The order in which a walk yields the entries of the file system. Depth-first
+/// finishes a subtree before moving to its siblings, which can spend hours in
+/// one huge subtree; breadth-first yields every directory's entries before the
+/// entries of its subdirectories so early results cover the whole tree.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WalkOrder {
+    #[default]
+    DepthFirst,
+    BreadthFirst,
+}
+
+impl std::str::FromStr for WalkOrder {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "depth-first" => Ok(WalkOrder::DepthFirst),
+            "breadth-first" => Ok(WalkOrder::BreadthFirst),
+            _ => Err(anyhow::anyhow!(
+                "unknown walk order `{}`, expected `depth-first` or `breadth-first`",
+                s
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for WalkOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            WalkOrder::DepthFirst => "depth-first",
+            WalkOrder::BreadthFirst => "breadth-first",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct WalkOptions {
+    pub order: WalkOrder,
+    /// only walk the first N entries (by name) of every directory, the rest of a
+    /// sparse or huge directory (and their subtrees) are skipped
+    pub max_dir_entries: Option<usize>,
+}
+
+impl WalkOptions {
+    pub fn is_default(&self) -> bool {
+        *self == WalkOptions::default()
+    }
+}
+
+/// What a walk didn't yield: for every directory which had more than
+/// `max_dir_entries` entries, how many of them were skipped, and the paths
+/// which couldn't be read.
+#[derive(Debug, Default)]
+pub struct WalkReport {
+    pub truncated_dirs: BTreeMap<String, usize>,
+    pub unreadable: Vec<AccessIssue>,
+}
+
+#[derive(Default)]
+pub struct Walked {
+    pub entries: Vec<ignore::DirEntry>,
+    pub report: WalkReport,
+}
+
+/// Walk `root_path` honoring `.gitignore`, `.ignore` and `custom_ignore_filenames`
+/// files the way `ignore::Walk` does, in `options.order`.
+pub fn smart_ignore_walk(
+    root_path: impl AsRef<Path>,
+    ignore_hidden: bool,
+    custom_ignore_filenames: &[String],
+    options: &WalkOptions,
+) -> Walked {
+    let (walk_builder, dir_entries) =
+        smart_ignore_walk_builder(root_path, ignore_hidden, custom_ignore_filenames, options);
+
+    let mut entries = Vec::new();
+    let mut unreadable = Vec::new();
+    for result in walk_builder.build() {
+        match result {
+            Ok(entry) => entries.push(entry),
+            Err(err) => unreadable.extend(walk_access_issue(&err)),
+        }
+    }
+    if options.order == WalkOrder::BreadthFirst {
+        // stable, so entries of the same depth keep the walk's order
+        entries.sort_by_key(|entry| entry.depth());
+    }
+
+    Walked {
+        entries,
+        report: WalkReport {
+            truncated_dirs: truncated_dirs(&dir_entries, options),
+            unreadable,
+        },
+    }
+}
+
+/// Walk `root_path` like `smart_ignore_walk` but only descend to `paths` (and
+/// into the ones which are directories), e.g. the paths file system events were
+/// reported for, so the ignore files of their ancestors still apply without
+/// walking the rest of the tree. Entries of the directories on the way are
+/// yielded too; paths which don't exist anymore are not.
+pub fn smart_ignore_walk_paths(
+    root_path: impl AsRef<Path>,
+    paths: &[PathBuf],
+    ignore_hidden: bool,
+    custom_ignore_filenames: &[String],
+) -> Walked {
+    let (mut walk_builder, _) = smart_ignore_walk_builder(
+        root_path,
+        ignore_hidden,
+        custom_ignore_filenames,
+        &WalkOptions::default(),
+    );
+    let paths = paths.to_vec();
+    walk_builder.filter_entry(move |entry| {
+        !entry.file_type().is_some_and(|ft| ft.is_dir())
+            || paths
+                .iter()
+                .any(|path| path.starts_with(entry.path()) || entry.path().starts_with(path))
+    });
+
+    let mut entries = Vec::new();
+    let mut unreadable = Vec::new();
+    for result in walk_builder.build() {
+        match result {
+            Ok(entry) => entries.push(entry),
+            Err(err) => unreadable.extend(walk_access_issue(&err)),
+        }
+    }
+    Walked {
+        entries,
+        report: WalkReport {
+            truncated_dirs: BTreeMap::new(),
+            unreadable,
+        },
+    }
+}
+
+/// What `spawn_smart_ignore_walk` sends: an entry, or a path which couldn't be read.
+pub type WalkedEntry = Result<ignore::DirEntry, AccessIssue>;
+
+/// Walk like `smart_ignore_walk` on a thread of its own, sending the entries
+/// (and the paths which couldn't be read) as they're walked so that the whole
+/// tree is never held in memory; at most `bound` entries wait for the receiver.
+/// A breadth-first walk can only be sorted once it is complete so its entries
+/// are sent afterwards. Either way a directory is sent before its entries. The
+/// thread stops early when the receiver is dropped and returns the directories
+/// `max_dir_entries` truncated, see `WalkReport::truncated_dirs`.
+pub fn spawn_smart_ignore_walk(
+    root_path: PathBuf,
+    ignore_hidden: bool,
+    custom_ignore_filenames: Vec<String>,
+    options: WalkOptions,
+    bound: usize,
+) -> (Receiver<WalkedEntry>, JoinHandle<BTreeMap<String, usize>>) {
+    let (walked, received) = sync_channel(bound);
+    let walker = std::thread::spawn(move || {
+        if options.order == WalkOrder::BreadthFirst {
+            let Walked { entries, report } =
+                smart_ignore_walk(root_path, ignore_hidden, &custom_ignore_filenames, &options);
+            let unreadable = report.unreadable.into_iter().map(Err);
+            for walked_entry in unreadable.chain(entries.into_iter().map(Ok)) {
+                if walked.send(walked_entry).is_err() {
+                    break;
+                }
+            }
+            return report.truncated_dirs;
+        }
+
+        let (walk_builder, dir_entries) =
+            smart_ignore_walk_builder(root_path, ignore_hidden, &custom_ignore_filenames, &options);
+        'walk: for result in walk_builder.build() {
+            let walked_entries = match result {
+                Ok(entry) => vec![Ok(entry)],
+                Err(err) => walk_access_issue(&err).into_iter().map(Err).collect(),
+            };
+            for walked_entry in walked_entries {
+                if walked.send(walked_entry).is_err() {
+                    break 'walk;
+                }
+            }
+        }
+        truncated_dirs(&dir_entries, &options)
+    });
+    (received, walker)
+}
+
+fn smart_ignore_walk_builder(
+    root_path: impl AsRef<Path>,
+    ignore_hidden: bool,
+    custom_ignore_filenames: &[String],
+    options: &WalkOptions,
+) -> (ignore::WalkBuilder, Arc<Mutex<HashMap<PathBuf, usize>>>) {
+    let mut walk_builder = ignore::WalkBuilder::new(root_path);
+    walk_builder.hidden(ignore_hidden);
+    for cf in custom_ignore_filenames {
+        walk_builder.add_custom_ignore_filename(cf);
+    }
+
+    let dir_entries: Arc<Mutex<HashMap<PathBuf, usize>>> = Default::default();
+    if let Some(max_dir_entries) = options.max_dir_entries {
+        // sorted so the same entries are kept in every walk
+        walk_builder.sort_by_file_name(|a, b| a.cmp(b));
+        let dir_entries = dir_entries.clone();
+        walk_builder.filter_entry(move |entry| {
+            let Some(parent) = entry.path().parent().filter(|_| entry.depth() > 0) else {
+                return true;
+            };
+            let mut dir_entries = dir_entries.lock().unwrap();
+            let count = dir_entries.entry(parent.to_path_buf()).or_default();
+            *count += 1;
+            *count <= max_dir_entries
+        });
+    }
+    (walk_builder, dir_entries)
+}
+
+fn truncated_dirs(
+    dir_entries: &Mutex<HashMap<PathBuf, usize>>,
+    options: &WalkOptions,
+) -> BTreeMap<String, usize> {
+    match options.max_dir_entries {
+        Some(max_dir_entries) => dir_entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, count)| **count > max_dir_entries)
+            .map(|(dir, count)| (dir.to_string_lossy().to_string(), count - max_dir_entries))
+            .collect(),
+        None => BTreeMap::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn relative(root: &Path, walked: &Walked) -> Vec<String> {
+        walked
+            .entries
+            .iter()
+            .filter(|entry| entry.depth() > 0)
+            .map(|entry| {
+                entry
+                    .path()
+                    .strip_prefix(root)
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_breadth_first_bounded_walk() -> anyhow::Result<()> {
+        let root = tempfile::tempdir()?;
+        for dir in ["a/deep/deeper", "b"] {
+            std::fs::create_dir_all(root.path().join(dir))?;
+        }
+        for file in [
+            "a/deep/deeper/x.txt",
+            "a/1.txt",
+            "b/1.txt",
+            "b/2.txt",
+            "b/3.txt",
+        ] {
+            std::fs::write(root.path().join(file), "content")?;
+        }
+
+        let walked = smart_ignore_walk(
+            root.path(),
+            false,
+            &[],
+            &WalkOptions {
+                order: WalkOrder::BreadthFirst,
+                max_dir_entries: None,
+            },
+        );
+        let paths = relative(root.path(), &walked);
+        assert_eq!(paths.len(), 9);
+        let depths: Vec<_> = walked.entries.iter().map(|e| e.depth()).collect();
+        assert!(depths.windows(2).all(|w| w[0] <= w[1]));
+        assert!(walked.report.truncated_dirs.is_empty());
+
+        let walked = smart_ignore_walk(
+            root.path(),
+            false,
+            &[],
+            &WalkOptions {
+                order: WalkOrder::BreadthFirst,
+                max_dir_entries: Some(2),
+            },
+        );
+        // `b` has 3 entries, only the first 2 by name are kept
+        assert_eq!(
+            relative(root.path(), &walked),
+            vec![
+                "a",
+                "b",
+                "a/1.txt",
+                "a/deep",
+                "b/1.txt",
+                "b/2.txt",
+                "a/deep/deeper",
+                "a/deep/deeper/x.txt"
+            ]
+        );
+        let truncated: Vec<_> = walked
+            .report
+            .truncated_dirs
+            .iter()
+            .map(|(dir, skipped)| (Path::new(dir).strip_prefix(root.path()).unwrap(), *skipped))
+            .collect();
+        assert_eq!(truncated, vec![(Path::new("b"), 1)]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_spawned_walk_streams_entries() -> anyhow::Result<()> {
+        let root = tempfile::tempdir()?;
+        std::fs::create_dir_all(root.path().join("a/deep"))?;
+        for file in ["a/deep/x.txt", "a/1.txt", "b.txt", "c.txt"] {
+            std::fs::write(root.path().join(file), "content")?;
+        }
+
+        for order in [WalkOrder::DepthFirst, WalkOrder::BreadthFirst] {
+            let options = WalkOptions {
+                order,
+                max_dir_entries: Some(2),
+            };
+            let (received, walker) = spawn_smart_ignore_walk(
+                root.path().to_path_buf(),
+                false,
+                vec![],
+                options.clone(),
+                1,
+            );
+            let streamed: Vec<_> = received
+                .into_iter()
+                .map(|walked| walked.unwrap().into_path())
+                .collect();
+            let walked = smart_ignore_walk(root.path(), false, &[], &options);
+            let expected: Vec<_> = walked
+                .entries
+                .iter()
+                .map(|e| e.path().to_path_buf())
+                .collect();
+            assert_eq!(streamed, expected);
+            // every directory comes before its entries
+            for (i, path) in streamed.iter().enumerate() {
+                if let Some(parent) = path.parent().filter(|_| path != root.path()) {
+                    assert!(streamed[..i].iter().any(|p| p == parent));
+                }
+            }
+            assert_eq!(walker.join().unwrap(), walked.report.truncated_dirs);
+        }
+
+        // dropping the receiver stops the walk
+        let (received, walker) = spawn_smart_ignore_walk(
+            root.path().to_path_buf(),
+            false,
+            vec![],
+            WalkOptions::default(),
+            1,
+        );
+        drop(received);
+        assert!(walker.join().unwrap().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_walk_order_from_str() {
+        assert_eq!(
+            "breadth-first".parse::<WalkOrder>().unwrap(),
+            WalkOrder::BreadthFirst
+        );
+        assert_eq!(WalkOrder::DepthFirst.to_string(), "depth-first");
+        assert!("bfs".parse::<WalkOrder>().is_err());
+    }
+
+    #[test]
+    fn test_walk_paths_honors_ancestors_ignore_files() -> anyhow::Result<()> {
+        let root = tempfile::tempdir()?;
+        for dir in ["a/deep", "b", "ignored"] {
+            std::fs::create_dir_all(root.path().join(dir))?;
+        }
+        for file in ["a/deep/x.txt", "a/y.txt", "b/1.txt", "ignored/z.txt"] {
+            std::fs::write(root.path().join(file), "content")?;
+        }
+        std::fs::write(root.path().join(".ignore"), "ignored/\n")?;
+
+        let walked = smart_ignore_walk_paths(
+            root.path(),
+            &[
+                root.path().join("a/deep"),
+                root.path().join("b/1.txt"),
+                root.path().join("b/gone.txt"),
+                root.path().join("ignored/z.txt"),
+            ],
+            false,
+            &[],
+        );
+        let mut paths = relative(root.path(), &walked);
+        paths.sort();
+        // `a/y.txt` is a sibling on the way to `a/deep`, `ignored/` isn't walked
+        assert_eq!(
+            paths,
+            vec![
+                ".ignore",
+                "a",
+                "a/deep",
+                "a/deep/x.txt",
+                "a/y.txt",
+                "b",
+                "b/1.txt"
+            ]
+        );
+        Ok(())
+    }
+}
@@ -4,10 +4,18 @@ use async_imap::{
     Session,
 };
 use async_trait::async_trait;
+use common::secret::Secret;
 use futures_util::TryStreamExt;
 use indicatif::{ProgressBar, ProgressStyle};
 use mail_parser::{Message, MessageParser, MimeHeaders, PartType};
-use std::{fmt::Debug, sync::Arc};
+use std::{
+    fmt::Debug,
+    io,
+    pin::Pin,
+    sync::Arc,
+    task::{Context as TaskContext, Poll},
+};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 use tokio::net::TcpStream;
 use tokio_rustls::TlsConnector;
 use tokio_rustls::{
@@ -15,9 +23,12 @@ use tokio_rustls::{
     rustls::{ClientConfig, RootCertStore},
 };
 
-use tracing::debug;
+use tracing::{debug, warn};
 
-use crate::{Attachment, EmailResource, Folder, ImapConfig, ImapResource};
+use crate::{
+    xoauth2::XOAuth2, Attachment, EmailResource, Folder, FolderCheckpoint, ImapConfig, ImapOAuth2,
+    ImapResource,
+};
 
 #[async_trait]
 trait SessionAbstraction: Debug + Send + Sync {
@@ -31,6 +42,9 @@ trait SessionAbstraction: Debug + Send + Sync {
         &mut self,
         sequence_set: &str,
     ) -> anyhow::Result<Vec<Fetch>>;
+    async fn uid_search(&mut self, query: &str) -> anyhow::Result<Vec<u32>>;
+    async fn uid_fetch_messages_from_folder(&mut self, uid_set: &str)
+        -> anyhow::Result<Vec<Fetch>>;
     async fn specified_folders(
         &mut self,
         ref_name: Option<&str>,
@@ -38,9 +52,57 @@ trait SessionAbstraction: Debug + Send + Sync {
     ) -> anyhow::Result<Vec<Folder>>;
 }
 
+/// The connection to the server: TLS, unless `tls` is off for a server on the
+/// loopback interface (e.g. a local bridge or the `admin self-test` server).
+#[derive(Debug)]
+enum ImapStream {
+    Tls(Box<TlsStream<TcpStream>>),
+    Plain(TcpStream),
+}
+
+impl AsyncRead for ImapStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            ImapStream::Tls(stream) => Pin::new(stream).poll_read(cx, buf),
+            ImapStream::Plain(stream) => Pin::new(stream).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for ImapStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            ImapStream::Tls(stream) => Pin::new(stream).poll_write(cx, buf),
+            ImapStream::Plain(stream) => Pin::new(stream).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            ImapStream::Tls(stream) => Pin::new(stream).poll_flush(cx),
+            ImapStream::Plain(stream) => Pin::new(stream).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            ImapStream::Tls(stream) => Pin::new(stream).poll_shutdown(cx),
+            ImapStream::Plain(stream) => Pin::new(stream).poll_shutdown(cx),
+        }
+    }
+}
+
 #[derive(Debug)]
 struct SessionHolder {
-    session: Session<TlsStream<TcpStream>>,
+    session: Session<ImapStream>,
 }
 
 #[async_trait]
@@ -77,18 +139,50 @@ impl SessionAbstraction for SessionHolder {
         &mut self,
         sequence_set: &str,
     ) -> anyhow::Result<Vec<Fetch>> {
-        let messsages_stream = self.session.fetch(sequence_set, "RFC822").await?;
+        let messsages_stream = self.session.fetch(sequence_set, "(UID RFC822)").await?;
         Ok(messsages_stream.try_collect().await?)
     }
+
+    async fn uid_search(&mut self, query: &str) -> anyhow::Result<Vec<u32>> {
+        Ok(self.session.uid_search(query).await?.into_iter().collect())
+    }
+
+    async fn uid_fetch_messages_from_folder(
+        &mut self,
+        uid_set: &str,
+    ) -> anyhow::Result<Vec<Fetch>> {
+        let messsages_stream = self.session.uid_fetch(uid_set, "(UID RFC822)").await?;
+        Ok(messsages_stream.try_collect().await?)
+    }
+}
+
+/// The UIDs after `last_uid`, oldest first and at most `batch_size` of them, so
+/// successive runs catch up with a busy folder. `UID SEARCH n:*` always matches
+/// the last message, even when its UID is below `n`.
+fn uids_after(mut uids: Vec<u32>, last_uid: u32, batch_size: usize) -> Vec<u32> {
+    uids.retain(|uid| *uid > last_uid);
+    uids.sort_unstable();
+    uids.truncate(batch_size);
+    uids
+}
+
+/// How the IMAP session is authenticated.
+#[derive(Debug, Clone)]
+enum ImapCredentials {
+    /// `LOGIN` with the (app) password
+    Password(Secret),
+    /// `AUTHENTICATE XOAUTH2` with an access token
+    OAuth2(ImapOAuth2),
 }
 
 /// This is the default IMAP service that utilizes the `rust-imap` library
 #[derive(Debug)]
 pub struct DefaultImapService {
     username: String,
-    password: String,
+    credentials: ImapCredentials,
     addr: String,
     port: u16,
+    tls: bool,
     batch_size: u64,
     extract_attachments: bool,
     session: Option<Box<dyn SessionAbstraction>>,
@@ -100,9 +194,15 @@ impl DefaultImapService {
     pub fn new(value: ImapConfig) -> Self {
         DefaultImapService {
             username: value.username.expect("Expected username"),
-            password: value.password.expect("Expected Password"),
+            credentials: match (value.oauth2, value.password) {
+                (Some(oauth2), _) => ImapCredentials::OAuth2(oauth2),
+                (None, password) => ImapCredentials::Password(
+                    password.expect("Expected a password or an OAuth2 access token"),
+                ),
+            },
             addr: value.addr.expect("Failed to get address"),
             port: value.port,
+            tls: value.tls,
             batch_size: value.batch_size,
             extract_attachments: value.extract_attachments,
             session: None,
@@ -186,9 +286,16 @@ impl DefaultImapService {
         let body = message
             .body()
             .ok_or_else(|| anyhow!("Message did not have a body"))?;
+        EmailResource::from_rfc822(body, extract_attachments)
+    }
+}
 
+impl EmailResource {
+    /// Parse a raw RFC 822 (MIME) message, as fetched from a mailbox or
+    /// received over SMTP.
+    pub fn from_rfc822(raw: &[u8], extract_attachments: bool) -> anyhow::Result<EmailResource> {
         let message = MessageParser::default()
-            .parse(body)
+            .parse(raw)
             .ok_or_else(|| anyhow!("Failed to parse email message"))?;
 
         let email = EmailResource {
@@ -205,12 +312,12 @@ impl DefaultImapService {
                 })
                 .unwrap_or_default()
                 .to_string(),
-            cc: Self::parse_addresses(message.cc()),
-            bcc: Self::parse_addresses(message.bcc()),
+            cc: DefaultImapService::parse_addresses(message.cc()),
+            bcc: DefaultImapService::parse_addresses(message.bcc()),
             references: vec![],
             in_reply_to: None,
             message_id: message.message_id().unwrap_or_default().to_string(),
-            to: Self::parse_addresses(message.to()),
+            to: DefaultImapService::parse_addresses(message.to()),
             date: message.date().map(|d| d.to_rfc3339()).unwrap_or_default(),
             text_plain: message
                 .text_bodies()
@@ -229,7 +336,7 @@ impl DefaultImapService {
             raw_text: String::from_utf8_lossy(message.raw_message()).into_owned(),
             raw_json: serde_json::to_string(&message)?,
             attachments: if extract_attachments {
-                Some(Self::extract_attachments(&message))
+                Some(DefaultImapService::extract_attachments(&message))
             } else {
                 None
             },
@@ -242,25 +349,44 @@ impl DefaultImapService {
 #[async_trait]
 impl ImapResource for DefaultImapService {
     async fn init(&mut self) -> anyhow::Result<()> {
-        let mut root_store = RootCertStore::empty();
-        root_store.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
-
-        let client_config = ClientConfig::builder()
-            .with_root_certificates(root_store)
-            .with_no_client_auth();
-        let config_ref = Arc::new(client_config);
-        let server_name = self.addr.clone().try_into()?;
-
-        let conn = TlsConnector::from(config_ref.clone());
         let stream = TcpStream::connect(format!("{}:{}", self.addr, self.port)).await?;
-        let tls = conn.connect(server_name, stream).await?;
-        
-        let client = async_imap::Client::new(tls);
+        let stream = if self.tls {
+            let mut root_store = RootCertStore::empty();
+            root_store.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+
+            let client_config = ClientConfig::builder()
+                .with_root_certificates(root_store)
+                .with_no_client_auth();
+            let config_ref = Arc::new(client_config);
+            let server_name = self.addr.clone().try_into()?;
+
+            let conn = TlsConnector::from(config_ref.clone());
+            ImapStream::Tls(Box::new(conn.connect(server_name, stream).await?))
+        } else if stream.peer_addr()?.ip().is_loopback() {
+            ImapStream::Plain(stream)
+        } else {
+            return Err(anyhow!(
+                "{} isn't on the loopback interface, credentials are only sent without TLS to local servers",
+                self.addr
+            ));
+        };
 
-        let session = client
-            .login(&self.username, &self.password)
-            .await
-            .map_err(|e| e.0)?;
+        let client = async_imap::Client::new(stream);
+
+        let session = match &self.credentials {
+            ImapCredentials::Password(password) => client
+                .login(&self.username, password.expose())
+                .await
+                .map_err(|e| e.0)?,
+            ImapCredentials::OAuth2(oauth2) => {
+                let access_token = oauth2.access_token().await?;
+                client
+                    .authenticate("XOAUTH2", XOAuth2::new(&self.username, access_token))
+                    .await
+                    .map_err(|e| e.0)
+                    .with_context(|| format!("XOAUTH2 authentication of {}", self.username))?
+            }
+        };
 
         self.session = Some(Box::new(SessionHolder { session }));
 
@@ -275,6 +401,22 @@ impl ImapResource for DefaultImapService {
         self.username.to_string()
     }
 
+    async fn connection(&self) -> anyhow::Result<Box<dyn ImapResource>> {
+        let mut service = DefaultImapService {
+            username: self.username.clone(),
+            credentials: self.credentials.clone(),
+            addr: self.addr.clone(),
+            port: self.port,
+            tls: self.tls,
+            batch_size: self.batch_size,
+            extract_attachments: self.extract_attachments,
+            session: None,
+            progress: self.progress.as_ref().map(|_| ProgressBar::new_spinner()),
+        };
+        service.init().await?;
+        Ok(Box::new(service))
+    }
+
     async fn folders(&mut self) -> anyhow::Result<Vec<String>> {
         let sess = self.session_mut();
         sess.list_folders(None, Some("*")).await
@@ -309,11 +451,28 @@ impl ImapResource for DefaultImapService {
         let folder_metadata = serde_json::to_value(mailbox.to_string())?;
         folder.metadata(folder_metadata);
 
+        match (folder.resume_from, mailbox.uid_validity) {
+            (Some(resume_from), Some(uid_validity)) if resume_from.uid_validity == uid_validity => {
+                return self
+                    .process_messages_after(folder, resume_from, batch_size as usize)
+                    .await;
+            }
+            (Some(resume_from), uid_validity) => warn!(
+                "UIDVALIDITY of {} changed from {} to {:?}, fetching the latest messages again",
+                folder.name, resume_from.uid_validity, uid_validity
+            ),
+            (None, _) => {}
+        }
+
         let messages_total = mailbox.exists;
 
         debug!("Number of messages in folder: {messages_total}");
         if messages_total == 0 {
             eprintln!("No messages in {} folder", folder.name);
+            folder.checkpoint = mailbox.uid_validity.map(|uid_validity| FolderCheckpoint {
+                uid_validity,
+                last_uid: 0,
+            });
             return Ok(());
         }
 
@@ -323,6 +482,7 @@ impl ImapResource for DefaultImapService {
         // Max number of emails to fetch per batch because of IMAP limitations
         let batch_size = 1000;
         let mut emails = Vec::new();
+        let mut last_uid = 0;
 
         // TODO: do this part outside, fetch only the batch, then refetch if less
         while remaining_emails > 0 {
@@ -348,6 +508,7 @@ impl ImapResource for DefaultImapService {
                 for message in fetched_messages.iter() {
                     let email = Self::convert_to_email_resource(message, extract_attachments)?;
                     emails.push(email);
+                    last_uid = last_uid.max(message.uid.unwrap_or_default());
                 }
             }
 
@@ -371,7 +532,87 @@ impl ImapResource for DefaultImapService {
             ));
         }
         folder.messages(emails);
+        folder.checkpoint = mailbox.uid_validity.map(|uid_validity| FolderCheckpoint {
+            uid_validity,
+            last_uid,
+        });
 
         Ok(())
     }
 }
+
+impl DefaultImapService {
+    /// Fetch the messages of the selected folder which arrived after `resume_from`.
+    async fn process_messages_after(
+        &mut self,
+        folder: &mut crate::Folder,
+        resume_from: FolderCheckpoint,
+        batch_size: usize,
+    ) -> anyhow::Result<()> {
+        let extract_attachments = self.extract_attachments;
+        let uids = {
+            let sess = self.session_mut();
+            let found = sess
+                .uid_search(&format!("UID {}:*", resume_from.last_uid + 1))
+                .await?;
+            uids_after(found, resume_from.last_uid, batch_size)
+        };
+        debug!(
+            "{} new messages in {} after UID {}",
+            uids.len(),
+            folder.name,
+            resume_from.last_uid
+        );
+
+        let mut emails = Vec::with_capacity(uids.len());
+        // Max number of emails to fetch per batch because of IMAP limitations
+        for batch in uids.chunks(1000) {
+            self.update_progress(
+                format!("Fetching {} new messages from {}", batch.len(), folder.name),
+                false,
+            )?;
+            let uid_set = batch
+                .iter()
+                .map(u32::to_string)
+                .collect::<Vec<_>>()
+                .join(",");
+            let sess = self.session_mut();
+            for message in sess.uid_fetch_messages_from_folder(&uid_set).await?.iter() {
+                emails.push(Self::convert_to_email_resource(
+                    message,
+                    extract_attachments,
+                )?);
+            }
+        }
+
+        if let Some(spinner) = &self.progress {
+            spinner.finish_with_message(format!(
+                "Fetched {} new messages from {} folder successfully",
+                emails.len(),
+                folder.name
+            ));
+        }
+        folder.messages(emails);
+        folder.checkpoint = Some(FolderCheckpoint {
+            last_uid: uids.last().copied().unwrap_or(resume_from.last_uid),
+            ..resume_from
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resumed_uids() {
+        // `UID SEARCH UID 43:*` with no new message still answers the last one
+        assert_eq!(uids_after(vec![42], 42, 10), Vec::<u32>::new());
+        assert_eq!(
+            uids_after(vec![50, 43, 61, 44], 42, 10),
+            vec![43, 44, 50, 61]
+        );
+        assert_eq!(uids_after(vec![50, 43, 61, 44], 42, 2), vec![43, 44]);
+    }
+}
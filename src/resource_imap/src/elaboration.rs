@@ -21,6 +21,9 @@ pub struct ImapElaboration {
     pub folders_available: Vec<String>,
     /// All the folders ingested
     pub folders_ingested: Vec<String>,
+    /// Message volume per day, top senders, attachment types and volume spikes
+    /// compared to previous sessions, also in `ur_ingest_session_imap_acct_stat`
+    pub mailbox_stats: Option<serde_json::Value>,
 }
 
 impl ImapElaboration {
@@ -33,6 +36,7 @@ impl ImapElaboration {
             folders: HashMap::new(),
             folders_available: vec![],
             folders_ingested: vec![],
+            mailbox_stats: None,
         }
     }
 }
@@ -50,6 +54,8 @@ pub struct FolderElaboration {
     pub text_plain_count: usize,
     /// Total number of text/html content encountered for all emails in the folder
     pub html_content_count: usize,
+    /// Total number of attachments stored for all emails in the folder
+    pub attachment_count: usize,
 }
 
 impl FolderElaboration {
@@ -60,6 +66,7 @@ impl FolderElaboration {
             folder_process_duration: None,
             text_plain_count: 0,
             html_content_count: 0,
+            attachment_count: 0,
         }
     }
 }
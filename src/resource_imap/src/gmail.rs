@@ -0,0 +1,419 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use base64::{
+    alphabet,
+    engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
+    Engine,
+};
+use common::{
+    oauth2::{OAuth2Client, OAuth2Flow, OAuth2Provider, GMAIL_SCOPES},
+    secret::Secret,
+};
+use futures_util::future::join_all;
+use indicatif::ProgressBar;
+use reqwest::{header::RETRY_AFTER, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Semaphore;
+use tracing::debug;
+
+use crate::{
+    EmailResource, Folder, ImapConfig, ImapResource, Microsoft365AuthServerConfig,
+    TokenGenerationMethod,
+};
+
+const GMAIL_API_URL: &str = "https://gmail.googleapis.com/gmail/v1/users/me";
+/// Largest page of message IDs the API returns
+const MAX_PAGE_SIZE: usize = 500;
+/// Times a throttled (or failing) request is retried
+const MAX_RETRIES: u32 = 5;
+/// Labels which don't hold emails
+const SKIPPED_LABELS: [&str; 1] = ["CHAT"];
+
+/// Gmail sends the raw messages as URL-safe base64, padded or not.
+const RAW_MESSAGE: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::URL_SAFE,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// Credentials of the Google Cloud OAuth client `surveilr` gets an
+/// `access_token` with on behalf of the user.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GmailConfig {
+    /// Client ID of the OAuth client from the Google Cloud console
+    pub client_id: String,
+    /// Client Secret of the OAuth client, Google requires it for device code too
+    pub client_secret: Secret,
+    /// The mode to generate an access_token. Default is 'DeviceCode'.
+    pub mode: TokenGenerationMethod,
+    /// Address to start the authentication server on. Used by the AuthCode mode
+    pub auth_server: Option<Microsoft365AuthServerConfig>,
+}
+
+/// A label as listed, the counts are only returned for a single label.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Label {
+    id: String,
+    name: String,
+    #[serde(rename = "type")]
+    label_type: Option<String>,
+    messages_total: Option<usize>,
+    messages_unread: Option<usize>,
+    threads_total: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LabelList {
+    #[serde(default)]
+    labels: Vec<Label>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MessageRef {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MessageRefList {
+    #[serde(default)]
+    messages: Vec<MessageRef>,
+    next_page_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawMessage {
+    raw: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Profile {
+    email_address: String,
+}
+
+/// Using the Gmail REST API, for Google Workspace mailboxes where IMAP is
+/// disabled by the administrator. Labels are the folders and the messages are
+/// fetched in their raw (RFC 822) form, so they're parsed, attachments
+/// included, exactly like the ones fetched over IMAP.
+#[derive(Debug, Clone)]
+pub struct GmailApiResource {
+    client_id: String,
+    client_secret: Secret,
+    /// The mode to generate an access_token. Default is 'DeviceCode'.
+    mode: TokenGenerationMethod,
+    /// Address to start the authentication server on, for the AuthCode mode
+    auth_server: Option<Microsoft365AuthServerConfig>,
+    access_token: Option<Secret>,
+    /// Address of the mailbox, from the profile of the signed in user
+    email_address: Option<String>,
+    http: reqwest::Client,
+    batch_size: usize,
+    progress: Option<ProgressBar>,
+    extract_attachments: bool,
+    /// Bounds the message downloads of all the connections
+    download_permits: Arc<Semaphore>,
+}
+
+impl GmailApiResource {
+    pub fn new(gmail: &GmailConfig, config: &ImapConfig) -> Self {
+        GmailApiResource {
+            client_id: gmail.client_id.clone(),
+            client_secret: gmail.client_secret.clone(),
+            mode: gmail.mode.clone(),
+            auth_server: gmail.auth_server.clone(),
+            access_token: None,
+            email_address: None,
+            http: reqwest::Client::new(),
+            batch_size: config.batch_size as usize,
+            progress: if config.progress {
+                Some(ProgressBar::new_spinner())
+            } else {
+                None
+            },
+            extract_attachments: config.extract_attachments,
+            download_permits: Arc::new(Semaphore::new(config.attachment_concurrency.max(1))),
+        }
+    }
+
+    fn oauth_client(&self) -> OAuth2Client {
+        OAuth2Client::new(
+            OAuth2Provider::google(),
+            &self.client_id,
+            Some(self.client_secret.clone()),
+            &GMAIL_SCOPES,
+        )
+    }
+
+    fn oauth_flow(&self) -> anyhow::Result<OAuth2Flow> {
+        match self.mode {
+            TokenGenerationMethod::AuthCode => {
+                let server_config = self
+                    .auth_server
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("Server config absent"))?;
+                Ok(OAuth2Flow::AuthCode {
+                    redirect_uri: format!("{}{}", server_config.addr, server_config.base_url),
+                    port: server_config.port,
+                })
+            }
+            TokenGenerationMethod::DeviceCode => Ok(OAuth2Flow::DeviceCode),
+        }
+    }
+
+    /// Send a GET request to the Gmail API, retrying throttled and failed
+    /// requests.
+    async fn get<T: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> anyhow::Result<T> {
+        let access_token = self
+            .access_token
+            .as_ref()
+            .ok_or_else(|| anyhow!("Access token should be present"))?;
+        let url = format!("{GMAIL_API_URL}/{path}");
+        let mut attempt = 0;
+        loop {
+            let request = self
+                .http
+                .get(&url)
+                .query(query)
+                .bearer_auth(access_token.expose());
+            let (failure, wait) = match request.send().await {
+                Ok(res) if res.status().is_success() => {
+                    return res.json().await.with_context(|| {
+                        format!("[ingest_imap]: gmail. Deserializing {} failed", url)
+                    })
+                }
+                Ok(res)
+                    if res.status() == StatusCode::TOO_MANY_REQUESTS
+                        || res.status().is_server_error() =>
+                {
+                    (res.status().to_string(), retry_after(&res))
+                }
+                Ok(res) => {
+                    return Err(anyhow!(
+                        "[ingest_imap]: gmail. GET {} failed: {} {}",
+                        url,
+                        res.status(),
+                        res.text().await.unwrap_or_default()
+                    ))
+                }
+                Err(err) => (err.to_string(), None),
+            };
+            attempt += 1;
+            if attempt > MAX_RETRIES {
+                return Err(anyhow!(
+                    "[ingest_imap]: gmail. GET {} failed after {} attempts: {}",
+                    url,
+                    attempt,
+                    failure
+                ));
+            }
+            let wait = wait.unwrap_or_else(|| Duration::from_millis(500 * 2u64.pow(attempt)));
+            debug!("GET {url} failed ({failure}), retrying in {wait:?}");
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    async fn labels(&self) -> anyhow::Result<Vec<Label>> {
+        let list: LabelList = self.get("labels", &[]).await?;
+        Ok(list.labels)
+    }
+
+    /// The IDs of the latest `max` messages with the label `label_id`.
+    async fn message_ids(&self, label_id: &str, max: usize) -> anyhow::Result<Vec<String>> {
+        let mut ids = Vec::new();
+        let mut page_token = None;
+        while ids.len() < max {
+            let mut query = vec![
+                ("labelIds", label_id.to_string()),
+                (
+                    "maxResults",
+                    (max - ids.len()).min(MAX_PAGE_SIZE).to_string(),
+                ),
+            ];
+            if let Some(token) = page_token.take() {
+                query.push(("pageToken", token));
+            }
+            let page: MessageRefList = self.get("messages", &query).await?;
+            ids.extend(page.messages.into_iter().map(|m| m.id));
+            match page.next_page_token {
+                Some(token) => page_token = Some(token),
+                None => break,
+            }
+        }
+        ids.truncate(max);
+        Ok(ids)
+    }
+
+    async fn message(&self, id: &str) -> anyhow::Result<EmailResource> {
+        let message: RawMessage = {
+            let _permit = self.download_permits.acquire().await?;
+            self.get(&format!("messages/{id}"), &[("format", "raw".to_string())])
+                .await?
+        };
+        parse_raw_message(&message.raw, self.extract_attachments)
+            .with_context(|| format!("[ingest_imap]: gmail. Parsing message {} failed", id))
+    }
+
+    fn update_progress(&self, message: String) {
+        if let Some(spinner) = &self.progress {
+            spinner.set_message(message);
+            spinner.tick();
+        }
+    }
+}
+
+fn parse_raw_message(raw: &str, extract_attachments: bool) -> anyhow::Result<EmailResource> {
+    let raw = RAW_MESSAGE.decode(raw.trim())?;
+    EmailResource::from_rfc822(&raw, extract_attachments)
+}
+
+/// Labels are matched like Microsoft 365 folders: `*` or a case-insensitive
+/// part of the name.
+fn matches_folder(label_name: &str, pattern: &str) -> bool {
+    !SKIPPED_LABELS.contains(&label_name)
+        && (pattern == "*" || label_name.to_lowercase().contains(&pattern.to_lowercase()))
+}
+
+/// How long Gmail asked to wait before retrying a throttled request.
+fn retry_after(response: &Response) -> Option<Duration> {
+    response
+        .headers()
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+#[async_trait]
+impl ImapResource for GmailApiResource {
+    async fn init(&mut self) -> anyhow::Result<()> {
+        // a token cached by an earlier session (or `admin credentials oauth`) is reused
+        let access_token = self
+            .oauth_client()
+            .token(&self.oauth_flow()?)
+            .await?
+            .access_token;
+        self.access_token = Some(access_token);
+
+        let profile: Profile = self.get("profile", &[]).await?;
+        self.email_address = Some(profile.email_address);
+
+        Ok(())
+    }
+
+    fn username(&mut self) -> String {
+        self.email_address
+            .clone()
+            .unwrap_or_else(|| self.client_id.to_string())
+    }
+
+    async fn connection(&self) -> anyhow::Result<Box<dyn ImapResource>> {
+        // reuse the access token so that the user is not asked to authenticate again
+        if self.access_token.is_none() {
+            return Err(anyhow!("Access token should be present"));
+        }
+        let mut resource = self.clone();
+        resource.progress = self.progress.as_ref().map(|_| ProgressBar::new_spinner());
+        Ok(Box::new(resource))
+    }
+
+    async fn folders(&mut self) -> anyhow::Result<Vec<String>> {
+        Ok(self.labels().await?.into_iter().map(|l| l.name).collect())
+    }
+
+    async fn specified_folders(&mut self, folder_pattern: &str) -> anyhow::Result<Vec<Folder>> {
+        let mut folders = Vec::new();
+        for label in self.labels().await? {
+            if !matches_folder(&label.name, folder_pattern) {
+                continue;
+            }
+            // the list has no counts, empty labels are left out like empty folders
+            let label: Label = self.get(&format!("labels/{}", label.id), &[]).await?;
+            if label.messages_total.unwrap_or_default() == 0 {
+                continue;
+            }
+            let mut folder = Folder::from(label.name.to_string());
+            folder.metadata(serde_json::to_value(&label)?);
+            folders.push(folder);
+        }
+        Ok(folders)
+    }
+
+    async fn process_messages_in_folder(&mut self, folder: &mut Folder) -> anyhow::Result<()> {
+        let label_id = match folder.metadata.get("id").and_then(|id| id.as_str()) {
+            Some(id) => id.to_string(),
+            None => self
+                .labels()
+                .await?
+                .into_iter()
+                .find(|l| l.name == folder.name)
+                .map(|l| l.id)
+                .ok_or_else(|| anyhow!("[ingest_imap]: gmail. No {} label", folder.name))?,
+        };
+
+        self.update_progress(format!("Listing messages of label: {}", folder.name));
+        let ids = self.message_ids(&label_id, self.batch_size).await?;
+        debug!("Number of messages in label {}: {}", folder.name, ids.len());
+
+        self.update_progress(format!(
+            "Fetching {} messages from {}",
+            ids.len(),
+            folder.name
+        ));
+        let messages = join_all(ids.iter().map(|id| self.message(id)))
+            .await
+            .into_iter()
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        if let Some(spinner) = &self.progress {
+            spinner.finish_with_message(format!(
+                "Fetched {} from {} label successfully",
+                messages.len(),
+                folder.name
+            ));
+        }
+        folder.messages(messages);
+
+        Ok(())
+    }
+
+    fn progress(&mut self) -> bool {
+        self.progress.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raw_messages() -> anyhow::Result<()> {
+        let raw = "From: alice@example.com\r\nTo: bob@example.com\r\nSubject: Quarterly evidence\r\nMessage-ID: <1@example.com>\r\n\r\nAttached.\r\n";
+        let unpadded = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(raw);
+        let email = parse_raw_message(&unpadded, true)?;
+        assert_eq!(email.subject, "Quarterly evidence");
+        assert_eq!(email.from, "alice@example.com");
+        assert_eq!(email.message_id, "1@example.com");
+
+        let padded = base64::engine::general_purpose::URL_SAFE.encode(raw);
+        assert_eq!(parse_raw_message(&padded, false)?.to, ["bob@example.com"]);
+        Ok(())
+    }
+
+    #[test]
+    fn label_patterns() {
+        assert!(matches_folder("INBOX", "*"));
+        assert!(matches_folder("Audits/2024", "audits"));
+        assert!(!matches_folder("SENT", "inbox"));
+        assert!(!matches_folder("CHAT", "*"));
+    }
+}
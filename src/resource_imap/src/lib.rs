@@ -1,14 +1,22 @@
 use async_trait::async_trait;
+use common::secret::Secret;
 use serde::{Deserialize, Serialize};
 
 mod default_imap_service;
 pub mod elaboration;
+mod gmail;
+pub mod mock_server;
 mod msft;
+mod xoauth2;
 
+pub use gmail::GmailConfig;
 pub use msft::{Microsoft365AuthServerConfig, TokenGenerationMethod, Microsoft365Config};
+pub use xoauth2::ImapOAuth2;
 use tracing::debug;
 
-use crate::{default_imap_service::DefaultImapService, msft::MicrosoftImapResource};
+use crate::{
+    default_imap_service::DefaultImapService, gmail::GmailApiResource, msft::MicrosoftImapResource,
+};
 
 #[async_trait]
 pub trait ImapResource {
@@ -24,14 +32,17 @@ pub trait ImapResource {
     async fn process_messages_in_folder(&mut self, folder: &mut Folder) -> anyhow::Result<()>;
     /// Username of the IMAP server
     fn username(&mut self) -> String;
+    /// Open another, independently initialized connection to the same mailbox so that
+    /// folders can be fetched concurrently
+    async fn connection(&self) -> anyhow::Result<Box<dyn ImapResource>>;
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Attachment {
-    filename: String,
-    content_type: String,
-    content: Vec<u8>,
-    content_id: String,
+    pub filename: String,
+    pub content_type: String,
+    pub content: Vec<u8>,
+    pub content_id: String,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -49,21 +60,58 @@ pub struct EmailResource {
     pub text_html: Vec<String>,
     pub raw_text: String,
     pub raw_json: String,
-    attachments: Option<Vec<Attachment>>,
+    pub attachments: Option<Vec<Attachment>>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ImapConfig {
     pub username: Option<String>,
-    pub password: Option<String>,
+    pub password: Option<Secret>,
+    /// Authenticate with XOAUTH2 instead of the password
+    pub oauth2: Option<ImapOAuth2>,
     pub addr: Option<String>,
     pub port: u16,
+    /// Connect over TLS, only servers on the loopback interface may be
+    /// reached without it
+    pub tls: bool,
     pub folder: String,
     pub mailboxes: Vec<String>,
     pub batch_size: u64,
     pub extract_attachments: bool,
     pub microsoft365: Option<Microsoft365Config>,
+    /// Use the Gmail API instead of IMAP
+    pub gmail: Option<GmailConfig>,
     pub progress: bool,
+    pub folder_concurrency: usize,
+    pub attachment_concurrency: usize,
+}
+
+impl ImapConfig {
+    /// Replace the password, access token and client secrets given as
+    /// references (`env://`, `keyring://`, `vault://`) by their values.
+    pub fn resolve_secrets(&mut self) -> anyhow::Result<()> {
+        if let Some(password) = &self.password {
+            self.password = Some(password.resolve()?);
+        }
+        if let Some(ImapOAuth2::AccessToken(token)) = &self.oauth2 {
+            self.oauth2 = Some(ImapOAuth2::AccessToken(token.resolve()?));
+        }
+        if let Some(microsoft365) = &mut self.microsoft365 {
+            microsoft365.client_secret = microsoft365.client_secret.resolve()?;
+        }
+        if let Some(gmail) = &mut self.gmail {
+            gmail.client_secret = gmail.client_secret.resolve()?;
+        }
+        Ok(())
+    }
+}
+
+/// Where an ingestion stopped in a folder: the folder's UIDVALIDITY and the
+/// highest UID fetched. UIDs are only comparable while UIDVALIDITY is unchanged.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FolderCheckpoint {
+    pub uid_validity: u32,
+    pub last_uid: u32,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -71,6 +119,11 @@ pub struct Folder {
     pub name: String,
     pub metadata: serde_json::Value,
     pub messages: Vec<EmailResource>,
+    /// Only fetch the messages after this checkpoint, when the folder's
+    /// UIDVALIDITY still matches it
+    pub resume_from: Option<FolderCheckpoint>,
+    /// The checkpoint of the fetched messages, for services with UIDs (IMAP)
+    pub checkpoint: Option<FolderCheckpoint>,
 }
 
 impl From<String> for Folder {
@@ -79,6 +132,8 @@ impl From<String> for Folder {
             name: value,
             metadata: serde_json::Value::Null,
             messages: vec![],
+            resume_from: None,
+            checkpoint: None,
         }
     }
 }
@@ -96,6 +151,9 @@ impl Folder {
 pub async fn imap(config: &ImapConfig) -> anyhow::Result<Box<dyn ImapResource>> {
     debug!("{config:#?}");
 
+    if let Some(gmail) = &config.gmail {
+        return Ok(Box::new(GmailApiResource::new(gmail, config)));
+    }
     Ok(match &config.microsoft365 {
         Some(microsoft) => {
             let mut msft_resource = MicrosoftImapResource::new(
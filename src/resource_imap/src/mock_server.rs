@@ -0,0 +1,372 @@
+use std::{io, net::SocketAddr, sync::Arc};
+
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+use tracing::debug;
+
+/// A folder of the mock server, its messages have the UIDs 1, 2, 3...
+#[derive(Debug, Clone)]
+pub struct MockImapFolder {
+    pub name: String,
+    pub uid_validity: u32,
+    /// RFC 822 messages
+    pub messages: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+struct MockMailbox {
+    username: String,
+    password: String,
+    folders: Vec<MockImapFolder>,
+}
+
+/// A plain-text IMAP server on the loopback interface serving a fixed, read-only
+/// mailbox: just enough of IMAP4rev1 (`LOGIN`, `LIST`, `SELECT`, `FETCH`,
+/// `UID SEARCH` and `UID FETCH`) for `DefaultImapService` to ingest it, so
+/// IMAP ingestion can be exercised without a mail server (`admin self-test`).
+/// The server stops when dropped.
+#[derive(Debug)]
+pub struct MockImapServer {
+    addr: SocketAddr,
+    accepting: JoinHandle<()>,
+}
+
+impl MockImapServer {
+    pub async fn start(
+        username: &str,
+        password: &str,
+        folders: Vec<MockImapFolder>,
+    ) -> io::Result<MockImapServer> {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await?;
+        let addr = listener.local_addr()?;
+        let mailbox = Arc::new(MockMailbox {
+            username: username.to_string(),
+            password: password.to_string(),
+            folders,
+        });
+        let accepting = tokio::spawn(async move {
+            while let Ok((stream, client)) = listener.accept().await {
+                let mailbox = mailbox.clone();
+                tokio::spawn(async move {
+                    if let Err(err) = serve(stream, &mailbox).await {
+                        debug!("[MockImapServer] connection from {client}: {err}");
+                    }
+                });
+            }
+        });
+        Ok(MockImapServer { addr, accepting })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+impl Drop for MockImapServer {
+    fn drop(&mut self) {
+        self.accepting.abort();
+    }
+}
+
+async fn serve(stream: TcpStream, mailbox: &MockMailbox) -> io::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    writer
+        .write_all(b"* OK [CAPABILITY IMAP4rev1] surveilr mock IMAP server ready\r\n")
+        .await?;
+
+    let mut authenticated = false;
+    let mut selected: Option<&MockImapFolder> = None;
+    while let Some(line) = lines.next_line().await? {
+        let (tag, command) = line.split_once(' ').unwrap_or((&line, ""));
+        let (verb, arguments) = command.split_once(' ').unwrap_or((command, ""));
+        let verb = verb.to_ascii_uppercase();
+        let mut response = Vec::new();
+        match verb.as_str() {
+            "CAPABILITY" => {
+                response.extend(b"* CAPABILITY IMAP4rev1\r\n");
+                response.extend(format!("{tag} OK CAPABILITY completed\r\n").bytes());
+            }
+            "NOOP" => response.extend(format!("{tag} OK NOOP completed\r\n").bytes()),
+            "LOGOUT" => {
+                response.extend(b"* BYE logging out\r\n");
+                response.extend(format!("{tag} OK LOGOUT completed\r\n").bytes());
+                writer.write_all(&response).await?;
+                return Ok(());
+            }
+            "LOGIN" => match arguments_list(arguments).as_slice() {
+                [username, password]
+                    if *username == mailbox.username && *password == mailbox.password =>
+                {
+                    authenticated = true;
+                    response.extend(format!("{tag} OK LOGIN completed\r\n").bytes());
+                }
+                _ => response.extend(
+                    format!("{tag} NO [AUTHENTICATIONFAILED] Invalid credentials\r\n").bytes(),
+                ),
+            },
+            _ if !authenticated => {
+                response.extend(format!("{tag} NO Not authenticated\r\n").bytes())
+            }
+            "LIST" => {
+                let pattern = arguments_list(arguments).pop().unwrap_or_default();
+                for folder in &mailbox.folders {
+                    if matches_pattern(&pattern, &folder.name) {
+                        response.extend(
+                            format!("* LIST (\\HasNoChildren) \"/\" \"{}\"\r\n", folder.name)
+                                .bytes(),
+                        );
+                    }
+                }
+                response.extend(format!("{tag} OK LIST completed\r\n").bytes());
+            }
+            "SELECT" | "EXAMINE" => {
+                let name = arguments_list(arguments).pop().unwrap_or_default();
+                selected = mailbox.folders.iter().find(|folder| folder.name == name);
+                match selected {
+                    Some(folder) => {
+                        let exists = folder.messages.len();
+                        response.extend(
+                            format!(
+                                "* FLAGS (\\Seen \\Answered \\Flagged \\Deleted \\Draft)\r\n\
+                                 * {exists} EXISTS\r\n\
+                                 * 0 RECENT\r\n\
+                                 * OK [UIDVALIDITY {}] UIDs valid\r\n\
+                                 * OK [UIDNEXT {}] Predicted next UID\r\n\
+                                 {tag} OK [READ-ONLY] {verb} completed\r\n",
+                                folder.uid_validity,
+                                exists + 1
+                            )
+                            .bytes(),
+                        );
+                    }
+                    None => response.extend(format!("{tag} NO No such mailbox\r\n").bytes()),
+                }
+            }
+            // sequence numbers and UIDs are the same since messages are never expunged
+            "FETCH" | "UID" => {
+                let (verb, arguments) = match verb.as_str() {
+                    "UID" => {
+                        let (uid_verb, arguments) =
+                            arguments.split_once(' ').unwrap_or((arguments, ""));
+                        (format!("UID {}", uid_verb.to_ascii_uppercase()), arguments)
+                    }
+                    _ => (verb.clone(), arguments),
+                };
+                match (selected, verb.as_str()) {
+                    (None, _) => {
+                        response.extend(format!("{tag} NO No mailbox selected\r\n").bytes())
+                    }
+                    (Some(folder), "FETCH" | "UID FETCH") => {
+                        let set = arguments.split(' ').next().unwrap_or_default();
+                        for uid in message_set(set, folder.messages.len() as u32) {
+                            let message = &folder.messages[uid as usize - 1];
+                            response.extend(
+                                format!(
+                                    "* {uid} FETCH (UID {uid} RFC822 {{{}}}\r\n",
+                                    message.len()
+                                )
+                                .bytes(),
+                            );
+                            response.extend(message);
+                            response.extend(b")\r\n");
+                        }
+                        response.extend(format!("{tag} OK {verb} completed\r\n").bytes());
+                    }
+                    (Some(folder), "UID SEARCH") => {
+                        let criteria = arguments.trim();
+                        let set = criteria
+                            .strip_prefix("UID ")
+                            .unwrap_or(if criteria == "ALL" { "1:*" } else { criteria });
+                        let uids = message_set(set, folder.messages.len() as u32)
+                            .iter()
+                            .map(u32::to_string)
+                            .collect::<Vec<_>>();
+                        response.extend(format!("* SEARCH {}\r\n", uids.join(" ")).bytes());
+                        response.extend(format!("{tag} OK {verb} completed\r\n").bytes());
+                    }
+                    (Some(_), _) => response.extend(
+                        format!("{tag} BAD {verb} isn't supported by the mock server\r\n").bytes(),
+                    ),
+                }
+            }
+            _ => response
+                .extend(format!("{tag} BAD {verb} isn't supported by the mock server\r\n").bytes()),
+        }
+        writer.write_all(&response).await?;
+    }
+    Ok(())
+}
+
+/// The quoted strings and atoms of a command's arguments.
+fn arguments_list(arguments: &str) -> Vec<String> {
+    let mut list = Vec::new();
+    let mut chars = arguments.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            ' ' => {}
+            '"' => {
+                let mut quoted = String::new();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => quoted.extend(chars.next()),
+                        '"' => break,
+                        c => quoted.push(c),
+                    }
+                }
+                list.push(quoted);
+            }
+            c => {
+                let mut atom = String::from(c);
+                while let Some(c) = chars.next_if(|c| *c != ' ') {
+                    atom.push(c);
+                }
+                list.push(atom);
+            }
+        }
+    }
+    list
+}
+
+/// `LIST` patterns, `*` and `%` match any characters.
+fn matches_pattern(pattern: &str, name: &str) -> bool {
+    match pattern.find(['*', '%']) {
+        None => pattern == name,
+        Some(wildcard) => {
+            let (prefix, rest) = (&pattern[..wildcard], &pattern[wildcard + 1..]);
+            name.starts_with(prefix)
+                && (prefix.len()..=name.len()).any(|start| {
+                    name.is_char_boundary(start) && matches_pattern(rest, &name[start..])
+                })
+        }
+    }
+}
+
+/// The messages (1 to `last`) of a set such as `2:4,7` or `5:*`. As in IMAP, a
+/// range beyond the last message still includes it (`9:*` is `last:9`).
+fn message_set(set: &str, last: u32) -> Vec<u32> {
+    let number = |n: &str| match n {
+        "*" => Some(last),
+        n => n.parse::<u32>().ok(),
+    };
+    let mut messages = Vec::new();
+    for range in set.split(',') {
+        let (from, to) = range.split_once(':').unwrap_or((range, range));
+        if let (Some(from), Some(to)) = (number(from), number(to)) {
+            let (from, to) = (from.min(to).max(1), from.max(to).min(last));
+            messages.extend(from..=to);
+        }
+    }
+    messages.sort_unstable();
+    messages.dedup();
+    messages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{imap, Folder, FolderCheckpoint, ImapConfig};
+    use common::secret::Secret;
+
+    fn message(subject: &str) -> Vec<u8> {
+        format!(
+            "From: auditor@example.com\r\nTo: ops@example.com\r\nSubject: {subject}\r\n\
+             Date: Tue, 1 Oct 2024 10:00:00 +0000\r\nMessage-ID: <{subject}@example.com>\r\n\r\n\
+             {subject} body\r\n"
+        )
+        .into_bytes()
+    }
+
+    #[test]
+    fn message_sets() {
+        assert_eq!(message_set("1:3", 5), vec![1, 2, 3]);
+        assert_eq!(message_set("4:*", 5), vec![4, 5]);
+        assert_eq!(message_set("9:*", 5), vec![5]);
+        assert_eq!(message_set("2,5,1", 5), vec![1, 2, 5]);
+        assert!(message_set("1:3", 0).is_empty());
+        assert!(matches_pattern("*", "INBOX"));
+        assert!(matches_pattern("IN%", "INBOX"));
+        assert!(!matches_pattern("Sent", "INBOX"));
+        assert_eq!(
+            arguments_list(r#""user@example.com" "p\"w" INBOX"#),
+            vec!["user@example.com", "p\"w", "INBOX"]
+        );
+    }
+
+    #[tokio::test]
+    async fn ingest_from_mock_server() -> anyhow::Result<()> {
+        let server = MockImapServer::start(
+            "ops@example.com",
+            "secret",
+            vec![
+                MockImapFolder {
+                    name: "INBOX".to_string(),
+                    uid_validity: 7,
+                    messages: vec![message("first"), message("second")],
+                },
+                MockImapFolder {
+                    name: "Archive".to_string(),
+                    uid_validity: 9,
+                    messages: vec![],
+                },
+            ],
+        )
+        .await?;
+        let config = |password: &str| ImapConfig {
+            username: Some("ops@example.com".to_string()),
+            password: Some(Secret::new(password)),
+            oauth2: None,
+            addr: Some("127.0.0.1".to_string()),
+            port: server.addr().port(),
+            tls: false,
+            folder: "*".to_string(),
+            mailboxes: vec![],
+            batch_size: 100,
+            extract_attachments: false,
+            microsoft365: None,
+            gmail: None,
+            progress: false,
+            folder_concurrency: 1,
+            attachment_concurrency: 1,
+        };
+
+        assert!(imap(&config("wrong")).await?.init().await.is_err());
+
+        let mut resource = imap(&config("secret")).await?;
+        resource.init().await?;
+        let mut folders = resource.specified_folders("*").await?;
+        assert_eq!(
+            folders.iter().map(|f| f.name.as_str()).collect::<Vec<_>>(),
+            vec!["INBOX", "Archive"]
+        );
+        let inbox = &mut folders[0];
+        resource.process_messages_in_folder(inbox).await?;
+        let mut subjects = inbox
+            .messages
+            .iter()
+            .map(|m| m.subject.clone())
+            .collect::<Vec<_>>();
+        subjects.sort();
+        assert_eq!(subjects, vec!["first", "second"]);
+        assert_eq!(
+            inbox.checkpoint,
+            Some(FolderCheckpoint {
+                uid_validity: 7,
+                last_uid: 2
+            })
+        );
+
+        let mut resumed = Folder::from("INBOX".to_string());
+        resumed.resume_from = Some(FolderCheckpoint {
+            uid_validity: 7,
+            last_uid: 1,
+        });
+        resource.process_messages_in_folder(&mut resumed).await?;
+        assert_eq!(resumed.messages.len(), 1);
+        assert_eq!(resumed.messages[0].subject, "second");
+        Ok(())
+    }
+}
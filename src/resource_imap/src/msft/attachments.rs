@@ -0,0 +1,235 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::{anyhow, Context};
+use futures_util::StreamExt;
+use reqwest::{
+    header::{CONTENT_RANGE, RANGE, RETRY_AFTER},
+    Response, StatusCode,
+};
+use serde::Deserialize;
+use tokio::sync::Semaphore;
+use tracing::{debug, warn};
+
+use crate::Attachment;
+
+const GRAPH_API_URL: &str = "https://graph.microsoft.com/v1.0";
+/// Times a request is retried when throttled (or failing) and a download is
+/// resumed after its connection broke
+const MAX_RETRIES: u32 = 5;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AttachmentMetadata {
+    id: String,
+    #[serde(rename = "@odata.type")]
+    odata_type: String,
+    name: Option<String>,
+    content_type: Option<String>,
+    content_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AttachmentList {
+    value: Vec<AttachmentMetadata>,
+}
+
+/// Downloads the file attachments of messages through the Graph API, which
+/// doesn't return them with the messages. Downloads are bounded by a semaphore
+/// shared by all the connections of a session, wait as long as Graph asks
+/// when throttled and resume from the last received byte when interrupted.
+#[derive(Debug, Clone)]
+pub struct MsftGraphApiAttachments {
+    http: reqwest::Client,
+    bearer_token: String,
+    permits: Arc<Semaphore>,
+}
+
+impl MsftGraphApiAttachments {
+    pub fn new(bearer_token: &str, permits: Arc<Semaphore>) -> Self {
+        MsftGraphApiAttachments {
+            http: reqwest::Client::new(),
+            bearer_token: bearer_token.to_string(),
+            permits,
+        }
+    }
+
+    /// Send a GET request, starting at byte `offset` of the response, retrying
+    /// throttled and failed requests.
+    async fn get(&self, url: &str, offset: u64) -> anyhow::Result<Response> {
+        let mut attempt = 0;
+        loop {
+            let mut request = self.http.get(url).bearer_auth(&self.bearer_token);
+            if offset > 0 {
+                request = request.header(RANGE, format!("bytes={}-", offset));
+            }
+            let (failure, wait) = match request.send().await {
+                Ok(res) if res.status().is_success() => return Ok(res),
+                Ok(res)
+                    if res.status() == StatusCode::TOO_MANY_REQUESTS
+                        || res.status().is_server_error() =>
+                {
+                    (res.status().to_string(), retry_after(&res))
+                }
+                Ok(res) => {
+                    return Err(anyhow!(
+                        "[ingest_imap]: microsoft_365. GET {} failed: {}",
+                        url,
+                        res.status()
+                    ))
+                }
+                Err(err) => (err.to_string(), None),
+            };
+            attempt += 1;
+            if attempt > MAX_RETRIES {
+                return Err(anyhow!(
+                    "[ingest_imap]: microsoft_365. GET {} failed after {} attempts: {}",
+                    url,
+                    attempt,
+                    failure
+                ));
+            }
+            let wait = wait.unwrap_or_else(|| backoff(attempt));
+            debug!("GET {url} failed ({failure}), retrying in {wait:?}");
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    async fn list(&self, message_id: &str) -> anyhow::Result<Vec<AttachmentMetadata>> {
+        // `contentBytes` is left out so large attachments aren't inlined (base64) in the list
+        let url = format!(
+            "{GRAPH_API_URL}/me/messages/{message_id}/attachments?$select=id,name,contentType,contentId"
+        );
+        let list: AttachmentList = self.get(&url, 0).await?.json().await.with_context(|| {
+            format!(
+                "[ingest_imap]: microsoft_365. Deserializing attachments of message {} failed",
+                message_id
+            )
+        })?;
+        Ok(list.value)
+    }
+
+    /// Download the raw content of an attachment, resuming with a `Range`
+    /// request from what was already received when the transfer breaks.
+    async fn download(&self, message_id: &str, attachment_id: &str) -> anyhow::Result<Vec<u8>> {
+        let url =
+            format!("{GRAPH_API_URL}/me/messages/{message_id}/attachments/{attachment_id}/$value");
+        let mut content = Vec::new();
+        let mut interruptions = 0;
+        loop {
+            let response = self.get(&url, content.len() as u64).await?;
+            match response.status() {
+                StatusCode::PARTIAL_CONTENT => {}
+                // ranges aren't honored, start over
+                _ => content.clear(),
+            }
+            let expected = expected_size(&response);
+            let mut stream = response.bytes_stream();
+            let mut interrupted = None;
+            while let Some(chunk) = stream.next().await {
+                match chunk {
+                    Ok(bytes) => content.extend_from_slice(&bytes),
+                    Err(err) => {
+                        interrupted = Some(err);
+                        break;
+                    }
+                }
+            }
+            let interruption = match interrupted {
+                Some(err) => err.to_string(),
+                None => match expected {
+                    Some(size) if (content.len() as u64) < size => {
+                        format!("connection closed at {} of {} bytes", content.len(), size)
+                    }
+                    _ => return Ok(content),
+                },
+            };
+            interruptions += 1;
+            if interruptions > MAX_RETRIES {
+                return Err(anyhow!(
+                    "[ingest_imap]: microsoft_365. Download of {} interrupted {} times: {}",
+                    url,
+                    interruptions,
+                    interruption
+                ));
+            }
+            warn!(
+                "Download of attachment {attachment_id} interrupted after {} bytes, resuming: {interruption}",
+                content.len()
+            );
+        }
+    }
+
+    /// The file attachments of a message. Item and reference attachments (links
+    /// to other messages, events or cloud files) have no content to download.
+    pub async fn attachments(&self, message_id: &str) -> anyhow::Result<Vec<Attachment>> {
+        let mut attachments = Vec::new();
+        for metadata in self.list(message_id).await? {
+            if metadata.odata_type != "#microsoft.graph.fileAttachment" {
+                debug!(
+                    "Skipping {} attachment {}",
+                    metadata.odata_type, metadata.id
+                );
+                continue;
+            }
+            let content = {
+                let _permit = self.permits.acquire().await?;
+                self.download(message_id, &metadata.id).await?
+            };
+            attachments.push(Attachment {
+                filename: metadata.name.unwrap_or_else(|| "Untitled".to_string()),
+                content_type: metadata
+                    .content_type
+                    .unwrap_or_else(|| "application/octet-stream".to_string()),
+                content,
+                content_id: metadata.content_id.unwrap_or_default(),
+            });
+        }
+        Ok(attachments)
+    }
+}
+
+/// How long Graph asked to wait before retrying a throttled request.
+fn retry_after(response: &Response) -> Option<Duration> {
+    response
+        .headers()
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+fn backoff(attempt: u32) -> Duration {
+    Duration::from_millis(500 * 2u64.pow(attempt.min(6)))
+}
+
+/// The size of the complete content, from `Content-Range` for partial
+/// responses or `Content-Length` otherwise.
+fn expected_size(response: &Response) -> Option<u64> {
+    match response.headers().get(CONTENT_RANGE) {
+        Some(range) => parse_content_range_total(range.to_str().ok()?),
+        None => response.content_length(),
+    }
+}
+
+/// The total of a `Content-Range: bytes <start>-<end>/<total>` header.
+fn parse_content_range_total(content_range: &str) -> Option<u64> {
+    content_range.rsplit_once('/')?.1.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_range_total() {
+        assert_eq!(
+            parse_content_range_total("bytes 1048576-5242879/5242880"),
+            Some(5242880)
+        );
+        assert_eq!(parse_content_range_total("bytes 0-99/*"), None);
+        assert_eq!(backoff(1), Duration::from_secs(1));
+        assert_eq!(backoff(20), Duration::from_secs(32));
+    }
+}
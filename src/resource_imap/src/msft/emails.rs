@@ -1,7 +1,11 @@
 use crate::{EmailResource, Folder};
 use anyhow::Context;
-use graph_rs_sdk::{oauth::AccessToken, Graph, ODataQuery};
+use futures_util::future::join_all;
+use graph_rs_sdk::{Graph, ODataQuery};
 use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use super::attachments::MsftGraphApiAttachments;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -103,12 +107,15 @@ impl TryFrom<Message> for EmailResource {
 #[derive(Debug, Clone)]
 pub struct MsftGraphApiEmail {
     graph_client: Graph,
+    /// Present when attachments are extracted
+    attachments: Option<MsftGraphApiAttachments>,
 }
 
 impl MsftGraphApiEmail {
-    pub fn new(token: &AccessToken) -> Self {
+    pub fn new(bearer_token: &str, attachments: Option<MsftGraphApiAttachments>) -> Self {
         MsftGraphApiEmail {
-            graph_client: Graph::new(token.bearer_token()),
+            graph_client: Graph::new(bearer_token),
+            attachments,
         }
     }
 
@@ -190,11 +197,33 @@ impl MsftGraphApiEmail {
             "[ingest_imap]: microsoft_365. Deserializing email messages list failed"
         })?;
 
-        messages_list
-            .value
-            .into_iter()
-            .map(EmailResource::try_from)
-            .collect()
+        let Some(attachments) = self.attachments.as_ref() else {
+            return messages_list
+                .value
+                .into_iter()
+                .map(EmailResource::try_from)
+                .collect();
+        };
+
+        // attachments need a request (or more) each, downloads are bounded by `attachments`
+        join_all(messages_list.value.into_iter().map(|message| async move {
+            let message_id = message.id.clone();
+            let has_attachments = message.has_attachments;
+            let mut email = EmailResource::try_from(message)?;
+            if has_attachments {
+                match attachments.attachments(&message_id).await {
+                    Ok(downloaded) => email.attachments = Some(downloaded),
+                    Err(err) => warn!(
+                        "[ingest_imap]: microsoft_365. Attachments of {} were not downloaded: {:#}",
+                        email.message_id, err
+                    ),
+                }
+            }
+            Ok(email)
+        }))
+        .await
+        .into_iter()
+        .collect()
     }
 }
 
@@ -1,19 +1,20 @@
 use anyhow::anyhow;
 use async_trait::async_trait;
-use graph_rs_sdk::oauth::{AccessToken, OAuth};
+use common::{
+    oauth2::{OAuth2Client, OAuth2Flow, OAuth2Provider, MICROSOFT_GRAPH_SCOPES},
+    secret::Secret,
+};
 use indicatif::ProgressBar;
 use serde::{Deserialize, Serialize};
 use std::{fmt::Display, sync::Arc};
-use tokio::sync::{mpsc, Mutex};
-use tracing::warn;
+use tokio::sync::Semaphore;
 
 use crate::{Folder, ImapConfig, ImapResource};
 
-use self::emails::MsftGraphApiEmail;
+use self::{attachments::MsftGraphApiAttachments, emails::MsftGraphApiEmail};
 
-mod auth_code;
+mod attachments;
 // mod client_credential;
-mod device_code;
 mod emails;
 
 /// The method for retrieving the access token.
@@ -53,7 +54,7 @@ pub struct MicrosoftImapResource {
     /// Client ID of the application from MSFT Azure App Directory
     client_id: String,
     /// Client Secret of the application from MSFT Azure App Directory
-    client_secret: String,
+    client_secret: Secret,
     /// An optional redirect URL for `access_token` generation when using the AuthCode mode
     redirect_uri: Option<String>,
     /// The mode to generate an access_token. Default is 'DeviceCode'.
@@ -61,18 +62,26 @@ pub struct MicrosoftImapResource {
     /// Address to start the authentication server on. Used by the redirect_uri
     auth_server: Option<Microsoft365AuthServerConfig>,
     /// Access Token
-    access_token: Option<AccessToken>,
+    access_token: Option<Secret>,
     /// MAIL API Client
     mail_api_client: Option<MsftGraphApiEmail>,
     batch_size: usize,
     progress: Option<ProgressBar>,
+    extract_attachments: bool,
+    /// Bounds the attachment downloads of all the connections
+    attachment_permits: Arc<Semaphore>,
 }
 
 impl MicrosoftImapResource {
-    pub fn new(id: &str, secret: &str, mode: TokenGenerationMethod, config: &ImapConfig) -> Self {
+    pub fn new(
+        id: &str,
+        secret: &Secret,
+        mode: TokenGenerationMethod,
+        config: &ImapConfig,
+    ) -> Self {
         MicrosoftImapResource {
             client_id: id.to_string(),
-            client_secret: secret.to_string(),
+            client_secret: secret.clone(),
             redirect_uri: None,
             mode,
             auth_server: None,
@@ -84,9 +93,18 @@ impl MicrosoftImapResource {
             } else {
                 None
             },
+            extract_attachments: config.extract_attachments,
+            attachment_permits: Arc::new(Semaphore::new(config.attachment_concurrency.max(1))),
         }
     }
 
+    fn mail_api_client(&self, access_token: &Secret) -> MsftGraphApiEmail {
+        let attachments = self.extract_attachments.then(|| {
+            MsftGraphApiAttachments::new(access_token.expose(), self.attachment_permits.clone())
+        });
+        MsftGraphApiEmail::new(access_token.expose(), attachments)
+    }
+
     pub fn redirect_uri(&mut self, uri: Option<String>) -> &mut MicrosoftImapResource {
         self.redirect_uri = uri;
         self
@@ -100,91 +118,48 @@ impl MicrosoftImapResource {
         self
     }
 
-    fn oauth_client(&self) -> OAuth {
-        let mut oauth = OAuth::new();
-        oauth
-            .client_id(&self.client_id)
-            .add_scope("files.read")
-            .add_scope("Mail.Read")
-            .add_scope("User.Read")
-            .add_scope("offline_access")
-            .access_token_url("https://login.microsoftonline.com/common/oauth2/v2.0/token")
-            .refresh_token_url("https://login.microsoftonline.com/common/oauth2/v2.0/token");
+    fn oauth_client(&self) -> OAuth2Client {
+        // device code clients are public, they must not send the secret
+        let client_secret = match self.mode {
+            TokenGenerationMethod::AuthCode => Some(self.client_secret.clone()),
+            TokenGenerationMethod::DeviceCode => None,
+        };
+        OAuth2Client::new(
+            OAuth2Provider::microsoft("common"),
+            &self.client_id,
+            client_secret,
+            &MICROSOFT_GRAPH_SCOPES,
+        )
+    }
 
+    fn oauth_flow(&self) -> anyhow::Result<OAuth2Flow> {
         match self.mode {
             TokenGenerationMethod::AuthCode => {
-                if self.auth_server.is_some() {
-                    // can safely unwrap here
-                    let server_config = self.auth_server.as_ref().unwrap();
-                    oauth
-                        .client_secret(&self.client_secret)
-                        .authorize_url(
-                            "https://login.microsoftonline.com/common/oauth2/v2.0/authorize",
-                        )
-                        .response_type("code")
-                        .redirect_uri(&format!("{}{}", server_config.addr, server_config.base_url));
-                } else {
-                    warn!("The authenctication mode is auth code grant, but no server config was supplied");
-                }
+                let server_config = self
+                    .auth_server
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("Server config absent"))?;
+                Ok(OAuth2Flow::AuthCode {
+                    redirect_uri: format!("{}{}", server_config.addr, server_config.base_url),
+                    port: server_config.port,
+                })
             }
-            TokenGenerationMethod::DeviceCode => {
-                oauth.authorize_url(
-                    "https://login.microsoftonline.com/common/oauth2/v2.0/devicecode",
-                );
-            } // TokenGenerationMethod::ClientCredential => {
-              //     let server_config = self.auth_server.as_ref().unwrap();
-              //     println!("{server_config:#?}");
-              //     oauth
-              //         .add_scope("https://graph.microsoft.com/.default")
-              //         .redirect_uri(&format!("{}{}", server_config.addr, server_config.base_url))
-              //         .authorize_url("https://login.microsoftonline.com/common/adminconsent");
-              // }
-        };
-        oauth
+            TokenGenerationMethod::DeviceCode => Ok(OAuth2Flow::DeviceCode),
+        }
     }
 }
 
 #[async_trait]
 impl ImapResource for MicrosoftImapResource {
     async fn init(&mut self) -> anyhow::Result<()> {
-        let access_token = match self.mode {
-            TokenGenerationMethod::AuthCode => {
-                if let Some(auth_server) = &self.auth_server {
-                    let (tx, mut rx) = mpsc::channel::<AccessToken>(32);
-                    let client = self.oauth_client();
-                    let auth_server = auth_server.clone();
+        // a token cached by an earlier session (or `admin credentials oauth`) is reused
+        let access_token = self
+            .oauth_client()
+            .token(&self.oauth_flow()?)
+            .await?
+            .access_token;
 
-                    tokio::spawn(async move {
-                        auth_code::init_server(client, tx, auth_server.port).await;
-                    });
-
-                    rx.recv()
-                        .await
-                        .ok_or_else(|| anyhow!("Failed to receive access token"))?
-                } else {
-                    return Err(anyhow!("Server config absent"));
-                }
-            }
-            // TokenGenerationMethod::ClientCredential => {
-            //     let (tx, mut rx) = mpsc::channel::<AccessToken>(32);
-
-            //     let config_clone = msft_365_config.clone();
-            //     tokio::spawn(async move {
-            //         client_credential::init_server(config_clone, tx).await;
-            //     });
-
-            //     rx.recv()
-            //         .await
-            //         .ok_or_else(|| anyhow!("Failed to receive access token"))?
-            // }
-            TokenGenerationMethod::DeviceCode => {
-                device_code::init(Arc::new(Mutex::new(self.oauth_client())))
-                    .await
-                    .map_err(|err| anyhow!("{err}"))?
-            }
-        };
-
-        self.mail_api_client = Some(MsftGraphApiEmail::new(&access_token));
+        self.mail_api_client = Some(self.mail_api_client(&access_token));
         self.access_token = Some(access_token);
 
         Ok(())
@@ -194,6 +169,18 @@ impl ImapResource for MicrosoftImapResource {
         self.client_id.to_string()
     }
 
+    async fn connection(&self) -> anyhow::Result<Box<dyn ImapResource>> {
+        // reuse the access token so that the user is not asked to authenticate again
+        let access_token = self
+            .access_token
+            .as_ref()
+            .ok_or_else(|| anyhow!("Access token should be present"))?;
+        let mut resource = self.clone();
+        resource.mail_api_client = Some(self.mail_api_client(access_token));
+        resource.progress = self.progress.as_ref().map(|_| ProgressBar::new_spinner());
+        Ok(Box::new(resource))
+    }
+
     async fn folders(&mut self) -> anyhow::Result<Vec<String>> {
         let client = self
             .mail_api_client
@@ -249,7 +236,7 @@ pub struct Microsoft365Config {
     /// Client ID of the application from MSFT Azure App Directory
     pub client_id: String,
     /// Client Secret of the application from MSFT Azure App Directory
-    pub client_secret: String,
+    pub client_secret: Secret,
     /// An optional redirect URL for `access_token` generation when using the AuthCode mode
     pub redirect_uri: Option<String>,
     /// The mode to generate an access_token. Default is 'DeviceCode'.
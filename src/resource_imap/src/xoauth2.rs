@@ -0,0 +1,121 @@
+use anyhow::{anyhow, Context};
+use async_imap::Authenticator;
+use common::secret::Secret;
+use serde::{Deserialize, Serialize};
+
+/// Where the OAuth 2.0 access token used with XOAUTH2 (Gmail, Yahoo, Fastmail
+/// and other providers which don't accept passwords) comes from.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum ImapOAuth2 {
+    /// A token obtained beforehand, it isn't refreshed
+    AccessToken(Secret),
+    /// A shell command printing a token on its standard output, executed for
+    /// every connection so it can hand out refreshed tokens, e.g.
+    /// `gcloud auth print-access-token`
+    TokenCommand(String),
+}
+
+impl ImapOAuth2 {
+    pub async fn access_token(&self) -> anyhow::Result<Secret> {
+        match self {
+            ImapOAuth2::AccessToken(token) => Ok(token.clone()),
+            ImapOAuth2::TokenCommand(command) => {
+                let output = if cfg!(windows) {
+                    tokio::process::Command::new("cmd")
+                        .args(["/C", command])
+                        .output()
+                        .await
+                } else {
+                    tokio::process::Command::new("sh")
+                        .args(["-c", command])
+                        .output()
+                        .await
+                }
+                .with_context(|| format!("[ImapOAuth2] unable to execute {command}"))?;
+                if !output.status.success() {
+                    return Err(anyhow!(
+                        "[ImapOAuth2] {command} failed ({}): {}",
+                        output.status,
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    ));
+                }
+                let token = String::from_utf8(output.stdout)
+                    .with_context(|| format!("[ImapOAuth2] {command} printed invalid UTF-8"))?;
+                match token.trim() {
+                    "" => Err(anyhow!("[ImapOAuth2] {command} printed no access token")),
+                    token => Ok(Secret::new(token)),
+                }
+            }
+        }
+    }
+}
+
+/// The SASL XOAUTH2 mechanism: the user and bearer token go in the first
+/// response, a failure is answered with a challenge holding a JSON error
+/// which is acknowledged with an empty response.
+pub(crate) struct XOAuth2 {
+    user: String,
+    access_token: Secret,
+    responded: bool,
+}
+
+impl XOAuth2 {
+    pub(crate) fn new(user: &str, access_token: Secret) -> Self {
+        XOAuth2 {
+            user: user.to_string(),
+            access_token,
+            responded: false,
+        }
+    }
+}
+
+impl Authenticator for XOAuth2 {
+    type Response = String;
+
+    fn process(&mut self, _challenge: &[u8]) -> Self::Response {
+        if self.responded {
+            return String::new();
+        }
+        self.responded = true;
+        format!(
+            "user={}\x01auth=Bearer {}\x01\x01",
+            self.user,
+            self.access_token.expose()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xoauth2_responses() {
+        let mut authenticator = XOAuth2::new("user@gmail.com", Secret::new("ya29.token"));
+        assert_eq!(
+            authenticator.process(b""),
+            "user=user@gmail.com\x01auth=Bearer ya29.token\x01\x01"
+        );
+        // the error challenge, e.g. {"status":"400","schemes":"Bearer","scope":"..."}
+        assert_eq!(authenticator.process(br#"{"status":"400"}"#), "");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn token_command() {
+        let token = ImapOAuth2::TokenCommand("echo ' ya29.refreshed '".to_string())
+            .access_token()
+            .await
+            .unwrap();
+        assert_eq!(token.expose(), "ya29.refreshed");
+        assert!(ImapOAuth2::TokenCommand("exit 3".to_string())
+            .access_token()
+            .await
+            .is_err());
+        assert!(ImapOAuth2::TokenCommand("true".to_string())
+            .access_token()
+            .await
+            .is_err());
+    }
+}
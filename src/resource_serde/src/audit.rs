@@ -0,0 +1,105 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+/// An invocation of a command which changes or destroys an RSSD, recorded in
+/// `surveilr_audit` so the surveillance tool has an audit trail of its own.
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditEntry {
+    pub command: String,
+    pub argv: Vec<String>,
+    pub os_user: String,
+    pub host: String,
+    pub started_at: DateTime<Utc>,
+    pub error: Option<String>,
+}
+
+impl AuditEntry {
+    pub fn outcome(&self) -> &'static str {
+        match self.error {
+            None => "succeeded",
+            Some(_) => "failed",
+        }
+    }
+}
+
+/// The operating system user running surveilr.
+pub fn os_user() -> String {
+    ["USER", "USERNAME", "LOGNAME"]
+        .iter()
+        .find_map(|var| std::env::var(var).ok().filter(|user| !user.is_empty()))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Insert `entry` into `surveilr_audit` and return its ID.
+pub fn record_audit(conn: &Connection, entry: &AuditEntry) -> Result<String> {
+    conn.query_row(
+        "INSERT INTO surveilr_audit (surveilr_audit_id, command, argv, os_user, host, started_at, outcome, error)
+              VALUES (surveilr_pk(), ?, ?, ?, ?, ?, ?, ?)
+           RETURNING surveilr_audit_id",
+        params![
+            entry.command,
+            serde_json::to_string(&entry.argv)?,
+            entry.os_user,
+            entry.host,
+            entry.started_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+            entry.outcome(),
+            entry.error,
+        ],
+        |row| row.get(0),
+    )
+    .with_context(|| format!("[audit::record_audit] {}", entry.command))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persist::DbConn;
+
+    #[test]
+    fn test_record_audit() -> Result<()> {
+        let mut dbc = DbConn::new(":memory:", 0)?;
+        dbc.init(None)?.commit()?;
+
+        let mut entry = AuditEntry {
+            command: "admin merge".to_string(),
+            argv: vec!["surveilr".into(), "admin".into(), "merge".into()],
+            os_user: "alice".to_string(),
+            host: "ops-1".to_string(),
+            started_at: Utc::now(),
+            error: None,
+        };
+        record_audit(&dbc.conn, &entry)?;
+        entry.error = Some("no candidates".to_string());
+        record_audit(&dbc.conn, &entry)?;
+
+        let rows = dbc
+            .conn
+            .prepare(
+                "SELECT argv, os_user, host, outcome, error FROM surveilr_audit ORDER BY rowid",
+            )?
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].0, r#"["surveilr","admin","merge"]"#);
+        assert_eq!((rows[0].1.as_str(), rows[0].2.as_str()), ("alice", "ops-1"));
+        assert_eq!(
+            (rows[0].3.as_str(), rows[0].4.as_deref()),
+            ("succeeded", None)
+        );
+        assert_eq!(
+            (rows[1].3.as_str(), rows[1].4.as_deref()),
+            ("failed", Some("no candidates"))
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,321 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use rusqlite::{backup::Backup, Connection, OpenFlags};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tracing::{debug, warn};
+
+use crate::persist::set_busy_timeout;
+
+// copying a few pages at a time and pausing in between lets ingestion keep
+// committing while a backup is running (the backup restarts if it does)
+const BACKUP_PAGES_PER_STEP: std::os::raw::c_int = 256;
+const BACKUP_STEP_PAUSE: Duration = Duration::from_millis(25);
+
+/// A compressed RSSD created by `admin backup`, with its `sha256sum`-style
+/// checksum written next to it in `<artifact>.sha256`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BackupArtifact {
+    pub db_fs_path: String,
+    pub artifact_fs_path: String,
+    pub db_size_bytes: u64,
+    pub artifact_size_bytes: u64,
+    /// SHA-256 of the compressed artifact
+    pub sha256: String,
+}
+
+/// The path of the checksum file which accompanies `artifact`.
+pub fn checksum_fs_path(artifact: &str) -> String {
+    format!("{}.sha256", artifact)
+}
+
+struct HashingWriter<W: Write> {
+    inner: W,
+    hasher: Sha256,
+    written: u64,
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn sha256_file(path: &str) -> Result<String> {
+    let mut reader = BufReader::new(
+        File::open(path).with_context(|| format!("[backup::sha256_file] opening {}", path))?,
+    );
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let read = reader.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// A hidden file next to `path` so it can be renamed into place once complete.
+fn partial_fs_path(path: &Path, suffix: &str) -> PathBuf {
+    let name = format!(
+        ".{}.{}-{}",
+        path.file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        suffix,
+        ulid::Ulid::new()
+    );
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.join(name),
+        _ => PathBuf::from(name),
+    }
+}
+
+/// Create a new file only its owner can read, snapshots and backups hold the
+/// whole RSSD.
+fn create_private(path: &Path) -> io::Result<File> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)
+}
+
+/// The `-journal`, `-wal` and `-shm` files SQLite keeps next to `db_fs_path`.
+fn sidecar_fs_paths(db_fs_path: &str) -> Vec<PathBuf> {
+    ["-journal", "-wal", "-shm"]
+        .iter()
+        .map(|suffix| PathBuf::from(format!("{}{}", db_fs_path, suffix)))
+        .collect()
+}
+
+/// Snapshot `db_fs_path` with SQLite's online backup API, which is safe while
+/// other processes are writing to it, and stream the copy through zstd into
+/// `artifact`. The uncompressed snapshot is kept next to `artifact`, readable
+/// only by its owner, and removed once compressed.
+pub fn backup_rssd(db_fs_path: &str, artifact: &str, level: i32) -> Result<BackupArtifact> {
+    let src = Connection::open_with_flags(
+        db_fs_path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI,
+    )
+    .with_context(|| format!("[backup_rssd] SQLite database {}", db_fs_path))?;
+    set_busy_timeout(&src)?;
+
+    let snapshot = partial_fs_path(Path::new(artifact), "snapshot");
+    create_private(&snapshot)
+        .with_context(|| format!("[backup_rssd] creating snapshot {}", snapshot.display()))?;
+    let result = (|| -> Result<BackupArtifact> {
+        {
+            let mut dst = Connection::open(&snapshot)
+                .with_context(|| format!("[backup_rssd] snapshot {}", snapshot.display()))?;
+            let backup = Backup::new(&src, &mut dst)?;
+            backup
+                .run_to_completion(BACKUP_PAGES_PER_STEP, BACKUP_STEP_PAUSE, None)
+                .with_context(|| format!("[backup_rssd] backing up {}", db_fs_path))?;
+        }
+        let db_size_bytes = fs::metadata(&snapshot)?.len();
+
+        let partial = partial_fs_path(Path::new(artifact), "partial");
+        let mut writer = HashingWriter {
+            inner: BufWriter::new(
+                create_private(&partial)
+                    .with_context(|| format!("[backup_rssd] creating {}", partial.display()))?,
+            ),
+            hasher: Sha256::new(),
+            written: 0,
+        };
+        let compressed = (|| -> Result<()> {
+            let mut encoder = zstd::stream::Encoder::new(&mut writer, level)?;
+            encoder.include_checksum(true)?;
+            io::copy(&mut BufReader::new(File::open(&snapshot)?), &mut encoder)?;
+            encoder.finish()?.flush()?;
+            Ok(())
+        })();
+        if let Err(err) = compressed {
+            let _ = fs::remove_file(&partial);
+            return Err(err.context(format!("[backup_rssd] compressing into {}", artifact)));
+        }
+        fs::rename(&partial, artifact)
+            .with_context(|| format!("[backup_rssd] moving backup into {}", artifact))?;
+
+        let sha256 = format!("{:x}", writer.hasher.finalize());
+        let file_name = Path::new(artifact)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| artifact.to_string());
+        fs::write(
+            checksum_fs_path(artifact),
+            format!("{}  {}\n", sha256, file_name),
+        )
+        .with_context(|| format!("[backup_rssd] writing {}", checksum_fs_path(artifact)))?;
+
+        Ok(BackupArtifact {
+            db_fs_path: db_fs_path.to_string(),
+            artifact_fs_path: artifact.to_string(),
+            db_size_bytes,
+            artifact_size_bytes: writer.written,
+            sha256,
+        })
+    })();
+    let _ = fs::remove_file(&snapshot);
+    for sidecar in sidecar_fs_paths(&snapshot.to_string_lossy()) {
+        let _ = fs::remove_file(sidecar);
+    }
+    result
+}
+
+/// Verify `artifact` against its `.sha256` checksum (when present), decompress
+/// it and check the database's integrity before moving it into `db_fs_path`.
+/// An existing `db_fs_path` is only replaced when `replace_existing` is set and
+/// never while it has a rollback journal or WAL, which SQLite would apply to the
+/// restored database.
+pub fn restore_rssd(artifact: &str, db_fs_path: &str, replace_existing: bool) -> Result<()> {
+    if Path::new(db_fs_path).exists() && !replace_existing {
+        return Err(anyhow!(
+            "[restore_rssd] {} already exists, use --remove-existing-first to replace it",
+            db_fs_path
+        ));
+    }
+    if let Some(sidecar) = sidecar_fs_paths(db_fs_path)
+        .into_iter()
+        .find(|sidecar| sidecar.exists())
+    {
+        return Err(anyhow!(
+            "[restore_rssd] {} exists, make sure {} is not in use and remove it before restoring",
+            sidecar.display(),
+            db_fs_path
+        ));
+    }
+
+    let checksum_path = checksum_fs_path(artifact);
+    match fs::read_to_string(&checksum_path) {
+        Ok(checksum) => {
+            let expected = checksum.split_whitespace().next().unwrap_or_default();
+            let actual = sha256_file(artifact)?;
+            if !expected.eq_ignore_ascii_case(&actual) {
+                return Err(anyhow!(
+                    "[restore_rssd] {} checksum {} doesn't match {} in {}",
+                    artifact,
+                    actual,
+                    expected,
+                    checksum_path
+                ));
+            }
+            debug!("[restore_rssd] {} matches {}", artifact, checksum_path);
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            warn!(
+                "[restore_rssd] {} not found, relying on the zstd frame checksum",
+                checksum_path
+            );
+        }
+        Err(err) => {
+            return Err(err).with_context(|| format!("[restore_rssd] reading {}", checksum_path))
+        }
+    }
+
+    let partial = partial_fs_path(Path::new(db_fs_path), "restoring");
+    let restored = (|| -> Result<()> {
+        let mut decoder = zstd::stream::Decoder::new(
+            File::open(artifact).with_context(|| format!("[restore_rssd] opening {}", artifact))?,
+        )?;
+        let mut writer = BufWriter::new(File::create(&partial)?);
+        io::copy(&mut decoder, &mut writer)
+            .with_context(|| format!("[restore_rssd] decompressing {}", artifact))?;
+        writer.flush()?;
+        drop(writer);
+
+        let conn = Connection::open_with_flags(&partial, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let integrity: String = conn
+            .query_row("PRAGMA integrity_check", [], |row| row.get(0))
+            .with_context(|| format!("[restore_rssd] {} is not a SQLite database", artifact))?;
+        if integrity != "ok" {
+            return Err(anyhow!(
+                "[restore_rssd] integrity check of {} failed: {}",
+                artifact,
+                integrity
+            ));
+        }
+        Ok(())
+    })();
+    if let Err(err) = restored {
+        let _ = fs::remove_file(&partial);
+        return Err(err);
+    }
+
+    fs::rename(&partial, db_fs_path).with_context(|| {
+        format!(
+            "[restore_rssd] moving restored database into {}",
+            db_fs_path
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persist::DbConn;
+
+    #[test]
+    fn backup_and_restore_roundtrip() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("surveilr-backup-test-{}", ulid::Ulid::new()));
+        fs::create_dir_all(&dir)?;
+        let db = dir.join("rssd.sqlite.db").to_string_lossy().to_string();
+        let artifact = dir.join("rssd.sqlite.db.zst").to_string_lossy().to_string();
+        let restored = dir.join("restored.sqlite.db").to_string_lossy().to_string();
+
+        let mut dbc = DbConn::new(&db, 0)?;
+        let tx = dbc.init(None)?;
+        crate::persist::upserted_device(&tx, &common::DEVICE)?;
+        tx.commit()?;
+
+        let backup = backup_rssd(&db, &artifact, 3)?;
+        assert!(backup.artifact_size_bytes < backup.db_size_bytes);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&artifact)?.permissions().mode() & 0o777, 0o600);
+        }
+        // only the artifact and its checksum are left next to the RSSD
+        assert_eq!(fs::read_dir(&dir)?.count(), 3);
+        assert!(fs::read_to_string(checksum_fs_path(&artifact))?.starts_with(&backup.sha256));
+
+        restore_rssd(&artifact, &restored, false)?;
+        let devices: usize =
+            Connection::open(&restored)?
+                .query_row("SELECT COUNT(*) FROM device", [], |row| row.get(0))?;
+        assert_eq!(devices, 1);
+        // an existing database is only replaced when asked to
+        assert!(restore_rssd(&artifact, &restored, false).is_err());
+        // and never while a stale journal would be applied to the restored copy
+        fs::write(format!("{}-journal", restored), b"stale")?;
+        assert!(restore_rssd(&artifact, &restored, true).is_err());
+        fs::remove_file(format!("{}-journal", restored))?;
+        restore_rssd(&artifact, &restored, true)?;
+
+        fs::write(
+            checksum_fs_path(&artifact),
+            format!("{}  x\n", "0".repeat(64)),
+        )?;
+        assert!(restore_rssd(&artifact, &restored, true).is_err());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
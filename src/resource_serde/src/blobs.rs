@@ -0,0 +1,362 @@
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use resource::digest::{ContentHasher, DigestAlgorithm};
+use rusqlite::{Connection, OptionalExtension};
+
+pub const BLOB_STORE_METADATA_KEY: &str = "blob_store";
+
+/// What `uniform_resource.content` holds instead of content offloaded to the
+/// blob store, followed by the blob's digest (e.g. `sha256:<hex>`).
+pub const BLOB_POINTER_PREFIX: &str = "surveilr-blob:";
+
+const BLOB_DIGEST_ALGORITHMS: [DigestAlgorithm; 3] = [
+    DigestAlgorithm::Sha1,
+    DigestAlgorithm::Sha256,
+    DigestAlgorithm::Blake3,
+];
+
+/// The default `--blob-threshold`, in KB.
+pub const DEFAULT_BLOB_THRESHOLD_KB: usize = 1024;
+
+/// A content-addressed directory holding resource content outside the RSSD,
+/// each blob stored once no matter how many resources (or RSSDs) share it.
+/// Blobs are addressed by the digest algorithm of the ingest session which
+/// stored them and their hex digest, e.g. `sha256:<hex>`. SHA-1 blobs are at
+/// `<root>/<first two hex chars>/<hex>`, others at `<root>/<algorithm>/...`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlobStore {
+    root: PathBuf,
+}
+
+/// Hashes what's written through it.
+struct HashingWriter<W: Write> {
+    inner: W,
+    hasher: ContentHasher,
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn hex_digest_len(algorithm: DigestAlgorithm) -> usize {
+    match algorithm {
+        DigestAlgorithm::Sha1 => 40,
+        DigestAlgorithm::Sha256 | DigestAlgorithm::Blake3 => 64,
+    }
+}
+
+/// The algorithm and hex digest of the blob `digest` (`<algorithm>:<hex>`).
+pub fn parse_blob_digest(digest: &str) -> Result<(DigestAlgorithm, &str)> {
+    let (algorithm, hex) = digest
+        .split_once(':')
+        .ok_or_else(|| anyhow!("[parse_blob_digest] {digest} is not <algorithm>:<hex>"))?;
+    let algorithm: DigestAlgorithm = algorithm
+        .parse()
+        .with_context(|| format!("[parse_blob_digest] {digest}"))?;
+    if hex.len() != hex_digest_len(algorithm)
+        || !hex.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+    {
+        bail!("[parse_blob_digest] {digest} is not a {algorithm} hex digest");
+    }
+    Ok((algorithm, hex))
+}
+
+impl BlobStore {
+    /// The store in the directory `root`, created if it doesn't exist.
+    pub fn open(root: impl AsRef<Path>) -> Result<Self> {
+        let root = root.as_ref();
+        fs::create_dir_all(root)
+            .with_context(|| format!("[BlobStore::open] creating {}", root.display()))?;
+        Ok(BlobStore {
+            root: root
+                .canonicalize()
+                .with_context(|| format!("[BlobStore::open] {}", root.display()))?,
+        })
+    }
+
+    /// The store recorded in the RSSD's `rssd_metadata`, `None` when its
+    /// content was never offloaded.
+    pub fn recorded(conn: &Connection) -> Result<Option<Self>> {
+        let root: Option<String> = conn
+            .query_row(
+                "SELECT value FROM rssd_metadata WHERE key = ?",
+                [BLOB_STORE_METADATA_KEY],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(root.map(|root| BlobStore {
+            root: PathBuf::from(root),
+        }))
+    }
+
+    /// The store in `requested`, recorded the first time content is offloaded
+    /// from an RSSD. Other stores are rejected afterwards since the RSSD's
+    /// pointers only resolve in the recorded one.
+    pub fn establish(conn: &Connection, requested: &str) -> Result<Self> {
+        let requested = BlobStore::open(requested)?;
+        match BlobStore::recorded(conn)? {
+            Some(recorded) if recorded != requested => Err(anyhow!(
+                "[BlobStore::establish] RSSD content is offloaded to {} and cannot be offloaded to {}",
+                recorded.root.display(),
+                requested.root.display()
+            )),
+            Some(recorded) => Ok(recorded),
+            None => {
+                conn.execute(
+                    "INSERT INTO rssd_metadata (key, value) VALUES (?, ?)",
+                    [BLOB_STORE_METADATA_KEY, &requested.root.to_string_lossy()],
+                )?;
+                Ok(requested)
+            }
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn path(&self, digest: &str) -> Result<PathBuf> {
+        let (algorithm, hex) = parse_blob_digest(digest)?;
+        let root = match algorithm {
+            DigestAlgorithm::Sha1 => self.root.clone(),
+            _ => self.root.join(algorithm.to_string()),
+        };
+        Ok(root.join(&hex[..2]).join(hex))
+    }
+
+    pub fn contains(&self, digest: &str) -> bool {
+        self.path(digest).is_ok_and(|path| path.is_file())
+    }
+
+    /// Store the content read from `reader` and return its `algorithm` digest.
+    /// The blob is written to a temporary file and renamed over whatever is at
+    /// its path so readers never see a partial (or previously corrupted) one.
+    pub fn put_reader(&self, reader: &mut dyn Read, algorithm: DigestAlgorithm) -> Result<String> {
+        let mut temp = tempfile::NamedTempFile::new_in(&self.root)
+            .with_context(|| format!("[BlobStore::put] in {}", self.root.display()))?;
+        let digest = {
+            let mut writer = HashingWriter {
+                inner: io::BufWriter::new(temp.as_file_mut()),
+                hasher: algorithm.hasher(),
+            };
+            io::copy(reader, &mut writer).context("[BlobStore::put] writing blob")?;
+            writer.flush()?;
+            format!("{}:{}", algorithm, writer.hasher.finalize_hex())
+        };
+
+        let path = self.path(&digest)?;
+        fs::create_dir_all(path.parent().unwrap())?;
+        temp.persist(&path)
+            .with_context(|| format!("[BlobStore::put] storing {}", path.display()))?;
+        Ok(digest)
+    }
+
+    pub fn put(&self, content: &[u8], algorithm: DigestAlgorithm) -> Result<String> {
+        self.put_reader(&mut &content[..], algorithm)
+    }
+
+    /// Open the blob `digest` for reading.
+    pub fn reader(&self, digest: &str) -> Result<fs::File> {
+        let path = self.path(digest)?;
+        fs::File::open(&path).with_context(|| {
+            format!(
+                "[BlobStore::reader] blob {digest} is missing from {}",
+                self.root.display()
+            )
+        })
+    }
+
+    /// The content of the blob `digest`, checked against its digest.
+    pub fn get(&self, digest: &str) -> Result<Vec<u8>> {
+        let mut content = Vec::new();
+        self.reader(digest)?.read_to_end(&mut content)?;
+        let (algorithm, hex) = parse_blob_digest(digest)?;
+        let computed = algorithm.hex_digest(&content);
+        if computed != hex {
+            bail!(
+                "[BlobStore::get] blob {} is corrupt, its content hashes to {computed}",
+                self.path(digest)?.display()
+            );
+        }
+        Ok(content)
+    }
+
+    /// The digest the blob's content hashes to with `algorithm` (the resource's
+    /// `content_digest_algorithm`), read without holding it in memory.
+    pub fn digest_of(&self, digest: &str, algorithm: DigestAlgorithm) -> Result<String> {
+        let mut hasher = algorithm.hasher();
+        io::copy(&mut self.reader(digest)?, &mut hasher)?;
+        Ok(hasher.finalize_hex())
+    }
+
+    /// Copy the blob `digest` from `source` unless this store already has it,
+    /// returning whether it was copied.
+    pub fn copy_from(&self, source: &BlobStore, digest: &str) -> Result<bool> {
+        if self.contains(digest) {
+            return Ok(false);
+        }
+        let (algorithm, _) = parse_blob_digest(digest)?;
+        let copied = self.put_reader(&mut source.reader(digest)?, algorithm)?;
+        if copied != digest {
+            bail!(
+                "[BlobStore::copy_from] blob {} is corrupt, its content hashes to {copied}",
+                source.path(digest)?.display()
+            );
+        }
+        Ok(true)
+    }
+}
+
+pub fn blob_pointer(digest: &str) -> String {
+    format!("{BLOB_POINTER_PREFIX}{digest}")
+}
+
+/// The shortest and longest pointers, content of any other length is not one.
+pub fn blob_pointer_len_range() -> (usize, usize) {
+    let lengths = BLOB_DIGEST_ALGORITHMS.iter().map(|algorithm| {
+        BLOB_POINTER_PREFIX.len() + algorithm.to_string().len() + 1 + hex_digest_len(*algorithm)
+    });
+    (lengths.clone().min().unwrap(), lengths.max().unwrap())
+}
+
+/// The digest of the blob `content` points to. A resource whose actual content
+/// looks like a pointer isn't mistaken for one since it hashes to its own
+/// `content_digest` (with whichever algorithm it was ingested).
+pub fn pointed_blob<'a>(content: &'a [u8], content_digest: &str) -> Option<&'a str> {
+    let digest = std::str::from_utf8(content)
+        .ok()?
+        .strip_prefix(BLOB_POINTER_PREFIX)?;
+    let is_digest = parse_blob_digest(digest).is_ok();
+    let own_digest = BLOB_DIGEST_ALGORITHMS
+        .iter()
+        .any(|algorithm| algorithm.hex_digest(content) == content_digest);
+    (is_digest && !own_digest).then_some(digest)
+}
+
+/// The store and digest of the blob `uniform_resource_id`'s content was
+/// offloaded to, `None` when its content is in the RSSD.
+pub fn offloaded_blob(
+    conn: &Connection,
+    uniform_resource_id: &str,
+) -> Result<Option<(BlobStore, String)>> {
+    let row: Option<(Option<Vec<u8>>, String)> = conn
+        .query_row(
+            "SELECT content, content_digest FROM uniform_resource WHERE uniform_resource_id = ?",
+            [uniform_resource_id],
+            |row| {
+                Ok((
+                    row.get_ref(0)?.as_bytes_or_null()?.map(<[u8]>::to_vec),
+                    row.get(1)?,
+                ))
+            },
+        )
+        .optional()?;
+    let Some(digest) = row
+        .as_ref()
+        .and_then(|(content, content_digest)| pointed_blob(content.as_deref()?, content_digest))
+    else {
+        return Ok(None);
+    };
+    let store = BlobStore::recorded(conn)?.ok_or_else(|| {
+        anyhow!("[offloaded_blob] {uniform_resource_id} points to blob {digest} but the RSSD has no blob store")
+    })?;
+    Ok(Some((store, digest.to_string())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persist::DbConn;
+
+    #[test]
+    fn put_get_and_copy() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let store = BlobStore::open(dir.path().join("blobs"))?;
+        let sha1 = DigestAlgorithm::Sha1.hex_digest(b"large image");
+        let digest = store.put(b"large image", DigestAlgorithm::Sha1)?;
+        assert_eq!(digest, format!("sha1:{sha1}"));
+        assert_eq!(store.put(b"large image", DigestAlgorithm::Sha1)?, digest);
+        assert_eq!(store.get(&digest)?, b"large image");
+        assert_eq!(store.digest_of(&digest, DigestAlgorithm::Sha1)?, sha1);
+        assert_eq!(
+            store.digest_of(&digest, DigestAlgorithm::Blake3)?,
+            DigestAlgorithm::Blake3.hex_digest(b"large image")
+        );
+
+        let other = BlobStore::open(dir.path().join("other"))?;
+        assert!(other.copy_from(&store, &digest)?);
+        assert!(!other.copy_from(&store, &digest)?);
+        assert_eq!(other.get(&digest)?, b"large image");
+
+        fs::write(store.path(&digest)?, b"tampered")?;
+        assert!(store.get(&digest).is_err());
+        // storing the content again replaces the corrupted blob
+        store.put(b"large image", DigestAlgorithm::Sha1)?;
+        assert_eq!(store.get(&digest)?, b"large image");
+
+        // blobs are addressed by the session's digest algorithm
+        let blake3 = store.put(b"large image", DigestAlgorithm::Blake3)?;
+        assert_eq!(
+            blake3,
+            format!(
+                "blake3:{}",
+                DigestAlgorithm::Blake3.hex_digest(b"large image")
+            )
+        );
+        assert_ne!(store.path(&blake3)?, store.path(&digest)?);
+        assert_eq!(store.get(&blake3)?, b"large image");
+        assert!(store.path("md5:abc").is_err());
+        assert!(store.path("sha1:../../etc/passwd").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn pointers() {
+        let hex = DigestAlgorithm::Sha256.hex_digest(b"large image");
+        let digest = format!("sha256:{hex}");
+        let pointer = blob_pointer(&digest);
+        assert_eq!(pointed_blob(pointer.as_bytes(), &hex), Some(&*digest));
+        let (shortest, longest) = blob_pointer_len_range();
+        assert!((shortest..=longest).contains(&pointer.len()));
+        // pointers written before blobs were addressed by algorithm
+        let sha1 = DigestAlgorithm::Sha1.hex_digest(b"large image");
+        let legacy = format!("surveilr-blob:sha1:{sha1}");
+        assert_eq!(legacy.len(), shortest);
+        assert_eq!(
+            pointed_blob(legacy.as_bytes(), &sha1),
+            Some(&*format!("sha1:{sha1}"))
+        );
+        // a resource whose content happens to be a pointer
+        let own_digest = DigestAlgorithm::Sha1.hex_digest(pointer.as_bytes());
+        assert_eq!(pointed_blob(pointer.as_bytes(), &own_digest), None);
+        let own_digest = DigestAlgorithm::Blake3.hex_digest(pointer.as_bytes());
+        assert_eq!(pointed_blob(pointer.as_bytes(), &own_digest), None);
+        assert_eq!(pointed_blob(b"large image", &hex), None);
+    }
+
+    #[test]
+    fn established_once() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut dbc = DbConn::new(":memory:", 0)?;
+        let tx = dbc.init(None)?;
+        assert_eq!(BlobStore::recorded(&tx)?, None);
+        let root = dir.path().join("blobs");
+        let store = BlobStore::establish(&tx, root.to_str().unwrap())?;
+        assert_eq!(BlobStore::establish(&tx, root.to_str().unwrap())?, store);
+        assert_eq!(BlobStore::recorded(&tx)?, Some(store));
+        let other = dir.path().join("other");
+        assert!(BlobStore::establish(&tx, other.to_str().unwrap()).is_err());
+        Ok(())
+    }
+}
@@ -78,7 +78,7 @@ INSERT INTO "code_notebook_kernel" ("code_notebook_kernel_id", "kernel_name", "d
 INSERT INTO "code_notebook_kernel" ("code_notebook_kernel_id", "kernel_name", "description", "mime_type", "file_extn", "elaboration", "governance", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ('LLM Prompt', 'Large Lanugage Model (LLM) Prompt', NULL, 'text/vnd.netspective.llm-prompt', '.llm-prompt.txt', NULL, NULL, (CURRENT_TIMESTAMP), NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(kernel_name) DO UPDATE SET mime_type = EXCLUDED.mime_type, file_extn = EXCLUDED.file_extn;
 
 -- store all SQL that is potentially reusable in the database
-INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((ulid()), 'SQL', 'BootstrapSqlNotebook', 'bootstrapDDL', NULL, 'CREATE TABLE IF NOT EXISTS "assurance_schema" (
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'BootstrapSqlNotebook', 'bootstrapDDL', NULL, 'CREATE TABLE IF NOT EXISTS "assurance_schema" (
     "assurance_schema_id" VARCHAR PRIMARY KEY NOT NULL,
     "assurance_type" TEXT NOT NULL,
     "code" TEXT NOT NULL,
@@ -155,12 +155,12 @@ CREATE TABLE IF NOT EXISTS "code_notebook_state" (
             notebook_kernel_id = EXCLUDED.notebook_kernel_id,
             updated_at = CURRENT_TIMESTAMP,
             activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
-INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((ulid()), 'SQL', 'BootstrapSqlNotebook', 'bootstrapSeedDML', NULL, 'storeNotebookCellsDML "bootstrapSeedDML" did not return SQL (found: object)', 'd5bcdf4a75be14925b1008a47362707c00cb8990', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'BootstrapSqlNotebook', 'bootstrapSeedDML', NULL, 'storeNotebookCellsDML "bootstrapSeedDML" did not return SQL (found: object)', 'd5bcdf4a75be14925b1008a47362707c00cb8990', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
             interpretable_code = EXCLUDED.interpretable_code,
             notebook_kernel_id = EXCLUDED.notebook_kernel_id,
             updated_at = CURRENT_TIMESTAMP,
             activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
-INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((ulid()), 'SQL', 'ConstructionSqlNotebook', 'v001_once_initialDDL', NULL, 'CREATE TABLE IF NOT EXISTS "device" (
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'ConstructionSqlNotebook', 'v001_once_initialDDL', NULL, 'CREATE TABLE IF NOT EXISTS "device" (
     "device_id" VARCHAR PRIMARY KEY NOT NULL,
     "name" TEXT NOT NULL,
     "state" TEXT CHECK(json_valid(state)) NOT NULL,
@@ -245,6 +245,8 @@ CREATE TABLE IF NOT EXISTS "ur_ingest_session" (
     "deleted_at" TIMESTAMPTZ,
     "deleted_by" TEXT,
     "activity_log" TEXT,
+    "device_clock" TEXT CHECK(json_valid(device_clock) OR device_clock IS NULL),
+    "content_digest_algorithm" TEXT NOT NULL DEFAULT ''sha1'',
     FOREIGN KEY("device_id") REFERENCES "device"("device_id"),
     FOREIGN KEY("behavior_id") REFERENCES "behavior"("behavior_id"),
     UNIQUE("device_id", "created_at")
@@ -286,6 +288,8 @@ CREATE TABLE IF NOT EXISTS "uniform_resource" (
     "deleted_at" TIMESTAMPTZ,
     "deleted_by" TEXT,
     "activity_log" TEXT,
+    "semantic_identity" TEXT,
+    "content_digest_algorithm" TEXT NOT NULL DEFAULT ''sha1'',
     FOREIGN KEY("device_id") REFERENCES "device"("device_id"),
     FOREIGN KEY("ingest_session_id") REFERENCES "ur_ingest_session"("ur_ingest_session_id"),
     FOREIGN KEY("ingest_fs_path_id") REFERENCES "ur_ingest_session_fs_path"("ur_ingest_session_fs_path_id"),
@@ -409,40 +413,390 @@ CREATE TABLE IF NOT EXISTS "ur_ingest_session_imap_acct_folder_message" (
     "deleted_at" TIMESTAMPTZ,
     "deleted_by" TEXT,
     "activity_log" TEXT,
+    "date" TEXT,
     FOREIGN KEY("ingest_session_id") REFERENCES "ur_ingest_session"("ur_ingest_session_id"),
     FOREIGN KEY("ingest_imap_acct_folder_id") REFERENCES "ur_ingest_session_imap_acct_folder"("ur_ingest_session_imap_acct_folder_id"),
     FOREIGN KEY("uniform_resource_id") REFERENCES "uniform_resource"("uniform_resource_id"),
     UNIQUE("message", "message_id")
 );
+CREATE TABLE IF NOT EXISTS "uniform_resource_lineage" (
+    "uniform_resource_lineage_id" VARCHAR PRIMARY KEY NOT NULL,
+    "ingest_session_id" VARCHAR NOT NULL,
+    "source_uniform_resource_id" VARCHAR,
+    "output_uniform_resource_id" VARCHAR NOT NULL,
+    "ingest_fs_path_entry_id" VARCHAR,
+    "ingest_task_id" VARCHAR,
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    FOREIGN KEY("ingest_session_id") REFERENCES "ur_ingest_session"("ur_ingest_session_id"),
+    FOREIGN KEY("source_uniform_resource_id") REFERENCES "uniform_resource"("uniform_resource_id"),
+    FOREIGN KEY("output_uniform_resource_id") REFERENCES "uniform_resource"("uniform_resource_id"),
+    FOREIGN KEY("ingest_fs_path_entry_id") REFERENCES "ur_ingest_session_fs_path_entry"("ur_ingest_session_fs_path_entry_id"),
+    FOREIGN KEY("ingest_task_id") REFERENCES "ur_ingest_session_task"("ur_ingest_session_task_id")
+);
+CREATE TABLE IF NOT EXISTS "rssd_metadata" (
+    "key" TEXT PRIMARY KEY NOT NULL,
+    "value" TEXT NOT NULL,
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT
+);
+CREATE TABLE IF NOT EXISTS "query_snapshot" (
+    "query_snapshot_id" VARCHAR PRIMARY KEY NOT NULL,
+    "name" TEXT NOT NULL,
+    "query_sql" TEXT NOT NULL,
+    "result_schema" TEXT CHECK(json_valid(result_schema)) NOT NULL,
+    "result_rows" TEXT CHECK(json_valid(result_rows)) NOT NULL,
+    "row_count" INTEGER NOT NULL,
+    "content_digest" TEXT NOT NULL,
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT
+);
+CREATE TABLE IF NOT EXISTS "surveilr_audit" (
+    "surveilr_audit_id" VARCHAR PRIMARY KEY NOT NULL,
+    "command" TEXT NOT NULL,
+    "argv" TEXT CHECK(json_valid(argv)) NOT NULL,
+    "os_user" TEXT NOT NULL,
+    "host" TEXT NOT NULL,
+    "started_at" TIMESTAMPTZ NOT NULL,
+    "outcome" TEXT NOT NULL,
+    "error" TEXT,
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT
+);
+CREATE TABLE IF NOT EXISTS "ur_ingest_session_imap_acct_stat" (
+    "ur_ingest_session_imap_acct_stat_id" VARCHAR PRIMARY KEY NOT NULL,
+    "ingest_session_id" VARCHAR NOT NULL,
+    "ingest_account_id" VARCHAR NOT NULL,
+    "stat_kind" TEXT NOT NULL,
+    "stat_key" TEXT NOT NULL,
+    "stat_value" INTEGER NOT NULL,
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    FOREIGN KEY("ingest_session_id") REFERENCES "ur_ingest_session"("ur_ingest_session_id"),
+    FOREIGN KEY("ingest_account_id") REFERENCES "ur_ingest_session_imap_account"("ur_ingest_session_imap_account_id")
+);
+CREATE TABLE IF NOT EXISTS "policy_finding" (
+    "policy_finding_id" VARCHAR PRIMARY KEY NOT NULL,
+    "pack" TEXT NOT NULL,
+    "assertion" TEXT NOT NULL,
+    "severity" TEXT NOT NULL,
+    "state" TEXT NOT NULL,
+    "status" TEXT NOT NULL,
+    "row_count" INTEGER NOT NULL,
+    "sample_rows" TEXT CHECK(json_valid(sample_rows) OR sample_rows IS NULL),
+    "remediation" TEXT,
+    "error" TEXT,
+    "last_seen_at" TIMESTAMPTZ NOT NULL,
+    "acknowledged_at" TIMESTAMPTZ,
+    "acknowledged_by" TEXT,
+    "resolved_at" TIMESTAMPTZ,
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT
+);
+CREATE TABLE IF NOT EXISTS "policy_alert_rule" (
+    "policy_alert_rule_id" VARCHAR PRIMARY KEY NOT NULL,
+    "name" TEXT NOT NULL,
+    "min_severity" TEXT NOT NULL,
+    "fire_on" TEXT NOT NULL,
+    "channel" TEXT NOT NULL,
+    "target" TEXT NOT NULL,
+    "packs" TEXT CHECK(json_valid(packs) OR packs IS NULL),
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    UNIQUE("name")
+);
+CREATE TABLE IF NOT EXISTS "compliance_control" (
+    "compliance_control_id" VARCHAR PRIMARY KEY NOT NULL,
+    "framework" TEXT NOT NULL,
+    "control_code" TEXT NOT NULL,
+    "title" TEXT,
+    "description" TEXT,
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    UNIQUE("framework", "control_code")
+);
+CREATE TABLE IF NOT EXISTS "compliance_evidence_map" (
+    "compliance_evidence_map_id" VARCHAR PRIMARY KEY NOT NULL,
+    "compliance_control_id" VARCHAR NOT NULL,
+    "evidence_kind" TEXT NOT NULL,
+    "evidence_selector" TEXT NOT NULL,
+    "description" TEXT,
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    FOREIGN KEY("compliance_control_id") REFERENCES "compliance_control"("compliance_control_id"),
+    UNIQUE("compliance_control_id", "evidence_kind", "evidence_selector")
+);
+CREATE TABLE IF NOT EXISTS "known_file_hash" (
+    "known_file_hash_id" VARCHAR PRIMARY KEY NOT NULL,
+    "hash_set" TEXT NOT NULL,
+    "algorithm" TEXT NOT NULL,
+    "digest" TEXT NOT NULL,
+    "disposition" TEXT NOT NULL,
+    "confidence" FLOAT NOT NULL,
+    "file_name" TEXT,
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    UNIQUE("hash_set", "algorithm", "digest")
+);
+CREATE TABLE IF NOT EXISTS "uniform_resource_known_file" (
+    "uniform_resource_known_file_id" VARCHAR PRIMARY KEY NOT NULL,
+    "uniform_resource_id" VARCHAR NOT NULL,
+    "known_file_hash_id" VARCHAR NOT NULL,
+    "hash_set" TEXT NOT NULL,
+    "disposition" TEXT NOT NULL,
+    "confidence" FLOAT NOT NULL,
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    FOREIGN KEY("uniform_resource_id") REFERENCES "uniform_resource"("uniform_resource_id"),
+    FOREIGN KEY("known_file_hash_id") REFERENCES "known_file_hash"("known_file_hash_id"),
+    UNIQUE("uniform_resource_id", "known_file_hash_id")
+);
+CREATE TABLE IF NOT EXISTS "ur_ingest_session_git_repo" (
+    "ur_ingest_session_git_repo_id" VARCHAR PRIMARY KEY NOT NULL,
+    "ingest_session_id" VARCHAR NOT NULL,
+    "repo" TEXT NOT NULL,
+    "reference" TEXT NOT NULL,
+    "commit_sha" TEXT NOT NULL,
+    "author_name" TEXT NOT NULL,
+    "author_email" TEXT NOT NULL,
+    "authored_at" TIMESTAMPTZ NOT NULL,
+    "committer_name" TEXT NOT NULL,
+    "committer_email" TEXT NOT NULL,
+    "committed_at" TIMESTAMPTZ NOT NULL,
+    "subject" TEXT NOT NULL,
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    FOREIGN KEY("ingest_session_id") REFERENCES "ur_ingest_session"("ur_ingest_session_id"),
+    UNIQUE("ingest_session_id", "repo")
+);
+CREATE TABLE IF NOT EXISTS "surveilr_remote_sync" (
+    "surveilr_remote_sync_id" VARCHAR PRIMARY KEY NOT NULL,
+    "remote_url" TEXT NOT NULL,
+    "table_name" TEXT NOT NULL,
+    "synced_rowid" INTEGER NOT NULL,
+    "synced_rows" INTEGER NOT NULL,
+    "synced_at" TIMESTAMPTZ NOT NULL,
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    "synced_change_id" INTEGER NOT NULL DEFAULT 0,
+    "scanned_at" TIMESTAMPTZ,
+    UNIQUE("remote_url", "table_name")
+);
+CREATE TABLE IF NOT EXISTS "uniform_resource_equivalence" (
+    "uniform_resource_equivalence_id" VARCHAR PRIMARY KEY NOT NULL,
+    "content_digest" TEXT NOT NULL,
+    "uniform_resource_id" VARCHAR NOT NULL,
+    "device_id" VARCHAR NOT NULL,
+    "uri" TEXT NOT NULL,
+    "device_count" INTEGER NOT NULL,
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    FOREIGN KEY("uniform_resource_id") REFERENCES "uniform_resource"("uniform_resource_id"),
+    FOREIGN KEY("device_id") REFERENCES "device"("device_id"),
+    UNIQUE("content_digest", "uniform_resource_id")
+);
+CREATE TABLE IF NOT EXISTS "uniform_resource_chunk" (
+    "uniform_resource_chunk_id" VARCHAR PRIMARY KEY NOT NULL,
+    "uniform_resource_id" VARCHAR NOT NULL,
+    "chunk_index" INTEGER NOT NULL,
+    "content_digest" TEXT NOT NULL,
+    "size_bytes" INTEGER NOT NULL,
+    "content" BLOB NOT NULL,
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    FOREIGN KEY("uniform_resource_id") REFERENCES "uniform_resource"("uniform_resource_id"),
+    UNIQUE("uniform_resource_id", "chunk_index")
+);
+CREATE TABLE IF NOT EXISTS "ur_ingest_imap_folder_state" (
+    "ur_ingest_imap_folder_state_id" VARCHAR PRIMARY KEY NOT NULL,
+    "email" TEXT NOT NULL,
+    "folder_name" TEXT NOT NULL,
+    "uid_validity" INTEGER NOT NULL,
+    "last_uid" INTEGER NOT NULL,
+    "ingest_session_id" VARCHAR NOT NULL,
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    FOREIGN KEY("ingest_session_id") REFERENCES "ur_ingest_session"("ur_ingest_session_id"),
+    UNIQUE("email", "folder_name")
+);
+CREATE TABLE IF NOT EXISTS "uniform_resource_verification" (
+    "uniform_resource_verification_id" VARCHAR PRIMARY KEY NOT NULL,
+    "verification_id" TEXT NOT NULL,
+    "ingest_session_id" VARCHAR NOT NULL,
+    "uniform_resource_id" VARCHAR,
+    "uri" TEXT NOT NULL,
+    "status" TEXT NOT NULL,
+    "content_digest_algorithm" TEXT NOT NULL,
+    "expected_digest" TEXT,
+    "actual_digest" TEXT,
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    FOREIGN KEY("ingest_session_id") REFERENCES "ur_ingest_session"("ur_ingest_session_id"),
+    FOREIGN KEY("uniform_resource_id") REFERENCES "uniform_resource"("uniform_resource_id"),
+    UNIQUE("verification_id", "uri")
+);
+CREATE TABLE IF NOT EXISTS "surveilr_remote_sync_change" (
+    "surveilr_remote_sync_change_id" INTEGER PRIMARY KEY AUTOINCREMENT,
+    "table_name" TEXT NOT NULL,
+    "row_id" INTEGER NOT NULL,
+    "deleted_key" TEXT CHECK(json_valid(deleted_key) OR deleted_key IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT
+);
 
 CREATE INDEX IF NOT EXISTS "idx_device__name__state" ON "device"("name", "state");
 CREATE INDEX IF NOT EXISTS "idx_ur_ingest_session_fs_path__ingest_session_id__root_path" ON "ur_ingest_session_fs_path"("ingest_session_id", "root_path");
 CREATE INDEX IF NOT EXISTS "idx_uniform_resource__device_id__uri" ON "uniform_resource"("device_id", "uri");
+CREATE INDEX IF NOT EXISTS "idx_uniform_resource__device_id__nature__semantic_identity" ON "uniform_resource"("device_id", "nature", "semantic_identity");
 CREATE INDEX IF NOT EXISTS "idx_uniform_resource_transform__uniform_resource_id__content_digest" ON "uniform_resource_transform"("uniform_resource_id", "content_digest");
 CREATE INDEX IF NOT EXISTS "idx_ur_ingest_session_fs_path_entry__ingest_session_id__file_path_abs" ON "ur_ingest_session_fs_path_entry"("ingest_session_id", "file_path_abs");
 CREATE INDEX IF NOT EXISTS "idx_ur_ingest_session_task__ingest_session_id" ON "ur_ingest_session_task"("ingest_session_id");
 CREATE INDEX IF NOT EXISTS "idx_ur_ingest_session_imap_acct_folder__ingest_session_id__folder_name" ON "ur_ingest_session_imap_acct_folder"("ingest_session_id", "folder_name");
 CREATE INDEX IF NOT EXISTS "idx_ur_ingest_session_imap_acct_folder_message__ingest_session_id" ON "ur_ingest_session_imap_acct_folder_message"("ingest_session_id");
 CREATE INDEX IF NOT EXISTS "idx_ur_ingest_session_imap_account__ingest_session_id__email" ON "ur_ingest_session_imap_account"("ingest_session_id", "email");
-', '4cdfbc7f1483b3ac3d908cb75c939429f8db4384', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+CREATE INDEX IF NOT EXISTS "idx_uniform_resource_lineage__ingest_session_id" ON "uniform_resource_lineage"("ingest_session_id");
+CREATE INDEX IF NOT EXISTS "idx_uniform_resource_lineage__output_uniform_resource_id" ON "uniform_resource_lineage"("output_uniform_resource_id");
+CREATE INDEX IF NOT EXISTS "idx_query_snapshot__name__created_at" ON "query_snapshot"("name", "created_at");
+CREATE INDEX IF NOT EXISTS "idx_surveilr_audit__command__started_at" ON "surveilr_audit"("command", "started_at");
+CREATE INDEX IF NOT EXISTS "idx_ur_ingest_session_imap_acct_stat__ingest_session_id__stat_kind" ON "ur_ingest_session_imap_acct_stat"("ingest_session_id", "stat_kind");
+CREATE INDEX IF NOT EXISTS "idx_policy_finding__pack__assertion__state" ON "policy_finding"("pack", "assertion", "state");
+CREATE INDEX IF NOT EXISTS "idx_known_file_hash__algorithm__digest" ON "known_file_hash"("algorithm", "digest");
+CREATE INDEX IF NOT EXISTS "idx_uniform_resource_known_file__uniform_resource_id" ON "uniform_resource_known_file"("uniform_resource_id");
+CREATE INDEX IF NOT EXISTS "idx_ur_ingest_session_git_repo__repo__commit_sha" ON "ur_ingest_session_git_repo"("repo", "commit_sha");
+CREATE INDEX IF NOT EXISTS "idx_uniform_resource_equivalence__content_digest__device_id" ON "uniform_resource_equivalence"("content_digest", "device_id");
+
+INSERT INTO code_notebook_state (code_notebook_state_id, code_notebook_cell_id, from_state, to_state, transition_reason)
+     SELECT surveilr_pk(), code_notebook_cell_id, ''NONE'', ''EXECUTED'', ''v001_once_initialDDL''
+       FROM code_notebook_cell
+      WHERE notebook_name = ''ConstructionSqlNotebook'' AND cell_name IN (''v009_once_uniformResourceSemanticIdentityDDL'', ''v018_once_deviceClockDDL'', ''v026_once_contentDigestAlgorithmDDL'', ''v028_once_surveilrRemoteSyncChangeDDL'')
+ON CONFLICT DO NOTHING;
+', '6147071a361a16f806970f872e411c54192f5993', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
             interpretable_code = EXCLUDED.interpretable_code,
             notebook_kernel_id = EXCLUDED.notebook_kernel_id,
             updated_at = CURRENT_TIMESTAMP,
             activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
-INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((ulid()), 'SQL', 'ConstructionSqlNotebook', 'v001_seedDML', NULL, 'INSERT INTO "ur_ingest_resource_path_match_rule" ("ur_ingest_resource_path_match_rule_id", "namespace", "regex", "flags", "nature", "priority", "description", "elaboration", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((ulid()), ''default'', ''/(\.git|node_modules)/'', ''IGNORE_RESOURCE'', NULL, NULL, ''Ignore any entry with `/.git/` or `/node_modules/` in the path.'', NULL, (CURRENT_TIMESTAMP), NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT DO NOTHING;
-INSERT INTO "ur_ingest_resource_path_match_rule" ("ur_ingest_resource_path_match_rule_id", "namespace", "regex", "flags", "nature", "priority", "description", "elaboration", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((ulid()), ''default'', ''\.(?P<nature>md|mdx|html|json|jsonc|puml|txt|toml|yml|xml|tap)$'', ''CONTENT_ACQUIRABLE'', ''?P<nature>'', NULL, ''Ingest the content for md, mdx, html, json, jsonc, puml, txt, toml, and yml extensions. Assume the nature is the same as the extension.'', NULL, (CURRENT_TIMESTAMP), NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT DO NOTHING;
-INSERT INTO "ur_ingest_resource_path_match_rule" ("ur_ingest_resource_path_match_rule_id", "namespace", "regex", "flags", "nature", "priority", "description", "elaboration", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((ulid()), ''default'', ''surveilr\[(?P<nature>[^\]]*)\]'', ''CAPTURABLE_EXECUTABLE'', ''?P<nature>'', NULL, ''Any entry with `surveilr-[XYZ]` in the path will be treated as a capturable executable extracting `XYZ` as the nature'', NULL, (CURRENT_TIMESTAMP), NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT DO NOTHING;
-INSERT INTO "ur_ingest_resource_path_match_rule" ("ur_ingest_resource_path_match_rule_id", "namespace", "regex", "flags", "nature", "priority", "description", "elaboration", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((ulid()), ''default'', ''surveilr-SQL'', ''CAPTURABLE_EXECUTABLE | CAPTURABLE_SQL'', NULL, NULL, ''Any entry with surveilr-SQL in the path will be treated as a capturable SQL executable and allow execution of the SQL'', NULL, (CURRENT_TIMESTAMP), NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT DO NOTHING;
-
-INSERT INTO "ur_ingest_resource_path_rewrite_rule" ("ur_ingest_resource_path_rewrite_rule_id", "namespace", "regex", "replace", "priority", "description", "elaboration", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((ulid()), ''default'', ''(\.plantuml)$'', ''.puml'', NULL, ''Treat .plantuml as .puml files'', NULL, (CURRENT_TIMESTAMP), NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT DO NOTHING;
-INSERT INTO "ur_ingest_resource_path_rewrite_rule" ("ur_ingest_resource_path_rewrite_rule_id", "namespace", "regex", "replace", "priority", "description", "elaboration", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((ulid()), ''default'', ''(\.text)$'', ''.txt'', NULL, ''Treat .text as .txt files'', NULL, (CURRENT_TIMESTAMP), NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT DO NOTHING;
-INSERT INTO "ur_ingest_resource_path_rewrite_rule" ("ur_ingest_resource_path_rewrite_rule_id", "namespace", "regex", "replace", "priority", "description", "elaboration", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((ulid()), ''default'', ''(\.yaml)$'', ''.yml'', NULL, ''Treat .yaml as .yml files'', NULL, (CURRENT_TIMESTAMP), NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT DO NOTHING;
-', '49ef5d2732c31bf079ab2548ec0e9f3fcb47b9d8', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'ConstructionSqlNotebook', 'v001_seedDML', NULL, 'INSERT INTO "ur_ingest_resource_path_match_rule" ("ur_ingest_resource_path_match_rule_id", "namespace", "regex", "flags", "nature", "priority", "description", "elaboration", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), ''default'', ''/(\.git|node_modules)/'', ''IGNORE_RESOURCE'', NULL, NULL, ''Ignore any entry with `/.git/` or `/node_modules/` in the path.'', NULL, (CURRENT_TIMESTAMP), NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT DO NOTHING;
+INSERT INTO "ur_ingest_resource_path_match_rule" ("ur_ingest_resource_path_match_rule_id", "namespace", "regex", "flags", "nature", "priority", "description", "elaboration", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), ''default'', ''\.(?P<nature>md|mdx|html|json|jsonc|puml|txt|toml|yml|xml|tap)$'', ''CONTENT_ACQUIRABLE'', ''?P<nature>'', NULL, ''Ingest the content for md, mdx, html, json, jsonc, puml, txt, toml, and yml extensions. Assume the nature is the same as the extension.'', NULL, (CURRENT_TIMESTAMP), NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT DO NOTHING;
+INSERT INTO "ur_ingest_resource_path_match_rule" ("ur_ingest_resource_path_match_rule_id", "namespace", "regex", "flags", "nature", "priority", "description", "elaboration", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), ''default'', ''/systemd/(.+/)?[^/]+\.(service|socket|timer|path|mount|automount)$'', ''CONTENT_ACQUIRABLE'', ''systemd-unit'', NULL, ''Ingest the content of systemd units which start services, sockets, timers, paths and mounts as systemd-unit so their ExecStart, users and schedules are extracted.'', NULL, (CURRENT_TIMESTAMP), NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT DO NOTHING;
+INSERT INTO "ur_ingest_resource_path_match_rule" ("ur_ingest_resource_path_match_rule_id", "namespace", "regex", "flags", "nature", "priority", "description", "elaboration", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), ''default'', ''(/crontab|/cron\.d/[^/]+|/var/spool/cron/.+)$'', ''CONTENT_ACQUIRABLE'', ''crontab'', NULL, ''Ingest the content of system and user crontabs as crontab so their schedules, users and commands are extracted.'', NULL, (CURRENT_TIMESTAMP), NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT DO NOTHING;
+INSERT INTO "ur_ingest_resource_path_match_rule" ("ur_ingest_resource_path_match_rule_id", "namespace", "regex", "flags", "nature", "priority", "description", "elaboration", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), ''default'', ''(/init\.d/[^/]+|/etc/rc\.local)$'', ''CONTENT_ACQUIRABLE'', ''init-script'', NULL, ''Ingest the content of SysV init scripts and rc.local as init-script so the programs they start and their users are extracted.'', NULL, (CURRENT_TIMESTAMP), NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT DO NOTHING;
+INSERT INTO "ur_ingest_resource_path_match_rule" ("ur_ingest_resource_path_match_rule_id", "namespace", "regex", "flags", "nature", "priority", "description", "elaboration", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), ''default'', ''\.pdf$'', ''CONTENT_ACQUIRABLE'', ''pdf'', NULL, ''Ingest the content of PDFs as pdf so their text, page count and document information are extracted.'', NULL, (CURRENT_TIMESTAMP), NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT DO NOTHING;
+INSERT INTO "ur_ingest_resource_path_match_rule" ("ur_ingest_resource_path_match_rule_id", "namespace", "regex", "flags", "nature", "priority", "description", "elaboration", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), ''default'', ''\.(?P<nature>docx|xlsx|pptx)$'', ''CONTENT_ACQUIRABLE'', ''?P<nature>'', NULL, ''Ingest the content of Word documents, Excel workbooks and PowerPoint presentations so their paragraphs, sheet cells and slide text are extracted. Assume the nature is the same as the extension.'', NULL, (CURRENT_TIMESTAMP), NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT DO NOTHING;
+INSERT INTO "ur_ingest_resource_path_match_rule" ("ur_ingest_resource_path_match_rule_id", "namespace", "regex", "flags", "nature", "priority", "description", "elaboration", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), ''default'', ''surveilr\[(?P<nature>[^\]]*)\]'', ''CAPTURABLE_EXECUTABLE'', ''?P<nature>'', NULL, ''Any entry with `surveilr-[XYZ]` in the path will be treated as a capturable executable extracting `XYZ` as the nature'', NULL, (CURRENT_TIMESTAMP), NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT DO NOTHING;
+INSERT INTO "ur_ingest_resource_path_match_rule" ("ur_ingest_resource_path_match_rule_id", "namespace", "regex", "flags", "nature", "priority", "description", "elaboration", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), ''default'', ''surveilr-SQL'', ''CAPTURABLE_EXECUTABLE | CAPTURABLE_SQL'', NULL, NULL, ''Any entry with surveilr-SQL in the path will be treated as a capturable SQL executable and allow execution of the SQL'', NULL, (CURRENT_TIMESTAMP), NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT DO NOTHING;
+
+INSERT INTO "ur_ingest_resource_path_rewrite_rule" ("ur_ingest_resource_path_rewrite_rule_id", "namespace", "regex", "replace", "priority", "description", "elaboration", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), ''default'', ''(\.plantuml)$'', ''.puml'', NULL, ''Treat .plantuml as .puml files'', NULL, (CURRENT_TIMESTAMP), NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT DO NOTHING;
+INSERT INTO "ur_ingest_resource_path_rewrite_rule" ("ur_ingest_resource_path_rewrite_rule_id", "namespace", "regex", "replace", "priority", "description", "elaboration", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), ''default'', ''(\.text)$'', ''.txt'', NULL, ''Treat .text as .txt files'', NULL, (CURRENT_TIMESTAMP), NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT DO NOTHING;
+INSERT INTO "ur_ingest_resource_path_rewrite_rule" ("ur_ingest_resource_path_rewrite_rule_id", "namespace", "regex", "replace", "priority", "description", "elaboration", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), ''default'', ''(\.yaml)$'', ''.yml'', NULL, ''Treat .yaml as .yml files'', NULL, (CURRENT_TIMESTAMP), NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT DO NOTHING;
+', '4401af7edb670bf2966e5bf5672232e0b2b6643c', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
             interpretable_code = EXCLUDED.interpretable_code,
             notebook_kernel_id = EXCLUDED.notebook_kernel_id,
             updated_at = CURRENT_TIMESTAMP,
             activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
-INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((ulid()), 'SQL', 'ConstructionSqlNotebook', 'v002_fsContentIngestSessionFilesStatsViewDDL', NULL, 'DROP VIEW IF EXISTS "ur_ingest_session_files_stats";
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'ConstructionSqlNotebook', 'v002_fsContentIngestSessionFilesStatsViewDDL', NULL, 'DROP VIEW IF EXISTS "ur_ingest_session_files_stats";
 CREATE VIEW IF NOT EXISTS "ur_ingest_session_files_stats" AS
     WITH Summary AS (
         SELECT
@@ -505,7 +859,7 @@ CREATE VIEW IF NOT EXISTS "ur_ingest_session_files_stats" AS
             notebook_kernel_id = EXCLUDED.notebook_kernel_id,
             updated_at = CURRENT_TIMESTAMP,
             activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
-INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((ulid()), 'SQL', 'ConstructionSqlNotebook', 'v002_fsContentIngestSessionFilesStatsLatestViewDDL', NULL, 'DROP VIEW IF EXISTS "ur_ingest_session_files_stats_latest";
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'ConstructionSqlNotebook', 'v002_fsContentIngestSessionFilesStatsLatestViewDDL', NULL, 'DROP VIEW IF EXISTS "ur_ingest_session_files_stats_latest";
 CREATE VIEW IF NOT EXISTS "ur_ingest_session_files_stats_latest" AS
     SELECT iss.*
       FROM ur_ingest_session_files_stats AS iss
@@ -518,7 +872,7 @@ CREATE VIEW IF NOT EXISTS "ur_ingest_session_files_stats_latest" AS
             notebook_kernel_id = EXCLUDED.notebook_kernel_id,
             updated_at = CURRENT_TIMESTAMP,
             activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
-INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((ulid()), 'SQL', 'ConstructionSqlNotebook', 'v002_urIngestSessionTasksStatsViewDDL', NULL, 'DROP VIEW IF EXISTS "ur_ingest_session_tasks_stats";
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'ConstructionSqlNotebook', 'v002_urIngestSessionTasksStatsViewDDL', NULL, 'DROP VIEW IF EXISTS "ur_ingest_session_tasks_stats";
 CREATE VIEW IF NOT EXISTS "ur_ingest_session_tasks_stats" AS
       WITH Summary AS (
           SELECT
@@ -576,7 +930,7 @@ CREATE VIEW IF NOT EXISTS "ur_ingest_session_tasks_stats" AS
             notebook_kernel_id = EXCLUDED.notebook_kernel_id,
             updated_at = CURRENT_TIMESTAMP,
             activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
-INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((ulid()), 'SQL', 'ConstructionSqlNotebook', 'v002_urIngestSessionTasksStatsLatestViewDDL', NULL, 'DROP VIEW IF EXISTS "ur_ingest_session_tasks_stats_latest";
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'ConstructionSqlNotebook', 'v002_urIngestSessionTasksStatsLatestViewDDL', NULL, 'DROP VIEW IF EXISTS "ur_ingest_session_tasks_stats_latest";
 CREATE VIEW IF NOT EXISTS "ur_ingest_session_tasks_stats_latest" AS
     SELECT iss.*
       FROM ur_ingest_session_tasks_stats AS iss
@@ -589,7 +943,7 @@ CREATE VIEW IF NOT EXISTS "ur_ingest_session_tasks_stats_latest" AS
             notebook_kernel_id = EXCLUDED.notebook_kernel_id,
             updated_at = CURRENT_TIMESTAMP,
             activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
-INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((ulid()), 'SQL', 'ConstructionSqlNotebook', 'v002_urIngestSessionFileIssueViewDDL', NULL, 'DROP VIEW IF EXISTS "ur_ingest_session_file_issue";
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'ConstructionSqlNotebook', 'v002_urIngestSessionFileIssueViewDDL', NULL, 'DROP VIEW IF EXISTS "ur_ingest_session_file_issue";
 CREATE VIEW IF NOT EXISTS "ur_ingest_session_file_issue" AS
       SELECT us.device_id,
              us.ur_ingest_session_id,
@@ -615,209 +969,159 @@ CREATE VIEW IF NOT EXISTS "ur_ingest_session_file_issue" AS
             notebook_kernel_id = EXCLUDED.notebook_kernel_id,
             updated_at = CURRENT_TIMESTAMP,
             activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
-INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((ulid()), 'SQL', 'QuerySqlNotebook', 'infoSchema', NULL, 'SELECT tbl_name AS table_name,
-       c.cid AS column_id,
-       c.name AS column_name,
-       c."type" AS "type",
-       c."notnull" AS "notnull",
-       c.dflt_value as "default_value",
-       c.pk AS primary_key
-  FROM sqlite_master m,
-       pragma_table_info(m.tbl_name) c
- WHERE m.type = ''table'';', 'b51a0cbc609deb9b4046bb02b670e7686ac678fe', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'ConstructionSqlNotebook', 'v003_once_uniformResourceLineageDDL', NULL, 'CREATE TABLE IF NOT EXISTS "uniform_resource_lineage" (
+    "uniform_resource_lineage_id" VARCHAR PRIMARY KEY NOT NULL,
+    "ingest_session_id" VARCHAR NOT NULL,
+    "source_uniform_resource_id" VARCHAR,
+    "output_uniform_resource_id" VARCHAR NOT NULL,
+    "ingest_fs_path_entry_id" VARCHAR,
+    "ingest_task_id" VARCHAR,
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    FOREIGN KEY("ingest_session_id") REFERENCES "ur_ingest_session"("ur_ingest_session_id"),
+    FOREIGN KEY("source_uniform_resource_id") REFERENCES "uniform_resource"("uniform_resource_id"),
+    FOREIGN KEY("output_uniform_resource_id") REFERENCES "uniform_resource"("uniform_resource_id"),
+    FOREIGN KEY("ingest_fs_path_entry_id") REFERENCES "ur_ingest_session_fs_path_entry"("ur_ingest_session_fs_path_entry_id"),
+    FOREIGN KEY("ingest_task_id") REFERENCES "ur_ingest_session_task"("ur_ingest_session_task_id")
+);
+
+CREATE INDEX IF NOT EXISTS "idx_uniform_resource_lineage__ingest_session_id" ON "uniform_resource_lineage"("ingest_session_id");
+CREATE INDEX IF NOT EXISTS "idx_uniform_resource_lineage__output_uniform_resource_id" ON "uniform_resource_lineage"("output_uniform_resource_id");
+', 'e419362d1e4a16a2cc4399c8c116e6e237d13f75', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
             interpretable_code = EXCLUDED.interpretable_code,
             notebook_kernel_id = EXCLUDED.notebook_kernel_id,
             updated_at = CURRENT_TIMESTAMP,
             activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
-INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((ulid()), 'SQL', 'QuerySqlNotebook', 'infoSchemaOsQueryATCs', NULL, 'WITH table_columns AS (
-    SELECT m.tbl_name AS table_name,
-           group_concat(c.name) AS column_names_for_select,
-           json_group_array(c.name) AS column_names_for_atc_json
-      FROM sqlite_master m,
-           pragma_table_info(m.tbl_name) c
-     WHERE m.type = ''table''
-  GROUP BY m.tbl_name
-),
-target AS (
-  -- set SQLite parameter :osquery_atc_path to assign a different path
-  SELECT COALESCE(:osquery_atc_path, ''SQLITEDB_PATH'') AS path
-),
-table_query AS (
-    SELECT table_name,
-           ''SELECT '' || column_names_for_select || '' FROM '' || table_name AS query,
-           column_names_for_atc_json
-      FROM table_columns
-)
-SELECT json_object(''auto_table_construction'',
-          json_group_object(
-              table_name,
-              json_object(
-                  ''query'', query,
-                  ''columns'', json(column_names_for_atc_json),
-                  ''path'', path
-              )
-          )
-       ) AS osquery_auto_table_construction
-  FROM table_query, target;', '375b0b4fe291ebac350dc4ed9304f17b57ba8e44', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'ConstructionSqlNotebook', 'v004_once_rssdMetadataDDL', NULL, 'CREATE TABLE IF NOT EXISTS "rssd_metadata" (
+    "key" TEXT PRIMARY KEY NOT NULL,
+    "value" TEXT NOT NULL,
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT
+);
+', '548fc3862c61adbada418a3aac513d99c533f358', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
             interpretable_code = EXCLUDED.interpretable_code,
             notebook_kernel_id = EXCLUDED.notebook_kernel_id,
             updated_at = CURRENT_TIMESTAMP,
             activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
-INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((ulid()), 'SQL', 'QuerySqlNotebook', 'infoSchemaMarkdown', NULL, '-- TODO: https://github.com/lovasoa/SQLpage/discussions/109#discussioncomment-7359513
---       see the above for how to fix for SQLPage but figure out to use the same SQL
---       in and out of SQLPage (maybe do what Ophir said in discussion and create
---       custom output for SQLPage using componetns?)
-WITH TableInfo AS (
-  SELECT
-    m.tbl_name AS table_name,
-    CASE WHEN c.pk THEN ''*'' ELSE '''' END AS is_primary_key,
-    c.name AS column_name,
-    c."type" AS column_type,
-    CASE WHEN c."notnull" THEN ''*'' ELSE '''' END AS not_null,
-    COALESCE(c.dflt_value, '''') AS default_value,
-    COALESCE((SELECT pfkl."table" || ''.'' || pfkl."to" FROM pragma_foreign_key_list(m.tbl_name) AS pfkl WHERE pfkl."from" = c.name), '''') as fk_refs,
-    ROW_NUMBER() OVER (PARTITION BY m.tbl_name ORDER BY c.cid) AS row_num
-  FROM sqlite_master m JOIN pragma_table_info(m.tbl_name) c ON 1=1
-  WHERE m.type = ''table''
-  ORDER BY table_name, row_num
-),
-Views AS (
-  SELECT ''## Views '' AS markdown_output
-  UNION ALL
-  SELECT ''| View | Column | Type |'' AS markdown_output
-  UNION ALL
-  SELECT ''| ---- | ------ |----- |'' AS markdown_output
-  UNION ALL
-  SELECT ''| '' || tbl_name || '' | '' || c.name || '' | '' || c."type" || '' | ''
-  FROM
-    sqlite_master m,
-    pragma_table_info(m.tbl_name) c
-  WHERE
-    m.type = ''view''
-),
-Indexes AS (
-  SELECT ''## Indexes'' AS markdown_output
-  UNION ALL
-  SELECT ''| Table | Index | Columns |'' AS markdown_output
-  UNION ALL
-  SELECT ''| ----- | ----- | ------- |'' AS markdown_output
-  UNION ALL
-  SELECT ''| '' ||  m.name || '' | '' || il.name || '' | '' || group_concat(ii.name, '', '') || '' |'' AS markdown_output
-  FROM sqlite_master as m,
-    pragma_index_list(m.name) AS il,
-    pragma_index_info(il.name) AS ii
-  WHERE
-    m.type = ''table''
-  GROUP BY
-    m.name,
-    il.name
-)
-SELECT
-    markdown_output AS info_schema_markdown
-FROM
-  (
-    SELECT ''## Tables'' AS markdown_output
-    UNION ALL
-    SELECT
-      CASE WHEN ti.row_num = 1 THEN ''
-### `'' || ti.table_name || ''` Table
-| PK | Column | Type | Req? | Default | References |
-| -- | ------ | ---- | ---- | ------- | ---------- |
-'' ||
-        ''| '' || is_primary_key || '' | '' || ti.column_name || '' | '' || ti.column_type || '' | '' || ti.not_null || '' | '' || ti.default_value || '' | '' || ti.fk_refs || '' |''
-      ELSE
-        ''| '' || is_primary_key || '' | '' || ti.column_name || '' | '' || ti.column_type || '' | '' || ti.not_null || '' | '' || ti.default_value || '' | '' || ti.fk_refs || '' |''
-      END
-    FROM TableInfo ti
-    UNION ALL SELECT ''''
-    UNION ALL SELECT * FROM	Views
-    UNION ALL SELECT ''''
-    UNION ALL SELECT * FROM Indexes
-);', 'cbe800ad94b1b90f0045a78a03e9da246e46ff75', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'ConstructionSqlNotebook', 'v005_once_querySnapshotDDL', NULL, 'CREATE TABLE IF NOT EXISTS "query_snapshot" (
+    "query_snapshot_id" VARCHAR PRIMARY KEY NOT NULL,
+    "name" TEXT NOT NULL,
+    "query_sql" TEXT NOT NULL,
+    "result_schema" TEXT CHECK(json_valid(result_schema)) NOT NULL,
+    "result_rows" TEXT CHECK(json_valid(result_rows)) NOT NULL,
+    "row_count" INTEGER NOT NULL,
+    "content_digest" TEXT NOT NULL,
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT
+);
+
+CREATE INDEX IF NOT EXISTS "idx_query_snapshot__name__created_at" ON "query_snapshot"("name", "created_at");
+', '54b78b578ec54fb2ddfe4b81512e729f1e16f54a', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
             interpretable_code = EXCLUDED.interpretable_code,
             notebook_kernel_id = EXCLUDED.notebook_kernel_id,
             updated_at = CURRENT_TIMESTAMP,
             activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
-INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((ulid()), 'SQL', 'QuerySqlNotebook', 'htmlAnchors', NULL, '-- loadExtnSQL not provided to load ''asg017/html/html0''
-
--- find all HTML files in the uniform_resource table and return
--- each file and the anchors'' labels and hrefs in that file
--- TODO: create a table called fs_content_html_anchor to store this data after inserting it into uniform_resource
---       so that simple HTML lookups do not require the html0 extension to be loaded
-WITH html_content AS (
-  SELECT uniform_resource_id, content, content_digest, file_path, file_extn FROM uniform_resource WHERE nature = ''html''
-),
-html AS (
-  SELECT file_path,
-         text as label,
-         html_attribute_get(html, ''a'', ''href'') as href
-    FROM html_content, html_each(html_content.content, ''a'')
-)
-SELECT * FROM html;
-      ', '7d9cdf2286d82fae97cb3652e6b17744ebf7c817', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'ConstructionSqlNotebook', 'v006_once_redactImapAccountPasswords', NULL, 'UPDATE ur_ingest_session_imap_account
+   SET password = surveilr_credential_digest(password)
+ WHERE password IS NOT NULL;
+', '4655e3c8b83f3c700b7332956b515fc70a3c1f1c', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
             interpretable_code = EXCLUDED.interpretable_code,
             notebook_kernel_id = EXCLUDED.notebook_kernel_id,
             updated_at = CURRENT_TIMESTAMP,
             activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
-INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((ulid()), 'SQL', 'QuerySqlNotebook', 'htmlHeadMeta', NULL, '-- loadExtnSQL not provided to load ''asg017/html/html0''
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'ConstructionSqlNotebook', 'v007_once_surveilrAuditDDL', NULL, 'CREATE TABLE IF NOT EXISTS "surveilr_audit" (
+    "surveilr_audit_id" VARCHAR PRIMARY KEY NOT NULL,
+    "command" TEXT NOT NULL,
+    "argv" TEXT CHECK(json_valid(argv)) NOT NULL,
+    "os_user" TEXT NOT NULL,
+    "host" TEXT NOT NULL,
+    "started_at" TIMESTAMPTZ NOT NULL,
+    "outcome" TEXT NOT NULL,
+    "error" TEXT,
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT
+);
 
--- find all HTML files in the uniform_resource table and return
--- each file and the <head><meta name="key" content="value"> pair
--- TODO: create a table called resource_html_head_meta to store this data after inserting it into uniform_resource
---       so that simple HTML lookups do not require the html0 extension to be loaded
-WITH html_content AS (
-  SELECT uniform_resource_id, content, content_digest, file_path, file_extn FROM uniform_resource WHERE nature = ''html''
-),
-html AS (
-  SELECT file_path,
-         html_attribute_get(html, ''meta'', ''name'') as key,
-         html_attribute_get(html, ''meta'', ''content'') as value,
-         html
-    FROM html_content, html_each(html_content.content, ''head meta'')
-   WHERE key IS NOT NULL
-)
-SELECT * FROM html;
-      ', '70d990a17e2ce8d0ee2cf6dd368390c8b1db7645', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+CREATE INDEX IF NOT EXISTS "idx_surveilr_audit__command__started_at" ON "surveilr_audit"("command", "started_at");
+', 'a08cf69870ebab7666c828cb7b5bcf26518178cd', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
             interpretable_code = EXCLUDED.interpretable_code,
             notebook_kernel_id = EXCLUDED.notebook_kernel_id,
             updated_at = CURRENT_TIMESTAMP,
             activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
-INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((ulid()), 'SQL', 'AssuranceSqlNotebook', 'test1', NULL, 'WITH test_plan AS (
-    SELECT ''1..1'' AS tap_output
-),
-test1 AS (  -- Check if the ''fileio'' extension is loaded by calling the ''readfile'' function
-    SELECT
-        CASE
-            WHEN readfile(''README.md'') IS NOT NULL THEN ''ok 1 - fileio extension is loaded.''
-            ELSE ''not ok 1 - fileio extension is not loaded.''
-        END AS tap_output
-    FROM (SELECT 1) -- This is a dummy table of one row to ensure the SELECT runs.
-)
-SELECT tap_output FROM test_plan
-UNION ALL
-SELECT tap_output FROM test1;', '083111d6c0f2b4b0670eee784f113ddd8619187d', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'ConstructionSqlNotebook', 'v008_once_urIngestSessionImapAcctStatDDL', NULL, 'CREATE TABLE IF NOT EXISTS "ur_ingest_session_imap_acct_stat" (
+    "ur_ingest_session_imap_acct_stat_id" VARCHAR PRIMARY KEY NOT NULL,
+    "ingest_session_id" VARCHAR NOT NULL,
+    "ingest_account_id" VARCHAR NOT NULL,
+    "stat_kind" TEXT NOT NULL,
+    "stat_key" TEXT NOT NULL,
+    "stat_value" INTEGER NOT NULL,
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    FOREIGN KEY("ingest_session_id") REFERENCES "ur_ingest_session"("ur_ingest_session_id"),
+    FOREIGN KEY("ingest_account_id") REFERENCES "ur_ingest_session_imap_account"("ur_ingest_session_imap_account_id")
+);
+
+CREATE INDEX IF NOT EXISTS "idx_ur_ingest_session_imap_acct_stat__ingest_session_id__stat_kind" ON "ur_ingest_session_imap_acct_stat"("ingest_session_id", "stat_kind");
+', 'c7b9409b75e1275456d3bdb0e34eceb49421cc07', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
             interpretable_code = EXCLUDED.interpretable_code,
             notebook_kernel_id = EXCLUDED.notebook_kernel_id,
             updated_at = CURRENT_TIMESTAMP,
             activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
-INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((ulid()), 'LLM Prompt', 'LargeLanguageModelsPromptsNotebook', 'understand notebooks schema', NULL, 'Understand the following structure of an SQLite database designed to store code notebooks and execution kernels. The database comprises three main tables: ''code_notebook_kernel'', ''code_notebook_cell'', and ''code_notebook_state''.
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'ConstructionSqlNotebook', 'v009_once_uniformResourceSemanticIdentityDDL', NULL, 'ALTER TABLE uniform_resource ADD COLUMN semantic_identity TEXT;
 
-1. ''code_notebook_kernel'': This table stores information about various kernels or execution engines. Each record includes a unique kernel ID, kernel name, a description, MIME type, file extension, and other metadata such as creation and update timestamps.
-
-2. ''code_notebook_cell'': This table contains individual notebook cells. Each cell is linked to a kernel in the ''code_notebook_kernel'' table via ''notebook_kernel_id''. It includes details like the cell''s unique ID, notebook name, cell name, interpretable code, and relevant metadata.
-
-3. ''code_notebook_state'': This table tracks the state transitions of notebook cells. Each record links to a cell in the ''code_notebook_cell'' table and includes information about the state transition, such as the previous and new states, transition reason, and timestamps.
-
-The relationships are as follows: Each cell in ''code_notebook_cell'' is associated with a kernel in ''code_notebook_kernel''. The ''code_notebook_state'' table tracks changes in the state of each cell, linking back to the ''code_notebook_cell'' table.
-
-Use the following SQLite Schema to generate SQL queries that interact with these tables and once you understand them let me know so I can ask you for help:
-
-CREATE TABLE IF NOT EXISTS "assurance_schema" (
-    "assurance_schema_id" VARCHAR PRIMARY KEY NOT NULL,
-    "assurance_type" TEXT NOT NULL,
-    "code" TEXT NOT NULL,
-    "code_json" TEXT CHECK(json_valid(code_json) OR code_json IS NULL),
-    "governance" TEXT CHECK(json_valid(governance) OR governance IS NULL),
+CREATE INDEX IF NOT EXISTS "idx_uniform_resource__device_id__uri" ON "uniform_resource"("device_id", "uri");
+CREATE INDEX IF NOT EXISTS "idx_uniform_resource__device_id__nature__semantic_identity" ON "uniform_resource"("device_id", "nature", "semantic_identity");
+', 'e95cf1720e493bfdc3b3b17774cf7fa5af364fa0', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+            interpretable_code = EXCLUDED.interpretable_code,
+            notebook_kernel_id = EXCLUDED.notebook_kernel_id,
+            updated_at = CURRENT_TIMESTAMP,
+            activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'ConstructionSqlNotebook', 'v010_once_policyFindingDDL', NULL, 'CREATE TABLE IF NOT EXISTS "policy_finding" (
+    "policy_finding_id" VARCHAR PRIMARY KEY NOT NULL,
+    "pack" TEXT NOT NULL,
+    "assertion" TEXT NOT NULL,
+    "severity" TEXT NOT NULL,
+    "state" TEXT NOT NULL,
+    "status" TEXT NOT NULL,
+    "row_count" INTEGER NOT NULL,
+    "sample_rows" TEXT CHECK(json_valid(sample_rows) OR sample_rows IS NULL),
+    "remediation" TEXT,
+    "error" TEXT,
+    "last_seen_at" TIMESTAMPTZ NOT NULL,
+    "acknowledged_at" TIMESTAMPTZ,
+    "acknowledged_by" TEXT,
+    "resolved_at" TIMESTAMPTZ,
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
     "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
     "created_by" TEXT DEFAULT ''UNKNOWN'',
     "updated_at" TIMESTAMPTZ,
@@ -826,14 +1130,43 @@ CREATE TABLE IF NOT EXISTS "assurance_schema" (
     "deleted_by" TEXT,
     "activity_log" TEXT
 );
-CREATE TABLE IF NOT EXISTS "code_notebook_kernel" (
-    "code_notebook_kernel_id" VARCHAR PRIMARY KEY NOT NULL,
-    "kernel_name" TEXT NOT NULL,
+
+CREATE INDEX IF NOT EXISTS "idx_policy_finding__pack__assertion__state" ON "policy_finding"("pack", "assertion", "state");
+', '6308118a9e4fdf384dd3fa772f33d67ec0a3bc70', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+            interpretable_code = EXCLUDED.interpretable_code,
+            notebook_kernel_id = EXCLUDED.notebook_kernel_id,
+            updated_at = CURRENT_TIMESTAMP,
+            activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'ConstructionSqlNotebook', 'v011_once_policyAlertRuleDDL', NULL, 'CREATE TABLE IF NOT EXISTS "policy_alert_rule" (
+    "policy_alert_rule_id" VARCHAR PRIMARY KEY NOT NULL,
+    "name" TEXT NOT NULL,
+    "min_severity" TEXT NOT NULL,
+    "fire_on" TEXT NOT NULL,
+    "channel" TEXT NOT NULL,
+    "target" TEXT NOT NULL,
+    "packs" TEXT CHECK(json_valid(packs) OR packs IS NULL),
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    UNIQUE("name")
+);
+', 'db566d5262179298d3e993cfdef71d632b2eee0b', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+            interpretable_code = EXCLUDED.interpretable_code,
+            notebook_kernel_id = EXCLUDED.notebook_kernel_id,
+            updated_at = CURRENT_TIMESTAMP,
+            activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'ConstructionSqlNotebook', 'v012_once_complianceControlDDL', NULL, 'CREATE TABLE IF NOT EXISTS "compliance_control" (
+    "compliance_control_id" VARCHAR PRIMARY KEY NOT NULL,
+    "framework" TEXT NOT NULL,
+    "control_code" TEXT NOT NULL,
+    "title" TEXT,
     "description" TEXT,
-    "mime_type" TEXT,
-    "file_extn" TEXT,
     "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
-    "governance" TEXT CHECK(json_valid(governance) OR governance IS NULL),
     "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
     "created_by" TEXT DEFAULT ''UNKNOWN'',
     "updated_at" TIMESTAMPTZ,
@@ -841,18 +1174,20 @@ CREATE TABLE IF NOT EXISTS "code_notebook_kernel" (
     "deleted_at" TIMESTAMPTZ,
     "deleted_by" TEXT,
     "activity_log" TEXT,
-    UNIQUE("kernel_name")
+    UNIQUE("framework", "control_code")
 );
-CREATE TABLE IF NOT EXISTS "code_notebook_cell" (
-    "code_notebook_cell_id" VARCHAR PRIMARY KEY NOT NULL,
-    "notebook_kernel_id" VARCHAR NOT NULL,
-    "notebook_name" TEXT NOT NULL,
-    "cell_name" TEXT NOT NULL,
-    "cell_governance" TEXT CHECK(json_valid(cell_governance) OR cell_governance IS NULL),
-    "interpretable_code" TEXT NOT NULL,
-    "interpretable_code_hash" TEXT NOT NULL,
+', '2cf550e73f707480d197b7828420bf85648a7a42', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+            interpretable_code = EXCLUDED.interpretable_code,
+            notebook_kernel_id = EXCLUDED.notebook_kernel_id,
+            updated_at = CURRENT_TIMESTAMP,
+            activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'ConstructionSqlNotebook', 'v013_once_complianceEvidenceMapDDL', NULL, 'CREATE TABLE IF NOT EXISTS "compliance_evidence_map" (
+    "compliance_evidence_map_id" VARCHAR PRIMARY KEY NOT NULL,
+    "compliance_control_id" VARCHAR NOT NULL,
+    "evidence_kind" TEXT NOT NULL,
+    "evidence_selector" TEXT NOT NULL,
     "description" TEXT,
-    "arguments" TEXT CHECK(json_valid(arguments) OR arguments IS NULL),
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
     "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
     "created_by" TEXT DEFAULT ''UNKNOWN'',
     "updated_at" TIMESTAMPTZ,
@@ -860,17 +1195,22 @@ CREATE TABLE IF NOT EXISTS "code_notebook_cell" (
     "deleted_at" TIMESTAMPTZ,
     "deleted_by" TEXT,
     "activity_log" TEXT,
-    FOREIGN KEY("notebook_kernel_id") REFERENCES "code_notebook_kernel"("code_notebook_kernel_id"),
-    UNIQUE("notebook_name", "cell_name", "interpretable_code_hash")
+    FOREIGN KEY("compliance_control_id") REFERENCES "compliance_control"("compliance_control_id"),
+    UNIQUE("compliance_control_id", "evidence_kind", "evidence_selector")
 );
-CREATE TABLE IF NOT EXISTS "code_notebook_state" (
-    "code_notebook_state_id" VARCHAR PRIMARY KEY NOT NULL,
-    "code_notebook_cell_id" VARCHAR NOT NULL,
-    "from_state" TEXT NOT NULL,
-    "to_state" TEXT NOT NULL,
-    "transition_result" TEXT CHECK(json_valid(transition_result) OR transition_result IS NULL),
-    "transition_reason" TEXT,
-    "transitioned_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+', 'f63c88a0f9b3af283abc79835f8a2ad65ca90a4e', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+            interpretable_code = EXCLUDED.interpretable_code,
+            notebook_kernel_id = EXCLUDED.notebook_kernel_id,
+            updated_at = CURRENT_TIMESTAMP,
+            activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'ConstructionSqlNotebook', 'v014_once_knownFileHashDDL', NULL, 'CREATE TABLE IF NOT EXISTS "known_file_hash" (
+    "known_file_hash_id" VARCHAR PRIMARY KEY NOT NULL,
+    "hash_set" TEXT NOT NULL,
+    "algorithm" TEXT NOT NULL,
+    "digest" TEXT NOT NULL,
+    "disposition" TEXT NOT NULL,
+    "confidence" FLOAT NOT NULL,
+    "file_name" TEXT,
     "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
     "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
     "created_by" TEXT DEFAULT ''UNKNOWN'',
@@ -879,31 +1219,882 @@ CREATE TABLE IF NOT EXISTS "code_notebook_state" (
     "deleted_at" TIMESTAMPTZ,
     "deleted_by" TEXT,
     "activity_log" TEXT,
-    FOREIGN KEY("code_notebook_cell_id") REFERENCES "code_notebook_cell"("code_notebook_cell_id"),
-    UNIQUE("code_notebook_cell_id", "from_state", "to_state")
+    UNIQUE("hash_set", "algorithm", "digest")
 );
 
+CREATE INDEX IF NOT EXISTS "idx_known_file_hash__algorithm__digest" ON "known_file_hash"("algorithm", "digest");
+', '178fecad19eb27b9ed64a8b9178c7cac0dde22aa', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+            interpretable_code = EXCLUDED.interpretable_code,
+            notebook_kernel_id = EXCLUDED.notebook_kernel_id,
+            updated_at = CURRENT_TIMESTAMP,
+            activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'ConstructionSqlNotebook', 'v015_once_uniformResourceKnownFileDDL', NULL, 'CREATE TABLE IF NOT EXISTS "uniform_resource_known_file" (
+    "uniform_resource_known_file_id" VARCHAR PRIMARY KEY NOT NULL,
+    "uniform_resource_id" VARCHAR NOT NULL,
+    "known_file_hash_id" VARCHAR NOT NULL,
+    "hash_set" TEXT NOT NULL,
+    "disposition" TEXT NOT NULL,
+    "confidence" FLOAT NOT NULL,
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    FOREIGN KEY("uniform_resource_id") REFERENCES "uniform_resource"("uniform_resource_id"),
+    FOREIGN KEY("known_file_hash_id") REFERENCES "known_file_hash"("known_file_hash_id"),
+    UNIQUE("uniform_resource_id", "known_file_hash_id")
+);
 
-
-      ', '85958d3630a5761a53dade44c67533e246de074e', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
-                   interpretable_code = EXCLUDED.interpretable_code,
-                   notebook_kernel_id = EXCLUDED.notebook_kernel_id,
-                   updated_at = CURRENT_TIMESTAMP,
-                   activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
-INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((ulid()), 'LLM Prompt', 'LargeLanguageModelsPromptsNotebook', 'understand service schema', NULL, 'Understand the following structure of an SQLite database designed to store cybersecurity and compliance data for files in a file system.
-The database is designed to store devices in the ''device'' table and entities called ''resources'' stored in the immutable append-only
-''uniform_resource'' table. Each time files are "walked" they are stored in ingestion session and link back to ''uniform_resource''. Because all
-tables are generally append only and immutable it means that the ingest_session_fs_path_entry table can be used for revision control
-and historical tracking of file changes.
-
-Use the following SQLite Schema to generate SQL queries that interact with these tables and once you understand them let me know so I can ask you for help:
-
-CREATE TABLE IF NOT EXISTS "device" (
-    "device_id" VARCHAR PRIMARY KEY NOT NULL,
-    "name" TEXT NOT NULL,
-    "state" TEXT CHECK(json_valid(state)) NOT NULL,
-    "boundary" TEXT NOT NULL,
-    "segmentation" TEXT CHECK(json_valid(segmentation) OR segmentation IS NULL),
+CREATE INDEX IF NOT EXISTS "idx_uniform_resource_known_file__uniform_resource_id" ON "uniform_resource_known_file"("uniform_resource_id");
+', '0a4be4539b745f9f9a3480cbb7066f17c2100ecf', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+            interpretable_code = EXCLUDED.interpretable_code,
+            notebook_kernel_id = EXCLUDED.notebook_kernel_id,
+            updated_at = CURRENT_TIMESTAMP,
+            activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'ConstructionSqlNotebook', 'v016_persistenceSystemdUnitViewDDL', NULL, 'DROP VIEW IF EXISTS "persistence_systemd_unit";
+CREATE VIEW IF NOT EXISTS "persistence_systemd_unit" AS
+      SELECT ur.device_id,
+             urt.uniform_resource_id,
+             ur.uri,
+             json_extract(urt.content, ''$.unit'') AS unit,
+             json_extract(urt.content, ''$.unit_type'') AS unit_type,
+             json_extract(urt.content, ''$.description'') AS description,
+             json_extract(urt.content, ''$.exec_start'') AS exec_start,
+             json_extract(urt.content, ''$.user'') AS user,
+             json_extract(urt.content, ''$.group'') AS "group",
+             json_extract(urt.content, ''$.wanted_by'') AS wanted_by,
+             json_extract(urt.content, ''$.on_calendar'') AS on_calendar,
+             urt.content
+        FROM uniform_resource_transform urt
+        JOIN uniform_resource ur ON ur.uniform_resource_id = urt.uniform_resource_id
+       WHERE json_extract(urt.elaboration, ''$.persistence'') = ''systemd-unit'';', 'e51e5dd8a580c618099afc8702a4650ac5620c33', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+            interpretable_code = EXCLUDED.interpretable_code,
+            notebook_kernel_id = EXCLUDED.notebook_kernel_id,
+            updated_at = CURRENT_TIMESTAMP,
+            activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'ConstructionSqlNotebook', 'v016_persistenceCronJobViewDDL', NULL, 'DROP VIEW IF EXISTS "persistence_cron_job";
+CREATE VIEW IF NOT EXISTS "persistence_cron_job" AS
+      SELECT ur.device_id,
+             urt.uniform_resource_id,
+             ur.uri,
+             json_extract(job.value, ''$.schedule'') AS schedule,
+             json_extract(job.value, ''$.user'') AS user,
+             json_extract(job.value, ''$.command'') AS command
+        FROM uniform_resource_transform urt
+        JOIN uniform_resource ur ON ur.uniform_resource_id = urt.uniform_resource_id,
+             json_each(urt.content, ''$.jobs'') AS job
+       WHERE json_extract(urt.elaboration, ''$.persistence'') = ''crontab'';', '83c2268ed150b1aa8511e5e6195665f6c9c87bc7', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+            interpretable_code = EXCLUDED.interpretable_code,
+            notebook_kernel_id = EXCLUDED.notebook_kernel_id,
+            updated_at = CURRENT_TIMESTAMP,
+            activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'ConstructionSqlNotebook', 'v016_persistenceInitScriptViewDDL', NULL, 'DROP VIEW IF EXISTS "persistence_init_script";
+CREATE VIEW IF NOT EXISTS "persistence_init_script" AS
+      SELECT ur.device_id,
+             urt.uniform_resource_id,
+             ur.uri,
+             json_extract(urt.content, ''$.info.provides'') AS provides,
+             json_extract(urt.content, ''$.info.default_start'') AS default_start,
+             json_extract(urt.content, ''$.interpreter'') AS interpreter,
+             json_extract(urt.content, ''$.exec'') AS exec,
+             json_extract(urt.content, ''$.users'') AS users
+        FROM uniform_resource_transform urt
+        JOIN uniform_resource ur ON ur.uniform_resource_id = urt.uniform_resource_id
+       WHERE json_extract(urt.elaboration, ''$.persistence'') = ''init-script'';', 'da44f53ca8c20baac1f37381e680cf7129b10767', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+            interpretable_code = EXCLUDED.interpretable_code,
+            notebook_kernel_id = EXCLUDED.notebook_kernel_id,
+            updated_at = CURRENT_TIMESTAMP,
+            activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'ConstructionSqlNotebook', 'v017_browserHistoryVisitViewDDL', NULL, 'DROP VIEW IF EXISTS "browser_history_visit";
+CREATE VIEW IF NOT EXISTS "browser_history_visit" AS
+      SELECT ur.device_id,
+             urt.uniform_resource_id,
+             ur.uri AS store,
+             json_extract(ur.elaboration, ''$.browser.browser'') AS browser,
+             json_extract(ur.elaboration, ''$.browser.profile'') AS profile,
+             json_extract(visit.value, ''$.url'') AS url,
+             json_extract(visit.value, ''$.title'') AS title,
+             json_extract(visit.value, ''$.visited_at'') AS visited_at,
+             json_extract(visit.value, ''$.visit_count'') AS visit_count
+        FROM uniform_resource_transform urt
+        JOIN uniform_resource ur ON ur.uniform_resource_id = urt.uniform_resource_id,
+             json_each(urt.content) AS visit
+       WHERE ur.nature = ''browser-history'';', '9cc42ef255cde30c50728f23014aba3da0aa3241', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+            interpretable_code = EXCLUDED.interpretable_code,
+            notebook_kernel_id = EXCLUDED.notebook_kernel_id,
+            updated_at = CURRENT_TIMESTAMP,
+            activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'ConstructionSqlNotebook', 'v017_browserExtensionViewDDL', NULL, 'DROP VIEW IF EXISTS "browser_extension";
+CREATE VIEW IF NOT EXISTS "browser_extension" AS
+      SELECT ur.device_id,
+             urt.uniform_resource_id,
+             ur.uri AS store,
+             json_extract(ur.elaboration, ''$.browser.browser'') AS browser,
+             json_extract(ur.elaboration, ''$.browser.profile'') AS profile,
+             json_extract(extension.value, ''$.id'') AS extension_id,
+             json_extract(extension.value, ''$.name'') AS name,
+             json_extract(extension.value, ''$.version'') AS version,
+             json_extract(extension.value, ''$.permissions'') AS permissions,
+             json_extract(extension.value, ''$.enabled'') AS enabled
+        FROM uniform_resource_transform urt
+        JOIN uniform_resource ur ON ur.uniform_resource_id = urt.uniform_resource_id,
+             json_each(urt.content) AS extension
+       WHERE ur.nature = ''browser-extensions'';', '7fa5d29f5f07218ca63fde6b19cafd76301a2b9e', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+            interpretable_code = EXCLUDED.interpretable_code,
+            notebook_kernel_id = EXCLUDED.notebook_kernel_id,
+            updated_at = CURRENT_TIMESTAMP,
+            activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'ConstructionSqlNotebook', 'v018_once_deviceClockDDL', NULL, 'ALTER TABLE ur_ingest_session ADD COLUMN device_clock TEXT CHECK(json_valid(device_clock) OR device_clock IS NULL);
+ALTER TABLE ur_ingest_session_imap_acct_folder_message ADD COLUMN date TEXT;
+', 'a81a89041ad695c6ac3eb18403a89a67f416fc7c', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+            interpretable_code = EXCLUDED.interpretable_code,
+            notebook_kernel_id = EXCLUDED.notebook_kernel_id,
+            updated_at = CURRENT_TIMESTAMP,
+            activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'ConstructionSqlNotebook', 'v018_urIngestSessionDeviceClockViewDDL', NULL, 'DROP VIEW IF EXISTS "ur_ingest_session_device_clock";
+CREATE VIEW IF NOT EXISTS "ur_ingest_session_device_clock" AS
+      SELECT s.ur_ingest_session_id,
+             s.device_id,
+             d.name AS device_name,
+             s.ingest_started_at,
+             json_extract(s.device_clock, ''$.timezone'') AS timezone,
+             json_extract(s.device_clock, ''$.utc_offset'') AS utc_offset,
+             json_extract(s.device_clock, ''$.clock_source'') AS clock_source,
+             json_extract(s.device_clock, ''$.ntp_synchronized'') AS ntp_synchronized,
+             json_extract(s.device_clock, ''$.ntp_offset_ms'') AS ntp_offset_ms,
+             json_extract(s.device_clock, ''$.ntp_estimated_error_ms'') AS ntp_estimated_error_ms
+        FROM ur_ingest_session s
+        JOIN device d ON d.device_id = s.device_id
+       WHERE s.device_clock IS NOT NULL;', '07c74aa339a0b05c8bd2c11fadac20f45c93e4df', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+            interpretable_code = EXCLUDED.interpretable_code,
+            notebook_kernel_id = EXCLUDED.notebook_kernel_id,
+            updated_at = CURRENT_TIMESTAMP,
+            activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'ConstructionSqlNotebook', 'v019_once_urIngestSessionGitRepoDDL', NULL, 'CREATE TABLE IF NOT EXISTS "ur_ingest_session_git_repo" (
+    "ur_ingest_session_git_repo_id" VARCHAR PRIMARY KEY NOT NULL,
+    "ingest_session_id" VARCHAR NOT NULL,
+    "repo" TEXT NOT NULL,
+    "reference" TEXT NOT NULL,
+    "commit_sha" TEXT NOT NULL,
+    "author_name" TEXT NOT NULL,
+    "author_email" TEXT NOT NULL,
+    "authored_at" TIMESTAMPTZ NOT NULL,
+    "committer_name" TEXT NOT NULL,
+    "committer_email" TEXT NOT NULL,
+    "committed_at" TIMESTAMPTZ NOT NULL,
+    "subject" TEXT NOT NULL,
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    FOREIGN KEY("ingest_session_id") REFERENCES "ur_ingest_session"("ur_ingest_session_id"),
+    UNIQUE("ingest_session_id", "repo")
+);
+
+CREATE INDEX IF NOT EXISTS "idx_ur_ingest_session_git_repo__repo__commit_sha" ON "ur_ingest_session_git_repo"("repo", "commit_sha");
+', '84ed7e97e72382c2a10d8d25312202dac616f6d1', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+            interpretable_code = EXCLUDED.interpretable_code,
+            notebook_kernel_id = EXCLUDED.notebook_kernel_id,
+            updated_at = CURRENT_TIMESTAMP,
+            activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'ConstructionSqlNotebook', 'v020_once_surveilrRemoteSyncDDL', NULL, 'CREATE TABLE IF NOT EXISTS "surveilr_remote_sync" (
+    "surveilr_remote_sync_id" VARCHAR PRIMARY KEY NOT NULL,
+    "remote_url" TEXT NOT NULL,
+    "table_name" TEXT NOT NULL,
+    "synced_rowid" INTEGER NOT NULL,
+    "synced_rows" INTEGER NOT NULL,
+    "synced_at" TIMESTAMPTZ NOT NULL,
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    "synced_change_id" INTEGER NOT NULL DEFAULT 0,
+    "scanned_at" TIMESTAMPTZ,
+    UNIQUE("remote_url", "table_name")
+);
+CREATE TABLE IF NOT EXISTS "surveilr_remote_sync_change" (
+    "surveilr_remote_sync_change_id" INTEGER PRIMARY KEY AUTOINCREMENT,
+    "table_name" TEXT NOT NULL,
+    "row_id" INTEGER NOT NULL,
+    "deleted_key" TEXT CHECK(json_valid(deleted_key) OR deleted_key IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT
+);
+
+INSERT INTO code_notebook_state (code_notebook_state_id, code_notebook_cell_id, from_state, to_state, transition_reason)
+     SELECT surveilr_pk(), code_notebook_cell_id, ''NONE'', ''EXECUTED'', ''v020_once_surveilrRemoteSyncDDL''
+       FROM code_notebook_cell
+      WHERE notebook_name = ''ConstructionSqlNotebook'' AND cell_name IN (''v028_once_surveilrRemoteSyncChangeDDL'')
+ON CONFLICT DO NOTHING;
+', 'eab6d6d4374993f710fe0f3f63f907d3f6c3686c', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+            interpretable_code = EXCLUDED.interpretable_code,
+            notebook_kernel_id = EXCLUDED.notebook_kernel_id,
+            updated_at = CURRENT_TIMESTAMP,
+            activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'ConstructionSqlNotebook', 'v021_pdfDocumentViewDDL', NULL, 'DROP VIEW IF EXISTS "pdf_document";
+CREATE VIEW IF NOT EXISTS "pdf_document" AS
+      SELECT ur.device_id,
+             urt.uniform_resource_id,
+             ur.uri,
+             json_extract(urt.elaboration, ''$.pdf.version'') AS pdf_version,
+             json_extract(urt.elaboration, ''$.pdf.pages'') AS pages,
+             json_extract(urt.elaboration, ''$.pdf.encrypted'') AS encrypted,
+             json_extract(urt.elaboration, ''$.pdf.info.Title'') AS title,
+             json_extract(urt.elaboration, ''$.pdf.info.Author'') AS author,
+             json_extract(urt.elaboration, ''$.pdf.info.Subject'') AS subject,
+             json_extract(urt.elaboration, ''$.pdf.info.Keywords'') AS keywords,
+             json_extract(urt.elaboration, ''$.pdf.info.Creator'') AS creator,
+             json_extract(urt.elaboration, ''$.pdf.info.Producer'') AS producer,
+             json_extract(urt.elaboration, ''$.pdf.info.CreationDate'') AS created_at,
+             json_extract(urt.elaboration, ''$.pdf.info.ModDate'') AS modified_at,
+             urt.content AS text
+        FROM uniform_resource_transform urt
+        JOIN uniform_resource ur ON ur.uniform_resource_id = urt.uniform_resource_id
+       WHERE json_extract(urt.elaboration, ''$.pdf'') IS NOT NULL;', 'cff52882aa5a3f4a1f2e36268264c9f1955d2857', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+            interpretable_code = EXCLUDED.interpretable_code,
+            notebook_kernel_id = EXCLUDED.notebook_kernel_id,
+            updated_at = CURRENT_TIMESTAMP,
+            activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'ConstructionSqlNotebook', 'v022_officeDocumentViewDDL', NULL, 'DROP VIEW IF EXISTS "office_document";
+CREATE VIEW IF NOT EXISTS "office_document" AS
+      SELECT ur.device_id,
+             urt.uniform_resource_id,
+             ur.uri,
+             json_extract(urt.elaboration, ''$.office'') AS format,
+             json_extract(urt.content, ''$.properties.title'') AS title,
+             json_extract(urt.content, ''$.properties.creator'') AS creator,
+             json_extract(urt.content, ''$.properties.lastModifiedBy'') AS last_modified_by,
+             json_extract(urt.content, ''$.properties.created'') AS created_at,
+             json_extract(urt.content, ''$.properties.modified'') AS modified_at,
+             json_array_length(urt.content, ''$.paragraphs'') AS paragraphs,
+             json_array_length(urt.content, ''$.sheets'') AS sheets,
+             json_array_length(urt.content, ''$.slides'') AS slides,
+             urt.content
+        FROM uniform_resource_transform urt
+        JOIN uniform_resource ur ON ur.uniform_resource_id = urt.uniform_resource_id
+       WHERE json_extract(urt.elaboration, ''$.office'') IS NOT NULL;', '0cf780f4cb3f74fd809ef0528969ce0945f6c7d3', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+            interpretable_code = EXCLUDED.interpretable_code,
+            notebook_kernel_id = EXCLUDED.notebook_kernel_id,
+            updated_at = CURRENT_TIMESTAMP,
+            activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'ConstructionSqlNotebook', 'v023_once_uniformResourceEquivalenceDDL', NULL, 'CREATE TABLE IF NOT EXISTS "uniform_resource_equivalence" (
+    "uniform_resource_equivalence_id" VARCHAR PRIMARY KEY NOT NULL,
+    "content_digest" TEXT NOT NULL,
+    "uniform_resource_id" VARCHAR NOT NULL,
+    "device_id" VARCHAR NOT NULL,
+    "uri" TEXT NOT NULL,
+    "device_count" INTEGER NOT NULL,
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    FOREIGN KEY("uniform_resource_id") REFERENCES "uniform_resource"("uniform_resource_id"),
+    FOREIGN KEY("device_id") REFERENCES "device"("device_id"),
+    UNIQUE("content_digest", "uniform_resource_id")
+);
+
+CREATE INDEX IF NOT EXISTS "idx_uniform_resource_equivalence__content_digest__device_id" ON "uniform_resource_equivalence"("content_digest", "device_id");
+', 'ad20bc162f6f561f5a4792d3e0c4c60ff690af09', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+            interpretable_code = EXCLUDED.interpretable_code,
+            notebook_kernel_id = EXCLUDED.notebook_kernel_id,
+            updated_at = CURRENT_TIMESTAMP,
+            activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'ConstructionSqlNotebook', 'v024_once_uniformResourceChunkDDL', NULL, 'CREATE TABLE IF NOT EXISTS "uniform_resource_chunk" (
+    "uniform_resource_chunk_id" VARCHAR PRIMARY KEY NOT NULL,
+    "uniform_resource_id" VARCHAR NOT NULL,
+    "chunk_index" INTEGER NOT NULL,
+    "content_digest" TEXT NOT NULL,
+    "size_bytes" INTEGER NOT NULL,
+    "content" BLOB NOT NULL,
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    FOREIGN KEY("uniform_resource_id") REFERENCES "uniform_resource"("uniform_resource_id"),
+    UNIQUE("uniform_resource_id", "chunk_index")
+);
+', '902cf88fca1444949377a7f40607b08106f846b2', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+            interpretable_code = EXCLUDED.interpretable_code,
+            notebook_kernel_id = EXCLUDED.notebook_kernel_id,
+            updated_at = CURRENT_TIMESTAMP,
+            activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'ConstructionSqlNotebook', 'v025_once_urIngestImapFolderStateDDL', NULL, 'CREATE TABLE IF NOT EXISTS "ur_ingest_imap_folder_state" (
+    "ur_ingest_imap_folder_state_id" VARCHAR PRIMARY KEY NOT NULL,
+    "email" TEXT NOT NULL,
+    "folder_name" TEXT NOT NULL,
+    "uid_validity" INTEGER NOT NULL,
+    "last_uid" INTEGER NOT NULL,
+    "ingest_session_id" VARCHAR NOT NULL,
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    FOREIGN KEY("ingest_session_id") REFERENCES "ur_ingest_session"("ur_ingest_session_id"),
+    UNIQUE("email", "folder_name")
+);
+', '4111a143c373cdfce8cc819f121fd92938f8c8a2', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+            interpretable_code = EXCLUDED.interpretable_code,
+            notebook_kernel_id = EXCLUDED.notebook_kernel_id,
+            updated_at = CURRENT_TIMESTAMP,
+            activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'ConstructionSqlNotebook', 'v026_once_contentDigestAlgorithmDDL', NULL, 'ALTER TABLE ur_ingest_session ADD COLUMN content_digest_algorithm TEXT NOT NULL DEFAULT ''sha1'';
+ALTER TABLE uniform_resource ADD COLUMN content_digest_algorithm TEXT NOT NULL DEFAULT ''sha1'';
+', '7c4ed5b9fb753d18cf6a554a91ffc247c3e9b429', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+            interpretable_code = EXCLUDED.interpretable_code,
+            notebook_kernel_id = EXCLUDED.notebook_kernel_id,
+            updated_at = CURRENT_TIMESTAMP,
+            activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'ConstructionSqlNotebook', 'v027_once_uniformResourceVerificationDDL', NULL, 'CREATE TABLE IF NOT EXISTS "uniform_resource_verification" (
+    "uniform_resource_verification_id" VARCHAR PRIMARY KEY NOT NULL,
+    "verification_id" TEXT NOT NULL,
+    "ingest_session_id" VARCHAR NOT NULL,
+    "uniform_resource_id" VARCHAR,
+    "uri" TEXT NOT NULL,
+    "status" TEXT NOT NULL,
+    "content_digest_algorithm" TEXT NOT NULL,
+    "expected_digest" TEXT,
+    "actual_digest" TEXT,
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    FOREIGN KEY("ingest_session_id") REFERENCES "ur_ingest_session"("ur_ingest_session_id"),
+    FOREIGN KEY("uniform_resource_id") REFERENCES "uniform_resource"("uniform_resource_id"),
+    UNIQUE("verification_id", "uri")
+);
+', 'b30672274bf343f82e4691369a399dd26a0591a6', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+            interpretable_code = EXCLUDED.interpretable_code,
+            notebook_kernel_id = EXCLUDED.notebook_kernel_id,
+            updated_at = CURRENT_TIMESTAMP,
+            activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'ConstructionSqlNotebook', 'v028_once_surveilrRemoteSyncChangeDDL', NULL, 'ALTER TABLE surveilr_remote_sync ADD COLUMN synced_change_id INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE surveilr_remote_sync ADD COLUMN scanned_at TIMESTAMPTZ;
+UPDATE surveilr_remote_sync SET synced_rowid = 0;
+CREATE TABLE IF NOT EXISTS "surveilr_remote_sync_change" (
+    "surveilr_remote_sync_change_id" INTEGER PRIMARY KEY AUTOINCREMENT,
+    "table_name" TEXT NOT NULL,
+    "row_id" INTEGER NOT NULL,
+    "deleted_key" TEXT CHECK(json_valid(deleted_key) OR deleted_key IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT
+);
+', '588dce813b568ed6d6c435ee99dfe3721dbbb921', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+            interpretable_code = EXCLUDED.interpretable_code,
+            notebook_kernel_id = EXCLUDED.notebook_kernel_id,
+            updated_at = CURRENT_TIMESTAMP,
+            activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'QuerySqlNotebook', 'infoSchema', NULL, 'SELECT tbl_name AS table_name,
+       c.cid AS column_id,
+       c.name AS column_name,
+       c."type" AS "type",
+       c."notnull" AS "notnull",
+       c.dflt_value as "default_value",
+       c.pk AS primary_key
+  FROM sqlite_master m,
+       pragma_table_info(m.tbl_name) c
+ WHERE m.type = ''table'';', 'b51a0cbc609deb9b4046bb02b670e7686ac678fe', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+            interpretable_code = EXCLUDED.interpretable_code,
+            notebook_kernel_id = EXCLUDED.notebook_kernel_id,
+            updated_at = CURRENT_TIMESTAMP,
+            activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'QuerySqlNotebook', 'infoSchemaOsQueryATCs', NULL, 'WITH table_columns AS (
+    SELECT m.tbl_name AS table_name,
+           group_concat(c.name) AS column_names_for_select,
+           json_group_array(c.name) AS column_names_for_atc_json
+      FROM sqlite_master m,
+           pragma_table_info(m.tbl_name) c
+     WHERE m.type = ''table''
+  GROUP BY m.tbl_name
+),
+target AS (
+  -- set SQLite parameter :osquery_atc_path to assign a different path
+  SELECT COALESCE(:osquery_atc_path, ''SQLITEDB_PATH'') AS path
+),
+table_query AS (
+    SELECT table_name,
+           ''SELECT '' || column_names_for_select || '' FROM '' || table_name AS query,
+           column_names_for_atc_json
+      FROM table_columns
+)
+SELECT json_object(''auto_table_construction'',
+          json_group_object(
+              table_name,
+              json_object(
+                  ''query'', query,
+                  ''columns'', json(column_names_for_atc_json),
+                  ''path'', path
+              )
+          )
+       ) AS osquery_auto_table_construction
+  FROM table_query, target;', '375b0b4fe291ebac350dc4ed9304f17b57ba8e44', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+            interpretable_code = EXCLUDED.interpretable_code,
+            notebook_kernel_id = EXCLUDED.notebook_kernel_id,
+            updated_at = CURRENT_TIMESTAMP,
+            activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'QuerySqlNotebook', 'infoSchemaMarkdown', NULL, '-- TODO: https://github.com/lovasoa/SQLpage/discussions/109#discussioncomment-7359513
+--       see the above for how to fix for SQLPage but figure out to use the same SQL
+--       in and out of SQLPage (maybe do what Ophir said in discussion and create
+--       custom output for SQLPage using componetns?)
+WITH TableInfo AS (
+  SELECT
+    m.tbl_name AS table_name,
+    CASE WHEN c.pk THEN ''*'' ELSE '''' END AS is_primary_key,
+    c.name AS column_name,
+    c."type" AS column_type,
+    CASE WHEN c."notnull" THEN ''*'' ELSE '''' END AS not_null,
+    COALESCE(c.dflt_value, '''') AS default_value,
+    COALESCE((SELECT pfkl."table" || ''.'' || pfkl."to" FROM pragma_foreign_key_list(m.tbl_name) AS pfkl WHERE pfkl."from" = c.name), '''') as fk_refs,
+    ROW_NUMBER() OVER (PARTITION BY m.tbl_name ORDER BY c.cid) AS row_num
+  FROM sqlite_master m JOIN pragma_table_info(m.tbl_name) c ON 1=1
+  WHERE m.type = ''table''
+  ORDER BY table_name, row_num
+),
+Views AS (
+  SELECT ''## Views '' AS markdown_output
+  UNION ALL
+  SELECT ''| View | Column | Type |'' AS markdown_output
+  UNION ALL
+  SELECT ''| ---- | ------ |----- |'' AS markdown_output
+  UNION ALL
+  SELECT ''| '' || tbl_name || '' | '' || c.name || '' | '' || c."type" || '' | ''
+  FROM
+    sqlite_master m,
+    pragma_table_info(m.tbl_name) c
+  WHERE
+    m.type = ''view''
+),
+Indexes AS (
+  SELECT ''## Indexes'' AS markdown_output
+  UNION ALL
+  SELECT ''| Table | Index | Columns |'' AS markdown_output
+  UNION ALL
+  SELECT ''| ----- | ----- | ------- |'' AS markdown_output
+  UNION ALL
+  SELECT ''| '' ||  m.name || '' | '' || il.name || '' | '' || group_concat(ii.name, '', '') || '' |'' AS markdown_output
+  FROM sqlite_master as m,
+    pragma_index_list(m.name) AS il,
+    pragma_index_info(il.name) AS ii
+  WHERE
+    m.type = ''table''
+  GROUP BY
+    m.name,
+    il.name
+)
+SELECT
+    markdown_output AS info_schema_markdown
+FROM
+  (
+    SELECT ''## Tables'' AS markdown_output
+    UNION ALL
+    SELECT
+      CASE WHEN ti.row_num = 1 THEN ''
+### `'' || ti.table_name || ''` Table
+| PK | Column | Type | Req? | Default | References |
+| -- | ------ | ---- | ---- | ------- | ---------- |
+'' ||
+        ''| '' || is_primary_key || '' | '' || ti.column_name || '' | '' || ti.column_type || '' | '' || ti.not_null || '' | '' || ti.default_value || '' | '' || ti.fk_refs || '' |''
+      ELSE
+        ''| '' || is_primary_key || '' | '' || ti.column_name || '' | '' || ti.column_type || '' | '' || ti.not_null || '' | '' || ti.default_value || '' | '' || ti.fk_refs || '' |''
+      END
+    FROM TableInfo ti
+    UNION ALL SELECT ''''
+    UNION ALL SELECT * FROM	Views
+    UNION ALL SELECT ''''
+    UNION ALL SELECT * FROM Indexes
+);', 'cbe800ad94b1b90f0045a78a03e9da246e46ff75', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+            interpretable_code = EXCLUDED.interpretable_code,
+            notebook_kernel_id = EXCLUDED.notebook_kernel_id,
+            updated_at = CURRENT_TIMESTAMP,
+            activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'QuerySqlNotebook', 'infoSchemaComments', NULL, 'WITH info_schema_comment(table_name, column_name, description) AS (
+  VALUES
+    (''assurance_schema'', NULL, ''A Notebook is a group of Cells. A kernel is a computational engine that executes the code contained in a notebook cell. Each notebook is associated with a kernel of a specific programming language or code transformer which can interpret code and produce a result. For example, a SQL notebook might use a SQLite kernel for running SQL code and an AI Prompt might prepare AI prompts for LLMs.''),
+    (''assurance_schema'', ''assurance_schema_id'', ''assurance_schema primary key and internal label (not a ULID)''),
+    (''assurance_schema'', ''assurance_type'', ''''''JSON Schema'''', ''''XML Schema'''', etc.''),
+    (''assurance_schema'', ''code'', ''If the schema is other than JSON Schema, use this for the validation code''),
+    (''assurance_schema'', ''code_json'', ''If the schema is a JSON Schema or the assurance code has a JSON representation''),
+    (''assurance_schema'', ''governance'', ''JSON schema-specific governance data (description, documentation, usage, etc. in JSON)''),
+    (''code_notebook_kernel'', NULL, ''A Notebook is a group of Cells. A kernel is a computational engine that executes the code contained in a notebook cell. Each notebook is associated with a kernel of a specific programming language or code transformer which can interpret code and produce a result. For example, a SQL notebook might use a SQLite kernel for running SQL code and an AI Prompt might prepare AI prompts for LLMs.''),
+    (''code_notebook_kernel'', ''code_notebook_kernel_id'', ''code_notebook_kernel primary key and internal label (not a ULID)''),
+    (''code_notebook_kernel'', ''kernel_name'', ''the kernel name for human/display use cases''),
+    (''code_notebook_kernel'', ''description'', ''any further description of the kernel for human/display use cases''),
+    (''code_notebook_kernel'', ''mime_type'', ''MIME type of this kernel''''s code in case it will be served''),
+    (''code_notebook_kernel'', ''file_extn'', ''the typical file extension for these kernel''''s codebases, can be used for syntax highlighting, etc.''),
+    (''code_notebook_kernel'', ''elaboration'', ''kernel-specific attributes/properties''),
+    (''code_notebook_kernel'', ''governance'', ''kernel-specific governance data''),
+    (''code_notebook_cell'', NULL, ''Each Notebook is divided into cells, which are individual units of interpretable code. The content of Cells depends on the Notebook Kernel and contain the source code to be executed by the Notebook''''s Kernel. The output of the code (text, graphics, etc.) can be stateless or may be stateful and store its results and state transitions in code_notebook_state.''),
+    (''code_notebook_cell'', ''code_notebook_cell_id'', ''code_notebook_cell primary key''),
+    (''code_notebook_cell'', ''cell_governance'', ''any idempotency, versioning, hash, branch, tag or other "governance" data (dependent on the cell)''),
+    (''code_notebook_state'', NULL, ''Records the state of a notebook''''s cells'''' executions, computations, and results for Kernels that are stateful. For example, a SQL Notebook Cell that creates tables should only be run once (meaning it''''s statefule). Other Kernels might store results for functions and output defined in one cell can be used in later cells.''),
+    (''code_notebook_state'', ''code_notebook_state_id'', ''code_notebook_state primary key''),
+    (''code_notebook_state'', ''code_notebook_cell_id'', ''code_notebook_cell row this state describes''),
+    (''code_notebook_state'', ''from_state'', ''the previous state (set to "INITIAL" when it''''s the first transition)''),
+    (''code_notebook_state'', ''to_state'', ''the current state; if no rows exist it means no state transition occurred''),
+    (''code_notebook_state'', ''transition_result'', ''if the result of state change is necessary for future use''),
+    (''code_notebook_state'', ''transition_reason'', ''short text or code explaining why the transition occurred''),
+    (''code_notebook_state'', ''transitioned_at'', ''when the transition occurred''),
+    (''code_notebook_state'', ''elaboration'', ''any elaboration needed for the state transition''),
+    (''device'', NULL, ''Identity, network segmentation, and sysinfo for devices on which uniform_resource are found''),
+    (''device'', ''name'', ''unique device identifier (defaults to hostname)''),
+    (''device'', ''state'', ''should be "SINGLETON" if only one state is allowed, or other tags if multiple states are allowed''),
+    (''device'', ''boundary'', ''can be IP address, VLAN, or any other device name differentiator''),
+    (''device'', ''segmentation'', ''zero trust or other network segmentation''),
+    (''device'', ''state_sysinfo'', ''any sysinfo or other state data that is specific to this device (mutable)''),
+    (''device'', ''elaboration'', ''any elaboration needed for the device (mutable)''),
+    (''behavior'', NULL, ''Behaviors are configuration "presets" that can be used to drive application operations at runtime. For example, ingest behaviors include configs that indicate which files to ignore, which to scan, when to load content, etc. This is more convenient than creating behavior has a foreign key reference to the device table since behaviors might be device-specific.''),
+    (''behavior'', ''behavior_name'', ''Arbitrary but unique per-device behavior name (e.g. ingest::xyz)''),
+    (''behavior'', ''behavior_conf_json'', ''Configuration, settings, parameters, etc. describing the behavior (JSON, behavior-dependent)''),
+    (''behavior'', ''governance'', ''Descriptions or other "governance" details (JSON, behavior-dependent)''),
+    (''ur_ingest_resource_path_match_rule'', NULL, ''A regular expression can determine the flags to apply to an ingestion path and if the regular expr contains a nature capture group that pattern match will assign the nature too.''),
+    (''ur_ingest_resource_path_rewrite_rule'', NULL, ''A regular expression can determine whether certain paths should be rewritten before ur_ingest_resource_path_match_rule matches occur.''),
+    (''ur_ingest_session'', NULL, ''Immutable ingestion sessions represents any "discovery" or "walk" operation. This could be a device file system scan or any other resource discovery session. Each time a discovery operation starts, a record is created. ur_ingest_session has a foreign key reference to the device table so that the same device can be used for multiple ingest sessions but also the ingest sessions can be merged across workstations / servers for easier detection of changes and similaries between file systems on different devices.''),
+    (''ur_ingest_session_fs_path'', NULL, ''Immutable ingest session file system path represents a discovery or "walk" path. If the session included a file system scan, then root_path is the root file system path that was scanned. If the session was discovering resources in another target then root_path would be representative of the target path (could be a URI).''),
+    (''ur_ingest_session_imap_account'', NULL, ''Immutable ingest session folder system represents an email address to be ingested. Each session includes an email, then email is the folder that was scanned.''),
+    (''ur_ingest_session_imap_acct_folder'', NULL, ''Immutable ingest session folder system represents a folder or mailbox in an email account, e.g. "INBOX" or "SENT". Each session includes a folder scan, then folder_name is the folder that was scanned.''),
+    (''uniform_resource'', NULL, ''Immutable resource and content information. On multiple executions, uniform_resource are inserted only if the the content (see unique index for details). For historical logging, uniform_resource has foreign key references to both ur_ingest_session and ur_ingest_session_fs_path tables to indicate which particular session and ingestion path the resourced was inserted during.''),
+    (''uniform_resource'', ''uniform_resource_id'', ''uniform_resource ULID primary key''),
+    (''uniform_resource'', ''device_id'', ''which device row introduced this resource''),
+    (''uniform_resource'', ''ingest_session_id'', ''which ur_ingest_session row introduced this resource''),
+    (''uniform_resource'', ''ingest_fs_path_id'', ''which ur_ingest_session_fs_path row introduced this resource''),
+    (''uniform_resource'', ''uri'', ''the resource''''s URI (dependent on how it was acquired and on which device)''),
+    (''uniform_resource'', ''content_digest'', ''''''-'''' when no hash was computed (not NULL); content_digest for symlinks will be the same as their target''),
+    (''uniform_resource'', ''content'', ''either NULL if no content was acquired or the actual blob/text of the content''),
+    (''uniform_resource'', ''nature'', ''file extension or MIME''),
+    (''uniform_resource'', ''content_fm_body_attrs'', ''each component of frontmatter-based content ({ frontMatter: '''''''', body: '''''''', attrs: {...} })''),
+    (''uniform_resource'', ''frontmatter'', ''meta data or other "frontmatter" in JSON format''),
+    (''uniform_resource'', ''elaboration'', ''anything that doesn''''t fit in other columns (JSON)''),
+    (''uniform_resource'', ''semantic_identity'', ''nature-specific identity (e.g. email Message-ID, SBOM serial number); resources of the same nature with the same identity are deduplicated regardless of content digest''),
+    (''uniform_resource'', ''content_digest_algorithm'', ''the algorithm (sha1, sha256 or blake3) of content_digest''),
+    (''uniform_resource_transform'', NULL, ''uniform_resource transformed content''),
+    (''uniform_resource_transform'', ''uniform_resource_transform_id'', ''uniform_resource_transform ULID primary key''),
+    (''uniform_resource_transform'', ''uniform_resource_id'', ''uniform_resource row ID of original content''),
+    (''uniform_resource_transform'', ''content_digest'', ''transformed content hash''),
+    (''uniform_resource_transform'', ''content'', ''transformed content''),
+    (''uniform_resource_transform'', ''nature'', ''file extension or MIME''),
+    (''uniform_resource_transform'', ''elaboration'', ''anything that doesn''''t fit in other columns (JSON)''),
+    (''ur_ingest_session_fs_path_entry'', NULL, ''Contains entries related to file system content ingestion paths. On multiple executions, unlike uniform_resource, ur_ingest_session_fs_path_entry rows are always inserted and references the uniform_resource primary key of its related content. This method allows for a more efficient query of file version differences across sessions. With SQL queries, you can detect which sessions have a file added or modified, which sessions have a file deleted, and what the differences are in file contents if they were modified across sessions.''),
+    (''ur_ingest_session_task'', NULL, ''Contains entries related to task content ingestion paths. On multiple executions, unlike uniform_resource, ur_ingest_session_task rows are always inserted and references the uniform_resource primary key of its related content. This method allows for a more efficient query of file version differences across sessions. With SQL queries, you can detect which sessions have a file added or modified, which sessions have a file deleted, and what the differences are in file contents if they were modified across sessions.''),
+    (''ur_ingest_session_imap_acct_folder_message'', NULL, ''Contains messages related in a folder that was ingested. On multiple executions, unlike uniform_resource, ur_ingest_session_imap_acct_folder_message rows are always inserted and references the uniform_resource primary key of its related content. This method allows for a more efficient query of message version differences across sessions. With SQL queries, you can detect which sessions have a messaged added or modified, which sessions have a message deleted, and what the differences are in message contents if they were modified across sessions.''),
+    (''uniform_resource_lineage'', NULL, ''Links uniform_resource rows which were generated by capturable executables to the uniform_resource row of the executable itself and to the ur_ingest_session_fs_path_entry or ur_ingest_session_task row which recorded the execution. Like the other session tables, uniform_resource_lineage rows are always inserted so every derived artifact can be traced back to the code that generated it in each session.''),
+    (''uniform_resource_lineage'', ''source_uniform_resource_id'', ''uniform_resource row of the capturable executable (script)''),
+    (''uniform_resource_lineage'', ''output_uniform_resource_id'', ''uniform_resource row of the captured output''),
+    (''uniform_resource_lineage'', ''ingest_fs_path_entry_id'', ''ur_ingest_session_fs_path_entry row when the executable was found by \''),
+    (''uniform_resource_lineage'', ''ingest_task_id'', ''ur_ingest_session_task row when the executable was supplied by \''),
+    (''rssd_metadata'', NULL, ''Settings which were chosen when the RSSD was initialized and which must stay fixed for its lifetime (e.g. \''),
+    (''rssd_metadata'', ''key'', ''the name of the setting''),
+    (''rssd_metadata'', ''value'', ''the value of the setting''),
+    (''query_snapshot'', NULL, ''Materialized results of SQL queries saved by `snapshot create`. Each execution of a named query inserts a new query_snapshot row so reports can be versioned and compared with `snapshot diff`.''),
+    (''query_snapshot'', ''name'', ''the snapshot name, shared by every version of a report''),
+    (''query_snapshot'', ''query_sql'', ''the SQL which produced the result set''),
+    (''query_snapshot'', ''result_schema'', ''JSON array of the result columns and their declared types''),
+    (''query_snapshot'', ''result_rows'', ''JSON array of the result rows as objects''),
+    (''query_snapshot'', ''content_digest'', ''SHA-1 of result_rows, identical when a report has not changed''),
+    (''surveilr_audit'', NULL, ''Audit trail of the surveilr commands which change or destroy the RSSD (`admin init`, `admin merge`, `admin seed`, `admin restore`, `ingest files --save-behavior`, `notebooks publish` and `snapshot create`). Each invocation inserts a surveilr_audit row once the command finished, whether or not it succeeded.''),
+    (''surveilr_audit'', ''command'', ''the subcommand, e.g. `admin merge`''),
+    (''surveilr_audit'', ''argv'', ''JSON array of the command line arguments''),
+    (''surveilr_audit'', ''os_user'', ''the operating system user who ran the command''),
+    (''surveilr_audit'', ''host'', ''the host name of the device the command ran on''),
+    (''surveilr_audit'', ''started_at'', ''when the command started''),
+    (''surveilr_audit'', ''outcome'', ''`succeeded` or `failed`''),
+    (''surveilr_audit'', ''error'', ''the error of a failed command''),
+    (''ur_ingest_session_imap_acct_stat'', NULL, ''Mailbox statistics computed at the end of each `ingest imap` session for triage dashboards: one ur_ingest_session_imap_acct_stat row per statistic, e.g. the messages received on a day or sent by a sender. The same summary is stored in the session''''s elaboration.''),
+    (''ur_ingest_session_imap_acct_stat'', ''stat_kind'', ''`daily_volume`, `top_sender`, `attachment_type` or `volume_spike`''),
+    (''ur_ingest_session_imap_acct_stat'', ''stat_key'', ''the day (YYYY-MM-DD), sender address or attachment nature counted''),
+    (''ur_ingest_session_imap_acct_stat'', ''stat_value'', ''the number of messages (or attachments)''),
+    (''ur_ingest_session_imap_acct_stat'', ''elaboration'', ''for `volume_spike` rows, the baseline of previous sessions it exceeded''),
+    (''policy_finding'', NULL, ''Findings of failed policy assertions recorded by `policy run`. A finding stays open (`new`, then `acknowledged` with `policy ack`) while its assertion keeps failing and is `resolved` once it passes; failing again opens a new policy_finding row.''),
+    (''policy_finding'', ''pack'', ''the policy pack (notebook name) of the assertion''),
+    (''policy_finding'', ''assertion'', ''the assertion (cell name) which failed''),
+    (''policy_finding'', ''severity'', ''`info`, `low`, `medium`, `high` or `critical`''),
+    (''policy_finding'', ''state'', ''`new`, `acknowledged` or `resolved`''),
+    (''policy_finding'', ''status'', ''`FAIL` or `ERROR` (the SQL could not be evaluated) in the latest run''),
+    (''policy_finding'', ''row_count'', ''the number of rows the assertion returned in the latest run''),
+    (''policy_finding'', ''sample_rows'', ''JSON array of the first rows the assertion returned, as evidence''),
+    (''policy_finding'', ''last_seen_at'', ''when a run last found the assertion failing''),
+    (''policy_finding'', ''acknowledged_by'', ''the operating system user who acknowledged the finding''),
+    (''policy_finding'', ''resolved_at'', ''when a run first found the assertion passing again''),
+    (''policy_alert_rule'', NULL, ''Rules which notify about policy findings after `policy run`, managed with `policy alert`.''),
+    (''policy_alert_rule'', ''min_severity'', ''the least severe findings which are notified''),
+    (''policy_alert_rule'', ''fire_on'', ''`new` for findings first seen in the run, `open` for all unacknowledged findings''),
+    (''policy_alert_rule'', ''channel'', ''`webhook` or `email`''),
+    (''policy_alert_rule'', ''target'', ''the webhook URL or email address''),
+    (''policy_alert_rule'', ''packs'', ''JSON array of the packs the rule applies to, all packs if NULL''),
+    (''compliance_control'', NULL, ''Controls of compliance frameworks (e.g. SOC2, NIST 800-53, ISO 27001) which evidence is mapped to with `compliance map`, imported with `compliance import`.''),
+    (''compliance_control'', ''framework'', ''the framework identifier, e.g. `SOC2`''),
+    (''compliance_control'', ''control_code'', ''the control identifier within its framework, e.g. `CC6.1`''),
+    (''compliance_control'', ''title'', ''the short name of the control''),
+    (''compliance_evidence_map'', NULL, ''Evidence which demonstrates a compliance control, summarized per framework by `compliance report`.''),
+    (''compliance_evidence_map'', ''evidence_kind'', ''`resource` (uniform resources), `policy` (policy assertions) or `capturable-exec` (outputs of capturable executables)''),
+    (''compliance_evidence_map'', ''evidence_selector'', ''GLOB of the resources or capturable executables URIs, or a policy pack with an optional `::assertion`''),
+    (''known_file_hash'', NULL, ''Hashes of known files (NSRL reference data sets or custom allow and deny
+lists) loaded with `known-files load`, which ingested resources are
+matched against.''),
+    (''known_file_hash'', ''hash_set'', ''the name the hashes were loaded as, e.g. `nsrl`''),
+    (''known_file_hash'', ''algorithm'', ''`sha1`, `sha256` or `md5`''),
+    (''known_file_hash'', ''digest'', ''the lowercase hex digest''),
+    (''known_file_hash'', ''disposition'', ''`known-good` or `known-bad`''),
+    (''known_file_hash'', ''confidence'', ''how much the hash set is trusted, from 0 to 1''),
+    (''known_file_hash'', ''file_name'', ''the name of the known file, if the hash set has it''),
+    (''uniform_resource_known_file'', NULL, ''uniform_resource rows whose content matches a
+known_file_hash hash, recorded by `known-files match` or
+`ingest --match-known-files`; resources without a match are unknown.''),
+    (''uniform_resource_known_file'', ''hash_set'', ''the known_file_hash set the resource matched (the match source)''),
+    (''uniform_resource_known_file'', ''disposition'', ''`known-good` or `known-bad`''),
+    (''uniform_resource_known_file'', ''confidence'', ''the confidence of the matched hash''),
+    (''ur_ingest_session'', ''device_clock'', ''the device''''s time zone, UTC offset, clock source and NTP offset estimate when the session started (JSON)''),
+    (''ur_ingest_session'', ''content_digest_algorithm'', ''the algorithm (sha1, sha256 or blake3) of the content digests of the session''''s resources''),
+    (''ur_ingest_session_imap_acct_folder_message'', ''date'', ''the message''''s date (RFC 3339) with the sender''''s UTC offset; uniform_resource.last_modified_at has it in UTC''),
+    (''ur_ingest_session_git_repo'', NULL, ''The repository and commit each `ingest git` session read its files from,
+with the commit''''s author and committer. The dates are in UTC, the
+elaboration has them with their original UTC offsets.''),
+    (''ur_ingest_session_git_repo'', ''repo'', ''the repository''''s path or URL, as given''),
+    (''ur_ingest_session_git_repo'', ''reference'', ''the branch, tag or commit which was resolved, `HEAD` by default''),
+    (''ur_ingest_session_git_repo'', ''commit_sha'', ''the full SHA of the commit the files were read from''),
+    (''ur_ingest_session_git_repo'', ''authored_at'', ''the author date, in UTC''),
+    (''ur_ingest_session_git_repo'', ''committed_at'', ''the committer date, in UTC''),
+    (''ur_ingest_session_git_repo'', ''subject'', ''the first line of the commit message''),
+    (''surveilr_remote_sync'', NULL, ''How far the rows of each table of a local replica were pushed to a remote libSQL (e.g. Turso) database, one surveilr_remote_sync row per remote and table. The first sync of a table pushes its rows after synced_rowid until scanned_at is set, later syncs push the surveilr_remote_sync_change changes after synced_change_id.''),
+    (''surveilr_remote_sync'', ''remote_url'', ''the remote database''''s URL, without its auth token''),
+    (''surveilr_remote_sync'', ''table_name'', ''the local table whose rows were pushed''),
+    (''surveilr_remote_sync'', ''synced_rowid'', ''the rowid of the last row which was pushed''),
+    (''surveilr_remote_sync'', ''synced_rows'', ''how many rows of the table were pushed in total''),
+    (''surveilr_remote_sync'', ''synced_at'', ''when rows of the table were last pushed''),
+    (''surveilr_remote_sync'', ''synced_change_id'', ''the surveilr_remote_sync_change_id of the last change which was pushed''),
+    (''surveilr_remote_sync'', ''scanned_at'', ''when every row the table had at its first sync was pushed''),
+    (''uniform_resource_equivalence'', NULL, ''Links the uniform_resource rows of different devices whose content is identical, rebuilt by `admin merge`. Rows aren''''t collapsed: every resource whose content was ingested on more than one device has a uniform_resource_equivalence row, so the devices with a given content are a single indexed lookup by content_digest.''),
+    (''uniform_resource_equivalence'', ''content_digest'', ''the SHA-1 digest of the content the devices share''),
+    (''uniform_resource_equivalence'', ''device_id'', ''the device the resource was ingested on''),
+    (''uniform_resource_equivalence'', ''uri'', ''the resource''''s URI on that device''),
+    (''uniform_resource_equivalence'', ''device_count'', ''how many devices have a resource with this content''),
+    (''uniform_resource_chunk'', NULL, ''The content of a uniform_resource too large for a single SQLite blob (files over 1 GiB ingested with `--content-chunk-size`), split in ordered chunks. The uniform_resource row keeps the digest and size of the whole content and a NULL content; it''''s reassembled by concatenating the chunks in chunk_index order.''),
+    (''uniform_resource_chunk'', ''chunk_index'', ''the 0-based position of the chunk in the content''),
+    (''uniform_resource_chunk'', ''content_digest'', ''the SHA-1 digest of the chunk, checked when it''''s reassembled''),
+    (''uniform_resource_chunk'', ''size_bytes'', ''the size of the chunk in bytes''),
+    (''uniform_resource_chunk'', ''content'', ''the bytes of the chunk''),
+    (''ur_ingest_imap_folder_state'', NULL, ''Where `ingest imap --resume` stopped in every folder of a mailbox: the folder''''s UIDVALIDITY and the highest UID ingested. The next run only fetches the messages with a higher UID, unless the UIDVALIDITY changed.''),
+    (''ur_ingest_imap_folder_state'', ''email'', ''the address of the mailbox''),
+    (''ur_ingest_imap_folder_state'', ''uid_validity'', ''the folder''''s UIDVALIDITY when it was last ingested''),
+    (''ur_ingest_imap_folder_state'', ''last_uid'', ''the highest UID ingested from the folder''),
+    (''ur_ingest_imap_folder_state'', ''ingest_session_id'', ''the session which last advanced the checkpoint''),
+    (''uniform_resource_verification'', NULL, ''Each file `admin verify` checked against the ingest session it verified: whether the file''''s content still hashes to the stored content_digest. With `admin verify --record` the RSSD keeps a file integrity monitoring history.''),
+    (''uniform_resource_verification'', ''verification_id'', ''the `admin verify` run, shared by all of its rows''),
+    (''uniform_resource_verification'', ''ingest_session_id'', ''the ingest session whose resources were the baseline''),
+    (''uniform_resource_verification'', ''uniform_resource_id'', ''the verified resource, NULL for files added since the session''),
+    (''uniform_resource_verification'', ''status'', ''`unchanged`, `modified`, `tampered` (content changed but not size and modification time), `deleted`, `added` or `unreadable`''),
+    (''uniform_resource_verification'', ''content_digest_algorithm'', ''the algorithm of expected_digest and actual_digest''),
+    (''uniform_resource_verification'', ''expected_digest'', ''the resource''''s content_digest''),
+    (''uniform_resource_verification'', ''actual_digest'', ''the digest of the file''''s content when it was verified''),
+    (''uniform_resource_verification'', ''elaboration'', ''the expected and actual size and modification time, the error of unreadable files (JSON)''),
+    (''surveilr_remote_sync_change'', NULL, ''The rows of a local replica which were inserted, updated or deleted, recorded by triggers so syncs push them to remote libSQL databases; surveilr_remote_sync_change rows every remote was pushed are removed.''),
+    (''surveilr_remote_sync_change'', ''table_name'', ''the local table whose row changed''),
+    (''surveilr_remote_sync_change'', ''row_id'', ''the rowid of the row which changed''),
+    (''surveilr_remote_sync_change'', ''deleted_key'', ''JSON array of the primary key of a deleted row, NULL when the row was inserted or updated'')
+)
+SELECT table_name, column_name, description
+  FROM info_schema_comment;', 'b74807c6c009ea9d8f7b07a84122d04e873c4443', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+            interpretable_code = EXCLUDED.interpretable_code,
+            notebook_kernel_id = EXCLUDED.notebook_kernel_id,
+            updated_at = CURRENT_TIMESTAMP,
+            activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'QuerySqlNotebook', 'htmlAnchors', NULL, '-- loadExtnSQL not provided to load ''asg017/html/html0''
+
+-- find all HTML files in the uniform_resource table and return
+-- each file and the anchors'' labels and hrefs in that file
+-- TODO: create a table called fs_content_html_anchor to store this data after inserting it into uniform_resource
+--       so that simple HTML lookups do not require the html0 extension to be loaded
+WITH html_content AS (
+  SELECT uniform_resource_id, content, content_digest, file_path, file_extn FROM uniform_resource WHERE nature = ''html''
+),
+html AS (
+  SELECT file_path,
+         text as label,
+         html_attribute_get(html, ''a'', ''href'') as href
+    FROM html_content, html_each(html_content.content, ''a'')
+)
+SELECT * FROM html;
+      ', '7d9cdf2286d82fae97cb3652e6b17744ebf7c817', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+            interpretable_code = EXCLUDED.interpretable_code,
+            notebook_kernel_id = EXCLUDED.notebook_kernel_id,
+            updated_at = CURRENT_TIMESTAMP,
+            activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'QuerySqlNotebook', 'htmlHeadMeta', NULL, '-- loadExtnSQL not provided to load ''asg017/html/html0''
+
+-- find all HTML files in the uniform_resource table and return
+-- each file and the <head><meta name="key" content="value"> pair
+-- TODO: create a table called resource_html_head_meta to store this data after inserting it into uniform_resource
+--       so that simple HTML lookups do not require the html0 extension to be loaded
+WITH html_content AS (
+  SELECT uniform_resource_id, content, content_digest, file_path, file_extn FROM uniform_resource WHERE nature = ''html''
+),
+html AS (
+  SELECT file_path,
+         html_attribute_get(html, ''meta'', ''name'') as key,
+         html_attribute_get(html, ''meta'', ''content'') as value,
+         html
+    FROM html_content, html_each(html_content.content, ''head meta'')
+   WHERE key IS NOT NULL
+)
+SELECT * FROM html;
+      ', '70d990a17e2ce8d0ee2cf6dd368390c8b1db7645', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+            interpretable_code = EXCLUDED.interpretable_code,
+            notebook_kernel_id = EXCLUDED.notebook_kernel_id,
+            updated_at = CURRENT_TIMESTAMP,
+            activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'SQL', 'AssuranceSqlNotebook', 'test1', NULL, 'WITH test_plan AS (
+    SELECT ''1..1'' AS tap_output
+),
+test1 AS (  -- Check if the ''fileio'' extension is loaded by calling the ''readfile'' function
+    SELECT
+        CASE
+            WHEN readfile(''README.md'') IS NOT NULL THEN ''ok 1 - fileio extension is loaded.''
+            ELSE ''not ok 1 - fileio extension is not loaded.''
+        END AS tap_output
+    FROM (SELECT 1) -- This is a dummy table of one row to ensure the SELECT runs.
+)
+SELECT tap_output FROM test_plan
+UNION ALL
+SELECT tap_output FROM test1;', '083111d6c0f2b4b0670eee784f113ddd8619187d', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+            interpretable_code = EXCLUDED.interpretable_code,
+            notebook_kernel_id = EXCLUDED.notebook_kernel_id,
+            updated_at = CURRENT_TIMESTAMP,
+            activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'LLM Prompt', 'LargeLanguageModelsPromptsNotebook', 'understand notebooks schema', NULL, 'Understand the following structure of an SQLite database designed to store code notebooks and execution kernels. The database comprises three main tables: ''code_notebook_kernel'', ''code_notebook_cell'', and ''code_notebook_state''.
+
+1. ''code_notebook_kernel'': This table stores information about various kernels or execution engines. Each record includes a unique kernel ID, kernel name, a description, MIME type, file extension, and other metadata such as creation and update timestamps.
+
+2. ''code_notebook_cell'': This table contains individual notebook cells. Each cell is linked to a kernel in the ''code_notebook_kernel'' table via ''notebook_kernel_id''. It includes details like the cell''s unique ID, notebook name, cell name, interpretable code, and relevant metadata.
+
+3. ''code_notebook_state'': This table tracks the state transitions of notebook cells. Each record links to a cell in the ''code_notebook_cell'' table and includes information about the state transition, such as the previous and new states, transition reason, and timestamps.
+
+The relationships are as follows: Each cell in ''code_notebook_cell'' is associated with a kernel in ''code_notebook_kernel''. The ''code_notebook_state'' table tracks changes in the state of each cell, linking back to the ''code_notebook_cell'' table.
+
+Use the following SQLite Schema to generate SQL queries that interact with these tables and once you understand them let me know so I can ask you for help:
+
+CREATE TABLE IF NOT EXISTS "assurance_schema" (
+    "assurance_schema_id" VARCHAR PRIMARY KEY NOT NULL,
+    "assurance_type" TEXT NOT NULL,
+    "code" TEXT NOT NULL,
+    "code_json" TEXT CHECK(json_valid(code_json) OR code_json IS NULL),
+    "governance" TEXT CHECK(json_valid(governance) OR governance IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT
+);
+CREATE TABLE IF NOT EXISTS "code_notebook_kernel" (
+    "code_notebook_kernel_id" VARCHAR PRIMARY KEY NOT NULL,
+    "kernel_name" TEXT NOT NULL,
+    "description" TEXT,
+    "mime_type" TEXT,
+    "file_extn" TEXT,
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+    "governance" TEXT CHECK(json_valid(governance) OR governance IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    UNIQUE("kernel_name")
+);
+CREATE TABLE IF NOT EXISTS "code_notebook_cell" (
+    "code_notebook_cell_id" VARCHAR PRIMARY KEY NOT NULL,
+    "notebook_kernel_id" VARCHAR NOT NULL,
+    "notebook_name" TEXT NOT NULL,
+    "cell_name" TEXT NOT NULL,
+    "cell_governance" TEXT CHECK(json_valid(cell_governance) OR cell_governance IS NULL),
+    "interpretable_code" TEXT NOT NULL,
+    "interpretable_code_hash" TEXT NOT NULL,
+    "description" TEXT,
+    "arguments" TEXT CHECK(json_valid(arguments) OR arguments IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    FOREIGN KEY("notebook_kernel_id") REFERENCES "code_notebook_kernel"("code_notebook_kernel_id"),
+    UNIQUE("notebook_name", "cell_name", "interpretable_code_hash")
+);
+CREATE TABLE IF NOT EXISTS "code_notebook_state" (
+    "code_notebook_state_id" VARCHAR PRIMARY KEY NOT NULL,
+    "code_notebook_cell_id" VARCHAR NOT NULL,
+    "from_state" TEXT NOT NULL,
+    "to_state" TEXT NOT NULL,
+    "transition_result" TEXT CHECK(json_valid(transition_result) OR transition_result IS NULL),
+    "transition_reason" TEXT,
+    "transitioned_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    FOREIGN KEY("code_notebook_cell_id") REFERENCES "code_notebook_cell"("code_notebook_cell_id"),
+    UNIQUE("code_notebook_cell_id", "from_state", "to_state")
+);
+
+
+
+      ', '85958d3630a5761a53dade44c67533e246de074e', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+                   interpretable_code = EXCLUDED.interpretable_code,
+                   notebook_kernel_id = EXCLUDED.notebook_kernel_id,
+                   updated_at = CURRENT_TIMESTAMP,
+                   activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'LLM Prompt', 'LargeLanguageModelsPromptsNotebook', 'understand service schema', NULL, 'Understand the following structure of an SQLite database designed to store cybersecurity and compliance data for files in a file system.
+The database is designed to store devices in the ''device'' table and entities called ''resources'' stored in the immutable append-only
+''uniform_resource'' table. Each time files are "walked" they are stored in ingestion session and link back to ''uniform_resource''. Because all
+tables are generally append only and immutable it means that the ingest_session_fs_path_entry table can be used for revision control
+and historical tracking of file changes.
+
+Use the following SQLite Schema to generate SQL queries that interact with these tables and once you understand them let me know so I can ask you for help:
+
+CREATE TABLE IF NOT EXISTS "device" (
+    "device_id" VARCHAR PRIMARY KEY NOT NULL,
+    "name" TEXT NOT NULL,
+    "state" TEXT CHECK(json_valid(state)) NOT NULL,
+    "boundary" TEXT NOT NULL,
+    "segmentation" TEXT CHECK(json_valid(segmentation) OR segmentation IS NULL),
     "state_sysinfo" TEXT CHECK(json_valid(state_sysinfo) OR state_sysinfo IS NULL),
     "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
     "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
@@ -912,16 +2103,327 @@ CREATE TABLE IF NOT EXISTS "device" (
     "updated_by" TEXT,
     "deleted_at" TIMESTAMPTZ,
     "deleted_by" TEXT,
-    "activity_log" TEXT,
-    UNIQUE("name", "state", "boundary")
+    "activity_log" TEXT,
+    UNIQUE("name", "state", "boundary")
+);
+CREATE TABLE IF NOT EXISTS "behavior" (
+    "behavior_id" VARCHAR PRIMARY KEY NOT NULL,
+    "device_id" VARCHAR NOT NULL,
+    "behavior_name" TEXT NOT NULL,
+    "behavior_conf_json" TEXT CHECK(json_valid(behavior_conf_json)) NOT NULL,
+    "assurance_schema_id" VARCHAR,
+    "governance" TEXT CHECK(json_valid(governance) OR governance IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    FOREIGN KEY("device_id") REFERENCES "device"("device_id"),
+    FOREIGN KEY("assurance_schema_id") REFERENCES "assurance_schema"("assurance_schema_id"),
+    UNIQUE("device_id", "behavior_name")
+);
+CREATE TABLE IF NOT EXISTS "ur_ingest_resource_path_match_rule" (
+    "ur_ingest_resource_path_match_rule_id" VARCHAR PRIMARY KEY NOT NULL,
+    "namespace" TEXT NOT NULL,
+    "regex" TEXT NOT NULL,
+    "flags" TEXT NOT NULL,
+    "nature" TEXT,
+    "priority" TEXT,
+    "description" TEXT,
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    UNIQUE("namespace", "regex")
+);
+CREATE TABLE IF NOT EXISTS "ur_ingest_resource_path_rewrite_rule" (
+    "ur_ingest_resource_path_rewrite_rule_id" VARCHAR PRIMARY KEY NOT NULL,
+    "namespace" TEXT NOT NULL,
+    "regex" TEXT NOT NULL,
+    "replace" TEXT NOT NULL,
+    "priority" TEXT,
+    "description" TEXT,
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    UNIQUE("namespace", "regex", "replace")
+);
+CREATE TABLE IF NOT EXISTS "ur_ingest_session" (
+    "ur_ingest_session_id" VARCHAR PRIMARY KEY NOT NULL,
+    "device_id" VARCHAR NOT NULL,
+    "behavior_id" VARCHAR,
+    "behavior_json" TEXT CHECK(json_valid(behavior_json) OR behavior_json IS NULL),
+    "ingest_started_at" TIMESTAMPTZ NOT NULL,
+    "ingest_finished_at" TIMESTAMPTZ,
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    "device_clock" TEXT CHECK(json_valid(device_clock) OR device_clock IS NULL),
+    "content_digest_algorithm" TEXT NOT NULL DEFAULT ''sha1'',
+    FOREIGN KEY("device_id") REFERENCES "device"("device_id"),
+    FOREIGN KEY("behavior_id") REFERENCES "behavior"("behavior_id"),
+    UNIQUE("device_id", "created_at")
+);
+CREATE TABLE IF NOT EXISTS "ur_ingest_session_fs_path" (
+    "ur_ingest_session_fs_path_id" VARCHAR PRIMARY KEY NOT NULL,
+    "ingest_session_id" VARCHAR NOT NULL,
+    "root_path" TEXT NOT NULL,
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    FOREIGN KEY("ingest_session_id") REFERENCES "ur_ingest_session"("ur_ingest_session_id"),
+    UNIQUE("ingest_session_id", "root_path", "created_at")
+);
+CREATE TABLE IF NOT EXISTS "uniform_resource" (
+    "uniform_resource_id" VARCHAR PRIMARY KEY NOT NULL,
+    "device_id" VARCHAR NOT NULL,
+    "ingest_session_id" VARCHAR NOT NULL,
+    "ingest_fs_path_id" VARCHAR,
+    "ingest_imap_acct_folder_id" VARCHAR,
+    "uri" TEXT NOT NULL,
+    "content_digest" TEXT NOT NULL,
+    "content" BLOB,
+    "nature" TEXT,
+    "size_bytes" INTEGER,
+    "last_modified_at" TIMESTAMPTZ,
+    "content_fm_body_attrs" TEXT CHECK(json_valid(content_fm_body_attrs) OR content_fm_body_attrs IS NULL),
+    "frontmatter" TEXT CHECK(json_valid(frontmatter) OR frontmatter IS NULL),
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    "semantic_identity" TEXT,
+    "content_digest_algorithm" TEXT NOT NULL DEFAULT ''sha1'',
+    FOREIGN KEY("device_id") REFERENCES "device"("device_id"),
+    FOREIGN KEY("ingest_session_id") REFERENCES "ur_ingest_session"("ur_ingest_session_id"),
+    FOREIGN KEY("ingest_fs_path_id") REFERENCES "ur_ingest_session_fs_path"("ur_ingest_session_fs_path_id"),
+    FOREIGN KEY("ingest_imap_acct_folder_id") REFERENCES "ur_ingest_session_imap_acct_folder"("ur_ingest_session_imap_acct_folder_id"),
+    UNIQUE("device_id", "content_digest", "uri", "size_bytes", "last_modified_at")
+);
+CREATE TABLE IF NOT EXISTS "uniform_resource_transform" (
+    "uniform_resource_transform_id" VARCHAR PRIMARY KEY NOT NULL,
+    "uniform_resource_id" VARCHAR NOT NULL,
+    "uri" TEXT NOT NULL,
+    "content_digest" TEXT NOT NULL,
+    "content" BLOB,
+    "nature" TEXT,
+    "size_bytes" INTEGER,
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    FOREIGN KEY("uniform_resource_id") REFERENCES "uniform_resource"("uniform_resource_id"),
+    UNIQUE("uniform_resource_id", "content_digest", "nature", "size_bytes")
+);
+CREATE TABLE IF NOT EXISTS "ur_ingest_session_fs_path_entry" (
+    "ur_ingest_session_fs_path_entry_id" VARCHAR PRIMARY KEY NOT NULL,
+    "ingest_session_id" VARCHAR NOT NULL,
+    "ingest_fs_path_id" VARCHAR NOT NULL,
+    "uniform_resource_id" VARCHAR,
+    "file_path_abs" TEXT NOT NULL,
+    "file_path_rel_parent" TEXT NOT NULL,
+    "file_path_rel" TEXT NOT NULL,
+    "file_basename" TEXT NOT NULL,
+    "file_extn" TEXT,
+    "captured_executable" TEXT CHECK(json_valid(captured_executable) OR captured_executable IS NULL),
+    "ur_status" TEXT,
+    "ur_diagnostics" TEXT CHECK(json_valid(ur_diagnostics) OR ur_diagnostics IS NULL),
+    "ur_transformations" TEXT CHECK(json_valid(ur_transformations) OR ur_transformations IS NULL),
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    FOREIGN KEY("ingest_session_id") REFERENCES "ur_ingest_session"("ur_ingest_session_id"),
+    FOREIGN KEY("ingest_fs_path_id") REFERENCES "ur_ingest_session_fs_path"("ur_ingest_session_fs_path_id"),
+    FOREIGN KEY("uniform_resource_id") REFERENCES "uniform_resource"("uniform_resource_id")
+);
+CREATE TABLE IF NOT EXISTS "ur_ingest_session_task" (
+    "ur_ingest_session_task_id" VARCHAR PRIMARY KEY NOT NULL,
+    "ingest_session_id" VARCHAR NOT NULL,
+    "uniform_resource_id" VARCHAR,
+    "captured_executable" TEXT CHECK(json_valid(captured_executable)) NOT NULL,
+    "ur_status" TEXT,
+    "ur_diagnostics" TEXT CHECK(json_valid(ur_diagnostics) OR ur_diagnostics IS NULL),
+    "ur_transformations" TEXT CHECK(json_valid(ur_transformations) OR ur_transformations IS NULL),
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    FOREIGN KEY("ingest_session_id") REFERENCES "ur_ingest_session"("ur_ingest_session_id"),
+    FOREIGN KEY("uniform_resource_id") REFERENCES "uniform_resource"("uniform_resource_id")
+);
+CREATE TABLE IF NOT EXISTS "ur_ingest_session_imap_account" (
+    "ur_ingest_session_imap_account_id" VARCHAR PRIMARY KEY NOT NULL,
+    "ingest_session_id" VARCHAR NOT NULL,
+    "email" TEXT,
+    "password" TEXT,
+    "host" TEXT,
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    FOREIGN KEY("ingest_session_id") REFERENCES "ur_ingest_session"("ur_ingest_session_id"),
+    UNIQUE("ingest_session_id", "email")
+);
+CREATE TABLE IF NOT EXISTS "ur_ingest_session_imap_acct_folder" (
+    "ur_ingest_session_imap_acct_folder_id" VARCHAR PRIMARY KEY NOT NULL,
+    "ingest_session_id" VARCHAR NOT NULL,
+    "ingest_account_id" VARCHAR NOT NULL,
+    "folder_name" TEXT NOT NULL,
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    FOREIGN KEY("ingest_session_id") REFERENCES "ur_ingest_session"("ur_ingest_session_id"),
+    FOREIGN KEY("ingest_account_id") REFERENCES "ur_ingest_session_imap_account"("ur_ingest_session_imap_account_id"),
+    UNIQUE("ingest_account_id", "folder_name")
+);
+CREATE TABLE IF NOT EXISTS "ur_ingest_session_imap_acct_folder_message" (
+    "ur_ingest_session_imap_acct_folder_message_id" VARCHAR PRIMARY KEY NOT NULL,
+    "ingest_session_id" VARCHAR NOT NULL,
+    "ingest_imap_acct_folder_id" VARCHAR NOT NULL,
+    "uniform_resource_id" VARCHAR,
+    "message" TEXT NOT NULL,
+    "message_id" TEXT NOT NULL,
+    "subject" TEXT NOT NULL,
+    "from" TEXT NOT NULL,
+    "cc" TEXT CHECK(json_valid(cc)) NOT NULL,
+    "bcc" TEXT CHECK(json_valid(bcc)) NOT NULL,
+    "email_references" TEXT CHECK(json_valid(email_references)) NOT NULL,
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    "date" TEXT,
+    FOREIGN KEY("ingest_session_id") REFERENCES "ur_ingest_session"("ur_ingest_session_id"),
+    FOREIGN KEY("ingest_imap_acct_folder_id") REFERENCES "ur_ingest_session_imap_acct_folder"("ur_ingest_session_imap_acct_folder_id"),
+    FOREIGN KEY("uniform_resource_id") REFERENCES "uniform_resource"("uniform_resource_id"),
+    UNIQUE("message", "message_id")
+);
+CREATE TABLE IF NOT EXISTS "uniform_resource_lineage" (
+    "uniform_resource_lineage_id" VARCHAR PRIMARY KEY NOT NULL,
+    "ingest_session_id" VARCHAR NOT NULL,
+    "source_uniform_resource_id" VARCHAR,
+    "output_uniform_resource_id" VARCHAR NOT NULL,
+    "ingest_fs_path_entry_id" VARCHAR,
+    "ingest_task_id" VARCHAR,
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    FOREIGN KEY("ingest_session_id") REFERENCES "ur_ingest_session"("ur_ingest_session_id"),
+    FOREIGN KEY("source_uniform_resource_id") REFERENCES "uniform_resource"("uniform_resource_id"),
+    FOREIGN KEY("output_uniform_resource_id") REFERENCES "uniform_resource"("uniform_resource_id"),
+    FOREIGN KEY("ingest_fs_path_entry_id") REFERENCES "ur_ingest_session_fs_path_entry"("ur_ingest_session_fs_path_entry_id"),
+    FOREIGN KEY("ingest_task_id") REFERENCES "ur_ingest_session_task"("ur_ingest_session_task_id")
+);
+CREATE TABLE IF NOT EXISTS "rssd_metadata" (
+    "key" TEXT PRIMARY KEY NOT NULL,
+    "value" TEXT NOT NULL,
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT
+);
+CREATE TABLE IF NOT EXISTS "query_snapshot" (
+    "query_snapshot_id" VARCHAR PRIMARY KEY NOT NULL,
+    "name" TEXT NOT NULL,
+    "query_sql" TEXT NOT NULL,
+    "result_schema" TEXT CHECK(json_valid(result_schema)) NOT NULL,
+    "result_rows" TEXT CHECK(json_valid(result_rows)) NOT NULL,
+    "row_count" INTEGER NOT NULL,
+    "content_digest" TEXT NOT NULL,
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT
+);
+CREATE TABLE IF NOT EXISTS "surveilr_audit" (
+    "surveilr_audit_id" VARCHAR PRIMARY KEY NOT NULL,
+    "command" TEXT NOT NULL,
+    "argv" TEXT CHECK(json_valid(argv)) NOT NULL,
+    "os_user" TEXT NOT NULL,
+    "host" TEXT NOT NULL,
+    "started_at" TIMESTAMPTZ NOT NULL,
+    "outcome" TEXT NOT NULL,
+    "error" TEXT,
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT
 );
-CREATE TABLE IF NOT EXISTS "behavior" (
-    "behavior_id" VARCHAR PRIMARY KEY NOT NULL,
-    "device_id" VARCHAR NOT NULL,
-    "behavior_name" TEXT NOT NULL,
-    "behavior_conf_json" TEXT CHECK(json_valid(behavior_conf_json)) NOT NULL,
-    "assurance_schema_id" VARCHAR,
-    "governance" TEXT CHECK(json_valid(governance) OR governance IS NULL),
+CREATE TABLE IF NOT EXISTS "ur_ingest_session_imap_acct_stat" (
+    "ur_ingest_session_imap_acct_stat_id" VARCHAR PRIMARY KEY NOT NULL,
+    "ingest_session_id" VARCHAR NOT NULL,
+    "ingest_account_id" VARCHAR NOT NULL,
+    "stat_kind" TEXT NOT NULL,
+    "stat_key" TEXT NOT NULL,
+    "stat_value" INTEGER NOT NULL,
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
     "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
     "created_by" TEXT DEFAULT ''UNKNOWN'',
     "updated_at" TIMESTAMPTZ,
@@ -929,18 +2431,24 @@ CREATE TABLE IF NOT EXISTS "behavior" (
     "deleted_at" TIMESTAMPTZ,
     "deleted_by" TEXT,
     "activity_log" TEXT,
-    FOREIGN KEY("device_id") REFERENCES "device"("device_id"),
-    FOREIGN KEY("assurance_schema_id") REFERENCES "assurance_schema"("assurance_schema_id"),
-    UNIQUE("device_id", "behavior_name")
+    FOREIGN KEY("ingest_session_id") REFERENCES "ur_ingest_session"("ur_ingest_session_id"),
+    FOREIGN KEY("ingest_account_id") REFERENCES "ur_ingest_session_imap_account"("ur_ingest_session_imap_account_id")
 );
-CREATE TABLE IF NOT EXISTS "ur_ingest_resource_path_match_rule" (
-    "ur_ingest_resource_path_match_rule_id" VARCHAR PRIMARY KEY NOT NULL,
-    "namespace" TEXT NOT NULL,
-    "regex" TEXT NOT NULL,
-    "flags" TEXT NOT NULL,
-    "nature" TEXT,
-    "priority" TEXT,
-    "description" TEXT,
+CREATE TABLE IF NOT EXISTS "policy_finding" (
+    "policy_finding_id" VARCHAR PRIMARY KEY NOT NULL,
+    "pack" TEXT NOT NULL,
+    "assertion" TEXT NOT NULL,
+    "severity" TEXT NOT NULL,
+    "state" TEXT NOT NULL,
+    "status" TEXT NOT NULL,
+    "row_count" INTEGER NOT NULL,
+    "sample_rows" TEXT CHECK(json_valid(sample_rows) OR sample_rows IS NULL),
+    "remediation" TEXT,
+    "error" TEXT,
+    "last_seen_at" TIMESTAMPTZ NOT NULL,
+    "acknowledged_at" TIMESTAMPTZ,
+    "acknowledged_by" TEXT,
+    "resolved_at" TIMESTAMPTZ,
     "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
     "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
     "created_by" TEXT DEFAULT ''UNKNOWN'',
@@ -948,16 +2456,16 @@ CREATE TABLE IF NOT EXISTS "ur_ingest_resource_path_match_rule" (
     "updated_by" TEXT,
     "deleted_at" TIMESTAMPTZ,
     "deleted_by" TEXT,
-    "activity_log" TEXT,
-    UNIQUE("namespace", "regex")
+    "activity_log" TEXT
 );
-CREATE TABLE IF NOT EXISTS "ur_ingest_resource_path_rewrite_rule" (
-    "ur_ingest_resource_path_rewrite_rule_id" VARCHAR PRIMARY KEY NOT NULL,
-    "namespace" TEXT NOT NULL,
-    "regex" TEXT NOT NULL,
-    "replace" TEXT NOT NULL,
-    "priority" TEXT,
-    "description" TEXT,
+CREATE TABLE IF NOT EXISTS "policy_alert_rule" (
+    "policy_alert_rule_id" VARCHAR PRIMARY KEY NOT NULL,
+    "name" TEXT NOT NULL,
+    "min_severity" TEXT NOT NULL,
+    "fire_on" TEXT NOT NULL,
+    "channel" TEXT NOT NULL,
+    "target" TEXT NOT NULL,
+    "packs" TEXT CHECK(json_valid(packs) OR packs IS NULL),
     "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
     "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
     "created_by" TEXT DEFAULT ''UNKNOWN'',
@@ -966,15 +2474,14 @@ CREATE TABLE IF NOT EXISTS "ur_ingest_resource_path_rewrite_rule" (
     "deleted_at" TIMESTAMPTZ,
     "deleted_by" TEXT,
     "activity_log" TEXT,
-    UNIQUE("namespace", "regex", "replace")
+    UNIQUE("name")
 );
-CREATE TABLE IF NOT EXISTS "ur_ingest_session" (
-    "ur_ingest_session_id" VARCHAR PRIMARY KEY NOT NULL,
-    "device_id" VARCHAR NOT NULL,
-    "behavior_id" VARCHAR,
-    "behavior_json" TEXT CHECK(json_valid(behavior_json) OR behavior_json IS NULL),
-    "ingest_started_at" TIMESTAMPTZ NOT NULL,
-    "ingest_finished_at" TIMESTAMPTZ,
+CREATE TABLE IF NOT EXISTS "compliance_control" (
+    "compliance_control_id" VARCHAR PRIMARY KEY NOT NULL,
+    "framework" TEXT NOT NULL,
+    "control_code" TEXT NOT NULL,
+    "title" TEXT,
+    "description" TEXT,
     "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
     "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
     "created_by" TEXT DEFAULT ''UNKNOWN'',
@@ -983,14 +2490,14 @@ CREATE TABLE IF NOT EXISTS "ur_ingest_session" (
     "deleted_at" TIMESTAMPTZ,
     "deleted_by" TEXT,
     "activity_log" TEXT,
-    FOREIGN KEY("device_id") REFERENCES "device"("device_id"),
-    FOREIGN KEY("behavior_id") REFERENCES "behavior"("behavior_id"),
-    UNIQUE("device_id", "created_at")
+    UNIQUE("framework", "control_code")
 );
-CREATE TABLE IF NOT EXISTS "ur_ingest_session_fs_path" (
-    "ur_ingest_session_fs_path_id" VARCHAR PRIMARY KEY NOT NULL,
-    "ingest_session_id" VARCHAR NOT NULL,
-    "root_path" TEXT NOT NULL,
+CREATE TABLE IF NOT EXISTS "compliance_evidence_map" (
+    "compliance_evidence_map_id" VARCHAR PRIMARY KEY NOT NULL,
+    "compliance_control_id" VARCHAR NOT NULL,
+    "evidence_kind" TEXT NOT NULL,
+    "evidence_selector" TEXT NOT NULL,
+    "description" TEXT,
     "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
     "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
     "created_by" TEXT DEFAULT ''UNKNOWN'',
@@ -999,23 +2506,17 @@ CREATE TABLE IF NOT EXISTS "ur_ingest_session_fs_path" (
     "deleted_at" TIMESTAMPTZ,
     "deleted_by" TEXT,
     "activity_log" TEXT,
-    FOREIGN KEY("ingest_session_id") REFERENCES "ur_ingest_session"("ur_ingest_session_id"),
-    UNIQUE("ingest_session_id", "root_path", "created_at")
+    FOREIGN KEY("compliance_control_id") REFERENCES "compliance_control"("compliance_control_id"),
+    UNIQUE("compliance_control_id", "evidence_kind", "evidence_selector")
 );
-CREATE TABLE IF NOT EXISTS "uniform_resource" (
-    "uniform_resource_id" VARCHAR PRIMARY KEY NOT NULL,
-    "device_id" VARCHAR NOT NULL,
-    "ingest_session_id" VARCHAR NOT NULL,
-    "ingest_fs_path_id" VARCHAR,
-    "ingest_imap_acct_folder_id" VARCHAR,
-    "uri" TEXT NOT NULL,
-    "content_digest" TEXT NOT NULL,
-    "content" BLOB,
-    "nature" TEXT,
-    "size_bytes" INTEGER,
-    "last_modified_at" TIMESTAMPTZ,
-    "content_fm_body_attrs" TEXT CHECK(json_valid(content_fm_body_attrs) OR content_fm_body_attrs IS NULL),
-    "frontmatter" TEXT CHECK(json_valid(frontmatter) OR frontmatter IS NULL),
+CREATE TABLE IF NOT EXISTS "known_file_hash" (
+    "known_file_hash_id" VARCHAR PRIMARY KEY NOT NULL,
+    "hash_set" TEXT NOT NULL,
+    "algorithm" TEXT NOT NULL,
+    "digest" TEXT NOT NULL,
+    "disposition" TEXT NOT NULL,
+    "confidence" FLOAT NOT NULL,
+    "file_name" TEXT,
     "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
     "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
     "created_by" TEXT DEFAULT ''UNKNOWN'',
@@ -1024,20 +2525,15 @@ CREATE TABLE IF NOT EXISTS "uniform_resource" (
     "deleted_at" TIMESTAMPTZ,
     "deleted_by" TEXT,
     "activity_log" TEXT,
-    FOREIGN KEY("device_id") REFERENCES "device"("device_id"),
-    FOREIGN KEY("ingest_session_id") REFERENCES "ur_ingest_session"("ur_ingest_session_id"),
-    FOREIGN KEY("ingest_fs_path_id") REFERENCES "ur_ingest_session_fs_path"("ur_ingest_session_fs_path_id"),
-    FOREIGN KEY("ingest_imap_acct_folder_id") REFERENCES "ur_ingest_session_imap_acct_folder"("ur_ingest_session_imap_acct_folder_id"),
-    UNIQUE("device_id", "content_digest", "uri", "size_bytes", "last_modified_at")
+    UNIQUE("hash_set", "algorithm", "digest")
 );
-CREATE TABLE IF NOT EXISTS "uniform_resource_transform" (
-    "uniform_resource_transform_id" VARCHAR PRIMARY KEY NOT NULL,
+CREATE TABLE IF NOT EXISTS "uniform_resource_known_file" (
+    "uniform_resource_known_file_id" VARCHAR PRIMARY KEY NOT NULL,
     "uniform_resource_id" VARCHAR NOT NULL,
-    "uri" TEXT NOT NULL,
-    "content_digest" TEXT NOT NULL,
-    "content" BLOB,
-    "nature" TEXT,
-    "size_bytes" INTEGER,
+    "known_file_hash_id" VARCHAR NOT NULL,
+    "hash_set" TEXT NOT NULL,
+    "disposition" TEXT NOT NULL,
+    "confidence" FLOAT NOT NULL,
     "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
     "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
     "created_by" TEXT DEFAULT ''UNKNOWN'',
@@ -1047,22 +2543,22 @@ CREATE TABLE IF NOT EXISTS "uniform_resource_transform" (
     "deleted_by" TEXT,
     "activity_log" TEXT,
     FOREIGN KEY("uniform_resource_id") REFERENCES "uniform_resource"("uniform_resource_id"),
-    UNIQUE("uniform_resource_id", "content_digest", "nature", "size_bytes")
+    FOREIGN KEY("known_file_hash_id") REFERENCES "known_file_hash"("known_file_hash_id"),
+    UNIQUE("uniform_resource_id", "known_file_hash_id")
 );
-CREATE TABLE IF NOT EXISTS "ur_ingest_session_fs_path_entry" (
-    "ur_ingest_session_fs_path_entry_id" VARCHAR PRIMARY KEY NOT NULL,
+CREATE TABLE IF NOT EXISTS "ur_ingest_session_git_repo" (
+    "ur_ingest_session_git_repo_id" VARCHAR PRIMARY KEY NOT NULL,
     "ingest_session_id" VARCHAR NOT NULL,
-    "ingest_fs_path_id" VARCHAR NOT NULL,
-    "uniform_resource_id" VARCHAR,
-    "file_path_abs" TEXT NOT NULL,
-    "file_path_rel_parent" TEXT NOT NULL,
-    "file_path_rel" TEXT NOT NULL,
-    "file_basename" TEXT NOT NULL,
-    "file_extn" TEXT,
-    "captured_executable" TEXT CHECK(json_valid(captured_executable) OR captured_executable IS NULL),
-    "ur_status" TEXT,
-    "ur_diagnostics" TEXT CHECK(json_valid(ur_diagnostics) OR ur_diagnostics IS NULL),
-    "ur_transformations" TEXT CHECK(json_valid(ur_transformations) OR ur_transformations IS NULL),
+    "repo" TEXT NOT NULL,
+    "reference" TEXT NOT NULL,
+    "commit_sha" TEXT NOT NULL,
+    "author_name" TEXT NOT NULL,
+    "author_email" TEXT NOT NULL,
+    "authored_at" TIMESTAMPTZ NOT NULL,
+    "committer_name" TEXT NOT NULL,
+    "committer_email" TEXT NOT NULL,
+    "committed_at" TIMESTAMPTZ NOT NULL,
+    "subject" TEXT NOT NULL,
     "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
     "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
     "created_by" TEXT DEFAULT ''UNKNOWN'',
@@ -1072,18 +2568,15 @@ CREATE TABLE IF NOT EXISTS "ur_ingest_session_fs_path_entry" (
     "deleted_by" TEXT,
     "activity_log" TEXT,
     FOREIGN KEY("ingest_session_id") REFERENCES "ur_ingest_session"("ur_ingest_session_id"),
-    FOREIGN KEY("ingest_fs_path_id") REFERENCES "ur_ingest_session_fs_path"("ur_ingest_session_fs_path_id"),
-    FOREIGN KEY("uniform_resource_id") REFERENCES "uniform_resource"("uniform_resource_id")
+    UNIQUE("ingest_session_id", "repo")
 );
-CREATE TABLE IF NOT EXISTS "ur_ingest_session_task" (
-    "ur_ingest_session_task_id" VARCHAR PRIMARY KEY NOT NULL,
-    "ingest_session_id" VARCHAR NOT NULL,
-    "uniform_resource_id" VARCHAR,
-    "captured_executable" TEXT CHECK(json_valid(captured_executable)) NOT NULL,
-    "ur_status" TEXT,
-    "ur_diagnostics" TEXT CHECK(json_valid(ur_diagnostics) OR ur_diagnostics IS NULL),
-    "ur_transformations" TEXT CHECK(json_valid(ur_transformations) OR ur_transformations IS NULL),
-    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+CREATE TABLE IF NOT EXISTS "surveilr_remote_sync" (
+    "surveilr_remote_sync_id" VARCHAR PRIMARY KEY NOT NULL,
+    "remote_url" TEXT NOT NULL,
+    "table_name" TEXT NOT NULL,
+    "synced_rowid" INTEGER NOT NULL,
+    "synced_rows" INTEGER NOT NULL,
+    "synced_at" TIMESTAMPTZ NOT NULL,
     "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
     "created_by" TEXT DEFAULT ''UNKNOWN'',
     "updated_at" TIMESTAMPTZ,
@@ -1091,16 +2584,17 @@ CREATE TABLE IF NOT EXISTS "ur_ingest_session_task" (
     "deleted_at" TIMESTAMPTZ,
     "deleted_by" TEXT,
     "activity_log" TEXT,
-    FOREIGN KEY("ingest_session_id") REFERENCES "ur_ingest_session"("ur_ingest_session_id"),
-    FOREIGN KEY("uniform_resource_id") REFERENCES "uniform_resource"("uniform_resource_id")
+    "synced_change_id" INTEGER NOT NULL DEFAULT 0,
+    "scanned_at" TIMESTAMPTZ,
+    UNIQUE("remote_url", "table_name")
 );
-CREATE TABLE IF NOT EXISTS "ur_ingest_session_imap_account" (
-    "ur_ingest_session_imap_account_id" VARCHAR PRIMARY KEY NOT NULL,
-    "ingest_session_id" VARCHAR NOT NULL,
-    "email" TEXT,
-    "password" TEXT,
-    "host" TEXT,
-    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+CREATE TABLE IF NOT EXISTS "uniform_resource_equivalence" (
+    "uniform_resource_equivalence_id" VARCHAR PRIMARY KEY NOT NULL,
+    "content_digest" TEXT NOT NULL,
+    "uniform_resource_id" VARCHAR NOT NULL,
+    "device_id" VARCHAR NOT NULL,
+    "uri" TEXT NOT NULL,
+    "device_count" INTEGER NOT NULL,
     "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
     "created_by" TEXT DEFAULT ''UNKNOWN'',
     "updated_at" TIMESTAMPTZ,
@@ -1108,15 +2602,34 @@ CREATE TABLE IF NOT EXISTS "ur_ingest_session_imap_account" (
     "deleted_at" TIMESTAMPTZ,
     "deleted_by" TEXT,
     "activity_log" TEXT,
-    FOREIGN KEY("ingest_session_id") REFERENCES "ur_ingest_session"("ur_ingest_session_id"),
-    UNIQUE("ingest_session_id", "email")
+    FOREIGN KEY("uniform_resource_id") REFERENCES "uniform_resource"("uniform_resource_id"),
+    FOREIGN KEY("device_id") REFERENCES "device"("device_id"),
+    UNIQUE("content_digest", "uniform_resource_id")
 );
-CREATE TABLE IF NOT EXISTS "ur_ingest_session_imap_acct_folder" (
-    "ur_ingest_session_imap_acct_folder_id" VARCHAR PRIMARY KEY NOT NULL,
-    "ingest_session_id" VARCHAR NOT NULL,
-    "ingest_account_id" VARCHAR NOT NULL,
+CREATE TABLE IF NOT EXISTS "uniform_resource_chunk" (
+    "uniform_resource_chunk_id" VARCHAR PRIMARY KEY NOT NULL,
+    "uniform_resource_id" VARCHAR NOT NULL,
+    "chunk_index" INTEGER NOT NULL,
+    "content_digest" TEXT NOT NULL,
+    "size_bytes" INTEGER NOT NULL,
+    "content" BLOB NOT NULL,
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    FOREIGN KEY("uniform_resource_id") REFERENCES "uniform_resource"("uniform_resource_id"),
+    UNIQUE("uniform_resource_id", "chunk_index")
+);
+CREATE TABLE IF NOT EXISTS "ur_ingest_imap_folder_state" (
+    "ur_ingest_imap_folder_state_id" VARCHAR PRIMARY KEY NOT NULL,
+    "email" TEXT NOT NULL,
     "folder_name" TEXT NOT NULL,
-    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
+    "uid_validity" INTEGER NOT NULL,
+    "last_uid" INTEGER NOT NULL,
+    "ingest_session_id" VARCHAR NOT NULL,
     "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
     "created_by" TEXT DEFAULT ''UNKNOWN'',
     "updated_at" TIMESTAMPTZ,
@@ -1125,21 +2638,19 @@ CREATE TABLE IF NOT EXISTS "ur_ingest_session_imap_acct_folder" (
     "deleted_by" TEXT,
     "activity_log" TEXT,
     FOREIGN KEY("ingest_session_id") REFERENCES "ur_ingest_session"("ur_ingest_session_id"),
-    FOREIGN KEY("ingest_account_id") REFERENCES "ur_ingest_session_imap_account"("ur_ingest_session_imap_account_id"),
-    UNIQUE("ingest_account_id", "folder_name")
+    UNIQUE("email", "folder_name")
 );
-CREATE TABLE IF NOT EXISTS "ur_ingest_session_imap_acct_folder_message" (
-    "ur_ingest_session_imap_acct_folder_message_id" VARCHAR PRIMARY KEY NOT NULL,
+CREATE TABLE IF NOT EXISTS "uniform_resource_verification" (
+    "uniform_resource_verification_id" VARCHAR PRIMARY KEY NOT NULL,
+    "verification_id" TEXT NOT NULL,
     "ingest_session_id" VARCHAR NOT NULL,
-    "ingest_imap_acct_folder_id" VARCHAR NOT NULL,
     "uniform_resource_id" VARCHAR,
-    "message" TEXT NOT NULL,
-    "message_id" TEXT NOT NULL,
-    "subject" TEXT NOT NULL,
-    "from" TEXT NOT NULL,
-    "cc" TEXT CHECK(json_valid(cc)) NOT NULL,
-    "bcc" TEXT CHECK(json_valid(bcc)) NOT NULL,
-    "email_references" TEXT CHECK(json_valid(email_references)) NOT NULL,
+    "uri" TEXT NOT NULL,
+    "status" TEXT NOT NULL,
+    "content_digest_algorithm" TEXT NOT NULL,
+    "expected_digest" TEXT,
+    "actual_digest" TEXT,
+    "elaboration" TEXT CHECK(json_valid(elaboration) OR elaboration IS NULL),
     "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
     "created_by" TEXT DEFAULT ''UNKNOWN'',
     "updated_at" TIMESTAMPTZ,
@@ -1148,20 +2659,43 @@ CREATE TABLE IF NOT EXISTS "ur_ingest_session_imap_acct_folder_message" (
     "deleted_by" TEXT,
     "activity_log" TEXT,
     FOREIGN KEY("ingest_session_id") REFERENCES "ur_ingest_session"("ur_ingest_session_id"),
-    FOREIGN KEY("ingest_imap_acct_folder_id") REFERENCES "ur_ingest_session_imap_acct_folder"("ur_ingest_session_imap_acct_folder_id"),
     FOREIGN KEY("uniform_resource_id") REFERENCES "uniform_resource"("uniform_resource_id"),
-    UNIQUE("message", "message_id")
+    UNIQUE("verification_id", "uri")
+);
+CREATE TABLE IF NOT EXISTS "surveilr_remote_sync_change" (
+    "surveilr_remote_sync_change_id" INTEGER PRIMARY KEY AUTOINCREMENT,
+    "table_name" TEXT NOT NULL,
+    "row_id" INTEGER NOT NULL,
+    "deleted_key" TEXT CHECK(json_valid(deleted_key) OR deleted_key IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT ''UNKNOWN'',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT
 );
 
 CREATE INDEX IF NOT EXISTS "idx_device__name__state" ON "device"("name", "state");
 CREATE INDEX IF NOT EXISTS "idx_ur_ingest_session_fs_path__ingest_session_id__root_path" ON "ur_ingest_session_fs_path"("ingest_session_id", "root_path");
 CREATE INDEX IF NOT EXISTS "idx_uniform_resource__device_id__uri" ON "uniform_resource"("device_id", "uri");
+CREATE INDEX IF NOT EXISTS "idx_uniform_resource__device_id__nature__semantic_identity" ON "uniform_resource"("device_id", "nature", "semantic_identity");
 CREATE INDEX IF NOT EXISTS "idx_uniform_resource_transform__uniform_resource_id__content_digest" ON "uniform_resource_transform"("uniform_resource_id", "content_digest");
 CREATE INDEX IF NOT EXISTS "idx_ur_ingest_session_fs_path_entry__ingest_session_id__file_path_abs" ON "ur_ingest_session_fs_path_entry"("ingest_session_id", "file_path_abs");
 CREATE INDEX IF NOT EXISTS "idx_ur_ingest_session_task__ingest_session_id" ON "ur_ingest_session_task"("ingest_session_id");
 CREATE INDEX IF NOT EXISTS "idx_ur_ingest_session_imap_acct_folder__ingest_session_id__folder_name" ON "ur_ingest_session_imap_acct_folder"("ingest_session_id", "folder_name");
 CREATE INDEX IF NOT EXISTS "idx_ur_ingest_session_imap_acct_folder_message__ingest_session_id" ON "ur_ingest_session_imap_acct_folder_message"("ingest_session_id");
 CREATE INDEX IF NOT EXISTS "idx_ur_ingest_session_imap_account__ingest_session_id__email" ON "ur_ingest_session_imap_account"("ingest_session_id", "email");
+CREATE INDEX IF NOT EXISTS "idx_uniform_resource_lineage__ingest_session_id" ON "uniform_resource_lineage"("ingest_session_id");
+CREATE INDEX IF NOT EXISTS "idx_uniform_resource_lineage__output_uniform_resource_id" ON "uniform_resource_lineage"("output_uniform_resource_id");
+CREATE INDEX IF NOT EXISTS "idx_query_snapshot__name__created_at" ON "query_snapshot"("name", "created_at");
+CREATE INDEX IF NOT EXISTS "idx_surveilr_audit__command__started_at" ON "surveilr_audit"("command", "started_at");
+CREATE INDEX IF NOT EXISTS "idx_ur_ingest_session_imap_acct_stat__ingest_session_id__stat_kind" ON "ur_ingest_session_imap_acct_stat"("ingest_session_id", "stat_kind");
+CREATE INDEX IF NOT EXISTS "idx_policy_finding__pack__assertion__state" ON "policy_finding"("pack", "assertion", "state");
+CREATE INDEX IF NOT EXISTS "idx_known_file_hash__algorithm__digest" ON "known_file_hash"("algorithm", "digest");
+CREATE INDEX IF NOT EXISTS "idx_uniform_resource_known_file__uniform_resource_id" ON "uniform_resource_known_file"("uniform_resource_id");
+CREATE INDEX IF NOT EXISTS "idx_ur_ingest_session_git_repo__repo__commit_sha" ON "ur_ingest_session_git_repo"("repo", "commit_sha");
+CREATE INDEX IF NOT EXISTS "idx_uniform_resource_equivalence__content_digest__device_id" ON "uniform_resource_equivalence"("content_digest", "device_id");
 
 
 DROP VIEW IF EXISTS "ur_ingest_session_files_stats";
@@ -1223,13 +2757,13 @@ CREATE VIEW IF NOT EXISTS "ur_ingest_session_files_stats" AS
         device_id,
         ingest_session_finished_at,
         file_extension;
-      ', 'b03283e4fd0b7f867d564c08481d2adaec5315cf', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+      ', '400857c8993a29b5d3a199080b8a0f71adb579fc', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
                    interpretable_code = EXCLUDED.interpretable_code,
                    notebook_kernel_id = EXCLUDED.notebook_kernel_id,
                    updated_at = CURRENT_TIMESTAMP,
                    activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));;
 
-INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((ulid()), 'PlantUML', 'SqlNotebooksOrchestrator', 'surveilrInfoSchemaDiagram', NULL, '@startuml surveilr-state
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'PlantUML', 'SqlNotebooksOrchestrator', 'surveilrInfoSchemaDiagram', NULL, '@startuml surveilr-state
   hide circle
   skinparam linetype ortho
   skinparam roundcorner 20
@@ -1300,6 +2834,8 @@ INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id",
     * ingest_started_at: TIMESTAMPTZ
       ingest_finished_at: TIMESTAMPTZ
       elaboration: TEXT
+      device_clock: TEXT
+      content_digest_algorithm: TEXT
     --
     urIngestSessionFsPaths: UrIngestSessionFsPath[]
     uniformResources: UniformResource[]
@@ -1335,6 +2871,8 @@ INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id",
       content_fm_body_attrs: TEXT
       frontmatter: TEXT
       elaboration: TEXT
+      semantic_identity: TEXT
+      content_digest_algorithm: TEXT
     --
     uniformResourceTransforms: UniformResourceTransform[]
   }
@@ -1417,6 +2955,216 @@ INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id",
     * cc: TEXT
     * bcc: TEXT
     * email_references: TEXT
+      date: TEXT
+  }
+
+  entity "uniform_resource_lineage" as uniform_resource_lineage {
+    * **uniform_resource_lineage_id**: VARCHAR
+    --
+    * ingest_session_id: VARCHAR
+      source_uniform_resource_id: VARCHAR
+    * output_uniform_resource_id: VARCHAR
+      ingest_fs_path_entry_id: VARCHAR
+      ingest_task_id: VARCHAR
+      elaboration: TEXT
+  }
+
+  entity "rssd_metadata" as rssd_metadata {
+    * **key**: TEXT
+    --
+    * value: TEXT
+      elaboration: TEXT
+  }
+
+  entity "query_snapshot" as query_snapshot {
+    * **query_snapshot_id**: VARCHAR
+    --
+    * name: TEXT
+    * query_sql: TEXT
+    * result_schema: TEXT
+    * result_rows: TEXT
+    * row_count: INTEGER
+    * content_digest: TEXT
+      elaboration: TEXT
+  }
+
+  entity "surveilr_audit" as surveilr_audit {
+    * **surveilr_audit_id**: VARCHAR
+    --
+    * command: TEXT
+    * argv: TEXT
+    * os_user: TEXT
+    * host: TEXT
+    * started_at: TIMESTAMPTZ
+    * outcome: TEXT
+      error: TEXT
+      elaboration: TEXT
+  }
+
+  entity "ur_ingest_session_imap_acct_stat" as ur_ingest_session_imap_acct_stat {
+    * **ur_ingest_session_imap_acct_stat_id**: VARCHAR
+    --
+    * ingest_session_id: VARCHAR
+    * ingest_account_id: VARCHAR
+    * stat_kind: TEXT
+    * stat_key: TEXT
+    * stat_value: INTEGER
+      elaboration: TEXT
+  }
+
+  entity "policy_finding" as policy_finding {
+    * **policy_finding_id**: VARCHAR
+    --
+    * pack: TEXT
+    * assertion: TEXT
+    * severity: TEXT
+    * state: TEXT
+    * status: TEXT
+    * row_count: INTEGER
+      sample_rows: TEXT
+      remediation: TEXT
+      error: TEXT
+    * last_seen_at: TIMESTAMPTZ
+      acknowledged_at: TIMESTAMPTZ
+      acknowledged_by: TEXT
+      resolved_at: TIMESTAMPTZ
+      elaboration: TEXT
+  }
+
+  entity "policy_alert_rule" as policy_alert_rule {
+    * **policy_alert_rule_id**: VARCHAR
+    --
+    * name: TEXT
+    * min_severity: TEXT
+    * fire_on: TEXT
+    * channel: TEXT
+    * target: TEXT
+      packs: TEXT
+      elaboration: TEXT
+  }
+
+  entity "compliance_control" as compliance_control {
+    * **compliance_control_id**: VARCHAR
+    --
+    * framework: TEXT
+    * control_code: TEXT
+      title: TEXT
+      description: TEXT
+      elaboration: TEXT
+  }
+
+  entity "compliance_evidence_map" as compliance_evidence_map {
+    * **compliance_evidence_map_id**: VARCHAR
+    --
+    * compliance_control_id: VARCHAR
+    * evidence_kind: TEXT
+    * evidence_selector: TEXT
+      description: TEXT
+      elaboration: TEXT
+  }
+
+  entity "known_file_hash" as known_file_hash {
+    * **known_file_hash_id**: VARCHAR
+    --
+    * hash_set: TEXT
+    * algorithm: TEXT
+    * digest: TEXT
+    * disposition: TEXT
+    * confidence: FLOAT
+      file_name: TEXT
+      elaboration: TEXT
+  }
+
+  entity "uniform_resource_known_file" as uniform_resource_known_file {
+    * **uniform_resource_known_file_id**: VARCHAR
+    --
+    * uniform_resource_id: VARCHAR
+    * known_file_hash_id: VARCHAR
+    * hash_set: TEXT
+    * disposition: TEXT
+    * confidence: FLOAT
+      elaboration: TEXT
+  }
+
+  entity "ur_ingest_session_git_repo" as ur_ingest_session_git_repo {
+    * **ur_ingest_session_git_repo_id**: VARCHAR
+    --
+    * ingest_session_id: VARCHAR
+    * repo: TEXT
+    * reference: TEXT
+    * commit_sha: TEXT
+    * author_name: TEXT
+    * author_email: TEXT
+    * authored_at: TIMESTAMPTZ
+    * committer_name: TEXT
+    * committer_email: TEXT
+    * committed_at: TIMESTAMPTZ
+    * subject: TEXT
+      elaboration: TEXT
+  }
+
+  entity "surveilr_remote_sync" as surveilr_remote_sync {
+    * **surveilr_remote_sync_id**: VARCHAR
+    --
+    * remote_url: TEXT
+    * table_name: TEXT
+    * synced_rowid: INTEGER
+    * synced_rows: INTEGER
+    * synced_at: TIMESTAMPTZ
+    * synced_change_id: INTEGER
+      scanned_at: TIMESTAMPTZ
+  }
+
+  entity "uniform_resource_equivalence" as uniform_resource_equivalence {
+    * **uniform_resource_equivalence_id**: VARCHAR
+    --
+    * content_digest: TEXT
+    * uniform_resource_id: VARCHAR
+    * device_id: VARCHAR
+    * uri: TEXT
+    * device_count: INTEGER
+  }
+
+  entity "uniform_resource_chunk" as uniform_resource_chunk {
+    * **uniform_resource_chunk_id**: VARCHAR
+    --
+    * uniform_resource_id: VARCHAR
+    * chunk_index: INTEGER
+    * content_digest: TEXT
+    * size_bytes: INTEGER
+    * content: BLOB
+  }
+
+  entity "ur_ingest_imap_folder_state" as ur_ingest_imap_folder_state {
+    * **ur_ingest_imap_folder_state_id**: VARCHAR
+    --
+    * email: TEXT
+    * folder_name: TEXT
+    * uid_validity: INTEGER
+    * last_uid: INTEGER
+    * ingest_session_id: VARCHAR
+  }
+
+  entity "uniform_resource_verification" as uniform_resource_verification {
+    * **uniform_resource_verification_id**: VARCHAR
+    --
+    * verification_id: TEXT
+    * ingest_session_id: VARCHAR
+      uniform_resource_id: VARCHAR
+    * uri: TEXT
+    * status: TEXT
+    * content_digest_algorithm: TEXT
+      expected_digest: TEXT
+      actual_digest: TEXT
+      elaboration: TEXT
+  }
+
+  entity "surveilr_remote_sync_change" as surveilr_remote_sync_change {
+    * **surveilr_remote_sync_change_id**: INTEGER
+    --
+    * table_name: TEXT
+    * row_id: INTEGER
+      deleted_key: TEXT
   }
 
   device |o..o{ behavior
@@ -1439,12 +3187,29 @@ INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id",
   ur_ingest_session |o..o{ ur_ingest_session_imap_acct_folder_message
   ur_ingest_session_imap_acct_folder |o..o{ ur_ingest_session_imap_acct_folder_message
   uniform_resource |o..o{ ur_ingest_session_imap_acct_folder_message
-@enduml', '23f9c3c8a6b75ff4c471689c9851fb2b84da7f1c', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+  ur_ingest_session |o..o{ uniform_resource_lineage
+  uniform_resource |o..o{ uniform_resource_lineage
+  uniform_resource |o..o{ uniform_resource_lineage
+  ur_ingest_session_fs_path_entry |o..o{ uniform_resource_lineage
+  ur_ingest_session_task |o..o{ uniform_resource_lineage
+  ur_ingest_session |o..o{ ur_ingest_session_imap_acct_stat
+  ur_ingest_session_imap_account |o..o{ ur_ingest_session_imap_acct_stat
+  compliance_control |o..o{ compliance_evidence_map
+  uniform_resource |o..o{ uniform_resource_known_file
+  known_file_hash |o..o{ uniform_resource_known_file
+  ur_ingest_session |o..o{ ur_ingest_session_git_repo
+  uniform_resource |o..o{ uniform_resource_equivalence
+  device |o..o{ uniform_resource_equivalence
+  uniform_resource |o..o{ uniform_resource_chunk
+  ur_ingest_session |o..o{ ur_ingest_imap_folder_state
+  ur_ingest_session |o..o{ uniform_resource_verification
+  uniform_resource |o..o{ uniform_resource_verification
+@enduml', '5ba2adde6e0959c3c6552512bf4df27a906fc9ac', NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL) ON CONFLICT(notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
              interpretable_code = EXCLUDED.interpretable_code,
              notebook_kernel_id = EXCLUDED.notebook_kernel_id,
              updated_at = CURRENT_TIMESTAMP,
              activity_log = json_insert(COALESCE(activity_log, '[]'), '$[' || json_array_length(COALESCE(activity_log, '[]')) || ']', json_object('code_notebook_cell_id', code_notebook_cell_id, 'notebook_kernel_id', notebook_kernel_id, 'notebook_name', notebook_name, 'cell_name', cell_name, 'cell_governance', cell_governance, 'interpretable_code', interpretable_code, 'interpretable_code_hash', interpretable_code_hash, 'description', description, 'arguments', arguments, 'created_at', created_at, 'created_by', created_by, 'updated_at', updated_at, 'updated_by', updated_by, 'deleted_at', deleted_at, 'deleted_by', deleted_by, 'activity_log', activity_log));
-INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((ulid()), 'PlantUML', 'SqlNotebooksOrchestrator', 'notebooksInfoSchemaDiagram', NULL, '@startuml surveilr-code-notebooks
+INSERT INTO "code_notebook_cell" ("code_notebook_cell_id", "notebook_kernel_id", "notebook_name", "cell_name", "cell_governance", "interpretable_code", "interpretable_code_hash", "description", "arguments", "created_at", "created_by", "updated_at", "updated_by", "deleted_at", "deleted_by", "activity_log") VALUES ((surveilr_pk()), 'PlantUML', 'SqlNotebooksOrchestrator', 'notebooksInfoSchemaDiagram', NULL, '@startuml surveilr-code-notebooks
   hide circle
   skinparam linetype ortho
   skinparam roundcorner 20
@@ -0,0 +1,287 @@
+use std::io::Read;
+
+use anyhow::Context;
+use clap::{Args, Subcommand};
+use comfy_table::{presets::UTF8_FULL, Table};
+use rusqlite::Connection;
+use serde::Serialize;
+
+use crate::compliance::{
+    compliance_report, controls, import_controls, unmap_evidence, upsert_control, Control,
+    Evidence, EvidenceKind,
+};
+use crate::persist::DbConn;
+
+const DEFAULT_STATEDB_FS_PATH: &str = "resource-surveillance.sqlite.db";
+
+/// Map resources, policies and capturable executable outputs to the controls of
+/// compliance frameworks and report their coverage
+#[derive(Debug, Serialize, Args, Clone)]
+pub struct ComplianceArgs {
+    /// target SQLite database
+    #[arg(short='d', long, default_value = DEFAULT_STATEDB_FS_PATH, default_missing_value = "always", env="SURVEILR_STATEDB_FS_PATH")]
+    pub state_db_fs_path: String,
+
+    /// open the database read-only (`controls` and `report`) so it's safe while
+    /// it's being written
+    #[arg(long)]
+    read_only: bool,
+
+    #[command(subcommand)]
+    pub command: ComplianceCommands,
+}
+
+/// The evidence selected by `map` and `unmap`.
+#[derive(Debug, Serialize, Args, Clone)]
+#[group(multiple = false)]
+pub struct EvidenceArgs {
+    /// uniform resources whose URI matches this GLOB
+    #[arg(long)]
+    resources: Option<String>,
+
+    /// the assertions of a policy pack, `pack` or `pack::assertion`
+    #[arg(long)]
+    policy: Option<String>,
+
+    /// outputs of capturable executables whose URI matches this GLOB
+    #[arg(long)]
+    capturable_exec: Option<String>,
+}
+
+impl EvidenceArgs {
+    fn evidence(&self) -> Option<(EvidenceKind, &str)> {
+        match (&self.resources, &self.policy, &self.capturable_exec) {
+            (Some(glob), _, _) => Some((EvidenceKind::Resource, glob)),
+            (_, Some(pack), _) => Some((EvidenceKind::Policy, pack)),
+            (_, _, Some(glob)) => Some((EvidenceKind::CapturableExec, glob)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Subcommand, Clone)]
+pub enum ComplianceCommands {
+    /// create or update controls and their evidence from a YAML or JSON catalog
+    Import {
+        /// the catalog, an array of `{framework, control, title, description,
+        /// evidence: [{kind, selector}]}`, `-` for STDIN
+        file: String,
+    },
+    /// list the controls and their evidence
+    Controls {
+        /// only list the controls of these frameworks
+        #[arg(short, long)]
+        framework: Vec<String>,
+    },
+    /// map evidence to a control, creating the control if needed
+    Map {
+        /// the framework, e.g. `SOC2`
+        #[arg(short, long)]
+        framework: String,
+
+        /// the control identifier, e.g. `CC6.1`
+        #[arg(short, long)]
+        control: String,
+
+        #[command(flatten)]
+        evidence: EvidenceArgs,
+
+        /// the control's title
+        #[arg(long)]
+        title: Option<String>,
+
+        /// how the evidence supports the control
+        #[arg(long)]
+        description: Option<String>,
+    },
+    /// remove evidence from a control, all of it if none is given
+    Unmap {
+        /// the framework
+        #[arg(short, long)]
+        framework: String,
+
+        /// the control identifier
+        #[arg(short, long)]
+        control: String,
+
+        #[command(flatten)]
+        evidence: EvidenceArgs,
+    },
+    /// summarize the coverage of each framework's controls and their gaps,
+    /// evaluating the mapped policies
+    Report {
+        /// only report on these frameworks
+        #[arg(short, long)]
+        framework: Vec<String>,
+
+        /// emit the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+impl ComplianceArgs {
+    pub fn execute(&self) -> anyhow::Result<()> {
+        if self.read_only {
+            if !matches!(
+                self.command,
+                ComplianceCommands::Controls { .. } | ComplianceCommands::Report { .. }
+            ) {
+                anyhow::bail!("this compliance command writes to the database, remove --read-only")
+            }
+            let dbc = DbConn::open(&self.state_db_fs_path, 0).with_context(|| {
+                format!(
+                    "[ComplianceArgs::execute] SQLite database {}",
+                    self.state_db_fs_path
+                )
+            })?;
+            return self.query(&dbc.conn);
+        }
+
+        let mut dbc = DbConn::new(&self.state_db_fs_path, 0).with_context(|| {
+            format!(
+                "[ComplianceArgs::execute] SQLite database {}",
+                self.state_db_fs_path
+            )
+        })?;
+        // makes sure RSSDs created before `compliance_control` existed are migrated
+        let tx = dbc.init(None)?;
+
+        match &self.command {
+            ComplianceCommands::Import { file } => {
+                let mut catalog = String::new();
+                if file == "-" {
+                    std::io::stdin().read_to_string(&mut catalog)?;
+                } else {
+                    catalog = std::fs::read_to_string(file)
+                        .with_context(|| format!("[ComplianceArgs::execute] {}", file))?;
+                }
+                let imported = import_controls(&tx, &catalog)?;
+                tx.commit()?;
+                println!("{} controls imported", imported);
+                Ok(())
+            }
+            ComplianceCommands::Map {
+                framework,
+                control,
+                evidence,
+                title,
+                description,
+            } => {
+                let Some((kind, selector)) = evidence.evidence() else {
+                    anyhow::bail!("--resources, --policy or --capturable-exec is required");
+                };
+                upsert_control(
+                    &tx,
+                    &Control {
+                        framework: framework.clone(),
+                        control_code: control.clone(),
+                        title: title.clone(),
+                        description: None,
+                        evidence: vec![Evidence {
+                            kind,
+                            selector: selector.to_string(),
+                            description: description.clone(),
+                        }],
+                    },
+                )?;
+                tx.commit()?;
+                println!("{} {}: {} {} mapped", framework, control, kind, selector);
+                Ok(())
+            }
+            ComplianceCommands::Unmap {
+                framework,
+                control,
+                evidence,
+            } => {
+                let removed = unmap_evidence(&tx, framework, control, evidence.evidence())?;
+                tx.commit()?;
+                if removed == 0 {
+                    anyhow::bail!("{} {} has no such evidence", framework, control);
+                }
+                println!("{} {}: {} evidence unmapped", framework, control, removed);
+                Ok(())
+            }
+            _ => self.query(&tx),
+        }
+    }
+
+    /// The commands which only read the RSSD.
+    fn query(&self, conn: &Connection) -> anyhow::Result<()> {
+        match &self.command {
+            ComplianceCommands::Controls { framework } => {
+                let mut table = Table::new();
+                table.load_preset(UTF8_FULL).set_header(vec![
+                    "Framework",
+                    "Control",
+                    "Title",
+                    "Evidence",
+                ]);
+                for c in controls(conn, framework)? {
+                    table.add_row(vec![
+                        c.framework,
+                        c.control_code,
+                        c.title.unwrap_or_default(),
+                        c.evidence
+                            .iter()
+                            .map(|e| format!("{} {}", e.kind, e.selector))
+                            .collect::<Vec<_>>()
+                            .join("\n"),
+                    ]);
+                }
+                println!("{table}");
+            }
+            ComplianceCommands::Report { framework, json } => {
+                let report = compliance_report(conn, framework)?;
+                if *json {
+                    println!("{}", serde_json::to_string_pretty(&report)?);
+                    return Ok(());
+                }
+                for f in report {
+                    let mut table = Table::new();
+                    table
+                        .load_preset(UTF8_FULL)
+                        .set_header(vec!["Control", "Title", "Status", "Evidence"]);
+                    for c in &f.controls {
+                        table.add_row(vec![
+                            c.control_code.clone(),
+                            c.title.clone().unwrap_or_default(),
+                            c.status.to_string(),
+                            c.evidence
+                                .iter()
+                                .map(|e| match e.evidence.kind {
+                                    EvidenceKind::Policy if !e.failing.is_empty() => format!(
+                                        "{} {} (failing: {})",
+                                        e.evidence.kind,
+                                        e.evidence.selector,
+                                        e.failing.join(", ")
+                                    ),
+                                    EvidenceKind::Policy => format!(
+                                        "{} {} ({} assertions)",
+                                        e.evidence.kind, e.evidence.selector, e.found
+                                    ),
+                                    _ => format!(
+                                        "{} {} ({} found)",
+                                        e.evidence.kind, e.evidence.selector, e.found
+                                    ),
+                                })
+                                .collect::<Vec<_>>()
+                                .join("\n"),
+                        ]);
+                    }
+                    println!("{}\n{table}", f.framework);
+                    println!(
+                        "{} controls: {} satisfied, {} failing, {} gaps ({:.0}% covered)\n",
+                        f.controls.len(),
+                        f.satisfied,
+                        f.failing,
+                        f.gaps,
+                        f.coverage()
+                    );
+                }
+            }
+            _ => unreachable!("only the compliance commands which read are queries"),
+        }
+        Ok(())
+    }
+}
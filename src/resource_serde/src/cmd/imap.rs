@@ -1,5 +1,10 @@
 use clap::{Args, Subcommand, ValueEnum};
-use resource_imap::{ImapConfig, Microsoft365AuthServerConfig, Microsoft365Config};
+use common::secret::Secret;
+#[cfg(feature = "imap")]
+use resource_imap::{
+    GmailConfig, ImapConfig, ImapOAuth2, Microsoft365AuthServerConfig, Microsoft365Config,
+    TokenGenerationMethod,
+};
 use serde::Serialize;
 const DEFAULT_STATEDB_FS_PATH: &str = "resource-surveillance.sqlite.db";
 
@@ -18,7 +23,7 @@ pub struct Microsoft365ServiceArgs {
     pub client_id: String,
     /// Client Secret of the application from MSFT Azure App Directory
     #[arg(short = 's', long, env = "MICROSOFT_365_CLIENT_SECRET")]
-    pub client_secret: String,
+    pub client_secret: Secret,
     /// The mode to generate an access_token. Default is 'DeviceCode'.
     #[arg(short = 'm', long)]
     pub mode: Microsoft365AuthMethod,
@@ -39,12 +44,42 @@ pub struct Microsoft365ServiceArgs {
     pub port: u16,
 }
 
+#[derive(Debug, Serialize, Args, Clone)]
+pub struct GmailServiceArgs {
+    /// Client ID of the OAuth client from the Google Cloud console
+    #[arg(short = 'i', long, env = "GMAIL_CLIENT_ID")]
+    pub client_id: String,
+    /// Client Secret of the OAuth client from the Google Cloud console
+    #[arg(short = 's', long, env = "GMAIL_CLIENT_SECRET")]
+    pub client_secret: Secret,
+    /// The mode to generate an access_token.
+    #[arg(short = 'm', long, default_value = "device-code")]
+    pub mode: Microsoft365AuthMethod,
+    /// Address to start the authentication server on, when using the `auth_code` mode for token generation.
+    #[arg(
+        short = 'a',
+        long,
+        default_value = "http://127.0.0.1:8000",
+        env = "GMAIL_CLIENT_REDIRECT_URI"
+    )]
+    pub addr: String,
+    /// Redirect URL. Base redirect URL path. It gets concatenated with the server address to form the full redirect url,
+    /// when using the `auth_code` mode for token generation.
+    #[arg(short = 'r', long, default_value = "/redirect")]
+    pub redirect_uri: String,
+    /// Port to bind the server to
+    #[arg(short = 'p', long, default_value = "8000")]
+    pub port: u16,
+}
+
 /// Email services that require oauth or a more complicated workflow
 #[derive(Debug, Serialize, Subcommand, Clone)]
 pub enum ServiceCommands {
     /// Microsoft 365 Credentials
     #[clap(name = "microsoft-365")]
     Microsoft365(Microsoft365ServiceArgs),
+    /// Google Workspace and Gmail mailboxes, through the Gmail API instead of IMAP
+    Gmail(GmailServiceArgs),
 }
 
 #[derive(Debug, Serialize, Clone, ValueEnum, Default)]
@@ -72,9 +107,20 @@ pub struct IngestImapArgs {
     pub username: Option<String>,
 
     /// password to the email. mainly an app password.
-    /// See the documentation on how to create an app password
+    /// See the documentation on how to create an app password.
+    /// Also accepts a reference: env://NAME, keyring://service/account or vault://path#key
     #[arg(short, long)]
-    pub password: Option<String>,
+    pub password: Option<Secret>,
+
+    /// OAuth2 access token, to authenticate with XOAUTH2 instead of a password
+    /// (Gmail and other providers which enforce OAuth)
+    #[arg(long, env = "SURVEILR_IMAP_ACCESS_TOKEN", conflicts_with = "password")]
+    pub access_token: Option<Secret>,
+
+    /// command printing an OAuth2 access token, executed for every connection so that
+    /// refreshed tokens are used, e.g. "gcloud auth print-access-token"
+    #[arg(long, conflicts_with_all = ["password", "access_token"])]
+    pub token_command: Option<String>,
 
     /// IMAP server address. e.g imap.gmail.com or outlook.office365.com
     #[arg(short = 'a', long)]
@@ -84,6 +130,11 @@ pub struct IngestImapArgs {
     #[arg(long, default_value = "993")]
     pub port: u16,
 
+    /// Connect without TLS, only allowed for servers on the loopback interface
+    /// (e.g. a local mail bridge listening on 127.0.0.1:1143)
+    #[arg(long)]
+    pub no_tls: bool,
+
     /// Mailboxes to read from. i.e folders. Takes a regular expression matching the folder names.
     /// The default is a "*" which means all folders.
     #[arg(short, long, default_value = "*")]
@@ -105,66 +156,100 @@ pub struct IngestImapArgs {
     #[arg(long, default_value = "false")]
     pub progress: bool,
 
+    /// Maximum number of folders to fetch concurrently, each over its own connection.
+    /// Fetched folders are still written to the database one at a time.
+    #[arg(long, default_value = "1")]
+    pub folder_concurrency: usize,
+
+    /// Maximum number of Microsoft 365 attachments (or Gmail messages) downloaded concurrently, across all folders.
+    #[arg(long, default_value = "4")]
+    pub attachment_concurrency: usize,
+
+    /// Only fetch the messages which arrived since the previous run, using the
+    /// UIDVALIDITY and last UID of every folder kept in `ur_ingest_imap_folder_state`.
+    /// IMAP servers only, Microsoft 365 and the Gmail API fetch the latest messages.
+    #[arg(long)]
+    pub resume: bool,
+
     /// Command line configuration for services that need extra authenctication to access emails.
     #[command(subcommand)]
     pub command: Option<ServiceCommands>,
 }
 
+#[cfg(feature = "imap")]
+impl From<Microsoft365AuthMethod> for TokenGenerationMethod {
+    fn from(value: Microsoft365AuthMethod) -> Self {
+        match value {
+            Microsoft365AuthMethod::AuthCode => TokenGenerationMethod::AuthCode,
+            Microsoft365AuthMethod::DeviceCode => TokenGenerationMethod::DeviceCode,
+            // Microsoft365AuthMethod::ClientCredential => TokenGenerationMethod::ClientCredential,
+        }
+    }
+}
+
+#[cfg(feature = "imap")]
 impl From<IngestImapArgs> for ImapConfig {
     fn from(value: IngestImapArgs) -> Self {
+        let (microsoft365, gmail) = match value.command {
+            Some(ServiceCommands::Microsoft365(config)) => {
+                let (server, redirect_uri) = match (config.addr, config.redirect_uri) {
+                    (Some(a), Some(r)) => {
+                        let full_redirect_url = format!("{a}{r}");
+                        let server_config = Microsoft365AuthServerConfig {
+                            addr: a,
+                            base_url: r,
+                            port: config.port,
+                        };
+                        (Some(server_config), Some(full_redirect_url))
+                    }
+                    _ => (None, None),
+                };
+
+                let msft_config = Microsoft365Config {
+                    client_id: config.client_id,
+                    client_secret: config.client_secret,
+                    redirect_uri,
+                    mode: config.mode.into(),
+                    auth_server: server,
+                };
+                (Some(msft_config), None)
+            }
+            Some(ServiceCommands::Gmail(config)) => {
+                let gmail_config = GmailConfig {
+                    client_id: config.client_id,
+                    client_secret: config.client_secret,
+                    mode: config.mode.into(),
+                    auth_server: Some(Microsoft365AuthServerConfig {
+                        addr: config.addr,
+                        base_url: config.redirect_uri,
+                        port: config.port,
+                    }),
+                };
+                (None, Some(gmail_config))
+            }
+            None => (None, None),
+        };
+
         ImapConfig {
             username: value.username,
             password: value.password,
+            oauth2: match (value.access_token, value.token_command) {
+                (Some(token), _) => Some(ImapOAuth2::AccessToken(token)),
+                (None, Some(command)) => Some(ImapOAuth2::TokenCommand(command)),
+                (None, None) => None,
+            },
             addr: value.server_addr,
             port: value.port,
+            tls: !value.no_tls,
             folder: value.folder,
             mailboxes: vec![],
             batch_size: value.batch_size,
             extract_attachments: value.extract_attachments,
             progress: value.progress,
-            microsoft365: {
-                if let Some(service_cmds) = value.command {
-                    match service_cmds {
-                        ServiceCommands::Microsoft365(config) => {
-                            let (server, redirect_uri) = match (config.addr, config.redirect_uri) {
-                                (Some(a), Some(r)) => {
-                                    let full_redirect_url = format!("{a}{r}");
-                                    let server_config = Microsoft365AuthServerConfig {
-                                        addr: a,
-                                        base_url: r,
-                                        port: config.port,
-                                    };
-                                    (Some(server_config), Some(full_redirect_url))
-                                }
-                                _ => (None, None),
-                            };
-
-                            let msft_config = Microsoft365Config {
-                                client_id: config.client_id,
-                                client_secret: config.client_secret,
-                                redirect_uri,
-                                mode: {
-                                    match config.mode {
-                                        Microsoft365AuthMethod::AuthCode => {
-                                            resource_imap::TokenGenerationMethod::AuthCode
-                                        }
-                                        Microsoft365AuthMethod::DeviceCode => {
-                                            resource_imap::TokenGenerationMethod::DeviceCode
-                                        }
-                                        // Microsoft365AuthMethod::ClientCredential => {
-                                        //     resource_imap::TokenGenerationMethod::ClientCredential
-                                        // }
-                                    }
-                                },
-                                auth_server: server,
-                            };
-                            Some(msft_config)
-                        }
-                    }
-                } else {
-                    None
-                }
-            },
+            folder_concurrency: value.folder_concurrency,
+            attachment_concurrency: value.attachment_concurrency,
+            microsoft365,
+            gmail,
         }
     }
 }
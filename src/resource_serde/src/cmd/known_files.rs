@@ -0,0 +1,221 @@
+use anyhow::Context;
+use clap::{Args, Subcommand};
+use comfy_table::{presets::UTF8_FULL, Table};
+use rusqlite::Connection;
+use serde::Serialize;
+
+use crate::known_files::{
+    hash_sets, known_file_matches, load_hash_set, match_known_files, remove_hash_set, Disposition,
+    HashSetLoad,
+};
+use crate::persist::DbConn;
+
+const DEFAULT_STATEDB_FS_PATH: &str = "resource-surveillance.sqlite.db";
+
+/// Load known-good and known-bad file hash sets (NSRL or custom) and tag the
+/// uniform resources which match them
+#[derive(Debug, Serialize, Args, Clone)]
+pub struct KnownFilesArgs {
+    /// target SQLite database
+    #[arg(short='d', long, default_value = DEFAULT_STATEDB_FS_PATH, default_missing_value = "always", env="SURVEILR_STATEDB_FS_PATH")]
+    pub state_db_fs_path: String,
+
+    /// open the database read-only (`sets` and `ls`) so it's safe while it's
+    /// being written
+    #[arg(long)]
+    read_only: bool,
+
+    #[command(subcommand)]
+    pub command: KnownFilesCommands,
+}
+
+#[derive(Debug, Serialize, Subcommand, Clone)]
+pub enum KnownFilesCommands {
+    /// load a hash set: an NSRL RDSv3 database, NSRL's legacy `NSRLFile.txt`,
+    /// a CSV with `sha1`, `sha256` or `md5` columns or `sha256sum` output
+    Load {
+        /// the hash set file, `-` for STDIN
+        file: String,
+
+        /// the name of the hash set, e.g. `nsrl`
+        #[arg(short, long)]
+        set: String,
+
+        /// the disposition of hashes without a `disposition` column
+        #[arg(long, value_enum, default_value_t = Disposition::KnownGood)]
+        disposition: Disposition,
+
+        /// how much the hash set is trusted (0 to 1), for hashes without a
+        /// `confidence` column
+        #[arg(long, default_value_t = 1.0)]
+        confidence: f64,
+
+        /// remove the set's previous hashes and matches first
+        #[arg(long)]
+        replace: bool,
+    },
+    /// list the hash sets
+    Sets,
+    /// tag the resources whose content matches a loaded hash
+    Match {
+        /// only the resources of this ingest session
+        #[arg(long)]
+        session: Option<String>,
+    },
+    /// list the known-bad, known-good or unknown resources
+    Ls {
+        /// resources with this disposition, unknown ones without it
+        #[arg(long, value_enum)]
+        disposition: Option<Disposition>,
+
+        /// only the resources of this ingest session
+        #[arg(long)]
+        session: Option<String>,
+
+        /// only resources of these natures, e.g. `--nature exe --nature bin`
+        #[arg(long)]
+        nature: Vec<String>,
+
+        /// emit the resources as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// remove a hash set and its matches
+    Remove {
+        /// the name of the hash set
+        set: String,
+    },
+}
+
+impl KnownFilesArgs {
+    pub fn execute(&self) -> anyhow::Result<()> {
+        if self.read_only {
+            if !matches!(
+                self.command,
+                KnownFilesCommands::Sets | KnownFilesCommands::Ls { .. }
+            ) {
+                anyhow::bail!("this known-files command writes to the database, remove --read-only")
+            }
+            let dbc = DbConn::open(&self.state_db_fs_path, 0).with_context(|| {
+                format!(
+                    "[KnownFilesArgs::execute] SQLite database {}",
+                    self.state_db_fs_path
+                )
+            })?;
+            return self.query(&dbc.conn);
+        }
+
+        let mut dbc = DbConn::new(&self.state_db_fs_path, 0).with_context(|| {
+            format!(
+                "[KnownFilesArgs::execute] SQLite database {}",
+                self.state_db_fs_path
+            )
+        })?;
+        // makes sure RSSDs created before `known_file_hash` existed are migrated
+        let tx = dbc.init(None)?;
+
+        match &self.command {
+            KnownFilesCommands::Load {
+                file,
+                set,
+                disposition,
+                confidence,
+                replace,
+            } => {
+                let stats = load_hash_set(
+                    &tx,
+                    file,
+                    &HashSetLoad {
+                        hash_set: set.clone(),
+                        disposition: *disposition,
+                        confidence: *confidence,
+                        replace: *replace,
+                    },
+                )?;
+                tx.commit()?;
+                println!(
+                    "{} hashes loaded into {} ({} lines skipped)",
+                    stats.loaded, set, stats.skipped
+                );
+                Ok(())
+            }
+            KnownFilesCommands::Match { session } => {
+                let stats = match_known_files(&tx, session.as_deref())?;
+                tx.commit()?;
+                println!(
+                    "{} resources: {} known-good, {} known-bad, {} unknown",
+                    stats.resources, stats.known_good, stats.known_bad, stats.unknown
+                );
+                Ok(())
+            }
+            KnownFilesCommands::Remove { set } => {
+                let removed = remove_hash_set(&tx, set)?;
+                tx.commit()?;
+                if removed == 0 {
+                    anyhow::bail!("there is no hash set {}", set);
+                }
+                println!("{} hashes of {} removed", removed, set);
+                Ok(())
+            }
+            _ => self.query(&tx),
+        }
+    }
+
+    /// The commands which only read the RSSD.
+    fn query(&self, conn: &Connection) -> anyhow::Result<()> {
+        match &self.command {
+            KnownFilesCommands::Sets => {
+                let mut table = Table::new();
+                table.load_preset(UTF8_FULL).set_header(vec![
+                    "Set",
+                    "Algorithm",
+                    "Disposition",
+                    "Hashes",
+                    "Matched resources",
+                ]);
+                for set in hash_sets(conn)? {
+                    table.add_row(vec![
+                        set.hash_set,
+                        set.algorithm,
+                        set.disposition,
+                        set.hashes.to_string(),
+                        set.matched_resources.to_string(),
+                    ]);
+                }
+                println!("{table}");
+            }
+            KnownFilesCommands::Ls {
+                disposition,
+                session,
+                nature,
+                json,
+            } => {
+                let matches = known_file_matches(conn, *disposition, session.as_deref(), nature)?;
+                if *json {
+                    println!("{}", serde_json::to_string_pretty(&matches)?);
+                    return Ok(());
+                }
+                let mut table = Table::new();
+                table.load_preset(UTF8_FULL).set_header(vec![
+                    "URI",
+                    "Nature",
+                    "Disposition",
+                    "Set",
+                    "Confidence",
+                ]);
+                for m in matches {
+                    table.add_row(vec![
+                        m.uri,
+                        m.nature.unwrap_or_default(),
+                        m.disposition.unwrap_or_else(|| "unknown".to_string()),
+                        m.hash_set.unwrap_or_default(),
+                        m.confidence.map(|c| c.to_string()).unwrap_or_default(),
+                    ]);
+                }
+                println!("{table}");
+            }
+            _ => unreachable!("only the known-files commands which read are queries"),
+        }
+        Ok(())
+    }
+}
@@ -1,14 +1,41 @@
-use clap::{Args, Subcommand};
+use clap::{Args, Subcommand, ValueEnum};
+use common::secret::Secret;
+use resource::digest::DigestAlgorithm;
+use resource::walk::WalkOrder;
 use serde::Serialize;
 
 use self::imap::IngestImapArgs;
+use crate::ingest::{
+    parse_max_duration, Browser, BrowserArtifact, NatureTrigger, SeedProfile, StateDbRoute,
+    UrlRedaction,
+};
+use crate::persist::PrimaryKeyStrategy;
+use crate::schema_doc::{SchemaDocDiagram, SchemaExportFormat};
 
 const DEFAULT_STATEDB_FS_PATH: &str = "resource-surveillance.sqlite.db";
 const DEFAULT_MERGED_STATEDB_FS_PATH: &str = "resource-surveillance-aggregated.sqlite.db";
 
+pub mod compliance;
 pub mod imap;
+pub mod known_files;
+pub mod orchestrate;
+pub mod policy;
+pub mod resources;
+pub mod serve;
+pub mod sessions;
+pub mod snapshot;
 pub mod transform;
 
+/// The error for a command whose subsystem was left out of this binary with
+/// `--no-default-features`.
+pub fn feature_not_compiled(feature: &str) -> anyhow::Error {
+    anyhow::anyhow!(
+        "this surveilr binary was built without the `{}` feature, rebuild with `cargo build --features {}` to use it",
+        feature,
+        feature
+    )
+}
+
 /// Admin / maintenance utilities
 #[derive(Debug, Serialize, Args, Clone)]
 pub struct AdminArgs {
@@ -16,8 +43,8 @@ pub struct AdminArgs {
     pub command: AdminCommands,
 }
 
-    #[derive(Debug, Serialize, Subcommand, Clone)]
-    pub enum AdminCommands {
+#[derive(Debug, Serialize, Subcommand, Clone)]
+pub enum AdminCommands {
     /// initialize an empty database with bootstrap.sql
     Init {
         /// target SQLite database
@@ -35,6 +62,10 @@ pub struct AdminArgs {
         /// add the current device in the empty database's device table
         #[arg(long)]
         with_device: bool,
+
+        /// how primary keys are generated, fixed for the lifetime of the database
+        #[arg(long, value_enum, env = "SURVEILR_PK_STRATEGY")]
+        pk_strategy: Option<PrimaryKeyStrategy>,
     },
 
     /// merge multiple surveillance state databases into a single one
@@ -62,8 +93,182 @@ pub struct AdminArgs {
         /// only generate SQL and emit to STDOUT (no actual merge)
         #[arg(long)]
         sql_only: bool,
+
+        /// after merging, re-hash merged resources' content and compare it with the candidates
+        #[arg(long)]
+        verify: bool,
+
+        /// verify a random sample of this many resources instead of all of them
+        #[arg(long, requires = "verify")]
+        verify_sample: Option<usize>,
+
+        /// number of threads re-hashing content (defaults to the available CPUs)
+        #[arg(long, requires = "verify")]
+        verify_workers: Option<usize>,
+    },
+
+    /// generate Markdown documentation (with an ER diagram) from a live RSSD's schema
+    SchemaDoc {
+        /// target SQLite database
+        #[arg(short='d', long, default_value = DEFAULT_STATEDB_FS_PATH, default_missing_value = "always", env="SURVEILR_STATEDB_FS_PATH")]
+        state_db_fs_path: String,
+
+        /// the ER diagram syntax to embed in the Markdown
+        #[arg(long, value_enum, default_value_t = SchemaDocDiagram::Mermaid)]
+        diagram: SchemaDocDiagram,
+
+        /// write the documentation to this file instead of STDOUT
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+
+    /// generate a JSON Schema or OpenAPI description of a database's tables for integrators
+    SchemaExport {
+        /// target SQLite database
+        #[arg(short='d', long, default_value = DEFAULT_STATEDB_FS_PATH, default_missing_value = "always", env="SURVEILR_STATEDB_FS_PATH")]
+        state_db_fs_path: String,
+
+        /// the kind of document to generate
+        #[arg(long, value_enum, default_value_t = SchemaExportFormat::JsonSchema)]
+        format: SchemaExportFormat,
+
+        /// write the schema to this file instead of STDOUT
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+
+    /// populate a database with synthetic devices, sessions, resources, emails and CE results
+    Seed {
+        /// target SQLite database
+        #[arg(short='d', long, default_value = DEFAULT_STATEDB_FS_PATH, default_missing_value = "always", env="SURVEILR_STATEDB_FS_PATH")]
+        state_db_fs_path: String,
+
+        /// how much synthetic data to generate
+        #[arg(long, value_enum, default_value_t = SeedProfile::Test)]
+        profile: SeedProfile,
+
+        /// remove the existing database first
+        #[arg(short, long)]
+        remove_existing_first: bool,
+    },
+
+    /// back up a database, even while it's being ingested into, to a zstd-compressed file
+    Backup {
+        /// target SQLite database
+        #[arg(short='d', long, default_value = DEFAULT_STATEDB_FS_PATH, default_missing_value = "always", env="SURVEILR_STATEDB_FS_PATH")]
+        state_db_fs_path: String,
+
+        /// the backup file, defaults to the database path with a `.zst` extension
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// zstd compression level (1-22)
+        #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(i32).range(1..=22))]
+        level: i32,
+    },
+
+    /// verify and decompress a backup created with `admin backup` into a database
+    Restore {
+        /// the backup file
+        #[arg(short, long)]
+        input: String,
+
+        /// target SQLite database
+        #[arg(short='d', long, default_value = DEFAULT_STATEDB_FS_PATH, default_missing_value = "always", env="SURVEILR_STATEDB_FS_PATH")]
+        state_db_fs_path: String,
+
+        /// replace the existing database
+        #[arg(short, long)]
+        remove_existing_first: bool,
     },
 
+    /// re-run the classifier rules against stored resources' URIs and update natures which changed
+    Reclassify {
+        /// target SQLite database
+        #[arg(short='d', long, default_value = DEFAULT_STATEDB_FS_PATH, default_missing_value = "always", env="SURVEILR_STATEDB_FS_PATH")]
+        state_db_fs_path: String,
+
+        /// only report the natures which would change (the default)
+        #[arg(long, conflicts_with = "apply")]
+        dry_run: bool,
+
+        /// update the natures of the resources whose classification changed
+        #[arg(long)]
+        apply: bool,
+
+        /// re-run registered transforms (e.g. xml -> json) over the reclassified resources
+        #[arg(long, requires = "apply")]
+        retransform: bool,
+    },
+
+    /// delete old ingest sessions and drop the content of old resources so an RSSD doesn't grow
+    /// unbounded, reporting the reclaimable bytes
+    Prune {
+        /// target SQLite database
+        #[arg(short='d', long, default_value = DEFAULT_STATEDB_FS_PATH, default_missing_value = "always", env="SURVEILR_STATEDB_FS_PATH")]
+        state_db_fs_path: String,
+
+        /// keep the N most recent ingest sessions of each device, older sessions are deleted with
+        /// the resources no kept session encountered
+        #[arg(long, value_name = "N")]
+        keep_sessions: Option<usize>,
+
+        /// drop the content of resources stored more than DAYS days ago which no session
+        /// encountered since, keeping their metadata
+        #[arg(long, value_name = "DAYS")]
+        content_older_than: Option<u32>,
+
+        /// VACUUM the RSSD after pruning to give the freed space back to the file system
+        #[arg(long)]
+        vacuum: bool,
+
+        /// only report what would be pruned and the reclaimable bytes (the default)
+        #[arg(long, conflicts_with = "apply")]
+        dry_run: bool,
+
+        /// prune the RSSD
+        #[arg(long)]
+        apply: bool,
+    },
+
+    /// re-hash the files an `ingest files` session walked and report those modified, tampered
+    /// with (content changed, size and modification time didn't), deleted or added since, using
+    /// the RSSD as a file integrity monitoring baseline; fails when any file changed
+    Verify {
+        /// target SQLite database
+        #[arg(short='d', long, default_value = DEFAULT_STATEDB_FS_PATH, default_missing_value = "always", env="SURVEILR_STATEDB_FS_PATH")]
+        state_db_fs_path: String,
+
+        /// the ingest session to verify, the latest finished `ingest files` session of this
+        /// device by default
+        #[arg(long, value_name = "INGEST_SESSION_ID")]
+        session: Option<String>,
+
+        /// emit the report as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// store the report as `uniform_resource_verification` rows
+        #[arg(long)]
+        record: bool,
+    },
+
+    /// run a miniature end-to-end test against a temporary RSSD (files and capturable executables,
+    /// IMAP with an embedded mock server, a UDI-PGP query) and report pass/fail per subsystem, e.g.
+    /// after installing or upgrading on a new platform
+    SelfTest {
+        /// keep the temporary RSSD and fixtures for inspection
+        #[arg(long)]
+        keep: bool,
+
+        /// emit the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// define indexed columns computed from uniform_resource rows (e.g. a JSON field of a nature) for hot query paths
+    ComputedColumn(ComputedColumnArgs),
+
     /// generate CLI help markdown
     CliHelpMd,
 
@@ -74,6 +279,47 @@ pub struct AdminArgs {
     Credentials(CredentialArgs),
 }
 
+/// Computed columns are exposed as `ur_computed_<name>` views over `uniform_resource`
+#[derive(Debug, Serialize, Args, Clone)]
+pub struct ComputedColumnArgs {
+    /// target SQLite database
+    #[arg(short='d', long, default_value = DEFAULT_STATEDB_FS_PATH, default_missing_value = "always", env="SURVEILR_STATEDB_FS_PATH")]
+    pub state_db_fs_path: String,
+
+    #[command(subcommand)]
+    pub command: ComputedColumnCommands,
+}
+
+#[derive(Debug, Serialize, Subcommand, Clone)]
+pub enum ComputedColumnCommands {
+    /// define a computed column and index its expression
+    Add {
+        /// column name, the view is named `ur_computed_<name>`
+        name: String,
+
+        /// SQL expression over a uniform_resource row, e.g. "json_extract(CAST(content AS TEXT), '$.version')"
+        #[arg(short, long)]
+        expr: String,
+
+        /// only compute the column for resources of this nature
+        #[arg(short, long)]
+        nature: Option<String>,
+
+        /// create the view without indexing the expression
+        #[arg(long)]
+        no_index: bool,
+    },
+
+    /// list the computed columns
+    Ls,
+
+    /// drop a computed column and its index
+    Drop {
+        /// column name
+        name: String,
+    },
+}
+
 /// Credentials for several services used in surveilr
 #[derive(Debug, Serialize, Args, Clone)]
 pub struct CredentialArgs {
@@ -103,6 +349,52 @@ pub enum CredentialsCommands {
         #[arg(long)]
         export: bool,
     },
+    /// sign in to an OAuth 2.0 provider ahead of time and cache the token API-based ingest sources use
+    Oauth {
+        /// the authorization server
+        #[arg(short, long, value_enum)]
+        provider: OAuthProvider,
+        /// Client ID of the application registered with the provider
+        #[arg(short = 'i', long)]
+        client_id: String,
+        /// Client Secret of the application, for confidential clients
+        #[arg(short = 's', long)]
+        client_secret: Option<Secret>,
+        /// scopes to request, repeat for several (Microsoft and Google default to the scopes `ingest imap microsoft-365` and `ingest imap gmail` need)
+        #[arg(long)]
+        scope: Vec<String>,
+        /// how to sign in
+        #[arg(short, long, value_enum, default_value = "device-code")]
+        mode: OAuthFlowMode,
+        /// port the `auth-code` redirect is served on
+        #[arg(long, default_value = "8000")]
+        port: u16,
+        /// path of the `auth-code` redirect URI, `http://127.0.0.1:<port><path>`
+        #[arg(long, default_value = "/redirect")]
+        redirect_path: String,
+        /// Microsoft tenant (`common`, `organizations` or a tenant ID)
+        #[arg(long, default_value = "common")]
+        tenant: String,
+        /// Okta organization domain, e.g. example.okta.com
+        #[arg(long, required_if_eq("provider", "okta"))]
+        okta_domain: Option<String>,
+    },
+}
+
+#[derive(Debug, Serialize, Clone, Copy, ValueEnum)]
+pub enum OAuthProvider {
+    Microsoft,
+    Google,
+    Github,
+    Okta,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, ValueEnum)]
+pub enum OAuthFlowMode {
+    /// enter a code shown in the terminal on any device
+    DeviceCode,
+    /// sign in with the browser of this machine
+    AuthCode,
 }
 
 /// Capturable Executables (CE) assurance tools
@@ -167,6 +459,11 @@ pub enum CapturableExecTestCommands {
     File {
         #[arg(short, long)]
         fs_path: String,
+
+        /// JSON document to send to the executable's STDIN instead of the synthetic one, e.g. a
+        /// `surveilr-ingest` context with realistic session/device values; use `-` to read it from STDIN
+        #[arg(long)]
+        stdin_json: Option<String>,
     },
 
     /// Execute a task string as if it was run by `ingest tasks` and show the output
@@ -190,6 +487,43 @@ pub enum CapturableExecTestCommands {
 pub struct IngestArgs {
     #[command(subcommand)]
     pub command: IngestCommands,
+
+    /// keep running, starting a new ingestion session every N seconds
+    #[arg(long, global = true)]
+    pub every: Option<u64>,
+
+    /// serve a health endpoint on this address while running with --every or
+    /// `ingest files --watch` (e.g. 127.0.0.1:5252)
+    #[arg(long, global = true)]
+    pub health_addr: Option<std::net::SocketAddr>,
+
+    /// print a JSON summary of each session (ID, counts, errors, duration, database) on STDOUT
+    #[arg(long, global = true)]
+    pub emit_session_json: bool,
+
+    /// with `-d :memory:`, write the in-memory database to this file when done
+    #[arg(long, global = true)]
+    pub export_on_exit: Option<String>,
+
+    /// only export on exit when this SQL query returns at least one row (the findings)
+    #[arg(long, global = true, requires = "export_on_exit")]
+    pub export_if_sql: Option<String>,
+
+    /// tag the session's resources which match the loaded known-file hash sets
+    /// (see `known-files load`)
+    #[arg(long, global = true)]
+    pub match_known_files: bool,
+
+    /// run at the lowest CPU and IO priorities (nice/ionice on Unix, idle
+    /// priority class on Windows) so collection doesn't slow down the host;
+    /// capturable executables inherit them
+    #[arg(long, global = true)]
+    pub background: bool,
+
+    /// with `-d libsql://...`, the local SQLite replica sessions are written to
+    /// before they're pushed to the remote database
+    #[arg(long, global = true)]
+    pub remote_replica: Option<String>,
 }
 
 /// Ingest content from device file system and other sources
@@ -230,6 +564,175 @@ pub struct IngestFilesArgs {
     /// save the options as a new behavior
     #[arg(long)]
     pub save_behavior: Option<String>,
+
+    /// jq-style filter applied to JSON output of capturable executables before it's stored
+    #[arg(long)]
+    pub ce_json_filter: Option<String>,
+
+    /// execute SQL-producing capturable executables but only validate their SQL in a
+    /// rolled-back transaction, reporting errors without mutating the database
+    /// (transaction control statements such as COMMIT are reported as errors)
+    #[arg(long)]
+    pub ce_sql_validate_only: bool,
+
+    /// keep the temporary work directories given to capturable executables instead
+    /// of removing them after the session
+    #[arg(long)]
+    pub keep_ce_workdirs: bool,
+
+    /// decode base64 and MIME multipart content, storing each decoded payload as a
+    /// uniform_resource_transform of its inner content type
+    #[arg(long)]
+    pub decode_payloads: bool,
+
+    /// store JSON content canonicalized (sorted keys, no insignificant whitespace)
+    /// so semantically identical documents get the same content digest
+    #[arg(long)]
+    pub canonical_json: bool,
+
+    /// store the content of files larger than 1 GiB in chunks of this many MB
+    /// (`uniform_resource_chunk`) instead of a single blob SQLite can't hold
+    #[arg(long, num_args = 0..=1, default_missing_value = "64")]
+    pub content_chunk_size: Option<usize>,
+
+    /// store the content of binary resources of at least `--blob-threshold` KB in
+    /// this content-addressed directory instead of the RSSD, which keeps a pointer
+    /// to it; the directory is recorded in the RSSD and can't change afterwards
+    #[arg(long, env = "SURVEILR_BLOB_STORE", value_name = "DIR")]
+    pub blob_store: Option<String>,
+
+    /// the size, in KB, from which binary content is stored in `--blob-store`
+    #[arg(long, value_name = "KB", default_value_t = crate::blobs::DEFAULT_BLOB_THRESHOLD_KB)]
+    pub blob_threshold: usize,
+
+    /// read and hash content and run capturable executables on this many worker
+    /// threads while a single writer stores the resources, in walk order
+    #[arg(short = 'j', long, value_name = "N", default_value_t = 1)]
+    pub jobs: usize,
+
+    /// commit the session every N resources so a long walk's progress is durable
+    /// and its transaction stays small, by default every 10000 with `--jobs` and
+    /// only at the end of the session otherwise
+    #[arg(long, value_name = "N", conflicts_with = "ce_sql_validate_only", value_parser = clap::value_parser!(u64).range(1..))]
+    pub commit_every: Option<u64>,
+
+    /// hash content with `sha256` or `blake3` instead of `sha1`, recorded as the
+    /// session's and its resources' `content_digest_algorithm`
+    #[arg(long, default_value_t = DigestAlgorithm::Sha1)]
+    pub digest: DigestAlgorithm,
+
+    /// expand zip (including zip64), tar and tar.gz archives and store each
+    /// member as its own resource, linked to the archive
+    #[arg(long)]
+    pub expand_archives: bool,
+
+    /// password tried on encrypted archive members, repeat for evidence bundles
+    /// with different passwords; members none of them decrypts are skipped
+    #[arg(long, env = "SURVEILR_ARCHIVE_PASSWORD")]
+    pub archive_password: Vec<Secret>,
+
+    /// skip archive members larger than this many bytes (uncompressed)
+    #[arg(long, default_value_t = 256 * 1024 * 1024)]
+    pub max_archive_member_size: u64,
+
+    /// run a follow-up action on every ingested resource of a nature within the same
+    /// session, `<nature>=transform:<format>` or `<nature>=exec:<command>` (e.g.
+    /// `png=exec:tesseract $SURVEILR_TRIGGER_URI stdout`)
+    #[arg(long)]
+    pub trigger: Vec<NatureTrigger>,
+    /// write matching resources to another SQLite database instead of -d,
+    /// `binary=<db>`, `text=<db>`, `nature:<nature>=<db>` or `path:<root>=<db>`
+    /// (first match wins, each database gets its own session)
+    #[arg(long)]
+    pub route: Vec<StateDbRoute>,
+
+    /// don't apply the `.surveilr_collect.yml` manifests found in the root paths
+    #[arg(long)]
+    pub ignore_collect_manifests: bool,
+
+    /// regex matching the whole path of the `.surveilr_collect.yml` manifests
+    /// whose `capture` rules are applied, the files they match are run as
+    /// capturable executables so the capture rules of other manifests are ignored
+    #[arg(long)]
+    pub trust_collect_manifest: Vec<String>,
+
+    /// `breadth-first` ingests every directory's entries before descending into
+    /// subdirectories so early results (and limits) cover the whole tree
+    #[arg(long, default_value_t = WalkOrder::DepthFirst)]
+    pub walk_order: WalkOrder,
+
+    /// only walk the first N entries (by name) of every directory, skipping the
+    /// rest of sparse or huge directories
+    #[arg(long)]
+    pub max_dir_entries: Option<usize>,
+
+    /// store files surveilr isn't allowed to read as `SKIPPED` rather than `ERROR`,
+    /// they're still listed in the session's access issues
+    #[arg(long, conflicts_with = "fail_on_unreadable")]
+    pub skip_unreadable: bool,
+
+    /// fail the session as soon as a path can't be read
+    #[arg(long)]
+    pub fail_on_unreadable: bool,
+
+    /// read files matching this glob through `sudo -n -u <SUDO_USER> cat` when
+    /// surveilr isn't allowed to read them itself
+    #[arg(long)]
+    pub sudo_read: Vec<String>,
+
+    /// the user `--sudo-read` files are read as
+    #[arg(long, default_value = "root")]
+    pub sudo_user: String,
+
+    /// don't read or hash the files the previous session has with the same size
+    /// and modification time, record them as `UNCHANGED` entries instead
+    #[arg(long)]
+    pub incremental: bool,
+
+    /// keep watching the root paths after ingesting them, storing the files
+    /// created or modified and recording the deleted ones in the same session
+    /// until interrupted
+    #[arg(
+        long,
+        conflicts_with_all = ["dry_run", "route", "incremental", "ce_sql_validate_only", "expand_archives", "commit_every"]
+    )]
+    pub watch: bool,
+
+    /// how often `--watch` stores the files the file system reported as
+    /// changed, in seconds
+    #[arg(long, default_value = "2", requires = "watch")]
+    pub watch_interval: u64,
+
+    /// have `--watch` walk the root paths every `--watch-interval` instead of
+    /// relying on file system events, e.g. for network file systems which
+    /// don't report them
+    #[arg(long, requires = "watch")]
+    pub watch_poll: bool,
+
+    /// how often `--watch` records what it ingested so far in the session's
+    /// elaboration, in seconds
+    #[arg(long, default_value = "60", requires = "watch")]
+    pub checkpoint_every: u64,
+
+    #[command(flatten)]
+    pub limits: IngestLimitsArgs,
+}
+
+/// Guardrails which stop a runaway ingest session; the partial session is kept
+/// and marked as aborted in its `elaboration`
+#[derive(Debug, Default, Serialize, Args, Clone)]
+pub struct IngestLimitsArgs {
+    /// stop the session once this many resources failed
+    #[arg(long)]
+    pub abort_after_errors: Option<usize>,
+
+    /// stop the session once this many resources were ingested
+    #[arg(long)]
+    pub max_resources: Option<usize>,
+
+    /// stop the session after this long (e.g. `90s`, `30m`, `2h`)
+    #[arg(long, value_parser = parse_max_duration)]
+    pub max_duration: Option<std::time::Duration>,
 }
 
 /// Notebooks maintenance utilities
@@ -247,6 +750,10 @@ pub struct IngestTasksArgs {
     #[arg(long)]
     pub stdin: bool,
 
+    /// read tasks from this file instead of STDIN, e.g. for `--every` and `orchestrate`
+    #[arg(long)]
+    pub tasks_from: Option<String>,
+
     /// show session stats after completion
     #[arg(long)]
     pub stats: bool,
@@ -254,6 +761,307 @@ pub struct IngestTasksArgs {
     /// show session stats as JSON after completion
     #[arg(long)]
     pub stats_json: bool,
+
+    /// jq-style filter applied to JSON output of capturable executables before it's stored
+    #[arg(long)]
+    pub ce_json_filter: Option<String>,
+
+    /// execute SQL-producing capturable executables but only validate their SQL in a
+    /// rolled-back transaction, reporting errors without mutating the database
+    /// (transaction control statements such as COMMIT are reported as errors)
+    #[arg(long)]
+    pub ce_sql_validate_only: bool,
+
+    /// keep the temporary work directories given to capturable executables instead
+    /// of removing them after the session
+    #[arg(long)]
+    pub keep_ce_workdirs: bool,
+
+    /// decode base64 and MIME multipart content, storing each decoded payload as a
+    /// uniform_resource_transform of its inner content type
+    #[arg(long)]
+    pub decode_payloads: bool,
+
+    /// store JSON content canonicalized (sorted keys, no insignificant whitespace)
+    /// so semantically identical documents get the same content digest
+    #[arg(long)]
+    pub canonical_json: bool,
+
+    #[command(flatten)]
+    pub limits: IngestLimitsArgs,
+}
+
+/// Catalog external `http(s)://` and `s3://` resources as uniform resources
+#[derive(Debug, Serialize, Args, Clone)]
+pub struct IngestUrisArgs {
+    /// target SQLite database
+    #[arg(short='d', long, default_value = DEFAULT_STATEDB_FS_PATH, default_missing_value = "always", env="SURVEILR_STATEDB_FS_PATH")]
+    pub state_db_fs_path: String,
+
+    /// one or more globs to match as SQL files and batch execute them in alpha order
+    #[arg(short = 'I', long)]
+    pub state_db_init_sql: Vec<String>,
+
+    /// one or more `http(s)://` or `s3://` URIs to ingest
+    #[arg(required_unless_present = "uris_from")]
+    pub uris: Vec<String>,
+
+    /// read more URIs, one per line, from this file (`-` for STDIN)
+    #[arg(long)]
+    pub uris_from: Option<String>,
+
+    /// only record each resource's size, modification time, content type and ETag,
+    /// its content is fetched on demand with `resources fetch`
+    #[arg(long)]
+    pub meta_only: bool,
+}
+
+/// Ingest the responses of HTTP/REST APIs, following their pagination
+#[derive(Debug, Serialize, Args, Clone)]
+pub struct IngestApiArgs {
+    /// target SQLite database
+    #[arg(short='d', long, default_value = DEFAULT_STATEDB_FS_PATH, default_missing_value = "always", env="SURVEILR_STATEDB_FS_PATH")]
+    pub state_db_fs_path: String,
+
+    /// one or more globs to match as SQL files and batch execute them in alpha order
+    #[arg(short = 'I', long)]
+    pub state_db_init_sql: Vec<String>,
+
+    /// one or more URLs to `GET`
+    #[arg(required_unless_present = "stdin")]
+    pub urls: Vec<String>,
+
+    /// a request header of the URLs as `Name: value`, the value may be a secret
+    /// reference (`env://NAME`, `keyring://service/account`, `vault://path#key`)
+    #[arg(short = 'H', long)]
+    pub header: Vec<Secret>,
+
+    /// JSON pointer to the next page's URL in the responses, e.g. `/links/next`
+    #[arg(long)]
+    pub next_path: Option<String>,
+
+    /// JSON pointer to the next page's cursor in the responses, e.g. `/meta/next_cursor`
+    #[arg(long)]
+    pub cursor_path: Option<String>,
+
+    /// the query parameter the cursor is passed as
+    #[arg(long, default_value = "cursor")]
+    pub cursor_param: String,
+
+    /// the most pages read from each endpoint
+    #[arg(long, default_value_t = 100)]
+    pub max_pages: usize,
+
+    /// fail the endpoint when a page is larger than this many bytes
+    #[arg(long, default_value_t = 64 * 1024 * 1024)]
+    pub max_page_size: u64,
+
+    /// read more endpoints from STDIN, one JSON object per line with the `url`
+    /// and optionally `headers`, `next_path`, `cursor_path`, `cursor_param` and `max_pages`
+    #[arg(long)]
+    pub stdin: bool,
+}
+
+/// Ingest the files of container images without running them
+#[derive(Debug, Serialize, Args, Clone)]
+pub struct IngestOciArgs {
+    /// target SQLite database
+    #[arg(short='d', long, default_value = DEFAULT_STATEDB_FS_PATH, default_missing_value = "always", env="SURVEILR_STATEDB_FS_PATH")]
+    pub state_db_fs_path: String,
+
+    /// one or more globs to match as SQL files and batch execute them in alpha order
+    #[arg(short = 'I', long)]
+    pub state_db_init_sql: Vec<String>,
+
+    /// an image reference (e.g. `alpine:3.19`, `ghcr.io/org/app@sha256:...`) or
+    /// a `docker save` or OCI layout tarball
+    pub image: String,
+
+    /// the platform to ingest from multi-platform images
+    #[arg(long, default_value = "linux/amd64")]
+    pub platform: String,
+
+    /// pull from the registry over http instead of https
+    #[arg(long)]
+    pub plain_http: bool,
+
+    /// registry username
+    #[arg(long, env = "SURVEILR_OCI_USERNAME")]
+    pub username: Option<String>,
+
+    /// registry password or token
+    #[arg(long, env = "SURVEILR_OCI_PASSWORD")]
+    pub password: Option<Secret>,
+
+    /// files larger than this many bytes are recorded without their content
+    #[arg(long, default_value_t = 64 * 1024 * 1024)]
+    pub max_file_size: u64,
+}
+
+/// Collect browser history and extensions from the device's browser profiles
+#[derive(Debug, Serialize, Args, Clone)]
+pub struct IngestBrowsersArgs {
+    /// target SQLite database
+    #[arg(short='d', long, default_value = DEFAULT_STATEDB_FS_PATH, default_missing_value = "always", env="SURVEILR_STATEDB_FS_PATH")]
+    pub state_db_fs_path: String,
+
+    /// one or more globs to match as SQL files and batch execute them in alpha order
+    #[arg(short = 'I', long)]
+    pub state_db_init_sql: Vec<String>,
+
+    /// home directories to look for browser profiles in (default: the current
+    /// user's), e.g. `--home /mnt/evidence/home/alice`
+    #[arg(long)]
+    pub home: Vec<String>,
+
+    /// only these browsers (default: all)
+    #[arg(long, value_enum)]
+    pub browser: Vec<Browser>,
+
+    /// only these artifacts (default: all)
+    #[arg(long, value_enum)]
+    pub artifact: Vec<BrowserArtifact>,
+
+    /// how much of the visited URLs to keep
+    #[arg(long, value_enum, default_value_t = UrlRedaction::QueryStrings)]
+    pub redact: UrlRedaction,
+
+    /// only visits since this `YYYY-MM-DD` date or RFC 3339 timestamp
+    #[arg(long)]
+    pub since: Option<String>,
+}
+
+/// Ingest the objects of S3 or S3-compatible (e.g. MinIO) buckets
+#[derive(Debug, Serialize, Args, Clone)]
+pub struct IngestS3Args {
+    /// target SQLite database
+    #[arg(short='d', long, default_value = DEFAULT_STATEDB_FS_PATH, default_missing_value = "always", env="SURVEILR_STATEDB_FS_PATH")]
+    pub state_db_fs_path: String,
+
+    /// one or more globs to match as SQL files and batch execute them in alpha order
+    #[arg(short = 'I', long)]
+    pub state_db_init_sql: Vec<String>,
+
+    /// the bucket to walk
+    #[arg(short, long)]
+    pub bucket: String,
+
+    /// one or more key prefixes to walk, e.g. `--prefix evidence/2024/` (default:
+    /// the whole bucket)
+    #[arg(short, long)]
+    pub prefix: Vec<String>,
+
+    /// the bucket's region (default: `AWS_REGION`, `AWS_DEFAULT_REGION` or us-east-1)
+    #[arg(long)]
+    pub region: Option<String>,
+
+    /// an S3-compatible service's URL, e.g. `http://localhost:9000` (default:
+    /// `AWS_ENDPOINT_URL_S3` or `AWS_ENDPOINT_URL`, else AWS)
+    #[arg(long)]
+    pub endpoint: Option<String>,
+
+    /// the access key ID (default: `AWS_ACCESS_KEY_ID`), public buckets need none
+    #[arg(long)]
+    pub access_key_id: Option<String>,
+
+    /// the secret access key (default: `AWS_SECRET_ACCESS_KEY`)
+    #[arg(long)]
+    pub secret_access_key: Option<Secret>,
+
+    /// the session token of temporary credentials (default: `AWS_SESSION_TOKEN`)
+    #[arg(long)]
+    pub session_token: Option<Secret>,
+
+    /// only record each object's key, size, modification time and ETag, its
+    /// content is fetched on demand with `resources fetch`
+    #[arg(long)]
+    pub meta_only: bool,
+
+    /// objects larger than this many bytes are recorded without their content
+    #[arg(long, default_value_t = 64 * 1024 * 1024)]
+    pub max_object_size: u64,
+}
+
+/// Ingest the files of a commit of a local or remote git repository
+#[derive(Debug, Serialize, Args, Clone)]
+pub struct IngestGitArgs {
+    /// target SQLite database
+    #[arg(short='d', long, default_value = DEFAULT_STATEDB_FS_PATH, default_missing_value = "always", env="SURVEILR_STATEDB_FS_PATH")]
+    pub state_db_fs_path: String,
+
+    /// one or more globs to match as SQL files and batch execute them in alpha order
+    #[arg(short = 'I', long)]
+    pub state_db_init_sql: Vec<String>,
+
+    /// a local repository (or work tree) or a URL `git clone` accepts, e.g.
+    /// `https://github.com/org/repo.git`
+    pub repo: String,
+
+    /// the branch, tag or commit whose files are ingested (default: `HEAD`)
+    #[arg(long = "ref")]
+    pub reference: Option<String>,
+
+    /// files larger than this many bytes are recorded without their content
+    #[arg(long, default_value_t = 64 * 1024 * 1024)]
+    pub max_file_size: u64,
+}
+
+/// Record the paths and digests of a checksum manifest as resources whose
+/// content isn't acquired but attested by the manifest
+#[derive(Debug, Serialize, Args, Clone)]
+pub struct IngestManifestArgs {
+    /// target SQLite database
+    #[arg(short='d', long, default_value = DEFAULT_STATEDB_FS_PATH, default_missing_value = "always", env="SURVEILR_STATEDB_FS_PATH")]
+    pub state_db_fs_path: String,
+
+    /// one or more globs to match as SQL files and batch execute them in alpha order
+    #[arg(short = 'I', long)]
+    pub state_db_init_sql: Vec<String>,
+
+    /// `sha256sum`, `sha1sum` or `md5sum` output, `-` for STDIN
+    pub manifest: String,
+
+    /// who provided the manifest (e.g. the lab or custodian), recorded with every resource
+    #[arg(long)]
+    pub attested_by: Option<String>,
+}
+
+/// Capture macOS property lists (XML or binary) as JSON resources
+#[derive(Debug, Serialize, Args, Clone)]
+pub struct IngestPlistArgs {
+    /// target SQLite database
+    #[arg(short='d', long, default_value = DEFAULT_STATEDB_FS_PATH, default_missing_value = "always", env="SURVEILR_STATEDB_FS_PATH")]
+    pub state_db_fs_path: String,
+
+    /// one or more globs to match as SQL files and batch execute them in alpha order
+    #[arg(short = 'I', long)]
+    pub state_db_init_sql: Vec<String>,
+
+    /// plist files or globs, e.g. `/Library/Preferences/*.plist`
+    #[arg(required = true)]
+    pub paths: Vec<String>,
+}
+
+/// Capture Windows registry keys and their values as JSON resources
+#[derive(Debug, Serialize, Args, Clone)]
+pub struct IngestRegistryArgs {
+    /// target SQLite database
+    #[arg(short='d', long, default_value = DEFAULT_STATEDB_FS_PATH, default_missing_value = "always", env="SURVEILR_STATEDB_FS_PATH")]
+    pub state_db_fs_path: String,
+
+    /// one or more globs to match as SQL files and batch execute them in alpha order
+    #[arg(short = 'I', long)]
+    pub state_db_init_sql: Vec<String>,
+
+    /// keys to capture as globs where `*` matches one key and `**` any
+    /// number of them, e.g. `HKLM\SOFTWARE\Policies\**`
+    #[arg(short, long, required = true)]
+    pub key: Vec<String>,
+
+    /// read the keys from these `.reg` exports (`reg export`, `regedit`)
+    /// instead of the live registry, which only Windows has
+    #[arg(long)]
+    pub reg_file: Vec<String>,
 }
 
 /// Ingest uniform resources content from multiple sources
@@ -263,6 +1071,35 @@ pub enum IngestCommands {
     Files(IngestFilesArgs),
     Tasks(IngestTasksArgs),
     Imap(IngestImapArgs),
+    Uris(IngestUrisArgs),
+    Api(IngestApiArgs),
+    Oci(IngestOciArgs),
+    Browsers(IngestBrowsersArgs),
+    S3(IngestS3Args),
+    Git(IngestGitArgs),
+    Manifest(IngestManifestArgs),
+    Plist(IngestPlistArgs),
+    Registry(IngestRegistryArgs),
+}
+
+impl IngestCommands {
+    /// The `-d` target SQLite database of the ingestion.
+    pub fn state_db_fs_path_mut(&mut self) -> &mut String {
+        match self {
+            IngestCommands::Files(args) => &mut args.state_db_fs_path,
+            IngestCommands::Tasks(args) => &mut args.state_db_fs_path,
+            IngestCommands::Imap(args) => &mut args.state_db_fs_path,
+            IngestCommands::Uris(args) => &mut args.state_db_fs_path,
+            IngestCommands::Api(args) => &mut args.state_db_fs_path,
+            IngestCommands::Oci(args) => &mut args.state_db_fs_path,
+            IngestCommands::Browsers(args) => &mut args.state_db_fs_path,
+            IngestCommands::S3(args) => &mut args.state_db_fs_path,
+            IngestCommands::Git(args) => &mut args.state_db_fs_path,
+            IngestCommands::Manifest(args) => &mut args.state_db_fs_path,
+            IngestCommands::Plist(args) => &mut args.state_db_fs_path,
+            IngestCommands::Registry(args) => &mut args.state_db_fs_path,
+        }
+    }
 }
 
 /// Notebooks maintenance utilities
@@ -276,6 +1113,10 @@ pub struct NotebooksArgs {
     #[arg(short = 'I', long)]
     state_db_init_sql: Vec<String>,
 
+    /// open the database read-only (`cat` and `ls`) so it's safe while it's being written
+    #[arg(long)]
+    pub read_only: bool,
+
     #[command(subcommand)]
     pub command: NotebooksCommands,
 }
@@ -303,6 +1144,27 @@ pub enum NotebooksCommands {
         #[arg(short, long)]
         migratable: bool,
     },
+
+    /// publish notebooks' SQL cells as SQLPage pages (upserted into sqlpage_files)
+    Publish {
+        /// search for these notebooks (include % for LIKE otherwise =)
+        #[arg(short, long)]
+        notebook: Vec<String>,
+
+        /// search for these cells (include % for LIKE otherwise =), without
+        /// it migration (bootstrap, construction and `once_`) cells are skipped
+        #[arg(short, long)]
+        cell: Vec<String>,
+
+        /// prefix for the SQLPage paths, each page is `<prefix><cell>.sql`
+        /// (`/`, `..` and other unsafe characters of names become `_`)
+        #[arg(short, long, default_value = "")]
+        path_prefix: String,
+
+        /// only show which pages would be published
+        #[arg(long)]
+        dry_run: bool,
+    },
 }
 
 /// Configuration to start the SQLPage webserver
@@ -332,4 +1194,9 @@ pub struct SQLPageArgs {
     /// Metrics port. Used for scraping metrics with tools like OpenObserve or Prometheus
     #[arg(short = 'm', long)]
     pub metrics: Option<u16>,
+
+    /// serve the database read-only, SQLPage's own migrations are skipped so the
+    /// RSSD's `sqlpage_files` and other tables must already exist
+    #[arg(long)]
+    pub read_only: bool,
 }
@@ -0,0 +1,15 @@
+use clap::Args;
+use serde::Serialize;
+
+/// Run ingestions on cron schedules until interrupted
+#[derive(Debug, Serialize, Args, Clone)]
+pub struct OrchestrateArgs {
+    /// TOML file with a `[[schedule]]` table (`name`, `cron` and `command`) per
+    /// ingestion, `command` being the `surveilr ingest ...` arguments
+    #[arg(short, long, env = "SURVEILR_SCHEDULES")]
+    pub schedules: String,
+
+    /// only validate the schedules and list their next run times
+    #[arg(long)]
+    pub dry_run: bool,
+}
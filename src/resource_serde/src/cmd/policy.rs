@@ -0,0 +1,481 @@
+use anyhow::Context;
+use clap::{Args, Subcommand};
+use comfy_table::{presets::UTF8_FULL, Table};
+use rusqlite::Connection;
+use serde::Serialize;
+
+use tracing::warn;
+
+use crate::audit::os_user;
+use crate::persist::DbConn;
+use crate::policy::{
+    acknowledge_findings, alert_rules, evaluate_policies, policy_assertions, record_findings,
+    remove_alert_rule, send_alerts, store_alert_rule, store_policy_assertion, stored_findings,
+    AlertChannel, AlertRule, AlertTrigger, FindingState, FindingStatus, PolicyAssertion,
+    PolicyExpectation, PolicyRule, PolicySeverity, RecordedFinding, SmtpRelay,
+};
+
+const DEFAULT_STATEDB_FS_PATH: &str = "resource-surveillance.sqlite.db";
+
+/// Evaluate packs of SQL assertions (stored as notebook cells) against the RSSD
+#[derive(Debug, Serialize, Args, Clone)]
+pub struct PolicyArgs {
+    /// target SQLite database
+    #[arg(short='d', long, default_value = DEFAULT_STATEDB_FS_PATH, default_missing_value = "always", env="SURVEILR_STATEDB_FS_PATH")]
+    pub state_db_fs_path: String,
+
+    /// open the database read-only (`run`, `ls`, `findings` and `alert ls`) so it's safe
+    /// while it's being written, `run` then neither records findings nor sends alerts
+    #[arg(long)]
+    read_only: bool,
+
+    #[command(subcommand)]
+    pub command: PolicyCommands,
+}
+
+#[derive(Debug, Serialize, Subcommand, Clone)]
+pub enum PolicyCommands {
+    /// evaluate the assertions, record and emit the findings and send the alerts,
+    /// fails if any assertion fails
+    Run {
+        /// only evaluate these packs (notebook names)
+        #[arg(short, long)]
+        pack: Vec<String>,
+
+        /// only fail for failed assertions of at least this severity
+        #[arg(long, value_enum, default_value_t = PolicySeverity::Info)]
+        fail_on: PolicySeverity,
+
+        /// emit the findings as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// record the findings without sending alerts
+        #[arg(long)]
+        no_alerts: bool,
+
+        /// SMTP relay (`host:port`) email alerts are sent through
+        #[arg(long, env = "SURVEILR_SMTP_SERVER")]
+        smtp_server: Option<String>,
+
+        /// sender address of email alerts
+        #[arg(long, default_value = "surveilr@localhost", env = "SURVEILR_SMTP_FROM")]
+        smtp_from: String,
+    },
+    /// list the recorded findings
+    Findings {
+        /// only list findings in these states
+        #[arg(short, long, value_enum)]
+        state: Vec<FindingState>,
+
+        /// only list the findings of these packs (notebook names)
+        #[arg(short, long)]
+        pack: Vec<String>,
+    },
+    /// acknowledge new findings so alerts on open findings skip them
+    Ack {
+        /// the IDs of the findings
+        #[arg(required = true)]
+        policy_finding_id: Vec<String>,
+    },
+    /// manage the rules which send alerts about findings after `policy run`
+    Alert {
+        #[command(subcommand)]
+        command: PolicyAlertCommands,
+    },
+    /// list the assertions
+    Ls {
+        /// only list the assertions of these packs (notebook names)
+        #[arg(short, long)]
+        pack: Vec<String>,
+    },
+    /// store an assertion as a cell of its pack's notebook, replacing an earlier version
+    Add {
+        /// the read-only SQL query of the assertion
+        sql: String,
+
+        /// the pack (notebook name)
+        #[arg(short, long)]
+        pack: String,
+
+        /// the assertion (cell name)
+        #[arg(short, long)]
+        name: String,
+
+        /// whether the query must return no rows (violations) or some rows (evidence)
+        #[arg(short, long, value_enum)]
+        expect: PolicyExpectation,
+
+        /// severity of a failure
+        #[arg(short, long, value_enum, default_value_t = PolicySeverity::Medium)]
+        severity: PolicySeverity,
+
+        /// how to remediate a failure
+        #[arg(short, long)]
+        remediation: Option<String>,
+
+        /// what the assertion checks
+        #[arg(long)]
+        description: Option<String>,
+    },
+}
+
+#[derive(Debug, Serialize, Subcommand, Clone)]
+pub enum PolicyAlertCommands {
+    /// create or replace an alert rule
+    Add {
+        /// the rule name
+        name: String,
+
+        /// the least severe findings to notify about
+        #[arg(short, long, value_enum, default_value_t = PolicySeverity::High)]
+        min_severity: PolicySeverity,
+
+        /// notify about findings first seen in the run or all unacknowledged ones
+        #[arg(short, long, value_enum, default_value_t = AlertTrigger::New)]
+        fire_on: AlertTrigger,
+
+        /// URL the findings are POSTed to as JSON
+        #[arg(long, conflicts_with = "email", required_unless_present = "email")]
+        webhook: Option<String>,
+
+        /// address the findings are emailed to (see `policy run --smtp-server`)
+        #[arg(long)]
+        email: Option<String>,
+
+        /// only notify about the findings of these packs (notebook names)
+        #[arg(short, long)]
+        pack: Vec<String>,
+    },
+    /// list the alert rules
+    Ls,
+    /// remove an alert rule
+    Rm {
+        /// the rule name
+        name: String,
+    },
+}
+
+impl PolicyArgs {
+    pub async fn execute(&self) -> anyhow::Result<()> {
+        if self.read_only {
+            match &self.command {
+                PolicyCommands::Add { .. }
+                | PolicyCommands::Ack { .. }
+                | PolicyCommands::Alert {
+                    command: PolicyAlertCommands::Add { .. } | PolicyAlertCommands::Rm { .. },
+                } => {
+                    anyhow::bail!("this policy command writes to the database, remove --read-only")
+                }
+                _ => {}
+            }
+            let dbc = DbConn::open(&self.state_db_fs_path, 0).with_context(|| {
+                format!(
+                    "[PolicyArgs::execute] SQLite database {}",
+                    self.state_db_fs_path
+                )
+            })?;
+            return self.query(&dbc.conn);
+        }
+
+        let mut dbc = DbConn::new(&self.state_db_fs_path, 0).with_context(|| {
+            format!(
+                "[PolicyArgs::execute] SQLite database {}",
+                self.state_db_fs_path
+            )
+        })?;
+        // makes sure RSSDs created before `policy_finding` existed are migrated
+        let tx = dbc.init(None)?;
+
+        match &self.command {
+            PolicyCommands::Add {
+                sql,
+                pack,
+                name,
+                expect,
+                severity,
+                remediation,
+                description,
+            } => {
+                store_policy_assertion(
+                    &tx,
+                    &PolicyAssertion {
+                        pack: pack.clone(),
+                        name: name.clone(),
+                        sql: sql.clone(),
+                        description: description.clone(),
+                        rule: PolicyRule {
+                            expect: *expect,
+                            severity: *severity,
+                            remediation: remediation.clone(),
+                        },
+                    },
+                )?;
+                tx.commit()?;
+                println!("{}::{}: stored", pack, name);
+                Ok(())
+            }
+            PolicyCommands::Ack { policy_finding_id } => {
+                let skipped = acknowledge_findings(&tx, policy_finding_id, &os_user())?;
+                tx.commit()?;
+                println!(
+                    "{} findings acknowledged",
+                    policy_finding_id.len() - skipped.len()
+                );
+                if !skipped.is_empty() {
+                    anyhow::bail!("not new findings: {}", skipped.join(", "));
+                }
+                Ok(())
+            }
+            PolicyCommands::Alert {
+                command:
+                    PolicyAlertCommands::Add {
+                        name,
+                        min_severity,
+                        fire_on,
+                        webhook,
+                        email,
+                        pack,
+                    },
+            } => {
+                let (channel, target) = match (webhook, email) {
+                    (Some(url), _) => (AlertChannel::Webhook, url.clone()),
+                    (None, Some(address)) => (AlertChannel::Email, address.clone()),
+                    (None, None) => unreachable!("clap requires --webhook or --email"),
+                };
+                store_alert_rule(
+                    &tx,
+                    &AlertRule {
+                        name: name.clone(),
+                        min_severity: *min_severity,
+                        fire_on: *fire_on,
+                        channel,
+                        target,
+                        packs: pack.clone(),
+                    },
+                )?;
+                tx.commit()?;
+                println!("alert rule {}: stored", name);
+                Ok(())
+            }
+            PolicyCommands::Alert {
+                command: PolicyAlertCommands::Rm { name },
+            } => {
+                let removed = remove_alert_rule(&tx, name)?;
+                tx.commit()?;
+                if !removed {
+                    anyhow::bail!("no alert rule named '{}'", name);
+                }
+                println!("alert rule {}: removed", name);
+                Ok(())
+            }
+            PolicyCommands::Run {
+                pack,
+                fail_on,
+                json,
+                no_alerts,
+                smtp_server,
+                smtp_from,
+            } => {
+                let findings = evaluate_policies(&tx, pack)?;
+                let recorded = record_findings(&tx, &findings)?;
+                let rules = alert_rules(&tx)?;
+                tx.commit()?;
+                emit_findings(&recorded, *json)?;
+
+                let mut undelivered = 0;
+                if !no_alerts {
+                    let smtp = smtp_server.as_ref().map(|server| SmtpRelay {
+                        server: server.clone(),
+                        from: smtp_from.clone(),
+                    });
+                    let deliveries =
+                        send_alerts(&rules, &recorded, &self.state_db_fs_path, smtp.as_ref()).await;
+                    for d in deliveries {
+                        match d.error {
+                            Some(err) => {
+                                undelivered += 1;
+                                warn!("alert {} to {} failed: {}", d.rule, d.target, err);
+                            }
+                            None => eprintln!(
+                                "alert {}: {} findings sent to {}",
+                                d.rule, d.findings, d.target
+                            ),
+                        }
+                    }
+                }
+                check_findings(&recorded, *fail_on)?;
+                if undelivered > 0 {
+                    anyhow::bail!("{} policy alerts could not be delivered", undelivered);
+                }
+                Ok(())
+            }
+            _ => self.query(&tx),
+        }
+    }
+
+    /// The commands which only read the RSSD.
+    fn query(&self, conn: &Connection) -> anyhow::Result<()> {
+        match &self.command {
+            PolicyCommands::Ls { pack } => {
+                let mut table = Table::new();
+                table.load_preset(UTF8_FULL).set_header(vec![
+                    "Pack",
+                    "Assertion",
+                    "Expect",
+                    "Severity",
+                    "Description",
+                ]);
+                for a in policy_assertions(conn, pack)? {
+                    table.add_row(vec![
+                        a.pack,
+                        a.name,
+                        a.rule.expect.to_string(),
+                        a.rule.severity.to_string(),
+                        a.description.unwrap_or_default(),
+                    ]);
+                }
+                println!("{table}");
+            }
+            PolicyCommands::Findings { state, pack } => {
+                let mut table = Table::new();
+                table.load_preset(UTF8_FULL).set_header(vec![
+                    "Finding",
+                    "Pack",
+                    "Assertion",
+                    "Severity",
+                    "State",
+                    "Status",
+                    "Rows",
+                    "First seen",
+                    "Last seen",
+                ]);
+                for f in stored_findings(conn, state, pack)? {
+                    table.add_row(vec![
+                        f.policy_finding_id,
+                        f.pack,
+                        f.assertion,
+                        f.severity,
+                        f.state.to_string(),
+                        f.status,
+                        f.row_count.to_string(),
+                        f.created_at,
+                        f.last_seen_at,
+                    ]);
+                }
+                println!("{table}");
+            }
+            PolicyCommands::Alert {
+                command: PolicyAlertCommands::Ls,
+            } => {
+                let mut table = Table::new();
+                table.load_preset(UTF8_FULL).set_header(vec![
+                    "Rule",
+                    "Min severity",
+                    "Fire on",
+                    "Channel",
+                    "Target",
+                    "Packs",
+                ]);
+                for r in alert_rules(conn)? {
+                    table.add_row(vec![
+                        r.name,
+                        r.min_severity.to_string(),
+                        r.fire_on.to_string(),
+                        r.channel.to_string(),
+                        r.target,
+                        r.packs.join(", "),
+                    ]);
+                }
+                println!("{table}");
+            }
+            PolicyCommands::Run {
+                pack,
+                fail_on,
+                json,
+                ..
+            } => {
+                // read-only runs don't record findings, so none have states
+                let recorded: Vec<_> = evaluate_policies(conn, pack)?
+                    .into_iter()
+                    .map(|finding| RecordedFinding {
+                        policy_finding_id: None,
+                        state: None,
+                        new: false,
+                        finding,
+                    })
+                    .collect();
+                emit_findings(&recorded, *json)?;
+                check_findings(&recorded, *fail_on)?;
+            }
+            _ => unreachable!("only the policy commands which read are queries"),
+        }
+        Ok(())
+    }
+}
+
+fn emit_findings(recorded: &[RecordedFinding], json: bool) -> anyhow::Result<()> {
+    if json {
+        println!("{}", serde_json::to_string_pretty(recorded)?);
+        return Ok(());
+    }
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL).set_header(vec![
+        "Pack",
+        "Assertion",
+        "Severity",
+        "Status",
+        "Rows",
+        "Finding",
+        "Remediation",
+    ]);
+    for rf in recorded {
+        let f = &rf.finding;
+        table.add_row(vec![
+            f.pack.clone(),
+            f.assertion.clone(),
+            f.severity.to_string(),
+            f.status.to_string(),
+            f.row_count.to_string(),
+            match (&rf.policy_finding_id, rf.state) {
+                (Some(id), Some(_)) if rf.new => format!("{} (new)", id),
+                (Some(id), Some(state)) => format!("{} ({})", id, state),
+                _ => String::new(),
+            },
+            f.error
+                .clone()
+                .or_else(|| f.remediation.clone())
+                .unwrap_or_default(),
+        ]);
+    }
+    println!("{table}");
+    let count = |status| {
+        recorded
+            .iter()
+            .filter(|rf| rf.finding.status == status)
+            .count()
+    };
+    println!(
+        "{} assertions: {} passed, {} failed, {} errors",
+        recorded.len(),
+        count(FindingStatus::Pass),
+        count(FindingStatus::Fail),
+        count(FindingStatus::Error),
+    );
+    Ok(())
+}
+
+fn check_findings(recorded: &[RecordedFinding], fail_on: PolicySeverity) -> anyhow::Result<()> {
+    let failed = recorded
+        .iter()
+        .filter(|rf| rf.finding.fails(fail_on))
+        .count();
+    if failed > 0 {
+        anyhow::bail!(
+            "{} policy assertions failed with severity {} or higher",
+            failed,
+            fail_on
+        );
+    }
+    Ok(())
+}
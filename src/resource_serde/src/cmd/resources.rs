@@ -0,0 +1,133 @@
+use std::io::Write;
+
+use anyhow::Context;
+use clap::{Args, Subcommand};
+use resource::remote::RemoteFetcher;
+use serde::Serialize;
+
+use crate::blobs::offloaded_blob;
+use crate::ingest::{chunked_content_size, fetch_uniform_resource, write_chunked_content};
+use crate::persist::DbConn;
+
+const DEFAULT_STATEDB_FS_PATH: &str = "resource-surveillance.sqlite.db";
+
+/// Work with the uniform resources stored in the RSSD
+#[derive(Debug, Serialize, Args, Clone)]
+pub struct ResourcesArgs {
+    /// target SQLite database
+    #[arg(short='d', long, default_value = DEFAULT_STATEDB_FS_PATH, default_missing_value = "always", env="SURVEILR_STATEDB_FS_PATH")]
+    pub state_db_fs_path: String,
+
+    #[command(subcommand)]
+    pub command: ResourcesCommands,
+}
+
+#[derive(Debug, Serialize, Subcommand, Clone)]
+pub enum ResourcesCommands {
+    /// read a resource's content, fetching remote (`http(s)://`, `s3://`) resources
+    /// cataloged with `ingest uris --meta-only` and caching them in the RSSD;
+    /// content stored in chunks is reassembled and content offloaded to the blob
+    /// store read from it
+    Fetch {
+        /// the resource's `uniform_resource_id`
+        uniform_resource_id: String,
+
+        /// fetch the remote content again even when it's cached
+        #[arg(long)]
+        refresh: bool,
+
+        /// write the content to this file (`-` for STDOUT)
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+}
+
+impl ResourcesArgs {
+    pub async fn execute(&self) -> anyhow::Result<()> {
+        let mut dbc = DbConn::new(&self.state_db_fs_path, 0).with_context(|| {
+            format!(
+                "[ResourcesArgs::execute] SQLite database {}",
+                self.state_db_fs_path
+            )
+        })?;
+        let tx = dbc.init(None)?;
+
+        match &self.command {
+            ResourcesCommands::Fetch {
+                uniform_resource_id,
+                refresh,
+                output,
+            } => {
+                // chunked content is too large to hold in memory, it's streamed
+                if let (Some(output), Some(size)) =
+                    (output, chunked_content_size(&tx, uniform_resource_id)?)
+                {
+                    if output == "-" {
+                        write_chunked_content(
+                            &tx,
+                            uniform_resource_id,
+                            &mut std::io::stdout().lock(),
+                        )?;
+                        return Ok(());
+                    }
+                    let mut file =
+                        std::io::BufWriter::new(std::fs::File::create(output).with_context(
+                            || format!("[ResourcesArgs::execute] creating {output}"),
+                        )?);
+                    write_chunked_content(&tx, uniform_resource_id, &mut file)?;
+                    file.flush()?;
+                    println!(
+                        "{uniform_resource_id}: {size} bytes reassembled from chunks in {output}"
+                    );
+                    return Ok(());
+                }
+                // blobs can be just as large
+                if let (Some(output), Some((store, digest))) =
+                    (output, offloaded_blob(&tx, uniform_resource_id)?)
+                {
+                    if output == "-" {
+                        std::io::copy(&mut store.reader(&digest)?, &mut std::io::stdout().lock())?;
+                        return Ok(());
+                    }
+                    let blob = store.path(&digest)?;
+                    let size = std::fs::copy(&blob, output)
+                        .with_context(|| format!("[ResourcesArgs::execute] writing {output}"))?;
+                    println!(
+                        "{uniform_resource_id}: {size} bytes from blob {} in {output}",
+                        blob.display()
+                    );
+                    return Ok(());
+                }
+                let fetched = fetch_uniform_resource(
+                    &tx,
+                    &RemoteFetcher::default(),
+                    uniform_resource_id,
+                    *refresh,
+                )
+                .await?;
+                tx.commit()?;
+                match output.as_deref() {
+                    Some("-") => {
+                        std::io::stdout().write_all(&fetched.content)?;
+                        return Ok(());
+                    }
+                    Some(output) => std::fs::write(output, &fetched.content)
+                        .with_context(|| format!("[ResourcesArgs::execute] writing {output}"))?,
+                    None => {}
+                }
+                println!(
+                    "{} ({}): {} bytes {}",
+                    fetched.uri,
+                    fetched.uniform_resource_id,
+                    fetched.content.len(),
+                    if fetched.cached {
+                        "already in the RSSD"
+                    } else {
+                        "fetched and cached in the RSSD"
+                    }
+                );
+                Ok(())
+            }
+        }
+    }
+}
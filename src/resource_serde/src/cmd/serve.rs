@@ -0,0 +1,58 @@
+use clap::{Args, Subcommand};
+use serde::Serialize;
+
+const DEFAULT_STATEDB_FS_PATH: &str = "resource-surveillance.sqlite.db";
+
+/// Long-running listeners which ingest what is pushed to them
+#[derive(Debug, Serialize, Args, Clone)]
+pub struct ServeArgs {
+    #[command(subcommand)]
+    pub command: ServeCommands,
+}
+
+#[derive(Debug, Serialize, Subcommand, Clone)]
+pub enum ServeCommands {
+    /// Accept messages journaled by mail servers over SMTP and ingest them as they arrive
+    SmtpJournal(SmtpJournalArgs),
+}
+
+#[derive(Debug, Serialize, Args, Clone)]
+pub struct SmtpJournalArgs {
+    /// target SQLite database
+    #[arg(short='d', long, default_value = DEFAULT_STATEDB_FS_PATH, default_missing_value = "always", env="SURVEILR_STATEDB_FS_PATH")]
+    pub state_db_fs_path: String,
+
+    /// address to listen on, the journaling rule's SMTP connector delivers here
+    #[arg(
+        short,
+        long,
+        default_value = "127.0.0.1:2525",
+        env = "SURVEILR_SMTP_JOURNAL_ADDR"
+    )]
+    pub addr: String,
+
+    /// mailbox the journaled messages are recorded under (their URIs are `smtp://<mailbox>/<message-id>`)
+    #[arg(short, long, default_value = "journal")]
+    pub mailbox: String,
+
+    /// largest message accepted, in bytes (advertised with the SMTP SIZE extension)
+    #[arg(long, default_value = "26214400")]
+    pub max_message_size: usize,
+
+    /// Extract Attachments
+    #[arg(short, long, default_value = "true")]
+    pub extract_attachments: bool,
+}
+
+impl ServeArgs {
+    pub async fn execute(&self) -> anyhow::Result<()> {
+        match &self.command {
+            #[cfg(feature = "imap")]
+            ServeCommands::SmtpJournal(args) => {
+                crate::ingest::serve_smtp_journal(args).await.map(|_| ())
+            }
+            #[cfg(not(feature = "imap"))]
+            ServeCommands::SmtpJournal(_) => Err(super::feature_not_compiled("imap")),
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use anyhow::Context;
+use clap::{Args, Subcommand};
+use comfy_table::{presets::UTF8_FULL, Table};
+use rusqlite::Connection;
+use serde::Serialize;
+
+use crate::persist::DbConn;
+use crate::sessions::{find_session, list_sessions, session_details, SessionsFilter};
+
+const DEFAULT_STATEDB_FS_PATH: &str = "resource-surveillance.sqlite.db";
+
+/// Browse the ingest sessions in the RSSD
+#[derive(Debug, Serialize, Args, Clone)]
+pub struct SessionsArgs {
+    /// target SQLite database
+    #[arg(short='d', long, default_value = DEFAULT_STATEDB_FS_PATH, default_missing_value = "always", env="SURVEILR_STATEDB_FS_PATH")]
+    pub state_db_fs_path: String,
+
+    /// open the database read-only so it's safe while it's being written
+    #[arg(long)]
+    read_only: bool,
+
+    #[command(subcommand)]
+    pub command: SessionsCommands,
+}
+
+#[derive(Debug, Serialize, Subcommand, Clone)]
+pub enum SessionsCommands {
+    /// list ingest sessions, most recent first
+    Ls {
+        /// only the sessions of this device (its name)
+        #[arg(long)]
+        device: Option<String>,
+
+        /// only sessions of this kind: `files`, `tasks`, `imap`, `uris`, `s3`,
+        /// `git`, `oci`, `browsers`, `seed` or `other`
+        #[arg(long)]
+        kind: Option<String>,
+
+        /// how many sessions to list, 0 for all of them
+        #[arg(short, long, default_value_t = 20)]
+        limit: usize,
+
+        /// emit the sessions as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// show a session's behavior, root paths, natures and errors
+    Show {
+        /// the session's ID, a unique prefix of it or `latest`
+        #[arg(default_value = "latest")]
+        session: String,
+
+        /// emit the session as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+impl SessionsArgs {
+    pub fn execute(&self) -> anyhow::Result<()> {
+        if self.read_only {
+            let dbc = DbConn::open(&self.state_db_fs_path, 0).with_context(|| {
+                format!(
+                    "[SessionsArgs::execute] SQLite database {}",
+                    self.state_db_fs_path
+                )
+            })?;
+            return self.query(&dbc.conn);
+        }
+
+        let mut dbc = DbConn::new(&self.state_db_fs_path, 0).with_context(|| {
+            format!(
+                "[SessionsArgs::execute] SQLite database {}",
+                self.state_db_fs_path
+            )
+        })?;
+        // makes sure the columns the listing reads exist in older RSSDs
+        let tx = dbc.init(None)?;
+        self.query(&tx)
+    }
+
+    fn query(&self, conn: &Connection) -> anyhow::Result<()> {
+        match &self.command {
+            SessionsCommands::Ls {
+                device,
+                kind,
+                limit,
+                json,
+            } => {
+                let filter = SessionsFilter {
+                    device_name: device.clone(),
+                    kind: kind.clone(),
+                    limit: (*limit > 0).then_some(*limit),
+                };
+                let sessions = list_sessions(conn, &filter)?;
+                if *json {
+                    println!("{}", serde_json::to_string_pretty(&sessions)?);
+                    return Ok(());
+                }
+                let mut table = Table::new();
+                table.load_preset(UTF8_FULL).set_header(vec![
+                    "Session",
+                    "Device",
+                    "Kind",
+                    "Started",
+                    "Finished",
+                    "Resources",
+                    "Entries",
+                    "Tasks",
+                    "Messages",
+                    "Errors",
+                ]);
+                for s in sessions {
+                    table.add_row(vec![
+                        s.ingest_session_id,
+                        s.device_name,
+                        s.kind,
+                        s.ingest_started_at,
+                        s.ingest_finished_at.unwrap_or_else(|| "-".to_string()),
+                        s.uniform_resources.to_string(),
+                        s.fs_path_entries.to_string(),
+                        s.tasks.to_string(),
+                        s.imap_messages.to_string(),
+                        s.errors.to_string(),
+                    ]);
+                }
+                println!("{table}");
+            }
+            SessionsCommands::Show { session, json } => {
+                let details = session_details(conn, &find_session(conn, session)?)?;
+                if *json {
+                    println!("{}", serde_json::to_string_pretty(&details)?);
+                    return Ok(());
+                }
+                let s = &details.listing;
+                let mut table = Table::new();
+                table.load_preset(UTF8_FULL);
+                table.add_row(vec!["Session", &s.ingest_session_id]);
+                table.add_row(vec![
+                    "Device",
+                    &format!("{} ({})", s.device_name, s.device_id),
+                ]);
+                table.add_row(vec!["Kind", &s.kind]);
+                table.add_row(vec!["Started", &s.ingest_started_at]);
+                table.add_row(vec![
+                    "Finished",
+                    s.ingest_finished_at.as_deref().unwrap_or("-"),
+                ]);
+                table.add_row(vec!["Resources", &s.uniform_resources.to_string()]);
+                table.add_row(vec!["Entries", &s.fs_path_entries.to_string()]);
+                table.add_row(vec!["Tasks", &s.tasks.to_string()]);
+                table.add_row(vec!["Messages", &s.imap_messages.to_string()]);
+                table.add_row(vec!["Errors", &s.errors.to_string()]);
+                for (label, value) in [
+                    ("Behavior", &details.behavior),
+                    ("Elaboration", &details.elaboration),
+                    ("Device clock", &details.device_clock),
+                ] {
+                    if let Some(value) = value {
+                        table.add_row(vec![label, &serde_json::to_string_pretty(value)?]);
+                    }
+                }
+                println!("{table}");
+
+                if !details.root_paths.is_empty() {
+                    let mut table = Table::new();
+                    table
+                        .load_preset(UTF8_FULL)
+                        .set_header(vec!["Root path", "Entries"]);
+                    for p in &details.root_paths {
+                        table.add_row(vec![p.root_path.clone(), p.entries.to_string()]);
+                    }
+                    println!("{table}");
+                }
+                if !details.natures.is_empty() {
+                    let mut table = Table::new();
+                    table
+                        .load_preset(UTF8_FULL)
+                        .set_header(vec!["Nature", "Resources"]);
+                    for n in &details.natures {
+                        table.add_row(vec![
+                            n.nature.clone().unwrap_or_else(|| "-".to_string()),
+                            n.uniform_resources.to_string(),
+                        ]);
+                    }
+                    println!("{table}");
+                }
+                if !details.error_details.is_empty() {
+                    let mut table = Table::new();
+                    table
+                        .load_preset(UTF8_FULL)
+                        .set_header(vec!["Failed", "Diagnostics"]);
+                    for e in &details.error_details {
+                        table.add_row(vec![
+                            e.source.clone(),
+                            e.diagnostics
+                                .as_ref()
+                                .map_or_else(String::new, |d| d.to_string()),
+                        ]);
+                    }
+                    println!("{table}");
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,150 @@
+use anyhow::Context;
+use clap::{Args, Subcommand};
+use comfy_table::{presets::UTF8_FULL, Table};
+use rusqlite::Connection;
+use serde::Serialize;
+
+use crate::persist::DbConn;
+use crate::snapshot::{
+    create_snapshot, diff_snapshots, find_snapshot, list_snapshots, previous_snapshot,
+};
+
+const DEFAULT_STATEDB_FS_PATH: &str = "resource-surveillance.sqlite.db";
+
+/// Save SQL query results as named, versioned snapshots in the RSSD and compare them
+#[derive(Debug, Serialize, Args, Clone)]
+pub struct SnapshotArgs {
+    /// target SQLite database
+    #[arg(short='d', long, default_value = DEFAULT_STATEDB_FS_PATH, default_missing_value = "always", env="SURVEILR_STATEDB_FS_PATH")]
+    pub state_db_fs_path: String,
+
+    /// open the database read-only (`ls` and `diff`) so it's safe while it's being written
+    #[arg(long)]
+    read_only: bool,
+
+    #[command(subcommand)]
+    pub command: SnapshotCommands,
+}
+
+#[derive(Debug, Serialize, Subcommand, Clone)]
+pub enum SnapshotCommands {
+    /// run a read-only SQL query and store its result set as a new version of a snapshot
+    Create {
+        /// the SQL query whose results should be saved
+        sql: String,
+
+        /// snapshot name, reuse it to version the same report over time
+        #[arg(short, long)]
+        name: String,
+    },
+    /// list stored snapshots
+    Ls {
+        /// only list the versions of this snapshot
+        #[arg(short, long)]
+        name: Option<String>,
+    },
+    /// compare two snapshots (IDs or names, a name means its latest version) and
+    /// emit the differences as JSON
+    Diff {
+        /// the older snapshot
+        from: String,
+
+        /// the newer snapshot, defaults to the latest version of `from`'s name
+        to: Option<String>,
+
+        /// columns which identify a row so modified rows are reported as changed
+        #[arg(short, long)]
+        key: Vec<String>,
+    },
+}
+
+impl SnapshotArgs {
+    pub fn execute(&self) -> anyhow::Result<()> {
+        if self.read_only {
+            if let SnapshotCommands::Create { .. } = &self.command {
+                anyhow::bail!("snapshot create writes to the database, remove --read-only");
+            }
+            let dbc = DbConn::open(&self.state_db_fs_path, 0).with_context(|| {
+                format!(
+                    "[SnapshotArgs::execute] SQLite database {}",
+                    self.state_db_fs_path
+                )
+            })?;
+            return self.query(&dbc.conn);
+        }
+
+        let mut dbc = DbConn::new(&self.state_db_fs_path, 0).with_context(|| {
+            format!(
+                "[SnapshotArgs::execute] SQLite database {}",
+                self.state_db_fs_path
+            )
+        })?;
+        // makes sure RSSDs created before `query_snapshot` existed are migrated
+        let tx = dbc.init(None)?;
+
+        match &self.command {
+            SnapshotCommands::Create { sql, name } => {
+                let snapshot = create_snapshot(&tx, name, sql)?;
+                let previous = previous_snapshot(&tx, &snapshot)?;
+                tx.commit()?;
+                println!(
+                    "{}: snapshot {} with {} rows{}",
+                    snapshot.name,
+                    snapshot.query_snapshot_id,
+                    snapshot.rows.len(),
+                    match previous {
+                        Some(p) if p.content_digest == snapshot.content_digest =>
+                            format!(" (unchanged since {})", p.query_snapshot_id),
+                        _ => String::new(),
+                    }
+                );
+                Ok(())
+            }
+            _ => self.query(&tx),
+        }
+    }
+
+    /// The commands which only read snapshots.
+    fn query(&self, conn: &Connection) -> anyhow::Result<()> {
+        match &self.command {
+            SnapshotCommands::Create { .. } => unreachable!("snapshot create is not a query"),
+            SnapshotCommands::Ls { name } => {
+                let mut table = Table::new();
+                table
+                    .load_preset(UTF8_FULL)
+                    .set_header(vec!["Name", "Snapshot", "Rows", "Digest", "Created"]);
+                for s in list_snapshots(conn, name.as_deref())? {
+                    table.add_row(vec![
+                        s.name,
+                        s.query_snapshot_id,
+                        s.row_count.to_string(),
+                        s.content_digest,
+                        s.created_at,
+                    ]);
+                }
+                println!("{table}");
+            }
+            SnapshotCommands::Diff { from, to, key } => {
+                let (from, to) = match to {
+                    Some(to) => (find_snapshot(conn, from)?, find_snapshot(conn, to)?),
+                    None => {
+                        let from = find_snapshot(conn, from)?;
+                        let to = find_snapshot(conn, &from.name)?;
+                        if from.query_snapshot_id == to.query_snapshot_id {
+                            // only a name was given, compare its two latest versions
+                            let previous = previous_snapshot(conn, &to)?.with_context(|| {
+                                format!("snapshot '{}' has only one version", to.name)
+                            })?;
+                            (previous, to)
+                        } else {
+                            (from, to)
+                        }
+                    }
+                };
+                let diff = diff_snapshots(&from, &to, key);
+                println!("{}", serde_json::to_string_pretty(&diff)?);
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,8 +1,12 @@
+#[cfg(feature = "transform")]
 use anyhow::anyhow;
 use clap::{Args, Subcommand, ValueEnum};
 use serde::Serialize;
 
-use crate::transformers::{HtmlTransformer, Transformer};
+#[cfg(feature = "transform")]
+use crate::transformers::{
+    backfill_transforms, BackfillTransform, HtmlTransformer, Transformer,
+};
 
 const DEFAULT_STATEDB_FS_PATH: &str = "resource-surveillance.sqlite.db";
 
@@ -44,10 +48,42 @@ pub enum TransformCommands {
     },
     /// Transform markdown content
     Markdown {},
+    /// Run a registered transform over already-ingested resources which lack
+    /// a corresponding uniform_resource_transform row
+    Backfill {
+        /// nature of the uniform_resource rows to transform (e.g. xml, html)
+        #[arg(short, long)]
+        nature: String,
+
+        /// format the content should be transformed into
+        #[arg(short, long, default_value = "json")]
+        transformer: Format,
+
+        /// number of resources to transform and commit at a time
+        #[arg(short, long, default_value = "100")]
+        batch_size: usize,
+    },
 }
 
+#[cfg(not(feature = "transform"))]
 impl TransformArgs {
     pub fn transform(&self) -> anyhow::Result<()> {
+        Err(super::feature_not_compiled("transform"))
+    }
+}
+
+#[cfg(feature = "transform")]
+impl TransformArgs {
+    pub fn transform(&self) -> anyhow::Result<()> {
+        if let TransformCommands::Backfill {
+            nature,
+            transformer,
+            batch_size,
+        } = &self.command
+        {
+            return self.backfill(nature, transformer, *batch_size);
+        }
+
         let transformer: Box<dyn Transformer> = match &self.command {
             TransformCommands::Html { css_select, .. } => Box::new(HtmlTransformer::new(
                 css_select.to_vec(),
@@ -59,4 +95,30 @@ impl TransformArgs {
         transformer.insert(self.reset_transforms)?;
         Ok(())
     }
+
+    fn backfill(&self, nature: &str, format: &Format, batch_size: usize) -> anyhow::Result<()> {
+        let format = match format {
+            Format::Json => "json",
+        };
+        let transform = BackfillTransform::registered(nature, format)?;
+        let summary = backfill_transforms(
+            &self.state_db_fs_path,
+            transform,
+            batch_size.max(1),
+            |progress| {
+                println!(
+                    "{} -> {}: {}/{} transformed, {} failed",
+                    nature,
+                    format,
+                    progress.transformed,
+                    progress.pending,
+                    progress.failed
+                )
+            },
+        )?;
+        if summary.pending == 0 {
+            println!("{} -> {}: nothing to backfill", nature, format);
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,480 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use anyhow::{anyhow, Context, Result};
+use clap::ValueEnum;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+
+use crate::policy::{evaluate_assertion, policy_assertions, FindingStatus};
+
+const UPSERT_CONTROL_SQL: &str = "INSERT INTO compliance_control (compliance_control_id, framework, control_code, title, description)
+       VALUES (surveilr_pk(), ?, ?, ?, ?)
+  ON CONFLICT (framework, control_code) DO UPDATE SET
+       title = COALESCE(EXCLUDED.title, title),
+       description = COALESCE(EXCLUDED.description, description),
+       updated_at = CURRENT_TIMESTAMP
+  RETURNING compliance_control_id";
+const UPSERT_EVIDENCE_MAP_SQL: &str = "INSERT INTO compliance_evidence_map (compliance_evidence_map_id, compliance_control_id, evidence_kind, evidence_selector, description)
+       VALUES (surveilr_pk(), ?, ?, ?, ?)
+  ON CONFLICT (compliance_control_id, evidence_kind, evidence_selector) DO UPDATE SET
+       description = COALESCE(EXCLUDED.description, description),
+       updated_at = CURRENT_TIMESTAMP";
+const DEL_EVIDENCE_MAP_SQL: &str = "DELETE FROM compliance_evidence_map
+  WHERE compliance_control_id = (SELECT compliance_control_id FROM compliance_control WHERE framework = ?1 AND control_code = ?2)
+    AND (?3 IS NULL OR (evidence_kind = ?3 AND evidence_selector = ?4))";
+const SEL_CONTROLS_SQL: &str = "SELECT c.framework, c.control_code, c.title, c.description, m.evidence_kind, m.evidence_selector, m.description
+   FROM compliance_control c
+   LEFT JOIN compliance_evidence_map m ON m.compliance_control_id = c.compliance_control_id
+  ORDER BY c.framework, c.control_code, m.evidence_kind, m.evidence_selector";
+const SEL_RESOURCE_EVIDENCE_SQL: &str = "SELECT COUNT(*), MAX(COALESCE(updated_at, created_at))
+   FROM uniform_resource
+  WHERE uri GLOB ?";
+// outputs of capturable executables are the resources their walk entries or
+// tasks point to
+const SEL_CE_EVIDENCE_SQL: &str = "SELECT COUNT(*), MAX(COALESCE(ur.updated_at, ur.created_at))
+   FROM uniform_resource ur
+  WHERE ur.uri GLOB ?
+    AND (EXISTS (SELECT 1 FROM ur_ingest_session_task t WHERE t.uniform_resource_id = ur.uniform_resource_id)
+         OR EXISTS (SELECT 1 FROM ur_ingest_session_fs_path_entry e
+                     WHERE e.uniform_resource_id = ur.uniform_resource_id AND e.captured_executable IS NOT NULL))";
+
+/// What a `compliance_evidence_map` selector selects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum EvidenceKind {
+    /// uniform resources whose URI matches the GLOB selector
+    Resource,
+    /// the assertions of a policy pack (`pack` or `pack::assertion`)
+    Policy,
+    /// outputs of capturable executables whose URI matches the GLOB selector
+    CapturableExec,
+}
+
+impl fmt::Display for EvidenceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.to_possible_value().unwrap().get_name())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Evidence {
+    pub kind: EvidenceKind,
+    pub selector: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// A framework control with the evidence mapped to it, the shape of the
+/// entries of `compliance import` catalogs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Control {
+    pub framework: String,
+    #[serde(alias = "control")]
+    pub control_code: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub evidence: Vec<Evidence>,
+}
+
+/// Create the control (keeping the stored title and description when none
+/// are given) and map its evidence, returns its ID.
+pub fn upsert_control(conn: &Connection, control: &Control) -> Result<String> {
+    let context = || {
+        format!(
+            "[compliance::upsert_control] {} {}",
+            control.framework, control.control_code
+        )
+    };
+    let compliance_control_id: String = conn
+        .query_row(
+            UPSERT_CONTROL_SQL,
+            params![
+                control.framework,
+                control.control_code,
+                control.title,
+                control.description
+            ],
+            |row| row.get(0),
+        )
+        .with_context(context)?;
+    let mut map_stmt = conn.prepare_cached(UPSERT_EVIDENCE_MAP_SQL)?;
+    for evidence in &control.evidence {
+        map_stmt
+            .execute(params![
+                compliance_control_id,
+                evidence.kind.to_string(),
+                evidence.selector,
+                evidence.description
+            ])
+            .with_context(context)?;
+    }
+    Ok(compliance_control_id)
+}
+
+/// Import a YAML (or JSON) array of controls, returns how many there were.
+pub fn import_controls(conn: &Connection, catalog: &str) -> Result<usize> {
+    let controls: Vec<Control> = serde_yaml::from_str(catalog)
+        .with_context(|| "[compliance::import_controls] catalog is not an array of controls")?;
+    for control in &controls {
+        upsert_control(conn, control)?;
+    }
+    Ok(controls.len())
+}
+
+/// Remove a control's mapping to `evidence`, or all its mappings.
+pub fn unmap_evidence(
+    conn: &Connection,
+    framework: &str,
+    control_code: &str,
+    evidence: Option<(EvidenceKind, &str)>,
+) -> Result<usize> {
+    conn.execute(
+        DEL_EVIDENCE_MAP_SQL,
+        params![
+            framework,
+            control_code,
+            evidence.map(|(kind, _)| kind.to_string()),
+            evidence.map(|(_, selector)| selector)
+        ],
+    )
+    .with_context(|| {
+        format!(
+            "[compliance::unmap_evidence] {} {}",
+            framework, control_code
+        )
+    })
+}
+
+/// The controls of `frameworks` (all if empty) with their evidence.
+pub fn controls(conn: &Connection, frameworks: &[String]) -> Result<Vec<Control>> {
+    let mut stmt = conn.prepare(SEL_CONTROLS_SQL)?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                Control {
+                    framework: row.get(0)?,
+                    control_code: row.get(1)?,
+                    title: row.get(2)?,
+                    description: row.get(3)?,
+                    evidence: vec![],
+                },
+                row.get::<_, Option<String>>(4)?,
+                row.get::<_, Option<String>>(5)?,
+                row.get::<_, Option<String>>(6)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .with_context(|| "[compliance::controls] compliance_control")?;
+
+    let mut controls: Vec<Control> = vec![];
+    for (control, kind, selector, description) in rows {
+        if !frameworks.is_empty() && !frameworks.contains(&control.framework) {
+            continue;
+        }
+        let same = controls.last().is_some_and(|last| {
+            last.framework == control.framework && last.control_code == control.control_code
+        });
+        if !same {
+            controls.push(control);
+        }
+        if let (Some(kind), Some(selector)) = (kind, selector) {
+            let kind = EvidenceKind::from_str(&kind, true)
+                .map_err(|_| anyhow!("[compliance::controls] invalid evidence kind '{}'", kind))?;
+            controls.last_mut().unwrap().evidence.push(Evidence {
+                kind,
+                selector,
+                description,
+            });
+        }
+    }
+    Ok(controls)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ControlStatus {
+    /// all the mapped evidence was found and the mapped policies pass
+    Satisfied,
+    /// a mapped policy assertion fails
+    Failing,
+    /// no evidence is mapped or some mapped evidence wasn't found
+    Gap,
+}
+
+impl fmt::Display for ControlStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ControlStatus::Satisfied => "satisfied",
+            ControlStatus::Failing => "failing",
+            ControlStatus::Gap => "gap",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EvidenceCoverage {
+    #[serde(flatten)]
+    pub evidence: Evidence,
+    /// the resources found, or the assertions evaluated
+    pub found: usize,
+    /// the assertions which didn't pass
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failing: Vec<String>,
+    /// when the most recent resource was stored
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latest_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ControlCoverage {
+    pub control_code: String,
+    pub title: Option<String>,
+    pub status: ControlStatus,
+    pub evidence: Vec<EvidenceCoverage>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FrameworkCoverage {
+    pub framework: String,
+    pub satisfied: usize,
+    pub failing: usize,
+    pub gaps: usize,
+    pub controls: Vec<ControlCoverage>,
+}
+
+impl FrameworkCoverage {
+    /// Percentage of the framework's controls which are satisfied.
+    pub fn coverage(&self) -> f64 {
+        match self.controls.len() {
+            0 => 0.0,
+            n => 100.0 * self.satisfied as f64 / n as f64,
+        }
+    }
+}
+
+/// Evaluates evidence, each policy assertion only once per report.
+struct EvidenceEvaluator<'a> {
+    conn: &'a Connection,
+    assertions: HashMap<String, Vec<(String, bool)>>,
+}
+
+impl EvidenceEvaluator<'_> {
+    fn evaluate(&mut self, evidence: &Evidence) -> Result<EvidenceCoverage> {
+        let mut coverage = EvidenceCoverage {
+            evidence: evidence.clone(),
+            found: 0,
+            failing: vec![],
+            latest_at: None,
+        };
+        match evidence.kind {
+            EvidenceKind::Resource | EvidenceKind::CapturableExec => {
+                let sql = match evidence.kind {
+                    EvidenceKind::Resource => SEL_RESOURCE_EVIDENCE_SQL,
+                    _ => SEL_CE_EVIDENCE_SQL,
+                };
+                (coverage.found, coverage.latest_at) = self
+                    .conn
+                    .query_row(sql, [&evidence.selector], |row| {
+                        Ok((row.get(0)?, row.get(1)?))
+                    })
+                    .with_context(|| format!("[compliance::evaluate] {}", evidence.selector))?;
+            }
+            EvidenceKind::Policy => {
+                let (pack, assertion) = match evidence.selector.split_once("::") {
+                    Some((pack, assertion)) => (pack, Some(assertion)),
+                    None => (evidence.selector.as_str(), None),
+                };
+                if !self.assertions.contains_key(pack) {
+                    let evaluated = policy_assertions(self.conn, &[pack.to_string()])?
+                        .iter()
+                        .map(|a| {
+                            let finding = evaluate_assertion(self.conn, a);
+                            (a.name.clone(), finding.status == FindingStatus::Pass)
+                        })
+                        .collect();
+                    self.assertions.insert(pack.to_string(), evaluated);
+                }
+                for (name, passed) in &self.assertions[pack] {
+                    if assertion.is_none_or(|assertion| assertion == name) {
+                        coverage.found += 1;
+                        if !passed {
+                            coverage.failing.push(format!("{}::{}", pack, name));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(coverage)
+    }
+}
+
+/// Summarize the coverage of the controls of `frameworks` (all if empty) by
+/// their evidence, evaluating the mapped policy assertions.
+pub fn compliance_report(
+    conn: &Connection,
+    frameworks: &[String],
+) -> Result<Vec<FrameworkCoverage>> {
+    let mut evaluator = EvidenceEvaluator {
+        conn,
+        assertions: HashMap::new(),
+    };
+    let mut report: BTreeMap<String, FrameworkCoverage> = BTreeMap::new();
+    for control in controls(conn, frameworks)? {
+        let evidence = control
+            .evidence
+            .iter()
+            .map(|evidence| evaluator.evaluate(evidence))
+            .collect::<Result<Vec<_>>>()?;
+        let status = if evidence.iter().any(|e| !e.failing.is_empty()) {
+            ControlStatus::Failing
+        } else if evidence.is_empty() || evidence.iter().any(|e| e.found == 0) {
+            ControlStatus::Gap
+        } else {
+            ControlStatus::Satisfied
+        };
+
+        let framework =
+            report
+                .entry(control.framework.clone())
+                .or_insert_with(|| FrameworkCoverage {
+                    framework: control.framework.clone(),
+                    satisfied: 0,
+                    failing: 0,
+                    gaps: 0,
+                    controls: vec![],
+                });
+        match status {
+            ControlStatus::Satisfied => framework.satisfied += 1,
+            ControlStatus::Failing => framework.failing += 1,
+            ControlStatus::Gap => framework.gaps += 1,
+        }
+        framework.controls.push(ControlCoverage {
+            control_code: control.control_code,
+            title: control.title,
+            status,
+            evidence,
+        });
+    }
+    Ok(report.into_values().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingest::{seed_rssd, SeedProfile};
+    use crate::persist::DbConn;
+    use crate::policy::{
+        store_policy_assertion, PolicyAssertion, PolicyExpectation, PolicyRule, PolicySeverity,
+    };
+
+    const CATALOG: &str = r#"
+- framework: SOC2
+  control: CC6.1
+  title: Logical access
+  evidence:
+    - kind: resource
+      selector: "*/evidence/reports/*"
+    - kind: policy
+      selector: baseline::has-markdown
+- framework: SOC2
+  control: CC7.2
+  title: Monitoring
+  evidence:
+    - kind: capturable-exec
+      selector: "osqueryi *"
+    - kind: policy
+      selector: baseline
+- framework: SOC2
+  control: CC8.1
+  title: Change management
+- framework: ISO27001
+  control: A.8.15
+  evidence:
+    - kind: resource
+      selector: "*/siem/*"
+"#;
+
+    #[test]
+    fn test_compliance_report() -> Result<()> {
+        let mut dbc = DbConn::new(":memory:", 0)?;
+        dbc.init(None)?.commit()?;
+        seed_rssd(&dbc.conn, SeedProfile::Demo)?;
+        for (name, sql, expect) in [
+            (
+                "has-markdown",
+                "SELECT uri FROM uniform_resource WHERE nature = 'md'",
+                PolicyExpectation::Rows,
+            ),
+            (
+                "no-html",
+                "SELECT uri FROM uniform_resource WHERE nature = 'html'",
+                PolicyExpectation::NoRows,
+            ),
+        ] {
+            store_policy_assertion(
+                &dbc.conn,
+                &PolicyAssertion {
+                    pack: "baseline".to_string(),
+                    name: name.to_string(),
+                    sql: sql.to_string(),
+                    description: None,
+                    rule: PolicyRule {
+                        expect,
+                        severity: PolicySeverity::High,
+                        remediation: None,
+                    },
+                },
+            )?;
+        }
+        assert_eq!(import_controls(&dbc.conn, CATALOG)?, 4);
+        // importing again updates rather than duplicates
+        assert_eq!(import_controls(&dbc.conn, CATALOG)?, 4);
+        assert_eq!(controls(&dbc.conn, &["SOC2".to_string()])?.len(), 3);
+
+        let report = compliance_report(&dbc.conn, &[])?;
+        let status: Vec<_> = report
+            .iter()
+            .flat_map(|f| {
+                f.controls
+                    .iter()
+                    .map(move |c| (f.framework.as_str(), c.control_code.as_str(), c.status))
+            })
+            .collect();
+        assert_eq!(
+            status,
+            vec![
+                ("ISO27001", "A.8.15", ControlStatus::Gap),
+                ("SOC2", "CC6.1", ControlStatus::Satisfied),
+                ("SOC2", "CC7.2", ControlStatus::Failing),
+                ("SOC2", "CC8.1", ControlStatus::Gap),
+            ]
+        );
+        let cc72 = &report[1].controls[1];
+        assert!(cc72.evidence[0].found > 0);
+        assert_eq!(cc72.evidence[1].found, 2);
+        assert_eq!(cc72.evidence[1].failing, vec!["baseline::no-html"]);
+        assert_eq!(
+            (report[1].satisfied, report[1].failing, report[1].gaps),
+            (1, 1, 1)
+        );
+
+        assert_eq!(
+            unmap_evidence(
+                &dbc.conn,
+                "SOC2",
+                "CC7.2",
+                Some((EvidenceKind::Policy, "baseline"))
+            )?,
+            1
+        );
+        let report = compliance_report(&dbc.conn, &["SOC2".to_string()])?;
+        assert_eq!(report.len(), 1);
+        assert_eq!(report[0].controls[1].status, ControlStatus::Satisfied);
+        assert_eq!(unmap_evidence(&dbc.conn, "SOC2", "CC6.1", None)?, 2);
+        Ok(())
+    }
+}
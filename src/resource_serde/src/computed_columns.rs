@@ -0,0 +1,313 @@
+use anyhow::{anyhow, Context, Result};
+use common::query_sql_rows_no_args;
+use rusqlite::{params, Connection, OptionalExtension, Result as RusqliteResult};
+use serde::Serialize;
+
+/// Computed columns are views over `uniform_resource` named with this prefix,
+/// backed by an expression index, rather than generated columns: a generated
+/// column changes the table's shape and `SELECT *` copies (e.g. `admin merge`)
+/// between RSSDs with different computed columns would fail.
+pub const COMPUTED_COLUMN_VIEW_PREFIX: &str = "ur_computed_";
+pub const COMPUTED_COLUMN_INDEX_PREFIX: &str = "idx_ur_computed_";
+
+query_sql_rows_no_args!(
+    computed_column_views,
+    "SELECT v.name AS view_name, v.sql AS view_sql, i.name AS index_name
+       FROM sqlite_master v
+  LEFT JOIN sqlite_master i ON i.type = 'index' AND i.name = 'idx_' || v.name
+      WHERE v.type = 'view' AND v.name LIKE 'ur\\_computed\\_%' ESCAPE '\\'
+   ORDER BY v.name";
+    view_name: String,
+    view_sql: String,
+    index_name: Option<String>
+);
+
+/// An operator-defined column computed from each `uniform_resource` row (of a
+/// nature, if given), e.g. `json_extract(CAST(content AS TEXT), '$.version')`
+/// for `json`; content is stored as a BLOB, which the JSON functions read as
+/// JSONB, hence the cast.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ComputedColumn {
+    pub name: String,
+    pub expression: String,
+    pub nature: Option<String>,
+}
+
+impl ComputedColumn {
+    pub fn view_name(&self) -> String {
+        format!("{COMPUTED_COLUMN_VIEW_PREFIX}{}", self.name)
+    }
+
+    pub fn index_name(&self) -> String {
+        format!("{COMPUTED_COLUMN_INDEX_PREFIX}{}", self.name)
+    }
+
+    fn where_clause(&self) -> String {
+        match &self.nature {
+            Some(nature) => format!(" WHERE nature = {}", sql_literal(nature)),
+            None => String::new(),
+        }
+    }
+
+    // the view's column is written exactly like the indexed expression so the
+    // query planner uses the index for lookups through the view
+    fn view_sql(&self) -> String {
+        format!(
+            "CREATE VIEW \"{}\" AS\n  SELECT uniform_resource_id, device_id, uri, nature, ({}) AS \"{}\"\n    FROM uniform_resource{}",
+            self.view_name(),
+            self.expression,
+            self.name,
+            self.where_clause()
+        )
+    }
+
+    fn index_sql(&self) -> String {
+        format!(
+            "CREATE INDEX \"{}\" ON uniform_resource (({})){}",
+            self.index_name(),
+            self.expression,
+            self.where_clause()
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ComputedColumnDefinition {
+    pub name: String,
+    pub view_name: String,
+    pub indexed: bool,
+    pub sql: String,
+}
+
+fn sql_literal(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "''"))
+}
+
+/// Names become part of view and index identifiers so only plain identifiers
+/// are accepted.
+fn validate_name(name: &str) -> Result<()> {
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "[computed_columns] {name:?} isn't a valid column name, use letters, digits and underscores"
+        ))
+    }
+}
+
+/// Expressions are spliced (in parentheses) into the view's and index's SQL so
+/// they have to be a single expression over a `uniform_resource` row, checked
+/// by preparing a query which selects it the same way; `;` is rejected outright.
+fn validate_expression(conn: &Connection, expression: &str) -> Result<()> {
+    if expression.contains(';') {
+        return Err(anyhow!(
+            "[computed_columns] {expression:?} can't contain `;`, it must be a single expression"
+        ));
+    }
+    let stmt = conn
+        .prepare(&format!(
+            "SELECT ({expression}) FROM uniform_resource LIMIT 0"
+        ))
+        .with_context(|| format!("[computed_columns] {expression:?} isn't a valid expression"))?;
+    if stmt.column_count() != 1 {
+        return Err(anyhow!(
+            "[computed_columns] {expression:?} must be a single expression, not a list"
+        ));
+    }
+    Ok(())
+}
+
+/// Define `column` as a view over `uniform_resource`, indexing its expression
+/// unless `indexed` is false. The expression is validated first, then the index
+/// and view are created in a savepoint which is rolled back when either fails
+/// (e.g. a non-deterministic expression can't be indexed) so nothing is left
+/// behind. Returns the number of rows the column covers.
+pub fn add_computed_column(
+    conn: &Connection,
+    column: &ComputedColumn,
+    indexed: bool,
+) -> Result<usize> {
+    validate_name(&column.name)?;
+    let view_name = column.view_name();
+    let existing: Option<String> = conn
+        .query_row(
+            "SELECT name FROM sqlite_master WHERE name = ?",
+            params![view_name],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if existing.is_some() {
+        return Err(anyhow!(
+            "[computed_columns] {} is already defined, drop it first",
+            column.name
+        ));
+    }
+    validate_expression(conn, &column.expression)?;
+
+    conn.execute_batch("SAVEPOINT computed_column")?;
+    let created = create_computed_column(conn, column, indexed);
+    conn.execute_batch(match created {
+        Ok(_) => "RELEASE computed_column",
+        Err(_) => "ROLLBACK TO computed_column; RELEASE computed_column",
+    })?;
+    created
+}
+
+fn create_computed_column(
+    conn: &Connection,
+    column: &ComputedColumn,
+    indexed: bool,
+) -> Result<usize> {
+    let covered: usize = conn
+        .query_row(
+            &format!(
+                "SELECT COUNT(({})) FROM uniform_resource{}",
+                column.expression,
+                column.where_clause()
+            ),
+            [],
+            |row| row.get(0),
+        )
+        .with_context(|| format!("[computed_columns] evaluating {}", column.expression))?;
+    if indexed {
+        conn.execute_batch(&column.index_sql()).with_context(|| {
+            format!(
+                "[computed_columns] indexing {}, only deterministic expressions can be indexed",
+                column.expression
+            )
+        })?;
+    }
+    conn.execute_batch(&column.view_sql())
+        .with_context(|| format!("[computed_columns] creating view {}", column.view_name()))?;
+    Ok(covered)
+}
+
+/// The computed columns defined in the RSSD.
+pub fn computed_columns(conn: &Connection) -> Result<Vec<ComputedColumnDefinition>> {
+    let mut definitions = Vec::new();
+    computed_column_views(conn, |_, view_name, sql, index_name| {
+        definitions.push(ComputedColumnDefinition {
+            name: view_name[COMPUTED_COLUMN_VIEW_PREFIX.len()..].to_string(),
+            view_name,
+            indexed: index_name.is_some(),
+            sql,
+        });
+        Ok(())
+    })
+    .with_context(|| "[computed_columns] listing views")?;
+    Ok(definitions)
+}
+
+/// Drop the view and index of the computed column `name`, returning whether it
+/// was defined.
+pub fn drop_computed_column(conn: &Connection, name: &str) -> Result<bool> {
+    validate_name(name)?;
+    let defined = computed_columns(conn)?
+        .iter()
+        .any(|definition| definition.name == name);
+    conn.execute_batch(&format!(
+        "DROP INDEX IF EXISTS \"{COMPUTED_COLUMN_INDEX_PREFIX}{name}\";
+         DROP VIEW IF EXISTS \"{COMPUTED_COLUMN_VIEW_PREFIX}{name}\";"
+    ))
+    .with_context(|| format!("[computed_columns] dropping {name}"))?;
+    Ok(defined)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persist::DbConn;
+
+    #[test]
+    fn test_computed_columns() -> Result<()> {
+        let mut dbc = DbConn::new(":memory:", 0)?;
+        let tx = dbc.init(None)?;
+        tx.execute_batch(
+            "INSERT INTO device (device_id, name, state, boundary) VALUES ('D1', 'host', '{}', 'test');
+             INSERT INTO ur_ingest_session (ur_ingest_session_id, device_id, ingest_started_at)
+                  VALUES ('S1', 'D1', CURRENT_TIMESTAMP);
+             INSERT INTO uniform_resource (uniform_resource_id, device_id, ingest_session_id, uri, nature, content, content_digest)
+                  VALUES ('UR1', 'D1', 'S1', 'a.json', 'json', CAST('{\"version\":\"1.2\"}' AS BLOB), 'd1'),
+                         ('UR2', 'D1', 'S1', 'b.json', 'json', CAST('{\"version\":\"2.0\"}' AS BLOB), 'd2'),
+                         ('UR3', 'D1', 'S1', 'c.txt', 'txt', 'not json', 'd3');",
+        )?;
+
+        let version = ComputedColumn {
+            name: "version".to_string(),
+            expression: "json_extract(CAST(content AS TEXT), '$.version')".to_string(),
+            nature: Some("json".to_string()),
+        };
+        assert_eq!(add_computed_column(&tx, &version, true)?, 2);
+        assert!(add_computed_column(&tx, &version, true).is_err());
+
+        let uri: String = tx.query_row(
+            "SELECT uri FROM ur_computed_version WHERE version = '2.0'",
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(uri, "b.json");
+        let plan: String = tx.query_row(
+            "EXPLAIN QUERY PLAN SELECT uri FROM ur_computed_version WHERE version = '2.0'",
+            [],
+            |row| row.get(3),
+        )?;
+        assert!(plan.contains("idx_ur_computed_version"), "{plan}");
+
+        let bad_name = ComputedColumn {
+            name: "version; DROP TABLE device".to_string(),
+            ..version.clone()
+        };
+        assert!(add_computed_column(&tx, &bad_name, true).is_err());
+        let bad_expression = ComputedColumn {
+            name: "broken".to_string(),
+            expression: "json_extract(content, '$.version'".to_string(),
+            nature: None,
+        };
+        assert!(add_computed_column(&tx, &bad_expression, true).is_err());
+        let non_deterministic = ComputedColumn {
+            name: "sampled".to_string(),
+            expression: "random()".to_string(),
+            nature: None,
+        };
+        assert!(add_computed_column(&tx, &non_deterministic, true).is_err());
+        for expression in [
+            "uri; DROP TABLE device",
+            "uri, nature",
+            "uri) AS x, (nature",
+            "uri -- comment",
+            "no_such_column",
+        ] {
+            let injected = ComputedColumn {
+                name: "injected".to_string(),
+                expression: expression.to_string(),
+                nature: None,
+            };
+            assert!(
+                add_computed_column(&tx, &injected, true).is_err(),
+                "{expression}"
+            );
+        }
+        // nothing is left behind by the definitions which failed
+        let indexes: usize = tx.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE name LIKE 'idx_ur_computed_%'",
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(indexes, 1);
+
+        let definitions = computed_columns(&tx)?;
+        assert_eq!(definitions.len(), 1);
+        assert_eq!(definitions[0].name, "version");
+        assert!(definitions[0].indexed);
+
+        assert!(drop_computed_column(&tx, "version")?);
+        assert!(!drop_computed_column(&tx, "version")?);
+        assert!(computed_columns(&tx)?.is_empty());
+        Ok(())
+    }
+}
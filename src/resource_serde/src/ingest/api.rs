@@ -0,0 +1,647 @@
+use std::collections::{BTreeMap, HashSet};
+use std::io::BufRead;
+
+use anyhow::{anyhow, Context, Result};
+use common::secret::Secret;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE, LAST_MODIFIED, LINK};
+use reqwest::Url;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JsonValue};
+use tracing::{debug, error};
+
+use super::uris::{content_value, sha1_hex};
+use super::{INS_UR_INGEST_SESSION_FINISH_SQL, INS_UR_INGEST_SESSION_SQL};
+use crate::cmd::IngestApiArgs;
+use crate::persist::*;
+
+const INS_API_UR_SQL: &str = "
+    INSERT INTO uniform_resource (uniform_resource_id, device_id, ingest_session_id, uri, nature, content, content_digest, size_bytes, last_modified_at, elaboration)
+                          VALUES (surveilr_pk(), ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                       RETURNING uniform_resource_id";
+
+// a page answered the same by an earlier session isn't recorded again
+const SEL_API_UR_SQL: &str = "
+    SELECT uniform_resource_id FROM uniform_resource
+     WHERE device_id = ? AND uri = ? AND content_digest = ?";
+
+/// A REST API endpoint whose responses are ingested, given on the command line
+/// or as a line of JSON on STDIN.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApiEndpoint {
+    pub url: String,
+    /// request headers, whose values may be secret references (`env://NAME`,
+    /// `keyring://service/account`, `vault://path#key`)
+    #[serde(default)]
+    pub headers: BTreeMap<String, Secret>,
+    /// JSON pointer to the next page's URL in the response, e.g. `/links/next`
+    pub next_path: Option<String>,
+    /// JSON pointer to the next page's cursor in the response, e.g.
+    /// `/meta/next_cursor`
+    pub cursor_path: Option<String>,
+    /// the query parameter the cursor is passed as (default `cursor`)
+    pub cursor_param: Option<String>,
+    /// the most pages read
+    pub max_pages: Option<usize>,
+}
+
+/// A response of an endpoint, stored as a uniform resource.
+#[derive(Debug)]
+struct ApiPage {
+    url: Url,
+    number: usize,
+    content_type: Option<String>,
+    last_modified_at: Option<String>,
+    content: Vec<u8>,
+    next: Option<Url>,
+}
+
+impl IngestApiArgs {
+    /// The URLs given as arguments followed by the endpoints on STDIN, with
+    /// the arguments' headers and pagination unless a line has its own.
+    pub fn endpoints(&self) -> Result<Vec<ApiEndpoint>> {
+        let headers = self
+            .header
+            .iter()
+            .map(|header| {
+                let (name, value) = header.expose().split_once(':').ok_or_else(|| {
+                    anyhow!("[IngestApiArgs::endpoints] expected `Name: value` headers")
+                })?;
+                Ok((name.trim().to_string(), Secret::from(value.trim())))
+            })
+            .collect::<Result<BTreeMap<_, _>>>()?;
+        let mut endpoints: Vec<ApiEndpoint> = self
+            .urls
+            .iter()
+            .map(|url| ApiEndpoint {
+                url: url.clone(),
+                headers: headers.clone(),
+                next_path: self.next_path.clone(),
+                cursor_path: self.cursor_path.clone(),
+                ..Default::default()
+            })
+            .collect();
+        if self.stdin {
+            endpoints.extend(read_endpoints(std::io::stdin().lock())?);
+        }
+        for endpoint in &mut endpoints {
+            endpoint
+                .cursor_param
+                .get_or_insert_with(|| self.cursor_param.clone());
+            endpoint.max_pages.get_or_insert(self.max_pages);
+        }
+        Ok(endpoints)
+    }
+}
+
+/// Endpoints, one JSON object per line like `{"url": "https://...", "headers":
+/// {"Authorization": "env://API_TOKEN"}, "next_path": "/links/next"}`.
+fn read_endpoints(lines: impl BufRead) -> Result<Vec<ApiEndpoint>> {
+    let mut endpoints = Vec::new();
+    for (idx, line) in lines.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        endpoints.push(
+            serde_json::from_str(line)
+                .with_context(|| format!("[read_endpoints] line {}: {line}", idx + 1))?,
+        );
+    }
+    Ok(endpoints)
+}
+
+/// The nature of a response from its `Content-Type`, e.g. `json` for
+/// `application/json` and `application/vnd.github+json`. Types without a
+/// nature of their own are kept as the nature.
+fn content_type_nature(content_type: Option<&str>, content: &[u8]) -> String {
+    let mime = content_type
+        .and_then(|content_type| content_type.split(';').next())
+        .map(|mime| mime.trim().to_lowercase())
+        .unwrap_or_default();
+    let subtype = mime.split_once('/').map_or("", |(_, subtype)| subtype);
+    let nature = match subtype {
+        "" if serde_json::from_slice::<JsonValue>(content).is_ok() => "json",
+        "" if std::str::from_utf8(content).is_ok() => "txt",
+        "" => "application/octet-stream",
+        "json" => "json",
+        subtype if subtype.ends_with("+json") => "json",
+        "x-ndjson" | "jsonl" => "jsonl",
+        "xml" => "xml",
+        subtype if subtype.ends_with("+xml") => "xml",
+        "html" => "html",
+        "csv" => "csv",
+        "markdown" => "md",
+        "yaml" | "x-yaml" => "yml",
+        "toml" => "toml",
+        "pdf" => "pdf",
+        "plain" => "txt",
+        _ => &mime,
+    };
+    nature.to_string()
+}
+
+/// The target of the `rel="next"` link of a `Link` header, e.g.
+/// `<https://api.example.com/items?page=2>; rel="next", <...>; rel="last"`.
+fn link_next(link: &str) -> Option<String> {
+    link.split(',').find_map(|link| {
+        let (target, link_params) = link.split_once(';')?;
+        let next = link_params
+            .split(';')
+            .filter_map(|param| param.split_once('='))
+            .any(|(name, value)| {
+                name.trim().eq_ignore_ascii_case("rel")
+                    && value
+                        .trim()
+                        .trim_matches('"')
+                        .split_whitespace()
+                        .any(|rel| rel.eq_ignore_ascii_case("next"))
+            });
+        next.then(|| {
+            target
+                .trim()
+                .trim_start_matches('<')
+                .trim_end_matches('>')
+                .to_string()
+        })
+    })
+}
+
+/// The page after `url`: the `Link` header's `rel="next"`, else the URL or
+/// the cursor at the endpoint's JSON pointers in the response.
+fn next_page(
+    url: &Url,
+    headers: &HeaderMap,
+    content: &[u8],
+    endpoint: &ApiEndpoint,
+) -> Result<Option<Url>> {
+    if let Some(next) = headers
+        .get_all(LINK)
+        .iter()
+        .filter_map(|link| link.to_str().ok())
+        .find_map(link_next)
+    {
+        return Ok(Some(url.join(&next)?));
+    }
+    if endpoint.next_path.is_none() && endpoint.cursor_path.is_none() {
+        return Ok(None);
+    }
+    let Ok(json) = serde_json::from_slice::<JsonValue>(content) else {
+        return Ok(None);
+    };
+    if let Some(next) = endpoint
+        .next_path
+        .as_deref()
+        .and_then(|pointer| json.pointer(pointer))
+        .and_then(JsonValue::as_str)
+        .filter(|next| !next.is_empty())
+    {
+        return Ok(Some(url.join(next)?));
+    }
+    let cursor = match endpoint
+        .cursor_path
+        .as_deref()
+        .and_then(|pointer| json.pointer(pointer))
+    {
+        Some(JsonValue::String(cursor)) if !cursor.is_empty() => cursor.clone(),
+        Some(JsonValue::Number(cursor)) => cursor.to_string(),
+        _ => return Ok(None),
+    };
+    let param = endpoint.cursor_param.as_deref().unwrap_or("cursor");
+    let query: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(name, _)| name != param)
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect();
+    let mut next = url.clone();
+    next.query_pairs_mut()
+        .clear()
+        .extend_pairs(query)
+        .append_pair(param, &cursor);
+    Ok(Some(next))
+}
+
+/// The endpoint's headers with their secret references resolved.
+fn request_headers(endpoint: &ApiEndpoint) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    for (name, value) in &endpoint.headers {
+        let value = value.resolve()?;
+        let mut value = HeaderValue::from_str(value.expose())
+            .with_context(|| format!("[request_headers] invalid value of {name}"))?;
+        value.set_sensitive(true);
+        headers.insert(HeaderName::from_bytes(name.as_bytes())?, value);
+    }
+    Ok(headers)
+}
+
+/// Follows redirects which stay on the origin they started from, the headers
+/// (credentials) of an endpoint are only ever sent to the endpoint's origin.
+fn same_origin_redirects() -> reqwest::redirect::Policy {
+    reqwest::redirect::Policy::custom(|attempt| {
+        let origin = attempt.previous().first().map(Url::origin);
+        if attempt.previous().len() > 10 {
+            attempt.error("too many redirects")
+        } else if origin.is_some_and(|origin| origin != attempt.url().origin()) {
+            let error = format!("redirected to another origin ({})", attempt.url());
+            attempt.error(error)
+        } else {
+            attempt.follow()
+        }
+    })
+}
+
+/// The body of `response`, failing once it's larger than `max_size` bytes
+/// instead of reading all of it in memory.
+async fn read_page(mut response: reqwest::Response, max_size: u64) -> Result<Vec<u8>> {
+    let too_large = || anyhow!("[read_page] larger than --max-page-size {max_size}");
+    if response.content_length().is_some_and(|len| len > max_size) {
+        return Err(too_large());
+    }
+    let mut content = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if (content.len() + chunk.len()) as u64 > max_size {
+            return Err(too_large());
+        }
+        content.extend_from_slice(&chunk);
+    }
+    Ok(content)
+}
+
+/// `GET` the endpoint's pages, following its pagination, until there's no
+/// next page, a page links back to one already read or `max_pages` is reached.
+/// Pagination stays on the endpoint's origin so its headers aren't sent
+/// elsewhere. `on_page` receives the pages as they're read.
+async fn fetch_pages(
+    client: &reqwest::Client,
+    endpoint: &ApiEndpoint,
+    max_page_size: u64,
+    mut on_page: impl FnMut(ApiPage),
+) -> Result<()> {
+    let headers = request_headers(endpoint)?;
+    let mut url = Url::parse(&endpoint.url)
+        .with_context(|| format!("[fetch_pages] invalid URL {}", endpoint.url))?;
+    let origin = url.origin();
+    let mut read = HashSet::new();
+    for number in 1..=endpoint.max_pages.unwrap_or(usize::MAX) {
+        if !read.insert(url.clone()) {
+            break;
+        }
+        if url.origin() != origin {
+            return Err(anyhow!(
+                "[fetch_pages] the next page {url} isn't on the origin of {}",
+                endpoint.url
+            ));
+        }
+        let response = client
+            .get(url.clone())
+            .headers(headers.clone())
+            .send()
+            .await
+            .with_context(|| format!("[fetch_pages] GET {url}"))?
+            .error_for_status()
+            .with_context(|| format!("[fetch_pages] GET {url}"))?;
+        let response_headers = response.headers().clone();
+        let content = read_page(response, max_page_size)
+            .await
+            .with_context(|| format!("[fetch_pages] reading {url}"))?;
+        let header = |name| {
+            response_headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let next = next_page(&url, &response_headers, &content, endpoint)?;
+        on_page(ApiPage {
+            url: url.clone(),
+            number,
+            content_type: header(CONTENT_TYPE),
+            last_modified_at: header(LAST_MODIFIED)
+                .and_then(|date| chrono::DateTime::parse_from_rfc2822(&date).ok())
+                .map(|date| date.with_timezone(&chrono::Utc).to_string()),
+            content,
+            next: next.clone(),
+        });
+        match next {
+            Some(next) => url = next,
+            None => break,
+        }
+    }
+    Ok(())
+}
+
+/// Insert a page unless an earlier session stored the same response. Returns
+/// whether it was inserted.
+fn insert_api_page(
+    conn: &Connection,
+    device_id: &str,
+    ingest_session_id: &str,
+    endpoint: &ApiEndpoint,
+    page: ApiPage,
+) -> Result<bool> {
+    let uri = page.url.to_string();
+    let digest = sha1_hex(&page.content);
+    let existing: Option<String> = conn
+        .query_row(SEL_API_UR_SQL, params![device_id, uri, digest], |row| {
+            row.get(0)
+        })
+        .optional()?;
+    if existing.is_some() {
+        return Ok(false);
+    }
+    let nature = content_type_nature(page.content_type.as_deref(), &page.content);
+    let elaboration = json!({
+        "api": {
+            "endpoint": endpoint.url,
+            "page": page.number,
+            "content_type": page.content_type,
+            "next": page.next.as_ref().map(Url::to_string),
+        }
+    })
+    .to_string();
+    let size_bytes = page.content.len();
+    conn.query_row(
+        INS_API_UR_SQL,
+        params![
+            device_id,
+            ingest_session_id,
+            uri,
+            nature,
+            content_value(page.content),
+            digest,
+            size_bytes,
+            page.last_modified_at,
+            elaboration,
+        ],
+        |row| row.get::<_, String>(0),
+    )
+    .with_context(|| format!("[insert_api_page] {uri}"))?;
+    Ok(true)
+}
+
+/// Store the responses of HTTP/REST APIs (e.g. the SaaS services compliance
+/// evidence comes from) as uniform resources, one per page, following `Link`
+/// headers and the next-page URLs or cursors in JSON responses.
+pub async fn ingest_api(args: &IngestApiArgs) -> Result<String> {
+    let endpoints = args.endpoints()?;
+
+    // the pages are all read before the database is written so that no
+    // transaction is held while waiting on the network
+    let client = reqwest::Client::builder()
+        .user_agent(concat!("surveilr/", env!("CARGO_PKG_VERSION")))
+        .redirect(same_origin_redirects())
+        .build()?;
+    let mut fetched = Vec::with_capacity(endpoints.len());
+    for endpoint in &endpoints {
+        let mut pages = Vec::new();
+        // the pages read before an error are still stored
+        let result = fetch_pages(&client, endpoint, args.max_page_size, |page| {
+            pages.push(page)
+        })
+        .await;
+        fetched.push((endpoint, pages, result));
+    }
+
+    let mut dbc = DbConn::new(&args.state_db_fs_path, 0).with_context(|| {
+        format!(
+            "[ingest_api] SQLite transaction in {}",
+            args.state_db_fs_path
+        )
+    })?;
+    let db_fs_path = dbc.db_fs_path.clone();
+    let tx = dbc.init(Some(&args.state_db_init_sql))?;
+    let (device_id, _device_name) = upserted_device(&tx, &common::DEVICE)
+        .with_context(|| format!("[ingest_api] upserted_device in {}", db_fs_path))?;
+
+    // the headers' values are redacted
+    let behavior = json!({ "api": { "endpoints": endpoints } }).to_string();
+    let ingest_session_id: String = tx
+        .query_row(
+            INS_UR_INGEST_SESSION_SQL,
+            params![device_id, None::<String>, behavior],
+            |row| row.get(0),
+        )
+        .with_context(|| format!("[ingest_api] inserting ingest session in {}", db_fs_path))?;
+    debug!("API Session: {ingest_session_id}");
+
+    let (mut pages, mut unchanged) = (0, 0);
+    let mut failed = Vec::new();
+    for (endpoint, endpoint_pages, result) in fetched {
+        for page in endpoint_pages {
+            match insert_api_page(&tx, &device_id, &ingest_session_id, endpoint, page)? {
+                true => pages += 1,
+                false => unchanged += 1,
+            }
+        }
+        if let Err(err) = result {
+            error!("[ingest_api] {} in {}: {:#}", endpoint.url, db_fs_path, err);
+            failed.push(json!({ "url": endpoint.url, "error": format!("{:#}", err) }));
+        }
+    }
+
+    let session_elaboration = json!({
+        "api": {
+            "pages": pages,
+            "unchanged": unchanged,
+            "failed": failed,
+        }
+    })
+    .to_string();
+    tx.execute(
+        INS_UR_INGEST_SESSION_FINISH_SQL,
+        params![ingest_session_id, session_elaboration],
+    )
+    .with_context(|| format!("[ingest_api] finishing session in {}", db_fs_path))?;
+    tx.commit().with_context(|| {
+        format!(
+            "[ingest_api] unable to perform final commit in {}",
+            db_fs_path
+        )
+    })?;
+    Ok(ingest_session_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follow_pagination() -> Result<()> {
+        let url = Url::parse("https://api.example.com/v1/findings?state=open&cursor=a1")?;
+        let endpoint = ApiEndpoint {
+            url: url.to_string(),
+            next_path: Some("/links/next".to_string()),
+            cursor_path: Some("/meta/next_cursor".to_string()),
+            ..Default::default()
+        };
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            LINK,
+            HeaderValue::from_static(
+                r#"<https://api.example.com/v1/findings?page=1>; rel="prev", </v1/findings?page=3>; rel="next""#,
+            ),
+        );
+        assert_eq!(
+            next_page(&url, &headers, b"[]", &endpoint)?.map(String::from),
+            Some("https://api.example.com/v1/findings?page=3".to_string())
+        );
+
+        let headers = HeaderMap::new();
+        let json =
+            br#"{"links": {"next": "?state=open&cursor=b2"}, "meta": {"next_cursor": "zz"}}"#;
+        assert_eq!(
+            next_page(&url, &headers, json, &endpoint)?.map(String::from),
+            Some("https://api.example.com/v1/findings?state=open&cursor=b2".to_string())
+        );
+        let json = br#"{"links": {"next": null}, "meta": {"next_cursor": "b 2"}}"#;
+        assert_eq!(
+            next_page(&url, &headers, json, &endpoint)?.map(String::from),
+            Some("https://api.example.com/v1/findings?state=open&cursor=b+2".to_string())
+        );
+        let json = br#"{"links": {"next": ""}, "meta": {"next_cursor": null}}"#;
+        assert_eq!(next_page(&url, &headers, json, &endpoint)?, None);
+        Ok(())
+    }
+
+    /// Answers `/big` with more than 100 bytes, `/p1` with a next page on
+    /// another origin (`localhost` instead of `127.0.0.1`) and `/moved` with a
+    /// redirect to it.
+    async fn serve_pages() -> Result<std::net::SocketAddr> {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = vec![0; 1024];
+                let Ok(read) = stream.read(&mut request).await else {
+                    continue;
+                };
+                let request = String::from_utf8_lossy(&request[..read]).to_string();
+                let path = request.split_whitespace().nth(1).unwrap_or_default();
+                let elsewhere = format!("http://localhost:{}/p2", addr.port());
+                let response = match path {
+                    "/big" => format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nConnection: close\r\n\r\n{}",
+                        "x".repeat(1000)
+                    ),
+                    "/p1" => format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 2\r\nLink: <{elsewhere}>; rel=\"next\"\r\nConnection: close\r\n\r\n[]"
+                    ),
+                    _ => format!(
+                        "HTTP/1.1 302 Found\r\nLocation: {elsewhere}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                    ),
+                };
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        Ok(addr)
+    }
+
+    #[tokio::test]
+    async fn pages_stay_on_the_origin_and_are_bounded() -> Result<()> {
+        let addr = serve_pages().await?;
+        let client = reqwest::Client::builder()
+            .redirect(same_origin_redirects())
+            .build()?;
+        let endpoint = |path: &str| ApiEndpoint {
+            url: format!("http://{addr}{path}"),
+            headers: BTreeMap::from([("X-Api-Key".to_string(), Secret::from("s3cr3t"))]),
+            ..Default::default()
+        };
+
+        let mut pages = Vec::new();
+        let fetched = fetch_pages(&client, &endpoint("/p1"), 100, |page| pages.push(page)).await;
+        assert!(format!("{:#}", fetched.unwrap_err()).contains("isn't on the origin"));
+        assert_eq!(pages.len(), 1);
+
+        let fetched = fetch_pages(&client, &endpoint("/moved"), 100, |_| {}).await;
+        assert!(format!("{:#}", fetched.unwrap_err()).contains("another origin"));
+
+        let fetched = fetch_pages(&client, &endpoint("/big"), 100, |_| {}).await;
+        assert!(format!("{:#}", fetched.unwrap_err()).contains("--max-page-size 100"));
+        assert!(fetch_pages(&client, &endpoint("/big"), 1000, |_| {})
+            .await
+            .is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn natures_and_endpoints() -> Result<()> {
+        let nature = |content_type| content_type_nature(content_type, b"{}");
+        assert_eq!(nature(Some("application/json; charset=utf-8")), "json");
+        assert_eq!(nature(Some("application/vnd.github+json")), "json");
+        assert_eq!(nature(Some("text/csv")), "csv");
+        assert_eq!(nature(Some("application/atom+xml")), "xml");
+        assert_eq!(nature(Some("application/zip")), "application/zip");
+        assert_eq!(nature(None), "json");
+        assert_eq!(content_type_nature(None, b"ok"), "txt");
+
+        let endpoints = read_endpoints(
+            r#"# SaaS evidence
+{"url": "https://api.example.com/users", "headers": {"Authorization": "env://API_TOKEN"}, "cursor_path": "/next"}
+
+{"url": "https://api.example.com/groups", "max_pages": 2}"#
+                .as_bytes(),
+        )?;
+        assert_eq!(endpoints.len(), 2);
+        assert_eq!(
+            endpoints[0].headers["Authorization"].expose(),
+            "env://API_TOKEN"
+        );
+        assert_eq!(endpoints[1].max_pages, Some(2));
+        // the secrets aren't recorded in the session's behavior
+        assert!(!json!(endpoints).to_string().contains("API_TOKEN"));
+        assert!(read_endpoints(r#"{"uri": "https://api.example.com"}"#.as_bytes()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn unchanged_pages_are_stored_once() -> Result<()> {
+        let mut dbc = DbConn::new(":memory:", 0)?;
+        let tx = dbc.init(None)?;
+        let (device_id, _) = upserted_device(&tx, &common::DEVICE)?;
+        let session_id: String = tx.query_row(
+            INS_UR_INGEST_SESSION_SQL,
+            params![device_id, None::<String>, "{}"],
+            |row| row.get(0),
+        )?;
+        let endpoint = ApiEndpoint {
+            url: "https://api.example.com/users".to_string(),
+            ..Default::default()
+        };
+        let page = || ApiPage {
+            url: Url::parse("https://api.example.com/users?page=2").unwrap(),
+            number: 2,
+            content_type: Some("application/json".to_string()),
+            last_modified_at: None,
+            content: br#"[{"login": "jane"}]"#.to_vec(),
+            next: None,
+        };
+        assert!(insert_api_page(
+            &tx,
+            &device_id,
+            &session_id,
+            &endpoint,
+            page()
+        )?);
+        assert!(!insert_api_page(
+            &tx,
+            &device_id,
+            &session_id,
+            &endpoint,
+            page()
+        )?);
+        let (nature, login, page_number): (String, String, usize) = tx.query_row(
+            "SELECT nature, content ->> '$[0].login', elaboration ->> '$.api.page' FROM uniform_resource",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+        assert_eq!(
+            (nature.as_str(), login.as_str(), page_number),
+            ("json", "jane", 2)
+        );
+        Ok(())
+    }
+}
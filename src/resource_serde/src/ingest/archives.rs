@@ -0,0 +1,145 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use resource::archive::{expand_archive, ArchiveMember, ArchivePolicy, ArchiveSummary};
+use resource::digest::DigestAlgorithm;
+use rusqlite::{params, types::Value};
+use serde_json::json;
+
+use super::{insert_lineage, IngestContext};
+
+/// Where the members of an archive go: next to the archive's own resource,
+/// with paths nested under the archive's paths.
+pub struct ArchiveTarget<'a> {
+    pub device_id: &'a str,
+    pub ingest_session_id: &'a str,
+    pub ingest_fs_path_id: &'a str,
+    pub archive_ur_id: &'a str,
+    pub file_path_abs: &'a str,
+    pub file_path_rel: &'a str,
+    pub digest: DigestAlgorithm,
+}
+
+/// Store every member of the zip, tar or tar.gz archive as a uniform resource with the URI
+/// `<archive>!/<member>`, linked to the archive through lineage, plus a file
+/// system entry. Members which aren't expanded (encrypted without a working
+/// password, too large, unreadable) only get a `SKIPPED` entry whose
+/// `ur_diagnostics` tells why. Archives nested in archives aren't expanded.
+pub fn ingest_archive_members(
+    ingest_stmts: &mut IngestContext<'_>,
+    target: &ArchiveTarget<'_>,
+    policy: &ArchivePolicy,
+) -> Result<ArchiveSummary> {
+    expand_archive(Path::new(target.file_path_abs), policy, |member| {
+        insert_member(ingest_stmts, target, member)
+    })
+    .with_context(|| format!("[ingest_archive_members] {}", target.file_path_abs))
+}
+
+fn insert_member(
+    ingest_stmts: &mut IngestContext<'_>,
+    target: &ArchiveTarget<'_>,
+    member: ArchiveMember,
+) -> Result<()> {
+    let uri = format!("{}!/{}", target.file_path_abs, member.name);
+    let member_path = Path::new(&member.name);
+    let basename = member_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| member.name.clone());
+    let extn = member_path
+        .extension()
+        .map(|extn| extn.to_string_lossy().to_lowercase());
+    let rel_parent = match member_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => {
+            format!("{}!/{}", target.file_path_rel, parent.display())
+        }
+        _ => format!("{}!", target.file_path_rel),
+    };
+    let elaboration = json!({
+        "archive": {
+            "uniform_resource_id": target.archive_ur_id,
+            "member": member.name,
+            "encrypted": member.encrypted,
+        }
+    })
+    .to_string();
+
+    let (ur_id, ur_status, ur_diagnostics) = match member.content {
+        Ok(content) => {
+            let digest = target.digest.hex_digest(&content);
+            let size = content.len();
+            // text is stored as text so it can be queried like walked files
+            let content = match String::from_utf8(content) {
+                Ok(text) => Value::Text(text),
+                Err(err) => Value::Blob(err.into_bytes()),
+            };
+            let ur_id: String = ingest_stmts
+                .ins_ur_stmt
+                .query_row(
+                    params![
+                        target.device_id,
+                        target.ingest_session_id,
+                        target.ingest_fs_path_id,
+                        uri,
+                        extn.clone().unwrap_or_else(|| "bin".to_string()),
+                        content,
+                        digest,
+                        size,
+                        member.last_modified_at,
+                        &None::<String>, // content_fm_body_attrs
+                        &None::<String>, // frontmatter
+                        &None::<String>, // ingest_imap_acct_folder_id
+                    ],
+                    |row| row.get(0),
+                )
+                .with_context(|| format!("[ingest_archive_members] insert {uri}"))?;
+            (Some(ur_id), None, None)
+        }
+        Err(skip) => (
+            None,
+            Some("SKIPPED"),
+            Some(serde_json::to_string_pretty(&json!({
+                "instance": "archive::MemberSkip",
+                "message": format!("archive member {} not expanded", member.name),
+                "archive": target.file_path_abs,
+                "size_bytes": member.size_bytes,
+                "skip": skip,
+            }))?),
+        ),
+    };
+
+    let entry_id: String = ingest_stmts
+        .ins_ur_isfsp_entry_stmt
+        .query_row(
+            params![
+                target.ingest_session_id,
+                target.ingest_fs_path_id,
+                ur_id,
+                uri,
+                rel_parent,
+                format!("{}!/{}", target.file_path_rel, member.name),
+                basename,
+                extn.unwrap_or_default(),
+                ur_status,
+                ur_diagnostics,
+                &None::<String>, // captured_executable
+                elaboration,
+            ],
+            |row| row.get(0),
+        )
+        .with_context(|| format!("[ingest_archive_members] file system entry of {uri}"))?;
+
+    if let Some(ur_id) = &ur_id {
+        insert_lineage(
+            ingest_stmts,
+            target.ingest_session_id,
+            Some(&target.archive_ur_id.to_string()),
+            ur_id,
+            Some(&entry_id),
+            None,
+        )
+        .with_context(|| format!("[ingest_archive_members] lineage of {uri}"))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use serde_json::Value;
+
+/// The canonical form of a JSON document: object keys sorted and no
+/// insignificant whitespace, so semantically identical documents get the same
+/// content digest. `None` when `text` isn't valid JSON.
+pub fn canonicalize_json(text: &str) -> Option<String> {
+    let value: Value = serde_json::from_str(text).ok()?;
+    let mut canonical = String::with_capacity(text.len());
+    write_canonical(&value, &mut canonical);
+    Some(canonical)
+}
+
+// keys are sorted explicitly since serde_json keeps insertion order when any
+// crate in the build enables its `preserve_order` feature
+fn write_canonical(value: &Value, out: &mut String) {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by_key(|(key, _)| *key);
+            out.push('{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::String(key.clone()).to_string());
+                out.push(':');
+                write_canonical(value, out);
+            }
+            out.push('}');
+        }
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        }
+        scalar => out.push_str(&scalar.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonicalize_json() {
+        let a = canonicalize_json(r#"{ "b": [1, {"y": true, "x": null}], "a": "é\n" }"#).unwrap();
+        let b =
+            canonicalize_json("{\"a\":\"é\\n\",\n  \"b\":[1,{\"x\":null,\"y\":true}]}").unwrap();
+        assert_eq!(a, r#"{"a":"é\n","b":[1,{"x":null,"y":true}]}"#);
+        assert_eq!(a, b);
+        // array order is significant
+        assert_ne!(
+            canonicalize_json("[1, 2]").unwrap(),
+            canonicalize_json("[2, 1]").unwrap()
+        );
+        assert!(canonicalize_json("{ not json").is_none());
+    }
+}
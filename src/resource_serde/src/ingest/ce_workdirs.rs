@@ -0,0 +1,100 @@
+use std::cell::RefCell;
+use std::path::PathBuf;
+
+use tempfile::TempDir;
+use tracing::{info, warn};
+
+/// The temporary work directories of the capturable executables of an ingest
+/// session, so scripts which write intermediate files don't write them into
+/// the walked tree (where a later session would ingest them). Each capturable
+/// executable gets its own empty directory under a session root which is only
+/// created when the first one runs and is removed with all its directories
+/// when the session drops it, unless they're kept (`--keep-ce-workdirs`).
+#[derive(Debug)]
+pub struct CeWorkdirs {
+    ingest_session_id: String,
+    keep: bool,
+    root: RefCell<Option<TempDir>>,
+}
+
+impl CeWorkdirs {
+    pub fn new(ingest_session_id: &str, keep: bool) -> CeWorkdirs {
+        CeWorkdirs {
+            ingest_session_id: ingest_session_id.to_string(),
+            keep,
+            root: RefCell::new(None),
+        }
+    }
+
+    /// A new empty directory for one capturable executable.
+    pub fn create(&self) -> std::io::Result<PathBuf> {
+        let mut root = self.root.borrow_mut();
+        if root.is_none() {
+            *root = Some(
+                tempfile::Builder::new()
+                    .prefix(&format!("surveilr-ce-{}-", self.ingest_session_id))
+                    .tempdir()?,
+            );
+        }
+        let workdir = tempfile::Builder::new()
+            .prefix("ce-")
+            .tempdir_in(root.as_ref().unwrap().path())?;
+        // removed along with the root
+        Ok(workdir.into_path())
+    }
+
+    /// The session root, if a capturable executable ran.
+    pub fn root(&self) -> Option<PathBuf> {
+        self.root
+            .borrow()
+            .as_ref()
+            .map(|root| root.path().to_path_buf())
+    }
+}
+
+impl Drop for CeWorkdirs {
+    fn drop(&mut self) {
+        let Some(root) = self.root.take() else {
+            return;
+        };
+        if self.keep {
+            // outside of info! so the directories are kept even when it's disabled
+            let path = root.into_path();
+            info!("kept capturable executable workdirs in {}", path.display());
+            return;
+        }
+        let path = root.path().display().to_string();
+        if let Err(err) = root.close() {
+            warn!(
+                "unable to remove capturable executable workdirs {}: {}",
+                path, err
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ce_workdirs() -> std::io::Result<()> {
+        let workdirs = CeWorkdirs::new("session", false);
+        assert_eq!(workdirs.root(), None);
+        let first = workdirs.create()?;
+        let second = workdirs.create()?;
+        assert_ne!(first, second);
+        let root = workdirs.root().unwrap();
+        assert!(first.starts_with(&root) && second.starts_with(&root));
+        std::fs::write(first.join("intermediate.json"), "{}")?;
+        drop(workdirs);
+        assert!(!root.exists());
+
+        let workdirs = CeWorkdirs::new("session", true);
+        let kept = workdirs.create()?;
+        let root = workdirs.root().unwrap();
+        drop(workdirs);
+        assert!(kept.is_dir());
+        std::fs::remove_dir_all(root)
+    }
+}
@@ -0,0 +1,143 @@
+use std::io::{Read, Write};
+
+use anyhow::{anyhow, Context, Result};
+use indoc::indoc;
+use rusqlite::{params, Connection, OptionalExtension, Statement};
+
+use super::uris::sha1_hex;
+
+pub(super) const INS_UR_CHUNK_SQL: &str = indoc! {"
+        INSERT INTO uniform_resource_chunk (uniform_resource_chunk_id, uniform_resource_id, chunk_index, content_digest, size_bytes, content)
+                                    VALUES (surveilr_pk(), ?, ?, ?, ?, ?)
+                               ON CONFLICT (uniform_resource_id, chunk_index) DO NOTHING"};
+
+pub(super) const SEL_UR_CHUNKED_SIZE_SQL: &str = indoc! {"
+        SELECT SUM(size_bytes) FROM uniform_resource_chunk
+         WHERE uniform_resource_id = ?
+        HAVING COUNT(*) > 0"};
+
+const SEL_UR_CHUNKS_SQL: &str = indoc! {"
+        SELECT chunk_index, content_digest, content FROM uniform_resource_chunk
+         WHERE uniform_resource_id = ?
+      ORDER BY chunk_index"};
+
+/// Store the content read from `reader` as `uniform_resource_chunk` rows of at
+/// most `chunk_size` bytes, returning the number of chunks and bytes stored.
+pub fn insert_content_chunks(
+    ins_ur_chunk_stmt: &mut Statement,
+    uniform_resource_id: &str,
+    reader: &mut dyn Read,
+    chunk_size: usize,
+) -> Result<(usize, u64)> {
+    let mut chunks = 0;
+    let mut size = 0;
+    loop {
+        let mut chunk = Vec::with_capacity(chunk_size);
+        reader
+            .take(chunk_size as u64)
+            .read_to_end(&mut chunk)
+            .with_context(|| {
+                format!("[insert_content_chunks] reading chunk {chunks} of {uniform_resource_id}")
+            })?;
+        if chunk.is_empty() {
+            return Ok((chunks, size));
+        }
+        ins_ur_chunk_stmt
+            .execute(params![
+                uniform_resource_id,
+                chunks,
+                sha1_hex(&chunk),
+                chunk.len(),
+                chunk
+            ])
+            .with_context(|| {
+                format!("[insert_content_chunks] storing chunk {chunks} of {uniform_resource_id}")
+            })?;
+        chunks += 1;
+        size += chunk.len() as u64;
+    }
+}
+
+/// The size of the content stored in chunks for `uniform_resource_id`, `None`
+/// when its content isn't chunked.
+pub fn chunked_content_size(conn: &Connection, uniform_resource_id: &str) -> Result<Option<u64>> {
+    Ok(conn
+        .query_row(SEL_UR_CHUNKED_SIZE_SQL, [uniform_resource_id], |row| {
+            row.get(0)
+        })
+        .optional()?)
+}
+
+/// Reassemble the chunked content of `uniform_resource_id` into `writer`,
+/// checking every chunk's digest. Returns the bytes written or `None` when the
+/// resource's content isn't chunked.
+pub fn write_chunked_content(
+    conn: &Connection,
+    uniform_resource_id: &str,
+    writer: &mut dyn Write,
+) -> Result<Option<u64>> {
+    let mut stmt = conn.prepare(SEL_UR_CHUNKS_SQL)?;
+    let mut rows = stmt.query([uniform_resource_id])?;
+    let mut expected_index = 0;
+    let mut size = 0;
+    while let Some(row) = rows.next()? {
+        let chunk_index: i64 = row.get(0)?;
+        let content_digest: String = row.get(1)?;
+        let content = row.get_ref(2)?.as_blob()?;
+        if chunk_index != expected_index {
+            return Err(anyhow!(
+                "[write_chunked_content] chunk {expected_index} of {uniform_resource_id} is missing"
+            ));
+        }
+        if sha1_hex(content) != content_digest {
+            return Err(anyhow!(
+                "[write_chunked_content] chunk {chunk_index} of {uniform_resource_id} doesn't match its digest {content_digest}"
+            ));
+        }
+        writer.write_all(content)?;
+        expected_index += 1;
+        size += content.len() as u64;
+    }
+    Ok((expected_index > 0).then_some(size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persist::DbConn;
+
+    #[test]
+    fn chunked_content_roundtrip() -> Result<()> {
+        let mut dbc = DbConn::new(":memory:", 0)?;
+        let tx = dbc.init(None)?;
+        tx.execute_batch(indoc! {"
+            INSERT INTO device (device_id, name, state, boundary) VALUES ('D1', 'host', '{}', 'test');
+            INSERT INTO ur_ingest_session (ur_ingest_session_id, device_id, ingest_started_at)
+                 VALUES ('S1', 'D1', CURRENT_TIMESTAMP);
+            INSERT INTO uniform_resource (uniform_resource_id, device_id, ingest_session_id, uri, content_digest, size_bytes)
+                 VALUES ('UR1', 'D1', 'S1', '/evidence/disk.img', 'digest', 2500);"})?;
+
+        let content: Vec<u8> = (0..2500).map(|i| (i % 251) as u8).collect();
+        let mut ins_ur_chunk_stmt = tx.prepare(INS_UR_CHUNK_SQL)?;
+        let stored =
+            insert_content_chunks(&mut ins_ur_chunk_stmt, "UR1", &mut content.as_slice(), 1000)?;
+        assert_eq!(stored, (3, 2500));
+        assert_eq!(chunked_content_size(&tx, "UR1")?, Some(2500));
+        assert_eq!(chunked_content_size(&tx, "UR2")?, None);
+
+        let mut reassembled = Vec::new();
+        assert_eq!(
+            write_chunked_content(&tx, "UR1", &mut reassembled)?,
+            Some(2500)
+        );
+        assert_eq!(reassembled, content);
+        assert_eq!(write_chunked_content(&tx, "UR2", &mut Vec::new())?, None);
+
+        tx.execute(
+            "UPDATE uniform_resource_chunk SET content = x'00' WHERE chunk_index = 1",
+            [],
+        )?;
+        assert!(write_chunked_content(&tx, "UR1", &mut Vec::new()).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,352 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use resource::{
+    EncounterableResourceFlags, EncounterableResourcePathClassifier, FlaggableRegEx,
+    PFRE_READ_NATURE_FROM_REGEX,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+/// The manifest data owners put in a directory to describe how it should be surveilled.
+pub const COLLECT_MANIFEST_FILE_NAME: &str = ".surveilr_collect.yml";
+
+/// Overrides declared in a `.surveilr_collect.yml`, they apply to the manifest's
+/// directory and everything under it. A manifest in a subdirectory adds to the
+/// lists of its parent's and replaces its `max_size_bytes`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CollectManifest {
+    /// natures (file extensions) whose content is acquired
+    pub acquire: Vec<String>,
+    /// regexes, relative to the directory, of files to run as capturable executables
+    pub capture: Vec<String>,
+    /// regexes, relative to the directory, of files to skip
+    pub ignore: Vec<String>,
+    /// larger files are recorded as skipped instead of being stored
+    pub max_size_bytes: Option<u64>,
+    /// labels recorded in the `elaboration` of every walked file
+    pub tags: Vec<String>,
+}
+
+impl CollectManifest {
+    fn merged_into(&self, parent: &CollectManifest) -> CollectManifest {
+        let extend = |parent: &[String], child: &[String]| {
+            let mut merged = parent.to_vec();
+            merged.extend(child.iter().filter(|c| !parent.contains(c)).cloned());
+            merged
+        };
+        CollectManifest {
+            acquire: extend(&parent.acquire, &self.acquire),
+            capture: extend(&parent.capture, &self.capture),
+            ignore: extend(&parent.ignore, &self.ignore),
+            max_size_bytes: self.max_size_bytes.or(parent.max_size_bytes),
+            tags: extend(&parent.tags, &self.tags),
+        }
+    }
+}
+
+/// A manifest found while walking, merged with the manifests of its ancestors.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CollectManifestScope {
+    pub dir: PathBuf,
+    pub manifest_fs_path: PathBuf,
+    pub manifest: CollectManifest,
+}
+
+impl CollectManifestScope {
+    /// What the `ur_ingest_session_fs_path_entry.elaboration` of files in this
+    /// scope holds about it.
+    pub fn entry_elaboration(&self) -> serde_json::Map<String, serde_json::Value> {
+        let mut elaboration = serde_json::Map::new();
+        if !self.manifest.tags.is_empty() {
+            elaboration.insert("collect_manifest".to_string(), json!(self.manifest_fs_path));
+            elaboration.insert("tags".to_string(), json!(self.manifest.tags));
+        }
+        elaboration
+    }
+}
+
+/// Whether a manifest is trusted by `--trust-collect-manifest`: its whole path
+/// has to match one of the `patterns`, so a pattern naming a directory (or any
+/// other part of a path) doesn't trust every manifest under it.
+pub fn trusted_collect_manifests(patterns: &[String]) -> Result<impl Fn(&Path) -> bool> {
+    let patterns = patterns
+        .iter()
+        .map(|pattern| {
+            regex::Regex::new(&format!("^(?:{pattern})$"))
+                .with_context(|| format!("[trusted_collect_manifests] {pattern}"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(move |manifest_fs_path: &Path| {
+        let manifest_fs_path = manifest_fs_path.to_string_lossy();
+        patterns
+            .iter()
+            .any(|pattern| pattern.is_match(&manifest_fs_path))
+    })
+}
+
+/// Every `.surveilr_collect.yml` under a root path, deepest directories first.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CollectManifests {
+    pub scopes: Vec<CollectManifestScope>,
+    /// manifests whose `capture` rules were ignored, they aren't trusted
+    pub untrusted_capture: Vec<PathBuf>,
+}
+
+impl CollectManifests {
+    /// Parse the manifests among the `walked` paths of `root_path` (which should
+    /// be canonical), the entries of the ingestion's own walk so they're found in
+    /// the directories it ingests and nowhere else. `capture` rules run the files
+    /// they match, they're only kept for the manifests `trusted` accepts and the
+    /// others are listed in `untrusted_capture`.
+    pub fn from_walked<P: AsRef<Path>>(
+        root_path: &Path,
+        walked: impl IntoIterator<Item = P>,
+        trusted: impl Fn(&Path) -> bool,
+    ) -> Result<CollectManifests> {
+        let mut manifest_fs_paths: Vec<PathBuf> = walked
+            .into_iter()
+            .map(|path| path.as_ref().to_path_buf())
+            .filter(|path| {
+                path.file_name()
+                    .is_some_and(|name| name == COLLECT_MANIFEST_FILE_NAME)
+                    && path.is_file()
+            })
+            .collect();
+        // parents before their children so ancestors are already merged
+        manifest_fs_paths.sort_by_key(|path| path.components().count());
+
+        let mut manifests = CollectManifests::default();
+        for manifest_fs_path in manifest_fs_paths {
+            let dir = manifest_fs_path.parent().unwrap_or(root_path).to_path_buf();
+            manifests.add(dir, manifest_fs_path, &trusted)?;
+        }
+        Ok(manifests)
+    }
+
+    /// Parse the manifest of `dir` if it has one, for walks which stream their
+    /// entries: they yield a directory before its entries (and its parent before
+    /// it) so the manifest governs everything under `dir` which is walked next.
+    /// Returns whether a manifest was found.
+    pub fn discover(&mut self, dir: &Path, trusted: impl Fn(&Path) -> bool) -> Result<bool> {
+        let manifest_fs_path = dir.join(COLLECT_MANIFEST_FILE_NAME);
+        if !manifest_fs_path.is_file() {
+            return Ok(false);
+        }
+        self.add(dir.to_path_buf(), manifest_fs_path, trusted)?;
+        Ok(true)
+    }
+
+    /// Add the manifest of `dir`, merged into the closest manifest of its
+    /// ancestors which must already have been added.
+    fn add(
+        &mut self,
+        dir: PathBuf,
+        manifest_fs_path: PathBuf,
+        trusted: impl Fn(&Path) -> bool,
+    ) -> Result<()> {
+        let yaml = std::fs::read_to_string(&manifest_fs_path).with_context(|| {
+            format!(
+                "[CollectManifests::add] reading {}",
+                manifest_fs_path.display()
+            )
+        })?;
+        let mut manifest: CollectManifest = serde_yaml::from_str(&yaml).with_context(|| {
+            format!(
+                "[CollectManifests::add] parsing {}",
+                manifest_fs_path.display()
+            )
+        })?;
+        if !manifest.capture.is_empty() && !trusted(&manifest_fs_path) {
+            manifest.capture.clear();
+            self.untrusted_capture.push(manifest_fs_path.clone());
+        }
+        // deepest directories first, so the first ancestor found is the closest
+        let manifest = match self.scopes.iter().find(|s| dir.starts_with(&s.dir)) {
+            Some(parent) => manifest.merged_into(&parent.manifest),
+            None => manifest,
+        };
+        let depth = dir.components().count();
+        let at = self
+            .scopes
+            .iter()
+            .position(|s| s.dir.components().count() < depth)
+            .unwrap_or(self.scopes.len());
+        self.scopes.insert(
+            at,
+            CollectManifestScope {
+                dir,
+                manifest_fs_path,
+                manifest,
+            },
+        );
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scopes.is_empty()
+    }
+
+    /// The closest manifest governing `path`.
+    pub fn scope(&self, path: &Path) -> Option<&CollectManifestScope> {
+        self.scopes.iter().find(|s| path.starts_with(&s.dir))
+    }
+
+    /// Put the manifests' rules ahead of the CLI or behavior rules in `classifier`;
+    /// the first matching rule classifies a path so the deepest manifest wins and
+    /// files no manifest mentions are classified as before.
+    pub fn apply(&self, classifier: &mut EncounterableResourcePathClassifier) -> Result<()> {
+        let mut flaggables = Vec::new();
+        for scope in &self.scopes {
+            let dir = regex::escape(&scope.dir.to_string_lossy());
+            let scoped = |relative: &str| regex::Regex::new(&format!("^{dir}/(?:{relative})"));
+            for ignore in &scope.manifest.ignore {
+                flaggables.push(FlaggableRegEx {
+                    regex: scoped(ignore)?,
+                    flags: EncounterableResourceFlags::IGNORE_RESOURCE,
+                    nature: None,
+                });
+            }
+            for capture in &scope.manifest.capture {
+                flaggables.push(FlaggableRegEx {
+                    regex: scoped(capture)?,
+                    flags: EncounterableResourceFlags::CAPTURABLE_EXECUTABLE,
+                    nature: capture
+                        .contains("(?P<nature>")
+                        .then(|| PFRE_READ_NATURE_FROM_REGEX.to_string()),
+                });
+            }
+            if !scope.manifest.acquire.is_empty() {
+                let natures: Vec<String> = scope
+                    .manifest
+                    .acquire
+                    .iter()
+                    .map(|n| regex::escape(n))
+                    .collect();
+                flaggables.push(FlaggableRegEx {
+                    regex: scoped(&format!(r".*\.(?P<nature>{})$", natures.join("|")))?,
+                    flags: EncounterableResourceFlags::CONTENT_ACQUIRABLE,
+                    nature: Some(PFRE_READ_NATURE_FROM_REGEX.to_string()),
+                });
+            }
+        }
+        flaggables.append(&mut classifier.flaggables);
+        classifier.flaggables = flaggables;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use resource::{EncounterableResourceClass, EncounterableResourceUriClassifier};
+
+    #[test]
+    fn trusted_manifests_match_whole_paths() -> Result<()> {
+        let trusted = trusted_collect_manifests(&[
+            r"/data/finance/\.surveilr_collect\.yml".to_string(),
+            r"/srv/[a-z]+/\.surveilr_collect\.yml|/opt/ops/\.surveilr_collect\.yml".to_string(),
+        ])?;
+        assert!(trusted(Path::new("/data/finance/.surveilr_collect.yml")));
+        assert!(trusted(Path::new("/srv/hr/.surveilr_collect.yml")));
+        assert!(trusted(Path::new("/opt/ops/.surveilr_collect.yml")));
+        // matching part of the path isn't enough
+        assert!(!trusted(Path::new(
+            "/uploads/data/finance/.surveilr_collect.yml"
+        )));
+        assert!(!trusted(Path::new("/srv/hr/evil/.surveilr_collect.yml")));
+        assert!(!trusted(Path::new("/opt/ops/.surveilr_collect.yml.bak")));
+
+        let partial = trusted_collect_manifests(&["finance".to_string()])?;
+        assert!(!partial(Path::new("/data/finance/.surveilr_collect.yml")));
+        assert!(trusted_collect_manifests(&["(".to_string()]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn nested_manifests_override_the_classifier() -> Result<()> {
+        let root = std::env::temp_dir().join(format!("surveilr-collect-{}", ulid::Ulid::new()));
+        std::fs::create_dir_all(root.join("finance/reports"))?;
+        let root = root.canonicalize()?;
+        std::fs::write(
+            root.join(COLLECT_MANIFEST_FILE_NAME),
+            "acquire: [csv]\nignore: ['drafts/']\ntags: [corp]\nmax_size_bytes: 1000\n",
+        )?;
+        std::fs::write(
+            root.join("finance").join(COLLECT_MANIFEST_FILE_NAME),
+            "capture: ['collect\\.sh$']\ntags: [sox]\n",
+        )?;
+        assert!(serde_yaml::from_str::<CollectManifest>("acquires: [pdf]").is_err());
+
+        let walked = walkdir::WalkDir::new(&root)
+            .into_iter()
+            .flatten()
+            .map(|entry| entry.into_path())
+            .collect::<Vec<_>>();
+        let manifests = CollectManifests::from_walked(&root, &walked, |path| {
+            path.starts_with(root.join("finance"))
+        })?;
+        // nobody vouched for the finance manifest, its collect.sh isn't run
+        let untrusted = CollectManifests::from_walked(&root, &walked, |_| false)?;
+        assert_eq!(
+            untrusted.untrusted_capture,
+            vec![root.join("finance").join(COLLECT_MANIFEST_FILE_NAME)]
+        );
+        assert!(untrusted
+            .scopes
+            .iter()
+            .all(|s| s.manifest.capture.is_empty()));
+        let finance = manifests
+            .scope(&root.join("finance/reports/q1.csv"))
+            .unwrap();
+        assert_eq!(finance.dir, root.join("finance"));
+        assert_eq!(finance.manifest.tags, vec!["corp", "sox"]);
+        assert_eq!(finance.manifest.max_size_bytes, Some(1000));
+        assert_eq!(
+            manifests.scope(&root.join("a.csv")).unwrap().manifest.tags,
+            vec!["corp"]
+        );
+
+        let mut classifier = EncounterableResourcePathClassifier::default();
+        manifests.apply(&mut classifier)?;
+        let classify = |path: PathBuf| {
+            let mut class = EncounterableResourceClass {
+                flags: EncounterableResourceFlags::empty(),
+                nature: None,
+            };
+            classifier.classify(&path.to_string_lossy(), &mut class);
+            class
+        };
+        let csv = classify(root.join("finance/reports/q1.csv"));
+        assert!(csv
+            .flags
+            .contains(EncounterableResourceFlags::CONTENT_ACQUIRABLE));
+        assert_eq!(csv.nature.as_deref(), Some("csv"));
+        assert!(classify(root.join("finance/collect.sh"))
+            .flags
+            .contains(EncounterableResourceFlags::CAPTURABLE_EXECUTABLE));
+        assert!(classify(root.join("drafts/a.md"))
+            .flags
+            .contains(EncounterableResourceFlags::IGNORE_RESOURCE));
+        // outside of the manifests' directories the default rules still apply
+        assert!(!classify(PathBuf::from("/elsewhere/q1.csv"))
+            .flags
+            .contains(EncounterableResourceFlags::CONTENT_ACQUIRABLE));
+
+        // discovered directory by directory as a walk enters them, parents first
+        let mut discovered = CollectManifests::default();
+        for dir in [
+            root.clone(),
+            root.join("drafts"),
+            root.join("finance"),
+            root.join("finance/reports"),
+        ] {
+            discovered.discover(&dir, |path| path.starts_with(root.join("finance")))?;
+        }
+        assert_eq!(discovered, manifests);
+
+        std::fs::remove_dir_all(&root)?;
+        Ok(())
+    }
+}
@@ -1,25 +1,61 @@
 use crate::{
+    blobs::BlobStore,
     cmd::IngestFilesArgs,
     ingest::{
-        insert_uniform_resource, upserted_device, DbConn, IngestContext, IngestFilesBehavior,
-        UniformResourceWriterAction, UniformResourceWriterEntry, UniformResourceWriterState,
+        extract_persistence, ingest_archive_members, insert_lineage, insert_uniform_resource,
+        routed_state_db, routed_state_dbs, run_nature_triggers, trusted_collect_manifests,
+        upserted_device, validate_captured_sql, ArchiveTarget, CeWorkdirs, CollectManifests,
+        DbConn, IncrementalStats, IngestContext, IngestFilesBehavior, IngestedSession, PrefetchJob,
+        Prefetcher, PreviousResources, SessionAbort, SessionGuard, UniformResourceWriterAction,
+        UniformResourceWriterEntry, UniformResourceWriterResult, UniformResourceWriterState,
         INS_UR_INGEST_SESSION_FINISH_SQL, INS_UR_INGEST_SESSION_SQL, INS_UR_ISFSP_ENTRY_SQL,
-        INS_UR_ISFSP_SQL,
+        INS_UR_ISFSP_SQL, UPD_UR_INGEST_SESSION_DIGEST_SQL, UPD_UR_ISFSP_ELABORATION_SQL,
     },
 };
-use anyhow::{Context, Result};
-use resource::{extract_path_info, ResourcesCollection, UriNatureSupplier};
+use anyhow::{anyhow, Context, Result};
+use resource::{
+    access::{AccessIssue, AccessStage, SudoRead},
+    archive::{archive_format, ArchivePolicy},
+    extract_path_info,
+    git::{GitRepo, GitRepoCache},
+    walk::{self, WalkOptions},
+    ContentResource, EncounterableResource, ResourcesCollection, UniformResource,
+    UriNatureSupplier,
+};
 use rusqlite::params;
 use serde_json::json;
-use tracing::{debug, error};
+use std::cell::RefCell;
+use tracing::{debug, error, warn};
 
-pub fn ingest_files(debug: u8, ingest_args: &IngestFilesArgs) -> Result<String> {
-    let mut dbc = DbConn::new(&ingest_args.state_db_fs_path, debug).with_context(|| {
-        format!(
-            "[ingest_files] SQLite transaction in {}",
-            ingest_args.state_db_fs_path
-        )
-    })?;
+/// How many walked entries may wait for the writer before the walk pauses.
+const WALK_CHANNEL_BOUND: usize = 1024;
+
+/// How many resources are stored between commits with `--jobs` unless
+/// `--commit-every` says otherwise.
+const DEFAULT_JOBS_COMMIT_EVERY: u64 = 10_000;
+
+/// Ingest the root paths into the default RSSD and, when `--route` is used, the
+/// routed resources into their own RSSDs, each in its own session.
+pub fn ingest_files(debug: u8, ingest_args: &IngestFilesArgs) -> Result<Vec<IngestedSession>> {
+    routed_state_dbs(&ingest_args.route, &ingest_args.state_db_fs_path)
+        .into_iter()
+        .map(|state_db_fs_path| {
+            Ok(IngestedSession {
+                state_db_fs_path: state_db_fs_path.to_string(),
+                ingest_session_id: ingest_files_into(debug, ingest_args, state_db_fs_path)?,
+            })
+        })
+        .collect()
+}
+
+/// Walk the root paths and store the resources routed to `state_db_fs_path`.
+fn ingest_files_into(
+    debug: u8,
+    ingest_args: &IngestFilesArgs,
+    state_db_fs_path: &str,
+) -> Result<String> {
+    let mut dbc = DbConn::new(state_db_fs_path, debug)
+        .with_context(|| format!("[ingest_files] SQLite transaction in {}", state_db_fs_path))?;
     let db_fs_path = dbc.db_fs_path.clone();
 
     // putting everything inside a transaction improves performance significantly
@@ -32,14 +68,27 @@ pub fn ingest_files(debug: u8, ingest_args: &IngestFilesArgs) -> Result<String>
         )
     })?;
 
-    // the ulid() function we're using below is not built into SQLite, we define
+    // the surveilr_pk() function we're using below is not built into SQLite, we define
     // it in persist::prepare_conn so it's initialized as part of `dbc`.
 
     let (mut behavior, mut behavior_id) = IngestFilesBehavior::new(&device_id, ingest_args, &tx)
         .with_context(|| format!("[ingest_files] behavior issue {}", db_fs_path))?;
-    if !ingest_args.include_state_db_in_ingestion {
-        let canonical_db_fs_path = std::fs::canonicalize(std::path::Path::new(&db_fs_path))
-            .with_context(|| format!("[ingest_files] unable to canonicalize in {}", db_fs_path))?;
+    let blob_store = ingest_args
+        .blob_store
+        .as_deref()
+        .map(|blob_store| BlobStore::establish(&tx, blob_store))
+        .transpose()?;
+    // none of the RSSDs being written to are ingested, in-memory ones and routed ones
+    // which don't exist yet can't be encountered
+    let excluded_db_fs_paths = if ingest_args.include_state_db_in_ingestion {
+        vec![]
+    } else {
+        routed_state_dbs(&ingest_args.route, &ingest_args.state_db_fs_path)
+    };
+    for excluded_db_fs_path in excluded_db_fs_paths {
+        let Ok(canonical_db_fs_path) = std::fs::canonicalize(excluded_db_fs_path) else {
+            continue;
+        };
         let canonical_db_fs_path = canonical_db_fs_path.to_string_lossy().to_string();
         let mut wal_path = std::path::PathBuf::from(&canonical_db_fs_path);
         let mut db_journal_path = std::path::PathBuf::from(&canonical_db_fs_path);
@@ -97,6 +146,51 @@ pub fn ingest_files(debug: u8, ingest_args: &IngestFilesArgs) -> Result<String>
         })?;
 
     debug!("Walk Session: {ingest_session_id}");
+    tx.execute(
+        UPD_UR_INGEST_SESSION_DIGEST_SQL,
+        params![ingest_session_id, ingest_args.digest.to_string()],
+    )
+    .with_context(|| {
+        format!(
+            "[ingest_files] recording --digest {} in {}",
+            ingest_args.digest, db_fs_path
+        )
+    })?;
+    // looked up before this session's entries exist so it can't find itself
+    let previous_resources = if ingest_args.incremental {
+        PreviousResources::from_conn(&tx, &device_id, ingest_args.digest)
+            .with_context(|| format!("[ingest_files] --incremental in {}", db_fs_path))?
+    } else {
+        PreviousResources::default()
+    };
+    let mut unchanged = 0;
+    let mut validation_errors: Vec<String> = Vec::new();
+    let mut guard = SessionGuard::new(&ingest_args.limits);
+    let ce_workdirs = CeWorkdirs::new(&ingest_session_id, ingest_args.keep_ce_workdirs);
+    let mut aborted: Option<SessionAbort> = None;
+    // validation sessions are rolled back as a whole so they're never committed early
+    let commit_every = ingest_args
+        .commit_every
+        .or((ingest_args.jobs > 1).then_some(DEFAULT_JOBS_COMMIT_EVERY))
+        .filter(|_| !ingest_args.ce_sql_validate_only);
+    let mut encountered: u64 = 0;
+    let mut access_issues: Vec<AccessIssue> = Vec::new();
+    let archive_policy = ArchivePolicy {
+        passwords: ingest_args.archive_password.clone(),
+        max_member_size: ingest_args.max_archive_member_size,
+    };
+    let mut archives: Vec<serde_json::Value> = Vec::new();
+    // capturable executables are run every time and archives are read to
+    // expand their members in this session
+    let unchanged_ur_id = |resource: &UniformResource<ContentResource>| match resource {
+        UniformResource::CapturableExec(_) => None,
+        _ if ingest_args.expand_archives
+            && archive_format(std::path::Path::new(resource.uri())).is_some() =>
+        {
+            None
+        }
+        _ => previous_resources.unchanged(resource.resource()),
+    };
 
     {
         let env_current_dir = std::env::current_dir()
@@ -104,9 +198,26 @@ pub fn ingest_files(debug: u8, ingest_args: &IngestFilesArgs) -> Result<String>
             .to_string_lossy()
             .to_string();
 
-        let mut ingest_stmts = IngestContext::from_conn(&tx, &ingest_args.state_db_fs_path)
+        let mut ingest_stmts = IngestContext::from_conn(&tx, state_db_fs_path)
             .with_context(|| format!("[ingest_files] ingest_stmts in {}", db_fs_path))?;
 
+        let sudo_read = (!ingest_args.sudo_read.is_empty())
+            .then(|| SudoRead::new(&ingest_args.sudo_read, &ingest_args.sudo_user))
+            .transpose()
+            .with_context(|| {
+                format!(
+                    "[ingest_files] --sudo-read {}",
+                    ingest_args.sudo_read.join(", ")
+                )
+            })?;
+
+        let trusted_collect_manifest =
+            trusted_collect_manifests(&ingest_args.trust_collect_manifest)
+                .with_context(|| "[ingest_files] --trust-collect-manifest")?;
+
+        // the files under a root can be in different repositories (e.g. a
+        // directory of clones), each file records the one it's in
+        let mut git_repos = GitRepoCache::default();
         for root_path in &behavior.root_fs_paths {
             let canonical_path_buf = std::fs::canonicalize(std::path::Path::new(&root_path))
                 .with_context(|| {
@@ -115,9 +226,107 @@ pub fn ingest_files(debug: u8, ingest_args: &IngestFilesArgs) -> Result<String>
                         root_path, db_fs_path
                     )
                 })?;
-            let canonical_path = canonical_path_buf.into_os_string().into_string().unwrap();
-
-            let ins_ur_wsp_params = params![ingest_session_id, canonical_path];
+            // paths inside a git work tree record the exact code version they were walked at
+            let git_repo = GitRepo::discover(&canonical_path_buf);
+            let canonical_path = canonical_path_buf.to_string_lossy().to_string();
+            let walk_options = WalkOptions {
+                order: ingest_args.walk_order,
+                max_dir_entries: ingest_args.max_dir_entries,
+            };
+            // the walk streams its entries from a thread of its own so the tree is
+            // never held in memory, they're classified as they arrive
+            let (walked, walker) = walk::spawn_smart_ignore_walk(
+                canonical_path_buf.clone(),
+                false,
+                behavior.classifier.smart_ignore_conf_files.clone(),
+                walk_options.clone(),
+                WALK_CHANNEL_BOUND,
+            );
+            let resources = ResourcesCollection::new(Vec::new(), &behavior.classifier, None)
+                .with_sudo_read(sudo_read.clone())
+                .with_digest(ingest_args.digest);
+            // data owners' .surveilr_collect.yml rules take precedence over the
+            // behavior's, they're found as the walk enters their directories
+            let collect_manifests = RefCell::new(CollectManifests::default());
+            let classifier = RefCell::new(behavior.classifier.clone());
+            let walk_issues: RefCell<Vec<AccessIssue>> = RefCell::new(Vec::new());
+            let walk_failure: RefCell<Option<anyhow::Error>> = RefCell::new(None);
+            let walked_resources = walked
+                .into_iter()
+                .map_while(|walked_entry| {
+                    let entry = match walked_entry {
+                        Ok(entry) => entry,
+                        Err(issue) if ingest_args.fail_on_unreadable => {
+                            *walk_failure.borrow_mut() = Some(anyhow!(
+                                "[ingest_files] unable to walk {} ({}), failing because of --fail-on-unreadable",
+                                issue.path,
+                                issue.error
+                            ));
+                            return None;
+                        }
+                        Err(issue) => {
+                            walk_issues.borrow_mut().push(issue);
+                            return Some(None);
+                        }
+                    };
+                    if entry.file_type().is_some_and(|ft| ft.is_dir())
+                        && !ingest_args.ignore_collect_manifests
+                    {
+                        let mut manifests = collect_manifests.borrow_mut();
+                        let untrusted = manifests.untrusted_capture.len();
+                        let discovered = manifests
+                            .discover(entry.path(), &trusted_collect_manifest)
+                            .and_then(|discovered| {
+                                if discovered {
+                                    let mut rules = behavior.classifier.clone();
+                                    manifests.apply(&mut rules)?;
+                                    *classifier.borrow_mut() = rules;
+                                }
+                                Ok(())
+                            })
+                            .with_context(|| {
+                                format!("[ingest_files] collect manifests in {}", root_path)
+                            });
+                        if let Err(err) = discovered {
+                            *walk_failure.borrow_mut() = Some(err);
+                            return None;
+                        }
+                        for manifest_fs_path in &manifests.untrusted_capture[untrusted..] {
+                            warn!(
+                                "[ingest_files] ignoring the capture rules of {}, it doesn't match --trust-collect-manifest",
+                                manifest_fs_path.display()
+                            );
+                        }
+                    }
+                    let encounterable = EncounterableResource::SmartIgnore(entry);
+                    Some(resources.classified_uniform_resource(&encounterable, &classifier.borrow()))
+                })
+                .flatten();
+            let oversized = |resource: &UniformResource<ContentResource>| {
+                let collect_manifests = collect_manifests.borrow();
+                let scope = collect_manifests.scope(std::path::Path::new(resource.uri()))?;
+                let max_size_bytes = scope.manifest.max_size_bytes?;
+                let size_bytes = std::fs::metadata(resource.uri()).ok()?.len();
+                (size_bytes > max_size_bytes).then(|| {
+                    json!({
+                        "message": "larger than the collect manifest's max_size_bytes",
+                        "collect_manifest": scope.manifest_fs_path,
+                        "max_size_bytes": max_size_bytes,
+                        "size_bytes": size_bytes,
+                    })
+                })
+            };
+            let mut elaboration = serde_json::Map::new();
+            if let Some(git_repo) = &git_repo {
+                elaboration.insert("git".to_string(), json!(git_repo));
+            }
+            // completed once the walk is, see below
+            let ins_ur_wsp_params = params![
+                ingest_session_id,
+                canonical_path,
+                (!elaboration.is_empty())
+                    .then(|| serde_json::Value::Object(elaboration.clone()).to_string())
+            ];
             let ingest_fs_path_id: String = ingest_stmts
                 .ins_ur_isfsp_stmt
                 .query_row(ins_ur_wsp_params, |row| row.get(0))
@@ -130,10 +339,6 @@ pub fn ingest_files(debug: u8, ingest_args: &IngestFilesArgs) -> Result<String>
 
             debug!("  Walk Session Path: {root_path} ({ingest_fs_path_id})");
 
-            let rp: Vec<String> = vec![canonical_path.clone()];
-            let resources =
-                ResourcesCollection::from_smart_ignore(&rp, &behavior.classifier, None, false);
-
             let mut urw_state = UniformResourceWriterState {
                 state_db_fs_path: &db_fs_path,
                 ingest_files_behavior: Some(&behavior),
@@ -143,33 +348,135 @@ pub fn ingest_files(debug: u8, ingest_args: &IngestFilesArgs) -> Result<String>
                 ingest_fs_path_id: Some(&ingest_fs_path_id),
                 resources: &resources,
                 ingest_stmts: &mut ingest_stmts,
+                ce_json_filter: behavior.ce_json_filter.as_deref(),
+                ce_workdirs: &ce_workdirs,
+                decode_payloads: ingest_args.decode_payloads,
+                canonical_json: ingest_args.canonical_json,
+                content_chunk_size: ingest_args.content_chunk_size.map(|mb| mb * 1024 * 1024),
+                blob_store: blob_store.as_ref(),
+                blob_threshold: ingest_args.blob_threshold * 1024,
             };
 
-            for resource_result in resources.uniform_resources() {
+            // with --jobs the content of the resources ahead is read (or they're
+            // executed) on workers while this thread stores them in walk order
+            let ce_stdin = urw_state.capturable_exec_stdin();
+            let prefetched_resources =
+                Prefetcher::new(walked_resources, ingest_args.jobs, |resource_result| {
+                    let resource = resource_result.as_ref().ok()?;
+                    let stored_here = routed_state_db(
+                        &ingest_args.route,
+                        &ingest_args.state_db_fs_path,
+                        resource,
+                    ) == state_db_fs_path;
+                    let wanted = stored_here
+                        && oversized(resource).is_none()
+                        && unchanged_ur_id(resource).is_none();
+                    wanted
+                        .then(|| PrefetchJob::of(&resources, resource, &ce_stdin))
+                        .flatten()
+                });
+            for (resource_result, prefetched) in prefetched_resources {
+                if let Some(abort) = guard.exceeded() {
+                    aborted = Some(abort);
+                    break;
+                }
+                if let Some(commit_every) = commit_every {
+                    if encountered > 0 && encountered.is_multiple_of(commit_every) {
+                        // a batch of resources is durable, the next one starts
+                        tx.execute_batch("COMMIT; BEGIN IMMEDIATE")
+                            .with_context(|| {
+                                format!(
+                                    "[ingest_files] committing after {} resources in {}",
+                                    encountered, db_fs_path
+                                )
+                            })?;
+                    }
+                }
+                encountered += 1;
                 match resource_result {
-                    Ok(resource) => {
+                    Ok(resource)
+                        if routed_state_db(
+                            &ingest_args.route,
+                            &ingest_args.state_db_fs_path,
+                            &resource,
+                        ) != state_db_fs_path => {}
+                    Ok(mut resource) => {
+                        let executed = prefetched.and_then(|p| p.apply(&mut resource));
                         let mut urw_entry = UniformResourceWriterEntry {
                             path: Some(resource.uri()),
                             tried_alternate_nature: None,
+                            executed,
+                        };
+                        let mut entry_elaboration = collect_manifests
+                            .borrow()
+                            .scope(std::path::Path::new(resource.uri()))
+                            .map(|scope| scope.entry_elaboration())
+                            .unwrap_or_default();
+                        if let Some(git_repo) =
+                            git_repos.discover(std::path::Path::new(resource.uri()))
+                        {
+                            entry_elaboration.insert("git".to_string(), json!(git_repo));
+                        }
+                        let inserted = match (oversized(&resource), unchanged_ur_id(&resource)) {
+                            (Some(diagnostics), _) => UniformResourceWriterResult {
+                                uri: resource.uri().to_string(),
+                                action: UniformResourceWriterAction::Skipped(diagnostics),
+                            },
+                            (None, Some(ur_id)) => {
+                                unchanged += 1;
+                                UniformResourceWriterResult {
+                                    uri: resource.uri().to_string(),
+                                    action: UniformResourceWriterAction::Unchanged(ur_id.clone()),
+                                }
+                            }
+                            (None, None) => {
+                                insert_uniform_resource(&resource, &mut urw_state, &mut urw_entry)
+                            }
                         };
-                        let inserted =
-                            insert_uniform_resource(&resource, &mut urw_state, &mut urw_entry);
                         let mut ur_status = inserted.action.ur_status();
                         let mut ur_diagnostics = inserted.action.ur_diagnostics();
+                        if let Some(error) = inserted.action.access_denied() {
+                            if ingest_args.fail_on_unreadable {
+                                return Err(anyhow!(
+                                    "[ingest_files] unable to read {} ({}), failing because of --fail-on-unreadable",
+                                    inserted.uri,
+                                    error
+                                ));
+                            }
+                            if ingest_args.skip_unreadable {
+                                ur_status = Some(String::from("SKIPPED"));
+                                ur_diagnostics = Some(serde_json::to_string_pretty(&json!({
+                                    "instance": "UniformResourceWriterAction::access_denied",
+                                    "message": "not allowed to read the file, skipped because of --skip-unreadable",
+                                    "error": error
+                                })).unwrap());
+                            }
+                            access_issues.push(AccessIssue {
+                                path: inserted.uri.clone(),
+                                stage: AccessStage::Read,
+                                error,
+                            });
+                        }
                         let mut captured_exec_diags: Option<String> = None;
+                        let mut captured_exec_script_ur_id: Option<&String> = None;
 
                         let uniform_resource_id = match &inserted.action {
                             UniformResourceWriterAction::Inserted(
                                 ref uniform_resource_id,
                                 None,
-                            ) => Some(uniform_resource_id),
+                            )
+                            | UniformResourceWriterAction::Unchanged(ref uniform_resource_id) => {
+                                Some(uniform_resource_id)
+                            }
                             UniformResourceWriterAction::InsertedExecutableOutput(
                                 ref uniform_resource_id,
                                 None,
                                 diags,
+                                script_ur_id,
                             ) => {
                                 captured_exec_diags =
                                     Some(serde_json::to_string_pretty(&diags).unwrap());
+                                captured_exec_script_ur_id = script_ur_id.as_ref();
                                 Some(uniform_resource_id)
                             }
                             UniformResourceWriterAction::CapturedExecutableSqlOutput(
@@ -178,9 +485,20 @@ pub fn ingest_files(debug: u8, ingest_args: &IngestFilesArgs) -> Result<String>
                             ) => {
                                 captured_exec_diags =
                                     Some(serde_json::to_string_pretty(&diags).unwrap());
-                                match tx.execute_batch(sql_script) {
+                                let executed = if ingest_args.ce_sql_validate_only {
+                                    validate_captured_sql(&tx, sql_script)
+                                } else {
+                                    tx.execute_batch(sql_script)
+                                };
+                                match executed {
                                     Ok(_) => {
-                                        ur_status = Some(String::from("EXECUTED_CAPTURED_SQL"));
+                                        ur_status = Some(String::from(
+                                            if ingest_args.ce_sql_validate_only {
+                                                "VALIDATED_CAPTURED_SQL"
+                                            } else {
+                                                "EXECUTED_CAPTURED_SQL"
+                                            },
+                                        ));
                                         ur_diagnostics = Some(serde_json::to_string_pretty(&json!({
                                             "instance": "UniformResourceWriterAction::CapturedExecutableSqlOutput(err)",
                                             "SQL": sql_script
@@ -188,6 +506,11 @@ pub fn ingest_files(debug: u8, ingest_args: &IngestFilesArgs) -> Result<String>
                                         None
                                     }
                                     Err(err) => {
+                                        if ingest_args.ce_sql_validate_only {
+                                            error!("[ingest_files] captured SQL from {} failed validation: {}", &inserted.uri, err);
+                                            validation_errors
+                                                .push(format!("{}: {}", inserted.uri, err));
+                                        }
                                         ur_status = Some(String::from("ERROR"));
                                         ur_diagnostics = Some(serde_json::to_string_pretty(&json!({
                                             "instance": "UniformResourceWriterAction::CapturedExecutableSqlOutput(err)",
@@ -202,6 +525,7 @@ pub fn ingest_files(debug: u8, ingest_args: &IngestFilesArgs) -> Result<String>
                             _ => None,
                         };
 
+                        guard.record(ur_status.as_deref());
                         match extract_path_info(
                             std::path::Path::new(&canonical_path),
                             std::path::Path::new(&inserted.uri),
@@ -213,7 +537,24 @@ pub fn ingest_files(debug: u8, ingest_args: &IngestFilesArgs) -> Result<String>
                                 file_basename,
                                 file_extn,
                             )) => {
-                                match urw_state.ingest_stmts.ins_ur_isfsp_entry_stmt.execute(
+                                // archives are usually of unknown nature, stored without content
+                                let archive = match &inserted.action {
+                                    UniformResourceWriterAction::Inserted(archive_ur_id, _)
+                                        if ingest_args.expand_archives
+                                            && archive_format(std::path::Path::new(
+                                                &inserted.uri,
+                                            ))
+                                            .is_some() =>
+                                    {
+                                        Some((
+                                            archive_ur_id,
+                                            file_path_abs.to_string_lossy().to_string(),
+                                            file_path_rel.to_string_lossy().to_string(),
+                                        ))
+                                    }
+                                    _ => None,
+                                };
+                                match urw_state.ingest_stmts.ins_ur_isfsp_entry_stmt.query_row(
                                     params![
                                         ingest_session_id,
                                         ingest_fs_path_id,
@@ -225,17 +566,54 @@ pub fn ingest_files(debug: u8, ingest_args: &IngestFilesArgs) -> Result<String>
                                             .unwrap(),
                                         file_path_rel.into_os_string().into_string().unwrap(),
                                         file_basename,
-                                        if let Some(file_extn) = file_extn {
-                                            file_extn
-                                        } else {
-                                            String::from("")
-                                        },
+                                        file_extn.unwrap_or_default(),
                                         ur_status,
                                         ur_diagnostics,
-                                        captured_exec_diags
+                                        captured_exec_diags,
+                                        (!entry_elaboration.is_empty()).then(|| {
+                                            serde_json::Value::Object(entry_elaboration).to_string()
+                                        })
                                     ],
+                                    |row| row.get::<_, String>(0),
                                 ) {
-                                    Ok(_) => {}
+                                    Ok(ingest_fs_path_entry_id) => {
+                                        if let (
+                                            UniformResourceWriterAction::InsertedExecutableOutput(
+                                                ..,
+                                            ),
+                                            Some(output_ur_id),
+                                        ) = (&inserted.action, uniform_resource_id)
+                                        {
+                                            if let Err(err) = insert_lineage(
+                                                urw_state.ingest_stmts,
+                                                &ingest_session_id,
+                                                captured_exec_script_ur_id,
+                                                output_ur_id,
+                                                Some(&ingest_fs_path_entry_id),
+                                                None,
+                                            ) {
+                                                error!("[ingest_files] unable to insert lineage for {} in {}: {}", &inserted.uri, db_fs_path, err)
+                                            }
+                                        }
+                                        if let Some((archive_ur_id, file_path_abs, file_path_rel)) =
+                                            archive
+                                        {
+                                            let target = ArchiveTarget {
+                                                device_id: &device_id,
+                                                ingest_session_id: &ingest_session_id,
+                                                ingest_fs_path_id: &ingest_fs_path_id,
+                                                archive_ur_id,
+                                                file_path_abs: &file_path_abs,
+                                                file_path_rel: &file_path_rel,
+                                                digest: ingest_args.digest,
+                                            };
+                                            archives.push(expanded_archive(
+                                                urw_state.ingest_stmts,
+                                                &target,
+                                                &archive_policy,
+                                            ));
+                                        }
+                                    }
                                     Err(err) => {
                                         error!( "[ingest_files] unable to insert UR walk session path file system entry for {} in {}: {} ({})",
                                         &inserted.uri, db_fs_path, err, INS_UR_ISFSP_ENTRY_SQL
@@ -253,12 +631,124 @@ pub fn ingest_files(debug: u8, ingest_args: &IngestFilesArgs) -> Result<String>
                     }
                     Err(e) => {
                         error!("[ingest_files] Error processing a resource: {}", e);
+                        guard.record(Some("ERROR"));
                     }
                 }
             }
+
+            // the walk's outcome is only known once it is complete
+            if let Some(failure) = walk_failure.into_inner() {
+                return Err(failure);
+            }
+            access_issues.extend(walk_issues.into_inner());
+            let truncated_dirs = walker
+                .join()
+                .map_err(|_| anyhow!("[ingest_files] the walk of {} panicked", root_path))?;
+            let collect_manifests = collect_manifests.into_inner();
+            for (dir, skipped) in &truncated_dirs {
+                warn!(
+                    "[ingest_files] skipped {} entries of {} (--max-dir-entries {})",
+                    skipped,
+                    dir,
+                    walk_options.max_dir_entries.unwrap_or_default()
+                );
+            }
+            if !collect_manifests.is_empty() {
+                let manifests: Vec<_> = collect_manifests
+                    .scopes
+                    .iter()
+                    .map(|scope| &scope.manifest_fs_path)
+                    .collect();
+                elaboration.insert("collect_manifests".to_string(), json!(manifests));
+            }
+            if !collect_manifests.untrusted_capture.is_empty() {
+                elaboration.insert(
+                    "collect_manifests_untrusted_capture".to_string(),
+                    json!(collect_manifests.untrusted_capture),
+                );
+            }
+            if !walk_options.is_default() {
+                elaboration.insert(
+                    "walk".to_string(),
+                    json!({
+                        "order": walk_options.order,
+                        "max_dir_entries": walk_options.max_dir_entries,
+                        "truncated_dirs": truncated_dirs,
+                    }),
+                );
+            }
+            if !elaboration.is_empty() {
+                tx.execute(
+                    UPD_UR_ISFSP_ELABORATION_SQL,
+                    params![
+                        ingest_fs_path_id,
+                        serde_json::Value::Object(elaboration).to_string()
+                    ],
+                )
+                .with_context(|| {
+                    format!(
+                        "[ingest_files] walk elaboration of {} in {}",
+                        root_path, db_fs_path
+                    )
+                })?;
+            }
+            if aborted.is_some() {
+                break;
+            }
         }
     }
-    match tx.execute(INS_UR_INGEST_SESSION_FINISH_SQL, params![ingest_session_id]) {
+    let mut session_elaboration = serde_json::Map::new();
+    if !behavior.nature_triggers.is_empty() {
+        let stats = run_nature_triggers(&tx, &ingest_session_id, &behavior.nature_triggers)
+            .with_context(|| format!("[ingest_files] nature triggers in {}", db_fs_path))?;
+        debug!("Nature triggers: {:?}", stats);
+        session_elaboration.insert("nature_triggers".to_string(), json!(stats));
+    }
+    let persistence = extract_persistence(&tx, &ingest_session_id)
+        .with_context(|| format!("[ingest_files] persistence extraction in {}", db_fs_path))?;
+    if persistence.extracted() > 0 {
+        session_elaboration.insert("persistence".to_string(), json!(persistence));
+    }
+    if !ingest_args.route.is_empty() {
+        session_elaboration.insert("state_db_routes".to_string(), json!(ingest_args.route));
+    }
+    if ingest_args.incremental {
+        let stats = IncrementalStats {
+            previous_session_id: previous_resources.ingest_session_id.clone(),
+            unchanged,
+        };
+        debug!("Incremental: {:?}", stats);
+        session_elaboration.insert("incremental".to_string(), json!(stats));
+    }
+    if !access_issues.is_empty() {
+        warn!(
+            "[ingest_files] {} path(s) couldn't be read in session {}:\n{}",
+            access_issues.len(),
+            ingest_session_id,
+            access_issues
+                .iter()
+                .map(|issue| format!("  {}: {}", issue.path, issue.error))
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+        session_elaboration.insert("access_issues".to_string(), json!(access_issues));
+    }
+    if !archives.is_empty() {
+        session_elaboration.insert("archives".to_string(), json!(archives));
+    }
+    if let Some(abort) = aborted {
+        error!(
+            "[ingest_files] session {} in {} aborted after {} resources ({} errors), --{} reached",
+            ingest_session_id, db_fs_path, abort.resources, abort.errors, abort.limit
+        );
+        session_elaboration.insert("aborted".to_string(), json!(abort));
+    }
+    let session_elaboration = (!session_elaboration.is_empty())
+        .then(|| serde_json::Value::Object(session_elaboration).to_string());
+    match tx.execute(
+        INS_UR_INGEST_SESSION_FINISH_SQL,
+        params![ingest_session_id, session_elaboration],
+    ) {
         Ok(_) => {}
         Err(err) => {
             error!(
@@ -267,6 +757,24 @@ pub fn ingest_files(debug: u8, ingest_args: &IngestFilesArgs) -> Result<String>
             )
         }
     }
+    if ingest_args.ce_sql_validate_only {
+        // validation mode must never mutate the RSSD so discard the whole session
+        tx.rollback().with_context(|| {
+            format!(
+                "[ingest_files] unable to roll back validation session in {}",
+                db_fs_path
+            )
+        })?;
+        if !validation_errors.is_empty() {
+            return Err(anyhow!(
+                "[ingest_files] captured SQL failed validation in {} executable(s):\n{}",
+                validation_errors.len(),
+                validation_errors.join("\n")
+            ));
+        }
+        return Ok(ingest_session_id);
+    }
+
     // putting everything inside a transaction improves performance significantly
     tx.commit().with_context(|| {
         format!(
@@ -277,3 +785,27 @@ pub fn ingest_files(debug: u8, ingest_args: &IngestFilesArgs) -> Result<String>
 
     Ok(ingest_session_id)
 }
+
+/// Expand an ingested archive, summarizing it for the session elaboration;
+/// archives which can't be expanded are logged and summarized with the error.
+fn expanded_archive(
+    ingest_stmts: &mut IngestContext<'_>,
+    target: &ArchiveTarget<'_>,
+    policy: &ArchivePolicy,
+) -> serde_json::Value {
+    match ingest_archive_members(ingest_stmts, target, policy) {
+        Ok(summary) => {
+            if summary.skipped_encrypted > 0 {
+                warn!(
+                    "[ingest_files] {} encrypted member(s) of {} skipped, see --archive-password",
+                    summary.skipped_encrypted, target.file_path_abs
+                );
+            }
+            json!({ "uri": target.file_path_abs, "summary": summary })
+        }
+        Err(err) => {
+            error!("[ingest_files] unable to expand archive: {:#}", err);
+            json!({ "uri": target.file_path_abs, "error": format!("{:#}", err) })
+        }
+    }
+}
@@ -0,0 +1,298 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use common::clock::normalize_to_utc;
+use resource::git::GitTree;
+use resource::EncounterableResourcePathClassifier;
+use rusqlite::{params, Connection};
+use serde_json::json;
+use tracing::debug;
+
+use super::uris::{content_value, sha1_hex, UNFETCHED_CONTENT_DIGEST};
+use super::{INS_UR_INGEST_SESSION_FINISH_SQL, INS_UR_INGEST_SESSION_SQL};
+use crate::cmd::IngestGitArgs;
+use crate::persist::*;
+use crate::reclassify::classified_nature;
+
+const INS_GIT_UR_SQL: &str = "
+    INSERT INTO uniform_resource (uniform_resource_id, device_id, ingest_session_id, uri, nature, content, content_digest, size_bytes, last_modified_at, elaboration)
+                          VALUES (surveilr_pk(), ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                     ON CONFLICT (device_id, content_digest, uri, size_bytes, last_modified_at)
+                   DO UPDATE SET size_bytes = EXCLUDED.size_bytes
+                       RETURNING uniform_resource_id";
+
+const INS_GIT_REPO_SQL: &str = "
+    INSERT INTO ur_ingest_session_git_repo (ur_ingest_session_git_repo_id, ingest_session_id, repo, reference, commit_sha, author_name, author_email, authored_at, committer_name, committer_email, committed_at, subject, elaboration)
+                                    VALUES (surveilr_pk(), ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                                 RETURNING ur_ingest_session_git_repo_id";
+
+/// Ingest the files of a commit of a git repository as uniform resources,
+/// `<repo>@<commit>:<path>`, without checking it out. Repositories which aren't
+/// local directories are cloned into a temporary directory first. The commit is
+/// recorded in `ur_ingest_session_git_repo`.
+pub fn ingest_git(args: &IngestGitArgs) -> Result<String> {
+    let tree = GitTree::open(&args.repo, args.reference.as_deref())
+        .with_context(|| format!("[ingest_git] repository {}", args.repo))?;
+
+    let mut dbc = DbConn::new(&args.state_db_fs_path, 0).with_context(|| {
+        format!(
+            "[ingest_git] SQLite transaction in {}",
+            args.state_db_fs_path
+        )
+    })?;
+    let db_fs_path = dbc.db_fs_path.clone();
+    let tx = dbc.init(Some(&args.state_db_init_sql))?;
+    let (device_id, _device_name) = upserted_device(&tx, &common::DEVICE)
+        .with_context(|| format!("[ingest_git] upserted_device in {}", db_fs_path))?;
+    let classifier = EncounterableResourcePathClassifier::default_from_conn(&tx)?;
+
+    let behavior = json!({
+        "git": {
+            "repo": args.repo,
+            "reference": args.reference,
+            "max_file_size": args.max_file_size,
+        }
+    })
+    .to_string();
+    let ingest_session_id: String = tx
+        .query_row(
+            INS_UR_INGEST_SESSION_SQL,
+            params![device_id, None::<String>, behavior],
+            |row| row.get(0),
+        )
+        .with_context(|| format!("[ingest_git] inserting ingest session in {}", db_fs_path))?;
+    debug!("Git Session: {ingest_session_id}");
+
+    let session_elaboration = insert_git_tree(
+        &tx,
+        &device_id,
+        &ingest_session_id,
+        &tree,
+        &classifier,
+        args.max_file_size,
+    )
+    .with_context(|| format!("[ingest_git] {} in {}", args.repo, db_fs_path))?;
+    tx.execute(
+        INS_UR_INGEST_SESSION_FINISH_SQL,
+        params![ingest_session_id, session_elaboration.to_string()],
+    )
+    .with_context(|| format!("[ingest_git] finishing session in {}", db_fs_path))?;
+    tx.commit().with_context(|| {
+        format!(
+            "[ingest_git] unable to perform final commit in {}",
+            db_fs_path
+        )
+    })?;
+    Ok(ingest_session_id)
+}
+
+/// Store the files of `tree` and its `ur_ingest_session_git_repo` row, returning
+/// the session's elaboration.
+fn insert_git_tree(
+    conn: &Connection,
+    device_id: &str,
+    ingest_session_id: &str,
+    tree: &GitTree,
+    classifier: &EncounterableResourcePathClassifier,
+    max_file_size: u64,
+) -> Result<serde_json::Value> {
+    let commit = &tree.commit;
+    // stored in UTC like walked files' times, the offsets stay in the elaboration
+    let utc = |at: &str| normalize_to_utc(at).map_or_else(|| at.to_string(), |at| at.utc);
+    let (authored_at, committed_at) = (utc(&commit.authored_at), utc(&commit.committed_at));
+
+    let mut ins_ur_stmt = conn.prepare(INS_GIT_UR_SQL)?;
+    let mut ignored = 0;
+    let summary = tree.walk(max_file_size, |blob, content| {
+        let Some(nature) = classified_nature(classifier, &blob.path) else {
+            ignored += 1;
+            return Ok(());
+        };
+        // files without an extension (LICENSE, Makefile, scripts) aren't JSON
+        let nature = match Path::new(&blob.path).extension() {
+            None if nature == "json" => "bin".to_string(),
+            _ => nature,
+        };
+        let uri = format!("{}@{}:{}", tree.repo, commit.sha, blob.path);
+        let elaboration = json!({
+            "git": {
+                "repo": tree.repo,
+                "commit": commit.sha,
+                "path": blob.path,
+                "mode": blob.mode,
+                "blob": blob.oid,
+                "oversized": content.is_none(),
+            }
+        })
+        .to_string();
+        let (digest, content) = match content {
+            Some(content) => (sha1_hex(&content), Some(content_value(content))),
+            None => (UNFETCHED_CONTENT_DIGEST.to_string(), None),
+        };
+        ins_ur_stmt
+            .query_row(
+                params![
+                    device_id,
+                    ingest_session_id,
+                    uri,
+                    nature,
+                    content,
+                    digest,
+                    blob.size,
+                    committed_at,
+                    elaboration,
+                ],
+                |row| row.get::<_, String>(0),
+            )
+            .with_context(|| format!("[insert_git_tree] {uri}"))?;
+        Ok(())
+    })?;
+    drop(ins_ur_stmt);
+
+    let files = summary.blobs - ignored;
+    let repo_elaboration = json!({
+        "authored_at": commit.authored_at,
+        "committed_at": commit.committed_at,
+        "cloned": tree.cloned,
+        "files": files,
+        "ignored": ignored,
+        "oversized": summary.oversized,
+    });
+    conn.query_row(
+        INS_GIT_REPO_SQL,
+        params![
+            ingest_session_id,
+            tree.repo,
+            tree.reference,
+            commit.sha,
+            commit.author_name,
+            commit.author_email,
+            authored_at,
+            commit.committer_name,
+            commit.committer_email,
+            committed_at,
+            commit.subject,
+            repo_elaboration.to_string(),
+        ],
+        |row| row.get::<_, String>(0),
+    )
+    .with_context(|| format!("[insert_git_tree] {} repository", tree.repo))?;
+
+    Ok(json!({
+        "git": {
+            "repo": tree.repo,
+            "reference": tree.reference,
+            "commit": commit.sha,
+            "files": files,
+            "ignored": ignored,
+            "oversized": summary.oversized,
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use super::*;
+    use crate::persist::DbConn;
+
+    #[test]
+    fn test_git_tree_files_are_resources() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .arg("-C")
+                .arg(dir.path())
+                .args([
+                    "-c",
+                    "user.name=Jane Doe",
+                    "-c",
+                    "user.email=jane@example.com",
+                ])
+                .args(args)
+                .env("GIT_AUTHOR_DATE", "2024-03-04T09:00:00+05:00")
+                .env("GIT_COMMITTER_DATE", "2024-03-04T10:00:00+05:00")
+                .output()
+                .map(|output| output.status.success());
+            assert!(matches!(status, Ok(true)), "git {args:?}");
+        };
+        git(&["init", "--quiet"]);
+        std::fs::write(dir.path().join("README.md"), "# repo\n")?;
+        std::fs::write(dir.path().join("LICENSE"), "MIT")?;
+        git(&["add", "."]);
+        git(&["commit", "--quiet", "-m", "initial"]);
+
+        let repo = dir.path().to_string_lossy().to_string();
+        let tree = GitTree::open(&repo, None)?;
+        let mut dbc = DbConn::new(":memory:", 0)?;
+        let tx = dbc.init(None)?;
+        let (device_id, _) = upserted_device(&tx, &common::DEVICE)?;
+        let classifier = EncounterableResourcePathClassifier::default_from_conn(&tx)?;
+        let session_id: String = tx.query_row(
+            INS_UR_INGEST_SESSION_SQL,
+            params![device_id, None::<String>, "{}"],
+            |row| row.get(0),
+        )?;
+        let elaboration = insert_git_tree(&tx, &device_id, &session_id, &tree, &classifier, 1024)?;
+        assert_eq!(elaboration["git"]["files"], 2);
+
+        let resources: Vec<(String, String, String, String)> = tx
+            .prepare(
+                "SELECT uri, nature, content, last_modified_at
+                   FROM uniform_resource
+                  WHERE ingest_session_id = ?
+               ORDER BY uri",
+            )?
+            .query_map([&session_id], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        let sha = &tree.commit.sha;
+        assert_eq!(
+            resources,
+            vec![
+                (
+                    format!("{repo}@{sha}:LICENSE"),
+                    "bin".to_string(),
+                    "MIT".to_string(),
+                    "2024-03-04 05:00:00 UTC".to_string()
+                ),
+                (
+                    format!("{repo}@{sha}:README.md"),
+                    "md".to_string(),
+                    "# repo\n".to_string(),
+                    "2024-03-04 05:00:00 UTC".to_string()
+                ),
+            ]
+        );
+
+        let (commit_sha, author_email, authored_at, subject, original): (
+            String,
+            String,
+            String,
+            String,
+            String,
+        ) = tx.query_row(
+            "SELECT commit_sha, author_email, authored_at, subject, elaboration ->> '$.authored_at'
+               FROM ur_ingest_session_git_repo
+              WHERE ingest_session_id = ?",
+            [&session_id],
+            |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                ))
+            },
+        )?;
+        assert_eq!(&commit_sha, sha);
+        assert_eq!(author_email, "jane@example.com");
+        assert_eq!(authored_at, "2024-03-04 04:00:00 UTC");
+        assert_eq!(subject, "initial");
+        assert_eq!(original, "2024-03-04T09:00:00+05:00");
+        Ok(())
+    }
+}
@@ -0,0 +1,696 @@
+use std::collections::BTreeMap;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use serde_json::{json, Map, Value as JsonValue};
+use tracing::{debug, warn};
+
+use super::uris::sha1_hex;
+use super::{INS_UR_INGEST_SESSION_FINISH_SQL, INS_UR_INGEST_SESSION_SQL};
+use crate::cmd::{IngestPlistArgs, IngestRegistryArgs};
+use crate::persist::*;
+
+const INS_HOST_CONFIG_UR_SQL: &str = "
+    INSERT INTO uniform_resource (uniform_resource_id, device_id, ingest_session_id, uri, nature, content, content_digest, size_bytes, last_modified_at, elaboration)
+                          VALUES (surveilr_pk(), ?, ?, ?, 'json', ?, ?, ?, ?, ?)
+                       RETURNING uniform_resource_id";
+
+// a plist or key captured unchanged by an earlier session isn't recorded again
+const SEL_HOST_CONFIG_UR_SQL: &str = "
+    SELECT uniform_resource_id FROM uniform_resource
+     WHERE device_id = ? AND uri = ? AND content_digest = ?";
+
+const REGISTRY_HIVES: [(&str, &str); 5] = [
+    ("HKLM", "HKEY_LOCAL_MACHINE"),
+    ("HKCU", "HKEY_CURRENT_USER"),
+    ("HKCR", "HKEY_CLASSES_ROOT"),
+    ("HKU", "HKEY_USERS"),
+    ("HKCC", "HKEY_CURRENT_CONFIG"),
+];
+
+/// A plist file or registry key as it's stored: its configuration as JSON.
+struct HostConfigResource {
+    uri: String,
+    content: JsonValue,
+    last_modified_at: Option<String>,
+    elaboration: JsonValue,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct CaptureError {
+    source: String,
+    error: String,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+struct CaptureStats {
+    captured: usize,
+    unchanged: usize,
+    errors: Vec<CaptureError>,
+}
+
+impl CaptureStats {
+    fn error(&mut self, source: &str, err: anyhow::Error) {
+        warn!("[ingest_host_config] {source}: {err:#}");
+        self.errors.push(CaptureError {
+            source: source.to_string(),
+            error: format!("{err:#}"),
+        });
+    }
+}
+
+fn insert_host_config(
+    conn: &Connection,
+    device_id: &str,
+    ingest_session_id: &str,
+    resources: impl IntoIterator<Item = HostConfigResource>,
+    stats: &mut CaptureStats,
+) -> Result<()> {
+    let mut ins_ur_stmt = conn.prepare(INS_HOST_CONFIG_UR_SQL)?;
+    let mut sel_ur_stmt = conn.prepare(SEL_HOST_CONFIG_UR_SQL)?;
+    for resource in resources {
+        let content = serde_json::to_string_pretty(&resource.content)?;
+        let digest = sha1_hex(content.as_bytes());
+        let existing: Option<String> = sel_ur_stmt
+            .query_row(params![device_id, resource.uri, digest], |row| row.get(0))
+            .optional()?;
+        if existing.is_some() {
+            stats.unchanged += 1;
+            continue;
+        }
+        ins_ur_stmt
+            .query_row(
+                params![
+                    device_id,
+                    ingest_session_id,
+                    resource.uri,
+                    content,
+                    digest,
+                    content.len(),
+                    resource.last_modified_at,
+                    resource.elaboration.to_string(),
+                ],
+                |row| row.get::<_, String>(0),
+            )
+            .with_context(|| format!("[insert_host_config] {}", resource.uri))?;
+        stats.captured += 1;
+    }
+    Ok(())
+}
+
+/// Capture configuration into a new ingest session whose behavior and
+/// elaboration are recorded under `kind`.
+fn ingest_host_config(
+    kind: &str,
+    state_db_fs_path: &str,
+    state_db_init_sql: &[String],
+    behavior: JsonValue,
+    capture: impl FnOnce(&Connection, &str, &str) -> Result<CaptureStats>,
+) -> Result<String> {
+    let mut dbc = DbConn::new(state_db_fs_path, 0)
+        .with_context(|| format!("[ingest_{kind}] SQLite transaction in {state_db_fs_path}"))?;
+    let db_fs_path = dbc.db_fs_path.clone();
+    let tx = dbc.init(Some(state_db_init_sql))?;
+    let (device_id, _device_name) = upserted_device(&tx, &common::DEVICE)
+        .with_context(|| format!("[ingest_{kind}] upserted_device in {}", db_fs_path))?;
+
+    let behavior = json!({ kind: behavior }).to_string();
+    let ingest_session_id: String = tx
+        .query_row(
+            INS_UR_INGEST_SESSION_SQL,
+            params![device_id, None::<String>, behavior],
+            |row| row.get(0),
+        )
+        .with_context(|| format!("[ingest_{kind}] inserting ingest session in {}", db_fs_path))?;
+    debug!("{kind} Session: {ingest_session_id}");
+
+    let stats = capture(&tx, &device_id, &ingest_session_id)?;
+
+    let session_elaboration = json!({ kind: stats }).to_string();
+    tx.execute(
+        INS_UR_INGEST_SESSION_FINISH_SQL,
+        params![ingest_session_id, session_elaboration],
+    )
+    .with_context(|| format!("[ingest_{kind}] finishing session in {}", db_fs_path))?;
+    tx.commit().with_context(|| {
+        format!(
+            "[ingest_{kind}] unable to perform final commit in {}",
+            db_fs_path
+        )
+    })?;
+    Ok(ingest_session_id)
+}
+
+/// Capture macOS property lists (XML or binary) as JSON resources, one
+/// per file, so the device's configuration can be queried with SQLite's JSON
+/// functions.
+pub fn ingest_plist(args: &IngestPlistArgs) -> Result<String> {
+    ingest_host_config(
+        "plist",
+        &args.state_db_fs_path,
+        &args.state_db_init_sql,
+        json!({ "paths": args.paths }),
+        |conn, device_id, ingest_session_id| {
+            let mut stats = CaptureStats::default();
+            let mut resources = Vec::new();
+            for path in plist_paths(&args.paths, &mut stats) {
+                match plist_resource(&path) {
+                    Ok(resource) => resources.push(resource),
+                    Err(err) => stats.error(&path.to_string_lossy(), err),
+                }
+            }
+            insert_host_config(conn, device_id, ingest_session_id, resources, &mut stats)?;
+            Ok(stats)
+        },
+    )
+}
+
+/// The files matching `patterns`, which are paths or globs.
+fn plist_paths(patterns: &[String], stats: &mut CaptureStats) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    for pattern in patterns {
+        let matched = match glob::glob(pattern) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .filter(|path| path.is_file())
+                .collect::<Vec<_>>(),
+            Err(err) => {
+                stats.error(pattern, err.into());
+                continue;
+            }
+        };
+        if matched.is_empty() {
+            stats.error(pattern, anyhow::anyhow!("no file matches"));
+        }
+        paths.extend(matched);
+    }
+    paths.sort();
+    paths.dedup();
+    paths
+}
+
+fn plist_resource(path: &Path) -> Result<HostConfigResource> {
+    let bytes = std::fs::read(path)?;
+    let format = match bytes.starts_with(b"bplist") {
+        true => "binary",
+        false => "xml",
+    };
+    let value = plist::Value::from_reader(Cursor::new(&bytes))?;
+    let metadata = std::fs::metadata(path)?;
+    Ok(HostConfigResource {
+        uri: path.to_string_lossy().to_string(),
+        content: plist_json(value),
+        last_modified_at: metadata
+            .modified()
+            .ok()
+            .map(|at| chrono::DateTime::<chrono::Utc>::from(at).to_string()),
+        elaboration: json!({
+            "plist": {
+                "format": format,
+                "size_bytes": bytes.len(),
+            }
+        }),
+    })
+}
+
+/// The JSON of a plist value: data is base64 and dates are RFC 3339.
+fn plist_json(value: plist::Value) -> JsonValue {
+    match value {
+        plist::Value::Array(items) => JsonValue::Array(items.into_iter().map(plist_json).collect()),
+        plist::Value::Dictionary(dict) => JsonValue::Object(
+            dict.into_iter()
+                .map(|(key, value)| (key, plist_json(value)))
+                .collect(),
+        ),
+        plist::Value::Boolean(value) => json!(value),
+        plist::Value::Data(data) => json!(STANDARD.encode(data)),
+        plist::Value::Date(date) => json!(date.to_xml_format()),
+        plist::Value::Real(value) => json!(value),
+        plist::Value::Integer(value) => match value.as_signed() {
+            Some(value) => json!(value),
+            None => json!(value.as_unsigned()),
+        },
+        plist::Value::String(value) => json!(value),
+        plist::Value::Uid(uid) => json!(uid.get()),
+        _ => JsonValue::Null,
+    }
+}
+
+/// Capture the Windows registry keys matching `--key` globs as JSON resources,
+/// one per key with its values. The keys are read from `.reg` exports, which
+/// `reg export` writes from the live registry on Windows.
+pub fn ingest_registry(args: &IngestRegistryArgs) -> Result<String> {
+    let globs = RegistryKeyGlobs::new(&args.key)?;
+    if args.reg_file.is_empty() && !cfg!(windows) {
+        bail!("[ingest_registry] the live registry is only on Windows, pass --reg-file exports");
+    }
+    ingest_host_config(
+        "registry",
+        &args.state_db_fs_path,
+        &args.state_db_init_sql,
+        json!({ "keys": args.key, "reg_files": args.reg_file }),
+        |conn, device_id, ingest_session_id| {
+            let mut stats = CaptureStats::default();
+            let exports = match args.reg_file.is_empty() {
+                false => args
+                    .reg_file
+                    .iter()
+                    .map(|reg_file| {
+                        (
+                            reg_file.clone(),
+                            std::fs::read(reg_file).map_err(Into::into),
+                        )
+                    })
+                    .collect(),
+                true => globs
+                    .roots()
+                    .into_iter()
+                    .map(|root| {
+                        let export = export_registry_key(&root);
+                        (root, export)
+                    })
+                    .collect::<Vec<_>>(),
+            };
+            // keys exported more than once (e.g. overlapping roots) are captured once
+            let mut keys = BTreeMap::new();
+            for (source, export) in exports {
+                match export.and_then(|bytes| parse_reg_export(&decode_reg_export(&bytes)?)) {
+                    Ok(exported) => {
+                        for key in exported.into_iter().filter(|key| globs.is_match(&key.path)) {
+                            keys.entry(key.path.to_uppercase())
+                                .or_insert((source.clone(), key));
+                        }
+                    }
+                    Err(err) => stats.error(&source, err),
+                }
+            }
+            let resources = keys.into_values().map(|(source, key)| HostConfigResource {
+                elaboration: json!({
+                    "registry": {
+                        "source": source,
+                        "values": key.values.len(),
+                    }
+                }),
+                uri: key.path.clone(),
+                content: json!({ "key": key.path, "values": key.values }),
+                last_modified_at: None,
+            });
+            insert_host_config(conn, device_id, ingest_session_id, resources, &mut stats)?;
+            Ok(stats)
+        },
+    )
+}
+
+/// `reg export` of `key` (and its subkeys) from the live registry.
+fn export_registry_key(key: &str) -> Result<Vec<u8>> {
+    let export = tempfile::Builder::new().suffix(".reg").tempfile()?;
+    let output = std::process::Command::new("reg")
+        .arg("export")
+        .arg(key)
+        .arg(export.path())
+        .arg("/y")
+        .output()
+        .context("running reg export")?;
+    if !output.status.success() {
+        bail!(
+            "reg export failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(std::fs::read(export.path())?)
+}
+
+/// The key path with its hive spelled out and `/` between the keys, so `*`
+/// matches a single key.
+fn normalized_key_path(path: &str) -> String {
+    let path = path.trim().trim_matches('\\').replace('\\', "/");
+    let (hive, rest) = path.split_once('/').unwrap_or((&path, ""));
+    let hive = REGISTRY_HIVES
+        .iter()
+        .find(|(short, _)| short.eq_ignore_ascii_case(hive))
+        .map_or(hive, |(_, long)| long);
+    match rest {
+        "" => hive.to_string(),
+        rest => format!("{hive}/{rest}"),
+    }
+}
+
+/// `--key` globs: `*` matches one key level, `**` any number, case-insensitively
+/// as the registry does.
+struct RegistryKeyGlobs {
+    patterns: Vec<String>,
+    set: GlobSet,
+}
+
+impl RegistryKeyGlobs {
+    fn new(patterns: &[String]) -> Result<Self> {
+        let patterns: Vec<String> = patterns.iter().map(|p| normalized_key_path(p)).collect();
+        let mut builder = GlobSetBuilder::new();
+        for pattern in &patterns {
+            builder.add(
+                GlobBuilder::new(pattern)
+                    .case_insensitive(true)
+                    .literal_separator(true)
+                    .build()
+                    .with_context(|| format!("[ingest_registry] invalid key glob {pattern}"))?,
+            );
+        }
+        Ok(RegistryKeyGlobs {
+            set: builder.build()?,
+            patterns,
+        })
+    }
+
+    fn is_match(&self, key_path: &str) -> bool {
+        self.set.is_match(normalized_key_path(key_path))
+    }
+
+    /// The keys to export for the globs: their keys before the first wildcard.
+    fn roots(&self) -> Vec<String> {
+        let mut roots: Vec<String> = self
+            .patterns
+            .iter()
+            .map(|pattern| {
+                pattern
+                    .split('/')
+                    .take_while(|key| !key.contains(['*', '?', '[', '{']))
+                    .collect::<Vec<_>>()
+                    .join("\\")
+            })
+            .collect();
+        roots.sort();
+        roots.dedup();
+        roots
+    }
+}
+
+/// A key of a `.reg` export and its values, by name (`(Default)` for the
+/// key's default value).
+#[derive(Debug, PartialEq)]
+struct RegistryKey {
+    path: String,
+    values: Map<String, JsonValue>,
+}
+
+/// `regedit` and `reg export` write UTF-16LE with a BOM, `REGEDIT4` exports
+/// are ANSI.
+fn decode_reg_export(bytes: &[u8]) -> Result<String> {
+    match bytes {
+        [0xFF, 0xFE, utf16 @ ..] => Ok(String::from_utf16(
+            &utf16
+                .chunks_exact(2)
+                .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+                .collect::<Vec<_>>(),
+        )?),
+        [0xEF, 0xBB, 0xBF, utf8 @ ..] => Ok(String::from_utf8_lossy(utf8).to_string()),
+        _ => Ok(String::from_utf8_lossy(bytes).to_string()),
+    }
+}
+
+fn parse_reg_export(text: &str) -> Result<Vec<RegistryKey>> {
+    let mut lines = text.lines().map(|line| line.trim_end_matches('\r'));
+    let unicode = match lines.by_ref().find(|line| !line.trim().is_empty()) {
+        Some(header) if header.starts_with("Windows Registry Editor") => true,
+        Some(header) if header.starts_with("REGEDIT4") => false,
+        _ => bail!("not a registry export"),
+    };
+
+    let mut keys: Vec<RegistryKey> = Vec::new();
+    // deleted keys (`[-...]`) have no values to capture
+    let mut in_key = false;
+    while let Some(line) = lines.next() {
+        let mut line = line.trim_start().to_string();
+        // hex data continues on the next lines after a `\`
+        while line.ends_with('\\') {
+            line.pop();
+            match lines.next() {
+                Some(next) => line.push_str(next.trim()),
+                None => break,
+            }
+        }
+        if line.is_empty() || line.starts_with(';') {
+            continue;
+        }
+        if let Some(path) = line.strip_prefix('[') {
+            let path = path.trim_end().trim_end_matches(']');
+            in_key = !path.starts_with('-');
+            if in_key {
+                keys.push(RegistryKey {
+                    path: path.to_string(),
+                    values: Map::new(),
+                });
+            }
+            continue;
+        }
+        let Some(key) = keys.last_mut().filter(|_| in_key) else {
+            continue;
+        };
+        let (name, data) = parse_reg_value_name(&line)
+            .with_context(|| format!("[{}] invalid value {line}", key.path))?;
+        // `-` deletes the value
+        if data == "-" {
+            continue;
+        }
+        let value = parse_reg_value_data(data, unicode)
+            .with_context(|| format!("[{}] invalid value {line}", key.path))?;
+        key.values.insert(name, value);
+    }
+    Ok(keys)
+}
+
+/// The value's name and its data after the `=`.
+fn parse_reg_value_name(line: &str) -> Option<(String, &str)> {
+    if let Some(data) = line.strip_prefix('@') {
+        return Some((
+            "(Default)".to_string(),
+            data.trim_start().strip_prefix('=')?,
+        ));
+    }
+    let (name, len) = parse_reg_string(line)?;
+    Some((name, line[len..].trim_start().strip_prefix('=')?))
+}
+
+/// A `"quoted"` string at the start of `text` unescaped, with the length it
+/// took.
+fn parse_reg_string(text: &str) -> Option<(String, usize)> {
+    let mut chars = text.char_indices();
+    if chars.next()?.1 != '"' {
+        return None;
+    }
+    let mut unescaped = String::new();
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '\\' => unescaped.push(chars.next()?.1),
+            '"' => return Some((unescaped, idx + 1)),
+            c => unescaped.push(c),
+        }
+    }
+    None
+}
+
+fn parse_reg_value_data(data: &str, unicode: bool) -> Option<JsonValue> {
+    let data = data.trim();
+    if data.starts_with('"') {
+        let (value, _) = parse_reg_string(data)?;
+        return Some(json!({ "type": "REG_SZ", "data": value }));
+    }
+    if let Some(dword) = data.strip_prefix("dword:") {
+        let value = u32::from_str_radix(dword.trim(), 16).ok()?;
+        return Some(json!({ "type": "REG_DWORD", "data": value }));
+    }
+    let (kind, bytes) = data.split_once(':')?;
+    let kind = match kind {
+        "hex" => 3,
+        kind => u32::from_str_radix(kind.strip_prefix("hex(")?.strip_suffix(')')?, 16).ok()?,
+    };
+    let bytes = bytes
+        .split(',')
+        .map(str::trim)
+        .filter(|byte| !byte.is_empty())
+        .map(|byte| u8::from_str_radix(byte, 16))
+        .collect::<Result<Vec<u8>, _>>()
+        .ok()?;
+    let text = || match unicode {
+        true => String::from_utf16_lossy(
+            &bytes
+                .chunks_exact(2)
+                .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+                .collect::<Vec<_>>(),
+        ),
+        false => String::from_utf8_lossy(&bytes).to_string(),
+    };
+    let hex = || {
+        bytes
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>()
+    };
+    Some(match kind {
+        0 => json!({ "type": "REG_NONE", "data": hex() }),
+        1 => json!({ "type": "REG_SZ", "data": text().trim_end_matches('\0') }),
+        2 => json!({ "type": "REG_EXPAND_SZ", "data": text().trim_end_matches('\0') }),
+        3 => json!({ "type": "REG_BINARY", "data": hex() }),
+        4 if bytes.len() == 4 => json!({
+            "type": "REG_DWORD",
+            "data": u32::from_le_bytes(bytes.try_into().ok()?),
+        }),
+        5 if bytes.len() == 4 => json!({
+            "type": "REG_DWORD_BIG_ENDIAN",
+            "data": u32::from_be_bytes(bytes.try_into().ok()?),
+        }),
+        7 => json!({
+            "type": "REG_MULTI_SZ",
+            "data": text()
+                .split('\0')
+                .filter(|s| !s.is_empty())
+                .collect::<Vec<_>>(),
+        }),
+        0xb if bytes.len() == 8 => json!({
+            "type": "REG_QWORD",
+            "data": u64::from_le_bytes(bytes.try_into().ok()?),
+        }),
+        kind => json!({ "type": format!("hex({kind:x})"), "data": hex() }),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REG_EXPORT: &str = r#"Windows Registry Editor Version 5.00
+
+[HKEY_LOCAL_MACHINE\SOFTWARE\Policies\Google\Chrome]
+"PasswordManagerEnabled"=dword:00000000
+@="managed"
+"DownloadDirectory"="C:\\Users\\\"shared\"\\Downloads"
+"ExtensionInstallBlocklist"=hex(7):2a,00,00,00,00,00
+"ProxyServer"=hex(2):25,00,50,00,52,00,4f,00,58,00,59,00,25,00,00,00
+"Quota"=hex(b):00,00,00,00,01,00,00,00
+"Blob"=hex:de,ad,\
+  be,ef
+"Removed"=-
+
+[-HKEY_LOCAL_MACHINE\SOFTWARE\Policies\Old]
+"Ignored"=dword:00000001
+
+[HKEY_LOCAL_MACHINE\SOFTWARE\Policies\Google\Chrome\Recommended]
+"HomepageLocation"="https://example.com"
+
+[HKEY_LOCAL_MACHINE\SOFTWARE\Microsoft]
+"#;
+
+    #[test]
+    fn parse_registry_exports() -> Result<()> {
+        let mut utf16 = vec![0xFF, 0xFE];
+        utf16.extend(REG_EXPORT.encode_utf16().flat_map(u16::to_le_bytes));
+        let keys = parse_reg_export(&decode_reg_export(&utf16)?)?;
+        assert_eq!(keys.len(), 3);
+        assert_eq!(
+            JsonValue::Object(keys[0].values.clone()),
+            json!({
+                "PasswordManagerEnabled": { "type": "REG_DWORD", "data": 0 },
+                "(Default)": { "type": "REG_SZ", "data": "managed" },
+                "DownloadDirectory": { "type": "REG_SZ", "data": "C:\\Users\\\"shared\"\\Downloads" },
+                "ExtensionInstallBlocklist": { "type": "REG_MULTI_SZ", "data": ["*"] },
+                "ProxyServer": { "type": "REG_EXPAND_SZ", "data": "%PROXY%" },
+                "Quota": { "type": "REG_QWORD", "data": 4294967296u64 },
+                "Blob": { "type": "REG_BINARY", "data": "deadbeef" },
+            })
+        );
+        assert!(parse_reg_export("[HKEY_LOCAL_MACHINE\\SOFTWARE]").is_err());
+
+        let globs = RegistryKeyGlobs::new(&[
+            r"HKLM\software\policies\**".to_string(),
+            r"HKLM\SOFTWARE\*".to_string(),
+        ])?;
+        assert!(globs.is_match(&keys[1].path));
+        assert!(globs.is_match(&keys[2].path));
+        assert!(!globs.is_match(r"HKEY_LOCAL_MACHINE\SOFTWARE\Microsoft\Windows"));
+        assert_eq!(
+            globs.roots(),
+            vec![
+                r"HKEY_LOCAL_MACHINE\SOFTWARE".to_string(),
+                r"HKEY_LOCAL_MACHINE\software\policies".to_string(),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn capture_plists() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::write(
+            dir.path().join("com.example.app.plist"),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>AutoUpdate</key><true/>
+    <key>Interval</key><integer>86400</integer>
+    <key>Servers</key><array><string>a.example.com</string></array>
+    <key>Token</key><data>AQID</data>
+    <key>Installed</key><date>2024-03-01T10:00:00Z</date>
+</dict>
+</plist>"#,
+        )?;
+        std::fs::write(dir.path().join("broken.plist"), "<plist><dict>")?;
+
+        let pattern = dir.path().join("*.plist").to_string_lossy().to_string();
+        let mut stats = CaptureStats::default();
+        let paths = plist_paths(&[pattern], &mut stats);
+        assert_eq!(paths.len(), 2);
+        let resources: Vec<_> = paths
+            .iter()
+            .filter_map(|path| plist_resource(path).ok())
+            .collect();
+        assert_eq!(resources.len(), 1);
+
+        let mut dbc = DbConn::new(":memory:", 0)?;
+        let tx = dbc.init(None)?;
+        let (device_id, _) = upserted_device(&tx, &common::DEVICE)?;
+        let session_id: String = tx.query_row(
+            INS_UR_INGEST_SESSION_SQL,
+            params![device_id, None::<String>, "{}"],
+            |row| row.get(0),
+        )?;
+        insert_host_config(&tx, &device_id, &session_id, resources, &mut stats)?;
+        assert_eq!(stats.captured, 1);
+
+        let (nature, format, auto_update, token, installed): (
+            String,
+            String,
+            bool,
+            String,
+            String,
+        ) = tx.query_row(
+            "SELECT nature, elaboration ->> '$.plist.format', content ->> '$.AutoUpdate',
+                        content ->> '$.Token', content ->> '$.Installed'
+                   FROM uniform_resource WHERE uri LIKE '%com.example.app.plist'",
+            [],
+            |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                ))
+            },
+        )?;
+        assert_eq!(nature, "json");
+        assert_eq!(format, "xml");
+        assert!(auto_update);
+        assert_eq!(token, "AQID");
+        assert_eq!(installed, "2024-03-01T10:00:00Z");
+
+        let again = paths.iter().filter_map(|path| plist_resource(path).ok());
+        insert_host_config(&tx, &device_id, &session_id, again, &mut stats)?;
+        assert_eq!((stats.captured, stats.unchanged), (1, 1));
+        Ok(())
+    }
+}
@@ -0,0 +1,516 @@
+use std::{future::Future, time::Duration};
+
+use anyhow::{anyhow, Context, Result};
+use resource_imap::EmailResource;
+use rusqlite::{params, TransactionBehavior};
+use serde_json::json;
+use tokio::{
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    sync::{mpsc, oneshot},
+    task::JoinSet,
+};
+use tracing::{debug, error, info, warn};
+
+use super::{persist_message, stats::MailboxStats};
+use crate::{
+    cmd::serve::SmtpJournalArgs,
+    ingest::{IngestContext, INS_UR_INGEST_SESSION_FINISH_SQL, INS_UR_INGEST_SESSION_SQL},
+    persist::{upserted_device, DbConn},
+};
+
+/// Folder the journaled messages are stored in
+const JOURNAL_FOLDER: &str = "journal";
+/// Longest SMTP command line accepted (RFC 5321 allows 512 octets)
+const MAX_COMMAND_LINE: usize = 4096;
+/// Connections silent for this long are dropped
+const IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+/// Messages waiting for the writer before connections are made to wait
+const JOURNAL_QUEUE: usize = 16;
+
+/// A received message and where to report whether it was committed.
+struct Journaled {
+    email: EmailResource,
+    stored: oneshot::Sender<Result<()>>,
+}
+
+/// Listen for SMTP journaling connections until interrupted (Ctrl-C), storing
+/// each message in its own transaction before it's acknowledged so nothing a
+/// mail server considers delivered is lost. Returns the session ID.
+pub async fn serve_smtp_journal(args: &SmtpJournalArgs) -> Result<String> {
+    let listener = TcpListener::bind(&args.addr)
+        .await
+        .with_context(|| format!("[serve_smtp_journal] unable to listen on {}", args.addr))?;
+    info!(
+        "SMTP journal endpoint listening on {}",
+        listener.local_addr()?
+    );
+    serve(listener, args, async {
+        let _ = tokio::signal::ctrl_c().await;
+    })
+    .await
+}
+
+async fn serve(
+    listener: TcpListener,
+    args: &SmtpJournalArgs,
+    shutdown: impl Future<Output = ()>,
+) -> Result<String> {
+    let journal = Journal::start(args)?;
+    let ingest_session_id = journal.ingest_session_id.clone();
+    debug!("SMTP journal session: {ingest_session_id}");
+
+    // a single writer owns the database connection, connections wait for its ack
+    let (queue, received) = mpsc::channel::<Journaled>(JOURNAL_QUEUE);
+    let writer = tokio::task::spawn_blocking(move || journal.write(received));
+
+    let mut connections = JoinSet::new();
+    tokio::pin!(shutdown);
+    loop {
+        tokio::select! {
+            _ = &mut shutdown => break,
+            accepted = listener.accept() => {
+                let (stream, peer) = match accepted {
+                    Ok(accepted) => accepted,
+                    Err(err) => {
+                        warn!("[serve_smtp_journal] accept failed: {err}");
+                        continue;
+                    }
+                };
+                let queue = queue.clone();
+                let max_message_size = args.max_message_size;
+                let extract_attachments = args.extract_attachments;
+                connections.spawn(async move {
+                    debug!("SMTP connection from {peer}");
+                    if let Err(err) =
+                        converse(stream, queue, max_message_size, extract_attachments).await
+                    {
+                        debug!("SMTP connection from {peer} ended: {err:#}");
+                    }
+                });
+            }
+        }
+    }
+
+    // connections in the middle of a transaction are dropped, their servers retry
+    connections.shutdown().await;
+    drop(queue);
+    writer
+        .await
+        .with_context(|| "[serve_smtp_journal] the writer panicked")??;
+    info!("SMTP journal session {ingest_session_id} finished");
+    Ok(ingest_session_id)
+}
+
+/// The ingest session of a running endpoint, all messages are stored in its
+/// `journal` folder.
+struct Journal {
+    dbc: DbConn,
+    ingest_session_id: String,
+    device_id: String,
+    acct_id: String,
+    acct_folder_id: String,
+    mailbox: String,
+    addr: String,
+    stats: MailboxStats,
+    received: usize,
+    failed: usize,
+}
+
+impl Journal {
+    fn start(args: &SmtpJournalArgs) -> Result<Journal> {
+        let mut dbc = DbConn::new(&args.state_db_fs_path, 0).with_context(|| {
+            format!(
+                "[serve_smtp_journal] SQLite transaction in {}",
+                args.state_db_fs_path
+            )
+        })?;
+        let (ingest_session_id, device_id, acct_id, acct_folder_id) = {
+            let tx = dbc.init(None)?;
+            let (device_id, _) = upserted_device(&tx, &common::DEVICE)?;
+            let ingest_session_id: String = tx
+                .query_row(
+                    INS_UR_INGEST_SESSION_SQL,
+                    params![device_id, None::<String>, None::<String>],
+                    |row| row.get(0),
+                )
+                .with_context(|| "[serve_smtp_journal] Failed to create an ingest session")?;
+            let (acct_id, acct_folder_id) = journal_folder(&tx, &ingest_session_id, args)?;
+            tx.commit()
+                .with_context(|| "[serve_smtp_journal] Failed to commit the session")?;
+            (ingest_session_id, device_id, acct_id, acct_folder_id)
+        };
+        Ok(Journal {
+            dbc,
+            ingest_session_id,
+            device_id,
+            acct_id,
+            acct_folder_id,
+            mailbox: args.mailbox.clone(),
+            addr: args.addr.clone(),
+            stats: MailboxStats::default(),
+            received: 0,
+            failed: 0,
+        })
+    }
+
+    /// Store the received messages until every connection is gone, then
+    /// finish the session.
+    fn write(mut self, mut received: mpsc::Receiver<Journaled>) -> Result<()> {
+        while let Some(Journaled { email, stored }) = received.blocking_recv() {
+            let result = self.store(&email);
+            match &result {
+                Ok(_) => self.received += 1,
+                Err(err) => {
+                    self.failed += 1;
+                    error!(
+                        "[serve_smtp_journal] {} not stored: {err:#}",
+                        email.message_id
+                    );
+                }
+            }
+            // the connection may have been dropped meanwhile, its server retries
+            let _ = stored.send(result);
+        }
+        self.finish()
+    }
+
+    fn store(&mut self, email: &EmailResource) -> Result<()> {
+        let tx = self
+            .dbc
+            .conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let attachment_natures = {
+            let mut ingest_stmts = IngestContext::from_conn(&tx, &self.dbc.db_fs_path)?;
+            persist_message(
+                &mut ingest_stmts,
+                &self.ingest_session_id,
+                &self.device_id,
+                &self.acct_folder_id,
+                &self.mailbox,
+                email,
+            )?
+        };
+        tx.commit()?;
+        self.stats.record(email, &attachment_natures);
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<()> {
+        let tx = self.dbc.conn.transaction()?;
+        let mailbox_stats = self
+            .stats
+            .persist(&tx, &self.ingest_session_id, &self.acct_id)?;
+        let elaboration = json!({
+            "smtp_journal": {
+                "addr": self.addr,
+                "mailbox": self.mailbox,
+                "received_count": self.received,
+                "failed_count": self.failed,
+            },
+            "mailbox_stats": mailbox_stats,
+        });
+        tx.execute(
+            INS_UR_INGEST_SESSION_FINISH_SQL,
+            params![
+                self.ingest_session_id,
+                serde_json::to_string_pretty(&elaboration)?
+            ],
+        )?;
+        tx.commit()
+            .with_context(|| "[serve_smtp_journal] Failed to finish the session")
+    }
+}
+
+/// The account and folder rows the messages of a session are stored under.
+fn journal_folder(
+    tx: &rusqlite::Transaction,
+    ingest_session_id: &str,
+    args: &SmtpJournalArgs,
+) -> Result<(String, String)> {
+    let mut ingest_stmts = IngestContext::from_conn(tx, &args.state_db_fs_path)?;
+    let acct_id: String = ingest_stmts.ur_ingest_session_imap_account_stmt.query_row(
+        params![ingest_session_id, args.mailbox, None::<String>, args.addr],
+        |row| row.get(0),
+    )?;
+    let acct_folder_id: String = ingest_stmts
+        .ur_ingest_session_imap_acct_folder_stmt
+        .query_row(
+            params![
+                ingest_session_id,
+                acct_id,
+                JOURNAL_FOLDER,
+                json!({ "smtp_journal": args.addr }).to_string()
+            ],
+            |row| row.get(0),
+        )?;
+    Ok((acct_id, acct_folder_id))
+}
+
+/// Conduct an SMTP (RFC 5321) conversation with a mail server, handing each
+/// message to the writer and only accepting it once it was committed.
+async fn converse(
+    stream: TcpStream,
+    queue: mpsc::Sender<Journaled>,
+    max_message_size: usize,
+    extract_attachments: bool,
+) -> Result<()> {
+    let (read, mut write) = stream.into_split();
+    let mut reader = BufReader::new(read);
+    let hostname = common::DEVICE.name().to_string();
+    reply(
+        &mut write,
+        &format!("220 {hostname} surveilr SMTP journal ready"),
+    )
+    .await?;
+
+    let mut sender = None::<String>;
+    let mut recipients = 0;
+    loop {
+        let Some(line) = read_line(&mut reader, MAX_COMMAND_LINE).await? else {
+            return Ok(());
+        };
+        if !line.ends_with(b"\n") {
+            reply(&mut write, "500 5.5.2 line too long").await?;
+            return Ok(());
+        }
+        let line = String::from_utf8_lossy(&line);
+        let line = line.trim_end();
+        let (verb, params) = line.split_once(' ').unwrap_or((line, ""));
+        let response = match verb.to_ascii_uppercase().as_str() {
+            "EHLO" => format!("250-{hostname}\r\n250-SIZE {max_message_size}\r\n250 8BITMIME"),
+            "HELO" => format!("250 {hostname}"),
+            "MAIL" => match declared_size(params) {
+                Some(size) if size > max_message_size => {
+                    "552 5.3.4 message exceeds fixed maximum message size".to_string()
+                }
+                _ => {
+                    sender = Some(params.to_string());
+                    recipients = 0;
+                    "250 2.1.0 OK".to_string()
+                }
+            },
+            "RCPT" if sender.is_none() => "503 5.5.1 MAIL first".to_string(),
+            "RCPT" => {
+                recipients += 1;
+                "250 2.1.5 OK".to_string()
+            }
+            "DATA" if recipients == 0 => "503 5.5.1 RCPT first".to_string(),
+            "DATA" => {
+                reply(&mut write, "354 end data with <CR><LF>.<CR><LF>").await?;
+                sender = None;
+                recipients = 0;
+                match read_data(&mut reader, max_message_size).await? {
+                    Some(raw) => journal(&queue, &raw, extract_attachments).await,
+                    None => "552 5.3.4 message exceeds fixed maximum message size".to_string(),
+                }
+            }
+            "RSET" => {
+                sender = None;
+                recipients = 0;
+                "250 2.0.0 OK".to_string()
+            }
+            "NOOP" => "250 2.0.0 OK".to_string(),
+            "VRFY" => "252 2.1.5 cannot verify".to_string(),
+            "QUIT" => {
+                reply(&mut write, "221 2.0.0 bye").await?;
+                return Ok(());
+            }
+            _ => "502 5.5.2 command not recognized".to_string(),
+        };
+        reply(&mut write, &response).await?;
+    }
+}
+
+/// Hand a received message to the writer and wait for the reply to give.
+async fn journal(queue: &mpsc::Sender<Journaled>, raw: &[u8], extract_attachments: bool) -> String {
+    let email = match EmailResource::from_rfc822(raw, extract_attachments) {
+        Ok(email) => email,
+        Err(err) => {
+            warn!("[serve_smtp_journal] rejected a message: {err:#}");
+            return "554 5.6.0 message could not be parsed".to_string();
+        }
+    };
+    let (stored, committed) = oneshot::channel();
+    if queue.send(Journaled { email, stored }).await.is_err() {
+        return "421 4.3.2 shutting down".to_string();
+    }
+    match committed.await {
+        Ok(Ok(())) => "250 2.0.0 OK stored".to_string(),
+        _ => "451 4.3.0 message could not be stored, try again later".to_string(),
+    }
+}
+
+async fn reply<W: AsyncWrite + Unpin>(write: &mut W, response: &str) -> Result<()> {
+    write.write_all(response.as_bytes()).await?;
+    write.write_all(b"\r\n").await?;
+    write.flush().await?;
+    Ok(())
+}
+
+/// Read a line of at most `limit` bytes, `None` once the peer is gone.
+async fn read_line<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    limit: usize,
+) -> Result<Option<Vec<u8>>> {
+    let mut line = Vec::new();
+    let read = tokio::time::timeout(
+        IDLE_TIMEOUT,
+        reader.take(limit as u64).read_until(b'\n', &mut line),
+    )
+    .await
+    .map_err(|_| anyhow!("idle for {IDLE_TIMEOUT:?}"))??;
+    Ok((read > 0).then_some(line))
+}
+
+/// Read the message which follows `DATA` up to the lone `.` line, removing
+/// the dots added by transparency (RFC 5321 4.5.2). `None` when it's larger
+/// than `max_message_size`, the rest of it is read and discarded.
+async fn read_data<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    max_message_size: usize,
+) -> Result<Option<Vec<u8>>> {
+    let mut message = Vec::new();
+    let mut too_large = false;
+    let mut line_start = true;
+    loop {
+        let chunk = read_line(reader, max_message_size + 3)
+            .await?
+            .ok_or_else(|| anyhow!("connection closed during DATA"))?;
+        let starts_line = line_start;
+        line_start = chunk.ends_with(b"\n");
+        if starts_line && (chunk == b".\r\n" || chunk == b".\n") {
+            break;
+        }
+        if too_large {
+            continue;
+        }
+        let content = match starts_line && chunk.starts_with(b".") {
+            true => &chunk[1..],
+            false => &chunk[..],
+        };
+        if message.len() + content.len() > max_message_size {
+            too_large = true;
+            message = Vec::new();
+            continue;
+        }
+        message.extend_from_slice(content);
+    }
+    Ok((!too_large).then_some(message))
+}
+
+/// The `SIZE=` parameter of a `MAIL FROM` command (RFC 1870).
+fn declared_size(params: &str) -> Option<usize> {
+    params
+        .split_whitespace()
+        .find_map(|param| match param.split_once('=') {
+            Some((key, value)) if key.eq_ignore_ascii_case("SIZE") => value.parse().ok(),
+            _ => None,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn expect<R: AsyncBufRead + Unpin>(reader: &mut R, code: &str) -> String {
+        loop {
+            let line = read_line(reader, MAX_COMMAND_LINE).await.unwrap().unwrap();
+            let line = String::from_utf8(line).unwrap();
+            assert!(line.starts_with(code), "expected {code}, got {line}");
+            // multiline replies continue with `<code>-`
+            if line.as_bytes()[3] == b' ' {
+                return line;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_smtp_journal_round_trip() -> Result<()> {
+        let db = std::env::temp_dir().join(format!("surveilr-smtp-{}.db", ulid::Ulid::new()));
+        let args = SmtpJournalArgs {
+            state_db_fs_path: db.to_string_lossy().to_string(),
+            addr: "127.0.0.1:0".to_string(),
+            mailbox: "journal@example.com".to_string(),
+            max_message_size: 1024,
+            extract_attachments: true,
+        };
+        let listener = TcpListener::bind(&args.addr).await?;
+        let addr = listener.local_addr()?;
+        let (stop, stopped) = oneshot::channel::<()>();
+        let server = tokio::spawn(async move {
+            serve(listener, &args, async {
+                let _ = stopped.await;
+            })
+            .await
+        });
+
+        let (read, mut write) = TcpStream::connect(addr).await?.into_split();
+        let mut reader = BufReader::new(read);
+        expect(&mut reader, "220").await;
+        write
+            .write_all("EHLO mta.example.com\r\n".as_bytes())
+            .await?;
+        expect(&mut reader, "250").await;
+        write.write_all("DATA\r\n".as_bytes()).await?;
+        expect(&mut reader, "503").await;
+        write
+            .write_all("MAIL FROM:<a@example.com> SIZE=4096\r\n".as_bytes())
+            .await?;
+        expect(&mut reader, "552").await;
+        write
+            .write_all(
+                "MAIL FROM:<a@example.com>\r\nRCPT TO:<journal@example.com>\r\nDATA\r\n".as_bytes(),
+            )
+            .await?;
+        expect(&mut reader, "250").await;
+        expect(&mut reader, "250").await;
+        expect(&mut reader, "354").await;
+        write
+            .write_all(
+                concat!(
+                    "From: a@example.com\r\nTo: b@example.com\r\nSubject: Q3\r\n",
+                    "Message-ID: <q3@example.com>\r\nDate: Mon, 4 Mar 2024 09:00:00 +0000\r\n\r\n",
+                    "..numbers attached\r\n.\r\n"
+                )
+                .as_bytes(),
+            )
+            .await?;
+        assert!(expect(&mut reader, "250").await.contains("stored"));
+        write
+            .write_all(
+                "MAIL FROM:<a@example.com>\r\nRCPT TO:<journal@example.com>\r\nDATA\r\n".as_bytes(),
+            )
+            .await?;
+        for code in ["250", "250", "354"] {
+            expect(&mut reader, code).await;
+        }
+        let mut huge = "Subject: huge\r\n\r\n".to_string();
+        huge.push_str(&"x".repeat(2000));
+        huge.push_str("\r\n.\r\n");
+        write.write_all(huge.as_bytes()).await?;
+        expect(&mut reader, "552").await;
+        write.write_all(b"QUIT\r\n").await?;
+        expect(&mut reader, "221").await;
+
+        stop.send(()).unwrap();
+        let session_id = server.await??;
+
+        let dbc = DbConn::new(&db, 0)?;
+        let (uri, content): (String, String) = dbc.conn.query_row(
+            "SELECT uri, content FROM uniform_resource WHERE ingest_session_id = ? AND nature = 'text'",
+            [&session_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        assert_eq!(uri, "smtp://journal@example.com/q3@example.com");
+        assert!(content.contains("\r\n.numbers attached"));
+        let finished: String = dbc.conn.query_row(
+            "SELECT ingest_finished_at FROM ur_ingest_session WHERE ur_ingest_session_id = ?",
+            [&session_id],
+            |row| row.get(0),
+        )?;
+        assert!(!finished.is_empty());
+        std::fs::remove_file(&db)?;
+        Ok(())
+    }
+}
@@ -1,90 +1,250 @@
 use std::{collections::HashMap, time::Instant};
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
+use common::{clock::normalize_to_utc, secret::Secret};
+use futures_util::future::join_all;
 use indicatif::{ProgressBar, ProgressStyle};
 use resource_imap::{
     elaboration::{FolderElaboration, ImapElaboration},
-    imap, Folder, ImapConfig, ImapResource,
+    imap, EmailResource, Folder, FolderCheckpoint, ImapConfig, ImapResource,
 };
 use rusqlite::params;
 use serde_json::json;
 use sha1::{Digest, Sha1};
-use tracing::{debug, error};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, error, warn};
 
 use crate::{
     cmd::imap::IngestImapArgs,
-    ingest::{IngestContext, INS_UR_INGEST_SESSION_FINISH_SQL, INS_UR_INGEST_SESSION_SQL},
+    ingest::{
+        insert_lineage, IngestContext, INS_UR_INGEST_SESSION_FINISH_SQL, INS_UR_INGEST_SESSION_SQL,
+    },
 };
 
 use super::{upserted_device, DbConn};
 
-/// Main entry point for ingesting emails from IMAP.
-pub async fn ingest_imap(args: &IngestImapArgs) -> Result<()> {
-    let mut dbc = establish_db_connection(args)?;
-    let db_fs_path = &dbc.db_fs_path.clone();
+mod journal;
+mod stats;
 
-    let tx = start_transaction(&mut dbc, args)?;
-    let (device_id, _) = upsert_device(&tx)?;
-    let ingest_session_id = create_ingest_session(&tx, &device_id)?;
+pub use journal::serve_smtp_journal;
+use stats::MailboxStats;
 
-    debug!("Imap Session: {ingest_session_id}");
+/// Main entry point for ingesting emails from IMAP, returns the session ID.
+pub async fn ingest_imap(args: &IngestImapArgs) -> Result<String> {
+    let dbc = establish_db_connection(args)?;
 
-    let config: ImapConfig = args.clone().into();
+    let mut config: ImapConfig = args.clone().into();
+    config.resolve_secrets()?;
+
+    let imap_resource = imap(&config).await?;
+    ingest_mailbox(dbc, args, config, imap_resource).await
+}
+
+/// Ingests the folders of the mailbox `imap_resource` is connected to. The
+/// session is stored by a writer on a blocking thread of its own, which owns the
+/// RSSD, while up to `folder_concurrency` connections fetch the folders.
+async fn ingest_mailbox(
+    dbc: DbConn,
+    args: &IngestImapArgs,
+    config: ImapConfig,
+    mut imap_resource: Box<dyn ImapResource>,
+) -> Result<String> {
     let mut elaboration = ImapElaboration::new(&config);
 
-    let mut imap_resource = imap(&config).await?;
     imap_resource.init().await?;
     let available_folders = imap_resource.folders().await?;
     let mut folders_to_be_ingested = imap_resource.specified_folders(&config.folder).await?;
 
     elaboration.discovered_folder_count = available_folders.len();
+    // the Gmail API signs in with OAuth, the address comes from the mailbox profile
+    let account = match &config.gmail {
+        Some(_) => Some(imap_resource.username()),
+        None => config.username.clone(),
+    };
+
+    let resume_account = if args.resume {
+        match (&config.microsoft365, &config.gmail, &account) {
+            (None, None, Some(account)) => Some(account.clone()),
+            _ => {
+                warn!("[ingest_imap] --resume is only supported by IMAP servers, fetching the latest messages");
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let mailbox = MailboxWriter {
+        args: args.clone(),
+        account,
+        password: config.password.clone(),
+        addr: config.addr.clone(),
+        username: imap_resource.username(),
+        progress: imap_resource.progress(),
+        resume_account,
+    };
+    let connections = connections(
+        imap_resource,
+        config.folder_concurrency,
+        folders_to_be_ingested.len(),
+    )
+    .await?;
+
+    let (folders_tx, folders_rx) = mpsc::channel(connections.len());
+    let (checkpoints_tx, checkpoints_rx) = oneshot::channel();
+    let writer = tokio::task::spawn_blocking(move || {
+        mailbox.write(dbc, elaboration, checkpoints_tx, folders_rx)
+    });
+
+    let checkpoints = match checkpoints_rx.await {
+        Ok(checkpoints) => checkpoints,
+        // the writer failed before it could look them up
+        Err(_) => return writer.await?,
+    };
+    for folder in folders_to_be_ingested.iter_mut() {
+        if let Some(checkpoint) = checkpoints.get(&folder.name) {
+            folder.resume_from = Some(*checkpoint);
+        }
+    }
 
-    {
-        let mut ingest_stmts = IngestContext::from_conn(&tx, db_fs_path)
-            .with_context(|| format!("[ingest_imap] ingest_stmts in {}", db_fs_path))?;
-        let acct_id: String = ingest_stmts.ur_ingest_session_imap_account_stmt.query_row(
+    fetch_folders(connections, folders_to_be_ingested, folders_tx).await;
+    writer.await?
+}
+
+/// The mailbox's session, stored by `MailboxWriter::write` on the thread that
+/// owns the RSSD.
+struct MailboxWriter {
+    args: IngestImapArgs,
+    account: Option<String>,
+    password: Option<Secret>,
+    addr: Option<String>,
+    username: String,
+    progress: bool,
+    resume_account: Option<String>,
+}
+
+impl MailboxWriter {
+    /// Creates the session, sends the checkpoints `--resume` starts from and
+    /// persists each folder as it arrives through `folders` until every
+    /// connection is done, then finishes the session.
+    fn write(
+        self,
+        mut dbc: DbConn,
+        mut elaboration: ImapElaboration,
+        checkpoints: oneshot::Sender<HashMap<String, FolderCheckpoint>>,
+        mut folders: mpsc::Receiver<(Folder, Result<()>)>,
+    ) -> Result<String> {
+        let db_fs_path = &dbc.db_fs_path.clone();
+
+        let tx = start_transaction(&mut dbc, &self.args)?;
+        let (device_id, _) = upsert_device(&tx)?;
+        let ingest_session_id = create_ingest_session(&tx, &device_id)?;
+
+        debug!("Imap Session: {ingest_session_id}");
+
+        let resumed = match &self.resume_account {
+            Some(account) => folder_checkpoints(&tx, account)?,
+            None => HashMap::new(),
+        };
+        if checkpoints.send(resumed).is_err() {
+            return Err(anyhow!("[ingest_imap] the mailbox connections are gone"));
+        }
+
+        {
+            let mut ingest_stmts = IngestContext::from_conn(&tx, db_fs_path)
+                .with_context(|| format!("[ingest_imap] ingest_stmts in {}", db_fs_path))?;
+            let acct_id: String = ingest_stmts.ur_ingest_session_imap_account_stmt.query_row(
+                params![
+                    ingest_session_id,
+                    self.account,
+                    self.password.as_ref().map(|p| p.expose()),
+                    self.addr
+                ],
+                |row| row.get(0),
+            )?;
+
+            let start = Instant::now();
+            let mut stats = MailboxStats::default();
+            let mut folder_elaborations = HashMap::new();
+            while let Some((folder, fetched)) = folders.blocking_recv() {
+                if let Err(err) = fetched {
+                    error!("{err}");
+                    continue;
+                }
+                let elaboration = persist_folder(
+                    &mut ingest_stmts,
+                    &ingest_session_id,
+                    &device_id,
+                    &acct_id,
+                    &self.username,
+                    self.progress,
+                    &folder,
+                    &mut stats,
+                )?;
+                folder_elaborations.insert(folder.name, elaboration);
+            }
+            let email_ingest_duration = format!("{:.2?}", start.elapsed());
+
+            let mailbox_stats = stats.persist(&tx, &ingest_session_id, &acct_id)?;
+            for spike in mailbox_stats["volume_spikes"]
+                .as_array()
+                .into_iter()
+                .flatten()
+            {
+                warn!(
+                    "[ingest_imap] {} messages on {}, previous sessions averaged {:.1} a day",
+                    spike["messages"],
+                    spike["day"],
+                    spike["baseline_mean"].as_f64().unwrap_or_default()
+                );
+            }
+
+            elaboration.folders = folder_elaborations;
+            elaboration.email_ingest_duration = Some(email_ingest_duration);
+            elaboration.mailbox_stats = Some(mailbox_stats);
+        }
+
+        match tx.execute(
+            INS_UR_INGEST_SESSION_FINISH_SQL,
             params![
                 ingest_session_id,
-                config.username,
-                config.password,
-                config.addr
+                serde_json::to_string_pretty(&elaboration)?
             ],
-            |row| row.get(0),
-        )?;
-
-        let start = Instant::now();
-        let folder_elaborations = process_folders(
-            &mut ingest_stmts,
-            &ingest_session_id,
-            &device_id,
-            &acct_id,
-            &mut folders_to_be_ingested,
-            &mut imap_resource,
-        ).await?;
-        let email_ingest_duration = format!("{:.2?}", start.elapsed());
-
-
-        elaboration.folders = folder_elaborations;
-        elaboration.email_ingest_duration = Some(email_ingest_duration);
-    }
-
-    match tx.execute(
-        INS_UR_INGEST_SESSION_FINISH_SQL,
-        params![
-            ingest_session_id,
-            serde_json::to_string_pretty(&elaboration)?
-        ],
-    ) {
-        Ok(_) => {}
-        Err(err) => {
-            error!(
-                "[ingest_files] unable to execute SQL {} in {}: {}",
-                INS_UR_INGEST_SESSION_FINISH_SQL, db_fs_path, err
-            )
+        ) {
+            Ok(_) => {}
+            Err(err) => {
+                error!(
+                    "[ingest_files] unable to execute SQL {} in {}: {}",
+                    INS_UR_INGEST_SESSION_FINISH_SQL, db_fs_path, err
+                )
+            }
         }
+
+        finalize_transaction(tx)?;
+        Ok(ingest_session_id)
     }
+}
 
-    finalize_transaction(tx)
+/// The checkpoints of the folders of `email` kept by previous sessions.
+fn folder_checkpoints(
+    tx: &rusqlite::Transaction,
+    email: &str,
+) -> Result<HashMap<String, FolderCheckpoint>> {
+    let mut stmt = tx.prepare(
+        "SELECT folder_name, uid_validity, last_uid FROM ur_ingest_imap_folder_state WHERE email = ?",
+    )?;
+    let checkpoints = stmt
+        .query_map([email], |row| {
+            Ok((
+                row.get(0)?,
+                FolderCheckpoint {
+                    uid_validity: row.get(1)?,
+                    last_uid: row.get(2)?,
+                },
+            ))
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(checkpoints)
 }
 
 /// Establishes a connection to the database.
@@ -119,242 +279,593 @@ fn create_ingest_session(tx: &rusqlite::Transaction, device_id: &String) -> Resu
     .with_context(|| "[ingest_imap] Failed to create an ingest session")
 }
 
-async fn process_folders(
+/// `resource` and the additional connections to its mailbox, up to
+/// `folder_concurrency` but no more than there are folders.
+async fn connections(
+    resource: Box<dyn ImapResource>,
+    folder_concurrency: usize,
+    folders_count: usize,
+) -> Result<Vec<Box<dyn ImapResource>>> {
+    let connections_count = folder_concurrency.clamp(1, folders_count.max(1));
+    let mut connections = Vec::with_capacity(connections_count);
+    for _ in 1..connections_count {
+        connections.push(
+            resource
+                .connection()
+                .await
+                .with_context(|| "[ingest_imap] Failed to open an additional mailbox connection")?,
+        );
+    }
+    connections.insert(0, resource);
+    Ok(connections)
+}
+
+/// Fetches the folders over the `connections`, each one fetching its share in
+/// order, and sends every folder to the writer as soon as it has been downloaded.
+async fn fetch_folders(
+    connections: Vec<Box<dyn ImapResource>>,
+    folders: Vec<Folder>,
+    writer: mpsc::Sender<(Folder, Result<()>)>,
+) {
+    debug!(
+        "Fetching {} folders over {} connection(s)",
+        folders.len(),
+        connections.len()
+    );
+
+    let mut queues: Vec<Vec<Folder>> = (0..connections.len()).map(|_| Vec::new()).collect();
+    let queues_count = queues.len();
+    for (index, folder) in folders.into_iter().enumerate() {
+        queues[index % queues_count].push(folder);
+    }
+
+    join_all(
+        connections
+            .into_iter()
+            .zip(queues)
+            .map(|(mut conn, queue)| {
+                let writer = writer.clone();
+                async move {
+                    for mut folder in queue {
+                        let fetched = conn.process_messages_in_folder(&mut folder).await;
+                        if writer.send((folder, fetched)).await.is_err() {
+                            // the writer has stopped, most likely because of an error
+                            break;
+                        }
+                    }
+                }
+            }),
+    )
+    .await;
+}
+
+#[allow(clippy::too_many_arguments)]
+fn persist_folder(
     ingest_stmts: &mut IngestContext<'_>,
     ingest_session_id: &str,
     device_id: &str,
     acct_id: &str,
-    folders: &mut [Folder],
-    resource: &mut Box<dyn ImapResource>,
-) -> Result<HashMap<String, FolderElaboration>> {
-    let mut folder_elaborations = HashMap::new();
-
-    for folder in folders.iter_mut() {
-        match resource.process_messages_in_folder(folder).await {
-            Ok(_) => {}
-            Err(err) => {
-                error!("{err}");
-                continue;
-            }
-        };
-
-        let Folder {
-            name,
-            messages,
-            metadata,
-        } = folder;
-
+    username: &str,
+    progress: bool,
+    folder: &Folder,
+    stats: &mut MailboxStats,
+) -> Result<FolderElaboration> {
+    let Folder {
+        name,
+        messages,
+        metadata,
+        checkpoint,
+        ..
+    } = folder;
+
+    let pb = if progress {
         let pb = ProgressBar::new(messages.len() as u64);
-        if resource.progress() {
-            let pb = ProgressBar::new(messages.len() as u64);
-            pb.set_style(ProgressStyle::default_bar()
+        pb.set_style(ProgressStyle::default_bar()
                 .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos:>7}/{len:7} {msg}")?
                 .progress_chars("##-"));
-            pb.set_message(format!("Processing Messages in folder: {}", name));
-        }
+        pb.set_message(format!("Processing Messages in folder: {}", name));
+        pb
+    } else {
+        ProgressBar::hidden()
+    };
 
-        let mut elaboration = FolderElaboration::new(name, messages.len());
-        let account_elaboration = json!({ "metadata": serde_json::to_string_pretty(metadata)? });
+    let mut elaboration = FolderElaboration::new(name, messages.len());
+    let account_elaboration = json!({ "metadata": serde_json::to_string_pretty(metadata)? });
 
-        let acct_folder_id: String = {
-            let start = Instant::now();
-            let result = ingest_stmts
-                .ur_ingest_session_imap_acct_folder_stmt
-                .query_row(
-                    params![
-                        ingest_session_id,
-                        acct_id,
-                        name.to_string(),
-                        account_elaboration.to_string(),
-                    ],
-                    |row| row.get(0),
-                )?;
-            debug!("Account folder ID query time: {:.2?}", start.elapsed());
-            result
-        };
+    let acct_folder_id: String = {
+        let start = Instant::now();
+        let result = ingest_stmts
+            .ur_ingest_session_imap_acct_folder_stmt
+            .query_row(
+                params![
+                    ingest_session_id,
+                    acct_id,
+                    name.to_string(),
+                    account_elaboration.to_string(),
+                ],
+                |row| row.get(0),
+            )?;
+        debug!("Account folder ID query time: {:.2?}", start.elapsed());
+        result
+    };
+
+    let mut text_plain_count = 0;
+    let mut html_content_count = 0;
+    let mut attachment_count = 0;
+
+    for email in messages.iter() {
+        let attachment_natures = persist_message(
+            ingest_stmts,
+            ingest_session_id,
+            device_id,
+            &acct_folder_id,
+            username,
+            email,
+        )?;
+        text_plain_count += email.text_plain.len();
+        html_content_count += email.text_html.len();
+        attachment_count += attachment_natures.len();
+        stats.record(email, &attachment_natures);
 
-        let mut text_plain_count = 0;
-        let mut html_content_count = 0;
-
-        for email in messages.iter() {
-            let text = &email.raw_text;
-            let uri = format!("smtp://{}/{}", resource.username(), email.message_id);
-
-            // 4. get all the attachments and do the same
-
-            // 1. insert the raw text into ur, nature is text
-            let ur_id: String = {
-                let start = Instant::now(); // Start timing
-                let result = ingest_stmts.ins_ur_stmt.query_row(
-                    params![
-                        device_id,
-                        ingest_session_id,
-                        &None::<String>,
-                        format!("smtp://{}/{}", resource.username(), email.message_id),
-                        "text".to_string(),
-                        email.raw_text,
-                        {
-                            let mut hasher = Sha1::new();
-                            hasher.update(email.raw_text.as_bytes());
-                            format!("{:x}", hasher.finalize())
-                        },
-                        email.raw_text.as_bytes().len(),
-                        email.date,
-                        &None::<String>, // content_fm_body_attrs
-                        &None::<String>, // frontmatter
-                        acct_folder_id,
-                    ],
-                    |row| row.get(0),
-                )?;
-                debug!("Uniform Resource insert time: {:.2?}", start.elapsed()); // Print elapsed time
-                result
-            };
-
-            let _ur_sess_message_id: String = {
-                let start = Instant::now();
-                let result = ingest_stmts
-                    .ur_ingest_session_imap_acct_folder_message_stmt
-                    .query_row(
-                        params![
-                            ingest_session_id,
-                            acct_folder_id,
-                            ur_id,
-                            text,
-                            email.message_id,
-                            email.subject,
-                            email.from,
-                            serde_json::to_string_pretty(&email.cc).unwrap_or("[]".to_string()),
-                            serde_json::to_string_pretty(&email.bcc).unwrap_or("[]".to_string()),
-                            serde_json::to_string_pretty(&email.references)
-                                .unwrap_or("[".to_string()),
-                        ],
-                        |row| row.get(0),
-                    )?;
-                debug!("IMAP Acct Message insert time: {:.2?}", start.elapsed()); // Print elapsed time
-                result
-            };
+        if progress {
+            pb.inc(1);
+        }
+    }
 
-            {
-                let json = &email.raw_json;
-                let size = json.as_bytes().len();
-                let hash = {
-                    let mut hasher = Sha1::new();
-                    hasher.update(json.as_bytes());
-                    format!("{:x}", hasher.finalize())
-                };
-                let start = Instant::now();
-                // 2. insert the whole json into ur, nature is json
-                let _: String = ingest_stmts.ins_ur_stmt.query_row(
-                    params![
-                        device_id,
-                        ingest_session_id,
-                        &None::<String>,
-                        format!("{uri}/json"),
-                        "json".to_string(),
-                        json,
-                        hash,
-                        size,
-                        email.date,
-                        &None::<String>, // content_fm_body_attrs
-                        &None::<String>, // frontmatter
-                        acct_folder_id,
-                    ],
-                    |row| row.get(0),
-                )?;
-                debug!("Full email JSON insert time: {:.2?}", start.elapsed());
-            }
+    pb.finish_with_message(format!("Finished processing folder: {}", name));
+
+    // the next `--resume` starts after the messages stored above
+    if let Some(checkpoint) = checkpoint {
+        ingest_stmts
+            .ur_ingest_imap_folder_state_stmt
+            .execute(params![
+                username,
+                name,
+                checkpoint.uid_validity,
+                checkpoint.last_uid,
+                ingest_session_id,
+            ])?;
+    }
 
-            // 3. take out all the text/plain, insert it into ur as a row, nature text
-            let start = Instant::now();
-            for plain_text in &email.text_plain {
-                let size = plain_text.as_bytes().len();
-                let hash = {
-                    let mut hasher = Sha1::new();
-                    hasher.update(plain_text.as_bytes());
-                    format!("{:x}", hasher.finalize())
-                };
-
-                let _: String = ingest_stmts.ins_ur_stmt.query_row(
-                    params![
-                        device_id,
-                        ingest_session_id,
-                        &None::<String>,
-                        format!("{uri}/txt"),
-                        "txt".to_string(),
-                        plain_text,
-                        hash,
-                        size,
-                        email.date,
-                        &None::<String>, // content_fm_body_attrs
-                        &None::<String>, // frontmatter
-                        acct_folder_id,
-                    ],
-                    |row| row.get(0),
-                )?;
-            }
-            debug!(
-                "It took {:.2?} to insert {} plain texts in Uniform Resource",
-                start.elapsed(),
-                email.text_plain.len()
-            );
-            text_plain_count += email.text_plain.len();
+    elaboration.html_content_count = html_content_count;
+    elaboration.text_plain_count = text_plain_count;
+    elaboration.attachment_count = attachment_count;
 
-            let start = Instant::now();
-            // 4. take out the text/html, insert it into uniform_resource, transform it to json and then put it in uniform_resource_transform.
-            for html in &email.text_html {
-                let size = html.as_bytes().len();
-                let hash = {
+    Ok(elaboration)
+}
+
+/// Store a message as `uniform_resource` rows (the raw message, its JSON, its
+/// plain text and HTML bodies and its attachments) in the folder
+/// `acct_folder_id`, returning the natures of the stored attachments.
+pub(crate) fn persist_message(
+    ingest_stmts: &mut IngestContext<'_>,
+    ingest_session_id: &str,
+    device_id: &str,
+    acct_folder_id: &str,
+    username: &str,
+    email: &EmailResource,
+) -> Result<Vec<String>> {
+    let text = &email.raw_text;
+    let uri = format!("smtp://{}/{}", username, email.message_id);
+    // UTC like walked files' times, the message row keeps the sender's offset
+    let last_modified_at = normalize_to_utc(&email.date)
+        .map(|date| date.utc)
+        .unwrap_or_else(|| email.date.clone());
+
+    // 1. insert the raw text into ur, nature is text
+    let ur_id: String = {
+        let start = Instant::now(); // Start timing
+        let result = ingest_stmts.ins_ur_stmt.query_row(
+            params![
+                device_id,
+                ingest_session_id,
+                &None::<String>,
+                format!("smtp://{}/{}", username, email.message_id),
+                "text".to_string(),
+                email.raw_text,
+                {
                     let mut hasher = Sha1::new();
-                    hasher.update(html.as_bytes());
+                    hasher.update(email.raw_text.as_bytes());
                     format!("{:x}", hasher.finalize())
-                };
-                let _ur_id: String = ingest_stmts.ins_ur_stmt.query_row(
-                    params![
-                        device_id,
-                        ingest_session_id,
-                        &None::<String>,
-                        format!("{uri}/html"),
-                        "html".to_string(),
-                        html,
-                        hash,
-                        size,
-                        email.date,
-                        &None::<String>, // content_fm_body_attrs
-                        &None::<String>, // frontmatter
-                        acct_folder_id,
-                    ],
-                    |row| row.get(0),
-                )?;
-            }
-            debug!(
-                "It took {:.2?} to insert {} htmls in Uniform Resource",
-                start.elapsed(),
-                email.text_html.len()
-            );
-            html_content_count += email.text_html.len();
-
-            if resource.progress() {
-                pb.inc(1);
-            }
-        }
+                },
+                email.raw_text.len(),
+                &last_modified_at,
+                &None::<String>, // content_fm_body_attrs
+                &None::<String>, // frontmatter
+                acct_folder_id,
+            ],
+            |row| row.get(0),
+        )?;
+        debug!("Uniform Resource insert time: {:.2?}", start.elapsed()); // Print elapsed time
+        result
+    };
+    // the same message in several folders (or mailboxes) shares its Message-ID
+    if !email.message_id.is_empty() {
+        ingest_stmts.set_semantic_identity(&ur_id, &email.message_id)?;
+    }
 
-        pb.finish_with_message(format!("Finished processing folder: {}", name));
+    let _ur_sess_message_id: String = {
+        let start = Instant::now();
+        let result = ingest_stmts
+            .ur_ingest_session_imap_acct_folder_message_stmt
+            .query_row(
+                params![
+                    ingest_session_id,
+                    acct_folder_id,
+                    ur_id,
+                    text,
+                    email.message_id,
+                    email.subject,
+                    email.from,
+                    serde_json::to_string_pretty(&email.cc).unwrap_or("[]".to_string()),
+                    serde_json::to_string_pretty(&email.bcc).unwrap_or("[]".to_string()),
+                    serde_json::to_string_pretty(&email.references).unwrap_or("[".to_string()),
+                    email.date,
+                ],
+                |row| row.get(0),
+            )?;
+        debug!("IMAP Acct Message insert time: {:.2?}", start.elapsed()); // Print elapsed time
+        result
+    };
 
-        // println!(
-        //     "Processing all the emails for the {} folder took {:.2?}",
-        //     folder.name,
-        //     folder_process_start.elapsed()
-        // );
+    {
+        let json = &email.raw_json;
+        let size = json.len();
+        let hash = {
+            let mut hasher = Sha1::new();
+            hasher.update(json.as_bytes());
+            format!("{:x}", hasher.finalize())
+        };
+        let start = Instant::now();
+        // 2. insert the whole json into ur, nature is json
+        let _: String = ingest_stmts.ins_ur_stmt.query_row(
+            params![
+                device_id,
+                ingest_session_id,
+                &None::<String>,
+                format!("{uri}/json"),
+                "json".to_string(),
+                json,
+                hash,
+                size,
+                &last_modified_at,
+                &None::<String>, // content_fm_body_attrs
+                &None::<String>, // frontmatter
+                acct_folder_id,
+            ],
+            |row| row.get(0),
+        )?;
+        debug!("Full email JSON insert time: {:.2?}", start.elapsed());
+    }
+
+    // 3. take out all the text/plain, insert it into ur as a row, nature text
+    let start = Instant::now();
+    for plain_text in &email.text_plain {
+        let size = plain_text.len();
+        let hash = {
+            let mut hasher = Sha1::new();
+            hasher.update(plain_text.as_bytes());
+            format!("{:x}", hasher.finalize())
+        };
 
-        elaboration.html_content_count = html_content_count;
-        elaboration.text_plain_count = text_plain_count;
-        folder_elaborations.insert(name.to_string(), elaboration);
+        let _: String = ingest_stmts.ins_ur_stmt.query_row(
+            params![
+                device_id,
+                ingest_session_id,
+                &None::<String>,
+                format!("{uri}/txt"),
+                "txt".to_string(),
+                plain_text,
+                hash,
+                size,
+                &last_modified_at,
+                &None::<String>, // content_fm_body_attrs
+                &None::<String>, // frontmatter
+                acct_folder_id,
+            ],
+            |row| row.get(0),
+        )?;
+    }
+    debug!(
+        "It took {:.2?} to insert {} plain texts in Uniform Resource",
+        start.elapsed(),
+        email.text_plain.len()
+    );
+
+    let start = Instant::now();
+    // 4. take out the text/html, insert it into uniform_resource, transform it to json and then put it in uniform_resource_transform.
+    for html in &email.text_html {
+        let size = html.len();
+        let hash = {
+            let mut hasher = Sha1::new();
+            hasher.update(html.as_bytes());
+            format!("{:x}", hasher.finalize())
+        };
+        let _ur_id: String = ingest_stmts.ins_ur_stmt.query_row(
+            params![
+                device_id,
+                ingest_session_id,
+                &None::<String>,
+                format!("{uri}/html"),
+                "html".to_string(),
+                html,
+                hash,
+                size,
+                &last_modified_at,
+                &None::<String>, // content_fm_body_attrs
+                &None::<String>, // frontmatter
+                acct_folder_id,
+            ],
+            |row| row.get(0),
+        )?;
+    }
+    debug!(
+        "It took {:.2?} to insert {} htmls in Uniform Resource",
+        start.elapsed(),
+        email.text_html.len()
+    );
+
+    // 5. store each attachment as its own resource, linked to the message
+    let mut attachment_natures = Vec::new();
+    for (index, attachment) in email.attachments.iter().flatten().enumerate() {
+        let nature = attachment_nature(&attachment.filename, &attachment.content_type);
+        let hash = {
+            let mut hasher = Sha1::new();
+            hasher.update(&attachment.content);
+            format!("{:x}", hasher.finalize())
+        };
+        let attachment_ur_id: String = ingest_stmts.ins_ur_stmt.query_row(
+            params![
+                device_id,
+                ingest_session_id,
+                &None::<String>,
+                format!("{uri}/attachment/{index}/{}", attachment.filename),
+                nature,
+                attachment.content,
+                hash,
+                attachment.content.len(),
+                &last_modified_at,
+                &None::<String>, // content_fm_body_attrs
+                &None::<String>, // frontmatter
+                acct_folder_id,
+            ],
+            |row| row.get(0),
+        )?;
+        insert_lineage(
+            ingest_stmts,
+            ingest_session_id,
+            Some(&ur_id),
+            &attachment_ur_id,
+            None,
+            None,
+        )?;
+        attachment_natures.push(nature);
     }
+    Ok(attachment_natures)
+}
 
-    Ok(folder_elaborations)
+/// The nature of an attachment is its file extension, like files walked by
+/// `ingest files`, or its content type when the file name has none.
+fn attachment_nature(filename: &str, content_type: &str) -> String {
+    match std::path::Path::new(filename).extension() {
+        Some(extn) => extn.to_string_lossy().to_lowercase(),
+        None => content_type.to_string(),
+    }
 }
 
 fn finalize_transaction(tx: rusqlite::Transaction) -> Result<()> {
     tx.commit()
         .with_context(|| "[ingest_imap] Failed to commit the transaction")
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    };
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+    use clap::Parser;
+
+    use super::*;
+    use crate::ingest::INS_UR_INGEST_SESSION_IMAP_ACCT;
+    use crate::persist::{upserted_device, DbConn};
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        imap: IngestImapArgs,
+    }
+
+    /// The connection which fetched a folder, its name and its `resume_from`.
+    type FetchedFolder = (usize, String, Option<FolderCheckpoint>);
+
+    /// A mailbox with five empty folders which take a while to fetch, recording
+    /// how many are fetched at once and which connection fetched them, in order.
+    #[derive(Clone, Default)]
+    struct SlowMailbox {
+        connection: usize,
+        opened: Arc<AtomicUsize>,
+        in_flight: Arc<AtomicUsize>,
+        max_in_flight: Arc<AtomicUsize>,
+        fetched: Arc<Mutex<Vec<FetchedFolder>>>,
+    }
+
+    #[async_trait]
+    impl ImapResource for SlowMailbox {
+        fn progress(&mut self) -> bool {
+            false
+        }
+
+        async fn init(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn folders(&mut self) -> Result<Vec<String>> {
+            Ok((0..5).map(|index| format!("folder-{index}")).collect())
+        }
+
+        async fn specified_folders(&mut self, _folder_pattern: &str) -> Result<Vec<Folder>> {
+            Ok(self
+                .folders()
+                .await?
+                .into_iter()
+                .map(Folder::from)
+                .collect())
+        }
+
+        async fn process_messages_in_folder(&mut self, folder: &mut Folder) -> Result<()> {
+            let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            self.fetched.lock().unwrap().push((
+                self.connection,
+                folder.name.clone(),
+                folder.resume_from,
+            ));
+            folder.checkpoint = Some(FolderCheckpoint {
+                uid_validity: 1700000000,
+                last_uid: 10,
+            });
+            Ok(())
+        }
+
+        fn username(&mut self) -> String {
+            "ops@example.com".to_string()
+        }
+
+        async fn connection(&self) -> Result<Box<dyn ImapResource>> {
+            let connection = self.opened.fetch_add(1, Ordering::SeqCst) + 1;
+            Ok(Box::new(SlowMailbox {
+                connection,
+                ..self.clone()
+            }))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_folders_fetched_concurrently_in_order() -> Result<()> {
+        let state = tempfile::tempdir()?;
+        let state_db = state.path().join("rssd.sqlite.db");
+        let args = Cli::parse_from([
+            "imap",
+            "-d",
+            &state_db.to_string_lossy(),
+            "-u",
+            "ops@example.com",
+            "--folder-concurrency",
+            "2",
+            "--resume",
+        ])
+        .imap;
+        let mailbox = SlowMailbox::default();
+        let ingest = |mailbox: &SlowMailbox| {
+            let mailbox = mailbox.clone();
+            let args = args.clone();
+            async move {
+                let dbc = DbConn::new(&args.state_db_fs_path, 0)?;
+                ingest_mailbox(dbc, &args, args.clone().into(), Box::new(mailbox)).await
+            }
+        };
+
+        let session_id = ingest(&mailbox).await?;
+        // one connection was opened besides the first and no more than two
+        // folders were ever fetched at once
+        assert_eq!(mailbox.opened.load(Ordering::SeqCst), 1);
+        assert_eq!(mailbox.max_in_flight.load(Ordering::SeqCst), 2);
+        let fetched_by = |connection: usize| -> Vec<String> {
+            mailbox
+                .fetched
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(by, _, _)| *by == connection)
+                .map(|(_, name, _)| name.clone())
+                .collect()
+        };
+        assert_eq!(fetched_by(0), ["folder-0", "folder-2", "folder-4"]);
+        assert_eq!(fetched_by(1), ["folder-1", "folder-3"]);
+
+        let dbc = DbConn::open(&state_db, 0)?;
+        let (folders, checkpoints): (usize, usize) = dbc.conn.query_row(
+            "SELECT (SELECT COUNT(*) FROM json_each(s.elaboration, '$.folders')),
+                    (SELECT COUNT(*) FROM ur_ingest_imap_folder_state WHERE ingest_session_id = s.ur_ingest_session_id)
+               FROM ur_ingest_session s
+              WHERE s.ur_ingest_session_id = ?",
+            [&session_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        // every folder was stored
+        assert_eq!((folders, checkpoints), (5, 5));
+
+        // the writer looked up the checkpoints before any folder was fetched
+        mailbox.fetched.lock().unwrap().clear();
+        // sessions of a device are unique by their (second resolution) creation time
+        std::thread::sleep(Duration::from_millis(1100));
+        ingest(&mailbox).await?;
+        assert!(mailbox
+            .fetched
+            .lock()
+            .unwrap()
+            .iter()
+            .all(|(_, _, resume_from)| resume_from.map(|c| c.last_uid) == Some(10)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_folder_checkpoints() -> Result<()> {
+        let mut dbc = DbConn::new(":memory:", 0)?;
+        let tx = dbc.init(None)?;
+        let (device_id, _) = upserted_device(&tx, &common::DEVICE)?;
+        let mut ingest_stmts = IngestContext::from_conn(&tx, ":memory:")?;
+        let mut session = |session_id: &str, created_at: &str, last_uid: u32| -> Result<()> {
+            tx.execute(
+                "INSERT INTO ur_ingest_session (ur_ingest_session_id, device_id, behavior_json, ingest_started_at, created_at)
+                 VALUES (?, ?, '{}', CURRENT_TIMESTAMP, ?)",
+                params![session_id, device_id, created_at],
+            )?;
+            let acct_id: String = tx.query_row(
+                INS_UR_INGEST_SESSION_IMAP_ACCT,
+                params![
+                    session_id,
+                    "ops@example.com",
+                    None::<String>,
+                    "imap.example.com"
+                ],
+                |row| row.get(0),
+            )?;
+            let mut folder = Folder::from("INBOX".to_string());
+            folder.checkpoint = Some(FolderCheckpoint {
+                uid_validity: 1700000000,
+                last_uid,
+            });
+            persist_folder(
+                &mut ingest_stmts,
+                session_id,
+                &device_id,
+                &acct_id,
+                "ops@example.com",
+                false,
+                &folder,
+                &mut MailboxStats::default(),
+            )?;
+            Ok(())
+        };
+        session("first", "2024-03-04 00:00:00", 42)?;
+        session("second", "2024-03-05 00:00:00", 57)?;
+
+        let checkpoints = folder_checkpoints(&tx, "ops@example.com")?;
+        assert_eq!(
+            checkpoints.get("INBOX"),
+            Some(&FolderCheckpoint {
+                uid_validity: 1700000000,
+                last_uid: 57
+            })
+        );
+        assert!(folder_checkpoints(&tx, "other@example.com")?.is_empty());
+        Ok(())
+    }
+}
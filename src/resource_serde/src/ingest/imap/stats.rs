@@ -0,0 +1,287 @@
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{Context, Result};
+use chrono::DateTime;
+use indoc::indoc;
+use resource_imap::EmailResource;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use serde_json::json;
+
+/// How many of the busiest senders are kept
+const TOP_SENDERS: usize = 10;
+/// Days of previous sessions needed before spikes are detected
+const MIN_BASELINE_DAYS: usize = 3;
+/// A day is a spike when its volume exceeds the baseline mean by this many
+/// standard deviations
+const SPIKE_STDDEVS: f64 = 3.0;
+
+const INS_UR_INGEST_SESSION_IMAP_ACCT_STAT: &str = indoc! {"
+    INSERT INTO ur_ingest_session_imap_acct_stat (ur_ingest_session_imap_acct_stat_id, ingest_session_id, ingest_account_id, stat_kind, stat_key, stat_value, elaboration)
+                                          VALUES (surveilr_pk(), ?, ?, ?, ?, ?, ?)"};
+
+// the daily volumes recorded by earlier sessions of the same mailbox
+const SEL_BASELINE_DAILY_VOLUME: &str = indoc! {"
+    SELECT stat.stat_value
+      FROM ur_ingest_session_imap_acct_stat stat
+      JOIN ur_ingest_session_imap_account acct ON acct.ur_ingest_session_imap_account_id = stat.ingest_account_id
+     WHERE stat.stat_kind = 'daily_volume'
+       AND stat.ingest_session_id != ?
+       AND acct.email IS (SELECT email FROM ur_ingest_session_imap_account WHERE ur_ingest_session_imap_account_id = ?)"};
+
+/// A day on which more messages arrived than the previous sessions of the
+/// mailbox make plausible.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VolumeSpike {
+    pub day: String,
+    pub messages: usize,
+    pub baseline_mean: f64,
+    pub baseline_stddev: f64,
+    pub baseline_days: usize,
+}
+
+/// Message volume, senders and attachment types of the messages of an
+/// `ingest imap` session, accumulated as folders are persisted.
+#[derive(Debug, Default)]
+pub struct MailboxStats {
+    messages: usize,
+    daily_volume: BTreeMap<String, usize>,
+    senders: HashMap<String, usize>,
+    attachment_types: BTreeMap<String, usize>,
+}
+
+impl MailboxStats {
+    pub fn record(&mut self, email: &EmailResource, attachment_natures: &[String]) {
+        self.messages += 1;
+        if let Some(day) = day_of(&email.date) {
+            *self.daily_volume.entry(day).or_default() += 1;
+        }
+        if !email.from.is_empty() {
+            *self.senders.entry(email.from.to_lowercase()).or_default() += 1;
+        }
+        for nature in attachment_natures {
+            *self.attachment_types.entry(nature.clone()).or_default() += 1;
+        }
+    }
+
+    /// The busiest senders, most messages first.
+    pub fn top_senders(&self) -> Vec<(&str, usize)> {
+        let mut senders: Vec<_> = self
+            .senders
+            .iter()
+            .map(|(sender, count)| (sender.as_str(), *count))
+            .collect();
+        senders.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        senders.truncate(TOP_SENDERS);
+        senders
+    }
+
+    /// The days whose volume exceeds the `baseline` daily volumes by more than
+    /// `SPIKE_STDDEVS` standard deviations (at least one message).
+    pub fn volume_spikes(&self, baseline: &[usize]) -> Vec<VolumeSpike> {
+        if baseline.len() < MIN_BASELINE_DAYS {
+            return Vec::new();
+        }
+        let days = baseline.len() as f64;
+        let mean = baseline.iter().sum::<usize>() as f64 / days;
+        let variance = baseline
+            .iter()
+            .map(|v| (*v as f64 - mean).powi(2))
+            .sum::<f64>()
+            / days;
+        let stddev = variance.sqrt();
+        let threshold = mean + SPIKE_STDDEVS * stddev.max(1.0);
+        self.daily_volume
+            .iter()
+            .filter(|(_, messages)| **messages as f64 > threshold)
+            .map(|(day, messages)| VolumeSpike {
+                day: day.clone(),
+                messages: *messages,
+                baseline_mean: mean,
+                baseline_stddev: stddev,
+                baseline_days: baseline.len(),
+            })
+            .collect()
+    }
+
+    /// Store the statistics as `ur_ingest_session_imap_acct_stat` rows, with
+    /// spikes detected against the previous sessions of the same mailbox, and
+    /// return their summary for the session elaboration.
+    pub fn persist(
+        &self,
+        conn: &Connection,
+        ingest_session_id: &str,
+        acct_id: &str,
+    ) -> Result<serde_json::Value> {
+        let baseline = conn
+            .prepare(SEL_BASELINE_DAILY_VOLUME)?
+            .query_map(params![ingest_session_id, acct_id], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<usize>>>()
+            .with_context(|| {
+                format!("[ingest_imap::stats] baseline of session {ingest_session_id}")
+            })?;
+        let spikes = self.volume_spikes(&baseline);
+        let top_senders = self.top_senders();
+
+        let mut stmt = conn.prepare(INS_UR_INGEST_SESSION_IMAP_ACCT_STAT)?;
+        let mut insert = |kind: &str, key: &str, value: usize, elaboration: Option<String>| {
+            stmt.execute(params![
+                ingest_session_id,
+                acct_id,
+                kind,
+                key,
+                value,
+                elaboration
+            ])
+            .with_context(|| format!("[ingest_imap::stats] {kind} {key}"))
+        };
+        for (day, messages) in &self.daily_volume {
+            insert("daily_volume", day, *messages, None)?;
+        }
+        for (sender, messages) in &top_senders {
+            insert("top_sender", sender, *messages, None)?;
+        }
+        for (nature, attachments) in &self.attachment_types {
+            insert("attachment_type", nature, *attachments, None)?;
+        }
+        for spike in &spikes {
+            insert(
+                "volume_spike",
+                &spike.day,
+                spike.messages,
+                Some(serde_json::to_string(spike)?),
+            )?;
+        }
+
+        Ok(json!({
+            "messages": self.messages,
+            "daily_volume": self.daily_volume,
+            "top_senders": top_senders
+                .iter()
+                .map(|(sender, messages)| json!({ "sender": sender, "messages": messages }))
+                .collect::<Vec<_>>(),
+            "attachment_types": self.attachment_types,
+            "volume_spikes": spikes,
+        }))
+    }
+}
+
+/// The `YYYY-MM-DD` day of an RFC 3339 (IMAP) or ISO 8601 (Microsoft Graph)
+/// message date.
+fn day_of(date: &str) -> Option<String> {
+    match DateTime::parse_from_rfc3339(date) {
+        Ok(parsed) => Some(parsed.date_naive().to_string()),
+        Err(_) => chrono::NaiveDate::parse_from_str(date.get(..10)?, "%Y-%m-%d")
+            .ok()
+            .map(|day| day.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn email(from: &str, date: &str) -> EmailResource {
+        serde_json::from_value(json!({
+            "subject": "", "from": from, "cc": [], "bcc": [], "references": [],
+            "in_reply_to": null, "message_id": "", "to": [], "date": date,
+            "text_plain": [], "text_html": [], "raw_text": "", "raw_json": "",
+            "attachments": null
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_mailbox_stats() {
+        let mut stats = MailboxStats::default();
+        for _ in 0..12 {
+            stats.record(&email("Alerts@example.com", "2024-03-02T08:00:00Z"), &[]);
+        }
+        stats.record(
+            &email("bob@example.com", "2024-03-01T23:30:00+00:00"),
+            &["pdf".to_string(), "csv".to_string()],
+        );
+        stats.record(
+            &email("alerts@example.com", "not a date"),
+            &["pdf".to_string()],
+        );
+
+        assert_eq!(
+            stats.top_senders(),
+            vec![("alerts@example.com", 13), ("bob@example.com", 1)]
+        );
+        assert_eq!(stats.daily_volume["2024-03-01"], 1);
+        assert_eq!(stats.daily_volume["2024-03-02"], 12);
+        assert_eq!(stats.attachment_types["pdf"], 2);
+
+        // too little history to tell what's normal
+        assert!(stats.volume_spikes(&[1, 2]).is_empty());
+        let spikes = stats.volume_spikes(&[1, 2, 1, 2]);
+        assert_eq!(spikes.len(), 1);
+        assert_eq!(
+            (spikes[0].day.as_str(), spikes[0].messages),
+            ("2024-03-02", 12)
+        );
+        assert!(stats.volume_spikes(&[10, 14, 12, 9]).is_empty());
+    }
+
+    #[test]
+    fn test_persist_compares_to_previous_sessions() -> Result<()> {
+        use crate::ingest::INS_UR_INGEST_SESSION_IMAP_ACCT;
+        use crate::persist::{upserted_device, DbConn};
+
+        let mut dbc = DbConn::new(":memory:", 0)?;
+        let tx = dbc.init(None)?;
+        let (device_id, _) = upserted_device(&tx, &common::DEVICE)?;
+        // sessions of the same device are unique per second of `created_at`
+        let session = |session_id: &str,
+                       created_at: &str,
+                       emails: Vec<EmailResource>|
+         -> Result<serde_json::Value> {
+            tx.execute(
+                "INSERT INTO ur_ingest_session (ur_ingest_session_id, device_id, behavior_json, ingest_started_at, created_at)
+                 VALUES (?, ?, '{}', CURRENT_TIMESTAMP, ?)",
+                params![session_id, device_id, created_at],
+            )?;
+            let acct_id: String = tx.query_row(
+                INS_UR_INGEST_SESSION_IMAP_ACCT,
+                params![
+                    session_id,
+                    "ops@example.com",
+                    None::<String>,
+                    "imap.example.com"
+                ],
+                |row| row.get(0),
+            )?;
+            let mut stats = MailboxStats::default();
+            for email in &emails {
+                stats.record(email, &[]);
+            }
+            stats.persist(&tx, session_id, &acct_id)
+        };
+
+        let quiet = ["2024-03-01", "2024-03-02", "2024-03-03"]
+            .iter()
+            .map(|day| email("bob@example.com", &format!("{day}T09:00:00Z")))
+            .collect();
+        let summary = session("quiet", "2024-03-04 00:00:00", quiet)?;
+        assert_eq!(summary["daily_volume"]["2024-03-02"], 1);
+        assert_eq!(summary["volume_spikes"], json!([]));
+
+        let burst = (0..20)
+            .map(|_| email("alerts@example.com", "2024-03-04T09:00:00Z"))
+            .collect();
+        let summary = session("burst", "2024-03-05 00:00:00", burst)?;
+        assert_eq!(summary["volume_spikes"][0]["day"], "2024-03-04");
+        assert_eq!(summary["volume_spikes"][0]["baseline_days"], 3);
+        assert_eq!(summary["top_senders"][0]["sender"], "alerts@example.com");
+
+        let spikes: i64 = tx.query_row(
+            "SELECT count(*) FROM ur_ingest_session_imap_acct_stat WHERE stat_kind = 'volume_spike'",
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(spikes, 1);
+        Ok(())
+    }
+}
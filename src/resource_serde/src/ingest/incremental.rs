@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use resource::{digest::DigestAlgorithm, ContentResource};
+use rusqlite::{Connection, OptionalExtension};
+use serde::Serialize;
+
+// the most recent finished `ingest files` session of the device which hashed
+// content with the same algorithm
+const SEL_PREVIOUS_FILES_SESSION_SQL: &str = "
+    SELECT s.ur_ingest_session_id
+      FROM ur_ingest_session s
+     WHERE s.device_id = ?
+       AND s.content_digest_algorithm = ?
+       AND s.ingest_finished_at IS NOT NULL
+       AND EXISTS (SELECT 1 FROM ur_ingest_session_fs_path p WHERE p.ingest_session_id = s.ur_ingest_session_id)
+  ORDER BY s.ingest_started_at DESC, s.rowid DESC
+     LIMIT 1";
+
+// stored (or unchanged) resources of the session; resources are deduplicated
+// across sessions so they're found through the session's entries
+const SEL_PREVIOUS_RESOURCES_SQL: &str = "
+    SELECT ur.uri, ur.size_bytes, ur.last_modified_at, ur.uniform_resource_id
+      FROM ur_ingest_session_fs_path_entry e
+      JOIN uniform_resource ur ON ur.uniform_resource_id = e.uniform_resource_id
+     WHERE e.ingest_session_id = ?
+       AND ur.size_bytes IS NOT NULL
+       AND ur.last_modified_at IS NOT NULL";
+
+/// The resources of the previous `ingest files` session, so `--incremental`
+/// sessions can skip reading and hashing the files which haven't changed since.
+#[derive(Debug, Default)]
+pub struct PreviousResources {
+    pub ingest_session_id: Option<String>,
+    // (uri, size_bytes, last_modified_at) to uniform_resource_id
+    resources: HashMap<(String, i64, String), String>,
+}
+
+impl PreviousResources {
+    pub fn from_conn(
+        conn: &Connection,
+        device_id: &str,
+        digest: DigestAlgorithm,
+    ) -> Result<PreviousResources> {
+        let Some(ingest_session_id) = conn
+            .query_row(
+                SEL_PREVIOUS_FILES_SESSION_SQL,
+                [device_id, &digest.to_string()],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()
+            .context("[PreviousResources::from_conn] previous session")?
+        else {
+            return Ok(PreviousResources::default());
+        };
+        let resources = conn
+            .prepare(SEL_PREVIOUS_RESOURCES_SQL)?
+            .query_map([&ingest_session_id], |row| {
+                Ok(((row.get(0)?, row.get(1)?, row.get(2)?), row.get(3)?))
+            })?
+            .collect::<rusqlite::Result<HashMap<_, _>>>()
+            .with_context(|| {
+                format!(
+                    "[PreviousResources::from_conn] resources of {}",
+                    ingest_session_id
+                )
+            })?;
+        Ok(PreviousResources {
+            ingest_session_id: Some(ingest_session_id),
+            resources,
+        })
+    }
+
+    /// The `uniform_resource_id` of `resource` when the previous session has it
+    /// with the same URI, size and modification time.
+    pub fn unchanged(&self, resource: &ContentResource) -> Option<&String> {
+        let (size, last_modified_at) = (resource.size?, resource.last_modified_at?);
+        // the same text `insert_uniform_resource` stores
+        let key = (
+            resource.uri.clone(),
+            i64::try_from(size).ok()?,
+            last_modified_at.to_string(),
+        );
+        self.resources.get(&key)
+    }
+}
+
+/// What an `--incremental` session skipped, stored as `incremental` in the
+/// session's `elaboration`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IncrementalStats {
+    pub previous_session_id: Option<String>,
+    pub unchanged: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use crate::cmd::IngestFilesArgs;
+    use crate::ingest::ingest_files;
+    use crate::persist::DbConn;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        files: IngestFilesArgs,
+    }
+
+    #[test]
+    fn test_incremental_skips_unchanged_files() -> Result<()> {
+        let root = tempfile::tempdir()?;
+        let state = tempfile::tempdir()?;
+        std::fs::write(root.path().join("a.md"), "# a")?;
+        std::fs::write(root.path().join("b.md"), "# b")?;
+        let state_db = state.path().join("rssd.sqlite.db");
+        let args = Cli::parse_from([
+            "ingest",
+            "-r",
+            &root.path().to_string_lossy(),
+            "-d",
+            &state_db.to_string_lossy(),
+            "--incremental",
+        ])
+        .files;
+
+        let statuses = |session: &str| -> Result<Vec<(String, Option<String>)>> {
+            let dbc = DbConn::open(&state_db, 0)?;
+            let mut stmt = dbc.conn.prepare(
+                "SELECT file_basename, ur_status
+                   FROM ur_ingest_session_fs_path_entry
+                  WHERE ingest_session_id = ?
+               ORDER BY file_basename",
+            )?;
+            let statuses = stmt
+                .query_map([session], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<rusqlite::Result<_>>()?;
+            Ok(statuses)
+        };
+
+        let first = ingest_files(0, &args)?.remove(0).ingest_session_id;
+        assert_eq!(
+            statuses(&first)?,
+            vec![("a.md".to_string(), None), ("b.md".to_string(), None)]
+        );
+
+        std::fs::write(root.path().join("b.md"), "# b, changed")?;
+        // sessions of a device are unique by their (second resolution) creation time
+        std::thread::sleep(std::time::Duration::from_millis(1100));
+        let second = ingest_files(0, &args)?.remove(0).ingest_session_id;
+        assert_eq!(
+            statuses(&second)?,
+            vec![
+                ("a.md".to_string(), Some("UNCHANGED".to_string())),
+                ("b.md".to_string(), None)
+            ]
+        );
+
+        let dbc = DbConn::open(&state_db, 0)?;
+        let (previous, unchanged, a_ur_ids): (String, usize, usize) = dbc.conn.query_row(
+            "SELECT s.elaboration ->> '$.incremental.previous_session_id',
+                    s.elaboration ->> '$.incremental.unchanged',
+                    (SELECT COUNT(DISTINCT uniform_resource_id) FROM ur_ingest_session_fs_path_entry WHERE file_basename = 'a.md')
+               FROM ur_ingest_session s
+              WHERE s.ur_ingest_session_id = ?",
+            [&second],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+        assert_eq!(previous, first);
+        assert_eq!(unchanged, 1);
+        // the unchanged entry points at the resource the first session stored
+        assert_eq!(a_ur_ids, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_incremental_needs_the_same_digest() -> Result<()> {
+        let root = tempfile::tempdir()?;
+        let state = tempfile::tempdir()?;
+        std::fs::write(root.path().join("a.md"), "# a")?;
+        let state_db = state.path().join("rssd.sqlite.db");
+        let args = |digest: &str| {
+            Cli::parse_from([
+                "ingest",
+                "-r",
+                &root.path().to_string_lossy(),
+                "-d",
+                &state_db.to_string_lossy(),
+                "--incremental",
+                "--digest",
+                digest,
+            ])
+            .files
+        };
+        let digests = |session: &str| -> Result<(String, String, String)> {
+            let dbc = DbConn::open(&state_db, 0)?;
+            let digests = dbc.conn.query_row(
+                "SELECT s.content_digest_algorithm, ur.content_digest_algorithm, ur.content_digest
+                   FROM ur_ingest_session_fs_path_entry e
+                   JOIN ur_ingest_session s ON s.ur_ingest_session_id = e.ingest_session_id
+                   JOIN uniform_resource ur ON ur.uniform_resource_id = e.uniform_resource_id
+                  WHERE e.ingest_session_id = ?",
+                [session],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )?;
+            Ok(digests)
+        };
+
+        let first = ingest_files(0, &args("sha1"))?.remove(0).ingest_session_id;
+        assert_eq!(
+            digests(&first)?,
+            (
+                "sha1".to_string(),
+                "sha1".to_string(),
+                DigestAlgorithm::Sha1.hex_digest("# a")
+            )
+        );
+
+        // the file is unchanged but the previous session hashed it with SHA-1
+        std::thread::sleep(std::time::Duration::from_millis(1100));
+        let second = ingest_files(0, &args("blake3"))?
+            .remove(0)
+            .ingest_session_id;
+        assert_eq!(
+            digests(&second)?,
+            (
+                "blake3".to_string(),
+                "blake3".to_string(),
+                DigestAlgorithm::Blake3.hex_digest("# a")
+            )
+        );
+        Ok(())
+    }
+}
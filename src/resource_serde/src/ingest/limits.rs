@@ -0,0 +1,132 @@
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::cmd::IngestLimitsArgs;
+
+/// Parse `--max-duration` values like `90`, `90s`, `30m`, `2h` or `1d`.
+pub fn parse_max_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let (amount, unit_secs) = match s.char_indices().last() {
+        Some((i, 's')) => (&s[..i], 1),
+        Some((i, 'm')) => (&s[..i], 60),
+        Some((i, 'h')) => (&s[..i], 60 * 60),
+        Some((i, 'd')) => (&s[..i], 24 * 60 * 60),
+        _ => (s, 1),
+    };
+    match amount.parse::<u64>() {
+        Ok(amount) if amount > 0 => Ok(Duration::from_secs(amount * unit_secs)),
+        _ => Err(format!(
+            "'{}' should be a positive number of seconds or end with s, m, h or d (e.g. 30m)",
+            s
+        )),
+    }
+}
+
+/// Why an ingest session stopped early, stored as `aborted` in the session's
+/// `elaboration` so a partial session can't be mistaken for a complete one.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SessionAbort {
+    /// `max-resources`, `abort-after-errors` or `max-duration`
+    pub limit: &'static str,
+    pub resources: usize,
+    pub errors: usize,
+    pub elapsed_ms: u128,
+}
+
+/// Counts the resources and errors of a session against its `IngestLimitsArgs`.
+#[derive(Debug)]
+pub struct SessionGuard<'a> {
+    limits: &'a IngestLimitsArgs,
+    started: Instant,
+    resources: usize,
+    errors: usize,
+}
+
+impl<'a> SessionGuard<'a> {
+    pub fn new(limits: &'a IngestLimitsArgs) -> SessionGuard<'a> {
+        SessionGuard {
+            limits,
+            started: Instant::now(),
+            resources: 0,
+            errors: 0,
+        }
+    }
+
+    /// Count a stored resource and whether its `ur_status` is `ERROR`.
+    pub fn record(&mut self, ur_status: Option<&str>) {
+        self.resources += 1;
+        if ur_status == Some("ERROR") {
+            self.errors += 1;
+        }
+    }
+
+    /// The limit which was reached, checked before each resource is ingested.
+    pub fn exceeded(&self) -> Option<SessionAbort> {
+        let elapsed = self.started.elapsed();
+        let limit = if self
+            .limits
+            .abort_after_errors
+            .is_some_and(|max| self.errors >= max)
+        {
+            "abort-after-errors"
+        } else if self
+            .limits
+            .max_resources
+            .is_some_and(|max| self.resources >= max)
+        {
+            "max-resources"
+        } else if self.limits.max_duration.is_some_and(|max| elapsed >= max) {
+            "max-duration"
+        } else {
+            return None;
+        };
+        Some(SessionAbort {
+            limit,
+            resources: self.resources,
+            errors: self.errors,
+            elapsed_ms: elapsed.as_millis(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_limits() {
+        assert_eq!(parse_max_duration("30m"), Ok(Duration::from_secs(1800)));
+        assert_eq!(parse_max_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_max_duration("1d"), Ok(Duration::from_secs(86400)));
+        assert!(parse_max_duration("0s").is_err());
+        assert!(parse_max_duration("m").is_err());
+        assert!(parse_max_duration("5w").is_err());
+
+        let limits = IngestLimitsArgs {
+            abort_after_errors: Some(2),
+            max_resources: Some(3),
+            max_duration: None,
+        };
+        let mut guard = SessionGuard::new(&limits);
+        guard.record(None);
+        guard.record(Some("ERROR"));
+        assert_eq!(guard.exceeded(), None);
+        guard.record(Some("ERROR"));
+        assert_eq!(guard.exceeded().unwrap().limit, "abort-after-errors");
+
+        let unlimited = IngestLimitsArgs::default();
+        let mut guard = SessionGuard::new(&unlimited);
+        (0..1000).for_each(|_| guard.record(Some("ERROR")));
+        assert_eq!(guard.exceeded(), None);
+
+        let limits = IngestLimitsArgs {
+            max_duration: Some(Duration::ZERO),
+            ..Default::default()
+        };
+        assert_eq!(
+            SessionGuard::new(&limits).exceeded().unwrap().limit,
+            "max-duration"
+        );
+    }
+}
@@ -0,0 +1,284 @@
+use std::io::BufRead;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use resource::EncounterableResourcePathClassifier;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::json;
+use tracing::{debug, warn};
+
+use super::uris::UNFETCHED_CONTENT_DIGEST;
+use super::{INS_UR_INGEST_SESSION_FINISH_SQL, INS_UR_INGEST_SESSION_SQL};
+use crate::cmd::IngestManifestArgs;
+use crate::known_files::{read_hash_set, HashAlgorithm, KnownFile};
+use crate::persist::*;
+use crate::reclassify::classified_nature;
+
+const INS_ATTESTED_UR_SQL: &str = "
+    INSERT INTO uniform_resource (uniform_resource_id, device_id, ingest_session_id, uri, nature, content, content_digest, size_bytes, last_modified_at, elaboration)
+                          VALUES (surveilr_pk(), ?, ?, ?, ?, NULL, ?, NULL, NULL, ?)
+                       RETURNING uniform_resource_id";
+
+// a path attested with the same digest by an earlier manifest isn't recorded again
+const SEL_ATTESTED_UR_SQL: &str = "
+    SELECT uniform_resource_id FROM uniform_resource
+     WHERE device_id = ? AND uri = ?
+       AND elaboration ->> '$.attested.algorithm' = ? AND elaboration ->> '$.attested.digest' = ?";
+
+/// Record the paths and digests of a checksum manifest (`sha256sum`, `sha1sum`
+/// or `md5sum` output) as uniform resources without content, attested by the
+/// manifest rather than acquired, for evidence too sensitive or large to copy.
+pub fn ingest_manifest(args: &IngestManifestArgs) -> Result<String> {
+    let (manifest, manifest_sha256) = read_manifest(&args.manifest)?;
+
+    let mut dbc = DbConn::new(&args.state_db_fs_path, 0).with_context(|| {
+        format!(
+            "[ingest_manifest] SQLite transaction in {}",
+            args.state_db_fs_path
+        )
+    })?;
+    let db_fs_path = dbc.db_fs_path.clone();
+    let tx = dbc.init(Some(&args.state_db_init_sql))?;
+    let (device_id, _device_name) = upserted_device(&tx, &common::DEVICE)
+        .with_context(|| format!("[ingest_manifest] upserted_device in {}", db_fs_path))?;
+    let classifier = EncounterableResourcePathClassifier::default_from_conn(&tx)?;
+
+    let behavior = json!({
+        "manifest": {
+            "path": args.manifest,
+            "sha256": manifest_sha256,
+            "attested_by": args.attested_by,
+        }
+    })
+    .to_string();
+    let ingest_session_id: String = tx
+        .query_row(
+            INS_UR_INGEST_SESSION_SQL,
+            params![device_id, None::<String>, behavior],
+            |row| row.get(0),
+        )
+        .with_context(|| {
+            format!(
+                "[ingest_manifest] inserting ingest session in {}",
+                db_fs_path
+            )
+        })?;
+    debug!("Manifest Session: {ingest_session_id}");
+
+    let attestation = Attestation {
+        manifest: &args.manifest,
+        manifest_sha256: &manifest_sha256,
+        attested_by: args.attested_by.as_deref(),
+    };
+    let stats = insert_attested(
+        &tx,
+        &device_id,
+        &ingest_session_id,
+        &classifier,
+        &attestation,
+        manifest.as_slice(),
+    )
+    .with_context(|| format!("[ingest_manifest] {} in {}", args.manifest, db_fs_path))?;
+    if stats.skipped_lines > 0 {
+        warn!(
+            "[ingest_manifest] {} lines of {} aren't digests of paths",
+            stats.skipped_lines, args.manifest
+        );
+    }
+
+    let session_elaboration = json!({
+        "manifest": {
+            "attested": stats.attested,
+            "already_attested": stats.already_attested,
+            "skipped_lines": stats.skipped_lines,
+        }
+    })
+    .to_string();
+    tx.execute(
+        INS_UR_INGEST_SESSION_FINISH_SQL,
+        params![ingest_session_id, session_elaboration],
+    )
+    .with_context(|| format!("[ingest_manifest] finishing session in {}", db_fs_path))?;
+    tx.commit().with_context(|| {
+        format!(
+            "[ingest_manifest] unable to perform final commit in {}",
+            db_fs_path
+        )
+    })?;
+    Ok(ingest_session_id)
+}
+
+/// The manifest's content (`-` is STDIN) and its SHA-256, so the session
+/// records exactly which manifest the digests came from.
+fn read_manifest(manifest: &str) -> Result<(Vec<u8>, String)> {
+    let content = if manifest == "-" {
+        let mut content = Vec::new();
+        std::io::Read::read_to_end(&mut std::io::stdin().lock(), &mut content)?;
+        content
+    } else {
+        std::fs::read(manifest).with_context(|| format!("[ingest_manifest] reading {manifest}"))?
+    };
+    let sha256 = HashAlgorithm::Sha256.hex_digest(&content);
+    Ok((content, sha256))
+}
+
+/// Where the digests of attested resources come from.
+struct Attestation<'a> {
+    manifest: &'a str,
+    manifest_sha256: &'a str,
+    attested_by: Option<&'a str>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct AttestedStats {
+    attested: usize,
+    already_attested: usize,
+    skipped_lines: usize,
+}
+
+fn insert_attested(
+    conn: &Connection,
+    device_id: &str,
+    ingest_session_id: &str,
+    classifier: &EncounterableResourcePathClassifier,
+    attestation: &Attestation,
+    manifest: impl BufRead,
+) -> Result<AttestedStats> {
+    let mut ins_ur_stmt = conn.prepare(INS_ATTESTED_UR_SQL)?;
+    let mut sel_ur_stmt = conn.prepare(SEL_ATTESTED_UR_SQL)?;
+    let mut stats = AttestedStats::default();
+    let skipped_lines = read_hash_set(manifest, |known_file| {
+        let KnownFile {
+            algorithm,
+            digest,
+            file_name: Some(path),
+            ..
+        } = known_file
+        else {
+            // a digest without a path can't be attested
+            stats.skipped_lines += 1;
+            return Ok(());
+        };
+        let algorithm = algorithm.to_string();
+        let existing: Option<String> = sel_ur_stmt
+            .query_row(params![device_id, path, algorithm, digest], |row| {
+                row.get(0)
+            })
+            .optional()?;
+        if existing.is_some() {
+            stats.already_attested += 1;
+            return Ok(());
+        }
+        let nature = classified_nature(classifier, &path).or_else(|| {
+            Path::new(&path)
+                .extension()
+                .map(|extn| extn.to_string_lossy().to_lowercase())
+        });
+        let elaboration = json!({
+            "attested": {
+                "content_acquired": false,
+                "algorithm": algorithm,
+                "digest": digest,
+                "manifest": attestation.manifest,
+                "manifest_sha256": attestation.manifest_sha256,
+                "attested_by": attestation.attested_by,
+            }
+        })
+        .to_string();
+        ins_ur_stmt
+            .query_row(
+                params![
+                    device_id,
+                    ingest_session_id,
+                    path,
+                    nature,
+                    UNFETCHED_CONTENT_DIGEST,
+                    elaboration,
+                ],
+                |row| row.get::<_, String>(0),
+            )
+            .with_context(|| format!("[insert_attested] {path}"))?;
+        stats.attested += 1;
+        Ok(())
+    })?;
+    stats.skipped_lines += skipped_lines;
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attested_resources() -> Result<()> {
+        let mut dbc = DbConn::new(":memory:", 0)?;
+        let tx = dbc.init(None)?;
+        let (device_id, _) = upserted_device(&tx, &common::DEVICE)?;
+        let classifier = EncounterableResourcePathClassifier::default_from_conn(&tx)?;
+        let session_id: String = tx.query_row(
+            INS_UR_INGEST_SESSION_SQL,
+            params![device_id, None::<String>, "{}"],
+            |row| row.get(0),
+        )?;
+        let manifest = "# sha256sum output
+e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855  evidence/disk.img
+9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08 *evidence/report.pdf
+9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08
+not a digest  evidence/notes.txt
+";
+        let attestation = Attestation {
+            manifest: "SHA256SUMS",
+            manifest_sha256: "abc",
+            attested_by: Some("acme forensics"),
+        };
+        let stats = insert_attested(
+            &tx,
+            &device_id,
+            &session_id,
+            &classifier,
+            &attestation,
+            manifest.as_bytes(),
+        )?;
+        assert_eq!(
+            stats,
+            AttestedStats {
+                attested: 2,
+                already_attested: 0,
+                skipped_lines: 2
+            }
+        );
+
+        let (nature, content_digest, content, digest, acquired): (
+            Option<String>,
+            String,
+            Option<Vec<u8>>,
+            String,
+            bool,
+        ) = tx.query_row(
+            "SELECT nature, content_digest, content, elaboration ->> '$.attested.digest', elaboration ->> '$.attested.content_acquired'
+               FROM uniform_resource WHERE uri = 'evidence/report.pdf'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)),
+        )?;
+        assert_eq!(nature.as_deref(), Some("pdf"));
+        assert_eq!(content_digest, UNFETCHED_CONTENT_DIGEST);
+        assert_eq!(content, None);
+        assert_eq!(
+            digest,
+            "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08"
+        );
+        assert!(!acquired);
+
+        let again = insert_attested(
+            &tx,
+            &device_id,
+            &session_id,
+            &classifier,
+            &attestation,
+            manifest.as_bytes(),
+        )?;
+        assert_eq!(again.attested, 0);
+        assert_eq!(again.already_attested, 2);
+        Ok(())
+    }
+}
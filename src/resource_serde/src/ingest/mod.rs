@@ -1,28 +1,95 @@
+use std::io::Read;
+
 use crate::cmd::IngestFilesArgs;
 use anyhow::{Context, Result};
 use autometrics::autometrics;
 use indoc::indoc;
+use resource::access::is_access_denied;
+use resource::frontmatter::{document_frontmatter, FrontmatterNature};
+use resource::identity::semantic_identity;
 use resource::shell::ShellResult;
 use resource::shell::ShellStdIn;
-use rusqlite::{params, Connection};
+use rusqlite::{params, Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use sha1::{Digest, Sha1};
+use tracing::error;
 
+use crate::blobs::{blob_pointer, BlobStore};
 use crate::persist::*;
+use pipeline::{ExecutedCapturable, PrefetchJob, Prefetcher};
 use resource::*;
 
+mod api;
+mod archives;
+mod browsers;
+mod canonical_json;
+mod ce_workdirs;
+mod chunks;
+mod collect_manifest;
 mod files;
+mod git;
+mod host_config;
+#[cfg(feature = "imap")]
 mod imap;
+mod incremental;
+mod limits;
+mod manifest;
+mod oci;
+mod osquery_pack;
+mod persistence;
+mod pipeline;
+mod routing;
+mod s3;
+mod seed;
+mod summary;
 mod tasks;
+mod triggers;
+mod uris;
+mod watch;
 
+pub use api::{ingest_api, ApiEndpoint};
+pub use archives::{ingest_archive_members, ArchiveTarget};
+pub use browsers::{
+    browser_profiles, ingest_browsers, Browser, BrowserArtifact, BrowserExtension, BrowserProfile,
+    HistoryVisit, UrlRedaction,
+};
+pub use canonical_json::canonicalize_json;
+pub use ce_workdirs::CeWorkdirs;
+pub use chunks::{chunked_content_size, insert_content_chunks, write_chunked_content};
+pub use collect_manifest::{
+    trusted_collect_manifests, CollectManifest, CollectManifestScope, CollectManifests,
+    COLLECT_MANIFEST_FILE_NAME,
+};
 pub use files::ingest_files;
-pub use imap::ingest_imap;
+pub use git::ingest_git;
+pub use host_config::{ingest_plist, ingest_registry};
+#[cfg(feature = "imap")]
+pub use imap::{ingest_imap, serve_smtp_journal};
+pub use incremental::{IncrementalStats, PreviousResources};
+pub use limits::{parse_max_duration, SessionAbort, SessionGuard};
+pub use manifest::ingest_manifest;
+pub use oci::ingest_oci;
+pub use osquery_pack::{ingest_osquery_pack, persist_osquery_pack_results, OsqueryPackResult};
+pub use persistence::{
+    extract_persistence, CronJob, Crontab, InitScript, PersistenceMechanism, PersistenceStats,
+    SystemdUnit,
+};
+pub use routing::{
+    routed_state_db, routed_state_dbs, IngestedSession, StateDbRoute, StateDbRouteRule,
+};
+pub use s3::ingest_s3;
+pub use seed::{seed_rssd, SeedProfile, SeedStats};
+pub use summary::{session_errors, IngestSessionError, IngestSessionSummary};
 pub use tasks::ingest_tasks;
+pub use triggers::{run_nature_triggers, NatureTrigger, TriggerAction, TriggerStats};
+pub use uris::{fetch_uniform_resource, ingest_uris, FetchedResource, UNFETCHED_CONTENT_DIGEST};
+pub use watch::{watch_files, FilesWatch, WatchEvent, WatchSnapshot, WatchStats};
 
 // separate the SQL from the execute so we can use it in logging, errors, etc.
 const INS_UR_INGEST_SESSION_SQL: &str = indoc! {"
-        INSERT INTO ur_ingest_session (ur_ingest_session_id, device_id, behavior_id, behavior_json, ingest_started_at) 
-                             VALUES (ulid(), ?, ?, ?, CURRENT_TIMESTAMP) RETURNING ur_ingest_session_id"};
+        INSERT INTO ur_ingest_session (ur_ingest_session_id, device_id, behavior_id, behavior_json, ingest_started_at, device_clock) 
+                             VALUES (surveilr_pk(), ?, ?, ?, CURRENT_TIMESTAMP, surveilr_device_clock()) RETURNING ur_ingest_session_id"};
 
 const INS_UR_INGEST_SESSION_FINISH_SQL: &str = indoc! {"
 UPDATE ur_ingest_session
@@ -31,42 +98,76 @@ SET ingest_finished_at = CURRENT_TIMESTAMP,
 WHERE ur_ingest_session_id = ?1;
 "};
 
+// sessions hash content with SHA-1 unless `--digest` chose another algorithm
+const UPD_UR_INGEST_SESSION_DIGEST_SQL: &str = indoc! {"
+UPDATE ur_ingest_session
+SET content_digest_algorithm = ?2
+WHERE ur_ingest_session_id = ?1;
+"};
+
 const INS_UR_ISFSP_SQL: &str = indoc! {"
-        INSERT INTO ur_ingest_session_fs_path (ur_ingest_session_fs_path_id, ingest_session_id, root_path) 
-                                  VALUES (ulid(), ?, ?) RETURNING ur_ingest_session_fs_path_id"};
+        INSERT INTO ur_ingest_session_fs_path (ur_ingest_session_fs_path_id, ingest_session_id, root_path, elaboration) 
+                                  VALUES (surveilr_pk(), ?, ?, ?) RETURNING ur_ingest_session_fs_path_id"};
+
+const UPD_UR_ISFSP_ELABORATION_SQL: &str = indoc! {"
+UPDATE ur_ingest_session_fs_path
+SET elaboration = ?2
+WHERE ur_ingest_session_fs_path_id = ?1;
+"};
 
 // in INS_UR_SQL the `DO UPDATE SET size_bytes = EXCLUDED.size_bytes` is a workaround to allow RETURNING uniform_resource_id when the row already exists
 const INS_UR_SQL: &str = indoc! {"
-        INSERT INTO uniform_resource (uniform_resource_id, device_id, ingest_session_id, ingest_fs_path_id, uri, nature, content, content_digest, size_bytes, last_modified_at, content_fm_body_attrs, frontmatter, ingest_imap_acct_folder_id)
-                              VALUES (ulid(), ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?) 
+        INSERT INTO uniform_resource (uniform_resource_id, device_id, ingest_session_id, ingest_fs_path_id, uri, nature, content, content_digest, size_bytes, last_modified_at, content_fm_body_attrs, frontmatter, ingest_imap_acct_folder_id, content_digest_algorithm)
+                              VALUES (surveilr_pk(), ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, (SELECT content_digest_algorithm FROM ur_ingest_session WHERE ur_ingest_session_id = ?2)) 
                          ON CONFLICT (device_id, content_digest, uri, size_bytes, last_modified_at) 
                            DO UPDATE SET size_bytes = EXCLUDED.size_bytes
                            RETURNING uniform_resource_id"};
 
 pub const INS_UR_TRANSFORM_SQL: &str = indoc! {"
         INSERT INTO uniform_resource_transform (uniform_resource_transform_id, uniform_resource_id, uri, nature, content_digest, content, size_bytes, elaboration)
-                                        VALUES (ulid(), ?, ?, ?, ?, ?, ?, ?) 
+                                        VALUES (surveilr_pk(), ?, ?, ?, ?, ?, ?, ?) 
                                    ON CONFLICT (uniform_resource_id, content_digest, nature, size_bytes) 
                                  DO UPDATE SET size_bytes = EXCLUDED.size_bytes
                                      RETURNING uniform_resource_transform_id"};
 
 const INS_UR_ISFSP_ENTRY_SQL: &str = indoc! {"
-        INSERT INTO ur_ingest_session_fs_path_entry (ur_ingest_session_fs_path_entry_id, ingest_session_id, ingest_fs_path_id, uniform_resource_id, file_path_abs, file_path_rel_parent, file_path_rel, file_basename, file_extn, ur_status, ur_diagnostics, captured_executable) 
-                                           VALUES (ulid(), ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"};
+        INSERT INTO ur_ingest_session_fs_path_entry (ur_ingest_session_fs_path_entry_id, ingest_session_id, ingest_fs_path_id, uniform_resource_id, file_path_abs, file_path_rel_parent, file_path_rel, file_basename, file_extn, ur_status, ur_diagnostics, captured_executable, elaboration) 
+                                           VALUES (surveilr_pk(), ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING ur_ingest_session_fs_path_entry_id"};
 
 const INS_UR_IS_TASK_SQL: &str = indoc! {"
         INSERT INTO ur_ingest_session_task (ur_ingest_session_task_id, ingest_session_id, uniform_resource_id, captured_executable, ur_status, ur_diagnostics) 
-                                            VALUES (ulid(), ?, ?, ?, ?, ?)"};
+                                            VALUES (surveilr_pk(), ?, ?, ?, ?, ?) RETURNING ur_ingest_session_task_id"};
+
+const INS_UR_LINEAGE_SQL: &str = indoc! {"
+        INSERT INTO uniform_resource_lineage (uniform_resource_lineage_id, ingest_session_id, source_uniform_resource_id, output_uniform_resource_id, ingest_fs_path_entry_id, ingest_task_id) 
+                                      VALUES (surveilr_pk(), ?, ?, ?, ?, ?)"};
+
+// the first resource of a nature with a semantic identity is the one later ones are deduplicated to
+const SEL_UR_SEMANTIC_IDENTITY_SQL: &str = indoc! {"
+        SELECT uniform_resource_id FROM uniform_resource
+         WHERE device_id = ? AND nature IS ? AND semantic_identity = ?
+      ORDER BY created_at, rowid LIMIT 1"};
+
+const UPD_UR_SEMANTIC_IDENTITY_SQL: &str = indoc! {"
+        UPDATE uniform_resource
+           SET semantic_identity = ?
+         WHERE uniform_resource_id = ?"};
+
+// merges the members of a JSON object into the resource's elaboration
+const UPD_UR_ELABORATION_SQL: &str = indoc! {"
+        UPDATE uniform_resource
+           SET elaboration = json_patch(COALESCE(elaboration, '{}'), ?)
+         WHERE uniform_resource_id = ?"};
 
 const INS_UR_INGEST_SESSION_IMAP_ACCT: &str = indoc! {"
 INSERT INTO ur_ingest_session_imap_account (ur_ingest_session_imap_account_id, ingest_session_id, email, password, host, elaboration, created_at, created_by) 
-VALUES (ulid(), ?, ?, ?, ?, '{}', CURRENT_TIMESTAMP, 'system') 
+VALUES (surveilr_pk(), ?, ?, surveilr_credential_digest(?), ?, '{}', CURRENT_TIMESTAMP, 'system') 
 ON CONFLICT (ingest_session_id, email) 
 DO UPDATE SET password = EXCLUDED.password, host = EXCLUDED.host 
 RETURNING ur_ingest_session_imap_account_id;"};
 
 const INS_UR_INGEST_SESSION_IMAP_ACCT_FOLDER: &str = indoc! {"INSERT INTO ur_ingest_session_imap_acct_folder (ur_ingest_session_imap_acct_folder_id, ingest_session_id, ingest_account_id, folder_name, elaboration, created_at, created_by)
-VALUES (ulid(), ?, ?, ?, ?, CURRENT_TIMESTAMP, 'system') 
+VALUES (surveilr_pk(), ?, ?, ?, ?, CURRENT_TIMESTAMP, 'system') 
 ON CONFLICT (ingest_account_id, folder_name) 
 DO UPDATE SET created_at = EXCLUDED.created_at RETURNING ur_ingest_session_imap_acct_folder_id;"};
 
@@ -83,11 +184,12 @@ INSERT INTO ur_ingest_session_imap_acct_folder_message (
     cc,
     bcc,
     email_references,
+    date,
     created_at, 
     created_by
 )
 VALUES (
-    ulid(), ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP, 'system'
+    surveilr_pk(), ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP, 'system'
 ) 
 ON CONFLICT (message, message_id)
 DO UPDATE SET 
@@ -98,6 +200,13 @@ DO UPDATE SET
 RETURNING ur_ingest_session_imap_acct_folder_message_id;
 "};
 
+const INS_UR_INGEST_IMAP_FOLDER_STATE: &str = indoc! {"
+INSERT INTO ur_ingest_imap_folder_state (ur_ingest_imap_folder_state_id, email, folder_name, uid_validity, last_uid, ingest_session_id)
+VALUES (surveilr_pk(), ?, ?, ?, ?, ?)
+ON CONFLICT (email, folder_name)
+DO UPDATE SET uid_validity = EXCLUDED.uid_validity, last_uid = EXCLUDED.last_uid,
+              ingest_session_id = EXCLUDED.ingest_session_id, updated_at = CURRENT_TIMESTAMP;"};
+
 #[allow(dead_code)]
 #[derive(Debug)]
 pub struct IngestContext<'conn> {
@@ -106,9 +215,16 @@ pub struct IngestContext<'conn> {
     ins_ur_transform_stmt: rusqlite::Statement<'conn>,
     ins_ur_isfsp_entry_stmt: rusqlite::Statement<'conn>,
     ins_ur_is_task_stmt: rusqlite::Statement<'conn>,
+    ins_ur_lineage_stmt: rusqlite::Statement<'conn>,
+    sel_ur_semantic_identity_stmt: rusqlite::Statement<'conn>,
+    upd_ur_semantic_identity_stmt: rusqlite::Statement<'conn>,
+    upd_ur_elaboration_stmt: rusqlite::Statement<'conn>,
     ur_ingest_session_imap_account_stmt: rusqlite::Statement<'conn>,
     ur_ingest_session_imap_acct_folder_stmt: rusqlite::Statement<'conn>,
     ur_ingest_session_imap_acct_folder_message_stmt: rusqlite::Statement<'conn>,
+    ur_ingest_imap_folder_state_stmt: rusqlite::Statement<'conn>,
+    ins_ur_chunk_stmt: rusqlite::Statement<'conn>,
+    sel_ur_chunked_size_stmt: rusqlite::Statement<'conn>,
 }
 
 impl<'conn> IngestContext<'conn> {
@@ -144,6 +260,30 @@ impl<'conn> IngestContext<'conn> {
                 INS_UR_ISFSP_ENTRY_SQL, db_fs_path
             )
         })?;
+        let ins_ur_lineage_stmt = conn.prepare(INS_UR_LINEAGE_SQL).with_context(|| {
+            format!(
+                "[IngestContext::from_conn] unable to create `ins_ur_lineage_stmt` SQL {} in {}",
+                INS_UR_LINEAGE_SQL, db_fs_path
+            )
+        })?;
+        let sel_ur_semantic_identity_stmt = conn.prepare(SEL_UR_SEMANTIC_IDENTITY_SQL).with_context(|| {
+            format!(
+                "[IngestContext::from_conn] unable to create `sel_ur_semantic_identity_stmt` SQL {} in {}",
+                SEL_UR_SEMANTIC_IDENTITY_SQL, db_fs_path
+            )
+        })?;
+        let upd_ur_semantic_identity_stmt = conn.prepare(UPD_UR_SEMANTIC_IDENTITY_SQL).with_context(|| {
+            format!(
+                "[IngestContext::from_conn] unable to create `upd_ur_semantic_identity_stmt` SQL {} in {}",
+                UPD_UR_SEMANTIC_IDENTITY_SQL, db_fs_path
+            )
+        })?;
+        let upd_ur_elaboration_stmt = conn.prepare(UPD_UR_ELABORATION_SQL).with_context(|| {
+            format!(
+                "[IngestContext::from_conn] unable to create `upd_ur_elaboration_stmt` SQL {} in {}",
+                UPD_UR_ELABORATION_SQL, db_fs_path
+            )
+        })?;
 
         let ur_ingest_session_imap_account_stmt = conn.prepare(INS_UR_INGEST_SESSION_IMAP_ACCT).with_context(|| {
             format!(
@@ -166,17 +306,79 @@ impl<'conn> IngestContext<'conn> {
             )
         })?;
 
+        let ur_ingest_imap_folder_state_stmt = conn.prepare(INS_UR_INGEST_IMAP_FOLDER_STATE).with_context(|| {
+            format!(
+                "[IngestContext::from_conn] unable to create `ur_ingest_imap_folder_state_stmt` SQL {} in {}",
+                INS_UR_INGEST_IMAP_FOLDER_STATE, db_fs_path
+            )
+        })?;
+
+        let ins_ur_chunk_stmt = conn.prepare(chunks::INS_UR_CHUNK_SQL).with_context(|| {
+            format!(
+                "[IngestContext::from_conn] unable to create `ins_ur_chunk_stmt` SQL {} in {}",
+                chunks::INS_UR_CHUNK_SQL,
+                db_fs_path
+            )
+        })?;
+        let sel_ur_chunked_size_stmt = conn.prepare(chunks::SEL_UR_CHUNKED_SIZE_SQL).with_context(|| {
+            format!(
+                "[IngestContext::from_conn] unable to create `sel_ur_chunked_size_stmt` SQL {} in {}",
+                chunks::SEL_UR_CHUNKED_SIZE_SQL, db_fs_path
+            )
+        })?;
+
         Ok(IngestContext {
             ins_ur_isfsp_stmt,
             ins_ur_stmt,
             ins_ur_transform_stmt,
             ins_ur_isfsp_entry_stmt,
             ins_ur_is_task_stmt: ins_ur_istask_entry_stmt,
+            ins_ur_lineage_stmt,
+            sel_ur_semantic_identity_stmt,
+            upd_ur_semantic_identity_stmt,
+            upd_ur_elaboration_stmt,
             ur_ingest_session_imap_account_stmt,
             ur_ingest_session_imap_acct_folder_stmt,
             ur_ingest_session_imap_acct_folder_message_stmt,
+            ur_ingest_imap_folder_state_stmt,
+            ins_ur_chunk_stmt,
+            sel_ur_chunked_size_stmt,
         })
     }
+
+    /// The resource of the same nature with the same semantic identity, which a
+    /// new resource is deduplicated to even when their content digests differ.
+    pub fn semantically_identical(
+        &mut self,
+        device_id: &str,
+        nature: Option<&str>,
+        semantic_identity: &str,
+    ) -> rusqlite::Result<Option<String>> {
+        self.sel_ur_semantic_identity_stmt
+            .query_row(params![device_id, nature, semantic_identity], |row| {
+                row.get(0)
+            })
+            .optional()
+    }
+
+    pub fn set_semantic_identity(
+        &mut self,
+        uniform_resource_id: &str,
+        semantic_identity: &str,
+    ) -> rusqlite::Result<usize> {
+        self.upd_ur_semantic_identity_stmt
+            .execute(params![semantic_identity, uniform_resource_id])
+    }
+
+    /// Merge the members of `elaboration`, a JSON object, into the resource's.
+    pub fn elaborate(
+        &mut self,
+        uniform_resource_id: &str,
+        elaboration: &serde_json::Value,
+    ) -> rusqlite::Result<usize> {
+        self.upd_ur_elaboration_stmt
+            .execute(params![elaboration.to_string(), uniform_resource_id])
+    }
 }
 
 pub struct UniformResourceWriterState<'a, 'conn> {
@@ -188,15 +390,64 @@ pub struct UniformResourceWriterState<'a, 'conn> {
     ingest_stmts: &'a mut IngestContext<'conn>,
     ingest_files_behavior: Option<&'a IngestFilesBehavior>,
     ingest_fs_path_id: Option<&'a String>,
+    ce_json_filter: Option<&'a str>,
+    ce_workdirs: &'a CeWorkdirs,
+    decode_payloads: bool,
+    canonical_json: bool,
+    // bytes per `uniform_resource_chunk` for content too large for a blob
+    content_chunk_size: Option<usize>,
+    // binary content of at least `blob_threshold` bytes is stored here
+    blob_store: Option<&'a BlobStore>,
+    blob_threshold: usize,
 }
 
 impl<'a, 'conn> UniformResourceWriterState<'a, 'conn> {
     fn capturable_exec_ctx(&self, entry: &mut UniformResourceWriterEntry) -> ShellStdIn {
-        let path = if entry.path.is_some() {
-            json!({ "path": entry.path.unwrap() })
+        self.capturable_exec_stdin().stdin(entry.path)
+    }
+
+    fn capturable_exec_stdin(&self) -> CapturableExecStdIn<'a> {
+        CapturableExecStdIn {
+            state_db_fs_path: self.state_db_fs_path,
+            env_current_dir: self.env_current_dir,
+            device_id: self.device_id,
+            ingest_session_id: self.ingest_session_id,
+            ingest_files_behavior: self.ingest_files_behavior,
+            ingest_fs_path_id: self.ingest_fs_path_id,
+            ce_workdirs: self.ce_workdirs,
+        }
+    }
+}
+
+/// What capturable executables get on STDIN: the session they run in and a
+/// work directory of their own.
+pub struct CapturableExecStdIn<'a> {
+    state_db_fs_path: &'a str,
+    env_current_dir: &'a str,
+    device_id: &'a str,
+    ingest_session_id: &'a str,
+    ingest_files_behavior: Option<&'a IngestFilesBehavior>,
+    ingest_fs_path_id: Option<&'a String>,
+    ce_workdirs: &'a CeWorkdirs,
+}
+
+impl<'a> CapturableExecStdIn<'a> {
+    fn stdin(&self, path: Option<&str>) -> ShellStdIn {
+        let path = if let Some(path) = path {
+            json!({ "path": path })
         } else {
             json!(null)
         };
+        let workdir = match self.ce_workdirs.create() {
+            Ok(workdir) => json!(workdir),
+            Err(err) => {
+                error!(
+                    "[capturable_exec_ctx] unable to create a CE workdir: {}",
+                    err
+                );
+                json!(null)
+            }
+        };
         let ctx = json!({
             "surveilr-ingest": {
                 "args": { "state_db_fs_path": self.state_db_fs_path },
@@ -207,6 +458,7 @@ impl<'a, 'conn> UniformResourceWriterState<'a, 'conn> {
                     "walk-session-id": self.ingest_session_id,
                     "walk-path-id": self.ingest_fs_path_id,
                     "dir-entry": path,
+                    "ce-workdir": workdir,
                 },
             }
         });
@@ -217,12 +469,15 @@ impl<'a, 'conn> UniformResourceWriterState<'a, 'conn> {
 pub struct UniformResourceWriterEntry<'a> {
     path: Option<&'a str>,
     tried_alternate_nature: Option<String>,
+    // the capturable executable already ran on an `ingest files --jobs` worker
+    executed: Option<ExecutedCapturable>,
 }
 
 #[derive(Debug)]
 pub enum UniformResourceWriterAction {
     Inserted(String, Option<String>),
-    InsertedExecutableOutput(String, Option<String>, serde_json::Value),
+    // output UR ID, status, diagnostics and the UR ID of the capturable executable itself (if stored)
+    InsertedExecutableOutput(String, Option<String>, serde_json::Value, Option<String>),
     CapturedExecutableSqlOutput(String, serde_json::Value),
     CapturedExecutableNonZeroExit(ShellResult, serde_json::Value),
     ContentSupplierError(Box<dyn std::error::Error>),
@@ -230,6 +485,10 @@ pub enum UniformResourceWriterAction {
     CapturableExecNotExecutable(),
     CapturableExecError(anyhow::Error),
     CapturableExecUrCreateError(Box<dyn std::error::Error>),
+    // not stored because of a `.surveilr_collect.yml` limit, with the diagnostics
+    Skipped(serde_json::Value),
+    // not read because the previous session has it (`--incremental`), its UR ID
+    Unchanged(String),
     Error(anyhow::Error),
 }
 
@@ -237,7 +496,7 @@ impl UniformResourceWriterAction {
     fn ur_status(&self) -> Option<String> {
         match self {
             UniformResourceWriterAction::Inserted(_, ur_status) => ur_status.clone(),
-            UniformResourceWriterAction::InsertedExecutableOutput(_, ur_status, _) => {
+            UniformResourceWriterAction::InsertedExecutableOutput(_, ur_status, _, _) => {
                 ur_status.clone()
             }
             UniformResourceWriterAction::CapturedExecutableSqlOutput(_, _) => None,
@@ -254,13 +513,30 @@ impl UniformResourceWriterAction {
             | UniformResourceWriterAction::CapturableExecNotExecutable() => {
                 Some(String::from("ISSUE"))
             }
+            UniformResourceWriterAction::Skipped(_) => Some(String::from("SKIPPED")),
+            UniformResourceWriterAction::Unchanged(_) => Some(String::from("UNCHANGED")),
+        }
+    }
+
+    /// The error of a resource surveilr isn't allowed to read.
+    fn access_denied(&self) -> Option<String> {
+        match self {
+            UniformResourceWriterAction::ContentSupplierError(err)
+                if is_access_denied(err.as_ref()) =>
+            {
+                Some(err.to_string())
+            }
+            UniformResourceWriterAction::Error(err) if err.chain().any(is_access_denied) => {
+                Some(err.to_string())
+            }
+            _ => None,
         }
     }
 
     fn ur_diagnostics(&self) -> Option<String> {
         match self {
             UniformResourceWriterAction::Inserted(_, _) => None,
-            UniformResourceWriterAction::InsertedExecutableOutput(_, _, _) => None,
+            UniformResourceWriterAction::InsertedExecutableOutput(_, _, _, _) => None,
             UniformResourceWriterAction::CapturedExecutableSqlOutput(_, _) => None,
             UniformResourceWriterAction::CapturedExecutableNonZeroExit(_, diags) => {
                 Some(serde_json::to_string_pretty(&json!({
@@ -303,6 +579,9 @@ impl UniformResourceWriterAction {
                     "message": "UniformResourceWriterAction::Error(err)",
                     "error": err.to_string()
                 })).unwrap()),
+            UniformResourceWriterAction::Skipped(diags) =>
+                Some(serde_json::to_string_pretty(diags).unwrap()),
+            UniformResourceWriterAction::Unchanged(_) => None,
         }
     }
 }
@@ -320,6 +599,12 @@ pub trait UniformResourceWriter<Resource> {
         entry: &mut UniformResourceWriterEntry,
     ) -> UniformResourceWriterResult;
 
+    /// The `content_fm_body_attrs` and `frontmatter` of a resource whose text
+    /// is itself structured (not only Markdown frontmatter).
+    fn text_frontmatter(&self, _text: &str) -> Option<(String, String)> {
+        None
+    }
+
     fn insert_text(
         &self,
         urw_state: &mut UniformResourceWriterState<'_, '_>,
@@ -329,32 +614,87 @@ pub trait UniformResourceWriter<Resource> {
         let uri = resource.uri.clone();
         match resource.content_text_supplier.as_ref() {
             Some(text_supplier) => match text_supplier() {
-                Ok(text) => match urw_state.ingest_stmts.ins_ur_stmt.query_row(
-                    params![
-                        urw_state.device_id,
-                        urw_state.ingest_session_id,
-                        urw_state.ingest_fs_path_id,
-                        resource.uri,
-                        resource.nature,
-                        text.content_text(),
-                        text.content_digest_hash(),
-                        resource.size,
-                        resource.last_modified_at.unwrap().to_string(),
-                        &None::<String>, // content_fm_body_attrs
-                        &None::<String>, // frontmatter
-                        &None::<String>, // ur_ingest_session_imap_acct_folder_id
-                    ],
-                    |row| row.get(0),
-                ) {
-                    Ok(new_or_existing_ur_id) => UniformResourceWriterResult {
-                        uri,
-                        action: UniformResourceWriterAction::Inserted(new_or_existing_ur_id, None),
-                    },
-                    Err(err) => UniformResourceWriterResult {
-                        uri,
-                        action: UniformResourceWriterAction::Error(err.into()),
-                    },
-                },
+                Ok(text) => {
+                    let identity = resource
+                        .nature
+                        .as_deref()
+                        .and_then(|nature| semantic_identity(nature, text.content_text()));
+                    if let Some(identity) = &identity {
+                        match urw_state.ingest_stmts.semantically_identical(
+                            urw_state.device_id,
+                            resource.nature.as_deref(),
+                            identity,
+                        ) {
+                            Ok(Some(existing_ur_id)) => {
+                                return UniformResourceWriterResult {
+                                    uri,
+                                    action: UniformResourceWriterAction::Inserted(
+                                        existing_ur_id,
+                                        None,
+                                    ),
+                                }
+                            }
+                            Ok(None) => {}
+                            Err(err) => {
+                                return UniformResourceWriterResult {
+                                    uri,
+                                    action: UniformResourceWriterAction::Error(err.into()),
+                                }
+                            }
+                        }
+                    }
+                    let (fm_attrs, fm_json) = self.text_frontmatter(text.content_text()).unzip();
+                    match urw_state.ingest_stmts.ins_ur_stmt.query_row(
+                        params![
+                            urw_state.device_id,
+                            urw_state.ingest_session_id,
+                            urw_state.ingest_fs_path_id,
+                            resource.uri,
+                            resource.nature,
+                            text.content_text(),
+                            text.content_digest_hash(),
+                            resource.size,
+                            resource.last_modified_at.unwrap().to_string(),
+                            fm_attrs,
+                            fm_json,
+                            &None::<String>, // ur_ingest_session_imap_acct_folder_id
+                        ],
+                        |row| row.get::<_, String>(0),
+                    ) {
+                        Ok(new_or_existing_ur_id) => {
+                            if let Some(identity) = &identity {
+                                if let Err(err) = urw_state
+                                    .ingest_stmts
+                                    .set_semantic_identity(&new_or_existing_ur_id, identity)
+                                {
+                                    return UniformResourceWriterResult {
+                                        uri,
+                                        action: UniformResourceWriterAction::Error(err.into()),
+                                    };
+                                }
+                            }
+                            if urw_state.decode_payloads {
+                                insert_decoded_payloads(
+                                    urw_state.ingest_stmts,
+                                    &new_or_existing_ur_id,
+                                    &uri,
+                                    text.content_text(),
+                                );
+                            }
+                            UniformResourceWriterResult {
+                                uri,
+                                action: UniformResourceWriterAction::Inserted(
+                                    new_or_existing_ur_id,
+                                    None,
+                                ),
+                            }
+                        }
+                        Err(err) => UniformResourceWriterResult {
+                            uri,
+                            action: UniformResourceWriterAction::Error(err.into()),
+                        },
+                    }
+                }
                 Err(err) => UniformResourceWriterResult {
                     uri,
                     action: UniformResourceWriterAction::ContentSupplierError(err),
@@ -375,14 +715,64 @@ pub trait UniformResourceWriter<Resource> {
         _entry: &mut UniformResourceWriterEntry,
     ) -> UniformResourceWriterResult {
         let uri = resource.uri.clone();
-        match urw_state.ingest_stmts.ins_ur_stmt.query_row(
+        // content too large to hold in memory is stored in chunks when asked
+        // to, otherwise it's read in the resource's row as usual
+        let mut reader = match bc.content_reader().transpose() {
+            Ok(reader) => reader,
+            Err(err) => {
+                return UniformResourceWriterResult {
+                    uri,
+                    action: UniformResourceWriterAction::Error(err.into()),
+                }
+            }
+        };
+        // unless there's a blob store, which takes large content (and all the
+        // content too large to hold in memory) so the row only holds a pointer
+        let pointer = match urw_state.blob_store {
+            Some(blob_store)
+                if reader.is_some() || bc.content_binary().len() >= urw_state.blob_threshold =>
+            {
+                let stored = match reader.take() {
+                    Some(mut reader) => {
+                        blob_store.put_reader(&mut reader, urw_state.resources.digest)
+                    }
+                    None => blob_store.put(bc.content_binary(), urw_state.resources.digest),
+                };
+                match stored {
+                    Ok(digest) => Some(blob_pointer(&digest)),
+                    Err(err) => {
+                        return UniformResourceWriterResult {
+                            uri,
+                            action: UniformResourceWriterAction::Error(err),
+                        }
+                    }
+                }
+            }
+            _ => None,
+        };
+        let mut streamed = Vec::new();
+        if let (Some(reader), None) = (reader.as_mut(), urw_state.content_chunk_size) {
+            if let Err(err) = reader.read_to_end(&mut streamed) {
+                return UniformResourceWriterResult {
+                    uri,
+                    action: UniformResourceWriterAction::Error(err.into()),
+                };
+            }
+        }
+        let content = match (&pointer, &reader, urw_state.content_chunk_size) {
+            (Some(pointer), _, _) => Some(pointer.as_bytes()),
+            (None, Some(_), Some(_)) => None,
+            (None, Some(_), None) => Some(streamed.as_slice()),
+            (None, None, _) => Some(bc.content_binary().as_slice()),
+        };
+        let inserted = urw_state.ingest_stmts.ins_ur_stmt.query_row(
             params![
                 urw_state.device_id,
                 urw_state.ingest_session_id,
                 urw_state.ingest_fs_path_id,
                 resource.uri,
                 resource.nature,
-                bc.content_binary(),
+                content,
                 bc.content_digest_hash(),
                 resource.size,
                 resource.last_modified_at.unwrap().to_string(),
@@ -390,20 +780,50 @@ pub trait UniformResourceWriter<Resource> {
                 &None::<String>, // frontmatter
                 &None::<String>, // ur_ingest_session_imap_acct_folder_id
             ],
-            |row| row.get(0),
-        ) {
+            |row| row.get::<_, String>(0),
+        );
+        let chunked = match (inserted, reader, urw_state.content_chunk_size) {
+            (Ok(ur_id), Some(mut reader), Some(chunk_size)) => {
+                store_content_chunks(urw_state.ingest_stmts, &ur_id, &mut reader, chunk_size)
+                    .map(|_| ur_id)
+            }
+            (inserted, _, _) => inserted.map_err(anyhow::Error::from),
+        };
+        match chunked {
             Ok(new_or_existing_ur_id) => UniformResourceWriterResult {
                 uri,
                 action: UniformResourceWriterAction::Inserted(new_or_existing_ur_id, None),
             },
             Err(err) => UniformResourceWriterResult {
                 uri,
-                action: UniformResourceWriterAction::Error(err.into()),
+                action: UniformResourceWriterAction::Error(err),
             },
         }
     }
 }
 
+/// Store the content of `ur_id` in chunks unless an earlier session already did.
+fn store_content_chunks(
+    ingest_stmts: &mut IngestContext,
+    ur_id: &str,
+    reader: &mut dyn std::io::Read,
+    chunk_size: usize,
+) -> Result<()> {
+    let stored: Option<u64> = ingest_stmts
+        .sel_ur_chunked_size_stmt
+        .query_row([ur_id], |row| row.get(0))
+        .optional()?;
+    if stored.is_none() {
+        insert_content_chunks(
+            &mut ingest_stmts.ins_ur_chunk_stmt,
+            ur_id,
+            reader,
+            chunk_size,
+        )?;
+    }
+    Ok(())
+}
+
 // this is the unknown resource content handler
 impl UniformResourceWriter<ContentResource> for ContentResource {
     fn insert(
@@ -458,7 +878,10 @@ impl UniformResourceWriter<ContentResource> for CapturableExecResource<ContentRe
         entry: &mut UniformResourceWriterEntry,
     ) -> UniformResourceWriterResult {
         // if resources collection instance wants to, store the executable as a uniform_resource itself so we have history;
-        self.insert_text(urw_state, &self.resource, entry);
+        let script_ur_id = match self.insert_text(urw_state, &self.resource, entry).action {
+            UniformResourceWriterAction::Inserted(script_ur_id, _) => Some(script_ur_id),
+            _ => None,
+        };
 
         // now try to execute the capturable executable and store its output
         match &self.executable {
@@ -468,17 +891,49 @@ impl UniformResourceWriter<ContentResource> for CapturableExecResource<ContentRe
                 nature,
                 is_batched_sql,
             ) => {
-                let stdin = urw_state.capturable_exec_ctx(entry);
-                match executive.execute(stdin.clone()) {
-                    Ok(shell_result) => {
-                        let captured_executable_diags = json!({
+                let (stdin, executed, elapsed) = match entry.executed.take() {
+                    Some(executed) => (executed.stdin, executed.result, executed.elapsed),
+                    None => {
+                        let stdin = urw_state.capturable_exec_ctx(entry);
+                        let started = std::time::Instant::now();
+                        let executed = executive.execute(stdin.clone());
+                        (stdin, executed, started.elapsed())
+                    }
+                };
+                match executed {
+                    Ok(mut shell_result) => {
+                        let mut captured_executable_diags = json!({
                             "args": [],
                             "interpretable-code": interpretable_code,
                             "stdin": stdin.json(),
                             "exit-status": format!("{:?}", shell_result.status),
                             "stderr": shell_result.stderr,
+                            "environment": resource::shell::execution_environment(interpretable_code, elapsed, shell_result.max_rss_kb),
                         });
 
+                        let mut output_filter = None;
+                        if let (Some(filter), true) = (urw_state.ce_json_filter, nature == "json") {
+                            if shell_result.success() && !*is_batched_sql {
+                                // trim noisy output to the relevant fields before it's stored
+                                match filter_json_output(filter, &shell_result.stdout) {
+                                    Ok(filtered) => {
+                                        captured_executable_diags["output-filter"] = json!(filter);
+                                        shell_result.stdout = filtered;
+                                        output_filter = Some(filter);
+                                    }
+                                    Err(err) => {
+                                        return UniformResourceWriterResult {
+                                            uri: self.resource.uri.clone(),
+                                            action:
+                                                UniformResourceWriterAction::CapturableExecError(
+                                                    err,
+                                                ),
+                                        }
+                                    }
+                                }
+                            }
+                        }
+
                         if shell_result.success() {
                             if *is_batched_sql {
                                 // the text is considered SQL and should be executed by the
@@ -493,7 +948,7 @@ impl UniformResourceWriter<ContentResource> for CapturableExecResource<ContentRe
                                 };
                             }
 
-                            let hash = shell_result.stdout_hash();
+                            let hash = urw_state.resources.digest.hex_digest(&shell_result.stdout);
                             let output_res = ContentResource {
                                 flags: self.resource.flags,
                                 uri: self.resource.uri.clone(),
@@ -519,10 +974,26 @@ impl UniformResourceWriter<ContentResource> for CapturableExecResource<ContentRe
                                         insert_uniform_resource(&ur, urw_state, entry);
                                     match inserted_output.action {
                                         UniformResourceWriterAction::Inserted(ur_id, ur_status) => {
+                                            // the stored output isn't what the executable wrote
+                                            if let Some(filter) = output_filter {
+                                                if let Err(err) = urw_state.ingest_stmts.elaborate(
+                                                    &ur_id,
+                                                    &json!({ "ce_json_filter": filter }),
+                                                ) {
+                                                    return UniformResourceWriterResult {
+                                                        uri: inserted_output.uri,
+                                                        action: UniformResourceWriterAction::Error(
+                                                            anyhow::Error::from(err).context(
+                                                                "[CapturableExecResource.insert] unable to record the CE JSON filter",
+                                                            ),
+                                                        ),
+                                                    };
+                                                }
+                                            }
                                             UniformResourceWriterResult {
                                                 uri: inserted_output.uri,
                                                 action: UniformResourceWriterAction::InsertedExecutableOutput(ur_id, ur_status,
-                                                    captured_executable_diags),
+                                                    captured_executable_diags, script_ur_id),
                                             }
                                         },
                                         _ => inserted_output
@@ -593,13 +1064,163 @@ impl UniformResourceWriter<ContentResource> for ImageResource<ContentResource> {
     }
 }
 
+/// Store the binary content of a document whose text is extracted next.
+fn insert_document_binary(
+    writer: &impl UniformResourceWriter<ContentResource>,
+    urw_state: &mut UniformResourceWriterState<'_, '_>,
+    resource: &ContentResource,
+    entry: &mut UniformResourceWriterEntry,
+) -> UniformResourceWriterResult {
+    match resource.content_binary_supplier.as_ref() {
+        Some(document_supplier) => match document_supplier() {
+            Ok(document_src) => writer.insert_binary(urw_state, resource, document_src, entry),
+            Err(err) => UniformResourceWriterResult {
+                uri: resource.uri.clone(),
+                action: UniformResourceWriterAction::ContentSupplierError(err),
+            },
+        },
+        None => UniformResourceWriterResult {
+            uri: resource.uri.clone(),
+            action: UniformResourceWriterAction::ContentUnavailable(),
+        },
+    }
+}
+
+/// Store what was extracted from the document `ur_id` as its transform.
+fn insert_document_transform(
+    urw_state: &mut UniformResourceWriterState<'_, '_>,
+    ur_id: &String,
+    uri: &str,
+    nature: &str,
+    extracted: Result<(String, serde_json::Value), anyhow::Error>,
+) -> UniformResourceWriterResult {
+    let (content, elaboration) = match extracted {
+        Ok(extracted) => extracted,
+        Err(err) => {
+            return UniformResourceWriterResult {
+                uri: uri.to_string(),
+                action: UniformResourceWriterAction::Error(err),
+            };
+        }
+    };
+    let hash = {
+        let mut hasher = Sha1::new();
+        hasher.update(&content);
+        format!("{:x}", hasher.finalize())
+    };
+
+    match urw_state.ingest_stmts.ins_ur_transform_stmt.query_row(
+        params![
+            ur_id,
+            uri.to_string(),
+            nature.to_string(),
+            hash,
+            content,
+            content.len(),
+            elaboration.to_string(),
+        ],
+        |row| row.get::<_, String>(0),
+    ) {
+        Ok(_) => UniformResourceWriterResult {
+            uri: uri.to_string(),
+            action: UniformResourceWriterAction::Inserted(ur_id.clone(), None),
+        },
+        Err(err) => UniformResourceWriterResult {
+            uri: uri.to_string(),
+            action: UniformResourceWriterAction::Error(err.into()),
+        },
+    }
+}
+
+/// The PDF itself is stored as binary content and its text, with its page
+/// count and document information as elaboration, as a `txt` transform.
+impl UniformResourceWriter<ContentResource> for PdfResource<ContentResource> {
+    fn insert(
+        &self,
+        urw_state: &mut UniformResourceWriterState<'_, '_>,
+        entry: &mut UniformResourceWriterEntry,
+    ) -> UniformResourceWriterResult {
+        let ur_res = insert_document_binary(self, urw_state, &self.resource, entry);
+        let UniformResourceWriterAction::Inserted(ur_id, _) = &ur_res.action else {
+            return ur_res;
+        };
+        let extracted = self
+            .extract()
+            .map(|document| (document.text.clone(), json!({ "pdf": document })));
+        insert_document_transform(urw_state, ur_id, &self.resource.uri, "txt", extracted)
+    }
+}
+
+/// Word documents, workbooks and presentations are stored as binary content
+/// and their paragraphs, sheet cells or slides as a `json` transform.
+impl UniformResourceWriter<ContentResource> for OfficeResource<ContentResource> {
+    fn insert(
+        &self,
+        urw_state: &mut UniformResourceWriterState<'_, '_>,
+        entry: &mut UniformResourceWriterEntry,
+    ) -> UniformResourceWriterResult {
+        let ur_res = insert_document_binary(self, urw_state, &self.resource, entry);
+        let UniformResourceWriterAction::Inserted(ur_id, _) = &ur_res.action else {
+            return ur_res;
+        };
+        let extracted = self.extract().and_then(|document| {
+            Ok((
+                serde_json::to_string_pretty(&document)?,
+                json!({ "office": document.format }),
+            ))
+        });
+        insert_document_transform(urw_state, ur_id, &self.resource.uri, "json", extracted)
+    }
+}
+
 impl UniformResourceWriter<ContentResource> for JsonResource<ContentResource> {
     fn insert(
         &self,
         urw_state: &mut UniformResourceWriterState<'_, '_>,
         entry: &mut UniformResourceWriterEntry,
     ) -> UniformResourceWriterResult {
-        self.insert_text(urw_state, &self.resource, entry)
+        if !urw_state.canonical_json || !matches!(self.format, JsonFormat::Json) {
+            return self.insert_text(urw_state, &self.resource, entry);
+        }
+
+        let json_text = match self.resource.content_text_supplier.as_ref() {
+            Some(json_supplier) => match json_supplier() {
+                Ok(json_src) => json_src.content_text().to_string(),
+                Err(err) => {
+                    return UniformResourceWriterResult {
+                        uri: self.resource.uri.clone(),
+                        action: UniformResourceWriterAction::ContentSupplierError(err),
+                    }
+                }
+            },
+            None => {
+                return UniformResourceWriterResult {
+                    uri: self.resource.uri.clone(),
+                    action: UniformResourceWriterAction::ContentUnavailable(),
+                }
+            }
+        };
+        // invalid JSON is stored as-is
+        let text = canonical_json::canonicalize_json(&json_text).unwrap_or(json_text);
+        let hash = urw_state.resources.digest.hex_digest(&text);
+        let canonical = ContentResource {
+            flags: self.resource.flags,
+            uri: self.resource.uri.clone(),
+            nature: self.resource.nature.clone(),
+            size: self.resource.size,
+            created_at: self.resource.created_at,
+            last_modified_at: self.resource.last_modified_at,
+            content_binary_supplier: None,
+            content_text_supplier: Some(Box::new(
+                move || -> Result<Box<dyn TextContent>, Box<dyn std::error::Error>> {
+                    Ok(Box::new(ResourceTextContent {
+                        text: text.clone(),
+                        hash: hash.clone(),
+                    }) as Box<dyn TextContent>)
+                },
+            )),
+        };
+        self.insert_text(urw_state, &canonical, entry)
     }
 }
 
@@ -611,6 +1232,28 @@ impl UniformResourceWriter<ContentResource> for JsonableTextResource<ContentReso
     ) -> UniformResourceWriterResult {
         self.insert_text(urw_state, &self.resource, entry)
     }
+
+    // YAML and TOML documents are stored like Markdown frontmatter which spans the
+    // whole document so config files are queryable by key; unparseable ones aren't
+    fn text_frontmatter(&self, text: &str) -> Option<(String, String)> {
+        let nature = match self.schema {
+            JsonableTextSchema::Yaml => FrontmatterNature::YamlFM,
+            JsonableTextSchema::Toml => FrontmatterNature::TomlFM,
+            _ => return None,
+        };
+        let attrs = document_frontmatter(&nature, text)
+            .ok()
+            .filter(|attrs| !attrs.is_null())?;
+        let fm_attrs = json!({
+            "frontMatter": text,
+            "body": "",
+            "attrs": attrs
+        });
+        Some((
+            serde_json::to_string_pretty(&fm_attrs).unwrap(),
+            serde_json::to_string_pretty(&attrs).unwrap(),
+        ))
+    }
 }
 
 impl UniformResourceWriter<ContentResource> for MarkdownResource<ContentResource> {
@@ -729,14 +1372,14 @@ impl UniformResourceWriter<ContentResource> for XmlResource<ContentResource> {
                     "json".to_string(),
                     hash,
                     json,
-                    json.as_bytes().len(),
+                    json.len(),
                     None::<&String>,
                 ],
                 |row| row.get::<_, String>(0),
             ) {
-                Ok(id) => UniformResourceWriterResult {
+                Ok(_) => UniformResourceWriterResult {
                     uri: uri.to_string(),
-                    action: UniformResourceWriterAction::Inserted(id, None),
+                    action: UniformResourceWriterAction::Inserted(ur_id.clone(), None),
                 },
                 Err(err) => UniformResourceWriterResult {
                     uri: uri.to_string(),
@@ -759,6 +1402,142 @@ impl UniformResourceWriter<ContentResource> for ImapResource<ContentResource> {
     }
 }
 
+/// Store each payload decoded from base64 or MIME multipart `content` as a
+/// `uniform_resource_transform` whose nature is the inner content type. Failures
+/// are logged since the encoded resource itself has already been stored.
+pub fn insert_decoded_payloads(
+    ingest_stmts: &mut IngestContext<'_>,
+    ur_id: &str,
+    uri: &str,
+    content: &str,
+) -> usize {
+    let mut inserted = 0;
+    for payload in payload::decode_payload(content) {
+        let transform_uri = match payload.part {
+            Some(part) => format!("{uri}#part-{part}"),
+            None => format!("{uri}#base64"),
+        };
+        let elaboration = json!({
+            "decoded-from": payload.encoding,
+            "content-type": payload.content_type,
+            "part": payload.part,
+            "filename": payload.filename,
+        });
+        let hash = payload.content_digest_hash();
+        let size = payload.content.len();
+        // keep textual payloads as TEXT so they can be queried with SQL (e.g. json_extract)
+        let content: rusqlite::types::Value = match String::from_utf8(payload.content) {
+            Ok(text) => text.into(),
+            Err(err) => err.into_bytes().into(),
+        };
+        match ingest_stmts.ins_ur_transform_stmt.query_row(
+            params![
+                ur_id,
+                transform_uri,
+                payload.content_type,
+                hash,
+                content,
+                size,
+                elaboration.to_string(),
+            ],
+            |row| row.get::<_, String>(0),
+        ) {
+            Ok(_) => inserted += 1,
+            Err(err) => error!(
+                "[insert_decoded_payloads] unable to store decoded payload {}: {}",
+                transform_uri, err
+            ),
+        }
+    }
+    inserted
+}
+
+/// Record that `output_ur_id` was generated by the capturable executable stored
+/// as `source_ur_id`, linked to the session entry (file or task) which ran it.
+pub fn insert_lineage(
+    ingest_stmts: &mut IngestContext<'_>,
+    ingest_session_id: &str,
+    source_ur_id: Option<&String>,
+    output_ur_id: &str,
+    ingest_fs_path_entry_id: Option<&String>,
+    ingest_task_id: Option<&String>,
+) -> rusqlite::Result<usize> {
+    ingest_stmts.ins_ur_lineage_stmt.execute(params![
+        ingest_session_id,
+        source_ur_id,
+        output_ur_id,
+        ingest_fs_path_entry_id,
+        ingest_task_id,
+    ])
+}
+
+/// Execute SQL emitted by a capturable executable inside a savepoint which is
+/// always rolled back, so the SQL is checked against the RSSD schema without
+/// mutating the database. Statements are run one at a time and transaction
+/// control (`BEGIN`, `COMMIT`, `END`, `ROLLBACK`, `SAVEPOINT`, `RELEASE`) is
+/// rejected before it runs since it could escape the savepoint.
+pub fn validate_captured_sql(conn: &Connection, sql_script: &str) -> rusqlite::Result<()> {
+    conn.execute_batch("SAVEPOINT ce_sql_validate")?;
+    let validated = execute_validated_statements(conn, sql_script);
+    conn.execute_batch("ROLLBACK TO ce_sql_validate; RELEASE ce_sql_validate")?;
+    validated
+}
+
+fn execute_validated_statements(conn: &Connection, sql_script: &str) -> rusqlite::Result<()> {
+    let mut batch = rusqlite::Batch::new(conn, sql_script);
+    while let Some(mut stmt) = batch.next()? {
+        let sql = stmt.expanded_sql().unwrap_or_default();
+        if let Some(keyword) = transaction_control_keyword(&sql) {
+            return Err(rusqlite::Error::SqliteFailure(
+                rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_AUTH),
+                Some(format!(
+                    "{keyword} is not allowed in captured SQL which is only validated"
+                )),
+            ));
+        }
+        let mut rows = stmt.raw_query();
+        while rows.next()?.is_some() {}
+    }
+    Ok(())
+}
+
+/// The leading keyword of `sql` when it is a transaction control statement,
+/// after any whitespace and comments.
+fn transaction_control_keyword(sql: &str) -> Option<String> {
+    let mut rest = sql;
+    loop {
+        rest = rest.trim_start();
+        if let Some(comment) = rest.strip_prefix("--") {
+            rest = comment.split_once('\n').map_or("", |(_, after)| after);
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            rest = comment.split_once("*/").map_or("", |(_, after)| after);
+        } else {
+            break;
+        }
+    }
+    let keyword: String = rest
+        .chars()
+        .take_while(|c| c.is_ascii_alphabetic())
+        .collect::<String>()
+        .to_uppercase();
+    matches!(
+        keyword.as_str(),
+        "BEGIN" | "COMMIT" | "END" | "ROLLBACK" | "SAVEPOINT" | "RELEASE"
+    )
+    .then_some(keyword)
+}
+
+fn filter_json_output(filter: &str, stdout: &str) -> anyhow::Result<String> {
+    let output: serde_json::Value = serde_json::from_str(stdout).with_context(|| {
+        format!(
+            "[filter_json_output] output is not valid JSON for '{}'",
+            filter
+        )
+    })?;
+    let filtered = resource::jq::jq_filter(filter, output)?;
+    Ok(serde_json::to_string_pretty(&filtered)?)
+}
+
 fn insert_uniform_resource(
     resource: &UniformResource<ContentResource>,
     urw_state: &mut UniformResourceWriterState<'_, '_>,
@@ -771,6 +1550,8 @@ fn insert_uniform_resource(
         UniformResource::JsonableText(jtr) => jtr.insert(urw_state, entry),
         UniformResource::Image(img) => img.insert(urw_state, entry),
         UniformResource::Markdown(md) => md.insert(urw_state, entry),
+        UniformResource::Office(office) => office.insert(urw_state, entry),
+        UniformResource::Pdf(pdf) => pdf.insert(urw_state, entry),
         UniformResource::PlainText(txt) => txt.insert(urw_state, entry),
         UniformResource::SourceCode(sc) => sc.insert(urw_state, entry),
         UniformResource::Xml(xml) => xml.insert(urw_state, entry),
@@ -814,6 +1595,11 @@ fn insert_uniform_resource(
 pub struct IngestFilesBehavior {
     pub classifier: EncounterableResourcePathClassifier,
     pub root_fs_paths: Vec<String>,
+    #[serde(default)]
+    pub nature_triggers: Vec<NatureTrigger>,
+    /// jq-style filter the JSON output of capturable executables was trimmed with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ce_json_filter: Option<String>,
 }
 
 impl IngestFilesBehavior {
@@ -841,12 +1627,17 @@ impl IngestFilesBehavior {
                         behavior_name, ingest_args.state_db_fs_path
                     )
                 })?;
-            let behavior = IngestFilesBehavior::from_json(&behavior_json).with_context(|| {
-                format!(
-                    "[IngestFilesBehavior.new] unable to deserialize behavior {} in {}",
-                    behavior_json, ingest_args.state_db_fs_path
-                )
-            })?;
+            let mut behavior =
+                IngestFilesBehavior::from_json(&behavior_json).with_context(|| {
+                    format!(
+                        "[IngestFilesBehavior.new] unable to deserialize behavior {} in {}",
+                        behavior_json, ingest_args.state_db_fs_path
+                    )
+                })?;
+            // a filter given on the command line overrides the saved one
+            if ingest_args.ce_json_filter.is_some() {
+                behavior.ce_json_filter = ingest_args.ce_json_filter.clone();
+            }
             Ok((behavior, Some(behavior_id)))
         } else {
             Ok((
@@ -864,6 +1655,8 @@ impl IngestFilesBehavior {
         Ok(IngestFilesBehavior {
             classifier: EncounterableResourcePathClassifier::default_from_conn(conn)?,
             root_fs_paths: args.root_fs_path.clone(),
+            nature_triggers: args.trigger.clone(),
+            ce_json_filter: args.ce_json_filter.clone(),
         })
     }
 
@@ -888,7 +1681,7 @@ impl IngestFilesBehavior {
             .query_row(
                 r#"
              INSERT INTO behavior (behavior_id, device_id, behavior_name, behavior_conf_json)
-                           VALUES (ulid(), ?, ?, ?)
+                           VALUES (surveilr_pk(), ?, ?, ?)
              ON CONFLICT (device_id, behavior_name) DO UPDATE
                      SET behavior_conf_json = EXCLUDED.behavior_conf_json, 
                          updated_at = CURRENT_TIMESTAMP
@@ -916,6 +1709,8 @@ impl IngestFilesBehavior {
 pub struct IngestTasksBehavior {
     pub lines: Vec<String>,         // what was given
     pub encounterable: Vec<String>, // after filtering for comments, blanks, etc.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ce_json_filter: Option<String>,
 }
 
 impl IngestTasksBehavior {
@@ -929,11 +1724,446 @@ impl IngestTasksBehavior {
         IngestTasksBehavior {
             lines: lines.clone(),
             encounterable: lines,
+            ce_json_filter: None,
         }
     }
 
+    #[autometrics]
+    pub fn from_file(path: &str) -> Result<Self> {
+        let lines: Vec<_> = std::fs::read_to_string(path)
+            .with_context(|| format!("[IngestTasksBehavior::from_file] unable to read {path}"))?
+            .lines()
+            .map(String::from)
+            .collect();
+        Ok(IngestTasksBehavior {
+            lines: lines.clone(),
+            encounterable: lines,
+            ce_json_filter: None,
+        })
+    }
+
     #[autometrics]
     pub fn persistable_json_text(&self) -> Result<String, serde_json::Error> {
         serde_json::to_string_pretty(self)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        files: IngestFilesArgs,
+    }
+
+    /// The schema of the RSSD and the number of rows in each of its tables.
+    fn rssd_state(state_db: &std::path::Path) -> Result<Vec<(String, Option<String>, usize)>> {
+        let dbc = DbConn::open(state_db, 0)?;
+        let tables: Vec<(String, Option<String>)> = dbc
+            .conn
+            .prepare("SELECT name, sql FROM sqlite_master WHERE type = 'table' ORDER BY name")?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+        tables
+            .into_iter()
+            .map(|(name, sql)| {
+                let rows: usize =
+                    dbc.conn
+                        .query_row(&format!("SELECT COUNT(*) FROM \"{name}\""), [], |row| {
+                            row.get(0)
+                        })?;
+                Ok((name, sql, rows))
+            })
+            .collect()
+    }
+
+    /// A root with a capturable executable emitting `sql` and an initialized RSSD.
+    fn sql_ce_fixture(sql: &str) -> Result<(tempfile::TempDir, tempfile::TempDir)> {
+        let root = tempfile::tempdir()?;
+        let state = tempfile::tempdir()?;
+        let ce = root.path().join("emit.surveilr-SQL.sh");
+        std::fs::write(&ce, format!("#!/bin/sh\ncat <<'SQL'\n{sql}\nSQL\n"))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&ce, std::fs::Permissions::from_mode(0o755))?;
+        }
+        let mut dbc = DbConn::new(state.path().join("rssd.sqlite.db"), 0)?;
+        dbc.init(None)?.commit()?;
+        Ok((root, state))
+    }
+
+    fn validate_only(root: &tempfile::TempDir, state: &tempfile::TempDir) -> Result<String> {
+        let args = Cli::parse_from([
+            "ingest",
+            "-r",
+            &root.path().to_string_lossy(),
+            "-d",
+            &state.path().join("rssd.sqlite.db").to_string_lossy(),
+            "--ce-sql-validate-only",
+        ])
+        .files;
+        Ok(ingest_files(0, &args)?.remove(0).ingest_session_id)
+    }
+
+    #[test]
+    fn test_validate_captured_sql_rejects_transaction_control() -> Result<()> {
+        let mut dbc = DbConn::new(":memory:", 0)?;
+        let tx = dbc.init(None)?;
+        validate_captured_sql(
+            &tx,
+            "CREATE TABLE validated (id); INSERT INTO validated VALUES (1);",
+        )?;
+        for script in [
+            "CREATE TABLE escaped (id); COMMIT; BEGIN;",
+            "CREATE TABLE escaped (id); END",
+            "ROLLBACK TO ce_sql_validate",
+            "CREATE TABLE escaped (id);\n-- released\nRELEASE ce_sql_validate",
+            "/* nested */ SAVEPOINT inner",
+        ] {
+            let err = validate_captured_sql(&tx, script).unwrap_err();
+            assert!(err.to_string().contains("not allowed"), "{script}: {err}");
+        }
+        assert!(!tx.is_autocommit());
+        let tables: usize = tx.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE name IN ('validated', 'escaped')",
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(tables, 0);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_validate_only_leaves_rssd_unchanged() -> Result<()> {
+        let (root, state) = sql_ce_fixture(
+            "CREATE TABLE ce_validated (id INTEGER);\nINSERT INTO ce_validated VALUES (1);",
+        )?;
+        let before = rssd_state(&state.path().join("rssd.sqlite.db"))?;
+        validate_only(&root, &state)?;
+        assert_eq!(rssd_state(&state.path().join("rssd.sqlite.db"))?, before);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_failed_validation_leaves_rssd_unchanged() -> Result<()> {
+        for sql in [
+            "CREATE TABLE ce_validated (id INTEGER);\nINSERT INTO missing_table VALUES (1);",
+            "CREATE TABLE ce_escaped (id INTEGER);\nCOMMIT;\nBEGIN;",
+        ] {
+            let (root, state) = sql_ce_fixture(sql)?;
+            let before = rssd_state(&state.path().join("rssd.sqlite.db"))?;
+            let err = validate_only(&root, &state).unwrap_err();
+            assert!(err.to_string().contains("failed validation"), "{err}");
+            assert_eq!(rssd_state(&state.path().join("rssd.sqlite.db"))?, before);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_streamed_walk_honors_collect_manifests() -> Result<()> {
+        let root = tempfile::tempdir()?;
+        let state = tempfile::tempdir()?;
+        std::fs::create_dir_all(root.path().join("finance/drafts"))?;
+        std::fs::write(root.path().join("a.md"), "# a")?;
+        std::fs::write(root.path().join("finance/b.md"), "# b")?;
+        std::fs::write(root.path().join("finance/drafts/c.md"), "# c")?;
+        std::fs::write(
+            root.path().join("finance/.surveilr_collect.yml"),
+            "ignore: ['drafts/']\n",
+        )?;
+        let ingested = |db: &str, extra: &[&str]| -> Result<(Vec<String>, String)> {
+            let state_db = state.path().join(db);
+            let mut argv = vec![
+                "ingest".to_string(),
+                "-r".to_string(),
+                root.path().to_string_lossy().to_string(),
+                "-d".to_string(),
+                state_db.to_string_lossy().to_string(),
+            ];
+            argv.extend(extra.iter().map(|arg| arg.to_string()));
+            ingest_files(0, &Cli::parse_from(argv).files)?;
+            let dbc = DbConn::open(&state_db, 0)?;
+            let basenames = dbc
+                .conn
+                .prepare(
+                    "SELECT file_basename FROM ur_ingest_session_fs_path_entry
+                      WHERE uniform_resource_id IS NOT NULL ORDER BY file_basename",
+                )?
+                .query_map([], |row| row.get(0))?
+                .collect::<rusqlite::Result<_>>()?;
+            let manifests = dbc.conn.query_row(
+                "SELECT elaboration ->> '$.collect_manifests' FROM ur_ingest_session_fs_path",
+                [],
+                |row| row.get(0),
+            )?;
+            Ok((basenames, manifests))
+        };
+        let (basenames, manifests) = ingested("single.sqlite.db", &[])?;
+        assert!(basenames.contains(&"b.md".to_string()), "{basenames:?}");
+        // the manifest found as the walk entered finance/ ignored its drafts
+        assert!(!basenames.contains(&"c.md".to_string()), "{basenames:?}");
+        assert!(manifests.contains("finance"), "{manifests}");
+        // storing in batches from parallel workers changes neither
+        assert_eq!(
+            ingested("batched.sqlite.db", &["-j", "2", "--commit-every", "1"])?,
+            (basenames, manifests)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_commit_every_keeps_stored_batches() -> Result<()> {
+        let root = tempfile::tempdir()?;
+        let state = tempfile::tempdir()?;
+        std::fs::create_dir_all(root.path().join("x/z"))?;
+        std::fs::write(root.path().join("a.md"), "# a")?;
+        std::fs::write(root.path().join("b.md"), "# b")?;
+        // the walk only reaches the broken manifest after both files
+        std::fs::write(
+            root.path().join("x/z/.surveilr_collect.yml"),
+            "acquires: [pdf]\n",
+        )?;
+        let stored = |db: &str, extra: &[&str]| -> Result<usize> {
+            let state_db = state.path().join(db);
+            DbConn::new(&state_db, 0)?.init(None)?.commit()?;
+            let mut argv = vec![
+                "ingest".to_string(),
+                "-r".to_string(),
+                root.path().to_string_lossy().to_string(),
+                "-d".to_string(),
+                state_db.to_string_lossy().to_string(),
+                "--walk-order".to_string(),
+                "breadth-first".to_string(),
+            ];
+            argv.extend(extra.iter().map(|arg| arg.to_string()));
+            let err = ingest_files(0, &Cli::parse_from(argv).files).unwrap_err();
+            assert!(format!("{err:#}").contains("collect manifests"), "{err:#}");
+            let dbc = DbConn::open(&state_db, 0)?;
+            Ok(dbc
+                .conn
+                .query_row("SELECT COUNT(*) FROM uniform_resource", [], |row| {
+                    row.get(0)
+                })?)
+        };
+        assert_eq!(stored("whole.sqlite.db", &[])?, 0);
+        assert_eq!(stored("batched.sqlite.db", &["--commit-every", "1"])?, 1);
+
+        assert!(Cli::try_parse_from([
+            "ingest",
+            "-r",
+            ".",
+            "--commit-every",
+            "1",
+            "--ce-sql-validate-only"
+        ])
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_entries_record_their_git_repo() -> Result<()> {
+        let root = tempfile::tempdir()?;
+        let state = tempfile::tempdir()?;
+        // a directory of clones, the root itself isn't in a repository
+        for (repo, branch) in [("api", "main"), ("web", "dev")] {
+            let repo = root.path().join(repo);
+            std::fs::create_dir_all(repo.join(".git"))?;
+            std::fs::write(
+                repo.join(".git/HEAD"),
+                format!("ref: refs/heads/{branch}\n"),
+            )?;
+            std::fs::write(repo.join("README.md"), "# repo")?;
+        }
+        std::fs::write(root.path().join("notes.md"), "# notes")?;
+
+        let state_db = state.path().join("rssd.sqlite.db");
+        let args = Cli::parse_from([
+            "ingest",
+            "-r",
+            &root.path().to_string_lossy(),
+            "-d",
+            &state_db.to_string_lossy(),
+        ])
+        .files;
+        ingest_files(0, &args)?;
+
+        let dbc = DbConn::open(&state_db, 0)?;
+        let entries: Vec<(String, Option<String>, Option<String>)> = dbc
+            .conn
+            .prepare(
+                "SELECT file_path_rel, elaboration ->> '$.git.branch',
+                        elaboration ->> '$.git.root_path_rel'
+                   FROM ur_ingest_session_fs_path_entry
+                  WHERE file_basename != 'HEAD' ORDER BY file_path_rel",
+            )?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+        let readme = Some("README.md".to_string());
+        assert_eq!(
+            entries,
+            vec![
+                ("api/README.md".into(), Some("main".into()), readme.clone()),
+                ("notes.md".into(), None, None),
+                ("web/README.md".into(), Some("dev".into()), readme),
+            ]
+        );
+        let root_git: Option<String> = dbc.conn.query_row(
+            "SELECT elaboration ->> '$.git' FROM ur_ingest_session_fs_path",
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(root_git, None);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_capturable_exec_lineage() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = tempfile::tempdir()?;
+        let state = tempfile::tempdir()?;
+        for (name, script) in [
+            ("hosts.surveilr[json].sh", "echo '[{\"host\": \"db\"}]'"),
+            ("uptime.surveilr[txt].sh", "echo up"),
+        ] {
+            let ce = root.path().join(name);
+            // drain the stdin surveilr writes to every capturable executable
+            std::fs::write(&ce, format!("#!/bin/sh\ncat > /dev/null\n{script}\n"))?;
+            std::fs::set_permissions(&ce, std::fs::Permissions::from_mode(0o755))?;
+        }
+        std::fs::write(root.path().join("notes.md"), "# notes")?;
+        let state_db = state.path().join("rssd.sqlite.db");
+        // the executables are only stored as resources when their content is acquired
+        let mut dbc = DbConn::new(&state_db, 0)?;
+        let tx = dbc.init(None)?;
+        tx.execute(
+            "UPDATE ur_ingest_resource_path_match_rule
+                SET flags = 'CAPTURABLE_EXECUTABLE | CONTENT_ACQUIRABLE'
+              WHERE namespace = 'default' AND flags = 'CAPTURABLE_EXECUTABLE'",
+            [],
+        )?;
+        tx.commit()?;
+        let args = Cli::parse_from([
+            "ingest",
+            "-r",
+            &root.path().to_string_lossy(),
+            "-d",
+            &state_db.to_string_lossy(),
+        ])
+        .files;
+        let session_id = ingest_files(0, &args)?.remove(0).ingest_session_id;
+
+        let dbc = DbConn::open(&state_db, 0)?;
+        let lineage: Vec<(String, String, String, String)> = dbc
+            .conn
+            .prepare(
+                "SELECT e.file_basename, src.uri, src.uniform_resource_id, out.nature
+                   FROM uniform_resource_lineage l
+                   JOIN ur_ingest_session_fs_path_entry e
+                     ON e.ur_ingest_session_fs_path_entry_id = l.ingest_fs_path_entry_id
+                   JOIN uniform_resource src ON src.uniform_resource_id = l.source_uniform_resource_id
+                   JOIN uniform_resource out ON out.uniform_resource_id = l.output_uniform_resource_id
+                  WHERE l.ingest_session_id = ?
+                    AND e.uniform_resource_id = l.output_uniform_resource_id
+               ORDER BY e.file_basename",
+            )?
+            .query_map([&session_id], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        // one row per output, linked to the entry and the executable which made it
+        let names: Vec<_> = lineage
+            .iter()
+            .map(|(entry, _, _, nature)| (entry.as_str(), nature.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("hosts.surveilr[json].sh", "json"),
+                ("uptime.surveilr[txt].sh", "txt")
+            ]
+        );
+        for (entry, source_uri, source_id, _) in &lineage {
+            assert!(source_uri.ends_with(entry.as_str()), "{source_uri}");
+            let captured: String = dbc.conn.query_row(
+                "SELECT uniform_resource_id FROM ur_ingest_session_fs_path_entry
+                  WHERE ingest_session_id = ? AND file_basename = ?",
+                [&session_id, entry],
+                |row| row.get(0),
+            )?;
+            assert_ne!(source_id, &captured);
+        }
+        let linked: usize = dbc.conn.query_row(
+            "SELECT COUNT(*) FROM uniform_resource_lineage WHERE ingest_session_id = ?",
+            [&session_id],
+            |row| row.get(0),
+        )?;
+        assert_eq!(linked, 2, "notes.md has no lineage");
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_ce_json_filter_is_recorded() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = tempfile::tempdir()?;
+        let state = tempfile::tempdir()?;
+        let ce = root.path().join("hosts.surveilr[json].sh");
+        std::fs::write(
+            &ce,
+            "#!/bin/sh\ncat > /dev/null\necho '{\"hosts\": [\"db\"], \"noise\": 1}'\n",
+        )?;
+        std::fs::set_permissions(&ce, std::fs::Permissions::from_mode(0o755))?;
+        let state_db = state.path().join("rssd.sqlite.db");
+        let ingest = |args: &[&str]| -> Result<String> {
+            let mut cli = vec![
+                "ingest",
+                "-r",
+                root.path().to_str().unwrap(),
+                "-d",
+                state_db.to_str().unwrap(),
+            ];
+            cli.extend(args);
+            Ok(ingest_files(0, &Cli::parse_from(cli).files)?
+                .remove(0)
+                .ingest_session_id)
+        };
+
+        // the saved behavior applies the filter to later sessions too
+        let saved = ingest(&["--ce-json-filter", ".hosts", "--save-behavior", "hosts"])?;
+        // sessions are unique per device and second
+        std::thread::sleep(std::time::Duration::from_millis(1100));
+        let sessions = [saved, ingest(&["--behavior", "hosts"])?];
+        let dbc = DbConn::open(&state_db, 0)?;
+        for session_id in &sessions {
+            let (behavior_filter, content, elaboration_filter): (String, String, String) =
+                dbc.conn.query_row(
+                    "SELECT s.behavior_json ->> '$.ce_json_filter', ur.content,
+                            ur.elaboration ->> '$.ce_json_filter'
+                       FROM ur_ingest_session s
+                       JOIN ur_ingest_session_fs_path_entry e
+                         ON e.ingest_session_id = s.ur_ingest_session_id
+                       JOIN uniform_resource ur ON ur.uniform_resource_id = e.uniform_resource_id
+                      WHERE s.ur_ingest_session_id = ?",
+                    [session_id],
+                    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+                )?;
+            assert_eq!(behavior_filter, ".hosts");
+            assert_eq!(elaboration_filter, ".hosts");
+            assert_eq!(
+                serde_json::from_str::<serde_json::Value>(&content)?,
+                json!(["db"])
+            );
+        }
+        Ok(())
+    }
+}
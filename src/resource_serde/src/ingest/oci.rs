@@ -0,0 +1,154 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use resource::oci::{OciImage, OciPlatform, RegistryCredentials};
+use resource::EncounterableResourcePathClassifier;
+use rusqlite::params;
+use serde_json::json;
+use tracing::debug;
+
+use super::uris::{content_value, sha1_hex, UNFETCHED_CONTENT_DIGEST};
+use super::{extract_persistence, INS_UR_INGEST_SESSION_FINISH_SQL, INS_UR_INGEST_SESSION_SQL};
+use crate::cmd::IngestOciArgs;
+use crate::persist::*;
+use crate::reclassify::classified_nature;
+
+const INS_OCI_UR_SQL: &str = "
+    INSERT INTO uniform_resource (uniform_resource_id, device_id, ingest_session_id, uri, nature, content, content_digest, size_bytes, last_modified_at, elaboration)
+                          VALUES (surveilr_pk(), ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                     ON CONFLICT (device_id, content_digest, uri, size_bytes, last_modified_at)
+                   DO UPDATE SET size_bytes = EXCLUDED.size_bytes
+                       RETURNING uniform_resource_id";
+
+/// Ingest the regular files of a container image's flattened file system as
+/// uniform resources, `<image>!/<path>`, without running the image. The image
+/// is read from a `docker save` or OCI layout tarball when `args.image` is an
+/// existing file, otherwise it's pulled from its registry.
+pub async fn ingest_oci(args: &IngestOciArgs) -> Result<String> {
+    let platform: OciPlatform = args.platform.parse()?;
+    let image = if Path::new(&args.image).is_file() {
+        OciImage::from_tarball(Path::new(&args.image), &platform)
+    } else {
+        let credentials = match (&args.username, &args.password) {
+            (Some(username), Some(password)) => Some(RegistryCredentials {
+                username: username.clone(),
+                password: password.clone(),
+            }),
+            _ => None,
+        };
+        OciImage::pull(
+            &args.image,
+            &platform,
+            credentials.as_ref(),
+            args.plain_http,
+        )
+        .await
+    }
+    .with_context(|| format!("[ingest_oci] image {}", args.image))?;
+
+    let mut dbc = DbConn::new(&args.state_db_fs_path, 0).with_context(|| {
+        format!(
+            "[ingest_oci] SQLite transaction in {}",
+            args.state_db_fs_path
+        )
+    })?;
+    let db_fs_path = dbc.db_fs_path.clone();
+    let tx = dbc.init(Some(&args.state_db_init_sql))?;
+    let (device_id, _device_name) = upserted_device(&tx, &common::DEVICE)
+        .with_context(|| format!("[ingest_oci] upserted_device in {}", db_fs_path))?;
+    let classifier = EncounterableResourcePathClassifier::default_from_conn(&tx)?;
+
+    let behavior = json!({ "oci": { "image": args.image, "platform": args.platform } }).to_string();
+    let ingest_session_id: String = tx
+        .query_row(
+            INS_UR_INGEST_SESSION_SQL,
+            params![device_id, None::<String>, behavior],
+            |row| row.get(0),
+        )
+        .with_context(|| format!("[ingest_oci] inserting ingest session in {}", db_fs_path))?;
+    debug!("OCI Session: {ingest_session_id}");
+
+    let mut ins_ur_stmt = tx.prepare(INS_OCI_UR_SQL)?;
+    let mut ignored = 0;
+    let summary = image.walk(args.max_file_size, |file, content| {
+        let Some(nature) = classified_nature(&classifier, &file.path) else {
+            ignored += 1;
+            return Ok(());
+        };
+        // files without an extension (binaries, most of /etc) aren't JSON
+        let nature = match Path::new(&file.path).extension() {
+            None if nature == "json" => "bin".to_string(),
+            _ => nature,
+        };
+        let uri = format!("{}!{}", image.reference, file.path);
+        let layer = &image.layers[file.layer];
+        let elaboration = json!({
+            "oci": {
+                "image": image.reference,
+                "manifest_digest": image.manifest_digest,
+                "layer": {
+                    "index": file.layer,
+                    "digest": layer.digest,
+                    "media_type": layer.media_type,
+                },
+                "path": file.path,
+                "mode": format!("{:o}", file.mode),
+                "oversized": content.is_none(),
+            }
+        })
+        .to_string();
+        let (digest, content) = match content {
+            Some(content) => (sha1_hex(&content), Some(content_value(content))),
+            None => (UNFETCHED_CONTENT_DIGEST.to_string(), None),
+        };
+        ins_ur_stmt
+            .query_row(
+                params![
+                    device_id,
+                    ingest_session_id,
+                    uri,
+                    nature,
+                    content,
+                    digest,
+                    file.size,
+                    chrono::DateTime::from_timestamp(file.mtime, 0).map(|at| at.to_string()),
+                    elaboration,
+                ],
+                |row| row.get::<_, String>(0),
+            )
+            .with_context(|| format!("[ingest_oci] {uri}"))?;
+        Ok(())
+    })?;
+    drop(ins_ur_stmt);
+    let persistence = extract_persistence(&tx, &ingest_session_id)
+        .with_context(|| format!("[ingest_oci] persistence extraction in {}", db_fs_path))?;
+
+    let session_elaboration = json!({
+        "oci": {
+            "image": image.reference,
+            "source": image.source,
+            "name": image.name,
+            "manifest_digest": image.manifest_digest,
+            "config_digest": image.config_digest,
+            "layers": image.layers,
+            "files": summary.files - ignored,
+            "ignored": ignored,
+            "oversized": summary.oversized,
+            "whiteouts": summary.whiteouts,
+        },
+        "persistence": persistence,
+    })
+    .to_string();
+    tx.execute(
+        INS_UR_INGEST_SESSION_FINISH_SQL,
+        params![ingest_session_id, session_elaboration],
+    )
+    .with_context(|| format!("[ingest_oci] finishing session in {}", db_fs_path))?;
+    tx.commit().with_context(|| {
+        format!(
+            "[ingest_oci] unable to perform final commit in {}",
+            db_fs_path
+        )
+    })?;
+    Ok(ingest_session_id)
+}
@@ -0,0 +1,215 @@
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use serde_json::{json, Value as JsonValue};
+use sha1::{Digest, Sha1};
+use tracing::{debug, error};
+
+use super::{
+    INS_UR_INGEST_SESSION_FINISH_SQL, INS_UR_INGEST_SESSION_SQL, INS_UR_IS_TASK_SQL, INS_UR_SQL,
+};
+use crate::persist::*;
+
+/// The outcome of one osquery pack query executed on one SSH target.
+#[derive(Debug, Clone, Serialize)]
+pub struct OsqueryPackResult {
+    /// the target's identifier, recorded as the name of its `device` row
+    pub host_id: String,
+    /// the target's connection string (e.g. `ssh://admin@10.0.0.5:22`),
+    /// recorded as the boundary of its `device` row
+    pub ssh_target: String,
+    pub query_name: String,
+    pub query: String,
+    pub rows: std::result::Result<Vec<JsonValue>, String>,
+}
+
+/// Store the results of an osquery pack executed across SSH targets in a new
+/// ingest session. Each host becomes a `device` and each successful query a
+/// `uniform_resource` (JSON rows) owned by that device; every query, including
+/// failed ones, is recorded in `ur_ingest_session_task`.
+pub fn persist_osquery_pack_results(
+    conn: &Connection,
+    pack_name: &str,
+    behavior_json: &str,
+    results: &[OsqueryPackResult],
+) -> Result<String> {
+    let (collector_device_id, _) = upserted_device(conn, &common::DEVICE)
+        .context("[persist_osquery_pack_results] upserted collector device")?;
+    let ingest_session_id: String = conn
+        .query_row(
+            INS_UR_INGEST_SESSION_SQL,
+            params![collector_device_id, None::<String>, behavior_json],
+            |row| row.get(0),
+        )
+        .context("[persist_osquery_pack_results] inserting ingest session")?;
+
+    let mut succeeded = 0;
+    for result in results {
+        let (host_device_id, _): (String, String) = upsert_device(
+            conn,
+            &result.host_id,
+            &result.ssh_target,
+            &common::DEVICE.state_json(),
+            &json!({ "ssh_target": result.ssh_target }).to_string(),
+        )
+        .with_context(|| {
+            format!(
+                "[persist_osquery_pack_results] upserting device {}",
+                result.host_id
+            )
+        })?;
+        let uri = format!(
+            "{}/osquery/{}/{}",
+            result.ssh_target, pack_name, result.query_name
+        );
+        let captured_executable = json!({
+            "pack": pack_name,
+            "query_name": result.query_name,
+            "query": result.query,
+            "host_id": result.host_id,
+            "ssh_target": result.ssh_target,
+        })
+        .to_string();
+
+        let (uniform_resource_id, ur_status, ur_diagnostics) = match &result.rows {
+            Ok(rows) => {
+                let content = serde_json::to_string(rows)?;
+                let mut hasher = Sha1::new();
+                hasher.update(content.as_bytes());
+                let content_digest = format!("{:x}", hasher.finalize());
+                let ur_id: String = conn
+                    .query_row(
+                        INS_UR_SQL,
+                        params![
+                            host_device_id,
+                            ingest_session_id,
+                            None::<String>,
+                            uri,
+                            "json",
+                            content,
+                            content_digest,
+                            content.len(),
+                            None::<String>,
+                            None::<String>,
+                            None::<String>,
+                            None::<String>
+                        ],
+                        |row| row.get(0),
+                    )
+                    .with_context(|| format!("[persist_osquery_pack_results] inserting {}", uri))?;
+                succeeded += 1;
+                debug!("{}: {} rows", uri, rows.len());
+                (Some(ur_id), None, None)
+            }
+            Err(err) => {
+                error!("[persist_osquery_pack_results] {} failed: {}", uri, err);
+                (
+                    None,
+                    Some("ERROR".to_string()),
+                    Some(
+                        json!({ "message": "osquery pack query failed", "error": err }).to_string(),
+                    ),
+                )
+            }
+        };
+
+        conn.query_row(
+            INS_UR_IS_TASK_SQL,
+            params![
+                ingest_session_id,
+                uniform_resource_id,
+                captured_executable,
+                ur_status,
+                ur_diagnostics
+            ],
+            |row| row.get::<_, String>(0),
+        )
+        .with_context(|| {
+            format!(
+                "[persist_osquery_pack_results] inserting task entry for {}",
+                uri
+            )
+        })?;
+    }
+
+    conn.execute(
+        INS_UR_INGEST_SESSION_FINISH_SQL,
+        params![
+            ingest_session_id,
+            json!({ "pack": pack_name, "succeeded": succeeded, "failed": results.len() - succeeded })
+                .to_string()
+        ],
+    )
+    .context("[persist_osquery_pack_results] finishing ingest session")?;
+
+    Ok(ingest_session_id)
+}
+
+/// Open (and migrate) the RSSD and store the pack results in a single transaction.
+pub fn ingest_osquery_pack(
+    debug: u8,
+    state_db_fs_path: &str,
+    pack_name: &str,
+    behavior_json: &str,
+    results: &[OsqueryPackResult],
+) -> Result<String> {
+    let mut dbc = DbConn::new(state_db_fs_path, debug)
+        .with_context(|| format!("[ingest_osquery_pack] SQLite database {}", state_db_fs_path))?;
+    let tx = dbc.init(None)?;
+    let ingest_session_id = persist_osquery_pack_results(&tx, pack_name, behavior_json, results)?;
+    tx.commit().with_context(|| {
+        format!(
+            "[ingest_osquery_pack] unable to perform final commit in {}",
+            state_db_fs_path
+        )
+    })?;
+    Ok(ingest_session_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_results_have_per_host_provenance() -> Result<()> {
+        let mut dbc = DbConn::new(":memory:", 0)?;
+        let tx = dbc.init(None)?;
+        let results = vec![
+            OsqueryPackResult {
+                host_id: "web-1".to_string(),
+                ssh_target: "ssh://admin@10.0.0.5:22".to_string(),
+                query_name: "listening_ports".to_string(),
+                query: "SELECT port FROM listening_ports".to_string(),
+                rows: Ok(vec![json!({ "port": "22" }), json!({ "port": "443" })]),
+            },
+            OsqueryPackResult {
+                host_id: "db-1".to_string(),
+                ssh_target: "ssh://admin@10.0.0.6".to_string(),
+                query_name: "listening_ports".to_string(),
+                query: "SELECT port FROM listening_ports".to_string(),
+                rows: Err("connection refused".to_string()),
+            },
+        ];
+        let session_id = persist_osquery_pack_results(&tx, "ir", "{}", &results)?;
+
+        let (device, uri, rows): (String, String, i64) = tx.query_row(
+            "SELECT d.name, ur.uri, json_array_length(ur.content)
+               FROM uniform_resource ur JOIN device d ON d.device_id = ur.device_id
+              WHERE ur.ingest_session_id = ?",
+            [&session_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+        assert_eq!(device, "web-1");
+        assert_eq!(uri, "ssh://admin@10.0.0.5:22/osquery/ir/listening_ports");
+        assert_eq!(rows, 2);
+
+        let failed: String = tx.query_row(
+            "SELECT json_extract(captured_executable, '$.host_id') FROM ur_ingest_session_task
+              WHERE ingest_session_id = ? AND ur_status = 'ERROR'",
+            [&session_id],
+            |row| row.get(0),
+        )?;
+        assert_eq!(failed, "db-1");
+        Ok(())
+    }
+}
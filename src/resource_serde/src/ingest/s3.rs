@@ -0,0 +1,502 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use resource::remote::{RemoteFetcher, RemoteMetaData, S3Config, S3Object};
+use resource::{EncounterableResourceFlags, EncounterableResourcePathClassifier};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::json;
+use tracing::{debug, error};
+
+use super::uris::{
+    cache_content, remote_elaboration, FetchedContent, REMOTE_CONTENT_CHUNK_SIZE,
+    SEL_REMOTE_UR_SQL, UNFETCHED_CONTENT_DIGEST,
+};
+use super::{
+    INS_UR_INGEST_SESSION_FINISH_SQL, INS_UR_INGEST_SESSION_SQL, INS_UR_ISFSP_ENTRY_SQL,
+    INS_UR_ISFSP_SQL,
+};
+use crate::cmd::IngestS3Args;
+use crate::persist::*;
+use crate::reclassify::classified;
+
+const INS_S3_UR_SQL: &str = "
+    INSERT INTO uniform_resource (uniform_resource_id, device_id, ingest_session_id, ingest_fs_path_id, uri, nature, content, content_digest, size_bytes, last_modified_at, elaboration)
+                          VALUES (surveilr_pk(), ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                     ON CONFLICT (device_id, content_digest, uri, size_bytes, last_modified_at)
+                   DO UPDATE SET size_bytes = EXCLUDED.size_bytes
+                       RETURNING uniform_resource_id";
+
+impl IngestS3Args {
+    /// The AWS environment variables' configuration, overridden by the arguments.
+    pub fn s3_config(&self) -> S3Config {
+        let mut s3 = S3Config::from_env();
+        if let Some(region) = &self.region {
+            s3.region = region.clone();
+        }
+        if let Some(endpoint) = &self.endpoint {
+            s3.endpoint = Some(endpoint.clone());
+        }
+        if let Some(access_key_id) = &self.access_key_id {
+            s3.access_key_id = Some(access_key_id.clone());
+        }
+        if let Some(secret_access_key) = &self.secret_access_key {
+            s3.secret_access_key = Some(secret_access_key.clone());
+        }
+        if let Some(session_token) = &self.session_token {
+            s3.session_token = Some(session_token.clone());
+        }
+        s3
+    }
+}
+
+/// The bucket prefix an object was listed from, recorded like a walked root path.
+struct S3Root<'a> {
+    ingest_fs_path_id: String,
+    bucket: &'a str,
+    prefix: &'a str,
+}
+
+impl S3Root<'_> {
+    fn root_path(&self) -> String {
+        format!("s3://{}/{}", self.bucket, self.prefix)
+    }
+}
+
+/// Walk the objects under the prefixes of an S3 (or S3-compatible) bucket and
+/// store them as uniform resources the way `ingest files` stores the files of
+/// root paths: each prefix is an `ur_ingest_session_fs_path` and each object a
+/// `ur_ingest_session_fs_path_entry`. The classifier's path rules decide which
+/// objects are ignored and which have their content fetched, the others (and
+/// all of them with `meta_only`) are cataloged for `resources fetch`.
+pub async fn ingest_s3(args: &IngestS3Args) -> Result<String> {
+    let fetcher = RemoteFetcher::new(args.s3_config());
+    let prefixes = match args.prefix.is_empty() {
+        true => vec![String::new()],
+        false => args.prefix.clone(),
+    };
+
+    let mut dbc = DbConn::new(&args.state_db_fs_path, 0).with_context(|| {
+        format!(
+            "[ingest_s3] SQLite transaction in {}",
+            args.state_db_fs_path
+        )
+    })?;
+    let db_fs_path = dbc.db_fs_path.clone();
+    let tx = dbc.init(Some(&args.state_db_init_sql))?;
+    let (device_id, _device_name) = upserted_device(&tx, &common::DEVICE)
+        .with_context(|| format!("[ingest_s3] upserted_device in {}", db_fs_path))?;
+    let classifier = EncounterableResourcePathClassifier::default_from_conn(&tx)?;
+
+    let behavior = json!({
+        "s3": {
+            "bucket": args.bucket,
+            "prefixes": prefixes,
+            "region": fetcher.s3.region,
+            "endpoint": fetcher.s3.endpoint,
+            "meta_only": args.meta_only,
+            "max_object_size": args.max_object_size,
+        }
+    })
+    .to_string();
+    let ingest_session_id: String = tx
+        .query_row(
+            INS_UR_INGEST_SESSION_SQL,
+            params![device_id, None::<String>, behavior],
+            |row| row.get(0),
+        )
+        .with_context(|| format!("[ingest_s3] inserting ingest session in {}", db_fs_path))?;
+    debug!("S3 Session: {ingest_session_id}");
+
+    let (mut objects, mut fetched, mut cataloged, mut ignored) = (0, 0, 0, 0);
+    let mut failed = Vec::new();
+    for prefix in &prefixes {
+        let objects_listed = fetcher
+            .list_objects(&args.bucket, prefix)
+            .await
+            .with_context(|| format!("[ingest_s3] listing s3://{}/{}", args.bucket, prefix))?;
+        let root_path = format!("s3://{}/{}", args.bucket, prefix);
+        let elaboration = json!({
+            "s3": { "bucket": args.bucket, "prefix": prefix, "objects": objects_listed.len() }
+        });
+        let root = S3Root {
+            ingest_fs_path_id: tx
+                .query_row(
+                    INS_UR_ISFSP_SQL,
+                    params![ingest_session_id, root_path, elaboration.to_string()],
+                    |row| row.get(0),
+                )
+                .with_context(|| format!("[ingest_s3] inserting {root_path} in {db_fs_path}"))?,
+            bucket: &args.bucket,
+            prefix,
+        };
+        debug!("  S3 Prefix: {root_path} ({})", root.ingest_fs_path_id);
+
+        for object in objects_listed {
+            objects += 1;
+            let uri = format!("s3://{}/{}", args.bucket, object.key);
+            let Some((nature, flags)) = classified(&classifier, &uri) else {
+                ignored += 1;
+                continue;
+            };
+            let acquire = !args.meta_only
+                && object.size_bytes <= args.max_object_size
+                && flags.contains(EncounterableResourceFlags::CONTENT_ACQUIRABLE);
+            let remote = match acquire {
+                true => {
+                    let mut content = FetchedContent::new(REMOTE_CONTENT_CHUNK_SIZE);
+                    fetcher
+                        .fetch_object_into(&args.bucket, &object.key, &mut content)
+                        .await
+                        .map(|meta_data| (meta_data, Some(content)))
+                }
+                false => Ok((object.meta_data(), None)),
+            };
+            let inserted = match remote {
+                Ok((meta_data, content)) => insert_object(
+                    &tx,
+                    &device_id,
+                    &ingest_session_id,
+                    &root,
+                    &object,
+                    &nature,
+                    &meta_data,
+                    content,
+                ),
+                Err(err) => {
+                    insert_object_error(&tx, &ingest_session_id, &root, &object, &err).and(Err(err))
+                }
+            };
+            match inserted {
+                Ok(true) => fetched += 1,
+                Ok(false) => cataloged += 1,
+                Err(err) => {
+                    error!("[ingest_s3] {} in {}: {:#}", uri, db_fs_path, err);
+                    failed.push(json!({ "uri": uri, "error": format!("{:#}", err) }));
+                }
+            }
+        }
+    }
+
+    let session_elaboration = json!({
+        "s3": {
+            "bucket": args.bucket,
+            "objects": objects,
+            "fetched": fetched,
+            "cataloged": cataloged,
+            "ignored": ignored,
+            "failed": failed,
+        }
+    })
+    .to_string();
+    tx.execute(
+        INS_UR_INGEST_SESSION_FINISH_SQL,
+        params![ingest_session_id, session_elaboration],
+    )
+    .with_context(|| format!("[ingest_s3] finishing session in {}", db_fs_path))?;
+    tx.commit().with_context(|| {
+        format!(
+            "[ingest_s3] unable to perform final commit in {}",
+            db_fs_path
+        )
+    })?;
+    Ok(ingest_session_id)
+}
+
+/// `ur_ingest_session_fs_path_entry` path columns of an object: the object's
+/// URI, the prefix's URI, the key relative to the prefix, the base name and
+/// the extension.
+fn object_path(root: &S3Root, object: &S3Object) -> (String, String, String, String, String) {
+    let file_path_rel = object
+        .key
+        .strip_prefix(root.prefix)
+        .unwrap_or(&object.key)
+        .trim_start_matches('/');
+    let file_basename = object.key.rsplit('/').next().unwrap_or_default();
+    let file_extn = Path::new(file_basename)
+        .extension()
+        .map(|extn| extn.to_string_lossy().to_string())
+        .unwrap_or_default();
+    (
+        format!("s3://{}/{}", root.bucket, object.key),
+        root.root_path(),
+        file_path_rel.to_string(),
+        file_basename.to_string(),
+        file_extn,
+    )
+}
+
+/// Insert (or find) the uniform resource of an object and its walk entry,
+/// storing `content` when it was fetched. Returns whether the resource has its
+/// content.
+#[allow(clippy::too_many_arguments)]
+fn insert_object(
+    conn: &Connection,
+    device_id: &str,
+    ingest_session_id: &str,
+    root: &S3Root,
+    object: &S3Object,
+    nature: &str,
+    meta_data: &RemoteMetaData,
+    content: Option<FetchedContent>,
+) -> Result<bool> {
+    let (uri, file_path_rel_parent, file_path_rel, file_basename, file_extn) =
+        object_path(root, object);
+    let last_modified_at = object.last_modified_at.map(|at| at.to_string());
+    let size_bytes = content
+        .as_ref()
+        .map(FetchedContent::size)
+        .unwrap_or(object.size_bytes);
+
+    // an object (version) is stored once, its content filled in when it's fetched
+    let existing: Option<(String, bool)> = conn
+        .query_row(
+            SEL_REMOTE_UR_SQL,
+            params![device_id, uri, last_modified_at, size_bytes],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    let (uniform_resource_id, fetched) = match (existing, content) {
+        (Some((uniform_resource_id, has_content)), None) => (uniform_resource_id, has_content),
+        (Some((uniform_resource_id, _)), Some(mut content)) => {
+            cache_content(conn, &uniform_resource_id, meta_data, &mut content)
+                .with_context(|| format!("[insert_object] caching {uri}"))?;
+            (uniform_resource_id, true)
+        }
+        (None, mut content) => {
+            let fetched = content.is_some();
+            let (digest, row_value) = match content.as_mut() {
+                Some(content) => (content.digest(), content.row_value()?),
+                None => (UNFETCHED_CONTENT_DIGEST.to_string(), None),
+            };
+            let elaboration = json!({
+                "remote": remote_elaboration(meta_data, fetched),
+                "s3": {
+                    "bucket": root.bucket,
+                    "key": object.key,
+                    "storage_class": object.storage_class,
+                },
+            });
+            let uniform_resource_id: String = conn
+                .query_row(
+                    INS_S3_UR_SQL,
+                    params![
+                        device_id,
+                        ingest_session_id,
+                        root.ingest_fs_path_id,
+                        uri,
+                        nature,
+                        row_value,
+                        digest,
+                        size_bytes,
+                        last_modified_at,
+                        elaboration.to_string(),
+                    ],
+                    |row| row.get(0),
+                )
+                .with_context(|| format!("[insert_object] {uri}"))?;
+            if let (Some(content), None) = (content.as_mut(), &row_value) {
+                content
+                    .store_chunks(conn, &uniform_resource_id)
+                    .with_context(|| format!("[insert_object] storing chunks of {uri}"))?;
+            }
+            (uniform_resource_id, fetched)
+        }
+    };
+    conn.query_row(
+        INS_UR_ISFSP_ENTRY_SQL,
+        params![
+            ingest_session_id,
+            root.ingest_fs_path_id,
+            uniform_resource_id,
+            uri,
+            file_path_rel_parent,
+            file_path_rel,
+            file_basename,
+            file_extn,
+            None::<String>,
+            None::<String>,
+            None::<String>,
+            None::<String>,
+        ],
+        |row| row.get::<_, String>(0),
+    )
+    .with_context(|| format!("[insert_object] walk entry of {uri}"))?;
+    Ok(fetched)
+}
+
+/// Record an object whose content couldn't be fetched as an `ERROR` walk entry.
+fn insert_object_error(
+    conn: &Connection,
+    ingest_session_id: &str,
+    root: &S3Root,
+    object: &S3Object,
+    err: &anyhow::Error,
+) -> Result<()> {
+    let (uri, file_path_rel_parent, file_path_rel, file_basename, file_extn) =
+        object_path(root, object);
+    conn.query_row(
+        INS_UR_ISFSP_ENTRY_SQL,
+        params![
+            ingest_session_id,
+            root.ingest_fs_path_id,
+            None::<String>,
+            uri,
+            file_path_rel_parent,
+            file_path_rel,
+            file_basename,
+            file_extn,
+            "ERROR",
+            json!({ "message": "unable to fetch the object", "error": format!("{:#}", err) })
+                .to_string(),
+            None::<String>,
+            None::<String>,
+        ],
+        |row| row.get::<_, String>(0),
+    )
+    .with_context(|| format!("[insert_object_error] walk entry of {uri}"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::uris::sha1_hex;
+    use super::*;
+    use crate::persist::DbConn;
+
+    #[test]
+    fn test_objects_are_walk_entries() -> Result<()> {
+        let mut dbc = DbConn::new(":memory:", 0)?;
+        let tx = dbc.init(None)?;
+        let (device_id, _) = upserted_device(&tx, &common::DEVICE)?;
+        let session_id: String = tx.query_row(
+            INS_UR_INGEST_SESSION_SQL,
+            params![device_id, None::<String>, "{}"],
+            |row| row.get(0),
+        )?;
+        let root = S3Root {
+            ingest_fs_path_id: tx.query_row(
+                INS_UR_ISFSP_SQL,
+                params![session_id, "s3://evidence/scans/", "{}"],
+                |row| row.get(0),
+            )?,
+            bucket: "evidence",
+            prefix: "scans/",
+        };
+
+        let last_modified_at = chrono::DateTime::from_timestamp(1_711_886_400, 0);
+        let report = S3Object {
+            key: "scans/2024/q1.md".to_string(),
+            size_bytes: 7,
+            last_modified_at,
+            etag: Some("abc".to_string()),
+            ..Default::default()
+        };
+        let image = S3Object {
+            key: "scans/2024/q1.png".to_string(),
+            size_bytes: 1024,
+            last_modified_at,
+            storage_class: Some("GLACIER".to_string()),
+            ..Default::default()
+        };
+        // cataloged first, then fetched into the same resource
+        assert!(!insert_object(
+            &tx,
+            &device_id,
+            &session_id,
+            &root,
+            &report,
+            "md",
+            &report.meta_data(),
+            None,
+        )?);
+        assert!(insert_object(
+            &tx,
+            &device_id,
+            &session_id,
+            &root,
+            &report,
+            "md",
+            &report.meta_data(),
+            Some(b"# Q1 ok"[..].into()),
+        )?);
+        assert!(!insert_object(
+            &tx,
+            &device_id,
+            &session_id,
+            &root,
+            &image,
+            "png",
+            &image.meta_data(),
+            None,
+        )?);
+        // cataloging the same object again finds the same resource
+        assert!(!insert_object(
+            &tx,
+            &device_id,
+            &session_id,
+            &root,
+            &image,
+            "png",
+            &image.meta_data(),
+            None,
+        )?);
+        insert_object_error(
+            &tx,
+            &session_id,
+            &root,
+            &S3Object {
+                key: "scans/denied.json".to_string(),
+                ..Default::default()
+            },
+            &anyhow::anyhow!("403 Forbidden"),
+        )?;
+
+        let resources: usize = tx.query_row(
+            "SELECT COUNT(*) FROM uniform_resource WHERE ingest_fs_path_id = ?",
+            [&root.ingest_fs_path_id],
+            |row| row.get(0),
+        )?;
+        assert_eq!(resources, 2);
+        let (content, digest): (String, String) = tx.query_row(
+            "SELECT content, content_digest FROM uniform_resource WHERE uri = 's3://evidence/scans/2024/q1.md'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        assert_eq!(
+            (content.as_str(), digest),
+            ("# Q1 ok", sha1_hex(b"# Q1 ok"))
+        );
+
+        let entries: Vec<(String, String, String, String, Option<String>)> = tx
+            .prepare(
+                "SELECT file_path_abs, file_path_rel_parent, file_path_rel, file_extn, ur_status
+                   FROM ur_ingest_session_fs_path_entry
+                  WHERE ingest_fs_path_id = ?
+               ORDER BY file_path_abs, created_at",
+            )?
+            .query_map([&root.ingest_fs_path_id], |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        assert_eq!(entries.len(), 5);
+        assert_eq!(
+            entries[0],
+            (
+                "s3://evidence/scans/2024/q1.md".to_string(),
+                "s3://evidence/scans/".to_string(),
+                "2024/q1.md".to_string(),
+                "md".to_string(),
+                None
+            )
+        );
+        assert_eq!(entries[4].0, "s3://evidence/scans/denied.json");
+        assert_eq!(entries[4].4.as_deref(), Some("ERROR"));
+        Ok(())
+    }
+}
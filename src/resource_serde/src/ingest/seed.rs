@@ -0,0 +1,469 @@
+use std::collections::HashSet;
+
+use anyhow::{Context, Result};
+use chrono::{Duration, Utc};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use serde_json::json;
+use sha1::{Digest, Sha1};
+
+use super::{
+    INS_UR_INGEST_SESSION_IMAP_ACCT, INS_UR_INGEST_SESSION_IMAP_ACCT_FOLDER,
+    INS_UR_INGEST_SESSION_IMAP_ACCT_FOLDER_MESSAGE, INS_UR_ISFSP_ENTRY_SQL, INS_UR_ISFSP_SQL,
+    INS_UR_IS_TASK_SQL, INS_UR_SQL,
+};
+use crate::persist::upsert_device;
+
+// seeded sessions are spread over the past days instead of all starting "now"
+// (which would also break the unique device_id and created_at constraint)
+const SEED_INGEST_SESSION_SQL: &str = "
+    INSERT INTO ur_ingest_session (ur_ingest_session_id, device_id, behavior_json, ingest_started_at, ingest_finished_at, elaboration, created_at)
+                           VALUES (surveilr_pk(), ?1, ?2, ?3, ?4, ?5, ?3) RETURNING ur_ingest_session_id";
+
+const SEED_ROOT_PATH: &str = "/seed";
+const SEED_MAILBOX: &str = "compliance@example.com";
+
+/// How much synthetic evidence `admin seed` generates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, clap::ValueEnum)]
+pub enum SeedProfile {
+    /// enough devices, sessions and history to make dashboards look alive
+    Demo,
+    /// a small, predictable data set for queries and integration tests
+    #[default]
+    Test,
+}
+
+impl SeedProfile {
+    /// (devices, sessions per device, files per session, emails)
+    fn size(&self) -> (usize, usize, usize, usize) {
+        match self {
+            SeedProfile::Demo => (6, 4, 12, 20),
+            SeedProfile::Test => (2, 1, 4, 3),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            SeedProfile::Demo => "demo",
+            SeedProfile::Test => "test",
+        }
+    }
+}
+
+/// The rows `admin seed` created.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct SeedStats {
+    pub devices: usize,
+    pub ingest_sessions: usize,
+    pub uniform_resources: usize,
+    pub fs_path_entries: usize,
+    pub imap_messages: usize,
+    pub ce_results: usize,
+    pub ce_errors: usize,
+}
+
+const DEVICE_ROLES: [(&str, &str); 4] = [
+    ("web", "Ubuntu 22.04"),
+    ("db", "Red Hat Enterprise Linux 9"),
+    ("build", "Debian 12"),
+    ("laptop", "macOS 14"),
+];
+
+const FILE_CATEGORIES: [(&str, &str, &str); 5] = [
+    ("policies", "access-control", "md"),
+    ("configs", "sshd", "json"),
+    ("reports", "vulnerability-scan", "html"),
+    ("logs", "auth", "txt"),
+    ("reports", "patch-status", "csv"),
+];
+
+fn sha1_hex(content: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(content.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+fn timestamp(at: chrono::DateTime<Utc>) -> String {
+    at.format("%Y-%m-%d %H:%M:%S%.3f").to_string()
+}
+
+/// The content and frontmatter of a synthetic file; policies don't change
+/// between sessions so re-ingesting them reuses the same `uniform_resource`.
+fn seed_file(device: &str, session: usize, file: usize, nature: &str) -> (String, Option<String>) {
+    match nature {
+        "md" => {
+            let frontmatter = json!({
+                "title": format!("Policy {}", file + 1),
+                "owner": "security@example.com",
+                "reviewed": "2024-01-15",
+            });
+            (
+                format!(
+                    "---\ntitle: Policy {}\nowner: security@example.com\nreviewed: 2024-01-15\n---\n# Policy {}\n\nAccess to {} is granted on least privilege and reviewed quarterly.\n",
+                    file + 1,
+                    file + 1,
+                    device
+                ),
+                Some(frontmatter.to_string()),
+            )
+        }
+        "json" => (
+            json!({
+                "host": device,
+                "PermitRootLogin": "no",
+                "PasswordAuthentication": session % 2 == 1,
+                "MaxAuthTries": 3 + file % 3,
+            })
+            .to_string(),
+            None,
+        ),
+        "html" => (
+            format!(
+                "<html><head><title>{device} scan {session}</title></head><body><h1>Vulnerability scan</h1><p>{} critical, {} high findings</p></body></html>",
+                (file + session) % 2,
+                (file * 3 + session) % 7
+            ),
+            None,
+        ),
+        "csv" => (
+            format!(
+                "package,installed,available\nopenssl,3.0.{},3.0.{}\nsudo,1.9.{},1.9.{}\n",
+                session,
+                session + file % 2,
+                10 + session,
+                11 + session
+            ),
+            None,
+        ),
+        _ => (
+            (0..3)
+                .map(|line| {
+                    format!(
+                        "{device} sshd[{}]: Accepted publickey for ops from 10.0.{}.{} port 22\n",
+                        1000 + session * 10 + line,
+                        file,
+                        line + 1
+                    )
+                })
+                .collect(),
+            None,
+        ),
+    }
+}
+
+/// Populate an RSSD with synthetic devices, ingest sessions, files, emails and
+/// capturable executable (CE) results so dashboards, queries and integrations
+/// can be developed without production evidence. Everything it creates lives
+/// under the `/seed` root path and its sessions' `behavior_json` name the profile.
+pub fn seed_rssd(conn: &Connection, profile: SeedProfile) -> Result<SeedStats> {
+    let (devices, sessions_per_device, files_per_session, emails) = profile.size();
+    let now = Utc::now();
+    let mut stats = SeedStats::default();
+    let mut uniform_resource_ids = HashSet::new();
+
+    let mut ins_session_stmt = conn.prepare(SEED_INGEST_SESSION_SQL)?;
+    let mut ins_fs_path_stmt = conn.prepare(INS_UR_ISFSP_SQL)?;
+    let mut ins_ur_stmt = conn.prepare(INS_UR_SQL)?;
+    let mut ins_entry_stmt = conn.prepare(INS_UR_ISFSP_ENTRY_SQL)?;
+    let mut ins_task_stmt = conn.prepare(INS_UR_IS_TASK_SQL)?;
+    let behavior_json = json!({ "seed": profile.name() }).to_string();
+
+    for device_index in 0..devices {
+        let (role, os) = DEVICE_ROLES[device_index % DEVICE_ROLES.len()];
+        let device_name = format!("{}-{:02}", role, device_index + 1);
+        let (device_id, _) = upsert_device(
+            conn,
+            &device_name,
+            &format!("seed-{}", profile.name()),
+            &json!({ "os": os, "role": role, "seed": profile.name() }).to_string(),
+            &json!({ "os_name": os, "host_name": format!("{device_name}.example.com") })
+                .to_string(),
+        )
+        .with_context(|| format!("[seed_rssd] device {}", device_name))?;
+        stats.devices += 1;
+
+        for session_index in 0..sessions_per_device {
+            let started_at = now - Duration::days((sessions_per_device - session_index) as i64)
+                + Duration::hours(device_index as i64);
+            let session_id: String = ins_session_stmt
+                .query_row(
+                    params![
+                        device_id,
+                        behavior_json,
+                        timestamp(started_at),
+                        timestamp(started_at + Duration::seconds(42 + session_index as i64)),
+                        json!({ "seed": profile.name(), "session": session_index }).to_string()
+                    ],
+                    |row| row.get(0),
+                )
+                .with_context(|| format!("[seed_rssd] ingest session for {}", device_name))?;
+            stats.ingest_sessions += 1;
+
+            let root_path = format!("{SEED_ROOT_PATH}/{device_name}/evidence");
+            let fs_path_id: String = ins_fs_path_stmt
+                .query_row(params![session_id, root_path, None::<String>], |row| {
+                    row.get(0)
+                })?;
+            for file_index in 0..files_per_session {
+                let (category, stem, nature) = FILE_CATEGORIES[file_index % FILE_CATEGORIES.len()];
+                let file_path_rel = format!("{category}/{stem}-{:02}.{nature}", file_index + 1);
+                let uri = format!("{root_path}/{file_path_rel}");
+                let (content, frontmatter) =
+                    seed_file(&device_name, session_index, file_index, nature);
+                let last_modified_at = if nature == "md" {
+                    "2024-01-15 09:30:00".to_string()
+                } else {
+                    timestamp(started_at - Duration::minutes(5))
+                };
+                let ur_id: String = ins_ur_stmt
+                    .query_row(
+                        params![
+                            device_id,
+                            session_id,
+                            fs_path_id,
+                            uri,
+                            nature,
+                            content,
+                            sha1_hex(&content),
+                            content.len(),
+                            last_modified_at,
+                            None::<String>,
+                            frontmatter,
+                            None::<String>,
+                        ],
+                        |row| row.get(0),
+                    )
+                    .with_context(|| format!("[seed_rssd] uniform_resource {}", uri))?;
+                let file_basename = file_path_rel.rsplit('/').next().unwrap_or_default();
+                ins_entry_stmt.query_row(
+                    params![
+                        session_id,
+                        fs_path_id,
+                        ur_id,
+                        uri,
+                        format!("{root_path}/{category}"),
+                        file_path_rel,
+                        file_basename,
+                        nature,
+                        None::<String>,
+                        None::<String>,
+                        None::<String>,
+                        None::<String>,
+                    ],
+                    |row| row.get::<_, String>(0),
+                )?;
+                uniform_resource_ids.insert(ur_id);
+                stats.fs_path_entries += 1;
+            }
+
+            // every session runs the same CEs, every third one has a failing check
+            let system_info = json!([{
+                "hostname": format!("{device_name}.example.com"),
+                "cpu_brand": "Intel(R) Xeon(R) Platinum 8375C",
+                "physical_memory": 17179869184u64 * (1 + device_index as u64 % 2),
+            }])
+            .to_string();
+            let uptime = format!(
+                "up {} days, {} users, load average: 0.{}",
+                session_index + device_index + 1,
+                device_index % 3 + 1,
+                10 + session_index
+            );
+            for (task, nature, output) in [
+                (
+                    "osqueryi --json \"SELECT * FROM system_info\"",
+                    "json",
+                    system_info,
+                ),
+                ("uptime", "text", uptime),
+            ] {
+                let captured_executable = json!({ "src": task, "nature": nature }).to_string();
+                let ur_id: String = ins_ur_stmt.query_row(
+                    params![
+                        device_id,
+                        session_id,
+                        None::<String>,
+                        task,
+                        nature,
+                        output,
+                        sha1_hex(&output),
+                        output.len(),
+                        timestamp(started_at),
+                        None::<String>,
+                        None::<String>,
+                        None::<String>,
+                    ],
+                    |row| row.get(0),
+                )?;
+                ins_task_stmt.query_row(
+                    params![
+                        session_id,
+                        ur_id,
+                        captured_executable,
+                        None::<String>,
+                        None::<String>
+                    ],
+                    |row| row.get::<_, String>(0),
+                )?;
+                uniform_resource_ids.insert(ur_id);
+                stats.ce_results += 1;
+            }
+            if (device_index + session_index) % 3 == 0 {
+                let diagnostics = json!({
+                    "src": "check-disk-encryption.sh",
+                    "issue": "[CapturableExecutable::TextFromExecutableUri.executed_text] invalid exit status",
+                    "remediation": "ensure that executable is called with proper arguments and input formats",
+                    "exit-status": "Exited(1)",
+                    "stderr": "fdesetup: FileVault is Off",
+                })
+                .to_string();
+                ins_task_stmt.query_row(
+                    params![
+                        session_id,
+                        None::<String>,
+                        json!({ "src": "check-disk-encryption.sh", "nature": "json" }).to_string(),
+                        "ERROR",
+                        diagnostics
+                    ],
+                    |row| row.get::<_, String>(0),
+                )?;
+                stats.ce_results += 1;
+                stats.ce_errors += 1;
+            }
+        }
+    }
+
+    if emails > 0 {
+        let (device_id, _) = upsert_device(
+            conn,
+            "mail-gateway",
+            &format!("seed-{}", profile.name()),
+            &json!({ "role": "mail", "seed": profile.name() }).to_string(),
+            &json!({ "host_name": "mail.example.com" }).to_string(),
+        )?;
+        stats.devices += 1;
+        let session_id: String = ins_session_stmt.query_row(
+            params![
+                device_id,
+                behavior_json,
+                timestamp(now - Duration::hours(1)),
+                timestamp(now - Duration::hours(1) + Duration::seconds(12)),
+                json!({ "seed": profile.name() }).to_string()
+            ],
+            |row| row.get(0),
+        )?;
+        stats.ingest_sessions += 1;
+        let account_id: String = conn.prepare(INS_UR_INGEST_SESSION_IMAP_ACCT)?.query_row(
+            params![session_id, SEED_MAILBOX, None::<String>, "imap.example.com"],
+            |row| row.get(0),
+        )?;
+        let folder_id: String = conn
+            .prepare(INS_UR_INGEST_SESSION_IMAP_ACCT_FOLDER)?
+            .query_row(
+                params![
+                    session_id,
+                    account_id,
+                    "INBOX",
+                    json!({ "seed": profile.name() }).to_string()
+                ],
+                |row| row.get(0),
+            )?;
+        let mut ins_message_stmt = conn.prepare(INS_UR_INGEST_SESSION_IMAP_ACCT_FOLDER_MESSAGE)?;
+        let subjects = [
+            "Quarterly access review",
+            "Vulnerability scan results",
+            "Incident postmortem",
+            "Vendor security questionnaire",
+        ];
+        for email_index in 0..emails {
+            let subject = format!(
+                "{} #{}",
+                subjects[email_index % subjects.len()],
+                email_index + 1
+            );
+            let message_id = format!("<seed-{}-{}@example.com>", profile.name(), email_index + 1);
+            let from = format!("auditor{}@example.com", email_index % 3 + 1);
+            let text = format!(
+                "From: {from}\r\nTo: {SEED_MAILBOX}\r\nSubject: {subject}\r\nMessage-ID: {message_id}\r\n\r\nPlease find the evidence for {subject} attached.\r\n"
+            );
+            let ur_id: String = ins_ur_stmt.query_row(
+                params![
+                    device_id,
+                    session_id,
+                    None::<String>,
+                    format!("smtp://{}/{}", SEED_MAILBOX, message_id),
+                    "text",
+                    text,
+                    sha1_hex(&text),
+                    text.len(),
+                    timestamp(now - Duration::hours(email_index as i64 * 7)),
+                    None::<String>,
+                    None::<String>,
+                    folder_id,
+                ],
+                |row| row.get(0),
+            )?;
+            ins_message_stmt.query_row(
+                params![
+                    session_id,
+                    folder_id,
+                    ur_id,
+                    text,
+                    message_id,
+                    subject,
+                    from,
+                    "[]",
+                    "[]",
+                    "[]",
+                    // the seeded messages have no Date header
+                    None::<String>
+                ],
+                |row| row.get::<_, String>(0),
+            )?;
+            uniform_resource_ids.insert(ur_id);
+            stats.imap_messages += 1;
+        }
+    }
+
+    stats.uniform_resources = uniform_resource_ids.len();
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persist::DbConn;
+
+    #[test]
+    fn seed_test_profile() -> Result<()> {
+        let mut dbc = DbConn::new(":memory:", 0)?;
+        let tx = dbc.init(None)?;
+        let stats = seed_rssd(&tx, SeedProfile::Test)?;
+        assert_eq!(
+            stats,
+            SeedStats {
+                devices: 3,
+                ingest_sessions: 3,
+                uniform_resources: 15,
+                fs_path_entries: 8,
+                imap_messages: 3,
+                ce_results: 5,
+                ce_errors: 1,
+            }
+        );
+
+        let unfinished: usize = tx.query_row(
+            "SELECT COUNT(*) FROM ur_ingest_session WHERE ingest_finished_at IS NULL",
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(unfinished, 0);
+
+        // seeding again reuses the devices and unchanged resources
+        let again = seed_rssd(&tx, SeedProfile::Test)?;
+        assert_eq!(again.ingest_sessions, 3);
+        let devices: usize = tx.query_row("SELECT COUNT(*) FROM device", [], |row| row.get(0))?;
+        assert_eq!(devices, 3);
+        Ok(())
+    }
+}
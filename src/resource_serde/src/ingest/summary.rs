@@ -0,0 +1,153 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+
+// walked files and executed tasks which couldn't be stored or failed
+const SESSION_ERRORS_SQL: &str = "
+    SELECT file_path_abs, ur_diagnostics
+      FROM ur_ingest_session_fs_path_entry
+     WHERE ingest_session_id = ?1 AND ur_status = 'ERROR'
+    UNION ALL
+    SELECT ur_ingest_session_task_id, ur_diagnostics
+      FROM ur_ingest_session_task
+     WHERE ingest_session_id = ?1 AND ur_status = 'ERROR'";
+
+/// A resource which failed during an ingest session.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IngestSessionError {
+    /// the file path or the `ur_ingest_session_task_id`
+    pub source: String,
+    pub diagnostics: Option<JsonValue>,
+}
+
+/// The machine-readable result of an ingest session, emitted with
+/// `--emit-session-json` so orchestration doesn't have to parse logs.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IngestSessionSummary {
+    pub ingest_session_id: String,
+    pub state_db_fs_path: String,
+    pub ingest_started_at: Option<String>,
+    pub ingest_finished_at: Option<String>,
+    pub duration_ms: u128,
+    /// `uniform_resource` rows created (or changed) in the session
+    pub uniform_resources: usize,
+    pub fs_path_entries: usize,
+    pub tasks: usize,
+    pub imap_messages: usize,
+    pub errors: Vec<IngestSessionError>,
+    /// the limit which stopped the session early (see `IngestLimitsArgs`)
+    pub aborted: Option<JsonValue>,
+}
+
+impl IngestSessionSummary {
+    /// Read the summary of `ingest_session_id` once it has been committed.
+    pub fn from_conn(
+        conn: &Connection,
+        ingest_session_id: &str,
+        state_db_fs_path: &str,
+        duration: Duration,
+    ) -> Result<IngestSessionSummary> {
+        let count = |table: &str| -> Result<usize> {
+            conn.query_row(
+                &format!("SELECT COUNT(*) FROM {table} WHERE ingest_session_id = ?"),
+                [ingest_session_id],
+                |row| row.get(0),
+            )
+            .with_context(|| format!("[IngestSessionSummary::from_conn] counting {}", table))
+        };
+
+        // a session which was rolled back (e.g. --ce-sql-validate-only) has no row
+        let (ingest_started_at, ingest_finished_at, aborted) = conn
+            .query_row(
+                "SELECT ingest_started_at, ingest_finished_at, json_extract(elaboration, '$.aborted') FROM ur_ingest_session WHERE ur_ingest_session_id = ?",
+                [ingest_session_id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get::<_, Option<String>>(2)?)),
+            )
+            .unwrap_or((None, None, None));
+
+        let errors = session_errors(conn, ingest_session_id)?;
+
+        Ok(IngestSessionSummary {
+            ingest_session_id: ingest_session_id.to_string(),
+            state_db_fs_path: state_db_fs_path.to_string(),
+            ingest_started_at,
+            ingest_finished_at,
+            duration_ms: duration.as_millis(),
+            uniform_resources: count("uniform_resource")?,
+            fs_path_entries: count("ur_ingest_session_fs_path_entry")?,
+            tasks: count("ur_ingest_session_task")?,
+            imap_messages: count("ur_ingest_session_imap_acct_folder_message")?,
+            errors,
+            aborted: aborted.and_then(|aborted| serde_json::from_str(&aborted).ok()),
+        })
+    }
+}
+
+/// The walked files and tasks of `ingest_session_id` which failed.
+pub fn session_errors(
+    conn: &Connection,
+    ingest_session_id: &str,
+) -> Result<Vec<IngestSessionError>> {
+    let mut stmt = conn.prepare(SESSION_ERRORS_SQL)?;
+    let errors = stmt
+        .query_map(params![ingest_session_id], |row| {
+            let diagnostics: Option<String> = row.get(1)?;
+            Ok(IngestSessionError {
+                source: row.get(0)?,
+                diagnostics: diagnostics.and_then(|d| serde_json::from_str(&d).ok()),
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .with_context(|| format!("[session_errors] errors of session {}", ingest_session_id))?;
+    Ok(errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persist::{upserted_device, DbConn};
+
+    #[test]
+    fn summarize_session() -> Result<()> {
+        let mut dbc = DbConn::new(":memory:", 0)?;
+        let tx = dbc.init(None)?;
+        let (device_id, _) = upserted_device(&tx, &common::DEVICE)?;
+        tx.execute(
+            "INSERT INTO ur_ingest_session (ur_ingest_session_id, device_id, behavior_json, ingest_started_at, ingest_finished_at, elaboration)
+             VALUES ('session', ?, '{}', CURRENT_TIMESTAMP, CURRENT_TIMESTAMP, '{\"aborted\": {\"limit\": \"max-resources\"}}')",
+            [&device_id],
+        )?;
+        tx.execute(
+            "INSERT INTO uniform_resource (uniform_resource_id, device_id, ingest_session_id, uri, nature, content, content_digest, size_bytes)
+             VALUES ('ur', ?, 'session', '/tmp/a.md', 'md', '# a', 'digest', 3)",
+            [&device_id],
+        )?;
+        tx.execute(
+            "INSERT INTO ur_ingest_session_task (ur_ingest_session_task_id, ingest_session_id, captured_executable, ur_status, ur_diagnostics)
+             VALUES ('task', 'session', '{}', 'ERROR', '{\"message\": \"exit 1\"}')",
+            [],
+        )?;
+
+        let summary =
+            IngestSessionSummary::from_conn(&tx, "session", "test.db", Duration::from_millis(5))?;
+        assert_eq!(summary.uniform_resources, 1);
+        assert_eq!(summary.tasks, 1);
+        assert_eq!(summary.fs_path_entries, 0);
+        assert!(summary.ingest_finished_at.is_some());
+        assert_eq!(
+            summary.aborted,
+            Some(serde_json::json!({ "limit": "max-resources" }))
+        );
+        assert_eq!(
+            summary.errors,
+            vec![IngestSessionError {
+                source: "task".to_string(),
+                diagnostics: Some(serde_json::json!({ "message": "exit 1" })),
+            }]
+        );
+        Ok(())
+    }
+}
@@ -1,12 +1,13 @@
 use std::collections::HashMap;
 
 use super::{
-    insert_uniform_resource, IngestContext, IngestTasksBehavior, UniformResourceWriterAction,
+    insert_lineage, insert_uniform_resource, validate_captured_sql, CeWorkdirs, IngestContext,
+    IngestTasksBehavior, SessionAbort, SessionGuard, UniformResourceWriterAction,
     UniformResourceWriterEntry, UniformResourceWriterState, INS_UR_INGEST_SESSION_FINISH_SQL,
     INS_UR_INGEST_SESSION_SQL, INS_UR_IS_TASK_SQL,
 };
 use crate::cmd::IngestTasksArgs;
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use rusqlite::params;
 use serde_json::json;
 use tracing::debug;
@@ -35,11 +36,15 @@ pub fn ingest_tasks(debug: u8, ingest_args: &IngestTasksArgs) -> Result<String>
         )
     })?;
 
-    let mut behavior = IngestTasksBehavior::from_stdin();
+    let mut behavior = match &ingest_args.tasks_from {
+        Some(path) => IngestTasksBehavior::from_file(path)?,
+        None => IngestTasksBehavior::from_stdin(),
+    };
     let classifier = EncounterableResourcePathClassifier::default_from_conn(&tx)?;
     let (encounterable, resources) =
         ResourcesCollection::from_tasks_lines(&behavior.lines, &classifier, &None::<HashMap<_, _>>);
     behavior.encounterable = encounterable;
+    behavior.ce_json_filter = ingest_args.ce_json_filter.clone();
 
     let ingest_session_id: String = tx
         .query_row(
@@ -63,6 +68,10 @@ pub fn ingest_tasks(debug: u8, ingest_args: &IngestTasksArgs) -> Result<String>
         })?;
 
     debug!("Walk Session: {ingest_session_id}");
+    let mut validation_errors: Vec<String> = Vec::new();
+    let mut guard = SessionGuard::new(&ingest_args.limits);
+    let ce_workdirs = CeWorkdirs::new(&ingest_session_id, ingest_args.keep_ce_workdirs);
+    let mut aborted: Option<SessionAbort> = None;
 
     {
         let env_current_dir = std::env::current_dir()
@@ -82,14 +91,26 @@ pub fn ingest_tasks(debug: u8, ingest_args: &IngestTasksArgs) -> Result<String>
             ingest_fs_path_id: None,
             resources: &resources,
             ingest_stmts: &mut ingest_stmts,
+            ce_json_filter: behavior.ce_json_filter.as_deref(),
+            ce_workdirs: &ce_workdirs,
+            decode_payloads: ingest_args.decode_payloads,
+            canonical_json: ingest_args.canonical_json,
+            content_chunk_size: None,
+            blob_store: None,
+            blob_threshold: 0,
         };
 
         for resource_result in resources.uniform_resources() {
+            if let Some(abort) = guard.exceeded() {
+                aborted = Some(abort);
+                break;
+            }
             match resource_result {
                 Ok(resource) => {
                     let mut urw_entry = UniformResourceWriterEntry {
                         path: Some(resource.uri()),
                         tried_alternate_nature: None,
+                        executed: None,
                     };
 
                     debug!("{:?}", urw_entry.path);
@@ -99,15 +120,18 @@ pub fn ingest_tasks(debug: u8, ingest_args: &IngestTasksArgs) -> Result<String>
                     let mut ur_status = inserted.action.ur_status();
                     let mut ur_diagnostics = inserted.action.ur_diagnostics();
                     let captured_executable: Option<String>;
+                    let mut captured_exec_script_ur_id: Option<&String> = None;
 
                     let uniform_resource_id = match &inserted.action {
                         UniformResourceWriterAction::InsertedExecutableOutput(
                             ref uniform_resource_id,
                             _,
                             diags,
+                            script_ur_id,
                         ) => {
                             captured_executable =
                                 Some(serde_json::to_string_pretty(&diags).unwrap());
+                            captured_exec_script_ur_id = script_ur_id.as_ref();
                             Some(uniform_resource_id)
                         }
                         UniformResourceWriterAction::CapturedExecutableSqlOutput(
@@ -116,9 +140,19 @@ pub fn ingest_tasks(debug: u8, ingest_args: &IngestTasksArgs) -> Result<String>
                         ) => {
                             captured_executable =
                                 Some(serde_json::to_string_pretty(&diags).unwrap());
-                            match tx.execute_batch(sql_script) {
+                            let executed = if ingest_args.ce_sql_validate_only {
+                                validate_captured_sql(&tx, sql_script)
+                            } else {
+                                tx.execute_batch(sql_script)
+                            };
+                            match executed {
                                 Ok(_) => {
-                                    ur_status = Some(String::from("EXECUTED_CAPTURED_SQL"));
+                                    ur_status =
+                                        Some(String::from(if ingest_args.ce_sql_validate_only {
+                                            "VALIDATED_CAPTURED_SQL"
+                                        } else {
+                                            "EXECUTED_CAPTURED_SQL"
+                                        }));
                                     ur_diagnostics = Some(serde_json::to_string_pretty(&json!({
                                             "instance": "UniformResourceWriterAction::CapturedExecutableSqlOutput(err)",
                                             "SQL": sql_script
@@ -126,6 +160,10 @@ pub fn ingest_tasks(debug: u8, ingest_args: &IngestTasksArgs) -> Result<String>
                                     None
                                 }
                                 Err(err) => {
+                                    if ingest_args.ce_sql_validate_only {
+                                        error!("[ingest_tasks] captured SQL from {} failed validation: {}", &inserted.uri, err);
+                                        validation_errors.push(format!("{}: {}", inserted.uri, err));
+                                    }
                                     ur_status = Some(String::from("ERROR"));
                                     ur_diagnostics = Some(serde_json::to_string_pretty(&json!({
                                             "instance": "UniformResourceWriterAction::CapturedExecutableSqlOutput(err)",
@@ -147,14 +185,31 @@ pub fn ingest_tasks(debug: u8, ingest_args: &IngestTasksArgs) -> Result<String>
                         }
                     };
 
-                    match urw_state.ingest_stmts.ins_ur_is_task_stmt.execute(params![
-                        ingest_session_id,
-                        uniform_resource_id,
-                        captured_executable,
-                        ur_status,
-                        ur_diagnostics,
-                    ]) {
-                        Ok(_) => {}
+                    guard.record(ur_status.as_deref());
+                    match urw_state.ingest_stmts.ins_ur_is_task_stmt.query_row(
+                        params![
+                            ingest_session_id,
+                            uniform_resource_id,
+                            captured_executable,
+                            ur_status,
+                            ur_diagnostics,
+                        ],
+                        |row| row.get::<_, String>(0),
+                    ) {
+                        Ok(ingest_task_id) => {
+                            if let Some(output_ur_id) = uniform_resource_id {
+                                if let Err(err) = insert_lineage(
+                                    urw_state.ingest_stmts,
+                                    &ingest_session_id,
+                                    captured_exec_script_ur_id,
+                                    output_ur_id,
+                                    None,
+                                    Some(&ingest_task_id),
+                                ) {
+                                    error!("[ingest_tasks] unable to insert lineage for {} in {}: {}", &inserted.uri, db_fs_path, err)
+                                }
+                            }
+                        }
                         Err(err) => {
                             error!( "[ingest_tasks] unable to insert UR task entry for {} in {}: {} ({})",
                             &inserted.uri, db_fs_path, err, INS_UR_IS_TASK_SQL
@@ -164,12 +219,23 @@ pub fn ingest_tasks(debug: u8, ingest_args: &IngestTasksArgs) -> Result<String>
                 }
                 Err(e) => {
                     error!("Error processing a ingest_tasks resource: {}", e);
+                    guard.record(Some("ERROR"));
                 }
             }
         }
     }
 
-    match tx.execute(INS_UR_INGEST_SESSION_FINISH_SQL, params![ingest_session_id]) {
+    let session_elaboration = aborted.map(|abort| {
+        error!(
+            "[ingest_tasks] session {} in {} aborted after {} tasks ({} errors), --{} reached",
+            ingest_session_id, db_fs_path, abort.resources, abort.errors, abort.limit
+        );
+        json!({ "aborted": abort }).to_string()
+    });
+    match tx.execute(
+        INS_UR_INGEST_SESSION_FINISH_SQL,
+        params![ingest_session_id, session_elaboration],
+    ) {
         Ok(_) => {}
         Err(err) => {
             error!(
@@ -179,6 +245,24 @@ pub fn ingest_tasks(debug: u8, ingest_args: &IngestTasksArgs) -> Result<String>
         }
     }
 
+    if ingest_args.ce_sql_validate_only {
+        // validation mode must never mutate the RSSD so discard the whole session
+        tx.rollback().with_context(|| {
+            format!(
+                "[ingest_tasks] unable to roll back validation session in {}",
+                db_fs_path
+            )
+        })?;
+        if !validation_errors.is_empty() {
+            return Err(anyhow!(
+                "[ingest_tasks] captured SQL failed validation in {} executable(s):\n{}",
+                validation_errors.len(),
+                validation_errors.join("\n")
+            ));
+        }
+        return Ok(ingest_session_id);
+    }
+
     // putting everything inside a transaction improves performance significantly
     tx.commit().with_context(|| {
         format!(
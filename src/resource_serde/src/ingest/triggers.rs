@@ -0,0 +1,299 @@
+use anyhow::{anyhow, Context, Result};
+use resource::shell::{DenoTaskShellExecutive, ShellExecutive, ShellStdIn};
+use rusqlite::{params, types::ValueRef, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha1::{Digest, Sha1};
+use tracing::{debug, error};
+
+use super::INS_UR_TRANSFORM_SQL;
+#[cfg(feature = "transform")]
+use crate::transformers::BackfillTransform;
+
+// resources ingested in the session, including unchanged ones which kept their original session
+const SESSION_RESOURCES_BY_NATURE_SQL: &str = "
+    SELECT ur.uniform_resource_id, ur.uri, ur.content
+      FROM uniform_resource ur
+     WHERE ur.nature = ?2
+       AND (ur.ingest_session_id = ?1
+            OR ur.uniform_resource_id IN (SELECT uniform_resource_id FROM ur_ingest_session_fs_path_entry
+                                           WHERE ingest_session_id = ?1))
+  ORDER BY ur.rowid";
+
+/// What to run on a resource when a [`NatureTrigger`] fires.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "kebab-case")]
+pub enum TriggerAction {
+    /// a registered transform (the ones available to `transform backfill`)
+    Transform { format: String },
+    /// a Deno Task Shell command which receives the resource's text content on
+    /// STDIN and its URI in `SURVEILR_TRIGGER_URI`; STDOUT becomes the transform
+    Exec { command: String },
+}
+
+/// "When a resource of `nature` is ingested, run `action` on it", declared in
+/// the ingest behavior and executed at the end of the same session. The output
+/// is stored in `uniform_resource_transform`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NatureTrigger {
+    pub nature: String,
+    #[serde(flatten)]
+    pub action: TriggerAction,
+}
+
+impl std::str::FromStr for NatureTrigger {
+    type Err = anyhow::Error;
+
+    /// `<nature>=transform:<format>` or `<nature>=exec:<command>`
+    fn from_str(s: &str) -> Result<Self> {
+        let (nature, action) = s.split_once('=').ok_or_else(|| {
+            anyhow!(
+                "trigger '{}' should be <nature>=transform:<format> or <nature>=exec:<command>",
+                s
+            )
+        })?;
+        let action = match action.split_once(':') {
+            Some(("transform", format)) => {
+                registered_transform(nature, format, None)?;
+                TriggerAction::Transform {
+                    format: format.to_string(),
+                }
+            }
+            Some(("exec", command)) if !command.trim().is_empty() => TriggerAction::Exec {
+                command: command.to_string(),
+            },
+            _ => {
+                return Err(anyhow!(
+                    "trigger action '{}' should be transform:<format> or exec:<command>",
+                    action
+                ))
+            }
+        };
+        Ok(NatureTrigger {
+            nature: nature.to_string(),
+            action,
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct TriggerStats {
+    pub fired: usize,
+    pub failed: usize,
+}
+
+impl NatureTrigger {
+    /// Produce the transform's URI, nature, content and elaboration for one resource.
+    fn apply(&self, uri: &str, content: &[u8]) -> Result<(String, String, String, Option<String>)> {
+        match &self.action {
+            TriggerAction::Transform { format } => {
+                let text = std::str::from_utf8(content)
+                    .with_context(|| format!("[NatureTrigger::apply] {} is not text", uri))?;
+                let (transformed_uri, content) =
+                    registered_transform(&self.nature, format, Some((uri, text)))?;
+                Ok((transformed_uri, format.clone(), content, None))
+            }
+            TriggerAction::Exec { command } => {
+                let mut executive = DenoTaskShellExecutive::new(command.clone(), None);
+                executive
+                    .env_vars
+                    .insert("SURVEILR_TRIGGER_URI".to_string(), uri.to_string());
+                executive
+                    .env_vars
+                    .insert("SURVEILR_TRIGGER_NATURE".to_string(), self.nature.clone());
+                let stdin = match std::str::from_utf8(content) {
+                    Ok(text) => ShellStdIn::Text(text.to_string()),
+                    Err(_) => ShellStdIn::None,
+                };
+                let result = executive.execute(stdin)?;
+                if !result.success() {
+                    return Err(anyhow!(
+                        "`{}` exited with {:?}: {}",
+                        command,
+                        result.status,
+                        result.stderr
+                    ));
+                }
+                let nature = match serde_json::from_str::<serde_json::Value>(&result.stdout) {
+                    Ok(value) if value.is_object() || value.is_array() => "json",
+                    _ => "text",
+                };
+                let elaboration = json!({ "trigger": self, "stderr": result.stderr }).to_string();
+                Ok((
+                    format!("{uri}/exec"),
+                    nature.to_string(),
+                    result.stdout,
+                    Some(elaboration),
+                ))
+            }
+        }
+    }
+}
+
+/// Look up the transform registered for `nature` and `format` and, given a
+/// resource's URI and text, apply it.
+#[cfg(feature = "transform")]
+fn registered_transform(
+    nature: &str,
+    format: &str,
+    resource: Option<(&str, &str)>,
+) -> Result<(String, String)> {
+    let transform = BackfillTransform::registered(nature, format)?;
+    match resource {
+        Some((uri, text)) => transform
+            .apply(uri, text)
+            .map(|(transformed_uri, content, _)| (transformed_uri, content)),
+        None => Ok(Default::default()),
+    }
+}
+
+#[cfg(not(feature = "transform"))]
+fn registered_transform(
+    _nature: &str,
+    _format: &str,
+    _resource: Option<(&str, &str)>,
+) -> Result<(String, String)> {
+    Err(crate::cmd::feature_not_compiled("transform"))
+}
+
+/// Fire `triggers` on the resources of their nature ingested in `ingest_session_id`.
+/// A trigger which fails on a resource is logged and counted but doesn't fail the
+/// session.
+pub fn run_nature_triggers(
+    conn: &Connection,
+    ingest_session_id: &str,
+    triggers: &[NatureTrigger],
+) -> Result<TriggerStats> {
+    let mut stats = TriggerStats::default();
+    let mut ins_transform_stmt = conn.prepare(INS_UR_TRANSFORM_SQL)?;
+    for trigger in triggers {
+        let mut stmt = conn.prepare(SESSION_RESOURCES_BY_NATURE_SQL)?;
+        let resources = stmt
+            .query_map(params![ingest_session_id, trigger.nature], |row| {
+                let content = match row.get_ref(2)? {
+                    ValueRef::Text(bytes) | ValueRef::Blob(bytes) => bytes.to_vec(),
+                    _ => vec![],
+                };
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, content))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .with_context(|| {
+                format!(
+                    "[run_nature_triggers] resources of nature {} in session {}",
+                    trigger.nature, ingest_session_id
+                )
+            })?;
+
+        for (ur_id, uri, content) in resources {
+            match trigger.apply(&uri, &content) {
+                Ok((transformed_uri, nature, content, elaboration)) => {
+                    let mut hasher = Sha1::new();
+                    hasher.update(content.as_bytes());
+                    ins_transform_stmt.query_row(
+                        params![
+                            ur_id,
+                            transformed_uri,
+                            nature,
+                            format!("{:x}", hasher.finalize()),
+                            content,
+                            content.len(),
+                            elaboration
+                        ],
+                        |row| row.get::<_, String>(0),
+                    )?;
+                    debug!(
+                        "[run_nature_triggers] {:?} fired on {}",
+                        trigger.action, uri
+                    );
+                    stats.fired += 1;
+                }
+                Err(err) => {
+                    error!(
+                        "[run_nature_triggers] {:?} failed on {}: {}",
+                        trigger.action, uri, err
+                    );
+                    stats.failed += 1;
+                }
+            }
+        }
+    }
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persist::DbConn;
+
+    #[test]
+    fn parse_triggers() {
+        assert_eq!(
+            "html=transform:json".parse::<NatureTrigger>().unwrap(),
+            NatureTrigger {
+                nature: "html".to_string(),
+                action: TriggerAction::Transform {
+                    format: "json".to_string()
+                },
+            }
+        );
+        assert_eq!(
+            "png=exec:tesseract $SURVEILR_TRIGGER_URI stdout"
+                .parse::<NatureTrigger>()
+                .unwrap()
+                .action,
+            TriggerAction::Exec {
+                command: "tesseract $SURVEILR_TRIGGER_URI stdout".to_string()
+            }
+        );
+        assert!("png=transform:json".parse::<NatureTrigger>().is_err());
+        assert!("html".parse::<NatureTrigger>().is_err());
+        assert!("html=ocr:json".parse::<NatureTrigger>().is_err());
+    }
+
+    #[test]
+    fn triggers_fire_on_session_resources() -> Result<()> {
+        let mut dbc = DbConn::new(":memory:", 0)?;
+        let tx = dbc.init(None)?;
+        let (device_id, _) = crate::persist::upserted_device(&tx, &common::DEVICE)?;
+        tx.execute(
+            "INSERT INTO ur_ingest_session (ur_ingest_session_id, device_id, behavior_json, ingest_started_at)
+             VALUES ('session', ?, '{}', CURRENT_TIMESTAMP)",
+            [&device_id],
+        )?;
+        for (id, uri, nature, content) in [
+            ("html-ur", "/tmp/page.html", "html", "<p>hello</p>"),
+            ("md-ur", "/tmp/notes.md", "md", "# hello"),
+        ] {
+            tx.execute(
+                "INSERT INTO uniform_resource (uniform_resource_id, device_id, ingest_session_id, uri, nature, content, content_digest, size_bytes)
+                 VALUES (?, ?, 'session', ?, ?, ?, ?, ?)",
+                params![id, device_id, uri, nature, content, id, content.len()],
+            )?;
+        }
+
+        let triggers = vec![
+            "html=transform:json".parse()?,
+            "md=exec:cat".parse()?,
+            "md=exec:exit 1".parse()?,
+        ];
+        let stats = run_nature_triggers(&tx, "session", &triggers)?;
+        assert_eq!(
+            stats,
+            TriggerStats {
+                fired: 2,
+                failed: 1
+            }
+        );
+
+        let exec_output: (String, String) = tx.query_row(
+            "SELECT uri, content FROM uniform_resource_transform WHERE uniform_resource_id = 'md-ur'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        assert_eq!(
+            exec_output,
+            ("/tmp/notes.md/exec".to_string(), "# hello".to_string())
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,600 @@
+use std::io::{BufRead, Read, Seek, Write};
+
+use anyhow::{anyhow, Context, Result};
+use resource::remote::{is_remote_uri, RemoteFetcher, RemoteMetaData};
+use resource::EncounterableResourcePathClassifier;
+use rusqlite::{params, types::Value, Connection, OptionalExtension};
+use serde_json::json;
+use sha1::{Digest, Sha1};
+use tracing::{debug, error};
+
+use super::chunks::{insert_content_chunks, write_chunked_content, INS_UR_CHUNK_SQL};
+use super::{INS_UR_INGEST_SESSION_FINISH_SQL, INS_UR_INGEST_SESSION_SQL};
+use crate::blobs::offloaded_blob;
+use crate::cmd::IngestUrisArgs;
+use crate::persist::*;
+use crate::reclassify::classified_nature;
+
+/// Content digest of remote resources cataloged without their content
+pub const UNFETCHED_CONTENT_DIGEST: &str = "-";
+
+/// Fetched content of up to this many bytes is stored in the resource's row,
+/// larger content in `uniform_resource_chunk` rows of this size (the
+/// `--content-chunk-size` default) so no object is held in memory as a whole.
+pub(super) const REMOTE_CONTENT_CHUNK_SIZE: usize = 64 * 1024 * 1024;
+
+const INS_REMOTE_UR_SQL: &str = "
+    INSERT INTO uniform_resource (uniform_resource_id, device_id, ingest_session_id, uri, nature, content, content_digest, size_bytes, last_modified_at, elaboration)
+                          VALUES (surveilr_pk(), ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                     ON CONFLICT (device_id, content_digest, uri, size_bytes, last_modified_at)
+                   DO UPDATE SET size_bytes = EXCLUDED.size_bytes
+                       RETURNING uniform_resource_id";
+
+// the same version of the object, cataloged or fetched earlier (fetched first)
+pub(super) const SEL_REMOTE_UR_SQL: &str = "
+    SELECT uniform_resource_id, content_digest != '-'
+      FROM uniform_resource
+     WHERE device_id = ? AND uri = ? AND last_modified_at IS ?
+       AND (content_digest = '-' OR size_bytes IS ?)
+  ORDER BY content_digest = '-'
+     LIMIT 1";
+
+// content fetched again replaces the chunks of the previous fetch
+const DEL_UR_CHUNKS_SQL: &str = "DELETE FROM uniform_resource_chunk WHERE uniform_resource_id = ?";
+
+const SEL_UR_CONTENT_SQL: &str =
+    "SELECT uri, content FROM uniform_resource WHERE uniform_resource_id = ?";
+
+const UPD_UR_FETCHED_SQL: &str = "
+    UPDATE uniform_resource
+       SET content = ?, content_digest = ?, size_bytes = ?,
+           elaboration = json_set(COALESCE(elaboration, '{}'), '$.remote', json(?)),
+           updated_at = CURRENT_TIMESTAMP
+     WHERE uniform_resource_id = ?";
+
+/// A remote resource's content, see `fetch_uniform_resource`.
+#[derive(Debug)]
+pub struct FetchedResource {
+    pub uniform_resource_id: String,
+    pub uri: String,
+    pub content: Vec<u8>,
+    /// the content was already in the RSSD so nothing was fetched
+    pub cached: bool,
+}
+
+// stored as `$.remote` in the resource's elaboration
+pub(super) fn remote_elaboration(meta_data: &RemoteMetaData, fetched: bool) -> serde_json::Value {
+    json!({
+        "content_type": meta_data.content_type,
+        "etag": meta_data.etag,
+        "fetched": fetched,
+    })
+}
+
+pub(super) fn sha1_hex(content: &[u8]) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(content);
+    format!("{:x}", hasher.finalize())
+}
+
+// text is stored as text so it can be queried like walked files
+pub(super) fn content_value(content: Vec<u8>) -> Value {
+    match String::from_utf8(content) {
+        Ok(text) => Value::Text(text),
+        Err(err) => Value::Blob(err.into_bytes()),
+    }
+}
+
+/// Content streamed from a remote URI, hashed as it's received and spooled to
+/// a temporary file once it outgrows a chunk.
+pub(super) struct FetchedContent {
+    spool: tempfile::SpooledTempFile,
+    hasher: Sha1,
+    size: u64,
+    chunk_size: usize,
+}
+
+impl Write for FetchedContent {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.spool.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.spool.flush()
+    }
+}
+
+impl FetchedContent {
+    pub(super) fn new(chunk_size: usize) -> Self {
+        FetchedContent {
+            spool: tempfile::SpooledTempFile::new(chunk_size),
+            hasher: Sha1::new(),
+            size: 0,
+            chunk_size,
+        }
+    }
+
+    pub(super) fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub(super) fn digest(&self) -> String {
+        format!("{:x}", self.hasher.clone().finalize())
+    }
+
+    /// The content as it's stored in the resource's row, `None` when it's
+    /// larger than a chunk and stored with `store_chunks` instead.
+    pub(super) fn row_value(&mut self) -> Result<Option<Value>> {
+        if self.size > self.chunk_size as u64 {
+            return Ok(None);
+        }
+        let mut content = Vec::with_capacity(self.size as usize);
+        self.spool.rewind()?;
+        self.spool.read_to_end(&mut content)?;
+        Ok(Some(content_value(content)))
+    }
+
+    /// Store the content as `uniform_resource_chunk` rows of `uniform_resource_id`.
+    pub(super) fn store_chunks(
+        &mut self,
+        conn: &Connection,
+        uniform_resource_id: &str,
+    ) -> Result<()> {
+        self.spool.rewind()?;
+        let mut ins_ur_chunk_stmt = conn.prepare_cached(INS_UR_CHUNK_SQL)?;
+        insert_content_chunks(
+            &mut ins_ur_chunk_stmt,
+            uniform_resource_id,
+            &mut self.spool,
+            self.chunk_size,
+        )?;
+        Ok(())
+    }
+
+    /// All of the content, for callers which need it in memory.
+    pub(super) fn into_vec(mut self) -> Result<Vec<u8>> {
+        let mut content = Vec::with_capacity(self.size as usize);
+        self.spool.rewind()?;
+        self.spool.read_to_end(&mut content)?;
+        Ok(content)
+    }
+}
+
+impl From<&[u8]> for FetchedContent {
+    fn from(content: &[u8]) -> Self {
+        let mut fetched = FetchedContent::new(REMOTE_CONTENT_CHUNK_SIZE);
+        fetched
+            .write_all(content)
+            .expect("writing to a spooled temporary file");
+        fetched
+    }
+}
+
+/// Stream the content of `uri` into a `FetchedContent`.
+pub(super) async fn fetch_content(
+    fetcher: &RemoteFetcher,
+    uri: &str,
+) -> Result<(RemoteMetaData, FetchedContent)> {
+    let mut content = FetchedContent::new(REMOTE_CONTENT_CHUNK_SIZE);
+    let meta_data = fetcher.fetch_into(uri, &mut content).await?;
+    Ok((meta_data, content))
+}
+
+/// Store the content fetched from the URI of `uniform_resource_id` in its row,
+/// or in chunks when it's larger than one.
+pub(super) fn cache_content(
+    conn: &Connection,
+    uniform_resource_id: &str,
+    meta_data: &RemoteMetaData,
+    content: &mut FetchedContent,
+) -> Result<()> {
+    let row_value = content.row_value()?;
+    let chunked = row_value.is_none();
+    conn.execute(
+        UPD_UR_FETCHED_SQL,
+        params![
+            row_value,
+            content.digest(),
+            content.size(),
+            remote_elaboration(meta_data, true).to_string(),
+            uniform_resource_id,
+        ],
+    )?;
+    conn.execute(DEL_UR_CHUNKS_SQL, [uniform_resource_id])?;
+    if chunked {
+        content.store_chunks(conn, uniform_resource_id)?;
+    }
+    Ok(())
+}
+
+/// The content of a uniform resource, read through the RSSD: stored content is
+/// returned as is (unless `refresh`), otherwise the resource's remote URI is
+/// fetched and its content stored so the next read doesn't fetch it again.
+pub async fn fetch_uniform_resource(
+    conn: &Connection,
+    fetcher: &RemoteFetcher,
+    uniform_resource_id: &str,
+    refresh: bool,
+) -> Result<FetchedResource> {
+    let (uri, content): (String, Option<Vec<u8>>) = conn
+        .query_row(SEL_UR_CONTENT_SQL, [uniform_resource_id], |row| {
+            Ok((
+                row.get(0)?,
+                row.get_ref(1)?.as_bytes_or_null()?.map(<[u8]>::to_vec),
+            ))
+        })
+        .optional()?
+        .ok_or_else(|| {
+            anyhow!("[fetch_uniform_resource] uniform resource {uniform_resource_id} not found")
+        })?;
+
+    // content stored in `uniform_resource_chunk` is reassembled and content
+    // offloaded to the blob store read from it
+    let content = match content {
+        Some(_) => match offloaded_blob(conn, uniform_resource_id)? {
+            Some((store, digest)) => Some(store.get(&digest)?),
+            None => content,
+        },
+        None => {
+            let mut chunked = Vec::new();
+            write_chunked_content(conn, uniform_resource_id, &mut chunked)?.map(|_| chunked)
+        }
+    };
+
+    match content {
+        Some(content) if !refresh || !is_remote_uri(&uri) => Ok(FetchedResource {
+            uniform_resource_id: uniform_resource_id.to_string(),
+            uri,
+            content,
+            cached: true,
+        }),
+        _ if !is_remote_uri(&uri) => Err(anyhow!(
+            "[fetch_uniform_resource] {uri} ({uniform_resource_id}) has no content and isn't a remote URI"
+        )),
+        _ => {
+            let (meta_data, mut content) = fetch_content(fetcher, &uri).await?;
+            cache_content(conn, uniform_resource_id, &meta_data, &mut content)
+                .with_context(|| format!("[fetch_uniform_resource] caching {uri}"))?;
+            Ok(FetchedResource {
+                uniform_resource_id: uniform_resource_id.to_string(),
+                uri,
+                content: content.into_vec()?,
+                cached: false,
+            })
+        }
+    }
+}
+
+impl IngestUrisArgs {
+    /// The URIs given as arguments followed by the ones in `--uris-from`.
+    pub fn all_uris(&self) -> Result<Vec<String>> {
+        let mut uris = self.uris.clone();
+        if let Some(uris_from) = &self.uris_from {
+            let lines: Vec<String> = if uris_from == "-" {
+                std::io::stdin().lock().lines().collect::<Result<_, _>>()?
+            } else {
+                std::fs::read_to_string(uris_from)
+                    .with_context(|| format!("[IngestUrisArgs::all_uris] reading {uris_from}"))?
+                    .lines()
+                    .map(str::to_string)
+                    .collect()
+            };
+            uris.extend(
+                lines
+                    .into_iter()
+                    .map(|line| line.trim().to_string())
+                    .filter(|line| !line.is_empty() && !line.starts_with('#')),
+            );
+        }
+        Ok(uris)
+    }
+}
+
+/// Catalog external (`http(s)://`, `s3://`) resources as uniform resources. With
+/// `meta_only`, only their metadata is recorded (through `HEAD` requests) and
+/// the content is left for `fetch_uniform_resource` to retrieve on demand.
+pub async fn ingest_uris(args: &IngestUrisArgs) -> Result<String> {
+    let uris = args.all_uris()?;
+    let mut dbc = DbConn::new(&args.state_db_fs_path, 0).with_context(|| {
+        format!(
+            "[ingest_uris] SQLite transaction in {}",
+            args.state_db_fs_path
+        )
+    })?;
+    let db_fs_path = dbc.db_fs_path.clone();
+    let tx = dbc.init(Some(&args.state_db_init_sql))?;
+    let (device_id, _device_name) = upserted_device(&tx, &common::DEVICE)
+        .with_context(|| format!("[ingest_uris] upserted_device in {}", db_fs_path))?;
+    let classifier = EncounterableResourcePathClassifier::default_from_conn(&tx)?;
+
+    let behavior = json!({ "uris": uris, "meta_only": args.meta_only }).to_string();
+    let ingest_session_id: String = tx
+        .query_row(
+            INS_UR_INGEST_SESSION_SQL,
+            params![device_id, None::<String>, behavior],
+            |row| row.get(0),
+        )
+        .with_context(|| format!("[ingest_uris] inserting ingest session in {}", db_fs_path))?;
+    debug!("URIs Session: {ingest_session_id}");
+
+    let fetcher = RemoteFetcher::default();
+    let (mut cataloged, mut fetched, mut ignored) = (0, 0, 0);
+    let mut failed = Vec::new();
+    for uri in uris {
+        if !is_remote_uri(&uri) {
+            failed.push(json!({ "uri": uri, "error": "not an http(s):// or s3:// URI" }));
+            continue;
+        }
+        let Some(nature) = classified_nature(&classifier, &uri) else {
+            ignored += 1;
+            continue;
+        };
+        let remote = if args.meta_only {
+            fetcher
+                .meta_data(&uri)
+                .await
+                .map(|meta_data| (meta_data, None))
+        } else {
+            fetch_content(&fetcher, &uri)
+                .await
+                .map(|(meta_data, content)| (meta_data, Some(content)))
+        };
+        let inserted = match remote {
+            Ok((meta_data, content)) => insert_remote(
+                &tx,
+                &device_id,
+                &ingest_session_id,
+                &uri,
+                &nature,
+                &meta_data,
+                content,
+            ),
+            Err(err) => Err(err),
+        };
+        match inserted {
+            Ok(true) => fetched += 1,
+            Ok(false) => cataloged += 1,
+            Err(err) => {
+                error!("[ingest_uris] {} in {}: {:#}", uri, db_fs_path, err);
+                failed.push(json!({ "uri": uri, "error": format!("{:#}", err) }));
+            }
+        }
+    }
+
+    let session_elaboration = json!({
+        "remote": {
+            "cataloged": cataloged,
+            "fetched": fetched,
+            "ignored": ignored,
+            "failed": failed,
+        }
+    })
+    .to_string();
+    tx.execute(
+        INS_UR_INGEST_SESSION_FINISH_SQL,
+        params![ingest_session_id, session_elaboration],
+    )
+    .with_context(|| format!("[ingest_uris] finishing session in {}", db_fs_path))?;
+    tx.commit().with_context(|| {
+        format!(
+            "[ingest_uris] unable to perform final commit in {}",
+            db_fs_path
+        )
+    })?;
+    Ok(ingest_session_id)
+}
+
+/// Insert (or find) the uniform resource of a remote object, storing `content`
+/// when it was fetched. Returns whether the resource has its content.
+fn insert_remote(
+    conn: &Connection,
+    device_id: &str,
+    ingest_session_id: &str,
+    uri: &str,
+    nature: &str,
+    meta_data: &RemoteMetaData,
+    content: Option<FetchedContent>,
+) -> Result<bool> {
+    let last_modified_at = meta_data.last_modified_at.map(|at| at.to_string());
+    let size_bytes = content
+        .as_ref()
+        .map(FetchedContent::size)
+        .or(meta_data.size_bytes);
+
+    // catalog an object (version) once, fill in its content when it's fetched
+    let existing: Option<(String, bool)> = conn
+        .query_row(
+            SEL_REMOTE_UR_SQL,
+            params![device_id, uri, last_modified_at, size_bytes],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    match (existing, content) {
+        (Some((_, has_content)), None) => Ok(has_content),
+        (Some((uniform_resource_id, _)), Some(mut content)) => {
+            cache_content(conn, &uniform_resource_id, meta_data, &mut content)
+                .with_context(|| format!("[insert_remote] caching {uri}"))?;
+            Ok(true)
+        }
+        (None, mut content) => {
+            let fetched = content.is_some();
+            let (digest, row_value) = match content.as_mut() {
+                Some(content) => (content.digest(), content.row_value()?),
+                None => (UNFETCHED_CONTENT_DIGEST.to_string(), None),
+            };
+            let uniform_resource_id: String = conn
+                .query_row(
+                    INS_REMOTE_UR_SQL,
+                    params![
+                        device_id,
+                        ingest_session_id,
+                        uri,
+                        nature,
+                        row_value,
+                        digest,
+                        size_bytes,
+                        last_modified_at,
+                        json!({ "remote": remote_elaboration(meta_data, fetched) }).to_string(),
+                    ],
+                    |row| row.get::<_, String>(0),
+                )
+                .with_context(|| format!("[insert_remote] {uri}"))?;
+            if let (Some(content), None) = (content.as_mut(), &row_value) {
+                content
+                    .store_chunks(conn, &uniform_resource_id)
+                    .with_context(|| format!("[insert_remote] storing chunks of {uri}"))?;
+            }
+            Ok(fetched)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persist::DbConn;
+
+    #[tokio::test]
+    async fn test_cataloged_resources_are_fetched_once() -> Result<()> {
+        let mut dbc = DbConn::new(":memory:", 0)?;
+        let tx = dbc.init(None)?;
+        let (device_id, _) = upserted_device(&tx, &common::DEVICE)?;
+        let session_id: String = tx.query_row(
+            INS_UR_INGEST_SESSION_SQL,
+            params![device_id, None::<String>, "{}"],
+            |row| row.get(0),
+        )?;
+        let uri = "s3://evidence/scans/q1.json";
+        let meta_data = RemoteMetaData {
+            size_bytes: Some(2),
+            etag: Some("abc".to_string()),
+            ..Default::default()
+        };
+        assert!(!insert_remote(
+            &tx,
+            &device_id,
+            &session_id,
+            uri,
+            "json",
+            &meta_data,
+            None
+        )?);
+        // cataloging the same object again finds the same row
+        assert!(!insert_remote(
+            &tx,
+            &device_id,
+            &session_id,
+            uri,
+            "json",
+            &meta_data,
+            None
+        )?);
+        let ur_id: String = tx.query_row(
+            "SELECT uniform_resource_id FROM uniform_resource WHERE uri = ? AND content_digest = '-'",
+            [uri],
+            |row| row.get(0),
+        )?;
+
+        // an unreachable endpoint proves the cached content is read without fetching
+        let fetcher = RemoteFetcher::new(resource::remote::S3Config {
+            region: "us-east-1".to_string(),
+            endpoint: Some("http://127.0.0.1:9".to_string()),
+            ..Default::default()
+        });
+        assert!(fetch_uniform_resource(&tx, &fetcher, &ur_id, false)
+            .await
+            .is_err());
+
+        assert!(insert_remote(
+            &tx,
+            &device_id,
+            &session_id,
+            uri,
+            "json",
+            &meta_data,
+            Some(b"{}"[..].into())
+        )?);
+        let fetched = fetch_uniform_resource(&tx, &fetcher, &ur_id, false).await?;
+        assert!(fetched.cached);
+        assert_eq!(fetched.content, b"{}");
+        let (rows, digest, etag): (usize, String, String) = tx.query_row(
+            "SELECT COUNT(*), MAX(content_digest), MAX(elaboration ->> '$.remote.etag') FROM uniform_resource WHERE uri = ?",
+            [uri],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+        assert_eq!(
+            (rows, digest.as_str(), etag.as_str()),
+            (1, sha1_hex(b"{}").as_str(), "abc")
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_large_content_is_stored_in_chunks() -> Result<()> {
+        let mut dbc = DbConn::new(":memory:", 0)?;
+        let tx = dbc.init(None)?;
+        let (device_id, _) = upserted_device(&tx, &common::DEVICE)?;
+        let session_id: String = tx.query_row(
+            INS_UR_INGEST_SESSION_SQL,
+            params![device_id, None::<String>, "{}"],
+            |row| row.get(0),
+        )?;
+        let uri = "https://example.com/evidence/disk.img";
+        let fetched = |content: &[u8]| -> Result<FetchedContent> {
+            let mut fetched = FetchedContent::new(4);
+            fetched.write_all(content)?;
+            Ok(fetched)
+        };
+        let chunks = |ur_id: &str| -> Result<usize> {
+            Ok(tx.query_row(
+                "SELECT COUNT(*) FROM uniform_resource_chunk WHERE uniform_resource_id = ?",
+                [ur_id],
+                |row| row.get(0),
+            )?)
+        };
+
+        assert!(insert_remote(
+            &tx,
+            &device_id,
+            &session_id,
+            uri,
+            "img",
+            &RemoteMetaData::default(),
+            Some(fetched(b"0123456789")?)
+        )?);
+        let (ur_id, content_is_null, size): (String, bool, u64) = tx.query_row(
+            "SELECT uniform_resource_id, content IS NULL, size_bytes FROM uniform_resource WHERE uri = ?",
+            [uri],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+        assert!(content_is_null);
+        assert_eq!((size, chunks(&ur_id)?), (10, 3));
+        // chunked content is fetched, it's not mistaken for an unfetched resource
+        let fetcher = RemoteFetcher::new(resource::remote::S3Config::default());
+        let cached = fetch_uniform_resource(&tx, &fetcher, &ur_id, false).await?;
+        assert!(cached.cached);
+        assert_eq!(cached.content, b"0123456789");
+
+        // content fetched again replaces the chunks, or them all when it fits in the row
+        cache_content(
+            &tx,
+            &ur_id,
+            &RemoteMetaData::default(),
+            &mut fetched(b"abcdef")?,
+        )?;
+        assert_eq!(chunks(&ur_id)?, 2);
+        cache_content(
+            &tx,
+            &ur_id,
+            &RemoteMetaData::default(),
+            &mut fetched(b"abc")?,
+        )?;
+        assert_eq!(chunks(&ur_id)?, 0);
+        let cached = fetch_uniform_resource(&tx, &fetcher, &ur_id, false).await?;
+        assert_eq!(cached.content, b"abc");
+        Ok(())
+    }
+}
@@ -0,0 +1,757 @@
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context, Result};
+use notify::{EventKind, PollWatcher, RecursiveMode, Watcher};
+use resource::{
+    walk, EncounterableResource, EncounteredResource, ResourcesCollection, UriNatureSupplier,
+};
+use rusqlite::params;
+use serde::Serialize;
+use serde_json::json;
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    blobs::BlobStore,
+    cmd::IngestFilesArgs,
+    ingest::{
+        insert_uniform_resource, upserted_device, CeWorkdirs, DbConn, IngestContext,
+        IngestFilesBehavior, UniformResourceWriterAction, UniformResourceWriterEntry,
+        UniformResourceWriterState, INS_UR_INGEST_SESSION_FINISH_SQL, INS_UR_INGEST_SESSION_SQL,
+        INS_UR_ISFSP_SQL, UPD_UR_INGEST_SESSION_DIGEST_SQL,
+    },
+};
+
+const UPD_WATCH_CHECKPOINT_SQL: &str = "
+    UPDATE ur_ingest_session
+       SET elaboration = json_set(COALESCE(elaboration, '{}'), '$.watch', json(?2)),
+           updated_at = CURRENT_TIMESTAMP
+     WHERE ur_ingest_session_id = ?1";
+
+type WatchEvents = notify::Result<notify::Event>;
+
+/// How a watched file changed since the previous poll, stored as `watch_event`
+/// in its entry's `elaboration`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WatchEvent {
+    Created,
+    Modified,
+    Deleted,
+}
+
+/// What a watch ingested so far, stored as `watch` in the session's
+/// `elaboration` at each checkpoint and when the watch stops.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct WatchStats {
+    pub polls: usize,
+    pub checkpoints: usize,
+    pub created: usize,
+    pub modified: usize,
+    pub deleted: usize,
+    pub errors: usize,
+}
+
+/// The files under a watched root by URI, with their size and modification
+/// time; a file whose size or modification time differs was modified.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct WatchSnapshot(BTreeMap<String, (Option<u64>, Option<String>)>);
+
+impl WatchSnapshot {
+    /// Capturable executables are run by walks, not watched.
+    pub fn from_resources(resources: &ResourcesCollection) -> WatchSnapshot {
+        WatchSnapshot(
+            resources
+                .encountered()
+                .filter_map(|er| match er {
+                    EncounteredResource::Resource(cr, _) => Some((
+                        cr.uri,
+                        (cr.size, cr.last_modified_at.map(|at| at.to_rfc3339())),
+                    )),
+                    _ => None,
+                })
+                .collect(),
+        )
+    }
+
+    /// The files created, modified or deleted since `previous`, by URI.
+    pub fn changes(&self, previous: &WatchSnapshot) -> Vec<(String, WatchEvent)> {
+        let mut changes: Vec<_> = self
+            .0
+            .iter()
+            .filter_map(|(uri, stat)| match previous.0.get(uri) {
+                None => Some((uri.clone(), WatchEvent::Created)),
+                Some(previous_stat) if previous_stat != stat => {
+                    Some((uri.clone(), WatchEvent::Modified))
+                }
+                Some(_) => None,
+            })
+            .chain(
+                previous
+                    .0
+                    .keys()
+                    .filter(|uri| !self.0.contains_key(*uri))
+                    .map(|uri| (uri.clone(), WatchEvent::Deleted)),
+            )
+            .collect();
+        changes.sort();
+        changes
+    }
+
+    /// The files of this snapshot which are `paths` or under them.
+    fn within(&self, paths: &[PathBuf]) -> WatchSnapshot {
+        WatchSnapshot(
+            self.0
+                .iter()
+                .filter(|(uri, _)| paths.iter().any(|path| Path::new(uri).starts_with(path)))
+                .map(|(uri, stat)| (uri.clone(), stat.clone()))
+                .collect(),
+        )
+    }
+
+    /// Replace the files under `paths` (e.g. the ones file system events were
+    /// reported for) with the ones `walked` has there now, returning the files
+    /// created, modified or deleted since.
+    pub fn merge(
+        &mut self,
+        walked: &WatchSnapshot,
+        paths: &[PathBuf],
+    ) -> Vec<(String, WatchEvent)> {
+        let previous = self.within(paths);
+        let current = walked.within(paths);
+        let changes = current.changes(&previous);
+        for uri in previous.0.keys() {
+            self.0.remove(uri);
+        }
+        self.0.extend(current.0);
+        changes
+    }
+}
+
+struct WatchedEntry {
+    uri: String,
+    event: WatchEvent,
+    uniform_resource_id: Option<String>,
+    ur_status: Option<String>,
+    ur_diagnostics: Option<String>,
+}
+
+struct WatchedRoot {
+    canonical_path: String,
+    ingest_fs_path_id: String,
+    snapshot: WatchSnapshot,
+    walked: bool,
+}
+
+/// A long-running `ingest files --watch` session: the first poll walks the
+/// root paths and stores their files, later ones store the files created or
+/// modified since the previous poll and record the deleted ones, as reported
+/// by the file system's events. Only the reported paths are walked, unless the
+/// events were lost (e.g. an overflowing inotify queue) and a root has to be
+/// walked again.
+pub struct FilesWatch<'a> {
+    ingest_args: &'a IngestFilesArgs,
+    dbc: DbConn,
+    device_id: String,
+    ingest_session_id: String,
+    behavior: IngestFilesBehavior,
+    blob_store: Option<BlobStore>,
+    roots: Vec<WatchedRoot>,
+    ce_workdirs: CeWorkdirs,
+    events: Receiver<WatchEvents>,
+    // dropping it stops the events
+    _watcher: Box<dyn Watcher + Send>,
+    pub stats: WatchStats,
+}
+
+impl<'a> FilesWatch<'a> {
+    pub fn start(debug: u8, ingest_args: &'a IngestFilesArgs) -> Result<FilesWatch<'a>> {
+        let state_db_fs_path = &ingest_args.state_db_fs_path;
+        let mut dbc = DbConn::new(state_db_fs_path, debug).with_context(|| {
+            format!(
+                "[FilesWatch::start] SQLite transaction in {}",
+                state_db_fs_path
+            )
+        })?;
+        let db_fs_path = dbc.db_fs_path.clone();
+        let tx = dbc.init(Some(&ingest_args.state_db_init_sql))?;
+        let (device_id, _device_name) = upserted_device(&tx, &common::DEVICE)
+            .with_context(|| format!("[FilesWatch::start] upserted_device in {}", db_fs_path))?;
+        let (mut behavior, behavior_id) = IngestFilesBehavior::new(&device_id, ingest_args, &tx)
+            .with_context(|| format!("[FilesWatch::start] behavior issue {}", db_fs_path))?;
+        let blob_store = ingest_args
+            .blob_store
+            .as_deref()
+            .map(|blob_store| BlobStore::establish(&tx, blob_store))
+            .transpose()?;
+        // the RSSD and its journals change with every poll's commit
+        let canonical_db_fs_path = std::fs::canonicalize(&db_fs_path)?
+            .to_string_lossy()
+            .to_string();
+        for suffix in ["", "-wal", "-shm", "-journal"] {
+            behavior
+                .classifier
+                .add_ignore_exact(&format!("{}{}", canonical_db_fs_path, suffix));
+        }
+
+        let ingest_session_id: String = tx
+            .query_row(
+                INS_UR_INGEST_SESSION_SQL,
+                params![device_id, behavior_id, behavior.persistable_json_text()?],
+                |row| row.get(0),
+            )
+            .with_context(|| format!("[FilesWatch::start] watch session in {}", db_fs_path))?;
+        tx.execute(
+            UPD_UR_INGEST_SESSION_DIGEST_SQL,
+            params![ingest_session_id, ingest_args.digest.to_string()],
+        )
+        .with_context(|| format!("[FilesWatch::start] --digest in {}", db_fs_path))?;
+        let mut roots = Vec::new();
+        for root_path in &behavior.root_fs_paths {
+            let canonical_path = std::fs::canonicalize(root_path)
+                .with_context(|| {
+                    format!("[FilesWatch::start] unable to canonicalize {}", root_path)
+                })?
+                .to_string_lossy()
+                .to_string();
+            let ingest_fs_path_id: String = tx
+                .query_row(
+                    INS_UR_ISFSP_SQL,
+                    params![
+                        ingest_session_id,
+                        canonical_path,
+                        json!({ "watch": true }).to_string()
+                    ],
+                    |row| row.get(0),
+                )
+                .with_context(|| format!("[FilesWatch::start] root path {}", root_path))?;
+            roots.push(WatchedRoot {
+                canonical_path,
+                ingest_fs_path_id,
+                snapshot: WatchSnapshot::default(),
+                walked: false,
+            });
+        }
+        tx.commit()
+            .with_context(|| format!("[FilesWatch::start] commit in {}", db_fs_path))?;
+        debug!("Watch Session: {ingest_session_id}");
+        // before the first poll's walk so changes made during it aren't missed
+        let (events_tx, events) = channel();
+        let watcher = watch_roots(&roots, events_tx, ingest_args)?;
+
+        Ok(FilesWatch {
+            ingest_args,
+            ce_workdirs: CeWorkdirs::new(&ingest_session_id, ingest_args.keep_ce_workdirs),
+            dbc,
+            device_id,
+            ingest_session_id,
+            behavior,
+            blob_store,
+            roots,
+            events,
+            _watcher: watcher,
+            stats: WatchStats::default(),
+        })
+    }
+
+    pub fn ingest_session_id(&self) -> &str {
+        &self.ingest_session_id
+    }
+
+    /// Store what changed since the previous poll in its own transaction,
+    /// returning how many files changed.
+    pub fn poll(&mut self) -> Result<usize> {
+        let FilesWatch {
+            ingest_args,
+            dbc,
+            device_id,
+            ingest_session_id,
+            behavior,
+            blob_store,
+            roots,
+            ce_workdirs,
+            events,
+            stats,
+            ..
+        } = self;
+        let mut changed_paths: Vec<PathBuf> = Vec::new();
+        let mut rescan = false;
+        for event in events.try_iter() {
+            match event {
+                Ok(event) if event.need_rescan() => rescan = true,
+                // reading files (e.g. to ingest them) doesn't change them
+                Ok(event) if matches!(event.kind, EventKind::Access(_)) => {}
+                Ok(event) => changed_paths.extend(event.paths),
+                Err(err) => {
+                    warn!("[FilesWatch::poll] walking the root paths again: {}", err);
+                    rescan = true;
+                }
+            }
+        }
+        let db_fs_path = dbc.db_fs_path.clone();
+        let env_current_dir = std::env::current_dir()?.to_string_lossy().to_string();
+        let tx = dbc.conn.transaction()?;
+        let mut changed = 0;
+        {
+            let mut ingest_stmts = IngestContext::from_conn(&tx, &db_fs_path)?;
+            for root in roots.iter_mut() {
+                let (mut resources, changes) = if !root.walked || rescan {
+                    let (resources, _walk_report) = ResourcesCollection::from_smart_ignore_walk(
+                        std::slice::from_ref(&root.canonical_path),
+                        &behavior.classifier,
+                        None,
+                        false,
+                        &Default::default(),
+                    );
+                    let snapshot = WatchSnapshot::from_resources(&resources);
+                    let changes = snapshot.changes(&root.snapshot);
+                    root.snapshot = snapshot;
+                    root.walked = true;
+                    (resources, changes)
+                } else {
+                    let paths: Vec<PathBuf> = changed_paths
+                        .iter()
+                        .filter(|path| path.starts_with(&root.canonical_path))
+                        .cloned()
+                        .collect();
+                    if paths.is_empty() {
+                        continue;
+                    }
+                    let walked = walk::smart_ignore_walk_paths(
+                        &root.canonical_path,
+                        &paths,
+                        false,
+                        &behavior.classifier.smart_ignore_conf_files,
+                    );
+                    let resources = ResourcesCollection::new(
+                        walked
+                            .entries
+                            .into_iter()
+                            .map(EncounterableResource::SmartIgnore)
+                            .collect(),
+                        &behavior.classifier,
+                        None,
+                    );
+                    let changes = root
+                        .snapshot
+                        .merge(&WatchSnapshot::from_resources(&resources), &paths);
+                    (resources, changes)
+                };
+                resources.digest = ingest_args.digest;
+                if changes.is_empty() {
+                    continue;
+                }
+                changed += changes.len();
+
+                let stored: HashSet<String> = changes
+                    .iter()
+                    .filter(|(_, event)| *event != WatchEvent::Deleted)
+                    .map(|(uri, _)| uri.clone())
+                    .collect();
+                resources
+                    .encounterable
+                    .retain(|er| stored.contains(&er.uri()));
+                let mut entries: Vec<WatchedEntry> = Vec::new();
+                let mut urw_state = UniformResourceWriterState {
+                    state_db_fs_path: &db_fs_path,
+                    ingest_files_behavior: Some(behavior),
+                    env_current_dir: &env_current_dir,
+                    device_id: device_id.as_str(),
+                    ingest_session_id: ingest_session_id.as_str(),
+                    ingest_fs_path_id: Some(&root.ingest_fs_path_id),
+                    resources: &resources,
+                    ingest_stmts: &mut ingest_stmts,
+                    ce_json_filter: behavior.ce_json_filter.as_deref(),
+                    ce_workdirs: &*ce_workdirs,
+                    decode_payloads: ingest_args.decode_payloads,
+                    canonical_json: ingest_args.canonical_json,
+                    content_chunk_size: ingest_args.content_chunk_size.map(|mb| mb * 1024 * 1024),
+                    blob_store: blob_store.as_ref(),
+                    blob_threshold: ingest_args.blob_threshold * 1024,
+                };
+                for resource_result in resources.uniform_resources() {
+                    let resource = match resource_result {
+                        Ok(resource) => resource,
+                        Err(err) => {
+                            error!("[FilesWatch::poll] Error processing a resource: {}", err);
+                            stats.errors += 1;
+                            continue;
+                        }
+                    };
+                    let mut urw_entry = UniformResourceWriterEntry {
+                        path: Some(resource.uri().as_str()),
+                        tried_alternate_nature: None,
+                        executed: None,
+                    };
+                    let inserted =
+                        insert_uniform_resource(&resource, &mut urw_state, &mut urw_entry);
+                    let uniform_resource_id = match &inserted.action {
+                        UniformResourceWriterAction::Inserted(id, None) => Some(id.clone()),
+                        _ => None,
+                    };
+                    let ur_status = inserted.action.ur_status();
+                    if ur_status.as_deref() == Some("ERROR") {
+                        stats.errors += 1;
+                    }
+                    let event = changes
+                        .iter()
+                        .find(|(uri, _)| *uri == inserted.uri)
+                        .map_or(WatchEvent::Modified, |(_, event)| *event);
+                    entries.push(WatchedEntry {
+                        ur_diagnostics: inserted.action.ur_diagnostics(),
+                        uri: inserted.uri,
+                        event,
+                        uniform_resource_id,
+                        ur_status,
+                    });
+                }
+                // deleted files are recorded without a resource
+                entries.extend(
+                    changes
+                        .iter()
+                        .filter(|(_, event)| *event == WatchEvent::Deleted)
+                        .map(|(uri, event)| WatchedEntry {
+                            uri: uri.clone(),
+                            event: *event,
+                            uniform_resource_id: None,
+                            ur_status: Some("DELETED".to_string()),
+                            ur_diagnostics: None,
+                        }),
+                );
+
+                for WatchedEntry {
+                    uri,
+                    event,
+                    uniform_resource_id,
+                    ur_status,
+                    ur_diagnostics,
+                } in entries
+                {
+                    match event {
+                        WatchEvent::Created => stats.created += 1,
+                        WatchEvent::Modified => stats.modified += 1,
+                        WatchEvent::Deleted => stats.deleted += 1,
+                    }
+                    let Some((file_path_rel_parent, file_path_rel, file_basename, file_extn)) =
+                        watched_path_info(&root.canonical_path, &uri)
+                    else {
+                        error!("[FilesWatch::poll] error extracting path info for {}", uri);
+                        continue;
+                    };
+                    ingest_stmts
+                        .ins_ur_isfsp_entry_stmt
+                        .query_row(
+                            params![
+                                ingest_session_id.as_str(),
+                                root.ingest_fs_path_id,
+                                uniform_resource_id,
+                                uri,
+                                file_path_rel_parent,
+                                file_path_rel,
+                                file_basename,
+                                file_extn.unwrap_or_default(),
+                                ur_status,
+                                ur_diagnostics,
+                                None::<String>,
+                                json!({ "watch_event": event }).to_string()
+                            ],
+                            |row| row.get::<_, String>(0),
+                        )
+                        .with_context(|| {
+                            format!("[FilesWatch::poll] entry of {} in {}", uri, db_fs_path)
+                        })?;
+                }
+            }
+        }
+        tx.commit()
+            .with_context(|| format!("[FilesWatch::poll] commit in {}", db_fs_path))?;
+        stats.polls += 1;
+        Ok(changed)
+    }
+
+    /// Record what the watch ingested so far in the session's `elaboration`.
+    pub fn checkpoint(&mut self) -> Result<()> {
+        self.stats.checkpoints += 1;
+        self.dbc
+            .conn
+            .execute(
+                UPD_WATCH_CHECKPOINT_SQL,
+                params![self.ingest_session_id, json!(self.stats).to_string()],
+            )
+            .with_context(|| {
+                format!(
+                    "[FilesWatch::checkpoint] session {} in {}",
+                    self.ingest_session_id, self.dbc.db_fs_path
+                )
+            })?;
+        Ok(())
+    }
+
+    /// Finish the session, returning its ID.
+    pub fn finish(self) -> Result<String> {
+        self.dbc
+            .conn
+            .execute(
+                INS_UR_INGEST_SESSION_FINISH_SQL,
+                params![
+                    self.ingest_session_id,
+                    json!({ "watch": self.stats }).to_string()
+                ],
+            )
+            .with_context(|| {
+                format!(
+                    "[FilesWatch::finish] session {} in {}",
+                    self.ingest_session_id, self.dbc.db_fs_path
+                )
+            })?;
+        Ok(self.ingest_session_id)
+    }
+}
+
+/// Send the file system's events for the files under the roots to `events`;
+/// with `--watch-poll`, or when the file system's events aren't available
+/// (e.g. too many inotify watches), the roots are walked every
+/// `--watch-interval` seconds to find them instead.
+fn watch_roots(
+    roots: &[WatchedRoot],
+    events: Sender<WatchEvents>,
+    ingest_args: &IngestFilesArgs,
+) -> Result<Box<dyn Watcher + Send>> {
+    fn watch(watcher: &mut dyn Watcher, roots: &[WatchedRoot]) -> notify::Result<()> {
+        for root in roots {
+            watcher.watch(Path::new(&root.canonical_path), RecursiveMode::Recursive)?;
+        }
+        Ok(())
+    }
+
+    if !ingest_args.watch_poll {
+        match notify::recommended_watcher(events.clone()) {
+            Ok(mut watcher) => match watch(&mut watcher, roots) {
+                Ok(()) => return Ok(Box::new(watcher)),
+                Err(err) => warn!("[watch_roots] walking the root paths instead: {}", err),
+            },
+            Err(err) => warn!("[watch_roots] walking the root paths instead: {}", err),
+        }
+    }
+    let mut watcher = PollWatcher::new(
+        events,
+        notify::Config::default()
+            .with_poll_interval(Duration::from_secs(ingest_args.watch_interval)),
+    )?;
+    watch(&mut watcher, roots).context("[watch_roots] walking the root paths")?;
+    Ok(Box::new(watcher))
+}
+
+/// Watch the root paths until `stop` is set, storing the changes every
+/// `--watch-interval` seconds and checkpointing every `--checkpoint-every` seconds. Each
+/// checkpoint's outcome (the session ID) and how long it took since the poll
+/// before it are passed to `checkpointed`, e.g. for health checks.
+pub fn watch_files(
+    debug: u8,
+    ingest_args: &IngestFilesArgs,
+    stop: &AtomicBool,
+    mut checkpointed: impl FnMut(&Result<Option<String>>, Duration),
+) -> Result<String> {
+    if ingest_args.watch_interval == 0 {
+        return Err(anyhow!(
+            "[watch_files] --watch-interval must be at least 1 second"
+        ));
+    }
+    let interval = Duration::from_secs(ingest_args.watch_interval);
+    let checkpoint_every = Duration::from_secs(ingest_args.checkpoint_every);
+    let mut watch = FilesWatch::start(debug, ingest_args)?;
+    info!(
+        "[watch_files] watching {} in session {}",
+        watch.behavior.root_fs_paths.join(", "),
+        watch.ingest_session_id()
+    );
+    let mut checkpointed_at = Instant::now();
+    while !stop.load(Ordering::SeqCst) {
+        let polled = Instant::now();
+        let changed = watch.poll()?;
+        if changed > 0 {
+            debug!("[watch_files] {} file(s) changed", changed);
+        }
+        if checkpointed_at.elapsed() >= checkpoint_every {
+            let checkpoint = watch
+                .checkpoint()
+                .map(|_| Some(watch.ingest_session_id().to_string()));
+            checkpointed(&checkpoint, polled.elapsed());
+            checkpoint?;
+            checkpointed_at = Instant::now();
+        }
+        // sleep in short steps so stopping doesn't wait for the whole interval
+        while polled.elapsed() < interval && !stop.load(Ordering::SeqCst) {
+            std::thread::sleep(Duration::from_millis(100).min(interval));
+        }
+    }
+    info!(
+        "[watch_files] stopped watching session {}: {:?}",
+        watch.ingest_session_id(),
+        watch.stats
+    );
+    watch.finish()
+}
+
+/// The parent, relative path, basename and extension of a watched file, which
+/// may not exist anymore.
+fn watched_path_info(
+    root_path: &str,
+    uri: &str,
+) -> Option<(String, String, String, Option<String>)> {
+    let path = Path::new(uri);
+    Some((
+        path.parent()?.to_string_lossy().to_string(),
+        path.strip_prefix(root_path)
+            .ok()?
+            .to_string_lossy()
+            .to_string(),
+        path.file_name()?.to_string_lossy().to_string(),
+        path.extension()
+            .map(|extn| extn.to_string_lossy().to_string()),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use crate::persist::DbConn;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        files: IngestFilesArgs,
+    }
+
+    /// Poll until `expected` files changed, the file system's events arrive
+    /// asynchronously.
+    fn poll_until(watch: &mut FilesWatch, expected: usize) -> Result<usize> {
+        let started = Instant::now();
+        let mut changed = watch.poll()?;
+        while changed < expected && started.elapsed() < Duration::from_secs(10) {
+            std::thread::sleep(Duration::from_millis(100));
+            changed += watch.poll()?;
+        }
+        Ok(changed)
+    }
+
+    fn watch_records_changes_in_one_session(watch_args: &[&str]) -> Result<()> {
+        let root = tempfile::tempdir()?;
+        std::fs::write(root.path().join("a.md"), "# a")?;
+        std::fs::write(root.path().join("b.md"), "# b")?;
+        // the RSSD is inside the watched root, it mustn't be ingested
+        let state_db = root.path().join("rssd.sqlite.db");
+        let root_path = root.path().to_string_lossy();
+        let state_db_path = state_db.to_string_lossy();
+        let mut cli_args = vec!["ingest", "-r", &root_path, "-d", &state_db_path, "--watch"];
+        cli_args.extend(watch_args);
+        let args = Cli::parse_from(cli_args).files;
+
+        let mut watch = FilesWatch::start(0, &args)?;
+        assert_eq!(watch.poll()?, 2);
+        assert_eq!(watch.poll()?, 0);
+
+        std::fs::write(root.path().join("a.md"), "# a, changed")?;
+        std::fs::write(root.path().join("c.md"), "# c")?;
+        std::fs::remove_file(root.path().join("b.md"))?;
+        // a new directory's files are found even if they were written before it
+        // was watched
+        std::fs::create_dir(root.path().join("sub"))?;
+        std::fs::write(root.path().join("sub").join("d.md"), "# d")?;
+        assert_eq!(poll_until(&mut watch, 4)?, 4);
+        assert_eq!(watch.poll()?, 0);
+        watch.checkpoint()?;
+        let session = watch.finish()?;
+
+        let dbc = DbConn::open(&state_db, 0)?;
+        let mut stmt = dbc.conn.prepare(
+            "SELECT file_basename, elaboration ->> '$.watch_event', ur_status, uniform_resource_id IS NOT NULL
+               FROM ur_ingest_session_fs_path_entry
+              WHERE ingest_session_id = ?
+           ORDER BY created_at, file_basename",
+        )?;
+        let mut entries = stmt
+            .query_map([&session], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })?
+            .collect::<rusqlite::Result<Vec<(String, String, Option<String>, bool)>>>()?;
+        entries.sort();
+        assert_eq!(
+            entries,
+            vec![
+                ("a.md".to_string(), "created".to_string(), None, true),
+                ("a.md".to_string(), "modified".to_string(), None, true),
+                ("b.md".to_string(), "created".to_string(), None, true),
+                (
+                    "b.md".to_string(),
+                    "deleted".to_string(),
+                    Some("DELETED".to_string()),
+                    false
+                ),
+                ("c.md".to_string(), "created".to_string(), None, true),
+                ("d.md".to_string(), "created".to_string(), None, true),
+            ]
+        );
+
+        let (finished, stats): (bool, String) = dbc.conn.query_row(
+            "SELECT ingest_finished_at IS NOT NULL, elaboration ->> '$.watch'
+               FROM ur_ingest_session WHERE ur_ingest_session_id = ?",
+            [&session],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        assert!(finished);
+        let stats: serde_json::Value = serde_json::from_str(&stats)?;
+        assert!(stats["polls"].as_u64() >= Some(4));
+        assert_eq!(stats["created"], 4);
+        assert_eq!(stats["modified"], 1);
+        assert_eq!(stats["deleted"], 1);
+        assert_eq!(stats["checkpoints"], 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_watch_records_changes_in_one_session() -> Result<()> {
+        watch_records_changes_in_one_session(&[])
+    }
+
+    #[test]
+    fn test_watch_poll_records_changes_in_one_session() -> Result<()> {
+        watch_records_changes_in_one_session(&["--watch-poll", "--watch-interval", "1"])
+    }
+
+    #[test]
+    fn test_watch_reports_each_checkpoint() -> Result<()> {
+        let root = tempfile::tempdir()?;
+        let state = tempfile::tempdir()?;
+        std::fs::write(root.path().join("a.md"), "# a")?;
+        let args = Cli::parse_from([
+            "ingest",
+            "-r",
+            &root.path().to_string_lossy(),
+            "-d",
+            &state.path().join("rssd.sqlite.db").to_string_lossy(),
+            "--watch",
+            "--watch-interval",
+            "1",
+            "--checkpoint-every",
+            "0",
+        ])
+        .files;
+
+        let stop = AtomicBool::new(false);
+        let mut checkpoints = Vec::new();
+        let session = watch_files(0, &args, &stop, |checkpoint, _| {
+            checkpoints.push(checkpoint.as_ref().ok().cloned().flatten());
+            if checkpoints.len() == 2 {
+                stop.store(true, Ordering::SeqCst);
+            }
+        })?;
+        assert_eq!(checkpoints, vec![Some(session.clone()), Some(session)]);
+        Ok(())
+    }
+}
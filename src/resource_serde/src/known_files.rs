@@ -0,0 +1,686 @@
+use std::fmt;
+use std::io::BufRead;
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use clap::ValueEnum;
+use rusqlite::{params, Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use sha1::Digest;
+
+const UPSERT_KNOWN_FILE_HASH_SQL: &str = "INSERT INTO known_file_hash (known_file_hash_id, hash_set, algorithm, digest, disposition, confidence, file_name)
+       VALUES (surveilr_pk(), ?, ?, ?, ?, ?, ?)
+  ON CONFLICT (hash_set, algorithm, digest) DO UPDATE SET
+       disposition = EXCLUDED.disposition,
+       confidence = EXCLUDED.confidence,
+       file_name = COALESCE(EXCLUDED.file_name, file_name),
+       updated_at = CURRENT_TIMESTAMP";
+const DEL_SET_MATCHES_SQL: &str = "DELETE FROM uniform_resource_known_file WHERE hash_set = ?";
+const DEL_SET_HASHES_SQL: &str = "DELETE FROM known_file_hash WHERE hash_set = ?";
+
+// the resources of a session are the ones it stored and the ones its walk
+// entries and tasks point to (content seen before is stored once)
+const IN_SCOPE_SQL: &str = "(?1 IS NULL
+        OR ur.ingest_session_id = ?1
+        OR ur.uniform_resource_id IN (SELECT uniform_resource_id FROM ur_ingest_session_fs_path_entry WHERE ingest_session_id = ?1)
+        OR ur.uniform_resource_id IN (SELECT uniform_resource_id FROM ur_ingest_session_task WHERE ingest_session_id = ?1))";
+const UPSERT_MATCH_SQL_SUFFIX: &str = "
+  ON CONFLICT (uniform_resource_id, known_file_hash_id) DO UPDATE SET
+       disposition = EXCLUDED.disposition,
+       confidence = EXCLUDED.confidence,
+       updated_at = CURRENT_TIMESTAMP";
+
+/// Whether a known file is expected or should be investigated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Disposition {
+    /// expected files, e.g. the NSRL's operating system and application files
+    KnownGood,
+    /// malware and other files which should never be found
+    KnownBad,
+}
+
+impl fmt::Display for Disposition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.to_possible_value().unwrap().get_name())
+    }
+}
+
+impl std::str::FromStr for Disposition {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        <Disposition as ValueEnum>::from_str(s, true)
+            .map_err(|_| anyhow!("unknown disposition '{}'", s))
+    }
+}
+
+/// The algorithms of known-file hashes; SHA-1 is the `content_digest` of
+/// uniform resources so those match without reading the content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    Sha1,
+    Sha256,
+    Md5,
+}
+
+impl fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.to_possible_value().unwrap().get_name())
+    }
+}
+
+impl HashAlgorithm {
+    /// The algorithm of a hex digest, by its length.
+    fn of_digest(digest: &str) -> Option<HashAlgorithm> {
+        if !digest.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        match digest.len() {
+            40 => Some(HashAlgorithm::Sha1),
+            64 => Some(HashAlgorithm::Sha256),
+            32 => Some(HashAlgorithm::Md5),
+            _ => None,
+        }
+    }
+
+    /// The algorithm of a hash set column, e.g. NSRL's `"SHA-1"`.
+    fn of_column(name: &str) -> Option<HashAlgorithm> {
+        match name.to_lowercase().replace(['-', '_'], "").as_str() {
+            "sha1" => Some(HashAlgorithm::Sha1),
+            "sha256" => Some(HashAlgorithm::Sha256),
+            "md5" => Some(HashAlgorithm::Md5),
+            _ => None,
+        }
+    }
+
+    pub fn hex_digest(&self, content: &[u8]) -> String {
+        match self {
+            HashAlgorithm::Sha1 => format!("{:x}", sha1::Sha1::digest(content)),
+            HashAlgorithm::Sha256 => format!("{:x}", sha2::Sha256::digest(content)),
+            HashAlgorithm::Md5 => format!("{:x}", md5::Md5::digest(content)),
+        }
+    }
+}
+
+/// A hash of a hash set; the disposition and confidence default to the ones
+/// the set is loaded with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KnownFile {
+    pub algorithm: HashAlgorithm,
+    pub digest: String,
+    pub file_name: Option<String>,
+    pub disposition: Option<Disposition>,
+    pub confidence: Option<f64>,
+}
+
+/// The fields of a CSV line, with `"quoted, fields"`.
+fn csv_fields(line: &str) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// The columns of a hash set with a header line.
+#[derive(Debug, Default)]
+struct HashSetColumns {
+    /// in order of preference, SHA-1 first
+    digests: Vec<(usize, Option<HashAlgorithm>)>,
+    file_name: Option<usize>,
+    disposition: Option<usize>,
+    confidence: Option<usize>,
+}
+
+impl HashSetColumns {
+    fn from_header(fields: &[String]) -> Option<HashSetColumns> {
+        let mut columns = HashSetColumns::default();
+        for (index, name) in fields.iter().enumerate() {
+            let name = name.trim().to_lowercase();
+            match name.as_str() {
+                "filename" | "file_name" | "name" | "path" => columns.file_name = Some(index),
+                "disposition" => columns.disposition = Some(index),
+                "confidence" => columns.confidence = Some(index),
+                "hash" | "digest" => columns.digests.push((index, None)),
+                _ => {
+                    if let Some(algorithm) = HashAlgorithm::of_column(&name) {
+                        columns.digests.push((index, Some(algorithm)));
+                    }
+                }
+            }
+        }
+        columns
+            .digests
+            .sort_by_key(|(_, algorithm)| match algorithm {
+                Some(HashAlgorithm::Sha1) => 0,
+                Some(HashAlgorithm::Sha256) => 1,
+                Some(HashAlgorithm::Md5) => 2,
+                None => 3,
+            });
+        (!columns.digests.is_empty()).then_some(columns)
+    }
+
+    fn known_file(&self, fields: &[String]) -> Result<Option<KnownFile>> {
+        let field = |index: Option<usize>| {
+            index
+                .and_then(|index| fields.get(index))
+                .map(|value| value.trim())
+                .filter(|value| !value.is_empty())
+        };
+        let Some((algorithm, digest)) = self.digests.iter().find_map(|(index, algorithm)| {
+            let digest = field(Some(*index))?.to_lowercase();
+            let detected = HashAlgorithm::of_digest(&digest)?;
+            (algorithm.is_none() || *algorithm == Some(detected)).then_some((detected, digest))
+        }) else {
+            return Ok(None);
+        };
+        Ok(Some(KnownFile {
+            algorithm,
+            digest,
+            file_name: field(self.file_name).map(|name| name.to_string()),
+            disposition: field(self.disposition).map(|d| d.parse()).transpose()?,
+            confidence: field(self.confidence)
+                .map(|c| c.parse::<f64>())
+                .transpose()
+                .context("confidence isn't a number")?,
+        }))
+    }
+}
+
+/// Read the hashes of a text hash set: CSV with a header naming its columns
+/// (NSRL's legacy `NSRLFile.txt` or `sha1,file_name,disposition,confidence`)
+/// or one hash per line optionally followed by a file name (`sha256sum` output).
+/// Returns the number of lines which aren't hashes.
+pub fn read_hash_set(
+    reader: impl BufRead,
+    mut visit: impl FnMut(KnownFile) -> Result<()>,
+) -> Result<usize> {
+    let mut columns: Option<HashSetColumns> = None;
+    let mut first = true;
+    let mut skipped = 0;
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields = csv_fields(line);
+        // the first line names the columns unless it's a hash
+        if std::mem::take(&mut first) {
+            if let Some(header) = HashSetColumns::from_header(&fields) {
+                columns = Some(header);
+                continue;
+            }
+        }
+        let known_file = match &columns {
+            Some(columns) => columns
+                .known_file(&fields)
+                .with_context(|| format!("[read_hash_set] line {}", index + 1))?,
+            None => {
+                // `<hash>  <name>`, `<hash> *<name>` or `<hash>,<name>`
+                let (digest, name) = line
+                    .split_once(|c: char| c == ',' || c.is_whitespace())
+                    .unwrap_or((line, ""));
+                let digest = digest.trim_matches('"').to_lowercase();
+                let name = name.trim().trim_start_matches('*').trim_matches('"');
+                HashAlgorithm::of_digest(&digest).map(|algorithm| KnownFile {
+                    algorithm,
+                    digest,
+                    file_name: (!name.is_empty()).then(|| name.to_string()),
+                    disposition: None,
+                    confidence: None,
+                })
+            }
+        };
+        match known_file {
+            Some(known_file) => visit(known_file)?,
+            None => skipped += 1,
+        }
+    }
+    Ok(skipped)
+}
+
+/// Read the `FILE` table of an NSRL RDSv3 SQLite database.
+fn read_rds_v3(path: &Path, mut visit: impl FnMut(KnownFile) -> Result<()>) -> Result<usize> {
+    let rds = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let mut stmt = rds
+        .prepare("SELECT sha1, sha256, md5, file_name FROM FILE")
+        .with_context(|| format!("[read_rds_v3] {} isn't an NSRL RDSv3", path.display()))?;
+    let mut rows = stmt.query([])?;
+    let mut skipped = 0;
+    while let Some(row) = rows.next()? {
+        let mut known_file = None;
+        for column in 0..3 {
+            let digest = row
+                .get::<_, Option<String>>(column)?
+                .unwrap_or_default()
+                .to_lowercase();
+            if let Some(algorithm) = HashAlgorithm::of_digest(&digest) {
+                known_file = Some(KnownFile {
+                    algorithm,
+                    digest,
+                    file_name: row.get(3)?,
+                    disposition: None,
+                    confidence: None,
+                });
+                break;
+            }
+        }
+        match known_file {
+            Some(known_file) => visit(known_file)?,
+            None => skipped += 1,
+        }
+    }
+    Ok(skipped)
+}
+
+/// How a hash set is loaded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HashSetLoad {
+    pub hash_set: String,
+    pub disposition: Disposition,
+    pub confidence: f64,
+    /// remove the set's hashes (and matches) before loading it
+    pub replace: bool,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct HashSetLoadStats {
+    pub loaded: usize,
+    pub skipped: usize,
+}
+
+/// Load a hash set from `path` (`-` for STDIN): an NSRL RDSv3 SQLite database
+/// or one of the text formats of [`read_hash_set`].
+pub fn load_hash_set(
+    conn: &Connection,
+    path: &str,
+    load: &HashSetLoad,
+) -> Result<HashSetLoadStats> {
+    if !(0.0..=1.0).contains(&load.confidence) {
+        return Err(anyhow!(
+            "[load_hash_set] confidence must be between 0 and 1"
+        ));
+    }
+    if load.replace {
+        remove_hash_set(conn, &load.hash_set)?;
+    }
+    let mut stmt = conn.prepare_cached(UPSERT_KNOWN_FILE_HASH_SQL)?;
+    let mut stats = HashSetLoadStats::default();
+    let mut upsert = |known_file: KnownFile| -> Result<()> {
+        let confidence = known_file.confidence.unwrap_or(load.confidence);
+        if !(0.0..=1.0).contains(&confidence) {
+            return Err(anyhow!("confidence {} isn't between 0 and 1", confidence));
+        }
+        stmt.execute(params![
+            load.hash_set,
+            known_file.algorithm.to_string(),
+            known_file.digest,
+            known_file
+                .disposition
+                .unwrap_or(load.disposition)
+                .to_string(),
+            confidence,
+            known_file.file_name,
+        ])?;
+        stats.loaded += 1;
+        Ok(())
+    };
+
+    let skipped = if path == "-" {
+        read_hash_set(std::io::stdin().lock(), &mut upsert)
+    } else {
+        let mut magic = [0u8; 16];
+        let is_sqlite = std::fs::File::open(path)
+            .and_then(|mut file| std::io::Read::read_exact(&mut file, &mut magic))
+            .is_ok()
+            && &magic == b"SQLite format 3\0";
+        if is_sqlite {
+            read_rds_v3(Path::new(path), &mut upsert)
+        } else {
+            let file = std::fs::File::open(path)?;
+            read_hash_set(std::io::BufReader::new(file), &mut upsert)
+        }
+    }
+    .with_context(|| format!("[load_hash_set] {}", path))?;
+    stats.skipped = skipped;
+    Ok(stats)
+}
+
+/// Remove a hash set and its matches, returning the number of hashes removed.
+pub fn remove_hash_set(conn: &Connection, hash_set: &str) -> Result<usize> {
+    conn.execute(DEL_SET_MATCHES_SQL, [hash_set])?;
+    Ok(conn.execute(DEL_SET_HASHES_SQL, [hash_set])?)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HashSetSummary {
+    pub hash_set: String,
+    pub algorithm: String,
+    pub disposition: String,
+    pub hashes: usize,
+    pub matched_resources: usize,
+}
+
+pub fn hash_sets(conn: &Connection) -> Result<Vec<HashSetSummary>> {
+    let mut stmt = conn.prepare(
+        "SELECT k.hash_set, k.algorithm, k.disposition, COUNT(*),
+                (SELECT COUNT(DISTINCT m.uniform_resource_id) FROM uniform_resource_known_file m
+                  JOIN known_file_hash mk ON mk.known_file_hash_id = m.known_file_hash_id
+                 WHERE mk.hash_set = k.hash_set AND mk.algorithm = k.algorithm AND mk.disposition = k.disposition)
+           FROM known_file_hash k
+          GROUP BY k.hash_set, k.algorithm, k.disposition
+          ORDER BY k.hash_set, k.algorithm, k.disposition",
+    )?;
+    let sets = stmt
+        .query_map([], |row| {
+            Ok(HashSetSummary {
+                hash_set: row.get(0)?,
+                algorithm: row.get(1)?,
+                disposition: row.get(2)?,
+                hashes: row.get(3)?,
+                matched_resources: row.get(4)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(sets)
+}
+
+/// The resources matched against the hash sets, a resource matching both
+/// known-good and known-bad hashes is known-bad.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct KnownFileMatchStats {
+    pub resources: usize,
+    pub known_good: usize,
+    pub known_bad: usize,
+    pub unknown: usize,
+}
+
+/// Tag the uniform resources of `ingest_session_id` (all of them without it)
+/// whose content matches a known-file hash in `uniform_resource_known_file`.
+/// Hashes of the resources' `content_digest_algorithm` (SHA-1 unless they were
+/// ingested with `--digest`) are matched against `content_digest`, the
+/// resources' content is only hashed for hash sets of other algorithms.
+pub fn match_known_files(
+    conn: &Connection,
+    ingest_session_id: Option<&str>,
+) -> Result<KnownFileMatchStats> {
+    conn.execute(
+        &format!(
+            "INSERT INTO uniform_resource_known_file (uniform_resource_known_file_id, uniform_resource_id, known_file_hash_id, hash_set, disposition, confidence, elaboration)
+             SELECT surveilr_pk(), ur.uniform_resource_id, k.known_file_hash_id, k.hash_set, k.disposition, k.confidence, json_object('algorithm', k.algorithm)
+               FROM uniform_resource ur
+               JOIN known_file_hash k ON k.algorithm = ur.content_digest_algorithm AND k.digest = ur.content_digest
+              WHERE {IN_SCOPE_SQL}{UPSERT_MATCH_SQL_SUFFIX}"
+        ),
+        params![ingest_session_id],
+    )
+    .context("[match_known_files] content digests")?;
+
+    let algorithms = conn
+        .prepare("SELECT DISTINCT algorithm FROM known_file_hash")?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?
+        .into_iter()
+        .filter_map(|algorithm| HashAlgorithm::from_str(&algorithm, true).ok())
+        .collect::<Vec<_>>();
+    if !algorithms.is_empty() {
+        let mut content_stmt = conn.prepare(&format!(
+            "SELECT ur.uniform_resource_id, ur.content, ur.content_digest_algorithm FROM uniform_resource ur
+              WHERE ur.content IS NOT NULL AND {IN_SCOPE_SQL}
+                AND EXISTS (SELECT 1 FROM known_file_hash k WHERE k.algorithm != ur.content_digest_algorithm)"
+        ))?;
+        let mut hash_stmt = conn.prepare(
+            "SELECT known_file_hash_id FROM known_file_hash WHERE algorithm = ? AND digest = ?",
+        )?;
+        let mut ins_stmt = conn.prepare(&format!(
+            "INSERT INTO uniform_resource_known_file (uniform_resource_known_file_id, uniform_resource_id, known_file_hash_id, hash_set, disposition, confidence, elaboration)
+             SELECT surveilr_pk(), ?1, known_file_hash_id, hash_set, disposition, confidence, json_object('algorithm', algorithm)
+               FROM known_file_hash WHERE known_file_hash_id = ?2{UPSERT_MATCH_SQL_SUFFIX}"
+        ))?;
+        let mut rows = content_stmt.query(params![ingest_session_id])?;
+        while let Some(row) = rows.next()? {
+            let uniform_resource_id: String = row.get(0)?;
+            let content = row.get_ref(1)?.as_bytes()?;
+            let content_digest_algorithm: String = row.get(2)?;
+            for algorithm in &algorithms {
+                // already matched against content_digest
+                if algorithm.to_string() == content_digest_algorithm {
+                    continue;
+                }
+                let digest = algorithm.hex_digest(content);
+                let known: Vec<String> = hash_stmt
+                    .query_map(params![algorithm.to_string(), digest], |row| row.get(0))?
+                    .collect::<rusqlite::Result<_>>()?;
+                for known_file_hash_id in known {
+                    ins_stmt
+                        .execute(params![uniform_resource_id, known_file_hash_id])
+                        .with_context(|| format!("[match_known_files] {} hashes", algorithm))?;
+                }
+            }
+        }
+    }
+
+    let (resources, known_good, known_bad): (usize, usize, usize) = conn
+        .query_row(
+            &format!(
+                "SELECT COUNT(*),
+                        COUNT(*) FILTER (WHERE disposition = 'known-good'),
+                        COUNT(*) FILTER (WHERE disposition = 'known-bad')
+                   FROM (SELECT ur.uniform_resource_id,
+                                -- known-bad sorts first
+                                (SELECT MIN(m.disposition) FROM uniform_resource_known_file m
+                                  WHERE m.uniform_resource_id = ur.uniform_resource_id) AS disposition
+                           FROM uniform_resource ur WHERE {IN_SCOPE_SQL})"
+            ),
+            params![ingest_session_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .context("[match_known_files] counting matches")?;
+    Ok(KnownFileMatchStats {
+        resources,
+        known_good,
+        known_bad,
+        unknown: resources - known_good - known_bad,
+    })
+}
+
+/// A uniform resource with its known-file match, `None` when it's unknown.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct KnownFileMatch {
+    pub uniform_resource_id: String,
+    pub uri: String,
+    pub nature: Option<String>,
+    pub disposition: Option<String>,
+    pub hash_set: Option<String>,
+    pub confidence: Option<f64>,
+}
+
+/// The resources of `ingest_session_id` (all of them without it) which are
+/// `disposition` or, without it, unknown, limited to `natures` when given.
+pub fn known_file_matches(
+    conn: &Connection,
+    disposition: Option<Disposition>,
+    ingest_session_id: Option<&str>,
+    natures: &[String],
+) -> Result<Vec<KnownFileMatch>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT ur.uniform_resource_id, ur.uri, ur.nature, m.disposition, m.hash_set, m.confidence
+           FROM uniform_resource ur
+           LEFT JOIN uniform_resource_known_file m ON m.uniform_resource_id = ur.uniform_resource_id
+          WHERE {IN_SCOPE_SQL}
+            AND ((?2 IS NULL AND m.disposition IS NULL)
+                 OR (?2 = 'known-bad' AND m.disposition = 'known-bad')
+                 OR (?2 = 'known-good' AND m.disposition = 'known-good'
+                     AND NOT EXISTS (SELECT 1 FROM uniform_resource_known_file bad
+                                      WHERE bad.uniform_resource_id = ur.uniform_resource_id AND bad.disposition = 'known-bad')))
+          ORDER BY ur.uri, m.hash_set"
+    ))?;
+    let matches = stmt
+        .query_map(
+            params![ingest_session_id, disposition.map(|d| d.to_string())],
+            |row| {
+                Ok(KnownFileMatch {
+                    uniform_resource_id: row.get(0)?,
+                    uri: row.get(1)?,
+                    nature: row.get(2)?,
+                    disposition: row.get(3)?,
+                    hash_set: row.get(4)?,
+                    confidence: row.get(5)?,
+                })
+            },
+        )?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(matches
+        .into_iter()
+        .filter(|m| natures.is_empty() || m.nature.as_ref().is_some_and(|n| natures.contains(n)))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingest::{seed_rssd, SeedProfile};
+    use crate::persist::DbConn;
+
+    #[test]
+    fn test_read_hash_set() -> Result<()> {
+        let nsrl = r#""SHA-1","MD5","CRC32","FileName","FileSize","ProductCode","OpSystemCode","SpecialCode"
+"000000206738748EDD92C4E3D2E823896700F849","392126E756571EBF112CB1C1CDEDF926","EBD105A0","I05002T2.PFB",13315,1184,"362",""
+"0000004DA6391F7F5D2F7FCCF36CEBDA60C6EA02","0E53C14A3E48D94FF596A2824307B492","AA6A7B16","00br2026.gif",2226,1184,"362",""
+"#;
+        let mut hashes = vec![];
+        assert_eq!(
+            read_hash_set(nsrl.as_bytes(), |k| {
+                hashes.push(k);
+                Ok(())
+            })?,
+            0
+        );
+        assert_eq!(hashes.len(), 2);
+        assert_eq!(hashes[0].algorithm, HashAlgorithm::Sha1);
+        assert_eq!(hashes[0].digest, "000000206738748edd92c4e3d2e823896700f849");
+        assert_eq!(hashes[1].file_name.as_deref(), Some("00br2026.gif"));
+
+        let custom = "md5,name,disposition,confidence
+44d88612fea8a8f36de82e1278abb02f,eicar.com,known-bad,0.9
+not-a-hash,broken.bin,,
+";
+        let mut hashes = vec![];
+        assert_eq!(
+            read_hash_set(custom.as_bytes(), |k| {
+                hashes.push(k);
+                Ok(())
+            })?,
+            1
+        );
+        assert_eq!(hashes[0].algorithm, HashAlgorithm::Md5);
+        assert_eq!(hashes[0].disposition, Some(Disposition::KnownBad));
+        assert_eq!(hashes[0].confidence, Some(0.9));
+
+        let sha256sum = "# sha256sum output
+e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855  empty.txt
+e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855 *bin/empty
+";
+        let mut hashes = vec![];
+        assert_eq!(
+            read_hash_set(sha256sum.as_bytes(), |k| {
+                hashes.push(k);
+                Ok(())
+            })?,
+            0
+        );
+        assert_eq!(hashes[1].algorithm, HashAlgorithm::Sha256);
+        assert_eq!(hashes[1].file_name.as_deref(), Some("bin/empty"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_match_known_files() -> Result<()> {
+        let mut dbc = DbConn::new(":memory:", 0)?;
+        dbc.init(None)?.commit()?;
+        seed_rssd(&dbc.conn, SeedProfile::Demo)?;
+        let conn = &dbc.conn;
+        let resources: Vec<(String, String, Vec<u8>)> = conn
+            .prepare(
+                // content which only one resource has
+                "SELECT uniform_resource_id, content_digest, content FROM uniform_resource
+              WHERE content IS NOT NULL GROUP BY content_digest HAVING COUNT(*) = 1
+              ORDER BY uri LIMIT 2",
+            )?
+            .query_map([], |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get_ref(2)?.as_bytes()?.to_vec(),
+                ))
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        let dir = tempfile::tempdir()?;
+        let good = dir.path().join("good.txt");
+        std::fs::write(
+            &good,
+            format!("sha1,file_name\n{},good.md\n", resources[0].1),
+        )?;
+        let bad = dir.path().join("bad.txt");
+        std::fs::write(
+            &bad,
+            format!(
+                "{}  bad.md\n{}  also-good.md\n",
+                HashAlgorithm::Sha256.hex_digest(&resources[1].2),
+                HashAlgorithm::Sha256.hex_digest(&resources[0].2)
+            ),
+        )?;
+
+        let load = |path: &Path, hash_set: &str, disposition| {
+            load_hash_set(
+                conn,
+                path.to_str().unwrap(),
+                &HashSetLoad {
+                    hash_set: hash_set.to_string(),
+                    disposition,
+                    confidence: 0.8,
+                    replace: false,
+                },
+            )
+        };
+        assert_eq!(load(&good, "nsrl", Disposition::KnownGood)?.loaded, 1);
+        assert_eq!(load(&bad, "iocs", Disposition::KnownBad)?.loaded, 2);
+        assert_eq!(hash_sets(conn)?.len(), 2);
+
+        let stats = match_known_files(conn, None)?;
+        assert_eq!((stats.known_good, stats.known_bad), (0, 2));
+        assert_eq!(stats.unknown, stats.resources - 2);
+        // matching again doesn't duplicate
+        assert_eq!(match_known_files(conn, None)?, stats);
+        let bad = known_file_matches(conn, Some(Disposition::KnownBad), None, &[])?;
+        assert_eq!(bad.len(), 2);
+        assert_eq!(bad[0].confidence, Some(0.8));
+
+        remove_hash_set(conn, "iocs")?;
+        let stats = match_known_files(conn, None)?;
+        assert_eq!((stats.known_good, stats.known_bad), (1, 0));
+        let good = known_file_matches(conn, Some(Disposition::KnownGood), None, &[])?;
+        assert_eq!(good[0].uniform_resource_id, resources[0].0);
+        assert_eq!(good[0].hash_set.as_deref(), Some("nsrl"));
+        assert_eq!(
+            known_file_matches(conn, None, None, &[])?.len(),
+            stats.unknown
+        );
+        Ok(())
+    }
+}
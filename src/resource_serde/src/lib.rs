@@ -1,5 +1,24 @@
+pub mod audit;
+pub mod backup;
+pub mod blobs;
 pub mod cmd;
+pub mod compliance;
+pub mod computed_columns;
 pub mod ingest;
+pub mod known_files;
+pub mod merge;
 pub mod models_polygenix;
 pub mod persist;
-pub mod transformers;
\ No newline at end of file
+pub mod policy;
+pub mod prune;
+pub mod reclassify;
+pub mod remote_db;
+pub mod schedule;
+pub mod schema_doc;
+pub mod self_test;
+pub mod sessions;
+pub mod snapshot;
+#[cfg(feature = "transform")]
+pub mod transformers;
+pub mod verify;
+pub mod walk_vtab;
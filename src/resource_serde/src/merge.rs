@@ -0,0 +1,425 @@
+use anyhow::{anyhow, Context, Result};
+use resource::digest::DigestAlgorithm;
+use rusqlite::{Connection, OpenFlags, OptionalExtension};
+use serde::Serialize;
+
+use crate::blobs::{blob_pointer_len_range, pointed_blob, BlobStore};
+use crate::persist::set_busy_timeout;
+
+/// A merged `uniform_resource` whose content can't be trusted, attributed to the
+/// candidate it was merged from.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MergeMismatch {
+    pub uniform_resource_id: String,
+    pub uri: String,
+    /// the first candidate (in merge order) with this resource, it's the one
+    /// `INSERT OR IGNORE` kept
+    pub source_db_fs_path: Option<String>,
+    pub merged_digest: String,
+    pub computed_digest: String,
+    pub source_digest: Option<String>,
+    pub reason: String,
+}
+
+/// What `admin merge --verify` found.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct MergeVerification {
+    pub verified: usize,
+    pub workers: usize,
+    pub mismatches: Vec<MergeMismatch>,
+}
+
+/// Rebuilds `uniform_resource_equivalence` from the merged `uniform_resource`
+/// rows: every resource whose content digest was ingested on more than one
+/// device is linked to the others through that digest. Placeholder digests
+/// (`-`, content that wasn't read) and the digest of empty content are left
+/// out, they'd link unrelated resources. `surveilr_pk()` must be registered.
+pub const LINK_CONTENT_EQUIVALENCE_SQL: &str = "
+DELETE FROM uniform_resource_equivalence;
+INSERT INTO uniform_resource_equivalence (uniform_resource_equivalence_id, content_digest, uniform_resource_id, device_id, uri, device_count)
+     SELECT surveilr_pk(), ur.content_digest, ur.uniform_resource_id, ur.device_id, ur.uri, shared.device_count
+       FROM uniform_resource ur
+       JOIN (SELECT content_digest, COUNT(DISTINCT device_id) AS device_count
+               FROM uniform_resource
+              WHERE content_digest NOT IN ('-', 'da39a3ee5e6b4b0d3255bfef95601890afd80709')
+              GROUP BY content_digest
+             HAVING COUNT(DISTINCT device_id) > 1) shared ON shared.content_digest = ur.content_digest;
+";
+
+/// Link the resources of different devices with identical content, see
+/// [`LINK_CONTENT_EQUIVALENCE_SQL`], and return how many were linked.
+pub fn link_content_equivalence(conn: &Connection) -> Result<usize> {
+    conn.execute_batch(LINK_CONTENT_EQUIVALENCE_SQL)
+        .context("[link_content_equivalence] rebuilding uniform_resource_equivalence")?;
+    Ok(conn.query_row(
+        "SELECT COUNT(*) FROM uniform_resource_equivalence",
+        [],
+        |row| row.get(0),
+    )?)
+}
+
+/// What `merge_blobs` copied.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct MergedBlobs {
+    /// the merged RSSD's blob store, `None` when no candidate offloaded content
+    pub blob_store: Option<String>,
+    pub copied: usize,
+    /// digests of blobs none of the candidates' stores has
+    pub missing: Vec<String>,
+}
+
+/// Copy the blobs the merged resources point to from the candidates' blob
+/// stores into the merged RSSD's, which is the first candidate's store when
+/// the merged RSSD has none yet. Blobs already there aren't copied again so
+/// candidates sharing a store cost nothing.
+pub fn merge_blobs(conn: &Connection, candidates: &[String]) -> Result<MergedBlobs> {
+    let mut sources = Vec::new();
+    for candidate in candidates {
+        if let Some(store) = BlobStore::recorded(&open_read_only(candidate)?)
+            .with_context(|| format!("[merge_blobs] reading {}", candidate))?
+        {
+            sources.push(store);
+        }
+    }
+    let store = match (BlobStore::recorded(conn)?, sources.first()) {
+        (Some(store), _) => store,
+        (None, Some(first)) => BlobStore::establish(conn, &first.root().to_string_lossy())?,
+        (None, None) => return Ok(MergedBlobs::default()),
+    };
+
+    let mut merged = MergedBlobs {
+        blob_store: Some(store.root().to_string_lossy().to_string()),
+        ..Default::default()
+    };
+    // only pointers have this length, most content is skipped without reading it
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT content, content_digest FROM uniform_resource WHERE length(content) BETWEEN ? AND ?",
+    )?;
+    let (shortest, longest) = blob_pointer_len_range();
+    let mut rows = stmt.query([shortest, longest])?;
+    while let Some(row) = rows.next()? {
+        let content = row.get_ref(0)?.as_bytes()?;
+        let Some(digest) = pointed_blob(content, row.get_ref(1)?.as_str()?) else {
+            continue;
+        };
+        if store.contains(digest) {
+            continue;
+        }
+        match sources.iter().find(|source| source.contains(digest)) {
+            Some(source) => {
+                store.copy_from(source, digest)?;
+                merged.copied += 1;
+            }
+            None => merged.missing.push(digest.to_string()),
+        }
+    }
+    Ok(merged)
+}
+
+fn open_read_only(db_fs_path: &str) -> Result<Connection> {
+    let conn = Connection::open_with_flags(
+        db_fs_path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI,
+    )
+    .with_context(|| format!("[merge::open_read_only] SQLite database {}", db_fs_path))?;
+    set_busy_timeout(&conn)?;
+    Ok(conn)
+}
+
+fn verify_chunk(
+    merged_db_fs_path: &str,
+    candidates: &[String],
+    ids: &[String],
+) -> Result<Vec<MergeMismatch>> {
+    let merged = open_read_only(merged_db_fs_path)?;
+    let blob_store = BlobStore::recorded(&merged)?;
+    let sources = candidates
+        .iter()
+        .map(|c| open_read_only(c).map(|conn| (c, conn)))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut select_merged = merged.prepare(
+        "SELECT uri, content, content_digest, content_digest_algorithm FROM uniform_resource WHERE uniform_resource_id = ?",
+    )?;
+    let mut mismatches = Vec::new();
+    for id in ids {
+        let (uri, content, merged_digest, algorithm) = select_merged.query_row([id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get_ref(1)?
+                    .as_bytes_or_null()?
+                    .unwrap_or_default()
+                    .to_vec(),
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+            ))
+        })?;
+        let algorithm: DigestAlgorithm = algorithm
+            .parse()
+            .with_context(|| format!("[merge::verify_chunk] {}", uri))?;
+        // offloaded content is hashed from its blob
+        let (computed_digest, blob_missing) =
+            match (pointed_blob(&content, &merged_digest), &blob_store) {
+                (Some(digest), Some(store)) if store.contains(digest) => {
+                    (store.digest_of(digest, algorithm)?, false)
+                }
+                (Some(digest), _) => (digest.to_string(), true),
+                (None, _) => (algorithm.hex_digest(&content), false),
+            };
+
+        let mut source = None;
+        for (source_db_fs_path, conn) in &sources {
+            let digest: Option<String> = conn
+                .query_row(
+                    "SELECT content_digest FROM uniform_resource WHERE uniform_resource_id = ?",
+                    [id],
+                    |row| row.get(0),
+                )
+                .optional()
+                .with_context(|| format!("[merge::verify_chunk] reading {}", source_db_fs_path))?;
+            if let Some(digest) = digest {
+                source = Some((source_db_fs_path.to_string(), digest));
+                break;
+            }
+        }
+
+        let reason = match &source {
+            None => Some("not found in any candidate"),
+            Some(_) if blob_missing => Some("content's blob is missing from the blob store"),
+            Some(_) if computed_digest != merged_digest => {
+                Some("content doesn't hash to its content_digest")
+            }
+            Some((_, source_digest)) if *source_digest != merged_digest => {
+                Some("content_digest differs from the candidate's")
+            }
+            Some(_) => None,
+        };
+        if let Some(reason) = reason {
+            let (source_db_fs_path, source_digest) = source.unzip();
+            mismatches.push(MergeMismatch {
+                uniform_resource_id: id.clone(),
+                uri,
+                source_db_fs_path,
+                merged_digest,
+                computed_digest,
+                source_digest,
+                reason: reason.to_string(),
+            });
+        }
+    }
+    Ok(mismatches)
+}
+
+/// Re-hash the content of every (or a random `sample` of) `uniform_resource`
+/// row in `merged_db_fs_path` across `workers` threads, each with its own
+/// read-only connections, and compare it with the digest stored in the merged
+/// database and in the candidate it came from.
+pub fn verify_merged_rssd(
+    merged_db_fs_path: &str,
+    candidates: &[String],
+    sample: Option<usize>,
+    workers: usize,
+) -> Result<MergeVerification> {
+    let ids: Vec<String> = {
+        let conn = open_read_only(merged_db_fs_path)?;
+        let mut stmt = conn.prepare(
+            "SELECT uniform_resource_id FROM uniform_resource
+              WHERE content IS NOT NULL
+              ORDER BY CASE WHEN ?1 IS NULL THEN uniform_resource_id ELSE random() END
+              LIMIT coalesce(?1, -1)",
+        )?;
+        let ids = stmt
+            .query_map([sample.map(|s| s as i64)], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .with_context(|| {
+                format!(
+                    "[verify_merged_rssd] selecting resources from {}",
+                    merged_db_fs_path
+                )
+            })?;
+        ids
+    };
+    if ids.is_empty() {
+        return Ok(MergeVerification::default());
+    }
+
+    let workers = workers.clamp(1, ids.len());
+    let chunk_size = ids.len().div_ceil(workers);
+    let mismatches = std::thread::scope(|scope| {
+        let handles: Vec<_> = ids
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(|| verify_chunk(merged_db_fs_path, candidates, chunk)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .map_err(|_| anyhow!("[verify_merged_rssd] verification worker panicked"))?
+            })
+            .collect::<Result<Vec<_>>>()
+    })?;
+
+    Ok(MergeVerification {
+        verified: ids.len(),
+        workers: ids.len().div_ceil(chunk_size),
+        mismatches: mismatches.into_iter().flatten().collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingest::{seed_rssd, SeedProfile};
+    use crate::persist::DbConn;
+
+    #[test]
+    fn verify_merged_rssd_attributes_mismatches() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("surveilr-merge-test-{}", ulid::Ulid::new()));
+        std::fs::create_dir_all(&dir)?;
+        let candidate = dir
+            .join("candidate.sqlite.db")
+            .to_string_lossy()
+            .to_string();
+        let merged = dir.join("merged.sqlite.db").to_string_lossy().to_string();
+
+        let mut dbc = DbConn::new(&candidate, 0)?;
+        let tx = dbc.init(None)?;
+        seed_rssd(&tx, SeedProfile::Test)?;
+        tx.commit()?;
+        drop(dbc);
+        std::fs::copy(&candidate, &merged)?;
+
+        let candidates = vec![candidate.clone()];
+        let verification = verify_merged_rssd(&merged, &candidates, None, 4)?;
+        assert_eq!(verification.verified, 15);
+        assert_eq!(verification.workers, 4);
+        assert!(verification.mismatches.is_empty());
+        assert_eq!(
+            verify_merged_rssd(&merged, &candidates, Some(5), 2)?.verified,
+            5
+        );
+
+        let conn = Connection::open(&merged)?;
+        let tampered: String = conn.query_row(
+            "UPDATE uniform_resource SET content = 'tampered'
+              WHERE uniform_resource_id = (SELECT min(uniform_resource_id) FROM uniform_resource)
+              RETURNING uniform_resource_id",
+            [],
+            |row| row.get(0),
+        )?;
+        drop(conn);
+
+        let verification = verify_merged_rssd(&merged, &candidates, None, 3)?;
+        assert_eq!(verification.mismatches.len(), 1);
+        let mismatch = &verification.mismatches[0];
+        assert_eq!(mismatch.uniform_resource_id, tampered);
+        assert_eq!(
+            mismatch.source_db_fs_path.as_deref(),
+            Some(candidate.as_str())
+        );
+        assert_eq!(
+            mismatch.reason,
+            "content doesn't hash to its content_digest"
+        );
+
+        // resources no candidate has can't be attributed
+        let verification = verify_merged_rssd(&merged, &[], Some(1), 1)?;
+        assert_eq!(
+            verification.mismatches[0].reason,
+            "not found in any candidate"
+        );
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn merge_blobs_follows_pointers() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let candidate = dir.path().join("candidate.sqlite.db");
+        let candidate = candidate.to_string_lossy().to_string();
+        let merged = dir.path().join("merged.sqlite.db");
+        let merged = merged.to_string_lossy().to_string();
+
+        // the candidate offloaded one resource's content
+        let mut dbc = DbConn::new(&candidate, 0)?;
+        let tx = dbc.init(None)?;
+        seed_rssd(&tx, SeedProfile::Test)?;
+        let store = BlobStore::establish(&tx, &dir.path().join("blobs").to_string_lossy())?;
+        let (id, content): (String, Vec<u8>) = tx.query_row(
+            "SELECT uniform_resource_id, content FROM uniform_resource
+              WHERE content_digest != '-' ORDER BY uniform_resource_id LIMIT 1",
+            [],
+            |row| Ok((row.get(0)?, row.get_ref(1)?.as_bytes()?.to_vec())),
+        )?;
+        let digest = store.put(&content, resource::digest::DigestAlgorithm::Sha1)?;
+        tx.execute(
+            "UPDATE uniform_resource SET content = ? WHERE uniform_resource_id = ?",
+            rusqlite::params![crate::blobs::blob_pointer(&digest).as_bytes(), id],
+        )?;
+        tx.commit()?;
+        drop(dbc);
+        std::fs::copy(&candidate, &merged)?;
+
+        // the merged RSSD gets a store of its own, it has none of the candidate's metadata
+        let conn = Connection::open(&merged)?;
+        conn.execute(
+            "DELETE FROM rssd_metadata WHERE key = ?",
+            [crate::blobs::BLOB_STORE_METADATA_KEY],
+        )?;
+        let target = BlobStore::establish(&conn, &dir.path().join("merged").to_string_lossy())?;
+        let candidates = vec![candidate.clone()];
+        assert!(!verify_merged_rssd(&merged, &candidates, None, 1)?
+            .mismatches
+            .is_empty());
+        let blobs = merge_blobs(&conn, &candidates)?;
+        assert_eq!(blobs.copied, 1);
+        assert!(blobs.missing.is_empty());
+        assert_eq!(target.get(&digest)?, content);
+        assert_eq!(merge_blobs(&conn, &candidates)?.copied, 0);
+        drop(conn);
+        assert!(verify_merged_rssd(&merged, &candidates, None, 1)?
+            .mismatches
+            .is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn link_content_equivalence_across_devices() -> Result<()> {
+        let mut dbc = DbConn::new(":memory:", 0)?;
+        let tx = dbc.init(None)?;
+        seed_rssd(&tx, SeedProfile::Test)?;
+        assert_eq!(link_content_equivalence(&tx)?, 0);
+
+        // another host ingested the same file
+        let (digest, resource_id): (String, String) = tx.query_row(
+            "SELECT content_digest, uniform_resource_id FROM uniform_resource
+              WHERE content_digest != '-' ORDER BY uniform_resource_id LIMIT 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        tx.execute_batch(&format!(
+            "INSERT INTO device (device_id, name, boundary, state) VALUES ('host-b', 'host-b', 'fleet', '{{}}');
+             CREATE TEMP TABLE copied AS SELECT * FROM uniform_resource WHERE uniform_resource_id = '{resource_id}';
+             UPDATE copied SET uniform_resource_id = 'host-b-resource', device_id = 'host-b', uri = '/srv/copy';
+             INSERT INTO uniform_resource SELECT * FROM copied;"
+        ))?;
+
+        assert_eq!(link_content_equivalence(&tx)?, 2);
+        let hosts: Vec<(String, i64)> = tx
+            .prepare(
+                "SELECT device_id, device_count FROM uniform_resource_equivalence
+                  WHERE content_digest = ? ORDER BY device_id",
+            )?
+            .query_map([&digest], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+        assert_eq!(hosts.len(), 2);
+        assert!(hosts.iter().all(|(_, count)| *count == 2));
+        assert!(hosts.iter().any(|(device_id, _)| device_id == "host-b"));
+
+        // rebuilt, not appended to
+        assert_eq!(link_content_equivalence(&tx)?, 2);
+        Ok(())
+    }
+}
@@ -12,6 +12,23 @@ const UR_INGEST_SESSION_TASK: &str = "ur_ingest_session_task";
 const UR_INGEST_SESSION_IMAP_ACCOUNT: &str = "ur_ingest_session_imap_account";
 const UR_INGEST_SESSION_IMAP_ACCT_FOLDER: &str = "ur_ingest_session_imap_acct_folder";
 const UR_INGEST_SESSION_IMAP_ACCT_FOLDER_MESSAGE: &str = "ur_ingest_session_imap_acct_folder_message";
+const UR_INGEST_SESSION_IMAP_ACCT_STAT: &str = "ur_ingest_session_imap_acct_stat";
+const UNIFORM_RESOURCE_LINEAGE: &str = "uniform_resource_lineage";
+const RSSD_METADATA: &str = "rssd_metadata";
+const QUERY_SNAPSHOT: &str = "query_snapshot";
+const SURVEILR_AUDIT: &str = "surveilr_audit";
+const POLICY_FINDING: &str = "policy_finding";
+const POLICY_ALERT_RULE: &str = "policy_alert_rule";
+const COMPLIANCE_CONTROL: &str = "compliance_control";
+const COMPLIANCE_EVIDENCE_MAP: &str = "compliance_evidence_map";
+const KNOWN_FILE_HASH: &str = "known_file_hash";
+const UNIFORM_RESOURCE_KNOWN_FILE: &str = "uniform_resource_known_file";
+const UR_INGEST_SESSION_GIT_REPO: &str = "ur_ingest_session_git_repo";
+const SURVEILR_REMOTE_SYNC: &str = "surveilr_remote_sync";
+const UNIFORM_RESOURCE_EQUIVALENCE: &str = "uniform_resource_equivalence";
+const UNIFORM_RESOURCE_CHUNK: &str = "uniform_resource_chunk";
+const UR_INGEST_IMAP_FOLDER_STATE: &str = "ur_ingest_imap_folder_state";
+const UNIFORM_RESOURCE_VERIFICATION: &str = "uniform_resource_verification";
 const ASSURANCE_SCHEMA: &str = "assurance_schema";
 const CODE_NOTEBOOK_KERNEL: &str = "code_notebook_kernel";
 const CODE_NOTEBOOK_CELL: &str = "code_notebook_cell";
@@ -80,6 +97,8 @@ pub struct UrIngestSession {
     ingest_started_at: String, // uknown type 'TIMESTAMPTZ', mapping to String by default
     ingest_finished_at: Option<String>, // uknown type 'TIMESTAMPTZ', mapping to String by default
     elaboration: Option<String>, // uknown type 'string::json', mapping to String by default
+    device_clock: Option<String>, // uknown type 'string::json', mapping to String by default
+    content_digest_algorithm: String, // 'string' maps directly to Rust type
     ur_ingest_session_fs_paths: Vec<UrIngestSessionFsPath>, // `ur_ingest_session_fs_path` belongsTo collection
     uniform_resources: Vec<UniformResource>, // `uniform_resource` belongsTo collection
     ur_ingest_session_fs_path_entrys: Vec<UrIngestSessionFsPathEntry>, // `ur_ingest_session_fs_path_entry` belongsTo collection
@@ -115,6 +134,8 @@ pub struct UniformResource {
     content_fm_body_attrs: Option<String>, // uknown type 'string::json', mapping to String by default
     frontmatter: Option<String>, // uknown type 'string::json', mapping to String by default
     elaboration: Option<String>, // uknown type 'string::json', mapping to String by default
+    semantic_identity: Option<String>, // 'string' maps directly to Rust type
+    content_digest_algorithm: String, // 'string' maps directly to Rust type
     uniform_resource_transforms: Vec<UniformResourceTransform>, // `uniform_resource_transform` belongsTo collection
 }
 
@@ -200,6 +221,223 @@ pub struct UrIngestSessionImapAcctFolderMessage {
     cc: String, // uknown type 'string::json', mapping to String by default
     bcc: String, // uknown type 'string::json', mapping to String by default
     email_references: String, // uknown type 'string::json', mapping to String by default
+    date: Option<String>, // 'string' maps directly to Rust type
+}
+
+// `ur_ingest_session_imap_acct_stat` table
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct UrIngestSessionImapAcctStat {
+    ur_ingest_session_imap_acct_stat_id: String, // PRIMARY KEY ('string' maps directly to Rust type)
+    ingest_session_id: String, // 'string' maps directly to Rust type
+    ingest_account_id: String, // 'string' maps directly to Rust type
+    stat_kind: String, // 'string' maps directly to Rust type
+    stat_key: String, // 'string' maps directly to Rust type
+    stat_value: i64, // 'integer' maps directly to Rust type
+    elaboration: Option<String>, // uknown type 'string::json', mapping to String by default
+}
+
+// `uniform_resource_lineage` table
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct UniformResourceLineage {
+    uniform_resource_lineage_id: String, // PRIMARY KEY ('string' maps directly to Rust type)
+    ingest_session_id: String, // 'string' maps directly to Rust type
+    source_uniform_resource_id: Option<String>, // 'string' maps directly to Rust type
+    output_uniform_resource_id: String, // 'string' maps directly to Rust type
+    ingest_fs_path_entry_id: Option<String>, // 'string' maps directly to Rust type
+    ingest_task_id: Option<String>, // 'string' maps directly to Rust type
+    elaboration: Option<String>, // uknown type 'string::json', mapping to String by default
+}
+
+// `rssd_metadata` table
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct RssdMetadata {
+    key: String, // PRIMARY KEY ('string' maps directly to Rust type)
+    value: String, // 'string' maps directly to Rust type
+    elaboration: Option<String>, // uknown type 'string::json', mapping to String by default
+}
+
+// `query_snapshot` table
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct QuerySnapshot {
+    query_snapshot_id: String, // PRIMARY KEY ('string' maps directly to Rust type)
+    name: String, // 'string' maps directly to Rust type
+    query_sql: String, // 'string' maps directly to Rust type
+    result_schema: String, // uknown type 'string::json', mapping to String by default
+    result_rows: String, // uknown type 'string::json', mapping to String by default
+    row_count: i64, // 'integer' maps directly to Rust type
+    content_digest: String, // 'string' maps directly to Rust type
+    elaboration: Option<String>, // uknown type 'string::json', mapping to String by default
+}
+
+// `surveilr_audit` table
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SurveilrAudit {
+    surveilr_audit_id: String, // PRIMARY KEY ('string' maps directly to Rust type)
+    command: String, // 'string' maps directly to Rust type
+    argv: String, // uknown type 'string::json', mapping to String by default
+    os_user: String, // 'string' maps directly to Rust type
+    host: String, // 'string' maps directly to Rust type
+    started_at: String, // uknown type 'TIMESTAMPTZ', mapping to String by default
+    outcome: String, // 'string' maps directly to Rust type
+    error: Option<String>, // 'string' maps directly to Rust type
+    elaboration: Option<String>, // uknown type 'string::json', mapping to String by default
+}
+
+// `policy_finding` table
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct PolicyFinding {
+    policy_finding_id: String, // PRIMARY KEY ('string' maps directly to Rust type)
+    pack: String, // 'string' maps directly to Rust type
+    assertion: String, // 'string' maps directly to Rust type
+    severity: String, // 'string' maps directly to Rust type
+    state: String, // 'string' maps directly to Rust type
+    status: String, // 'string' maps directly to Rust type
+    row_count: i64, // 'integer' maps directly to Rust type
+    sample_rows: Option<String>, // uknown type 'string::json', mapping to String by default
+    remediation: Option<String>, // 'string' maps directly to Rust type
+    error: Option<String>, // 'string' maps directly to Rust type
+    last_seen_at: String, // uknown type 'TIMESTAMPTZ', mapping to String by default
+    acknowledged_at: Option<String>, // uknown type 'TIMESTAMPTZ', mapping to String by default
+    acknowledged_by: Option<String>, // 'string' maps directly to Rust type
+    resolved_at: Option<String>, // uknown type 'TIMESTAMPTZ', mapping to String by default
+    elaboration: Option<String>, // uknown type 'string::json', mapping to String by default
+}
+
+// `policy_alert_rule` table
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct PolicyAlertRule {
+    policy_alert_rule_id: String, // PRIMARY KEY ('string' maps directly to Rust type)
+    name: String, // 'string' maps directly to Rust type
+    min_severity: String, // 'string' maps directly to Rust type
+    fire_on: String, // 'string' maps directly to Rust type
+    channel: String, // 'string' maps directly to Rust type
+    target: String, // 'string' maps directly to Rust type
+    packs: Option<String>, // uknown type 'string::json', mapping to String by default
+    elaboration: Option<String>, // uknown type 'string::json', mapping to String by default
+}
+
+// `compliance_control` table
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ComplianceControl {
+    compliance_control_id: String, // PRIMARY KEY ('string' maps directly to Rust type)
+    framework: String, // 'string' maps directly to Rust type
+    control_code: String, // 'string' maps directly to Rust type
+    title: Option<String>, // 'string' maps directly to Rust type
+    description: Option<String>, // 'string' maps directly to Rust type
+    elaboration: Option<String>, // uknown type 'string::json', mapping to String by default
+}
+
+// `compliance_evidence_map` table
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ComplianceEvidenceMap {
+    compliance_evidence_map_id: String, // PRIMARY KEY ('string' maps directly to Rust type)
+    compliance_control_id: String, // 'string' maps directly to Rust type
+    evidence_kind: String, // 'string' maps directly to Rust type
+    evidence_selector: String, // 'string' maps directly to Rust type
+    description: Option<String>, // 'string' maps directly to Rust type
+    elaboration: Option<String>, // uknown type 'string::json', mapping to String by default
+}
+
+// `known_file_hash` table
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct KnownFileHash {
+    known_file_hash_id: String, // PRIMARY KEY ('string' maps directly to Rust type)
+    hash_set: String, // 'string' maps directly to Rust type
+    algorithm: String, // 'string' maps directly to Rust type
+    digest: String, // 'string' maps directly to Rust type
+    disposition: String, // 'string' maps directly to Rust type
+    confidence: f64, // 'float' maps directly to Rust type
+    file_name: Option<String>, // 'string' maps directly to Rust type
+    elaboration: Option<String>, // uknown type 'string::json', mapping to String by default
+}
+
+// `uniform_resource_known_file` table
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct UniformResourceKnownFile {
+    uniform_resource_known_file_id: String, // PRIMARY KEY ('string' maps directly to Rust type)
+    uniform_resource_id: String, // 'string' maps directly to Rust type
+    known_file_hash_id: String, // 'string' maps directly to Rust type
+    hash_set: String, // 'string' maps directly to Rust type
+    disposition: String, // 'string' maps directly to Rust type
+    confidence: f64, // 'float' maps directly to Rust type
+    elaboration: Option<String>, // uknown type 'string::json', mapping to String by default
+}
+
+// `ur_ingest_session_git_repo` table
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct UrIngestSessionGitRepo {
+    ur_ingest_session_git_repo_id: String, // PRIMARY KEY ('string' maps directly to Rust type)
+    ingest_session_id: String, // 'string' maps directly to Rust type
+    repo: String, // 'string' maps directly to Rust type
+    reference: String, // 'string' maps directly to Rust type
+    commit_sha: String, // 'string' maps directly to Rust type
+    author_name: String, // 'string' maps directly to Rust type
+    author_email: String, // 'string' maps directly to Rust type
+    authored_at: String, // uknown type 'TIMESTAMPTZ', mapping to String by default
+    committer_name: String, // 'string' maps directly to Rust type
+    committer_email: String, // 'string' maps directly to Rust type
+    committed_at: String, // uknown type 'TIMESTAMPTZ', mapping to String by default
+    subject: String, // 'string' maps directly to Rust type
+    elaboration: Option<String>, // uknown type 'string::json', mapping to String by default
+}
+
+// `surveilr_remote_sync` table
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SurveilrRemoteSync {
+    surveilr_remote_sync_id: String, // PRIMARY KEY ('string' maps directly to Rust type)
+    remote_url: String, // 'string' maps directly to Rust type
+    table_name: String, // 'string' maps directly to Rust type
+    synced_rowid: i64, // 'integer' maps directly to Rust type
+    synced_rows: i64, // 'integer' maps directly to Rust type
+    synced_at: String, // uknown type 'TIMESTAMPTZ', mapping to String by default
+}
+
+// `uniform_resource_equivalence` table
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct UniformResourceEquivalence {
+    uniform_resource_equivalence_id: String, // PRIMARY KEY ('string' maps directly to Rust type)
+    content_digest: String, // 'string' maps directly to Rust type
+    uniform_resource_id: String, // 'string' maps directly to Rust type
+    device_id: String, // 'string' maps directly to Rust type
+    uri: String, // 'string' maps directly to Rust type
+    device_count: i64, // 'integer' maps directly to Rust type
+}
+
+// `uniform_resource_chunk` table
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct UniformResourceChunk {
+    uniform_resource_chunk_id: String, // PRIMARY KEY ('string' maps directly to Rust type)
+    uniform_resource_id: String, // 'string' maps directly to Rust type
+    chunk_index: i64, // 'integer' maps directly to Rust type
+    content_digest: String, // 'string' maps directly to Rust type
+    size_bytes: i64, // 'integer' maps directly to Rust type
+    content: Vec<u8>, // 'blob' maps directly to Rust type
+}
+
+// `ur_ingest_imap_folder_state` table
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct UrIngestImapFolderState {
+    ur_ingest_imap_folder_state_id: String, // PRIMARY KEY ('string' maps directly to Rust type)
+    email: String, // 'string' maps directly to Rust type
+    folder_name: String, // 'string' maps directly to Rust type
+    uid_validity: i64, // 'integer' maps directly to Rust type
+    last_uid: i64, // 'integer' maps directly to Rust type
+    ingest_session_id: String, // 'string' maps directly to Rust type
+}
+
+// `uniform_resource_verification` table
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct UniformResourceVerification {
+    uniform_resource_verification_id: String, // PRIMARY KEY ('string' maps directly to Rust type)
+    verification_id: String, // 'string' maps directly to Rust type
+    ingest_session_id: String, // 'string' maps directly to Rust type
+    uniform_resource_id: Option<String>, // 'string' maps directly to Rust type
+    uri: String, // 'string' maps directly to Rust type
+    status: String, // 'string' maps directly to Rust type
+    content_digest_algorithm: String, // 'string' maps directly to Rust type
+    expected_digest: Option<String>, // 'string' maps directly to Rust type
+    actual_digest: Option<String>, // 'string' maps directly to Rust type
+    elaboration: Option<String>, // uknown type 'string::json', mapping to String by default
 }
 
 // `assurance_schema` table
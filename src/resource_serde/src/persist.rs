@@ -4,6 +4,7 @@ use std::path::Path;
 
 use anyhow::{anyhow, Context, Result};
 use autometrics::autometrics;
+use base64::{engine::general_purpose::STANDARD, Engine};
 use comfy_table::*;
 use globset::Glob;
 use is_executable::IsExecutable;
@@ -12,6 +13,7 @@ use opentelemetry::KeyValue;
 // adds path.is_executable
 use rusqlite::functions::FunctionFlags;
 use rusqlite::{types::ValueRef, Connection, Result as RusqliteResult, ToSql};
+use serde::Serialize;
 use serde_json::{json, Value as JsonValue};
 use tracing::{debug, error, info};
 use ulid::Ulid;
@@ -21,11 +23,23 @@ extern crate globwalk;
 use common::{execute_sql, execute_sql_batch, query_sql_rows_no_args, query_sql_single};
 
 use common::device::Device;
+pub use common::sqlite_helpers::{set_busy_timeout, DEFAULT_BUSY_TIMEOUT_MS};
 use resource::*;
 
 #[autometrics]
 pub fn prepare_conn(db: &Connection) -> RusqliteResult<()> {
-    declare_ulid_function(db)
+    declare_ulid_function(db)?;
+    // RSSDs which haven't been initialized yet get their key recorded by `init`
+    let credential_key = recorded_credential_digest_key(db)
+        .ok()
+        .flatten()
+        .unwrap_or_else(common::secret::new_credential_digest_key);
+    declare_credential_digest_function(db, credential_key)?;
+    declare_device_clock_function(db)?;
+    crate::walk_vtab::declare_walk_function(db)?;
+    // RSSDs which predate `rssd_metadata` (or are brand new) use the default
+    let strategy = recorded_pk_strategy(db).ok().flatten().unwrap_or_default();
+    declare_pk_function(db, strategy)
 }
 
 #[autometrics]
@@ -36,6 +50,178 @@ pub fn declare_ulid_function(db: &Connection) -> RusqliteResult<()> {
     })
 }
 
+/// Register `surveilr_credential_digest(text)`, used to store (and migrate
+/// existing) credentials as digests instead of plaintext. `key` is the RSSD's
+/// credential digest key, see `establish_credential_digest_key`.
+#[autometrics]
+pub fn declare_credential_digest_function(db: &Connection, key: Vec<u8>) -> RusqliteResult<()> {
+    db.create_scalar_function(
+        "surveilr_credential_digest",
+        1,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        move |ctx| {
+            assert_eq!(ctx.len(), 1, "called with unexpected number of arguments");
+            let value: Option<String> = ctx.get(0)?;
+            Ok(value.map(|v| common::secret::credential_digest(&key, &v)))
+        },
+    )
+}
+
+pub const CREDENTIAL_DIGEST_KEY_METADATA_KEY: &str = "credential_digest_key";
+
+/// The credential digest key recorded in `rssd_metadata` (base64), `None` if it
+/// was never recorded.
+pub fn recorded_credential_digest_key(db: &Connection) -> Result<Option<Vec<u8>>> {
+    let value: Option<String> = match db.query_row(
+        "SELECT value FROM rssd_metadata WHERE key = ?",
+        [CREDENTIAL_DIGEST_KEY_METADATA_KEY],
+        |row| row.get(0),
+    ) {
+        Ok(value) => Some(value),
+        Err(rusqlite::Error::QueryReturnedNoRows) => None,
+        Err(err) => return Err(err.into()),
+    };
+    value
+        .map(|v| {
+            STANDARD
+                .decode(v)
+                .with_context(|| "[recorded_credential_digest_key] invalid key in rssd_metadata")
+        })
+        .transpose()
+}
+
+/// Record `key` as the RSSD's credential digest key the first time it is
+/// initialized. The key never changes afterwards so digests of the same credential
+/// stay comparable across sessions; it stays in the RSSD so whoever can read the
+/// digests can also read the key, but it keeps precomputed tables from applying
+/// to every RSSD at once.
+pub fn establish_credential_digest_key(db: &Connection, key: &[u8]) -> Result<()> {
+    if recorded_credential_digest_key(db)?.is_none() {
+        db.execute(
+            "INSERT INTO rssd_metadata (key, value) VALUES (?, ?)",
+            [CREDENTIAL_DIGEST_KEY_METADATA_KEY, &STANDARD.encode(key)],
+        )?;
+    }
+    Ok(())
+}
+
+/// Register `surveilr_device_clock()`, the device's time zone and clock state
+/// (JSON) which every ingest session records.
+#[autometrics]
+pub fn declare_device_clock_function(db: &Connection) -> RusqliteResult<()> {
+    db.create_scalar_function(
+        "surveilr_device_clock",
+        0,
+        FunctionFlags::SQLITE_UTF8,
+        move |ctx| {
+            assert_eq!(ctx.len(), 0, "called with unexpected number of arguments");
+            serde_json::to_string(&common::clock::DeviceClock::observe())
+                .map_err(|err| rusqlite::Error::UserFunctionError(err.into()))
+        },
+    )
+}
+
+pub const PK_STRATEGY_METADATA_KEY: &str = "primary_key_strategy";
+
+/// How primary keys are generated for rows inserted by surveilr. The strategy is
+/// chosen when an RSSD is initialized, recorded in `rssd_metadata` and used by the
+/// `surveilr_pk()` SQL function for the lifetime of the RSSD. Both strategies are
+/// time-ordered with enough randomness (80 bits for ULID, 74 bits for UUIDv7) that
+/// devices generating keys offline will not collide when their RSSDs are merged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, clap::ValueEnum)]
+pub enum PrimaryKeyStrategy {
+    #[default]
+    Ulid,
+    #[value(name = "uuidv7")]
+    #[serde(rename = "uuidv7")]
+    UuidV7,
+}
+
+impl PrimaryKeyStrategy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PrimaryKeyStrategy::Ulid => "ulid",
+            PrimaryKeyStrategy::UuidV7 => "uuidv7",
+        }
+    }
+
+    pub fn generate(&self) -> String {
+        match self {
+            PrimaryKeyStrategy::Ulid => Ulid::new().to_string(),
+            PrimaryKeyStrategy::UuidV7 => uuid::Uuid::now_v7().to_string(),
+        }
+    }
+}
+
+impl std::str::FromStr for PrimaryKeyStrategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "ulid" => Ok(PrimaryKeyStrategy::Ulid),
+            "uuidv7" => Ok(PrimaryKeyStrategy::UuidV7),
+            _ => Err(anyhow!("unknown primary key strategy '{}'", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for PrimaryKeyStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Register `surveilr_pk()`, which all of surveilr's INSERT SQL uses for primary keys.
+#[autometrics]
+pub fn declare_pk_function(db: &Connection, strategy: PrimaryKeyStrategy) -> RusqliteResult<()> {
+    db.create_scalar_function("surveilr_pk", 0, FunctionFlags::SQLITE_UTF8, move |ctx| {
+        assert_eq!(ctx.len(), 0, "called with unexpected number of arguments");
+        Ok(strategy.generate())
+    })
+}
+
+/// The strategy recorded in `rssd_metadata`, `None` if it was never recorded.
+pub fn recorded_pk_strategy(db: &Connection) -> Result<Option<PrimaryKeyStrategy>> {
+    let value: Option<String> = match db.query_row(
+        "SELECT value FROM rssd_metadata WHERE key = ?",
+        [PK_STRATEGY_METADATA_KEY],
+        |row| row.get(0),
+    ) {
+        Ok(value) => Some(value),
+        Err(rusqlite::Error::QueryReturnedNoRows) => None,
+        Err(err) => return Err(err.into()),
+    };
+    value.map(|v| v.parse()).transpose()
+}
+
+/// Record `requested` (or the default) the first time an RSSD is initialized and
+/// reject attempts to switch strategies afterwards, then register `surveilr_pk()`.
+pub fn establish_pk_strategy(
+    db: &Connection,
+    requested: Option<PrimaryKeyStrategy>,
+) -> Result<PrimaryKeyStrategy> {
+    let strategy = match (recorded_pk_strategy(db)?, requested) {
+        (Some(recorded), Some(requested)) if recorded != requested => {
+            return Err(anyhow!(
+                "RSSD was initialized with the '{}' primary key strategy and cannot be switched to '{}'",
+                recorded,
+                requested
+            ))
+        }
+        (Some(recorded), _) => recorded,
+        (None, requested) => {
+            let strategy = requested.unwrap_or_default();
+            db.execute(
+                "INSERT INTO rssd_metadata (key, value) VALUES (?, ?)",
+                [PK_STRATEGY_METADATA_KEY, strategy.as_str()],
+            )?;
+            strategy
+        }
+    };
+    declare_pk_function(db, strategy)?;
+    Ok(strategy)
+}
+
 #[derive(Debug)]
 pub struct DbConn {
     pub db_fs_path: String,
@@ -54,6 +240,8 @@ impl DbConn {
 
         let conn = Connection::open(&db_fs_path)
             .with_context(|| format!("[DbConn::new] SQLite database {}", db_path))?;
+        set_busy_timeout(&conn)
+            .with_context(|| format!("[DbConn::new] busy timeout for {}", db_path))?;
         prepare_conn(&conn)
             .with_context(|| format!("[DbConn::new] prepare SQLite connection for {}", db_path))?;
 
@@ -66,15 +254,23 @@ impl DbConn {
         })
     }
 
-    // open an existing database and error out if it doesn't exist
+    // open an existing database read-only and error out if it doesn't exist, this
+    // is what `--read-only` commands use so they never migrate or lock the RSSD
     #[autometrics]
     pub fn open<P: AsRef<Path>>(db_fs_path: P, vebose_level: u8) -> Result<DbConn> {
         let db_path = db_fs_path
             .as_ref()
             .to_str()
             .ok_or_else(|| anyhow!("Failed to convert database path to string"))?;
-        let conn =
-            Connection::open_with_flags(&db_fs_path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let conn = Connection::open_with_flags(
+            &db_fs_path,
+            rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY | rusqlite::OpenFlags::SQLITE_OPEN_URI,
+        )
+        .with_context(|| format!("[DbConn::open] SQLite database {}", db_path))?;
+        set_busy_timeout(&conn)
+            .with_context(|| format!("[DbConn::open] busy timeout for {}", db_path))?;
+        prepare_conn(&conn)
+            .with_context(|| format!("[DbConn::open] prepare SQLite connection for {}", db_path))?;
         Ok(DbConn {
             db_fs_path: db_path.to_string(),
             conn,
@@ -84,15 +280,58 @@ impl DbConn {
 
     #[autometrics]
     pub fn init(&mut self, db_init_sql: Option<&[String]>) -> Result<rusqlite::Transaction> {
-        // putting everything inside a transaction improves performance significantly
+        self.init_with_pk_strategy(db_init_sql, None)
+    }
+
+    /// Same as `init` but, for a new RSSD, records `pk_strategy` as the strategy
+    /// used to generate primary keys (an existing RSSD must match it).
+    #[autometrics]
+    pub fn init_with_pk_strategy(
+        &mut self,
+        db_init_sql: Option<&[String]>,
+        pk_strategy: Option<PrimaryKeyStrategy>,
+    ) -> Result<rusqlite::Transaction> {
+        // putting everything inside a transaction improves performance significantly;
+        // IMMEDIATE takes the write lock up front so a concurrent writer makes us wait
+        // (busy timeout) rather than fail when a read transaction is upgraded
         let tx = self
             .conn
-            .transaction()
+            .transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)
             .with_context(|| format!("[DbConn::new] SQLite transaction in {}", self.db_fs_path))?;
 
+        // bootstrap.sql generates its primary keys with surveilr_pk() so a new RSSD
+        // must use the requested strategy before rssd_metadata exists to record it
+        if let Some(requested) = pk_strategy {
+            let strategy = recorded_pk_strategy(&tx)
+                .ok()
+                .flatten()
+                .unwrap_or(requested);
+            declare_pk_function(&tx, strategy).with_context(|| {
+                format!("[DbConn::new] primary key strategy in {}", self.db_fs_path)
+            })?;
+        }
+
+        // migrations digest existing credentials so the key they use must be the
+        // one recorded afterwards
+        let credential_key = recorded_credential_digest_key(&tx)
+            .ok()
+            .flatten()
+            .unwrap_or_else(common::secret::new_credential_digest_key);
+        declare_credential_digest_function(&tx, credential_key.clone()).with_context(|| {
+            format!("[DbConn::new] credential digest key in {}", self.db_fs_path)
+        })?;
+
         execute_migrations(&tx, "ingest")
             .with_context(|| format!("[DbConn::new] execute_migrations in {}", self.db_fs_path))?;
 
+        establish_credential_digest_key(&tx, &credential_key).with_context(|| {
+            format!("[DbConn::new] credential digest key in {}", self.db_fs_path)
+        })?;
+
+        establish_pk_strategy(&tx, pk_strategy).with_context(|| {
+            format!("[DbConn::new] primary key strategy in {}", self.db_fs_path)
+        })?;
+
         if let Some(state_db_init_sql) = db_init_sql {
             // TODO: add the executed files into the behaviors or other activity log!?
             execute_globs_batch(
@@ -198,6 +437,49 @@ impl DbConn {
     }
 }
 
+/// What `-d` is set to for an RSSD which only lives in memory.
+pub const IN_MEMORY_STATE_DB: &str = ":memory:";
+
+/// An RSSD kept in memory for the lifetime of the process, for ephemeral CI
+/// checks which only persist the database when they find something. Each
+/// `DbConn` opened on `uri` shares the same database (plain `:memory:` would
+/// give every connection its own empty one) as long as this value is alive.
+pub struct InMemoryStateDb {
+    pub uri: String,
+    keeper: Connection,
+}
+
+impl InMemoryStateDb {
+    pub fn new() -> Result<InMemoryStateDb> {
+        let uri = format!(
+            "file:surveilr-{}-{}?mode=memory&cache=shared",
+            std::process::id(),
+            Ulid::new()
+        );
+        let keeper = Connection::open(&uri)
+            .with_context(|| format!("[InMemoryStateDb::new] SQLite database {}", uri))?;
+        Ok(InMemoryStateDb { uri, keeper })
+    }
+
+    /// Whether `sql` returns at least one row, used to decide if there are findings.
+    pub fn has_rows(&self, sql: &str) -> Result<bool> {
+        let mut stmt = self
+            .keeper
+            .prepare(sql)
+            .with_context(|| format!("[InMemoryStateDb::has_rows] preparing {}", sql))?;
+        let mut rows = stmt.query([])?;
+        Ok(rows.next()?.is_some())
+    }
+
+    /// Write the database to a file with `VACUUM INTO`, `fs_path` must not exist.
+    pub fn export(&self, fs_path: &str) -> Result<()> {
+        self.keeper
+            .execute("VACUUM INTO ?", [fs_path])
+            .with_context(|| format!("[InMemoryStateDb::export] exporting to {}", fs_path))?;
+        Ok(())
+    }
+}
+
 execute_sql_batch!(bootstrap_ddl, include_str!("bootstrap.sql"));
 
 query_sql_single!(
@@ -229,7 +511,7 @@ query_sql_single!(
 execute_sql!(
     insert_notebook_cell_state,
     r"INSERT INTO code_notebook_state (code_notebook_state_id, code_notebook_cell_id, from_state, to_state, transition_reason)
-                               VALUES (ulid(), (SELECT code_notebook_cell_id FROM code_notebook_cell WHERE notebook_name = ?1 AND cell_name = ?2), ?3, ?4, ?5)",
+                               VALUES (surveilr_pk(), (SELECT code_notebook_cell_id FROM code_notebook_cell WHERE notebook_name = ?1 AND cell_name = ?2), ?3, ?4, ?5)",
     notebook_name: &str,
     cell_name: &str,
     from_state: &str,
@@ -237,6 +519,15 @@ execute_sql!(
     transition_reason: &str
 );
 
+execute_sql!(
+    upsert_sqlpage_file,
+    r"INSERT INTO sqlpage_files (path, contents, last_modified)
+                       VALUES (?1, ?2, CURRENT_TIMESTAMP)
+       ON CONFLICT(path) DO UPDATE SET contents = EXCLUDED.contents, last_modified = CURRENT_TIMESTAMP",
+    path: &str,
+    contents: &str
+);
+
 // Executes a query to select the most recently inserted cells for each all
 // rows in ConstructionSqlNotebook. Code notebook cells are unique for
 // notebook_name, cell_name and interpretable_code_hash which means there may
@@ -346,10 +637,10 @@ query_sql_rows_no_args!(
     transitioned_at: String
 );
 
-// ulid() is not built into SQLite, be sure to register it with prepare_conn
+// surveilr_pk() is not built into SQLite, be sure to register it with prepare_conn
 query_sql_single!(
     upsert_device,
-    r"INSERT INTO device (device_id, name, boundary, state, state_sysinfo) VALUES (ulid(), ?, ?, ?, ?)
+    r"INSERT INTO device (device_id, name, boundary, state, state_sysinfo) VALUES (surveilr_pk(), ?, ?, ?, ?)
       ON CONFLICT(name, state, boundary) DO UPDATE SET updated_at = CURRENT_TIMESTAMP
       RETURNING device_id, name",
     name: &str,
@@ -381,15 +672,15 @@ query_sql_single!(
 ///
 /// # Examples
 ///
-/// ```
+/// ```no_run
 /// # use rusqlite::{Connection, Result as SqliteResult};
 /// # use std::vec::Vec;
-/// use surveilr_static::persist::prepare_conn;
-/// use surveilr_static::persist::select_notebooks_and_cells;
+/// use resource_serde::persist::prepare_conn;
+/// use resource_serde::persist::select_notebooks_and_cells;
 ///
 /// # fn main() -> SqliteResult<()> {
 /// let conn = Connection::open("code_notebooks.db")?;
-/// prepare_conn(&conn)?; // make sure to register custom functions like ulid()
+/// prepare_conn(&conn)?; // make sure to register custom functions like surveilr_pk()
 /// let notebooks = vec!["Notebook1".to_string(), "Notebook2".to_string()];
 /// let cells = vec!["CellA".to_string(), "CellB".to_string()];
 /// let results = select_notebooks_and_cells(&conn, &notebooks, &cells)?;
@@ -469,7 +760,7 @@ pub fn select_notebooks_and_cells(
     results
 }
 
-/**
+/*
  * IMPORTANT TODO: ensure all high performance loops are wrapped in prepare
  * statements along with BEGIN/END transactions in batches (prepared stmts
  * are faster than non-prepared, prepared stmts inside transactions are
@@ -824,6 +1115,128 @@ mod tests {
         assert!(!result.unwrap().is_empty());
     }
 
+    #[test]
+    fn test_imap_passwords_migrated_to_digests() -> anyhow::Result<()> {
+        let mut dbc = DbConn::new(":memory:", 0)?;
+        let tx = dbc.init(None)?;
+        let (device_id, _) = upserted_device(&tx, &common::DEVICE)?;
+        tx.execute(
+            "INSERT INTO ur_ingest_session (ur_ingest_session_id, device_id, behavior_json, ingest_started_at)
+             VALUES ('session', ?, '{}', CURRENT_TIMESTAMP)",
+            [device_id],
+        )?;
+        // simulate a row stored before passwords were redacted
+        tx.execute(
+            "INSERT INTO ur_ingest_session_imap_account (ur_ingest_session_imap_account_id, ingest_session_id, email, password)
+             VALUES ('acct', 'session', 'user@example.com', 'hunter2')",
+            [],
+        )?;
+        tx.execute(
+            "DELETE FROM code_notebook_state WHERE code_notebook_cell_id IN
+               (SELECT code_notebook_cell_id FROM code_notebook_cell WHERE cell_name = 'v006_once_redactImapAccountPasswords')",
+            [],
+        )?;
+        execute_migrations(&tx, "test_imap_passwords_migrated_to_digests")?;
+
+        let password: String = tx.query_row(
+            "SELECT password FROM ur_ingest_session_imap_account WHERE ur_ingest_session_imap_account_id = 'acct'",
+            [],
+            |row| row.get(0),
+        )?;
+        let key = recorded_credential_digest_key(&tx)?.expect("key recorded by init");
+        assert_eq!(password, common::secret::credential_digest(&key, "hunter2"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_credential_digest_key_recorded_once() -> anyhow::Result<()> {
+        let mut dbc = DbConn::new(":memory:", 0)?;
+        let first = {
+            let tx = dbc.init(None)?;
+            let digest: String =
+                tx.query_row("SELECT surveilr_credential_digest('hunter2')", [], |row| {
+                    row.get(0)
+                })?;
+            tx.commit()?;
+            digest
+        };
+        let key = recorded_credential_digest_key(&dbc.conn)?.expect("key recorded by init");
+        assert_eq!(first, common::secret::credential_digest(&key, "hunter2"));
+
+        // reopening keeps the key so digests stay comparable
+        let tx = dbc.init(None)?;
+        assert_eq!(recorded_credential_digest_key(&tx)?, Some(key));
+        let again: String =
+            tx.query_row("SELECT surveilr_credential_digest('hunter2')", [], |row| {
+                row.get(0)
+            })?;
+        assert_eq!(again, first);
+
+        // another RSSD has its own key
+        let mut other = DbConn::new(":memory:", 0)?;
+        let tx = other.init(None)?;
+        let other_digest: String =
+            tx.query_row("SELECT surveilr_credential_digest('hunter2')", [], |row| {
+                row.get(0)
+            })?;
+        assert_ne!(other_digest, first);
+        Ok(())
+    }
+
+    #[test]
+    fn test_new_rssd_migrations_executed() -> anyhow::Result<()> {
+        let mut dbc = DbConn::new(":memory:", 0)?;
+        let tx = dbc.init(None)?;
+        // failed `once_` migrations aren't recorded as executed either
+        let mut pending = vec![];
+        migratable_notebook_cells_not_executed(&tx, |_, _, cell_name, _, _, _| {
+            if cell_name.contains("_once_") {
+                pending.push(cell_name);
+            }
+            Ok(())
+        })?;
+        assert_eq!(pending, Vec::<String>::new());
+
+        // from v001_once_initialDDL rather than v026_once_contentDigestAlgorithmDDL
+        let default: String = tx.query_row(
+            "SELECT dflt_value FROM pragma_table_info('uniform_resource') WHERE name = 'content_digest_algorithm'",
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(default, "'sha1'");
+        Ok(())
+    }
+
+    #[test]
+    fn test_pk_strategy_recorded_once() -> anyhow::Result<()> {
+        let mut dbc = DbConn::new(":memory:", 0)?;
+        {
+            let tx = dbc.init_with_pk_strategy(None, Some(PrimaryKeyStrategy::UuidV7))?;
+            let pk: String = tx.query_row("SELECT surveilr_pk()", [], |row| row.get(0))?;
+            assert!(uuid::Uuid::parse_str(&pk).is_ok_and(|u| u.get_version_num() == 7));
+
+            // rows seeded by bootstrap.sql use the strategy too
+            let cell_id: String = tx.query_row(
+                "SELECT code_notebook_cell_id FROM code_notebook_cell LIMIT 1",
+                [],
+                |row| row.get(0),
+            )?;
+            assert!(uuid::Uuid::parse_str(&cell_id).is_ok_and(|u| u.get_version_num() == 7));
+            tx.commit()?;
+        }
+        assert_eq!(
+            recorded_pk_strategy(&dbc.conn)?,
+            Some(PrimaryKeyStrategy::UuidV7)
+        );
+
+        // reopening without a preference keeps the recorded strategy
+        assert!(dbc.init(None).is_ok());
+        assert!(dbc
+            .init_with_pk_strategy(None, Some(PrimaryKeyStrategy::Ulid))
+            .is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_dbconn_new() {
         let db_fs_path = ":memory:";
@@ -833,6 +1246,81 @@ mod tests {
         assert_eq!(conn.db_fs_path, db_fs_path);
     }
 
+    #[test]
+    fn test_concurrent_writers_wait_and_read_only_readers() -> anyhow::Result<()> {
+        let mut db_path = std::env::current_dir()?;
+        db_path.push("test_concurrent_writers_wait_and_read_only_readers.db");
+        let _ = fs::remove_file(&db_path);
+        DbConn::new(&db_path, 0)?.init(None)?.commit()?;
+
+        let writer_path = db_path.clone();
+        let (locked, wait_for_lock) = std::sync::mpsc::channel();
+        let writer = std::thread::spawn(move || -> anyhow::Result<()> {
+            let mut dbc = DbConn::new(&writer_path, 0)?;
+            let tx = dbc.init(None)?;
+            locked.send(())?;
+            std::thread::sleep(std::time::Duration::from_millis(300));
+            tx.commit()?;
+            Ok(())
+        });
+        wait_for_lock.recv()?;
+
+        // readers aren't blocked by the writer and can't write themselves
+        let reader = DbConn::open(&db_path, 0)?;
+        let count: i64 =
+            reader
+                .conn
+                .query_row("SELECT COUNT(*) FROM code_notebook_cell", [], |row| {
+                    row.get(0)
+                })?;
+        assert!(count >= 1);
+        assert!(reader
+            .conn
+            .execute("DELETE FROM code_notebook_cell", [])
+            .is_err());
+
+        // a second writer waits for the lock instead of failing with `database is locked`
+        DbConn::new(&db_path, 0)?.init(None)?.commit()?;
+        writer.join().unwrap()?;
+
+        fs::remove_file(db_path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_in_memory_state_db_is_shared_and_exported() -> anyhow::Result<()> {
+        let state_db = InMemoryStateDb::new()?;
+        DbConn::new(&state_db.uri, 0)?.init(None)?.commit()?;
+        let findings = "SELECT 1 FROM ur_ingest_session";
+        assert!(!state_db.has_rows(findings)?);
+
+        let mut dbc = DbConn::new(&state_db.uri, 0)?;
+        let tx = dbc.init(None)?;
+        let (device_id, _) = upserted_device(&tx, &common::DEVICE)?;
+        tx.execute(
+            "INSERT INTO ur_ingest_session (ur_ingest_session_id, device_id, behavior_json, ingest_started_at)
+             VALUES ('session', ?, '{}', CURRENT_TIMESTAMP)",
+            [&device_id],
+        )?;
+        tx.commit()?;
+        assert!(state_db.has_rows(findings)?);
+
+        let mut export_path = std::env::current_dir()?;
+        export_path.push("test_in_memory_state_db_export.db");
+        state_db.export(export_path.to_str().unwrap())?;
+        let exported = DbConn::open(&export_path, 0)?;
+        let sessions: i64 =
+            exported
+                .conn
+                .query_row("SELECT COUNT(*) FROM ur_ingest_session", [], |row| {
+                    row.get(0)
+                })?;
+        assert_eq!(sessions, 1);
+
+        fs::remove_file(export_path)?;
+        Ok(())
+    }
+
     #[test]
     fn test_query_result_as_formatted_table() -> anyhow::Result<()> {
         let mut db_path = std::env::current_dir()?;
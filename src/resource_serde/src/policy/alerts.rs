@@ -0,0 +1,480 @@
+use std::fmt;
+
+use anyhow::{anyhow, Context, Result};
+use clap::ValueEnum;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::{
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::TcpStream,
+};
+
+use super::findings::{FindingState, RecordedFinding};
+use super::{FindingStatus, PolicySeverity};
+
+const UPSERT_ALERT_RULE_SQL: &str = "INSERT INTO policy_alert_rule (policy_alert_rule_id, name, min_severity, fire_on, channel, target, packs)
+       VALUES (surveilr_pk(), ?, ?, ?, ?, ?, ?)
+  ON CONFLICT (name) DO UPDATE SET
+       min_severity = EXCLUDED.min_severity,
+       fire_on = EXCLUDED.fire_on,
+       channel = EXCLUDED.channel,
+       target = EXCLUDED.target,
+       packs = EXCLUDED.packs,
+       updated_at = CURRENT_TIMESTAMP";
+const SEL_ALERT_RULES_SQL: &str =
+    "SELECT name, min_severity, fire_on, channel, target, packs FROM policy_alert_rule ORDER BY name";
+const DEL_ALERT_RULE_SQL: &str = "DELETE FROM policy_alert_rule WHERE name = ?";
+
+/// Which findings of a run an alert rule notifies about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertTrigger {
+    /// findings whose assertion was not failing in the previous run
+    New,
+    /// every failing finding which isn't acknowledged
+    Open,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertChannel {
+    /// POST the findings as JSON to the target URL
+    Webhook,
+    /// mail the findings to the target address through an SMTP relay
+    Email,
+}
+
+impl fmt::Display for AlertTrigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.to_possible_value().unwrap().get_name())
+    }
+}
+
+impl fmt::Display for AlertChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.to_possible_value().unwrap().get_name())
+    }
+}
+
+/// A `policy_alert_rule` row.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AlertRule {
+    pub name: String,
+    pub min_severity: PolicySeverity,
+    pub fire_on: AlertTrigger,
+    pub channel: AlertChannel,
+    /// the webhook URL or email address
+    pub target: String,
+    /// the packs the rule applies to, all packs if empty
+    pub packs: Vec<String>,
+}
+
+impl AlertRule {
+    pub fn matches(&self, recorded: &RecordedFinding) -> bool {
+        let f = &recorded.finding;
+        f.status != FindingStatus::Pass
+            && recorded.state != Some(FindingState::Acknowledged)
+            && f.severity >= self.min_severity
+            && (self.packs.is_empty() || self.packs.contains(&f.pack))
+            && (self.fire_on == AlertTrigger::Open || recorded.new)
+    }
+}
+
+/// Create or replace the alert rule with the same name.
+pub fn store_alert_rule(conn: &Connection, rule: &AlertRule) -> Result<()> {
+    conn.execute(
+        UPSERT_ALERT_RULE_SQL,
+        params![
+            rule.name,
+            rule.min_severity.to_string(),
+            rule.fire_on.to_string(),
+            rule.channel.to_string(),
+            rule.target,
+            if rule.packs.is_empty() {
+                None
+            } else {
+                Some(serde_json::to_string(&rule.packs)?)
+            }
+        ],
+    )
+    .with_context(|| format!("[policy::store_alert_rule] {}", rule.name))?;
+    Ok(())
+}
+
+/// Remove the named alert rule, `false` if there is none.
+pub fn remove_alert_rule(conn: &Connection, name: &str) -> Result<bool> {
+    Ok(conn
+        .execute(DEL_ALERT_RULE_SQL, [name])
+        .with_context(|| format!("[policy::remove_alert_rule] {}", name))?
+        > 0)
+}
+
+pub fn alert_rules(conn: &Connection) -> Result<Vec<AlertRule>> {
+    let mut stmt = conn.prepare(SEL_ALERT_RULES_SQL)?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, String>(4)?,
+                row.get::<_, Option<String>>(5)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .with_context(|| "[policy::alert_rules] policy_alert_rule")?;
+    rows.into_iter()
+        .map(|(name, min_severity, fire_on, channel, target, packs)| {
+            let invalid = |column: &str, value: &str| {
+                anyhow!(
+                    "[policy::alert_rules] invalid {} '{}' of alert rule {}",
+                    column,
+                    value,
+                    name
+                )
+            };
+            Ok(AlertRule {
+                min_severity: PolicySeverity::from_str(&min_severity, true)
+                    .map_err(|_| invalid("min_severity", &min_severity))?,
+                fire_on: AlertTrigger::from_str(&fire_on, true)
+                    .map_err(|_| invalid("fire_on", &fire_on))?,
+                channel: AlertChannel::from_str(&channel, true)
+                    .map_err(|_| invalid("channel", &channel))?,
+                packs: match packs {
+                    Some(packs) => {
+                        serde_json::from_str(&packs).map_err(|_| invalid("packs", &packs))?
+                    }
+                    None => vec![],
+                },
+                name,
+                target,
+            })
+        })
+        .collect()
+}
+
+/// The SMTP server email alerts are relayed through (without TLS or
+/// authentication, e.g. a local MTA).
+#[derive(Debug, Clone)]
+pub struct SmtpRelay {
+    pub server: String,
+    pub from: String,
+}
+
+/// The outcome of notifying one alert rule's findings.
+#[derive(Debug, Clone, Serialize)]
+pub struct AlertDelivery {
+    pub rule: String,
+    pub channel: AlertChannel,
+    pub target: String,
+    pub findings: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Notify each rule's matching findings; rules without matching findings
+/// aren't notified and failed deliveries are reported rather than returned
+/// as errors so one unreachable target doesn't stop the others.
+pub async fn send_alerts(
+    rules: &[AlertRule],
+    recorded: &[RecordedFinding],
+    state_db_fs_path: &str,
+    smtp: Option<&SmtpRelay>,
+) -> Vec<AlertDelivery> {
+    let http = reqwest::Client::new();
+    let mut deliveries = vec![];
+    for rule in rules {
+        let findings: Vec<&RecordedFinding> = recorded.iter().filter(|f| rule.matches(f)).collect();
+        if findings.is_empty() {
+            continue;
+        }
+        let sent = match rule.channel {
+            AlertChannel::Webhook => send_webhook(&http, rule, &findings, state_db_fs_path).await,
+            AlertChannel::Email => match smtp {
+                Some(smtp) => send_email(smtp, rule, &findings, state_db_fs_path).await,
+                None => Err(anyhow!("no SMTP server to send the email through")),
+            },
+        };
+        deliveries.push(AlertDelivery {
+            rule: rule.name.clone(),
+            channel: rule.channel,
+            target: rule.target.clone(),
+            findings: findings.len(),
+            error: sent.err().map(|err| format!("{:#}", err)),
+        });
+    }
+    deliveries
+}
+
+async fn send_webhook(
+    http: &reqwest::Client,
+    rule: &AlertRule,
+    findings: &[&RecordedFinding],
+    state_db_fs_path: &str,
+) -> Result<()> {
+    let payload = json!({
+        "rule": rule.name,
+        "state_db_fs_path": state_db_fs_path,
+        "findings": findings,
+    });
+    http.post(&rule.target)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(payload.to_string())
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .with_context(|| format!("[policy::send_webhook] {}", rule.target))?;
+    Ok(())
+}
+
+/// Plain text summary of the findings, one paragraph each.
+fn email_body(findings: &[&RecordedFinding], state_db_fs_path: &str) -> String {
+    let mut body = format!("Policy findings in {}:\r\n", state_db_fs_path);
+    for rf in findings {
+        let f = &rf.finding;
+        body.push_str(&format!(
+            "\r\n[{}] {}::{} {} ({} rows){}\r\n",
+            f.severity,
+            f.pack,
+            f.assertion,
+            f.status,
+            f.row_count,
+            if rf.new { ", new" } else { "" }
+        ));
+        if let Some(id) = &rf.policy_finding_id {
+            body.push_str(&format!("  finding: {}\r\n", id));
+        }
+        if let Some(error) = &f.error {
+            body.push_str(&format!("  error: {}\r\n", error));
+        }
+        if let Some(remediation) = &f.remediation {
+            body.push_str(&format!("  remediation: {}\r\n", remediation));
+        }
+    }
+    body
+}
+
+/// Read an SMTP reply (all its lines) and check its code.
+async fn smtp_reply(reader: &mut (impl AsyncBufRead + Unpin), expected: &str) -> Result<()> {
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).await? == 0 {
+            return Err(anyhow!("connection closed, expected {}", expected));
+        }
+        if line.len() < 4 || line.as_bytes()[3] != b'-' {
+            break;
+        }
+    }
+    if !line.starts_with(expected) {
+        return Err(anyhow!("expected {}, got {}", expected, line.trim_end()));
+    }
+    Ok(())
+}
+
+async fn send_email(
+    smtp: &SmtpRelay,
+    rule: &AlertRule,
+    findings: &[&RecordedFinding],
+    state_db_fs_path: &str,
+) -> Result<()> {
+    let context = || format!("[policy::send_email] {} via {}", rule.target, smtp.server);
+    let (read, mut write) = TcpStream::connect(&smtp.server)
+        .await
+        .with_context(context)?
+        .into_split();
+    let mut reader = BufReader::new(read);
+    smtp_reply(&mut reader, "220").await.with_context(context)?;
+
+    let message = format!(
+        "From: <{from}>\r\nTo: <{to}>\r\nSubject: [surveilr] {count} policy findings ({rule})\r\nDate: {date}\r\nMessage-ID: <{id}@surveilr>\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n{body}",
+        from = smtp.from,
+        to = rule.target,
+        count = findings.len(),
+        rule = rule.name,
+        date = chrono::Utc::now().to_rfc2822(),
+        id = ulid::Ulid::new(),
+        body = email_body(findings, state_db_fs_path)
+    );
+    // lines starting with a dot are escaped by doubling it (RFC 5321 4.5.2)
+    let data = message
+        .split("\r\n")
+        .map(|line| match line.starts_with('.') {
+            true => format!(".{}", line),
+            false => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\r\n");
+
+    for (command, expected) in [
+        (format!("EHLO {}", common::DEVICE.name()), "250"),
+        (format!("MAIL FROM:<{}>", smtp.from), "250"),
+        (format!("RCPT TO:<{}>", rule.target), "250"),
+        ("DATA".to_string(), "354"),
+        (format!("{}\r\n.", data), "250"),
+        ("QUIT".to_string(), "221"),
+    ] {
+        write
+            .write_all(format!("{}\r\n", command).as_bytes())
+            .await
+            .with_context(context)?;
+        smtp_reply(&mut reader, expected)
+            .await
+            .with_context(context)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persist::DbConn;
+    use crate::policy::{PolicyExpectation, PolicyFinding};
+    use tokio::net::TcpListener;
+
+    fn recorded(severity: PolicySeverity, new: bool, state: FindingState) -> RecordedFinding {
+        RecordedFinding {
+            policy_finding_id: Some("01FINDING".to_string()),
+            state: Some(state),
+            new,
+            finding: PolicyFinding {
+                pack: "evidence".to_string(),
+                assertion: "no-html".to_string(),
+                description: None,
+                expect: PolicyExpectation::NoRows,
+                severity,
+                status: FindingStatus::Fail,
+                row_count: 1,
+                sample_rows: vec![],
+                remediation: Some(".remove the HTML exports".to_string()),
+                error: None,
+            },
+        }
+    }
+
+    fn rule(fire_on: AlertTrigger, channel: AlertChannel, target: &str) -> AlertRule {
+        AlertRule {
+            name: "high".to_string(),
+            min_severity: PolicySeverity::High,
+            fire_on,
+            channel,
+            target: target.to_string(),
+            packs: vec![],
+        }
+    }
+
+    #[test]
+    fn test_alert_rules() -> Result<()> {
+        let mut dbc = DbConn::new(":memory:", 0)?;
+        dbc.init(None)?.commit()?;
+        let mut high = rule(AlertTrigger::New, AlertChannel::Webhook, "http://hook");
+        store_alert_rule(&dbc.conn, &high)?;
+        high.packs = vec!["evidence".to_string()];
+        high.fire_on = AlertTrigger::Open;
+        store_alert_rule(&dbc.conn, &high)?;
+        assert_eq!(alert_rules(&dbc.conn)?, vec![high.clone()]);
+        assert!(remove_alert_rule(&dbc.conn, "high")?);
+        assert!(!remove_alert_rule(&dbc.conn, "high")?);
+
+        let new = rule(AlertTrigger::New, AlertChannel::Webhook, "");
+        assert!(new.matches(&recorded(PolicySeverity::Critical, true, FindingState::New)));
+        assert!(!new.matches(&recorded(
+            PolicySeverity::Critical,
+            false,
+            FindingState::New
+        )));
+        assert!(!new.matches(&recorded(PolicySeverity::Medium, true, FindingState::New)));
+        assert!(high.matches(&recorded(PolicySeverity::High, false, FindingState::New)));
+        assert!(!high.matches(&recorded(
+            PolicySeverity::High,
+            false,
+            FindingState::Acknowledged
+        )));
+        high.packs = vec!["other".to_string()];
+        assert!(!high.matches(&recorded(PolicySeverity::High, true, FindingState::New)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_email_alert() -> Result<()> {
+        // a relay which accepts everything and hands back the message data
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let server = listener.local_addr()?.to_string();
+        let relay = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await?;
+            let (read, mut write) = stream.into_split();
+            let mut reader = BufReader::new(read);
+            write.write_all(b"220 relay\r\n").await?;
+            let (mut data, mut in_data, mut line) = (String::new(), false, String::new());
+            loop {
+                line.clear();
+                if reader.read_line(&mut line).await? == 0 {
+                    break;
+                }
+                let reply: &[u8] = if in_data {
+                    if line != ".\r\n" {
+                        data.push_str(&line);
+                        continue;
+                    }
+                    in_data = false;
+                    b"250 queued\r\n"
+                } else if line.starts_with("EHLO") {
+                    b"250-relay\r\n250 8BITMIME\r\n"
+                } else if line.starts_with("DATA") {
+                    in_data = true;
+                    b"354 go ahead\r\n"
+                } else if line.starts_with("QUIT") {
+                    write.write_all(b"221 bye\r\n").await?;
+                    break;
+                } else {
+                    b"250 ok\r\n"
+                };
+                write.write_all(reply).await?;
+            }
+            anyhow::Ok(data)
+        });
+
+        let smtp = SmtpRelay {
+            server,
+            from: "surveilr@example.com".to_string(),
+        };
+        let deliveries = send_alerts(
+            &[rule(
+                AlertTrigger::New,
+                AlertChannel::Email,
+                "soc@example.com",
+            )],
+            &[
+                recorded(PolicySeverity::Critical, true, FindingState::New),
+                recorded(PolicySeverity::Critical, false, FindingState::New),
+            ],
+            "rssd.db",
+            Some(&smtp),
+        )
+        .await;
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(deliveries[0].error, None);
+        assert_eq!(deliveries[0].findings, 1);
+
+        let data = relay.await??;
+        assert!(data.contains("Subject: [surveilr] 1 policy findings (high)\r\n"));
+        assert!(data.contains("[critical] evidence::no-html FAIL (1 rows), new\r\n"));
+        assert!(data.contains("  remediation: .remove the HTML exports\r\n"));
+
+        let undeliverable = send_alerts(
+            &[rule(
+                AlertTrigger::New,
+                AlertChannel::Email,
+                "soc@example.com",
+            )],
+            &[recorded(PolicySeverity::Critical, true, FindingState::New)],
+            "rssd.db",
+            None,
+        )
+        .await;
+        assert!(undeliverable[0].error.is_some());
+        Ok(())
+    }
+}
@@ -0,0 +1,284 @@
+use std::fmt;
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use super::{FindingStatus, PolicyFinding};
+
+// a finding is open until its assertion passes again, created_at only has
+// second precision so rowid orders findings opened within the same second
+const SEL_OPEN_FINDING_SQL: &str = "SELECT policy_finding_id, state FROM policy_finding
+  WHERE pack = ? AND assertion = ? AND state IN ('new', 'acknowledged')
+  ORDER BY created_at DESC, rowid DESC
+  LIMIT 1";
+const INS_FINDING_SQL: &str = "INSERT INTO policy_finding (policy_finding_id, pack, assertion, severity, state, status, row_count, sample_rows, remediation, error, last_seen_at)
+       VALUES (surveilr_pk(), ?, ?, ?, 'new', ?, ?, ?, ?, ?, CURRENT_TIMESTAMP)
+    RETURNING policy_finding_id";
+const UPD_OPEN_FINDING_SQL: &str = "UPDATE policy_finding
+    SET severity = ?, status = ?, row_count = ?, sample_rows = ?, remediation = ?, error = ?,
+        last_seen_at = CURRENT_TIMESTAMP, updated_at = CURRENT_TIMESTAMP
+  WHERE policy_finding_id = ?";
+const RESOLVE_FINDINGS_SQL: &str = "UPDATE policy_finding
+    SET state = 'resolved', resolved_at = CURRENT_TIMESTAMP, updated_at = CURRENT_TIMESTAMP
+  WHERE pack = ? AND assertion = ? AND state IN ('new', 'acknowledged')
+  RETURNING policy_finding_id";
+const ACK_FINDING_SQL: &str = "UPDATE policy_finding
+    SET state = 'acknowledged', acknowledged_at = CURRENT_TIMESTAMP, acknowledged_by = ?, updated_at = CURRENT_TIMESTAMP
+  WHERE policy_finding_id = ? AND state = 'new'";
+const SEL_FINDINGS_SQL: &str = "SELECT policy_finding_id, pack, assertion, severity, state, status, row_count, created_at, last_seen_at, acknowledged_by, resolved_at
+   FROM policy_finding
+  ORDER BY created_at, rowid";
+
+/// Where a finding is in its lifecycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum FindingState {
+    /// first seen failing and not acknowledged yet
+    New,
+    /// known, alerts which fire on open findings skip it
+    Acknowledged,
+    /// the assertion passed again
+    Resolved,
+}
+
+impl fmt::Display for FindingState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.to_possible_value().unwrap().get_name())
+    }
+}
+
+impl FindingState {
+    fn parse(state: &str) -> FindingState {
+        FindingState::from_str(state, true).unwrap_or(FindingState::New)
+    }
+}
+
+/// A finding of a `policy run` with the `policy_finding` row it was recorded as.
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordedFinding {
+    /// the open finding of a failed assertion, or the finding a passed
+    /// assertion resolved (if it had one)
+    pub policy_finding_id: Option<String>,
+    pub state: Option<FindingState>,
+    /// the assertion was not failing in the previous run
+    pub new: bool,
+    #[serde(flatten)]
+    pub finding: PolicyFinding,
+}
+
+/// Record the findings of a run: failed assertions open a new finding or
+/// update their open one, passed assertions resolve their open finding.
+pub fn record_findings(
+    conn: &Connection,
+    findings: &[PolicyFinding],
+) -> Result<Vec<RecordedFinding>> {
+    let mut sel_open_stmt = conn.prepare(SEL_OPEN_FINDING_SQL)?;
+    let mut ins_stmt = conn.prepare(INS_FINDING_SQL)?;
+    let mut upd_stmt = conn.prepare(UPD_OPEN_FINDING_SQL)?;
+    let mut resolve_stmt = conn.prepare(RESOLVE_FINDINGS_SQL)?;
+
+    let mut recorded = Vec::with_capacity(findings.len());
+    for f in findings {
+        let context = || format!("[policy::record_findings] {}::{}", f.pack, f.assertion);
+        if f.status == FindingStatus::Pass {
+            let resolved: Option<String> = resolve_stmt
+                .query_row(params![f.pack, f.assertion], |row| row.get(0))
+                .optional()
+                .with_context(context)?;
+            recorded.push(RecordedFinding {
+                state: resolved.as_ref().map(|_| FindingState::Resolved),
+                policy_finding_id: resolved,
+                new: false,
+                finding: f.clone(),
+            });
+            continue;
+        }
+
+        let sample_rows = serde_json::to_string(&f.sample_rows)?;
+        let open: Option<(String, String)> = sel_open_stmt
+            .query_row(params![f.pack, f.assertion], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .optional()
+            .with_context(context)?;
+        let (policy_finding_id, state, new) = match open {
+            Some((policy_finding_id, state)) => {
+                upd_stmt
+                    .execute(params![
+                        f.severity.to_string(),
+                        f.status.to_string(),
+                        f.row_count,
+                        sample_rows,
+                        f.remediation,
+                        f.error,
+                        policy_finding_id
+                    ])
+                    .with_context(context)?;
+                (policy_finding_id, FindingState::parse(&state), false)
+            }
+            None => {
+                let policy_finding_id: String = ins_stmt
+                    .query_row(
+                        params![
+                            f.pack,
+                            f.assertion,
+                            f.severity.to_string(),
+                            f.status.to_string(),
+                            f.row_count,
+                            sample_rows,
+                            f.remediation,
+                            f.error
+                        ],
+                        |row| row.get(0),
+                    )
+                    .with_context(context)?;
+                (policy_finding_id, FindingState::New, true)
+            }
+        };
+        recorded.push(RecordedFinding {
+            policy_finding_id: Some(policy_finding_id),
+            state: Some(state),
+            new,
+            finding: f.clone(),
+        });
+    }
+    Ok(recorded)
+}
+
+/// Acknowledge `new` findings, returns the IDs which weren't `new` findings.
+pub fn acknowledge_findings(
+    conn: &Connection,
+    policy_finding_ids: &[String],
+    acknowledged_by: &str,
+) -> Result<Vec<String>> {
+    let mut ack_stmt = conn.prepare(ACK_FINDING_SQL)?;
+    let mut skipped = vec![];
+    for id in policy_finding_ids {
+        let acknowledged = ack_stmt
+            .execute(params![acknowledged_by, id])
+            .with_context(|| format!("[policy::acknowledge_findings] {}", id))?;
+        if acknowledged == 0 {
+            skipped.push(id.clone());
+        }
+    }
+    Ok(skipped)
+}
+
+/// A `policy_finding` row, as listed by `policy findings`.
+#[derive(Debug, Clone, Serialize)]
+pub struct StoredFinding {
+    pub policy_finding_id: String,
+    pub pack: String,
+    pub assertion: String,
+    pub severity: String,
+    pub state: FindingState,
+    pub status: String,
+    pub row_count: usize,
+    pub created_at: String,
+    pub last_seen_at: String,
+    pub acknowledged_by: Option<String>,
+    pub resolved_at: Option<String>,
+}
+
+/// The findings in `states` (all if empty) of `packs` (all if empty), oldest first.
+pub fn stored_findings(
+    conn: &Connection,
+    states: &[FindingState],
+    packs: &[String],
+) -> Result<Vec<StoredFinding>> {
+    let mut stmt = conn.prepare(SEL_FINDINGS_SQL)?;
+    let findings = stmt
+        .query_map([], |row| {
+            Ok(StoredFinding {
+                policy_finding_id: row.get(0)?,
+                pack: row.get(1)?,
+                assertion: row.get(2)?,
+                severity: row.get(3)?,
+                state: FindingState::parse(&row.get::<_, String>(4)?),
+                status: row.get(5)?,
+                row_count: row.get(6)?,
+                created_at: row.get(7)?,
+                last_seen_at: row.get(8)?,
+                acknowledged_by: row.get(9)?,
+                resolved_at: row.get(10)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .with_context(|| "[policy::stored_findings] policy_finding")?;
+    Ok(findings
+        .into_iter()
+        .filter(|f| states.is_empty() || states.contains(&f.state))
+        .filter(|f| packs.is_empty() || packs.contains(&f.pack))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persist::DbConn;
+    use crate::policy::{PolicyExpectation, PolicySeverity};
+
+    fn finding(status: FindingStatus) -> PolicyFinding {
+        PolicyFinding {
+            pack: "evidence".to_string(),
+            assertion: "no-html".to_string(),
+            description: None,
+            expect: PolicyExpectation::NoRows,
+            severity: PolicySeverity::High,
+            status,
+            row_count: if status == FindingStatus::Pass { 0 } else { 2 },
+            sample_rows: vec![],
+            remediation: None,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_finding_lifecycle() -> Result<()> {
+        let mut dbc = DbConn::new(":memory:", 0)?;
+        dbc.init(None)?.commit()?;
+        let conn = &dbc.conn;
+
+        let first = record_findings(conn, &[finding(FindingStatus::Fail)])?.remove(0);
+        assert_eq!((first.state, first.new), (Some(FindingState::New), true));
+
+        // still failing: the same finding, no longer new to alerts
+        let again = record_findings(conn, &[finding(FindingStatus::Fail)])?.remove(0);
+        assert_eq!(again.policy_finding_id, first.policy_finding_id);
+        assert_eq!((again.state, again.new), (Some(FindingState::New), false));
+
+        let id = first.policy_finding_id.clone().unwrap();
+        let skipped = acknowledge_findings(conn, &[id.clone(), "unknown".to_string()], "auditor")?;
+        assert_eq!(skipped, vec!["unknown".to_string()]);
+        let acked = record_findings(conn, &[finding(FindingStatus::Error)])?.remove(0);
+        assert_eq!(acked.state, Some(FindingState::Acknowledged));
+
+        let passed = record_findings(conn, &[finding(FindingStatus::Pass)])?.remove(0);
+        assert_eq!(passed.policy_finding_id, Some(id.clone()));
+        assert_eq!(passed.state, Some(FindingState::Resolved));
+        let passed = record_findings(conn, &[finding(FindingStatus::Pass)])?.remove(0);
+        assert_eq!(passed.policy_finding_id, None);
+
+        // failing after being resolved opens a new finding
+        let reopened = record_findings(conn, &[finding(FindingStatus::Fail)])?.remove(0);
+        assert!(reopened.new);
+        assert_ne!(reopened.policy_finding_id, Some(id.clone()));
+
+        let stored = stored_findings(conn, &[], &[])?;
+        assert_eq!(
+            stored
+                .iter()
+                .map(|f| (f.state, f.acknowledged_by.as_deref()))
+                .collect::<Vec<_>>(),
+            vec![
+                (FindingState::Resolved, Some("auditor")),
+                (FindingState::New, None)
+            ]
+        );
+        assert_eq!(stored_findings(conn, &[FindingState::New], &[])?.len(), 1);
+        assert!(stored_findings(conn, &[], &["other".to_string()])?.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,384 @@
+use std::fmt;
+
+use anyhow::{anyhow, Context, Result};
+use clap::ValueEnum;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value as JsonValue};
+use sha1::{Digest, Sha1};
+
+use crate::snapshot::json_value;
+
+mod alerts;
+mod findings;
+
+pub use alerts::{
+    alert_rules, remove_alert_rule, send_alerts, store_alert_rule, AlertChannel, AlertDelivery,
+    AlertRule, AlertTrigger, SmtpRelay,
+};
+pub use findings::{
+    acknowledge_findings, record_findings, stored_findings, FindingState, RecordedFinding,
+    StoredFinding,
+};
+
+/// Failed assertions keep at most this many of their rows as evidence.
+pub const FINDING_SAMPLE_ROWS: usize = 10;
+
+// a cell is a policy assertion when its `arguments` have a `policy` object;
+// only the latest version of each cell is evaluated (storing an earlier SQL
+// again updates that version) and timestamps only have second precision so
+// rowid orders versions stored within the same second
+const SEL_POLICY_ASSERTIONS_SQL: &str = r#"
+    SELECT notebook_name, cell_name, interpretable_code, description, policy
+      FROM (SELECT notebook_name, cell_name, interpretable_code, description,
+                   arguments ->> '$.policy' AS policy,
+                   ROW_NUMBER() OVER (PARTITION BY notebook_name, cell_name ORDER BY COALESCE(updated_at, created_at) DESC, rowid DESC) AS version
+              FROM code_notebook_cell
+             WHERE notebook_kernel_id = 'SQL')
+     WHERE version = 1 AND policy IS NOT NULL
+  ORDER BY notebook_name, cell_name"#;
+
+const INS_POLICY_ASSERTION_SQL: &str = r#"
+    INSERT INTO code_notebook_cell (code_notebook_cell_id, notebook_kernel_id, notebook_name, cell_name, interpretable_code, interpretable_code_hash, description, arguments)
+         VALUES (surveilr_pk(), 'SQL', ?, ?, ?, ?, ?, ?)
+    ON CONFLICT (notebook_name, cell_name, interpretable_code_hash) DO UPDATE SET
+         description = EXCLUDED.description,
+         arguments = EXCLUDED.arguments,
+         updated_at = CURRENT_TIMESTAMP"#;
+
+/// Which result sets satisfy an assertion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum PolicyExpectation {
+    /// the query finds violations, any row fails the assertion
+    NoRows,
+    /// the query finds evidence, no rows fail the assertion
+    Rows,
+}
+
+impl fmt::Display for PolicyExpectation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.to_possible_value().unwrap().get_name())
+    }
+}
+
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ValueEnum,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicySeverity {
+    Info,
+    Low,
+    #[default]
+    Medium,
+    High,
+    Critical,
+}
+
+impl fmt::Display for PolicySeverity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.to_possible_value().unwrap().get_name())
+    }
+}
+
+/// The `policy` object of a cell's `arguments`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PolicyRule {
+    pub expect: PolicyExpectation,
+    #[serde(default)]
+    pub severity: PolicySeverity,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remediation: Option<String>,
+}
+
+/// A SQL assertion stored as a cell of its pack's notebook.
+#[derive(Debug, Clone, Serialize)]
+pub struct PolicyAssertion {
+    pub pack: String,
+    pub name: String,
+    pub sql: String,
+    pub description: Option<String>,
+    pub rule: PolicyRule,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum FindingStatus {
+    Pass,
+    Fail,
+    /// the assertion's SQL could not be evaluated
+    Error,
+}
+
+impl fmt::Display for FindingStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            FindingStatus::Pass => "PASS",
+            FindingStatus::Fail => "FAIL",
+            FindingStatus::Error => "ERROR",
+        })
+    }
+}
+
+/// The outcome of evaluating one assertion.
+#[derive(Debug, Clone, Serialize)]
+pub struct PolicyFinding {
+    pub pack: String,
+    pub assertion: String,
+    pub description: Option<String>,
+    pub expect: PolicyExpectation,
+    pub severity: PolicySeverity,
+    pub status: FindingStatus,
+    pub row_count: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sample_rows: Vec<JsonValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remediation: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl PolicyFinding {
+    /// Whether the finding should fail a run which tolerates findings below `fail_on`.
+    pub fn fails(&self, fail_on: PolicySeverity) -> bool {
+        self.status != FindingStatus::Pass && self.severity >= fail_on
+    }
+}
+
+/// The latest version of the assertions in `packs` (all packs if empty).
+pub fn policy_assertions(conn: &Connection, packs: &[String]) -> Result<Vec<PolicyAssertion>> {
+    let mut stmt = conn.prepare(SEL_POLICY_ASSERTIONS_SQL)?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, String>(4)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .with_context(|| "[policy::policy_assertions] notebook cells")?;
+
+    rows.into_iter()
+        .filter(|(pack, ..)| packs.is_empty() || packs.contains(pack))
+        .map(|(pack, name, sql, description, rule)| {
+            let rule = serde_json::from_str(&rule).with_context(|| {
+                format!(
+                    "[policy::policy_assertions] invalid policy arguments of {}::{}: {}",
+                    pack, name, rule
+                )
+            })?;
+            Ok(PolicyAssertion {
+                pack,
+                name,
+                sql,
+                description,
+                rule,
+            })
+        })
+        .collect()
+}
+
+/// Store `assertion` as a new version of its cell in the pack's notebook.
+pub fn store_policy_assertion(conn: &Connection, assertion: &PolicyAssertion) -> Result<()> {
+    let mut hasher = Sha1::new();
+    hasher.update(assertion.sql.as_bytes());
+    conn.execute(
+        INS_POLICY_ASSERTION_SQL,
+        params![
+            assertion.pack,
+            assertion.name,
+            assertion.sql,
+            format!("{:x}", hasher.finalize()),
+            assertion.description,
+            serde_json::json!({ "policy": assertion.rule }).to_string()
+        ],
+    )
+    .with_context(|| {
+        format!(
+            "[policy::store_policy_assertion] {}::{}",
+            assertion.pack, assertion.name
+        )
+    })?;
+    Ok(())
+}
+
+/// Run the assertion's read-only SQL and judge its rows.
+fn assertion_rows(conn: &Connection, sql: &str) -> Result<(usize, Vec<JsonValue>)> {
+    let mut stmt = conn.prepare(sql)?;
+    if !stmt.readonly() {
+        return Err(anyhow!("only read-only queries can be policy assertions"));
+    }
+    let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+    let mut rows = stmt.query([])?;
+    let (mut row_count, mut sample_rows) = (0, vec![]);
+    while let Some(row) = rows.next()? {
+        if sample_rows.len() < FINDING_SAMPLE_ROWS {
+            sample_rows.push(JsonValue::Object(
+                columns
+                    .iter()
+                    .enumerate()
+                    .map(|(i, c)| Ok((c.clone(), json_value(row.get_ref(i)?))))
+                    .collect::<rusqlite::Result<Map<_, _>>>()?,
+            ));
+        }
+        row_count += 1;
+    }
+    Ok((row_count, sample_rows))
+}
+
+/// Evaluate an assertion; SQL errors are reported as `ERROR` findings rather
+/// than stopping the run.
+pub fn evaluate_assertion(conn: &Connection, assertion: &PolicyAssertion) -> PolicyFinding {
+    let mut finding = PolicyFinding {
+        pack: assertion.pack.clone(),
+        assertion: assertion.name.clone(),
+        description: assertion.description.clone(),
+        expect: assertion.rule.expect,
+        severity: assertion.rule.severity,
+        status: FindingStatus::Pass,
+        row_count: 0,
+        sample_rows: vec![],
+        remediation: None,
+        error: None,
+    };
+    match assertion_rows(conn, &assertion.sql) {
+        Ok((row_count, sample_rows)) => {
+            finding.row_count = row_count;
+            let passed = match assertion.rule.expect {
+                PolicyExpectation::NoRows => row_count == 0,
+                PolicyExpectation::Rows => row_count > 0,
+            };
+            if !passed {
+                finding.status = FindingStatus::Fail;
+                finding.sample_rows = sample_rows;
+            }
+        }
+        Err(err) => {
+            finding.status = FindingStatus::Error;
+            finding.error = Some(format!("{:#}", err));
+        }
+    }
+    if finding.status != FindingStatus::Pass {
+        finding.remediation = assertion.rule.remediation.clone();
+    }
+    finding
+}
+
+/// Evaluate the assertions of `packs` (all packs if empty).
+pub fn evaluate_policies(conn: &Connection, packs: &[String]) -> Result<Vec<PolicyFinding>> {
+    let assertions = policy_assertions(conn, packs)?;
+    if assertions.is_empty() {
+        return Err(anyhow!(
+            "[policy::evaluate_policies] no policy assertions found{}",
+            if packs.is_empty() {
+                String::new()
+            } else {
+                format!(" in {}", packs.join(", "))
+            }
+        ));
+    }
+    Ok(assertions
+        .iter()
+        .map(|assertion| evaluate_assertion(conn, assertion))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingest::{seed_rssd, SeedProfile};
+    use crate::persist::DbConn;
+
+    fn assertion(name: &str, sql: &str, expect: PolicyExpectation) -> PolicyAssertion {
+        PolicyAssertion {
+            pack: "evidence".to_string(),
+            name: name.to_string(),
+            sql: sql.to_string(),
+            description: None,
+            rule: PolicyRule {
+                expect,
+                severity: PolicySeverity::High,
+                remediation: Some(format!("fix {}", name)),
+            },
+        }
+    }
+
+    #[test]
+    fn test_evaluate_policies() -> Result<()> {
+        let mut dbc = DbConn::new(":memory:", 0)?;
+        dbc.init(None)?.commit()?;
+        seed_rssd(&dbc.conn, SeedProfile::Test)?;
+
+        for a in [
+            assertion(
+                "has-markdown",
+                "SELECT uri FROM uniform_resource WHERE nature = 'md'",
+                PolicyExpectation::Rows,
+            ),
+            assertion(
+                "no-html",
+                "SELECT uri FROM uniform_resource WHERE nature = 'html'",
+                PolicyExpectation::NoRows,
+            ),
+            assertion(
+                "not-read-only",
+                "DELETE FROM uniform_resource",
+                PolicyExpectation::NoRows,
+            ),
+        ] {
+            store_policy_assertion(&dbc.conn, &a)?;
+        }
+        // a new version of a cell replaces the old one
+        let mut relaxed = assertion(
+            "no-html",
+            "SELECT uri FROM uniform_resource WHERE nature = 'html' AND 0",
+            PolicyExpectation::NoRows,
+        );
+        relaxed.rule.severity = PolicySeverity::Low;
+        store_policy_assertion(&dbc.conn, &relaxed)?;
+
+        let findings = evaluate_policies(&dbc.conn, &[])?;
+        let status: Vec<_> = findings
+            .iter()
+            .map(|f| (f.assertion.as_str(), f.status, f.severity))
+            .collect();
+        assert_eq!(
+            status,
+            vec![
+                ("has-markdown", FindingStatus::Pass, PolicySeverity::High),
+                ("no-html", FindingStatus::Pass, PolicySeverity::Low),
+                ("not-read-only", FindingStatus::Error, PolicySeverity::High),
+            ]
+        );
+        assert!(findings[2].fails(PolicySeverity::High));
+        assert_eq!(
+            findings[2].remediation.as_deref(),
+            Some("fix not-read-only")
+        );
+        assert!(evaluate_policies(&dbc.conn, &["missing".to_string()]).is_err());
+
+        store_policy_assertion(
+            &dbc.conn,
+            &assertion(
+                "no-html",
+                "SELECT uri FROM uniform_resource WHERE nature IN ('html', 'htm')",
+                PolicyExpectation::NoRows,
+            ),
+        )?;
+        let failed = evaluate_policies(&dbc.conn, &["evidence".to_string()])?
+            .into_iter()
+            .find(|f| f.assertion == "no-html")
+            .unwrap();
+        assert_eq!(failed.status, FindingStatus::Fail);
+        assert!(failed.row_count > 0);
+        assert_eq!(
+            failed.sample_rows.len(),
+            failed.row_count.min(FINDING_SAMPLE_ROWS)
+        );
+        assert!(!failed.fails(PolicySeverity::Critical));
+        Ok(())
+    }
+}
@@ -0,0 +1,265 @@
+use anyhow::{Context, Result};
+use rusqlite::{named_params, Connection, ToSql};
+use serde::Serialize;
+
+// the sessions beyond the most recent `:keep` of each device; sessions IMAP
+// folders resume from are kept
+const PRUNED_SESSIONS_SQL: &str = "
+    SELECT ur_ingest_session_id
+      FROM (SELECT ur_ingest_session_id,
+                   ROW_NUMBER() OVER (PARTITION BY device_id
+                                          ORDER BY ingest_started_at DESC, ur_ingest_session_id DESC) AS recency
+              FROM ur_ingest_session)
+     WHERE recency > :keep
+       AND ur_ingest_session_id NOT IN (SELECT ingest_session_id FROM ur_ingest_imap_folder_state)";
+
+// the sessions which encountered each resource, resources are only stored
+// once so later sessions refer to the resource an earlier one inserted
+const RESOURCE_ENCOUNTERS_SQL: &str = "
+    SELECT uniform_resource_id, ingest_session_id, ingest_fs_path_id, NULL AS ingest_imap_acct_folder_id
+      FROM ur_ingest_session_fs_path_entry WHERE uniform_resource_id IS NOT NULL
+    UNION ALL
+    SELECT uniform_resource_id, ingest_session_id, NULL, NULL
+      FROM ur_ingest_session_task WHERE uniform_resource_id IS NOT NULL
+    UNION ALL
+    SELECT uniform_resource_id, ingest_session_id, NULL, ingest_imap_acct_folder_id
+      FROM ur_ingest_session_imap_acct_folder_message WHERE uniform_resource_id IS NOT NULL";
+
+// resources inserted by a pruned session which a kept session encountered
+// too are moved to the earliest such session
+const REASSIGN_RESOURCES_SQL: &str = "
+    UPDATE uniform_resource
+       SET (ingest_session_id, ingest_fs_path_id, ingest_imap_acct_folder_id) = (
+               SELECT e.ingest_session_id, e.ingest_fs_path_id, e.ingest_imap_acct_folder_id
+                 FROM resource_encounter e
+                 JOIN ur_ingest_session s ON s.ur_ingest_session_id = e.ingest_session_id
+                WHERE e.uniform_resource_id = uniform_resource.uniform_resource_id
+                  AND e.ingest_session_id NOT IN pruned_session
+             ORDER BY s.ingest_started_at
+                LIMIT 1),
+           updated_at = CURRENT_TIMESTAMP
+     WHERE ingest_session_id IN pruned_session
+       AND EXISTS (SELECT 1 FROM resource_encounter e
+                    WHERE e.uniform_resource_id = uniform_resource.uniform_resource_id
+                      AND e.ingest_session_id NOT IN pruned_session)";
+
+const PRUNED_RESOURCES_SQL: &str =
+    "SELECT uniform_resource_id FROM uniform_resource WHERE ingest_session_id IN pruned_session";
+
+// in the order of the foreign keys
+const DEL_PRUNED_SQL: &str = "
+    DELETE FROM uniform_resource_lineage
+     WHERE ingest_session_id IN pruned_session
+        OR source_uniform_resource_id IN pruned_resource
+        OR output_uniform_resource_id IN pruned_resource;
+    DELETE FROM uniform_resource_chunk WHERE uniform_resource_id IN pruned_resource;
+    DELETE FROM uniform_resource_transform WHERE uniform_resource_id IN pruned_resource;
+    DELETE FROM uniform_resource_known_file WHERE uniform_resource_id IN pruned_resource;
+    DELETE FROM uniform_resource_equivalence WHERE uniform_resource_id IN pruned_resource;
+    DELETE FROM uniform_resource_verification
+     WHERE ingest_session_id IN pruned_session
+        OR uniform_resource_id IN pruned_resource;
+    DELETE FROM ur_ingest_session_fs_path_entry WHERE ingest_session_id IN pruned_session;
+    DELETE FROM ur_ingest_session_task WHERE ingest_session_id IN pruned_session;
+    DELETE FROM ur_ingest_session_imap_acct_folder_message WHERE ingest_session_id IN pruned_session;
+    DELETE FROM uniform_resource WHERE uniform_resource_id IN pruned_resource;
+    DELETE FROM ur_ingest_session_imap_acct_folder WHERE ingest_session_id IN pruned_session;
+    DELETE FROM ur_ingest_session_imap_acct_stat WHERE ingest_session_id IN pruned_session;
+    DELETE FROM ur_ingest_session_imap_account WHERE ingest_session_id IN pruned_session;
+    DELETE FROM ur_ingest_session_git_repo WHERE ingest_session_id IN pruned_session;
+    DELETE FROM ur_ingest_session_fs_path WHERE ingest_session_id IN pruned_session;
+    DELETE FROM ur_ingest_session WHERE ur_ingest_session_id IN pruned_session;";
+
+// resources stored before the cutoff which no session since encountered
+const STALE_CONTENT_SQL: &str = "
+    SELECT ur.uniform_resource_id
+      FROM uniform_resource ur
+     WHERE ur.created_at < datetime('now', :age)
+       AND (ur.content IS NOT NULL
+            OR EXISTS (SELECT 1 FROM uniform_resource_chunk c WHERE c.uniform_resource_id = ur.uniform_resource_id)
+            OR EXISTS (SELECT 1 FROM uniform_resource_transform t
+                        WHERE t.uniform_resource_id = ur.uniform_resource_id AND t.content IS NOT NULL))
+       AND NOT EXISTS (SELECT 1 FROM resource_encounter e
+                         JOIN ur_ingest_session s ON s.ur_ingest_session_id = e.ingest_session_id
+                        WHERE e.uniform_resource_id = ur.uniform_resource_id
+                          AND s.ingest_started_at >= datetime('now', :age))";
+
+const DROP_STALE_CONTENT_SQL: &str = "
+    DELETE FROM uniform_resource_chunk WHERE uniform_resource_id IN stale_resource;
+    UPDATE uniform_resource_transform SET content = NULL, updated_at = CURRENT_TIMESTAMP
+     WHERE uniform_resource_id IN stale_resource AND content IS NOT NULL;
+    UPDATE uniform_resource
+       SET content = NULL,
+           elaboration = json_set(COALESCE(elaboration, '{}'), '$.content_pruned_at', CURRENT_TIMESTAMP),
+           updated_at = CURRENT_TIMESTAMP
+     WHERE uniform_resource_id IN stale_resource;";
+
+/// What `admin prune` removes from an RSSD.
+#[derive(Debug, Clone, Default)]
+pub struct PrunePolicy {
+    /// keep only this many of each device's most recent ingest sessions
+    pub keep_sessions: Option<usize>,
+    /// drop the content (keeping the metadata) of resources stored more than
+    /// this many days ago which no session encountered since
+    pub content_older_than_days: Option<u32>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct PruneReport {
+    /// ingest sessions deleted
+    pub sessions: usize,
+    /// resources deleted with their sessions
+    pub resources: usize,
+    /// resources of deleted sessions kept since a newer session encountered them
+    pub reassigned_resources: usize,
+    /// resources whose content was dropped
+    pub contents: usize,
+    /// bytes of the pages the prune freed
+    pub freed_bytes: u64,
+    /// bytes of all the free pages, what `VACUUM` gives back to the file system
+    pub reclaimable_bytes: u64,
+}
+
+fn free_bytes(conn: &Connection) -> Result<u64> {
+    let (free_pages, page_size): (u64, u64) = conn.query_row(
+        "SELECT freelist_count, page_size FROM pragma_freelist_count(), pragma_page_size()",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    Ok(free_pages * page_size)
+}
+
+/// (Re)create the temporary table `name` with the rows of `select` and return
+/// their number.
+fn temp_table(
+    conn: &Connection,
+    name: &str,
+    select: &str,
+    params: &[(&str, &dyn ToSql)],
+) -> Result<usize> {
+    conn.execute(&format!("DROP TABLE IF EXISTS temp.{name}"), [])?;
+    conn.execute(&format!("CREATE TEMP TABLE {name} AS {select}"), params)
+        .with_context(|| format!("[prune::temp_table] {name}"))?;
+    count(conn, name)
+}
+
+fn count(conn: &Connection, table: &str) -> Result<usize> {
+    Ok(
+        conn.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+            row.get(0)
+        })?,
+    )
+}
+
+/// Apply `policy` in `conn`'s transaction. The freed pages are counted before
+/// the transaction commits so rolling it back makes a dry run.
+pub fn prune(conn: &Connection, policy: &PrunePolicy) -> Result<PruneReport> {
+    let mut report = PruneReport::default();
+    let free_before = free_bytes(conn)?;
+    temp_table(conn, "resource_encounter", RESOURCE_ENCOUNTERS_SQL, &[])?;
+    conn.execute(
+        "CREATE INDEX temp.idx_resource_encounter ON resource_encounter(uniform_resource_id)",
+        [],
+    )?;
+
+    if let Some(keep) = policy.keep_sessions {
+        report.sessions = temp_table(
+            conn,
+            "pruned_session",
+            PRUNED_SESSIONS_SQL,
+            named_params! { ":keep": keep },
+        )?;
+        report.reassigned_resources = conn
+            .execute(REASSIGN_RESOURCES_SQL, [])
+            .context("[prune] reassigning resources")?;
+        report.resources = temp_table(conn, "pruned_resource", PRUNED_RESOURCES_SQL, &[])?;
+        conn.execute_batch(DEL_PRUNED_SQL)
+            .context("[prune] deleting sessions")?;
+    }
+
+    if let Some(days) = policy.content_older_than_days {
+        report.contents = temp_table(
+            conn,
+            "stale_resource",
+            STALE_CONTENT_SQL,
+            named_params! { ":age": format!("-{days} days") },
+        )?;
+        conn.execute_batch(DROP_STALE_CONTENT_SQL)
+            .context("[prune] dropping content")?;
+    }
+
+    report.reclaimable_bytes = free_bytes(conn)?;
+    report.freed_bytes = report.reclaimable_bytes.saturating_sub(free_before);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingest::{seed_rssd, SeedProfile};
+    use crate::persist::DbConn;
+
+    fn dangling(conn: &Connection) -> Result<i64> {
+        Ok(conn.query_row(
+            "SELECT (SELECT COUNT(*) FROM uniform_resource
+                      WHERE ingest_session_id NOT IN (SELECT ur_ingest_session_id FROM ur_ingest_session))
+                  + (SELECT COUNT(*) FROM ur_ingest_session_fs_path_entry
+                      WHERE uniform_resource_id NOT IN (SELECT uniform_resource_id FROM uniform_resource))
+                  + (SELECT COUNT(*) FROM uniform_resource
+                      WHERE ingest_fs_path_id NOT IN (SELECT ur_ingest_session_fs_path_id FROM ur_ingest_session_fs_path))",
+            [],
+            |row| row.get(0),
+        )?)
+    }
+
+    #[test]
+    fn keep_last_sessions() -> Result<()> {
+        let mut dbc = DbConn::new(":memory:", 0)?;
+        let tx = dbc.init(None)?;
+        let seeded = seed_rssd(&tx, SeedProfile::Demo)?;
+
+        let report = prune(
+            &tx,
+            &PrunePolicy {
+                keep_sessions: Some(1),
+                ..Default::default()
+            },
+        )?;
+        assert_eq!(report.sessions, seeded.ingest_sessions - seeded.devices);
+        assert!(report.resources > 0);
+        // the unchanged files are still referenced by the latest sessions
+        assert!(report.reassigned_resources > 0);
+        assert_eq!(count(&tx, "ur_ingest_session")?, seeded.devices);
+        assert_eq!(dangling(&tx)?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn drop_stale_content() -> Result<()> {
+        let mut dbc = DbConn::new(":memory:", 0)?;
+        let tx = dbc.init(None)?;
+        seed_rssd(&tx, SeedProfile::Test)?;
+        tx.execute_batch(
+            "UPDATE uniform_resource SET created_at = datetime('now', '-90 days');
+             UPDATE ur_ingest_session SET ingest_started_at = datetime('now', '-60 days');",
+        )?;
+        let resources = count(&tx, "uniform_resource")?;
+
+        let report = prune(
+            &tx,
+            &PrunePolicy {
+                content_older_than_days: Some(30),
+                ..Default::default()
+            },
+        )?;
+        assert!(report.contents > 0);
+        assert_eq!(count(&tx, "uniform_resource")?, resources);
+        let with_content: usize = tx.query_row(
+            "SELECT COUNT(*) FROM uniform_resource WHERE content IS NOT NULL",
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(with_content, 0);
+        Ok(())
+    }
+}
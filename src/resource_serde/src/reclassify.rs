@@ -0,0 +1,193 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use common::query_sql_rows_no_args;
+use resource::{
+    EncounterableResourceClass, EncounterableResourceFlags, EncounterableResourcePathClassifier,
+    EncounterableResourceUriClassifier,
+};
+use rusqlite::{params, Connection, Result as RusqliteResult};
+use serde::Serialize;
+
+const UPD_UR_NATURE_SQL: &str =
+    "UPDATE uniform_resource SET nature = ?, updated_at = CURRENT_TIMESTAMP WHERE uniform_resource_id = ?";
+const DEL_UR_TRANSFORMS_SQL: &str =
+    "DELETE FROM uniform_resource_transform WHERE uniform_resource_id = ?";
+
+// only resources found by walking paths were classified by path rules, tasks
+// and emails get their natures elsewhere
+query_sql_rows_no_args!(
+    walked_resources,
+    "SELECT uniform_resource_id, uri, nature
+       FROM uniform_resource
+      WHERE ingest_fs_path_id IS NOT NULL
+   ORDER BY uniform_resource_id";
+    uniform_resource_id: String,
+    uri: String,
+    nature: Option<String>
+);
+
+/// A resource whose nature differs under the current classifier rules.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NatureChange {
+    pub uniform_resource_id: String,
+    pub uri: String,
+    pub from: Option<String>,
+    pub to: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Reclassification {
+    pub examined: usize,
+    /// resources the current rules would now ignore, their natures are kept
+    pub ignored: usize,
+    pub changes: Vec<NatureChange>,
+}
+
+impl Reclassification {
+    /// `(from, to)` natures with the number of resources changing that way.
+    pub fn by_nature(&self) -> BTreeMap<(String, String), usize> {
+        let mut by_nature = BTreeMap::new();
+        for change in &self.changes {
+            let from = change.from.clone().unwrap_or_default();
+            *by_nature.entry((from, change.to.clone())).or_default() += 1;
+        }
+        by_nature
+    }
+}
+
+/// The nature ingestion would give `uri` with `classifier`: the classifier's
+/// nature, else the extension, else `json` (see `EncounterableResource::encountered`).
+/// `None` if the resource would be ignored.
+pub fn classified_nature(
+    classifier: &EncounterableResourcePathClassifier,
+    uri: &str,
+) -> Option<String> {
+    classified(classifier, uri).map(|(nature, _)| nature)
+}
+
+/// Like `classified_nature`, with the flags (e.g. `CONTENT_ACQUIRABLE`) of the
+/// rules matching `uri`.
+pub fn classified(
+    classifier: &EncounterableResourcePathClassifier,
+    uri: &str,
+) -> Option<(String, EncounterableResourceFlags)> {
+    let mut class = EncounterableResourceClass {
+        flags: EncounterableResourceFlags::empty(),
+        nature: None,
+    };
+    classifier.classify(uri, &mut class);
+    if class
+        .flags
+        .contains(EncounterableResourceFlags::IGNORE_RESOURCE)
+    {
+        return None;
+    }
+    let nature = class.nature.unwrap_or_else(|| {
+        Path::new(uri)
+            .extension()
+            .map(|extn| extn.to_string_lossy().to_string())
+            .unwrap_or_else(|| "json".to_string())
+    });
+    Some((nature, class.flags))
+}
+
+/// Re-run `classifier` against the URIs of the stored resources and report the
+/// ones whose nature would change, without changing anything.
+pub fn reclassify(
+    conn: &Connection,
+    classifier: &EncounterableResourcePathClassifier,
+) -> Result<Reclassification> {
+    let mut reclassification = Reclassification::default();
+    walked_resources(conn, |_, uniform_resource_id, uri, nature| {
+        reclassification.examined += 1;
+        match classified_nature(classifier, &uri) {
+            None => reclassification.ignored += 1,
+            Some(to) if nature.as_deref() != Some(to.as_str()) => {
+                reclassification.changes.push(NatureChange {
+                    uniform_resource_id,
+                    uri,
+                    from: nature,
+                    to,
+                })
+            }
+            Some(_) => {}
+        }
+        Ok(())
+    })
+    .with_context(|| "[reclassify] stored resources")?;
+    Ok(reclassification)
+}
+
+/// Update the natures of the changed resources. With `drop_transforms`, their
+/// transforms (derived under the old nature) are deleted so they can be
+/// re-run for the new nature.
+pub fn apply_reclassification(
+    conn: &Connection,
+    changes: &[NatureChange],
+    drop_transforms: bool,
+) -> Result<usize> {
+    let mut upd_nature_stmt = conn.prepare(UPD_UR_NATURE_SQL)?;
+    let mut del_transforms_stmt = conn.prepare(DEL_UR_TRANSFORMS_SQL)?;
+    let mut updated = 0;
+    for change in changes {
+        updated += upd_nature_stmt
+            .execute(params![change.to, change.uniform_resource_id])
+            .with_context(|| format!("[apply_reclassification] {}", change.uri))?;
+        if drop_transforms {
+            del_transforms_stmt
+                .execute(params![change.uniform_resource_id])
+                .with_context(|| {
+                    format!("[apply_reclassification] transforms of {}", change.uri)
+                })?;
+        }
+    }
+    Ok(updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingest::{seed_rssd, SeedProfile};
+    use crate::persist::DbConn;
+
+    #[test]
+    fn test_reclassify() -> Result<()> {
+        let mut dbc = DbConn::new(":memory:", 0)?;
+        dbc.init(None)?.commit()?;
+        seed_rssd(&dbc.conn, SeedProfile::Demo)?;
+
+        let classifier = EncounterableResourcePathClassifier::default_from_conn(&dbc.conn)?;
+        let unchanged = reclassify(&dbc.conn, &classifier)?;
+        assert!(unchanged.examined > 0);
+        assert_eq!(unchanged.changes, vec![]);
+
+        // CSV reports are now classified as `csv-report`
+        dbc.conn.execute(
+            "INSERT INTO ur_ingest_resource_path_match_rule (ur_ingest_resource_path_match_rule_id, namespace, regex, flags, nature, priority, description)
+                  VALUES ('csv-reports', 'default', '/reports/.*\\.csv$', 'CONTENT_ACQUIRABLE', 'csv-report', NULL, 'CSV reports')",
+            [],
+        )?;
+        let classifier = EncounterableResourcePathClassifier::default_from_conn(&dbc.conn)?;
+        let changed = reclassify(&dbc.conn, &classifier)?;
+        assert!(!changed.changes.is_empty());
+        assert!(changed
+            .changes
+            .iter()
+            .all(|change| change.uri.ends_with(".csv")
+                && change.from.as_deref() == Some("csv")
+                && change.to == "csv-report"));
+        assert_eq!(
+            changed
+                .by_nature()
+                .get(&("csv".to_string(), "csv-report".to_string())),
+            Some(&changed.changes.len())
+        );
+
+        let updated = apply_reclassification(&dbc.conn, &changed.changes, true)?;
+        assert_eq!(updated, changed.changes.len());
+        assert_eq!(reclassify(&dbc.conn, &classifier)?.changes, vec![]);
+        Ok(())
+    }
+}
@@ -0,0 +1,767 @@
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD_NO_PAD, Engine};
+use rusqlite::{params, types::ValueRef, Connection, OptionalExtension};
+use serde::Serialize;
+use serde_json::{json, Value as JsonValue};
+
+use crate::persist::DbConn;
+
+/// The environment variable with the auth token of a remote libSQL database,
+/// unless its URL has an `authToken` parameter.
+pub const REMOTE_STATE_DB_AUTH_TOKEN_ENV: &str = "SURVEILR_LIBSQL_AUTH_TOKEN";
+
+// tables which are never pushed, the replica's own bookkeeping
+const LOCAL_ONLY_TABLES: [&str; 2] = ["surveilr_remote_sync", "surveilr_remote_sync_change"];
+
+// rows (and about how many bytes of them) pushed in one request
+const SYNC_BATCH_ROWS: usize = 500;
+const SYNC_BATCH_BYTES: usize = 4 * 1024 * 1024;
+
+const UPSERT_REMOTE_SYNC_SQL: &str = "
+    INSERT INTO surveilr_remote_sync (surveilr_remote_sync_id, remote_url, table_name, synced_rowid, synced_rows, synced_at, synced_change_id, scanned_at)
+                              VALUES (surveilr_pk(), ?1, ?2, ?3, ?4, CURRENT_TIMESTAMP, ?5, CASE WHEN ?6 THEN CURRENT_TIMESTAMP END)
+                         ON CONFLICT (remote_url, table_name)
+                       DO UPDATE SET synced_rowid = EXCLUDED.synced_rowid,
+                                     synced_rows = synced_rows + EXCLUDED.synced_rows,
+                                     synced_at = CURRENT_TIMESTAMP,
+                                     synced_change_id = EXCLUDED.synced_change_id,
+                                     scanned_at = COALESCE(scanned_at, EXCLUDED.scanned_at),
+                                     updated_at = CURRENT_TIMESTAMP";
+
+// changes every remote which syncs the table has pushed are no longer needed
+const PRUNE_REMOTE_SYNC_CHANGES_SQL: &str = "
+    DELETE FROM surveilr_remote_sync_change
+          WHERE table_name = ?1
+            AND surveilr_remote_sync_change_id <= (SELECT MIN(synced_change_id) FROM surveilr_remote_sync WHERE table_name = ?1)";
+
+/// Whether `-d` names a remote libSQL database (e.g. `libsql://<db>.turso.io`)
+/// rather than a SQLite file.
+pub fn is_remote_state_db(state_db: &str) -> bool {
+    ["libsql://", "https://", "http://"]
+        .iter()
+        .any(|scheme| state_db.starts_with(scheme))
+}
+
+/// A remote libSQL (e.g. Turso) database which sessions are written to through
+/// a local replica: ingestion writes the replica like any RSSD and `sync`
+/// pushes the rows which were inserted, updated or deleted since the last sync
+/// over libSQL's HTTP API, so small edge devices stream evidence to a managed
+/// store instead of shipping `.db` files.
+#[derive(Debug, Clone)]
+pub struct RemoteStateDb {
+    /// the URL without its auth token, as recorded in `surveilr_remote_sync`
+    pub url: String,
+    /// the SQLite file ingestion writes to
+    pub replica_fs_path: String,
+    pipeline_url: String,
+    auth_token: Option<String>,
+}
+
+impl RemoteStateDb {
+    /// `url` is `libsql://`, `https://` or `http://` with an optional
+    /// `authToken` parameter; the replica defaults to `<host>.replica.sqlite.db`.
+    pub fn new(url: &str, replica_fs_path: Option<&str>) -> Result<RemoteStateDb> {
+        let http_url = match url.strip_prefix("libsql://") {
+            Some(rest) => format!("https://{}", rest),
+            None => url.to_string(),
+        };
+        let mut parsed = reqwest::Url::parse(&http_url)
+            .with_context(|| format!("[RemoteStateDb::new] remote database URL {}", url))?;
+        let host = parsed
+            .host_str()
+            .ok_or_else(|| anyhow!("[RemoteStateDb::new] {} has no host", url))?
+            .to_string();
+        let auth_token = parsed
+            .query_pairs()
+            .find(|(key, _)| key == "authToken")
+            .map(|(_, token)| token.to_string())
+            .or_else(|| std::env::var(REMOTE_STATE_DB_AUTH_TOKEN_ENV).ok());
+        parsed.set_query(None);
+        let base = parsed.as_str().trim_end_matches('/').to_string();
+        Ok(RemoteStateDb {
+            url: match url.strip_prefix("libsql://") {
+                Some(_) => base.replacen("https://", "libsql://", 1),
+                None => base.clone(),
+            },
+            replica_fs_path: replica_fs_path
+                .map(String::from)
+                .unwrap_or_else(|| format!("{}.replica.sqlite.db", host)),
+            pipeline_url: format!("{}/v2/pipeline", base),
+            auth_token,
+        })
+    }
+
+    /// Push the replica's changes which weren't pushed yet, creating the
+    /// remote's tables, indexes and views first when it doesn't have them.
+    pub async fn sync(&self) -> Result<RemoteSyncStats> {
+        let http = reqwest::Client::new();
+        let mut stats = RemoteSyncStats::default();
+        track_changes(&DbConn::new(&self.replica_fs_path, 0)?.conn)?;
+        let schema = remote_schema(&DbConn::open(&self.replica_fs_path, 0)?.conn)?;
+        self.execute(&http, &schema).await?;
+        stats.requests += 1;
+
+        loop {
+            let batch = next_sync_batch(&DbConn::open(&self.replica_fs_path, 0)?.conn, &self.url)?;
+            let Some(batch) = batch else {
+                break;
+            };
+            if !batch.statements.is_empty() {
+                self.execute(&http, &batch.statements)
+                    .await
+                    .with_context(|| {
+                        format!(
+                            "[RemoteStateDb::sync] {} rows of {}",
+                            batch.rows, batch.table_name
+                        )
+                    })?;
+                stats.requests += 1;
+            }
+            mark_synced(
+                &DbConn::new(&self.replica_fs_path, 0)?.conn,
+                &self.url,
+                &batch,
+            )?;
+            if batch.rows > 0 && !stats.tables.contains(&batch.table_name) {
+                stats.tables.push(batch.table_name.clone());
+            }
+            stats.rows += batch.rows;
+        }
+        Ok(stats)
+    }
+
+    /// Run `statements` in a single transaction of the remote database.
+    async fn execute(&self, http: &reqwest::Client, statements: &[RemoteStatement]) -> Result<()> {
+        let mut request = http
+            .post(&self.pipeline_url)
+            .json(&pipeline_request(statements));
+        if let Some(auth_token) = &self.auth_token {
+            request = request.bearer_auth(auth_token);
+        }
+        let response = request
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .with_context(|| format!("[RemoteStateDb::execute] {}", self.url))?
+            .json::<JsonValue>()
+            .await
+            .with_context(|| format!("[RemoteStateDb::execute] response of {}", self.url))?;
+        match pipeline_error(&response) {
+            Some(error) => bail!("[RemoteStateDb::execute] {}: {}", self.url, error),
+            None => Ok(()),
+        }
+    }
+}
+
+/// What a `RemoteStateDb::sync` pushed.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct RemoteSyncStats {
+    pub tables: Vec<String>,
+    pub rows: usize,
+    pub requests: usize,
+}
+
+/// A statement and its arguments as libSQL's HTTP API (Hrana) takes them.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RemoteStatement {
+    pub sql: String,
+    pub args: Vec<RemoteValue>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum RemoteValue {
+    Null,
+    // integers are strings so they aren't rounded as JSON numbers
+    Integer { value: String },
+    Float { value: f64 },
+    Text { value: String },
+    Blob { base64: String },
+}
+
+impl From<ValueRef<'_>> for RemoteValue {
+    fn from(value: ValueRef<'_>) -> Self {
+        match value {
+            ValueRef::Null => RemoteValue::Null,
+            ValueRef::Integer(i) => RemoteValue::Integer {
+                value: i.to_string(),
+            },
+            ValueRef::Real(f) => RemoteValue::Float { value: f },
+            ValueRef::Text(text) => RemoteValue::Text {
+                value: String::from_utf8_lossy(text).to_string(),
+            },
+            ValueRef::Blob(blob) => RemoteValue::Blob {
+                base64: STANDARD_NO_PAD.encode(blob),
+            },
+        }
+    }
+}
+
+/// The rows of one table which are pushed together: those after
+/// `synced_rowid` while the table is first scanned, afterwards the rows
+/// inserted, updated or deleted after `synced_change_id`.
+#[derive(Debug, Clone)]
+pub struct SyncBatch {
+    pub table_name: String,
+    pub last_rowid: i64,
+    pub last_change_id: i64,
+    /// every row the table had when its first sync started was pushed
+    pub scanned: bool,
+    pub rows: usize,
+    pub statements: Vec<RemoteStatement>,
+}
+
+/// A Hrana pipeline running `statements` in one transaction, rolled back when
+/// one of them fails.
+fn pipeline_request(statements: &[RemoteStatement]) -> JsonValue {
+    let stmt = |sql: &str| json!({ "sql": sql });
+    let ok = |step: usize| json!({ "type": "ok", "step": step });
+    let mut steps = vec![json!({ "stmt": stmt("BEGIN") })];
+    for (i, statement) in statements.iter().enumerate() {
+        steps.push(json!({ "stmt": statement, "condition": ok(i) }));
+    }
+    let commit = steps.len();
+    steps.push(json!({ "stmt": stmt("COMMIT"), "condition": ok(commit - 1) }));
+    steps.push(json!({
+        "stmt": stmt("ROLLBACK"),
+        "condition": { "type": "not", "cond": ok(commit) },
+    }));
+    json!({
+        "requests": [
+            { "type": "batch", "batch": { "steps": steps } },
+            { "type": "close" },
+        ]
+    })
+}
+
+/// The first error in a Hrana pipeline response.
+fn pipeline_error(response: &JsonValue) -> Option<String> {
+    let message = |error: &JsonValue| {
+        error["message"]
+            .as_str()
+            .map_or_else(|| error.to_string(), String::from)
+    };
+    let results = response["results"].as_array()?;
+    results
+        .iter()
+        .find_map(|result| match result["type"].as_str() {
+            Some("error") => Some(message(&result["error"])),
+            _ => result["response"]["result"]["step_errors"]
+                .as_array()?
+                .iter()
+                .find(|error| !error.is_null())
+                .map(message),
+        })
+}
+
+/// The local tables which are pushed, in the order they were created.
+fn synced_tables(conn: &Connection) -> Result<Vec<String>> {
+    let tables = conn
+        .prepare(
+            "SELECT name FROM sqlite_master
+              WHERE type = 'table' AND name NOT LIKE 'sqlite_%'
+              ORDER BY rowid",
+        )?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(tables
+        .into_iter()
+        .filter(|table| !LOCAL_ONLY_TABLES.contains(&table.as_str()))
+        .collect())
+}
+
+/// `CREATE ... IF NOT EXISTS` statements for the replica's tables, indexes and
+/// views, run on the remote before every sync.
+pub fn remote_schema(conn: &Connection) -> Result<Vec<RemoteStatement>> {
+    let local_only = LOCAL_ONLY_TABLES
+        .iter()
+        .map(|table| format!("'{}'", table))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut stmt = conn.prepare(&format!(
+        "SELECT type, sql FROM sqlite_master
+          WHERE sql IS NOT NULL AND name NOT LIKE 'sqlite_%' AND tbl_name NOT IN ({local_only})
+          ORDER BY CASE type WHEN 'table' THEN 0 WHEN 'index' THEN 1 WHEN 'view' THEN 2 ELSE 3 END, rowid"
+    ))?;
+    let statements = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?
+        .into_iter()
+        .filter_map(|(kind, sql)| {
+            let create = match kind.as_str() {
+                "table" => "CREATE TABLE",
+                "index" if sql.starts_with("CREATE UNIQUE INDEX") => "CREATE UNIQUE INDEX",
+                "index" => "CREATE INDEX",
+                "view" => "CREATE VIEW",
+                _ => return None,
+            };
+            let sql = match sql.strip_prefix(create) {
+                Some(rest) if !rest.trim_start().starts_with("IF NOT EXISTS") => {
+                    format!("{} IF NOT EXISTS{}", create, rest)
+                }
+                _ => sql,
+            };
+            Some(RemoteStatement { sql, args: vec![] })
+        })
+        .collect();
+    Ok(statements)
+}
+
+/// The primary key columns of `table_name`, empty when it has none.
+fn primary_key(conn: &Connection, table_name: &str) -> Result<Vec<String>> {
+    Ok(conn
+        .prepare("SELECT name FROM pragma_table_info(?) WHERE pk > 0 ORDER BY pk")?
+        .query_map([table_name], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
+/// Record the rows of the synced tables which are inserted, updated or deleted
+/// in `surveilr_remote_sync_change` so that syncs push them; rows are updated
+/// in place (e.g. upserts, reclassification) and deleted (e.g. `admin prune`)
+/// so their rowids alone don't tell what the remote lacks. Tables without a
+/// primary key (none of the RSSD's) only get their inserts and updates pushed.
+pub fn track_changes(conn: &Connection) -> Result<()> {
+    for table_name in synced_tables(conn)? {
+        let key = primary_key(conn, &table_name)?;
+        let key_of = |row: &str| {
+            key.iter()
+                .map(|column| format!("{row}.\"{column}\""))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let log = |row: &str| {
+            format!(
+                "INSERT INTO surveilr_remote_sync_change (table_name, row_id) VALUES ('{table_name}', {row}.rowid);"
+            )
+        };
+        let log_delete = |condition: &str| {
+            format!(
+                "INSERT INTO surveilr_remote_sync_change (table_name, row_id, deleted_key) SELECT '{table_name}', OLD.rowid, json_array({}){condition};",
+                key_of("OLD")
+            )
+        };
+        let mut sql = format!(
+            "CREATE TRIGGER IF NOT EXISTS \"surveilr_remote_sync_{table_name}_insert\" AFTER INSERT ON \"{table_name}\" BEGIN {} END;",
+            log("NEW")
+        );
+        if key.is_empty() {
+            sql.push_str(&format!(
+                "CREATE TRIGGER IF NOT EXISTS \"surveilr_remote_sync_{table_name}_update\" AFTER UPDATE ON \"{table_name}\" BEGIN {} END;",
+                log("NEW")
+            ));
+        } else {
+            // a row whose key changed is deleted under its old one
+            let key_changed = key
+                .iter()
+                .map(|column| format!("OLD.\"{column}\" IS NOT NEW.\"{column}\""))
+                .collect::<Vec<_>>()
+                .join(" OR ");
+            sql.push_str(&format!(
+                "CREATE TRIGGER IF NOT EXISTS \"surveilr_remote_sync_{table_name}_update\" AFTER UPDATE ON \"{table_name}\" BEGIN {} {} END;
+                 CREATE TRIGGER IF NOT EXISTS \"surveilr_remote_sync_{table_name}_delete\" AFTER DELETE ON \"{table_name}\" BEGIN {} END;",
+                log_delete(&format!(" WHERE {key_changed}")),
+                log("NEW"),
+                log_delete("")
+            ));
+        }
+        conn.execute_batch(&sql)
+            .with_context(|| format!("[track_changes] triggers of {}", table_name))?;
+    }
+    Ok(())
+}
+
+/// `INSERT OR REPLACE` of rows of `table_name`, the columns of `stmt` after its
+/// leading rowid.
+fn upsert_sql(table_name: &str, stmt: &rusqlite::Statement) -> (String, usize) {
+    let columns: Vec<String> = stmt
+        .column_names()
+        .into_iter()
+        .skip(1)
+        .map(|column| format!("\"{}\"", column))
+        .collect();
+    let sql = format!(
+        "INSERT OR REPLACE INTO \"{}\" ({}) VALUES ({})",
+        table_name,
+        columns.join(", "),
+        vec!["?"; columns.len()].join(", ")
+    );
+    (sql, columns.len())
+}
+
+/// The arguments of a row's upsert and about how many bytes they are.
+fn upsert_args(row: &rusqlite::Row, columns: usize) -> Result<(Vec<RemoteValue>, usize)> {
+    let args = (1..=columns)
+        .map(|i| row.get_ref(i).map(RemoteValue::from))
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let bytes = args
+        .iter()
+        .map(|arg| match arg {
+            RemoteValue::Text { value } => value.len(),
+            RemoteValue::Blob { base64 } => base64.len(),
+            _ => 8,
+        })
+        .sum();
+    Ok((args, bytes))
+}
+
+/// The next rows to push to `remote_url`, from the first table which has rows
+/// it wasn't pushed yet; `None` when everything was pushed. A table's first
+/// sync pushes all of its rows, later ones what `track_changes` recorded since.
+pub fn next_sync_batch(conn: &Connection, remote_url: &str) -> Result<Option<SyncBatch>> {
+    for table_name in synced_tables(conn)? {
+        let synced = conn
+            .query_row(
+                "SELECT synced_rowid, synced_change_id, scanned_at IS NOT NULL FROM surveilr_remote_sync WHERE remote_url = ? AND table_name = ?",
+                params![remote_url, table_name],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()?;
+        let (synced_rowid, synced_change_id, scanned) = match synced {
+            Some(synced) => synced,
+            // the scan pushes the rows as they are, changes recorded until now included
+            None => (
+                0,
+                conn.query_row(
+                    "SELECT COALESCE(MAX(surveilr_remote_sync_change_id), 0) FROM surveilr_remote_sync_change",
+                    [],
+                    |row| row.get(0),
+                )?,
+                false,
+            ),
+        };
+        let mut batch = SyncBatch {
+            table_name: table_name.clone(),
+            last_rowid: synced_rowid,
+            last_change_id: synced_change_id,
+            scanned,
+            rows: 0,
+            statements: vec![],
+        };
+        let mut bytes = 0;
+
+        if !scanned {
+            let mut stmt = conn
+                .prepare(&format!(
+                    "SELECT rowid, * FROM \"{table_name}\" WHERE rowid > ? ORDER BY rowid LIMIT {SYNC_BATCH_ROWS}"
+                ))
+                .with_context(|| format!("[next_sync_batch] rows of {}", table_name))?;
+            let (sql, columns) = upsert_sql(&table_name, &stmt);
+            let mut rows = stmt.query([synced_rowid])?;
+            while let Some(row) = rows.next()? {
+                let (args, size) = upsert_args(row, columns)?;
+                bytes += size;
+                batch.last_rowid = row.get(0)?;
+                batch.rows += 1;
+                batch.statements.push(RemoteStatement {
+                    sql: sql.clone(),
+                    args,
+                });
+                if bytes >= SYNC_BATCH_BYTES {
+                    break;
+                }
+            }
+            if batch.rows > 0 {
+                return Ok(Some(batch));
+            }
+            batch.scanned = true;
+        }
+
+        let mut row_stmt = conn
+            .prepare(&format!(
+                "SELECT rowid, * FROM \"{table_name}\" WHERE rowid = ?"
+            ))
+            .with_context(|| format!("[next_sync_batch] rows of {}", table_name))?;
+        let (sql, columns) = upsert_sql(&table_name, &row_stmt);
+        let key = primary_key(conn, &table_name)?;
+        let delete_sql = format!(
+            "DELETE FROM \"{}\" WHERE {}",
+            table_name,
+            key.iter()
+                .map(|column| format!("\"{}\" = ?", column))
+                .collect::<Vec<_>>()
+                .join(" AND ")
+        );
+        let mut changes_stmt = conn.prepare(&format!(
+            "SELECT surveilr_remote_sync_change_id, row_id, deleted_key FROM surveilr_remote_sync_change
+              WHERE table_name = ? AND surveilr_remote_sync_change_id > ?
+              ORDER BY surveilr_remote_sync_change_id LIMIT {SYNC_BATCH_ROWS}"
+        ))?;
+        let mut changes = changes_stmt.query(params![table_name, synced_change_id])?;
+        while let Some(change) = changes.next()? {
+            batch.last_change_id = change.get(0)?;
+            match change.get::<_, Option<String>>(2)? {
+                Some(deleted_key) => {
+                    let args = serde_json::from_str::<Vec<JsonValue>>(&deleted_key)?
+                        .into_iter()
+                        .map(|value| match value {
+                            JsonValue::Null => RemoteValue::Null,
+                            JsonValue::Number(n) if n.is_i64() => RemoteValue::Integer {
+                                value: n.to_string(),
+                            },
+                            JsonValue::Number(n) => RemoteValue::Float {
+                                value: n.as_f64().unwrap_or_default(),
+                            },
+                            JsonValue::String(value) => RemoteValue::Text { value },
+                            other => RemoteValue::Text {
+                                value: other.to_string(),
+                            },
+                        })
+                        .collect();
+                    bytes += deleted_key.len();
+                    batch.statements.push(RemoteStatement {
+                        sql: delete_sql.clone(),
+                        args,
+                    });
+                }
+                None => {
+                    // rows deleted since are pushed by their own change
+                    let mut rows = row_stmt.query([change.get::<_, i64>(1)?])?;
+                    let Some(row) = rows.next()? else {
+                        continue;
+                    };
+                    let (args, size) = upsert_args(row, columns)?;
+                    bytes += size;
+                    batch.statements.push(RemoteStatement {
+                        sql: sql.clone(),
+                        args,
+                    });
+                }
+            }
+            batch.rows += 1;
+            if bytes >= SYNC_BATCH_BYTES {
+                break;
+            }
+        }
+        if batch.rows > 0
+            || batch.scanned != scanned
+            || batch.last_change_id != synced_change_id
+            || synced.is_none()
+        {
+            return Ok(Some(batch));
+        }
+    }
+    Ok(None)
+}
+
+/// Record that `batch` was pushed so the next sync starts after it.
+pub fn mark_synced(conn: &Connection, remote_url: &str, batch: &SyncBatch) -> Result<()> {
+    conn.execute(
+        UPSERT_REMOTE_SYNC_SQL,
+        params![
+            remote_url,
+            batch.table_name,
+            batch.last_rowid,
+            batch.rows,
+            batch.last_change_id,
+            batch.scanned
+        ],
+    )
+    .with_context(|| format!("[mark_synced] {} in {}", batch.table_name, remote_url))?;
+    conn.execute(PRUNE_REMOTE_SYNC_CHANGES_SQL, [&batch.table_name])
+        .with_context(|| format!("[mark_synced] changes of {}", batch.table_name))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::types::Value;
+
+    use super::*;
+    use crate::persist::upserted_device;
+
+    /// Run statements the way the remote would, against a local database.
+    fn apply(remote: &Connection, statements: &[RemoteStatement]) -> Result<()> {
+        for statement in statements {
+            let args: Vec<Value> = statement
+                .args
+                .iter()
+                .map(|arg| match arg {
+                    RemoteValue::Null => Value::Null,
+                    RemoteValue::Integer { value } => Value::Integer(value.parse().unwrap()),
+                    RemoteValue::Float { value } => Value::Real(*value),
+                    RemoteValue::Text { value } => Value::Text(value.clone()),
+                    RemoteValue::Blob { base64 } => {
+                        Value::Blob(STANDARD_NO_PAD.decode(base64).unwrap())
+                    }
+                })
+                .collect();
+            remote.execute(&statement.sql, rusqlite::params_from_iter(args))?;
+        }
+        Ok(())
+    }
+
+    fn count(conn: &Connection, table: &str) -> Result<i64> {
+        Ok(
+            conn.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+                row.get(0)
+            })?,
+        )
+    }
+
+    #[test]
+    fn test_remote_state_db_url() -> Result<()> {
+        let remote = RemoteStateDb::new("libsql://evidence-acme.turso.io?authToken=secret", None)?;
+        assert_eq!(remote.url, "libsql://evidence-acme.turso.io");
+        assert_eq!(
+            remote.pipeline_url,
+            "https://evidence-acme.turso.io/v2/pipeline"
+        );
+        assert_eq!(remote.auth_token.as_deref(), Some("secret"));
+        assert_eq!(
+            remote.replica_fs_path,
+            "evidence-acme.turso.io.replica.sqlite.db"
+        );
+
+        let remote = RemoteStateDb::new("http://127.0.0.1:8080/", Some("edge.sqlite.db"))?;
+        assert_eq!(remote.pipeline_url, "http://127.0.0.1:8080/v2/pipeline");
+        assert_eq!(remote.replica_fs_path, "edge.sqlite.db");
+        assert!(is_remote_state_db("libsql://evidence-acme.turso.io"));
+        assert!(!is_remote_state_db("resource-surveillance.sqlite.db"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_pipeline_errors() {
+        let ok = json!({ "results": [
+            { "type": "ok", "response": { "type": "batch", "result": { "step_results": [], "step_errors": [null, null] } } },
+            { "type": "ok", "response": { "type": "close" } },
+        ]});
+        assert_eq!(pipeline_error(&ok), None);
+        let failed = json!({ "results": [
+            { "type": "ok", "response": { "type": "batch", "result": { "step_errors": [null, { "message": "no such table: device" }] } } },
+        ]});
+        assert_eq!(
+            pipeline_error(&failed).as_deref(),
+            Some("no such table: device")
+        );
+        let rejected =
+            json!({ "results": [{ "type": "error", "error": { "message": "unauthorized" } }] });
+        assert_eq!(pipeline_error(&rejected).as_deref(), Some("unauthorized"));
+    }
+
+    #[test]
+    fn test_sync_pushes_new_rows_once() -> Result<()> {
+        let mut dbc = DbConn::new(":memory:", 0)?;
+        let tx = dbc.init(None)?;
+        upserted_device(&tx, &common::DEVICE)?;
+        tx.commit()?;
+        let replica = &dbc.conn;
+        let remote = Connection::open_in_memory()?;
+        let url = "libsql://evidence-acme.turso.io";
+
+        let sync = |replica: &Connection| -> Result<usize> {
+            track_changes(replica)?;
+            apply(&remote, &remote_schema(replica)?)?;
+            let mut rows = 0;
+            while let Some(batch) = next_sync_batch(replica, url)? {
+                apply(&remote, &batch.statements)?;
+                mark_synced(replica, url, &batch)?;
+                rows += batch.rows;
+            }
+            Ok(rows)
+        };
+
+        let pushed = sync(replica)?;
+        assert!(pushed > 0);
+        assert_eq!(count(&remote, "device")?, 1);
+        assert_eq!(
+            count(&remote, "code_notebook_cell")?,
+            count(replica, "code_notebook_cell")?
+        );
+        // the bookkeeping stays local
+        let synced: i64 = remote.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE name = 'surveilr_remote_sync'",
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(synced, 0);
+
+        assert_eq!(sync(replica)?, 0);
+        replica.execute(
+            "INSERT INTO device (device_id, name, state, boundary) VALUES ('edge-2', 'edge-2', '{}', 'edge')",
+            [],
+        )?;
+        assert_eq!(sync(replica)?, 1);
+        assert_eq!(count(&remote, "device")?, 2);
+        let (synced_rows, device_syncs): (i64, i64) = replica.query_row(
+            "SELECT synced_rows, (SELECT COUNT(*) FROM surveilr_remote_sync WHERE table_name = 'device')
+               FROM surveilr_remote_sync
+              WHERE remote_url = ? AND table_name = 'device'",
+            [url],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        assert_eq!((synced_rows, device_syncs), (2, 1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_sync_pushes_updates_and_deletes() -> Result<()> {
+        let mut dbc = DbConn::new(":memory:", 0)?;
+        let tx = dbc.init(None)?;
+        upserted_device(&tx, &common::DEVICE)?;
+        tx.commit()?;
+        let replica = &dbc.conn;
+        let remote = Connection::open_in_memory()?;
+        let url = "libsql://evidence-acme.turso.io";
+
+        let sync = |replica: &Connection| -> Result<()> {
+            track_changes(replica)?;
+            apply(&remote, &remote_schema(replica)?)?;
+            while let Some(batch) = next_sync_batch(replica, url)? {
+                apply(&remote, &batch.statements)?;
+                mark_synced(replica, url, &batch)?;
+            }
+            Ok(())
+        };
+        let devices = |conn: &Connection| -> Result<Vec<(String, String, String)>> {
+            Ok(conn
+                .prepare("SELECT device_id, name, boundary FROM device ORDER BY device_id")?
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+                .collect::<rusqlite::Result<Vec<_>>>()?)
+        };
+
+        sync(replica)?;
+        replica.execute(
+            "INSERT INTO device (device_id, name, state, boundary) VALUES ('edge-2', 'edge-2', '{}', 'edge')",
+            [],
+        )?;
+        sync(replica)?;
+        assert_eq!(count(&remote, "device")?, 2);
+
+        // updated in place, deleted, and a deleted row's rowid reused by another row
+        replica.execute(
+            "UPDATE device SET boundary = 'reclassified' WHERE device_id <> 'edge-2'",
+            [],
+        )?;
+        let rowid: i64 = replica.query_row(
+            "SELECT rowid FROM device WHERE device_id = 'edge-2'",
+            [],
+            |row| row.get(0),
+        )?;
+        replica.execute("DELETE FROM device WHERE device_id = 'edge-2'", [])?;
+        replica.execute(
+            "INSERT INTO device (rowid, device_id, name, state, boundary) VALUES (?, 'edge-3', 'edge-3', '{}', 'edge')",
+            [rowid],
+        )?;
+        // a key which changed is deleted under the old one
+        replica.execute(
+            "UPDATE device SET device_id = 'edge-4' WHERE device_id = 'edge-3'",
+            [],
+        )?;
+        sync(replica)?;
+        assert_eq!(devices(&remote)?, devices(replica)?);
+        assert_eq!(count(&remote, "device")?, 2);
+        let boundary: String = remote.query_row(
+            "SELECT boundary FROM device WHERE device_id <> 'edge-4'",
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(boundary, "reclassified");
+
+        // changes every remote was pushed aren't kept
+        assert_eq!(count(replica, "surveilr_remote_sync_change")?, 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::HashSet;
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, TimeZone};
+use croner::Cron;
+use serde::Deserialize;
+
+/// A standard five-field cron expression (minute, hour, day of month, month and
+/// day of week) or one of the `@hourly`, `@daily`, `@weekly`, `@monthly` and
+/// `@yearly` macros, parsed and evaluated with `croner`. As in cron, a day
+/// matches when either the day of month or the day of week does if both are
+/// restricted.
+#[derive(Debug, Clone)]
+pub struct CronSchedule {
+    expression: String,
+    cron: Cron,
+}
+
+impl CronSchedule {
+    pub fn parse(expression: &str) -> Result<Self> {
+        let expression = expression.trim();
+        // croner knows every macro of cron but `@midnight`
+        let pattern = if expression.eq_ignore_ascii_case("@midnight") {
+            "@daily"
+        } else {
+            expression
+        };
+        let cron = Cron::new(pattern)
+            .parse()
+            .map_err(|err| anyhow!("[CronSchedule::parse] `{expression}`: {err}"))?;
+        Ok(CronSchedule {
+            expression: expression.to_string(),
+            cron,
+        })
+    }
+
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+
+    /// The first minute after `after` the schedule matches, in `after`'s time
+    /// zone. Local times skipped by a daylight saving change run when the
+    /// clocks have moved forward, repeated ones the first time. `None` when
+    /// nothing ever matches (e.g. `0 0 31 2 *`).
+    pub fn next_after<Tz: TimeZone>(&self, after: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+        self.cron
+            .find_next_occurrence(after, false)
+            .ok()
+            .filter(|next| next > after)
+    }
+}
+
+/// A command run on a cron schedule by `orchestrate`.
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    pub name: String,
+    pub cron: CronSchedule,
+    /// the `surveilr` arguments, without the program name
+    pub command: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScheduleSpec {
+    name: String,
+    cron: String,
+    command: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SchedulesSpec {
+    #[serde(default)]
+    schedule: Vec<ScheduleSpec>,
+}
+
+/// The `[[schedule]]` tables of a schedules TOML document.
+pub fn parse_schedules(toml_text: &str) -> Result<Vec<Schedule>> {
+    let spec: SchedulesSpec =
+        toml::from_str(toml_text).context("[parse_schedules] invalid schedules TOML")?;
+    if spec.schedule.is_empty() {
+        bail!("[parse_schedules] no [[schedule]] tables");
+    }
+    let mut names = HashSet::new();
+    spec.schedule
+        .into_iter()
+        .map(|spec| {
+            if !names.insert(spec.name.clone()) {
+                bail!(
+                    "[parse_schedules] schedule `{}` is defined twice",
+                    spec.name
+                );
+            }
+            if spec.command.is_empty() {
+                bail!("[parse_schedules] schedule `{}` has no command", spec.name);
+            }
+            Ok(Schedule {
+                cron: CronSchedule::parse(&spec.cron)
+                    .with_context(|| format!("[parse_schedules] schedule `{}`", spec.name))?,
+                name: spec.name,
+                command: spec.command,
+            })
+        })
+        .collect()
+}
+
+/// The schedules in the TOML file at `path`.
+pub fn read_schedules(path: &str) -> Result<Vec<Schedule>> {
+    let toml_text = std::fs::read_to_string(path)
+        .with_context(|| format!("[read_schedules] unable to read {path}"))?;
+    parse_schedules(&toml_text).with_context(|| format!("[read_schedules] {path}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn next(expression: &str, after: &str) -> String {
+        let after = DateTime::parse_from_rfc3339(after)
+            .unwrap()
+            .with_timezone(&Utc);
+        CronSchedule::parse(expression)
+            .unwrap()
+            .next_after(&after)
+            .map(|next| next.to_rfc3339())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn cron_next_after() {
+        assert_eq!(
+            next("*/15 * * * *", "2024-03-01T10:07:30Z"),
+            "2024-03-01T10:15:00+00:00"
+        );
+        assert_eq!(
+            next("0 9 * * mon-fri", "2024-03-01T09:00:00Z"),
+            "2024-03-04T09:00:00+00:00"
+        );
+        assert_eq!(
+            next("@monthly", "2024-12-15T00:00:00Z"),
+            "2025-01-01T00:00:00+00:00"
+        );
+        assert_eq!(
+            next("@midnight", "2024-12-15T00:00:00Z"),
+            "2024-12-16T00:00:00+00:00"
+        );
+        assert_eq!(
+            next("30 2 29 feb *", "2024-03-01T00:00:00Z"),
+            "2028-02-29T02:30:00+00:00"
+        );
+        // either the day of month or the day of week (Sunday as 7)
+        assert_eq!(
+            next("0 0 13 * 7", "2024-09-01T00:00:00Z"),
+            "2024-09-08T00:00:00+00:00"
+        );
+        assert_eq!(next("0 0 31 2 *", "2024-01-01T00:00:00Z"), "");
+    }
+
+    #[test]
+    fn cron_invalid() {
+        for expression in [
+            "* * * *",
+            "60 * * * *",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "0 0 * foo *",
+            "0 * * * * *",
+        ] {
+            assert!(CronSchedule::parse(expression).is_err(), "{expression}");
+        }
+    }
+
+    #[test]
+    fn schedules_toml() {
+        let schedules = parse_schedules(
+            r#"
+            [[schedule]]
+            name = "docs"
+            cron = "0 */6 * * *"
+            command = ["ingest", "files", "-r", "docs"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(schedules.len(), 1);
+        assert_eq!(schedules[0].cron.expression(), "0 */6 * * *");
+        assert_eq!(schedules[0].command[1], "files");
+
+        assert!(parse_schedules(
+            r#"
+            [[schedule]]
+            name = "docs"
+            cron = "@daily"
+            command = []
+            "#
+        )
+        .is_err());
+    }
+}
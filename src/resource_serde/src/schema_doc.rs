@@ -0,0 +1,491 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+use anyhow::{Context, Result};
+use rusqlite::Connection;
+use serde::Serialize;
+use serde_json::{json, Map, Value as JsonValue};
+
+use crate::persist::{bootstrap_ddl, prepare_conn, select_notebook_cell_code_latest};
+
+const COMMENTS_NOTEBOOK: &str = "QuerySqlNotebook";
+const COMMENTS_CELL: &str = "infoSchemaComments";
+
+/// Which diagram syntax `admin schema-doc` embeds in the generated Markdown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, clap::ValueEnum)]
+pub enum SchemaDocDiagram {
+    #[default]
+    Mermaid,
+    Dot,
+}
+
+/// The machine-readable description `admin schema-export` generates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, clap::ValueEnum)]
+pub enum SchemaExportFormat {
+    /// a JSON Schema (draft 2020-12) document with one definition per table
+    #[default]
+    JsonSchema,
+    /// an OpenAPI 3.1 document with one component schema per table and view
+    OpenApi,
+}
+
+#[derive(Debug)]
+struct Column {
+    name: String,
+    data_type: String,
+    not_null: bool,
+    default_value: Option<String>,
+    primary_key: bool,
+}
+
+#[derive(Debug)]
+struct ForeignKey {
+    from: String,
+    table: String,
+    to: String,
+}
+
+#[derive(Debug)]
+struct Relation {
+    name: String,
+    is_view: bool,
+    columns: Vec<Column>,
+    foreign_keys: Vec<ForeignKey>,
+}
+
+/// table name -> (table description, column name -> column description)
+type Comments = HashMap<String, (Option<String>, HashMap<String, String>)>;
+
+fn relations(conn: &Connection) -> Result<Vec<Relation>> {
+    let mut stmt = conn.prepare(
+        "SELECT name, type FROM sqlite_master
+          WHERE type IN ('table', 'view') AND name NOT LIKE 'sqlite_%'
+          ORDER BY type, name",
+    )?;
+    let names = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)? == "view"))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut result = Vec::new();
+    for (name, is_view) in names {
+        let columns = conn
+            .prepare("SELECT name, type, \"notnull\", dflt_value, pk FROM pragma_table_info(?)")?
+            .query_map([&name], |row| {
+                Ok(Column {
+                    name: row.get(0)?,
+                    data_type: row.get(1)?,
+                    not_null: row.get(2)?,
+                    default_value: row.get(3)?,
+                    primary_key: row.get::<_, i64>(4)? > 0,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let foreign_keys = conn
+            .prepare("SELECT \"from\", \"table\", \"to\" FROM pragma_foreign_key_list(?)")?
+            .query_map([&name], |row| {
+                Ok(ForeignKey {
+                    from: row.get(0)?,
+                    table: row.get(1)?,
+                    to: row.get(2)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        result.push(Relation {
+            name,
+            is_view,
+            columns,
+            foreign_keys,
+        });
+    }
+    Ok(result)
+}
+
+/// Table and column descriptions come from the `infoSchemaComments` bootstrap
+/// notebook cell; RSSDs which predate the cell use the one from this build.
+fn comments(conn: &Connection) -> Result<Comments> {
+    let mut comments = Comments::new();
+    let sql = match select_notebook_cell_code_latest(conn, COMMENTS_NOTEBOOK, COMMENTS_CELL) {
+        Ok((_, sql)) => sql,
+        Err(_) => {
+            let bootstrapped = Connection::open_in_memory()?;
+            prepare_conn(&bootstrapped)?;
+            bootstrap_ddl(&bootstrapped)?;
+            select_notebook_cell_code_latest(&bootstrapped, COMMENTS_NOTEBOOK, COMMENTS_CELL)?.1
+        }
+    };
+    let mut stmt = conn.prepare(&sql).with_context(|| {
+        format!("[schema_doc::comments] preparing {COMMENTS_NOTEBOOK}::{COMMENTS_CELL}")
+    })?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let table: String = row.get(0)?;
+        let column: Option<String> = row.get(1)?;
+        let description: String = row.get(2)?;
+        let entry = comments.entry(table).or_default();
+        match column {
+            Some(column) => {
+                entry.1.insert(column, description);
+            }
+            None => entry.0 = Some(description),
+        }
+    }
+    Ok(comments)
+}
+
+/// The most recently executed `_once_` migration, used as the schema version.
+fn schema_version(conn: &Connection) -> Option<String> {
+    conn.query_row(
+        "SELECT cnc.cell_name
+           FROM code_notebook_state cns
+           JOIN code_notebook_cell cnc ON cns.code_notebook_cell_id = cnc.code_notebook_cell_id
+          WHERE cns.to_state = 'EXECUTED' AND cnc.cell_name LIKE '%_once_%'
+          ORDER BY cnc.cell_name DESC
+          LIMIT 1",
+        [],
+        |row| row.get(0),
+    )
+    .ok()
+}
+
+fn mermaid_token(text: &str) -> String {
+    let token: String = text
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if token.is_empty() {
+        "ANY".to_string()
+    } else {
+        token
+    }
+}
+
+fn mermaid(tables: &[&Relation]) -> String {
+    let mut out = String::from("```mermaid\nerDiagram\n");
+    for table in tables {
+        let _ = writeln!(out, "    {} {{", table.name);
+        for column in &table.columns {
+            let keys: Vec<&str> = [
+                column.primary_key.then_some("PK"),
+                table
+                    .foreign_keys
+                    .iter()
+                    .any(|fk| fk.from == column.name)
+                    .then_some("FK"),
+            ]
+            .into_iter()
+            .flatten()
+            .collect();
+            let line = format!(
+                "        {} {} {}",
+                mermaid_token(&column.data_type),
+                mermaid_token(&column.name),
+                keys.join(",")
+            );
+            let _ = writeln!(out, "{}", line.trim_end());
+        }
+        out.push_str("    }\n");
+    }
+    for table in tables {
+        for fk in &table.foreign_keys {
+            let _ = writeln!(
+                out,
+                "    {} ||--o{{ {} : \"{}\"",
+                fk.table, table.name, fk.from
+            );
+        }
+    }
+    out.push_str("```\n");
+    out
+}
+
+fn dot_escape(text: &str) -> String {
+    text.chars()
+        .flat_map(|c| match c {
+            '{' | '}' | '|' | '<' | '>' | '"' | '\\' => vec!['\\', c],
+            _ => vec![c],
+        })
+        .collect()
+}
+
+fn dot(tables: &[&Relation]) -> String {
+    let mut out =
+        String::from("```dot\ndigraph rssd {\n    rankdir=LR;\n    node [shape=record];\n");
+    for table in tables {
+        let columns: Vec<String> = table
+            .columns
+            .iter()
+            .map(|c| {
+                format!(
+                    "{}{} : {}\\l",
+                    if c.primary_key { "* " } else { "" },
+                    dot_escape(&c.name),
+                    dot_escape(&c.data_type)
+                )
+            })
+            .collect();
+        let _ = writeln!(
+            out,
+            "    \"{}\" [label=\"{{{}|{}}}\"];",
+            table.name,
+            dot_escape(&table.name),
+            columns.join("")
+        );
+    }
+    for table in tables {
+        for fk in &table.foreign_keys {
+            let _ = writeln!(
+                out,
+                "    \"{}\" -> \"{}\" [label=\"{}\"];",
+                table.name,
+                fk.table,
+                dot_escape(&fk.from)
+            );
+        }
+    }
+    out.push_str("}\n```\n");
+    out
+}
+
+/// Introspect a live RSSD and emit ER-style Markdown documentation with an
+/// embedded Mermaid or DOT diagram.
+pub fn schema_doc(conn: &Connection, diagram: SchemaDocDiagram) -> Result<String> {
+    let relations = relations(conn).context("[schema_doc] introspecting schema")?;
+    let comments = comments(conn)?;
+    let tables: Vec<&Relation> = relations.iter().filter(|r| !r.is_view).collect();
+    let views: Vec<&Relation> = relations.iter().filter(|r| r.is_view).collect();
+
+    let mut out = String::from("# RSSD Schema\n\n");
+    if let Some(version) = schema_version(conn) {
+        let _ = writeln!(out, "Schema version (latest migration): `{}`\n", version);
+    }
+
+    out.push_str("## Entity Relationship Diagram\n\n");
+    out.push_str(&match diagram {
+        SchemaDocDiagram::Mermaid => mermaid(&tables),
+        SchemaDocDiagram::Dot => dot(&tables),
+    });
+
+    let no_comments = (None, HashMap::new());
+    out.push_str("\n## Tables\n");
+    for table in &tables {
+        let (description, column_comments) = comments.get(&table.name).unwrap_or(&no_comments);
+        let references: BTreeMap<&str, String> = table
+            .foreign_keys
+            .iter()
+            .map(|fk| (fk.from.as_str(), format!("{}.{}", fk.table, fk.to)))
+            .collect();
+        let _ = writeln!(out, "\n### `{}`\n", table.name);
+        if let Some(description) = description {
+            let _ = writeln!(out, "{}\n", description);
+        }
+        out.push_str("| PK | Column | Type | Req? | Default | References | Description |\n");
+        out.push_str("| -- | ------ | ---- | ---- | ------- | ---------- | ----------- |\n");
+        for column in &table.columns {
+            let _ = writeln!(
+                out,
+                "| {} | {} | {} | {} | {} | {} | {} |",
+                if column.primary_key { "*" } else { "" },
+                column.name,
+                column.data_type,
+                if column.not_null { "*" } else { "" },
+                column
+                    .default_value
+                    .as_deref()
+                    .unwrap_or("")
+                    .replace('|', "\\|"),
+                references
+                    .get(column.name.as_str())
+                    .map(String::as_str)
+                    .unwrap_or(""),
+                column_comments
+                    .get(&column.name)
+                    .map(|d| d.replace('|', "\\|"))
+                    .unwrap_or_default()
+            );
+        }
+    }
+
+    if !views.is_empty() {
+        out.push_str("\n## Views\n\n| View | Columns |\n| ---- | ------- |\n");
+        for view in &views {
+            let columns: Vec<&str> = view.columns.iter().map(|c| c.name.as_str()).collect();
+            let _ = writeln!(out, "| {} | {} |", view.name, columns.join(", "));
+        }
+    }
+
+    Ok(out)
+}
+
+/// The JSON Schema of a value stored in a column of the given SQLite type,
+/// following SQLite's type affinity rules.
+fn column_json_type(data_type: &str) -> JsonValue {
+    let upper = data_type.to_ascii_uppercase();
+    if upper.contains("INT") {
+        json!({ "type": "integer" })
+    } else if upper.contains("TIMESTAMP") || upper.contains("DATETIME") {
+        json!({ "type": "string", "format": "date-time" })
+    } else if upper == "DATE" {
+        json!({ "type": "string", "format": "date" })
+    } else if upper.contains("CHAR") || upper.contains("CLOB") || upper.contains("TEXT") {
+        json!({ "type": "string" })
+    } else if upper.contains("REAL") || upper.contains("FLOA") || upper.contains("DOUB") {
+        json!({ "type": "number" })
+    } else if upper.contains("BOOL") {
+        json!({ "type": "boolean" })
+    } else if upper.contains("BLOB") {
+        json!({ "type": "string", "contentEncoding": "base64" })
+    } else {
+        // untyped columns (e.g. in views) may hold anything
+        json!({})
+    }
+}
+
+fn relation_schema(relation: &Relation, comments: &Comments) -> JsonValue {
+    let no_comments = (None, HashMap::new());
+    let (description, column_comments) = comments.get(&relation.name).unwrap_or(&no_comments);
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+    for column in &relation.columns {
+        let mut property = column_json_type(&column.data_type);
+        let property_map = property
+            .as_object_mut()
+            .expect("column schemas are objects");
+        if let Some(description) = column_comments.get(&column.name) {
+            property_map.insert("description".to_string(), json!(description));
+        }
+        let nullable = !column.not_null && !column.primary_key;
+        if nullable {
+            if let Some(data_type) = property_map.get("type").cloned() {
+                property_map.insert("type".to_string(), json!([data_type, "null"]));
+            }
+        }
+        property_map.insert("x-sqlite-type".to_string(), json!(column.data_type));
+        if column.primary_key {
+            property_map.insert("x-primary-key".to_string(), json!(true));
+        }
+        if let Some(fk) = relation
+            .foreign_keys
+            .iter()
+            .find(|fk| fk.from == column.name)
+        {
+            property_map.insert(
+                "x-references".to_string(),
+                json!(format!("{}.{}", fk.table, fk.to)),
+            );
+        }
+        if let Some(default_value) = &column.default_value {
+            property_map.insert("x-sqlite-default".to_string(), json!(default_value));
+        } else if column.not_null && !relation.is_view {
+            required.push(column.name.clone());
+        }
+        properties.insert(column.name.clone(), property);
+    }
+
+    let mut schema = Map::new();
+    schema.insert("type".to_string(), json!("object"));
+    schema.insert("title".to_string(), json!(relation.name));
+    if let Some(description) = description {
+        schema.insert("description".to_string(), json!(description));
+    }
+    schema.insert("properties".to_string(), JsonValue::Object(properties));
+    if !required.is_empty() {
+        schema.insert("required".to_string(), json!(required));
+    }
+    if relation.is_view {
+        schema.insert("x-sqlite-view".to_string(), json!(true));
+    }
+    JsonValue::Object(schema)
+}
+
+/// Introspect a live RSSD and describe each table (and view) as a JSON Schema
+/// so integrators can code against the evidence model without reading the
+/// bootstrap SQL. The document is stamped with the surveilr release and the
+/// RSSD's latest migration.
+pub fn schema_export(conn: &Connection, format: SchemaExportFormat) -> Result<JsonValue> {
+    let relations = relations(conn).context("[schema_export] introspecting schema")?;
+    let comments = comments(conn)?;
+    let version = env!("CARGO_PKG_VERSION");
+    let migration = schema_version(conn);
+
+    let definitions: Map<String, JsonValue> = relations
+        .iter()
+        .filter(|r| format == SchemaExportFormat::OpenApi || !r.is_view)
+        .map(|r| (r.name.clone(), relation_schema(r, &comments)))
+        .collect();
+
+    Ok(match format {
+        SchemaExportFormat::JsonSchema => json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "title": "surveilr Resource Surveillance State Database (RSSD)",
+            "x-surveilr-version": version,
+            "x-rssd-migration": migration,
+            "$defs": definitions,
+        }),
+        SchemaExportFormat::OpenApi => json!({
+            "openapi": "3.1.0",
+            "info": {
+                "title": "surveilr Resource Surveillance State Database (RSSD)",
+                "version": version,
+                "x-rssd-migration": migration,
+            },
+            "paths": {},
+            "components": { "schemas": definitions },
+        }),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persist::DbConn;
+
+    #[test]
+    fn test_schema_doc_from_bootstrapped_rssd() -> Result<()> {
+        let mut dbc = DbConn::new(":memory:", 0)?;
+        dbc.init(None)?.commit()?;
+
+        let markdown = schema_doc(&dbc.conn, SchemaDocDiagram::Mermaid)?;
+        assert!(markdown.contains("```mermaid\nerDiagram\n"));
+        assert!(markdown.contains("    device ||--o{ uniform_resource : \"device_id\""));
+        assert!(markdown.contains("### `uniform_resource_lineage`"));
+        // descriptions are sourced from the infoSchemaComments notebook cell
+        assert!(markdown.contains("unique device identifier (defaults to hostname)"));
+
+        let markdown = schema_doc(&dbc.conn, SchemaDocDiagram::Dot)?;
+        assert!(markdown.contains("\"uniform_resource\" -> \"device\" [label=\"device_id\"];"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_schema_export_from_bootstrapped_rssd() -> Result<()> {
+        let mut dbc = DbConn::new(":memory:", 0)?;
+        dbc.init(None)?.commit()?;
+
+        let schema = schema_export(&dbc.conn, SchemaExportFormat::JsonSchema)?;
+        assert_eq!(schema["x-surveilr-version"], env!("CARGO_PKG_VERSION"));
+        let ur = &schema["$defs"]["uniform_resource"];
+        assert_eq!(
+            ur["properties"]["device_id"]["x-references"],
+            "device.device_id"
+        );
+        assert_eq!(
+            ur["properties"]["size_bytes"]["type"],
+            json!(["integer", "null"])
+        );
+        assert!(ur["required"]
+            .as_array()
+            .unwrap()
+            .contains(&json!("device_id")));
+        assert_eq!(
+            schema["$defs"]["device"]["properties"]["name"]["description"],
+            "unique device identifier (defaults to hostname)"
+        );
+
+        let openapi = schema_export(&dbc.conn, SchemaExportFormat::OpenApi)?;
+        assert_eq!(openapi["openapi"], "3.1.0");
+        assert!(openapi["components"]["schemas"]["uniform_resource"].is_object());
+        Ok(())
+    }
+}
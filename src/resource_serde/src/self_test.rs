@@ -0,0 +1,318 @@
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use anyhow::{anyhow, Context, Result};
+use clap::Parser;
+use serde::Serialize;
+
+use crate::cmd::IngestFilesArgs;
+use crate::ingest::ingest_files;
+use crate::persist::{upserted_device, DbConn};
+
+const FIXTURE_MARKDOWN: &str = "---
+title: surveilr self-test
+tags: [self-test]
+---
+
+This file was written by `surveilr admin self-test`.
+";
+const FIXTURE_JSON: &str = r#"{ "self_test": { "version": 1 } }"#;
+const FIXTURE_TEXT: &str = "plain text written by surveilr admin self-test\n";
+const FIXTURE_CE_NAME: &str = "self-test.surveilr[json].sh";
+// capturable executables are handed the ingestion's context on STDIN, it's
+// drained so writing it never fails with a broken pipe
+const FIXTURE_CE: &str = "#!/bin/sh\ncat > /dev/null\necho '{ \"captured\": true }'\n";
+const FIXTURE_CE_OUTPUT: &str = "{ \"captured\": true }\n";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SelfTestOutcome {
+    Passed,
+    Failed,
+    /// the subsystem wasn't compiled into this build
+    Skipped,
+}
+
+impl Display for SelfTestOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SelfTestOutcome::Passed => "pass",
+            SelfTestOutcome::Failed => "FAIL",
+            SelfTestOutcome::Skipped => "skipped",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SubsystemCheck {
+    pub subsystem: String,
+    pub outcome: SelfTestOutcome,
+    pub detail: String,
+    pub duration_ms: u128,
+}
+
+/// The checks of `admin self-test`, run one subsystem after another against a
+/// temporary RSSD which is removed afterwards unless it's kept for inspection.
+#[derive(Debug)]
+pub struct SelfTest {
+    work_dir: tempfile::TempDir,
+    pub checks: Vec<SubsystemCheck>,
+}
+
+impl SelfTest {
+    pub fn new() -> Result<SelfTest> {
+        Ok(SelfTest {
+            work_dir: tempfile::Builder::new()
+                .prefix("surveilr-self-test-")
+                .tempdir()
+                .with_context(|| "[self_test] creating the work directory")?,
+            checks: Vec::new(),
+        })
+    }
+
+    pub fn work_dir(&self) -> &Path {
+        self.work_dir.path()
+    }
+
+    pub fn state_db_fs_path(&self) -> PathBuf {
+        self.work_dir().join("self-test.sqlite.db")
+    }
+
+    /// Record the result of a subsystem's check started at `started`.
+    pub fn record(&mut self, subsystem: &str, started: Instant, result: Result<String>) {
+        let (outcome, detail) = match result {
+            Ok(detail) => (SelfTestOutcome::Passed, detail),
+            Err(err) => (SelfTestOutcome::Failed, format!("{err:#}")),
+        };
+        self.checks.push(SubsystemCheck {
+            subsystem: subsystem.to_string(),
+            outcome,
+            detail,
+            duration_ms: started.elapsed().as_millis(),
+        });
+    }
+
+    pub fn skip(&mut self, subsystem: &str, reason: &str) {
+        self.checks.push(SubsystemCheck {
+            subsystem: subsystem.to_string(),
+            outcome: SelfTestOutcome::Skipped,
+            detail: reason.to_string(),
+            duration_ms: 0,
+        });
+    }
+
+    pub fn passed(&self) -> bool {
+        self.checks
+            .iter()
+            .all(|check| check.outcome != SelfTestOutcome::Failed)
+    }
+
+    /// Keep the work directory (RSSD and fixtures) instead of removing it.
+    pub fn keep(self) -> PathBuf {
+        self.work_dir.into_path()
+    }
+
+    /// Create the RSSD, applying every migration, and check its integrity.
+    pub fn check_rssd(&self) -> Result<String> {
+        let mut dbc = DbConn::new(self.state_db_fs_path(), 0)?;
+        let tx = dbc.init(None)?;
+        upserted_device(&tx, &common::DEVICE)?;
+        tx.commit()?;
+
+        let integrity: String = dbc
+            .conn
+            .query_row("PRAGMA integrity_check", [], |row| row.get(0))?;
+        if integrity != "ok" {
+            return Err(anyhow!("integrity check: {integrity}"));
+        }
+        let tables: usize = dbc.conn.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table'",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(format!("initialized with {tables} tables"))
+    }
+
+    /// Walk a fixture tree, including a capturable executable where shell
+    /// scripts can run, and check every file became a uniform resource.
+    pub fn check_files_ingest(&self) -> Result<String> {
+        let root = self.work_dir().join("fixtures");
+        let mut fixtures = vec![
+            ("README.md", FIXTURE_MARKDOWN),
+            ("self-test.json", FIXTURE_JSON),
+            ("self-test.txt", FIXTURE_TEXT),
+        ];
+        if cfg!(unix) {
+            fixtures.push((FIXTURE_CE_NAME, FIXTURE_CE));
+        }
+        std::fs::create_dir_all(&root)?;
+        for (name, content) in &fixtures {
+            std::fs::write(root.join(name), content)
+                .with_context(|| format!("[self_test] writing fixture {name}"))?;
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(
+                root.join(FIXTURE_CE_NAME),
+                std::fs::Permissions::from_mode(0o755),
+            )?;
+        }
+
+        let state_db_fs_path = self.state_db_fs_path();
+        let args = FilesCli::try_parse_from([
+            "self-test",
+            "-r",
+            &root.to_string_lossy(),
+            "-d",
+            &state_db_fs_path.to_string_lossy(),
+        ])?
+        .files;
+        let session = ingest_files(0, &args)?
+            .pop()
+            .ok_or_else(|| anyhow!("no ingest session was created"))?;
+
+        let dbc = DbConn::open(&state_db_fs_path, 0)?;
+        let resources: usize = dbc.conn.query_row(
+            "SELECT COUNT(*) FROM uniform_resource WHERE ingest_session_id = ?",
+            [&session.ingest_session_id],
+            |row| row.get(0),
+        )?;
+        if resources != fixtures.len() {
+            return Err(anyhow!(
+                "{resources} of the {} fixtures were ingested",
+                fixtures.len()
+            ));
+        }
+        if cfg!(unix) {
+            let captured: String = dbc
+                .conn
+                .query_row(
+                    "SELECT CAST(content AS TEXT) FROM uniform_resource
+                      WHERE ingest_session_id = ? AND uri LIKE '%' || ?",
+                    [&session.ingest_session_id, FIXTURE_CE_NAME],
+                    |row| row.get(0),
+                )
+                .with_context(|| "[self_test] the capturable executable's output")?;
+            if captured != FIXTURE_CE_OUTPUT {
+                return Err(anyhow!(
+                    "the capturable executable captured {captured:?} instead of {FIXTURE_CE_OUTPUT:?}"
+                ));
+            }
+        }
+        Ok(format!(
+            "{resources} resources in ingest session {}",
+            session.ingest_session_id
+        ))
+    }
+
+    /// Ingest a mailbox served by the embedded mock IMAP server.
+    #[cfg(feature = "imap")]
+    pub async fn check_imap_ingest(&self) -> Result<String> {
+        use resource_imap::mock_server::{MockImapFolder, MockImapServer};
+
+        let message = |subject: &str| {
+            format!(
+                "From: surveilr@localhost\r\nTo: self-test@localhost\r\nSubject: {subject}\r\n\
+                 Date: Tue, 1 Oct 2024 10:00:00 +0000\r\nMessage-ID: <{subject}@localhost>\r\n\r\n\
+                 written by surveilr admin self-test\r\n"
+            )
+            .into_bytes()
+        };
+        let messages = vec![message("self-test-1"), message("self-test-2")];
+        let expected = messages.len();
+        let server = MockImapServer::start(
+            "self-test@localhost",
+            "self-test",
+            vec![MockImapFolder {
+                name: "INBOX".to_string(),
+                uid_validity: 1,
+                messages,
+            }],
+        )
+        .await
+        .with_context(|| "[self_test] starting the mock IMAP server")?;
+
+        // sessions of a device are unique by their (second resolution) creation
+        // time, the files ingestion may have started one this second
+        tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+        let state_db_fs_path = self.state_db_fs_path();
+        let args = ImapCli::try_parse_from([
+            "self-test",
+            "-d",
+            &state_db_fs_path.to_string_lossy(),
+            "-u",
+            "self-test@localhost",
+            "-p",
+            "self-test",
+            "-a",
+            "127.0.0.1",
+            "--port",
+            &server.addr().port().to_string(),
+            "--no-tls",
+        ])?
+        .imap;
+        let ingest_session_id = crate::ingest::ingest_imap(&args).await?;
+
+        let dbc = DbConn::open(&state_db_fs_path, 0)?;
+        let ingested: usize = dbc.conn.query_row(
+            "SELECT COUNT(*) FROM ur_ingest_session_imap_acct_folder_message WHERE ingest_session_id = ?",
+            [&ingest_session_id],
+            |row| row.get(0),
+        )?;
+        if ingested != expected {
+            return Err(anyhow!(
+                "{ingested} of the {expected} messages served on {} were ingested",
+                server.addr()
+            ));
+        }
+        Ok(format!(
+            "{ingested} messages from {} in ingest session {ingest_session_id}",
+            server.addr()
+        ))
+    }
+}
+
+// the ingestions are configured through their command line arguments so they
+// run with the same defaults as the commands operators use
+#[derive(Parser)]
+struct FilesCli {
+    #[command(flatten)]
+    files: IngestFilesArgs,
+}
+
+#[cfg(feature = "imap")]
+#[derive(Parser)]
+struct ImapCli {
+    #[command(flatten)]
+    imap: crate::cmd::imap::IngestImapArgs,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_self_test() -> Result<()> {
+        let mut self_test = SelfTest::new()?;
+        let started = Instant::now();
+        let rssd = self_test.check_rssd();
+        self_test.record("rssd", started, rssd);
+        let started = Instant::now();
+        let files = self_test.check_files_ingest();
+        self_test.record("ingest files", started, files);
+        #[cfg(feature = "imap")]
+        {
+            let started = Instant::now();
+            let imap = self_test.check_imap_ingest().await;
+            self_test.record("ingest imap", started, imap);
+        }
+        self_test.skip("udi-pgp", "not compiled");
+        assert!(self_test.passed(), "{:#?}", self_test.checks);
+
+        self_test.record("broken", Instant::now(), Err(anyhow!("broken")));
+        assert!(!self_test.passed());
+        Ok(())
+    }
+}
@@ -0,0 +1,333 @@
+use anyhow::{bail, Context, Result};
+use rusqlite::{params, Connection, Row};
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+
+use crate::ingest::{session_errors, IngestSessionError};
+
+// what kind of ingestion a session was, from the behavior the `ingest` commands
+// record or else the rows the session has
+const SESSION_KIND_SQL: &str = "
+    CASE
+        WHEN json_valid(s.behavior_json) AND json_type(s.behavior_json, '$.seed') IS NOT NULL THEN 'seed'
+        WHEN json_valid(s.behavior_json) AND json_type(s.behavior_json, '$.s3') IS NOT NULL THEN 's3'
+        WHEN json_valid(s.behavior_json) AND json_type(s.behavior_json, '$.oci') IS NOT NULL THEN 'oci'
+        WHEN json_valid(s.behavior_json) AND json_type(s.behavior_json, '$.git') IS NOT NULL THEN 'git'
+        WHEN json_valid(s.behavior_json) AND json_type(s.behavior_json, '$.browsers') IS NOT NULL THEN 'browsers'
+        WHEN json_valid(s.behavior_json) AND json_type(s.behavior_json, '$.plist') IS NOT NULL THEN 'plist'
+        WHEN json_valid(s.behavior_json) AND json_type(s.behavior_json, '$.registry') IS NOT NULL THEN 'registry'
+        WHEN json_valid(s.behavior_json) AND json_type(s.behavior_json, '$.uris') IS NOT NULL THEN 'uris'
+        WHEN json_valid(s.behavior_json) AND json_type(s.behavior_json, '$.api') IS NOT NULL THEN 'api'
+        WHEN EXISTS (SELECT 1 FROM ur_ingest_session_imap_account a WHERE a.ingest_session_id = s.ur_ingest_session_id) THEN 'imap'
+        WHEN EXISTS (SELECT 1 FROM ur_ingest_session_imap_acct_folder_message m WHERE m.ingest_session_id = s.ur_ingest_session_id) THEN 'imap'
+        WHEN EXISTS (SELECT 1 FROM ur_ingest_session_task t WHERE t.ingest_session_id = s.ur_ingest_session_id) THEN 'tasks'
+        WHEN EXISTS (SELECT 1 FROM ur_ingest_session_fs_path p WHERE p.ingest_session_id = s.ur_ingest_session_id) THEN 'files'
+        ELSE 'other'
+    END";
+
+/// A row of `sessions ls`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IngestSessionListing {
+    pub ingest_session_id: String,
+    pub device_id: String,
+    pub device_name: String,
+    /// `files`, `tasks`, `imap`, `uris`, `api`, `s3`, `git`, `oci`, `browsers`,
+    /// `plist`, `registry`, `seed` or `other`
+    pub kind: String,
+    pub ingest_started_at: String,
+    /// `None` while the session is running (or when it was interrupted)
+    pub ingest_finished_at: Option<String>,
+    pub uniform_resources: usize,
+    pub fs_path_entries: usize,
+    pub tasks: usize,
+    pub imap_messages: usize,
+    /// walked files and tasks which failed
+    pub errors: usize,
+}
+
+/// Which sessions `list_sessions` returns.
+#[derive(Debug, Clone, Default)]
+pub struct SessionsFilter {
+    pub device_name: Option<String>,
+    pub kind: Option<String>,
+    /// the most recent ones, all of them when `None`
+    pub limit: Option<usize>,
+}
+
+fn sessions_sql(where_sql: &str) -> String {
+    format!(
+        "SELECT * FROM (
+            SELECT s.ur_ingest_session_id,
+                   s.device_id,
+                   d.name,
+                   {SESSION_KIND_SQL} AS kind,
+                   s.ingest_started_at,
+                   s.ingest_finished_at,
+                   (SELECT COUNT(*) FROM uniform_resource ur WHERE ur.ingest_session_id = s.ur_ingest_session_id),
+                   (SELECT COUNT(*) FROM ur_ingest_session_fs_path_entry e WHERE e.ingest_session_id = s.ur_ingest_session_id),
+                   (SELECT COUNT(*) FROM ur_ingest_session_task t WHERE t.ingest_session_id = s.ur_ingest_session_id),
+                   (SELECT COUNT(*) FROM ur_ingest_session_imap_acct_folder_message m WHERE m.ingest_session_id = s.ur_ingest_session_id),
+                   (SELECT COUNT(*) FROM ur_ingest_session_fs_path_entry e WHERE e.ingest_session_id = s.ur_ingest_session_id AND e.ur_status = 'ERROR')
+                 + (SELECT COUNT(*) FROM ur_ingest_session_task t WHERE t.ingest_session_id = s.ur_ingest_session_id AND t.ur_status = 'ERROR'),
+                   s.rowid AS seq
+              FROM ur_ingest_session s
+              JOIN device d ON d.device_id = s.device_id
+        )
+        WHERE {where_sql}
+        ORDER BY ingest_started_at DESC, seq DESC"
+    )
+}
+
+fn listing_from_row(row: &Row<'_>) -> rusqlite::Result<IngestSessionListing> {
+    Ok(IngestSessionListing {
+        ingest_session_id: row.get(0)?,
+        device_id: row.get(1)?,
+        device_name: row.get(2)?,
+        kind: row.get(3)?,
+        ingest_started_at: row.get(4)?,
+        ingest_finished_at: row.get(5)?,
+        uniform_resources: row.get(6)?,
+        fs_path_entries: row.get(7)?,
+        tasks: row.get(8)?,
+        imap_messages: row.get(9)?,
+        errors: row.get(10)?,
+    })
+}
+
+/// The ingest sessions matching `filter`, most recent first.
+pub fn list_sessions(
+    conn: &Connection,
+    filter: &SessionsFilter,
+) -> Result<Vec<IngestSessionListing>> {
+    let mut stmt = conn.prepare(&format!(
+        "{} LIMIT ?3",
+        sessions_sql("(?1 IS NULL OR name = ?1) AND (?2 IS NULL OR kind = ?2)")
+    ))?;
+    let sessions = stmt
+        .query_map(
+            params![
+                filter.device_name,
+                filter.kind,
+                filter.limit.map_or(-1, |limit| limit as i64)
+            ],
+            listing_from_row,
+        )?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .context("[list_sessions] listing ingest sessions")?;
+    Ok(sessions)
+}
+
+/// The ID of the session `session` names: `latest` (the most recent one), its
+/// ID or a prefix of only its ID.
+pub fn find_session(conn: &Connection, session: &str) -> Result<String> {
+    if session == "latest" {
+        return list_sessions(
+            conn,
+            &SessionsFilter {
+                limit: Some(1),
+                ..Default::default()
+            },
+        )?
+        .pop()
+        .map(|s| s.ingest_session_id)
+        .context("there are no ingest sessions");
+    }
+    let mut stmt = conn.prepare(
+        "SELECT ur_ingest_session_id
+           FROM ur_ingest_session
+          WHERE ur_ingest_session_id = ?1 OR substr(ur_ingest_session_id, 1, length(?1)) = ?1
+          ORDER BY ur_ingest_session_id != ?1
+          LIMIT 2",
+    )?;
+    let ids = stmt
+        .query_map([session], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    match &ids[..] {
+        [] => bail!("there is no ingest session {}", session),
+        [id, ..] if id == session => Ok(id.clone()),
+        [id] => Ok(id.clone()),
+        _ => bail!("more than one ingest session starts with {}", session),
+    }
+}
+
+/// A root path (or S3 prefix) of a session and how many entries it had.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IngestSessionRootPath {
+    pub root_path: String,
+    pub entries: usize,
+}
+
+/// How many of a session's uniform resources have a nature.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IngestSessionNature {
+    pub nature: Option<String>,
+    pub uniform_resources: usize,
+}
+
+/// What `sessions show` reports about a session.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IngestSessionDetails {
+    #[serde(flatten)]
+    pub listing: IngestSessionListing,
+    /// the saved behavior's ID, `ingest files --save-behavior`
+    pub behavior_id: Option<String>,
+    pub behavior: Option<JsonValue>,
+    pub elaboration: Option<JsonValue>,
+    pub device_clock: Option<JsonValue>,
+    pub root_paths: Vec<IngestSessionRootPath>,
+    pub natures: Vec<IngestSessionNature>,
+    pub error_details: Vec<IngestSessionError>,
+}
+
+/// The details of the session `ingest_session_id`.
+pub fn session_details(conn: &Connection, ingest_session_id: &str) -> Result<IngestSessionDetails> {
+    let listing = conn
+        .query_row(
+            &sessions_sql("ur_ingest_session_id = ?1"),
+            [ingest_session_id],
+            listing_from_row,
+        )
+        .with_context(|| format!("[session_details] session {}", ingest_session_id))?;
+    let json = |text: Option<String>| text.and_then(|text| serde_json::from_str(&text).ok());
+    let (behavior_id, behavior, elaboration, device_clock) = conn.query_row(
+        "SELECT behavior_id, behavior_json, elaboration, device_clock
+           FROM ur_ingest_session
+          WHERE ur_ingest_session_id = ?",
+        [ingest_session_id],
+        |row| {
+            Ok((
+                row.get::<_, Option<String>>(0)?,
+                json(row.get(1)?),
+                json(row.get(2)?),
+                json(row.get(3)?),
+            ))
+        },
+    )?;
+
+    let root_paths = conn
+        .prepare(
+            "SELECT p.root_path,
+                    (SELECT COUNT(*) FROM ur_ingest_session_fs_path_entry e WHERE e.ingest_fs_path_id = p.ur_ingest_session_fs_path_id)
+               FROM ur_ingest_session_fs_path p
+              WHERE p.ingest_session_id = ?
+              ORDER BY p.root_path",
+        )?
+        .query_map([ingest_session_id], |row| {
+            Ok(IngestSessionRootPath {
+                root_path: row.get(0)?,
+                entries: row.get(1)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let natures = conn
+        .prepare(
+            "SELECT nature, COUNT(*) AS uniform_resources
+               FROM uniform_resource
+              WHERE ingest_session_id = ?
+              GROUP BY nature
+              ORDER BY uniform_resources DESC, nature",
+        )?
+        .query_map([ingest_session_id], |row| {
+            Ok(IngestSessionNature {
+                nature: row.get(0)?,
+                uniform_resources: row.get(1)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(IngestSessionDetails {
+        listing,
+        behavior_id,
+        behavior,
+        elaboration,
+        device_clock,
+        root_paths,
+        natures,
+        error_details: session_errors(conn, ingest_session_id)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persist::{upserted_device, DbConn};
+
+    #[test]
+    fn test_list_and_show_sessions() -> Result<()> {
+        let mut dbc = DbConn::new(":memory:", 0)?;
+        let tx = dbc.init(None)?;
+        let (device_id, _) = upserted_device(&tx, &common::DEVICE)?;
+        tx.execute_batch(&format!(
+            "INSERT INTO ur_ingest_session (ur_ingest_session_id, device_id, behavior_json, ingest_started_at, ingest_finished_at, created_at)
+             VALUES ('01-files', '{device_id}', '{{}}', '2024-03-01 10:00:00', '2024-03-01 10:05:00', '2024-03-01 10:00:00'),
+                    ('01-s3', '{device_id}', '{{\"s3\": {{\"bucket\": \"evidence\"}}}}', '2024-03-02 10:00:00', NULL, '2024-03-02 10:00:00'),
+                    ('02-tasks', '{device_id}', '{{}}', '2024-03-03 10:00:00', '2024-03-03 10:00:01', '2024-03-03 10:00:00');
+             INSERT INTO ur_ingest_session_fs_path (ur_ingest_session_fs_path_id, ingest_session_id, root_path)
+             VALUES ('root', '01-files', '/data');
+             INSERT INTO uniform_resource (uniform_resource_id, device_id, ingest_session_id, ingest_fs_path_id, uri, nature, content, content_digest, size_bytes)
+             VALUES ('a', '{device_id}', '01-files', 'root', '/data/a.md', 'md', 'a', 'a', 1),
+                    ('b', '{device_id}', '01-files', 'root', '/data/b.md', 'md', 'b', 'b', 1),
+                    ('c', '{device_id}', '01-files', 'root', '/data/c.json', 'json', 'c', 'c', 1);
+             INSERT INTO ur_ingest_session_fs_path_entry (ur_ingest_session_fs_path_entry_id, ingest_session_id, ingest_fs_path_id, uniform_resource_id, file_path_abs, file_path_rel_parent, file_path_rel, file_basename, ur_status, ur_diagnostics)
+             VALUES ('e1', '01-files', 'root', 'a', '/data/a.md', '/data', 'a.md', 'a.md', NULL, NULL),
+                    ('e2', '01-files', 'root', NULL, '/data/secret', '/data', 'secret', 'secret', 'ERROR', '{{\"message\": \"permission denied\"}}');
+             INSERT INTO ur_ingest_session_task (ur_ingest_session_task_id, ingest_session_id, captured_executable)
+             VALUES ('t1', '02-tasks', '{{}}');"
+        ))?;
+
+        let sessions = list_sessions(&tx, &SessionsFilter::default())?;
+        let kinds: Vec<(&str, &str)> = sessions
+            .iter()
+            .map(|s| (s.ingest_session_id.as_str(), s.kind.as_str()))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("02-tasks", "tasks"),
+                ("01-s3", "s3"),
+                ("01-files", "files")
+            ]
+        );
+        let files = &sessions[2];
+        assert_eq!(
+            (files.uniform_resources, files.fs_path_entries, files.errors),
+            (3, 2, 1)
+        );
+
+        let filter = SessionsFilter {
+            kind: Some("s3".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(list_sessions(&tx, &filter)?.len(), 1);
+        let filter = SessionsFilter {
+            limit: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(list_sessions(&tx, &filter)?.len(), 2);
+
+        assert_eq!(find_session(&tx, "latest")?, "02-tasks");
+        assert_eq!(find_session(&tx, "01-f")?, "01-files");
+        assert_eq!(find_session(&tx, "01-s3")?, "01-s3");
+        assert!(find_session(&tx, "01").is_err());
+        assert!(find_session(&tx, "03").is_err());
+
+        let details = session_details(&tx, "01-files")?;
+        assert_eq!(
+            details.root_paths,
+            vec![IngestSessionRootPath {
+                root_path: "/data".to_string(),
+                entries: 2
+            }]
+        );
+        assert_eq!(
+            details.natures[0],
+            IngestSessionNature {
+                nature: Some("md".to_string()),
+                uniform_resources: 2
+            }
+        );
+        assert_eq!(details.error_details.len(), 1);
+        assert_eq!(details.error_details[0].source, "/data/secret");
+        Ok(())
+    }
+}
@@ -0,0 +1,348 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Context, Result};
+use rusqlite::{params, types::ValueRef, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value as JsonValue};
+use sha1::{Digest, Sha1};
+
+/// A result column as reported by SQLite when the snapshot query was prepared.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotColumn {
+    pub name: String,
+    pub decl_type: Option<String>,
+}
+
+/// A materialized query result stored in `query_snapshot`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Snapshot {
+    pub query_snapshot_id: String,
+    pub name: String,
+    pub query_sql: String,
+    pub columns: Vec<SnapshotColumn>,
+    pub rows: Vec<JsonValue>,
+    pub content_digest: String,
+    pub created_at: String,
+}
+
+/// Rows which are in one snapshot and not the other; when key columns are
+/// given, rows with the same key but different values are reported as changed.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SnapshotDiff {
+    pub from: String,
+    pub to: String,
+    pub columns_added: Vec<String>,
+    pub columns_removed: Vec<String>,
+    pub rows_added: Vec<JsonValue>,
+    pub rows_removed: Vec<JsonValue>,
+    pub rows_changed: Vec<JsonValue>,
+    pub rows_unchanged: usize,
+}
+
+impl SnapshotDiff {
+    pub fn is_empty(&self) -> bool {
+        self.columns_added.is_empty()
+            && self.columns_removed.is_empty()
+            && self.rows_added.is_empty()
+            && self.rows_removed.is_empty()
+            && self.rows_changed.is_empty()
+    }
+}
+
+pub(crate) fn json_value(value: ValueRef<'_>) -> JsonValue {
+    match value {
+        ValueRef::Null => JsonValue::Null,
+        ValueRef::Integer(int_val) => json!(int_val),
+        ValueRef::Real(float_val) => json!(float_val),
+        ValueRef::Text(text) => json!(String::from_utf8_lossy(text)),
+        ValueRef::Blob(blob) => json!(blob
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>()),
+    }
+}
+
+fn rows_digest(rows: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(rows.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// Run the read-only `sql` and store its result set as a new version of the
+/// `name` snapshot.
+pub fn create_snapshot(conn: &Connection, name: &str, sql: &str) -> Result<Snapshot> {
+    let mut stmt = conn
+        .prepare(sql)
+        .with_context(|| format!("[snapshot::create_snapshot] preparing {}", sql))?;
+    if !stmt.readonly() {
+        return Err(anyhow!(
+            "[snapshot::create_snapshot] only read-only queries can be snapshotted: {}",
+            sql
+        ));
+    }
+    let columns: Vec<SnapshotColumn> = stmt
+        .columns()
+        .iter()
+        .map(|c| SnapshotColumn {
+            name: c.name().to_string(),
+            decl_type: c.decl_type().map(str::to_string),
+        })
+        .collect();
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(JsonValue::Object(
+                columns
+                    .iter()
+                    .enumerate()
+                    .map(|(i, c)| Ok((c.name.clone(), json_value(row.get_ref(i)?))))
+                    .collect::<rusqlite::Result<Map<_, _>>>()?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .with_context(|| format!("[snapshot::create_snapshot] executing {}", sql))?;
+
+    let result_rows = serde_json::to_string(&rows)?;
+    let content_digest = rows_digest(&result_rows);
+    let (query_snapshot_id, created_at) = conn
+        .query_row(
+            "INSERT INTO query_snapshot (query_snapshot_id, name, query_sql, result_schema, result_rows, row_count, content_digest)
+                  VALUES (surveilr_pk(), ?, ?, ?, ?, ?, ?)
+               RETURNING query_snapshot_id, created_at",
+            params![
+                name,
+                sql,
+                serde_json::to_string(&columns)?,
+                result_rows,
+                rows.len(),
+                content_digest
+            ],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .with_context(|| format!("[snapshot::create_snapshot] storing snapshot {}", name))?;
+
+    Ok(Snapshot {
+        query_snapshot_id,
+        name: name.to_string(),
+        query_sql: sql.to_string(),
+        columns,
+        rows,
+        content_digest,
+        created_at,
+    })
+}
+
+// created_at only has second precision so rowid orders snapshots taken within the same second
+const SELECT_SNAPSHOT_SQL: &str = "SELECT query_snapshot_id, name, query_sql, result_schema, result_rows, content_digest, created_at
+       FROM query_snapshot";
+
+fn snapshot_from_row(row: &Row<'_>) -> rusqlite::Result<(Snapshot, String, String)> {
+    Ok((
+        Snapshot {
+            query_snapshot_id: row.get(0)?,
+            name: row.get(1)?,
+            query_sql: row.get(2)?,
+            columns: vec![],
+            rows: vec![],
+            content_digest: row.get(5)?,
+            created_at: row.get(6)?,
+        },
+        row.get(3)?,
+        row.get(4)?,
+    ))
+}
+
+fn parsed(found: Option<(Snapshot, String, String)>) -> Result<Option<Snapshot>> {
+    found
+        .map(|(mut snapshot, schema, rows)| {
+            snapshot.columns = serde_json::from_str(&schema)?;
+            snapshot.rows = serde_json::from_str(&rows)?;
+            Ok(snapshot)
+        })
+        .transpose()
+}
+
+/// Find a snapshot by its ID or, by name, the most recent version of it.
+pub fn find_snapshot(conn: &Connection, id_or_name: &str) -> Result<Snapshot> {
+    let found = conn
+        .query_row(
+            &format!(
+                "{SELECT_SNAPSHOT_SQL}
+                  WHERE query_snapshot_id = ?1 OR name = ?1
+                  ORDER BY query_snapshot_id = ?1 DESC, created_at DESC, rowid DESC
+                  LIMIT 1"
+            ),
+            [id_or_name],
+            snapshot_from_row,
+        )
+        .optional()?;
+    parsed(found)?.ok_or_else(|| anyhow!("no snapshot with ID or name '{}'", id_or_name))
+}
+
+/// The version of the same-named snapshot which was taken before `snapshot`.
+pub fn previous_snapshot(conn: &Connection, snapshot: &Snapshot) -> Result<Option<Snapshot>> {
+    let found = conn
+        .query_row(
+            &format!(
+                "{SELECT_SNAPSHOT_SQL}
+                  WHERE name = ?1 AND (created_at < ?2 OR (created_at = ?2 AND rowid < (SELECT rowid FROM query_snapshot WHERE query_snapshot_id = ?3)))
+                  ORDER BY created_at DESC, rowid DESC
+                  LIMIT 1"
+            ),
+            params![snapshot.name, snapshot.created_at, snapshot.query_snapshot_id],
+            snapshot_from_row,
+        )
+        .optional()?;
+    parsed(found)
+}
+
+/// A stored snapshot without its result set, as listed by `snapshot ls`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotSummary {
+    pub name: String,
+    pub query_snapshot_id: String,
+    pub row_count: i64,
+    pub content_digest: String,
+    pub created_at: String,
+}
+
+/// Every stored snapshot (or only the versions of `name`), oldest first.
+pub fn list_snapshots(conn: &Connection, name: Option<&str>) -> Result<Vec<SnapshotSummary>> {
+    let mut stmt = conn.prepare(
+        "SELECT name, query_snapshot_id, row_count, content_digest, created_at
+           FROM query_snapshot
+          WHERE ?1 IS NULL OR name = ?1
+          ORDER BY name, created_at, rowid",
+    )?;
+    let snapshots = stmt
+        .query_map([name], |row| {
+            Ok(SnapshotSummary {
+                name: row.get(0)?,
+                query_snapshot_id: row.get(1)?,
+                row_count: row.get(2)?,
+                content_digest: row.get(3)?,
+                created_at: row.get(4)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(snapshots)
+}
+
+fn row_key(row: &JsonValue, key: &[String]) -> String {
+    JsonValue::Array(key.iter().map(|k| row[k].clone()).collect()).to_string()
+}
+
+/// Compare two snapshots. Without `key` columns rows are compared as a whole
+/// (so a modified row shows up as removed and added).
+pub fn diff_snapshots(from: &Snapshot, to: &Snapshot, key: &[String]) -> SnapshotDiff {
+    let mut diff = SnapshotDiff {
+        from: from.query_snapshot_id.clone(),
+        to: to.query_snapshot_id.clone(),
+        columns_added: to
+            .columns
+            .iter()
+            .filter(|c| !from.columns.iter().any(|f| f.name == c.name))
+            .map(|c| c.name.clone())
+            .collect(),
+        columns_removed: from
+            .columns
+            .iter()
+            .filter(|c| !to.columns.iter().any(|t| t.name == c.name))
+            .map(|c| c.name.clone())
+            .collect(),
+        ..Default::default()
+    };
+
+    if key.is_empty() {
+        let mut remaining: HashMap<String, Vec<&JsonValue>> = HashMap::new();
+        for row in &from.rows {
+            remaining.entry(row.to_string()).or_default().push(row);
+        }
+        for row in &to.rows {
+            match remaining.get_mut(&row.to_string()).and_then(Vec::pop) {
+                Some(_) => diff.rows_unchanged += 1,
+                None => diff.rows_added.push(row.clone()),
+            }
+        }
+        diff.rows_removed = from
+            .rows
+            .iter()
+            .filter(|row| {
+                remaining
+                    .get_mut(&row.to_string())
+                    .and_then(Vec::pop)
+                    .is_some()
+            })
+            .cloned()
+            .collect();
+    } else {
+        let from_rows: HashMap<String, &JsonValue> = from
+            .rows
+            .iter()
+            .map(|row| (row_key(row, key), row))
+            .collect();
+        let to_keys: HashMap<String, &JsonValue> =
+            to.rows.iter().map(|row| (row_key(row, key), row)).collect();
+        for row in &to.rows {
+            match from_rows.get(&row_key(row, key)) {
+                Some(previous) if *previous == row => diff.rows_unchanged += 1,
+                Some(previous) => diff
+                    .rows_changed
+                    .push(json!({ "from": previous, "to": row })),
+                None => diff.rows_added.push(row.clone()),
+            }
+        }
+        diff.rows_removed = from
+            .rows
+            .iter()
+            .filter(|row| !to_keys.contains_key(&row_key(row, key)))
+            .cloned()
+            .collect();
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persist::DbConn;
+
+    #[test]
+    fn test_create_and_diff_snapshots() -> Result<()> {
+        let mut dbc = DbConn::new(":memory:", 0)?;
+        dbc.init(None)?.commit()?;
+        dbc.conn.execute_batch(
+            "CREATE TABLE report (id INTEGER, status TEXT);
+             INSERT INTO report VALUES (1, 'open'), (2, 'open');",
+        )?;
+        let sql = "SELECT id, status FROM report ORDER BY id";
+
+        let first = create_snapshot(&dbc.conn, "weekly", sql)?;
+        assert_eq!(first.rows.len(), 2);
+        assert_eq!(first.columns[1].name, "status");
+        assert!(create_snapshot(&dbc.conn, "weekly", "DELETE FROM report").is_err());
+
+        dbc.conn.execute_batch(
+            "UPDATE report SET status = 'closed' WHERE id = 1;
+             INSERT INTO report VALUES (3, 'open');",
+        )?;
+        let second = create_snapshot(&dbc.conn, "weekly", sql)?;
+        assert_ne!(first.content_digest, second.content_digest);
+
+        let latest = find_snapshot(&dbc.conn, "weekly")?;
+        assert_eq!(latest.query_snapshot_id, second.query_snapshot_id);
+        let previous = previous_snapshot(&dbc.conn, &latest)?.unwrap();
+        assert_eq!(previous.query_snapshot_id, first.query_snapshot_id);
+
+        let diff = diff_snapshots(&previous, &latest, &[]);
+        assert_eq!(diff.rows_added.len(), 2);
+        assert_eq!(diff.rows_removed, vec![json!({"id": 1, "status": "open"})]);
+        assert_eq!(diff.rows_unchanged, 1);
+
+        let diff = diff_snapshots(&previous, &latest, &["id".to_string()]);
+        assert_eq!(diff.rows_added, vec![json!({"id": 3, "status": "open"})]);
+        assert_eq!(diff.rows_changed.len(), 1);
+        assert!(diff.rows_removed.is_empty());
+        assert!(diff_snapshots(&latest, &latest, &[]).is_empty());
+        Ok(())
+    }
+}
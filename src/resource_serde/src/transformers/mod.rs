@@ -6,9 +6,36 @@ use html_parser::Dom;
 use rusqlite::{params, Connection, Result as RusqliteResult, ToSql};
 use scraper::{Html, Selector};
 use sha1::{Digest, Sha1};
+use tracing::error;
 
 use crate::{ingest::INS_UR_TRANSFORM_SQL, persist::DbConn};
 
+const BACKFILL_PENDING_COUNT_SQL: &str = "
+    SELECT COUNT(*)
+      FROM uniform_resource ur
+     WHERE ur.nature = ?1
+       AND ur.content IS NOT NULL
+       AND NOT EXISTS (SELECT 1 FROM uniform_resource_transform urt
+                        WHERE urt.uniform_resource_id = ur.uniform_resource_id AND urt.nature = ?2)";
+
+query_sql_rows!(
+    backfill_pending_batch,
+    "SELECT ur.uniform_resource_id, ur.uri, CAST(ur.content AS TEXT) AS content
+       FROM uniform_resource ur
+      WHERE ur.nature = ?1
+        AND ur.content IS NOT NULL
+        AND NOT EXISTS (SELECT 1 FROM uniform_resource_transform urt
+                         WHERE urt.uniform_resource_id = ur.uniform_resource_id AND urt.nature = ?2)
+        AND ur.uniform_resource_id > ?3
+   ORDER BY ur.uniform_resource_id
+      LIMIT ?4",
+    nature: &str,
+    transform_nature: &str,
+    after_ur_id: &str,
+    batch_size: i64;
+    uniform_resource_id: String, uri: String, content: String
+);
+
 query_sql_rows!(
     get_content_by_nature,
     "SELECT content, uniform_resource_id, uri FROM uniform_resource WHERE nature = ?",
@@ -47,6 +74,7 @@ pub trait Transformer {
                 self.db_path()
             )
         })?;
+        crate::persist::set_busy_timeout(&conn)?;
 
         let mut results = Vec::new();
 
@@ -99,7 +127,7 @@ pub trait Transformer {
 
             for tc in transformed_resources {
                 let content = serde_json::to_string_pretty(&tc.content)?;
-                let size = content.as_bytes().len();
+                let size = content.len();
                 let hash = {
                     let mut hasher = Sha1::new();
                     hasher.update(content.as_bytes());
@@ -234,3 +262,238 @@ impl Transformer for HtmlTransformer {
         Ok(tcs)
     }
 }
+
+/// Transforms which are applied during ingestion and can be re-run over
+/// already-ingested resources by `transform backfill`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BackfillTransform {
+    XmlToJson,
+    HtmlToJson,
+}
+
+impl BackfillTransform {
+    /// Find the registered transform for resources of `nature` into `format`.
+    pub fn registered(nature: &str, format: &str) -> anyhow::Result<Self> {
+        match (nature, format) {
+            ("xml", "json") => Ok(BackfillTransform::XmlToJson),
+            ("html", "json") => Ok(BackfillTransform::HtmlToJson),
+            _ => Err(anyhow!(
+                "no registered transform for nature '{}' into '{}' (available: xml -> json, html -> json)",
+                nature,
+                format
+            )),
+        }
+    }
+
+    pub fn nature(&self) -> &'static str {
+        match self {
+            BackfillTransform::XmlToJson => "xml",
+            BackfillTransform::HtmlToJson => "html",
+        }
+    }
+
+    pub fn transform_nature(&self) -> &'static str {
+        "json"
+    }
+
+    /// Produce the transformed URI, content and content digest the same way
+    /// ingestion (xml) or `transform html` does so that backfilled rows match.
+    pub(crate) fn apply(&self, uri: &str, content: &str) -> anyhow::Result<(String, String, String)> {
+        match self {
+            BackfillTransform::XmlToJson => {
+                let (json, hash) = resource::xml_to_json(content)?;
+                Ok((uri.to_string(), json, hash))
+            }
+            BackfillTransform::HtmlToJson => {
+                let html = HtmlTransformer::new(vec![], String::new());
+                let json = serde_json::to_string_pretty(&vec![html.convert_html_to_value(content)?])?;
+                let hash = {
+                    let mut hasher = Sha1::new();
+                    hasher.update(json.as_bytes());
+                    format!("{:x}", hasher.finalize())
+                };
+                Ok((format!("{uri}/json"), json, hash))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct BackfillProgress {
+    /// resources lacking a transform when the backfill started
+    pub pending: usize,
+    pub transformed: usize,
+    pub failed: usize,
+}
+
+/// Run `transform` over resources which do not yet have a corresponding
+/// `uniform_resource_transform` row (e.g. ingested before the transformer
+/// existed). Each batch is committed separately and `progress` is called after
+/// every batch so long backfills can be monitored and safely interrupted.
+pub fn backfill_transforms(
+    db_path: &str,
+    transform: BackfillTransform,
+    batch_size: usize,
+    mut progress: impl FnMut(&BackfillProgress),
+) -> anyhow::Result<BackfillProgress> {
+    let mut dbc = DbConn::new(db_path, 0)
+        .with_context(|| format!("[backfill_transforms] SQLite database {}", db_path))?;
+    dbc.init(None)?
+        .commit()
+        .with_context(|| format!("[backfill_transforms] migrations in {}", db_path))?;
+
+    let nature = transform.nature();
+    let transform_nature = transform.transform_nature();
+    let mut summary = BackfillProgress {
+        pending: dbc.conn.query_row(
+            BACKFILL_PENDING_COUNT_SQL,
+            params![nature, transform_nature],
+            |row| row.get::<_, usize>(0),
+        )?,
+        ..Default::default()
+    };
+
+    // failed resources keep lacking a transform so page by ID instead of re-querying from the start
+    let mut after_ur_id = String::new();
+    loop {
+        let tx = dbc.conn.transaction()?;
+        let mut batch = Vec::new();
+        backfill_pending_batch(
+            &tx,
+            |_, ur_id, uri, content| {
+                batch.push((ur_id, uri, content));
+                Ok(())
+            },
+            nature,
+            transform_nature,
+            after_ur_id.as_str(),
+            batch_size as i64,
+        )?;
+        if batch.is_empty() {
+            break;
+        }
+
+        {
+            let mut stmt = tx.prepare(INS_UR_TRANSFORM_SQL)?;
+            for (ur_id, uri, content) in &batch {
+                match transform.apply(uri, content) {
+                    Ok((transformed_uri, json, hash)) => {
+                        stmt.query_row(
+                            params![
+                                ur_id,
+                                transformed_uri,
+                                transform_nature,
+                                hash,
+                                json,
+                                json.len(),
+                                None::<&String>,
+                            ],
+                            |row| row.get::<_, String>(0),
+                        )?;
+                        summary.transformed += 1;
+                    }
+                    Err(err) => {
+                        error!("[backfill_transforms] unable to transform {}: {}", uri, err);
+                        summary.failed += 1;
+                    }
+                }
+            }
+        }
+        tx.commit()
+            .with_context(|| format!("[backfill_transforms] batch commit in {}", db_path))?;
+
+        after_ur_id = batch.last().map(|(id, _, _)| id.clone()).unwrap_or_default();
+        progress(&summary);
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingest::{seed_rssd, SeedProfile};
+
+    #[test]
+    fn test_backfill_transforms() -> anyhow::Result<()> {
+        let state = tempfile::tempdir()?;
+        let db_path = state.path().join("rssd.sqlite.db");
+        let db_path = db_path.to_string_lossy();
+        let mut dbc = DbConn::new(db_path.as_ref(), 0)?;
+        dbc.init(None)?.commit()?;
+        seed_rssd(&dbc.conn, SeedProfile::Test)?;
+        let seeded: usize = dbc.conn.query_row(
+            "SELECT COUNT(*) FROM uniform_resource WHERE nature = 'html'",
+            [],
+            |row| row.get(0),
+        )?;
+
+        // more html resources alongside the seeded ones, one of them already transformed
+        let mut ins_html = dbc.conn.prepare(
+            "INSERT INTO uniform_resource (uniform_resource_id, device_id, ingest_session_id, ingest_fs_path_id, uri, nature, content, content_digest, size_bytes, last_modified_at)
+                  SELECT surveilr_pk(), device_id, ingest_session_id, ingest_fs_path_id, ?1, 'html', ?2, ?1, length(?2), last_modified_at
+                    FROM uniform_resource LIMIT 1
+               RETURNING uniform_resource_id",
+        )?;
+        let mut html_ids = Vec::new();
+        for (uri, content) in [
+            ("/seed/hosts-1.html", "<html><body><p>web</p></body></html>"),
+            ("/seed/hosts-2.html", "<html><body><p>db</p></body></html>"),
+            ("/seed/empty.html", ""),
+            (
+                "/seed/hosts-3.html",
+                "<html><body><p>build</p></body></html>",
+            ),
+        ] {
+            html_ids
+                .push(ins_html.query_row(params![uri, content], |row| row.get::<_, String>(0))?);
+        }
+        drop(ins_html);
+        let (uri, json, hash) = BackfillTransform::HtmlToJson.apply(
+            "/seed/hosts-3.html",
+            "<html><body><p>build</p></body></html>",
+        )?;
+        dbc.conn.query_row(
+            INS_UR_TRANSFORM_SQL,
+            params![
+                html_ids[3],
+                uri,
+                "json",
+                hash,
+                json,
+                json.len(),
+                None::<&String>
+            ],
+            |row| row.get::<_, String>(0),
+        )?;
+        let transforms = |dbc: &DbConn| -> anyhow::Result<usize> {
+            Ok(dbc.conn.query_row(
+                "SELECT COUNT(*) FROM uniform_resource_transform WHERE nature = 'json'",
+                [],
+                |row| row.get(0),
+            )?)
+        };
+        let before = transforms(&dbc)?;
+
+        // batches smaller than the pending resources report progress after each one
+        let mut batches = Vec::new();
+        let summary =
+            backfill_transforms(&db_path, BackfillTransform::HtmlToJson, 2, |progress| {
+                batches.push(progress.transformed + progress.failed)
+            })?;
+        let pending = seeded + 3;
+        assert_eq!(
+            (summary.pending, summary.transformed, summary.failed),
+            (pending, pending - 1, 1)
+        );
+        assert_eq!(batches.len(), pending.div_ceil(2));
+        assert_eq!(batches.last(), Some(&pending));
+        assert_eq!(transforms(&dbc)?, before + pending - 1);
+
+        // only the resource which can't be transformed is left and it stays that way
+        let rerun = backfill_transforms(&db_path, BackfillTransform::HtmlToJson, 2, |_| {})?;
+        assert_eq!((rerun.pending, rerun.transformed, rerun.failed), (1, 0, 1));
+        assert_eq!(transforms(&dbc)?, before + pending - 1);
+        Ok(())
+    }
+}
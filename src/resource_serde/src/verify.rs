@@ -0,0 +1,448 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use resource::{digest::DigestAlgorithm, EncounteredResource, ResourcesCollection};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use serde_json::json;
+
+use crate::ingest::{canonicalize_json, CollectManifests, IngestFilesBehavior};
+
+// the most recent finished `ingest files` session of the device
+const SEL_LATEST_FILES_SESSION_SQL: &str = "
+    SELECT s.ur_ingest_session_id
+      FROM ur_ingest_session s
+     WHERE s.device_id = ?
+       AND s.ingest_finished_at IS NOT NULL
+       AND EXISTS (SELECT 1 FROM ur_ingest_session_fs_path p WHERE p.ingest_session_id = s.ur_ingest_session_id)
+  ORDER BY s.ingest_started_at DESC, s.rowid DESC
+     LIMIT 1";
+
+const SEL_SESSION_SQL: &str = "
+    SELECT behavior_json, content_digest_algorithm
+      FROM ur_ingest_session
+     WHERE ur_ingest_session_id = ?";
+
+const SEL_SESSION_ROOT_PATHS_SQL: &str = "
+    SELECT root_path FROM ur_ingest_session_fs_path WHERE ingest_session_id = ? ORDER BY root_path";
+
+// the files the session walked; captured executables' output and archive
+// members aren't the content of a file so they can't be verified
+const SEL_SESSION_ENTRIES_SQL: &str = "
+    SELECT e.file_path_abs, ur.uniform_resource_id, ur.nature, ur.content_digest,
+           ur.content_digest_algorithm, ur.size_bytes, ur.last_modified_at
+      FROM ur_ingest_session_fs_path_entry e
+      LEFT JOIN uniform_resource ur ON ur.uniform_resource_id = e.uniform_resource_id
+     WHERE e.ingest_session_id = ?
+       AND e.captured_executable IS NULL
+       AND json_extract(e.elaboration, '$.archive') IS NULL
+  ORDER BY e.file_path_abs";
+
+const INS_UR_VERIFICATION_SQL: &str = "
+    INSERT INTO uniform_resource_verification (uniform_resource_verification_id, verification_id, ingest_session_id, uniform_resource_id, uri, status, content_digest_algorithm, expected_digest, actual_digest, elaboration)
+                                       VALUES (surveilr_pk(), ?, ?, ?, ?, ?, ?, ?, ?, ?)";
+
+/// What `admin verify` found for a file compared with the ingest session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VerificationStatus {
+    Unchanged,
+    /// the content, size or modification time changed
+    Modified,
+    /// the content changed but the size and modification time didn't, e.g.
+    /// the modification time was reset after editing the file
+    Tampered,
+    Deleted,
+    /// a file the session didn't walk
+    Added,
+    Unreadable,
+}
+
+impl std::fmt::Display for VerificationStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            VerificationStatus::Unchanged => "unchanged",
+            VerificationStatus::Modified => "modified",
+            VerificationStatus::Tampered => "tampered",
+            VerificationStatus::Deleted => "deleted",
+            VerificationStatus::Added => "added",
+            VerificationStatus::Unreadable => "unreadable",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ResourceVerification {
+    pub uri: String,
+    /// `None` for added files
+    pub uniform_resource_id: Option<String>,
+    pub status: VerificationStatus,
+    pub content_digest_algorithm: DigestAlgorithm,
+    pub expected_digest: Option<String>,
+    pub actual_digest: Option<String>,
+    /// the expected and actual size and modification time, or the error
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub elaboration: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VerificationReport {
+    /// the session the files were verified against
+    pub ingest_session_id: String,
+    pub root_paths: Vec<String>,
+    /// the number of files with each status
+    pub summary: BTreeMap<VerificationStatus, usize>,
+    pub resources: Vec<ResourceVerification>,
+}
+
+impl VerificationReport {
+    /// The files which aren't unchanged.
+    pub fn changes(&self) -> impl Iterator<Item = &ResourceVerification> {
+        self.resources
+            .iter()
+            .filter(|verified| verified.status != VerificationStatus::Unchanged)
+    }
+}
+
+// a file as the session stored it
+struct Baseline {
+    uniform_resource_id: String,
+    nature: Option<String>,
+    content_digest: String,
+    algorithm: DigestAlgorithm,
+    size_bytes: Option<i64>,
+    last_modified_at: Option<String>,
+}
+
+/// The file's size and modification time as `insert_uniform_resource` stores them.
+fn file_stat(path: &Path) -> io::Result<(i64, Option<String>)> {
+    let metadata = fs::metadata(path)?;
+    let last_modified_at = metadata
+        .modified()
+        .ok()
+        .map(|at| chrono::DateTime::<chrono::Utc>::from(at).to_string());
+    Ok((metadata.len() as i64, last_modified_at))
+}
+
+fn file_digest(path: &Path, algorithm: DigestAlgorithm) -> io::Result<String> {
+    let mut hasher = algorithm.hasher();
+    io::copy(&mut fs::File::open(path)?, &mut hasher)?;
+    Ok(hasher.finalize_hex())
+}
+
+fn verify_file(uri: &str, baseline: &Baseline) -> ResourceVerification {
+    let path = Path::new(uri);
+    let expected = json!({
+        "size_bytes": baseline.size_bytes,
+        "last_modified_at": baseline.last_modified_at,
+    });
+    let verification = |status, actual_digest, elaboration| ResourceVerification {
+        uri: uri.to_string(),
+        uniform_resource_id: Some(baseline.uniform_resource_id.clone()),
+        status,
+        content_digest_algorithm: baseline.algorithm,
+        expected_digest: Some(baseline.content_digest.clone()),
+        actual_digest,
+        elaboration,
+    };
+    let unreadable = |err: io::Error| {
+        verification(
+            VerificationStatus::Unreadable,
+            None,
+            Some(json!({ "expected": expected, "error": err.to_string() })),
+        )
+    };
+
+    let (size_bytes, last_modified_at) = match file_stat(path) {
+        Ok(stat) => stat,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            return verification(
+                VerificationStatus::Deleted,
+                None,
+                Some(json!({ "expected": expected })),
+            )
+        }
+        Err(err) => return unreadable(err),
+    };
+    let same_stat = Some(size_bytes) == baseline.size_bytes
+        && last_modified_at.is_some()
+        && last_modified_at == baseline.last_modified_at;
+    let changed = |status, actual_digest| {
+        verification(
+            status,
+            actual_digest,
+            Some(json!({
+                "expected": expected,
+                "actual": { "size_bytes": size_bytes, "last_modified_at": last_modified_at },
+            })),
+        )
+    };
+
+    // the content wasn't read when it was ingested so only its stat can be compared
+    if baseline.content_digest == "-" {
+        return if same_stat {
+            verification(VerificationStatus::Unchanged, None, None)
+        } else {
+            changed(VerificationStatus::Modified, None)
+        };
+    }
+    let actual_digest = match file_digest(path, baseline.algorithm) {
+        Ok(digest) => digest,
+        Err(err) => return unreadable(err),
+    };
+    // `--canonical-json` sessions hashed the canonical form of JSON files
+    let canonical_digest = || {
+        let text = fs::read_to_string(path).ok()?;
+        canonicalize_json(&text).map(|text| baseline.algorithm.hex_digest(text))
+    };
+    if actual_digest == baseline.content_digest
+        || (baseline.nature.as_deref() == Some("json")
+            && canonical_digest().as_ref() == Some(&baseline.content_digest))
+    {
+        verification(VerificationStatus::Unchanged, Some(actual_digest), None)
+    } else if same_stat {
+        changed(VerificationStatus::Tampered, Some(actual_digest))
+    } else {
+        changed(VerificationStatus::Modified, Some(actual_digest))
+    }
+}
+
+/// The ID of the latest finished `ingest files` session of `device_id`, the
+/// default baseline of `admin verify`.
+pub fn latest_files_session(conn: &Connection, device_id: &str) -> Result<Option<String>> {
+    conn.query_row(SEL_LATEST_FILES_SESSION_SQL, [device_id], |row| row.get(0))
+        .optional()
+        .context("[verify::latest_files_session]")
+}
+
+/// Compare the files `ingest_session_id` walked with the file system: re-hash
+/// each one with its resource's digest algorithm and walk the session's root
+/// paths again with its behavior to find the files added since.
+pub fn verify_session(conn: &Connection, ingest_session_id: &str) -> Result<VerificationReport> {
+    let (behavior_json, algorithm): (Option<String>, String) = conn
+        .query_row(SEL_SESSION_SQL, [ingest_session_id], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })
+        .optional()?
+        .ok_or_else(|| anyhow!("[verify_session] no ingest session {}", ingest_session_id))?;
+    let algorithm: DigestAlgorithm = algorithm.parse()?;
+    let root_paths = conn
+        .prepare(SEL_SESSION_ROOT_PATHS_SQL)?
+        .query_map([ingest_session_id], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+    if root_paths.is_empty() {
+        return Err(anyhow!(
+            "[verify_session] {} didn't walk any paths, only `ingest files` sessions can be verified",
+            ingest_session_id
+        ));
+    }
+
+    let mut walked = BTreeSet::new();
+    let mut resources = Vec::new();
+    let mut stmt = conn.prepare(SEL_SESSION_ENTRIES_SQL)?;
+    let mut rows = stmt.query([ingest_session_id])?;
+    while let Some(row) = rows.next()? {
+        let uri: String = row.get(0)?;
+        // files walked from overlapping root paths have several entries
+        if !walked.insert(uri.clone()) {
+            continue;
+        }
+        // entries without a resource (skipped, errors) have nothing to verify
+        let Some(uniform_resource_id) = row.get::<_, Option<String>>(1)? else {
+            continue;
+        };
+        let baseline = Baseline {
+            uniform_resource_id,
+            nature: row.get(2)?,
+            content_digest: row.get(3)?,
+            algorithm: row.get::<_, String>(4)?.parse()?,
+            size_bytes: row.get(5)?,
+            last_modified_at: row.get(6)?,
+        };
+        resources.push(verify_file(&uri, &baseline));
+    }
+
+    let behavior = behavior_json
+        .as_deref()
+        .map(IngestFilesBehavior::from_json)
+        .transpose()
+        .with_context(|| format!("[verify_session] behavior of {}", ingest_session_id))?;
+    let mut classifier = behavior.map(|b| b.classifier).unwrap_or_default();
+    let existing: Vec<String> = root_paths
+        .iter()
+        .filter(|root_path| Path::new(root_path).exists())
+        .cloned()
+        .collect();
+    let (rewalked, _) = ResourcesCollection::from_smart_ignore_walk(
+        &existing,
+        &classifier,
+        None,
+        false,
+        &Default::default(),
+    );
+    for root_path in &existing {
+        let walked = rewalked
+            .encounterable
+            .iter()
+            .map(|er| er.uri())
+            .filter(|uri| Path::new(uri).starts_with(root_path));
+        // nothing is run, the capture rules only keep executables out of the files
+        CollectManifests::from_walked(Path::new(root_path), walked, |_| true)
+            .and_then(|manifests| manifests.apply(&mut classifier))
+            .with_context(|| format!("[verify_session] collect manifests in {}", root_path))?;
+    }
+    let rewalked = rewalked.with_classifier(classifier);
+    for encountered in rewalked.encountered() {
+        let EncounteredResource::Resource(cr, _) = encountered else {
+            continue;
+        };
+        if walked.contains(&cr.uri) {
+            continue;
+        }
+        let path = Path::new(&cr.uri);
+        let (actual_digest, elaboration) = match file_digest(path, algorithm) {
+            Ok(digest) => (
+                Some(digest),
+                file_stat(path).ok().map(|(size_bytes, last_modified_at)| {
+                    json!({ "actual": { "size_bytes": size_bytes, "last_modified_at": last_modified_at } })
+                }),
+            ),
+            Err(err) => (None, Some(json!({ "error": err.to_string() }))),
+        };
+        resources.push(ResourceVerification {
+            uri: cr.uri,
+            uniform_resource_id: None,
+            status: VerificationStatus::Added,
+            content_digest_algorithm: algorithm,
+            expected_digest: None,
+            actual_digest,
+            elaboration,
+        });
+    }
+
+    let mut summary = BTreeMap::new();
+    for verified in &resources {
+        *summary.entry(verified.status).or_default() += 1;
+    }
+    Ok(VerificationReport {
+        ingest_session_id: ingest_session_id.to_string(),
+        root_paths,
+        summary,
+        resources,
+    })
+}
+
+/// Store `report` as `uniform_resource_verification` rows and return the ID
+/// of the verification they share.
+pub fn record_verification(conn: &Connection, report: &VerificationReport) -> Result<String> {
+    let verification_id: String = conn.query_row("SELECT surveilr_pk()", [], |row| row.get(0))?;
+    let mut stmt = conn.prepare(INS_UR_VERIFICATION_SQL)?;
+    for verified in &report.resources {
+        stmt.execute(params![
+            verification_id,
+            report.ingest_session_id,
+            verified.uniform_resource_id,
+            verified.uri,
+            verified.status.to_string(),
+            verified.content_digest_algorithm.to_string(),
+            verified.expected_digest,
+            verified.actual_digest,
+            verified.elaboration.as_ref().map(|e| e.to_string()),
+        ])
+        .with_context(|| format!("[record_verification] {}", verified.uri))?;
+    }
+    Ok(verification_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use crate::cmd::IngestFilesArgs;
+    use crate::ingest::ingest_files;
+    use crate::persist::DbConn;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        files: IngestFilesArgs,
+    }
+
+    #[test]
+    fn test_verify_reports_changes() -> Result<()> {
+        let root = tempfile::tempdir()?;
+        let state = tempfile::tempdir()?;
+        for name in ["same.md", "edited.md", "tampered.md", "deleted.md"] {
+            fs::write(root.path().join(name), format!("# {name}"))?;
+        }
+        let state_db = state.path().join("rssd.sqlite.db");
+        let args = Cli::parse_from([
+            "ingest",
+            "-r",
+            &root.path().to_string_lossy(),
+            "-d",
+            &state_db.to_string_lossy(),
+            "--digest",
+            "sha256",
+        ])
+        .files;
+        let session = ingest_files(0, &args)?.remove(0).ingest_session_id;
+
+        fs::write(root.path().join("edited.md"), "# edited.md, edited")?;
+        // same size, modification time put back
+        let tampered = root.path().join("tampered.md");
+        let modified = fs::metadata(&tampered)?.modified()?;
+        fs::write(&tampered, "# TAMPERED.MD")?;
+        fs::File::options()
+            .write(true)
+            .open(&tampered)?
+            .set_modified(modified)?;
+        fs::remove_file(root.path().join("deleted.md"))?;
+        fs::write(root.path().join("added.md"), "# added.md")?;
+
+        let mut dbc = DbConn::new(&state_db, 0)?;
+        let tx = dbc.init(None)?;
+        let report = verify_session(&tx, &session)?;
+        let statuses: BTreeMap<_, _> = report
+            .resources
+            .iter()
+            .map(|verified| {
+                let name = Path::new(&verified.uri).file_name().unwrap();
+                (name.to_string_lossy().to_string(), verified.status)
+            })
+            .collect();
+        assert_eq!(
+            statuses,
+            BTreeMap::from([
+                ("added.md".to_string(), VerificationStatus::Added),
+                ("deleted.md".to_string(), VerificationStatus::Deleted),
+                ("edited.md".to_string(), VerificationStatus::Modified),
+                ("same.md".to_string(), VerificationStatus::Unchanged),
+                ("tampered.md".to_string(), VerificationStatus::Tampered),
+            ])
+        );
+        assert_eq!(report.changes().count(), 4);
+        let unchanged = report
+            .resources
+            .iter()
+            .find(|verified| verified.status == VerificationStatus::Unchanged)
+            .unwrap();
+        assert_eq!(
+            unchanged.actual_digest.as_deref(),
+            Some(DigestAlgorithm::Sha256.hex_digest("# same.md").as_str())
+        );
+
+        let verification_id = record_verification(&tx, &report)?;
+        let recorded: usize = tx.query_row(
+            "SELECT COUNT(*) FROM uniform_resource_verification WHERE verification_id = ? AND uniform_resource_id IS NOT NULL",
+            [&verification_id],
+            |row| row.get(0),
+        )?;
+        assert_eq!(recorded, 4);
+        Ok(())
+    }
+}
@@ -0,0 +1,280 @@
+use std::marker::PhantomData;
+use std::os::raw::c_int;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use globset::{Glob, GlobMatcher};
+use resource::walk::{smart_ignore_walk, WalkOptions};
+use rusqlite::ffi;
+use rusqlite::vtab::{
+    eponymous_only_module, Context, IndexConstraintOp, IndexInfo, VTab, VTabConfig, VTabConnection,
+    VTabCursor, Values,
+};
+use rusqlite::{Connection, Error, Result};
+
+const SMART_IGNORE_CONF_FILES: [&str; 1] = [".surveilr_ignore"];
+
+// hidden columns which receive the function's arguments
+const COLUMN_ROOT: c_int = 6;
+const COLUMN_PATTERN: c_int = 7;
+
+const PLAN_ROOT: c_int = 1;
+const PLAN_PATTERN: c_int = 2;
+
+/// Register the `surveilr_walk(root [, glob])` table-valued function, which
+/// lists the files under `root` (honoring `.gitignore`, `.ignore` and
+/// `.surveilr_ignore` the way `ingest files` does) whose path relative to
+/// `root` matches `glob` without ingesting them, so batched-SQL capturable
+/// executables and notebooks can decide what to ingest next:
+///
+/// ```sql
+/// SELECT path, size_bytes FROM surveilr_walk('/var/log', '**/*.log') WHERE size_bytes > 1000000;
+/// ```
+pub fn declare_walk_function(db: &Connection) -> Result<()> {
+    db.create_module(
+        "surveilr_walk",
+        eponymous_only_module::<WalkTab>(),
+        None::<()>,
+    )
+}
+
+#[derive(Debug)]
+struct WalkedFile {
+    path: String,
+    file_name: String,
+    file_extn: Option<String>,
+    size_bytes: Option<i64>,
+    last_modified_at: Option<String>,
+    is_dir: bool,
+}
+
+fn walk_files(root: &str, pattern: Option<&GlobMatcher>) -> Vec<WalkedFile> {
+    let walked = smart_ignore_walk(
+        root,
+        false,
+        &SMART_IGNORE_CONF_FILES.map(|s| s.to_string()),
+        &WalkOptions::default(),
+    );
+    walked
+        .entries
+        .into_iter()
+        .filter(|entry| entry.depth() > 0)
+        .filter(|entry| match pattern {
+            Some(pattern) => entry
+                .path()
+                .strip_prefix(root)
+                .is_ok_and(|relative| pattern.is_match(relative)),
+            None => true,
+        })
+        .map(|entry| {
+            let path = entry.path();
+            let metadata = entry.metadata().ok();
+            WalkedFile {
+                path: path.to_string_lossy().to_string(),
+                file_name: entry.file_name().to_string_lossy().to_string(),
+                file_extn: path
+                    .extension()
+                    .map(|extn| extn.to_string_lossy().to_string()),
+                size_bytes: metadata.as_ref().and_then(|m| i64::try_from(m.len()).ok()),
+                last_modified_at: metadata.as_ref().and_then(|m| m.modified().ok()).map(
+                    |modified| {
+                        DateTime::<Utc>::from(modified)
+                            .format("%Y-%m-%d %H:%M:%S")
+                            .to_string()
+                    },
+                ),
+                is_dir: entry.file_type().is_some_and(|ft| ft.is_dir()),
+            }
+        })
+        .collect()
+}
+
+#[repr(C)]
+struct WalkTab {
+    /// base class, must be first
+    base: ffi::sqlite3_vtab,
+}
+
+unsafe impl<'vtab> VTab<'vtab> for WalkTab {
+    type Aux = ();
+    type Cursor = WalkTabCursor<'vtab>;
+
+    fn connect(
+        db: &mut VTabConnection,
+        _aux: Option<&()>,
+        _args: &[&[u8]],
+    ) -> Result<(String, WalkTab)> {
+        // reads the file system so it's not allowed in triggers or views
+        db.config(VTabConfig::DirectOnly)?;
+        Ok((
+            "CREATE TABLE x(path, file_name, file_extn, size_bytes, last_modified_at, is_dir, root HIDDEN, pattern HIDDEN)"
+                .to_owned(),
+            WalkTab {
+                base: ffi::sqlite3_vtab::default(),
+            },
+        ))
+    }
+
+    fn best_index(&self, info: &mut IndexInfo) -> Result<()> {
+        let mut plan = 0;
+        let mut args: [Option<usize>; 2] = [None, None];
+        for (i, constraint) in info.constraints().enumerate() {
+            let (arg, flag) = match constraint.column() {
+                COLUMN_ROOT => (0, PLAN_ROOT),
+                COLUMN_PATTERN => (1, PLAN_PATTERN),
+                _ => continue,
+            };
+            if !constraint.is_usable() {
+                // try another plan, one which supplies the argument
+                return Err(Error::SqliteFailure(
+                    ffi::Error::new(ffi::SQLITE_CONSTRAINT),
+                    None,
+                ));
+            }
+            if constraint.operator() == IndexConstraintOp::SQLITE_INDEX_CONSTRAINT_EQ {
+                plan |= flag;
+                args[arg] = Some(i);
+            }
+        }
+        if plan & PLAN_ROOT == 0 {
+            return Err(Error::ModuleError(
+                "surveilr_walk(root [, glob]) requires the root path to walk".to_string(),
+            ));
+        }
+        for (argv_index, constraint) in args.iter().flatten().enumerate() {
+            let mut usage = info.constraint_usage(*constraint);
+            usage.set_argv_index(argv_index as c_int + 1);
+            usage.set_omit(true);
+        }
+        info.set_idx_num(plan);
+        Ok(())
+    }
+
+    fn open(&'vtab mut self) -> Result<WalkTabCursor<'vtab>> {
+        Ok(WalkTabCursor {
+            base: ffi::sqlite3_vtab_cursor::default(),
+            root: String::new(),
+            pattern: None,
+            files: Vec::new(),
+            row: 0,
+            phantom: PhantomData,
+        })
+    }
+}
+
+#[repr(C)]
+struct WalkTabCursor<'vtab> {
+    /// base class, must be first
+    base: ffi::sqlite3_vtab_cursor,
+    root: String,
+    pattern: Option<String>,
+    files: Vec<WalkedFile>,
+    row: usize,
+    phantom: PhantomData<&'vtab WalkTab>,
+}
+
+unsafe impl VTabCursor for WalkTabCursor<'_> {
+    fn filter(&mut self, plan: c_int, _idx_str: Option<&str>, args: &Values<'_>) -> Result<()> {
+        self.root = args.get::<Option<String>>(0)?.unwrap_or_default();
+        self.pattern = match plan & PLAN_PATTERN {
+            0 => None,
+            _ => args.get::<Option<String>>(1)?,
+        };
+        let matcher = match &self.pattern {
+            Some(pattern) => Some(
+                Glob::new(pattern)
+                    .map_err(|err| {
+                        Error::ModuleError(format!("[surveilr_walk] invalid glob: {}", err))
+                    })?
+                    .compile_matcher(),
+            ),
+            None => None,
+        };
+        self.files = match Path::new(&self.root).exists() {
+            true => walk_files(&self.root, matcher.as_ref()),
+            false => Vec::new(),
+        };
+        self.row = 0;
+        Ok(())
+    }
+
+    fn next(&mut self) -> Result<()> {
+        self.row += 1;
+        Ok(())
+    }
+
+    fn eof(&self) -> bool {
+        self.row >= self.files.len()
+    }
+
+    fn column(&self, ctx: &mut Context, i: c_int) -> Result<()> {
+        let file = &self.files[self.row];
+        match i {
+            0 => ctx.set_result(&file.path),
+            1 => ctx.set_result(&file.file_name),
+            2 => ctx.set_result(&file.file_extn),
+            3 => ctx.set_result(&file.size_bytes),
+            4 => ctx.set_result(&file.last_modified_at),
+            5 => ctx.set_result(&file.is_dir),
+            COLUMN_ROOT => ctx.set_result(&self.root),
+            _ => ctx.set_result(&self.pattern),
+        }
+    }
+
+    fn rowid(&self) -> Result<i64> {
+        Ok(self.row as i64 + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_surveilr_walk() -> anyhow::Result<()> {
+        let root = std::env::temp_dir().join(format!("surveilr-walk-{}", ulid::Ulid::new()));
+        std::fs::create_dir_all(root.join("sub"))?;
+        std::fs::write(root.join("a.json"), "{}")?;
+        std::fs::write(root.join("sub/b.json"), "[1, 2]")?;
+        std::fs::write(root.join("sub/c.txt"), "text")?;
+        std::fs::write(root.join(".surveilr_ignore"), "c.txt\n")?;
+
+        let conn = Connection::open_in_memory()?;
+        declare_walk_function(&conn)?;
+        let root_path = root.to_string_lossy().to_string();
+        let files = conn
+            .prepare(
+                "SELECT file_name, file_extn, size_bytes FROM surveilr_walk(?, '**/*.json') ORDER BY path",
+            )?
+            .query_map([&root_path], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, i64>(2)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        assert_eq!(
+            files,
+            vec![
+                ("a.json".to_string(), "json".to_string(), 2),
+                ("b.json".to_string(), "json".to_string(), 6)
+            ]
+        );
+
+        // without a glob every walked entry is listed, minus the ignored ones
+        let count: i64 = conn.query_row(
+            "SELECT count(*) FROM surveilr_walk(?) WHERE NOT is_dir",
+            [&root_path],
+            |row| row.get(0),
+        )?;
+        assert_eq!(count, 3);
+
+        assert!(conn
+            .query_row("SELECT count(*) FROM surveilr_walk", [], |row| row
+                .get::<_, i64>(0))
+            .is_err());
+        std::fs::remove_dir_all(&root)?;
+        Ok(())
+    }
+}
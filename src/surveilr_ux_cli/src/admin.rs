@@ -1,13 +1,30 @@
-use anyhow::Context;
+use std::time::Instant;
+
+use anyhow::{anyhow, Context};
 use autometrics::autometrics;
+use common::oauth2::{
+    OAuth2Client, OAuth2Flow, OAuth2Provider, GMAIL_SCOPES, MICROSOFT_GRAPH_SCOPES,
+};
 use resource_serde::models_polygenix;
 use serde_rusqlite::from_rows;
 use tracing::debug;
 use tracing::error;
 use tracing::info;
+use tracing::warn;
 
 use resource::*;
+use resource_serde::backup::{backup_rssd, checksum_fs_path, restore_rssd};
+use resource_serde::computed_columns::{
+    add_computed_column, computed_columns, drop_computed_column, ComputedColumn,
+};
+use resource_serde::ingest::{seed_rssd, SeedProfile};
+use resource_serde::merge::{merge_blobs, verify_merged_rssd, LINK_CONTENT_EQUIVALENCE_SQL};
 use resource_serde::persist::*;
+use resource_serde::prune::{prune, PrunePolicy};
+use resource_serde::reclassify::{apply_reclassification, reclassify};
+use resource_serde::schema_doc::{schema_doc, schema_export, SchemaDocDiagram, SchemaExportFormat};
+use resource_serde::self_test::SelfTest;
+use resource_serde::verify::{latest_files_session, record_verification, verify_session};
 
 use resource_serde::cmd::*;
 
@@ -20,19 +37,21 @@ pub struct Admin {}
 
 impl Admin {
     #[autometrics]
-    pub fn execute(&self, args: &AdminArgs, cli: &Cli) -> anyhow::Result<()> {
+    pub async fn execute(&self, args: &AdminArgs, cli: &Cli) -> anyhow::Result<()> {
         match &args.command {
             AdminCommands::Init {
                 state_db_fs_path,
                 state_db_init_sql,
                 remove_existing_first,
                 with_device,
+                pk_strategy,
             } => self.init(
                 cli,
                 state_db_fs_path,
                 state_db_init_sql,
                 *remove_existing_first,
                 *with_device,
+                *pk_strategy,
                 None,
             ),
             AdminCommands::Merge {
@@ -42,6 +61,9 @@ impl Admin {
                 ignore_candidates,
                 remove_existing_first,
                 sql_only,
+                verify,
+                verify_sample,
+                verify_workers,
             } => self.merge(
                 cli,
                 state_db_fs_path,
@@ -50,16 +72,77 @@ impl Admin {
                 ignore_candidates,
                 *remove_existing_first,
                 *sql_only,
+                verify.then_some(MergeVerifyArgs {
+                    sample: *verify_sample,
+                    workers: *verify_workers,
+                }),
+            ),
+            AdminCommands::SchemaDoc {
+                state_db_fs_path,
+                diagram,
+                output,
+            } => self.schema_doc(cli, state_db_fs_path, *diagram, output.as_deref()),
+            AdminCommands::SchemaExport {
+                state_db_fs_path,
+                format,
+                output,
+            } => self.schema_export(cli, state_db_fs_path, *format, output.as_deref()),
+            AdminCommands::Seed {
+                state_db_fs_path,
+                profile,
+                remove_existing_first,
+            } => self.seed(cli, state_db_fs_path, *profile, *remove_existing_first),
+            AdminCommands::Backup {
+                state_db_fs_path,
+                output,
+                level,
+            } => self.backup(state_db_fs_path, output.as_deref(), *level),
+            AdminCommands::Restore {
+                input,
+                state_db_fs_path,
+                remove_existing_first,
+            } => self.restore(input, state_db_fs_path, *remove_existing_first),
+            AdminCommands::Reclassify {
+                state_db_fs_path,
+                apply,
+                retransform,
+                ..
+            } => self.reclassify(cli, state_db_fs_path, *apply, *retransform),
+            AdminCommands::Prune {
+                state_db_fs_path,
+                keep_sessions,
+                content_older_than,
+                vacuum,
+                apply,
+                ..
+            } => self.prune(
+                cli,
+                state_db_fs_path,
+                &PrunePolicy {
+                    keep_sessions: *keep_sessions,
+                    content_older_than_days: *content_older_than,
+                },
+                *vacuum,
+                *apply,
             ),
+            AdminCommands::Verify {
+                state_db_fs_path,
+                session,
+                json,
+                record,
+            } => self.verify(cli, state_db_fs_path, session.as_deref(), *json, *record),
+            AdminCommands::SelfTest { keep, json } => self.self_test(cli, *keep, *json).await,
+            AdminCommands::ComputedColumn(computed) => self.computed_column(cli, computed),
             AdminCommands::CliHelpMd => self.cli_help_markdown(),
             AdminCommands::Test(test_args) => {
                 // test_args.command.execute(cli, args, test_args)
                 AdminTest::new().execute(cli, args, test_args)
             }
-            AdminCommands::Credentials(creds) => self.credentials(&creds.command),
+            AdminCommands::Credentials(creds) => self.credentials(&creds.command).await,
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     // #[autometrics]
     fn init(
         &self,
@@ -68,6 +151,7 @@ impl Admin {
         db_init_sql_globs: &[String],
         remove_existing_first: bool,
         with_device: bool,
+        pk_strategy: Option<PrimaryKeyStrategy>,
         sql_script: Option<&str>,
     ) -> anyhow::Result<()> {
         debug!("Initializing {}", db_fs_path);
@@ -83,7 +167,7 @@ impl Admin {
         let mut dbc = DbConn::new(db_fs_path, cli.debug)
             .with_context(|| format!("[AdminCommands::init] SQLite database {}", db_fs_path))?;
         let tx = dbc
-            .init(Some(db_init_sql_globs))
+            .init_with_pk_strategy(Some(db_init_sql_globs), pk_strategy)
             .with_context(|| format!("[AdminCommands::init] init transaction {}", db_fs_path))?;
 
         if with_device {
@@ -115,6 +199,514 @@ impl Admin {
         result
     }
 
+    fn schema_doc(
+        &self,
+        cli: &super::Cli,
+        db_fs_path: &str,
+        diagram: SchemaDocDiagram,
+        output: Option<&str>,
+    ) -> anyhow::Result<()> {
+        let dbc = DbConn::open(db_fs_path, cli.debug).with_context(|| {
+            format!("[AdminCommands::schema_doc] SQLite database {}", db_fs_path)
+        })?;
+        let markdown = schema_doc(&dbc.conn, diagram)
+            .with_context(|| format!("[AdminCommands::schema_doc] documenting {}", db_fs_path))?;
+        match output {
+            Some(output) => std::fs::write(output, markdown)
+                .with_context(|| format!("[AdminCommands::schema_doc] writing {}", output))?,
+            None => print!("{}", markdown),
+        }
+        Ok(())
+    }
+
+    fn schema_export(
+        &self,
+        cli: &super::Cli,
+        db_fs_path: &str,
+        format: SchemaExportFormat,
+        output: Option<&str>,
+    ) -> anyhow::Result<()> {
+        let dbc = DbConn::open(db_fs_path, cli.debug).with_context(|| {
+            format!(
+                "[AdminCommands::schema_export] SQLite database {}",
+                db_fs_path
+            )
+        })?;
+        let schema = schema_export(&dbc.conn, format)
+            .with_context(|| format!("[AdminCommands::schema_export] describing {}", db_fs_path))?;
+        let json = serde_json::to_string_pretty(&schema)?;
+        match output {
+            Some(output) => std::fs::write(output, format!("{}\n", json))
+                .with_context(|| format!("[AdminCommands::schema_export] writing {}", output))?,
+            None => println!("{}", json),
+        }
+        Ok(())
+    }
+
+    fn seed(
+        &self,
+        cli: &super::Cli,
+        db_fs_path: &String,
+        profile: SeedProfile,
+        remove_existing_first: bool,
+    ) -> anyhow::Result<()> {
+        self.init(
+            cli,
+            db_fs_path,
+            &[],
+            remove_existing_first,
+            false,
+            None,
+            None,
+        )?;
+
+        let mut dbc = DbConn::new(db_fs_path, cli.debug)
+            .with_context(|| format!("[AdminCommands::seed] SQLite database {}", db_fs_path))?;
+        let tx = dbc
+            .init(None)
+            .with_context(|| format!("[AdminCommands::seed] init transaction {}", db_fs_path))?;
+        let stats = seed_rssd(&tx, profile)
+            .with_context(|| format!("[AdminCommands::seed] seeding {}", db_fs_path))?;
+        tx.commit()
+            .with_context(|| format!("[AdminCommands::seed] transaction commit {}", db_fs_path))?;
+
+        println!(
+            "Seeded {} with {} devices, {} ingest sessions, {} uniform resources, {} emails and {} CE results ({} errors)",
+            db_fs_path,
+            stats.devices,
+            stats.ingest_sessions,
+            stats.uniform_resources,
+            stats.imap_messages,
+            stats.ce_results,
+            stats.ce_errors
+        );
+        Ok(())
+    }
+
+    fn backup(&self, db_fs_path: &str, output: Option<&str>, level: i32) -> anyhow::Result<()> {
+        let artifact = output
+            .map(String::from)
+            .unwrap_or_else(|| format!("{}.zst", db_fs_path));
+        let backup = backup_rssd(db_fs_path, &artifact, level)
+            .with_context(|| format!("[AdminCommands::backup] {} to {}", db_fs_path, artifact))?;
+        println!(
+            "Backed up {} ({} bytes) to {} ({} bytes, sha256 {} in {})",
+            backup.db_fs_path,
+            backup.db_size_bytes,
+            backup.artifact_fs_path,
+            backup.artifact_size_bytes,
+            backup.sha256,
+            checksum_fs_path(&artifact)
+        );
+        Ok(())
+    }
+
+    fn restore(
+        &self,
+        artifact: &str,
+        db_fs_path: &str,
+        remove_existing_first: bool,
+    ) -> anyhow::Result<()> {
+        restore_rssd(artifact, db_fs_path, remove_existing_first)
+            .with_context(|| format!("[AdminCommands::restore] {} to {}", artifact, db_fs_path))?;
+        println!("Restored {} from {}", db_fs_path, artifact);
+        Ok(())
+    }
+
+    fn reclassify(
+        &self,
+        cli: &super::Cli,
+        db_fs_path: &str,
+        apply: bool,
+        retransform: bool,
+    ) -> anyhow::Result<()> {
+        let mut dbc = DbConn::new(db_fs_path, cli.debug).with_context(|| {
+            format!("[AdminCommands::reclassify] SQLite database {}", db_fs_path)
+        })?;
+        let tx = dbc.init(None).with_context(|| {
+            format!(
+                "[AdminCommands::reclassify] init transaction {}",
+                db_fs_path
+            )
+        })?;
+        let classifier = EncounterableResourcePathClassifier::default_from_conn(&tx)
+            .with_context(|| format!("[AdminCommands::reclassify] rules in {}", db_fs_path))?;
+        let reclassification = reclassify(&tx, &classifier)?;
+
+        let mut changes = common::format::prepare_table(vec!["Resource", "URI", "From", "To"]);
+        for change in &reclassification.changes {
+            changes.add_row(vec![
+                change.uniform_resource_id.clone(),
+                change.uri.clone(),
+                change.from.clone().unwrap_or_default(),
+                change.to.clone(),
+            ]);
+        }
+        let mut by_nature = common::format::prepare_table(vec!["From", "To", "Resources"]);
+        for ((from, to), count) in reclassification.by_nature() {
+            by_nature.add_row(vec![from, to, count.to_string()]);
+        }
+        if !reclassification.changes.is_empty() {
+            println!("{changes}");
+            println!("{by_nature}");
+        }
+        println!(
+            "{} of {} resources in {} change nature, {} now ignored by the rules are left as is",
+            reclassification.changes.len(),
+            reclassification.examined,
+            db_fs_path,
+            reclassification.ignored
+        );
+
+        if !apply {
+            tx.rollback()?;
+            if !reclassification.changes.is_empty() {
+                println!("Dry run, use --apply to update the natures");
+            }
+            return Ok(());
+        }
+        let updated = apply_reclassification(&tx, &reclassification.changes, retransform)
+            .with_context(|| format!("[AdminCommands::reclassify] updating {}", db_fs_path))?;
+        tx.commit().with_context(|| {
+            format!(
+                "[AdminCommands::reclassify] transaction commit {}",
+                db_fs_path
+            )
+        })?;
+        println!("Updated the nature of {} resources", updated);
+
+        if retransform {
+            let natures: std::collections::BTreeSet<&str> = reclassification
+                .changes
+                .iter()
+                .map(|change| change.to.as_str())
+                .collect();
+            self.retransform(db_fs_path, natures)?;
+        }
+        Ok(())
+    }
+
+    fn prune(
+        &self,
+        cli: &super::Cli,
+        db_fs_path: &str,
+        policy: &PrunePolicy,
+        vacuum: bool,
+        apply: bool,
+    ) -> anyhow::Result<()> {
+        if policy.keep_sessions.is_none() && policy.content_older_than_days.is_none() && !vacuum {
+            return Err(anyhow!(
+                "[AdminCommands::prune] nothing to prune, use --keep-sessions, --content-older-than or --vacuum"
+            ));
+        }
+        if policy.keep_sessions == Some(0) {
+            return Err(anyhow!(
+                "[AdminCommands::prune] --keep-sessions must keep at least 1 session"
+            ));
+        }
+
+        let mut dbc = DbConn::new(db_fs_path, cli.debug)
+            .with_context(|| format!("[AdminCommands::prune] SQLite database {}", db_fs_path))?;
+        let tx = dbc
+            .init(None)
+            .with_context(|| format!("[AdminCommands::prune] init transaction {}", db_fs_path))?;
+        let report = prune(&tx, policy)
+            .with_context(|| format!("[AdminCommands::prune] pruning {}", db_fs_path))?;
+
+        if policy.keep_sessions.is_some() {
+            println!(
+                "{} ingest sessions deleted with {} resources no kept session encountered, {} resources moved to kept sessions",
+                report.sessions, report.resources, report.reassigned_resources
+            );
+        }
+        if policy.content_older_than_days.is_some() {
+            println!("Content of {} resources dropped", report.contents);
+        }
+        println!(
+            "{} bytes freed in {}, {} bytes reclaimable with VACUUM",
+            report.freed_bytes, db_fs_path, report.reclaimable_bytes
+        );
+
+        if !apply {
+            tx.rollback()?;
+            println!("Dry run, use --apply to prune");
+            return Ok(());
+        }
+        tx.commit()
+            .with_context(|| format!("[AdminCommands::prune] transaction commit {}", db_fs_path))?;
+
+        if vacuum {
+            let size = || {
+                std::fs::metadata(db_fs_path)
+                    .map(|meta| meta.len())
+                    .unwrap_or(0)
+            };
+            let before = size();
+            dbc.conn
+                .execute_batch("VACUUM")
+                .with_context(|| format!("[AdminCommands::prune] VACUUM {}", db_fs_path))?;
+            println!(
+                "Vacuumed {} from {} to {} bytes",
+                db_fs_path,
+                before,
+                size()
+            );
+        }
+        Ok(())
+    }
+
+    fn verify(
+        &self,
+        cli: &super::Cli,
+        db_fs_path: &str,
+        session: Option<&str>,
+        json: bool,
+        record: bool,
+    ) -> anyhow::Result<()> {
+        let mut dbc = DbConn::new(db_fs_path, cli.debug)
+            .with_context(|| format!("[AdminCommands::verify] SQLite database {}", db_fs_path))?;
+        let tx = dbc
+            .init(None)
+            .with_context(|| format!("[AdminCommands::verify] init transaction {}", db_fs_path))?;
+        let ingest_session_id = match session {
+            Some(session) => session.to_string(),
+            None => {
+                let (device_id, _) = upserted_device(&tx, &common::DEVICE)?;
+                latest_files_session(&tx, &device_id)?.ok_or_else(|| {
+                    anyhow!(
+                        "[AdminCommands::verify] no finished `ingest files` session of {} in {}, use --session",
+                        common::DEVICE.name,
+                        db_fs_path
+                    )
+                })?
+            }
+        };
+        let report = verify_session(&tx, &ingest_session_id).with_context(|| {
+            format!(
+                "[AdminCommands::verify] verifying {} in {}",
+                ingest_session_id, db_fs_path
+            )
+        })?;
+
+        if json {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        } else {
+            let mut changes =
+                common::format::prepare_table(vec!["Status", "URI", "Expected", "Actual"]);
+            for verified in report.changes() {
+                changes.add_row(vec![
+                    verified.status.to_string(),
+                    verified.uri.clone(),
+                    verified.expected_digest.clone().unwrap_or_default(),
+                    verified.actual_digest.clone().unwrap_or_default(),
+                ]);
+            }
+            if report.changes().next().is_some() {
+                println!("{changes}");
+            }
+            let summary: Vec<_> = report
+                .summary
+                .iter()
+                .map(|(status, count)| format!("{count} {status}"))
+                .collect();
+            println!(
+                "Verified {} files of ingest session {} in {}: {}",
+                report.resources.len(),
+                ingest_session_id,
+                db_fs_path,
+                summary.join(", ")
+            );
+        }
+
+        if record {
+            let verification_id = record_verification(&tx, &report)?;
+            tx.commit().with_context(|| {
+                format!("[AdminCommands::verify] transaction commit {}", db_fs_path)
+            })?;
+            if !json {
+                println!("Recorded as verification {}", verification_id);
+            }
+        } else {
+            tx.rollback()?;
+        }
+
+        let changes = report.changes().count();
+        if changes == 0 {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "[AdminCommands::verify] {} of {} files in {} changed since ingest session {}",
+                changes,
+                report.resources.len(),
+                report.root_paths.join(", "),
+                ingest_session_id
+            ))
+        }
+    }
+
+    #[cfg_attr(not(feature = "udi-pgp"), allow(unused_variables))]
+    async fn self_test(&self, cli: &Cli, keep: bool, json: bool) -> anyhow::Result<()> {
+        let mut self_test = SelfTest::new()?;
+
+        let started = Instant::now();
+        let rssd = self_test.check_rssd();
+        self_test.record("rssd", started, rssd);
+        let started = Instant::now();
+        let files = self_test.check_files_ingest();
+        self_test.record("ingest files", started, files);
+        #[cfg(feature = "imap")]
+        {
+            let started = Instant::now();
+            let imap = self_test.check_imap_ingest().await;
+            self_test.record("ingest imap", started, imap);
+        }
+        #[cfg(not(feature = "imap"))]
+        self_test.skip("ingest imap", "not compiled into this build");
+        #[cfg(feature = "udi-pgp")]
+        {
+            let started = Instant::now();
+            let udi_pgp = match crate::service_management::udi_pgp_log_options(cli) {
+                Ok(log) => crate::udi::pgp::self_test::self_test(self_test.work_dir(), log).await,
+                Err(err) => Err(err),
+            };
+            self_test.record("udi-pgp", started, udi_pgp);
+        }
+        #[cfg(not(feature = "udi-pgp"))]
+        self_test.skip("udi-pgp", "not compiled into this build");
+
+        if json {
+            println!("{}", serde_json::to_string_pretty(&self_test.checks)?);
+        } else {
+            let mut table =
+                common::format::prepare_table(vec!["Subsystem", "Result", "Duration", "Detail"]);
+            for check in &self_test.checks {
+                table.add_row(vec![
+                    check.subsystem.clone(),
+                    check.outcome.to_string(),
+                    format!("{} ms", check.duration_ms),
+                    check.detail.clone(),
+                ]);
+            }
+            println!("{table}");
+        }
+
+        let passed = self_test.passed();
+        if keep {
+            eprintln!("Kept the self-test RSSD in {}", self_test.keep().display());
+        }
+        if !passed {
+            return Err(anyhow!("[AdminCommands::self_test] some subsystems failed"));
+        }
+        Ok(())
+    }
+
+    fn computed_column(&self, cli: &super::Cli, args: &ComputedColumnArgs) -> anyhow::Result<()> {
+        let db_fs_path = &args.state_db_fs_path;
+        let mut dbc = DbConn::new(db_fs_path, cli.debug).with_context(|| {
+            format!(
+                "[AdminCommands::computed_column] SQLite database {}",
+                db_fs_path
+            )
+        })?;
+        let tx = dbc.init(None).with_context(|| {
+            format!(
+                "[AdminCommands::computed_column] init transaction {}",
+                db_fs_path
+            )
+        })?;
+        match &args.command {
+            ComputedColumnCommands::Add {
+                name,
+                expr,
+                nature,
+                no_index,
+            } => {
+                let column = ComputedColumn {
+                    name: name.clone(),
+                    expression: expr.clone(),
+                    nature: nature.clone(),
+                };
+                let covered = add_computed_column(&tx, &column, !no_index)?;
+                println!(
+                    "Defined {} as {} over {} resources, query it with SELECT * FROM {}",
+                    name,
+                    expr,
+                    covered,
+                    column.view_name()
+                );
+            }
+            ComputedColumnCommands::Ls => {
+                let mut table =
+                    common::format::prepare_table(vec!["Column", "View", "Indexed", "SQL"]);
+                for definition in computed_columns(&tx)? {
+                    table.add_row(vec![
+                        definition.name,
+                        definition.view_name,
+                        definition.indexed.to_string(),
+                        definition.sql,
+                    ]);
+                }
+                println!("{table}");
+            }
+            ComputedColumnCommands::Drop { name } => {
+                if drop_computed_column(&tx, name)? {
+                    println!("Dropped {}", name);
+                } else {
+                    println!("{} isn't defined in {}", name, db_fs_path);
+                }
+            }
+        }
+        tx.commit().with_context(|| {
+            format!(
+                "[AdminCommands::computed_column] transaction commit {}",
+                db_fs_path
+            )
+        })?;
+        Ok(())
+    }
+
+    #[cfg(feature = "transform")]
+    fn retransform(
+        &self,
+        db_fs_path: &str,
+        natures: std::collections::BTreeSet<&str>,
+    ) -> anyhow::Result<()> {
+        use resource_serde::transformers::{backfill_transforms, BackfillTransform};
+
+        // natures without a registered transform have nothing to re-run
+        for transform in natures
+            .into_iter()
+            .filter_map(|nature| BackfillTransform::registered(nature, "json").ok())
+        {
+            let summary =
+                backfill_transforms(db_fs_path, transform, 100, |_| {}).with_context(|| {
+                    format!(
+                        "[AdminCommands::retransform] {} in {}",
+                        transform.nature(),
+                        db_fs_path
+                    )
+                })?;
+            println!(
+                "{} -> {}: {}/{} transformed, {} failed",
+                transform.nature(),
+                transform.transform_nature(),
+                summary.transformed,
+                summary.pending,
+                summary.failed
+            );
+        }
+        Ok(())
+    }
+
+    #[cfg(not(feature = "transform"))]
+    fn retransform(
+        &self,
+        _db_fs_path: &str,
+        _natures: std::collections::BTreeSet<&str>,
+    ) -> anyhow::Result<()> {
+        Err(resource_serde::cmd::feature_not_compiled("transform"))
+    }
+
     fn cli_help_markdown(&self) -> anyhow::Result<()> {
         clap_markdown::print_help_markdown::<super::Cli>();
         Ok(())
@@ -131,6 +723,7 @@ impl Admin {
         ignore_candidates: &[String],
         remove_existing_first: bool,
         sql_only: bool,
+        verify: Option<MergeVerifyArgs>,
     ) -> Result<(), anyhow::Error> {
         let mut ignore_candidates = ignore_candidates.to_vec();
         ignore_candidates.push(state_db_fs_path.clone());
@@ -172,6 +765,34 @@ impl Admin {
             }
         }
 
+        // rows keep their primary keys when merged so every candidate must have been
+        // generated with the same strategy as the others (and the target)
+        let mut pk_strategies: Vec<(PrimaryKeyStrategy, &String)> = Vec::new();
+        for db_path in &db_paths {
+            let dbc = DbConn::open(db_path, cli.debug)
+                .with_context(|| format!("[AdminCommands::merge] opening candidate {}", db_path))?;
+            // RSSDs which predate `rssd_metadata` always used ULIDs
+            let strategy = recorded_pk_strategy(&dbc.conn)
+                .ok()
+                .flatten()
+                .unwrap_or_default();
+            pk_strategies.push((strategy, db_path));
+        }
+        let pk_strategy = pk_strategies.first().map(|(strategy, _)| *strategy);
+        if pk_strategies
+            .iter()
+            .any(|(strategy, _)| Some(*strategy) != pk_strategy)
+        {
+            return Err(anyhow!(
+                "[AdminCommands::merge] candidates use different primary key strategies and cannot be merged: {}",
+                pk_strategies
+                    .iter()
+                    .map(|(strategy, db_path)| format!("{} ({})", db_path, strategy))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+
         let mut sql_script = String::from("");
         for db_path in &db_paths {
             let db_path_sql_identifier = common::format::to_sql_friendly_identifier(db_path);
@@ -192,12 +813,32 @@ impl Admin {
             "behavior",
             "ur_ingest_session",
             "ur_ingest_session_fs_path",
+            "ur_ingest_session_imap_account",
+            "ur_ingest_session_imap_acct_folder",
             "uniform_resource",
             "uniform_resource_transform",
             "ur_ingest_session_fs_path_entry",
+            "ur_ingest_session_imap_acct_folder_message",
+            "ur_ingest_session_task",
+            "uniform_resource_lineage",
         ];
         for db_path in &db_paths {
+            // candidates created by older releases may not have every table
+            let dbc = DbConn::open(db_path, cli.debug)
+                .with_context(|| format!("[AdminCommands::merge] opening candidate {}", db_path))?;
             for merge_table in merge_tables {
+                let exists: bool = dbc.conn.query_row(
+                    "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?)",
+                    [merge_table],
+                    |row| row.get(0),
+                )?;
+                if !exists {
+                    debug!(
+                        "[AdminCommands::merge] {} has no {} table",
+                        db_path, merge_table
+                    );
+                    continue;
+                }
                 let db_path_sql_identifier = common::format::to_sql_friendly_identifier(db_path);
                 sql_script.push_str(
                     format!(
@@ -210,27 +851,112 @@ impl Admin {
             sql_script.push('\n');
         }
 
-        for db_path in &db_paths {
-            let db_path_sql_identifier = common::format::to_sql_friendly_identifier(db_path);
-            sql_script.push_str(format!("DETACH DATABASE {};\n", db_path_sql_identifier).as_str());
-        }
-
         if sql_only {
+            // sqlite3 has no surveilr_pk()
+            sql_script.push_str(
+                "-- uniform_resource_equivalence is only linked by `surveilr admin merge`\n",
+            );
+            sql_script.push_str("-- blob store content is only copied by `surveilr admin merge`\n");
+            for db_path in &db_paths {
+                let db_path_sql_identifier = common::format::to_sql_friendly_identifier(db_path);
+                sql_script
+                    .push_str(format!("DETACH DATABASE {};\n", db_path_sql_identifier).as_str());
+            }
             print!("{}", sql_script);
+            return Ok(());
+        }
+
+        // identical content ingested on different devices is linked, not collapsed
+        sql_script.push_str(LINK_CONTENT_EQUIVALENCE_SQL);
+
+        // the merge runs in init's transaction, where the candidates can't be
+        // detached (they're locked), they're released when its connection closes
+        self.init(
+            cli,
+            state_db_fs_path,
+            state_db_init_sql,
+            remove_existing_first,
+            false,
+            pk_strategy,
+            Some(sql_script.as_str()),
+        )?;
+
+        // offloaded content follows its pointers into the merged RSSD's blob store
+        let dbc = DbConn::new(state_db_fs_path, cli.debug).with_context(|| {
+            format!("[AdminCommands::merge] opening merged {}", state_db_fs_path)
+        })?;
+        let blobs = merge_blobs(&dbc.conn, &db_paths).with_context(|| {
+            format!(
+                "[AdminCommands::merge] copying blobs of {}",
+                state_db_fs_path
+            )
+        })?;
+        drop(dbc);
+        if let Some(blob_store) = &blobs.blob_store {
+            println!("Copied {} blobs to {}", blobs.copied, blob_store);
+        }
+        for digest in &blobs.missing {
+            warn!(
+                "[AdminCommands::merge] blob {} is missing from every candidate's blob store",
+                digest
+            );
+        }
+
+        match verify {
+            Some(verify) => self.verify_merge(state_db_fs_path, &db_paths, verify),
+            None => Ok(()),
+        }
+    }
+
+    fn verify_merge(
+        &self,
+        state_db_fs_path: &str,
+        db_paths: &[String],
+        verify: MergeVerifyArgs,
+    ) -> anyhow::Result<()> {
+        let workers = verify.workers.unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1)
+        });
+        let verification = verify_merged_rssd(state_db_fs_path, db_paths, verify.sample, workers)
+            .with_context(|| {
+            format!("[AdminCommands::merge] verifying {}", state_db_fs_path)
+        })?;
+        for mismatch in &verification.mismatches {
+            error!(
+                "[AdminCommands::merge] {} ({}) from {}: {} (merged {}, computed {}, candidate {})",
+                mismatch.uniform_resource_id,
+                mismatch.uri,
+                mismatch
+                    .source_db_fs_path
+                    .as_deref()
+                    .unwrap_or("unknown candidate"),
+                mismatch.reason,
+                mismatch.merged_digest,
+                mismatch.computed_digest,
+                mismatch.source_digest.as_deref().unwrap_or("-")
+            );
+        }
+        println!(
+            "Verified {} merged resources with {} workers: {} mismatches",
+            verification.verified,
+            verification.workers,
+            verification.mismatches.len()
+        );
+        if verification.mismatches.is_empty() {
             Ok(())
         } else {
-            self.init(
-                cli,
-                state_db_fs_path,
-                state_db_init_sql,
-                remove_existing_first,
-                false,
-                Some(sql_script.as_str()),
-            )
+            Err(anyhow!(
+                "[AdminCommands::merge] {} of {} verified resources in {} don't match their candidates",
+                verification.mismatches.len(),
+                verification.verified,
+                state_db_fs_path
+            ))
         }
     }
 
-    fn credentials(&self, cmd: &CredentialsCommands) -> anyhow::Result<()> {
+    async fn credentials(&self, cmd: &CredentialsCommands) -> anyhow::Result<()> {
         match cmd {
             CredentialsCommands::Microsoft365 {
                 client_id,
@@ -255,11 +981,79 @@ impl Admin {
                     }
                 }
             }
+            CredentialsCommands::Oauth {
+                provider,
+                client_id,
+                client_secret,
+                scope,
+                mode,
+                port,
+                redirect_path,
+                tenant,
+                okta_domain,
+            } => {
+                let (provider, default_scopes) = match provider {
+                    OAuthProvider::Microsoft => (
+                        OAuth2Provider::microsoft(tenant),
+                        &MICROSOFT_GRAPH_SCOPES[..],
+                    ),
+                    OAuthProvider::Google => (OAuth2Provider::google(), &GMAIL_SCOPES[..]),
+                    OAuthProvider::Github => (OAuth2Provider::github(), &[][..]),
+                    OAuthProvider::Okta => (
+                        OAuth2Provider::okta(okta_domain.as_deref().unwrap_or_default()),
+                        &[][..],
+                    ),
+                };
+                let scopes: Vec<&str> = match scope.is_empty() {
+                    true => default_scopes.to_vec(),
+                    false => scope.iter().map(String::as_str).collect(),
+                };
+                if scopes.is_empty() {
+                    return Err(anyhow!(
+                        "[AdminCommands::credentials] {} needs the scopes to request (--scope)",
+                        provider.name
+                    ));
+                }
+                let client_secret = client_secret
+                    .as_ref()
+                    .map(|secret| secret.resolve())
+                    .transpose()?;
+                let client = OAuth2Client::new(provider, client_id, client_secret, &scopes);
+                let flow = match mode {
+                    OAuthFlowMode::DeviceCode => OAuth2Flow::DeviceCode,
+                    OAuthFlowMode::AuthCode => OAuth2Flow::AuthCode {
+                        redirect_uri: format!("http://127.0.0.1:{port}{redirect_path}"),
+                        port: *port,
+                    },
+                };
+                let token = client.authorize(&flow).await.with_context(|| {
+                    format!(
+                        "[AdminCommands::credentials] signing in to {}",
+                        client.provider.name
+                    )
+                })?;
+                println!(
+                    "{} token cached in {}{}",
+                    client.provider.name,
+                    client.cache_path().display(),
+                    token
+                        .expires_at
+                        .map(|at| format!(", it expires at {at}"))
+                        .unwrap_or_default()
+                );
+            }
         }
         Ok(())
     }
 }
 
+/// `admin merge --verify` options.
+#[derive(Debug, Clone, Copy)]
+struct MergeVerifyArgs {
+    sample: Option<usize>,
+    workers: Option<usize>,
+}
+
 struct AdminTest {}
 
 impl AdminTest {
@@ -1,5 +1,8 @@
 use std::collections::HashMap;
 use std::env;
+use std::io::Read;
+
+use anyhow::Context;
 
 use autometrics::autometrics;
 use resource::shell::ShellStdIn;
@@ -7,6 +10,7 @@ use resource::*;
 use resource_serde::cmd::{
     CapturableExecArgs, CapturableExecCommands, CapturableExecTestArgs, CapturableExecTestCommands,
 };
+use resource_serde::ingest::CeWorkdirs;
 use serde_json::json;
 use tracing::debug;
 use tracing::error;
@@ -160,6 +164,8 @@ impl CapturableExec {
         );
         markdown.push("\n".to_string());
 
+        // removed once the CEs have been tried, the same as after an ingest session
+        let ce_workdirs = CeWorkdirs::new("synthetic", false);
         for resource_result in resources.uniform_resources() {
             match resource_result {
                 Ok(ur) => {
@@ -188,6 +194,7 @@ impl CapturableExec {
                                             "walk-session-id":  "synthetic",
                                             "walk-path-id":  "synthetic",
                                             "entry": { "path": path },
+                                            "ce-workdir": ce_workdirs.create().ok(),
                                         },
                                     }
                                 });
@@ -263,9 +270,13 @@ impl CapturableExecTest {
         cmd_args: &CapturableExecTestArgs,
     ) -> anyhow::Result<()> {
         match &cmd_args.command {
-            CapturableExecTestCommands::File { fs_path } => {
-                self.test_fs_path(cli, parent_args, cmd_args, fs_path)
-            }
+            CapturableExecTestCommands::File { fs_path, stdin_json } => self.test_fs_path(
+                cli,
+                parent_args,
+                cmd_args,
+                fs_path,
+                stdin_json.as_ref(),
+            ),
             CapturableExecTestCommands::Task { stdin, task, cwd } => {
                 self.task(*stdin, task, cwd.as_ref())
             }
@@ -278,6 +289,7 @@ impl CapturableExecTest {
         _parent_args: &CapturableExecArgs,
         cmd_args: &CapturableExecTestArgs,
         fs_path: &str,
+        stdin_json: Option<&String>,
     ) -> anyhow::Result<()> {
         let classifier: EncounterableResourcePathClassifier = Default::default();
         let mut erc = EncounterableResourceClass {
@@ -294,11 +306,14 @@ impl CapturableExecTest {
                 &erc,
             );
             let unknown_nature = "UNKNOWN_NATURE".to_string();
-            // pass in synthetic JSON into STDIN since some scripts may try to consume stdin
-            let stdin = ShellStdIn::Json(serde_json::json!({
-                "cli": cli,
-                "args": cmd_args
-            }));
+            // pass in the supplied context or synthetic JSON into STDIN since some scripts may try to consume stdin
+            let stdin = match stdin_json {
+                Some(src) => ShellStdIn::Json(self.stdin_json_ctx(src)?),
+                None => ShellStdIn::Json(serde_json::json!({
+                    "cli": cli,
+                    "args": cmd_args
+                })),
+            };
             let (src, nature, is_batch_sql) = match &ce {
                 CapturableExecutable::UriShellExecutive(_, uri, nature, is_batch_sql) => {
                     (uri.clone(), nature, is_batch_sql)
@@ -338,6 +353,22 @@ impl CapturableExecTest {
         Ok(())
     }
 
+    /// read the JSON context from a file or, when `src` is `-`, from STDIN
+    fn stdin_json_ctx(&self, src: &str) -> anyhow::Result<serde_json::Value> {
+        let json_text = if src == "-" {
+            let mut json_text = String::new();
+            std::io::stdin()
+                .read_to_string(&mut json_text)
+                .with_context(|| "[capturable_exec::stdin_json_ctx] unable to read STDIN")?;
+            json_text
+        } else {
+            std::fs::read_to_string(src)
+                .with_context(|| format!("[capturable_exec::stdin_json_ctx] unable to read {}", src))?
+        };
+        serde_json::from_str(&json_text)
+            .with_context(|| format!("[capturable_exec::stdin_json_ctx] invalid JSON in {}", src))
+    }
+
     fn task(
         &self,
         read_from_stdin: bool,
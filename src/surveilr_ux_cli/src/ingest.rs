@@ -1,15 +1,24 @@
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+use anyhow::anyhow;
 use autometrics::autometrics;
 use comfy_table::modifiers::UTF8_ROUND_CORNERS;
 use comfy_table::presets::UTF8_FULL_CONDENSED;
 use comfy_table::*;
 use serde_rusqlite::rusqlite;
-use tracing::info;
+use tokio::sync::{oneshot, Notify};
+use tokio::time::MissedTickBehavior;
+use tracing::{error, info};
 
 use resource::*;
 use resource_serde::cmd::{IngestArgs, IngestCommands, IngestFilesArgs, IngestTasksArgs};
-use resource_serde::{ingest, persist::*};
+use resource_serde::remote_db::{is_remote_state_db, RemoteStateDb};
+use resource_serde::{ingest, known_files, persist::*};
+
+use crate::ingest_health::{self, IngestHealth};
 
 // Implement methods for `AdminCommands`, ensure that whether the commands
 // are called from CLI or natively within Rust, all the calls remain ergonomic.
@@ -19,66 +28,400 @@ pub struct Ingest {}
 impl Ingest {
     #[autometrics]
     pub async fn execute(&self, cli: &super::Cli, args: &IngestArgs) -> anyhow::Result<()> {
+        let mut args = args.clone();
+        if args.background {
+            let priority = resource::priority::enter_background_mode();
+            info!(
+                "background mode: CPU priority {}, IO priority {}",
+                priority.cpu.as_deref().unwrap_or("unchanged"),
+                priority.io.as_deref().unwrap_or("unchanged")
+            );
+        }
+        let in_memory = if args.command.state_db_fs_path_mut() == IN_MEMORY_STATE_DB {
+            let state_db = InMemoryStateDb::new()?;
+            *args.command.state_db_fs_path_mut() = state_db.uri.clone();
+            Some(state_db)
+        } else if args.export_on_exit.is_some() {
+            return Err(anyhow!(
+                "[Ingest::execute] --export-on-exit requires `-d {}`",
+                IN_MEMORY_STATE_DB
+            ));
+        } else {
+            None
+        };
+
+        let remote = if is_remote_state_db(args.command.state_db_fs_path_mut()) {
+            let remote = RemoteStateDb::new(
+                args.command.state_db_fs_path_mut(),
+                args.remote_replica.as_deref(),
+            )?;
+            *args.command.state_db_fs_path_mut() = remote.replica_fs_path.clone();
+            Some(remote)
+        } else {
+            None
+        };
+
+        let watching = matches!(&args.command, IngestCommands::Files(ifa) if ifa.watch);
+        if args.health_addr.is_some() && args.every.is_none() && !watching {
+            return Err(anyhow!(
+                "[Ingest::execute] --health-addr requires --every or `ingest files --watch`"
+            ));
+        }
+
+        match args.every {
+            Some(every) => self.scheduled(cli, &args, remote.as_ref(), every).await?,
+            None => self.once(cli, &args, remote.as_ref()).await.map(|_| ())?,
+        }
+
+        if let (Some(state_db), Some(export_path)) = (&in_memory, &args.export_on_exit) {
+            let findings = match &args.export_if_sql {
+                Some(sql) => state_db.has_rows(sql)?,
+                None => true,
+            };
+            if findings {
+                state_db.export(export_path)?;
+                info!(
+                    "[Ingest::execute] exported the in-memory RSSD to {}",
+                    export_path
+                );
+            } else {
+                info!("[Ingest::execute] no findings, the in-memory RSSD was not exported");
+            }
+        }
+        Ok(())
+    }
+
+    /// Run a single ingestion session, returning the session ID when the
+    /// ingestion source creates one. With a remote state database the session
+    /// is pushed from the local replica once it's done.
+    async fn once(
+        &self,
+        cli: &super::Cli,
+        args: &IngestArgs,
+        remote: Option<&RemoteStateDb>,
+    ) -> anyhow::Result<Option<String>> {
+        let started = Instant::now();
+        let sessions = self.sessions(cli, args).await?;
+        if args.match_known_files {
+            for session in &sessions {
+                self.match_known_files(cli, session)?;
+            }
+        }
+        if args.emit_session_json {
+            for session in &sessions {
+                self.emit_session_json(cli, session, started.elapsed())?;
+            }
+        }
+        if let Some(remote) = remote {
+            let stats = remote.sync().await?;
+            info!(
+                "[Ingest::once] pushed {} rows of {} tables to {} in {} requests",
+                stats.rows,
+                stats.tables.len(),
+                remote.url,
+                stats.requests
+            );
+        }
+        Ok(sessions
+            .into_iter()
+            .next()
+            .map(|session| session.ingest_session_id))
+    }
+
+    /// The sessions created by the ingestion, the one in the default RSSD first.
+    async fn sessions(
+        &self,
+        cli: &super::Cli,
+        args: &IngestArgs,
+    ) -> anyhow::Result<Vec<ingest::IngestedSession>> {
+        let ingested = |state_db_fs_path: &str, ingest_session_id| {
+            vec![ingest::IngestedSession {
+                state_db_fs_path: state_db_fs_path.to_string(),
+                ingest_session_id,
+            }]
+        };
         match &args.command {
+            IngestCommands::Files(ifa) if ifa.watch => self
+                .watch_files(cli, ifa, args.health_addr)
+                .await
+                .map(|id| ingested(&ifa.state_db_fs_path, id)),
             IngestCommands::Files(ifa) => {
                 if ifa.dry_run {
                     self.files_dry_run(cli, &ifa.root_fs_path, ifa)
+                        .map(|_| vec![])
                 } else {
                     self.files(cli, ifa)
                 }
             }
-            IngestCommands::Tasks(ifa) => self.tasks(cli, ifa),
-            IngestCommands::Imap(ima) => ingest::ingest_imap(ima).await,
+            IngestCommands::Tasks(ifa) => self
+                .tasks(cli, ifa)
+                .map(|id| ingested(&ifa.state_db_fs_path, id)),
+            #[cfg(feature = "imap")]
+            IngestCommands::Imap(ima) => {
+                let mut ima = ima.clone();
+                ima.progress &= !cli.quiet;
+                ingest::ingest_imap(&ima)
+                    .await
+                    .map(|id| ingested(&ima.state_db_fs_path, id))
+            }
+            #[cfg(not(feature = "imap"))]
+            IngestCommands::Imap(_) => Err(resource_serde::cmd::feature_not_compiled("imap")),
+            IngestCommands::Uris(iua) => ingest::ingest_uris(iua)
+                .await
+                .map(|id| ingested(&iua.state_db_fs_path, id)),
+            IngestCommands::Api(iaa) => ingest::ingest_api(iaa)
+                .await
+                .map(|id| ingested(&iaa.state_db_fs_path, id)),
+            IngestCommands::Oci(ioa) => ingest::ingest_oci(ioa)
+                .await
+                .map(|id| ingested(&ioa.state_db_fs_path, id)),
+            IngestCommands::Browsers(iba) => {
+                ingest::ingest_browsers(iba).map(|id| ingested(&iba.state_db_fs_path, id))
+            }
+            IngestCommands::S3(isa) => ingest::ingest_s3(isa)
+                .await
+                .map(|id| ingested(&isa.state_db_fs_path, id)),
+            IngestCommands::Git(iga) => {
+                ingest::ingest_git(iga).map(|id| ingested(&iga.state_db_fs_path, id))
+            }
+            IngestCommands::Manifest(ima) => {
+                ingest::ingest_manifest(ima).map(|id| ingested(&ima.state_db_fs_path, id))
+            }
+            IngestCommands::Plist(ipa) => {
+                ingest::ingest_plist(ipa).map(|id| ingested(&ipa.state_db_fs_path, id))
+            }
+            IngestCommands::Registry(ira) => {
+                ingest::ingest_registry(ira).map(|id| ingested(&ira.state_db_fs_path, id))
+            }
+        }
+    }
+
+    /// Tag the session's resources which match known-file hashes.
+    fn match_known_files(
+        &self,
+        cli: &super::Cli,
+        session: &ingest::IngestedSession,
+    ) -> anyhow::Result<()> {
+        let mut dbc = DbConn::new(&session.state_db_fs_path, cli.debug)?;
+        let tx = dbc.init(None)?;
+        let stats = known_files::match_known_files(&tx, Some(&session.ingest_session_id))?;
+        tx.commit()?;
+        info!(
+            "[Ingest::match_known_files] session {}: {} known-good, {} known-bad, {} unknown",
+            session.ingest_session_id, stats.known_good, stats.known_bad, stats.unknown
+        );
+        Ok(())
+    }
+
+    /// Print the session's summary as a single line of JSON on STDOUT (logs go
+    /// to STDERR) so orchestration can capture the session ID.
+    fn emit_session_json(
+        &self,
+        cli: &super::Cli,
+        session: &ingest::IngestedSession,
+        duration: Duration,
+    ) -> anyhow::Result<()> {
+        let dbc = DbConn::open(&session.state_db_fs_path, cli.debug)?;
+        let summary = ingest::IngestSessionSummary::from_conn(
+            &dbc.conn,
+            &session.ingest_session_id,
+            &dbc.db_fs_path,
+            duration,
+        )?;
+        println!("{}", serde_json::to_string(&summary)?);
+        Ok(())
+    }
+
+    /// Keep ingesting every `every` seconds until interrupted, optionally
+    /// serving `/health` so fleet monitoring can detect stuck collectors.
+    async fn scheduled(
+        &self,
+        cli: &super::Cli,
+        args: &IngestArgs,
+        remote: Option<&RemoteStateDb>,
+        every: u64,
+    ) -> anyhow::Result<()> {
+        if let IngestCommands::Tasks(IngestTasksArgs {
+            tasks_from: None, ..
+        }) = args.command
+        {
+            return Err(anyhow!(
+                "[Ingest::scheduled] --every requires `ingest tasks --tasks-from` since tasks are read once from STDIN"
+            ));
+        }
+        if let IngestCommands::Files(IngestFilesArgs { watch: true, .. }) = args.command {
+            return Err(anyhow!(
+                "[Ingest::scheduled] --every is not supported with `ingest files --watch` since the watch already runs until interrupted"
+            ));
+        }
+        if every == 0 {
+            return Err(anyhow!(
+                "[Ingest::scheduled] --every must be at least 1 second"
+            ));
+        }
+
+        let interval = Duration::from_secs(every);
+        let health = IngestHealth::shared(interval);
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let health_server = args
+            .health_addr
+            .map(|addr| tokio::spawn(ingest_health::start(addr, health.clone(), shutdown_rx)));
+
+        // a single listener for the whole schedule: once it is registered SIGINT no
+        // longer terminates the process, so an interrupt during a session is kept
+        // until that session completes
+        let interrupted = Arc::new(AtomicBool::new(false));
+        let interrupt = Arc::new(Notify::new());
+        {
+            let interrupted = interrupted.clone();
+            let interrupt = interrupt.clone();
+            tokio::spawn(async move {
+                if tokio::signal::ctrl_c().await.is_ok() {
+                    interrupted.store(true, Ordering::SeqCst);
+                    interrupt.notify_one();
+                    info!("[Ingest::scheduled] interrupted, stopping once the current session completes (interrupt again to abort)");
+                    if tokio::signal::ctrl_c().await.is_ok() {
+                        std::process::exit(130);
+                    }
+                }
+            });
+        }
+
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        while !interrupted.load(Ordering::SeqCst) {
+            tokio::select! {
+                biased;
+                _ = interrupt.notified() => break,
+                _ = ticker.tick() => {}
+            }
+
+            let started = Instant::now();
+            // sessions mostly block, this keeps the interrupt listener and the health
+            // server running on the other workers even on a single CPU
+            let result = tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(self.once(cli, args, remote))
+            });
+            match &result {
+                Ok(session_id) => info!(
+                    "[Ingest::scheduled] session {} completed",
+                    session_id.as_deref().unwrap_or("(none)")
+                ),
+                Err(err) => error!("[Ingest::scheduled] session failed: {:#}", err),
+            }
+            health
+                .write()
+                .expect("ingest health lock poisoned")
+                .record(&result, started.elapsed());
+        }
+
+        let _ = shutdown_tx.send(());
+        if let Some(server) = health_server {
+            server.await??;
+        }
+        Ok(())
+    }
+
+    /// Watch the root paths in a single session until interrupted, optionally
+    /// serving `/health` with each checkpoint recorded like a scheduled session.
+    async fn watch_files(
+        &self,
+        cli: &super::Cli,
+        args: &IngestFilesArgs,
+        health_addr: Option<std::net::SocketAddr>,
+    ) -> anyhow::Result<String> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let interrupted = stop.clone();
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                interrupted.store(true, Ordering::SeqCst);
+            }
+        });
+
+        // checkpoints are only taken after a poll
+        let health = IngestHealth::shared(Duration::from_secs(
+            args.checkpoint_every.max(args.watch_interval),
+        ));
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let health_server = health_addr
+            .map(|addr| tokio::spawn(ingest_health::start(addr, health.clone(), shutdown_rx)));
+
+        let debug = cli.debug;
+        let args = args.clone();
+        let watched = tokio::task::spawn_blocking(move || {
+            ingest::watch_files(debug, &args, &stop, |checkpoint, elapsed| {
+                health
+                    .write()
+                    .expect("ingest health lock poisoned")
+                    .record(checkpoint, elapsed)
+            })
+        })
+        .await?;
+
+        let _ = shutdown_tx.send(());
+        if let Some(server) = health_server {
+            server.await??;
         }
+        watched
     }
 
-    fn files(&self, cli: &super::Cli, args: &IngestFilesArgs) -> anyhow::Result<()> {
+    fn files(
+        &self,
+        cli: &super::Cli,
+        args: &IngestFilesArgs,
+    ) -> anyhow::Result<Vec<ingest::IngestedSession>> {
         match ingest::ingest_files(cli.debug, args) {
-            Ok(ingest_session_id) => {
+            Ok(sessions) => {
                 if args.stats || args.stats_json {
-                    // only export the path if there's more than one
-                    let sql = if args.root_fs_path.len() > 1 || args.stats_json {
-                        r"SELECT ingest_session_root_fs_path as 'Path',
+                    for ingest::IngestedSession {
+                        state_db_fs_path,
+                        ingest_session_id,
+                    } in &sessions
+                    {
+                        // only export the path if there's more than one
+                        let sql = if args.root_fs_path.len() > 1 || args.stats_json {
+                            r"SELECT ingest_session_root_fs_path as 'Path',
                                  file_extension as 'Extn',
                                  total_file_count AS 'Count',
                                  file_count_with_content AS 'Content',
                                  file_count_with_frontmatter AS 'Frontmatter'
                             FROM ur_ingest_session_files_stats
                            WHERE ingest_session_id = ?"
-                    } else {
-                        r"SELECT file_extension as 'Extn',
+                        } else {
+                            r"SELECT file_extension as 'Extn',
                                  total_file_count AS 'Count',
                                  file_count_with_content AS 'Content',
                                  file_count_with_frontmatter AS 'Frontmatter'
                             FROM ur_ingest_session_files_stats
                            WHERE ingest_session_id = ?"
-                    };
-
-                    let dbc = DbConn::open(&args.state_db_fs_path, cli.debug)?;
-                    if args.stats_json {
-                        let value = dbc.query_result_as_json_value(
-                            sql,
-                            rusqlite::params![ingest_session_id],
-                        )?;
-                        info!("{}", serde_json::to_string_pretty(&value)?);
-                    } else {
-                        let table = dbc.query_result_as_formatted_table(
-                            sql,
-                            rusqlite::params![ingest_session_id],
-                        )?;
-                        info!(
-                            "\n==> `ur_ingest_session_files_stats` for session ID '{}':\n{}",
-                            ingest_session_id, table
-                        )
+                        };
+
+                        let dbc = DbConn::open(state_db_fs_path, cli.debug)?;
+                        if args.stats_json {
+                            let value = dbc.query_result_as_json_value(
+                                sql,
+                                rusqlite::params![ingest_session_id],
+                            )?;
+                            info!("{}", serde_json::to_string_pretty(&value)?);
+                        } else {
+                            let table = dbc.query_result_as_formatted_table(
+                                sql,
+                                rusqlite::params![ingest_session_id],
+                            )?;
+                            info!(
+                                "\n==> `ur_ingest_session_files_stats` for session ID '{}' in {}:\n{}",
+                                ingest_session_id, state_db_fs_path, table
+                            )
+                        }
                     }
                 }
-                Ok(())
+                Ok(sessions)
             }
             Err(err) => Err(err),
         }
     }
 
-    fn tasks(&self, cli: &super::Cli, args: &IngestTasksArgs) -> anyhow::Result<()> {
+    fn tasks(&self, cli: &super::Cli, args: &IngestTasksArgs) -> anyhow::Result<String> {
         match ingest::ingest_tasks(cli.debug, args) {
             Ok(ingest_session_id) => {
                 if args.stats || args.stats_json {
@@ -109,7 +452,7 @@ impl Ingest {
                         )
                     }
                 }
-                Ok(())
+                Ok(ingest_session_id)
             }
             Err(err) => Err(err),
         }
@@ -451,6 +794,36 @@ mod tests {
             stats: false,
             stats_json: false,
             save_behavior: None,
+            ce_json_filter: None,
+            ce_sql_validate_only: false,
+            keep_ce_workdirs: false,
+            decode_payloads: false,
+            canonical_json: false,
+            content_chunk_size: None,
+            blob_store: None,
+            blob_threshold: 1024,
+            jobs: 1,
+            commit_every: None,
+            digest: Default::default(),
+            expand_archives: false,
+            archive_password: vec![],
+            max_archive_member_size: 256 * 1024 * 1024,
+            trigger: vec![],
+            route: vec![],
+            ignore_collect_manifests: false,
+            trust_collect_manifest: vec![],
+            walk_order: Default::default(),
+            max_dir_entries: None,
+            skip_unreadable: false,
+            fail_on_unreadable: false,
+            sudo_read: vec![],
+            sudo_user: "root".to_string(),
+            incremental: false,
+            watch: false,
+            watch_interval: 2,
+            watch_poll: false,
+            checkpoint_every: 60,
+            limits: Default::default(),
         };
 
         let cli = build_cli(
@@ -460,12 +833,22 @@ mod tests {
         );
         let ingest_cmd = IngestCommands::Files(ingest_file_args);
         let ingest = Ingest::default();
-        let res = ingest.execute(
-            &cli,
-            &IngestArgs {
-                command: ingest_cmd.clone(),
-            },
-        ).await;
+        let res = ingest
+            .execute(
+                &cli,
+                &IngestArgs {
+                    command: ingest_cmd.clone(),
+                    every: None,
+                    health_addr: None,
+                    emit_session_json: false,
+                    export_on_exit: None,
+                    export_if_sql: None,
+                    match_known_files: false,
+                    background: false,
+                    remote_replica: None,
+                },
+            )
+            .await;
         assert!(res.is_ok());
     }
 
@@ -484,6 +867,36 @@ mod tests {
             stats: false,
             stats_json: false,
             save_behavior: None,
+            ce_json_filter: None,
+            ce_sql_validate_only: false,
+            keep_ce_workdirs: false,
+            decode_payloads: false,
+            canonical_json: false,
+            content_chunk_size: None,
+            blob_store: None,
+            blob_threshold: 1024,
+            jobs: 1,
+            commit_every: None,
+            digest: Default::default(),
+            expand_archives: false,
+            archive_password: vec![],
+            max_archive_member_size: 256 * 1024 * 1024,
+            trigger: vec![],
+            route: vec![],
+            ignore_collect_manifests: false,
+            trust_collect_manifest: vec![],
+            walk_order: Default::default(),
+            max_dir_entries: None,
+            skip_unreadable: false,
+            fail_on_unreadable: false,
+            sudo_read: vec![],
+            sudo_user: "root".to_string(),
+            incremental: false,
+            watch: false,
+            watch_interval: 2,
+            watch_poll: false,
+            checkpoint_every: 60,
+            limits: Default::default(),
         };
 
         let cli = build_cli(
@@ -493,12 +906,22 @@ mod tests {
         );
         let ingest_cmd = IngestCommands::Files(ingest_file_args);
         let ingest = Ingest::default();
-        let res = ingest.execute(
-            &cli,
-            &IngestArgs {
-                command: ingest_cmd.clone(),
-            },
-        ).await;
+        let res = ingest
+            .execute(
+                &cli,
+                &IngestArgs {
+                    command: ingest_cmd.clone(),
+                    every: None,
+                    health_addr: None,
+                    emit_session_json: true,
+                    export_on_exit: None,
+                    export_if_sql: None,
+                    match_known_files: false,
+                    background: false,
+                    remote_replica: None,
+                },
+            )
+            .await;
         assert!(res.is_ok());
     }
 }
@@ -0,0 +1,177 @@
+use std::{
+    net::SocketAddr,
+    process,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use axum::{extract::State, http::StatusCode, routing::get, Json, Router};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+use tracing::{error, info, warn};
+
+/// A collector is considered stuck when no session succeeded within this many intervals.
+const STUCK_AFTER_INTERVALS: u32 = 3;
+
+/// Health of a scheduled (`ingest --every`) collector, shared between the
+/// ingestion loop and the health server.
+#[derive(Debug, Clone)]
+pub struct IngestHealth {
+    pub interval: Duration,
+    pub started_at: DateTime<Utc>,
+    pub last_success_at: Option<DateTime<Utc>>,
+    pub last_session_id: Option<String>,
+    pub last_error: Option<String>,
+    pub sessions_succeeded: u64,
+    pub sessions_failed: u64,
+    /// scheduled sessions which came due while a previous session was still running
+    pub backlog: u64,
+}
+
+pub type SharedIngestHealth = Arc<RwLock<IngestHealth>>;
+
+impl IngestHealth {
+    pub fn shared(interval: Duration) -> SharedIngestHealth {
+        Arc::new(RwLock::new(IngestHealth {
+            interval,
+            started_at: Utc::now(),
+            last_success_at: None,
+            last_session_id: None,
+            last_error: None,
+            sessions_succeeded: 0,
+            sessions_failed: 0,
+            backlog: 0,
+        }))
+    }
+
+    /// Record the outcome of a session which took `elapsed` to complete.
+    pub fn record(&mut self, result: &anyhow::Result<Option<String>>, elapsed: Duration) {
+        match result {
+            Ok(session_id) => {
+                self.sessions_succeeded += 1;
+                self.last_success_at = Some(Utc::now());
+                self.last_session_id.clone_from(session_id);
+                self.last_error = None;
+            }
+            Err(err) => {
+                self.sessions_failed += 1;
+                self.last_error = Some(err.to_string());
+            }
+        }
+        if !self.interval.is_zero() {
+            self.backlog += (elapsed.as_millis() / self.interval.as_millis()) as u64;
+        }
+    }
+
+    fn status(&self) -> (StatusCode, &'static str) {
+        let since = self.last_success_at.unwrap_or(self.started_at);
+        let stuck_after = chrono::Duration::from_std(self.interval * STUCK_AFTER_INTERVALS)
+            .unwrap_or(chrono::Duration::MAX);
+        if Utc::now() - since > stuck_after {
+            (StatusCode::SERVICE_UNAVAILABLE, "fail")
+        } else if self.last_error.is_some() {
+            (StatusCode::OK, "warn")
+        } else {
+            (StatusCode::OK, "pass")
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct IngestHealthResponseData {
+    status: String,
+    version: String,
+    description: String,
+    service_id: String,
+    interval_secs: u64,
+    last_successful_session_at: Option<DateTime<Utc>>,
+    last_session_id: Option<String>,
+    last_error: Option<String>,
+    sessions_succeeded: u64,
+    sessions_failed: u64,
+    backlog: u64,
+}
+
+pub async fn start(
+    addr: SocketAddr,
+    health: SharedIngestHealth,
+    shutdown_signal: oneshot::Receiver<()>,
+) -> anyhow::Result<()> {
+    let app = Router::new()
+        .route("/health", get(get_health))
+        .with_state(health);
+
+    match tokio::net::TcpListener::bind(addr).await {
+        Ok(listener) => {
+            info!("Ingest health server is binding on {}", addr);
+            if let Err(e) = axum::serve(listener, app)
+                .with_graceful_shutdown(graceful_shutdown(shutdown_signal))
+                .await
+            {
+                error!("Ingest health server error: {}", e);
+                return Err(e.into());
+            }
+        }
+        Err(e) => {
+            error!("Failed to bind ingest health server on {}: {}", addr, e);
+            return Err(e.into());
+        }
+    }
+
+    Ok(())
+}
+
+async fn graceful_shutdown(shutdown_signal: oneshot::Receiver<()>) {
+    match shutdown_signal.await {
+        Ok(()) => info!("Ingest health server has received shutdown signal."),
+        Err(err) => warn!("Ingest health server shutdown signal dropped: {}", err),
+    }
+}
+
+async fn get_health(
+    State(health): State<SharedIngestHealth>,
+) -> (StatusCode, Json<IngestHealthResponseData>) {
+    let health = health.read().expect("ingest health lock poisoned").clone();
+    let (code, status) = health.status();
+    let res = IngestHealthResponseData {
+        status: status.to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        description: env!("CARGO_PKG_DESCRIPTION").to_string(),
+        service_id: process::id().to_string(),
+        interval_secs: health.interval.as_secs(),
+        last_successful_session_at: health.last_success_at,
+        last_session_id: health.last_session_id,
+        last_error: health.last_error,
+        sessions_succeeded: health.sessions_succeeded,
+        sessions_failed: health.sessions_failed,
+        backlog: health.backlog,
+    };
+    (code, Json(res))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    #[test]
+    fn test_record_and_status() {
+        let health = IngestHealth::shared(Duration::from_secs(10));
+        let mut health = health.write().unwrap();
+        assert_eq!(health.status().1, "pass");
+
+        health.record(&Err(anyhow!("boom")), Duration::from_secs(1));
+        assert_eq!(health.status().1, "warn");
+        assert_eq!(health.sessions_failed, 1);
+
+        health.record(&Ok(Some("session".to_string())), Duration::from_secs(25));
+        assert_eq!(health.status().1, "pass");
+        assert_eq!(health.backlog, 2);
+        assert_eq!(health.last_session_id.as_deref(), Some("session"));
+
+        health.last_success_at = Some(Utc::now() - chrono::Duration::seconds(31));
+        assert_eq!(health.status(), (StatusCode::SERVICE_UNAVAILABLE, "fail"));
+    }
+}
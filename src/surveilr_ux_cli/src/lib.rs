@@ -1,17 +1,33 @@
 use std::path::PathBuf;
 
+use chrono::{DateTime, Utc};
 use clap::{Parser, Subcommand, ValueEnum};
 use common::DEVICE;
+use resource_serde::audit::{os_user, record_audit, AuditEntry};
 use resource_serde::cmd::{
-    transform::TransformArgs, AdminArgs, CapturableExecArgs, IngestArgs, NotebooksArgs, SQLPageArgs,
+    compliance::{ComplianceArgs, ComplianceCommands},
+    known_files::{KnownFilesArgs, KnownFilesCommands},
+    orchestrate::OrchestrateArgs,
+    policy::{PolicyAlertCommands, PolicyArgs, PolicyCommands},
+    resources::ResourcesArgs,
+    serve::ServeArgs,
+    sessions::SessionsArgs,
+    snapshot::{SnapshotArgs, SnapshotCommands},
+    transform::TransformArgs,
+    AdminArgs, AdminCommands, CapturableExecArgs, ComputedColumnArgs, ComputedColumnCommands,
+    IngestArgs, IngestCommands, NotebooksArgs, NotebooksCommands, SQLPageArgs,
 };
+use resource_serde::persist::DbConn;
 use serde::Serialize;
+use tracing::warn;
 use udi::UdiArgs;
 
 pub mod admin;
 pub mod capexec;
 pub mod ingest;
+pub mod ingest_health;
 pub mod notebooks;
+pub mod orchestrate;
 pub mod service_management;
 pub mod sql_page;
 pub mod udi;
@@ -45,6 +61,30 @@ pub struct Cli {
     /// File for logs to be written to
     #[arg(long, value_parser)]
     pub log_file: Option<PathBuf>,
+
+    /// Rotate the log file before it grows past this size, e.g. 10M (K, M and G are powers of 1024)
+    #[arg(long, requires = "log_file", env = "SURVEILR_LOG_MAX_SIZE", value_parser = service_management::logger::parse_log_size)]
+    pub log_max_size: Option<u64>,
+
+    /// Rotate the log file every hour or day (UTC)
+    #[arg(long, value_enum, requires = "log_file", env = "SURVEILR_LOG_ROTATE")]
+    pub log_rotate: Option<service_management::logger::LogRotationPeriod>,
+
+    /// Number of rotated log files to keep, the oldest are deleted
+    #[arg(long, default_value_t = 5, env = "SURVEILR_LOG_KEEP")]
+    pub log_keep: usize,
+
+    /// Compress rotated log files with gzip
+    #[arg(long, env = "SURVEILR_LOG_COMPRESS", value_parser = clap::builder::FalseyValueParser::new())]
+    pub log_compress: bool,
+
+    /// Suppress spinners, progress bars, stats tables and informational logs (warnings and errors are still emitted)
+    #[arg(short, long, global = true, env = "SURVEILR_QUIET", value_parser = clap::builder::FalseyValueParser::new())]
+    pub quiet: bool,
+
+    /// Disable ANSI colors in logs and progress output (as does NO_COLOR set to any value)
+    #[arg(long, global = true, default_value_t = service_management::logger::no_color_env())]
+    pub no_color: bool,
 }
 
 #[allow(clippy::large_enum_variant)]
@@ -53,22 +93,185 @@ pub enum CliCommands {
     Admin(AdminArgs),
     CapturableExec(CapturableExecArgs),
     Ingest(IngestArgs),
+    Orchestrate(OrchestrateArgs),
     Notebooks(NotebooksArgs),
     #[clap(name = "sqlpage")]
     SQLPage(SQLPageArgs),
     #[clap(name = "udi")]
     Udi(UdiArgs),
     Transform(TransformArgs),
+    Snapshot(SnapshotArgs),
+    Serve(ServeArgs),
+    Resources(ResourcesArgs),
+    Policy(PolicyArgs),
+    Compliance(ComplianceArgs),
+    KnownFiles(KnownFilesArgs),
+    Sessions(SessionsArgs),
+}
+
+impl CliCommands {
+    /// The name and target RSSD of the commands which change or destroy state,
+    /// their invocations are recorded in the RSSD's `surveilr_audit` table.
+    pub fn audited(&self) -> Option<(&'static str, &str)> {
+        match self {
+            CliCommands::Admin(args) => match &args.command {
+                AdminCommands::Init {
+                    state_db_fs_path, ..
+                } => Some(("admin init", state_db_fs_path)),
+                AdminCommands::Merge {
+                    state_db_fs_path,
+                    sql_only: false,
+                    ..
+                } => Some(("admin merge", state_db_fs_path)),
+                AdminCommands::Seed {
+                    state_db_fs_path, ..
+                } => Some(("admin seed", state_db_fs_path)),
+                AdminCommands::Restore {
+                    state_db_fs_path, ..
+                } => Some(("admin restore", state_db_fs_path)),
+                AdminCommands::Reclassify {
+                    state_db_fs_path,
+                    apply: true,
+                    ..
+                } => Some(("admin reclassify --apply", state_db_fs_path)),
+                AdminCommands::Prune {
+                    state_db_fs_path,
+                    apply: true,
+                    ..
+                } => Some(("admin prune --apply", state_db_fs_path)),
+                AdminCommands::Verify {
+                    state_db_fs_path,
+                    record: true,
+                    ..
+                } => Some(("admin verify --record", state_db_fs_path)),
+                AdminCommands::ComputedColumn(ComputedColumnArgs {
+                    state_db_fs_path,
+                    command:
+                        ComputedColumnCommands::Add { .. } | ComputedColumnCommands::Drop { .. },
+                }) => Some(("admin computed-column", state_db_fs_path)),
+                _ => None,
+            },
+            CliCommands::Ingest(args) => match &args.command {
+                IngestCommands::Files(files) if files.save_behavior.is_some() => {
+                    Some(("ingest files --save-behavior", &files.state_db_fs_path))
+                }
+                _ => None,
+            },
+            CliCommands::Notebooks(args) => match (&args.command, &args.state_db_fs_path) {
+                (NotebooksCommands::Publish { dry_run: false, .. }, Some(state_db_fs_path)) => {
+                    Some(("notebooks publish", state_db_fs_path))
+                }
+                _ => None,
+            },
+            CliCommands::Snapshot(args) => match &args.command {
+                SnapshotCommands::Create { .. } => {
+                    Some(("snapshot create", &args.state_db_fs_path))
+                }
+                _ => None,
+            },
+            CliCommands::Policy(args) => match &args.command {
+                PolicyCommands::Add { .. } => Some(("policy add", &args.state_db_fs_path)),
+                PolicyCommands::Ack { .. } => Some(("policy ack", &args.state_db_fs_path)),
+                PolicyCommands::Alert {
+                    command: PolicyAlertCommands::Add { .. },
+                } => Some(("policy alert add", &args.state_db_fs_path)),
+                PolicyCommands::Alert {
+                    command: PolicyAlertCommands::Rm { .. },
+                } => Some(("policy alert rm", &args.state_db_fs_path)),
+                _ => None,
+            },
+            CliCommands::Compliance(args) => match &args.command {
+                ComplianceCommands::Import { .. } => {
+                    Some(("compliance import", &args.state_db_fs_path))
+                }
+                ComplianceCommands::Map { .. } => Some(("compliance map", &args.state_db_fs_path)),
+                ComplianceCommands::Unmap { .. } => {
+                    Some(("compliance unmap", &args.state_db_fs_path))
+                }
+                _ => None,
+            },
+            CliCommands::KnownFiles(args) => match &args.command {
+                KnownFilesCommands::Load { .. } => {
+                    Some(("known-files load", &args.state_db_fs_path))
+                }
+                KnownFilesCommands::Match { .. } => {
+                    Some(("known-files match", &args.state_db_fs_path))
+                }
+                KnownFilesCommands::Remove { .. } => {
+                    Some(("known-files remove", &args.state_db_fs_path))
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Record an audited command in its RSSD, unless the command failed before
+/// the RSSD was created.
+pub(crate) fn audit(
+    cli: &Cli,
+    command: &str,
+    argv: Vec<String>,
+    state_db_fs_path: &str,
+    started_at: DateTime<Utc>,
+    result: &anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    if !std::path::Path::new(state_db_fs_path).exists() {
+        return Ok(());
+    }
+    let entry = AuditEntry {
+        command: command.to_string(),
+        argv,
+        os_user: os_user(),
+        host: cli
+            .device_name
+            .clone()
+            .unwrap_or_else(|| DEVICE.name().to_string()),
+        started_at,
+        error: result.as_ref().err().map(|err| format!("{:#}", err)),
+    };
+    let mut dbc = DbConn::new(state_db_fs_path, 0)?;
+    // makes sure RSSDs created before `surveilr_audit` existed are migrated
+    let tx = dbc.init(None)?;
+    record_audit(&tx, &entry)?;
+    tx.commit()?;
+    Ok(())
 }
 
 pub async fn execute(cli: &Cli) -> anyhow::Result<()> {
+    let started_at = Utc::now();
+    let result = execute_command(cli).await;
+    if let Some((command, state_db_fs_path)) = cli.command.audited() {
+        let argv = std::env::args().collect();
+        if let Err(err) = audit(cli, command, argv, state_db_fs_path, started_at, &result) {
+            warn!(
+                "unable to record {} in {}: {:#}",
+                command, state_db_fs_path, err
+            );
+        }
+    }
+    result
+}
+
+async fn execute_command(cli: &Cli) -> anyhow::Result<()> {
     match &cli.command {
-        CliCommands::Admin(args) => admin::Admin::default().execute(args, cli),
+        CliCommands::Admin(args) => admin::Admin::default().execute(args, cli).await,
         CliCommands::CapturableExec(args) => capexec::CapturableExec::default().execute(cli, args),
         CliCommands::Ingest(args) => ingest::Ingest::default().execute(cli, args).await,
+        CliCommands::Orchestrate(args) => {
+            orchestrate::Orchestrate::default().execute(cli, args).await
+        }
         CliCommands::Notebooks(args) => notebooks::Notebooks::default().execute(cli, args),
         CliCommands::SQLPage(args) => sql_page::SqlPage::default().execute(args).await,
-        CliCommands::Udi(args) => args.execute().await,
+        CliCommands::Udi(args) => args.execute(cli).await,
         CliCommands::Transform(args) => args.transform(),
+        CliCommands::Snapshot(args) => args.execute(),
+        CliCommands::Serve(args) => args.execute().await,
+        CliCommands::Resources(args) => args.execute().await,
+        CliCommands::Policy(args) => args.execute().await,
+        CliCommands::Compliance(args) => args.execute(),
+        CliCommands::KnownFiles(args) => args.execute(),
+        CliCommands::Sessions(args) => args.execute(),
     }
 }
@@ -1,3 +1,4 @@
+use anyhow::{anyhow, Context};
 use autometrics::autometrics;
 use rusqlite::{Connection, OpenFlags};
 use tracing::error;
@@ -7,6 +8,44 @@ use common::format::*;
 use resource_serde::cmd::{NotebooksArgs, NotebooksCommands};
 use resource_serde::persist::*;
 
+/// Notebooks whose cells build the RSSD itself (bootstrap DDL and migrations).
+const MIGRATION_NOTEBOOKS: &[&str] = &["BootstrapSqlNotebook", "ConstructionSqlNotebook"];
+
+/// Without `--cell`, only cells meant to be queried are published; the DDL of
+/// migrations (and `once_` cells of any notebook) must be asked for by name.
+fn is_publishable(notebook: &str, cell: &str, cells: &[String]) -> bool {
+    !cells.is_empty() || !(MIGRATION_NOTEBOOKS.contains(&notebook) || cell.contains("_once_"))
+}
+
+/// Path of the SQLPage page of `cell`: a single path component (characters
+/// other than letters, digits, `-`, `_` and `.` become `_`) under the
+/// `/`-separated components of `path_prefix`, none of which may be `.` or `..`.
+fn sqlpage_path(path_prefix: &str, cell: &str) -> String {
+    let component = |part: &str| -> String {
+        let part: String = part
+            .chars()
+            .map(|c| match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' => c,
+                _ => '_',
+            })
+            .collect();
+        if part.chars().all(|c| c == '.') {
+            part.replace('.', "_")
+        } else {
+            part
+        }
+    };
+    let mut path: String = path_prefix
+        .split('/')
+        .filter(|part| !part.is_empty())
+        .map(|part| format!("{}/", component(part)))
+        .collect();
+    let page = component(cell.strip_suffix(".sql").unwrap_or(cell));
+    path.push_str(&page);
+    path.push_str(".sql");
+    path
+}
+
 // Implement methods for `NotebooksCommands`, ensure that whether the commands
 // are called from CLI or natively within Rust, all the calls remain ergonomic.
 #[derive(Debug, Default)]
@@ -28,9 +67,27 @@ impl Notebooks {
                     self.ls(args)
                 }
             }
+            NotebooksCommands::Publish {
+                notebook,
+                cell,
+                path_prefix,
+                dry_run,
+            } => self.publish(args, notebook, cell, path_prefix, *dry_run),
         }
     }
 
+    /// `cat` and `ls` only read so `--read-only` opens the RSSD without write access.
+    fn connection(&self, args: &NotebooksArgs, db_fs_path: &str) -> rusqlite::Result<Connection> {
+        let flags = if args.read_only {
+            OpenFlags::SQLITE_OPEN_READ_ONLY
+        } else {
+            OpenFlags::SQLITE_OPEN_READ_WRITE
+        };
+        let conn = Connection::open_with_flags(db_fs_path, flags)?;
+        set_busy_timeout(&conn)?;
+        Ok(conn)
+    }
+
     fn cat(
         &self,
         args: &NotebooksArgs,
@@ -39,9 +96,7 @@ impl Notebooks {
         seps: bool,
     ) -> anyhow::Result<()> {
         if let Some(db_fs_path) = args.state_db_fs_path.as_deref() {
-            if let Ok(conn) =
-                Connection::open_with_flags(db_fs_path, OpenFlags::SQLITE_OPEN_READ_WRITE)
-            {
+            if let Ok(conn) = self.connection(args, db_fs_path) {
                 match select_notebooks_and_cells(&conn, notebooks, cells) {
                     Ok(matched) => {
                         for row in matched {
@@ -64,11 +119,68 @@ impl Notebooks {
         Ok(())
     }
 
+    fn publish(
+        &self,
+        args: &NotebooksArgs,
+        notebooks: &Vec<String>,
+        cells: &Vec<String>,
+        path_prefix: &str,
+        dry_run: bool,
+    ) -> anyhow::Result<()> {
+        let db_fs_path = args
+            .state_db_fs_path
+            .as_deref()
+            .ok_or_else(|| anyhow!("Notebooks publish command requires a database"))?;
+        if args.read_only {
+            return Err(anyhow!(
+                "Notebooks publish writes to the database, remove --read-only"
+            ));
+        }
+        let mut dbc = DbConn::new(db_fs_path, 0)
+            .with_context(|| format!("[notebooks publish] SQLite database {}", db_fs_path))?;
+        let tx = dbc.init(None)?;
+
+        // a cell may have multiple versions so only the latest code is published
+        let mut matched: Vec<(String, String)> = Vec::new();
+        for (notebook, kernel, cell, _code) in select_notebooks_and_cells(&tx, notebooks, cells)? {
+            if !is_publishable(&notebook, &cell, cells) {
+                continue;
+            }
+            if kernel != "SQL" {
+                info!("Skipping {notebook}::{cell}, {kernel} cells cannot be SQLPage pages");
+            } else if !matched.contains(&(notebook.clone(), cell.clone())) {
+                matched.push((notebook, cell));
+            }
+        }
+
+        let mut rows: Vec<Vec<String>> = Vec::new();
+        for (notebook, cell) in matched {
+            let (cell_id, code) = select_notebook_cell_code_latest(&tx, &notebook, &cell)?;
+            let path = sqlpage_path(path_prefix, &cell);
+            if !dry_run {
+                upsert_sqlpage_file(&tx, &path, &code)
+                    .with_context(|| format!("[notebooks publish] {notebook}::{cell} as {path}"))?;
+            }
+            rows.push(vec![path, notebook, cell, cell_id]);
+        }
+
+        if dry_run {
+            tx.rollback()?;
+        } else {
+            tx.commit()
+                .with_context(|| format!("[notebooks publish] commit in {}", db_fs_path))?;
+        }
+
+        println!(
+            "{}",
+            as_ascii_table(&["SQLPage Path", "Notebook", "Cell", "Cell ID"], &rows)
+        );
+        Ok(())
+    }
+
     fn ls(&self, args: &NotebooksArgs) -> anyhow::Result<()> {
         if let Some(db_fs_path) = args.state_db_fs_path.as_deref() {
-            if let Ok(conn) =
-                Connection::open_with_flags(db_fs_path, OpenFlags::SQLITE_OPEN_READ_WRITE)
-            {
+            if let Ok(conn) = self.connection(args, db_fs_path) {
                 let mut rows: Vec<Vec<String>> = Vec::new(); // Declare the rows as a vector of vectors of strings
                 notebook_cells_versions(&conn, |_index, kernel, nb, cell: String, versions, id| {
                     rows.push(vec![nb, kernel, cell, versions.to_string(), id]);
@@ -88,9 +200,7 @@ impl Notebooks {
 
     fn ls_migrations(&self, args: &NotebooksArgs) -> anyhow::Result<()> {
         if let Some(db_fs_path) = args.state_db_fs_path.as_deref() {
-            if let Ok(conn) =
-                Connection::open_with_flags(db_fs_path, OpenFlags::SQLITE_OPEN_READ_WRITE)
-            {
+            if let Ok(conn) = self.connection(args, db_fs_path) {
                 let mut rows: Vec<Vec<String>> = Vec::new(); // Declare the rows as a vector of vectors of strings
                 migratable_notebook_cells_all_with_versions(
                     &conn,
@@ -181,3 +291,71 @@ impl Notebooks {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use crate::{Cli, CliCommands};
+
+    #[test]
+    fn test_sqlpage_path() {
+        assert_eq!(sqlpage_path("", "stats"), "stats.sql");
+        assert_eq!(
+            sqlpage_path("dashboards/", "stats.sql"),
+            "dashboards/stats.sql"
+        );
+        assert_eq!(
+            sqlpage_path("/dashboards//ops", "stats"),
+            "dashboards/ops/stats.sql"
+        );
+        assert_eq!(sqlpage_path("../", "../../index"), "__/.._.._index.sql");
+        assert_eq!(sqlpage_path("", ".."), "__.sql");
+        assert_eq!(sqlpage_path("a/../b/", "c/d"), "a/__/b/c_d.sql");
+    }
+
+    #[test]
+    fn test_publish_skips_migrations() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let db_fs_path = dir.path().join("publish.sqlite.db");
+        let db_fs_path = db_fs_path.to_string_lossy().to_string();
+        {
+            let mut dbc = DbConn::new(&db_fs_path, 0)?;
+            let tx = dbc.init(None)?;
+            tx.execute(
+                "INSERT INTO code_notebook_cell (code_notebook_cell_id, notebook_kernel_id, notebook_name, cell_name, interpretable_code, interpretable_code_hash)
+                 VALUES ('dash', 'SQL', 'Dashboards', '../../index', 'SELECT 1', 'hash')",
+                [],
+            )?;
+            tx.commit()?;
+        }
+
+        let publish = |cell: Option<&str>| -> anyhow::Result<Vec<String>> {
+            let mut args = vec!["surveilr", "notebooks", "-d", &db_fs_path, "publish"];
+            if let Some(cell) = cell {
+                args.extend(["--cell", cell]);
+            }
+            let cli = Cli::parse_from(args);
+            let CliCommands::Notebooks(notebooks_args) = &cli.command else {
+                unreachable!("notebooks command");
+            };
+            Notebooks::default().execute(&cli, notebooks_args)?;
+            let conn = Connection::open(&db_fs_path)?;
+            let mut stmt = conn.prepare("SELECT path FROM sqlpage_files ORDER BY path")?;
+            let paths = stmt
+                .query_map([], |row| row.get(0))?
+                .collect::<rusqlite::Result<Vec<String>>>()?;
+            Ok(paths)
+        };
+
+        let published = publish(None)?;
+        assert!(published.contains(&".._.._index.sql".to_string()));
+        assert!(!published.iter().any(|path| path.contains("_once_")));
+        assert!(!published.iter().any(|path| path.starts_with("bootstrap")));
+
+        let published = publish(Some("v001_once_initialDDL"))?;
+        assert!(published.contains(&"v001_once_initialDDL.sql".to_string()));
+        Ok(())
+    }
+}
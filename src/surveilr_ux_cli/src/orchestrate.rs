@@ -0,0 +1,167 @@
+use std::iter;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use chrono::{DateTime, Local, Utc};
+use clap::Parser;
+use comfy_table::{presets::UTF8_FULL, Table};
+use tracing::{error, info, warn};
+
+use resource_serde::cmd::orchestrate::OrchestrateArgs;
+use resource_serde::cmd::{IngestArgs, IngestCommands, IngestFilesArgs, IngestTasksArgs};
+use resource_serde::schedule::{read_schedules, Schedule};
+
+use crate::{audit, ingest::Ingest, Cli, CliCommands};
+
+/// The longest the orchestrator sleeps before checking the clock again, so
+/// clock changes and suspends delay runs by at most this long.
+const MAX_SLEEP: Duration = Duration::from_secs(60);
+
+/// A schedule with its parsed `ingest` command and when it's due next.
+struct Job {
+    schedule: Schedule,
+    cli: Cli,
+    ingest: IngestArgs,
+    next: Option<DateTime<Local>>,
+}
+
+#[derive(Debug, Default)]
+pub struct Orchestrate {}
+
+impl Orchestrate {
+    /// Run each schedule's ingestion whenever its cron expression comes due
+    /// (in local time) until interrupted. Runs are sequential: when a run is
+    /// still in progress at a schedule's next time that occurrence is skipped.
+    pub async fn execute(&self, cli: &Cli, args: &OrchestrateArgs) -> anyhow::Result<()> {
+        let now = Local::now();
+        let mut jobs = read_schedules(&args.schedules)?
+            .into_iter()
+            .map(|schedule| {
+                let (mut job_cli, ingest) = scheduled_ingest(&schedule)?;
+                job_cli.debug = job_cli.debug.max(cli.debug);
+                job_cli.quiet |= cli.quiet;
+                Ok(Job {
+                    next: schedule.cron.next_after(&now),
+                    schedule,
+                    cli: job_cli,
+                    ingest,
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        if args.dry_run {
+            let mut table = Table::new();
+            table
+                .load_preset(UTF8_FULL)
+                .set_header(vec!["Schedule", "Cron", "Next run", "Command"]);
+            for job in &jobs {
+                table.add_row(vec![
+                    job.schedule.name.clone(),
+                    job.schedule.cron.expression().to_string(),
+                    job.next
+                        .map(|next| next.format("%Y-%m-%d %H:%M %Z").to_string())
+                        .unwrap_or_else(|| "never".to_string()),
+                    job.schedule.command.join(" "),
+                ]);
+            }
+            println!("{table}");
+            return Ok(());
+        }
+
+        info!(
+            "[Orchestrate::execute] running {} schedules from {}",
+            jobs.len(),
+            args.schedules
+        );
+        loop {
+            let Some(due) = jobs.iter().filter_map(|job| job.next).min() else {
+                warn!("[Orchestrate::execute] none of the schedules will run again");
+                break;
+            };
+            let wait = (due - Local::now()).to_std().unwrap_or_default();
+            tokio::select! {
+                _ = tokio::time::sleep(wait.min(MAX_SLEEP)) => {}
+                _ = tokio::signal::ctrl_c() => break,
+            }
+            if Local::now() < due {
+                continue;
+            }
+
+            for job in jobs.iter_mut() {
+                if job.next.is_some_and(|next| next <= due) {
+                    self.run(job).await;
+                    job.next = job.schedule.cron.next_after(&Local::now().max(due));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Run the job's ingestion and record the run in its RSSD's
+    /// `surveilr_audit` table, the ingestion records its own sessions.
+    async fn run(&self, job: &Job) {
+        let name = &job.schedule.name;
+        let started_at = Utc::now();
+        info!("[Orchestrate::run] {} started", name);
+        let result = Ingest::default().execute(&job.cli, &job.ingest).await;
+        match &result {
+            Ok(()) => info!("[Orchestrate::run] {} completed", name),
+            Err(err) => error!("[Orchestrate::run] {} failed: {:#}", name, err),
+        }
+
+        let mut ingest = job.ingest.clone();
+        let state_db_fs_path = ingest.command.state_db_fs_path_mut().clone();
+        let argv = iter::once("surveilr".to_string())
+            .chain(job.schedule.command.iter().cloned())
+            .collect();
+        let command = format!("orchestrate {name}");
+        if let Err(err) = audit(
+            &job.cli,
+            &command,
+            argv,
+            &state_db_fs_path,
+            started_at,
+            &result,
+        ) {
+            warn!(
+                "unable to record {} in {}: {:#}",
+                command, state_db_fs_path, err
+            );
+        }
+    }
+}
+
+/// The `surveilr` invocation of the schedule's command, which must be an
+/// ingestion the orchestrator can run unattended.
+fn scheduled_ingest(schedule: &Schedule) -> anyhow::Result<(Cli, IngestArgs)> {
+    let unschedulable = |reason: &str| {
+        anyhow!(
+            "[Orchestrate] schedule `{}`: {}",
+            schedule.name,
+            reason.trim_end()
+        )
+    };
+    let cli = Cli::try_parse_from(
+        iter::once("surveilr").chain(schedule.command.iter().map(String::as_str)),
+    )
+    .map_err(|err| unschedulable(&err.to_string()))?;
+    let CliCommands::Ingest(ingest) = &cli.command else {
+        return Err(unschedulable("only `ingest` commands can be scheduled"));
+    };
+    if ingest.every.is_some() {
+        return Err(unschedulable(
+            "--every can't be scheduled, the cron expression decides when it runs",
+        ));
+    }
+    match &ingest.command {
+        IngestCommands::Files(IngestFilesArgs { watch: true, .. }) => Err(unschedulable(
+            "`ingest files --watch` runs until interrupted and can't be scheduled",
+        )),
+        IngestCommands::Tasks(IngestTasksArgs {
+            tasks_from: None, ..
+        }) => Err(unschedulable(
+            "`ingest tasks` requires --tasks-from since there's no STDIN to read tasks from",
+        )),
+        _ => Ok((cli.clone(), ingest.clone())),
+    }
+}
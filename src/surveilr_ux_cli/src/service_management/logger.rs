@@ -1,10 +1,16 @@
 use std::{
-    fs::File,
-    io,
-    path::PathBuf,
-    sync::{Arc, RwLock},
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
 };
 
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use flate2::{write::GzEncoder, Compression};
+use serde::Serialize;
 use tracing::Level;
 use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Registry};
 
@@ -41,33 +47,292 @@ pub enum LoggingMode {
     Compact,
 }
 
+/// Start a new log file when the hour or the day (UTC) changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize)]
+pub enum LogRotationPeriod {
+    Hourly,
+    Daily,
+}
+
+impl LogRotationPeriod {
+    /// The period `at` falls in, files written in different periods are rotated.
+    fn period(&self, at: DateTime<Utc>) -> String {
+        match self {
+            LogRotationPeriod::Hourly => at.format("%Y-%m-%dT%H").to_string(),
+            LogRotationPeriod::Daily => at.format("%Y-%m-%d").to_string(),
+        }
+    }
+}
+
+/// When `--log-file` is rotated and what happens to the rotated files, which
+/// are renamed `<log-file>.1` (the newest) to `<log-file>.<keep>`.
+#[derive(Debug, Clone, Default)]
+pub struct LogRotation {
+    pub max_size: Option<u64>,
+    pub period: Option<LogRotationPeriod>,
+    pub keep: usize,
+    pub compress: bool,
+}
+
+/// `NO_COLOR` (https://no-color.org) disables colors when it is set to any
+/// non-empty value, even `0` or `false`.
+pub fn no_color_env() -> bool {
+    std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+}
+
+/// Parse `--log-max-size` values like `1048576`, `512K`, `10M` or `1G` (powers of 1024).
+pub fn parse_log_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let upper = s.to_uppercase();
+    let upper = upper.strip_suffix('B').unwrap_or(&upper);
+    let (amount, unit) = match upper.char_indices().last() {
+        Some((i, 'K')) => (&upper[..i], 1 << 10),
+        Some((i, 'M')) => (&upper[..i], 1 << 20),
+        Some((i, 'G')) => (&upper[..i], 1 << 30),
+        _ => (upper, 1),
+    };
+    match amount.trim().parse::<u64>() {
+        Ok(amount) if amount > 0 => Ok(amount * unit),
+        _ => Err(format!(
+            "'{}' should be a positive number of bytes or end with K, M or G (e.g. 10M)",
+            s
+        )),
+    }
+}
+
+/// The log file, rotated according to a `LogRotation` before a write would
+/// exceed its size or when the period it was written in is over.
+struct RotatingLogFile {
+    path: PathBuf,
+    rotation: LogRotation,
+    file: File,
+    size: u64,
+    period: Option<String>,
+}
+
+impl RotatingLogFile {
+    fn open(path: &Path, rotation: LogRotation) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let metadata = file.metadata()?;
+        // a file left by a previous run belongs to the period it was last written in
+        let modified: DateTime<Utc> = metadata.modified().unwrap_or(SystemTime::now()).into();
+        Ok(RotatingLogFile {
+            path: path.to_path_buf(),
+            period: rotation.period.map(|period| period.period(modified)),
+            size: metadata.len(),
+            rotation,
+            file,
+        })
+    }
+
+    fn rotated_path(&self, n: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{n}"));
+        if self.rotation.compress {
+            path.push(".gz");
+        }
+        PathBuf::from(path)
+    }
+
+    fn needs_rotation(&self, len: usize, now: DateTime<Utc>) -> bool {
+        if self.size == 0 {
+            return false;
+        }
+        let too_big = self
+            .rotation
+            .max_size
+            .is_some_and(|max| self.size + len as u64 > max);
+        let period_over = match (self.rotation.period, &self.period) {
+            (Some(period), Some(current)) => &period.period(now) != current,
+            _ => false,
+        };
+        too_big || period_over
+    }
+
+    /// Shift `<log-file>.1..keep`, move the log file to `<log-file>.1` and
+    /// start an empty one.
+    fn rotate(&mut self, now: DateTime<Utc>) -> io::Result<()> {
+        self.file.flush()?;
+        let keep = self.rotation.keep;
+        if keep == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            let oldest = self.rotated_path(keep);
+            if oldest.exists() {
+                fs::remove_file(&oldest)?;
+            }
+            for n in (1..keep).rev() {
+                let rotated = self.rotated_path(n);
+                if rotated.exists() {
+                    fs::rename(&rotated, self.rotated_path(n + 1))?;
+                }
+            }
+            if self.rotation.compress {
+                let mut encoder =
+                    GzEncoder::new(File::create(self.rotated_path(1))?, Compression::default());
+                io::copy(&mut File::open(&self.path)?, &mut encoder)?;
+                encoder.finish()?;
+                fs::remove_file(&self.path)?;
+            } else {
+                fs::rename(&self.path, self.rotated_path(1))?;
+            }
+        }
+
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = 0;
+        self.period = self.rotation.period.map(|period| period.period(now));
+        Ok(())
+    }
+
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let now = Utc::now();
+        if self.needs_rotation(buf.len(), now) {
+            self.rotate(now)?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+}
+
+/// A handle on the shared `RotatingLogFile` for the writers of the subscriber.
+#[derive(Clone)]
+struct LogFileWriter(Arc<Mutex<RotatingLogFile>>);
+
+impl Write for LogFileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().expect("log file lock poisoned").write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.lock().expect("log file lock poisoned").file.flush()
+    }
+}
+
+/// Open `path` for appending logs, rotated according to `rotation`.
+pub fn open_log_file(
+    path: &Path,
+    rotation: LogRotation,
+) -> anyhow::Result<impl Write + Clone + Send + Sync + 'static> {
+    Ok(LogFileWriter(Arc::new(Mutex::new(
+        RotatingLogFile::open(path, rotation)
+            .with_context(|| format!("[logger::log] opening the log file {}", path.display()))?,
+    ))))
+}
+
+/// Install the global subscriber. Logs go to STDERR (or `log_file`, rotated
+/// according to `rotation`) so that they never interleave with command output
+/// written to STDOUT; `quiet` only lets warnings and errors through and
+/// `no_color` disables ANSI escape codes.
 pub fn log(
     debug_level: Verbosity,
     _mode: LoggingMode,
     log_file: Option<&PathBuf>,
+    rotation: LogRotation,
+    quiet: bool,
+    no_color: bool,
 ) -> anyhow::Result<()> {
-    let level: Level = debug_level.into();
+    let level: Level = if quiet {
+        Level::WARN
+    } else {
+        debug_level.into()
+    };
     let env_filter = EnvFilter::new(level.to_string());
+    let ansi = !no_color && log_file.is_none();
 
-    let log_file = log_file.cloned();
-    let log_file = Arc::new(RwLock::new(log_file));
+    let log_file = match log_file {
+        Some(path) => Some(open_log_file(path, rotation)?),
+        None => None,
+    };
 
     let writer_factory = move || -> Box<dyn io::Write + Send + Sync> {
-        let log_file = log_file.read().expect("RwLock read lock failed");
-        match &*log_file {
-            Some(path) => Box::new(File::create(path).expect("Failed to create log file")),
-            None => Box::new(io::stdout()),
+        match &log_file {
+            Some(writer) => Box::new(writer.clone()),
+            None => Box::new(io::stderr()),
         }
     };
 
     let fmt_layer = fmt::layer()
         .compact()
         .with_line_number(true)
+        .with_ansi(ansi)
         .with_writer(writer_factory);
 
-    let subscriber = Registry::default().with(env_filter).with(fmt_layer);
-
-    let _guard = subscriber.set_default();
+    Registry::default()
+        .with(env_filter)
+        .with(fmt_layer)
+        .try_init()
+        .context("[logger::log] installing the global tracing subscriber")?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn test_parse_log_size() {
+        assert_eq!(parse_log_size("4096"), Ok(4096));
+        assert_eq!(parse_log_size("512K"), Ok(512 * 1024));
+        assert_eq!(parse_log_size("10MB"), Ok(10 * 1024 * 1024));
+        assert_eq!(parse_log_size("1g"), Ok(1024 * 1024 * 1024));
+        assert!(parse_log_size("0").is_err());
+        assert!(parse_log_size("M").is_err());
+        assert!(parse_log_size("10T").is_err());
+    }
+
+    #[test]
+    fn test_rotate_by_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("surveilr.log");
+        let rotation = LogRotation {
+            max_size: Some(10),
+            keep: 2,
+            ..Default::default()
+        };
+        let mut log = RotatingLogFile::open(&path, rotation).unwrap();
+        for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+            log.write(line.as_bytes()).unwrap();
+        }
+        log.file.flush().unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "fourth\n");
+        assert_eq!(fs::read_to_string(log.rotated_path(1)).unwrap(), "third\n");
+        assert_eq!(fs::read_to_string(log.rotated_path(2)).unwrap(), "second\n");
+        // only `keep` rotated files are kept
+        assert!(!log.rotated_path(3).exists());
+    }
+
+    #[test]
+    fn test_rotate_by_period_and_compress() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("surveilr.log");
+        let rotation = LogRotation {
+            period: Some(LogRotationPeriod::Daily),
+            keep: 1,
+            compress: true,
+            ..Default::default()
+        };
+        let mut log = RotatingLogFile::open(&path, rotation).unwrap();
+        log.write(b"yesterday\n").unwrap();
+        // as if the file had been written yesterday
+        log.period = Some("1970-01-01".to_string());
+        log.write(b"today\n").unwrap();
+        log.file.flush().unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "today\n");
+        let rotated = log.rotated_path(1);
+        assert!(rotated.to_string_lossy().ends_with("surveilr.log.1.gz"));
+        let mut content = String::new();
+        GzDecoder::new(File::open(rotated).unwrap())
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "yesterday\n");
+    }
+}
@@ -1,5 +1,6 @@
 use crate::{Cli, CliCommands, LogMode};
 use opentelemetry_sdk::trace::{self};
+use resource_serde::cmd::AdminCommands;
 
 pub mod logger;
 mod observability;
@@ -14,19 +15,58 @@ impl From<LogMode> for logger::LoggingMode {
     }
 }
 
+/// How `--log-file` is rotated, from the `--log-*` flags.
+pub fn log_rotation(cli: &Cli) -> logger::LogRotation {
+    logger::LogRotation {
+        max_size: cli.log_max_size,
+        period: cli.log_rotate,
+        keep: cli.log_keep,
+        compress: cli.log_compress,
+    }
+}
+
+/// The UDI-PGP server installs its own subscriber, these are the logging flags
+/// it applies.
+#[cfg(feature = "udi-pgp")]
+pub fn udi_pgp_log_options(cli: &Cli) -> anyhow::Result<udi_pgp::LogOptions> {
+    let writer = match &cli.log_file {
+        Some(path) => Some(udi_pgp::SharedLogWriter::new(logger::open_log_file(
+            path,
+            log_rotation(cli),
+        )?)),
+        None => None,
+    };
+    Ok(udi_pgp::LogOptions {
+        quiet: cli.quiet,
+        no_color: cli.no_color,
+        writer,
+    })
+}
+
 pub fn start(cli: &Cli) -> anyhow::Result<Option<trace::Tracer>> {
-    match (&cli.log_mode, &cli.debug, &cli.log_file) {
-        (_, _, Some(file)) => logger::log(
-            cli.debug.into(),
-            cli.log_mode.unwrap_or_default().into(),
-            Some(file),
-        )?,
-        (_, _, None) => logger::log(
+    // the UDI-PGP server installs its own subscriber which records the query logs,
+    // `admin self-test` starts one too
+    let udi_pgp_server = match &cli.command {
+        CliCommands::Udi(args) => args.starts_server(),
+        CliCommands::Admin(args) => matches!(args.command, AdminCommands::SelfTest { .. }),
+        _ => false,
+    };
+    if !udi_pgp_server {
+        logger::log(
             cli.debug.into(),
             cli.log_mode.unwrap_or_default().into(),
-            None,
-        )?,
-    };
+            cli.log_file.as_ref(),
+            log_rotation(cli),
+            cli.quiet,
+            cli.no_color,
+        )?;
+    }
+
+    if cli.no_color {
+        // progress bars and spinners (indicatif) are styled through `console`
+        console::set_colors_enabled(false);
+        console::set_colors_enabled_stderr(false);
+    }
 
     let tracer = match &cli.command {
         CliCommands::SQLPage(args) => {
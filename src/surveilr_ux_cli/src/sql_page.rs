@@ -1,28 +1,43 @@
+#[cfg(feature = "sqlpage")]
 use std::net::ToSocketAddrs;
 
-use anyhow::{anyhow, Result};
+#[cfg(feature = "sqlpage")]
+use anyhow::anyhow;
+use anyhow::Result;
+#[cfg(feature = "sqlpage")]
 use opentelemetry::{trace::get_active_span, KeyValue};
 use resource_serde::cmd::SQLPageArgs;
+#[cfg(feature = "sqlpage")]
 use sqlpage::{
     app_config::{self, AppConfig},
     webserver, AppState,
 };
+#[cfg(feature = "sqlpage")]
 use tracing::{debug, info};
 
 #[derive(Debug, Default)]
 pub struct SqlPage {}
 
+#[cfg(not(feature = "sqlpage"))]
+impl SqlPage {
+    pub async fn execute(&self, _args: &SQLPageArgs) -> Result<()> {
+        Err(resource_serde::cmd::feature_not_compiled("sqlpage"))
+    }
+}
+
+#[cfg(feature = "sqlpage")]
 impl SqlPage {
     pub async fn execute(&self, args: &SQLPageArgs) -> Result<()> {
         self.start(args).await
     }
 
-    fn database_url(&self, db_fs_path: &str) -> Result<String> {
+    fn database_url(&self, db_fs_path: &str, read_only: bool) -> Result<String> {
         let prefix = "sqlite://".to_owned();
         let cwd = std::env::current_dir().unwrap_or_default();
         let db_path = cwd.join(db_fs_path);
         if let Ok(true) = db_path.try_exists() {
-            return Ok(prefix + db_path.to_str().unwrap());
+            let url = prefix + db_path.to_str().unwrap();
+            Ok(if read_only { url + "?mode=ro" } else { url })
         } else {
             Err(anyhow!("Could not build database url for: {db_fs_path}"))
         }
@@ -34,7 +49,7 @@ impl SqlPage {
 
         let addr = format!("0.0.0.0:{}", args.port).to_socket_addrs()?.next();
         app_config.listen_on = addr;
-        app_config.database_url = self.database_url(&args.state_db_fs_path)?;
+        app_config.database_url = self.database_url(&args.state_db_fs_path, args.read_only)?;
 
         debug!("Starting with the following configuration: {app_config:#?}");
         get_active_span(|span| {
@@ -48,7 +63,9 @@ impl SqlPage {
         });
 
         let state = AppState::init(&app_config).await?;
-        webserver::database::migrations::apply(&state.db).await?;
+        if !args.read_only {
+            webserver::database::migrations::apply(&state.db).await?;
+        }
 
         info!("Starting server...");
         self.log_welcome_message(&app_config);
@@ -1,7 +1,7 @@
 use clap::{Args, Subcommand};
 use serde::Serialize;
 
-use self::pgp::PgpArgs;
+use self::pgp::{PgpArgs, PgpCommands};
 
 pub mod pgp;
 
@@ -12,6 +12,7 @@ pub struct UdiArgs {
     commands: UdiCommands,
 }
 
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Serialize, Subcommand, Clone)]
 pub enum UdiCommands {
     Pgp(PgpArgs),
@@ -19,11 +20,19 @@ pub enum UdiCommands {
 }
 
 impl UdiArgs {
-    pub async fn execute(&self) -> anyhow::Result<()> {
+    /// Whether the command runs the UDI-PGP server (rather than a one-off command)
+    pub fn starts_server(&self) -> bool {
+        match &self.commands {
+            UdiCommands::Pgp(args) => !matches!(args.command, Some(PgpCommands::RunPack { .. })),
+            UdiCommands::Admin => false,
+        }
+    }
+
+    pub async fn execute(&self, cli: &crate::Cli) -> anyhow::Result<()> {
         match &self.commands {
             UdiCommands::Pgp(args) => {
                 args.register_suppliers().await;
-                args.execute().await
+                args.execute(cli).await
             }
             UdiCommands::Admin => Ok(()),
         }
@@ -1,20 +1,16 @@
-use std::{collections::HashMap, path::PathBuf, sync::Arc};
+use std::path::PathBuf;
 
-use anyhow::anyhow;
 use clap::{Args, Subcommand};
+use common::secret::Secret;
 use serde::Serialize;
-use tokio::sync::Mutex;
-use udi_pgp::{
-    auth::Auth,
-    config::{Supplier, SupplierType, UdiPgpConfig},
-    error::UdiPgpResult,
-    sql_supplier::{SqlSupplierMap, SqlSupplierType},
-    ssh::UdiPgpSshTarget,
-    UdiPgpModes,
-};
-use udi_pgp_osquery::OsquerySupplier;
+
+#[cfg(feature = "udi-pgp")]
+pub mod self_test;
+#[cfg(feature = "udi-pgp")]
+mod server;
 
 const DEFAULT_ADMIN_STATE_FS_PATH: &str = "resource-surveillance-admin.sqlite.db";
+const DEFAULT_STATEDB_FS_PATH: &str = "resource-surveillance.sqlite.db";
 
 /// UDI PostgreSQL Proxy for remote SQL starts up a server which pretends to be PostgreSQL
 /// but proxies its SQL to other CLI services with SQL-like interface (called SQL Suppliers).
@@ -30,7 +26,7 @@ pub struct PgpArgs {
 
     /// Password for authentication
     #[arg(short = 'p', long)]
-    pub password: Option<String>,
+    pub password: Option<Secret>,
 
     /// Identification for the supplier which will be passed to the client. e.g
     /// surveilr udi pgp -u john -p doe -i test-supplier osquery local
@@ -46,6 +42,11 @@ pub struct PgpArgs {
     #[arg(short='d', long, default_value = DEFAULT_ADMIN_STATE_FS_PATH, default_missing_value = "always", env="DEFAULT_ADMIN_STATE_FS_PATH")]
     pub admin_state_fs_path: String,
 
+    /// Seconds identical queries are answered from the cached result of the first one
+    /// (not cached by default), see the udi_pgp_cache introspection table
+    #[arg(long)]
+    pub cache_ttl: Option<u64>,
+
     #[command(subcommand)]
     pub command: Option<PgpCommands>,
 }
@@ -54,11 +55,60 @@ pub struct PgpArgs {
 pub enum PgpCommands {
     /// query a machine
     Osquery(OsqueryArgs),
+    /// execute Deno Task Shell commands on this machine, e.g.
+    /// psql -c "select * from tasks where command = 'osqueryi --json \"select * from system_info\"'"
+    Tasks {
+        /// regular expressions which must match a whole command line for it to be executed, commands
+        /// with shell metacharacters (; & | < > $ ` ( )) are always rejected (nothing is allowed by default)
+        #[arg(short = 'A', long, required = true)]
+        allowed_commands: Vec<String>,
+    },
+    /// execute every query of an osquery pack on remote targets and store the results,
+    /// attributed to each host's device, in the RSSD (no server is started), e.g.
+    /// surveilr udi pgp run-pack --pack incident-response.conf --targets targets.ncl
+    RunPack {
+        /// osquery pack file (the osqueryd `pack.conf` JSON format)
+        #[arg(short = 'p', long)]
+        pack: PathBuf,
+
+        /// SSH, WinRM or SSM targets as a .ncl or .json file, either a list of targets or a UDI-PGP
+        /// config with `ssh-targets`
+        #[arg(short = 't', long)]
+        targets: PathBuf,
+
+        /// target SQLite database
+        #[arg(short='d', long, default_value = DEFAULT_STATEDB_FS_PATH, default_missing_value = "always", env="SURVEILR_STATEDB_FS_PATH")]
+        state_db_fs_path: String,
+    },
+    /// UDI-PGP configuration files
+    Config {
+        #[command(subcommand)]
+        command: PgpConfigCommands,
+    },
+}
+
+#[derive(Debug, Serialize, Subcommand, Clone)]
+pub enum PgpConfigCommands {
+    /// check a config file, or a supplier meant for `SET udi_pgp_serve_ncl_supplier`, before
+    /// applying it to a server and report its errors with their line or field, e.g.
+    /// surveilr udi pgp config validate udi-pgp-config.ncl
+    Validate {
+        /// .ncl or .json config or supplier file
+        file: PathBuf,
+    },
 }
 
 /// Modes to execute osquery in
 #[derive(Debug, Serialize, Args, Clone)]
 pub struct OsqueryArgs {
+    /// return every column as text instead of INTEGER/BIGINT/DOUBLE/BOOLEAN (legacy behavior)
+    #[arg(long)]
+    pub text_columns: bool,
+
+    /// osquery schema JSON whose tables and columns are listed in `udi_pgp_schema`
+    #[arg(long)]
+    pub schema_file_path: Option<String>,
+
     #[command(subcommand)]
     pub command: OsqueryCommands,
 }
@@ -67,124 +117,35 @@ pub struct OsqueryArgs {
 pub enum OsqueryCommands {
     /// execute osquery on the local machine
     Local {
-        /// ATC Configuration File path
+        /// ATC Configuration File path, repeat to serve the tables of several files
         #[arg(short = 'a', long)]
-        atc_file_path: Option<String>,
+        atc_file_path: Vec<String>,
+
+        /// absolute path of osqueryi, instead of looking it up in PATH
+        #[arg(long)]
+        osqueryi_path: Option<String>,
+
+        /// SHA-256 of osqueryi, checked before every execution (requires --osqueryi-path)
+        #[arg(long, requires = "osqueryi_path")]
+        osqueryi_sha256: Option<String>,
+
+        /// environment variable passed to osqueryi, repeat for several (all are passed by default)
+        #[arg(long)]
+        osqueryi_env: Option<Vec<String>>,
     },
     /// execute osquery on remote hosts
     Remote {
-        /// SSH details of hosts to execute osquery on including and identifier. e,g. "user@127.0.0.1:22,john"/"user@host.com:1234,doe"
+        /// details of hosts to execute osquery on including and identifier. e,g. "user@127.0.0.1:22,john"/"winrm://user@host.com:5986,doe"/"ssm://i-0123456789abcdef0,web", followed by optional key=value tags e.g. "user@127.0.0.1:22,john,env=prod" and a failover priority among the targets sharing the identifier e.g. "user@127.0.0.2:22,john,priority=1"
         #[arg(short = 's', long)]
         ssh_targets: Vec<String>,
     },
 }
 
+#[cfg(not(feature = "udi-pgp"))]
 impl PgpArgs {
-    /// Register suppliers to udi-pgp-core. Use flag features
-    pub async fn register_suppliers(&self) {
-        udi_pgp_osquery::initialize().await;
-    }
-
-    pub async fn execute(&self) -> anyhow::Result<()> {
-        let (config, suppliers) = if let Some(config_file) = &self.config {
-            let config = UdiPgpConfig::try_from_file(config_file)?;
-            let suppliers = self.suppliers_from_config(&config);
-            (config, suppliers)
-        } else if let Some(pgp_command) = &self.command {
-            self.try_config_from_args(pgp_command)?
-        } else {
-            return Err(anyhow!("Either a subcommand or a config file is required"));
-        };
-
-        udi_pgp::run(&config, suppliers).await
-    }
-
-    fn suppliers_from_config(&self, config: &UdiPgpConfig) -> SqlSupplierMap {
-        config
-            .suppliers
-            .iter()
-            .map(|(k, v)| (k.to_string(), self.create_supplier_from_config(v)))
-            .collect()
-    }
-
-    fn create_supplier_from_config(
-        &self,
-        config_supplier: &Supplier,
-    ) -> Arc<Mutex<SqlSupplierType>> {
-        match config_supplier.supplier_type {
-            SupplierType::Osquery => Arc::new(Mutex::new(Box::new(OsquerySupplier::from(
-                config_supplier,
-            )) as SqlSupplierType)),
-            _ => unimplemented!(),
-        }
-    }
-
-    fn try_config_from_args(
-        &self,
-        commands: &PgpCommands,
-    ) -> anyhow::Result<(UdiPgpConfig, SqlSupplierMap)> {
-        let (username, password) = match (&self.username, &self.password) {
-            (Some(u), Some(p)) => (u, p),
-            _ => return Err(anyhow!("Authentication for supplier incomplete")),
-        };
-        let supplier_id = match &self.supplier_id {
-            None => return Err(anyhow!("Supplier ID must be present")),
-            Some(id) => id,
-        };
-
-        let auth = Auth::new(username, password);
-        let (supplier, config_supplier) = self.create_supplier_from_args(commands, auth)?;
-
-        let mut config_suppliers = HashMap::new();
-        config_suppliers.insert(supplier_id.to_string(), config_supplier);
-
-        let config = UdiPgpConfig::new(self.addr, config_suppliers, &self.admin_state_fs_path)?;
-        let mut suppliers = HashMap::new();
-        suppliers.insert(supplier_id.to_string(), Arc::new(Mutex::new(supplier)));
-
-        Ok((config, suppliers))
-    }
+    pub async fn register_suppliers(&self) {}
 
-    fn create_supplier_from_args(
-        &self,
-        command: &PgpCommands,
-        auth: Auth,
-    ) -> anyhow::Result<(SqlSupplierType, Supplier)> {
-        match command {
-            PgpCommands::Osquery(OsqueryArgs { command }) => match command {
-                OsqueryCommands::Local { atc_file_path } => {
-                    let mode = UdiPgpModes::Local;
-                    let supplier = Supplier::new(
-                        SupplierType::Osquery,
-                        mode.clone(),
-                        None,
-                        atc_file_path.clone(),
-                        vec![auth],
-                    );
-                    Ok((
-                        Box::new(OsquerySupplier::new(mode).with_atc_file(atc_file_path)),
-                        supplier,
-                    ))
-                }
-                OsqueryCommands::Remote { ssh_targets } => {
-                    let mode = UdiPgpModes::Remote;
-                    let targets = ssh_targets
-                        .iter()
-                        .map(UdiPgpSshTarget::try_from)
-                        .collect::<UdiPgpResult<Vec<_>>>()?;
-                    let supplier = Supplier::new(
-                        SupplierType::Osquery,
-                        mode.clone(),
-                        Some(targets),
-                        None,
-                        vec![auth],
-                    );
-                    Ok((
-                        Box::new(OsquerySupplier::new(mode).with_ssh_targets(ssh_targets.to_vec())),
-                        supplier,
-                    ))
-                }
-            },
-        }
+    pub async fn execute(&self, _cli: &crate::Cli) -> anyhow::Result<()> {
+        Err(resource_serde::cmd::feature_not_compiled("udi-pgp"))
     }
 }
@@ -0,0 +1,188 @@
+use std::{collections::HashMap, net::SocketAddr, path::Path, sync::Arc, time::Duration};
+
+use anyhow::{anyhow, Context};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+    sync::Mutex,
+};
+use udi_pgp::{
+    auth::Auth,
+    config::{Supplier, SupplierType, UdiPgpConfig},
+    sql_supplier::{SqlSupplierMap, SqlSupplierType},
+    LogOptions, UdiPgpModes,
+};
+use udi_pgp_tasks::TasksSupplier;
+
+const SELF_TEST_SUPPLIER: &str = "self-test";
+const SELF_TEST_USER: &str = "self-test";
+const SELF_TEST_PASSWORD: &str = "self-test";
+const SELF_TEST_QUERY: &str =
+    r#"SELECT command, output FROM tasks WHERE command = 'echo ''[{"self_test": "ok"}]'''"#;
+
+/// Start a UDI-PGP server with a tasks supplier on a free loopback port and
+/// query it the way `psql` would, for `admin self-test`.
+pub async fn self_test(work_dir: &Path, log: LogOptions) -> anyhow::Result<String> {
+    udi_pgp_tasks::initialize().await;
+
+    // the server binds its address itself, so a free port is looked up first
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?;
+    let admin_state_fs_path = work_dir.join("self-test-admin.sqlite.db");
+    // the config canonicalizes the admin database's path, it must exist
+    std::fs::File::create(&admin_state_fs_path)?;
+
+    let allowed_commands = vec!["echo .*".to_string()];
+    let supplier = Supplier::new(
+        SupplierType::Tasks,
+        UdiPgpModes::Local,
+        None,
+        None,
+        vec![Auth::new(SELF_TEST_USER, SELF_TEST_PASSWORD)],
+    )
+    .with_allowed_commands(allowed_commands.clone());
+    let config = UdiPgpConfig::new(
+        addr,
+        HashMap::from([(SELF_TEST_SUPPLIER.to_string(), supplier)]),
+        &admin_state_fs_path.to_string_lossy(),
+    )?
+    .with_log_options(log);
+    let tasks = TasksSupplier::new(UdiPgpModes::Local).with_allowed_commands(&allowed_commands)?;
+    let suppliers: SqlSupplierMap = HashMap::from([(
+        SELF_TEST_SUPPLIER.to_string(),
+        Arc::new(Mutex::new(Box::new(tasks) as SqlSupplierType)),
+    )]);
+
+    let server = tokio::spawn(async move { udi_pgp::run(&config, suppliers).await });
+    let rows = simple_query(addr, SELF_TEST_QUERY).await;
+    // the server only stops by itself when it fails, e.g. its port was taken
+    if server.is_finished() {
+        server
+            .await?
+            .with_context(|| format!("[self_test] UDI-PGP server on {addr}"))?;
+    } else {
+        server.abort();
+    }
+
+    let rows = rows.with_context(|| format!("[self_test] querying UDI-PGP on {addr}"))?;
+    match rows.first().and_then(|row| row.get(1)).cloned().flatten() {
+        Some(output) if output.contains("self_test") => Ok(format!(
+            "queried the tasks supplier on {addr}, {} row(s)",
+            rows.len()
+        )),
+        output => Err(anyhow!("unexpected output {output:?} from {addr}")),
+    }
+}
+
+/// Run `sql` with the PostgreSQL simple query protocol (cleartext password
+/// authentication), returning the data rows with their columns as text.
+async fn simple_query(addr: SocketAddr, sql: &str) -> anyhow::Result<Vec<Vec<Option<String>>>> {
+    let mut stream = connect(addr).await?;
+
+    let mut startup = 196608_i32.to_be_bytes().to_vec(); // protocol 3.0
+    for (name, value) in [("user", SELF_TEST_USER), ("database", SELF_TEST_SUPPLIER)] {
+        startup.extend(cstring(name));
+        startup.extend(cstring(value));
+    }
+    startup.push(0);
+    stream
+        .write_all(&((startup.len() + 4) as i32).to_be_bytes())
+        .await?;
+    stream.write_all(&startup).await?;
+
+    let mut queried = false;
+    let mut rows = Vec::new();
+    loop {
+        let (tag, body) = read_message(&mut stream).await?;
+        match tag {
+            b'R' => match body
+                .get(..4)
+                .map(|code| i32::from_be_bytes(code.try_into().unwrap()))
+            {
+                Some(0) => {}
+                Some(3) => write_message(&mut stream, b'p', &cstring(SELF_TEST_PASSWORD)).await?,
+                code => return Err(anyhow!("unsupported authentication request {code:?}")),
+            },
+            b'Z' if !queried => {
+                write_message(&mut stream, b'Q', &cstring(sql)).await?;
+                queried = true;
+            }
+            b'Z' => break,
+            b'D' => rows.push(data_row(&body)?),
+            b'E' => return Err(anyhow!("{}", error_message(&body))),
+            // parameter status, backend key data, row description, command complete...
+            _ => {}
+        }
+    }
+    write_message(&mut stream, b'X', &[]).await?;
+    Ok(rows)
+}
+
+/// Connect once the server listens, it's started concurrently.
+async fn connect(addr: SocketAddr) -> anyhow::Result<TcpStream> {
+    let mut attempts = 0;
+    loop {
+        match TcpStream::connect(addr).await {
+            Ok(stream) => return Ok(stream),
+            Err(err) if attempts >= 50 => return Err(err.into()),
+            Err(_) => {
+                attempts += 1;
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        }
+    }
+}
+
+fn cstring(value: &str) -> Vec<u8> {
+    let mut bytes = value.as_bytes().to_vec();
+    bytes.push(0);
+    bytes
+}
+
+async fn write_message(stream: &mut TcpStream, tag: u8, body: &[u8]) -> anyhow::Result<()> {
+    let mut message = vec![tag];
+    message.extend(((body.len() + 4) as i32).to_be_bytes());
+    message.extend(body);
+    stream.write_all(&message).await?;
+    Ok(())
+}
+
+async fn read_message(stream: &mut TcpStream) -> anyhow::Result<(u8, Vec<u8>)> {
+    let tag = stream.read_u8().await?;
+    let len = stream.read_i32().await?;
+    let mut body = vec![0; (len as usize).saturating_sub(4)];
+    stream.read_exact(&mut body).await?;
+    Ok((tag, body))
+}
+
+fn data_row(body: &[u8]) -> anyhow::Result<Vec<Option<String>>> {
+    let truncated = || anyhow!("truncated data row");
+    let columns = i16::from_be_bytes(body.get(..2).ok_or_else(truncated)?.try_into()?);
+    let mut offset = 2;
+    let mut row = Vec::with_capacity(columns.max(0) as usize);
+    for _ in 0..columns {
+        let len = i32::from_be_bytes(
+            body.get(offset..offset + 4)
+                .ok_or_else(truncated)?
+                .try_into()?,
+        );
+        offset += 4;
+        if len < 0 {
+            row.push(None);
+            continue;
+        }
+        let value = body
+            .get(offset..offset + len as usize)
+            .ok_or_else(truncated)?;
+        row.push(Some(String::from_utf8_lossy(value).to_string()));
+        offset += len as usize;
+    }
+    Ok(row)
+}
+
+/// The message (`M`) field of an error response.
+fn error_message(body: &[u8]) -> String {
+    body.split(|b| *b == 0)
+        .find_map(|field| field.strip_prefix(b"M"))
+        .map(|message| String::from_utf8_lossy(message).to_string())
+        .unwrap_or_else(|| "UDI-PGP returned an error".to_string())
+}
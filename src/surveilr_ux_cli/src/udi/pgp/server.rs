@@ -0,0 +1,274 @@
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
+
+use anyhow::anyhow;
+use resource_serde::ingest::{ingest_osquery_pack, OsqueryPackResult};
+use tokio::sync::Mutex;
+use udi_pgp::{
+    auth::Auth,
+    config::{
+        try_ssh_targets_from_file, validate_config_file, Supplier, SupplierExecutable,
+        SupplierType, UdiPgpConfig,
+    },
+    error::UdiPgpResult,
+    remote::{tags::TargetTagFilter, RemoteTransportKind, UdiPgpRemoteTarget},
+    sql_supplier::{SqlSupplierMap, SqlSupplierType},
+    UdiPgpModes,
+};
+use udi_pgp_osquery::{pack::OsqueryPack, OsquerySupplier};
+use udi_pgp_tasks::TasksSupplier;
+
+use crate::service_management::udi_pgp_log_options;
+
+use super::{OsqueryArgs, OsqueryCommands, PgpArgs, PgpCommands, PgpConfigCommands};
+
+impl PgpArgs {
+    /// Register suppliers to udi-pgp-core. Use flag features
+    pub async fn register_suppliers(&self) {
+        udi_pgp_osquery::initialize().await;
+        udi_pgp_tasks::initialize().await;
+    }
+
+    pub async fn execute(&self, cli: &crate::Cli) -> anyhow::Result<()> {
+        if let Some(PgpCommands::RunPack {
+            pack,
+            targets,
+            state_db_fs_path,
+        }) = &self.command
+        {
+            return self.run_pack(pack, targets, state_db_fs_path).await;
+        }
+        if let Some(PgpCommands::Config {
+            command: PgpConfigCommands::Validate { file },
+        }) = &self.command
+        {
+            return self.validate_config(file);
+        }
+
+        let (mut config, suppliers) = if let Some(config_file) = &self.config {
+            let config = UdiPgpConfig::try_from_file(config_file)?;
+            let suppliers = self.suppliers_from_config(&config)?;
+            (config, suppliers)
+        } else if let Some(pgp_command) = &self.command {
+            self.try_config_from_args(pgp_command)?
+        } else {
+            return Err(anyhow!("Either a subcommand or a config file is required"));
+        };
+
+        let config = config.with_log_options(udi_pgp_log_options(cli)?);
+        udi_pgp::run(&config, suppliers).await
+    }
+
+    async fn run_pack(
+        &self,
+        pack: &PathBuf,
+        targets: &PathBuf,
+        state_db_fs_path: &str,
+    ) -> anyhow::Result<()> {
+        let pack = OsqueryPack::try_from_file(pack)?;
+        let targets = try_ssh_targets_from_file(targets)?;
+        if targets.is_empty() {
+            return Err(anyhow!("No targets to run the pack on"));
+        }
+        let supplier = OsquerySupplier::from(&Supplier::new(
+            SupplierType::Osquery,
+            UdiPgpModes::Remote,
+            Some(targets),
+            None,
+            vec![],
+        ));
+
+        let mut results = Vec::new();
+        let every_target = TargetTagFilter::default();
+        for (query_name, query) in &pack.queries {
+            for (target, rows) in supplier
+                .query_ssh_targets(&query.query, &every_target)
+                .await
+            {
+                results.push(OsqueryPackResult {
+                    ssh_target: match target.transport {
+                        RemoteTransportKind::Ssh => format!(
+                            "ssh://{}@{}:{}",
+                            target.user,
+                            target.host,
+                            target.port.unwrap_or(22)
+                        ),
+                        _ => target.to_string(),
+                    },
+                    host_id: target.id,
+                    query_name: query_name.clone(),
+                    query: query.query.clone(),
+                    rows: rows.map_err(|err| err.to_string()),
+                });
+            }
+        }
+
+        let ingest_session_id = ingest_osquery_pack(
+            0,
+            state_db_fs_path,
+            &pack.name,
+            &serde_json::to_string(&pack)?,
+            &results,
+        )?;
+        let failed = results.iter().filter(|r| r.rows.is_err()).count();
+        println!(
+            "{}: {} queries on {} targets, {} succeeded, {} failed (ingest session {})",
+            pack.name,
+            pack.queries.len(),
+            results.len() / pack.queries.len().max(1),
+            results.len() - failed,
+            failed,
+            ingest_session_id
+        );
+        Ok(())
+    }
+
+    fn validate_config(&self, file: &PathBuf) -> anyhow::Result<()> {
+        let diagnostics = validate_config_file(file)?;
+        for diagnostic in &diagnostics {
+            println!("{}: {}", file.display(), diagnostic);
+        }
+        let errors = diagnostics.iter().filter(|d| d.is_error()).count();
+        if errors > 0 {
+            return Err(anyhow!("{} has {} error(s)", file.display(), errors));
+        }
+        println!(
+            "{} is valid ({} warning(s))",
+            file.display(),
+            diagnostics.len()
+        );
+        Ok(())
+    }
+
+    fn suppliers_from_config(&self, config: &UdiPgpConfig) -> anyhow::Result<SqlSupplierMap> {
+        config
+            .suppliers
+            .iter()
+            .map(|(k, v)| Ok((k.to_string(), self.create_supplier_from_config(v)?)))
+            .collect()
+    }
+
+    fn create_supplier_from_config(
+        &self,
+        config_supplier: &Supplier,
+    ) -> anyhow::Result<Arc<Mutex<SqlSupplierType>>> {
+        let supplier = match config_supplier.supplier_type {
+            SupplierType::Osquery => {
+                Box::new(OsquerySupplier::from(config_supplier)) as SqlSupplierType
+            }
+            SupplierType::Tasks => {
+                Box::new(TasksSupplier::try_from(config_supplier)?) as SqlSupplierType
+            }
+            ref other => return Err(anyhow::anyhow!(
+                "[create_supplier_from_config] {} suppliers can't be created from a configuration",
+                other
+            )),
+        };
+        Ok(Arc::new(Mutex::new(supplier)))
+    }
+
+    fn try_config_from_args(
+        &self,
+        commands: &PgpCommands,
+    ) -> anyhow::Result<(UdiPgpConfig, SqlSupplierMap)> {
+        let (username, password) = match (&self.username, &self.password) {
+            (Some(u), Some(p)) => (u, p),
+            _ => return Err(anyhow!("Authentication for supplier incomplete")),
+        };
+        let supplier_id = match &self.supplier_id {
+            None => return Err(anyhow!("Supplier ID must be present")),
+            Some(id) => id,
+        };
+
+        let auth = Auth::new(username, password.resolve()?.expose());
+        let (supplier, config_supplier) = self.create_supplier_from_args(commands, auth)?;
+        let config_supplier = config_supplier.with_cache_ttl(self.cache_ttl);
+
+        let mut config_suppliers = HashMap::new();
+        config_suppliers.insert(supplier_id.to_string(), config_supplier);
+
+        let config = UdiPgpConfig::new(self.addr, config_suppliers, &self.admin_state_fs_path)?;
+        let mut suppliers = HashMap::new();
+        suppliers.insert(supplier_id.to_string(), Arc::new(Mutex::new(supplier)));
+
+        Ok((config, suppliers))
+    }
+
+    fn create_supplier_from_args(
+        &self,
+        command: &PgpCommands,
+        auth: Auth,
+    ) -> anyhow::Result<(SqlSupplierType, Supplier)> {
+        match command {
+            PgpCommands::Osquery(OsqueryArgs {
+                command,
+                text_columns,
+                schema_file_path,
+            }) => match command {
+                OsqueryCommands::Local {
+                    atc_file_path,
+                    osqueryi_path,
+                    osqueryi_sha256,
+                    osqueryi_env,
+                } => {
+                    let mode = UdiPgpModes::Local;
+                    let executable = SupplierExecutable {
+                        path: osqueryi_path.clone(),
+                        sha256: osqueryi_sha256.clone(),
+                        env: osqueryi_env.clone(),
+                    };
+                    let supplier =
+                        Supplier::new(SupplierType::Osquery, mode.clone(), None, None, vec![auth])
+                            .with_atc_file_paths(atc_file_path.clone())
+                            .with_schema_file_path(schema_file_path.clone())
+                            .with_text_columns(*text_columns)
+                            .with_executable(executable.clone());
+                    Ok((
+                        Box::new(
+                            OsquerySupplier::new(mode)
+                                .with_atc_files(atc_file_path)
+                                .with_text_columns(*text_columns)
+                                .with_executable(executable),
+                        ),
+                        supplier,
+                    ))
+                }
+                OsqueryCommands::Remote { ssh_targets } => {
+                    let mode = UdiPgpModes::Remote;
+                    let targets = ssh_targets
+                        .iter()
+                        .map(UdiPgpRemoteTarget::try_from)
+                        .collect::<UdiPgpResult<Vec<_>>>()?;
+                    let supplier = Supplier::new(
+                        SupplierType::Osquery,
+                        mode.clone(),
+                        Some(targets),
+                        None,
+                        vec![auth],
+                    )
+                    .with_schema_file_path(schema_file_path.clone())
+                    .with_text_columns(*text_columns);
+                    Ok((
+                        Box::new(
+                            OsquerySupplier::new(mode)
+                                .with_ssh_targets(ssh_targets.to_vec())
+                                .with_text_columns(*text_columns),
+                        ),
+                        supplier,
+                    ))
+                }
+            },
+            PgpCommands::Tasks { allowed_commands } => {
+                let mode = UdiPgpModes::Local;
+                let supplier =
+                    Supplier::new(SupplierType::Tasks, mode.clone(), None, None, vec![auth])
+                        .with_allowed_commands(allowed_commands.clone());
+                Ok((
+                    Box::new(TasksSupplier::new(mode).with_allowed_commands(allowed_commands)?),
+                    supplier,
+                ))
+            }
+            PgpCommands::RunPack { .. } => Err(anyhow!("run-pack does not start a UDI-PGP server")),
+            PgpCommands::Config { .. } => Err(anyhow!("config does not start a UDI-PGP server")),
+        }
+    }
+}
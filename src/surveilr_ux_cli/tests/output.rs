@@ -0,0 +1,89 @@
+//! What the global `--quiet` and `--no-color` flags do to the output of the
+//! `surveilr` binary, including the UDI-PGP server which installs its own
+//! tracing subscriber.
+
+use std::{fs, path::Path, process::Output};
+
+use assert_cmd::Command;
+
+const ANSI_ESCAPE: &str = "\x1b[";
+
+fn surveilr(args: &[&str], env: &[(&str, &str)]) -> Output {
+    let mut cmd = Command::cargo_bin("surveilr").unwrap();
+    cmd.env_remove("NO_COLOR")
+        .env_remove("SURVEILR_QUIET")
+        .envs(env.iter().copied())
+        .args(args);
+    let output = cmd.output().unwrap();
+    assert!(
+        output.status.success(),
+        "surveilr {args:?} failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
+
+/// `ingest files --stats` on a single markdown file, returning STDERR.
+fn ingest_files_stats(dir: &Path, global: &[&str], env: &[(&str, &str)]) -> String {
+    let root = dir.join("root");
+    fs::create_dir_all(&root).unwrap();
+    fs::write(root.join("README.md"), "# surveilr\n").unwrap();
+    let db = dir.join("resource-surveillance.sqlite.db");
+    let _ = fs::remove_file(&db);
+
+    let mut args = global.to_vec();
+    args.extend([
+        "ingest",
+        "files",
+        "-d",
+        db.to_str().unwrap(),
+        "-r",
+        root.to_str().unwrap(),
+        "--stats",
+    ]);
+    String::from_utf8(surveilr(&args, env).stderr).unwrap()
+}
+
+#[test]
+fn test_no_color() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let colored = ingest_files_stats(dir.path(), &[], &[]);
+    assert!(colored.contains("ur_ingest_session_files_stats"));
+    assert!(colored.contains(ANSI_ESCAPE));
+
+    let plain = ingest_files_stats(dir.path(), &["--no-color"], &[]);
+    assert!(plain.contains("ur_ingest_session_files_stats"));
+    assert!(!plain.contains(ANSI_ESCAPE), "{plain}");
+
+    // any non-empty NO_COLOR disables colors (https://no-color.org)
+    for value in ["1", "0", "false"] {
+        let plain = ingest_files_stats(dir.path(), &[], &[("NO_COLOR", value)]);
+        assert!(!plain.contains(ANSI_ESCAPE), "NO_COLOR={value}: {plain}");
+    }
+}
+
+#[test]
+fn test_quiet() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let quiet = ingest_files_stats(dir.path(), &["--quiet"], &[]);
+    assert!(!quiet.contains("ur_ingest_session_files_stats"), "{quiet}");
+
+    let quiet = ingest_files_stats(dir.path(), &[], &[("SURVEILR_QUIET", "1")]);
+    assert!(!quiet.contains("ur_ingest_session_files_stats"), "{quiet}");
+}
+
+#[cfg(feature = "udi-pgp")]
+#[test]
+fn test_udi_pgp_server_logs() {
+    let listening = "UDI PGP SQLD listening on";
+
+    let logs =
+        String::from_utf8(surveilr(&["--no-color", "admin", "self-test"], &[]).stderr).unwrap();
+    assert!(logs.contains(listening), "{logs}");
+    assert!(!logs.contains(ANSI_ESCAPE), "{logs}");
+
+    let logs = String::from_utf8(surveilr(&["--quiet", "admin", "self-test"], &[]).stderr).unwrap();
+    assert!(!logs.contains(listening), "{logs}");
+}
@@ -49,6 +49,96 @@ CREATE TABLE IF NOT EXISTS "udi_pgp_observe_query_exec" (
     "deleted_by" TEXT,
     "activity_log" TEXT
 );
+CREATE TABLE IF NOT EXISTS "udi_pgp_sessions" (
+    "udi_pgp_session_id" UUID PRIMARY KEY NOT NULL,
+    "client_addr" TEXT NOT NULL,
+    "username" TEXT,
+    "supplier_id" TEXT,
+    "connected_at" TIMESTAMPTZ NOT NULL,
+    "queries_executed" INTEGER NOT NULL DEFAULT 0,
+    "last_activity_at" TIMESTAMPTZ,
+    "governance" TEXT CHECK(json_valid(governance) OR governance IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT 'UNKNOWN',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT
+);
+CREATE TABLE IF NOT EXISTS "udi_pgp_errors" (
+    "udi_pgp_error_id" UUID PRIMARY KEY NOT NULL,
+    "query_id" UUID NOT NULL,
+    "supplier_id" TEXT NOT NULL,
+    "host_id" TEXT NOT NULL,
+    "target" TEXT NOT NULL,
+    "message" TEXT NOT NULL,
+    "governance" TEXT CHECK(json_valid(governance) OR governance IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT 'UNKNOWN',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT
+);
+CREATE TABLE IF NOT EXISTS "udi_pgp_schema" (
+    "udi_pgp_schema_id" UUID PRIMARY KEY NOT NULL,
+    "supplier_id" TEXT NOT NULL,
+    "table_name" TEXT NOT NULL,
+    "column_name" TEXT NOT NULL,
+    "column_type" TEXT NOT NULL,
+    "column_index" INTEGER NOT NULL,
+    "description" TEXT,
+    "platforms" TEXT,
+    "source" TEXT NOT NULL,
+    "governance" TEXT CHECK(json_valid(governance) OR governance IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT 'UNKNOWN',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT
+);
+CREATE TABLE IF NOT EXISTS "udi_pgp_cache" (
+    "udi_pgp_cache_id" UUID PRIMARY KEY NOT NULL,
+    "supplier_id" TEXT NOT NULL,
+    "query_text" TEXT NOT NULL,
+    "hits" INTEGER NOT NULL DEFAULT 0,
+    "misses" INTEGER NOT NULL DEFAULT 0,
+    "rows_cached" INTEGER NOT NULL DEFAULT 0,
+    "cached_at" TIMESTAMPTZ,
+    "expires_at" TIMESTAMPTZ,
+    "last_hit_at" TIMESTAMPTZ,
+    "governance" TEXT CHECK(json_valid(governance) OR governance IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT 'UNKNOWN',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT,
+    UNIQUE("supplier_id", "query_text")
+);
+CREATE TABLE IF NOT EXISTS "udi_pgp_grants" (
+    "udi_pgp_grant_id" UUID PRIMARY KEY NOT NULL,
+    "supplier_id" TEXT NOT NULL,
+    "username" TEXT NOT NULL,
+    "role" TEXT NOT NULL,
+    "can_write" INTEGER NOT NULL,
+    "can_configure" INTEGER NOT NULL,
+    "allowed_tables" TEXT CHECK(json_valid(allowed_tables) OR allowed_tables IS NULL),
+    "allowed_queries" TEXT CHECK(json_valid(allowed_queries) OR allowed_queries IS NULL),
+    "governance" TEXT CHECK(json_valid(governance) OR governance IS NULL),
+    "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    "created_by" TEXT DEFAULT 'UNKNOWN',
+    "updated_at" TIMESTAMPTZ,
+    "updated_by" TEXT,
+    "deleted_at" TIMESTAMPTZ,
+    "deleted_by" TEXT,
+    "activity_log" TEXT
+);
 CREATE TABLE IF NOT EXISTS "udi_pgp_set" (
     "udi_pgp_set_id" VARCHAR PRIMARY KEY NOT NULL,
     "query_text" TEXT NOT NULL,
@@ -1,26 +1,252 @@
-use serde::{Deserialize, Serialize};
+use std::fmt::Display;
 
-/// Authentication that gets passed to pgwire
+use common::secret::Secret;
+use regex::Regex;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::parser::stmt::{StmtType, UdiPgpStatment};
+
+/// What a user may do with a supplier.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum Role {
+    /// Queries and writes to the supplier, and changes the configuration with
+    /// `SET udi_pgp_serve_*`. Users without a role are admins, as every user
+    /// was before roles.
+    #[default]
+    Admin,
+    /// Only reads: `SELECT`s, `EXPLAIN`s, cursors and the introspection tables.
+    ReadOnly,
+}
+
+impl Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Role::Admin => f.write_str("admin"),
+            Role::ReadOnly => f.write_str("read-only"),
+        }
+    }
+}
+
+/// The `allowed-queries` patterns, compiled once to match whole queries:
+/// `(?i)select .* limit \d+` allows `SELECT * FROM processes LIMIT 10` but
+/// not `SELECT * FROM processes WHERE name = 'LIMIT 10'`. Patterns which aren't
+/// valid regular expressions match nothing, the configuration validation
+/// reports them.
+#[derive(Debug, Clone, Default)]
+struct AllowedQueries {
+    patterns: Vec<String>,
+    regexes: Vec<Regex>,
+}
+
+impl AllowedQueries {
+    fn is_match(&self, query: &str) -> bool {
+        let query = query.trim().trim_end_matches(';').trim_end();
+        self.regexes.iter().any(|re| re.is_match(query))
+    }
+}
+
+impl From<Vec<String>> for AllowedQueries {
+    fn from(patterns: Vec<String>) -> Self {
+        let regexes = patterns
+            .iter()
+            .filter_map(|pattern| Regex::new(&format!("^(?:{pattern})$")).ok())
+            .collect();
+        AllowedQueries { patterns, regexes }
+    }
+}
+
+impl PartialEq for AllowedQueries {
+    fn eq(&self, other: &Self) -> bool {
+        self.patterns == other.patterns
+    }
+}
+
+impl Serialize for AllowedQueries {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.patterns.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for AllowedQueries {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::<String>::deserialize(deserializer).map(AllowedQueries::from)
+    }
+}
+
+/// Authentication that gets passed to pgwire, the password is redacted when
+/// debugged or serialized (e.g. into `udi_pgp_supplier.auth`). It also grants
+/// the user its permissions on the supplier, listed in `udi_pgp_grants`.
 // TODO think of making it base64
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Auth {
     username: String,
-    password: String,
+    password: Secret,
+    #[serde(default)]
+    role: Role,
+    /// Tables the user may query, all of them when empty.
+    #[serde(rename = "allowed-tables", default)]
+    allowed_tables: Vec<String>,
+    /// Regular expressions one of which the user's queries must match as a
+    /// whole, any query when empty.
+    #[serde(rename = "allowed-queries", default)]
+    allowed_queries: AllowedQueries,
 }
 
 impl Auth {
     pub fn new(u: &str, p: &str) -> Self {
         Auth {
             username: u.to_string(),
-            password: p.to_string(),
+            password: Secret::from(p),
+            role: Role::default(),
+            allowed_tables: vec![],
+            allowed_queries: AllowedQueries::default(),
         }
     }
 
+    pub fn with_role(mut self, role: Role) -> Self {
+        self.role = role;
+        self
+    }
+
+    pub fn with_allowed_tables(mut self, allowed_tables: Vec<String>) -> Self {
+        self.allowed_tables = allowed_tables;
+        self
+    }
+
+    pub fn with_allowed_queries(mut self, allowed_queries: Vec<String>) -> Self {
+        self.allowed_queries = allowed_queries.into();
+        self
+    }
+
     pub fn user(&self) -> &str {
         &self.username
     }
 
     pub fn password(&self) -> &str {
-        &self.password
+        self.password.expose()
+    }
+
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    pub fn allowed_tables(&self) -> &[String] {
+        &self.allowed_tables
+    }
+
+    pub fn allowed_queries(&self) -> &[String] {
+        &self.allowed_queries.patterns
+    }
+
+    /// The password given as a reference (`env://`, `keyring://`, `vault://`)
+    /// replaced by its value.
+    pub fn resolve(&self) -> anyhow::Result<Auth> {
+        Ok(Auth {
+            password: self.password.resolve()?,
+            ..self.clone()
+        })
+    }
+
+    /// Whether the user may execute `statement`, why it may not otherwise.
+    /// The queries clients send on their own (driver queries, `FETCH` and
+    /// `CLOSE`) are always allowed.
+    pub fn authorize(&self, statement: &UdiPgpStatment) -> Result<(), String> {
+        match statement.stmt_type {
+            StmtType::Driver | StmtType::Cursor => return Ok(()),
+            StmtType::Config if self.role != Role::Admin => {
+                return Err(format!(
+                    "{} is {}, changing the configuration requires the admin role",
+                    self.username, self.role
+                ));
+            }
+            _ => {}
+        }
+        if let (Some(operation), Role::ReadOnly) = (statement.write_operation(), self.role) {
+            // `EXPLAIN` only describes the write
+            if !statement.explain {
+                return Err(format!(
+                    "{} is {}, it can't execute {operation} statements",
+                    self.username, self.role
+                ));
+            }
+        }
+
+        // every table the statement touches, not only the routed ones, so
+        // that subqueries can't reach the tables which aren't allowed
+        if !self.allowed_tables.is_empty() {
+            if let Some(table) = statement.relations().into_iter().find(|table| {
+                !self
+                    .allowed_tables
+                    .iter()
+                    .any(|allowed| allowed.eq_ignore_ascii_case(table))
+            }) {
+                return Err(format!("{} isn't allowed to query {table}", self.username));
+            }
+        }
+        if !self.allowed_queries.patterns.is_empty()
+            && !self.allowed_queries.is_match(&statement.query)
+        {
+            return Err(format!(
+                "{}'s query doesn't match its allowed-queries",
+                self.username
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::UdiPgpQueryParser;
+
+    fn authorize(auth: &Auth, query: &str) -> Result<(), String> {
+        auth.authorize(&UdiPgpQueryParser::parse(query, false).unwrap())
+    }
+
+    #[test]
+    fn read_only_users_only_read() {
+        let auth = Auth::new("analyst", "secret").with_role(Role::ReadOnly);
+        assert!(authorize(&auth, "SELECT name FROM processes").is_ok());
+        assert!(authorize(&auth, "SELECT * FROM udi_pgp_sessions").is_ok());
+        assert!(authorize(&auth, "EXPLAIN INSERT INTO processes (name) VALUES ('x')").is_ok());
+        assert!(authorize(&auth, "INSERT INTO processes (name) VALUES ('x')").is_err());
+        assert!(authorize(&auth, "SET udi_pgp_serve_ncl_supplier = 'let x = {} in x'").is_err());
+
+        let admin = Auth::new("ops", "secret");
+        assert!(authorize(&admin, "INSERT INTO processes (name) VALUES ('x')").is_ok());
+    }
+
+    #[test]
+    fn allowed_tables_and_queries() {
+        let auth = Auth::new("analyst", "secret")
+            .with_role(Role::ReadOnly)
+            .with_allowed_tables(vec!["processes".to_string()])
+            .with_allowed_queries(vec![r"(?is)(SELECT|WITH) .+ LIMIT \d+".to_string()]);
+        assert!(authorize(&auth, "SELECT name FROM Processes LIMIT 10").is_ok());
+        assert!(authorize(&auth, "SELECT name FROM processes LIMIT 10;").is_ok());
+        assert!(authorize(&auth, "SELECT name FROM processes").is_err());
+        assert!(authorize(&auth, "SELECT username FROM users LIMIT 10").is_err());
+        // the patterns match whole queries
+        assert!(authorize(&auth, "SELECT name FROM processes -- LIMIT 1").is_err());
+        assert!(authorize(&auth, "SELECT name FROM processes WHERE name = 'LIMIT 1'").is_err());
+        // tables are found wherever they're referenced
+        for query in [
+            "SELECT name FROM processes WHERE EXISTS (SELECT uid FROM users) LIMIT 10",
+            "SELECT (SELECT username FROM users) FROM processes LIMIT 10",
+            "SELECT name FROM processes WHERE pid > 0 AND name IN (SELECT username FROM users) LIMIT 10",
+            "SELECT name FROM main.processes LIMIT 10",
+            "SELECT name FROM processes WHERE EXISTS (WITH users AS (SELECT 2 AS uid) SELECT uid FROM users) AND pid IN (SELECT uid FROM users) LIMIT 10",
+        ] {
+            assert!(authorize(&auth, query).is_err(), "{query}");
+        }
+        assert!(authorize(
+            &auth,
+            "WITH p AS (SELECT name FROM processes) SELECT name FROM p LIMIT 10"
+        )
+        .is_ok());
+        // the driver queries of clients aren't the user's
+        assert!(authorize(&auth, "SELECT version()").is_ok());
     }
 }
@@ -0,0 +1,145 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{Mutex, OnceLock},
+    time::SystemTime,
+};
+
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tracing::debug;
+
+use crate::error::{UdiPgpError, UdiPgpResult};
+
+/// Pins the binary a local supplier executes (e.g. `osqueryi`) so a
+/// compromised PATH can't redirect its queries to another binary.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+pub struct SupplierExecutable {
+    /// Absolute path of the binary, looked up in PATH when missing
+    pub path: Option<String>,
+    /// Hex SHA-256 of the binary, checked before it's executed
+    pub sha256: Option<String>,
+    /// Names of the environment variables passed to the binary, all of them
+    /// when missing and none when empty
+    pub env: Option<Vec<String>>,
+}
+
+/// Binaries whose checksum matched, with the size and modification time they
+/// had then: they aren't hashed again unless they change.
+fn verified() -> &'static Mutex<HashMap<PathBuf, (u64, SystemTime)>> {
+    static VERIFIED: OnceLock<Mutex<HashMap<PathBuf, (u64, SystemTime)>>> = OnceLock::new();
+    VERIFIED.get_or_init(Default::default)
+}
+
+impl SupplierExecutable {
+    /// A command executing the pinned binary, or `program` from PATH, once
+    /// its checksum is verified and with only the allowed environment.
+    pub fn command(&self, program: &str) -> UdiPgpResult<Command> {
+        let mut command = match &self.path {
+            Some(path) => {
+                if !Path::new(path).is_absolute() {
+                    return Err(UdiPgpError::UntrustedExecutable(
+                        path.to_string(),
+                        "the path must be absolute".to_string(),
+                    ));
+                }
+                if let Some(sha256) = &self.sha256 {
+                    verify_checksum(Path::new(path), sha256)?;
+                }
+                Command::new(path)
+            }
+            None if self.sha256.is_some() => {
+                return Err(UdiPgpError::UntrustedExecutable(
+                    program.to_string(),
+                    "a checksum needs the absolute path of the binary".to_string(),
+                ))
+            }
+            None => Command::new(program),
+        };
+        if let Some(env) = &self.env {
+            command.env_clear();
+            for name in env {
+                if let Some(value) = std::env::var_os(name) {
+                    command.env(name, value);
+                }
+            }
+        }
+        Ok(command)
+    }
+}
+
+fn verify_checksum(path: &Path, expected: &str) -> UdiPgpResult<()> {
+    let untrusted =
+        |reason: String| UdiPgpError::UntrustedExecutable(path.display().to_string(), reason);
+    let metadata = path.metadata().map_err(|err| untrusted(err.to_string()))?;
+    let stamp = (metadata.len(), metadata.modified()?);
+    if verified().lock().unwrap().get(path) == Some(&stamp) {
+        return Ok(());
+    }
+
+    let mut hasher = Sha256::new();
+    io::copy(
+        &mut File::open(path).map_err(|err| untrusted(err.to_string()))?,
+        &mut hasher,
+    )?;
+    let actual = format!("{:x}", hasher.finalize());
+    if !actual.eq_ignore_ascii_case(expected.trim()) {
+        verified().lock().unwrap().remove(path);
+        return Err(untrusted(format!(
+            "SHA-256 is {actual}, expected {expected}"
+        )));
+    }
+    debug!("Verified the SHA-256 of {}", path.display());
+    verified().lock().unwrap().insert(path.to_path_buf(), stamp);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pinned_executable() -> UdiPgpResult<()> {
+        let dir = tempfile::tempdir()?;
+        let binary = dir.path().join("osqueryi");
+        std::fs::write(&binary, b"#!/bin/sh\n")?;
+        let path = binary.to_string_lossy().to_string();
+        let sha256 = format!("{:x}", Sha256::digest(b"#!/bin/sh\n"));
+
+        let pinned = SupplierExecutable {
+            path: Some(path.clone()),
+            sha256: Some(sha256.to_uppercase()),
+            env: Some(vec!["PATH".to_string()]),
+        };
+        let command = pinned.command("osqueryi")?;
+        assert_eq!(command.get_program(), binary.as_os_str());
+        assert!(command.get_envs().all(|(name, _)| name == "PATH"));
+
+        std::fs::write(&binary, b"#!/bin/sh\nrm -rf /\n")?;
+        assert!(matches!(
+            pinned.command("osqueryi"),
+            Err(UdiPgpError::UntrustedExecutable(..))
+        ));
+
+        let relative = SupplierExecutable {
+            path: Some("bin/osqueryi".to_string()),
+            ..Default::default()
+        };
+        assert!(relative.command("osqueryi").is_err());
+        let unpinned = SupplierExecutable {
+            sha256: Some(sha256),
+            ..Default::default()
+        };
+        assert!(unpinned.command("osqueryi").is_err());
+        assert_eq!(
+            SupplierExecutable::default()
+                .command("osqueryi")?
+                .get_program(),
+            "osqueryi"
+        );
+        Ok(())
+    }
+}
@@ -11,10 +11,19 @@ use std::net::{SocketAddr, ToSocketAddrs};
 use std::path::{Path, PathBuf};
 use tracing::error;
 
-use crate::ssh::UdiPgpSshTarget;
+use crate::observability::LogOptions;
+use crate::remote::UdiPgpRemoteTarget;
 use crate::{auth::Auth, error::UdiPgpResult, UdiPgpError, UdiPgpModes};
 
+mod executable;
 mod nickel;
+mod validate;
+
+pub use executable::SupplierExecutable;
+pub use validate::{
+    ensure_valid, validate_config, validate_config_file, validate_supplier, ConfigDiagnostic,
+    ConfigSeverity,
+};
 
 static _NCL_SCHEMA: &str = r#"
 let ConfigString = fun label value =>
@@ -33,16 +42,32 @@ let SSHTarget = {
       | doc "e.g. localhost",
     port
       | Number
-      | doc "Port to bind SSH connection to"
-      | optional
-      | default
-      = 22,
+      | doc "Port of the connection, 22 for ssh and 5986 for winrm when missing"
+      | optional,
     user
       | String
-      | doc "Username for the ssh connection",
+      | doc "Username for the ssh or winrm connection, AWS CLI profile for ssm"
+      | optional,
     id
       | ConfigString
       | doc "Identifier for the remote connection. e.g lilit",
+    transport
+      | std.enum.TagOrString
+      | [| 'ssh, 'winrm, 'ssm |]
+      | doc "How the host is reached, host is the instance ID for ssm"
+      | optional,
+    password-env
+      | String
+      | doc "Environment variable holding the winrm password"
+      | optional,
+    tags
+      | { _ : String }
+      | doc "Labels selecting the target in queries, e.g. { env = \"prod\", role = \"db\" }"
+      | optional,
+    priority
+      | Number
+      | doc "Targets sharing an id are tried from the lowest priority, the others are failovers"
+      | optional,
   atc-file-path
       | String
       | optional
@@ -52,13 +77,26 @@ let SSHTarget = {
 let Authentication = {
   username | ConfigString,
   password | ConfigString,
+  role
+    | std.enum.TagOrString
+    | [| 'admin, '"read-only" |]
+    | doc "admin (the default) may also write and change the configuration, read-only only reads"
+    | optional,
+  allowed-tables
+    | Array String
+    | doc "Tables the user may query, all of them when missing"
+    | optional,
+  allowed-queries
+    | Array String
+    | doc "Regular expressions one of which the user's queries must match"
+    | optional,
 } in
 
 let Supplier =
   {
     type
       | std.enum.TagOrString
-      | [| 'osquery, |]
+      | [| 'osquery, 'tasks |]
       | doc "Enum values of supplier name",
     mode
       | std.enum.TagOrString
@@ -73,7 +111,34 @@ let Supplier =
     atc-file-path
       | String
       | optional
-      | doc "Osquery ATC absolute path"
+      | doc "Osquery ATC absolute path",
+    atc-file-paths
+      | Array String
+      | optional
+      | doc "Osquery ATC absolute paths, their tables are merged",
+    schema-file-path
+      | String
+      | optional
+      | doc "Osquery schema JSON listing the tables and columns in udi_pgp_schema",
+    allowed-commands
+      | Array String
+      | optional
+      | doc "Regular expressions of the commands a tasks supplier may execute",
+    text-columns
+      | Bool
+      | optional
+      | doc "Return every osquery column as text instead of typed (legacy behavior)",
+    cache-ttl
+      | Number
+      | optional
+      | doc "Seconds identical queries are answered from the cached result of the first one",
+    executable
+      | {
+          path | String | doc "Absolute path of osqueryi instead of looking it up in PATH" | optional,
+          sha256 | String | doc "SHA-256 of the binary, checked before it's executed" | optional,
+          env | Array String | doc "Environment variables passed to the binary, all when missing" | optional,
+        }
+      | optional
   } in
 
 let ConfigSchema =
@@ -123,6 +188,7 @@ pub enum SupplierType {
     Osquery,
     Git,
     Introspection,
+    Tasks,
 }
 
 impl Display for SupplierType {
@@ -131,6 +197,7 @@ impl Display for SupplierType {
             SupplierType::Git => f.write_str("git"),
             SupplierType::Osquery => f.write_str("osquery"),
             SupplierType::Introspection => f.write_str("introspection"),
+            SupplierType::Tasks => f.write_str("tasks"),
         }
     }
 }
@@ -141,15 +208,43 @@ pub struct Supplier {
     pub supplier_type: SupplierType,
     pub mode: UdiPgpModes,
     #[serde(rename = "ssh-targets")]
-    pub ssh_targets: Option<Vec<UdiPgpSshTarget>>,
+    pub ssh_targets: Option<Vec<UdiPgpRemoteTarget>>,
     #[serde(
         rename = "atc-file-path",
         deserialize_with = "deserialize_atc_file_path",
         default
     )]
     pub atc_file_path: Option<String>,
+    /// More ATC files, their tables are served along with `atc_file_path`'s.
+    #[serde(
+        rename = "atc-file-paths",
+        deserialize_with = "deserialize_atc_file_paths",
+        default
+    )]
+    pub atc_file_paths: Vec<String>,
+    /// An osquery schema JSON (an array of tables with their `columns`, as
+    /// published with osquery's table specs) listed in `udi_pgp_schema`.
+    #[serde(rename = "schema-file-path", default)]
+    pub schema_file_path: Option<String>,
     #[serde(default)]
     pub auth: Vec<Auth>,
+    /// Regular expressions of the Deno Task Shell commands a `tasks` supplier
+    /// is allowed to execute; nothing is executed when empty.
+    #[serde(rename = "allowed-commands", default)]
+    pub allowed_commands: Vec<String>,
+    /// Return every osquery column as VARCHAR text instead of mapping the
+    /// osquery column types (the behavior before typed columns).
+    #[serde(rename = "text-columns", default)]
+    pub text_columns: bool,
+    /// Seconds the result of a query is cached for, identical queries (after
+    /// normalization) are answered from memory meanwhile. Nothing is cached
+    /// when missing or 0.
+    #[serde(rename = "cache-ttl", default)]
+    pub cache_ttl: Option<u64>,
+    /// The binary a local osquery supplier executes, pinned rather than
+    /// looked up in PATH.
+    #[serde(default)]
+    pub executable: SupplierExecutable,
 }
 
 fn deserialize_supplier_type<'de, D>(deserializer: D) -> Result<SupplierType, D::Error>
@@ -162,7 +257,7 @@ where
         type Value = SupplierType;
 
         fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-            formatter.write_str("a valid supplier type (git or osquery)")
+            formatter.write_str("a valid supplier type (git, osquery or tasks)")
         }
 
         fn visit_str<E>(self, value: &str) -> Result<SupplierType, E>
@@ -170,9 +265,10 @@ where
             E: de::Error,
         {
             match value.to_lowercase().as_str() {
-                "git" | "osquery" => Ok(match value {
+                "git" | "osquery" | "tasks" => Ok(match value {
                     "git" => SupplierType::Git,
                     "osquery" => SupplierType::Osquery,
+                    "tasks" => SupplierType::Tasks,
                     _ => unreachable!(), // This should never happen
                 }),
                 _ => Err(de::Error::invalid_value(de::Unexpected::Str(value), &self)),
@@ -183,38 +279,47 @@ where
     deserializer.deserialize_str(SupplierTypeVisitor)
 }
 
+fn resolve_atc_file_path<E: de::Error>(p: &str) -> Result<String, E> {
+    match fs::canonicalize(p) {
+        Ok(resolved_path) => {
+            if resolved_path.exists() {
+                Ok(resolved_path.to_string_lossy().into_owned())
+            } else {
+                Err(E::custom(format!(
+                    "Provided atc_file_path '{}' does not exist after resolution. Resolved path was: '{}'",
+                    p,
+                    resolved_path.to_string_lossy()
+                )))
+            }
+        }
+        Err(_) => Err(E::custom(format!(
+            "Failed to resolve the provided atc_file_path '{}'. Please ensure the path exists.",
+            p
+        ))),
+    }
+}
+
 fn deserialize_atc_file_path<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
 where
     D: Deserializer<'de>,
 {
     let path: Option<String> = Option::deserialize(deserializer)?;
-    match &path {
-        Some(p) => match fs::canonicalize(p) {
-            Ok(resolved_path) => {
-                if resolved_path.exists() {
-                    Ok(Some(resolved_path.to_string_lossy().into_owned()))
-                } else {
-                    Err(serde::de::Error::custom(format!(
-                            "Provided atc_file_path '{}' does not exist after resolution. Resolved path was: '{}'",
-                            p,
-                            resolved_path.to_string_lossy()
-                        )))
-                }
-            }
-            Err(_) => Err(serde::de::Error::custom(format!(
-                "Failed to resolve the provided atc_file_path '{}'. Please ensure the path exists.",
-                p
-            ))),
-        },
-        None => Ok(None),
-    }
+    path.map(|p| resolve_atc_file_path(&p)).transpose()
+}
+
+fn deserialize_atc_file_paths<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let paths: Vec<String> = Vec::deserialize(deserializer)?;
+    paths.iter().map(|p| resolve_atc_file_path(p)).collect()
 }
 
 impl Supplier {
     pub fn new(
         supplier_type: SupplierType,
         mode: UdiPgpModes,
-        ssh_targets: Option<Vec<UdiPgpSshTarget>>,
+        ssh_targets: Option<Vec<UdiPgpRemoteTarget>>,
         atc_file_path: Option<String>,
         auth: Vec<Auth>,
     ) -> Self {
@@ -223,9 +328,65 @@ impl Supplier {
             mode,
             ssh_targets,
             atc_file_path,
+            atc_file_paths: vec![],
+            schema_file_path: None,
             auth,
+            allowed_commands: vec![],
+            text_columns: false,
+            cache_ttl: None,
+            executable: SupplierExecutable::default(),
         }
     }
+
+    pub fn with_allowed_commands(mut self, allowed_commands: Vec<String>) -> Self {
+        self.allowed_commands = allowed_commands;
+        self
+    }
+
+    pub fn with_text_columns(mut self, text_columns: bool) -> Self {
+        self.text_columns = text_columns;
+        self
+    }
+
+    pub fn with_cache_ttl(mut self, cache_ttl: Option<u64>) -> Self {
+        self.cache_ttl = cache_ttl;
+        self
+    }
+
+    pub fn with_atc_file_paths(mut self, atc_file_paths: Vec<String>) -> Self {
+        self.atc_file_paths = atc_file_paths;
+        self
+    }
+
+    pub fn with_schema_file_path(mut self, schema_file_path: Option<String>) -> Self {
+        self.schema_file_path = schema_file_path;
+        self
+    }
+
+    pub fn with_executable(mut self, executable: SupplierExecutable) -> Self {
+        self.executable = executable;
+        self
+    }
+
+    /// Resolve the passwords of `auth` given as secret references.
+    pub fn resolve_secrets(&mut self) -> UdiPgpResult<()> {
+        self.auth = self
+            .auth
+            .iter()
+            .map(Auth::resolve)
+            .collect::<anyhow::Result<_>>()
+            .map_err(|err| UdiPgpError::ConfigError(format!("{err:#}")))?;
+        Ok(())
+    }
+
+    /// Every ATC file of the supplier, `atc_file_path` first.
+    pub fn atc_files(&self) -> Vec<String> {
+        self.atc_file_path
+            .iter()
+            .chain(&self.atc_file_paths)
+            .cloned()
+            .collect()
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -241,6 +402,9 @@ pub struct UdiPgpConfig {
     pub verbose: bool,
     #[serde(rename = "admin-state-fs-path", default = "default_admin_state_path")]
     pub admin_state_fs_path: PathBuf,
+    /// Set by `surveilr` from its logging flags, not part of the config file
+    #[serde(skip)]
+    pub log: LogOptions,
 }
 
 impl UdiPgpConfig {
@@ -267,16 +431,19 @@ impl UdiPgpConfig {
             .and_then(|ext| ext.to_str())
             .ok_or_else(|| UdiPgpError::ConfigError("File has no extension".to_string()))?;
 
-        match extension {
-            "json" => Self::try_config_from_json(path.to_str().unwrap()),
-            "ncl" => {
-                let (config, _) = nickel::try_config_from_ncl(path.as_os_str())?;
-                Ok(config)
+        let mut config = match extension {
+            "json" => Self::try_config_from_json(path.to_str().unwrap())?,
+            "ncl" => nickel::try_config_from_ncl(path.as_os_str())?.0,
+            other => {
+                return Err(UdiPgpError::ConfigError(format!(
+                    "File extension not supported. Got {other:?}. Expected json or ncl"
+                )))
             }
-            other => Err(UdiPgpError::ConfigError(format!(
-                "File extension not supported. Got {other:?}. Expected json or ncl"
-            ))),
+        };
+        for supplier in config.suppliers.values_mut() {
+            supplier.resolve_secrets()?;
         }
+        Ok(config)
     }
 
     fn try_config_from_json(path: &str) -> UdiPgpResult<UdiPgpConfig> {
@@ -302,6 +469,11 @@ impl UdiPgpConfig {
         self.clone()
     }
 
+    pub fn with_log_options(&mut self, log: LogOptions) -> Self {
+        self.log = log;
+        self.clone()
+    }
+
     pub fn addr(&self) -> &SocketAddr {
         &self.addr
     }
@@ -338,16 +510,26 @@ impl UdiPgpConfig {
     }
 
     pub fn try_config_from_diagnostics(path: &PathBuf) -> UdiPgpResult<UdiPgpConfig> {
-        let file = File::open(path)?;
-        let reader = BufReader::new(file);
+        let value = Self::diagnostics_json(path)?;
+        ensure_valid(&validate_config(&value, ""))?;
 
-        let config = serde_json::from_reader(reader).map_err(|e| {
+        let config = serde_json::from_value(value).map_err(|e| {
             UdiPgpError::ConfigError(format!("Failed to parse JSON from file. Erro: {}", e))
         })?;
 
         Ok(config)
     }
 
+    /// The JSON a configuration was evaluated to, checked field by field
+    /// before it's deserialized so errors point at the offending field.
+    fn diagnostics_json(path: &PathBuf) -> UdiPgpResult<serde_json::Value> {
+        let file = File::open(path)?;
+        let reader = BufReader::new(file);
+        serde_json::from_reader(reader).map_err(|e| {
+            UdiPgpError::ConfigError(format!("Failed to parse JSON from file. Erro: {}", e))
+        })
+    }
+
     /// Write the NCL string config to JSON file for diagnostics
     /// `core` designates if it is core configuration or supplier config
     pub fn diagnostics(s: &str, core: bool) -> UdiPgpResult<PathBuf> {
@@ -368,15 +550,16 @@ impl UdiPgpConfig {
     ) -> UdiPgpResult<(String, Supplier)> {
         let supplier_id = Self::get_supplier_id_from_serve_stmt(s)?;
 
-        let file = File::open(path)?;
-        let reader = BufReader::new(file);
+        let value = Self::diagnostics_json(path)?;
+        ensure_valid(&validate_supplier(&value, &supplier_id))?;
 
-        let supplier: Supplier = serde_json::from_reader(reader).map_err(|err| {
+        let mut supplier: Supplier = serde_json::from_value(value).map_err(|err| {
             UdiPgpError::ConfigError(format!(
                 "Failed to parse supplier: {} in file: {:#?}",
                 err, path
             ))
         })?;
+        supplier.resolve_secrets()?;
         Ok((supplier_id, supplier))
     }
 
@@ -409,6 +592,33 @@ impl UdiPgpConfig {
     }
 }
 
+/// Load SSH targets from a `.ncl` or `.json` file which evaluates either to an
+/// array of targets or to a record with an `ssh-targets` array (like a supplier).
+pub fn try_ssh_targets_from_file<P: AsRef<Path>>(path: P) -> UdiPgpResult<Vec<UdiPgpRemoteTarget>> {
+    let path = path.as_ref();
+    let json = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("ncl") => nickel::try_json_from_ncl(path.as_os_str())?,
+        Some("json") => fs::read_to_string(path)?,
+        other => {
+            return Err(UdiPgpError::ConfigError(format!(
+                "File extension not supported. Got {other:?}. Expected json or ncl"
+            )))
+        }
+    };
+    let value: serde_json::Value = serde_json::from_str(&json)?;
+    let targets = match value.get("ssh-targets") {
+        Some(targets) => targets.clone(),
+        None => value,
+    };
+    serde_json::from_value(targets).map_err(|err| {
+        UdiPgpError::ConfigError(format!(
+            "Invalid SSH targets in {}: {}",
+            path.display(),
+            err
+        ))
+    })
+}
+
 fn default_addr() -> SocketAddr {
     "127.0.0.1:5432".to_socket_addrs().unwrap().next().unwrap()
 }
@@ -431,8 +641,38 @@ fn default_verbose() -> bool {
 }
 
 fn default_admin_state_path() -> PathBuf {
-    std::fs::canonicalize(std::path::Path::new(
-        "resource-surveillance-admin.sqlite.db",
-    ))
-    .unwrap()
+    // the admin database may not have been created in the working directory yet
+    let path = std::path::Path::new("resource-surveillance-admin.sqlite.db");
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn ssh_targets_from_json_file() {
+        let mut file = tempfile::Builder::new().suffix(".json").tempfile().unwrap();
+        write!(
+            file,
+            r#"{{ "ssh-targets": [{{ "host": "10.0.0.5", "port": 2222, "user": "admin", "id": "web-1" }}] }}"#
+        )
+        .unwrap();
+        let targets = try_ssh_targets_from_file(file.path()).unwrap();
+        assert_eq!(targets.len(), 1);
+        assert_eq!(targets[0].id, "web-1");
+        assert_eq!(targets[0].port, Some(2222));
+
+        let mut file = tempfile::Builder::new().suffix(".json").tempfile().unwrap();
+        write!(
+            file,
+            r#"[{{ "host": "db.local", "user": "ops", "id": "db" }}]"#
+        )
+        .unwrap();
+        let targets = try_ssh_targets_from_file(file.path()).unwrap();
+        assert_eq!(targets[0].host, "db.local");
+        assert_eq!(targets[0].port, None);
+    }
 }
@@ -1,9 +1,8 @@
 use nickel_lang_core::{
-    error::{report::ErrorFormat, Error as NickelError},
+    error::Error as NickelError,
     eval::cache::CacheImpl,
     program::Program,
     serialize::{self, ExportFormat},
-    term::RichTerm,
 };
 use serde_json::Value;
 use std::{
@@ -17,7 +16,7 @@ use tracing::error;
 
 use crate::error::{UdiPgpError, UdiPgpResult};
 
-use super::{Supplier, UdiPgpConfig};
+use super::{validate::ConfigDiagnostic, Supplier, UdiPgpConfig};
 
 /// Write the NCL string config to JSON file for diagnostics
 pub fn ncl_to_json_file(s: &str, core: bool) -> UdiPgpResult<PathBuf> {
@@ -29,10 +28,8 @@ pub fn ncl_to_json_file(s: &str, core: bool) -> UdiPgpResult<PathBuf> {
             UdiPgpError::ConfigError(err.to_string())
         })?;
 
-    let json = export(&mut program, ExportFormat::Json).map_err(|err| {
-        program.report(err, ErrorFormat::Text);
-        UdiPgpError::ConfigError("Failed to export configuration".to_string())
-    })?;
+    let json =
+        export(&mut program, ExportFormat::Json).map_err(|err| export_error(&mut program, err))?;
 
     write_and_persist_temp(&json)
 }
@@ -45,10 +42,8 @@ pub fn try_supplier_from_ncl(s: &str) -> UdiPgpResult<(Supplier, PathBuf)> {
             UdiPgpError::ConfigError(err.to_string())
         })?;
 
-    let json = export(&mut program, ExportFormat::Json).map_err(|err| {
-        program.report(err, ErrorFormat::Text);
-        UdiPgpError::ConfigError("Failed to export configuration".to_string())
-    })?;
+    let json =
+        export(&mut program, ExportFormat::Json).map_err(|err| export_error(&mut program, err))?;
 
     let path = write_and_persist_temp(&json)?;
     let file = File::open(&path)?;
@@ -70,14 +65,22 @@ pub fn try_config_from_ncl(path: impl Into<OsString>) -> UdiPgpResult<(UdiPgpCon
         UdiPgpError::ConfigError(err.to_string())
     })?;
 
-    let config = export(&mut program, ExportFormat::Json).map_err(|err| {
-        program.report(err, ErrorFormat::Text);
-        UdiPgpError::ConfigError("Failed to export configuration".to_string())
-    })?;
+    let config =
+        export(&mut program, ExportFormat::Json).map_err(|err| export_error(&mut program, err))?;
 
     config_from_json(&config, true)
 }
 
+/// Evaluate an arbitrary NCL file and export it as JSON.
+pub fn try_json_from_ncl(path: impl Into<OsString>) -> UdiPgpResult<String> {
+    let mut program = Program::new_from_file(path, std::io::stderr()).map_err(|err| {
+        error!("{}", err);
+        UdiPgpError::ConfigError(err.to_string())
+    })?;
+
+    export(&mut program, ExportFormat::Json).map_err(|err| export_error(&mut program, err))
+}
+
 pub fn try_config_from_ncl_string(s: &str) -> UdiPgpResult<(UdiPgpConfig, PathBuf)> {
     let src = Cursor::new(s);
     let mut program =
@@ -86,16 +89,34 @@ pub fn try_config_from_ncl_string(s: &str) -> UdiPgpResult<(UdiPgpConfig, PathBu
             UdiPgpError::ConfigError(err.to_string())
         })?;
 
-    let config = export(&mut program, ExportFormat::Json).map_err(|err| {
-        program.report(err, ErrorFormat::Text);
-        UdiPgpError::ConfigError("Failed to export configuration".to_string())
-    })?;
+    let config =
+        export(&mut program, ExportFormat::Json).map_err(|err| export_error(&mut program, err))?;
 
     config_from_json(&config, false)
 }
 
+/// Evaluate an NCL source and export it as JSON, the Nickel error report
+/// (with the line of the error in `source_name`) otherwise.
+pub fn export_ncl_source(s: &str, source_name: &str) -> Result<String, String> {
+    let mut program = Program::new_from_source(Cursor::new(s), source_name, std::io::sink())
+        .map_err(|err| err.to_string())?;
+    export(&mut program, ExportFormat::Json).map_err(|err| program.report_as_str(err))
+}
+
+/// A configuration error with the diagnostic of the Nickel error report,
+/// rather than an opaque failure.
+fn export_error(program: &mut Program<CacheImpl>, err: NickelError) -> UdiPgpError {
+    let report = program.report_as_str(err);
+    error!("{}", report);
+    UdiPgpError::ConfigError(format!(
+        "Failed to export configuration: {}",
+        ConfigDiagnostic::from_nickel_report(&report)
+    ))
+}
+
+#[allow(clippy::result_large_err)]
 fn export(program: &mut Program<CacheImpl>, format: ExportFormat) -> Result<String, NickelError> {
-    let rt = program.eval_full_for_export().map(RichTerm::from)?;
+    let rt = program.eval_full_for_export()?;
     serialize::validate(format, &rt)?;
     Ok(serialize::to_string(format, &rt)?)
 }
@@ -0,0 +1,719 @@
+use std::{fmt::Display, fs, path::Path};
+
+use regex::Regex;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use super::{nickel, parse_socket_addr};
+use crate::error::{UdiPgpError, UdiPgpResult};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigSeverity {
+    /// the configuration would be rejected (or fail once applied)
+    Error,
+    /// e.g. a field which isn't used, usually a typo
+    Warning,
+}
+
+/// A problem found in a configuration before it's applied.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfigDiagnostic {
+    pub severity: ConfigSeverity,
+    /// e.g. `suppliers.laptops.ssh-targets[0].host`
+    pub field: Option<String>,
+    /// Line of the NCL or JSON source, from 1 (only known for evaluation and
+    /// syntax errors)
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl ConfigDiagnostic {
+    fn error(field: &str, message: impl Into<String>) -> Self {
+        ConfigDiagnostic {
+            severity: ConfigSeverity::Error,
+            field: (!field.is_empty()).then(|| field.to_string()),
+            line: None,
+            message: message.into(),
+        }
+    }
+
+    fn warning(field: &str, message: impl Into<String>) -> Self {
+        ConfigDiagnostic {
+            severity: ConfigSeverity::Warning,
+            ..Self::error(field, message)
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == ConfigSeverity::Error
+    }
+
+    /// The headline and notes of a Nickel error report, e.g.
+    /// `error: missing definition for `host`` followed by `┌─ <supplier>:3:5`.
+    pub(super) fn from_nickel_report(report: &str) -> Self {
+        let ansi = Regex::new(r"\x1b\[[0-9;]*m").unwrap();
+        let report = ansi.replace_all(report, "");
+        let location = Regex::new(r"┌─ [^\n]*:(\d+):\d+").unwrap();
+        let quoted = Regex::new(r"`([^`]+)`").unwrap();
+
+        let mut lines = report.lines().map(str::trim);
+        let headline = lines
+            .next()
+            .unwrap_or_default()
+            .trim_start_matches("error:")
+            .trim()
+            .to_string();
+        let notes = lines
+            .filter_map(|line| line.strip_prefix("= "))
+            .collect::<Vec<_>>();
+        ConfigDiagnostic {
+            severity: ConfigSeverity::Error,
+            field: quoted.captures(&headline).map(|caps| caps[1].to_string()),
+            line: location
+                .captures(&report)
+                .and_then(|caps| caps[1].parse().ok()),
+            message: std::iter::once(headline.as_str())
+                .chain(notes)
+                .collect::<Vec<_>>()
+                .join("; "),
+        }
+    }
+}
+
+impl Display for ConfigDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self.severity {
+            ConfigSeverity::Error => "error",
+            ConfigSeverity::Warning => "warning",
+        })?;
+        let location = self
+            .line
+            .map(|line| format!("line {line}"))
+            .into_iter()
+            .chain(self.field.as_ref().map(|field| format!("`{field}`")))
+            .collect::<Vec<_>>();
+        if !location.is_empty() {
+            write!(f, " at {}", location.join(", "))?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// Fail with every error of the diagnostics, if any, as one message.
+pub fn ensure_valid(diagnostics: &[ConfigDiagnostic]) -> UdiPgpResult<()> {
+    let errors = diagnostics
+        .iter()
+        .filter(|d| d.is_error())
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(UdiPgpError::ConfigError(format!(
+            "Invalid configuration:\n{}",
+            errors.join("\n")
+        )))
+    }
+}
+
+/// Validate a `.ncl` or `.json` file holding either a UDI-PGP configuration
+/// (NCL files usually wrap it in a `config` record) or a single supplier, as
+/// used with `SET udi_pgp_serve_ncl_supplier`.
+pub fn validate_config_file<P: AsRef<Path>>(path: P) -> UdiPgpResult<Vec<ConfigDiagnostic>> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path)?;
+    let value = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("ncl") => match nickel::export_ncl_source(&source, &path.display().to_string()) {
+            Ok(json) => serde_json::from_str(&json)
+                .map_err(|err| UdiPgpError::ConfigError(err.to_string()))?,
+            Err(report) => return Ok(vec![ConfigDiagnostic::from_nickel_report(&report)]),
+        },
+        Some("json") => match serde_json::from_str(&source) {
+            Ok(value) => value,
+            Err(err) => {
+                return Ok(vec![ConfigDiagnostic {
+                    line: Some(err.line()),
+                    ..ConfigDiagnostic::error("", format!("invalid JSON: {err}"))
+                }])
+            }
+        },
+        other => {
+            return Err(UdiPgpError::ConfigError(format!(
+                "File extension not supported. Got {other:?}. Expected json or ncl"
+            )))
+        }
+    };
+
+    Ok(match &value {
+        Value::Object(record) if record.contains_key("config") => {
+            validate_config(&record["config"], "config")
+        }
+        Value::Object(record)
+            if !record.contains_key("suppliers")
+                && (record.contains_key("type") || record.contains_key("mode")) =>
+        {
+            validate_supplier(&value, "")
+        }
+        _ => validate_config(&value, ""),
+    })
+}
+
+/// Validate the evaluated (JSON) form of a configuration, `field` being where
+/// it is in its source.
+pub fn validate_config(value: &Value, field: &str) -> Vec<ConfigDiagnostic> {
+    let mut diagnostics = vec![];
+    let Some(record) = expect_record(value, field, &mut diagnostics) else {
+        return diagnostics;
+    };
+    unknown_fields(
+        record,
+        field,
+        &[
+            "addr",
+            "metrics",
+            "health",
+            "verbose",
+            "admin-state-fs-path",
+            "suppliers",
+        ],
+        &mut diagnostics,
+    );
+    for name in ["addr", "metrics", "health"] {
+        let field = join(field, name);
+        match record.get(name) {
+            None | Some(Value::Null) => {}
+            Some(Value::String(addr)) => {
+                if let Err(err) = parse_socket_addr(addr) {
+                    diagnostics.push(ConfigDiagnostic::error(
+                        &field,
+                        format!("expected a host:port address, {err}"),
+                    ));
+                }
+            }
+            Some(other) => diagnostics.push(mismatch(&field, "a host:port string", other)),
+        }
+    }
+    expect_bool(record, field, "verbose", &mut diagnostics);
+    expect_string(
+        record,
+        field,
+        "admin-state-fs-path",
+        false,
+        &mut diagnostics,
+    );
+    match record.get("suppliers") {
+        None => {}
+        Some(Value::Object(suppliers)) => {
+            for (name, supplier) in suppliers {
+                diagnostics.extend(validate_supplier(
+                    supplier,
+                    &join(&join(field, "suppliers"), name),
+                ));
+            }
+        }
+        Some(other) => diagnostics.push(mismatch(
+            &join(field, "suppliers"),
+            "a record of suppliers by name",
+            other,
+        )),
+    }
+    diagnostics
+}
+
+/// Validate the evaluated (JSON) form of a supplier.
+pub fn validate_supplier(value: &Value, field: &str) -> Vec<ConfigDiagnostic> {
+    let mut diagnostics = vec![];
+    let Some(record) = expect_record(value, field, &mut diagnostics) else {
+        return diagnostics;
+    };
+    unknown_fields(
+        record,
+        field,
+        &[
+            "type",
+            "mode",
+            "ssh-targets",
+            "atc-file-path",
+            "atc-file-paths",
+            "schema-file-path",
+            "auth",
+            "allowed-commands",
+            "text-columns",
+            "cache-ttl",
+            "executable",
+        ],
+        &mut diagnostics,
+    );
+    expect_one_of(
+        record,
+        field,
+        "type",
+        &["osquery", "tasks", "git"],
+        true,
+        &mut diagnostics,
+    );
+    expect_one_of(
+        record,
+        field,
+        "mode",
+        &["local", "remote"],
+        true,
+        &mut diagnostics,
+    );
+
+    if let Some(auth) = expect_array(record, field, "auth", &mut diagnostics) {
+        for (index, credentials) in auth.iter().enumerate() {
+            let field = format!("{}[{index}]", join(field, "auth"));
+            if let Some(credentials) = expect_record(credentials, &field, &mut diagnostics) {
+                unknown_fields(
+                    credentials,
+                    &field,
+                    &[
+                        "username",
+                        "password",
+                        "role",
+                        "allowed-tables",
+                        "allowed-queries",
+                    ],
+                    &mut diagnostics,
+                );
+                expect_string(credentials, &field, "username", true, &mut diagnostics);
+                expect_string(credentials, &field, "password", true, &mut diagnostics);
+                expect_one_of(
+                    credentials,
+                    &field,
+                    "role",
+                    &["admin", "read-only"],
+                    false,
+                    &mut diagnostics,
+                );
+                if let Some(tables) =
+                    expect_array(credentials, &field, "allowed-tables", &mut diagnostics)
+                {
+                    for (index, table) in tables.iter().enumerate() {
+                        if !table.is_string() {
+                            diagnostics.push(mismatch(
+                                &format!("{}[{index}]", join(&field, "allowed-tables")),
+                                "a table name",
+                                table,
+                            ));
+                        }
+                    }
+                }
+                expect_regexes(credentials, &field, "allowed-queries", &mut diagnostics);
+            }
+        }
+    }
+    if let Some(targets) = expect_array(record, field, "ssh-targets", &mut diagnostics) {
+        for (index, target) in targets.iter().enumerate() {
+            diagnostics.extend(validate_remote_target(
+                target,
+                &format!("{}[{index}]", join(field, "ssh-targets")),
+            ));
+        }
+        if record.get("mode").and_then(Value::as_str) == Some("local") && !targets.is_empty() {
+            diagnostics.push(ConfigDiagnostic::warning(
+                &join(field, "ssh-targets"),
+                "targets are only queried in remote mode",
+            ));
+        }
+    }
+
+    if let Some(path) = expect_string(record, field, "atc-file-path", false, &mut diagnostics) {
+        expect_existing_file(path, &join(field, "atc-file-path"), &mut diagnostics);
+    }
+    if let Some(paths) = expect_array(record, field, "atc-file-paths", &mut diagnostics) {
+        for (index, path) in paths.iter().enumerate() {
+            let field = format!("{}[{index}]", join(field, "atc-file-paths"));
+            match path {
+                Value::String(path) => expect_existing_file(path, &field, &mut diagnostics),
+                other => diagnostics.push(mismatch(&field, "a path", other)),
+            }
+        }
+    }
+    if let Some(path) = expect_string(record, field, "schema-file-path", false, &mut diagnostics) {
+        expect_existing_file(path, &join(field, "schema-file-path"), &mut diagnostics);
+    }
+    expect_regexes(record, field, "allowed-commands", &mut diagnostics);
+    expect_bool(record, field, "text-columns", &mut diagnostics);
+    expect_seconds(record, field, "cache-ttl", &mut diagnostics);
+    if let Some(executable) = record.get("executable").filter(|v| !v.is_null()) {
+        diagnostics.extend(validate_executable(executable, &join(field, "executable")));
+    }
+    diagnostics
+}
+
+fn validate_executable(value: &Value, field: &str) -> Vec<ConfigDiagnostic> {
+    let mut diagnostics = vec![];
+    let Some(record) = expect_record(value, field, &mut diagnostics) else {
+        return diagnostics;
+    };
+    unknown_fields(record, field, &["path", "sha256", "env"], &mut diagnostics);
+    let path = expect_string(record, field, "path", false, &mut diagnostics);
+    if let Some(path) = path {
+        if !Path::new(path).is_absolute() {
+            diagnostics.push(ConfigDiagnostic::error(
+                &join(field, "path"),
+                "expected an absolute path",
+            ));
+        } else {
+            expect_existing_file(path, &join(field, "path"), &mut diagnostics);
+        }
+    }
+    if let Some(sha256) = expect_string(record, field, "sha256", false, &mut diagnostics) {
+        if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+            diagnostics.push(ConfigDiagnostic::error(
+                &join(field, "sha256"),
+                "expected 64 hexadecimal digits",
+            ));
+        } else if path.is_none() {
+            diagnostics.push(ConfigDiagnostic::error(
+                &join(field, "sha256"),
+                "a checksum needs the absolute path of the binary",
+            ));
+        }
+    }
+    if let Some(env) = expect_array(record, field, "env", &mut diagnostics) {
+        for (index, name) in env.iter().enumerate() {
+            if !name.is_string() {
+                diagnostics.push(mismatch(
+                    &format!("{}[{index}]", join(field, "env")),
+                    "a variable name",
+                    name,
+                ));
+            }
+        }
+    }
+    diagnostics
+}
+
+fn validate_remote_target(value: &Value, field: &str) -> Vec<ConfigDiagnostic> {
+    let mut diagnostics = vec![];
+    let Some(record) = expect_record(value, field, &mut diagnostics) else {
+        return diagnostics;
+    };
+    unknown_fields(
+        record,
+        field,
+        &[
+            "host",
+            "port",
+            "user",
+            "id",
+            "transport",
+            "password-env",
+            "atc-file-path",
+            "tags",
+            "priority",
+            "status",
+        ],
+        &mut diagnostics,
+    );
+    expect_string(record, field, "host", true, &mut diagnostics);
+    expect_string(record, field, "id", true, &mut diagnostics);
+    expect_string(record, field, "user", false, &mut diagnostics);
+    expect_string(record, field, "password-env", false, &mut diagnostics);
+    expect_string(record, field, "atc-file-path", false, &mut diagnostics);
+    expect_one_of(
+        record,
+        field,
+        "transport",
+        &["ssh", "winrm", "ssm"],
+        false,
+        &mut diagnostics,
+    );
+    for name in ["port", "priority"] {
+        match record.get(name) {
+            None | Some(Value::Null) => {}
+            Some(Value::Number(n)) if n.as_u64().is_some_and(|n| n <= u16::MAX as u64) => {}
+            Some(other) => diagnostics.push(mismatch(
+                &join(field, name),
+                "a whole number from 0 to 65535",
+                other,
+            )),
+        }
+    }
+    match record.get("tags") {
+        None | Some(Value::Null) => {}
+        Some(Value::Object(tags)) => {
+            for (name, tag) in tags {
+                if !tag.is_string() {
+                    diagnostics.push(mismatch(&join(&join(field, "tags"), name), "a string", tag));
+                }
+            }
+        }
+        Some(other) => {
+            diagnostics.push(mismatch(&join(field, "tags"), "a record of strings", other))
+        }
+    }
+    diagnostics
+}
+
+fn join(field: &str, name: &str) -> String {
+    if field.is_empty() {
+        name.to_string()
+    } else {
+        format!("{field}.{name}")
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "a record",
+    }
+}
+
+fn mismatch(field: &str, expected: &str, got: &Value) -> ConfigDiagnostic {
+    ConfigDiagnostic::error(
+        field,
+        format!("expected {expected}, got {}", type_name(got)),
+    )
+}
+
+fn expect_record<'a>(
+    value: &'a Value,
+    field: &str,
+    diagnostics: &mut Vec<ConfigDiagnostic>,
+) -> Option<&'a Map<String, Value>> {
+    match value {
+        Value::Object(record) => Some(record),
+        other => {
+            diagnostics.push(mismatch(field, "a record", other));
+            None
+        }
+    }
+}
+
+fn unknown_fields(
+    record: &Map<String, Value>,
+    field: &str,
+    known: &[&str],
+    diagnostics: &mut Vec<ConfigDiagnostic>,
+) {
+    for name in record.keys().filter(|name| !known.contains(&name.as_str())) {
+        diagnostics.push(ConfigDiagnostic::warning(
+            &join(field, name),
+            format!("unknown field, expected one of {}", known.join(", ")),
+        ));
+    }
+}
+
+fn expect_string<'a>(
+    record: &'a Map<String, Value>,
+    field: &str,
+    name: &str,
+    required: bool,
+    diagnostics: &mut Vec<ConfigDiagnostic>,
+) -> Option<&'a str> {
+    let field = join(field, name);
+    match record.get(name) {
+        None | Some(Value::Null) if required => {
+            diagnostics.push(ConfigDiagnostic::error(&field, "missing required field"));
+            None
+        }
+        None | Some(Value::Null) => None,
+        Some(Value::String(s)) if required && s.is_empty() => {
+            diagnostics.push(ConfigDiagnostic::error(&field, "cannot be an empty string"));
+            None
+        }
+        Some(Value::String(s)) => Some(s),
+        Some(other) => {
+            diagnostics.push(mismatch(&field, "a string", other));
+            None
+        }
+    }
+}
+
+fn expect_one_of(
+    record: &Map<String, Value>,
+    field: &str,
+    name: &str,
+    allowed: &[&str],
+    required: bool,
+    diagnostics: &mut Vec<ConfigDiagnostic>,
+) {
+    if let Some(value) = expect_string(record, field, name, required, diagnostics) {
+        if !allowed.contains(&value) {
+            diagnostics.push(ConfigDiagnostic::error(
+                &join(field, name),
+                format!("expected one of {}, got {value:?}", allowed.join(", ")),
+            ));
+        }
+    }
+}
+
+fn expect_bool(
+    record: &Map<String, Value>,
+    field: &str,
+    name: &str,
+    diagnostics: &mut Vec<ConfigDiagnostic>,
+) {
+    match record.get(name) {
+        None | Some(Value::Null) | Some(Value::Bool(_)) => {}
+        Some(other) => diagnostics.push(mismatch(&join(field, name), "a boolean", other)),
+    }
+}
+
+fn expect_seconds(
+    record: &Map<String, Value>,
+    field: &str,
+    name: &str,
+    diagnostics: &mut Vec<ConfigDiagnostic>,
+) {
+    match record.get(name) {
+        None | Some(Value::Null) => {}
+        Some(Value::Number(n)) if n.is_u64() => {}
+        Some(other) => diagnostics.push(mismatch(
+            &join(field, name),
+            "a whole number of seconds",
+            other,
+        )),
+    }
+}
+
+fn expect_array<'a>(
+    record: &'a Map<String, Value>,
+    field: &str,
+    name: &str,
+    diagnostics: &mut Vec<ConfigDiagnostic>,
+) -> Option<&'a Vec<Value>> {
+    match record.get(name) {
+        None | Some(Value::Null) => None,
+        Some(Value::Array(items)) => Some(items),
+        Some(other) => {
+            diagnostics.push(mismatch(&join(field, name), "an array", other));
+            None
+        }
+    }
+}
+
+fn expect_regexes(
+    record: &Map<String, Value>,
+    field: &str,
+    key: &str,
+    diagnostics: &mut Vec<ConfigDiagnostic>,
+) {
+    let Some(patterns) = expect_array(record, field, key, diagnostics) else {
+        return;
+    };
+    for (index, pattern) in patterns.iter().enumerate() {
+        let field = format!("{}[{index}]", join(field, key));
+        match pattern {
+            Value::String(pattern) => {
+                if let Err(err) = Regex::new(pattern) {
+                    diagnostics.push(ConfigDiagnostic::error(
+                        &field,
+                        format!("invalid regular expression: {err}"),
+                    ));
+                }
+            }
+            other => diagnostics.push(mismatch(&field, "a regular expression", other)),
+        }
+    }
+}
+
+fn expect_existing_file(path: &str, field: &str, diagnostics: &mut Vec<ConfigDiagnostic>) {
+    if !Path::new(path).is_file() {
+        diagnostics.push(ConfigDiagnostic::error(
+            field,
+            format!("file {path:?} does not exist"),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn described(diagnostics: &[ConfigDiagnostic]) -> Vec<String> {
+        diagnostics.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn supplier_field_diagnostics() {
+        let supplier = json!({
+            "type": "osquery",
+            "mode": "remote",
+            "ssh-targets": [
+                { "host": "127.0.0.1", "id": "laptop", "port": 22 },
+                { "host": "", "port": 70000, "transport": "telnet", "tags": { "env": 1 } }
+            ],
+            "auth": [{ "username": "john", "role": "viewer", "allowed-queries": ["(LIMIT"] }],
+            "allowed-commands": ["osqueryi ("],
+            "cache-ttl": 1.5,
+            "executable": { "path": "bin/osqueryi", "sha256": "abc" },
+            "text-colums": true
+        });
+        let diagnostics = validate_supplier(&supplier, "suppliers.laptops");
+        assert_eq!(
+            described(&diagnostics),
+            vec![
+                "warning at `suppliers.laptops.text-colums`: unknown field, expected one of type, mode, ssh-targets, atc-file-path, atc-file-paths, schema-file-path, auth, allowed-commands, text-columns, cache-ttl, executable",
+                "error at `suppliers.laptops.auth[0].password`: missing required field",
+                "error at `suppliers.laptops.auth[0].role`: expected one of admin, read-only, got \"viewer\"",
+                "error at `suppliers.laptops.auth[0].allowed-queries[0]`: invalid regular expression: regex parse error:\n    (LIMIT\n    ^\nerror: unclosed group",
+                "error at `suppliers.laptops.ssh-targets[1].host`: cannot be an empty string",
+                "error at `suppliers.laptops.ssh-targets[1].id`: missing required field",
+                "error at `suppliers.laptops.ssh-targets[1].transport`: expected one of ssh, winrm, ssm, got \"telnet\"",
+                "error at `suppliers.laptops.ssh-targets[1].port`: expected a whole number from 0 to 65535, got a number",
+                "error at `suppliers.laptops.ssh-targets[1].tags.env`: expected a string, got a number",
+                "error at `suppliers.laptops.allowed-commands[0]`: invalid regular expression: regex parse error:\n    osqueryi (\n             ^\nerror: unclosed group",
+                "error at `suppliers.laptops.cache-ttl`: expected a whole number of seconds, got a number",
+                "error at `suppliers.laptops.executable.path`: expected an absolute path",
+                "error at `suppliers.laptops.executable.sha256`: expected 64 hexadecimal digits",
+            ]
+        );
+        assert!(ensure_valid(&diagnostics).is_err());
+
+        let config = json!({
+            "addr": "127.0.0.1:5432",
+            "verbose": "yes",
+            "suppliers": { "local": { "type": "osquery", "mode": "local", "auth": [] } }
+        });
+        let diagnostics = validate_config(&config, "");
+        assert_eq!(
+            described(&diagnostics),
+            vec!["error at `verbose`: expected a boolean, got a string"]
+        );
+    }
+
+    #[test]
+    fn ncl_evaluation_diagnostics() -> UdiPgpResult<()> {
+        let dir = tempfile::tempdir()?;
+        let supplier = dir.path().join("supplier.ncl");
+        fs::write(
+            &supplier,
+            "let laptops = {\n  type = \"osquery\",\n  mode = \"lcal\",\n  auth = [],\n} in laptops\n",
+        )?;
+        assert_eq!(
+            described(&validate_config_file(&supplier)?),
+            vec!["error at `mode`: expected one of local, remote, got \"lcal\""]
+        );
+
+        fs::write(
+            &supplier,
+            "let laptops = {\n  type = \"osquery\",\n  mode = unbound,\n} in laptops\n",
+        )?;
+        let diagnostics = validate_config_file(&supplier)?;
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line, Some(3));
+        assert!(diagnostics[0].message.contains("unbound"));
+
+        let config = dir.path().join("config.json");
+        fs::write(
+            &config,
+            "{\n  \"addr\": \"127.0.0.1:5432\",\n  \"verbose\": yes\n}",
+        )?;
+        let diagnostics = validate_config_file(&config)?;
+        assert_eq!(diagnostics[0].line, Some(3));
+        Ok(())
+    }
+}
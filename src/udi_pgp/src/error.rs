@@ -1,7 +1,7 @@
 use pgwire::error::{ErrorInfo, PgWireError};
 use std::{
     fmt::Display,
-    io::{Error as IOError, ErrorKind},
+    io::Error as IOError,
 };
 use thiserror::Error;
 
@@ -22,14 +22,20 @@ pub enum UdiPgpError {
     SchemaError(String, String),
     #[error("{0}")]
     QueryExecutionError(String),
-    #[error("Invalid SSH connection string: {0}")]
+    #[error("Invalid remote target: {0}")]
     SshConnectionParseError(String),
     #[error(transparent)]
-    SshTunnelError(#[from] crate::ssh::session::SshTunnelError),
+    SshTunnelError(#[from] crate::remote::ssh::session::SshTunnelError),
     #[error(transparent)]
-    SshKeyError(#[from] crate::ssh::key::SshKeyError),
+    SshKeyError(#[from] crate::remote::ssh::key::SshKeyError),
+    /// The target and the error
+    #[error("Failed to execute on {0}: {1}")]
+    RemoteTransportError(String, String),
     #[error("{0}")]
     ConfigError(String),
+    /// The pinned binary of a supplier and why it isn't executed
+    #[error("Refusing to execute {0}: {1}")]
+    UntrustedExecutable(String, String),
     #[error(transparent)]
     ConfigBuilderError(#[from] config::ConfigError),
     #[error(transparent)]
@@ -55,7 +61,7 @@ impl Display for UdiPgpErrorSeverity {
 
 impl From<UdiPgpError> for IOError {
     fn from(e: UdiPgpError) -> Self {
-        IOError::new(ErrorKind::Other, e)
+        IOError::other(e)
     }
 }
 
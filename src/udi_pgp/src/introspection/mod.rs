@@ -15,7 +15,27 @@
 //! ```
 //! - Query observabilty
 //! ```sql
-//! SELECT query_id, query_text, exec_status, exec_msg, elaboration, exec_start_at, exec_finish_at FROM udi_pgp_observe_query_exec; -- Show log entries, at start of surveilr it should be empty
+//! SELECT query_id, query_text, exec_status, exec_msg, elaboration, exec_start_at, exec_finish_at, created_by FROM udi_pgp_observe_query_exec; -- Show log entries, at start of surveilr it should be empty. Only admins see the queries of every user
+//! ```
+//! - Connected clients
+//! ```sql
+//! SELECT client_addr, username, supplier_id, connected_at, queries_executed, last_activity_at FROM udi_pgp_sessions; -- Show who is connected
+//! ```
+//! - Targets missing from the results of a query
+//! ```sql
+//! SELECT query_id, supplier_id, host_id, target, message, created_at FROM udi_pgp_errors; -- Show failed targets
+//! ```
+//! - Tables and columns of the suppliers
+//! ```sql
+//! SELECT supplier_id, table_name, column_name, column_type FROM udi_pgp_schema WHERE table_name LIKE 'process%'; -- Discover what can be queried
+//! ```
+//! - Query cache of the suppliers with a `cache-ttl`
+//! ```sql
+//! SELECT supplier_id, query_text, hits, misses, rows_cached, expires_at FROM udi_pgp_cache; -- Show cache hits and misses
+//! ```
+//! - Permissions of the suppliers' users
+//! ```sql
+//! SELECT supplier_id, username, role, can_write, can_configure, allowed_tables, allowed_queries FROM udi_pgp_grants; -- Show who can query what
 //! ```
 
 use std::{
@@ -40,14 +60,21 @@ use rusqlite::{types::ValueRef, Connection, Error as RusqliteError, Rows, Statem
 use crate::parser::stmt::UdiPgpStatment;
 
 mod error;
+mod schema;
 
 pub use self::error::IntrospectionError;
+pub use self::schema::supplier_columns;
 
 #[derive(Debug)]
 pub enum IntrospectionTable {
     Supplier,
     Config,
     QueryExec,
+    Sessions,
+    Errors,
+    Schema,
+    Cache,
+    Grants,
 }
 
 impl FromStr for IntrospectionTable {
@@ -58,9 +85,14 @@ impl FromStr for IntrospectionTable {
           "udi_pgp_supplier" => Ok(IntrospectionTable::Supplier), 
           "udi_pgp_config" => Ok(IntrospectionTable::Config),
           "udi_pgp_observe_query_exec" => Ok(IntrospectionTable::QueryExec),
+          "udi_pgp_sessions" => Ok(IntrospectionTable::Sessions),
+          "udi_pgp_errors" => Ok(IntrospectionTable::Errors),
+          "udi_pgp_schema" => Ok(IntrospectionTable::Schema),
+          "udi_pgp_cache" => Ok(IntrospectionTable::Cache),
+          "udi_pgp_grants" => Ok(IntrospectionTable::Grants),
             other => {
                 Err(IntrospectionError::TableError(format!(
-                    "Expected one of `udi_pgp_supplier`, `udi_pgp_observe_query_exec`, `udi_pgp_config`. Got: {}",
+                    "Expected one of `udi_pgp_supplier`, `udi_pgp_observe_query_exec`, `udi_pgp_config`, `udi_pgp_sessions`, `udi_pgp_errors`, `udi_pgp_schema`, `udi_pgp_cache`, `udi_pgp_grants`. Got: {}",
                     other
                 )))
             }
@@ -74,6 +106,11 @@ impl Display for IntrospectionTable {
             IntrospectionTable::Supplier => f.write_str("udi_pgp_supplier"),
             IntrospectionTable::Config => f.write_str("udi_pgp_config"),
             IntrospectionTable::QueryExec => f.write_str("udi_pgp_observe_query_exec"),
+            IntrospectionTable::Sessions => f.write_str("udi_pgp_sessions"),
+            IntrospectionTable::Errors => f.write_str("udi_pgp_errors"),
+            IntrospectionTable::Schema => f.write_str("udi_pgp_schema"),
+            IntrospectionTable::Cache => f.write_str("udi_pgp_cache"),
+            IntrospectionTable::Grants => f.write_str("udi_pgp_grants"),
         }
     }
 }
@@ -85,6 +122,8 @@ pub struct IntrospectionBackend {
 impl IntrospectionBackend {
     pub fn new(path: &PathBuf) -> Result<Self, RusqliteError> {
         let conn = Connection::open(path)?;
+        // the state manager writes to the same admin database
+        common::sqlite_helpers::set_busy_timeout(&conn)?;
         Ok(IntrospectionBackend {
             conn: Arc::new(Mutex::new(conn)),
         })
@@ -165,6 +204,16 @@ impl IntrospectionBackend {
         stream::iter(results)
     }
 
+    /// Shadow `udi_pgp_observe_query_exec` with a temporary table of the
+    /// queries `user` executed, for the rest of this connection.
+    pub fn scope_query_log(&self, user: &str) -> Result<(), RusqliteError> {
+        self.conn.lock().unwrap().execute(
+            "CREATE TEMP TABLE udi_pgp_observe_query_exec AS SELECT * FROM main.udi_pgp_observe_query_exec WHERE created_by = ?1",
+            [user],
+        )?;
+        Ok(())
+    }
+
     pub fn do_query<'a>(&self, stmt: &UdiPgpStatment) -> PgWireResult<Vec<Response<'a>>> {
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn
@@ -179,3 +228,91 @@ impl IntrospectionBackend {
             .map_err(|e| PgWireError::ApiError(Box::new(e)))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::{config::UdiPgpConfig, parser::UdiPgpQueryParser, state::StateManager};
+
+    /// The text-encoded fields of a data row
+    fn fields(row: &DataRow) -> Vec<Option<String>> {
+        let mut data = &row.data[..];
+        (0..row.field_count)
+            .map(|_| {
+                let len = i32::from_be_bytes(data[..4].try_into().unwrap());
+                data = &data[4..];
+                (len >= 0).then(|| {
+                    let (field, rest) = data.split_at(len as usize);
+                    data = rest;
+                    String::from_utf8_lossy(field).to_string()
+                })
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn sessions_follow_connected_clients() {
+        let dir = tempfile::tempdir().unwrap();
+        let admin_db = dir.path().join("admin.sqlite.db");
+        std::fs::File::create(&admin_db).unwrap();
+        let config = UdiPgpConfig::new(
+            "127.0.0.1:5432".parse().unwrap(),
+            HashMap::new(),
+            &admin_db.to_string_lossy(),
+        )
+        .unwrap();
+        let state = StateManager::init(&config).unwrap();
+        state.start_session(
+            "10.0.0.1:50000".to_string(),
+            Some("ops".to_string()),
+            Some("fleet".to_string()),
+        );
+        state.start_session("10.0.0.2:50000".to_string(), None, None);
+        state.record_session_query("10.0.0.1:50000".to_string());
+        state.record_session_query("10.0.0.1:50000".to_string());
+        state.start_session("10.0.0.3:50000".to_string(), None, None);
+        state.end_session("10.0.0.3:50000".to_string());
+
+        let backend = IntrospectionBackend::new(&config.admin_state_fs_path).unwrap();
+        let stmt = UdiPgpQueryParser::parse(
+            "SELECT client_addr, username, supplier_id, queries_executed FROM udi_pgp_sessions ORDER BY client_addr",
+            false,
+        )
+        .unwrap();
+        let rows = match backend.do_query(&stmt).unwrap().remove(0) {
+            Response::Query(response) => response.data_rows().collect::<Vec<_>>().await,
+            _ => panic!("expected rows"),
+        };
+        let sessions = rows
+            .iter()
+            .map(|row| fields(row.as_ref().unwrap()))
+            .collect::<Vec<_>>();
+        let text = |s: &str| Some(s.to_string());
+        assert_eq!(
+            sessions,
+            vec![
+                vec![
+                    text("10.0.0.1:50000"),
+                    text("ops"),
+                    text("fleet"),
+                    text("2")
+                ],
+                vec![text("10.0.0.2:50000"), None, None, text("0")],
+            ]
+        );
+
+        // a restarted server forgets the clients of its previous run
+        drop(state);
+        StateManager::init(&config).unwrap();
+        let stmt =
+            UdiPgpQueryParser::parse("SELECT client_addr FROM udi_pgp_sessions", false).unwrap();
+        match backend.do_query(&stmt).unwrap().remove(0) {
+            Response::Query(response) => assert_eq!(response.data_rows().count().await, 0),
+            _ => panic!("expected rows"),
+        }
+    }
+}
@@ -0,0 +1,217 @@
+use std::fs;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{
+    config::Supplier,
+    error::{UdiPgpError, UdiPgpResult},
+};
+
+/// A column of one of a supplier's tables, listed in `udi_pgp_schema`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SupplierColumn {
+    pub table_name: String,
+    pub column_name: String,
+    pub column_type: String,
+    /// Position of the column in its table, from 0
+    pub column_index: usize,
+    pub description: Option<String>,
+    /// e.g. `linux, darwin`
+    pub platforms: Option<String>,
+    /// The osquery schema JSON or ATC file the table is defined in
+    pub source: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OsquerySpecTable {
+    name: String,
+    #[serde(default)]
+    platforms: Vec<String>,
+    #[serde(default)]
+    columns: Vec<OsquerySpecColumn>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OsquerySpecColumn {
+    name: String,
+    description: Option<String>,
+    #[serde(rename = "type", default = "text_type")]
+    column_type: String,
+    #[serde(default)]
+    hidden: bool,
+}
+
+fn text_type() -> String {
+    "text".to_string()
+}
+
+/// The columns of the supplier's tables: those of its osquery schema JSON
+/// followed by those of its ATC files, which osquery serves as text.
+pub fn supplier_columns(supplier: &Supplier) -> UdiPgpResult<Vec<SupplierColumn>> {
+    let mut columns = match &supplier.schema_file_path {
+        Some(path) => osquery_schema_columns(path)?,
+        None => vec![],
+    };
+    for path in supplier.atc_files() {
+        columns.extend(atc_columns(&path)?);
+    }
+    Ok(columns)
+}
+
+/// Hidden columns (e.g. `processes.pid_with_namespace`) are listed too since
+/// they can be selected by name.
+fn osquery_schema_columns(path: &str) -> UdiPgpResult<Vec<SupplierColumn>> {
+    let tables: Vec<OsquerySpecTable> =
+        serde_json::from_str(&fs::read_to_string(path)?).map_err(|err| {
+            UdiPgpError::ConfigError(format!("Invalid osquery schema file {}: {}", path, err))
+        })?;
+    Ok(tables
+        .into_iter()
+        .flat_map(|table| {
+            let platforms = (!table.platforms.is_empty()).then(|| table.platforms.join(", "));
+            table
+                .columns
+                .into_iter()
+                .enumerate()
+                .map(move |(column_index, column)| SupplierColumn {
+                    table_name: table.name.clone(),
+                    column_name: column.name,
+                    column_type: column.column_type,
+                    column_index,
+                    description: match (column.description, column.hidden) {
+                        (Some(description), true) => Some(format!("{description} (hidden)")),
+                        (description, _) => description,
+                    },
+                    platforms: platforms.clone(),
+                    source: path.to_string(),
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect())
+}
+
+fn atc_columns(path: &str) -> UdiPgpResult<Vec<SupplierColumn>> {
+    let config: Value = serde_json::from_str(&fs::read_to_string(path)?)
+        .map_err(|err| UdiPgpError::ConfigError(format!("Invalid ATC file {}: {}", path, err)))?;
+    let Some(tables) = config
+        .get("auto_table_construction")
+        .and_then(Value::as_object)
+    else {
+        return Ok(vec![]);
+    };
+    Ok(tables
+        .iter()
+        .flat_map(|(table_name, definition)| {
+            let platforms = definition
+                .get("platform")
+                .and_then(Value::as_str)
+                .map(str::to_string);
+            definition
+                .get("columns")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_str)
+                .enumerate()
+                .map(move |(column_index, column_name)| SupplierColumn {
+                    table_name: table_name.clone(),
+                    column_name: column_name.to_string(),
+                    column_type: "text".to_string(),
+                    column_index,
+                    description: None,
+                    platforms: platforms.clone(),
+                    source: path.to_string(),
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{config::SupplierType, UdiPgpModes};
+
+    #[test]
+    fn columns_of_schema_and_atc_files() -> UdiPgpResult<()> {
+        let dir = std::env::temp_dir().join(format!("surveilr-schema-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir)?;
+        let schema = dir.join("osquery.json").to_string_lossy().to_string();
+        let atc = dir.join("atc.json").to_string_lossy().to_string();
+        fs::write(
+            &schema,
+            json!([{
+                "name": "processes",
+                "description": "All running processes on the host system.",
+                "platforms": ["darwin", "linux"],
+                "columns": [
+                    { "name": "pid", "description": "Process (or thread) ID", "type": "bigint" },
+                    { "name": "pid_with_namespace", "description": "Pids that contain a namespace", "type": "integer", "hidden": true }
+                ]
+            }])
+            .to_string(),
+        )?;
+        fs::write(
+            &atc,
+            json!({
+                "auto_table_construction": {
+                    "person": {
+                        "query": "SELECT id, name FROM person",
+                        "path": "/tmp/people.sqlite.db",
+                        "columns": ["id", "name"],
+                        "platform": "linux"
+                    }
+                }
+            })
+            .to_string(),
+        )?;
+
+        let supplier = Supplier::new(
+            SupplierType::Osquery,
+            UdiPgpModes::Local,
+            None,
+            Some(atc.clone()),
+            vec![],
+        )
+        .with_schema_file_path(Some(schema.clone()));
+        let columns = supplier_columns(&supplier)?;
+        let names = columns
+            .iter()
+            .map(|c| {
+                (
+                    c.table_name.as_str(),
+                    c.column_name.as_str(),
+                    c.column_type.as_str(),
+                    c.column_index,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                ("processes", "pid", "bigint", 0),
+                ("processes", "pid_with_namespace", "integer", 1),
+                ("person", "id", "text", 0),
+                ("person", "name", "text", 1),
+            ]
+        );
+        assert_eq!(columns[0].platforms.as_deref(), Some("darwin, linux"));
+        assert_eq!(columns[0].source, schema);
+        assert_eq!(
+            columns[1].description.as_deref(),
+            Some("Pids that contain a namespace (hidden)")
+        );
+        assert_eq!(columns[2].platforms.as_deref(), Some("linux"));
+        assert_eq!(columns[3].source, atc);
+
+        fs::write(&schema, "{ not json")?;
+        assert!(supplier_columns(&supplier).is_err());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -15,7 +15,7 @@ use tracing::{error, info};
 
 use crate::processor::UdiPgpProcessor;
 use crate::startup::UdiPgpAuthSource;
-use crate::state::StateManager;
+use crate::state::{messages::Message, StateManager};
 
 mod health;
 mod introspection;
@@ -30,9 +30,10 @@ pub mod auth;
 pub mod config;
 pub mod error;
 pub mod parser;
+pub mod remote;
 pub mod sql_supplier;
-pub mod ssh;
 
+pub use observability::{LogOptions, SharedLogWriter};
 pub use pgwire::api::results::FieldFormat;
 pub use pgwire::api::results::FieldInfo;
 pub use pgwire::api::Type;
@@ -65,21 +66,31 @@ impl Display for UdiPgpModes {
 #[derive(Debug, Clone, new)]
 pub struct Row {
     pub value: String,
+    /// sent as SQL NULL instead of `value`
+    #[new(default)]
+    pub is_null: bool,
+}
+
+impl Row {
+    pub fn null() -> Self {
+        Row {
+            value: String::new(),
+            is_null: true,
+        }
+    }
 }
 
 impl FromStr for Row {
     type Err = UdiPgpError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(Row {
-            value: s.to_string(),
-        })
+        Ok(Row::new(s.to_string()))
     }
 }
 
 impl From<String> for Row {
     fn from(value: String) -> Self {
-        Row { value }
+        Row::new(value)
     }
 }
 
@@ -113,17 +124,18 @@ pub async fn run(config: &UdiPgpConfig, suppliers: SqlSupplierMap) -> anyhow::Re
         });
     }
 
-    observability::init(&tx, config.verbose)?;
+    observability::init(&tx, config.verbose, &config.log)?;
+
+    let factory = FACTORY().lock().await;
+    let processor = UdiPgpProcessor::init(tx.clone(), factory.clone(), suppliers).await?;
 
     let authenticator = Arc::new(UdiPgpStartupHandler::new(
         UdiPgpAuthSource::new(tx.clone()),
         UdiPgpParameters::new(),
         tx.clone(),
+        processor.exec_supplier(),
     ));
 
-    let factory = FACTORY().lock().await;
-    let processor = UdiPgpProcessor::init(tx.clone(), factory.clone(), suppliers).await?;
-
     let mut rx = spawn_shutdown_handler();
     let listener = TcpListener::bind(config.addr()).await?;
 
@@ -136,18 +148,21 @@ pub async fn run(config: &UdiPgpConfig, suppliers: SqlSupplierMap) -> anyhow::Re
             }
 
             incoming_socket = listener.accept() => {
-                let (connection, _) = incoming_socket?;
+                let (connection, client_addr) = incoming_socket?;
                 let authenticator_ref = authenticator.clone();
                 let processor_ref = processor.make();
+                let state_tx = tx.clone();
                 tokio::spawn(async move {
-                    process_socket(
+                    let result = process_socket(
                         connection,
                         None,
                         authenticator_ref,
                         processor_ref.clone(),
                         processor_ref,
                     )
-                    .await
+                    .await;
+                    let _ = state_tx.send(Message::EndSession(client_addr)).await;
+                    result
                 });
             }
         }
@@ -17,6 +17,8 @@ pub struct QueryLogEntry {
     pub exec_finish_at: Option<String>,
     pub elaboration: Elaboration,
     pub exec_msg: Vec<String>,
+    /// The user who executed the query, recorded as the log's `created_by`.
+    pub username: Option<String>,
 }
 
 impl QueryLogEntry {
@@ -29,6 +31,7 @@ impl QueryLogEntry {
             exec_finish_at: None,
             exec_msg: vec![],
             elaboration: Elaboration::default(),
+            username: None,
         }
     }
 }
@@ -68,6 +71,13 @@ impl Write for QueryLogEntry {
 }
 
 impl Visit for QueryLogEntry {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "username" => self.username = Some(value.to_string()),
+            _ => self.record_debug(field, &value),
+        }
+    }
+
     fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
         match field.name() {
             "query_id" => self.query_id = format!("{:?}", value),
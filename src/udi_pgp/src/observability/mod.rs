@@ -1,4 +1,10 @@
-use std::{collections::HashMap, fmt::Debug, fmt::Write};
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    fmt::Write,
+    io,
+    sync::{Arc, Mutex},
+};
 
 use chrono::prelude::*;
 use derive_new::new;
@@ -8,7 +14,7 @@ use tracing::subscriber::set_global_default;
 use tracing::{debug, error, span, Event, Id, Level, Subscriber};
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::registry::LookupSpan;
-use tracing_subscriber::{fmt, EnvFilter, Layer, Registry};
+use tracing_subscriber::{filter::LevelFilter, fmt, EnvFilter, Layer, Registry};
 
 use crate::error::{UdiPgpError, UdiPgpResult};
 use crate::state::messages::{Message, UpdateLogEntry};
@@ -150,11 +156,70 @@ where
     }
 }
 
-pub fn init(state_tx: &mpsc::Sender<Message>, verbose: bool) -> anyhow::Result<()> {
+/// A writer shared by the subscriber's writers, e.g. the `surveilr --log-file`.
+#[derive(Clone)]
+pub struct SharedLogWriter(Arc<Mutex<dyn io::Write + Send>>);
+
+impl SharedLogWriter {
+    pub fn new(writer: impl io::Write + Send + 'static) -> Self {
+        SharedLogWriter(Arc::new(Mutex::new(writer)))
+    }
+}
+
+impl io::Write for SharedLogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().expect("log writer lock poisoned").write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.lock().expect("log writer lock poisoned").flush()
+    }
+}
+
+impl Debug for SharedLogWriter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SharedLogWriter")
+    }
+}
+
+/// How the server's logs are written, from the global `surveilr` logging flags.
+#[derive(Debug, Clone, Default)]
+pub struct LogOptions {
+    /// Only log warnings and errors (queries are still recorded in the query logs)
+    pub quiet: bool,
+    /// Disable ANSI escape codes
+    pub no_color: bool,
+    /// Write the logs here instead of STDERR
+    pub writer: Option<SharedLogWriter>,
+}
+
+pub fn init(
+    state_tx: &mpsc::Sender<Message>,
+    verbose: bool,
+    log: &LogOptions,
+) -> anyhow::Result<()> {
     let level = if verbose { Level::DEBUG } else { Level::INFO };
     let env_filter = EnvFilter::new(level.to_string());
 
-    let fmt_layer = fmt::layer().compact().with_line_number(true);
+    // `quiet` only filters what is printed, the query logs layer still sees every span
+    let printed = if log.quiet {
+        LevelFilter::WARN
+    } else {
+        LevelFilter::from_level(level)
+    };
+    let writer = log.writer.clone();
+    let writer_factory = move || -> Box<dyn io::Write + Send + Sync> {
+        match &writer {
+            Some(writer) => Box::new(writer.clone()),
+            None => Box::new(io::stderr()),
+        }
+    };
+    let fmt_layer = fmt::layer()
+        .compact()
+        .with_line_number(true)
+        .with_ansi(!log.no_color && log.writer.is_none())
+        .with_writer(writer_factory)
+        .with_filter(printed);
 
     let subscriber = Registry::default()
         .with(env_filter)
@@ -1,14 +1,46 @@
 use pgwire::api::Type;
-use sqlparser::ast::{Expr, Query, SelectItem, SetExpr};
+use sqlparser::ast::{
+    Expr, Function, FunctionArg, FunctionArgExpr, GroupByExpr, Query, SelectItem, SetExpr,
+};
 use tracing::instrument;
 
-use crate::parser::stmt::{ColumnMetadata, ExpressionType};
+use crate::parser::stmt::{ColumnMetadata, ExpressionType, FunctionMetadata, OrderByColumn};
 
 #[instrument(ret, level = "debug", fields(query))]
 pub fn get_column_names_from_query(query: &Query) -> Vec<ColumnMetadata> {
     get_column_names_from_set_expression(&query.body)
 }
 
+/// `GROUP BY` expressions of the (first) `SELECT` of the query.
+pub fn get_group_by_from_query(query: &Query) -> Vec<String> {
+    match query.body.as_ref() {
+        SetExpr::Select(select) => match &select.group_by {
+            GroupByExpr::Expressions(exprs) => exprs.iter().map(ToString::to_string).collect(),
+            GroupByExpr::All => vec!["ALL".to_string()],
+        },
+        SetExpr::Query(query) => get_group_by_from_query(query),
+        _ => vec![],
+    }
+}
+
+/// `ORDER BY` columns of the query, named like the keys of the result rows.
+pub fn get_order_by_from_query(query: &Query) -> Vec<OrderByColumn> {
+    query
+        .order_by
+        .iter()
+        .map(|order_by| OrderByColumn {
+            name: match &order_by.expr {
+                Expr::Identifier(ident) => ident.value.to_lowercase(),
+                Expr::CompoundIdentifier(compound) => compound
+                    .last()
+                    .map_or_else(String::new, |ident| ident.value.to_lowercase()),
+                expr => expr.to_string(),
+            },
+            asc: order_by.asc.unwrap_or(true),
+        })
+        .collect()
+}
+
 fn get_column_names_from_set_expression(set_expr: &SetExpr) -> Vec<ColumnMetadata> {
     match set_expr {
         SetExpr::Select(select) => get_column_names_from_projection(&select.projection),
@@ -72,13 +104,10 @@ fn get_column_name_from_expression(expr: &Expr) -> ColumnMetadata {
             }
         }
         Expr::Nested(e) => get_column_name_from_expression(e),
+        // named like SQLite names the result column, e.g. `COUNT(*)`
         Expr::Function(func) => ColumnMetadata {
-            name: func
-                .name
-                .0
-                .first()
-                .map_or_else(String::new, |ident| ident.value.clone()),
-            expr_type: ExpressionType::Function,
+            name: expr.to_string(),
+            expr_type: ExpressionType::Function(get_function_metadata(func)),
             alias: None,
             r#type: Type::VARCHAR,
         },
@@ -94,3 +123,28 @@ fn get_column_name_from_expression(expr: &Expr) -> ColumnMetadata {
         _ => ColumnMetadata::default(), // Default case for unhandled expressions
     }
 }
+
+fn get_function_metadata(func: &Function) -> FunctionMetadata {
+    let args = func
+        .args
+        .iter()
+        .filter_map(|arg| match arg {
+            FunctionArg::Named { arg, .. } | FunctionArg::Unnamed(arg) => match arg {
+                FunctionArgExpr::Wildcard => Some("*".to_string()),
+                FunctionArgExpr::Expr(Expr::Identifier(ident)) => Some(ident.value.to_lowercase()),
+                FunctionArgExpr::Expr(Expr::CompoundIdentifier(compound)) => {
+                    compound.last().map(|ident| ident.value.to_lowercase())
+                }
+                _ => None,
+            },
+        })
+        .collect();
+    FunctionMetadata {
+        name: func
+            .name
+            .0
+            .last()
+            .map_or_else(String::new, |ident| ident.value.to_lowercase()),
+        args,
+    }
+}
@@ -4,19 +4,24 @@ use anyhow::anyhow;
 use async_trait::async_trait;
 use derive_new::new;
 use pgwire::{
-    api::{stmt::QueryParser, Type},
+    api::{portal::Portal, stmt::QueryParser, Type},
     error::{ErrorInfo, PgWireError, PgWireResult},
 };
 use regex::Regex;
-use sqlparser::{ast::Statement, dialect::PostgreSqlDialect, parser::Parser};
+use sqlparser::{
+    ast::{CloseCursor, FetchDirection, Statement, Value},
+    dialect::PostgreSqlDialect,
+    parser::Parser,
+};
 
 use stmt::UdiPgpStatment;
 
 use crate::{error::UdiPgpResult, introspection::IntrospectionTable};
 
-use self::stmt::{ColumnMetadata, StmtType};
+use self::stmt::{ColumnMetadata, CursorCommand, OrderByColumn, StmtType};
 
 mod columns;
+pub mod params;
 pub mod stmt;
 mod tables;
 
@@ -82,17 +87,171 @@ pub struct UdiPgpQueryParser;
 impl UdiPgpQueryParser {
     pub fn parse(query: &str, schema: bool) -> PgWireResult<UdiPgpStatment> {
         let query = Self::remove_sql_comments(query)?;
-        let ast = Self::parse_query_to_ast(&query)?;
+        let (ast, explain) = match Self::parse_query_to_ast(&query)? {
+            Statement::Explain { statement, .. } => (*statement, true),
+            Statement::Declare { name, query, .. } => {
+                // the declared query is answered like any other, its rows are
+                // kept until they're fetched
+                let mut stmt = Self::parse(&query.to_string(), schema)?;
+                stmt.cursor = Some(CursorCommand::Declare(name.value));
+                return Ok(stmt);
+            }
+            ast @ (Statement::Fetch { .. } | Statement::Close { .. }) => {
+                return Self::parse_cursor_statement(query, ast);
+            }
+            ast => (ast, false),
+        };
+        // suppliers receive the explained statement, not the EXPLAIN
+        let query = if explain { ast.to_string() } else { query };
         let config_query = Self::query_is_udi_configuration(&ast);
         let (tables, columns) = Self::determine_tables_and_columns(schema, config_query, &ast)?;
         let introspection_query = Self::is_introspection_query(&tables);
+        let (group_by, order_by) = Self::determine_grouping_and_ordering(&ast);
 
         Ok(UdiPgpStatment {
             tables,
             columns,
+            group_by,
+            order_by,
             query: query.to_string(),
             stmt: ast,
             stmt_type: Self::determine_statement_type(&query, config_query, introspection_query),
+            explain,
+            cursor: None,
+        })
+    }
+
+    /// The portal's statement with the parameters bound to it in place of its
+    /// `$n` placeholders, parsed again so suppliers receive a complete query.
+    pub fn bind(portal: &Portal<UdiPgpStatment>) -> PgWireResult<UdiPgpStatment> {
+        let statement = &portal.statement.statement;
+        if portal.parameter_len() == 0 && params::parameter_count(&statement.query)? == 0 {
+            return Ok(statement.clone());
+        }
+
+        let parameters = (0..portal.parameter_len())
+            .map(|idx| {
+                // clients which let the server infer the types send them as text
+                let pg_type = portal
+                    .statement
+                    .parameter_types
+                    .get(idx)
+                    .cloned()
+                    .unwrap_or(Type::UNKNOWN);
+                let text = if portal.parameter_format.is_binary(idx) {
+                    Self::binary_parameter(portal, idx, &pg_type)?
+                } else {
+                    portal.parameters[idx]
+                        .as_ref()
+                        .map(|bytes| String::from_utf8_lossy(bytes).into_owned())
+                };
+                params::parameter_literal(text.as_deref(), &pg_type)
+            })
+            .collect::<PgWireResult<Vec<_>>>()?;
+
+        let mut bound = Self::parse(
+            &params::bind_parameters(&statement.query, &parameters)?,
+            false,
+        )?;
+        bound.explain = statement.explain;
+        bound.cursor = statement.cursor.clone();
+        Ok(bound)
+    }
+
+    /// The text of a binary parameter, for the types clients commonly send
+    /// in binary.
+    fn binary_parameter(
+        portal: &Portal<UdiPgpStatment>,
+        idx: usize,
+        pg_type: &Type,
+    ) -> PgWireResult<Option<String>> {
+        Ok(match *pg_type {
+            Type::INT2 => portal
+                .parameter::<i16>(idx, pg_type)?
+                .map(|v| v.to_string()),
+            Type::INT4 => portal
+                .parameter::<i32>(idx, pg_type)?
+                .map(|v| v.to_string()),
+            Type::INT8 => portal
+                .parameter::<i64>(idx, pg_type)?
+                .map(|v| v.to_string()),
+            Type::OID => portal
+                .parameter::<u32>(idx, pg_type)?
+                .map(|v| v.to_string()),
+            Type::FLOAT4 => portal
+                .parameter::<f32>(idx, pg_type)?
+                .map(|v| v.to_string()),
+            Type::FLOAT8 => portal
+                .parameter::<f64>(idx, pg_type)?
+                .map(|v| v.to_string()),
+            Type::BOOL => portal
+                .parameter::<bool>(idx, pg_type)?
+                .map(|v| v.to_string()),
+            Type::TEXT | Type::VARCHAR | Type::BPCHAR | Type::NAME | Type::UNKNOWN => {
+                portal.parameter::<String>(idx, pg_type)?
+            }
+            _ => {
+                return Err(PgWireError::UserError(Box::new(ErrorInfo::new(
+                    "ERROR".to_string(),
+                    // feature_not_supported
+                    "0A000".to_string(),
+                    format!(
+                        "Binary parameters of type {} aren't supported, bind ${} as text",
+                        pg_type.name(),
+                        idx + 1
+                    ),
+                ))));
+            }
+        })
+    }
+    fn parse_cursor_statement(query: String, ast: Statement) -> PgWireResult<UdiPgpStatment> {
+        let cursor = match &ast {
+            Statement::Fetch {
+                name, direction, ..
+            } => CursorCommand::Fetch {
+                name: name.value.clone(),
+                count: Self::fetch_count(direction)?,
+            },
+            Statement::Close {
+                cursor: CloseCursor::Specific { name },
+            } => CursorCommand::Close(Some(name.value.clone())),
+            _ => CursorCommand::Close(None),
+        };
+        Ok(UdiPgpStatment {
+            tables: vec![],
+            columns: vec![],
+            group_by: vec![],
+            order_by: vec![],
+            query,
+            stmt: ast,
+            stmt_type: StmtType::Cursor,
+            explain: false,
+            cursor: Some(cursor),
+        })
+    }
+
+    /// How many rows a `FETCH` reads, `None` for all of them. Cursors only move
+    /// forward since the rows are discarded once they're sent.
+    fn fetch_count(direction: &FetchDirection) -> PgWireResult<Option<usize>> {
+        let count = |limit: &Value| match limit {
+            Value::Number(n, _) => n.parse::<usize>().ok().map(Some),
+            _ => None,
+        };
+        match direction {
+            FetchDirection::Next | FetchDirection::Forward { limit: None } => Some(Some(1)),
+            FetchDirection::All | FetchDirection::ForwardAll => Some(None),
+            FetchDirection::Count { limit } | FetchDirection::Forward { limit: Some(limit) } => {
+                count(limit)
+            }
+            _ => None,
+        }
+        .ok_or_else(|| {
+            PgWireError::UserError(Box::new(ErrorInfo::new(
+                "ERROR".to_string(),
+                // feature_not_supported
+                "0A000".to_string(),
+                format!("Cursors only fetch forward, got: FETCH {}", direction),
+            )))
         })
     }
 
@@ -133,6 +292,16 @@ impl UdiPgpQueryParser {
         }
     }
 
+    fn determine_grouping_and_ordering(ast: &Statement) -> (Vec<String>, Vec<OrderByColumn>) {
+        match ast {
+            Statement::Query(q) => (
+                columns::get_group_by_from_query(q),
+                columns::get_order_by_from_query(q),
+            ),
+            _ => (vec![], vec![]),
+        }
+    }
+
     fn is_introspection_query(tables: &[String]) -> bool {
         tables
             .iter()
@@ -192,10 +361,42 @@ impl UdiPgpQueryParser {
                 tables::get_table_names_from_query(q),
                 columns::get_column_names_from_query(q),
             )),
+            Statement::Insert {
+                table_name,
+                columns,
+                ..
+            } => Ok((
+                table_name
+                    .0
+                    .first()
+                    .map(|t| t.value.clone())
+                    .into_iter()
+                    .collect(),
+                columns
+                    .iter()
+                    .map(|c| ColumnMetadata {
+                        name: c.value.clone(),
+                        ..Default::default()
+                    })
+                    .collect(),
+            )),
+            Statement::Update {
+                table, assignments, ..
+            } => Ok((
+                tables::get_table_names_from_table_with_joins(table),
+                assignments
+                    .iter()
+                    .filter_map(|a| a.id.last())
+                    .map(|c| ColumnMetadata {
+                        name: c.value.clone(),
+                        ..Default::default()
+                    })
+                    .collect(),
+            )),
             other => Err(PgWireError::UserError(Box::new(ErrorInfo::new(
                 "WARNING".to_string(),
                 "1111".to_string(),
-                format!("Expected SELECT, got: {}", other),
+                format!("Expected SELECT, INSERT or UPDATE, got: {}", other),
             )))),
         }
     }
@@ -258,6 +459,7 @@ impl UdiPgpQueryParser {
 
         match query {
             q if q.contains("transaction_id") && q.starts_with("select") => false,
+            q if q.contains("udi_pgp_sessions") => false,
             q if q.contains("settings") && !q.contains("pg_catalog") => false,
             q if q.contains("start_time") && q.starts_with("select") => false,
             q if q.contains("commit") && !q.contains("string_agg") && q.starts_with("select") => {
@@ -278,3 +480,169 @@ impl QueryParser for UdiPgpQueryParser {
         Self::parse(sql, false)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stmt::ExpressionType;
+
+    #[test]
+    fn parse_aggregates() {
+        let stmt = UdiPgpQueryParser::parse(
+            "SELECT name, COUNT(*), SUM(resident_size) AS memory FROM processes GROUP BY name ORDER BY memory DESC",
+            false,
+        )
+        .unwrap();
+        assert!(stmt.is_aggregate());
+        assert_eq!(stmt.group_by, vec!["name"]);
+        assert_eq!(
+            stmt.order_by,
+            vec![OrderByColumn {
+                name: "memory".to_string(),
+                asc: false
+            }]
+        );
+
+        let count = &stmt.columns[1];
+        assert_eq!(count.name, "COUNT(*)");
+        assert_eq!(count.alias, None);
+        let sum = &stmt.columns[2];
+        assert_eq!(sum.name, "SUM(resident_size)");
+        assert_eq!(sum.alias.as_deref(), Some("memory"));
+        match &sum.expr_type {
+            ExpressionType::Function(function) => {
+                assert_eq!(function.name, "sum");
+                assert_eq!(function.args, vec!["resident_size"]);
+                assert_eq!(function.result_type(|_| Some(Type::INT8)), Type::INT8);
+            }
+            other => panic!("expected a function, got {}", other),
+        }
+
+        let stmt = UdiPgpQueryParser::parse("SELECT lower(name) FROM processes", false).unwrap();
+        assert!(!stmt.is_aggregate());
+        assert_eq!(stmt.write_operation(), None);
+    }
+
+    #[test]
+    fn parse_writes() {
+        let stmt = UdiPgpQueryParser::parse(
+            "INSERT INTO annotation (uniform_resource_id, note) VALUES ('01HQ', 'reviewed')",
+            false,
+        )
+        .unwrap();
+        assert_eq!(stmt.stmt_type, StmtType::Supplier);
+        assert_eq!(stmt.write_operation(), Some(stmt::WriteOperation::Insert));
+        assert_eq!(stmt.tables, vec!["annotation"]);
+        let columns: Vec<&str> = stmt.columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(columns, vec!["uniform_resource_id", "note"]);
+
+        let stmt = UdiPgpQueryParser::parse(
+            "UPDATE annotation SET note = 'closed' WHERE uniform_resource_id = '01HQ'",
+            false,
+        )
+        .unwrap();
+        assert_eq!(stmt.write_operation(), Some(stmt::WriteOperation::Update));
+        assert_eq!(stmt.tables, vec!["annotation"]);
+        assert_eq!(stmt.columns[0].name, "note");
+
+        assert!(UdiPgpQueryParser::parse("DELETE FROM annotation", false).is_err());
+    }
+
+    #[test]
+    fn parse_explain() {
+        let stmt = UdiPgpQueryParser::parse(
+            "EXPLAIN SELECT name, pid FROM processes WHERE name = 'sshd' ORDER BY pid",
+            false,
+        )
+        .unwrap();
+        assert!(stmt.explain);
+        assert_eq!(stmt.stmt_type, StmtType::Supplier);
+        assert_eq!(
+            stmt.query,
+            "SELECT name, pid FROM processes WHERE name = 'sshd' ORDER BY pid"
+        );
+        assert_eq!(stmt.tables, vec!["processes"]);
+        assert_eq!(stmt.order_by[0].name, "pid");
+
+        let stmt = UdiPgpQueryParser::parse("SELECT name FROM processes", false).unwrap();
+        assert!(!stmt.explain);
+    }
+    #[test]
+    fn parse_cursors() {
+        let stmt = UdiPgpQueryParser::parse(
+            r#"DECLARE "c_1" CURSOR WITHOUT HOLD FOR SELECT name, pid FROM processes"#,
+            false,
+        )
+        .unwrap();
+        assert_eq!(stmt.stmt_type, StmtType::Supplier);
+        assert_eq!(stmt.cursor, Some(CursorCommand::Declare("c_1".to_string())));
+        assert_eq!(stmt.tables, vec!["processes"]);
+        assert_eq!(stmt.query, "SELECT name, pid FROM processes");
+
+        let stmt = UdiPgpQueryParser::parse(r#"FETCH FORWARD 2000 FROM "c_1""#, false).unwrap();
+        assert_eq!(stmt.stmt_type, StmtType::Cursor);
+        assert_eq!(
+            stmt.cursor,
+            Some(CursorCommand::Fetch {
+                name: "c_1".to_string(),
+                count: Some(2000)
+            })
+        );
+        let stmt = UdiPgpQueryParser::parse("FETCH ALL FROM c_1", false).unwrap();
+        assert_eq!(
+            stmt.cursor,
+            Some(CursorCommand::Fetch {
+                name: "c_1".to_string(),
+                count: None
+            })
+        );
+        assert!(UdiPgpQueryParser::parse("FETCH BACKWARD 10 FROM c_1", false).is_err());
+
+        let stmt = UdiPgpQueryParser::parse("CLOSE c_1", false).unwrap();
+        assert_eq!(
+            stmt.cursor,
+            Some(CursorCommand::Close(Some("c_1".to_string())))
+        );
+        let stmt = UdiPgpQueryParser::parse("CLOSE ALL", false).unwrap();
+        assert_eq!(stmt.cursor, Some(CursorCommand::Close(None)));
+    }
+
+    #[test]
+    fn parse_target_errors_introspection() {
+        let stmt = UdiPgpQueryParser::parse(
+            "SELECT query_id, host_id, target, message FROM udi_pgp_errors WHERE query_id = 'b5a1e7d0-3f0e-4b8e-9d2e-0c6e1b9f4a21'",
+            false,
+        )
+        .unwrap();
+        assert_eq!(stmt.stmt_type, StmtType::Introspection);
+    }
+    #[test]
+    fn parse_cache_introspection() {
+        let stmt = UdiPgpQueryParser::parse(
+            "SELECT supplier_id, query_text, hits, misses FROM udi_pgp_cache",
+            false,
+        )
+        .unwrap();
+        assert_eq!(stmt.stmt_type, StmtType::Introspection);
+    }
+
+    #[test]
+    fn parse_grants_introspection() {
+        let stmt = UdiPgpQueryParser::parse(
+            "SELECT username, role, allowed_tables FROM udi_pgp_grants WHERE supplier_id = 'fleet'",
+            false,
+        )
+        .unwrap();
+        assert_eq!(stmt.stmt_type, StmtType::Introspection);
+    }
+
+    #[test]
+    fn parse_schema_introspection() {
+        let stmt = UdiPgpQueryParser::parse(
+            "SELECT * FROM udi_pgp_schema WHERE table_name LIKE 'process%'",
+            false,
+        )
+        .unwrap();
+        assert_eq!(stmt.stmt_type, StmtType::Introspection);
+    }
+}
@@ -0,0 +1,189 @@
+use pgwire::{
+    api::Type,
+    error::{ErrorInfo, PgWireError, PgWireResult},
+};
+use sqlparser::{
+    ast::Value,
+    dialect::PostgreSqlDialect,
+    tokenizer::{Location, Token, Tokenizer},
+};
+
+/// The `$n` placeholders of `query`, with their byte offsets, in order.
+fn placeholders(query: &str) -> PgWireResult<Vec<(usize, String)>> {
+    let dialect = PostgreSqlDialect {};
+    let tokens = Tokenizer::new(&dialect, query)
+        .tokenize_with_location()
+        .map_err(|err| {
+            PgWireError::UserError(Box::new(ErrorInfo::new(
+                "FATAL".to_string(),
+                "PARSER".to_string(),
+                err.to_string(),
+            )))
+        })?;
+    Ok(tokens
+        .into_iter()
+        .filter_map(|token| match token.token {
+            Token::Placeholder(placeholder) if placeholder.starts_with('$') => {
+                byte_offset(query, &token.location).map(|offset| (offset, placeholder))
+            }
+            _ => None,
+        })
+        .collect())
+}
+
+/// The tokenizer counts lines and columns (of characters) from 1.
+fn byte_offset(query: &str, location: &Location) -> Option<usize> {
+    let line_start = match location.line {
+        0 | 1 => 0,
+        line => query.match_indices('\n').nth(line as usize - 2)?.0 + 1,
+    };
+    query[line_start..]
+        .char_indices()
+        .nth(location.column.checked_sub(1)? as usize)
+        .map(|(offset, _)| line_start + offset)
+}
+
+fn placeholder_index(placeholder: &str) -> Option<usize> {
+    placeholder[1..].parse::<usize>().ok()?.checked_sub(1)
+}
+
+/// The number of parameters `query` takes, its highest `$n`.
+pub fn parameter_count(query: &str) -> PgWireResult<usize> {
+    Ok(placeholders(query)?
+        .iter()
+        .filter_map(|(_, placeholder)| placeholder_index(placeholder))
+        .map(|idx| idx + 1)
+        .max()
+        .unwrap_or(0))
+}
+
+/// `query` with each `$n` replaced by the literal of the n-th parameter.
+/// Placeholders inside string literals or quoted identifiers are left alone.
+pub fn bind_parameters(query: &str, parameters: &[Value]) -> PgWireResult<String> {
+    let mut bound = query.to_string();
+    // replaced from the end so the offsets before stay valid
+    for (offset, placeholder) in placeholders(query)?.into_iter().rev() {
+        let value = placeholder_index(&placeholder)
+            .and_then(|idx| parameters.get(idx))
+            .ok_or_else(|| {
+                PgWireError::UserError(Box::new(ErrorInfo::new(
+                    "ERROR".to_string(),
+                    // protocol_violation
+                    "08P01".to_string(),
+                    format!(
+                        "bind message supplies {} parameters, but the statement uses {placeholder}",
+                        parameters.len()
+                    ),
+                )))
+            })?;
+        bound.replace_range(offset..offset + placeholder.len(), &bound_literal(value));
+    }
+    Ok(bound)
+}
+
+/// The text a parameter is substituted with. Negative numbers are parenthesized
+/// so that `-$1` bound to `-5` doesn't turn into the comment `--5`.
+fn bound_literal(value: &Value) -> String {
+    match value {
+        Value::Number(number, _) if number.starts_with('-') => format!("({value})"),
+        _ => value.to_string(),
+    }
+}
+
+/// The SQL literal of a text-format parameter of type `pg_type`. Numbers and
+/// booleans are written as such when they're declared so, everything else is
+/// quoted: the supplier compares it to its columns as PostgreSQL would an
+/// `unknown` literal.
+pub fn parameter_literal(text: Option<&str>, pg_type: &Type) -> PgWireResult<Value> {
+    let Some(text) = text else {
+        return Ok(Value::Null);
+    };
+    let invalid = || {
+        PgWireError::UserError(Box::new(ErrorInfo::new(
+            "ERROR".to_string(),
+            // invalid_text_representation
+            "22P02".to_string(),
+            format!(
+                "invalid input syntax for type {}: \"{text}\"",
+                pg_type.name()
+            ),
+        )))
+    };
+    Ok(match *pg_type {
+        Type::INT2 | Type::INT4 | Type::INT8 | Type::OID => Value::Number(
+            text.trim()
+                .parse::<i64>()
+                .map_err(|_| invalid())?
+                .to_string(),
+            false,
+        ),
+        Type::FLOAT4 | Type::FLOAT8 | Type::NUMERIC => {
+            let number = text.trim();
+            if !number.parse::<f64>().is_ok_and(f64::is_finite) {
+                return Err(invalid());
+            }
+            Value::Number(number.to_string(), false)
+        }
+        Type::BOOL => match text.trim().to_lowercase().as_str() {
+            "t" | "true" | "y" | "yes" | "on" | "1" => Value::Boolean(true),
+            "f" | "false" | "n" | "no" | "off" | "0" => Value::Boolean(false),
+            _ => return Err(invalid()),
+        },
+        _ => Value::SingleQuotedString(text.to_string()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bind_placeholders() {
+        let query = "SELECT name, '$1' AS \"$2\" FROM processes\nWHERE pid = $1 AND name <> $2";
+        assert_eq!(parameter_count(query).unwrap(), 2);
+        assert_eq!(
+            bind_parameters(
+                query,
+                &[
+                    parameter_literal(Some("42"), &Type::INT4).unwrap(),
+                    parameter_literal(Some("O'Brien"), &Type::UNKNOWN).unwrap(),
+                ]
+            )
+            .unwrap(),
+            "SELECT name, '$1' AS \"$2\" FROM processes\nWHERE pid = 42 AND name <> 'O''Brien'"
+        );
+        assert!(bind_parameters(query, &[Value::Null]).is_err());
+        assert_eq!(parameter_count("SELECT * FROM users").unwrap(), 0);
+    }
+
+    #[test]
+    fn bind_negative_numbers() {
+        let query =
+            "SELECT * FROM processes WHERE pid = -$1 AND parent = $1 AND load > $2 LIMIT 10";
+        assert_eq!(
+            bind_parameters(
+                query,
+                &[
+                    parameter_literal(Some("-5"), &Type::INT4).unwrap(),
+                    parameter_literal(Some("-1.5"), &Type::FLOAT8).unwrap(),
+                ]
+            )
+            .unwrap(),
+            "SELECT * FROM processes WHERE pid = -(-5) AND parent = (-5) AND load > (-1.5) LIMIT 10"
+        );
+    }
+
+    #[test]
+    fn typed_literals() {
+        assert_eq!(
+            parameter_literal(Some("on"), &Type::BOOL).unwrap(),
+            Value::Boolean(true)
+        );
+        assert_eq!(
+            parameter_literal(Some("1.5"), &Type::FLOAT8).unwrap(),
+            Value::Number("1.5".to_string(), false)
+        );
+        assert_eq!(parameter_literal(None, &Type::INT8).unwrap(), Value::Null);
+        assert!(parameter_literal(Some("1; DROP TABLE users"), &Type::INT8).is_err());
+    }
+}
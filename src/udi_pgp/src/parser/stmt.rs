@@ -1,8 +1,8 @@
-use std::fmt::Display;
+use std::{collections::BTreeSet, fmt::Display, ops::ControlFlow};
 
 use derive_new::new;
-use pgwire::api::Type;
-use sqlparser::ast::{ColumnDef, DataType, Statement};
+use pgwire::api::{results::Tag, Type};
+use sqlparser::ast::{visit_relations, ColumnDef, DataType, Statement};
 
 use crate::error::UdiPgpError;
 
@@ -50,6 +50,50 @@ impl Display for ColumnMetadata {
     }
 }
 
+/// SQLite/osquery aggregate functions, a query selecting one is answered by the
+/// supplier with the aggregated rows.
+const AGGREGATE_FUNCTIONS: [&str; 7] =
+    ["count", "sum", "total", "avg", "min", "max", "group_concat"];
+
+/// A function call in a query's projection, e.g. `SUM(resident_size)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionMetadata {
+    /// Lowercased name of the function.
+    pub name: String,
+    /// Lowercased names of the columns passed as arguments, `*` for `COUNT(*)`.
+    pub args: Vec<String>,
+}
+
+impl FunctionMetadata {
+    pub fn is_aggregate(&self) -> bool {
+        AGGREGATE_FUNCTIONS.contains(&self.name.as_str())
+    }
+
+    /// Type of the function's result, `arg_type` resolves the type of an
+    /// argument column. Functions whose result type isn't known are text.
+    pub fn result_type(&self, arg_type: impl Fn(&str) -> Option<Type>) -> Type {
+        let first_arg_type = || self.args.first().and_then(|arg| arg_type(arg));
+        match self.name.as_str() {
+            "count" => Type::INT8,
+            "avg" | "total" => Type::FLOAT8,
+            "sum" => match first_arg_type() {
+                Some(t) if t == Type::INT4 || t == Type::INT8 => Type::INT8,
+                _ => Type::FLOAT8,
+            },
+            "min" | "max" => first_arg_type().unwrap_or(Type::VARCHAR),
+            _ => Type::VARCHAR,
+        }
+    }
+}
+
+/// A column of a query's `ORDER BY` clause.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderByColumn {
+    /// Name of the ordering column as it appears in the result rows.
+    pub name: String,
+    pub asc: bool,
+}
+
 /// Enum representing the types of expressions a column in a SQL query can have.
 /// Corresponding directly to the types in the statement
 #[derive(Debug, Clone, PartialEq)]
@@ -59,7 +103,7 @@ pub enum ExpressionType {
     /// A column resulting from a binary operation.
     Binary,
     /// A column derived from a function.
-    Function,
+    Function(FunctionMetadata),
     /// A compound expression, potentially involving multiple operations or functions.
     Compound,
     /// A wildcard expression, representing multiple or all columns.
@@ -70,7 +114,7 @@ impl Display for ExpressionType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ExpressionType::Binary => f.write_str("binary"),
-            ExpressionType::Function => f.write_str("binary"),
+            ExpressionType::Function(_) => f.write_str("function"),
             ExpressionType::Compound => f.write_str("compound"),
             ExpressionType::Standard => f.write_str("standard"),
             ExpressionType::Wildcard => f.write_str("wildcard"),
@@ -89,6 +133,46 @@ pub enum StmtType {
     Introspection,
     /// Standard queries to suppliers
     Supplier,
+    /// `FETCH` from or `CLOSE` a cursor opened with `DECLARE`
+    Cursor,
+}
+
+/// A cursor statement, clients such as psycopg's server-side cursors read large
+/// results in batches with them instead of receiving every row at once.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CursorCommand {
+    /// `DECLARE name CURSOR FOR query`, the statement is the declared query.
+    Declare(String),
+    /// `FETCH [FORWARD] count FROM name`, `None` fetches all the remaining rows.
+    Fetch { name: String, count: Option<usize> },
+    /// `CLOSE name`, `None` for `CLOSE ALL`.
+    Close(Option<String>),
+}
+
+/// A statement which changes a supplier's data, suppliers opt into each one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteOperation {
+    Insert,
+    Update,
+}
+
+impl WriteOperation {
+    /// The command tag returned to the client once `rows` were written.
+    pub fn tag(&self, rows: usize) -> Tag {
+        match self {
+            WriteOperation::Insert => Tag::new("INSERT").with_oid(0).with_rows(rows),
+            WriteOperation::Update => Tag::new("UPDATE").with_rows(rows),
+        }
+    }
+}
+
+impl Display for WriteOperation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WriteOperation::Insert => f.write_str("INSERT"),
+            WriteOperation::Update => f.write_str("UPDATE"),
+        }
+    }
 }
 
 /// Represents the metadata of a parsed SQL query, including details about the tables and columns involved.
@@ -98,9 +182,70 @@ pub struct UdiPgpStatment {
     pub tables: Vec<String>,
     /// Metadata about the columns involved in the query.
     pub columns: Vec<ColumnMetadata>,
+    /// Expressions of the `GROUP BY` clause.
+    pub group_by: Vec<String>,
+    /// Columns of the `ORDER BY` clause.
+    pub order_by: Vec<OrderByColumn>,
     pub query: String,
     pub stmt: Statement,
     pub stmt_type: StmtType,
+    /// The query was prefixed with `EXPLAIN`: the supplier describes how it
+    /// would answer `stmt` instead of executing it.
+    pub explain: bool,
+    /// The query declares, fetches from or closes a cursor.
+    pub cursor: Option<CursorCommand>,
+}
+
+impl UdiPgpStatment {
+    /// Whether the query aggregates rows (an aggregate function or `GROUP BY`),
+    /// the aggregation is pushed down to the supplier along with the query.
+    pub fn is_aggregate(&self) -> bool {
+        !self.group_by.is_empty()
+            || self.columns.iter().any(|col| match &col.expr_type {
+                ExpressionType::Function(function) => function.is_aggregate(),
+                _ => false,
+            })
+    }
+
+    /// The write the query makes, `None` for `SELECT`s.
+    pub fn write_operation(&self) -> Option<WriteOperation> {
+        match self.stmt {
+            Statement::Insert { .. } => Some(WriteOperation::Insert),
+            Statement::Update { .. } => Some(WriteOperation::Update),
+            _ => None,
+        }
+    }
+
+    /// Lowercased names of every table the statement reads or writes, the
+    /// ones of subqueries and function arguments included, unlike `tables`
+    /// which only has the tables routed to suppliers. Schema-qualified tables
+    /// keep their schema (`main.processes`). Only the names of the top-level
+    /// `WITH` are left out: a nested CTE may shadow a real table outside it.
+    pub fn relations(&self) -> Vec<String> {
+        let ctes: BTreeSet<String> = match &self.stmt {
+            Statement::Query(query) => query
+                .with
+                .iter()
+                .flat_map(|with| &with.cte_tables)
+                .map(|cte| cte.alias.name.value.to_lowercase())
+                .collect(),
+            _ => BTreeSet::new(),
+        };
+        let mut relations = BTreeSet::new();
+        let _ = visit_relations(&self.stmt, |relation| {
+            let name = relation
+                .0
+                .iter()
+                .map(|ident| ident.value.to_lowercase())
+                .collect::<Vec<_>>()
+                .join(".");
+            if !ctes.contains(&name) {
+                relations.insert(name);
+            }
+            ControlFlow::<()>::Continue(())
+        });
+        relations.into_iter().collect()
+    }
 }
 
 impl TryFrom<ColumnDef> for ColumnMetadata {
@@ -114,7 +259,7 @@ impl TryFrom<ColumnDef> for ColumnMetadata {
             DataType::Text => Type::VARCHAR,
             DataType::Binary(_) => Type::BYTEA,
             DataType::Integer(_) => Type::INT4,
-            DataType::Double => Type::INT8,
+            DataType::Double => Type::FLOAT8,
             _ => {
                 return Err(UdiPgpError::TypeConversionError(
                     value.data_type.to_string(),
@@ -143,7 +288,7 @@ impl TryFrom<&ColumnDef> for ColumnMetadata {
             DataType::Text => Type::VARCHAR,
             DataType::Binary(_) => Type::BYTEA,
             DataType::Integer(_) => Type::INT4,
-            DataType::Double => Type::INT8,
+            DataType::Double => Type::FLOAT8,
             _ => {
                 return Err(UdiPgpError::TypeConversionError(
                     value.data_type.to_string(),
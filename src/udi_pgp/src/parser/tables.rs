@@ -1,6 +1,6 @@
 use std::collections::HashSet;
 
-use sqlparser::ast::{Expr, Query, SelectItem, SetExpr, TableFactor};
+use sqlparser::ast::{Expr, Query, SelectItem, SetExpr, TableFactor, TableWithJoins};
 use tracing::instrument;
 
 #[instrument(ret, level = "debug", fields(query))]
@@ -108,6 +108,15 @@ fn get_table_names_from_set_expression(expression: &SetExpr) -> Vec<String> {
     }
 }
 
+/// Tables of the `UPDATE <table>` target (including joined ones).
+pub fn get_table_names_from_table_with_joins(table: &TableWithJoins) -> Vec<String> {
+    let mut table_names = get_table_names_from_table_factor(table.relation.clone());
+    for join in &table.joins {
+        table_names.extend(get_table_names_from_table_factor(join.relation.clone()));
+    }
+    table_names
+}
+
 fn get_table_names_from_table_factor(f: TableFactor) -> Vec<String> {
     match f {
         TableFactor::Table { name, args, .. } => {
@@ -0,0 +1,266 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::{parser::stmt::UdiPgpStatment, FieldInfo, Row};
+
+/// Most results held at once, the least recently used are evicted first.
+pub const DEFAULT_MAX_CACHE_ENTRIES: usize = 1024;
+/// Most bytes of row values held at once, larger results aren't cached.
+pub const DEFAULT_MAX_CACHE_BYTES: usize = 64 * 1024 * 1024;
+
+/// The result of a supplier's query, answered again from memory until it
+/// expires.
+#[derive(Debug, Clone)]
+pub struct CachedResult {
+    pub schema: Vec<FieldInfo>,
+    pub rows: Vec<Vec<Row>>,
+}
+
+impl CachedResult {
+    /// Roughly the memory the result's rows take.
+    fn size_bytes(&self) -> usize {
+        self.rows
+            .iter()
+            .flatten()
+            .map(|row| row.value.len() + std::mem::size_of::<Row>())
+            .sum()
+    }
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    result: CachedResult,
+    size_bytes: usize,
+    expires_at: Instant,
+    last_used_at: Instant,
+}
+
+#[derive(Debug, Default)]
+struct CacheEntries {
+    entries: HashMap<(String, String), CacheEntry>,
+    size_bytes: usize,
+}
+
+impl CacheEntries {
+    fn remove(&mut self, key: &(String, String)) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.size_bytes -= entry.size_bytes;
+        }
+    }
+
+    fn retain(&mut self, mut keep: impl FnMut(&(String, String), &CacheEntry) -> bool) {
+        let removed: Vec<_> = self
+            .entries
+            .iter()
+            .filter(|(key, entry)| !keep(key, entry))
+            .map(|(key, _)| key.clone())
+            .collect();
+        removed.iter().for_each(|key| self.remove(key));
+    }
+}
+
+/// Results of the suppliers' queries keyed by the supplier and the normalized
+/// query, shared by the processors of every connection. At most `max_entries`
+/// results and `max_bytes` of rows are held.
+#[derive(Debug, Clone)]
+pub struct QueryCache {
+    entries: Arc<Mutex<CacheEntries>>,
+    max_entries: usize,
+    max_bytes: usize,
+}
+
+impl Default for QueryCache {
+    fn default() -> Self {
+        QueryCache::with_limits(DEFAULT_MAX_CACHE_ENTRIES, DEFAULT_MAX_CACHE_BYTES)
+    }
+}
+
+impl QueryCache {
+    pub fn with_limits(max_entries: usize, max_bytes: usize) -> Self {
+        QueryCache {
+            entries: Arc::default(),
+            max_entries,
+            max_bytes,
+        }
+    }
+
+    /// The query as it's keyed: the statement printed back from its AST, so
+    /// differences in whitespace and keyword case don't miss the cache.
+    pub fn normalize(statement: &UdiPgpStatment) -> String {
+        statement.stmt.to_string()
+    }
+
+    /// The result of the query if it's cached and hasn't expired.
+    pub fn get(&self, supplier_id: &str, query: &str) -> Option<CachedResult> {
+        let mut entries = self.entries.lock().unwrap();
+        let key = (supplier_id.to_string(), query.to_string());
+        let now = Instant::now();
+        match entries.entries.get_mut(&key) {
+            Some(entry) if entry.expires_at > now => {
+                entry.last_used_at = now;
+                Some(entry.result.clone())
+            }
+            Some(_) => {
+                entries.remove(&key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Cache the result, evicting expired and then the least recently used
+    /// results to stay within the limits. Returns whether it was cached, a
+    /// result larger than `max_bytes` isn't.
+    pub fn insert(
+        &self,
+        supplier_id: &str,
+        query: &str,
+        result: CachedResult,
+        ttl: Duration,
+    ) -> bool {
+        let size_bytes = result.size_bytes();
+        if size_bytes > self.max_bytes || self.max_entries == 0 {
+            return false;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        let now = Instant::now();
+        let key = (supplier_id.to_string(), query.to_string());
+        entries.remove(&key);
+        entries.retain(|_, entry| entry.expires_at > now);
+        while entries.entries.len() >= self.max_entries
+            || entries.size_bytes + size_bytes > self.max_bytes
+        {
+            let Some(lru) = entries
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used_at)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            entries.remove(&lru);
+        }
+        entries.size_bytes += size_bytes;
+        entries.entries.insert(
+            key,
+            CacheEntry {
+                result,
+                size_bytes,
+                expires_at: now + ttl,
+                last_used_at: now,
+            },
+        );
+        true
+    }
+
+    /// Drop the supplier's cached results, e.g. once it was written to.
+    pub fn clear_supplier(&self, supplier_id: &str) {
+        self.entries
+            .lock()
+            .unwrap()
+            .retain(|(supplier, _), _| supplier != supplier_id);
+    }
+
+    /// Drop every cached result, e.g. when a supplier's configuration changed.
+    pub fn clear(&self) {
+        *self.entries.lock().unwrap() = CacheEntries::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::UdiPgpQueryParser, FieldFormat, Type};
+
+    fn result(value: &str) -> CachedResult {
+        CachedResult {
+            schema: vec![FieldInfo::new(
+                "name".to_string(),
+                None,
+                None,
+                Type::VARCHAR,
+                FieldFormat::Text,
+            )],
+            rows: vec![vec![Row::from(value.to_string())]],
+        }
+    }
+
+    #[test]
+    fn normalized_queries_share_entries() {
+        let query =
+            |sql: &str| QueryCache::normalize(&UdiPgpQueryParser::parse(sql, false).unwrap());
+        assert_eq!(
+            query("SELECT * FROM users"),
+            query("select *\n  from   users")
+        );
+        assert_ne!(
+            query("SELECT * FROM users"),
+            query("SELECT * FROM users WHERE uid = 0")
+        );
+    }
+
+    #[test]
+    fn entries_expire() {
+        let cache = QueryCache::default();
+        cache.insert(
+            "fleet",
+            "SELECT * FROM users",
+            result("root"),
+            Duration::from_secs(60),
+        );
+        cache.insert(
+            "laptops",
+            "SELECT * FROM users",
+            result("john"),
+            Duration::ZERO,
+        );
+
+        let cached = cache.get("fleet", "SELECT * FROM users").unwrap();
+        assert_eq!(cached.rows[0][0].value, "root");
+        assert!(cache.get("laptops", "SELECT * FROM users").is_none());
+        assert!(cache.get("fleet", "SELECT * FROM groups").is_none());
+
+        cache.clear();
+        assert!(cache.get("fleet", "SELECT * FROM users").is_none());
+    }
+
+    #[test]
+    fn entries_are_bounded() {
+        let ttl = Duration::from_secs(60);
+        let cache = QueryCache::with_limits(2, 1024);
+        assert!(cache.insert("fleet", "SELECT 1", result("a"), ttl));
+        assert!(cache.insert("fleet", "SELECT 2", result("b"), ttl));
+        // SELECT 1 was used more recently than SELECT 2, which is evicted
+        assert!(cache.get("fleet", "SELECT 1").is_some());
+        assert!(cache.insert("fleet", "SELECT 3", result("c"), ttl));
+        assert!(cache.get("fleet", "SELECT 1").is_some());
+        assert!(cache.get("fleet", "SELECT 2").is_none());
+        assert!(cache.get("fleet", "SELECT 3").is_some());
+
+        // a result larger than the cache isn't cached, and doesn't evict anything
+        assert!(!cache.insert("fleet", "SELECT 4", result(&"x".repeat(1024)), ttl));
+        assert!(cache.get("fleet", "SELECT 4").is_none());
+        assert!(cache.get("fleet", "SELECT 1").is_some());
+
+        // results are evicted to make room for a large one
+        let large = result(&"x".repeat(1024 - 2 * std::mem::size_of::<Row>()));
+        assert!(cache.insert("fleet", "SELECT 5", large, ttl));
+        assert!(cache.get("fleet", "SELECT 5").is_some());
+        assert!(cache.get("fleet", "SELECT 1").is_none());
+        assert!(cache.get("fleet", "SELECT 3").is_none());
+    }
+
+    #[test]
+    fn writes_clear_the_suppliers_entries() {
+        let ttl = Duration::from_secs(60);
+        let cache = QueryCache::default();
+        cache.insert("fleet", "SELECT * FROM users", result("root"), ttl);
+        cache.insert("laptops", "SELECT * FROM users", result("john"), ttl);
+        cache.clear_supplier("fleet");
+        assert!(cache.get("fleet", "SELECT * FROM users").is_none());
+        assert!(cache.get("laptops", "SELECT * FROM users").is_some());
+    }
+}
@@ -0,0 +1,251 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    pin::Pin,
+    sync::{Arc, Mutex},
+};
+
+use futures::{
+    stream::{self, BoxStream, Peekable},
+    StreamExt,
+};
+use pgwire::{
+    api::results::{FieldInfo, QueryResponse, Response, Tag},
+    error::{ErrorInfo, PgWireError, PgWireResult},
+    messages::data::DataRow,
+};
+
+/// The rows of a query which weren't sent yet, BI tools and server-side
+/// cursors read them in batches with `Execute` row limits or `FETCH`. They're
+/// only read from the supplier as they're fetched.
+pub struct Cursor {
+    pub schema: Arc<Vec<FieldInfo>>,
+    rows: Peekable<BoxStream<'static, PgWireResult<DataRow>>>,
+}
+
+impl Cursor {
+    pub fn from_response(response: QueryResponse<'static>) -> Cursor {
+        Cursor {
+            schema: response.row_schema(),
+            rows: response.data_rows().boxed().peekable(),
+        }
+    }
+
+    pub async fn next(&mut self) -> Option<PgWireResult<DataRow>> {
+        self.rows.next().await
+    }
+
+    /// Take the next `max_rows` rows, all of the remaining ones when it's 0.
+    pub async fn fetch(&mut self, max_rows: usize) -> PgWireResult<Vec<DataRow>> {
+        let mut rows = Vec::new();
+        while max_rows == 0 || rows.len() < max_rows {
+            match self.next().await {
+                Some(row) => rows.push(row?),
+                None => break,
+            }
+        }
+        Ok(rows)
+    }
+
+    pub async fn is_exhausted(&mut self) -> bool {
+        Pin::new(&mut self.rows).peek().await.is_none()
+    }
+
+    /// A response with the next `max_rows` rows, all of the remaining ones
+    /// (read as they're sent) when it's 0.
+    pub async fn fetch_response(&mut self, max_rows: usize) -> PgWireResult<Response<'static>> {
+        if max_rows == 0 {
+            let rows = std::mem::replace(&mut self.rows, stream::empty().boxed().peekable());
+            return Ok(Response::Query(QueryResponse::new(
+                self.schema.clone(),
+                rows,
+            )));
+        }
+        let rows = self.fetch(max_rows).await?;
+        Ok(Response::Query(QueryResponse::new(
+            self.schema.clone(),
+            stream::iter(rows.into_iter().map(Ok)),
+        )))
+    }
+}
+
+impl fmt::Debug for Cursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cursor")
+            .field("schema", &self.schema)
+            .finish_non_exhaustive()
+    }
+}
+
+/// What executing a portal produced, kept from its `Describe` to its `Execute`
+/// and, for rows, across `Execute`s until they're all sent.
+#[derive(Debug)]
+pub enum PortalResult {
+    Rows(Cursor),
+    Execution(Tag),
+    Empty,
+}
+
+/// The suspended portals and `DECLARE`d cursors of a connection.
+#[derive(Debug, Default)]
+pub struct Cursors {
+    portals: Mutex<HashMap<String, PortalResult>>,
+    declared: Mutex<HashMap<String, Cursor>>,
+}
+
+impl Cursors {
+    pub fn take_portal(&self, name: &str) -> Option<PortalResult> {
+        self.portals
+            .lock()
+            .expect("portals lock poisoned")
+            .remove(name)
+    }
+
+    pub fn put_portal(&self, name: &str, result: PortalResult) {
+        self.portals
+            .lock()
+            .expect("portals lock poisoned")
+            .insert(name.to_string(), result);
+    }
+
+    /// Declare (or redeclare) the cursor `name` over `cursor`'s rows.
+    pub fn declare(&self, name: &str, cursor: Cursor) {
+        self.declared
+            .lock()
+            .expect("cursors lock poisoned")
+            .insert(name.to_string(), cursor);
+    }
+
+    /// The next `count` rows of the cursor `name`, all of them for `None`. An
+    /// exhausted cursor answers with no rows, which is how clients stop.
+    pub async fn fetch(&self, name: &str, count: Option<usize>) -> PgWireResult<Response<'static>> {
+        // the cursor is taken out while its rows are read, the lock can't be
+        // held across them
+        let mut cursor = self
+            .declared
+            .lock()
+            .expect("cursors lock poisoned")
+            .remove(name)
+            .ok_or_else(|| {
+                PgWireError::UserError(Box::new(ErrorInfo::new(
+                    "ERROR".to_string(),
+                    // invalid_cursor_name
+                    "34000".to_string(),
+                    format!("cursor \"{name}\" does not exist"),
+                )))
+            })?;
+        let response = match count {
+            // `FETCH 0` doesn't move the cursor
+            Some(0) => Ok(Response::Query(QueryResponse::new(
+                cursor.schema.clone(),
+                stream::empty(),
+            ))),
+            Some(count) => cursor.fetch_response(count).await,
+            None => cursor.fetch_response(0).await,
+        };
+        self.declared
+            .lock()
+            .expect("cursors lock poisoned")
+            .insert(name.to_string(), cursor);
+        response
+    }
+
+    /// Close the cursor `name`, all of them for `None`.
+    pub fn close(&self, name: Option<&str>) {
+        let mut declared = self.declared.lock().expect("cursors lock poisoned");
+        match name {
+            Some(name) => {
+                declared.remove(name);
+            }
+            None => declared.clear(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pgwire::api::{
+        results::{DataRowEncoder, FieldFormat},
+        Type,
+    };
+
+    use super::*;
+
+    async fn cursor(rows: usize) -> Cursor {
+        let schema = Arc::new(vec![FieldInfo::new(
+            "pid".to_string(),
+            None,
+            None,
+            Type::INT8,
+            FieldFormat::Text,
+        )]);
+        let rows = (0..rows)
+            .map(|pid| {
+                let mut encoder = DataRowEncoder::new(schema.clone());
+                encoder.encode_field(&(pid as i64))?;
+                encoder.finish()
+            })
+            .collect::<Vec<_>>();
+        Cursor::from_response(QueryResponse::new(schema, stream::iter(rows)))
+    }
+
+    async fn fetched(cursors: &Cursors, count: Option<usize>) -> usize {
+        match cursors.fetch("c", count).await.unwrap() {
+            Response::Query(response) => response.data_rows().count().await,
+            _ => panic!("expected rows"),
+        }
+    }
+
+    #[tokio::test]
+    async fn fetch_in_batches() {
+        let mut portal = cursor(5).await;
+        assert_eq!(portal.fetch(2).await.unwrap().len(), 2);
+        assert!(!portal.is_exhausted().await);
+        assert_eq!(portal.fetch(2).await.unwrap().len(), 2);
+        assert_eq!(portal.fetch(2).await.unwrap().len(), 1);
+        assert!(portal.is_exhausted().await);
+
+        let mut portal = cursor(3).await;
+        assert_eq!(portal.fetch(0).await.unwrap().len(), 3);
+        assert!(portal.is_exhausted().await);
+
+        let cursors = Cursors::default();
+        cursors.declare("c", cursor(5).await);
+        assert_eq!(fetched(&cursors, Some(1)).await, 1);
+        assert_eq!(fetched(&cursors, Some(0)).await, 0);
+        assert_eq!(fetched(&cursors, None).await, 4);
+        assert_eq!(fetched(&cursors, Some(10)).await, 0);
+
+        cursors.close(Some("c"));
+        assert!(cursors.fetch("c", None).await.is_err());
+        cursors.declare("c", cursor(1).await);
+        cursors.close(None);
+        assert!(cursors.fetch("c", None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn rows_are_read_as_fetched() {
+        let read = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = read.clone();
+        let schema = Arc::new(vec![FieldInfo::new(
+            "pid".to_string(),
+            None,
+            None,
+            Type::INT8,
+            FieldFormat::Text,
+        )]);
+        let row_schema = schema.clone();
+        let rows = stream::iter(0..1_000_000i64).map(move |pid| {
+            counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let mut encoder = DataRowEncoder::new(row_schema.clone());
+            encoder.encode_field(&pid)?;
+            encoder.finish()
+        });
+
+        let mut portal = Cursor::from_response(QueryResponse::new(schema, rows));
+        assert_eq!(portal.fetch(10).await.unwrap().len(), 10);
+        assert!(!portal.is_exhausted().await);
+        // the peeked row is the only one read ahead
+        assert_eq!(read.load(std::sync::atomic::Ordering::SeqCst), 11);
+    }
+}
@@ -1,6 +1,12 @@
-use std::{net::SocketAddr, path::PathBuf, pin::Pin, str::FromStr, sync::Arc};
+use std::{
+    net::SocketAddr,
+    path::PathBuf,
+    pin::Pin,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
 
-use futures::{stream, Stream};
+use futures::{future, stream, stream::BoxStream, Stream, StreamExt};
 use pgwire::{
     api::{
         results::{DataRowEncoder, FieldInfo, QueryResponse, Response, Tag},
@@ -14,6 +20,7 @@ use tokio::sync::{mpsc, oneshot, RwLock};
 use tracing::{debug, error};
 use uuid::Uuid;
 
+use self::{cache::QueryCache, cursors::Cursors};
 use crate::{
     config::UdiPgpConfig,
     error::{UdiPgpError, UdiPgpResult},
@@ -25,12 +32,14 @@ use crate::{
     },
     sql_supplier::{
         admin::{AdminSupplier, UdiPgpSupplierFactory},
-        SqlSupplierMap,
+        RowStream, SqlSupplierMap, TargetError,
     },
     state::messages::Message,
     Row,
 };
 
+pub mod cache;
+pub mod cursors;
 pub mod query_handler;
 
 #[derive(Debug)]
@@ -40,6 +49,13 @@ pub struct UdiPgpProcessor {
     exec_supplier: Arc<RwLock<AdminSupplier>>,
     health_shutdown: Arc<Option<oneshot::Sender<()>>>,
     metrics_shutdown: Arc<Option<oneshot::Sender<()>>>,
+    // each connection gets its own processor, so its own cursors
+    cursors: Cursors,
+    // while the query cache is shared by all of them
+    query_cache: QueryCache,
+    // targets which failed while the rows were streamed, warned about once
+    // the rows were sent
+    target_notices: Arc<Mutex<Vec<TargetError>>>,
 }
 
 impl UdiPgpProcessor {
@@ -56,11 +72,19 @@ impl UdiPgpProcessor {
             exec_supplier: Arc::new(RwLock::new(admin_supplier)),
             health_shutdown: Arc::new(None),
             metrics_shutdown: Arc::new(None),
+            cursors: Cursors::default(),
+            query_cache: QueryCache::default(),
+            target_notices: Arc::default(),
         };
         processor.start_core_services().await?;
         Ok(processor)
     }
 
+    /// The suppliers the processor executes queries with.
+    pub fn exec_supplier(&self) -> Arc<RwLock<AdminSupplier>> {
+        self.exec_supplier.clone()
+    }
+
     async fn read_config(&self) -> UdiPgpResult<UdiPgpConfig> {
         let (response_tx, response_rx) = oneshot::channel();
         let read_state_msg = Message::ReadConfig(response_tx);
@@ -168,21 +192,72 @@ impl UdiPgpProcessor {
     ) -> Pin<Box<dyn Stream<Item = PgWireResult<DataRow>> + Send + Sync>> {
         debug!("encoding rows");
 
-        let mut results = Vec::new();
-        let ncols = schema.len();
+        let results = rows
+            .iter()
+            .map(|row| Self::encode_row(&schema, row))
+            .collect::<Vec<_>>();
+
+        debug!("encoded rows successfully");
+        Box::pin(stream::iter(results))
+    }
 
-        rows.iter().for_each(|row| {
-            let mut encoder = DataRowEncoder::new(schema.clone());
-            for idx in 0..ncols {
-                let data = &row.get(idx).unwrap().value;
-                encoder.encode_field(&data).unwrap();
+    /// Encode the supplier's rows as they're read, so a large result is never
+    /// held in memory. Once they ended, the targets which failed are listed in
+    /// `udi_pgp_errors` and kept for the notices sent after the rows.
+    pub fn encode_row_stream(
+        &self,
+        schema: Arc<Vec<FieldInfo>>,
+        rows: RowStream,
+        supplier_id: &str,
+        query_id: &Uuid,
+    ) -> BoxStream<'static, PgWireResult<DataRow>> {
+        let target_errors = rows.target_errors();
+        let config_tx = self.config_tx.clone();
+        let target_notices = self.target_notices.clone();
+        let (supplier_id, query_id) = (supplier_id.to_string(), query_id.to_string());
+        let ended = stream::once(async move {
+            let errors = target_errors.take();
+            if errors.is_empty() {
+                return;
             }
+            target_notices
+                .lock()
+                .unwrap()
+                .extend(errors.iter().cloned());
+            if let Err(err) = config_tx
+                .send(Message::RecordTargetErrors {
+                    query_id,
+                    supplier_id,
+                    errors,
+                })
+                .await
+            {
+                error!("Failed to record target errors: {}", err);
+            }
+        })
+        .filter_map(|_| future::ready(None));
 
-            results.push(encoder.finish());
-        });
+        rows.map(move |row| Self::encode_row(&schema, &row?))
+            .chain(ended)
+            .boxed()
+    }
 
-        debug!("encoded rows successfully");
-        Box::pin(stream::iter(results))
+    fn encode_row(schema: &Arc<Vec<FieldInfo>>, row: &[Row]) -> PgWireResult<DataRow> {
+        let mut encoder = DataRowEncoder::new(schema.clone());
+        for idx in 0..schema.len() {
+            let cell = row.get(idx).unwrap();
+            if cell.is_null {
+                encoder.encode_field(&None::<&str>)?;
+            } else {
+                encoder.encode_field(&cell.value)?;
+            }
+        }
+        encoder.finish()
+    }
+
+    /// The failed targets of the streamed results, once their rows were sent.
+    fn take_target_notices(&self) -> Vec<TargetError> {
+        std::mem::take(&mut *self.target_notices.lock().unwrap())
     }
 
     pub async fn handle_config<'a>(
@@ -197,7 +272,7 @@ impl UdiPgpProcessor {
         Ok(vec![Response::Execution(Tag::new("UDI-PGP CONFIG SET"))])
     }
 
-    pub fn handle_driver<'a>(&self, query: &'a str) -> PgWireResult<Vec<Response<'a>>> {
+    pub fn handle_driver<'a>(&self, query: &str) -> PgWireResult<Vec<Response<'a>>> {
         match query {
             SET_SEARCH_PATH | SET_TIME_ZONE | SET_DATE_STYLE | SET_EXTRA_FLOAT_DIGITS => {
                 Ok(vec![Response::Execution(Tag::new("SET"))])
@@ -378,6 +453,8 @@ impl UdiPgpProcessor {
                 // config has been updated above, so to get the latest update
                 let config = self.read_config().await?;
                 exec_supplier.update(&config).await?;
+                // results of a replaced supplier, or cached for another TTL, are stale
+                self.query_cache.clear();
 
                 Ok(())
             }
@@ -412,6 +489,9 @@ impl MakeHandler for UdiPgpProcessor {
             exec_supplier: self.exec_supplier.clone(),
             health_shutdown: self.health_shutdown.clone(),
             metrics_shutdown: self.metrics_shutdown.clone(),
+            cursors: Cursors::default(),
+            query_cache: self.query_cache.clone(),
+            target_notices: Arc::default(),
         })
     }
 }
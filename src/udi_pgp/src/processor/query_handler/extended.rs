@@ -1,18 +1,101 @@
-use std::sync::Arc;
+use std::{fmt::Debug, sync::Arc};
 
 use async_trait::async_trait;
+use futures::{Sink, SinkExt};
 use pgwire::{
     api::{
-        portal::Portal, query::ExtendedQueryHandler, results::{DescribePortalResponse, DescribeStatementResponse, Response}, stmt::StoredStatement, ClientInfo
+        portal::Portal,
+        query::{send_execution_response, ExtendedQueryHandler},
+        results::{
+            DescribePortalResponse, DescribeResponse, DescribeStatementResponse, Response, Tag,
+        },
+        stmt::StoredStatement,
+        store::PortalStore,
+        ClientInfo, ClientPortalStore, Type, DEFAULT_NAME,
+    },
+    error::{PgWireError, PgWireResult},
+    messages::{
+        extendedquery::{
+            Bind, BindComplete, Close, CloseComplete, Execute, PortalSuspended,
+            TARGET_TYPE_BYTE_PORTAL, TARGET_TYPE_BYTE_STATEMENT,
+        },
+        response::EmptyQueryResponse,
+        PgWireBackendMessage,
     },
-    error::PgWireResult,
 };
+use tracing::{debug_span, error, info_span, Instrument};
+use uuid::Uuid;
 
 use crate::{
-    parser::{stmt::UdiPgpStatment, UdiPgpQueryParser},
-    processor::UdiPgpProcessor,
+    parser::{params, stmt::UdiPgpStatment, UdiPgpQueryParser},
+    processor::{
+        cursors::{Cursor, PortalResult},
+        UdiPgpProcessor,
+    },
+    state::messages::Message,
 };
 
+impl UdiPgpProcessor {
+    /// Execute the portal's statement, the result is kept by the caller until
+    /// the client fetched all of it.
+    async fn portal_result<C>(
+        &self,
+        client: &mut C,
+        portal: &Portal<UdiPgpStatment>,
+    ) -> PgWireResult<PortalResult>
+    where
+        C: ClientInfo + Sink<PgWireBackendMessage> + Unpin + Send + Sync,
+        C::Error: Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+    {
+        let config = self.read_config().await?;
+        let query_id = Uuid::new_v4();
+        let username = client.metadata().get("user").cloned().unwrap_or_default();
+        if let Err(err) = self
+            .config_tx
+            .send(Message::RecordSessionQuery(client.socket_addr()))
+            .await
+        {
+            error!("Failed to record session activity: {}", err);
+        }
+
+        let mut statement = UdiPgpQueryParser::bind(portal)?;
+        let span = if config.verbose {
+            debug_span!("extended query handler", query_text = statement.query, query_id = ?query_id, username = username.as_str())
+        } else {
+            info_span!("extended query handler", query_text = statement.query, query_id = ?query_id, username = username.as_str())
+        };
+        let responses = self
+            .execute_statement(client, &mut statement, &query_id)
+            .instrument(span)
+            .await?;
+        Ok(match responses.into_iter().next() {
+            Some(Response::Query(response)) => PortalResult::Rows(Cursor::from_response(response)),
+            Some(Response::Execution(tag)) => PortalResult::Execution(tag),
+            Some(Response::Error(err)) => return Err(PgWireError::UserError(err)),
+            Some(Response::EmptyQuery) | None => PortalResult::Empty,
+        })
+    }
+
+    /// The portal's result, executing it unless a `Describe` or an earlier
+    /// `Execute` already did.
+    async fn pending_portal_result<C>(
+        &self,
+        client: &mut C,
+        portal: &Portal<UdiPgpStatment>,
+    ) -> PgWireResult<PortalResult>
+    where
+        C: ClientInfo + Sink<PgWireBackendMessage> + Unpin + Send + Sync,
+        C::Error: Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+    {
+        match self.cursors.take_portal(&portal.name) {
+            Some(result) => Ok(result),
+            None => self.portal_result(client, portal).await,
+        }
+    }
+}
+
 #[async_trait]
 impl ExtendedQueryHandler for UdiPgpProcessor {
     type Statement = UdiPgpStatment;
@@ -22,37 +105,161 @@ impl ExtendedQueryHandler for UdiPgpProcessor {
         self.query_parser.clone().into()
     }
 
-    async fn do_query<'a, C>(
-        &self,
-        _client: &mut C,
-        _portal: &'a Portal<Self::Statement>,
+    /// Binding a portal again starts it over, so its pending rows are dropped.
+    async fn on_bind<C>(&self, client: &mut C, message: Bind) -> PgWireResult<()>
+    where
+        C: ClientInfo + ClientPortalStore + Sink<PgWireBackendMessage> + Unpin + Send + Sync,
+        C::PortalStore: PortalStore<Statement = Self::Statement>,
+        C::Error: Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+    {
+        let statement_name = message.statement_name.as_deref().unwrap_or(DEFAULT_NAME);
+        let statement = client
+            .portal_store()
+            .get_statement(statement_name)
+            .ok_or_else(|| PgWireError::StatementNotFound(statement_name.to_owned()))?;
+        let portal = Portal::try_new(&message, statement)?;
+        self.cursors.take_portal(&portal.name);
+        client.portal_store().put_portal(Arc::new(portal));
+        client
+            .send(PgWireBackendMessage::BindComplete(BindComplete::new()))
+            .await?;
+        Ok(())
+    }
+
+    /// Send at most `max_rows` of the portal's rows, as they're read; when rows
+    /// remain the portal is suspended and the client's next `Execute`
+    /// continues it.
+    async fn on_execute<C>(&self, client: &mut C, message: Execute) -> PgWireResult<()>
+    where
+        C: ClientInfo + ClientPortalStore + Sink<PgWireBackendMessage> + Unpin + Send + Sync,
+        C::PortalStore: PortalStore<Statement = Self::Statement>,
+        C::Error: Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+    {
+        let portal_name = message.name.as_deref().unwrap_or(DEFAULT_NAME);
+        let portal = client
+            .portal_store()
+            .get_portal(portal_name)
+            .ok_or_else(|| PgWireError::PortalNotFound(portal_name.to_owned()))?;
+
+        match self.pending_portal_result(client, &portal).await? {
+            PortalResult::Rows(mut cursor) => {
+                let max_rows = message.max_rows as usize;
+                let mut count = 0;
+                while max_rows == 0 || count < max_rows {
+                    match cursor.next().await {
+                        Some(row) => {
+                            client.feed(PgWireBackendMessage::DataRow(row?)).await?;
+                            count += 1;
+                        }
+                        None => break,
+                    }
+                }
+                if cursor.is_exhausted().await {
+                    send_execution_response(client, Tag::new("SELECT").with_rows(count)).await?;
+                    self.send_target_notices(client, &self.take_target_notices())
+                        .await?;
+                } else {
+                    client
+                        .send(PgWireBackendMessage::PortalSuspended(PortalSuspended::new()))
+                        .await?;
+                    self.cursors
+                        .put_portal(portal_name, PortalResult::Rows(cursor));
+                }
+            }
+            PortalResult::Execution(tag) => send_execution_response(client, tag).await?,
+            PortalResult::Empty => {
+                client
+                    .feed(PgWireBackendMessage::EmptyQueryResponse(
+                        EmptyQueryResponse::new(),
+                    ))
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn on_close<C>(&self, client: &mut C, message: Close) -> PgWireResult<()>
+    where
+        C: ClientInfo + ClientPortalStore + Sink<PgWireBackendMessage> + Unpin + Send + Sync,
+        C::PortalStore: PortalStore<Statement = Self::Statement>,
+        C::Error: Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+    {
+        let name = message.name.as_deref().unwrap_or(DEFAULT_NAME);
+        match message.target_type {
+            TARGET_TYPE_BYTE_STATEMENT => client.portal_store().rm_statement(name),
+            TARGET_TYPE_BYTE_PORTAL => {
+                client.portal_store().rm_portal(name);
+                self.cursors.take_portal(name);
+            }
+            _ => {}
+        }
+        client
+            .send(PgWireBackendMessage::CloseComplete(CloseComplete::new()))
+            .await?;
+        Ok(())
+    }
+
+    async fn do_query<'a, 'b: 'a, C>(
+        &'b self,
+        client: &mut C,
+        portal: &'a Portal<Self::Statement>,
         _max_rows: usize,
     ) -> PgWireResult<Response<'a>>
     where
-        C: ClientInfo + Unpin + Send + Sync,
+        C: ClientInfo + ClientPortalStore + Sink<PgWireBackendMessage> + Unpin + Send + Sync,
+        C::PortalStore: PortalStore<Statement = Self::Statement>,
+        C::Error: Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
     {
-        Ok(Response::EmptyQuery)
+        // `on_execute` sends the rows itself, in batches
+        Ok(match self.portal_result(client, portal).await? {
+            PortalResult::Rows(mut cursor) => cursor.fetch_response(0).await?,
+            PortalResult::Execution(tag) => Response::Execution(tag),
+            PortalResult::Empty => Response::EmptyQuery,
+        })
     }
 
+    /// The statement's parameters, those whose type the client left for the
+    /// server to infer are announced as text which is how they're bound.
     async fn do_describe_statement<C>(
         &self,
         _client: &mut C,
-        _target: &StoredStatement<Self::Statement>,
+        target: &StoredStatement<Self::Statement>,
     ) -> PgWireResult<DescribeStatementResponse>
     where
         C: ClientInfo + Unpin + Send + Sync,
     {
-        Ok(DescribeStatementResponse::new(vec![], vec![]))
+        let parameter_types = (0..params::parameter_count(&target.statement.query)?)
+            .map(|idx| match target.parameter_types.get(idx) {
+                Some(pg_type) if *pg_type != Type::UNKNOWN => pg_type.clone(),
+                _ => Type::TEXT,
+            })
+            .collect();
+        Ok(DescribeStatementResponse::new(parameter_types, vec![]))
     }
 
+    /// The portal is executed to learn its columns, its result is kept for
+    /// the `Execute` which follows.
     async fn do_describe_portal<C>(
         &self,
-        _client: &mut C,
-        _portal: &Portal<Self::Statement>,
+        client: &mut C,
+        portal: &Portal<Self::Statement>,
     ) -> PgWireResult<DescribePortalResponse>
     where
-        C: ClientInfo + Unpin + Send + Sync,
+        C: ClientInfo + ClientPortalStore + Sink<PgWireBackendMessage> + Unpin + Send + Sync,
+        C::PortalStore: PortalStore<Statement = Self::Statement>,
+        C::Error: Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
     {
-        todo!()
+        let result = self.pending_portal_result(client, portal).await?;
+        let response = match &result {
+            PortalResult::Rows(cursor) => DescribePortalResponse::new(cursor.schema.to_vec()),
+            _ => DescribePortalResponse::no_data(),
+        };
+        self.cursors.put_portal(&portal.name, result);
+        Ok(response)
     }
 }
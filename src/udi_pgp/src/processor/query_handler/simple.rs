@@ -1,31 +1,53 @@
+use std::{fmt::Debug, sync::Arc, time::Duration};
+
 use async_trait::async_trait;
+use chrono::Utc;
+use futures::{Sink, SinkExt};
 use pgwire::{
     api::{
-        query::SimpleQueryHandler,
-        results::{QueryResponse, Response},
-        ClientInfo,
+        query::{send_execution_response, send_query_response, SimpleQueryHandler},
+        results::{QueryResponse, Response, Tag},
+        ClientInfo, PgWireConnectionState,
     },
     error::{ErrorInfo, PgWireError, PgWireResult},
+    messages::{
+        response::{EmptyQueryResponse, ReadyForQuery, READY_STATUS_IDLE},
+        simplequery::Query,
+        PgWireBackendMessage,
+    },
 };
-use tracing::{debug, debug_span, info, info_span, Instrument};
+use tracing::{debug, debug_span, error, info, info_span, warn, Instrument};
 use uuid::Uuid;
 
 use crate::{
+    auth::{Auth, Role},
     introspection::IntrospectionBackend,
     parser::{
-        stmt::{StmtType, UdiPgpStatment},
+        stmt::{CursorCommand, StmtType, UdiPgpStatment},
         UdiPgpQueryParser,
     },
-    processor::UdiPgpProcessor,
+    processor::{
+        cache::{CachedResult, QueryCache},
+        cursors::Cursor,
+        UdiPgpProcessor,
+    },
+    sql_supplier::{QueryPlanStep, TargetError},
+    state::messages::{CacheLookup, Message},
+    FieldFormat, FieldInfo, Row, Type,
 };
 
 impl UdiPgpProcessor {
-    async fn handle_supplier<'a, C: ClientInfo + Unpin + Send + Sync>(
+    async fn handle_supplier<'a, C>(
         &self,
         client: &mut C,
         statement: &mut UdiPgpStatment,
         session_id: &Uuid,
-    ) -> PgWireResult<Vec<Response<'a>>> {
+    ) -> PgWireResult<Vec<Response<'a>>>
+    where
+        C: ClientInfo + Sink<PgWireBackendMessage> + Unpin + Send + Sync,
+        C::Error: Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+    {
         let metadata = client.metadata();
         let (supplier_id, _) =
             Self::extract_supplier_and_database(metadata.get("database").map(|x| x.as_str()))?;
@@ -36,10 +58,107 @@ impl UdiPgpProcessor {
         supplier.add_session_id(*session_id)?;
 
         info!("Supplier: {supplier_id} currently in use.");
+        if statement.explain {
+            let mut steps = vec![
+                QueryPlanStep::new("supplier", format!("{supplier_id} ({})", supplier.name())),
+                QueryPlanStep::new("tables", statement.tables.join(", ")),
+            ];
+            if let Some(operation) = statement.write_operation() {
+                let accepted = supplier.capabilities().contains(operation.into());
+                steps.push(QueryPlanStep::new(
+                    "write",
+                    format!(
+                        "{operation} {}",
+                        if accepted {
+                            "accepted"
+                        } else {
+                            "rejected, read-only supplier"
+                        }
+                    ),
+                ));
+            }
+            steps.extend(supplier.explain(statement)?);
+            return Ok(vec![self.plan_response(steps)]);
+        }
+
+        if let Some(operation) = statement.write_operation() {
+            if !supplier.capabilities().contains(operation.into()) {
+                return Err(PgWireError::UserError(Box::new(ErrorInfo::new(
+                    "ERROR".to_string(),
+                    // read_only_sql_transaction
+                    "25006".to_string(),
+                    format!("Supplier: {supplier_id} is read-only, it doesn't accept {operation} statements"),
+                ))));
+            }
+            let rows = supplier.write(statement).await;
+            // results cached before the write could be stale even if it failed
+            self.query_cache.clear_supplier(&supplier_id);
+            return Ok(vec![Response::Execution(operation.tag(rows?))]);
+        }
+
+        let cache_ttl = self
+            .read_config()
+            .await?
+            .suppliers
+            .get(&supplier_id)
+            .and_then(|config| config.cache_ttl)
+            .filter(|ttl| *ttl > 0)
+            .map(Duration::from_secs);
+        let cache_query = QueryCache::normalize(statement);
+        if cache_ttl.is_some() {
+            if let Some(cached) = self.query_cache.get(&supplier_id, &cache_query) {
+                debug!("Answering with the cached result of {cache_query}");
+                self.record_cache_lookup(&supplier_id, cache_query, CacheLookup::Hit)
+                    .await;
+                let row_stream = self.encode_rows(cached.schema.clone().into(), &cached.rows);
+                return Ok(vec![Response::Query(QueryResponse::new(
+                    cached.schema.into(),
+                    row_stream,
+                ))]);
+            }
+        }
+
         let (schema, rows) = (
             supplier.schema(statement).await?,
             supplier.execute(statement).await?,
         );
+        let Some(ttl) = cache_ttl else {
+            let schema = Arc::new(schema);
+            let row_stream = self.encode_row_stream(schema.clone(), rows, &supplier_id, session_id);
+            return Ok(vec![Response::Query(QueryResponse::new(
+                schema, row_stream,
+            ))]);
+        };
+
+        // a cached result is read whole before it's answered
+        let target_errors = rows.target_errors();
+        let rows = rows.collect_rows().await?;
+        let target_errors = target_errors.take();
+        // a result missing the rows of failed targets isn't cached
+        let cached = target_errors.is_empty()
+            && self.query_cache.insert(
+                &supplier_id,
+                &cache_query,
+                CachedResult {
+                    schema: schema.clone(),
+                    rows: rows.clone(),
+                },
+                ttl,
+            );
+        if cached {
+            let expires_at = Utc::now() + ttl;
+            self.record_cache_lookup(
+                &supplier_id,
+                cache_query,
+                CacheLookup::Miss {
+                    rows: rows.len(),
+                    expires_at: expires_at.format("%Y-%m-%dT%H:%M:%S").to_string(),
+                },
+            )
+            .await;
+        }
+        self.report_target_errors(client, &supplier_id, session_id, target_errors)
+            .await?;
 
         let row_stream = self.encode_rows(schema.clone().into(), &rows);
         let response = Response::Query(QueryResponse::new(schema.into(), row_stream));
@@ -47,41 +166,315 @@ impl UdiPgpProcessor {
         Ok(vec![response])
     }
 
+    /// Warn the client about the targets missing from the result, and list them
+    /// in `udi_pgp_errors` for the query.
+    async fn report_target_errors<C>(
+        &self,
+        client: &mut C,
+        supplier_id: &str,
+        query_id: &Uuid,
+        errors: Vec<TargetError>,
+    ) -> PgWireResult<()>
+    where
+        C: ClientInfo + Sink<PgWireBackendMessage> + Unpin + Send + Sync,
+        C::Error: Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+    {
+        if errors.is_empty() {
+            return Ok(());
+        }
+        self.send_target_notices(client, &errors).await?;
+        if let Err(err) = self
+            .config_tx
+            .send(Message::RecordTargetErrors {
+                query_id: query_id.to_string(),
+                supplier_id: supplier_id.to_string(),
+                errors,
+            })
+            .await
+        {
+            error!("Failed to record target errors: {}", err);
+        }
+        Ok(())
+    }
+
+    /// Warn the client about the targets missing from the result.
+    pub(crate) async fn send_target_notices<C>(
+        &self,
+        client: &mut C,
+        errors: &[TargetError],
+    ) -> PgWireResult<()>
+    where
+        C: ClientInfo + Sink<PgWireBackendMessage> + Unpin + Send + Sync,
+        C::Error: Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+    {
+        for error in errors {
+            let notice = ErrorInfo::new(
+                "WARNING".to_string(),
+                // warning
+                "01000".to_string(),
+                format!(
+                    "{} ({}) failed, its rows are missing: {}",
+                    error.host_id, error.target, error.message
+                ),
+            );
+            client
+                .feed(PgWireBackendMessage::NoticeResponse(notice.into()))
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Count the lookup in `udi_pgp_cache`.
+    async fn record_cache_lookup(
+        &self,
+        supplier_id: &str,
+        query_text: String,
+        lookup: CacheLookup,
+    ) {
+        if let Err(err) = self
+            .config_tx
+            .send(Message::RecordCacheLookup {
+                supplier_id: supplier_id.to_string(),
+                query_text,
+                lookup,
+            })
+            .await
+        {
+            error!("Failed to record query cache lookup: {}", err);
+        }
+    }
+
+    fn plan_response<'a>(&self, steps: Vec<QueryPlanStep>) -> Response<'a> {
+        let schema = ["step", "detail"]
+            .into_iter()
+            .map(|name| {
+                FieldInfo::new(
+                    name.to_string(),
+                    None,
+                    None,
+                    Type::VARCHAR,
+                    FieldFormat::Text,
+                )
+            })
+            .collect::<Vec<_>>();
+        let rows = steps
+            .into_iter()
+            .map(|step| vec![Row::from(step.step), Row::from(step.detail)])
+            .collect::<Vec<_>>();
+        let row_stream = self.encode_rows(schema.clone().into(), &rows);
+        Response::Query(QueryResponse::new(schema.into(), row_stream))
+    }
+
+    /// Answer a parsed statement, shared by the simple and extended query
+    /// protocols.
+    pub(crate) async fn execute_statement<C>(
+        &self,
+        client: &mut C,
+        statement: &mut UdiPgpStatment,
+        query_id: &Uuid,
+    ) -> PgWireResult<Vec<Response<'static>>>
+    where
+        C: ClientInfo + Sink<PgWireBackendMessage> + Unpin + Send + Sync,
+        C::Error: Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+    {
+        let auth = self.authorize(client, statement).await?;
+        let responses = match statement.stmt_type {
+            StmtType::Config => self.handle_config(statement, query_id).await?,
+            StmtType::Driver => self.handle_driver(&statement.query)?,
+            StmtType::Supplier => self.handle_supplier(client, statement, query_id).await?,
+            StmtType::Introspection => self.handle_introspection(statement, &auth).await?,
+            StmtType::Cursor => vec![self.handle_cursor(statement).await?],
+        };
+        match &statement.cursor {
+            Some(CursorCommand::Declare(name)) => self.declare_cursor(name, responses).await,
+            _ => Ok(responses),
+        }
+    }
+
+    /// Check the statement against the permissions the client's user was
+    /// granted on the supplier it connected to, the user's grant when allowed.
+    async fn authorize<C>(&self, client: &C, statement: &UdiPgpStatment) -> PgWireResult<Auth>
+    where
+        C: ClientInfo,
+    {
+        let metadata = client.metadata();
+        let (supplier_id, _) =
+            Self::extract_supplier_and_database(metadata.get("database").map(|x| x.as_str()))?;
+        let user = metadata.get("user").map(String::as_str).unwrap_or_default();
+        let auth = self
+            .read_config()
+            .await?
+            .supplier_auth(&supplier_id, user)?
+            // the user was removed since it connected
+            .ok_or_else(|| format!("{user} has no grant on supplier {supplier_id}"));
+        match auth.and_then(|auth| auth.authorize(statement).map(|_| auth)) {
+            Err(reason) => {
+                warn!("Denied query of {user} on {supplier_id}: {reason}");
+                Err(PgWireError::UserError(Box::new(ErrorInfo::new(
+                    "ERROR".to_string(),
+                    // insufficient_privilege
+                    "42501".to_string(),
+                    reason,
+                ))))
+            }
+            Ok(auth) => Ok(auth),
+        }
+    }
+
+    /// Keep the rows of a `DECLARE`d query for the `FETCH`es which follow.
+    async fn declare_cursor<'a>(
+        &self,
+        name: &str,
+        responses: Vec<Response<'static>>,
+    ) -> PgWireResult<Vec<Response<'a>>> {
+        match responses.into_iter().next() {
+            Some(Response::Query(response)) => {
+                self.cursors.declare(name, Cursor::from_response(response));
+                Ok(vec![Response::Execution(Tag::new("DECLARE CURSOR"))])
+            }
+            _ => Err(PgWireError::UserError(Box::new(ErrorInfo::new(
+                "ERROR".to_string(),
+                // invalid_cursor_definition
+                "42P11".to_string(),
+                format!("cursor \"{name}\" must be declared for a query returning rows"),
+            )))),
+        }
+    }
+
+    async fn handle_cursor(&self, statement: &UdiPgpStatment) -> PgWireResult<Response<'static>> {
+        match &statement.cursor {
+            Some(CursorCommand::Fetch { name, count }) => self.cursors.fetch(name, *count).await,
+            Some(CursorCommand::Close(name)) => {
+                self.cursors.close(name.as_deref());
+                Ok(Response::Execution(Tag::new("CLOSE CURSOR")))
+            }
+            _ => Ok(Response::EmptyQuery),
+        }
+    }
+
+    /// Answer a query of the introspection tables from the admin database.
+    /// Users who aren't admins only see their own queries in
+    /// `udi_pgp_observe_query_exec` and can't get around it by naming the
+    /// database (`main.udi_pgp_observe_query_exec`).
     async fn handle_introspection<'a>(
         &self,
         stmt: &UdiPgpStatment,
-        _session_id: &Uuid,
+        auth: &Auth,
     ) -> PgWireResult<Vec<Response<'a>>> {
+        let scoped = auth.role() != Role::Admin;
+        if scoped {
+            if let Some(table) = stmt.relations().into_iter().find(|t| t.contains('.')) {
+                return Err(PgWireError::UserError(Box::new(ErrorInfo::new(
+                    "ERROR".to_string(),
+                    // insufficient_privilege
+                    "42501".to_string(),
+                    format!("{} is {}, it can't query {table}", auth.user(), auth.role()),
+                ))));
+            }
+        }
+
         let config = self.read_config().await?;
+        let introspection_err = |err: rusqlite::Error| {
+            PgWireError::UserError(Box::new(ErrorInfo::new(
+                "FATAL".to_string(),
+                "INTROSPECTION".to_string(),
+                err.to_string(),
+            )))
+        };
         let introspection =
-            IntrospectionBackend::new(&config.admin_state_fs_path).map_err(|err| {
-                PgWireError::UserError(Box::new(ErrorInfo::new(
-                    "FATAL".to_string(),
-                    "INTROSPECTION".to_string(),
-                    err.to_string(),
-                )))
-            })?;
+            IntrospectionBackend::new(&config.admin_state_fs_path).map_err(introspection_err)?;
+        if scoped {
+            introspection
+                .scope_query_log(auth.user())
+                .map_err(introspection_err)?;
+        }
         introspection.do_query(stmt)
     }
 }
 
 #[async_trait]
 impl SimpleQueryHandler for UdiPgpProcessor {
+    /// pgwire's default, except that the failed targets of streamed results are
+    /// only known once their rows were sent, so they're warned about after them.
+    async fn on_query<C>(&self, client: &mut C, query: Query) -> PgWireResult<()>
+    where
+        C: ClientInfo + Sink<PgWireBackendMessage> + Unpin + Send + Sync,
+        C::Error: Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+    {
+        client.set_state(PgWireConnectionState::QueryInProgress);
+        let query_string = query.query.trim();
+        if query_string.is_empty() || query_string == ";" {
+            client
+                .feed(PgWireBackendMessage::EmptyQueryResponse(
+                    EmptyQueryResponse::new(),
+                ))
+                .await?;
+        } else {
+            for response in self.do_query(client, query_string).await? {
+                match response {
+                    Response::EmptyQuery => {
+                        client
+                            .feed(PgWireBackendMessage::EmptyQueryResponse(
+                                EmptyQueryResponse::new(),
+                            ))
+                            .await?;
+                    }
+                    Response::Query(results) => {
+                        send_query_response(client, results, true).await?;
+                    }
+                    Response::Execution(tag) => {
+                        send_execution_response(client, tag).await?;
+                    }
+                    Response::Error(err) => {
+                        client
+                            .feed(PgWireBackendMessage::ErrorResponse((*err).into()))
+                            .await?;
+                    }
+                }
+            }
+            self.send_target_notices(client, &self.take_target_notices())
+                .await?;
+        }
+
+        client
+            .feed(PgWireBackendMessage::ReadyForQuery(ReadyForQuery::new(
+                READY_STATUS_IDLE,
+            )))
+            .await?;
+        client.flush().await?;
+        client.set_state(PgWireConnectionState::ReadyForQuery);
+        Ok(())
+    }
+
     async fn do_query<'a, C>(
         &self,
         client: &mut C,
         query: &'a str,
     ) -> PgWireResult<Vec<Response<'a>>>
     where
-        C: ClientInfo + Unpin + Send + Sync,
+        C: ClientInfo + Sink<PgWireBackendMessage> + Unpin + Send + Sync,
+        C::Error: Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
     {
         let config = self.read_config().await?;
         let query_id = Uuid::new_v4();
+        let username = client.metadata().get("user").cloned().unwrap_or_default();
+        if let Err(err) = self
+            .config_tx
+            .send(Message::RecordSessionQuery(client.socket_addr()))
+            .await
+        {
+            error!("Failed to record session activity: {}", err);
+        }
 
         let span = if config.verbose {
-            debug_span!("simple query handler", query_text = query, query_id = ?query_id)
+            debug_span!("simple query handler", query_text = query, query_id = ?query_id, username = username.as_str())
         } else {
-            info_span!("simple query handler", query_text = query, query_id = ?query_id)
+            info_span!("simple query handler", query_text = query, query_id = ?query_id, username = username.as_str())
         };
 
         async {
@@ -89,15 +482,8 @@ impl SimpleQueryHandler for UdiPgpProcessor {
             debug!("Executing query: {query}");
             debug!("Parsed statement: {:#?}", statement);
 
-            Ok(match statement.stmt_type {
-                StmtType::Config => self.handle_config(&statement, &query_id).await?,
-                StmtType::Driver => self.handle_driver(query)?,
-                StmtType::Supplier => {
-                    self.handle_supplier(client, &mut statement, &query_id)
-                        .await?
-                }
-                StmtType::Introspection => self.handle_introspection(&statement, &query_id).await?,
-            })
+            self.execute_statement(client, &mut statement, &query_id)
+                .await
         }
         .instrument(span)
         .await
@@ -0,0 +1,481 @@
+use std::{fmt::Display, str::FromStr};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error};
+
+use crate::error::{UdiPgpError, UdiPgpResult};
+
+use self::{
+    ssh::SshTransport,
+    ssm::SsmTransport,
+    tags::{parse_tags, TargetTags},
+    winrm::WinRmTransport,
+};
+
+pub mod ssh;
+pub mod ssm;
+pub mod tags;
+pub mod winrm;
+
+/// Executes commands on a remote target.
+#[async_trait]
+pub trait RemoteTransport: Send + Sync {
+    /// Run `cmd` with `args` on the target and return what it wrote to stdout.
+    async fn execute_command(&self, cmd: &str, args: Vec<&str>) -> UdiPgpResult<String>;
+
+    /// Whether the target can be reached at all.
+    async fn check_access(&self) -> UdiPgpResult<bool>;
+}
+
+/// How a remote target is reached, configured per target.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RemoteTransportKind {
+    /// OpenSSH with the keys of the SSH agent
+    #[default]
+    Ssh,
+    /// PowerShell remoting (`Invoke-Command` through `pwsh`), for Windows hosts
+    Winrm,
+    /// AWS Systems Manager Run Command (through the `aws` CLI), the host is
+    /// the instance ID
+    Ssm,
+}
+
+impl Display for RemoteTransportKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RemoteTransportKind::Ssh => f.write_str("ssh"),
+            RemoteTransportKind::Winrm => f.write_str("winrm"),
+            RemoteTransportKind::Ssm => f.write_str("ssm"),
+        }
+    }
+}
+
+impl FromStr for RemoteTransportKind {
+    type Err = UdiPgpError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ssh" => Ok(RemoteTransportKind::Ssh),
+            "winrm" => Ok(RemoteTransportKind::Winrm),
+            "ssm" => Ok(RemoteTransportKind::Ssm),
+            other => Err(UdiPgpError::SshConnectionParseError(format!(
+                "Expected one of `ssh`, `winrm` or `ssm` transports. Got: {}",
+                other
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize)]
+pub struct TargetStatus {
+    pub is_accessible: bool,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize)]
+pub struct UdiPgpRemoteTarget {
+    /// The host name or address, or the instance ID for SSM
+    pub host: String,
+    pub port: Option<u16>,
+    /// The SSH or WinRM user, or the AWS CLI profile for SSM
+    #[serde(default)]
+    pub user: String,
+    pub id: String,
+    #[serde(default)]
+    pub transport: RemoteTransportKind,
+    /// Environment variable holding the WinRM password, the current
+    /// (Kerberos) credentials are used when it's not set
+    #[serde(rename = "password-env")]
+    pub password_env: Option<String>,
+    #[serde(rename = "atc-file-path")]
+    pub atc_file_path: Option<String>,
+    /// Labels such as `env=prod` or `role=db`, a query selects the targets it
+    /// runs on by comparing them in its `WHERE` clause
+    #[serde(default)]
+    pub tags: TargetTags,
+    /// Targets sharing an id are the same logical host, reached through the
+    /// one with the lowest priority unless it fails
+    #[serde(default)]
+    pub priority: u16,
+    pub status: Option<TargetStatus>,
+}
+
+impl UdiPgpRemoteTarget {
+    pub fn transport(&self) -> Box<dyn RemoteTransport> {
+        match self.transport {
+            RemoteTransportKind::Ssh => Box::new(SshTransport::new(self.clone())),
+            RemoteTransportKind::Winrm => Box::new(WinRmTransport::new(self.clone())),
+            RemoteTransportKind::Ssm => Box::new(SsmTransport::new(self.clone())),
+        }
+    }
+
+    pub async fn execute_command(&self, cmd: &str, args: Vec<&str>) -> UdiPgpResult<String> {
+        self.transport().execute_command(cmd, args).await
+    }
+
+    pub async fn is_accessible(&mut self) -> UdiPgpResult<()> {
+        match self.transport().check_access().await {
+            Ok(accessible) => {
+                self.status = Some(TargetStatus {
+                    is_accessible: accessible,
+                    reason: None,
+                })
+            }
+            Err(err) => {
+                error!("{}", err);
+                self.status = Some(TargetStatus {
+                    is_accessible: false,
+                    reason: Some(format!("{}", err)),
+                });
+            }
+        };
+        Ok(())
+    }
+}
+
+impl TryFrom<&String> for UdiPgpRemoteTarget {
+    type Error = UdiPgpError;
+
+    fn try_from(value: &String) -> Result<Self, Self::Error> {
+        UdiPgpRemoteTarget::from_str(value)
+    }
+}
+
+impl FromStr for UdiPgpRemoteTarget {
+    type Err = UdiPgpError;
+
+    /// Parses `[transport://][user@]host[:port],id[,key=value...]`, where the
+    /// transport is `ssh` when missing. The user is only required for SSH and
+    /// the `priority` key is the target's priority rather than a tag.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use UdiPgpError::SshConnectionParseError;
+
+        let parts: Vec<&str> = s.split(',').collect();
+        if parts.len() < 2 {
+            return Err(SshConnectionParseError(format!(
+                "Target: {s} should have at least two parts, the connection string and the id. It has {} parts.",
+                parts.len()
+            )));
+        }
+
+        let (transport, s) = match parts[0].split_once("://") {
+            Some((scheme, rest)) => (RemoteTransportKind::from_str(scheme)?, rest),
+            None => (RemoteTransportKind::Ssh, parts[0]),
+        };
+        let id = parts[1];
+        let (priorities, tags): (Vec<&str>, Vec<&str>) = parts[2..]
+            .iter()
+            .partition(|part| part.trim().starts_with("priority="));
+        let priority = match priorities.last() {
+            Some(part) => part.trim()["priority=".len()..].parse().map_err(|_| {
+                SshConnectionParseError(format!("priority should be a valid number: {}", part))
+            })?,
+            None => 0,
+        };
+        let tags = parse_tags(&tags.join(","))?;
+
+        let (user, rest) = match s.split_once('@') {
+            Some((user, rest)) => (user, rest),
+            None if transport != RemoteTransportKind::Ssh => ("", s),
+            None => {
+                return Err(SshConnectionParseError(format!(
+                    "connection string should have the format `ssh://user@address`: {}",
+                    s
+                )))
+            }
+        };
+
+        if user.is_empty() && transport == RemoteTransportKind::Ssh {
+            return Err(SshConnectionParseError(format!(
+                "user cannot be empty: {}",
+                s
+            )));
+        }
+
+        let (host, port_str) = rest.rsplit_once(':').unwrap_or((rest, ""));
+        let port = if !port_str.is_empty() {
+            Some(port_str.parse().map_err(|_| {
+                SshConnectionParseError(format!("port should be a valid number: {}", port_str))
+            })?)
+        } else {
+            None
+        };
+
+        if host.is_empty() {
+            return Err(SshConnectionParseError(format!(
+                "host cannot be empty: {}",
+                s
+            )));
+        }
+
+        Ok(Self {
+            host: host.to_owned(),
+            port,
+            user: user.to_owned(),
+            id: id.to_owned(),
+            transport,
+            password_env: None,
+            atc_file_path: None,
+            tags,
+            priority,
+            status: None,
+        })
+    }
+}
+
+/// The connection string of the target, e.g. `ssh://user@host:22`.
+impl Display for UdiPgpRemoteTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}://", self.transport)?;
+        if !self.user.is_empty() {
+            write!(f, "{}@", self.user)?;
+        }
+        write!(f, "{}", self.host)?;
+        if let Some(port) = self.port {
+            write!(f, ":{port}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The targets grouped by their id, in the order the ids are first listed,
+/// each group ordered by priority so its first target is the primary one and
+/// the others are tried in turn when it can't be reached.
+pub fn failover_groups(targets: Vec<UdiPgpRemoteTarget>) -> Vec<Vec<UdiPgpRemoteTarget>> {
+    let mut groups: Vec<Vec<UdiPgpRemoteTarget>> = Vec::new();
+    for target in targets {
+        match groups.iter_mut().find(|group| group[0].id == target.id) {
+            Some(group) => group.push(target),
+            None => groups.push(vec![target]),
+        }
+    }
+    // stable, targets of the same priority keep the order they're listed in
+    groups
+        .iter_mut()
+        .for_each(|group| group.sort_by_key(|target| target.priority));
+    groups
+}
+
+/// Run `cmd` on the targets of a failover group in turn until one succeeds and
+/// return that target with its output. When all of them fail the primary
+/// target is returned with every target's error.
+pub async fn execute_with_failover(
+    group: &[UdiPgpRemoteTarget],
+    cmd: &str,
+    args: Vec<&str>,
+) -> (UdiPgpRemoteTarget, UdiPgpResult<String>) {
+    let mut failures = Vec::new();
+    for target in group {
+        match target.execute_command(cmd, args.clone()).await {
+            Ok(output) => return (target.clone(), Ok(output)),
+            Err(err) => {
+                if group.len() > 1 {
+                    error!("{} ({}) failed, failing over: {}", target, target.id, err);
+                }
+                failures.push((target, err));
+            }
+        }
+    }
+    let primary = group[0].clone();
+    let error = match failures.len() {
+        1 => failures.remove(0).1,
+        _ => UdiPgpError::RemoteTransportError(
+            primary.id.clone(),
+            failures
+                .iter()
+                .map(|(target, err)| format!("{target}: {err}"))
+                .collect::<Vec<_>>()
+                .join("; "),
+        ),
+    };
+    (primary, Err(error))
+}
+
+/// Run a local CLI (e.g. `pwsh` or `aws`) on behalf of `target` and return its stdout.
+async fn run_local_cli(
+    target: &UdiPgpRemoteTarget,
+    program: &str,
+    args: &[String],
+) -> UdiPgpResult<String> {
+    debug!("Executing {} for {} with {:?}", program, target, args);
+    let output = tokio::process::Command::new(program)
+        .args(args)
+        .output()
+        .await
+        .map_err(|err| {
+            UdiPgpError::RemoteTransportError(
+                target.to_string(),
+                format!(
+                    "{program} is required for {} targets: {err}",
+                    target.transport
+                ),
+            )
+        })?;
+    if !output.status.success() {
+        return Err(UdiPgpError::RemoteTransportError(
+            target.to_string(),
+            format!(
+                "{program} exited with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(host: &str, port: Option<u16>, user: &str) -> UdiPgpRemoteTarget {
+        UdiPgpRemoteTarget {
+            host: host.to_string(),
+            port,
+            user: user.to_string(),
+            id: "prod".to_string(),
+            transport: RemoteTransportKind::Ssh,
+            password_env: None,
+            atc_file_path: None,
+            tags: TargetTags::new(),
+            priority: 0,
+            status: None,
+        }
+    }
+
+    #[test]
+    fn display_connection_string() {
+        let conn_str = target("127.0.0.1", Some(5432), "prod").to_string();
+        assert_eq!(&conn_str, "ssh://prod@127.0.0.1:5432");
+
+        // Missing port.
+        let conn_str = target("127.0.0.1", None, "prod").to_string();
+        assert_eq!(&conn_str, "ssh://prod@127.0.0.1");
+
+        let conn_str = UdiPgpRemoteTarget {
+            transport: RemoteTransportKind::Ssm,
+            ..target("i-0123456789abcdef0", None, "")
+        }
+        .to_string();
+        assert_eq!(&conn_str, "ssm://i-0123456789abcdef0");
+    }
+
+    #[test]
+    fn parse_connection_string() {
+        // Valid
+        let test_cases = vec![
+            ("user@host.com,prod", target("host.com", None, "user")),
+            (
+                "user@host.com:1234,prod",
+                target("host.com", Some(1234), "user"),
+            ),
+            (
+                "user@127.0.0.1:1234,prod",
+                target("127.0.0.1", Some(1234), "user"),
+            ),
+            ("ssh://user@host.com,prod", target("host.com", None, "user")),
+            (
+                "winrm://Administrator@win.local:5986,prod",
+                UdiPgpRemoteTarget {
+                    transport: RemoteTransportKind::Winrm,
+                    ..target("win.local", Some(5986), "Administrator")
+                },
+            ),
+            (
+                "ssm://i-0123456789abcdef0,prod",
+                UdiPgpRemoteTarget {
+                    transport: RemoteTransportKind::Ssm,
+                    ..target("i-0123456789abcdef0", None, "")
+                },
+            ),
+            (
+                "user@host.com,prod,env=prod,role=db",
+                UdiPgpRemoteTarget {
+                    tags: parse_tags("env=prod,role=db").unwrap(),
+                    ..target("host.com", None, "user")
+                },
+            ),
+            (
+                "user@standby.host.com,prod,priority=1,env=prod",
+                UdiPgpRemoteTarget {
+                    tags: parse_tags("env=prod").unwrap(),
+                    priority: 1,
+                    ..target("standby.host.com", None, "user")
+                },
+            ),
+        ];
+        for (s, v) in test_cases {
+            let s: UdiPgpRemoteTarget = s.parse().unwrap();
+            assert_eq!(s, v);
+        }
+
+        // Invalid
+        let test_cases = vec![
+            "random string",
+            "user@host.com",          // missing id
+            "ssh://user_at_host.com", // missing `@`
+            "ssh://@host.com",        // empty user
+            "ssh://user@",            // empty address
+            "ssh://user@:1234",       // empty host
+            "ssh://host.com:abc",     // invalid port
+            "user_at_host.com,prod",  // missing `@` for ssh
+            "ftp://user@host.com,prod",
+            "ssm://,prod",
+            "user@host.com,prod,prod", // tag without a value
+            "user@host.com,prod,priority=first",
+        ];
+        for s in test_cases {
+            s.parse::<UdiPgpRemoteTarget>()
+                .expect_err("invalid connection string should error");
+        }
+    }
+
+    #[test]
+    fn group_targets_for_failover() {
+        let targets = [
+            "ops@web-01-standby,web-01,priority=1",
+            "ops@db-01,db-01",
+            "ops@web-01,web-01",
+            "ops@web-01-dr,web-01,priority=1",
+        ]
+        .iter()
+        .map(|s| s.parse().unwrap())
+        .collect();
+        let groups = failover_groups(targets);
+        let groups = groups
+            .iter()
+            .map(|group| group.iter().map(|t| t.host.as_str()).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            groups,
+            vec![vec!["web-01", "web-01-standby", "web-01-dr"], vec!["db-01"]]
+        );
+    }
+
+    #[test]
+    fn deserialize_target_transport() {
+        let targets: Vec<UdiPgpRemoteTarget> = serde_json::from_str(
+            r#"[
+                { "id": "web-1", "host": "10.0.0.5", "user": "admin", "tags": { "env": "prod" } },
+                { "id": "win-1", "host": "win.local", "user": "ops", "transport": "winrm", "password-env": "WIN_PASSWORD" },
+                { "id": "ec2-1", "host": "i-0123456789abcdef0", "transport": "ssm" }
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            targets.iter().map(|t| t.transport).collect::<Vec<_>>(),
+            vec![
+                RemoteTransportKind::Ssh,
+                RemoteTransportKind::Winrm,
+                RemoteTransportKind::Ssm
+            ]
+        );
+        assert_eq!(targets[1].password_env.as_deref(), Some("WIN_PASSWORD"));
+        assert_eq!(targets[2].user, "");
+        assert_eq!(targets[0].tags.get("env").map(String::as_str), Some("prod"));
+        assert!(targets[1].tags.is_empty());
+        assert!(targets.iter().all(|t| t.priority == 0));
+    }
+}
@@ -0,0 +1,42 @@
+use async_trait::async_trait;
+
+use crate::error::{UdiPgpError, UdiPgpResult};
+
+use self::{key::SshKey, session::SshTunnelAccess};
+use super::{RemoteTransport, UdiPgpRemoteTarget};
+
+pub mod key;
+pub mod session;
+
+/// Executes commands over OpenSSH, authenticating with the SSH agent.
+#[derive(Debug, Clone)]
+pub struct SshTransport {
+    target: UdiPgpRemoteTarget,
+}
+
+impl SshTransport {
+    pub fn new(target: UdiPgpRemoteTarget) -> Self {
+        SshTransport { target }
+    }
+
+    fn access(&self) -> UdiPgpResult<SshTunnelAccess> {
+        let keypair = SshKey::generate_random().map_err(UdiPgpError::from)?;
+        Ok(SshTunnelAccess {
+            connection_string: format!("{}@{}", self.target.user, self.target.host),
+            keypair,
+        })
+    }
+}
+
+#[async_trait]
+impl RemoteTransport for SshTransport {
+    async fn execute_command(&self, cmd: &str, args: Vec<&str>) -> UdiPgpResult<String> {
+        let addr = format!("{}:{}", self.target.host, self.target.port.unwrap_or(22));
+        let (session, _) = self.access()?.create_tunnel(&addr).await?;
+        Ok(session.execute_command(cmd, args).await?)
+    }
+
+    async fn check_access(&self) -> UdiPgpResult<bool> {
+        Ok(self.access()?.check_access_connection().await?)
+    }
+}
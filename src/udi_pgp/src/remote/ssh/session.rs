@@ -9,7 +9,7 @@ use tokio::fs::File;
 use tokio::net::TcpListener;
 use tracing::{debug, trace};
 
-use crate::ssh::key::{SshKey, SshKeyError};
+use crate::remote::ssh::key::{SshKey, SshKeyError};
 
 #[derive(Debug, thiserror::Error)]
 pub enum SshTunnelError {
@@ -265,8 +265,7 @@ mod unix_impl {
     async fn get_random_localhost_port() -> Result<u16, io::Error> {
         // The 0 port indicates to the OS to assign a random port
         let listener = TcpListener::bind("localhost:0").await.map_err(|e| {
-            io::Error::new(
-                io::ErrorKind::Other,
+            io::Error::other(
                 format!("Failed to bind to a random port due to {e}"),
             )
         })?;
@@ -0,0 +1,172 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use tracing::debug;
+
+use crate::error::{UdiPgpError, UdiPgpResult};
+
+use super::{run_local_cli, RemoteTransport, UdiPgpRemoteTarget};
+
+const SSM_DOCUMENT: &str = "AWS-RunShellScript";
+const SSM_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// How long to wait for a command to complete on the instance.
+const SSM_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Executes commands on EC2 (or hybrid) instances with AWS Systems Manager
+/// Run Command, using the local `aws` CLI and its credentials. The target's
+/// host is the instance ID and its user, if any, the AWS CLI profile.
+#[derive(Debug, Clone)]
+pub struct SsmTransport {
+    target: UdiPgpRemoteTarget,
+}
+
+/// Quote `s` as a POSIX shell word.
+fn sh_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+impl SsmTransport {
+    pub fn new(target: UdiPgpRemoteTarget) -> Self {
+        SsmTransport { target }
+    }
+
+    /// Arguments of `aws ssm <args>` with the target's profile.
+    fn aws_args(&self, args: &[&str]) -> Vec<String> {
+        let mut aws_args = Vec::new();
+        if !self.target.user.is_empty() {
+            aws_args.extend(["--profile".to_string(), self.target.user.clone()]);
+        }
+        aws_args.push("ssm".to_string());
+        aws_args.extend(args.iter().map(|arg| arg.to_string()));
+        aws_args.extend(["--output".to_string(), "json".to_string()]);
+        aws_args
+    }
+
+    /// Arguments of the `aws ssm send-command` running `cmd` with `args`.
+    fn send_command_args(&self, cmd: &str, args: &[&str]) -> Vec<String> {
+        let command_line = std::iter::once(cmd)
+            .chain(args.iter().copied())
+            .map(sh_quote)
+            .collect::<Vec<_>>()
+            .join(" ");
+        let parameters = json!({ "commands": [command_line] }).to_string();
+        self.aws_args(&[
+            "send-command",
+            "--instance-ids",
+            &self.target.host,
+            "--document-name",
+            SSM_DOCUMENT,
+            "--comment",
+            "surveilr udi pgp",
+            "--parameters",
+            &parameters,
+        ])
+    }
+
+    async fn aws(&self, args: Vec<String>) -> UdiPgpResult<Value> {
+        let output = run_local_cli(&self.target, "aws", &args).await?;
+        Ok(serde_json::from_str(&output)?)
+    }
+
+    fn error(&self, message: String) -> UdiPgpError {
+        UdiPgpError::RemoteTransportError(self.target.to_string(), message)
+    }
+}
+
+#[async_trait]
+impl RemoteTransport for SsmTransport {
+    async fn execute_command(&self, cmd: &str, args: Vec<&str>) -> UdiPgpResult<String> {
+        let sent = self.aws(self.send_command_args(cmd, &args)).await?;
+        let command_id = sent["Command"]["CommandId"]
+            .as_str()
+            .ok_or_else(|| self.error("send-command didn't return a CommandId".to_string()))?
+            .to_string();
+        debug!("SSM command {} sent to {}", command_id, self.target);
+
+        let started = tokio::time::Instant::now();
+        loop {
+            tokio::time::sleep(SSM_POLL_INTERVAL).await;
+            let invocation = self
+                .aws(self.aws_args(&[
+                    "get-command-invocation",
+                    "--command-id",
+                    &command_id,
+                    "--instance-id",
+                    &self.target.host,
+                ]))
+                .await;
+            let invocation = match invocation {
+                Ok(invocation) => invocation,
+                // the invocation is only visible once the agent picked the command up
+                Err(err) if err.to_string().contains("InvocationDoesNotExist") => Value::Null,
+                Err(err) => return Err(err),
+            };
+
+            match invocation["Status"].as_str() {
+                Some("Success") => {
+                    return Ok(invocation["StandardOutputContent"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string())
+                }
+                None | Some("Pending") | Some("InProgress") | Some("Delayed") => {
+                    if started.elapsed() > SSM_TIMEOUT {
+                        return Err(self.error(format!(
+                            "command {command_id} didn't complete within {}s",
+                            SSM_TIMEOUT.as_secs()
+                        )));
+                    }
+                }
+                Some(status) => {
+                    return Err(self.error(format!(
+                        "command {command_id} {status}: {}",
+                        invocation["StandardErrorContent"]
+                            .as_str()
+                            .unwrap_or_default()
+                            .trim()
+                    )))
+                }
+            }
+        }
+    }
+
+    async fn check_access(&self) -> UdiPgpResult<bool> {
+        let filter = format!("Key=InstanceIds,Values={}", self.target.host);
+        let information = self
+            .aws(self.aws_args(&["describe-instance-information", "--filters", &filter]))
+            .await?;
+        Ok(information["InstanceInformationList"][0]["PingStatus"].as_str() == Some("Online"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn send_command_args() {
+        let target = UdiPgpRemoteTarget::from_str("ssm://prod@i-0123456789abcdef0,web-1").unwrap();
+        let args = SsmTransport::new(target).send_command_args(
+            "osqueryi",
+            &["--json", "select * from users where username = 'x'"],
+        );
+        assert_eq!(&args[..3], ["--profile", "prod", "ssm"]);
+        assert!(args.contains(&"i-0123456789abcdef0".to_string()));
+        assert!(args.contains(&SSM_DOCUMENT.to_string()));
+        let parameters: Value =
+            serde_json::from_str(&args[args.iter().position(|a| a == "--parameters").unwrap() + 1])
+                .unwrap();
+        assert_eq!(
+            parameters["commands"][0],
+            r#"'osqueryi' '--json' 'select * from users where username = '\''x'\'''"#
+        );
+        assert_eq!(&args[args.len() - 2..], ["--output", "json"]);
+
+        // without a profile the default AWS CLI credentials are used
+        let target = UdiPgpRemoteTarget::from_str("ssm://i-0123456789abcdef0,web-1").unwrap();
+        assert_eq!(SsmTransport::new(target).aws_args(&[])[0], "ssm");
+    }
+}
@@ -0,0 +1,261 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use sqlparser::ast::{BinaryOperator, Expr, SetExpr, Statement, Value};
+
+use crate::error::{UdiPgpError, UdiPgpResult};
+
+use super::UdiPgpRemoteTarget;
+
+/// The virtual column holding a remote target's tags (e.g. `env=prod,role=db`).
+/// Comparing it in the `WHERE` clause selects the targets a query fans out to:
+/// `WHERE udi_pgp_ssh_target_tags = 'env=prod'` or
+/// `WHERE udi_pgp_ssh_target_tags IN ('role=db', 'role=web')`.
+pub const TARGET_TAGS_COLUMN: &str = "udi_pgp_ssh_target_tags";
+
+pub type TargetTags = BTreeMap<String, String>;
+
+/// Parse `key=value` pairs separated by `,`, e.g. `env=prod,role=db`.
+pub fn parse_tags(s: &str) -> UdiPgpResult<TargetTags> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((key, value)) if !key.trim().is_empty() => {
+                Ok((key.trim().to_string(), value.trim().to_string()))
+            }
+            _ => Err(UdiPgpError::SshConnectionParseError(format!(
+                "tags should be key=value pairs, e.g. env=prod. Got: {}",
+                pair
+            ))),
+        })
+        .collect()
+}
+
+/// Format tags the way [`parse_tags`] reads them.
+pub fn format_tags(tags: &TargetTags) -> String {
+    tags.iter()
+        .map(|(key, value)| format!("{key}={value}"))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// The targets selected by the `udi_pgp_ssh_target_tags` predicates of a query.
+/// Every predicate (they're `AND`ed) lists alternative tag sets and a target
+/// is selected when, for every predicate, it has all the tags of one of them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TargetTagFilter {
+    predicates: Vec<Vec<TargetTags>>,
+}
+
+impl TargetTagFilter {
+    /// Take the `udi_pgp_ssh_target_tags` predicates out of the `WHERE` clause
+    /// of `stmt` (the targets don't know the column) and return the filter
+    /// along with the statement which is sent to the targets.
+    pub fn from_statement(stmt: &Statement) -> UdiPgpResult<(TargetTagFilter, Statement)> {
+        let mut filter = TargetTagFilter::default();
+        let mut stmt = stmt.clone();
+        if let Statement::Query(query) = &mut stmt {
+            if let SetExpr::Select(select) = query.body.as_mut() {
+                if let Some(selection) = select.selection.take() {
+                    let mut remaining = Vec::new();
+                    for conjunct in conjuncts(selection) {
+                        if mentions_tags(&conjunct) {
+                            let mut alternatives = Vec::new();
+                            collect_alternatives(&conjunct, &mut alternatives)?;
+                            filter.predicates.push(alternatives);
+                        } else {
+                            remaining.push(conjunct);
+                        }
+                    }
+                    select.selection = remaining.into_iter().reduce(|left, right| Expr::BinaryOp {
+                        left: Box::new(left),
+                        op: BinaryOperator::And,
+                        right: Box::new(right),
+                    });
+                }
+            }
+        }
+        Ok((filter, stmt))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.predicates.is_empty()
+    }
+
+    pub fn matches(&self, target: &UdiPgpRemoteTarget) -> bool {
+        self.predicates.iter().all(|alternatives| {
+            alternatives.iter().any(|tags| {
+                tags.iter()
+                    .all(|(key, value)| target.tags.get(key) == Some(value))
+            })
+        })
+    }
+}
+
+/// e.g. `env=prod AND (role=db OR role=web,tier=1)`
+impl Display for TargetTagFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let predicates = self
+            .predicates
+            .iter()
+            .map(|alternatives| {
+                let alternatives = alternatives.iter().map(format_tags).collect::<Vec<_>>();
+                if alternatives.len() > 1 && self.predicates.len() > 1 {
+                    format!("({})", alternatives.join(" OR "))
+                } else {
+                    alternatives.join(" OR ")
+                }
+            })
+            .collect::<Vec<_>>();
+        f.write_str(&predicates.join(" AND "))
+    }
+}
+
+fn conjuncts(expr: Expr) -> Vec<Expr> {
+    match expr {
+        Expr::BinaryOp {
+            left,
+            op: BinaryOperator::And,
+            right,
+        } => {
+            let mut all = conjuncts(*left);
+            all.extend(conjuncts(*right));
+            all
+        }
+        Expr::Nested(nested)
+            if matches!(
+                nested.as_ref(),
+                Expr::BinaryOp {
+                    op: BinaryOperator::And,
+                    ..
+                }
+            ) =>
+        {
+            conjuncts(*nested)
+        }
+        expr => vec![expr],
+    }
+}
+
+fn mentions_tags(expr: &Expr) -> bool {
+    expr.to_string().contains(TARGET_TAGS_COLUMN)
+}
+
+fn is_tags_column(expr: &Expr) -> bool {
+    match expr {
+        Expr::Identifier(ident) => ident.value.to_lowercase() == TARGET_TAGS_COLUMN,
+        Expr::CompoundIdentifier(idents) => idents
+            .last()
+            .is_some_and(|ident| ident.value.to_lowercase() == TARGET_TAGS_COLUMN),
+        _ => false,
+    }
+}
+
+fn tags_literal(expr: &Expr) -> UdiPgpResult<TargetTags> {
+    match expr {
+        Expr::Value(Value::SingleQuotedString(s)) => parse_tags(s),
+        other => Err(UdiPgpError::QueryExecutionError(format!(
+            "{TARGET_TAGS_COLUMN} must be compared with a string such as 'env=prod'. Got: {other}"
+        ))),
+    }
+}
+
+/// The tag sets of `=`, `IN (...)` and `OR`ed comparisons on the tags column.
+fn collect_alternatives(expr: &Expr, alternatives: &mut Vec<TargetTags>) -> UdiPgpResult<()> {
+    match expr {
+        Expr::Nested(expr) => collect_alternatives(expr, alternatives),
+        Expr::BinaryOp {
+            left,
+            op: BinaryOperator::Or,
+            right,
+        } => {
+            collect_alternatives(left, alternatives)?;
+            collect_alternatives(right, alternatives)
+        }
+        Expr::BinaryOp {
+            left,
+            op: BinaryOperator::Eq,
+            right,
+        } if is_tags_column(left) => {
+            alternatives.push(tags_literal(right)?);
+            Ok(())
+        }
+        Expr::InList {
+            expr,
+            list,
+            negated: false,
+        } if is_tags_column(expr) => {
+            for item in list {
+                alternatives.push(tags_literal(item)?);
+            }
+            Ok(())
+        }
+        other => Err(UdiPgpError::QueryExecutionError(format!(
+            "Unsupported predicate: {other}. {TARGET_TAGS_COLUMN} only supports = and IN (...), on its own or ORed with other {TARGET_TAGS_COLUMN} comparisons"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::parser::UdiPgpQueryParser;
+
+    fn target(s: &str) -> UdiPgpRemoteTarget {
+        UdiPgpRemoteTarget::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn filter_targets_by_tags() {
+        let stmt = UdiPgpQueryParser::parse(
+            "SELECT name FROM processes WHERE udi_pgp_ssh_target_tags = 'env=prod' AND pid > 1 AND (udi_pgp_ssh_target_tags = 'role=db' OR udi_pgp_ssh_target_tags IN ('role=web'))",
+            false,
+        )
+        .unwrap();
+        let (filter, query) = TargetTagFilter::from_statement(&stmt.stmt).unwrap();
+        assert_eq!(
+            query.to_string(),
+            "SELECT name FROM processes WHERE pid > 1"
+        );
+        assert_eq!(filter.to_string(), "env=prod AND (role=db OR role=web)");
+
+        assert!(filter.matches(&target("ops@db-1,db-1,env=prod,role=db")));
+        assert!(filter.matches(&target("ops@web-1,web-1,role=web,env=prod")));
+        assert!(!filter.matches(&target("ops@db-2,db-2,env=staging,role=db")));
+        assert!(!filter.matches(&target("ops@cache-1,cache-1,env=prod")));
+
+        // tags of a single comparison must all match
+        let stmt = UdiPgpQueryParser::parse(
+            "SELECT name FROM processes WHERE udi_pgp_ssh_target_tags = 'env=prod,role=db'",
+            false,
+        )
+        .unwrap();
+        let (filter, query) = TargetTagFilter::from_statement(&stmt.stmt).unwrap();
+        assert_eq!(query.to_string(), "SELECT name FROM processes");
+        assert!(filter.matches(&target("ops@db-1,db-1,env=prod,role=db")));
+        assert!(!filter.matches(&target("ops@web-1,web-1,env=prod,role=web")));
+
+        // without tag predicates the query fans out to every target
+        let stmt = UdiPgpQueryParser::parse("SELECT name FROM processes", false).unwrap();
+        let (filter, _) = TargetTagFilter::from_statement(&stmt.stmt).unwrap();
+        assert!(filter.is_empty());
+        assert!(filter.matches(&target("ops@db-1,db-1")));
+
+        let stmt = UdiPgpQueryParser::parse(
+            "SELECT name FROM processes WHERE udi_pgp_ssh_target_tags LIKE '%prod%'",
+            false,
+        )
+        .unwrap();
+        assert!(TargetTagFilter::from_statement(&stmt.stmt).is_err());
+    }
+
+    #[test]
+    fn parse_and_format_tags() {
+        let tags = parse_tags("role=db, env=prod").unwrap();
+        assert_eq!(format_tags(&tags), "env=prod,role=db");
+        assert!(parse_tags("prod").is_err());
+        assert!(parse_tags("").unwrap().is_empty());
+    }
+}
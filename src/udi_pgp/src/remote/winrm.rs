@@ -0,0 +1,142 @@
+use async_trait::async_trait;
+
+use crate::error::{UdiPgpError, UdiPgpResult};
+
+use super::{run_local_cli, RemoteTransport, UdiPgpRemoteTarget};
+
+/// WinRM over plain HTTP, every other port is used with `-UseSSL`.
+const WINRM_HTTP_PORT: u16 = 5985;
+const WINRM_HTTPS_PORT: u16 = 5986;
+
+/// Executes commands on Windows hosts with PowerShell remoting, i.e.
+/// `Invoke-Command` run by a local `pwsh`.
+#[derive(Debug, Clone)]
+pub struct WinRmTransport {
+    target: UdiPgpRemoteTarget,
+}
+
+/// Quote `s` as a PowerShell literal string.
+fn ps_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+impl WinRmTransport {
+    pub fn new(target: UdiPgpRemoteTarget) -> Self {
+        WinRmTransport { target }
+    }
+
+    fn connection_args(&self) -> String {
+        let port = self.target.port.unwrap_or(WINRM_HTTPS_PORT);
+        let mut args = format!(
+            "-ComputerName {} -Port {}",
+            ps_quote(&self.target.host),
+            port
+        );
+        if port != WINRM_HTTP_PORT {
+            args.push_str(" -UseSSL");
+        }
+        args
+    }
+
+    /// Statements defining `$credential`, if the target has a password.
+    fn credential(&self) -> UdiPgpResult<Option<String>> {
+        let Some(password_env) = &self.target.password_env else {
+            return Ok(None);
+        };
+        if std::env::var_os(password_env).is_none() {
+            return Err(UdiPgpError::RemoteTransportError(
+                self.target.to_string(),
+                format!(
+                    "the {password_env} environment variable with the WinRM password isn't set"
+                ),
+            ));
+        }
+        Ok(Some(format!(
+            "$password = ConvertTo-SecureString ([Environment]::GetEnvironmentVariable({})) -AsPlainText -Force; \
+             $credential = New-Object System.Management.Automation.PSCredential({}, $password); ",
+            ps_quote(password_env),
+            ps_quote(&self.target.user)
+        )))
+    }
+
+    /// The script `pwsh` runs to execute `cmd` with `args` on the target.
+    fn script(&self, cmd: &str, args: &[&str]) -> UdiPgpResult<String> {
+        let credential = self.credential()?;
+        let args = args
+            .iter()
+            .map(|arg| ps_quote(arg))
+            .collect::<Vec<_>>()
+            .join(", ");
+        Ok(format!(
+            "$ErrorActionPreference = 'Stop'; {}Invoke-Command {}{} -ScriptBlock {{ param($cmd, $cmdArgs) & $cmd @cmdArgs }} -ArgumentList {}, @({})",
+            credential.as_deref().unwrap_or_default(),
+            self.connection_args(),
+            if credential.is_some() {
+                " -Credential $credential"
+            } else {
+                ""
+            },
+            ps_quote(cmd),
+            args
+        ))
+    }
+
+    async fn pwsh(&self, script: String) -> UdiPgpResult<String> {
+        let args = ["-NoProfile", "-NonInteractive", "-Command"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .chain(std::iter::once(script))
+            .collect::<Vec<_>>();
+        run_local_cli(&self.target, "pwsh", &args).await
+    }
+}
+
+#[async_trait]
+impl RemoteTransport for WinRmTransport {
+    async fn execute_command(&self, cmd: &str, args: Vec<&str>) -> UdiPgpResult<String> {
+        let script = self.script(cmd, &args)?;
+        self.pwsh(script).await
+    }
+
+    async fn check_access(&self) -> UdiPgpResult<bool> {
+        self.pwsh(format!(
+            "$ErrorActionPreference = 'Stop'; Test-WSMan {} | Out-Null",
+            self.connection_args()
+        ))
+        .await
+        .map(|_| true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn invoke_command_script() {
+        let target = UdiPgpRemoteTarget::from_str("winrm://ops@win-1.local,win-1").unwrap();
+        let script = WinRmTransport::new(target)
+            .script(
+                "osqueryi",
+                &["--json", "select * from os_version where name = 'x'"],
+            )
+            .unwrap();
+        assert_eq!(
+            script,
+            "$ErrorActionPreference = 'Stop'; Invoke-Command -ComputerName 'win-1.local' -Port 5986 -UseSSL -ScriptBlock { param($cmd, $cmdArgs) & $cmd @cmdArgs } -ArgumentList 'osqueryi', @('--json', 'select * from os_version where name = ''x''')"
+        );
+
+        let mut target =
+            UdiPgpRemoteTarget::from_str("winrm://ops@win-1.local:5985,win-1").unwrap();
+        target.password_env = Some("PATH".to_string());
+        let script = WinRmTransport::new(target).script("osqueryi", &[]).unwrap();
+        assert!(script.contains("-Port 5985 -Credential $credential"));
+        assert!(script.contains("PSCredential('ops', $password)"));
+
+        let mut target = UdiPgpRemoteTarget::from_str("winrm://ops@win-1.local,win-1").unwrap();
+        target.password_env = Some("SURVEILR_TEST_UNSET_WINRM_PASSWORD".to_string());
+        assert!(WinRmTransport::new(target).script("osqueryi", &[]).is_err());
+    }
+}
@@ -8,7 +8,7 @@ use crate::{
     error::{self, UdiPgpResult},
 };
 
-use super::{SqlSupplierMap, SqlSupplierType};
+use super::{SqlSupplierMap, SqlSupplierType, SupplierCapabilities};
 
 /// Factory for suppiers to register
 #[derive(Debug, Clone, Default)]
@@ -52,6 +52,13 @@ impl AdminSupplier {
         self.suppliers.read().await.len()
     }
 
+    /// The statements the supplier accepts, if there is such a supplier.
+    pub async fn capabilities(&self, identifier: &str) -> Option<SupplierCapabilities> {
+        let supplier = self.suppliers.read().await.get(identifier).cloned()?;
+        let capabilities = supplier.lock().await.capabilities();
+        Some(capabilities)
+    }
+
     pub async fn supplier(&self, identifier: &str) -> UdiPgpResult<Arc<Mutex<SqlSupplierType>>> {
         let suppliers = self.suppliers.read().await;
         Ok(suppliers.get(identifier).cloned().ok_or_else(|| {
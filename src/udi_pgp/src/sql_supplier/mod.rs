@@ -1,19 +1,155 @@
-use std::{collections::HashMap, fmt, sync::Arc};
+use std::{
+    collections::HashMap,
+    fmt,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
 
 use async_trait::async_trait;
+use bitflags::bitflags;
+use futures::{stream, stream::BoxStream, Stream, StreamExt, TryStreamExt};
 use pgwire::api::results::FieldInfo;
 use tokio::sync::Mutex;
 use uuid::Uuid;
 
 use crate::{
     config::{Supplier, SupplierType},
-    error::UdiPgpResult,
-    parser::stmt::UdiPgpStatment,
+    error::{UdiPgpError, UdiPgpErrorSeverity, UdiPgpResult},
+    parser::stmt::{UdiPgpStatment, WriteOperation},
     Row,
 };
 
 pub mod admin;
 
+bitflags! {
+    /// The statements a supplier answers. Suppliers are read-only unless they
+    /// opt into writes, the processor rejects the writes they don't accept.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct SupplierCapabilities: u8 {
+        const READ = 1;
+        const INSERT = 1 << 1;
+        const UPDATE = 1 << 2;
+    }
+}
+
+/// e.g. `read,insert`
+impl fmt::Display for SupplierCapabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = self
+            .iter_names()
+            .map(|(name, _)| name.to_lowercase())
+            .collect::<Vec<_>>();
+        f.write_str(&names.join(","))
+    }
+}
+
+impl From<WriteOperation> for SupplierCapabilities {
+    fn from(operation: WriteOperation) -> Self {
+        match operation {
+            WriteOperation::Insert => SupplierCapabilities::INSERT,
+            WriteOperation::Update => SupplierCapabilities::UPDATE,
+        }
+    }
+}
+
+/// One row of the answer to an `EXPLAIN`, e.g. `target | ssh://ops@web-01:22`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryPlanStep {
+    pub step: String,
+    pub detail: String,
+}
+
+impl QueryPlanStep {
+    pub fn new(step: impl Into<String>, detail: impl Into<String>) -> Self {
+        QueryPlanStep {
+            step: step.into(),
+            detail: detail.into(),
+        }
+    }
+}
+
+/// A target which failed while the others answered a query, its rows are
+/// missing from the result. Listed in `udi_pgp_errors` and sent as a notice.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TargetError {
+    /// The target's identifier, as in the `udi_pgp_ssh_host_id` column
+    pub host_id: String,
+    /// The target's connection string, as in the `udi_pgp_ssh_target` column
+    pub target: String,
+    pub message: String,
+}
+
+/// The targets which failed while answering a query. The rows of the query
+/// fill it in as the targets answer, it's complete once they ended.
+#[derive(Debug, Clone, Default)]
+pub struct TargetErrors(Arc<std::sync::Mutex<Vec<TargetError>>>);
+
+impl TargetErrors {
+    pub fn push(&self, error: TargetError) {
+        self.0
+            .lock()
+            .expect("target errors lock poisoned")
+            .push(error);
+    }
+
+    pub fn take(&self) -> Vec<TargetError> {
+        std::mem::take(&mut *self.0.lock().expect("target errors lock poisoned"))
+    }
+}
+
+/// The rows answering a query, read as the supplier produces them so that a
+/// large result is never held in memory all at once.
+pub struct RowStream {
+    rows: BoxStream<'static, UdiPgpResult<Vec<Row>>>,
+    target_errors: TargetErrors,
+}
+
+impl RowStream {
+    pub fn new(rows: impl Stream<Item = UdiPgpResult<Vec<Row>>> + Send + 'static) -> Self {
+        RowStream {
+            rows: rows.boxed(),
+            target_errors: TargetErrors::default(),
+        }
+    }
+
+    /// Rows the supplier already holds, e.g. read from a single command's output.
+    pub fn from_rows(rows: Vec<Vec<Row>>) -> Self {
+        RowStream::new(stream::iter(rows.into_iter().map(Ok)))
+    }
+
+    /// The failed targets the rows fill in.
+    pub fn with_target_errors(mut self, target_errors: TargetErrors) -> Self {
+        self.target_errors = target_errors;
+        self
+    }
+
+    pub fn target_errors(&self) -> TargetErrors {
+        self.target_errors.clone()
+    }
+
+    /// Read every row, e.g. to cache the result.
+    pub async fn collect_rows(self) -> UdiPgpResult<Vec<Vec<Row>>> {
+        self.rows.try_collect().await
+    }
+}
+
+impl Stream for RowStream {
+    type Item = UdiPgpResult<Vec<Row>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rows.poll_next_unpin(cx)
+    }
+}
+
+impl fmt::Debug for RowStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RowStream")
+            .field("target_errors", &self.target_errors)
+            .finish_non_exhaustive()
+    }
+}
+
 #[async_trait]
 pub trait SqlSupplier: ClonableSqlSupplier {
     fn name(&self) -> &str;
@@ -22,7 +158,31 @@ pub trait SqlSupplier: ClonableSqlSupplier {
     fn add_session_id(&mut self, session_id: Uuid) -> UdiPgpResult<()>;
     fn generate_new(&self, supplier: Supplier) -> UdiPgpResult<SqlSupplierType>;
     async fn schema(&mut self, stmt: &mut UdiPgpStatment) -> UdiPgpResult<Vec<FieldInfo>>;
-    async fn execute(&mut self, stmt: &UdiPgpStatment) -> UdiPgpResult<Vec<Vec<Row>>>;
+    /// The rows answering `stmt`, in the columns `schema` announced. They may
+    /// be read after the supplier answered other queries, so the stream can't
+    /// borrow from the supplier.
+    async fn execute(&mut self, stmt: &UdiPgpStatment) -> UdiPgpResult<RowStream>;
+
+    /// How the supplier would answer `stmt`, without executing it: the query it
+    /// runs, where it runs it and which parts of the query it pushes down.
+    fn explain(&self, stmt: &UdiPgpStatment) -> UdiPgpResult<Vec<QueryPlanStep>> {
+        Ok(vec![QueryPlanStep::new("query", stmt.query.clone())])
+    }
+
+    /// Statements the supplier accepts, only `SELECT`s unless overridden.
+    fn capabilities(&self) -> SupplierCapabilities {
+        SupplierCapabilities::READ
+    }
+
+    /// Apply an `INSERT` or `UPDATE` the supplier opted into through
+    /// `capabilities` and return the number of affected rows.
+    async fn write(&mut self, stmt: &UdiPgpStatment) -> UdiPgpResult<usize> {
+        Err(UdiPgpError::SupplierError(
+            self.name().to_string(),
+            UdiPgpErrorSeverity::Warning,
+            format!("read-only supplier can't apply: {}", stmt.query),
+        ))
+    }
 }
 
 pub type SqlSupplierType = Box<dyn SqlSupplier + Send + Sync>;
@@ -52,3 +212,58 @@ impl fmt::Debug for dyn SqlSupplier + Send + Sync {
         f.debug_tuple("SqlSupplier").field(&self.name()).finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::UdiPgpQueryParser;
+
+    #[derive(Debug, Clone)]
+    struct ReadOnlySupplier;
+
+    #[async_trait]
+    impl SqlSupplier for ReadOnlySupplier {
+        fn name(&self) -> &str {
+            "read-only"
+        }
+
+        fn supplier_type(&self) -> SupplierType {
+            SupplierType::Osquery
+        }
+
+        fn update(&mut self, _supplier: Supplier) -> UdiPgpResult<()> {
+            Ok(())
+        }
+
+        fn add_session_id(&mut self, _session_id: Uuid) -> UdiPgpResult<()> {
+            Ok(())
+        }
+
+        fn generate_new(&self, _supplier: Supplier) -> UdiPgpResult<SqlSupplierType> {
+            Ok(Box::new(self.clone()))
+        }
+
+        async fn schema(&mut self, _stmt: &mut UdiPgpStatment) -> UdiPgpResult<Vec<FieldInfo>> {
+            Ok(vec![])
+        }
+
+        async fn execute(&mut self, _stmt: &UdiPgpStatment) -> UdiPgpResult<RowStream> {
+            Ok(RowStream::from_rows(vec![]))
+        }
+    }
+
+    #[tokio::test]
+    async fn suppliers_are_read_only_by_default() {
+        let mut supplier = ReadOnlySupplier;
+        let stmt =
+            UdiPgpQueryParser::parse("INSERT INTO notes (note) VALUES ('x')", false).unwrap();
+        let operation = stmt.write_operation().unwrap();
+        assert_eq!(supplier.capabilities(), SupplierCapabilities::READ);
+        assert_eq!(
+            (SupplierCapabilities::READ | SupplierCapabilities::UPDATE).to_string(),
+            "read,update"
+        );
+        assert!(!supplier.capabilities().contains(operation.into()));
+        assert!(supplier.write(&stmt).await.is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use std::{collections::HashMap, fmt::Debug};
+use std::{collections::HashMap, fmt::Debug, sync::Arc};
 
 use async_trait::async_trait;
 use derive_new::new;
@@ -17,16 +17,40 @@ use pgwire::{
         PgWireFrontendMessage,
     },
 };
-use tokio::sync::{mpsc, oneshot};
+use tokio::sync::{mpsc, oneshot, RwLock};
 use tracing::{debug, error, info};
 
 use crate::{
-    config::UdiPgpConfig,
+    config::{Supplier, UdiPgpConfig},
     error::{UdiPgpError, UdiPgpResult},
     processor::UdiPgpProcessor,
+    sql_supplier::{admin::AdminSupplier, SupplierCapabilities},
     state::messages::Message,
 };
 
+/// Features of the server, announced in the `udi_pgp_capabilities` parameter
+/// so clients don't have to probe for them.
+const UDI_PGP_CAPABILITIES: [&str; 9] = [
+    // `EXPLAIN` answers with the supplier's plan
+    "explain",
+    // `udi_pgp_*` introspection tables
+    "introspection",
+    // `SET udi_pgp_serve_*` configuration queries
+    "serve-config",
+    // the `udi_pgp_ssh_target_tags` column selects remote targets
+    "target-tags",
+    // failed remote targets are sent as notices and listed in `udi_pgp_errors`
+    "target-errors",
+    // suspended portals (`Execute` row limits) and `DECLARE`/`FETCH` cursors
+    "cursors",
+    // suppliers with a `cache-ttl` answer identical queries from memory
+    "query-cache",
+    // prepared statements bind their `$n` parameters
+    "parameters",
+    // users are granted roles and tables per supplier, listed in `udi_pgp_grants`
+    "grants",
+];
+
 pub struct UdiPgpAuthSource {
     config_tx: mpsc::Sender<Message>,
 }
@@ -104,12 +128,62 @@ pub struct UdiPgpParameters {
 impl UdiPgpParameters {
     pub fn new() -> UdiPgpParameters {
         UdiPgpParameters {
-            version: "15".into(),
+            // clients parse the leading PostgreSQL version, the rest is informative
+            version: format!("15.0 (surveilr {})", env!("CARGO_PKG_VERSION")),
             date_style: "ISO, MDY".into(),
         }
     }
 }
 
+/// Parameters describing UDI-PGP itself, sent along the standard ones at
+/// startup: the surveilr version, the suppliers, the server's features and
+/// the statements the supplier the client connected to accepts.
+fn udi_pgp_parameters(
+    suppliers: &HashMap<String, Supplier>,
+    supplier_capabilities: Option<SupplierCapabilities>,
+) -> HashMap<String, String> {
+    let mut suppliers = suppliers
+        .iter()
+        .map(|(id, supplier)| format!("{id}:{}", supplier.supplier_type))
+        .collect::<Vec<_>>();
+    suppliers.sort();
+
+    let mut params = HashMap::with_capacity(4);
+    params.insert(
+        "surveilr_version".to_owned(),
+        env!("CARGO_PKG_VERSION").to_owned(),
+    );
+    params.insert("udi_pgp_suppliers".to_owned(), suppliers.join(","));
+    params.insert(
+        "udi_pgp_capabilities".to_owned(),
+        UDI_PGP_CAPABILITIES.join(","),
+    );
+    if let Some(capabilities) = supplier_capabilities {
+        params.insert(
+            "udi_pgp_supplier_capabilities".to_owned(),
+            capabilities.to_string(),
+        );
+    }
+    params
+}
+
+/// The parameters of `base` along with the ones of the connection.
+struct ConnectionParameters<'a, P> {
+    base: &'a P,
+    extra: HashMap<String, String>,
+}
+
+impl<P: ServerParameterProvider> ServerParameterProvider for ConnectionParameters<'_, P> {
+    fn server_parameters<C>(&self, client: &C) -> Option<HashMap<String, String>>
+    where
+        C: ClientInfo,
+    {
+        let mut params = self.base.server_parameters(client).unwrap_or_default();
+        params.extend(self.extra.clone());
+        Some(params)
+    }
+}
+
 impl ServerParameterProvider for UdiPgpParameters {
     fn server_parameters<C>(&self, _client: &C) -> Option<HashMap<String, String>>
     where
@@ -130,6 +204,7 @@ pub struct UdiPgpStartupHandler<A, P> {
     auth_source: A,
     parameter_provider: P,
     config_tx: mpsc::Sender<Message>,
+    exec_supplier: Arc<RwLock<AdminSupplier>>,
 }
 
 impl<V: AuthSource, P: ServerParameterProvider> UdiPgpStartupHandler<V, P> {
@@ -154,6 +229,53 @@ impl<V: AuthSource, P: ServerParameterProvider> UdiPgpStartupHandler<V, P> {
             }
         }
     }
+
+    /// The parameters sent to a client connected to `supplier_id` once it's
+    /// authenticated.
+    async fn connection_parameters(
+        &self,
+        supplier_id: Option<String>,
+        config: &UdiPgpConfig,
+    ) -> ConnectionParameters<'_, P> {
+        let supplier_capabilities = match supplier_id {
+            Some(supplier_id) => {
+                self.exec_supplier
+                    .read()
+                    .await
+                    .capabilities(&supplier_id)
+                    .await
+            }
+            None => None,
+        };
+        ConnectionParameters {
+            base: &self.parameter_provider,
+            extra: udi_pgp_parameters(&config.suppliers, supplier_capabilities),
+        }
+    }
+
+    /// List the authenticated client in `udi_pgp_sessions`
+    async fn start_session(&self, msg: Message) {
+        if let Err(err) = self.config_tx.send(msg).await {
+            error!("Failed to record session: {}", err);
+        }
+    }
+}
+
+/// The supplier the client connected to, i.e. its database.
+fn client_supplier_id<C: ClientInfo>(client: &C) -> Option<String> {
+    UdiPgpProcessor::extract_supplier_and_database(
+        client.metadata().get("database").map(|x| x.as_str()),
+    )
+    .ok()
+    .map(|(supplier_id, _)| supplier_id)
+}
+
+fn start_session_message<C: ClientInfo>(client: &C) -> Message {
+    Message::StartSession {
+        client_addr: client.socket_addr(),
+        username: client.metadata().get("user").cloned(),
+        supplier_id: client_supplier_id(client),
+    }
 }
 
 #[async_trait]
@@ -177,7 +299,11 @@ impl<V: AuthSource, P: ServerParameterProvider> StartupHandler for UdiPgpStartup
 
                 let config = self.read_config().await?;
                 if config.suppliers.is_empty() {
-                    finish_authentication(client, &self.parameter_provider).await;
+                    let parameters = self
+                        .connection_parameters(client_supplier_id(client), &config)
+                        .await;
+                    finish_authentication(client, &parameters).await;
+                    self.start_session(start_session_message(client)).await;
                     return Ok(());
                 }
 
@@ -193,7 +319,12 @@ impl<V: AuthSource, P: ServerParameterProvider> StartupHandler for UdiPgpStartup
                 let login_info = LoginInfo::from_client_info(client);
                 let pass = self.auth_source.get_password(&login_info).await?;
                 if pass.password() == pwd.password.as_bytes() {
-                    finish_authentication(client, &self.parameter_provider).await
+                    let config = self.read_config().await?;
+                    let parameters = self
+                        .connection_parameters(client_supplier_id(client), &config)
+                        .await;
+                    finish_authentication(client, &parameters).await;
+                    self.start_session(start_session_message(client)).await;
                 } else {
                     let error_info = ErrorInfo::new(
                         "FATAL".to_owned(),
@@ -213,3 +344,48 @@ impl<V: AuthSource, P: ServerParameterProvider> StartupHandler for UdiPgpStartup
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::SupplierType, UdiPgpModes};
+
+    #[test]
+    fn announce_suppliers_and_capabilities() {
+        let suppliers = HashMap::from([
+            (
+                "tasks".to_string(),
+                Supplier::new(SupplierType::Tasks, UdiPgpModes::Local, None, None, vec![]),
+            ),
+            (
+                "fleet".to_string(),
+                Supplier::new(
+                    SupplierType::Osquery,
+                    UdiPgpModes::Remote,
+                    None,
+                    None,
+                    vec![],
+                ),
+            ),
+        ]);
+        let params = udi_pgp_parameters(
+            &suppliers,
+            Some(SupplierCapabilities::READ | SupplierCapabilities::INSERT),
+        );
+        assert_eq!(params["udi_pgp_suppliers"], "fleet:osquery,tasks:tasks");
+        assert_eq!(params["udi_pgp_supplier_capabilities"], "read,insert");
+        assert!(params["udi_pgp_capabilities"]
+            .split(',')
+            .any(|capability| capability == "explain"));
+        assert_eq!(params["surveilr_version"], env!("CARGO_PKG_VERSION"));
+
+        // connected to the admin supplier, or an unknown one
+        let params = udi_pgp_parameters(&HashMap::new(), None);
+        assert_eq!(params["udi_pgp_suppliers"], "");
+        assert!(!params.contains_key("udi_pgp_supplier_capabilities"));
+
+        assert!(UdiPgpParameters::new()
+            .version
+            .starts_with(&format!("15.0 (surveilr {}", env!("CARGO_PKG_VERSION"))));
+    }
+}
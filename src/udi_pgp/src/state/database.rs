@@ -1,9 +1,14 @@
 use super::StateManager;
 
-use crate::{config::UdiPgpConfig, observability::log_entry::QueryLogEntry};
+use crate::{
+    auth::Role, config::UdiPgpConfig, introspection::supplier_columns,
+    observability::log_entry::QueryLogEntry, sql_supplier::TargetError,
+    state::messages::CacheLookup,
+};
+use chrono::Utc;
 use common::{execute_sql, execute_sql_no_args};
 use rusqlite::{Connection, Result as RusqliteResult, ToSql};
-use tracing::info;
+use tracing::{error, info};
 use uuid::Uuid;
 
 execute_sql_no_args!(clear_suppliers, "DELETE FROM udi_pgp_supplier");
@@ -37,7 +42,7 @@ execute_sql!(
 
 execute_sql!(
     upsert_udi_pgp_observe_query_exec,
-    "INSERT INTO udi_pgp_observe_query_exec (udi_pgp_observe_query_exec_id, query_text, exec_start_at, exec_finish_at, elaboration, exec_msg, exec_status, governance, created_at, created_by) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, CURRENT_TIMESTAMP, 'UNKNOWN')
+    "INSERT INTO udi_pgp_observe_query_exec (udi_pgp_observe_query_exec_id, query_text, exec_start_at, exec_finish_at, elaboration, exec_msg, exec_status, governance, created_at, created_by) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, CURRENT_TIMESTAMP, ?9)
      ON CONFLICT(udi_pgp_observe_query_exec_id) DO UPDATE SET
      query_text=excluded.query_text,
      exec_start_at=excluded.exec_start_at,
@@ -53,7 +58,8 @@ execute_sql!(
     elaboration: String,
     exec_msg: String,
     exec_status: u8,
-    governance: Option<String>
+    governance: Option<String>,
+    created_by: String
 );
 
 execute_sql!(
@@ -74,7 +80,196 @@ execute_sql!(
     status_text: String
 );
 
+execute_sql_no_args!(clear_udi_pgp_sessions, "DELETE FROM udi_pgp_sessions");
+
+execute_sql!(
+    insert_udi_pgp_session,
+    "INSERT INTO udi_pgp_sessions (udi_pgp_session_id, client_addr, username, supplier_id, connected_at, queries_executed, last_activity_at, created_at, created_by) VALUES (?1, ?2, ?3, ?4, ?5, 0, ?5, CURRENT_TIMESTAMP, 'UNKNOWN')",
+    udi_pgp_session_id: String,
+    client_addr: String,
+    username: Option<String>,
+    supplier_id: Option<String>,
+    connected_at: String
+);
+
+execute_sql!(
+    update_udi_pgp_session_activity,
+    "UPDATE udi_pgp_sessions SET queries_executed = queries_executed + 1, last_activity_at = ?2, updated_at = CURRENT_TIMESTAMP WHERE client_addr = ?1",
+    client_addr: String,
+    last_activity_at: String
+);
+
+execute_sql!(
+    delete_udi_pgp_session,
+    "DELETE FROM udi_pgp_sessions WHERE client_addr = ?1",
+    client_addr: String
+);
+
+execute_sql!(
+    insert_udi_pgp_error,
+    "INSERT INTO udi_pgp_errors (udi_pgp_error_id, query_id, supplier_id, host_id, target, message, created_at, created_by) VALUES (?1, ?2, ?3, ?4, ?5, ?6, CURRENT_TIMESTAMP, 'UNKNOWN')",
+    udi_pgp_error_id: String,
+    query_id: String,
+    supplier_id: String,
+    host_id: String,
+    target: String,
+    message: String
+);
+
+execute_sql_no_args!(clear_udi_pgp_cache, "DELETE FROM udi_pgp_cache");
+
+execute_sql!(
+    record_udi_pgp_cache_hit,
+    "INSERT INTO udi_pgp_cache (udi_pgp_cache_id, supplier_id, query_text, hits, last_hit_at, created_at, created_by) VALUES (?1, ?2, ?3, 1, ?4, CURRENT_TIMESTAMP, 'UNKNOWN')
+     ON CONFLICT(supplier_id, query_text) DO UPDATE SET
+     hits = hits + 1,
+     last_hit_at = excluded.last_hit_at,
+     updated_at = CURRENT_TIMESTAMP",
+    udi_pgp_cache_id: String,
+    supplier_id: String,
+    query_text: String,
+    last_hit_at: String
+);
+
+execute_sql!(
+    record_udi_pgp_cache_miss,
+    "INSERT INTO udi_pgp_cache (udi_pgp_cache_id, supplier_id, query_text, misses, rows_cached, cached_at, expires_at, created_at, created_by) VALUES (?1, ?2, ?3, 1, ?4, ?5, ?6, CURRENT_TIMESTAMP, 'UNKNOWN')
+     ON CONFLICT(supplier_id, query_text) DO UPDATE SET
+     misses = misses + 1,
+     rows_cached = excluded.rows_cached,
+     cached_at = excluded.cached_at,
+     expires_at = excluded.expires_at,
+     updated_at = CURRENT_TIMESTAMP",
+    udi_pgp_cache_id: String,
+    supplier_id: String,
+    query_text: String,
+    rows_cached: usize,
+    cached_at: String,
+    expires_at: String
+);
+
+execute_sql_no_args!(clear_udi_pgp_schema, "DELETE FROM udi_pgp_schema");
+
+execute_sql!(
+    insert_udi_pgp_schema,
+    "INSERT INTO udi_pgp_schema (udi_pgp_schema_id, supplier_id, table_name, column_name, column_type, column_index, description, platforms, source, created_at, created_by) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, CURRENT_TIMESTAMP, 'UNKNOWN')",
+    udi_pgp_schema_id: String,
+    supplier_id: String,
+    table_name: String,
+    column_name: String,
+    column_type: String,
+    column_index: usize,
+    description: Option<String>,
+    platforms: Option<String>,
+    source: String
+);
+
+execute_sql_no_args!(clear_udi_pgp_grants, "DELETE FROM udi_pgp_grants");
+
+execute_sql!(
+    insert_udi_pgp_grant,
+    "INSERT INTO udi_pgp_grants (udi_pgp_grant_id, supplier_id, username, role, can_write, can_configure, allowed_tables, allowed_queries, created_at, created_by) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, CURRENT_TIMESTAMP, 'UNKNOWN')",
+    udi_pgp_grant_id: String,
+    supplier_id: String,
+    username: String,
+    role: String,
+    can_write: bool,
+    can_configure: bool,
+    allowed_tables: Option<String>,
+    allowed_queries: Option<String>
+);
+
 impl StateManager {
+    /// Sessions of a previous run are no longer connected
+    pub fn clear_sessions(&self) {
+        clear_udi_pgp_sessions(&self.conn).expect("Failed to clear sessions from DB");
+    }
+
+    pub fn start_session(
+        &self,
+        client_addr: String,
+        username: Option<String>,
+        supplier_id: Option<String>,
+    ) {
+        info!("Recording session for {client_addr}");
+        insert_udi_pgp_session(
+            &self.conn,
+            Uuid::new_v4().to_string(),
+            client_addr,
+            username,
+            supplier_id,
+            Utc::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
+        )
+        .expect("Failed to insert session");
+    }
+
+    pub fn record_session_query(&self, client_addr: String) {
+        update_udi_pgp_session_activity(
+            &self.conn,
+            client_addr,
+            Utc::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
+        )
+        .expect("Failed to update session activity");
+    }
+
+    pub fn end_session(&self, client_addr: String) {
+        info!("Removing session for {client_addr}");
+        delete_udi_pgp_session(&self.conn, client_addr).expect("Failed to delete session");
+    }
+
+    pub fn record_target_errors(
+        &self,
+        query_id: String,
+        supplier_id: String,
+        errors: &[TargetError],
+    ) {
+        for error in errors {
+            insert_udi_pgp_error(
+                &self.conn,
+                Uuid::new_v4().to_string(),
+                query_id.clone(),
+                supplier_id.clone(),
+                error.host_id.clone(),
+                error.target.clone(),
+                error.message.clone(),
+            )
+            .expect("Failed to record target error");
+        }
+    }
+
+    /// Results cached by a previous run are gone
+    pub fn clear_query_cache(&self) {
+        clear_udi_pgp_cache(&self.conn).expect("Failed to clear query cache from DB");
+    }
+
+    pub fn record_cache_lookup(
+        &self,
+        supplier_id: String,
+        query_text: String,
+        lookup: CacheLookup,
+    ) {
+        let now = Utc::now().format("%Y-%m-%dT%H:%M:%S").to_string();
+        match lookup {
+            CacheLookup::Hit => record_udi_pgp_cache_hit(
+                &self.conn,
+                Uuid::new_v4().to_string(),
+                supplier_id,
+                query_text,
+                now,
+            ),
+            CacheLookup::Miss { rows, expires_at } => record_udi_pgp_cache_miss(
+                &self.conn,
+                Uuid::new_v4().to_string(),
+                supplier_id,
+                query_text,
+                rows,
+                now,
+                expires_at,
+            ),
+        }
+        .expect("Failed to record query cache lookup");
+    }
+
     pub fn update_suppliers(&self, config: &UdiPgpConfig) {
         let conn = &self.conn;
 
@@ -86,7 +281,8 @@ impl StateManager {
             let ssh_targets_json =
                 serde_json::to_string(&supplier.ssh_targets).unwrap_or("null".to_string());
             let auth_json = serde_json::to_string(&supplier.auth).unwrap_or("null".to_string());
-            let atc_file_path = supplier.atc_file_path.clone();
+            let atc_files = supplier.atc_files();
+            let atc_file_path = (!atc_files.is_empty()).then(|| atc_files.join(", "));
 
             insert_supplier(
                 conn,
@@ -104,6 +300,66 @@ impl StateManager {
         info!("Inserting suppliers into DB was succesful");
     }
 
+    /// List the tables and columns of every supplier in `udi_pgp_schema`. A
+    /// supplier whose schema or ATC files can't be read is left out.
+    pub fn update_schema(&self, config: &UdiPgpConfig) {
+        let conn = &self.conn;
+        clear_udi_pgp_schema(conn).expect("Failed to clear schema from DB");
+
+        for (id, supplier) in &config.suppliers {
+            let columns = match supplier_columns(supplier) {
+                Ok(columns) => columns,
+                Err(err) => {
+                    error!("Failed to read the schema of supplier {id}: {err}");
+                    continue;
+                }
+            };
+            for column in columns {
+                insert_udi_pgp_schema(
+                    conn,
+                    Uuid::new_v4().to_string(),
+                    id.to_string(),
+                    column.table_name,
+                    column.column_name,
+                    column.column_type,
+                    column.column_index,
+                    column.description,
+                    column.platforms,
+                    column.source,
+                )
+                .expect("Failed to insert schema");
+            }
+        }
+    }
+
+    /// List what every user of every supplier may do in `udi_pgp_grants`, the
+    /// allowed tables and queries are `NULL` when they're unrestricted.
+    pub fn update_grants(&self, config: &UdiPgpConfig) {
+        let conn = &self.conn;
+        clear_udi_pgp_grants(conn).expect("Failed to clear grants from DB");
+
+        let restriction = |values: &[String]| {
+            (!values.is_empty()).then(|| serde_json::to_string(values).unwrap_or_default())
+        };
+        for (id, supplier) in &config.suppliers {
+            for auth in &supplier.auth {
+                let admin = auth.role() == Role::Admin;
+                insert_udi_pgp_grant(
+                    conn,
+                    Uuid::new_v4().to_string(),
+                    id.to_string(),
+                    auth.user().to_string(),
+                    auth.role().to_string(),
+                    admin,
+                    admin,
+                    restriction(auth.allowed_tables()),
+                    restriction(auth.allowed_queries()),
+                )
+                .expect("Failed to insert grants");
+            }
+        }
+    }
+
     pub fn update_core(&self, config: &UdiPgpConfig) {
         let conn = &self.conn;
 
@@ -148,6 +404,10 @@ impl StateManager {
             exec_msg,
             exec_status,
             None,
+            entry
+                .username
+                .clone()
+                .unwrap_or_else(|| "UNKNOWN".to_string()),
         )
         .expect("Failed to insert log");
 
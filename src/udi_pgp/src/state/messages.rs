@@ -5,6 +5,7 @@ use tracing::{span, Level};
 use crate::{
     config::{Supplier, UdiPgpConfig},
     observability::{log_entry::QueryLogEntry, QueryLogEntryMap},
+    sql_supplier::TargetError,
 };
 
 /// Update the start, end times and the events of an entry
@@ -18,6 +19,14 @@ pub enum UpdateLogEntry {
     Event(String, Level),
 }
 
+/// Outcome of looking a query up in the query cache
+pub enum CacheLookup {
+    /// Answered with the cached result
+    Hit,
+    /// Executed by the supplier, its rows are cached until `expires_at`
+    Miss { rows: usize, expires_at: String },
+}
+
 #[allow(dead_code)]
 pub enum Message {
     /// Updates the metrics and health addresses
@@ -42,6 +51,31 @@ pub enum Message {
         span_id: span::Id,
         msg: UpdateLogEntry,
     },
+    /// A client connected and authenticated, it is listed in `udi_pgp_sessions`
+    /// until it disconnects
+    StartSession {
+        client_addr: SocketAddr,
+        username: Option<String>,
+        supplier_id: Option<String>,
+    },
+    /// A connected client executed a query
+    RecordSessionQuery(SocketAddr),
+    /// A client disconnected
+    EndSession(SocketAddr),
+    /// Targets of a supplier failed while executing a query, they are listed
+    /// in `udi_pgp_errors`
+    RecordTargetErrors {
+        query_id: String,
+        supplier_id: String,
+        errors: Vec<TargetError>,
+    },
+    /// A supplier's query was looked up in the query cache, the hits and
+    /// misses are counted in `udi_pgp_cache`
+    RecordCacheLookup {
+        supplier_id: String,
+        query_text: String,
+        lookup: CacheLookup,
+    },
     /// Create a record for SET query, i.e a config query
     CreateConfigQueryLog {
         query_id: String,
@@ -18,7 +18,7 @@
 //!
 //! Example of sending a configuration update:
 //!
-//! ```no_run
+//! ```ignore
 //! # use tokio::sync::mpsc;
 //! # use tokio::task;
 //! # use tokio::time::Duration;
@@ -70,6 +70,7 @@ impl StateManager {
     /// Initialize the state manager, load the databse with tables and insert the core config
     pub fn init(config: &UdiPgpConfig) -> anyhow::Result<Self> {
         let connection = Connection::open(&config.admin_state_fs_path)?;
+        common::sqlite_helpers::set_busy_timeout(&connection)?;
 
         admin_ddl(&connection)?;
         let admindb_path = config.admin_state_fs_path.to_str().unwrap();
@@ -86,11 +87,16 @@ impl StateManager {
             None,
         )?;
 
-        Ok(StateManager {
+        let state_manager = StateManager {
             config: Arc::new(Mutex::new(config.clone())),
             log_entries: Arc::new(Mutex::new(HashMap::new())),
             conn: connection,
-        })
+        };
+        state_manager.clear_sessions();
+        state_manager.clear_query_cache();
+        state_manager.update_schema(config);
+        state_manager.update_grants(config);
+        Ok(state_manager)
     }
 
     pub async fn handle(&mut self, mut rx: mpsc::Receiver<Message>) {
@@ -130,6 +136,8 @@ impl StateManager {
                     config.suppliers.insert(id, supplier);
                     debug!("Supplier updated successfully",);
                     self.update_suppliers(&config);
+                    self.update_schema(&config);
+                    self.update_grants(&config);
                 }
                 Message::ReadLogEntries(response_tx) => {
                     debug!("Attempting to acquire lock to read log entries");
@@ -163,6 +171,25 @@ impl StateManager {
                         UpdateLogEntry::StartTime(t) => e.exec_start_at = Some(t),
                     });
                 }
+                Message::StartSession {
+                    client_addr,
+                    username,
+                    supplier_id,
+                } => self.start_session(client_addr.to_string(), username, supplier_id),
+                Message::RecordSessionQuery(client_addr) => {
+                    self.record_session_query(client_addr.to_string())
+                }
+                Message::EndSession(client_addr) => self.end_session(client_addr.to_string()),
+                Message::RecordTargetErrors {
+                    query_id,
+                    supplier_id,
+                    errors,
+                } => self.record_target_errors(query_id, supplier_id, &errors),
+                Message::RecordCacheLookup {
+                    supplier_id,
+                    query_text,
+                    lookup,
+                } => self.record_cache_lookup(supplier_id, query_text, lookup),
                 Message::CreateConfigQueryLog {
                     query_id,
                     query_text,
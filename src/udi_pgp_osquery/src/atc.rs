@@ -0,0 +1,344 @@
+use std::{collections::HashMap, fs, path::PathBuf, time::SystemTime};
+
+use chrono::{DateTime, Utc};
+use serde_json::{Map, Value};
+use tracing::{info, warn};
+use udi_pgp::error::{UdiPgpError, UdiPgpResult};
+use uuid::Uuid;
+
+use crate::schema::{self, OsquerySchema};
+
+/// The virtual table listing the tables loaded from the ATC files, e.g.
+/// `SELECT table_name, atc_file_path FROM osquery_atc_tables`
+pub const ATC_TABLES_TABLE: &str = "osquery_atc_tables";
+
+/// Columns of the virtual `osquery_atc_tables` table, in the order used for `SELECT *`.
+pub const ATC_TABLES_COLUMNS: [&str; 8] = [
+    "table_name",
+    "atc_file_path",
+    "query",
+    "path",
+    "columns",
+    "platform",
+    "loaded_at",
+    "udi_pgp_session_query_id",
+];
+
+/// A table defined in the `auto_table_construction` section of an ATC file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtcTable {
+    pub table_name: String,
+    pub atc_file_path: String,
+    pub query: String,
+    pub path: String,
+    pub columns: Vec<String>,
+    pub platform: Option<String>,
+}
+
+impl AtcTable {
+    /// Value of one of the [`ATC_TABLES_COLUMNS`] (except the session column).
+    pub fn cell(&self, column: &str, loaded_at: Option<&DateTime<Utc>>) -> Option<String> {
+        match column {
+            "table_name" => Some(self.table_name.clone()),
+            "atc_file_path" => Some(self.atc_file_path.clone()),
+            "query" => Some(self.query.clone()),
+            "path" => Some(self.path.clone()),
+            "columns" => Some(self.columns.join(", ")),
+            "platform" => self.platform.clone(),
+            "loaded_at" => loaded_at.map(|at| at.to_rfc3339()),
+            _ => None,
+        }
+    }
+}
+
+/// The ATC files of an osquery supplier. The files are read again, and the
+/// cached `.schema` of every table is dropped, whenever one of them changes so
+/// edits are picked up without restarting UDI-PGP. When there's more than one
+/// file their `auto_table_construction` sections are merged into a single
+/// config because `osqueryi` only accepts one `--config_path`.
+#[derive(Debug, Clone, Default)]
+pub struct AtcRegistry {
+    files: Vec<String>,
+    modified: Vec<Option<SystemTime>>,
+    tables: Vec<AtcTable>,
+    config_path: Option<String>,
+    loaded_at: Option<DateTime<Utc>>,
+    schemas: HashMap<String, HashMap<String, OsquerySchema>>,
+}
+
+impl AtcRegistry {
+    pub fn new(files: Vec<String>) -> Self {
+        AtcRegistry {
+            modified: vec![None; files.len()],
+            files,
+            ..Default::default()
+        }
+    }
+
+    pub fn files(&self) -> &[String] {
+        &self.files
+    }
+
+    pub fn tables(&self) -> &[AtcTable] {
+        &self.tables
+    }
+
+    pub fn loaded_at(&self) -> Option<&DateTime<Utc>> {
+        self.loaded_at.as_ref()
+    }
+
+    /// The file passed to `osqueryi --config_path`, available after [`Self::refresh`].
+    pub fn config_path(&self) -> Option<&str> {
+        self.config_path.as_deref()
+    }
+
+    /// Load the ATC files again if any of them was modified since they were
+    /// last loaded. Returns whether they were (re)loaded.
+    pub fn refresh(&mut self) -> UdiPgpResult<bool> {
+        if self.files.is_empty() {
+            return Ok(false);
+        }
+
+        let modified = self
+            .files
+            .iter()
+            .map(|file| {
+                fs::metadata(file)
+                    .and_then(|m| m.modified())
+                    .map_err(|err| {
+                        UdiPgpError::ConfigError(format!(
+                            "Failed to read ATC file {}: {}",
+                            file, err
+                        ))
+                    })
+            })
+            .collect::<UdiPgpResult<Vec<_>>>()?;
+        let modified = modified.into_iter().map(Some).collect::<Vec<_>>();
+        if self.loaded_at.is_some() && modified == self.modified {
+            return Ok(false);
+        }
+
+        let mut configs = Vec::with_capacity(self.files.len());
+        for file in &self.files {
+            let content = fs::read_to_string(file)?;
+            let config: Value = serde_json::from_str(&content).map_err(|err| {
+                UdiPgpError::ConfigError(format!("Invalid ATC file {}: {}", file, err))
+            })?;
+            configs.push((file.clone(), config));
+        }
+
+        let (tables, merged) = merge_atc_configs(&configs)?;
+        self.config_path = if self.files.len() == 1 {
+            Some(self.files[0].clone())
+        } else {
+            let path = match &self.config_path {
+                Some(path) => PathBuf::from(path),
+                None => std::env::temp_dir()
+                    .join(format!("surveilr-udi-pgp-atc-{}.json", Uuid::new_v4())),
+            };
+            fs::write(&path, serde_json::to_string_pretty(&merged)?)?;
+            Some(path.to_string_lossy().to_string())
+        };
+
+        let reloaded = self.loaded_at.is_some();
+        self.tables = tables;
+        self.modified = modified;
+        self.loaded_at = Some(Utc::now());
+        self.schemas.clear();
+        info!(
+            "{} {} ATC table(s) from {}",
+            if reloaded { "Reloaded" } else { "Loaded" },
+            self.tables.len(),
+            self.files.join(", ")
+        );
+        Ok(true)
+    }
+
+    /// The osquery schema of `tables`, keyed by column name. Each table's
+    /// `.schema` is only retrieved from `osqueryi` once per load of the ATC files.
+    pub fn schema(&mut self, tables: &[String]) -> UdiPgpResult<HashMap<String, OsquerySchema>> {
+        self.refresh()?;
+        let config_path = self.config_path.clone();
+        let mut merged = HashMap::new();
+        for table in tables {
+            if !self.schemas.contains_key(table) {
+                let schema = schema::get_schema(&vec![table.clone()], &config_path)?;
+                self.schemas.insert(table.clone(), schema);
+            }
+            merged.extend(self.schemas[table].clone());
+        }
+        Ok(merged)
+    }
+}
+
+/// Collect the tables of every ATC config and merge them into the first
+/// config. A table defined in more than one file is taken from the last one.
+fn merge_atc_configs(configs: &[(String, Value)]) -> UdiPgpResult<(Vec<AtcTable>, Value)> {
+    let mut tables: Vec<AtcTable> = Vec::new();
+    let mut merged_atc = Map::new();
+    for (file, config) in configs {
+        let atc = match config.get("auto_table_construction") {
+            None => continue,
+            Some(Value::Object(atc)) => atc,
+            Some(_) => {
+                return Err(UdiPgpError::ConfigError(format!(
+                    "Invalid ATC file {}: auto_table_construction must be an object",
+                    file
+                )))
+            }
+        };
+        for (table_name, definition) in atc {
+            let text = |key: &str| {
+                definition
+                    .get(key)
+                    .and_then(Value::as_str)
+                    .map(str::to_string)
+            };
+            let table = AtcTable {
+                table_name: table_name.clone(),
+                atc_file_path: file.clone(),
+                query: text("query").unwrap_or_default(),
+                path: text("path").unwrap_or_default(),
+                columns: definition
+                    .get("columns")
+                    .and_then(Value::as_array)
+                    .map(|columns| {
+                        columns
+                            .iter()
+                            .filter_map(Value::as_str)
+                            .map(str::to_string)
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                platform: text("platform"),
+            };
+            match tables.iter_mut().find(|t| t.table_name == *table_name) {
+                Some(existing) => {
+                    warn!(
+                        "ATC table {} of {} is replaced by the one in {}",
+                        table_name, existing.atc_file_path, file
+                    );
+                    *existing = table;
+                }
+                None => tables.push(table),
+            }
+            merged_atc.insert(table_name.clone(), definition.clone());
+        }
+    }
+
+    let mut merged = configs
+        .first()
+        .and_then(|(_, config)| config.as_object().cloned())
+        .unwrap_or_default();
+    merged.insert(
+        "auto_table_construction".to_string(),
+        Value::Object(merged_atc),
+    );
+    Ok((tables, Value::Object(merged)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn atc(table: &str, path: &str) -> Value {
+        json!({
+            "auto_table_construction": {
+                table: {
+                    "query": format!("SELECT id, name FROM {table}"),
+                    "path": path,
+                    "columns": ["id", "name"],
+                    "platform": "linux"
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn atc_files_are_merged_and_reloaded() -> UdiPgpResult<()> {
+        let dir = std::env::temp_dir().join(format!("surveilr-atc-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir)?;
+        let people = dir.join("people.json").to_string_lossy().to_string();
+        let devices = dir.join("devices.json").to_string_lossy().to_string();
+        fs::write(&people, atc("person", "/tmp/people.sqlite.db").to_string())?;
+        fs::write(
+            &devices,
+            atc("device", "/tmp/devices.sqlite.db").to_string(),
+        )?;
+
+        let mut registry = AtcRegistry::new(vec![people.clone(), devices.clone()]);
+        assert!(registry.refresh()?);
+        assert!(!registry.refresh()?);
+        let names = |registry: &AtcRegistry| {
+            registry
+                .tables()
+                .iter()
+                .map(|t| (t.table_name.clone(), t.atc_file_path.clone()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(&registry),
+            vec![
+                ("person".to_string(), people.clone()),
+                ("device".to_string(), devices.clone())
+            ]
+        );
+        let person = &registry.tables()[0];
+        assert_eq!(person.cell("columns", None), Some("id, name".to_string()));
+        assert_eq!(person.cell("platform", None), Some("linux".to_string()));
+
+        // osqueryi gets a single config holding the tables of both files
+        let merged: Value =
+            serde_json::from_str(&fs::read_to_string(registry.config_path().unwrap())?)?;
+        let merged_tables = merged["auto_table_construction"].as_object().unwrap();
+        assert!(merged_tables.contains_key("person") && merged_tables.contains_key("device"));
+
+        // a changed file is loaded again, later files win on duplicate tables
+        let later = SystemTime::now() + std::time::Duration::from_secs(5);
+        fs::write(
+            &devices,
+            atc("person", "/tmp/devices.sqlite.db").to_string(),
+        )?;
+        fs::File::options()
+            .write(true)
+            .open(&devices)?
+            .set_modified(later)?;
+        assert!(registry.refresh()?);
+        assert_eq!(
+            names(&registry),
+            vec![("person".to_string(), devices.clone())]
+        );
+        assert_eq!(registry.tables()[0].path, "/tmp/devices.sqlite.db");
+
+        fs::remove_dir_all(&dir)?;
+        if let Some(config_path) = registry.config_path() {
+            fs::remove_file(config_path)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn a_single_atc_file_is_passed_as_is() -> UdiPgpResult<()> {
+        let file = std::env::temp_dir().join(format!("surveilr-atc-test-{}.json", Uuid::new_v4()));
+        fs::write(&file, atc("person", "/tmp/people.sqlite.db").to_string())?;
+        let file = file.to_string_lossy().to_string();
+
+        let mut registry = AtcRegistry::new(vec![file.clone()]);
+        registry.refresh()?;
+        assert_eq!(registry.config_path(), Some(file.as_str()));
+        assert_eq!(registry.tables().len(), 1);
+
+        fs::write(&file, "{ not json")?;
+        fs::File::options()
+            .write(true)
+            .open(&file)?
+            .set_modified(SystemTime::now() + std::time::Duration::from_secs(5))?;
+        assert!(registry.refresh().is_err());
+        // the previously loaded tables stay available
+        assert_eq!(registry.tables().len(), 1);
+
+        fs::remove_file(&file)?;
+        Ok(())
+    }
+}
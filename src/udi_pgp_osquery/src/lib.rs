@@ -1,22 +1,80 @@
-use std::{collections::HashMap, process::Command, str::FromStr};
+use std::{cmp::Ordering, collections::HashMap, io::BufReader, process::Stdio, str::FromStr};
 
 use async_trait::async_trait;
-use futures::{stream, StreamExt};
+use atc::{AtcRegistry, ATC_TABLES_COLUMNS, ATC_TABLES_TABLE};
+use futures::{stream, Stream, StreamExt};
 use schema::OsquerySchema;
-use serde_json::Value;
+use serde::de::{Deserializer, SeqAccess, Visitor};
+use serde_json::{Map, Value};
+use sqlparser::ast::{SetExpr, Statement};
 use tracing::{debug, error, info};
 use udi_pgp::{
-    config::{Supplier, SupplierType},
+    config::{Supplier, SupplierExecutable, SupplierType},
     error::{UdiPgpError, UdiPgpResult},
-    parser::stmt::{ColumnMetadata, ExpressionType, UdiPgpStatment},
-    sql_supplier::{SqlSupplier, SqlSupplierType},
-    ssh::{key::SshKey, session::SshTunnelAccess, SshConnection, UdiPgpSshTarget},
+    parser::stmt::{ColumnMetadata, ExpressionType, OrderByColumn, UdiPgpStatment},
+    remote::{
+        execute_with_failover, failover_groups,
+        tags::{format_tags, TargetTagFilter, TARGET_TAGS_COLUMN},
+        UdiPgpRemoteTarget,
+    },
+    sql_supplier::{
+        QueryPlanStep, RowStream, SqlSupplier, SqlSupplierType, TargetError, TargetErrors,
+    },
     FieldFormat, FieldInfo, Row, Type, UdiPgpModes, FACTORY,
 };
 use uuid::Uuid;
 
+pub mod atc;
+pub mod pack;
 mod schema;
 
+/// Number of SSH targets queried at the same time in remote mode.
+const SSH_CONCURRENCY: usize = 5;
+
+/// Rows parsed from local `osqueryi` output ahead of the client reading them.
+const LOCAL_ROWS_BUFFER: usize = 1024;
+
+/// Parse the JSON array of rows `osqueryi --json` prints, handing each row to
+/// `row` as soon as it's read. Parsing stops early when `row` returns false.
+fn parse_json_rows(
+    reader: impl std::io::Read,
+    row: impl FnMut(Value) -> bool,
+) -> serde_json::Result<()> {
+    struct RowsVisitor<F> {
+        row: F,
+        stopped: bool,
+    }
+
+    impl<'de, F: FnMut(Value) -> bool> Visitor<'de> for &mut RowsVisitor<F> {
+        type Value = ();
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            formatter.write_str("a JSON array of rows")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+            while let Some(value) = seq.next_element::<Value>()? {
+                if !(self.row)(value) {
+                    self.stopped = true;
+                    return Err(serde::de::Error::custom("stopped"));
+                }
+            }
+            Ok(())
+        }
+    }
+
+    let mut visitor = RowsVisitor {
+        row,
+        stopped: false,
+    };
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    match deserializer.deserialize_seq(&mut visitor) {
+        _ if visitor.stopped => Ok(()),
+        Ok(()) => deserializer.end(),
+        Err(err) => Err(err),
+    }
+}
+
 pub async fn initialize() {
     let mut factory = FACTORY().lock().await;
     factory.register("osquery", generate_new);
@@ -24,10 +82,12 @@ pub async fn initialize() {
 
 fn generate_new(supplier: Supplier) -> UdiPgpResult<SqlSupplierType> {
     let sql_suppler = OsquerySupplier {
+        atc: AtcRegistry::new(supplier.atc_files()),
         mode: supplier.mode,
-        atc_file_path: supplier.atc_file_path,
         ssh_targets: supplier.ssh_targets,
         query_session_id: None,
+        text_columns: supplier.text_columns,
+        executable: supplier.executable,
     };
     Ok(Box::new(sql_suppler) as SqlSupplierType)
 }
@@ -35,18 +95,23 @@ fn generate_new(supplier: Supplier) -> UdiPgpResult<SqlSupplierType> {
 #[derive(Debug, Clone)]
 pub struct OsquerySupplier {
     pub mode: UdiPgpModes,
-    atc_file_path: Option<String>,
-    ssh_targets: Option<Vec<UdiPgpSshTarget>>,
+    atc: AtcRegistry,
+    ssh_targets: Option<Vec<UdiPgpRemoteTarget>>,
     query_session_id: Option<Uuid>,
+    text_columns: bool,
+    /// `osqueryi` of local mode, pinned or from PATH
+    executable: SupplierExecutable,
 }
 
 impl From<Supplier> for OsquerySupplier {
     fn from(value: Supplier) -> Self {
         OsquerySupplier {
+            atc: AtcRegistry::new(value.atc_files()),
             mode: value.mode,
-            atc_file_path: value.atc_file_path,
             ssh_targets: value.ssh_targets,
             query_session_id: None,
+            text_columns: value.text_columns,
+            executable: value.executable,
         }
     }
 }
@@ -55,9 +120,11 @@ impl From<&Supplier> for OsquerySupplier {
     fn from(value: &Supplier) -> Self {
         OsquerySupplier {
             mode: value.mode.clone(),
-            atc_file_path: value.atc_file_path.clone(),
+            atc: AtcRegistry::new(value.atc_files()),
             ssh_targets: value.ssh_targets.clone(),
             query_session_id: None,
+            text_columns: value.text_columns,
+            executable: value.executable.clone(),
         }
     }
 }
@@ -66,14 +133,16 @@ impl OsquerySupplier {
     pub fn new(mode: UdiPgpModes) -> Self {
         OsquerySupplier {
             mode,
-            atc_file_path: None,
+            atc: AtcRegistry::default(),
             ssh_targets: None,
             query_session_id: None,
+            text_columns: false,
+            executable: SupplierExecutable::default(),
         }
     }
 
-    pub fn with_atc_file(&mut self, file: &Option<String>) -> Self {
-        self.atc_file_path = file.clone();
+    pub fn with_atc_files(&mut self, files: &[String]) -> Self {
+        self.atc = AtcRegistry::new(files.to_vec());
         self.clone()
     }
 
@@ -82,25 +151,47 @@ impl OsquerySupplier {
         self.ssh_targets = Some(
             targets
                 .iter()
-                .map(|t| UdiPgpSshTarget::from_str(t.as_str()).unwrap())
+                .map(|t| UdiPgpRemoteTarget::from_str(t.as_str()).unwrap())
                 .collect(),
         );
         self.clone()
     }
 
+    pub fn with_text_columns(&mut self, text_columns: bool) -> Self {
+        self.text_columns = text_columns;
+        self.clone()
+    }
+
+    pub fn with_executable(&mut self, executable: SupplierExecutable) -> Self {
+        self.executable = executable;
+        self.clone()
+    }
+
     //This handles columns/alias that are not actually present in osquery
     //For example, binary operations with alias.
     //e.g  (1<<8) as promisc_flag. The "promisc_flag" is not present
     //in the "interface_details" in osquery
-    fn non_standard_column(&self, col: &ColumnMetadata) -> UdiPgpResult<OsquerySchema> {
-        // println!("{:#?}", expr);
-        match col.expr_type {
+    //or function calls such as COUNT(*) or SUM(resident_size) AS memory
+    fn non_standard_column(
+        &self,
+        col: &ColumnMetadata,
+        schema: &HashMap<String, OsquerySchema>,
+    ) -> UdiPgpResult<OsquerySchema> {
+        match &col.expr_type {
             ExpressionType::Binary => Ok(OsquerySchema::new(
                 "200".to_string(),
                 "".to_string(),
                 col.name.clone(),
                 "INT".to_string(),
             )),
+            ExpressionType::Function(function) => Ok(OsquerySchema::new(
+                "201".to_string(),
+                "".to_string(),
+                col.name.clone(),
+                function
+                    .result_type(|arg| schema.get(arg).map(|s| schema::pg_type(&s.column_type)))
+                    .to_string(),
+            )),
             _ => {
                 error!("Invalid column name: {}", col.name);
                 Err(UdiPgpError::SchemaError(
@@ -116,10 +207,10 @@ impl OsquerySupplier {
         stmt: &mut UdiPgpStatment,
         schema: &mut HashMap<String, OsquerySchema>,
     ) {
-        if stmt.columns.len() == 1 && stmt.columns.first().map_or(false, |c| c.name == "*") {
+        if stmt.columns.len() == 1 && stmt.columns.first().is_some_and(|c| c.name == "*") {
             stmt.columns = schema
-                .iter()
-                .map(|(_, schema)| ColumnMetadata {
+                .values()
+                .map(|schema| ColumnMetadata {
                     name: schema.name.clone(),
                     expr_type: ExpressionType::Standard,
                     alias: None,
@@ -127,15 +218,21 @@ impl OsquerySupplier {
                 })
                 .collect();
         } else {
+            // function columns keep the name SQLite gives their results
             stmt.columns
                 .iter_mut()
+                .filter(|col| !matches!(col.expr_type, ExpressionType::Function(_)))
                 .for_each(|col| col.name = col.name.to_lowercase());
         }
     }
 
     fn add_remote_specific_columns(&self, stmt: &mut UdiPgpStatment, mode: &UdiPgpModes) {
         if let UdiPgpModes::Remote = mode {
-            let remote_columns = ["udi_pgp_ssh_target", "udi_pgp_ssh_host_id"];
+            let remote_columns = [
+                "udi_pgp_ssh_target",
+                "udi_pgp_ssh_host_id",
+                TARGET_TAGS_COLUMN,
+            ];
             for &name in &remote_columns {
                 stmt.columns.push(ColumnMetadata {
                     name: name.to_string(),
@@ -147,6 +244,14 @@ impl OsquerySupplier {
         }
     }
 
+    fn column_type(&self, col_schema: &OsquerySchema) -> Type {
+        if self.text_columns {
+            Type::VARCHAR
+        } else {
+            schema::pg_type(&col_schema.column_type)
+        }
+    }
+
     fn column_to_field_info(
         &self,
         col: &ColumnMetadata,
@@ -176,8 +281,15 @@ impl OsquerySupplier {
                         "udi_pgp_ssh_target".to_string(),
                         "TEXT".to_string(),
                     )
+                } else if col.name == TARGET_TAGS_COLUMN {
+                    OsquerySchema::new(
+                        "103".to_string(),
+                        "name".to_owned(),
+                        TARGET_TAGS_COLUMN.to_string(),
+                        "TEXT".to_string(),
+                    )
                 } else {
-                    self.non_standard_column(col)?
+                    self.non_standard_column(col, schema)?
                 }
             }
         };
@@ -192,14 +304,94 @@ impl OsquerySupplier {
             None => col.name.to_string(),
         };
 
-        let field_info =
-            FieldInfo::new(name, None, Some(cid), col.r#type.clone(), FieldFormat::Text);
+        let field_info = FieldInfo::new(
+            name,
+            None,
+            Some(cid),
+            self.column_type(&col_schema),
+            FieldFormat::Text,
+        );
         Ok(field_info)
     }
 
-    fn execute_local_query(&self, query: &str) -> UdiPgpResult<Vec<Value>> {
-        let mut cmd = Command::new("osqueryi");
-        if let Some(cfg_file) = &self.atc_file_path {
+    /// Schema of the virtual `osquery_atc_tables` table, it has to be queried on its own.
+    fn atc_tables_schema(&mut self, stmt: &mut UdiPgpStatment) -> UdiPgpResult<Vec<FieldInfo>> {
+        if let Some(table) = stmt.tables.iter().find(|t| *t != ATC_TABLES_TABLE) {
+            return Err(UdiPgpError::SchemaError(
+                table.to_string(),
+                format!("{ATC_TABLES_TABLE} can't be queried along with other tables"),
+            ));
+        }
+        self.atc.refresh()?;
+
+        if stmt.columns.len() == 1 && stmt.columns.first().is_some_and(|c| c.name == "*") {
+            stmt.columns = ATC_TABLES_COLUMNS[..ATC_TABLES_COLUMNS.len() - 1]
+                .iter()
+                .map(|name| ColumnMetadata {
+                    name: name.to_string(),
+                    expr_type: ExpressionType::Standard,
+                    alias: None,
+                    r#type: Type::VARCHAR,
+                })
+                .collect();
+        } else {
+            stmt.columns
+                .iter_mut()
+                .for_each(|col| col.name = col.name.to_lowercase());
+        }
+        stmt.columns.push(ColumnMetadata::query_session_column());
+
+        stmt.columns
+            .iter()
+            .map(|col| {
+                let cid = ATC_TABLES_COLUMNS
+                    .iter()
+                    .position(|c| *c == col.name)
+                    .ok_or(UdiPgpError::SchemaError(
+                        ATC_TABLES_TABLE.to_string(),
+                        format!("Invalid column name: {}", col.name),
+                    ))?;
+                Ok(FieldInfo::new(
+                    col.alias.clone().unwrap_or(col.name.clone()),
+                    None,
+                    Some(cid as i16),
+                    Type::VARCHAR,
+                    FieldFormat::Text,
+                ))
+            })
+            .collect()
+    }
+
+    fn atc_tables_rows(&self, columns: &[ColumnMetadata]) -> Vec<Vec<Row>> {
+        self.atc
+            .tables()
+            .iter()
+            .map(|table| {
+                columns
+                    .iter()
+                    .map(|col| match col.name.as_str() {
+                        "udi_pgp_session_query_id" => match self.query_session_id {
+                            Some(id) => Row::from(id.to_string()),
+                            None => Row::null(),
+                        },
+                        name => match table.cell(name, self.atc.loaded_at()) {
+                            Some(value) => Row::from(value),
+                            None => Row::null(),
+                        },
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Run `query` with the local `osqueryi` and stream the rows as they're
+    /// parsed from its JSON output, which is never held in memory as a whole.
+    fn execute_local_query(
+        &self,
+        query: &str,
+    ) -> UdiPgpResult<impl Stream<Item = UdiPgpResult<Value>> + Send + 'static> {
+        let mut cmd = self.executable.command("osqueryi")?;
+        if let Some(cfg_file) = self.atc.config_path() {
             cmd.arg("--config_path").arg(cfg_file);
         }
         cmd.arg("--json").arg(query);
@@ -208,168 +400,317 @@ impl OsquerySupplier {
             cmd.get_args()
         );
 
-        let output = cmd.output()?;
-        if !output.status.success() {
-            return Err(UdiPgpError::QueryExecutionError("Query failed".to_string()));
-        }
-
-        let output_str = String::from_utf8(output.stdout)
-            .map_err(|err| UdiPgpError::QueryExecutionError(err.to_string()))?;
-        info!("Osquery query executed successfully.");
+        let mut child = cmd
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdout = child.stdout.take().ok_or(UdiPgpError::QueryExecutionError(
+            "osqueryi has no stdout".to_string(),
+        ))?;
+        let (tx, rx) = tokio::sync::mpsc::channel(LOCAL_ROWS_BUFFER);
+        tokio::task::spawn_blocking(move || {
+            let parsed = parse_json_rows(BufReader::new(stdout), |row| {
+                tx.blocking_send(Ok(row)).is_ok()
+            });
+            // the client stopped reading, osqueryi doesn't have to finish
+            if tx.is_closed() {
+                let _ = child.kill();
+            }
+            let result = match (parsed, child.wait()) {
+                (_, Ok(status)) if !status.success() && !tx.is_closed() => {
+                    Err(UdiPgpError::QueryExecutionError("Query failed".to_string()))
+                }
+                (Err(err), _) => Err(err.into()),
+                (_, Err(err)) => Err(err.into()),
+                (Ok(()), Ok(_)) => {
+                    info!("Osquery query executed successfully.");
+                    Ok(())
+                }
+            };
+            if let Err(err) = result {
+                let _ = tx.blocking_send(Err(err));
+            }
+        });
+        Ok(stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|row| (row, rx))
+        }))
+    }
 
-        let value: Value = serde_json::from_str(&output_str)?;
-        value
-            .as_array()
-            .ok_or(UdiPgpError::QueryExecutionError(
-                "Failed to convert json string to array".to_string(),
-            ))
+    /// The remote targets whose tags match `filter`.
+    fn matching_targets(&self, filter: &TargetTagFilter) -> Vec<UdiPgpRemoteTarget> {
+        self.ssh_targets
+            .as_deref()
+            .unwrap_or_default()
+            .iter()
+            .filter(|target| filter.matches(target))
             .cloned()
+            .collect()
     }
 
-    async fn execute_remote_query(
+    /// Execute `query` on every remote host matching `filter` (a few at a
+    /// time, over each target's transport) and return each host's rows, or
+    /// the error it failed with, so results can be attributed to the host
+    /// which produced them. Targets sharing an id are one host which fails
+    /// over from its primary target, the target which answered is returned.
+    pub async fn query_ssh_targets(
         &self,
         query: &str,
-    ) -> UdiPgpResult<(Vec<Value>, Vec<UdiPgpSshTarget>)> {
-        let targets = self.ssh_targets.as_ref().unwrap_or(&vec![]).clone();
+        filter: &TargetTagFilter,
+    ) -> Vec<(UdiPgpRemoteTarget, UdiPgpResult<Vec<Value>>)> {
+        self.ssh_target_results(query, filter).collect().await
+    }
 
-        let concurrency_limit = 5;
+    /// `query_ssh_targets`, each host's rows as soon as it answered.
+    fn ssh_target_results(
+        &self,
+        query: &str,
+        filter: &TargetTagFilter,
+    ) -> impl Stream<Item = (UdiPgpRemoteTarget, UdiPgpResult<Vec<Value>>)> + Send + 'static {
+        let groups = failover_groups(self.matching_targets(filter));
+        let query = query.to_owned();
 
-        let futures = targets.into_iter().map(|target| {
-            let query = query.to_owned();
+        let futures = groups.into_iter().map(move |group| {
+            let query = query.clone();
             async move {
-                let addr = match target.port {
-                    Some(port) => format!("{}:{}", target.host, port),
-                    None => format!("{}:{}", target.host, 22),
-                };
-
-                let keypair = SshKey::generate_random().map_err(UdiPgpError::from)?;
-                let access = SshTunnelAccess {
-                    connection_string: format!("{}@{}", target.user, target.host),
-                    keypair,
-                };
-                let (session, _) = access.create_tunnel(&addr).await?;
+                let (target, output) =
+                    execute_with_failover(&group, "osqueryi", vec!["--json", &query]).await;
+                let result = async {
+                    let value: Value = serde_json::from_str(&output?)?;
+                    value
+                        .as_array()
+                        .ok_or(UdiPgpError::QueryExecutionError(
+                            "Failed to convert json string to array".to_string(),
+                        ))
+                        .cloned()
+                }
+                .await;
+                (target, result)
+            }
+        });
 
-                let args = vec!["--json", &query];
-                let output = session.execute_command("osqueryi", args).await?;
+        stream::iter(futures).buffer_unordered(SSH_CONCURRENCY)
+    }
 
-                let value: Value = serde_json::from_str(&output)?;
-                let rows = value
-                    .as_array()
-                    .ok_or(UdiPgpError::QueryExecutionError(
-                        "Failed to convert json string to array".to_string(),
-                    ))?
-                    .clone();
+    /// The targets `stmt` selects and the query they're sent.
+    fn remote_query(stmt: &UdiPgpStatment) -> UdiPgpResult<(TargetTagFilter, String)> {
+        let (filter, target_stmt) = TargetTagFilter::from_statement(&stmt.stmt)?;
+        let query = if filter.is_empty() {
+            stmt.query.clone()
+        } else {
+            target_stmt.to_string()
+        };
+        Ok((filter, query))
+    }
 
-                Ok::<(Vec<Value>, UdiPgpSshTarget), UdiPgpError>((rows, target.clone()))
-            }
-        });
+    /// The rows of every target as they answer, the targets which fail are
+    /// added to `target_errors`.
+    fn stream_remote_query(
+        &self,
+        stmt: &UdiPgpStatment,
+        target_errors: TargetErrors,
+    ) -> UdiPgpResult<impl Stream<Item = (Value, UdiPgpRemoteTarget)> + Send + 'static> {
+        let (filter, query) = Self::remote_query(stmt)?;
+        Ok(self
+            .ssh_target_results(&query, &filter)
+            .flat_map(move |(target, result)| {
+                let rows = result.unwrap_or_else(|error| {
+                    error!("{}: {}", target, error);
+                    target_errors.push(target_error(&target, &error));
+                    vec![]
+                });
+                stream::iter(rows.into_iter().map(move |row| (row, target.clone())))
+            }))
+    }
 
-        let (successful_results, errors): (Vec<_>, Vec<_>) = stream::iter(futures)
-            .buffer_unordered(concurrency_limit)
-            .collect::<Vec<UdiPgpResult<(Vec<Value>, UdiPgpSshTarget)>>>()
-            .await
-            .into_iter()
-            .partition(Result::is_ok);
+    /// The rows of every target which answered, each with its target, and the
+    /// errors of the targets which didn't.
+    async fn execute_remote_query(
+        &self,
+        stmt: &UdiPgpStatment,
+    ) -> UdiPgpResult<(Vec<Value>, Vec<UdiPgpRemoteTarget>, Vec<TargetError>)> {
+        let (filter, query) = Self::remote_query(stmt)?;
 
-        let rows = successful_results
-            .iter()
-            .flat_map(|result| result.as_ref().unwrap().0.clone())
-            .collect::<Vec<_>>();
+        let mut rows = Vec::new();
+        // one target per row so every row can be attributed to its host
+        let mut row_targets = Vec::new();
+        let mut errors = Vec::new();
+        for (target, result) in self.query_ssh_targets(&query, &filter).await {
+            match result {
+                Ok(target_rows) => {
+                    row_targets.extend(std::iter::repeat_n(target, target_rows.len()));
+                    rows.extend(target_rows);
+                }
+                Err(error) => {
+                    error!("{}: {}", target, error);
+                    errors.push(target_error(&target, &error));
+                }
+            }
+        }
 
-        // Extract the SshConnectionParameters part
-        let connection_params: Vec<UdiPgpSshTarget> = successful_results
-            .into_iter()
-            .map(|result| result.unwrap().1) // Extract the SshConnectionParameters
-            .collect();
-        let errors = errors.into_iter().map(Result::unwrap_err);
+        Ok((rows, row_targets, errors))
+    }
 
-        // Log all errors
-        for error in errors {
-            error!("{}", error);
+    fn row_format(&self, stmt: &UdiPgpStatment) -> RowFormat {
+        RowFormat {
+            columns: stmt.columns.clone(),
+            query_session_id: self.query_session_id,
         }
+    }
+}
 
-        Ok((rows, connection_params))
+fn target_error(target: &UdiPgpRemoteTarget, error: &UdiPgpError) -> TargetError {
+    TargetError {
+        host_id: target.id.clone(),
+        target: target.to_string(),
+        message: error.to_string(),
     }
+}
+
+/// Turns osquery's JSON rows into the statement's columns, apart from the
+/// supplier so the rows can be converted as they're streamed.
+struct RowFormat {
+    columns: Vec<ColumnMetadata>,
+    query_session_id: Option<Uuid>,
+}
 
-    fn rows(
+impl RowFormat {
+    fn row(
         &self,
-        values: &[Value],
-        columns: &[ColumnMetadata],
-        targets: Option<Vec<UdiPgpSshTarget>>,
-    ) -> UdiPgpResult<Vec<Vec<Row>>> {
-        let mut rows = Vec::with_capacity(values.len());
-
-        // infinite iterator of None values to use when targets is None or isn't same length as rows(though this should not happen).
-        let default_targets = std::iter::repeat(None);
-        let target_iter = targets
-            .into_iter()
-            .flatten()
-            .map(Some)
-            .chain(default_targets);
-
-        for (row_value, target) in values.iter().zip(target_iter) {
-            let row_object = row_value
-                .as_object()
-                .ok_or(UdiPgpError::QueryExecutionError(
-                    "Row is not an object".to_string(),
-                ))?;
-
-            let mut cell_row = Vec::with_capacity(columns.len());
-            for col in columns {
-                let column_name = col.alias.as_ref().unwrap_or(&col.name);
-                let cell = match column_name.as_str() {
-                    "udi_pgp_ssh_target" | "config_path" => {
-                        // let target = ssh_target.as_ref().ok_or("SSH target not found")?;
-                        // match self.name() {
-                        //     SupplierName::OsqueryAtcLocal => target
-                        //         .config_file()
-                        //         .ok_or("Config file not found")?
-                        //         .to_string(),
-                        //     _ => target.ssh_target().to_string(),
-                        // }
-                        let value = match target {
-                            None => "".to_string(),
-                            Some(ref t) => SshConnection::Parameters(t.clone()).to_string(),
-                        };
-                        Row::from(value)
-                    }
-                    "udi_pgp_ssh_host_id" | "atc_id" => {
-                        // let target = ssh_target.as_ref().ok_or("SSH target not found")?;
-                        // target.id().to_string()
-                        let value = match target {
-                            None => "".to_string(),
-                            Some(ref t) => t.id.to_string(),
-                        };
-                        Row::from(value)
-                    }
-                    "udi_pgp_session_query_id" => {
-                        let value = match self.query_session_id {
-                            Some(id) => id.to_string(),
-                            None => "null".to_string(),
-                        };
-                        Row::from(value)
-                    }
-                    _ => {
-                        let default = Value::String("".to_string());
-                        let val = row_object
-                            .get(column_name)
-                            .unwrap_or(&default)
-                            .as_str()
-                            .ok_or(UdiPgpError::QueryExecutionError(
-                                "Invalid cell value".to_string(),
-                            ))?;
-                        Row::from(val.to_string())
-                    }
-                };
-                cell_row.push(cell);
-            }
-            rows.push(cell_row);
+        row_value: &Value,
+        target: Option<&UdiPgpRemoteTarget>,
+    ) -> UdiPgpResult<Vec<Row>> {
+        let row_object = row_value
+            .as_object()
+            .ok_or(UdiPgpError::QueryExecutionError(
+                "Row is not an object".to_string(),
+            ))?;
+
+        let mut cell_row = Vec::with_capacity(self.columns.len());
+        for col in &self.columns {
+            let column_name = col.alias.as_ref().unwrap_or(&col.name);
+            let cell = match column_name.as_str() {
+                "udi_pgp_ssh_target" | "config_path" => {
+                    // let target = ssh_target.as_ref().ok_or("SSH target not found")?;
+                    // match self.name() {
+                    //     SupplierName::OsqueryAtcLocal => target
+                    //         .config_file()
+                    //         .ok_or("Config file not found")?
+                    //         .to_string(),
+                    //     _ => target.ssh_target().to_string(),
+                    // }
+                    let value = match target {
+                        None => "".to_string(),
+                        Some(t) => t.to_string(),
+                    };
+                    Row::from(value)
+                }
+                "udi_pgp_ssh_host_id" | "atc_id" => {
+                    // let target = ssh_target.as_ref().ok_or("SSH target not found")?;
+                    // target.id().to_string()
+                    let value = match target {
+                        None => "".to_string(),
+                        Some(t) => t.id.to_string(),
+                    };
+                    Row::from(value)
+                }
+                TARGET_TAGS_COLUMN => {
+                    let value = match target {
+                        None => "".to_string(),
+                        Some(t) => format_tags(&t.tags),
+                    };
+                    Row::from(value)
+                }
+                "udi_pgp_session_query_id" => {
+                    let value = match self.query_session_id {
+                        Some(id) => id.to_string(),
+                        None => "null".to_string(),
+                    };
+                    Row::from(value)
+                }
+                _ if col.r#type != Type::VARCHAR => {
+                    schema::typed_row(cell_value(row_object, column_name), &col.r#type)
+                }
+                _ => {
+                    let default = Value::String("".to_string());
+                    let val = cell_value(row_object, column_name)
+                        .unwrap_or(&default)
+                        .as_str()
+                        .ok_or(UdiPgpError::QueryExecutionError(
+                            "Invalid cell value".to_string(),
+                        ))?;
+                    Row::from(val.to_string())
+                }
+            };
+            cell_row.push(cell);
         }
+        Ok(cell_row)
+    }
+}
 
-        Ok(rows)
+/// SQLite names unaliased expressions as they are written in the query, which
+/// may differ in case or spacing from the parsed expression.
+fn cell_value<'a>(row: &'a Map<String, Value>, column_name: &str) -> Option<&'a Value> {
+    row.get(column_name).or_else(|| {
+        let normalize = |name: &str| {
+            name.chars()
+                .filter(|c| !c.is_whitespace())
+                .collect::<String>()
+                .to_lowercase()
+        };
+        let column_name = normalize(column_name);
+        row.iter()
+            .find(|(key, _)| normalize(key) == column_name)
+            .map(|(_, value)| value)
+    })
+}
+
+fn compare_cells(a: Option<&Value>, b: Option<&Value>) -> Ordering {
+    let number = |value: Option<&Value>| match value {
+        Some(Value::Number(n)) => n.as_f64(),
+        Some(Value::String(s)) => s.parse::<f64>().ok(),
+        _ => None,
+    };
+    let text = |value: Option<&Value>| match value {
+        Some(Value::String(s)) => s.clone(),
+        Some(other) => other.to_string(),
+        None => String::new(),
+    };
+    match (number(a), number(b)) {
+        (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+        _ => text(a).cmp(&text(b)),
     }
 }
 
+/// Sort rows (and the targets they came from) by the query's `ORDER BY` columns.
+fn order_rows(
+    rows: Vec<Value>,
+    targets: Vec<UdiPgpRemoteTarget>,
+    order_by: &[OrderByColumn],
+) -> (Vec<Value>, Vec<UdiPgpRemoteTarget>) {
+    let mut pairs = rows.into_iter().zip(targets).collect::<Vec<_>>();
+    pairs.sort_by(|(a, _), (b, _)| {
+        order_by
+            .iter()
+            .map(|column| {
+                let cell = |row: &Value| {
+                    row.as_object()
+                        .and_then(|row| cell_value(row, &column.name))
+                        .cloned()
+                };
+                let ordering = compare_cells(cell(a).as_ref(), cell(b).as_ref());
+                if column.asc {
+                    ordering
+                } else {
+                    ordering.reverse()
+                }
+            })
+            .find(|ordering| *ordering != Ordering::Equal)
+            .unwrap_or(Ordering::Equal)
+    });
+    pairs.into_iter().unzip()
+}
+
 #[async_trait]
 impl SqlSupplier for OsquerySupplier {
     fn name(&self) -> &str {
@@ -381,9 +722,15 @@ impl SqlSupplier for OsquerySupplier {
     }
 
     fn update(&mut self, supplier: Supplier) -> UdiPgpResult<()> {
+        // keep the loaded tables and their cached schema unless the files changed
+        let atc_files = supplier.atc_files();
+        if atc_files != self.atc.files() {
+            self.atc = AtcRegistry::new(atc_files);
+        }
         self.mode = supplier.mode;
-        self.atc_file_path = supplier.atc_file_path;
         self.ssh_targets = supplier.ssh_targets;
+        self.text_columns = supplier.text_columns;
+        self.executable = supplier.executable;
         Ok(())
     }
 
@@ -397,7 +744,10 @@ impl SqlSupplier for OsquerySupplier {
     }
 
     async fn schema(&mut self, stmt: &mut UdiPgpStatment) -> UdiPgpResult<Vec<FieldInfo>> {
-        let mut schema = schema::get_schema(&stmt.tables, &self.atc_file_path)?;
+        if stmt.tables.iter().any(|t| t == ATC_TABLES_TABLE) {
+            return self.atc_tables_schema(stmt);
+        }
+        let mut schema = self.atc.schema(&stmt.tables)?;
         debug!("{:#?}", stmt.columns);
 
         // Process columns to either expand "*" or lowercase existing columns
@@ -412,10 +762,16 @@ impl SqlSupplier for OsquerySupplier {
             r#type: Type::VARCHAR,
         });
 
-        stmt.columns
+        let fields = stmt
+            .columns
             .iter()
             .map(|col| self.column_to_field_info(col, &schema))
-            .collect()
+            .collect::<UdiPgpResult<Vec<_>>>()?;
+        // `execute` encodes each cell according to the type announced here
+        for (col, field) in stmt.columns.iter_mut().zip(&fields) {
+            col.r#type = field.datatype().clone();
+        }
+        Ok(fields)
 
         // let mut schema = schema::get_schema(&stmt.tables, &self.atc_file_path)?;
         // debug!("{:#?}", stmt.columns);
@@ -516,14 +872,299 @@ impl SqlSupplier for OsquerySupplier {
         //     .collect()
     }
 
-    async fn execute(&mut self, stmt: &UdiPgpStatment) -> UdiPgpResult<Vec<Vec<Row>>> {
-        let (rows, targets) = match self.mode {
-            UdiPgpModes::Local => (self.execute_local_query(&stmt.query)?, None),
+    fn explain(&self, stmt: &UdiPgpStatment) -> UdiPgpResult<Vec<QueryPlanStep>> {
+        let remote = matches!(self.mode, UdiPgpModes::Remote);
+        // the tags only select the targets, the rest of the query is sent to them
+        let (filter, target_stmt) = if remote {
+            TargetTagFilter::from_statement(&stmt.stmt)?
+        } else {
+            (TargetTagFilter::default(), stmt.stmt.clone())
+        };
+        let query = if filter.is_empty() {
+            stmt.query.clone()
+        } else {
+            target_stmt.to_string()
+        };
+        let mut steps = vec![
+            QueryPlanStep::new("mode", self.mode.to_string()),
+            QueryPlanStep::new(
+                "atc",
+                match self.atc.files() {
+                    [] => "none".to_string(),
+                    files => files.join(", "),
+                },
+            ),
+            QueryPlanStep::new("osquery sql", format!("osqueryi --json \"{}\"", query)),
+        ];
+
+        if remote {
+            let targets = self.matching_targets(&filter);
+            if !filter.is_empty() {
+                steps.push(QueryPlanStep::new(
+                    "tags",
+                    format!(
+                        "{filter}: {} of {} targets",
+                        targets.len(),
+                        self.ssh_targets.as_deref().unwrap_or_default().len()
+                    ),
+                ));
+            }
+            if targets.is_empty() {
+                steps.push(QueryPlanStep::new(
+                    "target",
+                    if filter.is_empty() {
+                        "none configured, the query returns no rows"
+                    } else {
+                        "none matches the tags, the query returns no rows"
+                    },
+                ));
+            }
+            for target in &targets {
+                steps.push(QueryPlanStep::new(
+                    "target",
+                    format!("{} ({})", target, target.id),
+                ));
+            }
+            steps.push(QueryPlanStep::new(
+                "concurrency",
+                format!(
+                    "{} of {} targets at a time",
+                    SSH_CONCURRENCY.min(targets.len()),
+                    targets.len()
+                ),
+            ));
+        } else {
+            steps.push(QueryPlanStep::new("target", "localhost"));
+        }
+
+        let (selection, limit) = match &target_stmt {
+            Statement::Query(query) => (
+                match query.body.as_ref() {
+                    SetExpr::Select(select) => select.selection.as_ref().map(|s| s.to_string()),
+                    _ => None,
+                },
+                query.limit.as_ref().map(|l| l.to_string()),
+            ),
+            _ => (None, None),
+        };
+        let pushed_down = if remote {
+            "pushed down to every target"
+        } else {
+            "pushed down to osquery"
+        };
+        steps.push(QueryPlanStep::new(
+            "where",
+            match selection {
+                Some(selection) => format!("{selection}: {pushed_down}"),
+                None => "none, every row of the tables is returned".to_string(),
+            },
+        ));
+        if stmt.is_aggregate() {
+            steps.push(QueryPlanStep::new(
+                "aggregate",
+                if remote {
+                    "pushed down to every target, rows are aggregated per host and not across hosts"
+                } else {
+                    pushed_down
+                },
+            ));
+        }
+        if !stmt.order_by.is_empty() {
+            let columns = stmt
+                .order_by
+                .iter()
+                .map(|c| format!("{} {}", c.name, if c.asc { "ASC" } else { "DESC" }))
+                .collect::<Vec<_>>()
+                .join(", ");
+            steps.push(QueryPlanStep::new(
+                "order by",
+                if remote {
+                    format!("{columns}: {pushed_down}, merged rows are sorted again")
+                } else {
+                    format!("{columns}: {pushed_down}")
+                },
+            ));
+        }
+        if let Some(limit) = limit {
+            steps.push(QueryPlanStep::new(
+                "limit",
+                if remote {
+                    format!("{limit} rows per target")
+                } else {
+                    format!("{limit} rows")
+                },
+            ));
+        }
+        Ok(steps)
+    }
+
+    async fn execute(&mut self, stmt: &UdiPgpStatment) -> UdiPgpResult<RowStream> {
+        if stmt.tables.iter().any(|t| t == ATC_TABLES_TABLE) {
+            return Ok(RowStream::from_rows(self.atc_tables_rows(&stmt.columns)));
+        }
+        self.atc.refresh()?;
+        let format = self.row_format(stmt);
+        match self.mode {
+            UdiPgpModes::Local => {
+                let rows = self.execute_local_query(&stmt.query)?;
+                Ok(RowStream::new(rows.map(move |row| {
+                    row.and_then(|row| format.row(&row, None))
+                })))
+            }
+            // every host sorted its own rows, the merged rows have to be sorted
+            // again so they're all read before the first is sent
+            UdiPgpModes::Remote if !stmt.order_by.is_empty() => {
+                let (rows, targets, errors) = self.execute_remote_query(stmt).await?;
+                let (rows, targets) = order_rows(rows, targets, &stmt.order_by);
+                let target_errors = TargetErrors::default();
+                errors
+                    .into_iter()
+                    .for_each(|error| target_errors.push(error));
+                Ok(RowStream::new(
+                    stream::iter(rows.into_iter().zip(targets))
+                        .map(move |(row, target)| format.row(&row, Some(&target))),
+                )
+                .with_target_errors(target_errors))
+            }
             UdiPgpModes::Remote => {
-                let (rows, targets) = self.execute_remote_query(&stmt.query).await?;
-                (rows, Some(targets))
+                let target_errors = TargetErrors::default();
+                let rows = self.stream_remote_query(stmt, target_errors.clone())?;
+                Ok(
+                    RowStream::new(rows.map(move |(row, target)| format.row(&row, Some(&target))))
+                        .with_target_errors(target_errors),
+                )
             }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parse_rows_incrementally() {
+        let output = br#"[
+  {"name":"sshd","pid":"1"},
+  {"name":"cron","pid":"2"},
+  {"name":"nginx","pid":"3"}
+]
+"#;
+        let mut names = Vec::new();
+        parse_json_rows(&output[..], |row| {
+            names.push(row["name"].as_str().unwrap().to_string());
+            true
+        })
+        .unwrap();
+        assert_eq!(names, vec!["sshd", "cron", "nginx"]);
+
+        // rows are handed over as they're read, before the output ends
+        let mut first = None;
+        parse_json_rows(&output[..], |row| {
+            first = Some(row);
+            false
+        })
+        .unwrap();
+        assert_eq!(first.unwrap()["pid"], "1");
+        let truncated = &output[..40];
+        let mut read = 0;
+        assert!(parse_json_rows(truncated, |_| {
+            read += 1;
+            true
+        })
+        .is_err());
+        assert_eq!(read, 1);
+        assert!(parse_json_rows(&b"{}"[..], |_| true).is_err());
+    }
+
+    #[test]
+    fn order_merged_rows() {
+        let target =
+            |id: &str| UdiPgpRemoteTarget::from_str(&format!("ops@{id}.local:22,{id}")).unwrap();
+        let (rows, targets) = order_rows(
+            vec![
+                json!({ "name": "sshd", "SUM(resident_size)": "900" }),
+                json!({ "name": "nginx", "SUM(resident_size)": "10000" }),
+                json!({ "name": "cron", "SUM(resident_size)": "900" }),
+            ],
+            vec![target("a"), target("a"), target("b")],
+            &[
+                OrderByColumn {
+                    name: "sum(resident_size)".to_string(),
+                    asc: false,
+                },
+                OrderByColumn {
+                    name: "name".to_string(),
+                    asc: true,
+                },
+            ],
+        );
+        let names = rows
+            .iter()
+            .map(|row| row["name"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["nginx", "cron", "sshd"]);
+        assert_eq!(targets[1].id, "b");
+    }
+
+    #[test]
+    fn explain_remote_query() {
+        let supplier = OsquerySupplier::new(UdiPgpModes::Remote)
+            .with_ssh_targets(vec!["ops@web-01:22,web-01".to_string()]);
+        let stmt = udi_pgp::parser::UdiPgpQueryParser::parse(
+            "EXPLAIN SELECT name, SUM(resident_size) AS memory FROM processes WHERE uid = 0 GROUP BY name ORDER BY memory DESC LIMIT 5",
+            false,
+        )
+        .unwrap();
+        let steps = supplier.explain(&stmt).unwrap();
+        let detail = |step: &str| {
+            steps
+                .iter()
+                .find(|s| s.step == step)
+                .map(|s| s.detail.as_str())
+                .unwrap_or_default()
         };
-        self.rows(&rows, &stmt.columns, targets)
+        assert!(detail("osquery sql").starts_with("osqueryi --json \"SELECT name"));
+        assert_eq!(detail("target"), "ssh://ops@web-01:22 (web-01)");
+        assert_eq!(detail("where"), "uid = 0: pushed down to every target");
+        assert!(detail("aggregate").contains("not across hosts"));
+        assert!(detail("order by").starts_with("memory DESC"));
+        assert_eq!(detail("limit"), "5 rows per target");
+    }
+
+    #[test]
+    fn explain_tagged_targets() {
+        let supplier = OsquerySupplier::new(UdiPgpModes::Remote).with_ssh_targets(vec![
+            "ops@db-01:22,db-01,env=prod,role=db".to_string(),
+            "ops@web-01:22,web-01,env=prod,role=web".to_string(),
+            "ops@db-02:22,db-02,env=staging,role=db".to_string(),
+        ]);
+        let stmt = udi_pgp::parser::UdiPgpQueryParser::parse(
+            "EXPLAIN SELECT name FROM processes WHERE uid = 0 AND udi_pgp_ssh_target_tags = 'env=prod' AND udi_pgp_ssh_target_tags IN ('role=db')",
+            false,
+        )
+        .unwrap();
+        let steps = supplier.explain(&stmt).unwrap();
+        let details = |step: &str| {
+            steps
+                .iter()
+                .filter(|s| s.step == step)
+                .map(|s| s.detail.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            details("osquery sql"),
+            vec!["osqueryi --json \"SELECT name FROM processes WHERE uid = 0\""]
+        );
+        assert_eq!(
+            details("tags"),
+            vec!["env=prod AND role=db: 1 of 3 targets"]
+        );
+        assert_eq!(details("target"), vec!["ssh://ops@db-01:22 (db-01)"]);
+        assert_eq!(
+            details("where"),
+            vec!["uid = 0: pushed down to every target"]
+        );
     }
 }
@@ -0,0 +1,82 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+use udi_pgp::error::{UdiPgpError, UdiPgpResult};
+
+/// A query in an osquery pack, only `query` is required.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct OsqueryPackQuery {
+    pub query: String,
+    pub interval: Option<u64>,
+    pub platform: Option<String>,
+    pub version: Option<String>,
+    pub description: Option<String>,
+    pub value: Option<String>,
+}
+
+/// An osquery query pack (the `pack.conf` JSON format used by osqueryd), see
+/// https://osquery.readthedocs.io/en/stable/deployment/configuration/#query-packs
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct OsqueryPack {
+    #[serde(skip)]
+    pub name: String,
+    pub platform: Option<String>,
+    pub version: Option<String>,
+    #[serde(default)]
+    pub discovery: Vec<String>,
+    pub queries: BTreeMap<String, OsqueryPackQuery>,
+}
+
+impl OsqueryPack {
+    /// Parse a pack, its name is the file stem (e.g. `incident-response.conf`).
+    pub fn try_from_file<P: AsRef<Path>>(path: P) -> UdiPgpResult<OsqueryPack> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)?;
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| "pack".to_string());
+        Self::try_from_json(&name, &content)
+    }
+
+    pub fn try_from_json(name: &str, json: &str) -> UdiPgpResult<OsqueryPack> {
+        let mut pack: OsqueryPack = serde_json::from_str(json).map_err(|err| {
+            UdiPgpError::ConfigError(format!("Invalid osquery pack {}: {}", name, err))
+        })?;
+        pack.name = name.to_string();
+        Ok(pack)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_pack() {
+        let pack = OsqueryPack::try_from_json(
+            "incident-response",
+            r#"{
+                "platform": "posix",
+                "queries": {
+                    "listening_ports": {
+                        "query": "SELECT * FROM listening_ports;",
+                        "interval": 3600,
+                        "description": "Gather information about processes with listening ports."
+                    },
+                    "crontab": { "query": "SELECT * FROM crontab;" }
+                }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(pack.name, "incident-response");
+        assert_eq!(pack.platform.as_deref(), Some("posix"));
+        assert_eq!(
+            pack.queries.keys().collect::<Vec<_>>(),
+            vec!["crontab", "listening_ports"]
+        );
+        assert_eq!(pack.queries["listening_ports"].interval, Some(3600));
+
+        assert!(OsqueryPack::try_from_json("bad", r#"{ "platform": "posix" }"#).is_err());
+    }
+}
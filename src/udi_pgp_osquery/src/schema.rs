@@ -7,10 +7,12 @@ use std::{
 
 use anyhow::Context;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use tracing::{debug, error};
 use udi_pgp::{
     error::{UdiPgpError, UdiPgpResult},
     parser::UdiPgpQueryParser,
+    Row, Type,
 };
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -45,12 +47,58 @@ impl OsquerySchema {
     }
 }
 
+/// Map the type recorded in an [`OsquerySchema`] (the PG type parsed from the
+/// osquery table definition) to the PG type announced to clients. Columns
+/// which aren't in the osquery table definitions (e.g. expressions) stay text.
+pub fn pg_type(column_type: &str) -> Type {
+    match column_type {
+        "int4" => Type::INT4,
+        "int8" => Type::INT8,
+        "float8" => Type::FLOAT8,
+        "bool" => Type::BOOL,
+        _ => Type::VARCHAR,
+    }
+}
+
+/// Encode an osquery JSON value (osquery emits every value as a string) as the
+/// text representation of `ty`. Missing values, empty strings and values which
+/// are not valid for `ty` become NULL.
+pub fn typed_row(value: Option<&Value>, ty: &Type) -> Row {
+    let text = match value {
+        Some(Value::String(s)) => s.trim().to_string(),
+        Some(Value::Number(n)) => n.to_string(),
+        Some(Value::Bool(b)) => (if *b { "1" } else { "0" }).to_string(),
+        _ => return Row::null(),
+    };
+    let typed = if *ty == Type::INT4 {
+        text.parse::<i32>().ok().map(|v| v.to_string())
+    } else if *ty == Type::INT8 {
+        text.parse::<i64>().ok().map(|v| v.to_string())
+    } else if *ty == Type::FLOAT8 {
+        text.parse::<f64>().ok().map(|v| v.to_string())
+    } else if *ty == Type::BOOL {
+        match text.to_lowercase().as_str() {
+            "1" | "t" | "true" | "yes" => Some("t".to_string()),
+            "0" | "f" | "false" | "no" => Some("f".to_string()),
+            _ => None,
+        }
+    } else {
+        Some(text.clone())
+    };
+    typed.map(Row::from).unwrap_or_else(|| {
+        if !text.is_empty() {
+            debug!("'{}' is not a valid {}, returning NULL", text, ty);
+        }
+        Row::null()
+    })
+}
+
 fn format_schema_query(query: &str) -> String {
     query
         .split(';')
         .next()
         .map(|segment| segment.replace('`', "").replace("HIDDEN", ""))
-        .unwrap_or_else(|| "".to_string())
+        .unwrap_or_default()
 }
 
 pub fn get_schema(
@@ -144,3 +192,33 @@ pub fn get_schema(
     }
     Ok(schemas)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn typed_rows() {
+        let cell = |value: Value, ty: &Type| {
+            let row = typed_row(Some(&value), ty);
+            (!row.is_null).then_some(row.value)
+        };
+        assert_eq!(cell(json!("42"), &Type::INT4), Some("42".to_string()));
+        assert_eq!(
+            cell(json!("17179869184"), &Type::INT8),
+            Some("17179869184".to_string())
+        );
+        assert_eq!(cell(json!("0.50"), &Type::FLOAT8), Some("0.5".to_string()));
+        assert_eq!(cell(json!("1"), &Type::BOOL), Some("t".to_string()));
+        assert_eq!(cell(json!("0"), &Type::BOOL), Some("f".to_string()));
+        assert_eq!(cell(json!(""), &Type::INT8), None);
+        assert_eq!(cell(json!("n/a"), &Type::INT4), None);
+        assert_eq!(cell(json!(null), &Type::INT4), None);
+        assert!(typed_row(None, &Type::INT8).is_null);
+
+        assert_eq!(pg_type(&Type::INT8.to_string()), Type::INT8);
+        assert_eq!(pg_type(&Type::FLOAT8.to_string()), Type::FLOAT8);
+        assert_eq!(pg_type("TEXT"), Type::VARCHAR);
+    }
+}
@@ -0,0 +1,548 @@
+use async_trait::async_trait;
+use regex::Regex;
+use resource::shell::{DenoTaskShellExecutive, ShellExecutive, ShellResult, ShellStdIn};
+use serde_json::Value;
+use sqlparser::ast::{BinaryOperator, Expr, SetExpr, Statement};
+use tracing::{debug, info, warn};
+use udi_pgp::{
+    config::{Supplier, SupplierType},
+    error::{UdiPgpError, UdiPgpResult},
+    parser::stmt::{ColumnMetadata, ExpressionType, UdiPgpStatment},
+    sql_supplier::{QueryPlanStep, RowStream, SqlSupplier, SqlSupplierType},
+    FieldFormat, FieldInfo, Row, Type, UdiPgpModes, FACTORY,
+};
+use uuid::Uuid;
+
+/// The virtual table which SELECTs must target, e.g.
+/// `SELECT * FROM tasks WHERE command = 'osqueryi --json "select * from system_info"'`
+pub const TASKS_TABLE: &str = "tasks";
+
+/// Columns of the virtual `tasks` table, in the order used for `SELECT *`.
+const TASKS_COLUMNS: [&str; 6] = [
+    "command",
+    "nature",
+    "exit_status",
+    "output",
+    "stderr",
+    "udi_pgp_session_query_id",
+];
+
+const DEFAULT_NATURE: &str = "json";
+
+/// Characters with which Deno Task Shell sequences, pipes, redirects or
+/// substitutes commands; an allowed command must be a single simple command.
+const SHELL_METACHARACTERS: [char; 10] = [';', '&', '|', '<', '>', '$', '`', '(', ')', '\n'];
+
+pub async fn initialize() {
+    let mut factory = FACTORY().lock().await;
+    factory.register("tasks", generate_new);
+}
+
+fn generate_new(supplier: Supplier) -> UdiPgpResult<SqlSupplierType> {
+    Ok(Box::new(TasksSupplier::try_from(&supplier)?) as SqlSupplierType)
+}
+
+/// Executes Deno Task Shell commands on the host running UDI-PGP, just like
+/// `surveilr ingest tasks`, and returns their output as rows. Only commands
+/// whose whole command line matches one of `allowed_commands` and which have
+/// no shell metacharacters are executed.
+#[derive(Debug, Clone)]
+pub struct TasksSupplier {
+    pub mode: UdiPgpModes,
+    allowed_commands: Vec<Regex>,
+    query_session_id: Option<Uuid>,
+}
+
+impl TryFrom<&Supplier> for TasksSupplier {
+    type Error = UdiPgpError;
+
+    fn try_from(value: &Supplier) -> Result<Self, Self::Error> {
+        TasksSupplier::new(value.mode.clone()).with_allowed_commands(&value.allowed_commands)
+    }
+}
+
+/// The commands (and their nature) requested through the `WHERE` clause. Only
+/// ANDed predicates are accepted: several commands are requested with
+/// `command IN (...)` and each column may only be constrained to one set of
+/// values, so no predicate is silently ignored.
+#[derive(Debug, Default, PartialEq)]
+struct TaskPredicates {
+    commands: Vec<String>,
+    nature: Option<String>,
+}
+
+impl TaskPredicates {
+    fn from_statement(stmt: &Statement) -> UdiPgpResult<TaskPredicates> {
+        let mut predicates = TaskPredicates::default();
+        if let Statement::Query(query) = stmt {
+            if let SetExpr::Select(select) = query.body.as_ref() {
+                if let Some(selection) = &select.selection {
+                    predicates.collect(selection)?;
+                }
+            }
+        }
+        if predicates.commands.is_empty() {
+            return Err(UdiPgpError::QueryExecutionError(format!(
+                "A command is required, e.g. SELECT * FROM {TASKS_TABLE} WHERE command = 'echo \"[]\"'"
+            )));
+        }
+        Ok(predicates)
+    }
+
+    fn collect(&mut self, expr: &Expr) -> UdiPgpResult<()> {
+        match expr {
+            Expr::Nested(expr) => self.collect(expr),
+            Expr::BinaryOp { left, op, right } => match op {
+                BinaryOperator::And => {
+                    self.collect(left)?;
+                    self.collect(right)
+                }
+                BinaryOperator::Or => Err(UdiPgpError::QueryExecutionError(format!(
+                    "OR is not supported in {TASKS_TABLE} predicates, use command IN (...) to execute several commands"
+                ))),
+                BinaryOperator::Eq => {
+                    let value = string_literal(right)?;
+                    self.constrain(column_name(left)?, vec![value])
+                }
+                other => Err(UdiPgpError::QueryExecutionError(format!(
+                    "Unsupported operator in {TASKS_TABLE} predicate: {other}"
+                ))),
+            },
+            Expr::InList {
+                expr,
+                list,
+                negated: false,
+            } => {
+                let values = list
+                    .iter()
+                    .map(string_literal)
+                    .collect::<UdiPgpResult<Vec<_>>>()?;
+                self.constrain(column_name(expr)?, values)
+            }
+            other => Err(UdiPgpError::QueryExecutionError(format!(
+                "Unsupported {TASKS_TABLE} predicate: {other}"
+            ))),
+        }
+    }
+
+    /// Constrain `column` to `values`, which must be the same values when the
+    /// column is already constrained since both predicates couldn't hold.
+    fn constrain(&mut self, column: String, values: Vec<String>) -> UdiPgpResult<()> {
+        let conflicting = || {
+            Err(UdiPgpError::QueryExecutionError(format!(
+                "Conflicting {TASKS_TABLE} predicates on {column}, they can't all hold"
+            )))
+        };
+        match column.as_str() {
+            "command" => {
+                let sorted = |commands: &[String]| {
+                    let mut commands = commands.to_vec();
+                    commands.sort();
+                    commands.dedup();
+                    commands
+                };
+                if self.commands.is_empty() {
+                    self.commands = values;
+                } else if sorted(&self.commands) != sorted(&values) {
+                    return conflicting();
+                }
+            }
+            "nature" => match values.as_slice() {
+                [nature] if self.nature.as_ref().is_none_or(|n| n == nature) => {
+                    self.nature = Some(nature.clone())
+                }
+                _ => return conflicting(),
+            },
+            other => {
+                return Err(UdiPgpError::QueryExecutionError(format!(
+                    "Only command and nature may be filtered in {TASKS_TABLE}, got: {other}"
+                )))
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The `allowed_commands` patterns, anchored so they must match the whole
+/// command line.
+fn allowed_command_regexes(patterns: &[String]) -> UdiPgpResult<Vec<Regex>> {
+    patterns
+        .iter()
+        .map(|p| {
+            Regex::new(&format!("^(?:{p})$")).map_err(|err| {
+                UdiPgpError::ConfigError(format!("Invalid allowed command {p:?}: {err}"))
+            })
+        })
+        .collect()
+}
+
+fn column_name(expr: &Expr) -> UdiPgpResult<String> {
+    match expr {
+        Expr::Identifier(ident) => Ok(ident.value.to_lowercase()),
+        Expr::CompoundIdentifier(idents) if !idents.is_empty() => {
+            Ok(idents.last().unwrap().value.to_lowercase())
+        }
+        other => Err(UdiPgpError::QueryExecutionError(format!(
+            "Expected a column name, got: {other}"
+        ))),
+    }
+}
+
+fn string_literal(expr: &Expr) -> UdiPgpResult<String> {
+    match expr {
+        Expr::Value(sqlparser::ast::Value::SingleQuotedString(s))
+        | Expr::Value(sqlparser::ast::Value::DollarQuotedString(
+            sqlparser::ast::DollarQuotedString { value: s, .. },
+        )) => Ok(s.clone()),
+        other => Err(UdiPgpError::QueryExecutionError(format!(
+            "Expected a string literal, got: {other}"
+        ))),
+    }
+}
+
+/// One executed command; `outputs` has an entry per element when the command
+/// emitted a JSON array, otherwise its whole stdout.
+struct TaskResult {
+    command: String,
+    nature: String,
+    result: ShellResult,
+    outputs: Vec<String>,
+}
+
+impl TasksSupplier {
+    pub fn new(mode: UdiPgpModes) -> Self {
+        TasksSupplier {
+            mode,
+            allowed_commands: vec![],
+            query_session_id: None,
+        }
+    }
+
+    pub fn with_allowed_commands(mut self, patterns: &[String]) -> UdiPgpResult<Self> {
+        self.allowed_commands = allowed_command_regexes(patterns)?;
+        Ok(self)
+    }
+
+    fn authorize(&self, command: &str) -> UdiPgpResult<()> {
+        if command.contains(SHELL_METACHARACTERS) {
+            warn!(
+                "Rejected tasks supplier command with shell metacharacters: {}",
+                command
+            );
+            return Err(UdiPgpError::QueryExecutionError(format!(
+                "Command must not contain shell metacharacters ({}): {command}",
+                String::from_iter(SHELL_METACHARACTERS.iter().filter(|c| **c != '\n'))
+            )));
+        }
+        if self.allowed_commands.iter().any(|re| re.is_match(command)) {
+            Ok(())
+        } else {
+            warn!("Rejected tasks supplier command: {}", command);
+            Err(UdiPgpError::QueryExecutionError(format!(
+                "Command is not allowed by the supplier's allowed-commands: {command}"
+            )))
+        }
+    }
+
+    fn column_to_field_info(&self, col: &ColumnMetadata) -> UdiPgpResult<FieldInfo> {
+        let cid =
+            TASKS_COLUMNS
+                .iter()
+                .position(|c| *c == col.name)
+                .ok_or(UdiPgpError::SchemaError(
+                    TASKS_TABLE.to_string(),
+                    format!("Invalid column name: {}", col.name),
+                ))?;
+        let name = col.alias.clone().unwrap_or(col.name.clone());
+        Ok(FieldInfo::new(
+            name,
+            None,
+            Some(cid as i16),
+            col.r#type.clone(),
+            FieldFormat::Text,
+        ))
+    }
+
+    async fn execute_task(command: String, nature: String) -> UdiPgpResult<TaskResult> {
+        let cmd = command.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            DenoTaskShellExecutive::new(cmd, None).execute(ShellStdIn::None)
+        })
+        .await
+        .map_err(|err| UdiPgpError::QueryExecutionError(err.to_string()))?
+        .map_err(|err| UdiPgpError::QueryExecutionError(err.to_string()))?;
+        debug!("Executed task {:?}: {:?}", command, result.status);
+
+        let outputs = match serde_json::from_str::<Value>(&result.stdout) {
+            Ok(Value::Array(values)) if nature == DEFAULT_NATURE => {
+                values.iter().map(Value::to_string).collect()
+            }
+            _ => vec![result.stdout.clone()],
+        };
+        Ok(TaskResult {
+            command,
+            nature,
+            result,
+            outputs,
+        })
+    }
+
+    fn rows(&self, tasks: &[TaskResult], columns: &[ColumnMetadata]) -> Vec<Vec<Row>> {
+        let mut rows = Vec::new();
+        for task in tasks {
+            for output in &task.outputs {
+                let row = columns
+                    .iter()
+                    .map(|col| {
+                        Row::from(match col.name.as_str() {
+                            "command" => task.command.clone(),
+                            "nature" => task.nature.clone(),
+                            "exit_status" => format!("{:?}", task.result.status),
+                            "output" => output.clone(),
+                            "stderr" => task.result.stderr.clone(),
+                            "udi_pgp_session_query_id" => match self.query_session_id {
+                                Some(id) => id.to_string(),
+                                None => "null".to_string(),
+                            },
+                            _ => String::new(),
+                        })
+                    })
+                    .collect();
+                rows.push(row);
+            }
+        }
+        rows
+    }
+}
+
+#[async_trait]
+impl SqlSupplier for TasksSupplier {
+    fn name(&self) -> &str {
+        "tasks"
+    }
+
+    fn supplier_type(&self) -> SupplierType {
+        SupplierType::Tasks
+    }
+
+    fn update(&mut self, supplier: Supplier) -> UdiPgpResult<()> {
+        self.mode = supplier.mode;
+        self.allowed_commands = allowed_command_regexes(&supplier.allowed_commands)?;
+        Ok(())
+    }
+
+    fn add_session_id(&mut self, session_id: Uuid) -> UdiPgpResult<()> {
+        self.query_session_id = Some(session_id);
+        Ok(())
+    }
+
+    fn generate_new(&self, supplier: Supplier) -> UdiPgpResult<SqlSupplierType> {
+        generate_new(supplier)
+    }
+
+    async fn schema(&mut self, stmt: &mut UdiPgpStatment) -> UdiPgpResult<Vec<FieldInfo>> {
+        if let Some(table) = stmt.tables.iter().find(|t| t.as_str() != TASKS_TABLE) {
+            return Err(UdiPgpError::SchemaError(
+                table.to_string(),
+                format!("The tasks supplier only serves the {TASKS_TABLE} table"),
+            ));
+        }
+
+        if stmt.columns.len() == 1 && stmt.columns.first().is_some_and(|c| c.name == "*") {
+            stmt.columns = TASKS_COLUMNS[..TASKS_COLUMNS.len() - 1]
+                .iter()
+                .map(|name| ColumnMetadata {
+                    name: name.to_string(),
+                    expr_type: ExpressionType::Standard,
+                    alias: None,
+                    r#type: Type::VARCHAR,
+                })
+                .collect();
+        } else {
+            stmt.columns
+                .iter_mut()
+                .for_each(|col| col.name = col.name.to_lowercase());
+        }
+
+        // Always add the query session column
+        stmt.columns.push(ColumnMetadata::query_session_column());
+
+        stmt.columns
+            .iter()
+            .map(|col| self.column_to_field_info(col))
+            .collect()
+    }
+
+    fn explain(&self, stmt: &UdiPgpStatment) -> UdiPgpResult<Vec<QueryPlanStep>> {
+        let mut steps = vec![QueryPlanStep::new("mode", self.mode.to_string())];
+        let predicates = TaskPredicates::from_statement(&stmt.stmt)?;
+        for command in &predicates.commands {
+            steps.push(QueryPlanStep::new(
+                "command",
+                match self.authorize(command) {
+                    Ok(_) => format!("{command}: executed by Deno Task Shell"),
+                    Err(_) => format!("{command}: rejected, not in allowed-commands"),
+                },
+            ));
+        }
+        steps.push(QueryPlanStep::new(
+            "nature",
+            predicates.nature.as_deref().unwrap_or(DEFAULT_NATURE),
+        ));
+        Ok(steps)
+    }
+
+    async fn execute(&mut self, stmt: &UdiPgpStatment) -> UdiPgpResult<RowStream> {
+        if let UdiPgpModes::Remote = self.mode {
+            return Err(UdiPgpError::QueryExecutionError(
+                "The tasks supplier only executes commands on the local machine".to_string(),
+            ));
+        }
+
+        let predicates = TaskPredicates::from_statement(&stmt.stmt)?;
+        predicates
+            .commands
+            .iter()
+            .try_for_each(|command| self.authorize(command))?;
+
+        let nature = predicates
+            .nature
+            .unwrap_or_else(|| DEFAULT_NATURE.to_string());
+        let mut tasks = Vec::with_capacity(predicates.commands.len());
+        for command in predicates.commands {
+            tasks.push(Self::execute_task(command, nature.clone()).await?);
+        }
+        info!("Tasks supplier executed {} command(s).", tasks.len());
+
+        Ok(RowStream::from_rows(self.rows(&tasks, &stmt.columns)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use udi_pgp::parser::UdiPgpQueryParser;
+
+    use super::*;
+
+    #[test]
+    fn test_task_predicates() {
+        let stmt = UdiPgpQueryParser::parse(
+            "SELECT output FROM tasks WHERE command IN ('echo 1', 'echo 2', 'echo 3') AND nature = 'txt' AND (command IN ('echo 3', 'echo 2', 'echo 1'))",
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            TaskPredicates::from_statement(&stmt.stmt).unwrap(),
+            TaskPredicates {
+                commands: vec!["echo 1".into(), "echo 2".into(), "echo 3".into()],
+                nature: Some("txt".into()),
+            }
+        );
+
+        let stmt = UdiPgpQueryParser::parse("SELECT * FROM tasks", false).unwrap();
+        assert!(TaskPredicates::from_statement(&stmt.stmt).is_err());
+    }
+
+    #[test]
+    fn test_task_predicates_reject_or() {
+        for sql in [
+            "SELECT * FROM tasks WHERE command = 'echo 1' OR command = 'echo 2'",
+            "SELECT * FROM tasks WHERE command = 'echo 1' AND (nature = 'txt' OR nature = 'json')",
+        ] {
+            let stmt = UdiPgpQueryParser::parse(sql, false).unwrap();
+            let err = TaskPredicates::from_statement(&stmt.stmt).unwrap_err();
+            assert!(
+                err.to_string().contains("OR is not supported"),
+                "{sql}: {err}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_task_predicates_reject_conflicting_equalities() {
+        for sql in [
+            "SELECT * FROM tasks WHERE command = 'echo 1' AND command = 'echo 2'",
+            "SELECT * FROM tasks WHERE command IN ('echo 1', 'echo 2') AND command = 'echo 1'",
+            "SELECT * FROM tasks WHERE command = 'echo 1' AND nature = 'txt' AND nature = 'json'",
+            "SELECT * FROM tasks WHERE command = 'echo 1' AND nature IN ('txt', 'json')",
+        ] {
+            let stmt = UdiPgpQueryParser::parse(sql, false).unwrap();
+            let err = TaskPredicates::from_statement(&stmt.stmt).unwrap_err();
+            assert!(err.to_string().contains("Conflicting"), "{sql}: {err}");
+        }
+
+        // repeating the same predicate is harmless
+        let stmt = UdiPgpQueryParser::parse(
+            "SELECT * FROM tasks WHERE command = 'echo 1' AND nature = 'txt' AND command = 'echo 1' AND nature = 'txt'",
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            TaskPredicates::from_statement(&stmt.stmt).unwrap(),
+            TaskPredicates {
+                commands: vec!["echo 1".into()],
+                nature: Some("txt".into()),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute_allowed_commands() {
+        let mut supplier = TasksSupplier::new(UdiPgpModes::Local)
+            .with_allowed_commands(&["echo .*".to_string()])
+            .unwrap();
+
+        let mut stmt = UdiPgpQueryParser::parse(
+            r#"SELECT command, output FROM tasks WHERE command = 'echo ''[{"a": 1}, {"a": 2}]'''"#,
+            false,
+        )
+        .unwrap();
+        let fields = supplier.schema(&mut stmt).await.unwrap();
+        assert_eq!(fields.len(), 3);
+        let rows = supplier
+            .execute(&stmt)
+            .await
+            .unwrap()
+            .collect_rows()
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1][1].value, r#"{"a":2}"#);
+
+        let stmt = UdiPgpQueryParser::parse(
+            "SELECT * FROM tasks WHERE command = 'rm -rf /tmp/nothing'",
+            false,
+        )
+        .unwrap();
+        assert!(supplier.execute(&stmt).await.is_err());
+
+        // allowed commands must match the whole command line and be a single command
+        for command in [
+            "echo x; rm -rf /tmp/nothing",
+            "echo x && rm -rf /tmp/nothing",
+            "echo x | sh",
+            "echo $(rm -rf /tmp/nothing)",
+            "echo x > /tmp/nothing",
+        ] {
+            assert!(supplier.authorize(command).is_err(), "{command}");
+        }
+        let anchored = TasksSupplier::new(UdiPgpModes::Local)
+            .with_allowed_commands(&["echo".to_string()])
+            .unwrap();
+        assert!(anchored.authorize("echo").is_ok());
+        assert!(anchored.authorize("echo x").is_err());
+        assert!(anchored.authorize("rm -rf /tmp/nothing; echo").is_err());
+
+        let stmt = UdiPgpQueryParser::parse(
+            "EXPLAIN SELECT * FROM tasks WHERE command IN ('echo 1', 'rm -rf /tmp/nothing')",
+            false,
+        )
+        .unwrap();
+        let steps = supplier.explain(&stmt).unwrap();
+        assert_eq!(steps[1].detail, "echo 1: executed by Deno Task Shell");
+        assert_eq!(
+            steps[2].detail,
+            "rm -rf /tmp/nothing: rejected, not in allowed-commands"
+        );
+    }
+}
@@ -223,6 +223,137 @@ export function codeNotebooksModels<
     },
   });
 
+  const policyFinding = gm.textPkTable("policy_finding", {
+    policy_finding_id: gm.keys.varCharPrimaryKey(),
+    pack: gd.text(),
+    assertion: gd.text(),
+    severity: gd.text(),
+    state: gd.text(),
+    status: gd.text(),
+    row_count: gd.integer(),
+    sample_rows: gd.jsonTextNullable(),
+    remediation: gd.textNullable(),
+    error: gd.textNullable(),
+    last_seen_at: gd.dateTime(),
+    acknowledged_at: gd.dateTimeNullable(),
+    acknowledged_by: gd.textNullable(),
+    resolved_at: gd.dateTimeNullable(),
+    elaboration: gd.jsonTextNullable(),
+    ...gm.housekeeping.columns,
+  }, {
+    isIdempotent: true,
+    indexes: (props, tableName) => {
+      const tif = SQLa.tableIndexesFactory(tableName, props);
+      return [tif.index({ isIdempotent: true }, "pack", "assertion", "state")];
+    },
+    populateQS: (t, c, _cols, tableName) => {
+      t.description = markdown`
+        Findings of failed policy assertions recorded by \`policy run\`. A
+        finding stays open (\`new\`, then \`acknowledged\` with \`policy ack\`)
+        while its assertion keeps failing and is \`resolved\` once it passes;
+        failing again opens a new ${tableName} row.`;
+      c.pack.description = `the policy pack (notebook name) of the assertion`;
+      c.assertion.description = `the assertion (cell name) which failed`;
+      c.severity.description =
+        `\`info\`, \`low\`, \`medium\`, \`high\` or \`critical\``;
+      c.state.description = `\`new\`, \`acknowledged\` or \`resolved\``;
+      c.status.description =
+        `\`FAIL\` or \`ERROR\` (the SQL could not be evaluated) in the latest run`;
+      c.row_count.description =
+        `the number of rows the assertion returned in the latest run`;
+      c.sample_rows.description =
+        `JSON array of the first rows the assertion returned, as evidence`;
+      c.last_seen_at.description = `when a run last found the assertion failing`;
+      c.acknowledged_by.description =
+        `the operating system user who acknowledged the finding`;
+      c.resolved_at.description =
+        `when a run first found the assertion passing again`;
+    },
+  });
+
+  const policyAlertRule = gm.textPkTable("policy_alert_rule", {
+    policy_alert_rule_id: gm.keys.varCharPrimaryKey(),
+    name: gd.text(),
+    min_severity: gd.text(),
+    fire_on: gd.text(),
+    channel: gd.text(),
+    target: gd.text(),
+    packs: gd.jsonTextNullable(),
+    elaboration: gd.jsonTextNullable(),
+    ...gm.housekeeping.columns,
+  }, {
+    isIdempotent: true,
+    constraints: (props, tableName) => {
+      const c = SQLa.tableConstraints(tableName, props);
+      return [c.unique("name")];
+    },
+    populateQS: (t, c) => {
+      t.description = markdown`
+        Rules which notify about policy findings after \`policy run\`, managed
+        with \`policy alert\`.`;
+      c.min_severity.description = `the least severe findings which are notified`;
+      c.fire_on.description =
+        `\`new\` for findings first seen in the run, \`open\` for all unacknowledged findings`;
+      c.channel.description = `\`webhook\` or \`email\``;
+      c.target.description = `the webhook URL or email address`;
+      c.packs.description =
+        `JSON array of the packs the rule applies to, all packs if NULL`;
+    },
+  });
+
+  const complianceControl = gm.textPkTable("compliance_control", {
+    compliance_control_id: gm.keys.varCharPrimaryKey(),
+    framework: gd.text(),
+    control_code: gd.text(),
+    title: gd.textNullable(),
+    description: gd.textNullable(),
+    elaboration: gd.jsonTextNullable(),
+    ...gm.housekeeping.columns,
+  }, {
+    isIdempotent: true,
+    constraints: (props, tableName) => {
+      const c = SQLa.tableConstraints(tableName, props);
+      return [c.unique("framework", "control_code")];
+    },
+    populateQS: (t, c) => {
+      t.description = markdown`
+        Controls of compliance frameworks (e.g. SOC2, NIST 800-53, ISO 27001)
+        which evidence is mapped to with \`compliance map\`, imported with
+        \`compliance import\`.`;
+      c.framework.description = `the framework identifier, e.g. \`SOC2\``;
+      c.control_code.description =
+        `the control identifier within its framework, e.g. \`CC6.1\``;
+      c.title.description = `the short name of the control`;
+    },
+  });
+
+  const complianceEvidenceMap = gm.textPkTable("compliance_evidence_map", {
+    compliance_evidence_map_id: gm.keys.varCharPrimaryKey(),
+    compliance_control_id: complianceControl.belongsTo.compliance_control_id(),
+    evidence_kind: gd.text(),
+    evidence_selector: gd.text(),
+    description: gd.textNullable(),
+    elaboration: gd.jsonTextNullable(),
+    ...gm.housekeeping.columns,
+  }, {
+    isIdempotent: true,
+    constraints: (props, tableName) => {
+      const c = SQLa.tableConstraints(tableName, props);
+      return [
+        c.unique("compliance_control_id", "evidence_kind", "evidence_selector"),
+      ];
+    },
+    populateQS: (t, c) => {
+      t.description = markdown`
+        Evidence which demonstrates a compliance control, summarized per
+        framework by \`compliance report\`.`;
+      c.evidence_kind.description =
+        `\`resource\` (uniform resources), \`policy\` (policy assertions) or \`capturable-exec\` (outputs of capturable executables)`;
+      c.evidence_selector.description =
+        `GLOB of the resources' or capturable executables' URIs, or a policy pack with an optional \`::assertion\``;
+    },
+  });
+
   const informationSchema = {
     tables: [
       assuranceSchema,
@@ -393,6 +524,12 @@ export function serviceModels<EmitContext extends SQLa.SqlEmitContext>() {
     ingest_finished_at: gd.dateTimeNullable(),
     elaboration: gd.jsonTextNullable(),
     ...gm.housekeeping.columns,
+    // after the housekeeping columns because older RSSDs get it through
+    // `ALTER TABLE ... ADD COLUMN` (see v018_once_deviceClockDDL)
+    device_clock: gd.jsonTextNullable(),
+    // `sha1` unless `ingest files --digest` asked for another algorithm, older
+    // RSSDs get it with that default (see v026_once_contentDigestAlgorithmDDL)
+    content_digest_algorithm: gd.text(),
   }, {
     isIdempotent: true,
     constraints: (props, tableName) => {
@@ -401,7 +538,7 @@ export function serviceModels<EmitContext extends SQLa.SqlEmitContext>() {
         c.unique("device_id", "created_at"),
       ];
     },
-    populateQS: (t, _c, _cols, tableName) => {
+    populateQS: (t, c, _cols, tableName) => {
       t.description = markdown`
         Immutable ingestion sessions represents any "discovery" or "walk" operation.
         This could be a device file system scan or any other resource discovery
@@ -410,6 +547,10 @@ export function serviceModels<EmitContext extends SQLa.SqlEmitContext>() {
         same device can be used for multiple ingest sessions but also the ingest
         sessions can be merged across workstations / servers for easier detection
         of changes and similaries between file systems on different devices.`;
+      c.device_clock.description =
+        `the device's time zone, UTC offset, clock source and NTP offset estimate when the session started (JSON)`;
+      c.content_digest_algorithm.description =
+        `the algorithm (sha1, sha256 or blake3) of the content digests of the session's resources`;
     },
   });
 
@@ -532,6 +673,11 @@ export function serviceModels<EmitContext extends SQLa.SqlEmitContext>() {
     frontmatter: gd.jsonTextNullable(),
     elaboration: gd.jsonTextNullable(),
     ...gm.housekeeping.columns,
+    // after the housekeeping columns because older RSSDs get it through
+    // `ALTER TABLE ... ADD COLUMN` and `admin merge` copies rows with `SELECT *`
+    semantic_identity: gd.textNullable(),
+    // the algorithm of `content_digest`, copied from the ingest session
+    content_digest_algorithm: gd.text(),
   }, {
     isIdempotent: true,
     constraints: (props, tableName) => {
@@ -552,6 +698,12 @@ export function serviceModels<EmitContext extends SQLa.SqlEmitContext>() {
       const tif = SQLa.tableIndexesFactory(tableName, props);
       return [
         tif.index({ isIdempotent: true }, "device_id", "uri"),
+        tif.index(
+          { isIdempotent: true },
+          "device_id",
+          "nature",
+          "semantic_identity",
+        ),
       ];
     },
     populateQS: (t, c, _cols, tableName) => {
@@ -582,6 +734,10 @@ export function serviceModels<EmitContext extends SQLa.SqlEmitContext>() {
         `meta data or other "frontmatter" in JSON format`;
       c.elaboration.description =
         `anything that doesn't fit in other columns (JSON)`;
+      c.semantic_identity.description =
+        `nature-specific identity (e.g. email Message-ID, SBOM serial number); resources of the same nature with the same identity are deduplicated regardless of content digest`;
+      c.content_digest_algorithm.description =
+        `the algorithm (sha1, sha256 or blake3) of content_digest`;
     },
   });
 
@@ -744,6 +900,8 @@ export function serviceModels<EmitContext extends SQLa.SqlEmitContext>() {
       bcc: gd.jsonText(),
       email_references: gd.jsonText(),
       ...gm.housekeeping.columns,
+      // after the housekeeping columns, see v018_once_deviceClockDDL
+      date: gd.textNullable(),
     },
     {
       isIdempotent: true,
@@ -762,7 +920,7 @@ export function serviceModels<EmitContext extends SQLa.SqlEmitContext>() {
           ),
         ];
       },
-      populateQS: (t, _c, _cols, tableName) => {
+      populateQS: (t, c, _cols, tableName) => {
         t.description = markdown`
           Contains messages related in a folder that was ingested. On multiple executions,
           unlike ${uniformResource.tableName}, ${tableName} rows are always inserted and
@@ -771,6 +929,510 @@ export function serviceModels<EmitContext extends SQLa.SqlEmitContext>() {
           sessions. With SQL queries, you can detect which sessions have a messaged added or modified,
           which sessions have a message deleted, and what the differences are in message contents
           if they were modified across sessions.`;
+        c.date.description =
+          `the message's date (RFC 3339) with the sender's UTC offset; ${uniformResource.tableName}.last_modified_at has it in UTC`;
+      },
+    },
+  );
+
+  const uniformResourceLineage = gm.textPkTable(
+    "uniform_resource_lineage",
+    {
+      uniform_resource_lineage_id: gm.keys.varCharPrimaryKey(),
+      ingest_session_id: urIngestSession.references
+        .ur_ingest_session_id(),
+      source_uniform_resource_id: uniformResource.references
+        .uniform_resource_id().optional(), // the capturable executable itself, if it was stored
+      output_uniform_resource_id: uniformResource.references
+        .uniform_resource_id(),
+      ingest_fs_path_entry_id: urIngestSessionFsPathEntry.references
+        .ur_ingest_session_fs_path_entry_id().optional(),
+      ingest_task_id: urIngestSessionTaskEntry.references
+        .ur_ingest_session_task_id().optional(),
+      elaboration: gd.jsonTextNullable(), // anything that doesn't fit above
+      ...gm.housekeeping.columns,
+    },
+    {
+      isIdempotent: true,
+      indexes: (props, tableName) => {
+        const tif = SQLa.tableIndexesFactory(tableName, props);
+        return [
+          tif.index(
+            { isIdempotent: true },
+            "ingest_session_id",
+          ),
+          tif.index(
+            { isIdempotent: true },
+            "output_uniform_resource_id",
+          ),
+        ];
+      },
+      populateQS: (t, c, _cols, tableName) => {
+        t.description = markdown`
+          Links ${uniformResource.tableName} rows which were generated by capturable
+          executables to the ${uniformResource.tableName} row of the executable itself
+          and to the ${urIngestSessionFsPathEntry.tableName} or ${urIngestSessionTaskEntry.tableName}
+          row which recorded the execution. Like the other session tables, ${tableName}
+          rows are always inserted so every derived artifact can be traced back to the
+          code that generated it in each session.`;
+        c.source_uniform_resource_id.description =
+          `${uniformResource.tableName} row of the capturable executable (script)`;
+        c.output_uniform_resource_id.description =
+          `${uniformResource.tableName} row of the captured output`;
+        c.ingest_fs_path_entry_id.description =
+          `${urIngestSessionFsPathEntry.tableName} row when the executable was found by \`ingest files\``;
+        c.ingest_task_id.description =
+          `${urIngestSessionTaskEntry.tableName} row when the executable was supplied by \`ingest tasks\``;
+      },
+    },
+  );
+
+  const knownFileHash = gm.textPkTable("known_file_hash", {
+    known_file_hash_id: gm.keys.varCharPrimaryKey(),
+    hash_set: gd.text(),
+    algorithm: gd.text(),
+    digest: gd.text(),
+    disposition: gd.text(),
+    confidence: gd.float(),
+    file_name: gd.textNullable(),
+    elaboration: gd.jsonTextNullable(),
+    ...gm.housekeeping.columns,
+  }, {
+    isIdempotent: true,
+    constraints: (props, tableName) => {
+      const c = SQLa.tableConstraints(tableName, props);
+      return [c.unique("hash_set", "algorithm", "digest")];
+    },
+    indexes: (props, tableName) => {
+      const tif = SQLa.tableIndexesFactory(tableName, props);
+      return [tif.index({ isIdempotent: true }, "algorithm", "digest")];
+    },
+    populateQS: (t, c) => {
+      t.description = markdown`
+        Hashes of known files (NSRL reference data sets or custom allow and deny
+        lists) loaded with \`known-files load\`, which ingested resources are
+        matched against.`;
+      c.hash_set.description = `the name the hashes were loaded as, e.g. \`nsrl\``;
+      c.algorithm.description = `\`sha1\`, \`sha256\` or \`md5\``;
+      c.digest.description = `the lowercase hex digest`;
+      c.disposition.description = `\`known-good\` or \`known-bad\``;
+      c.confidence.description = `how much the hash set is trusted, from 0 to 1`;
+      c.file_name.description = `the name of the known file, if the hash set has it`;
+    },
+  });
+
+  const uniformResourceKnownFile = gm.textPkTable(
+    "uniform_resource_known_file",
+    {
+      uniform_resource_known_file_id: gm.keys.varCharPrimaryKey(),
+      uniform_resource_id: uniformResource.references.uniform_resource_id(),
+      known_file_hash_id: knownFileHash.references.known_file_hash_id(),
+      hash_set: gd.text(),
+      disposition: gd.text(),
+      confidence: gd.float(),
+      elaboration: gd.jsonTextNullable(),
+      ...gm.housekeeping.columns,
+    },
+    {
+      isIdempotent: true,
+      constraints: (props, tableName) => {
+        const c = SQLa.tableConstraints(tableName, props);
+        return [c.unique("uniform_resource_id", "known_file_hash_id")];
+      },
+      indexes: (props, tableName) => {
+        const tif = SQLa.tableIndexesFactory(tableName, props);
+        return [tif.index({ isIdempotent: true }, "uniform_resource_id")];
+      },
+      populateQS: (t, c) => {
+        t.description = markdown`
+          ${uniformResource.tableName} rows whose content matches a
+          ${knownFileHash.tableName} hash, recorded by \`known-files match\` or
+          \`ingest --match-known-files\`; resources without a match are unknown.`;
+        c.hash_set.description =
+          `the ${knownFileHash.tableName} set the resource matched (the match source)`;
+        c.disposition.description = `\`known-good\` or \`known-bad\``;
+        c.confidence.description = `the confidence of the matched hash`;
+      },
+    },
+  );
+
+  const urIngestSessionGitRepo = gm.textPkTable(
+    "ur_ingest_session_git_repo",
+    {
+      ur_ingest_session_git_repo_id: gm.keys.varCharPrimaryKey(),
+      ingest_session_id: urIngestSession.belongsTo
+        .ur_ingest_session_id(),
+      repo: gd.text(),
+      reference: gd.text(),
+      commit_sha: gd.text(),
+      author_name: gd.text(),
+      author_email: gd.text(),
+      authored_at: gd.dateTime(),
+      committer_name: gd.text(),
+      committer_email: gd.text(),
+      committed_at: gd.dateTime(),
+      subject: gd.text(),
+      elaboration: gd.jsonTextNullable(),
+      ...gm.housekeeping.columns,
+    },
+    {
+      isIdempotent: true,
+      constraints: (props, tableName) => {
+        const c = SQLa.tableConstraints(tableName, props);
+        return [c.unique("ingest_session_id", "repo")];
+      },
+      indexes: (props, tableName) => {
+        const tif = SQLa.tableIndexesFactory(tableName, props);
+        return [tif.index({ isIdempotent: true }, "repo", "commit_sha")];
+      },
+      populateQS: (t, c) => {
+        t.description = markdown`
+          The repository and commit each \`ingest git\` session read its files from,
+          with the commit's author and committer. The dates are in UTC, the
+          elaboration has them with their original UTC offsets.`;
+        c.repo.description = `the repository's path or URL, as given`;
+        c.reference.description =
+          `the branch, tag or commit which was resolved, \`HEAD\` by default`;
+        c.commit_sha.description =
+          `the full SHA of the commit the files were read from`;
+        c.authored_at.description = `the author date, in UTC`;
+        c.committed_at.description = `the committer date, in UTC`;
+        c.subject.description = `the first line of the commit message`;
+      },
+    },
+  );
+
+  const urIngestSessionImapAcctStat = gm.textPkTable(
+    "ur_ingest_session_imap_acct_stat",
+    {
+      ur_ingest_session_imap_acct_stat_id: gm.keys.varCharPrimaryKey(),
+      ingest_session_id: urIngestSession.belongsTo
+        .ur_ingest_session_id(),
+      ingest_account_id: urIngestSessionImapAccount.belongsTo
+        .ur_ingest_session_imap_account_id(),
+      stat_kind: gd.text(),
+      stat_key: gd.text(),
+      stat_value: gd.integer(),
+      elaboration: gd.jsonTextNullable(),
+      ...gm.housekeeping.columns,
+    },
+    {
+      isIdempotent: true,
+      indexes: (props, tableName) => {
+        const tif = SQLa.tableIndexesFactory(tableName, props);
+        return [
+          tif.index({ isIdempotent: true }, "ingest_session_id", "stat_kind"),
+        ];
+      },
+      populateQS: (t, c, _cols, tableName) => {
+        t.description = markdown`
+          Mailbox statistics computed at the end of each \`ingest imap\` session
+          for triage dashboards: one ${tableName} row per statistic, e.g. the
+          messages received on a day or sent by a sender. The same summary is
+          stored in the session's elaboration.`;
+        c.stat_kind.description =
+          `\`daily_volume\`, \`top_sender\`, \`attachment_type\` or \`volume_spike\``;
+        c.stat_key.description =
+          `the day (YYYY-MM-DD), sender address or attachment nature counted`;
+        c.stat_value.description = `the number of messages (or attachments)`;
+        c.elaboration.description =
+          `for \`volume_spike\` rows, the baseline of previous sessions it exceeded`;
+      },
+    },
+  );
+
+  const rssdMetadata = gm.textPkTable("rssd_metadata", {
+    key: gm.keys.textPrimaryKey(),
+    value: gd.text(),
+    elaboration: gd.jsonTextNullable(),
+    ...gm.housekeeping.columns,
+  }, {
+    isIdempotent: true,
+    populateQS: (t, c, _cols, tableName) => {
+      t.description = markdown`
+        Settings which were chosen when the RSSD was initialized and which must
+        stay fixed for its lifetime (e.g. \`primary_key_strategy\`). ${tableName}
+        is consulted on every open and compared across RSSDs before merging.`;
+      c.key.description = `the name of the setting`;
+      c.value.description = `the value of the setting`;
+    },
+  });
+
+  const querySnapshot = gm.textPkTable("query_snapshot", {
+    query_snapshot_id: gm.keys.varCharPrimaryKey(),
+    name: gd.text(),
+    query_sql: gd.text(),
+    result_schema: gd.jsonText(),
+    result_rows: gd.jsonText(),
+    row_count: gd.integer(),
+    content_digest: gd.text(),
+    elaboration: gd.jsonTextNullable(),
+    ...gm.housekeeping.columns,
+  }, {
+    isIdempotent: true,
+    indexes: (props, tableName) => {
+      const tif = SQLa.tableIndexesFactory(tableName, props);
+      return [tif.index({ isIdempotent: true }, "name", "created_at")];
+    },
+    populateQS: (t, c, _cols, tableName) => {
+      t.description = markdown`
+        Materialized results of SQL queries saved by \`snapshot create\`. Each
+        execution of a named query inserts a new ${tableName} row so reports can
+        be versioned and compared with \`snapshot diff\`.`;
+      c.name.description = `the snapshot name, shared by every version of a report`;
+      c.query_sql.description = `the SQL which produced the result set`;
+      c.result_schema.description =
+        `JSON array of the result columns and their declared types`;
+      c.result_rows.description = `JSON array of the result rows as objects`;
+      c.content_digest.description =
+        `SHA-1 of result_rows, identical when a report has not changed`;
+    },
+  });
+
+  const surveilrAudit = gm.textPkTable("surveilr_audit", {
+    surveilr_audit_id: gm.keys.varCharPrimaryKey(),
+    command: gd.text(),
+    argv: gd.jsonText(),
+    os_user: gd.text(),
+    host: gd.text(),
+    started_at: gd.dateTime(),
+    outcome: gd.text(),
+    error: gd.textNullable(),
+    elaboration: gd.jsonTextNullable(),
+    ...gm.housekeeping.columns,
+  }, {
+    isIdempotent: true,
+    indexes: (props, tableName) => {
+      const tif = SQLa.tableIndexesFactory(tableName, props);
+      return [tif.index({ isIdempotent: true }, "command", "started_at")];
+    },
+    populateQS: (t, c, _cols, tableName) => {
+      t.description = markdown`
+        Audit trail of the surveilr commands which change or destroy the RSSD
+        (\`admin init\`, \`admin merge\`, \`admin seed\`, \`admin restore\`,
+        \`ingest files --save-behavior\`, \`notebooks publish\` and
+        \`snapshot create\`). Each invocation inserts a ${tableName} row once the
+        command finished, whether or not it succeeded.`;
+      c.command.description = `the subcommand, e.g. \`admin merge\``;
+      c.argv.description = `JSON array of the command line arguments`;
+      c.os_user.description = `the operating system user who ran the command`;
+      c.host.description = `the host name of the device the command ran on`;
+      c.started_at.description = `when the command started`;
+      c.outcome.description = `\`succeeded\` or \`failed\``;
+      c.error.description = `the error of a failed command`;
+    },
+  });
+
+  const surveilrRemoteSync = gm.textPkTable("surveilr_remote_sync", {
+    surveilr_remote_sync_id: gm.keys.varCharPrimaryKey(),
+    remote_url: gd.text(),
+    table_name: gd.text(),
+    synced_rowid: gd.integer(),
+    synced_rows: gd.integer(),
+    synced_at: gd.dateTime(),
+    ...gm.housekeeping.columns,
+    // after the housekeeping columns because older RSSDs get them through
+    // `ALTER TABLE ... ADD COLUMN` (see v028_once_surveilrRemoteSyncChangeDDL)
+    synced_change_id: gd.integer(),
+    scanned_at: gd.dateTimeNullable(),
+  }, {
+    isIdempotent: true,
+    constraints: (props, tableName) => {
+      const c = SQLa.tableConstraints(tableName, props);
+      return [c.unique("remote_url", "table_name")];
+    },
+    populateQS: (t, c, _cols, tableName) => {
+      t.description = markdown`
+        How far the rows of each table of a local replica were pushed to a remote
+        libSQL (e.g. Turso) database, one ${tableName} row per remote and table.
+        The first sync of a table pushes its rows after synced_rowid until
+        scanned_at is set, later syncs push the surveilr_remote_sync_change
+        changes after synced_change_id.`;
+      c.remote_url.description =
+        `the remote database's URL, without its auth token`;
+      c.table_name.description = `the local table whose rows were pushed`;
+      c.synced_rowid.description = `the rowid of the last row which was pushed`;
+      c.synced_rows.description =
+        `how many rows of the table were pushed in total`;
+      c.synced_at.description = `when rows of the table were last pushed`;
+      c.synced_change_id.description =
+        `the surveilr_remote_sync_change_id of the last change which was pushed`;
+      c.scanned_at.description =
+        `when every row the table had at its first sync was pushed`;
+    },
+  });
+
+  const surveilrRemoteSyncChange = gm.autoIncPkTable(
+    "surveilr_remote_sync_change",
+    {
+      surveilr_remote_sync_change_id: gm.keys.autoIncPrimaryKey(),
+      table_name: gd.text(),
+      row_id: gd.integer(),
+      deleted_key: gd.jsonTextNullable(),
+      ...gm.housekeeping.columns,
+    },
+    {
+      isIdempotent: true,
+      populateQS: (t, c, _cols, tableName) => {
+        t.description = markdown`
+          The rows of a local replica which were inserted, updated or deleted,
+          recorded by triggers so syncs push them to remote libSQL databases;
+          ${tableName} rows every remote was pushed are removed.`;
+        c.table_name.description = `the local table whose row changed`;
+        c.row_id.description = `the rowid of the row which changed`;
+        c.deleted_key.description =
+          `JSON array of the primary key of a deleted row, NULL when the row was inserted or updated`;
+      },
+    },
+  );
+
+  const uniformResourceEquivalence = gm.textPkTable(
+    "uniform_resource_equivalence",
+    {
+      uniform_resource_equivalence_id: gm.keys.varCharPrimaryKey(),
+      content_digest: gd.text(),
+      uniform_resource_id: uniformResource.references.uniform_resource_id(),
+      device_id: device.references.device_id(),
+      uri: gd.text(),
+      device_count: gd.integer(),
+      ...gm.housekeeping.columns,
+    },
+    {
+      isIdempotent: true,
+      constraints: (props, tableName) => {
+        const c = SQLa.tableConstraints(tableName, props);
+        return [c.unique("content_digest", "uniform_resource_id")];
+      },
+      indexes: (props, tableName) => {
+        const tif = SQLa.tableIndexesFactory(tableName, props);
+        return [tif.index({ isIdempotent: true }, "content_digest", "device_id")];
+      },
+      populateQS: (t, c, _cols, tableName) => {
+        t.description = markdown`
+          Links the ${uniformResource.tableName} rows of different devices whose
+          content is identical, rebuilt by \`admin merge\`. Rows aren't collapsed:
+          every resource whose content was ingested on more than one device has a
+          ${tableName} row, so the devices with a given content are a single
+          indexed lookup by content_digest.`;
+        c.content_digest.description =
+          `the SHA-1 digest of the content the devices share`;
+        c.device_id.description = `the device the resource was ingested on`;
+        c.uri.description = `the resource's URI on that device`;
+        c.device_count.description =
+          `how many devices have a resource with this content`;
+      },
+    },
+  );
+
+  const uniformResourceChunk = gm.textPkTable(
+    "uniform_resource_chunk",
+    {
+      uniform_resource_chunk_id: gm.keys.varCharPrimaryKey(),
+      uniform_resource_id: uniformResource.references.uniform_resource_id(),
+      chunk_index: gd.integer(),
+      content_digest: gd.text(),
+      size_bytes: gd.integer(),
+      content: gd.blobText(),
+      ...gm.housekeeping.columns,
+    },
+    {
+      isIdempotent: true,
+      constraints: (props, tableName) => {
+        const c = SQLa.tableConstraints(tableName, props);
+        return [c.unique("uniform_resource_id", "chunk_index")];
+      },
+      populateQS: (t, c, _cols, tableName) => {
+        t.description = markdown`
+          The content of a ${uniformResource.tableName} too large for a single
+          SQLite blob (files over 1 GiB ingested with \`--content-chunk-size\`),
+          split in ordered chunks. The ${uniformResource.tableName} row keeps the
+          digest and size of the whole content and a NULL content; it's
+          reassembled by concatenating the ${tableName} rows in chunk_index order.`;
+        c.chunk_index.description =
+          `the 0-based position of the chunk in the content`;
+        c.content_digest.description =
+          `the SHA-1 digest of the chunk, checked when it's reassembled`;
+        c.size_bytes.description = `the size of the chunk in bytes`;
+        c.content.description = `the bytes of the chunk`;
+      },
+    },
+  );
+
+  const urIngestImapFolderState = gm.textPkTable(
+    "ur_ingest_imap_folder_state",
+    {
+      ur_ingest_imap_folder_state_id: gm.keys.varCharPrimaryKey(),
+      email: gd.text(),
+      folder_name: gd.text(),
+      uid_validity: gd.integer(),
+      last_uid: gd.integer(),
+      ingest_session_id: urIngestSession.references.ur_ingest_session_id(),
+      ...gm.housekeeping.columns,
+    },
+    {
+      isIdempotent: true,
+      constraints: (props, tableName) => {
+        const c = SQLa.tableConstraints(tableName, props);
+        return [c.unique("email", "folder_name")];
+      },
+      populateQS: (t, c) => {
+        t.description = markdown`
+          Where \`ingest imap --resume\` stopped in every folder of a mailbox: the
+          folder's UIDVALIDITY and the highest UID ingested. The next run only
+          fetches the messages with a higher UID, unless the UIDVALIDITY changed.`;
+        c.email.description = `the address of the mailbox`;
+        c.uid_validity.description =
+          `the folder's UIDVALIDITY when it was last ingested`;
+        c.last_uid.description = `the highest UID ingested from the folder`;
+        c.ingest_session_id.description =
+          `the session which last advanced the checkpoint`;
+      },
+    },
+  );
+
+  const uniformResourceVerification = gm.textPkTable(
+    "uniform_resource_verification",
+    {
+      uniform_resource_verification_id: gm.keys.varCharPrimaryKey(),
+      verification_id: gd.text(),
+      ingest_session_id: urIngestSession.references.ur_ingest_session_id(),
+      uniform_resource_id: uniformResource.references.uniform_resource_id()
+        .optional(),
+      uri: gd.text(),
+      status: gd.text(),
+      content_digest_algorithm: gd.text(),
+      expected_digest: gd.textNullable(),
+      actual_digest: gd.textNullable(),
+      elaboration: gd.jsonTextNullable(),
+      ...gm.housekeeping.columns,
+    },
+    {
+      isIdempotent: true,
+      constraints: (props, tableName) => {
+        const c = SQLa.tableConstraints(tableName, props);
+        return [c.unique("verification_id", "uri")];
+      },
+      populateQS: (t, c) => {
+        t.description = markdown`
+          Each file \`admin verify\` checked against the ingest session it
+          verified: whether the file's content still hashes to the stored
+          content_digest. With \`admin verify --record\` the RSSD keeps a file
+          integrity monitoring history.`;
+        c.verification_id.description =
+          `the \`admin verify\` run, shared by all of its rows`;
+        c.ingest_session_id.description =
+          `the ingest session whose resources were the baseline`;
+        c.uniform_resource_id.description =
+          `the verified resource, NULL for files added since the session`;
+        c.status.description =
+          `\`unchanged\`, \`modified\`, \`tampered\` (content changed but not size and modification time), \`deleted\`, \`added\` or \`unreadable\``;
+        c.content_digest_algorithm.description =
+          `the algorithm of expected_digest and actual_digest`;
+        c.expected_digest.description = `the resource's content_digest`;
+        c.actual_digest.description =
+          `the digest of the file's content when it was verified`;
+        c.elaboration.description =
+          `the expected and actual size and modification time, the error of unreadable files (JSON)`;
       },
     },
   );
@@ -790,6 +1452,24 @@ export function serviceModels<EmitContext extends SQLa.SqlEmitContext>() {
       urIngestSessionImapAccount,
       urIngestSessionImapAcctFolder,
       urIngestSessionImapAcctFolderMessage,
+      urIngestSessionImapAcctStat,
+      uniformResourceLineage,
+      rssdMetadata,
+      querySnapshot,
+      surveilrAudit,
+      policyFinding,
+      policyAlertRule,
+      complianceControl,
+      complianceEvidenceMap,
+      knownFileHash,
+      uniformResourceKnownFile,
+      urIngestSessionGitRepo,
+      surveilrRemoteSync,
+      uniformResourceEquivalence,
+      uniformResourceChunk,
+      urIngestImapFolderState,
+      uniformResourceVerification,
+      surveilrRemoteSyncChange,
     ],
     tableIndexes: [
       ...device.indexes,
@@ -805,6 +1485,16 @@ export function serviceModels<EmitContext extends SQLa.SqlEmitContext>() {
       ...urIngestSessionImapAcctFolder.indexes,
       ...urIngestSessionImapAcctFolderMessage.indexes,
       ...urIngestSessionImapAccount.indexes,
+      ...urIngestSessionImapAcctStat.indexes,
+      ...uniformResourceLineage.indexes,
+      ...rssdMetadata.indexes,
+      ...querySnapshot.indexes,
+      ...surveilrAudit.indexes,
+      ...policyFinding.indexes,
+      ...knownFileHash.indexes,
+      ...uniformResourceKnownFile.indexes,
+      ...urIngestSessionGitRepo.indexes,
+      ...uniformResourceEquivalence.indexes,
     ],
   };
 
@@ -824,6 +1514,24 @@ export function serviceModels<EmitContext extends SQLa.SqlEmitContext>() {
     urIngestSessionImapAccount,
     urIngestSessionImapAcctFolder,
     urIngestSessionImapAcctFolderMessage,
+    urIngestSessionImapAcctStat,
+    uniformResourceLineage,
+    rssdMetadata,
+    querySnapshot,
+    surveilrAudit,
+    policyFinding,
+    policyAlertRule,
+    complianceControl,
+    complianceEvidenceMap,
+    knownFileHash,
+    uniformResourceKnownFile,
+    urIngestSessionGitRepo,
+    surveilrRemoteSync,
+    uniformResourceEquivalence,
+    uniformResourceChunk,
+    urIngestImapFolderState,
+    uniformResourceVerification,
+    surveilrRemoteSyncChange,
   };
 }
 
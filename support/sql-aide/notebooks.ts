@@ -219,9 +219,10 @@ export class SqlNotebookHelpers<EmitContext extends SQLa.SqlEmitContext>
     };
   }
 
-  // ULID generator when the value is needed by the SQLite engine runtime
-  get sqlEngineNewUlid(): SQLa.SqlTextSupplier<EmitContext> {
-    return { SQL: () => `ulid()` };
+  // primary key generator (ULID or UUIDv7, per the RSSD's `primary_key_strategy`)
+  // when the value is needed by the SQLite engine runtime
+  get sqlEngineNewPk(): SQLa.SqlTextSupplier<EmitContext> {
+    return { SQL: () => `surveilr_pk()` };
   }
 
   get onConflictDoNothing(): SQLa.SqlTextSupplier<EmitContext> {
@@ -369,6 +370,37 @@ export class BootstrapSqlNotebook<
   }
 }
 
+// `once_` migrations which add columns to tables created by earlier versions;
+// v001_once_initialDDL already has these columns
+const addColumnMigrationCells = [
+  "v009_once_uniformResourceSemanticIdentityDDL",
+  "v018_once_deviceClockDDL",
+  "v026_once_contentDigestAlgorithmDDL",
+  "v028_once_surveilrRemoteSyncChangeDDL",
+];
+
+// new RSSDs get the columns of `ALTER TABLE ... ADD COLUMN` migrations from
+// v001_once_initialDDL (or the later migration which created the table) so
+// they're recorded as executed rather than failing (and being retried) on
+// every run; not a method since those are cells
+function addColumnMigrationsExecutedDML<
+  EmitContext extends SQLa.SqlEmitContext,
+>(
+  nbh: SqlNotebookHelpers<EmitContext>,
+  cells = addColumnMigrationCells,
+  executedBy = "v001_once_initialDDL",
+) {
+  const cellNames = cells.map((cell) => `'${cell}'`).join(", ");
+  // deno-fmt-ignore
+  return nbh.SQL`
+      INSERT INTO code_notebook_state (code_notebook_state_id, code_notebook_cell_id, from_state, to_state, transition_reason)
+           SELECT ${nbh.sqlEngineNewPk}, code_notebook_cell_id, 'NONE', 'EXECUTED', '${executedBy}'
+             FROM code_notebook_cell
+            WHERE notebook_name = 'ConstructionSqlNotebook' AND cell_name IN (${cellNames})
+      ON CONFLICT DO NOTHING;
+      `;
+}
+
 /**
  * Encapsulates SQL DDL and table/view/entity construction SQLa objects. The
  * actual models are not managed by this class but it does include all the
@@ -400,6 +432,8 @@ export class ConstructionSqlNotebook<EmitContext extends SQLa.SqlEmitContext>
       ${models.informationSchema.tables}
 
       ${models.informationSchema.tableIndexes}
+
+      ${addColumnMigrationsExecutedDML(nbh)}
       `;
   }
 
@@ -413,7 +447,7 @@ export class ConstructionSqlNotebook<EmitContext extends SQLa.SqlEmitContext>
     const urIngestPathMatchRules = () => {
       const { nbh: { models: { urIngestPathMatchRule } } } = this;
       const options = { onConflict: { SQL: () => `ON CONFLICT DO NOTHING` } };
-      const ur_ingest_resource_path_match_rule_id = nbh.sqlEngineNewUlid;
+      const ur_ingest_resource_path_match_rule_id = nbh.sqlEngineNewPk;
       // NOTE: all `\\` will be replaced by JS runtime with single `\`
       return [
         urIngestPathMatchRule.insertDML({
@@ -435,6 +469,56 @@ export class ConstructionSqlNotebook<EmitContext extends SQLa.SqlEmitContext>
             "Ingest the content for md, mdx, html, json, jsonc, puml, txt, toml, and yml extensions. Assume the nature is the same as the extension.",
           created_at,
         }, options),
+        urIngestPathMatchRule.insertDML({
+          ur_ingest_resource_path_match_rule_id,
+          namespace,
+          regex: "/systemd/(.+/)?[^/]+\\.(service|socket|timer|path|mount|automount)$",
+          flags: "CONTENT_ACQUIRABLE",
+          nature: "systemd-unit",
+          description:
+            "Ingest the content of systemd units which start services, sockets, timers, paths and mounts as systemd-unit so their ExecStart, users and schedules are extracted.",
+          created_at,
+        }, options),
+        urIngestPathMatchRule.insertDML({
+          ur_ingest_resource_path_match_rule_id,
+          namespace,
+          regex: "(/crontab|/cron\\.d/[^/]+|/var/spool/cron/.+)$",
+          flags: "CONTENT_ACQUIRABLE",
+          nature: "crontab",
+          description:
+            "Ingest the content of system and user crontabs as crontab so their schedules, users and commands are extracted.",
+          created_at,
+        }, options),
+        urIngestPathMatchRule.insertDML({
+          ur_ingest_resource_path_match_rule_id,
+          namespace,
+          regex: "(/init\\.d/[^/]+|/etc/rc\\.local)$",
+          flags: "CONTENT_ACQUIRABLE",
+          nature: "init-script",
+          description:
+            "Ingest the content of SysV init scripts and rc.local as init-script so the programs they start and their users are extracted.",
+          created_at,
+        }, options),
+        urIngestPathMatchRule.insertDML({
+          ur_ingest_resource_path_match_rule_id,
+          namespace,
+          regex: "\\.pdf$",
+          flags: "CONTENT_ACQUIRABLE",
+          nature: "pdf",
+          description:
+            "Ingest the content of PDFs as pdf so their text, page count and document information are extracted.",
+          created_at,
+        }, options),
+        urIngestPathMatchRule.insertDML({
+          ur_ingest_resource_path_match_rule_id,
+          namespace,
+          regex: "\\.(?P<nature>docx|xlsx|pptx)$",
+          flags: "CONTENT_ACQUIRABLE",
+          nature: "?P<nature>", // should be same as src/resource.rs::PFRE_READ_NATURE_FROM_REGEX
+          description:
+            "Ingest the content of Word documents, Excel workbooks and PowerPoint presentations so their paragraphs, sheet cells and slide text are extracted. Assume the nature is the same as the extension.",
+          created_at,
+        }, options),
         urIngestPathMatchRule.insertDML({
           ur_ingest_resource_path_match_rule_id,
           namespace,
@@ -460,7 +544,7 @@ export class ConstructionSqlNotebook<EmitContext extends SQLa.SqlEmitContext>
     const urIngestPathRewriteRules = () => {
       const { nbh: { models: { urIngestPathRewriteRule } } } = this;
       const options = { onConflict: { SQL: () => `ON CONFLICT DO NOTHING` } };
-      const ur_ingest_resource_path_rewrite_rule_id = nbh.sqlEngineNewUlid;
+      const ur_ingest_resource_path_rewrite_rule_id = nbh.sqlEngineNewPk;
       // NOTE: all `\\` will be replaced by JS runtime with single `\`
       return [
         urIngestPathRewriteRule.insertDML({
@@ -669,6 +753,391 @@ export class ConstructionSqlNotebook<EmitContext extends SQLa.SqlEmitContext>
                ufs.ur_status,
                ufs.ur_diagnostics;`
   }
+
+  // `once_` pragma so RSSDs created before `uniform_resource_lineage` existed
+  // get the table (new RSSDs already have it from v001_once_initialDDL)
+  v003_once_uniformResourceLineageDDL() {
+    const { nbh, nbh: { models: { uniformResourceLineage } } } = this;
+    // deno-fmt-ignore
+    return nbh.SQL`
+      ${uniformResourceLineage}
+
+      ${uniformResourceLineage.indexes}
+      `;
+  }
+
+  // `once_` pragma so RSSDs created before `rssd_metadata` existed get the table
+  v004_once_rssdMetadataDDL() {
+    const { nbh, nbh: { models: { rssdMetadata } } } = this;
+    // deno-fmt-ignore
+    return nbh.SQL`
+      ${rssdMetadata}
+      `;
+  }
+
+  // `once_` pragma so RSSDs created before `query_snapshot` existed get the table
+  v005_once_querySnapshotDDL() {
+    const { nbh, nbh: { models: { querySnapshot } } } = this;
+    // deno-fmt-ignore
+    return nbh.SQL`
+      ${querySnapshot}
+
+      ${querySnapshot.indexes}
+      `;
+  }
+
+  // `once_` pragma so IMAP passwords stored in plaintext by earlier versions are
+  // replaced by their digest; surveilr_credential_digest() is registered by surveilr
+  v006_once_redactImapAccountPasswords() {
+    // deno-fmt-ignore
+    return this.nbh.SQL`
+      UPDATE ur_ingest_session_imap_account
+         SET password = surveilr_credential_digest(password)
+       WHERE password IS NOT NULL;
+      `;
+  }
+
+  // `once_` pragma so RSSDs created before `surveilr_audit` existed get the table
+  v007_once_surveilrAuditDDL() {
+    const { nbh, nbh: { models: { surveilrAudit } } } = this;
+    // deno-fmt-ignore
+    return nbh.SQL`
+      ${surveilrAudit}
+
+      ${surveilrAudit.indexes}
+      `;
+  }
+
+  // `once_` pragma so RSSDs created before mailbox statistics existed get the table
+  v008_once_urIngestSessionImapAcctStatDDL() {
+    const { nbh, nbh: { models: { urIngestSessionImapAcctStat } } } = this;
+    // deno-fmt-ignore
+    return nbh.SQL`
+      ${urIngestSessionImapAcctStat}
+
+      ${urIngestSessionImapAcctStat.indexes}
+      `;
+  }
+
+  // `once_` pragma so RSSDs created before semantic identities existed get the
+  // column (appended, like in v001_once_initialDDL) and its index
+  v009_once_uniformResourceSemanticIdentityDDL() {
+    const { nbh, nbh: { models: { uniformResource } } } = this;
+    // deno-fmt-ignore
+    return nbh.SQL`
+      ALTER TABLE ${uniformResource.tableName} ADD COLUMN semantic_identity TEXT;
+
+      ${uniformResource.indexes}
+      `;
+  }
+
+  // `once_` pragma so RSSDs created before policy findings existed get the table
+  v010_once_policyFindingDDL() {
+    const { nbh, nbh: { models: { policyFinding } } } = this;
+    // deno-fmt-ignore
+    return nbh.SQL`
+      ${policyFinding}
+
+      ${policyFinding.indexes}
+      `;
+  }
+
+  // `once_` pragma so RSSDs created before policy alert rules existed get the table
+  v011_once_policyAlertRuleDDL() {
+    const { nbh, nbh: { models: { policyAlertRule } } } = this;
+    // deno-fmt-ignore
+    return nbh.SQL`
+      ${policyAlertRule}
+      `;
+  }
+
+  // `once_` pragma so RSSDs created before compliance mappings existed get the table
+  v012_once_complianceControlDDL() {
+    const { nbh, nbh: { models: { complianceControl } } } = this;
+    // deno-fmt-ignore
+    return nbh.SQL`
+      ${complianceControl}
+      `;
+  }
+
+  // `once_` pragma so RSSDs created before compliance mappings existed get the table
+  v013_once_complianceEvidenceMapDDL() {
+    const { nbh, nbh: { models: { complianceEvidenceMap } } } = this;
+    // deno-fmt-ignore
+    return nbh.SQL`
+      ${complianceEvidenceMap}
+      `;
+  }
+
+  // `once_` pragma so RSSDs created before known-file matching existed get the table
+  v014_once_knownFileHashDDL() {
+    const { nbh, nbh: { models: { knownFileHash } } } = this;
+    // deno-fmt-ignore
+    return nbh.SQL`
+      ${knownFileHash}
+      `;
+  }
+
+  // `once_` pragma so RSSDs created before known-file matching existed get the table
+  v015_once_uniformResourceKnownFileDDL() {
+    const { nbh, nbh: { models: { uniformResourceKnownFile } } } = this;
+    // deno-fmt-ignore
+    return nbh.SQL`
+      ${uniformResourceKnownFile}
+      `;
+  }
+
+  // views flattening the systemd unit, crontab and init script transforms
+  // stored by `extract_persistence` during ingestion for persistence audits
+  v016_persistenceSystemdUnitViewDDL() {
+    // deno-fmt-ignore
+    return this.nbh.viewDefn("persistence_systemd_unit")/* sql */`
+        SELECT ur.device_id,
+               urt.uniform_resource_id,
+               ur.uri,
+               json_extract(urt.content, '$.unit') AS unit,
+               json_extract(urt.content, '$.unit_type') AS unit_type,
+               json_extract(urt.content, '$.description') AS description,
+               json_extract(urt.content, '$.exec_start') AS exec_start,
+               json_extract(urt.content, '$.user') AS user,
+               json_extract(urt.content, '$.group') AS "group",
+               json_extract(urt.content, '$.wanted_by') AS wanted_by,
+               json_extract(urt.content, '$.on_calendar') AS on_calendar,
+               urt.content
+          FROM uniform_resource_transform urt
+          JOIN uniform_resource ur ON ur.uniform_resource_id = urt.uniform_resource_id
+         WHERE json_extract(urt.elaboration, '$.persistence') = 'systemd-unit';`
+  }
+
+  v016_persistenceCronJobViewDDL() {
+    // deno-fmt-ignore
+    return this.nbh.viewDefn("persistence_cron_job")/* sql */`
+        SELECT ur.device_id,
+               urt.uniform_resource_id,
+               ur.uri,
+               json_extract(job.value, '$.schedule') AS schedule,
+               json_extract(job.value, '$.user') AS user,
+               json_extract(job.value, '$.command') AS command
+          FROM uniform_resource_transform urt
+          JOIN uniform_resource ur ON ur.uniform_resource_id = urt.uniform_resource_id,
+               json_each(urt.content, '$.jobs') AS job
+         WHERE json_extract(urt.elaboration, '$.persistence') = 'crontab';`
+  }
+
+  v016_persistenceInitScriptViewDDL() {
+    // deno-fmt-ignore
+    return this.nbh.viewDefn("persistence_init_script")/* sql */`
+        SELECT ur.device_id,
+               urt.uniform_resource_id,
+               ur.uri,
+               json_extract(urt.content, '$.info.provides') AS provides,
+               json_extract(urt.content, '$.info.default_start') AS default_start,
+               json_extract(urt.content, '$.interpreter') AS interpreter,
+               json_extract(urt.content, '$.exec') AS exec,
+               json_extract(urt.content, '$.users') AS users
+          FROM uniform_resource_transform urt
+          JOIN uniform_resource ur ON ur.uniform_resource_id = urt.uniform_resource_id
+         WHERE json_extract(urt.elaboration, '$.persistence') = 'init-script';`
+  }
+
+  // views flattening the browser history and extension transforms stored by
+  // `ingest browsers`
+  v017_browserHistoryVisitViewDDL() {
+    // deno-fmt-ignore
+    return this.nbh.viewDefn("browser_history_visit")/* sql */`
+        SELECT ur.device_id,
+               urt.uniform_resource_id,
+               ur.uri AS store,
+               json_extract(ur.elaboration, '$.browser.browser') AS browser,
+               json_extract(ur.elaboration, '$.browser.profile') AS profile,
+               json_extract(visit.value, '$.url') AS url,
+               json_extract(visit.value, '$.title') AS title,
+               json_extract(visit.value, '$.visited_at') AS visited_at,
+               json_extract(visit.value, '$.visit_count') AS visit_count
+          FROM uniform_resource_transform urt
+          JOIN uniform_resource ur ON ur.uniform_resource_id = urt.uniform_resource_id,
+               json_each(urt.content) AS visit
+         WHERE ur.nature = 'browser-history';`
+  }
+
+
+  v017_browserExtensionViewDDL() {
+    // deno-fmt-ignore
+    return this.nbh.viewDefn("browser_extension")/* sql */`
+        SELECT ur.device_id,
+               urt.uniform_resource_id,
+               ur.uri AS store,
+               json_extract(ur.elaboration, '$.browser.browser') AS browser,
+               json_extract(ur.elaboration, '$.browser.profile') AS profile,
+               json_extract(extension.value, '$.id') AS extension_id,
+               json_extract(extension.value, '$.name') AS name,
+               json_extract(extension.value, '$.version') AS version,
+               json_extract(extension.value, '$.permissions') AS permissions,
+               json_extract(extension.value, '$.enabled') AS enabled
+          FROM uniform_resource_transform urt
+          JOIN uniform_resource ur ON ur.uniform_resource_id = urt.uniform_resource_id,
+               json_each(urt.content) AS extension
+         WHERE ur.nature = 'browser-extensions';`
+  }
+
+  // `once_` pragma so RSSDs created before sessions recorded the device's clock
+  // get the columns (appended, like in v001_once_initialDDL)
+  v018_once_deviceClockDDL() {
+    const { nbh, nbh: { models: { urIngestSession, urIngestSessionImapAcctFolderMessage } } } = this;
+    // deno-fmt-ignore
+    return nbh.SQL`
+      ALTER TABLE ${urIngestSession.tableName} ADD COLUMN device_clock TEXT CHECK(json_valid(device_clock) OR device_clock IS NULL);
+      ALTER TABLE ${urIngestSessionImapAcctFolderMessage.tableName} ADD COLUMN date TEXT;
+      `;
+  }
+
+  // each session's device clock, to line up timelines across devices
+  v018_urIngestSessionDeviceClockViewDDL() {
+    // deno-fmt-ignore
+    return this.nbh.viewDefn("ur_ingest_session_device_clock")/* sql */`
+        SELECT s.ur_ingest_session_id,
+               s.device_id,
+               d.name AS device_name,
+               s.ingest_started_at,
+               json_extract(s.device_clock, '$.timezone') AS timezone,
+               json_extract(s.device_clock, '$.utc_offset') AS utc_offset,
+               json_extract(s.device_clock, '$.clock_source') AS clock_source,
+               json_extract(s.device_clock, '$.ntp_synchronized') AS ntp_synchronized,
+               json_extract(s.device_clock, '$.ntp_offset_ms') AS ntp_offset_ms,
+               json_extract(s.device_clock, '$.ntp_estimated_error_ms') AS ntp_estimated_error_ms
+          FROM ur_ingest_session s
+          JOIN device d ON d.device_id = s.device_id
+         WHERE s.device_clock IS NOT NULL;`
+  }
+
+  // `once_` pragma so RSSDs created before `ingest git` existed get the table
+  v019_once_urIngestSessionGitRepoDDL() {
+    const { nbh, nbh: { models: { urIngestSessionGitRepo } } } = this;
+    // deno-fmt-ignore
+    return nbh.SQL`
+      ${urIngestSessionGitRepo}
+      `;
+  }
+
+  // `once_` pragma so RSSDs created before remote libSQL sync existed get the
+  // tables, which already have the columns v028_once_surveilrRemoteSyncChangeDDL adds
+  v020_once_surveilrRemoteSyncDDL() {
+    const { nbh, nbh: { models: { surveilrRemoteSync, surveilrRemoteSyncChange } } } = this;
+    // deno-fmt-ignore
+    return nbh.SQL`
+      ${surveilrRemoteSync}
+      ${surveilrRemoteSyncChange}
+      ${addColumnMigrationsExecutedDML(nbh, ["v028_once_surveilrRemoteSyncChangeDDL"], "v020_once_surveilrRemoteSyncDDL")}
+      `;
+  }
+
+  // text, page count and document information of ingested PDFs
+  v021_pdfDocumentViewDDL() {
+    // deno-fmt-ignore
+    return this.nbh.viewDefn("pdf_document")/* sql */`
+        SELECT ur.device_id,
+               urt.uniform_resource_id,
+               ur.uri,
+               json_extract(urt.elaboration, '$.pdf.version') AS pdf_version,
+               json_extract(urt.elaboration, '$.pdf.pages') AS pages,
+               json_extract(urt.elaboration, '$.pdf.encrypted') AS encrypted,
+               json_extract(urt.elaboration, '$.pdf.info.Title') AS title,
+               json_extract(urt.elaboration, '$.pdf.info.Author') AS author,
+               json_extract(urt.elaboration, '$.pdf.info.Subject') AS subject,
+               json_extract(urt.elaboration, '$.pdf.info.Keywords') AS keywords,
+               json_extract(urt.elaboration, '$.pdf.info.Creator') AS creator,
+               json_extract(urt.elaboration, '$.pdf.info.Producer') AS producer,
+               json_extract(urt.elaboration, '$.pdf.info.CreationDate') AS created_at,
+               json_extract(urt.elaboration, '$.pdf.info.ModDate') AS modified_at,
+               urt.content AS text
+          FROM uniform_resource_transform urt
+          JOIN uniform_resource ur ON ur.uniform_resource_id = urt.uniform_resource_id
+         WHERE json_extract(urt.elaboration, '$.pdf') IS NOT NULL;`
+  }
+
+  // properties and extracted content of ingested Word, Excel and PowerPoint documents
+  v022_officeDocumentViewDDL() {
+    // deno-fmt-ignore
+    return this.nbh.viewDefn("office_document")/* sql */`
+        SELECT ur.device_id,
+               urt.uniform_resource_id,
+               ur.uri,
+               json_extract(urt.elaboration, '$.office') AS format,
+               json_extract(urt.content, '$.properties.title') AS title,
+               json_extract(urt.content, '$.properties.creator') AS creator,
+               json_extract(urt.content, '$.properties.lastModifiedBy') AS last_modified_by,
+               json_extract(urt.content, '$.properties.created') AS created_at,
+               json_extract(urt.content, '$.properties.modified') AS modified_at,
+               json_array_length(urt.content, '$.paragraphs') AS paragraphs,
+               json_array_length(urt.content, '$.sheets') AS sheets,
+               json_array_length(urt.content, '$.slides') AS slides,
+               urt.content
+          FROM uniform_resource_transform urt
+          JOIN uniform_resource ur ON ur.uniform_resource_id = urt.uniform_resource_id
+         WHERE json_extract(urt.elaboration, '$.office') IS NOT NULL;`
+  }
+
+  // `once_` pragma so RSSDs created before `admin merge` linked identical
+  // content across devices get the table
+  v023_once_uniformResourceEquivalenceDDL() {
+    const { nbh, nbh: { models: { uniformResourceEquivalence } } } = this;
+    // deno-fmt-ignore
+    return nbh.SQL`
+      ${uniformResourceEquivalence}
+      `;
+  }
+
+  // `once_` pragma so RSSDs created before large content could be stored in
+  // chunks get the table
+  v024_once_uniformResourceChunkDDL() {
+    const { nbh, nbh: { models: { uniformResourceChunk } } } = this;
+    // deno-fmt-ignore
+    return nbh.SQL`
+      ${uniformResourceChunk}
+      `;
+  }
+
+  // `once_` pragma so RSSDs created before `ingest imap --resume` get the table
+  v025_once_urIngestImapFolderStateDDL() {
+    const { nbh, nbh: { models: { urIngestImapFolderState } } } = this;
+    // deno-fmt-ignore
+    return nbh.SQL`
+      ${urIngestImapFolderState}
+      `;
+  }
+
+  // `once_` pragma so RSSDs created before `ingest files --digest` get the
+  // columns, existing sessions and resources were all hashed with SHA-1
+  v026_once_contentDigestAlgorithmDDL() {
+    const { nbh, nbh: { models: { urIngestSession, uniformResource } } } = this;
+    // deno-fmt-ignore
+    return nbh.SQL`
+      ALTER TABLE ${urIngestSession.tableName} ADD COLUMN content_digest_algorithm TEXT NOT NULL DEFAULT 'sha1';
+      ALTER TABLE ${uniformResource.tableName} ADD COLUMN content_digest_algorithm TEXT NOT NULL DEFAULT 'sha1';
+      `;
+  }
+
+  // `once_` pragma so RSSDs created before `admin verify` get the table
+  v027_once_uniformResourceVerificationDDL() {
+    const { nbh, nbh: { models: { uniformResourceVerification } } } = this;
+    // deno-fmt-ignore
+    return nbh.SQL`
+      ${uniformResourceVerification}
+      `;
+  }
+
+  // `once_` pragma so replicas created before syncs pushed updates and deletes
+  // get the columns and table; their tables are pushed again from the start
+  v028_once_surveilrRemoteSyncChangeDDL() {
+    const { nbh, nbh: { models: { surveilrRemoteSync, surveilrRemoteSyncChange } } } = this;
+    // deno-fmt-ignore
+    return nbh.SQL`
+      ALTER TABLE ${surveilrRemoteSync.tableName} ADD COLUMN synced_change_id INTEGER NOT NULL DEFAULT 0;
+      ALTER TABLE ${surveilrRemoteSync.tableName} ADD COLUMN scanned_at TIMESTAMPTZ;
+      UPDATE ${surveilrRemoteSync.tableName} SET synced_rowid = 0;
+      ${surveilrRemoteSyncChange}
+      `;
+  }
 }
 
 /**
@@ -851,6 +1320,37 @@ export class QuerySqlNotebook<EmitContext extends SQLa.SqlEmitContext>
       );`;
   }
 
+  /**
+   * Table and column descriptions from the models' `populateQS` as rows so that
+   * documentation generated from a live RSSD (`surveilr admin schema-doc`) can
+   * annotate whatever schema is actually deployed.
+   */
+  infoSchemaComments() {
+    const { nbh: { models } } = this;
+    const literal = (text?: string) =>
+      text ? `'${text.replace(/\s+/g, " ").trim().replaceAll("'", "''")}'` : "NULL";
+    const tables = [
+      ...models.codeNbModels.informationSchema.tables,
+      ...models.informationSchema.tables,
+    ];
+    const rows = tables.flatMap((t) => [
+      ...(t.tblQualitySystem?.description
+        ? [`(${literal(t.tableName)}, NULL, ${literal(t.tblQualitySystem.description)})`]
+        : []),
+      ...t.domains.filter((d) => d.qualitySystem?.description).map((d) =>
+        `(${literal(t.tableName)}, ${literal(d.identity)}, ${literal(d.qualitySystem?.description)})`
+      ),
+    ]);
+    // deno-fmt-ignore
+    return this.nbh.SQL`
+      WITH info_schema_comment(table_name, column_name, description) AS (
+        VALUES
+          ${rows.join(",\n          ")}
+      )
+      SELECT table_name, column_name, description
+        FROM info_schema_comment;`;
+  }
+
   htmlAnchors() {
     // deno-fmt-ignore
     return this.nbh.SQL`
@@ -1151,7 +1651,7 @@ export function codeNotebookFactory<
               : `CodeNotebookFactory::cellsDML "${cell}" did not return a function or text (found: ${typeof state
                 .execResult})`);
           sqlDML.push(codeNotebookCell.insertDML({
-            code_notebook_cell_id: nbh.sqlEngineNewUlid,
+            code_notebook_cell_id: nbh.sqlEngineNewPk,
             notebook_kernel_id: kernelID,
             notebook_name: notebookName,
             cell_name: cell, // the class's method name is the "cell"
@@ -1353,7 +1853,7 @@ export class SqlNotebooksOrchestrator<EmitContext extends SQLa.SqlEmitContext> {
     };
     return [
       codeNotebookCell.insertDML({
-        code_notebook_cell_id: this.nbh.sqlEngineNewUlid,
+        code_notebook_cell_id: this.nbh.sqlEngineNewPk,
         notebook_kernel_id: "PlantUML",
         notebook_name: SqlNotebooksOrchestrator.prototype.constructor.name,
         cell_name: "surveilrInfoSchemaDiagram",
@@ -1361,7 +1861,7 @@ export class SqlNotebooksOrchestrator<EmitContext extends SQLa.SqlEmitContext> {
         interpretable_code_hash: await gitLikeHash(surveilrInfoSchemaDiagram),
       }, options),
       codeNotebookCell.insertDML({
-        code_notebook_cell_id: this.nbh.sqlEngineNewUlid,
+        code_notebook_cell_id: this.nbh.sqlEngineNewPk,
         notebook_kernel_id: "PlantUML",
         notebook_name: SqlNotebooksOrchestrator.prototype.constructor.name,
         cell_name: "notebooksInfoSchemaDiagram",
@@ -1559,7 +2059,7 @@ export class SqlNotebooksOrchestrator<EmitContext extends SQLa.SqlEmitContext> {
               : `storeNotebookCellsDML "${cell}" did not return SQL (found: ${typeof state
                 .execResult})`;
           sqlDML.push(codeNotebookCell.insertDML({
-            code_notebook_cell_id: this.nbh.sqlEngineNewUlid,
+            code_notebook_cell_id: this.nbh.sqlEngineNewPk,
             notebook_kernel_id: "SQL",
             notebook_name: notebookName,
             cell_name: cell, // the class's method name is the "cell"
@@ -44,7 +44,7 @@ Initiate a new connection to UDI-PGP through the SQL Notebook editor, set the da
 select uuid, hostname from system_info;
 
 /*markdown
-Add a new supplier called `hetzner` and introduce a known error by not supplying the `type`. You should see an error message stating that it failed to parse supplier due to a missing field.
+Add a new supplier called `hetzner` and introduce a known error by not supplying the `type`. You should see an error message naming the missing field, `hetzner.type`.
 */
 
 SET udi_pgp_serve_ncl_supplier = '